@@ -0,0 +1,15 @@
+-- migrations/20251216090000_create_audit_log.sql
+
+-- Registo de auditoria das ações administrativas (quem fez o quê, a quem e quando)
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    actor_id TEXT NOT NULL,        -- ID do admin que executou a ação
+    acao TEXT NOT NULL,            -- Ex: 'user.criado', 'user.roles', 'temp_role.atribuida'
+    alvo TEXT,                     -- ID do utilizador/objeto afetado (se houver)
+    detalhes TEXT,                 -- Resumo das alterações (diff legível)
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')) -- UTC, 'YYYY-MM-DD HH:MM:SS'
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_criado_em ON audit_log (criado_em);
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON audit_log (actor_id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_alvo ON audit_log (alvo);
@@ -0,0 +1,5 @@
+-- migrations/20251216100000_add_user_contacts.sql
+
+-- Campos de contacto opcionais (base para notificações e contactos de emergência)
+ALTER TABLE users ADD COLUMN email TEXT;    -- NULL se não definido
+ALTER TABLE users ADD COLUMN telefone TEXT; -- NULL se não definido
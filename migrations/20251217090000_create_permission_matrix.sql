@@ -0,0 +1,38 @@
+-- migrations/20251217090000_create_permission_matrix.sql
+
+-- Catálogo de roles (antes constantes no código: DEFINED_ROLES / TEMPORARY_ROLES)
+CREATE TABLE IF NOT EXISTS roles (
+    nome TEXT PRIMARY KEY COLLATE NOCASE,
+    descricao TEXT NOT NULL DEFAULT '',
+    permanente INTEGER NOT NULL DEFAULT 1, -- Pode ser atribuída no perfil do utilizador
+    temporaria INTEGER NOT NULL DEFAULT 0, -- Pode ser atribuída por período (roles temporárias)
+    sistema INTEGER NOT NULL DEFAULT 0     -- Roles de sistema não podem ser apagadas
+);
+
+-- Matriz role -> permissão (ex: 'presenca', 'admin', 'escala.gerir')
+CREATE TABLE IF NOT EXISTS role_permissoes (
+    role TEXT NOT NULL COLLATE NOCASE,
+    permissao TEXT NOT NULL,
+    PRIMARY KEY (role, permissao),
+    FOREIGN KEY (role) REFERENCES roles (nome) ON DELETE CASCADE
+);
+
+-- Estado inicial equivalente às regras que estavam no código
+INSERT OR IGNORE INTO roles (nome, descricao, permanente, temporaria, sistema) VALUES
+    ('admin', 'Administração do sistema', 1, 1, 1),
+    ('rancheiro', 'Rancho', 1, 1, 0),
+    ('escalante', 'Gestão da escala de serviço', 1, 1, 0),
+    ('monal', '', 1, 0, 0),
+    ('adal', '', 1, 0, 0),
+    ('comal', '', 1, 0, 0),
+    ('loja', 'Loja', 1, 1, 0),
+    ('chefe_de_dia', 'Chefe de dia', 0, 1, 0),
+    ('policia', 'Polícia', 0, 1, 0);
+
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('admin', 'admin'),
+    ('admin', 'presenca'),
+    ('admin', 'escala.gerir'),
+    ('policia', 'presenca'),
+    ('chefe_de_dia', 'presenca'),
+    ('escalante', 'escala.gerir');
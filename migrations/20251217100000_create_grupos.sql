@@ -0,0 +1,24 @@
+-- migrations/20251217100000_create_grupos.sql
+
+-- Grupos de utilizadores mais finos que turma/ano (pelotão, companhia, equipa)
+CREATE TABLE IF NOT EXISTS grupos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    nome TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    tipo TEXT NOT NULL CHECK (tipo IN ('pelotao', 'companhia', 'equipa')),
+    descricao TEXT NOT NULL DEFAULT '',
+    criado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Um utilizador pode pertencer a vários grupos (ex: um pelotão e uma equipa)
+CREATE TABLE IF NOT EXISTS grupo_membros (
+    grupo_id INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    PRIMARY KEY (grupo_id, user_id),
+    FOREIGN KEY (grupo_id) REFERENCES grupos (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_grupo_membros_user ON grupo_membros (user_id);
+
+-- Restrição opcional da escala: o posto só pode ser ocupado por membros deste grupo
+ALTER TABLE postos ADD COLUMN grupo_id INTEGER REFERENCES grupos (id) ON DELETE SET NULL;
@@ -0,0 +1,8 @@
+-- migrations/20251217110000_add_user_ativo.sql
+
+-- Utilizadores arquivados (ex: turma que terminou o curso) deixam de entrar,
+-- de ser escalados e de aparecer na presença, mas o histórico mantém-se.
+ALTER TABLE users ADD COLUMN ativo INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE users ADD COLUMN arquivado_em TEXT; -- UTC, 'YYYY-MM-DD HH:MM:SS'
+
+CREATE INDEX IF NOT EXISTS idx_users_ativo_ano ON users (ativo, ano);
@@ -0,0 +1,5 @@
+-- Permissão para a pesquisa de utilizadores (autocomplete em /api/users/search).
+-- Concedida inicialmente às roles que já escolhem utilizadores nos formulários.
+INSERT OR IGNORE INTO role_permissoes (role, permissao)
+SELECT nome, 'users.pesquisar' FROM roles
+WHERE nome IN ('admin', 'escalante', 'chefe_de_dia', 'policia');
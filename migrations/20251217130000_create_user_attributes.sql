@@ -0,0 +1,18 @@
+-- migrations/20251217130000_create_user_attributes.sql
+
+-- Campos extra definidos pelo admin (ex: tipo sanguíneo, número de cautela, posto/graduação)
+CREATE TABLE IF NOT EXISTS atributo_definicoes (
+    chave TEXT PRIMARY KEY NOT NULL COLLATE NOCASE, -- Identificador estável (ex: "tipo_sanguineo")
+    rotulo TEXT NOT NULL,                           -- Texto mostrado nos formulários e no CSV
+    ordem INTEGER NOT NULL DEFAULT 0                -- Ordem de apresentação
+);
+
+-- Valores por utilizador (chave-valor); campo vazio = sem linha
+CREATE TABLE IF NOT EXISTS user_attributes (
+    user_id TEXT NOT NULL,
+    chave TEXT NOT NULL COLLATE NOCASE,
+    valor TEXT NOT NULL,
+    PRIMARY KEY (user_id, chave),
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (chave) REFERENCES atributo_definicoes (chave) ON DELETE CASCADE
+);
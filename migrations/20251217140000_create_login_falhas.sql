@@ -0,0 +1,13 @@
+-- migrations/20251217140000_create_login_falhas.sql
+
+-- Tentativas de login falhadas (janela deslizante para limitar tentativas por utilizador e por IP).
+-- Linhas antigas são limpas pelo próprio serviço; não é um histórico.
+CREATE TABLE IF NOT EXISTS login_falhas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL COLLATE NOCASE, -- ID tentado (pode não existir)
+    ip TEXT NOT NULL,
+    ocorreu_em DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP -- UTC
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_falhas_user ON login_falhas (user_id, ocorreu_em);
+CREATE INDEX IF NOT EXISTS idx_login_falhas_ip ON login_falhas (ip, ocorreu_em);
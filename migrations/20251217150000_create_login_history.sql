@@ -0,0 +1,16 @@
+-- migrations/20251217150000_create_login_history.sql
+
+-- Histórico de todas as tentativas de login (sucesso ou falha), para o próprio utilizador e para investigações
+CREATE TABLE IF NOT EXISTS login_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,         -- ID tentado (pode não existir)
+    ip TEXT NOT NULL,
+    user_agent TEXT,
+    sucesso INTEGER NOT NULL,      -- 1 = login efetuado
+    motivo TEXT,                   -- Razão da falha: 'senha_invalida', 'user_desconhecido', 'conta_arquivada', 'bloqueado'
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')) -- UTC, 'YYYY-MM-DD HH:MM:SS'
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_history_user ON login_history (user_id, criado_em);
+CREATE INDEX IF NOT EXISTS idx_login_history_ip ON login_history (ip);
+CREATE INDEX IF NOT EXISTS idx_login_history_criado_em ON login_history (criado_em);
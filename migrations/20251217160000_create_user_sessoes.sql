@@ -0,0 +1,16 @@
+-- migrations/20251217160000_create_user_sessoes.sql
+
+-- Índice das sessões (tabela `sessions` do tower-sessions) por utilizador, para listar e revogar.
+-- O `id` numérico é o que aparece nas páginas/URLs: o session_id é o valor do cookie e nunca é exposto.
+CREATE TABLE IF NOT EXISTS user_sessoes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL UNIQUE,
+    user_id TEXT NOT NULL,
+    ip TEXT,                       -- Último IP visto
+    user_agent TEXT,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),        -- UTC
+    ultima_atividade TEXT NOT NULL DEFAULT (datetime('now')), -- UTC (atualizada no máx. 1x/minuto)
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_sessoes_user ON user_sessoes (user_id);
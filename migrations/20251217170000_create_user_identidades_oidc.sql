@@ -0,0 +1,12 @@
+-- migrations/20251217170000_create_user_identidades_oidc.sql
+
+-- Ligação entre a conta institucional (claim `sub` do fornecedor OIDC) e o utilizador local.
+-- Criada automaticamente no primeiro login institucional (por ID ou email).
+CREATE TABLE IF NOT EXISTS user_identidades_oidc (
+    sub TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL,
+    ligado_em TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_identidades_oidc_user ON user_identidades_oidc (user_id);
@@ -0,0 +1,5 @@
+-- migrations/20251217180000_add_users_password_esquema.sql
+
+-- Esquema do hash guardado em password_hash: 'bcrypt' (legado) ou 'argon2id'.
+-- Os hashes bcrypt são convertidos para Argon2id no próximo login bem-sucedido.
+ALTER TABLE users ADD COLUMN password_esquema TEXT NOT NULL DEFAULT 'bcrypt';
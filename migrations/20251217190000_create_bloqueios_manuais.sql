@@ -0,0 +1,12 @@
+-- migrations/20251217190000_create_bloqueios_manuais.sql
+
+-- Contas bloqueadas pela administração (ex.: medida disciplinar), independentes do limite de tentativas.
+-- Uma linha por utilizador; `ate` NULL = bloqueado até ser desbloqueado manualmente.
+CREATE TABLE IF NOT EXISTS bloqueios_manuais (
+    user_id TEXT PRIMARY KEY NOT NULL,
+    motivo TEXT NOT NULL,
+    bloqueado_por TEXT NOT NULL,                               -- ID do admin
+    bloqueado_em TEXT NOT NULL DEFAULT (datetime('now')),      -- UTC
+    ate TEXT,                                                  -- UTC
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,16 @@
+-- migrations/20251217200000_create_notificacoes.sql
+
+-- Notificações para um utilizador (mostradas no painel). Partilhada pelas várias funcionalidades:
+-- cada uma usa o seu `tipo` (ex: 'seguranca').
+CREATE TABLE IF NOT EXISTS notificacoes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    tipo TEXT NOT NULL,
+    titulo TEXT NOT NULL,
+    mensagem TEXT NOT NULL,
+    criada_em TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    lida_em TEXT,                                       -- UTC; NULL = por ler
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_notificacoes_user ON notificacoes (user_id, id);
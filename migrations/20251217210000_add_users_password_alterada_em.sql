@@ -0,0 +1,6 @@
+-- migrations/20251217210000_add_users_password_alterada_em.sql
+
+-- Data da última alteração de senha (UTC), para a política opcional de validade das senhas.
+-- As contas existentes começam a contar a partir desta migração.
+ALTER TABLE users ADD COLUMN password_alterada_em TEXT;
+UPDATE users SET password_alterada_em = datetime('now');
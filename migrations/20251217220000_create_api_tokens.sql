@@ -0,0 +1,16 @@
+-- migrations/20251217220000_create_api_tokens.sql
+
+-- Tokens de acesso à API JSON (/api/v1) para integrações, criados por cada utilizador em /user/tokens.
+-- Só se guarda o SHA-256 do token: o valor completo é mostrado uma única vez, na criação.
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    nome TEXT NOT NULL,                 -- Descrição dada pelo utilizador (ex.: "Painel da companhia")
+    prefixo TEXT NOT NULL,              -- Início do token, para o reconhecer na listagem
+    token_hash TEXT NOT NULL UNIQUE,    -- SHA-256 (hex) do token
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    ultimo_uso TEXT,                    -- UTC (atualizado no máx. 1x/minuto)
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user ON api_tokens (user_id);
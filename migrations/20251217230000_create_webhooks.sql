@@ -0,0 +1,33 @@
+-- migrations/20251217230000_create_webhooks.sql
+
+-- Webhooks configurados pela administração: URLs que recebem (POST JSON) os eventos subscritos.
+CREATE TABLE IF NOT EXISTS webhooks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL,
+    segredo TEXT NOT NULL,              -- Chave da assinatura HMAC-SHA256 (cabeçalho X-Mercal-Assinatura)
+    eventos TEXT NOT NULL,              -- Eventos subscritos, separados por vírgulas (ex.: "escala.publicada,user.criado")
+    ativo BOOLEAN NOT NULL DEFAULT 1,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    criado_por TEXT                     -- ID do administrador
+);
+
+-- Fila e registo das entregas: cada evento gera uma linha por webhook subscrito.
+CREATE TABLE IF NOT EXISTS webhook_entregas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    webhook_id INTEGER NOT NULL,
+    evento TEXT NOT NULL,
+    payload TEXT NOT NULL,              -- Corpo JSON enviado (igual em todas as tentativas)
+    estado TEXT NOT NULL DEFAULT 'pendente', -- 'pendente', 'entregue' ou 'falhada' (esgotou as tentativas)
+    tentativas INTEGER NOT NULL DEFAULT 0,
+    proxima_tentativa TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    ultimo_status INTEGER,              -- Código HTTP da última resposta
+    ultimo_erro TEXT,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    entregue_em TEXT,                   -- UTC
+    FOREIGN KEY (webhook_id) REFERENCES webhooks(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_entregas_fila ON webhook_entregas (estado, proxima_tentativa);
+
+-- Saída já comunicada como atrasada (evento presenca.atrasada): evita repetir o aviso para a mesma saída.
+ALTER TABLE presenca ADD COLUMN atraso_avisado TEXT;
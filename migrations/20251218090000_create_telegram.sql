@@ -0,0 +1,20 @@
+-- migrations/20251218090000_create_telegram.sql
+
+-- Ligação da conta a um chat do Telegram (bot de notificações).
+-- O utilizador gera um código no painel e envia-o ao bot (/start <código>); o bot guarda o chat_id.
+CREATE TABLE IF NOT EXISTS telegram_ligacoes (
+    user_id TEXT PRIMARY KEY NOT NULL,
+    chat_id INTEGER UNIQUE,             -- NULL enquanto o código não for usado
+    codigo TEXT UNIQUE,                 -- Código de ligação pendente (NULL depois de ligado)
+    codigo_expira_em TEXT,              -- UTC
+    ligado_em TEXT,                     -- UTC
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Lembretes de serviço já enviados (um por alocação e utilizador; uma troca aprovada volta a lembrar o substituto).
+CREATE TABLE IF NOT EXISTS lembretes_servico (
+    alocacao_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    enviado_em TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    PRIMARY KEY (alocacao_id, user_id)
+);
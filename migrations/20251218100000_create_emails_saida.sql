@@ -0,0 +1,23 @@
+-- migrations/20251218100000_create_emails_saida.sql
+
+-- Caixa de saída dos emails: cada email fica aqui até a tarefa de envio o entregar ao servidor SMTP.
+CREATE TABLE IF NOT EXISTS emails_saida (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT,                       -- Destinatário (NULL se a conta for apagada)
+    destinatario TEXT NOT NULL,         -- Endereço no momento em que o email foi criado
+    tipo TEXT NOT NULL,                 -- 'senha', 'escala', 'troca'
+    assunto TEXT NOT NULL,
+    corpo TEXT NOT NULL,                -- Texto simples
+    estado TEXT NOT NULL DEFAULT 'pendente', -- 'pendente', 'enviado' ou 'falhado' (esgotou as tentativas)
+    tentativas INTEGER NOT NULL DEFAULT 0,
+    proxima_tentativa TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    ultimo_erro TEXT,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    enviado_em TEXT,                    -- UTC
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_emails_saida_fila ON emails_saida (estado, proxima_tentativa);
+
+-- Preferência do utilizador: receber avisos por email (escala, trocas). Os emails de segurança são sempre enviados.
+ALTER TABLE users ADD COLUMN emails_ativos BOOLEAN NOT NULL DEFAULT 1;
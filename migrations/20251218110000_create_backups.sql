@@ -0,0 +1,17 @@
+-- migrations/20251218110000_create_backups.sql
+
+-- Registo das cópias de segurança da base de dados (VACUUM INTO para BACKUP_DIR; ver backup_service).
+CREATE TABLE IF NOT EXISTS backups (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ficheiro TEXT NOT NULL,             -- Nome do ficheiro dentro de BACKUP_DIR
+    origem TEXT NOT NULL,               -- 'automatico' ou 'manual'
+    criado_por TEXT,                    -- Admin que pediu a cópia (NULL nas automáticas)
+    estado TEXT NOT NULL,               -- 'ok' ou 'falhado'
+    tamanho INTEGER,                    -- Bytes (só se 'ok')
+    duracao_ms INTEGER,
+    erro TEXT,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    removido_em TEXT                    -- UTC; ficheiro apagado pela rotação
+);
+
+CREATE INDEX IF NOT EXISTS idx_backups_estado ON backups (estado, removido_em);
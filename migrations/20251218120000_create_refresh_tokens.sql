@@ -0,0 +1,20 @@
+-- migrations/20251218120000_create_refresh_tokens.sql
+
+-- Refresh tokens da autenticação JWT (/api/auth) da aplicação móvel. O access token (JWT) é de curta duração
+-- e não fica guardado; o refresh token permite obter um novo e é rodado a cada utilização.
+-- Só se guarda o SHA-256 do token. Os tokens obtidos a partir do mesmo login partilham a `familia`:
+-- reutilizar um token já rodado (sinal de roubo) revoga a família inteira.
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,    -- SHA-256 (hex) do token
+    familia TEXT NOT NULL,              -- UUID do login que deu origem ao token
+    dispositivo TEXT,                   -- User-Agent do login
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    expira_em TEXT NOT NULL,            -- UTC
+    revogado_em TEXT,                   -- UTC (rodado, logout ou sessões terminadas)
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_user ON refresh_tokens (user_id);
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_familia ON refresh_tokens (familia);
@@ -0,0 +1,8 @@
+-- migrations/20251218130000_add_escalas_publicada_em.sql
+
+-- Data/hora (UTC) da publicação de cada dia da escala, para o feed Atom (/feeds/escala.atom).
+-- Volta a NULL quando o dia é reaberto por errata e é renovada na nova publicação.
+ALTER TABLE escalas ADD COLUMN publicada_em TEXT;
+
+-- Dias já publicados: não se sabe quando foram; fica a meia-noite do próprio dia.
+UPDATE escalas SET publicada_em = data || ' 00:00:00' WHERE status = 'Publicada';
@@ -0,0 +1,27 @@
+-- migrations/20251218140000_create_google_calendar.sql
+
+-- Ligação da conta ao Google Calendar (OAuth): os serviços publicados do utilizador são criados,
+-- atualizados e apagados no calendário dele por uma tarefa de sincronização.
+CREATE TABLE IF NOT EXISTS google_calendar_ligacoes (
+    user_id TEXT PRIMARY KEY NOT NULL,
+    refresh_token TEXT NOT NULL,        -- Para obter novos access tokens (revogado ao desligar)
+    access_token TEXT,
+    access_expira_em TEXT,              -- UTC
+    calendario_id TEXT NOT NULL DEFAULT 'primary',
+    ligado_em TEXT NOT NULL DEFAULT (datetime('now')), -- UTC
+    sincronizado_em TEXT,               -- UTC, última sincronização sem erros
+    ultimo_erro TEXT,                   -- Mostrado no painel (NULL = tudo bem)
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Eventos criados no calendário de cada utilizador, um por alocação.
+-- `assinatura` resume os dados mostrados no evento (dia, posto, etiqueta): se mudar, o evento é atualizado.
+CREATE TABLE IF NOT EXISTS google_calendar_eventos (
+    user_id TEXT NOT NULL,
+    alocacao_id TEXT NOT NULL,
+    event_id TEXT NOT NULL,             -- ID do evento no Google Calendar
+    data TEXT NOT NULL,                 -- YYYY-MM-DD (os eventos de dias passados não são apagados)
+    assinatura TEXT NOT NULL,
+    PRIMARY KEY (user_id, alocacao_id),
+    FOREIGN KEY (user_id) REFERENCES google_calendar_ligacoes(user_id) ON DELETE CASCADE
+);
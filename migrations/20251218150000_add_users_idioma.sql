@@ -0,0 +1,5 @@
+-- migrations/20251218150000_add_users_idioma.sql
+
+-- Idioma escolhido pelo utilizador para as páginas ("pt" ou "en").
+-- NULL = sem escolha: usa-se o Accept-Language do browser ou o idioma por omissão.
+ALTER TABLE users ADD COLUMN idioma TEXT;
@@ -0,0 +1,9 @@
+-- migrations/20251218160000_presenca_utc.sql
+
+-- As marcações de presença eram guardadas com o fuso do servidor (ex: 2025-12-18T14:03:05.123-03:00).
+-- Passam a ser guardadas em UTC, com largura fixa (2025-12-18T17:03:05Z), para que a comparação
+-- entre saída e retorno como texto (dashboard, atrasos) continue correta; ver src/tempo.rs.
+UPDATE presenca SET
+    ultima_saida = COALESCE(strftime('%Y-%m-%dT%H:%M:%SZ', ultima_saida), ultima_saida),
+    ultimo_retorno = COALESCE(strftime('%Y-%m-%dT%H:%M:%SZ', ultimo_retorno), ultimo_retorno),
+    atraso_avisado = COALESCE(strftime('%Y-%m-%dT%H:%M:%SZ', atraso_avisado), atraso_avisado);
@@ -0,0 +1,14 @@
+-- migrations/20251218170000_create_tarefas_agendadas.sql
+
+-- Estado das tarefas periódicas do agendador (ver agendador_service): uma linha por tarefa registada.
+-- As tarefas são definidas no código; esta tabela guarda só a última execução e a próxima prevista.
+CREATE TABLE IF NOT EXISTS tarefas_agendadas (
+    nome TEXT PRIMARY KEY NOT NULL,     -- Nome da tarefa (ex: 'sessoes.limpeza')
+    ultima_execucao TEXT,               -- UTC (RFC3339); início da última execução
+    duracao_ms INTEGER,
+    estado TEXT,                        -- 'ok' ou 'erro' (última execução)
+    resultado TEXT,                     -- Resumo devolvido pela tarefa, ou a mensagem de erro
+    execucoes INTEGER NOT NULL DEFAULT 0,
+    falhas INTEGER NOT NULL DEFAULT 0,
+    proxima_execucao TEXT               -- UTC (RFC3339), já com o desvio aleatório
+);
@@ -0,0 +1,86 @@
+-- migrations/20251218180000_create_organizacoes.sql
+
+-- Várias organizações (companhias/unidades) na mesma instância, cada uma com os seus utilizadores,
+-- postos, grupos e escalas. Os dados existentes ficam na organização 1 ("principal").
+-- As tabelas escalas/alocacoes/grupos são recriadas (a chave de escalas passa a ser (organizacao_id, data));
+-- as migrações correm sem chaves estrangeiras (ver db::executar_migracoes).
+
+CREATE TABLE IF NOT EXISTS organizacoes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    codigo TEXT NOT NULL UNIQUE COLLATE NOCASE, -- Identificador curto (ex: 'cia1')
+    nome TEXT NOT NULL,
+    ativa INTEGER NOT NULL DEFAULT 1,           -- 0 = utilizadores sem acesso
+    criado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+INSERT OR IGNORE INTO organizacoes (id, codigo, nome) VALUES (1, 'principal', 'Unidade principal');
+
+-- Utilizadores e postos: coluna nova (os IDs dos utilizadores continuam únicos na instância)
+ALTER TABLE users ADD COLUMN organizacao_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizacoes (id);
+CREATE INDEX IF NOT EXISTS idx_users_organizacao ON users (organizacao_id);
+
+ALTER TABLE postos ADD COLUMN organizacao_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizacoes (id);
+
+-- Grupos: o nome passa a ser único dentro da organização
+CREATE TABLE grupos_novo (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizacoes (id),
+    nome TEXT NOT NULL COLLATE NOCASE,
+    tipo TEXT NOT NULL CHECK (tipo IN ('pelotao', 'companhia', 'equipa')),
+    descricao TEXT NOT NULL DEFAULT '',
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, nome)
+);
+INSERT INTO grupos_novo (id, nome, tipo, descricao, criado_em)
+SELECT id, nome, tipo, descricao, criado_em FROM grupos;
+DROP TABLE grupos;
+ALTER TABLE grupos_novo RENAME TO grupos;
+
+-- Escalas: um dia por organização
+CREATE TABLE escalas_novo (
+    organizacao_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizacoes (id),
+    data TEXT NOT NULL,       -- YYYY-MM-DD
+    tipo_rotina TEXT NOT NULL, -- 'RN' ou 'RD'
+    status TEXT DEFAULT 'Rascunho',
+    publicada_em TEXT,
+    PRIMARY KEY (organizacao_id, data)
+);
+INSERT INTO escalas_novo (data, tipo_rotina, status, publicada_em)
+SELECT data, tipo_rotina, status, publicada_em FROM escalas;
+DROP TABLE escalas;
+ALTER TABLE escalas_novo RENAME TO escalas;
+
+-- Alocações: apontam para o dia da escala da sua organização
+CREATE TABLE alocacoes_novo (
+    id TEXT PRIMARY KEY NOT NULL, -- UUID
+    organizacao_id INTEGER NOT NULL DEFAULT 1,
+    user_id TEXT NOT NULL,
+    posto_id INTEGER NOT NULL,
+    data TEXT NOT NULL,           -- YYYY-MM-DD
+    is_punicao BOOLEAN DEFAULT 0, -- 0=False, 1=True
+    tag TEXT DEFAULT NULL,
+
+    FOREIGN KEY (user_id) REFERENCES users (id),
+    FOREIGN KEY (posto_id) REFERENCES postos (id),
+    FOREIGN KEY (organizacao_id, data) REFERENCES escalas (organizacao_id, data),
+
+    -- Um militar não pode ter dois registos no mesmo dia
+    UNIQUE (user_id, data)
+);
+INSERT INTO alocacoes_novo (id, user_id, posto_id, data, is_punicao, tag)
+SELECT id, user_id, posto_id, data, is_punicao, tag FROM alocacoes;
+DROP TABLE alocacoes;
+ALTER TABLE alocacoes_novo RENAME TO alocacoes;
+CREATE INDEX IF NOT EXISTS idx_alocacoes_organizacao_data ON alocacoes (organizacao_id, data);
+
+-- Administração da instância (organizações, roles, webhooks, cópias de segurança, ...):
+-- role própria, dada a quem já administrava a instância
+INSERT OR IGNORE INTO roles (nome, descricao, permanente, temporaria, sistema) VALUES
+    ('superadmin', 'Administração da instância (todas as organizações)', 1, 0, 1);
+
+INSERT OR IGNORE INTO role_permissoes (role, permissao)
+SELECT 'superadmin', permissao FROM role_permissoes WHERE role = 'admin';
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES ('superadmin', 'superadmin');
+
+INSERT OR IGNORE INTO user_roles (user_id, role)
+SELECT user_id, 'superadmin' FROM user_roles WHERE role = 'admin';
@@ -0,0 +1,12 @@
+-- migrations/20251218190000_create_retencao_resumos.sql
+
+-- Totais por mês dos registos apagados pela política de retenção (ver retencao_service), para que as
+-- estatísticas antigas sobrevivam à limpeza das linhas originais
+CREATE TABLE IF NOT EXISTS retencao_resumos (
+    tabela TEXT NOT NULL,          -- 'audit_log', 'login_history' ou 'trocas'
+    mes TEXT NOT NULL,             -- 'YYYY-MM' (UTC) dos registos apagados
+    chave TEXT NOT NULL,           -- O que foi contado (ex: a ação da auditoria, 'falha:senha_invalida')
+    total INTEGER NOT NULL,
+    atualizado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (tabela, mes, chave)
+);
@@ -0,0 +1,10 @@
+-- migrations/20251218200000_create_manutencao.sql
+
+-- Modo de manutenção (só leitura): uma linha enquanto está ativo, ligado pela área de super-admin
+-- (ver manutencao_service). Guardado na base de dados para sobreviver a um reinício a meio de um restauro.
+CREATE TABLE IF NOT EXISTS manutencao (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    motivo TEXT NOT NULL DEFAULT '',   -- Mostrado no aviso das páginas
+    ativada_por TEXT NOT NULL,         -- ID do super-admin
+    ativada_em TEXT NOT NULL DEFAULT (datetime('now')) -- UTC
+);
@@ -0,0 +1,26 @@
+-- migrations/20251218210000_create_servico_ledger.sql
+
+-- Livro de serviços (só acrescenta, nunca altera nem apaga): uma linha por serviço creditado ou debitado.
+-- Os contadores users.servicos_rn / servicos_rd / saldo_punicoes passam a ser a soma destas linhas,
+-- atualizados na mesma transação (e reconciliados em /admin/contabilidade se divergirem).
+CREATE TABLE IF NOT EXISTS servico_ledger (
+    id TEXT PRIMARY KEY,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    user_id TEXT NOT NULL REFERENCES users (id),
+    conta TEXT NOT NULL CHECK (conta IN ('RN', 'RD', 'PUNICAO')), -- Contador afetado
+    delta INTEGER NOT NULL,        -- +1 / -1 (no saldo de punições: -1 = punição cumprida)
+    motivo TEXT NOT NULL,          -- 'alocacao', 'regeneracao', 'troca', 'saldo_inicial'
+    data TEXT,                     -- Dia do serviço ('YYYY-MM-DD'), se houver
+    referencia TEXT,               -- Alocação ou troca que originou o lançamento
+    criado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_servico_ledger_user ON servico_ledger (user_id, conta);
+CREATE INDEX IF NOT EXISTS idx_servico_ledger_organizacao ON servico_ledger (organizacao_id, criado_em);
+
+-- Saldo de abertura: os contadores atuais entram como um lançamento cada
+INSERT INTO servico_ledger (id, organizacao_id, user_id, conta, delta, motivo)
+SELECT lower(hex(randomblob(16))), organizacao_id, id, 'RN', servicos_rn, 'saldo_inicial' FROM users WHERE COALESCE(servicos_rn, 0) <> 0;
+INSERT INTO servico_ledger (id, organizacao_id, user_id, conta, delta, motivo)
+SELECT lower(hex(randomblob(16))), organizacao_id, id, 'RD', servicos_rd, 'saldo_inicial' FROM users WHERE COALESCE(servicos_rd, 0) <> 0;
+INSERT INTO servico_ledger (id, organizacao_id, user_id, conta, delta, motivo)
+SELECT lower(hex(randomblob(16))), organizacao_id, id, 'PUNICAO', saldo_punicoes, 'saldo_inicial' FROM users WHERE COALESCE(saldo_punicoes, 0) <> 0;
@@ -0,0 +1,15 @@
+-- migrations/20251219090000_create_user_preferences.sql
+
+-- Preferências da interface de cada utilizador (ver preferencias_service), guardadas no servidor para
+-- serem as mesmas em todos os dispositivos. Sem linha = valores por omissão.
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_id TEXT PRIMARY KEY NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    tema TEXT NOT NULL DEFAULT 'claro' CHECK (tema IN ('claro', 'escuro', 'sistema')), -- 'sistema' = o do sistema operativo
+    turma_presenca INTEGER,            -- Turma aberta por omissão em /presence (NULL = 1º ano)
+    por_pagina INTEGER,                -- Linhas por página nas listagens (NULL = o da aplicação)
+    -- Avisos por tipo de notificação, fora do painel (Telegram e email); os de segurança vão sempre
+    avisos_escala BOOLEAN NOT NULL DEFAULT 1,
+    avisos_troca BOOLEAN NOT NULL DEFAULT 1,
+    avisos_lembrete BOOLEAN NOT NULL DEFAULT 1,
+    atualizado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
@@ -0,0 +1,39 @@
+-- migrations/20251219100000_create_arranchamento.sql
+
+-- Arranchamento: cada utilizador marca as refeições do dia em que vai comer, até à hora de corte;
+-- o rancheiro vê a previsão por refeição e turma (ver arranchamento_service).
+
+-- Refeições servidas em cada organização, com a hora de corte das marcações
+CREATE TABLE IF NOT EXISTS refeicoes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    nome TEXT NOT NULL,
+    ordem INTEGER NOT NULL DEFAULT 0,          -- Ordem no dia (café, almoço, jantar, ...)
+    corte_dias INTEGER NOT NULL DEFAULT 1,     -- Dias antes da refeição em que fecham as marcações (1 = véspera)
+    corte_hora TEXT NOT NULL DEFAULT '12:00',  -- 'HH:MM', no fuso da aplicação
+    ativa BOOLEAN NOT NULL DEFAULT 1,          -- Inativa: não aparece para marcar (as marcações ficam)
+    UNIQUE (organizacao_id, nome)
+);
+
+-- Refeições habituais nas organizações existentes (as novas criam-nas na página do rancho)
+INSERT OR IGNORE INTO refeicoes (organizacao_id, nome, ordem, corte_dias, corte_hora)
+SELECT id, 'Café da manhã', 1, 1, '20:00' FROM organizacoes;
+INSERT OR IGNORE INTO refeicoes (organizacao_id, nome, ordem, corte_dias, corte_hora)
+SELECT id, 'Almoço', 2, 0, '09:00' FROM organizacoes;
+INSERT OR IGNORE INTO refeicoes (organizacao_id, nome, ordem, corte_dias, corte_hora)
+SELECT id, 'Jantar', 3, 0, '14:00' FROM organizacoes;
+
+-- Marcações: uma linha por utilizador, refeição e dia
+CREATE TABLE IF NOT EXISTS arranchamentos (
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    refeicao_id INTEGER NOT NULL REFERENCES refeicoes (id) ON DELETE CASCADE,
+    data TEXT NOT NULL, -- YYYY-MM-DD
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (user_id, refeicao_id, data)
+);
+CREATE INDEX IF NOT EXISTS idx_arranchamentos_data ON arranchamentos (data, refeicao_id);
+
+-- Permissão da página do rancho (previsão e refeições)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('rancheiro', 'rancho'),
+    ('admin', 'rancho');
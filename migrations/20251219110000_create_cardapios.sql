@@ -0,0 +1,14 @@
+-- migrations/20251219110000_create_cardapios.sql
+
+-- Cardápio: o rancheiro publica, para cada semana, o prato de cada refeição em cada dia
+-- (ver cardapio_service). Mostrado no painel do utilizador e em /api/v1/cardapio.
+CREATE TABLE IF NOT EXISTS cardapios (
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    refeicao_id INTEGER NOT NULL REFERENCES refeicoes (id) ON DELETE CASCADE,
+    data TEXT NOT NULL,        -- YYYY-MM-DD
+    descricao TEXT NOT NULL,   -- O que é servido (ex: "Arroz, feijão, frango grelhado")
+    publicado_por TEXT,        -- Rancheiro que gravou a última alteração
+    atualizado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (refeicao_id, data)
+);
+CREATE INDEX IF NOT EXISTS idx_cardapios_organizacao_data ON cardapios (organizacao_id, data);
@@ -0,0 +1,44 @@
+-- migrations/20251219120000_create_loja.sql
+
+-- Loja (cantina): produtos com preço e stock, vendas registadas pelo operador (permissão 'loja')
+-- e os itens de cada venda (ver loja_service). Valores em centavos.
+
+CREATE TABLE IF NOT EXISTS produtos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    nome TEXT NOT NULL,
+    preco INTEGER NOT NULL CHECK (preco >= 0), -- Centavos
+    stock INTEGER NOT NULL DEFAULT 0 CHECK (stock >= 0),
+    ativo BOOLEAN NOT NULL DEFAULT 1,          -- Inativo: deixa de aparecer para vender (as vendas ficam)
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, nome)
+);
+
+CREATE TABLE IF NOT EXISTS vendas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    user_id TEXT REFERENCES users (id) ON DELETE SET NULL, -- Comprador (NULL = venda ao balcão, sem identificação)
+    operador_id TEXT NOT NULL,                             -- Quem registou a venda
+    total INTEGER NOT NULL DEFAULT 0,                      -- Centavos
+    pagamento TEXT NOT NULL DEFAULT 'dinheiro',            -- 'dinheiro' ou 'cartao'
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    anulada_em TEXT,                                       -- Venda anulada (o stock é reposto)
+    anulada_por TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_vendas_organizacao_data ON vendas (organizacao_id, criado_em);
+CREATE INDEX IF NOT EXISTS idx_vendas_user ON vendas (user_id, criado_em);
+
+-- Itens de cada venda, com o preço unitário do momento
+CREATE TABLE IF NOT EXISTS venda_itens (
+    venda_id INTEGER NOT NULL REFERENCES vendas (id) ON DELETE CASCADE,
+    produto_id INTEGER NOT NULL REFERENCES produtos (id),
+    quantidade INTEGER NOT NULL CHECK (quantidade > 0),
+    preco INTEGER NOT NULL, -- Centavos, por unidade
+    PRIMARY KEY (venda_id, produto_id)
+);
+
+-- Permissão da loja (ponto de venda); o operador também pesquisa utilizadores (comprador)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('loja', 'loja'),
+    ('loja', 'users.pesquisar'),
+    ('admin', 'loja');
@@ -0,0 +1,17 @@
+-- migrations/20251219130000_create_conta_movimentos.sql
+
+-- Conta corrente da loja: carregamentos registados pelo operador e compras pagas com a conta
+-- (pagamento 'conta'). O saldo de um utilizador é a soma dos seus movimentos (ver loja_service).
+
+CREATE TABLE IF NOT EXISTS conta_movimentos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    tipo TEXT NOT NULL,                                        -- 'carregamento', 'compra' ou 'anulacao'
+    valor INTEGER NOT NULL,                                    -- Centavos: positivo = crédito, negativo = débito
+    venda_id INTEGER REFERENCES vendas (id) ON DELETE SET NULL, -- Compras e anulações
+    operador_id TEXT NOT NULL,                                 -- Quem registou o movimento
+    criado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_conta_movimentos_user ON conta_movimentos (user_id, criado_em);
+CREATE INDEX IF NOT EXISTS idx_conta_movimentos_organizacao ON conta_movimentos (organizacao_id, user_id);
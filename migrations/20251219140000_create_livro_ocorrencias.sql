@@ -0,0 +1,33 @@
+-- migrations/20251219140000_create_livro_ocorrencias.sql
+
+-- Livro de ocorrências: o chefe de dia regista as ocorrências do seu serviço, dia a dia (o dia da escala),
+-- e fecha o dia com a passagem de serviço ao seguinte. Um dia fechado já não muda (ver livro_service).
+
+CREATE TABLE IF NOT EXISTS livro_ocorrencias (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    data TEXT NOT NULL,       -- YYYY-MM-DD, dia do serviço (o da escala)
+    hora TEXT NOT NULL,       -- 'HH:MM' da ocorrência, no fuso da aplicação
+    autor_id TEXT NOT NULL,
+    texto TEXT NOT NULL,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    corrigido_em TEXT         -- Corrigida pelo autor antes do fecho do dia
+);
+CREATE INDEX IF NOT EXISTS idx_livro_ocorrencias_data ON livro_ocorrencias (organizacao_id, data);
+
+-- Fecho de cada dia: a passagem de serviço, escrita por quem o fecha, e quem a recebe
+CREATE TABLE IF NOT EXISTS livro_fechos (
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    data TEXT NOT NULL,       -- YYYY-MM-DD
+    fechado_por TEXT NOT NULL,
+    recebido_por TEXT,        -- Chefe de dia que entra (opcional)
+    passagem TEXT NOT NULL,
+    fechado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (organizacao_id, data)
+);
+
+-- Consulta do livro (administração e chefe de dia) e escrita (só o chefe de dia, durante o serviço)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('chefe_de_dia', 'livro'),
+    ('chefe_de_dia', 'livro.escrever'),
+    ('admin', 'livro');
@@ -0,0 +1,52 @@
+-- migrations/20251219150000_create_revistas.sql
+
+-- Revistas (inspeções aos quartos): listas de verificação com os itens e os pontos de cada um, e o
+-- resultado de cada revista a um utilizador, com os itens em falta (ver revista_service).
+
+CREATE TABLE IF NOT EXISTS revista_listas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    nome TEXT NOT NULL,
+    ativa BOOLEAN NOT NULL DEFAULT 1, -- Inativa: deixa de aparecer para novas revistas (as feitas ficam)
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, nome)
+);
+
+CREATE TABLE IF NOT EXISTS revista_itens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    lista_id INTEGER NOT NULL REFERENCES revista_listas (id) ON DELETE CASCADE,
+    descricao TEXT NOT NULL,
+    pontos INTEGER NOT NULL DEFAULT 1 CHECK (pontos > 0), -- Peso do item na pontuação
+    ordem INTEGER NOT NULL DEFAULT 0,
+    ativo BOOLEAN NOT NULL DEFAULT 1
+);
+CREATE INDEX IF NOT EXISTS idx_revista_itens_lista ON revista_itens (lista_id, ordem);
+
+-- Uma revista: a pontuação (pontos dos itens cumpridos) e o máximo possível no momento
+CREATE TABLE IF NOT EXISTS revistas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    lista_id INTEGER NOT NULL REFERENCES revista_listas (id),
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    quarto TEXT NOT NULL DEFAULT '',
+    data TEXT NOT NULL, -- YYYY-MM-DD
+    pontuacao INTEGER NOT NULL,
+    maximo INTEGER NOT NULL,
+    observacoes TEXT NOT NULL DEFAULT '',
+    inspetor_id TEXT NOT NULL,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_revistas_organizacao_data ON revistas (organizacao_id, data);
+CREATE INDEX IF NOT EXISTS idx_revistas_user ON revistas (user_id, data);
+
+-- Itens em falta em cada revista
+CREATE TABLE IF NOT EXISTS revista_falhas (
+    revista_id INTEGER NOT NULL REFERENCES revistas (id) ON DELETE CASCADE,
+    item_id INTEGER NOT NULL REFERENCES revista_itens (id),
+    PRIMARY KEY (revista_id, item_id)
+);
+
+-- Permissão das revistas (listas, registo e estatísticas)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('chefe_de_dia', 'revista'),
+    ('admin', 'revista');
@@ -0,0 +1,24 @@
+-- migrations/20251219160000_create_quartos.sql
+
+-- Alojamento: os quartos (agrupados por corredor, com o número de camas) e a cama atribuída a cada
+-- utilizador. Cada utilizador tem no máximo uma cama; cada cama, no máximo um ocupante.
+
+CREATE TABLE IF NOT EXISTS quartos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    nome TEXT NOT NULL,                 -- Ex: "12" ou "B-04"
+    corredor TEXT NOT NULL DEFAULT '',  -- Ex: "Corredor B" (vazio = sem corredor)
+    capacidade INTEGER NOT NULL CHECK (capacidade > 0), -- Número de camas
+    ativo BOOLEAN NOT NULL DEFAULT 1,   -- Inativo (ex: em obras): não recebe novos ocupantes
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, nome)
+);
+
+CREATE TABLE IF NOT EXISTS quarto_ocupantes (
+    user_id TEXT PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    quarto_id INTEGER NOT NULL REFERENCES quartos (id) ON DELETE CASCADE,
+    cama INTEGER NOT NULL CHECK (cama > 0), -- 1..capacidade
+    atribuido_por TEXT NOT NULL,
+    atribuido_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (quarto_id, cama)
+);
@@ -0,0 +1,40 @@
+-- migrations/20251219170000_create_cautelas.sql
+
+-- Cautelas de material: o catálogo do material (com a quantidade existente) e cada entrega a um
+-- utilizador, com a data prevista de devolução e o registo da devolução (ver cautela_service).
+
+CREATE TABLE IF NOT EXISTS material (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    nome TEXT NOT NULL,
+    referencia TEXT NOT NULL DEFAULT '', -- Ex: número de série ou NNO (opcional)
+    quantidade INTEGER NOT NULL CHECK (quantidade >= 0), -- Existências (em armazém + cauteladas)
+    ativo BOOLEAN NOT NULL DEFAULT 1,    -- Inativo: deixa de poder ser cautelado (as cautelas ficam)
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, nome)
+);
+
+CREATE TABLE IF NOT EXISTS cautelas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    material_id INTEGER NOT NULL REFERENCES material (id),
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    quantidade INTEGER NOT NULL CHECK (quantidade > 0),
+    devolucao_prevista TEXT NOT NULL, -- YYYY-MM-DD
+    observacoes TEXT NOT NULL DEFAULT '',
+    entregue_por TEXT NOT NULL,
+    entregue_em TEXT NOT NULL DEFAULT (datetime('now')),
+    devolvida_em TEXT,                -- NULL = cautela aberta
+    recebida_por TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_cautelas_abertas ON cautelas (organizacao_id, devolvida_em, devolucao_prevista);
+CREATE INDEX IF NOT EXISTS idx_cautelas_user ON cautelas (user_id, devolvida_em);
+CREATE INDEX IF NOT EXISTS idx_cautelas_material ON cautelas (material_id, devolvida_em);
+
+-- Permissão das cautelas (role 'material': quem entrega e recebe o material); também pesquisa utilizadores
+INSERT OR IGNORE INTO roles (nome, descricao, permanente, temporaria, sistema) VALUES
+    ('material', 'Material (cautelas)', 1, 1, 0);
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('material', 'cautela'),
+    ('material', 'users.pesquisar'),
+    ('admin', 'cautela');
@@ -0,0 +1,27 @@
+-- migrations/20251219180000_create_baixas.sql
+
+-- Baixas médicas registadas pela secção de saúde: o período e as restrições (dispensas). Uma baixa
+-- ativa tira o utilizador da geração da escala e é assinalada na lista de presença (ver baixa_service).
+
+CREATE TABLE IF NOT EXISTS baixas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    data_inicio TEXT NOT NULL,            -- YYYY-MM-DD
+    data_fim TEXT NOT NULL,               -- YYYY-MM-DD (inclusive); a alta antecipa-a para a véspera
+    restricoes TEXT NOT NULL DEFAULT '',  -- Códigos separados por vírgulas (ver baixa_service::RESTRICOES)
+    observacoes TEXT NOT NULL DEFAULT '',
+    registada_por TEXT NOT NULL,
+    registada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    CHECK (data_fim >= data_inicio)
+);
+CREATE INDEX IF NOT EXISTS idx_baixas_periodo ON baixas (organizacao_id, data_fim, data_inicio);
+CREATE INDEX IF NOT EXISTS idx_baixas_user ON baixas (user_id, data_inicio, data_fim);
+
+-- Permissão das baixas (role 'saude': secção de saúde); também pesquisa utilizadores
+INSERT OR IGNORE INTO roles (nome, descricao, permanente, temporaria, sistema) VALUES
+    ('saude', 'Secção de saúde', 1, 1, 0);
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('saude', 'baixas'),
+    ('saude', 'users.pesquisar'),
+    ('admin', 'baixas');
@@ -0,0 +1,19 @@
+-- migrations/20251219190000_create_visitantes.sql
+
+-- Registo de visitantes na porta de armas: quem entra (nome e documento), quem o recebe (anfitrião),
+-- a entrada e a saída. Operado por quem marca a presença (permissão "presenca"; ver visitante_service).
+
+CREATE TABLE IF NOT EXISTS visitantes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    nome TEXT NOT NULL,
+    documento TEXT NOT NULL,             -- Ex: número do documento de identificação
+    anfitriao_id TEXT REFERENCES users (id) ON DELETE SET NULL, -- Utilizador visitado
+    motivo TEXT NOT NULL DEFAULT '',
+    entrada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    entrada_por TEXT NOT NULL,
+    saida_em TEXT,                       -- NULL = ainda dentro
+    saida_por TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_visitantes_entrada ON visitantes (organizacao_id, entrada_em);
+CREATE INDEX IF NOT EXISTS idx_visitantes_dentro ON visitantes (organizacao_id, saida_em);
@@ -0,0 +1,27 @@
+-- migrations/20251219200000_create_agenda.sql
+
+-- Agenda institucional: formaturas, cerimónias e palestras com dia, hora, anos a que se destinam e se a
+-- presença é obrigatória. Com `presenca_turma`, no dia do evento a lista de presença das turmas
+-- abrangidas assinala quem está fora (ver agenda_service).
+
+CREATE TABLE IF NOT EXISTS agenda_eventos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    tipo TEXT NOT NULL CHECK (tipo IN ('formatura', 'cerimonia', 'palestra')),
+    titulo TEXT NOT NULL,
+    local TEXT NOT NULL DEFAULT '',
+    descricao TEXT NOT NULL DEFAULT '',
+    data TEXT NOT NULL,                   -- YYYY-MM-DD (fuso da aplicação)
+    hora_inicio TEXT NOT NULL,            -- HH:MM
+    hora_fim TEXT,                        -- HH:MM (NULL = sem hora de fim indicada)
+    anos TEXT NOT NULL DEFAULT '',        -- Anos separados por vírgulas (vazio = todos)
+    obrigatorio INTEGER NOT NULL DEFAULT 0,
+    presenca_turma INTEGER NOT NULL DEFAULT 0, -- A turma inteira é esperada a bordo (lista de presença)
+    criado_por TEXT NOT NULL,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_agenda_eventos_data ON agenda_eventos (organizacao_id, data, hora_inicio);
+
+-- Gestão da agenda (todos os utilizadores a consultam)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('admin', 'agenda');
@@ -0,0 +1,4 @@
+-- migrations/20251219210000_notificacoes_por_ler.sql
+
+-- Contador de notificações por ler, mostrado na barra de navegação em todas as páginas.
+CREATE INDEX IF NOT EXISTS idx_notificacoes_por_ler ON notificacoes (user_id) WHERE lida_em IS NULL;
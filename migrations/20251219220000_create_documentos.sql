@@ -0,0 +1,44 @@
+-- migrations/20251219220000_create_documentos.sql
+
+-- Repositório de documentos (normas, ordens de serviço, instruções) em PDF, organizados em pastas.
+-- Cada pasta tem uma categoria e quem a pode ver: roles e anos (vazio = todos). Os ficheiros ficam em
+-- documentos.diretorio (DOCUMENTS_DIR); a tabela guarda o nome gerado e o nome original.
+
+CREATE TABLE IF NOT EXISTS documento_pastas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    categoria TEXT NOT NULL CHECK (categoria IN ('normas', 'ordens_servico', 'instrucoes', 'outros')),
+    nome TEXT NOT NULL,
+    descricao TEXT NOT NULL DEFAULT '',
+    roles TEXT NOT NULL DEFAULT '',       -- Roles separadas por vírgulas (vazio = todas)
+    anos TEXT NOT NULL DEFAULT '',        -- Anos separados por vírgulas (vazio = todos)
+    criado_por TEXT NOT NULL,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, nome)
+);
+
+CREATE TABLE IF NOT EXISTS documentos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    pasta_id INTEGER NOT NULL REFERENCES documento_pastas (id),
+    titulo TEXT NOT NULL,
+    ficheiro TEXT NOT NULL UNIQUE,        -- Nome no diretório dos documentos (gerado)
+    nome_original TEXT NOT NULL,          -- Nome do ficheiro enviado (para a descarga)
+    tamanho INTEGER NOT NULL,             -- Bytes
+    enviado_por TEXT NOT NULL,
+    enviado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_documentos_pasta ON documentos (pasta_id, titulo);
+
+-- Registo das descargas (quem abriu cada documento e quando)
+CREATE TABLE IF NOT EXISTS documento_downloads (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    documento_id INTEGER NOT NULL REFERENCES documentos (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    descarregado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_documento_downloads_documento ON documento_downloads (documento_id, id);
+
+-- Gestão das pastas e envio de documentos (todos os utilizadores consultam o que podem ver)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('admin', 'documentos');
@@ -0,0 +1,30 @@
+-- migrations/20251219230000_create_processos_disciplinares.sql
+
+-- Processos disciplinares (FATD): a transgressão registada contra um utilizador, a defesa dele e a decisão.
+-- Estados: aguarda_defesa -> aguarda_decisao -> punido | justificado | arquivado (ver disciplina_service).
+-- A punição credita o saldo de punições pelo livro de serviços (servico_ledger, motivo 'fatd',
+-- referencia = id do processo), na mesma transação da decisão.
+
+CREATE TABLE IF NOT EXISTS processos_disciplinares (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    user_id TEXT NOT NULL REFERENCES users (id),
+    data_fato TEXT NOT NULL,           -- YYYY-MM-DD
+    descricao TEXT NOT NULL,           -- O fato (a transgressão)
+    estado TEXT NOT NULL DEFAULT 'aguarda_defesa'
+        CHECK (estado IN ('aguarda_defesa', 'aguarda_decisao', 'punido', 'justificado', 'arquivado')),
+    prazo_defesa TEXT NOT NULL,        -- YYYY-MM-DD (inclusive)
+    defesa TEXT,                       -- NULL = sem defesa (prazo terminado)
+    defesa_em TEXT,                    -- Passagem a aguarda_decisao
+    servicos INTEGER NOT NULL DEFAULT 0 CHECK (servicos >= 0), -- Serviços de punição creditados (se punido)
+    fundamentacao TEXT,                -- Da decisão
+    decidido_por TEXT,
+    decidido_em TEXT,
+    registado_por TEXT NOT NULL,
+    registado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_processos_disciplinares_estado ON processos_disciplinares (organizacao_id, estado);
+CREATE INDEX IF NOT EXISTS idx_processos_disciplinares_user ON processos_disciplinares (user_id);
+
+-- Registo e decisão dos processos (a defesa é do próprio, sem permissão)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES ('admin', 'disciplina');
@@ -0,0 +1,7 @@
+-- migrations/20251220090000_add_users_antiguidade.sql
+
+-- Antiguidade dentro do ano: 1 = o mais antigo. NULL = sem posição (fica depois de todos os do ano).
+-- Definida pela administração em /admin/antiguidade; desempata a geração da escala (ver escala_service)
+-- e ordena o efetivo por turma (impressão).
+ALTER TABLE users ADD COLUMN antiguidade INTEGER;
+CREATE INDEX IF NOT EXISTS idx_users_antiguidade ON users (organizacao_id, ano, antiguidade);
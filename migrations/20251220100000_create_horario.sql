@@ -0,0 +1,29 @@
+-- migrations/20251220100000_create_horario.sql
+
+-- Horário das aulas: o plano semanal de cada turma (dia da semana, hora, disciplina, sala e instrutor).
+-- Os postos marcados como diurnos coincidem com as aulas: a geração da escala não os atribui a quem tem
+-- uma aula obrigatória nesse dia da semana (ver horario_service e escala_service).
+
+CREATE TABLE IF NOT EXISTS aulas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    turma TEXT NOT NULL,
+    dia_semana INTEGER NOT NULL CHECK (dia_semana BETWEEN 1 AND 7), -- 1 = segunda ... 7 = domingo
+    hora_inicio TEXT NOT NULL,            -- HH:MM
+    hora_fim TEXT NOT NULL,               -- HH:MM
+    disciplina TEXT NOT NULL,
+    sala TEXT NOT NULL DEFAULT '',
+    instrutor TEXT NOT NULL DEFAULT '',
+    obrigatoria INTEGER NOT NULL DEFAULT 1,
+    criado_por TEXT NOT NULL,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    CHECK (hora_fim > hora_inicio)
+);
+CREATE INDEX IF NOT EXISTS idx_aulas_turma ON aulas (organizacao_id, turma, dia_semana, hora_inicio);
+
+-- Serviço de dia: quem tem aulas obrigatórias nesse dia da semana não é escalado para o posto
+ALTER TABLE postos ADD COLUMN diurno INTEGER NOT NULL DEFAULT 0;
+
+-- Gestão do horário (todos os utilizadores o consultam)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('admin', 'horario');
@@ -0,0 +1,22 @@
+-- migrations/20251220110000_create_provas.sql
+
+-- Calendário das provas de cada turma, registado pela administração. Cada prova cria indisponibilidades
+-- na véspera e no dia para os utilizadores ativos da turma (ligadas pela coluna `prova_id`), que são
+-- apagadas com a prova (ver prova_service).
+
+CREATE TABLE IF NOT EXISTS provas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    turma TEXT NOT NULL,
+    data TEXT NOT NULL,                   -- YYYY-MM-DD
+    disciplina TEXT NOT NULL,
+    observacoes TEXT NOT NULL DEFAULT '',
+    registada_por TEXT NOT NULL,
+    registada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, turma, data, disciplina)
+);
+CREATE INDEX IF NOT EXISTS idx_provas_data ON provas (organizacao_id, data);
+
+-- Indisponibilidades criadas por uma prova (NULL = registadas à mão)
+ALTER TABLE indisponibilidades ADD COLUMN prova_id INTEGER REFERENCES provas (id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS idx_indisponibilidades_prova ON indisponibilidades (prova_id);
@@ -0,0 +1,18 @@
+-- migrations/20251220120000_create_uniformes.sql
+
+-- Uniforme do dia: definido pelo chefe de dia ou pela administração, um por dia. Aparece no dashboard,
+-- na escala e no boletim diário.
+CREATE TABLE IF NOT EXISTS uniformes_dia (
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    data TEXT NOT NULL,          -- YYYY-MM-DD
+    uniforme TEXT NOT NULL,
+    observacoes TEXT NOT NULL DEFAULT '',
+    definido_por TEXT NOT NULL,
+    definido_em TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (organizacao_id, data)
+);
+
+-- Definir o uniforme (todos o consultam)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('chefe_de_dia', 'uniforme'),
+    ('admin', 'uniforme');
@@ -0,0 +1,24 @@
+-- migrations/20251220130000_create_portaria.sql
+
+-- Controlo de portaria: entradas e saídas de viaturas e entregas (matrícula, condutor e motivo), à parte
+-- da presença e dos visitantes. Operado pela polícia (permissão "portaria"; ver portaria_service).
+
+CREATE TABLE IF NOT EXISTS portaria (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    tipo TEXT NOT NULL CHECK (tipo IN ('viatura', 'entrega')),
+    matricula TEXT NOT NULL,             -- Em maiúsculas
+    condutor TEXT NOT NULL,
+    motivo TEXT NOT NULL DEFAULT '',
+    entrada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    entrada_por TEXT NOT NULL,
+    saida_em TEXT,                       -- NULL = ainda dentro
+    saida_por TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_portaria_entrada ON portaria (organizacao_id, entrada_em);
+CREATE INDEX IF NOT EXISTS idx_portaria_dentro ON portaria (organizacao_id, saida_em);
+
+-- Registo na portaria (a polícia) e consulta pela administração
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('policia', 'portaria'),
+    ('admin', 'portaria');
@@ -0,0 +1,44 @@
+-- migrations/20251220140000_cautelas_material_sensivel.sql
+
+-- Material sensível (armamento e afins): a entrega e a devolução são confirmadas por duas pessoas, quem
+-- entrega/recebe no armazém e o utilizador, cada uma com a sua senha; a devolução exige a verificação do
+-- material. Cada movimento fica num registo encadeado por hashes (ver cautela_service).
+ALTER TABLE material ADD COLUMN sensivel BOOLEAN NOT NULL DEFAULT 0;
+
+ALTER TABLE cautelas ADD COLUMN confirmada_por TEXT;            -- Utilizador que confirmou a receção (material sensível)
+ALTER TABLE cautelas ADD COLUMN devolucao_confirmada_por TEXT;  -- Utilizador que confirmou a devolução
+ALTER TABLE cautelas ADD COLUMN verificacao_devolucao TEXT NOT NULL DEFAULT ''; -- Estado do material verificado na devolução
+
+-- Registo dos movimentos do material sensível: cada linha leva o hash da anterior (da organização), pelo
+-- que uma alteração ou remoção deixa a cadeia quebrada. Sem chaves estrangeiras: o registo sobrevive à
+-- remoção da cautela ou do utilizador, e as IDs ficam como estavam (a fusão de utilizadores não as muda).
+CREATE TABLE IF NOT EXISTS cautela_registos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL,
+    cautela_id INTEGER NOT NULL,
+    evento TEXT NOT NULL CHECK (evento IN ('entrega', 'devolucao')),
+    operador_id TEXT NOT NULL,      -- Quem entregou / recebeu no armazém
+    confirmado_por TEXT NOT NULL,   -- Utilizador que recebeu / devolveu
+    detalhes TEXT NOT NULL,
+    criado_em TEXT NOT NULL,        -- UTC, 'YYYY-MM-DD HH:MM:SS' (entra no hash)
+    hash_anterior TEXT NOT NULL,
+    hash TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_cautela_registos_cautela ON cautela_registos (cautela_id, evento);
+
+-- Só se acrescenta: alterar o conteúdo ou apagar uma linha é recusado (a reimportação das mesmas linhas passa)
+CREATE TRIGGER IF NOT EXISTS cautela_registos_sem_alteracao
+BEFORE UPDATE ON cautela_registos
+WHEN NEW.hash IS NOT OLD.hash OR NEW.hash_anterior IS NOT OLD.hash_anterior OR NEW.detalhes IS NOT OLD.detalhes
+    OR NEW.operador_id IS NOT OLD.operador_id OR NEW.confirmado_por IS NOT OLD.confirmado_por
+    OR NEW.cautela_id IS NOT OLD.cautela_id OR NEW.evento IS NOT OLD.evento OR NEW.criado_em IS NOT OLD.criado_em
+    OR NEW.organizacao_id IS NOT OLD.organizacao_id
+BEGIN
+    SELECT RAISE(ABORT, 'O registo do material sensível não pode ser alterado.');
+END;
+
+CREATE TRIGGER IF NOT EXISTS cautela_registos_sem_remocao
+BEFORE DELETE ON cautela_registos
+BEGIN
+    SELECT RAISE(ABORT, 'O registo do material sensível não pode ser apagado.');
+END;
@@ -0,0 +1,29 @@
+-- migrations/20251220150000_create_sugestoes.sql
+
+-- Caixa de sugestões: qualquer utilizador envia uma sugestão, identificada ou anónima; quem tem a
+-- permissão "sugestoes" faz a triagem (recebida, em análise, respondida) e responde, podendo publicar
+-- a resposta para todos. Numa sugestão anónima o autor não fica guardado: só um pseudónimo (HMAC do ID
+-- com uma chave do servidor, ver sugestao_service), que deixa o autor ver as suas e nunca é mostrado.
+
+CREATE TABLE IF NOT EXISTS sugestoes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    autor_id TEXT,                       -- NULL = anónima
+    autor_anonimo TEXT,                  -- Pseudónimo do autor (só nas anónimas)
+    assunto TEXT NOT NULL,
+    texto TEXT NOT NULL,
+    estado TEXT NOT NULL DEFAULT 'recebida' CHECK (estado IN ('recebida', 'em_analise', 'respondida')),
+    resposta TEXT NOT NULL DEFAULT '',
+    publicada BOOLEAN NOT NULL DEFAULT 0, -- Resposta visível a todos (sem o autor)
+    respondida_por TEXT,
+    respondida_em TEXT,
+    criada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    CHECK ((autor_id IS NULL) <> (autor_anonimo IS NULL))
+);
+CREATE INDEX IF NOT EXISTS idx_sugestoes_estado ON sugestoes (organizacao_id, estado, criada_em);
+CREATE INDEX IF NOT EXISTS idx_sugestoes_autor ON sugestoes (autor_id);
+CREATE INDEX IF NOT EXISTS idx_sugestoes_anonimo ON sugestoes (autor_anonimo);
+
+-- Triagem e resposta pela administração
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('admin', 'sugestoes');
@@ -0,0 +1,48 @@
+-- migrations/20251220160000_create_faxina.sql
+
+-- Escala de faxina, à parte da escala de serviço: cada área de limpeza é atribuída por semana (de
+-- segunda a domingo) a um quarto ou a uma turma, em rotação (ver faxina_service). Tal como a escala,
+-- cada semana é gerada em rascunho, publicada pelo escalante e reaberta por errata. Quem fica
+-- responsável é guardado com a atribuição (os ocupantes do quarto ou a turma, sem os indisponíveis).
+
+CREATE TABLE IF NOT EXISTS faxina_areas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    nome TEXT NOT NULL,                  -- Ex: "Casa de banho do corredor B"
+    descricao TEXT NOT NULL DEFAULT '',  -- O que inclui
+    rotacao TEXT NOT NULL CHECK (rotacao IN ('quarto', 'turma')),
+    genero_restricao TEXT NOT NULL DEFAULT 'Misto' CHECK (genero_restricao IN ('M', 'F', 'Misto')),
+    ativa BOOLEAN NOT NULL DEFAULT 1,    -- Inativa: deixa de ser atribuída (o histórico fica)
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, nome)
+);
+
+CREATE TABLE IF NOT EXISTS faxina_semanas (
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    semana TEXT NOT NULL,                -- Segunda-feira da semana ('YYYY-MM-DD')
+    status TEXT NOT NULL DEFAULT 'Rascunho' CHECK (status IN ('Rascunho', 'Publicada')),
+    gerada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    publicada_em TEXT,                   -- NULL enquanto for rascunho
+    PRIMARY KEY (organizacao_id, semana)
+);
+
+CREATE TABLE IF NOT EXISTS faxina_atribuicoes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL,
+    semana TEXT NOT NULL,
+    area_id INTEGER NOT NULL REFERENCES faxina_areas (id) ON DELETE CASCADE,
+    quarto_id INTEGER REFERENCES quartos (id) ON DELETE SET NULL, -- Rotação por quarto
+    turma TEXT,                          -- Rotação por turma
+    grupo TEXT NOT NULL,                 -- Nome mostrado (ex: "Quarto 12", "Turma 2B")
+    FOREIGN KEY (organizacao_id, semana) REFERENCES faxina_semanas (organizacao_id, semana) ON DELETE CASCADE,
+    UNIQUE (organizacao_id, semana, area_id)
+);
+CREATE INDEX IF NOT EXISTS idx_faxina_atribuicoes_quarto ON faxina_atribuicoes (quarto_id);
+CREATE INDEX IF NOT EXISTS idx_faxina_atribuicoes_turma ON faxina_atribuicoes (organizacao_id, turma);
+
+CREATE TABLE IF NOT EXISTS faxina_membros (
+    atribuicao_id INTEGER NOT NULL REFERENCES faxina_atribuicoes (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    PRIMARY KEY (atribuicao_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS idx_faxina_membros_user ON faxina_membros (user_id);
@@ -0,0 +1,26 @@
+-- migrations/20251220170000_create_conceito.sql
+
+-- Conceito (comportamento) de cada utilizador: lançamentos positivos (elogio, mérito) e negativos (demérito,
+-- FATD) com pontos. O conceito de um período (semestre) é a soma dos pontos com a data nesse período (ver
+-- conceito_service). Os lançamentos 'fatd' são criados na decisão de punição do processo, na mesma
+-- transação (um por processo); os outros são registados por quem tem a permissão "conceito".
+
+CREATE TABLE IF NOT EXISTS conceito_registos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    user_id TEXT NOT NULL REFERENCES users (id),
+    data TEXT NOT NULL,                  -- YYYY-MM-DD (do fato; define o período)
+    origem TEXT NOT NULL CHECK (origem IN ('elogio', 'merito', 'demerito', 'fatd')),
+    pontos INTEGER NOT NULL,             -- Com sinal: positivos no elogio e no mérito, negativos nos outros
+    motivo TEXT NOT NULL,
+    processo_id INTEGER UNIQUE REFERENCES processos_disciplinares (id), -- Só na origem 'fatd'
+    registado_por TEXT NOT NULL,
+    registado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    CHECK ((origem IN ('elogio', 'merito') AND pontos > 0) OR (origem IN ('demerito', 'fatd') AND pontos < 0)),
+    CHECK ((origem = 'fatd') = (processo_id IS NOT NULL))
+);
+CREATE INDEX IF NOT EXISTS idx_conceito_registos_user ON conceito_registos (user_id, data);
+CREATE INDEX IF NOT EXISTS idx_conceito_registos_data ON conceito_registos (organizacao_id, data);
+
+-- Registo dos lançamentos e classificação por turma (cada um vê o seu conceito sem permissão)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES ('admin', 'conceito');
@@ -0,0 +1,38 @@
+-- migrations/20251220180000_create_comitivas.sql
+
+-- Comitivas: grupos que saem juntos (competição, visita) com um oficial responsável, entre duas datas.
+-- Enquanto a comitiva está aberta, cada membro tem uma indisponibilidade do período (com `comitiva_id`,
+-- fora da escala) e, nos dias do período, aparece fora na presença (ver comitiva_service). Encerrar a
+-- comitiva apaga essas indisponibilidades; os membros ficam para o histórico.
+
+CREATE TABLE IF NOT EXISTS comitivas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    nome TEXT NOT NULL,
+    tipo TEXT NOT NULL CHECK (tipo IN ('competicao', 'visita', 'outra')),
+    destino TEXT NOT NULL DEFAULT '',
+    data_inicio TEXT NOT NULL,           -- YYYY-MM-DD
+    data_fim TEXT NOT NULL,              -- YYYY-MM-DD (inclusive)
+    responsavel_id TEXT NOT NULL REFERENCES users (id), -- Também é membro
+    estado TEXT NOT NULL DEFAULT 'aberta' CHECK (estado IN ('aberta', 'encerrada')),
+    criada_por TEXT NOT NULL,
+    criada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    encerrada_por TEXT,
+    encerrada_em TEXT,
+    CHECK (data_fim >= data_inicio)
+);
+CREATE INDEX IF NOT EXISTS idx_comitivas_org ON comitivas (organizacao_id, estado, data_inicio);
+
+CREATE TABLE IF NOT EXISTS comitiva_membros (
+    comitiva_id INTEGER NOT NULL REFERENCES comitivas (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id),
+    PRIMARY KEY (comitiva_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS idx_comitiva_membros_user ON comitiva_membros (user_id);
+
+-- Indisponibilidades criadas por uma comitiva (apagadas ao encerrá-la)
+ALTER TABLE indisponibilidades ADD COLUMN comitiva_id INTEGER REFERENCES comitivas (id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS idx_indisponibilidades_comitiva ON indisponibilidades (comitiva_id);
+
+-- Criação, membros e encerramento das comitivas
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES ('admin', 'comitivas');
@@ -0,0 +1,60 @@
+-- migrations/20251220190000_create_plano_chamada.sql
+
+-- Contacto de emergência de cada utilizador (preenchido pelo próprio em /chamada) e plano de chamada: a
+-- cascata de telefonemas de uma turma ou de um grupo. Cada membro é chamado por outro membro (o chamador);
+-- quem não tem chamador é a raiz, avisada pelo oficial de dia. Os exercícios registam a confirmação de
+-- cada membro (ver chamada_service).
+
+CREATE TABLE IF NOT EXISTS contactos_emergencia (
+    user_id TEXT PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    nome TEXT NOT NULL,
+    parentesco TEXT NOT NULL DEFAULT '',
+    telefone TEXT NOT NULL,
+    atualizado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS chamada_planos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    nome TEXT NOT NULL,
+    turma TEXT,                                                   -- Plano de uma turma...
+    grupo_id INTEGER REFERENCES grupos (id) ON DELETE CASCADE,    -- ...ou de um grupo
+    ramificacao INTEGER NOT NULL DEFAULT 3 CHECK (ramificacao BETWEEN 1 AND 10), -- Chamadas por membro ao gerar
+    criado_por TEXT NOT NULL,
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    atualizado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, nome),
+    CHECK ((turma IS NULL) <> (grupo_id IS NULL))
+);
+
+CREATE TABLE IF NOT EXISTS chamada_ligacoes (
+    plano_id INTEGER NOT NULL REFERENCES chamada_planos (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id),
+    chamador_id TEXT REFERENCES users (id), -- NULL = raiz
+    ordem INTEGER NOT NULL,                 -- Ordem entre os chamados pelo mesmo chamador
+    PRIMARY KEY (plano_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS idx_chamada_ligacoes_user ON chamada_ligacoes (user_id);
+
+CREATE TABLE IF NOT EXISTS chamada_exercicios (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    plano_id INTEGER NOT NULL REFERENCES chamada_planos (id) ON DELETE CASCADE,
+    membros INTEGER NOT NULL,                -- Membros do plano ao iniciar (a chamar)
+    iniciado_por TEXT NOT NULL,
+    iniciado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    encerrado_por TEXT,
+    encerrado_em TEXT                        -- NULL = em curso
+);
+-- Um exercício em curso por plano
+CREATE UNIQUE INDEX IF NOT EXISTS idx_chamada_exercicios_aberto ON chamada_exercicios (plano_id) WHERE encerrado_em IS NULL;
+
+CREATE TABLE IF NOT EXISTS chamada_confirmacoes (
+    exercicio_id INTEGER NOT NULL REFERENCES chamada_exercicios (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id),
+    confirmado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    registado_por TEXT NOT NULL,             -- O próprio ou quem registou a confirmação recebida
+    PRIMARY KEY (exercicio_id, user_id)
+);
+
+-- Planos, cascata, impressão e exercícios (o próprio contacto e a confirmação não exigem permissão)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES ('admin', 'chamada');
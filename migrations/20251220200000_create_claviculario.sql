@@ -0,0 +1,37 @@
+-- migrations/20251220200000_create_claviculario.sql
+
+-- Claviculário: o registo das chaves (de cada sala ou área, com a hora até à qual têm de voltar) e cada
+-- levantamento, com quem a leva, quem a entregou e quem a recebeu de volta (ver claviculario_service).
+
+CREATE TABLE IF NOT EXISTS chaves (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    codigo TEXT NOT NULL,                       -- Número/etiqueta no claviculário (ex: "B-12")
+    local TEXT NOT NULL,                        -- Sala ou área que abre
+    hora_limite TEXT NOT NULL DEFAULT '22:00',  -- 'HH:MM', no fuso da aplicação: depois dela, a chave fora está em atraso
+    ativa BOOLEAN NOT NULL DEFAULT 1,           -- Inativa: deixa de poder ser levantada (o histórico fica)
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, codigo)
+);
+
+CREATE TABLE IF NOT EXISTS chave_movimentos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    chave_id INTEGER NOT NULL REFERENCES chaves (id),
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE, -- Quem levou a chave
+    observacoes TEXT NOT NULL DEFAULT '',
+    levantada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    entregue_por TEXT NOT NULL,                 -- Operador que entregou a chave
+    devolvida_em TEXT,                          -- NULL = chave fora
+    recebida_por TEXT
+);
+-- Uma chave só pode estar fora uma vez
+CREATE UNIQUE INDEX IF NOT EXISTS idx_chave_movimentos_fora ON chave_movimentos (chave_id) WHERE devolvida_em IS NULL;
+CREATE INDEX IF NOT EXISTS idx_chave_movimentos_chave ON chave_movimentos (chave_id, levantada_em);
+CREATE INDEX IF NOT EXISTS idx_chave_movimentos_user ON chave_movimentos (user_id, devolvida_em);
+
+-- O claviculário fica com a polícia (como a portaria); também pesquisa utilizadores
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('policia', 'claviculario'),
+    ('policia', 'users.pesquisar'),
+    ('admin', 'claviculario');
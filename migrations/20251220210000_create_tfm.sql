@@ -0,0 +1,25 @@
+-- migrations/20251220210000_create_tfm.sql
+
+-- Teste de Forma Física (TFM): um resultado por utilizador, exercício e prova, com a marca (tempo,
+-- repetições, distância), a pontuação e a menção (aprovado/reprovado) dada por quem avaliou. As
+-- estatísticas por turma contam a última prova de cada utilizador em cada exercício no ano (ver
+-- tfm_service). Cada utilizador vê o seu histórico na página pessoal.
+
+CREATE TABLE IF NOT EXISTS tfm_resultados (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    user_id TEXT NOT NULL REFERENCES users (id),
+    data TEXT NOT NULL,                  -- YYYY-MM-DD (da prova)
+    exercicio TEXT NOT NULL CHECK (exercicio IN ('corrida', 'flexoes', 'abdominais', 'barra', 'natacao')),
+    marca TEXT NOT NULL DEFAULT '',      -- Desempenho bruto (ex: '12:40', '38 repetições'); opcional
+    pontuacao INTEGER NOT NULL CHECK (pontuacao BETWEEN 0 AND 100),
+    aprovado INTEGER NOT NULL CHECK (aprovado IN (0, 1)),
+    observacoes TEXT NOT NULL DEFAULT '',
+    registado_por TEXT NOT NULL,
+    registado_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_tfm_resultados_user ON tfm_resultados (user_id, data);
+CREATE INDEX IF NOT EXISTS idx_tfm_resultados_data ON tfm_resultados (organizacao_id, data);
+
+-- Registo dos resultados e estatísticas por turma (cada um vê o seu histórico sem permissão)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES ('admin', 'tfm');
@@ -0,0 +1,45 @@
+-- migrations/20251220220000_create_enfermaria.sql
+
+-- Consultas da enfermaria: a secção de saúde abre vagas (dia e hora, hora local) e cada utilizador marca
+-- a sua numa vaga livre. Na chegada a consulta passa a 'na_enfermaria' (assinalada na lista de
+-- presença); no fim fica 'atendida' e, se resultar numa dispensa, com a baixa registada (ver
+-- enfermaria_service). Uma consulta cancelada liberta a vaga.
+
+CREATE TABLE IF NOT EXISTS consulta_vagas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    data TEXT NOT NULL,                  -- YYYY-MM-DD
+    hora TEXT NOT NULL,                  -- HH:MM (hora local)
+    duracao INTEGER NOT NULL CHECK (duracao > 0), -- Minutos
+    criada_por TEXT NOT NULL,
+    criada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, data, hora)
+);
+
+CREATE TABLE IF NOT EXISTS consultas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    vaga_id INTEGER NOT NULL REFERENCES consulta_vagas (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id),
+    motivo TEXT NOT NULL DEFAULT '',
+    estado TEXT NOT NULL DEFAULT 'marcada'
+        CHECK (estado IN ('marcada', 'na_enfermaria', 'atendida', 'faltou', 'cancelada')),
+    marcada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    chegada_em TEXT,                     -- UTC (passagem a 'na_enfermaria')
+    atendida_em TEXT,                    -- UTC (atendida, falta ou cancelamento)
+    atendida_por TEXT,
+    notas TEXT NOT NULL DEFAULT '',      -- Da secção de saúde
+    baixa_id INTEGER REFERENCES baixas (id) ON DELETE SET NULL -- Dispensa que resultou da consulta
+);
+-- Uma consulta por vaga e uma consulta por decidir por utilizador (as canceladas não contam)
+CREATE UNIQUE INDEX IF NOT EXISTS idx_consultas_vaga ON consultas (vaga_id) WHERE estado <> 'cancelada';
+CREATE UNIQUE INDEX IF NOT EXISTS idx_consultas_pendente ON consultas (user_id) WHERE estado IN ('marcada', 'na_enfermaria');
+CREATE INDEX IF NOT EXISTS idx_consultas_user ON consultas (user_id, marcada_em);
+CREATE INDEX IF NOT EXISTS idx_consulta_vagas_data ON consulta_vagas (organizacao_id, data, hora);
+
+-- Agenda da enfermaria (role 'saude', criada com as baixas); cada utilizador marca as suas consultas sem permissão
+INSERT OR IGNORE INTO roles (nome, descricao, permanente, temporaria, sistema) VALUES
+    ('saude', 'Secção de saúde', 1, 1, 0);
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('saude', 'enfermaria'),
+    ('admin', 'enfermaria');
@@ -0,0 +1,37 @@
+-- migrations/20251220230000_create_biblioteca.sql
+
+-- Biblioteca: o catálogo dos títulos (com o número de exemplares) e os empréstimos. Cada empréstimo
+-- guarda quem leva o livro, o operador que o entregou, o dia até ao qual tem de voltar e quem o recebeu
+-- de volta. Os empréstimos em atraso são lembrados uma vez por dia no centro de notificações (tarefa
+-- "biblioteca.atrasos"; `ultimo_aviso` evita avisos repetidos no mesmo dia).
+
+CREATE TABLE IF NOT EXISTS livros (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    cota TEXT NOT NULL,                          -- Cota/etiqueta na estante (ex: "HIS-042")
+    titulo TEXT NOT NULL,
+    autor TEXT NOT NULL DEFAULT '',
+    exemplares INTEGER NOT NULL DEFAULT 1 CHECK (exemplares >= 0),
+    ativo BOOLEAN NOT NULL DEFAULT 1,            -- Inativo: deixa de poder ser emprestado (o histórico fica)
+    criado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (organizacao_id, cota)
+);
+
+CREATE TABLE IF NOT EXISTS emprestimos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    livro_id INTEGER NOT NULL REFERENCES livros (id),
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    emprestado_em TEXT NOT NULL DEFAULT (datetime('now')),
+    devolver_ate TEXT NOT NULL,                  -- YYYY-MM-DD (inclusive)
+    entregue_por TEXT NOT NULL,                  -- Operador que entregou o livro
+    devolvido_em TEXT,                           -- NULL = livro emprestado
+    recebido_por TEXT,
+    ultimo_aviso TEXT                            -- YYYY-MM-DD do último aviso de atraso
+);
+CREATE INDEX IF NOT EXISTS idx_emprestimos_livro ON emprestimos (livro_id, devolvido_em);
+CREATE INDEX IF NOT EXISTS idx_emprestimos_user ON emprestimos (user_id, devolvido_em);
+CREATE INDEX IF NOT EXISTS idx_emprestimos_abertos ON emprestimos (organizacao_id, devolver_ate) WHERE devolvido_em IS NULL;
+
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('admin', 'biblioteca');
@@ -0,0 +1,26 @@
+-- migrations/20251221000000_create_lavanderia.sql
+
+-- Lavanderia: cada saco entregue por um utilizador, com o número da etiqueta e o operador que o recebeu.
+-- Quando está lavado passa a pronto (o utilizador é avisado para o vir retirar) e fecha com a retirada.
+-- O relatório lista os sacos prontos há N dias ou mais sem terem sido retirados (ver lavanderia_service).
+
+CREATE TABLE IF NOT EXISTS lavanderia_sacos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    etiqueta TEXT NOT NULL,                      -- Número da etiqueta presa ao saco
+    observacoes TEXT NOT NULL DEFAULT '',
+    entregue_em TEXT NOT NULL DEFAULT (datetime('now')),
+    recebido_por TEXT NOT NULL,                  -- Operador que recebeu o saco
+    pronto_em TEXT,                              -- NULL = ainda na lavagem
+    pronto_por TEXT,
+    retirado_em TEXT,                            -- NULL = saco na lavanderia
+    entregue_por TEXT                            -- Operador que o devolveu ao utilizador
+);
+-- Uma etiqueta só pode estar num saco por retirar
+CREATE UNIQUE INDEX IF NOT EXISTS idx_lavanderia_sacos_etiqueta ON lavanderia_sacos (organizacao_id, etiqueta) WHERE retirado_em IS NULL;
+CREATE INDEX IF NOT EXISTS idx_lavanderia_sacos_user ON lavanderia_sacos (user_id, entregue_em);
+CREATE INDEX IF NOT EXISTS idx_lavanderia_sacos_abertos ON lavanderia_sacos (organizacao_id, pronto_em) WHERE retirado_em IS NULL;
+
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('admin', 'lavanderia');
@@ -0,0 +1,53 @@
+-- migrations/20251221010000_create_enquetes.sql
+
+-- Enquetes internas: uma pergunta com opções de resposta única ou múltipla, o prazo (último dia em que se
+-- vota) e quem pode votar: roles e anos (vazio = todos), como as pastas dos documentos. Cada utilizador
+-- vota uma vez. O apuramento é feito no servidor (contadores das opções); nas anónimas só fica registado
+-- quem participou, nunca o que escolheu.
+
+CREATE TABLE IF NOT EXISTS enquetes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    organizacao_id INTEGER NOT NULL REFERENCES organizacoes (id),
+    pergunta TEXT NOT NULL,
+    descricao TEXT NOT NULL DEFAULT '',
+    multipla BOOLEAN NOT NULL DEFAULT 0,  -- Pode escolher várias opções
+    anonima BOOLEAN NOT NULL DEFAULT 0,   -- Os votos não ficam ligados ao utilizador
+    prazo TEXT NOT NULL,                  -- 'YYYY-MM-DD' (hora local): último dia de votação
+    roles TEXT NOT NULL DEFAULT '',       -- Roles separadas por vírgulas (vazio = todas)
+    anos TEXT NOT NULL DEFAULT '',        -- Anos separados por vírgulas (vazio = todos)
+    criada_por TEXT NOT NULL,
+    criada_em TEXT NOT NULL DEFAULT (datetime('now')),
+    encerrada_em TEXT,                    -- Encerrada antes do prazo (NULL = segue o prazo)
+    encerrada_por TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_enquetes_organizacao ON enquetes (organizacao_id, prazo);
+
+CREATE TABLE IF NOT EXISTS enquete_opcoes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    enquete_id INTEGER NOT NULL REFERENCES enquetes (id) ON DELETE CASCADE,
+    texto TEXT NOT NULL,
+    ordem INTEGER NOT NULL,
+    votos INTEGER NOT NULL DEFAULT 0      -- Contador do apuramento (anónimas e nominais)
+);
+CREATE INDEX IF NOT EXISTS idx_enquete_opcoes_enquete ON enquete_opcoes (enquete_id, ordem);
+
+-- Quem já votou (um voto por utilizador, também nas anónimas)
+CREATE TABLE IF NOT EXISTS enquete_participacoes (
+    enquete_id INTEGER NOT NULL REFERENCES enquetes (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    votou_em TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (enquete_id, user_id)
+);
+
+-- O que cada utilizador escolheu (só nas enquetes nominais)
+CREATE TABLE IF NOT EXISTS enquete_votos (
+    enquete_id INTEGER NOT NULL REFERENCES enquetes (id) ON DELETE CASCADE,
+    opcao_id INTEGER NOT NULL REFERENCES enquete_opcoes (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    PRIMARY KEY (opcao_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS idx_enquete_votos_enquete ON enquete_votos (enquete_id, user_id);
+
+-- Criar, encerrar e consultar os resultados (todos os utilizadores votam nas que lhes são dirigidas)
+INSERT OR IGNORE INTO role_permissoes (role, permissao) VALUES
+    ('admin', 'enquetes');
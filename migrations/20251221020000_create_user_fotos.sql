@@ -0,0 +1,16 @@
+-- migrations/20251221020000_create_user_fotos.sql
+
+-- Fotografia de cada utilizador (uma, JPEG), enviada pelos administradores na ficha do utilizador e
+-- usada no efetivo por turma (/admin/users/roster e o PDF). O ficheiro fica em `fotos.diretorio`,
+-- com um nome gerado; aqui ficam as dimensões, necessárias para o incluir no PDF.
+
+CREATE TABLE IF NOT EXISTS user_fotos (
+    user_id TEXT PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    ficheiro TEXT NOT NULL UNIQUE,               -- '<uuid>.jpg' em fotos.diretorio
+    largura INTEGER NOT NULL,                    -- Píxeis
+    altura INTEGER NOT NULL,
+    componentes INTEGER NOT NULL,                -- 1 = cinzentos, 3 = cor (RGB/YCbCr)
+    tamanho INTEGER NOT NULL,                    -- Bytes
+    enviada_por TEXT NOT NULL,
+    enviada_em TEXT NOT NULL DEFAULT (datetime('now'))
+);
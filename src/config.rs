@@ -0,0 +1,419 @@
+// src/config.rs
+//! Configuração da aplicação: lida de `config.toml` (ou do ficheiro indicado em CONFIG_FILE) e depois
+//! das variáveis de ambiente, que têm prioridade sobre o ficheiro. É validada no arranque (um valor
+//! inválido impede o servidor de arrancar) e fica no `AppState`.
+//! Ver `config.example.toml` para todas as opções e as variáveis de ambiente correspondentes.
+//!
+//! As restantes integrações (Telegram, JWT, OIDC, ...) continuam a ler só o ambiente.
+
+use crate::i18n::Idioma;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Ficheiro lido quando CONFIG_FILE não está definida (se não existir, usa-se só o ambiente).
+const FICHEIRO_PADRAO: &str = "config.toml";
+
+/// Configuração tipada da aplicação.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub servidor: ServidorConfig,
+    pub pedidos: PedidosConfig,
+    pub tls: TlsConfig,
+    pub estaticos: EstaticosConfig,
+    pub base_dados: BaseDadosConfig,
+    pub sessao: SessaoConfig,
+    pub escala: EscalaConfig,
+    pub roles: RolesConfig,
+    pub i18n: I18nConfig,
+    pub smtp: SmtpConfig,
+    pub retencao: RetencaoConfig,
+    pub documentos: DocumentosConfig,
+    pub fotos: FotosConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServidorConfig {
+    /// Endereço em que o servidor escuta (BIND_ADDRESS).
+    pub endereco: SocketAddr,
+}
+
+impl Default for ServidorConfig {
+    fn default() -> Self {
+        Self { endereco: SocketAddr::from(([0, 0, 0, 0], 3000)) }
+    }
+}
+
+/// Limites dos pedidos HTTP, para que um cliente parado ou um envio enorme não prendam as (poucas)
+/// ligações à base de dados. As rotas de envio de ficheiros (importação de dados) têm limites próprios.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PedidosConfig {
+    /// Tamanho máximo do corpo de um pedido, em KB (MAX_BODY_KB); acima dele a resposta é 413.
+    pub corpo_max_kb: usize,
+    /// Tamanho máximo nas rotas de envio de ficheiros, em MB (MAX_UPLOAD_MB).
+    pub upload_max_mb: usize,
+    /// Tempo máximo de resposta a um pedido, incluindo a receção do corpo, em segundos
+    /// (REQUEST_TIMEOUT_SECONDS); acima dele a resposta é 408.
+    pub timeout_segundos: u64,
+    /// Tempo máximo nas rotas de envio de ficheiros, em segundos (UPLOAD_TIMEOUT_SECONDS).
+    pub upload_timeout_segundos: u64,
+}
+
+impl Default for PedidosConfig {
+    fn default() -> Self {
+        Self { corpo_max_kb: 2048, upload_max_mb: 64, timeout_segundos: 30, upload_timeout_segundos: 300 }
+    }
+}
+
+impl PedidosConfig {
+    pub fn corpo_max_bytes(&self) -> usize {
+        self.corpo_max_kb.saturating_mul(1024)
+    }
+
+    pub fn upload_max_bytes(&self) -> usize {
+        self.upload_max_mb.saturating_mul(1024 * 1024)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_segundos)
+    }
+
+    pub fn upload_timeout(&self) -> Duration {
+        Duration::from_secs(self.upload_timeout_segundos)
+    }
+}
+
+/// HTTPS servido pela própria aplicação (rustls), para instalações sem proxy reverso.
+/// Desativado sem certificado: o servidor fala HTTP simples (ex: atrás de um proxy que trata do TLS).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Cadeia de certificados em PEM (TLS_CERT_FILE).
+    pub certificado: Option<PathBuf>,
+    /// Chave privada em PEM (TLS_KEY_FILE).
+    pub chave: Option<PathBuf>,
+    /// Endereço de um listener HTTP que só redireciona para HTTPS (TLS_REDIRECT_ADDRESS, ex: "0.0.0.0:80").
+    pub redirecionar_http: Option<SocketAddr>,
+}
+
+impl TlsConfig {
+    pub fn ativo(&self) -> bool {
+        self.certificado.is_some()
+    }
+}
+
+/// Ficheiros estáticos servidos em /static (ver `web::estaticos`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EstaticosConfig {
+    /// Diretório dos ficheiros (STATIC_DIR); sem ele, usam-se os embutidos no binário.
+    pub diretorio: Option<PathBuf>,
+    /// `max-age` do Cache-Control, em segundos (STATIC_CACHE_SECONDS); 0 = o browser revalida sempre.
+    pub cache_segundos: u64,
+}
+
+impl Default for EstaticosConfig {
+    fn default() -> Self {
+        Self { diretorio: None, cache_segundos: 3600 }
+    }
+}
+
+/// Base de dados SQLite (ver `db::create_db_pools`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BaseDadosConfig {
+    /// URL da base de dados SQLite (DATABASE_URL); obrigatória.
+    pub url: String,
+    /// Ligações do pool de leitura (DATABASE_MAX_CONNECTIONS); as escritas usam sempre uma só.
+    pub max_conexoes: u32,
+    /// Milissegundos à espera de uma base de dados ocupada antes de falhar (DATABASE_BUSY_TIMEOUT_MS).
+    pub espera_ms: u64,
+    /// Aplica as migrações em falta ao arrancar (DATABASE_AUTO_MIGRATE). Com `false`, o servidor não arranca
+    /// com migrações por aplicar: correr antes `mercal2 migrate run`.
+    pub migrar_ao_arrancar: bool,
+}
+
+impl Default for BaseDadosConfig {
+    fn default() -> Self {
+        Self { url: String::new(), max_conexoes: 8, espera_ms: 5000, migrar_ao_arrancar: true }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessaoConfig {
+    /// Horas de inatividade até a sessão expirar (SESSION_EXPIRY_HOURS).
+    pub expira_horas: i64,
+    /// Cookies só enviados por HTTPS (SESSION_COOKIE_SECURE). Por omissão, só com o TLS ativo;
+    /// atrás de um proxy que trata do HTTPS deve ser `true`.
+    pub cookie_seguro: Option<bool>,
+}
+
+impl Default for SessaoConfig {
+    fn default() -> Self {
+        Self { expira_horas: 24, cookie_seguro: None }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EscalaConfig {
+    /// Regra de fadiga: dias, antes e depois de um serviço, em que o militar não pode ter outro
+    /// (ESCALA_FATIGUE_DAYS). 1 = nunca dois serviços em dias seguidos; 0 = só não pode ter dois no mesmo dia.
+    pub fadiga_dias: i64,
+}
+
+impl Default for EscalaConfig {
+    fn default() -> Self {
+        Self { fadiga_dias: 1 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RolesConfig {
+    /// Roles que mantêm sempre a permissão de administração, seja qual for a matriz (ADMIN_ROLES,
+    /// separadas por vírgulas). Evita ficar sem acesso ao admin.
+    pub admin: Vec<String>,
+}
+
+impl Default for RolesConfig {
+    fn default() -> Self {
+        Self { admin: vec!["admin".to_string()] }
+    }
+}
+
+/// Tempo durante o qual se guardam os registos que crescem sem limite (ver `retencao_service`).
+/// 0 = guardar para sempre. Antes de apagar, os totais por mês ficam em `retencao_resumos`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetencaoConfig {
+    /// Dias do registo de auditoria (RETENTION_AUDIT_DAYS).
+    pub auditoria_dias: i64,
+    /// Dias do histórico de logins (RETENTION_LOGINS_DAYS).
+    pub logins_dias: i64,
+    /// Dias das trocas já resolvidas (RETENTION_TROCAS_DAYS); as pendentes e as de dívidas por pagar ficam.
+    pub trocas_dias: i64,
+    /// Só contar o que seria apagado, sem apagar (RETENTION_DRY_RUN).
+    pub simular: bool,
+}
+
+/// Repositório de documentos (ver `documento_service`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DocumentosConfig {
+    /// Diretório onde ficam os PDFs enviados (DOCUMENTS_DIR); criado no primeiro envio.
+    pub diretorio: PathBuf,
+}
+
+impl Default for DocumentosConfig {
+    fn default() -> Self {
+        Self { diretorio: PathBuf::from("data/documentos") }
+    }
+}
+
+/// Fotografias dos utilizadores (ver `foto_service`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FotosConfig {
+    /// Diretório onde ficam as fotografias enviadas (PHOTOS_DIR); criado no primeiro envio.
+    pub diretorio: PathBuf,
+}
+
+impl Default for FotosConfig {
+    fn default() -> Self {
+        Self { diretorio: PathBuf::from("data/fotos") }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct I18nConfig {
+    /// Idioma das páginas quando o utilizador não escolheu nenhum e o browser não pede um suportado
+    /// (DEFAULT_LOCALE): "pt" ou "en".
+    pub padrao: Idioma,
+    /// Fuso horário da unidade, para mostrar as horas e saber que dia é "hoje" (APP_TIMEZONE), ex:
+    /// "Europe/Lisbon". Por omissão, o fuso do servidor (ver `tempo`).
+    pub fuso_horario: Option<Tz>,
+}
+
+/// Servidor de email (ver `email_service::EmailConfig`). Desativado sem `host`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpConfig {
+    pub host: Option<String>,       // SMTP_HOST
+    pub porta: Option<u16>,         // SMTP_PORT (587)
+    pub tls: Option<String>,        // SMTP_TLS: "starttls", "tls" ou "none"
+    pub remetente: Option<String>,  // SMTP_FROM
+    pub utilizador: Option<String>, // SMTP_USER
+    pub senha: Option<String>,      // SMTP_PASSWORD
+}
+
+// A senha não aparece nos logs
+impl std::fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("host", &self.host)
+            .field("porta", &self.porta)
+            .field("tls", &self.tls)
+            .field("remetente", &self.remetente)
+            .field("utilizador", &self.utilizador)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Valor não vazio de uma variável de ambiente.
+fn var(nome: &str) -> Option<String> {
+    std::env::var(nome).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Substitui `campo` pelo valor da variável de ambiente, se estiver definida.
+fn sobrepor<T: std::str::FromStr>(campo: &mut T, nome: &str) -> Result<(), String> {
+    if let Some(v) = var(nome) {
+        *campo = v.parse().map_err(|_| format!("{} inválido: '{}'", nome, v))?;
+    }
+    Ok(())
+}
+
+/// Como `sobrepor`, para campos opcionais.
+fn sobrepor_opcional<T: std::str::FromStr>(campo: &mut Option<T>, nome: &str) -> Result<(), String> {
+    if let Some(v) = var(nome) {
+        *campo = Some(v.parse().map_err(|_| format!("{} inválido: '{}'", nome, v))?);
+    }
+    Ok(())
+}
+
+impl Config {
+    /// Se os cookies da sessão (e o do token CSRF) levam o atributo `Secure`.
+    pub fn cookie_seguro(&self) -> bool {
+        self.sessao.cookie_seguro.unwrap_or(self.tls.ativo())
+    }
+
+    /// Lê o ficheiro (CONFIG_FILE, ou `config.toml` se existir), aplica as variáveis de ambiente e valida.
+    /// Devolve também o ficheiro lido, para o log do arranque.
+    pub fn carregar() -> Result<(Self, Option<String>), String> {
+        let ficheiro = match var("CONFIG_FILE") {
+            Some(caminho) => Some(caminho),
+            None => Path::new(FICHEIRO_PADRAO).exists().then(|| FICHEIRO_PADRAO.to_string()),
+        };
+        let mut config = match &ficheiro {
+            Some(caminho) => {
+                let texto = std::fs::read_to_string(caminho).map_err(|e| format!("Não foi possível ler {}: {}", caminho, e))?;
+                toml::from_str::<Config>(&texto).map_err(|e| format!("{} inválido: {}", caminho, e))?
+            }
+            None => Config::default(),
+        };
+        config.aplicar_ambiente()?;
+        config.validar()?;
+        Ok((config, ficheiro))
+    }
+
+    /// As variáveis de ambiente definidas substituem os valores do ficheiro.
+    fn aplicar_ambiente(&mut self) -> Result<(), String> {
+        sobrepor(&mut self.servidor.endereco, "BIND_ADDRESS")?;
+        sobrepor(&mut self.pedidos.corpo_max_kb, "MAX_BODY_KB")?;
+        sobrepor(&mut self.pedidos.upload_max_mb, "MAX_UPLOAD_MB")?;
+        sobrepor(&mut self.pedidos.timeout_segundos, "REQUEST_TIMEOUT_SECONDS")?;
+        sobrepor(&mut self.pedidos.upload_timeout_segundos, "UPLOAD_TIMEOUT_SECONDS")?;
+        sobrepor_opcional(&mut self.tls.certificado, "TLS_CERT_FILE")?;
+        sobrepor_opcional(&mut self.tls.chave, "TLS_KEY_FILE")?;
+        sobrepor_opcional(&mut self.tls.redirecionar_http, "TLS_REDIRECT_ADDRESS")?;
+        sobrepor_opcional(&mut self.estaticos.diretorio, "STATIC_DIR")?;
+        sobrepor(&mut self.estaticos.cache_segundos, "STATIC_CACHE_SECONDS")?;
+        sobrepor(&mut self.base_dados.url, "DATABASE_URL")?;
+        sobrepor(&mut self.base_dados.max_conexoes, "DATABASE_MAX_CONNECTIONS")?;
+        sobrepor(&mut self.base_dados.espera_ms, "DATABASE_BUSY_TIMEOUT_MS")?;
+        sobrepor(&mut self.base_dados.migrar_ao_arrancar, "DATABASE_AUTO_MIGRATE")?;
+        sobrepor(&mut self.sessao.expira_horas, "SESSION_EXPIRY_HOURS")?;
+        sobrepor_opcional(&mut self.sessao.cookie_seguro, "SESSION_COOKIE_SECURE")?;
+        sobrepor(&mut self.escala.fadiga_dias, "ESCALA_FATIGUE_DAYS")?;
+        sobrepor(&mut self.i18n.padrao, "DEFAULT_LOCALE")?;
+        sobrepor_opcional(&mut self.i18n.fuso_horario, "APP_TIMEZONE")?;
+        if let Some(v) = var("ADMIN_ROLES") {
+            self.roles.admin = v.split(',').map(|r| r.trim().to_string()).filter(|r| !r.is_empty()).collect();
+        }
+        let smtp = &mut self.smtp;
+        sobrepor_opcional(&mut smtp.host, "SMTP_HOST")?;
+        sobrepor_opcional(&mut smtp.porta, "SMTP_PORT")?;
+        sobrepor_opcional(&mut smtp.tls, "SMTP_TLS")?;
+        sobrepor_opcional(&mut smtp.remetente, "SMTP_FROM")?;
+        sobrepor_opcional(&mut smtp.utilizador, "SMTP_USER")?;
+        sobrepor_opcional(&mut smtp.senha, "SMTP_PASSWORD")?;
+        let retencao = &mut self.retencao;
+        sobrepor(&mut retencao.auditoria_dias, "RETENTION_AUDIT_DAYS")?;
+        sobrepor(&mut retencao.logins_dias, "RETENTION_LOGINS_DAYS")?;
+        sobrepor(&mut retencao.trocas_dias, "RETENTION_TROCAS_DAYS")?;
+        sobrepor(&mut retencao.simular, "RETENTION_DRY_RUN")?;
+        sobrepor(&mut self.documentos.diretorio, "DOCUMENTS_DIR")?;
+        sobrepor(&mut self.fotos.diretorio, "PHOTOS_DIR")?;
+        Ok(())
+    }
+
+    /// Verifica os valores que não dependem de outros serviços (o SMTP é validado por `EmailConfig`).
+    fn validar(&self) -> Result<(), String> {
+        if self.base_dados.url.trim().is_empty() {
+            return Err("base_dados.url (DATABASE_URL) não definida".to_string());
+        }
+        if !(1..=64).contains(&self.base_dados.max_conexoes) {
+            return Err(format!("base_dados.max_conexoes tem de estar entre 1 e 64 (está {})", self.base_dados.max_conexoes));
+        }
+        let pedidos = &self.pedidos;
+        if pedidos.corpo_max_kb < 1 || pedidos.upload_max_mb < 1 {
+            return Err("pedidos.corpo_max_kb e pedidos.upload_max_mb têm de ser pelo menos 1".to_string());
+        }
+        if pedidos.upload_max_bytes() < pedidos.corpo_max_bytes() {
+            return Err("pedidos.upload_max_mb não pode ser menor do que pedidos.corpo_max_kb".to_string());
+        }
+        if pedidos.timeout_segundos < 1 || pedidos.upload_timeout_segundos < 1 {
+            return Err("pedidos.timeout_segundos e pedidos.upload_timeout_segundos têm de ser pelo menos 1".to_string());
+        }
+        match (&self.tls.certificado, &self.tls.chave) {
+            (Some(certificado), Some(chave)) => {
+                for ficheiro in [certificado, chave] {
+                    if !ficheiro.is_file() {
+                        return Err(format!("tls: ficheiro {} não encontrado", ficheiro.display()));
+                    }
+                }
+            }
+            (None, None) => {}
+            _ => return Err("tls.certificado e tls.chave (TLS_CERT_FILE e TLS_KEY_FILE) têm de ser definidos juntos".to_string()),
+        }
+        if let Some(redirecionar) = self.tls.redirecionar_http {
+            if !self.tls.ativo() {
+                return Err("tls.redirecionar_http (TLS_REDIRECT_ADDRESS) exige o TLS ativo".to_string());
+            }
+            if redirecionar.port() == self.servidor.endereco.port() {
+                return Err("tls.redirecionar_http não pode usar a mesma porta que servidor.endereco".to_string());
+            }
+        }
+        if self.sessao.expira_horas < 1 {
+            return Err(format!("sessao.expira_horas tem de ser pelo menos 1 (está {})", self.sessao.expira_horas));
+        }
+        if !(0..=30).contains(&self.escala.fadiga_dias) {
+            return Err(format!("escala.fadiga_dias tem de estar entre 0 e 30 (está {})", self.escala.fadiga_dias));
+        }
+        let retencao = &self.retencao;
+        if [retencao.auditoria_dias, retencao.logins_dias, retencao.trocas_dias].iter().any(|dias| *dias < 0) {
+            return Err("retencao: os dias não podem ser negativos (0 = guardar para sempre)".to_string());
+        }
+        if self.roles.admin.is_empty() {
+            return Err("roles.admin tem de ter pelo menos uma role".to_string());
+        }
+        if let Some(role) = self
+            .roles
+            .admin
+            .iter()
+            .find(|r| r.is_empty() || !r.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+        {
+            return Err(format!("roles.admin: nome de role inválido: '{}'", role));
+        }
+        Ok(())
+    }
+}
@@ -1,30 +1,191 @@
 // src/db.rs
-use crate::error::AppResult;
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+//! Ligação à base de dados SQLite, em modo WAL (as leituras não esperam pelas escritas) com dois pools:
+//! - escrita: uma única ligação, por onde passam todas as escritas (e o resto do código, via `AppState::db_pool`).
+//!   Com um só escritor, as escritas esperam a vez no pool em vez de falharem com "database is locked".
+//! - leitura: várias ligações só de leitura, para as páginas mais consultadas (`AppState::db_leitura`),
+//!   que assim não ficam à espera de uma escrita longa (ex: gerar a escala de um período).
+use crate::config::BaseDadosConfig;
+use crate::error::{AppError, AppResult};
+use crate::services::backup_service;
+use sqlx::migrate::{Migration, Migrator};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::Connection;
 use std::str::FromStr;
 use std::time::Duration; // Usar std::time::Duration aqui
 
-pub async fn create_db_pool() -> AppResult<SqlitePool> {
-    dotenvy::dotenv().ok(); // Carrega .env
-    let database_url = std::env::var("DATABASE_URL")?; // Lê URL da DB
+/// Migrações em ./migrations (embutidas no binário); também usadas por /readyz para ver se falta alguma.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
-    tracing::info!("Ligando à base de dados: {}", database_url);
+/// Os dois pools da base de dados (ver o topo do ficheiro).
+pub struct Pools {
+    pub escrita: SqlitePool,
+    pub leitura: SqlitePool,
+}
 
-    // Opções de conexão (criar se não existir, timeout)
-    let options = SqliteConnectOptions::from_str(&database_url)?
-        .create_if_missing(true)
-        .busy_timeout(Duration::from_secs(5));
+/// Liga à base de dados (ver `config::BaseDadosConfig`), aplica as migrações e abre o pool de leitura.
+pub async fn create_db_pools(config: &BaseDadosConfig) -> AppResult<Pools> {
 
-    // Cria o pool (conjunto de conexões reutilizáveis)
-    let pool = SqlitePoolOptions::new()
-        .max_connections(5) // Número máximo de conexões simultâneas
-        .connect_with(options)
+    tracing::info!("Ligando à base de dados: {}", config.url);
+
+    let options = opcoes(config)?;
+
+    if config.migrar_ao_arrancar {
+        tracing::info!("Executando migrações da base de dados...");
+        // Executa automaticamente os ficheiros SQL em ./migrations (cria o ficheiro e ativa o WAL)
+        let aplicadas = migrar(config).await?;
+        tracing::info!("Migrações concluídas ({} aplicada(s)).", aplicadas);
+    } else {
+        let pendentes: Vec<String> = estado_migracoes(config)
+            .await?
+            .into_iter()
+            .filter(|m| m.aplicada_em.is_none())
+            .map(|m| m.versao.to_string())
+            .collect();
+        if !pendentes.is_empty() {
+            return Err(AppError::Conflict(format!(
+                "{} migração(ões) por aplicar ({}); corra `mercal2 migrate run` (DATABASE_AUTO_MIGRATE=false)",
+                pendentes.len(), pendentes.join(", ")
+            )));
+        }
+    }
+
+    // Pool de escrita: uma só ligação
+    let escrita = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options.clone())
         .await?; // Conecta e retorna erro se falhar
 
-    tracing::info!("Executando migrações da base de dados...");
-    // Executa automaticamente os ficheiros SQL em ./migrations
-    sqlx::migrate!("./migrations").run(&pool).await?;
-    tracing::info!("Migrações concluídas.");
+    // Pool de leitura: só depois das migrações, para nunca ver o esquema a meio
+    let leitura = SqlitePoolOptions::new()
+        .max_connections(config.max_conexoes) // Número máximo de leituras simultâneas
+        .connect_with(options.read_only(true))
+        .await?;
+    tracing::info!("Pools da base de dados: 1 ligação de escrita, até {} de leitura (WAL).", config.max_conexoes);
+
+    Ok(Pools { escrita, leitura })
+}
+
+/// Opções comuns: WAL, chaves estrangeiras e tempo de espera quando a base de dados está ocupada.
+/// Em WAL, synchronous=NORMAL é seguro (não corrompe) e poupa um fsync por transação.
+fn opcoes(config: &BaseDadosConfig) -> AppResult<SqliteConnectOptions> {
+    Ok(SqliteConnectOptions::from_str(&config.url)?
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .foreign_keys(true)
+        .busy_timeout(Duration::from_millis(config.espera_ms)))
+}
+
+// --- Migrações (no arranque e `mercal2 migrate status|run|revert`) ---
+
+/// Estado de uma migração embutida no binário.
+pub struct EstadoMigracao {
+    pub versao: i64,
+    pub descricao: String,
+    pub aplicada_em: Option<String>, // None = por aplicar
+    pub reversivel: bool,            // Tem script de reversão (<versão>_<nome>.down.sql)
+}
+
+/// Ligação própria para as migrações, sem chaves estrangeiras: o SQLite só muda a chave de uma tabela
+/// recriando-a, e `PRAGMA foreign_keys` não tem efeito dentro da transação em que o sqlx corre cada migração
+/// (ao apagar a tabela antiga, as linhas das tabelas filhas seriam apagadas em cascata).
+async fn ligar_para_migrar(config: &BaseDadosConfig) -> AppResult<SqliteConnection> {
+    let options = opcoes(config)?.create_if_missing(true).foreign_keys(false);
+    Ok(SqliteConnection::connect_with(&options).await?)
+}
+
+/// Migrações já aplicadas (versão, data), por ordem; vazio numa base de dados nova.
+async fn aplicadas(conn: &mut SqliteConnection) -> AppResult<Vec<(i64, String)>> {
+    let existe: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if !existe {
+        return Ok(vec![]);
+    }
+    let aplicadas = sqlx::query_as("SELECT version, installed_on FROM _sqlx_migrations WHERE success ORDER BY version")
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(aplicadas)
+}
+
+/// Migrações de esquema (as `.up.sql` e as simples) embutidas no binário.
+fn migracoes() -> impl Iterator<Item = &'static Migration> {
+    MIGRATOR.iter().filter(|m| m.migration_type.is_up_migration())
+}
+
+/// Estado de todas as migrações embutidas no binário, por versão (`mercal2 migrate status`).
+pub async fn estado_migracoes(config: &BaseDadosConfig) -> AppResult<Vec<EstadoMigracao>> {
+    let mut conn = ligar_para_migrar(config).await?;
+    let aplicadas = aplicadas(&mut conn).await?;
+    conn.close().await?;
+
+    Ok(migracoes()
+        .map(|m| EstadoMigracao {
+            versao: m.version,
+            descricao: m.description.to_string(),
+            aplicada_em: aplicadas.iter().find(|(versao, _)| *versao == m.version).map(|(_, em)| em.clone()),
+            reversivel: m.migration_type.is_reversible(),
+        })
+        .collect())
+}
+
+/// Aplica as migrações em falta, depois de uma cópia da base de dados (se já tiver dados).
+/// No fim, regista as referências que tenham ficado partidas. Devolve quantas foram aplicadas.
+pub async fn migrar(config: &BaseDadosConfig) -> AppResult<usize> {
+    let mut conn = ligar_para_migrar(config).await?;
+    let ja_aplicadas = aplicadas(&mut conn).await?;
+    let pendentes: Vec<i64> = migracoes()
+        .map(|m| m.version)
+        .filter(|versao| !ja_aplicadas.iter().any(|(aplicada, _)| aplicada == versao))
+        .collect();
+
+    // Base de dados nova: não há nada a proteger
+    if let (Some(primeira), false) = (pendentes.first(), ja_aplicadas.is_empty()) {
+        backup_service::copiar_antes_de_migrar(&mut conn, &format!("antes-{}", primeira)).await?;
+    }
+    MIGRATOR.run(&mut conn).await?;
+
+    // (tabela, rowid, tabela referenciada, índice da chave)
+    let partidas: Vec<(String, Option<i64>, String, i64)> = sqlx::query_as("PRAGMA foreign_key_check")
+        .fetch_all(&mut conn)
+        .await?;
+    for (tabela, rowid, referenciada, _) in &partidas {
+        tracing::warn!("⚠️ Referência partida: {} (rowid {:?}) -> {}", tabela, rowid, referenciada);
+    }
+
+    conn.close().await?;
+    Ok(pendentes.len())
+}
+
+/// Desfaz a última migração aplicada com o seu script de reversão, depois de uma cópia da base de dados.
+/// As migrações sem script (todas as anteriores a esta funcionalidade) só se desfazem repondo a cópia feita
+/// antes de serem aplicadas. Devolve a migração desfeita.
+pub async fn reverter(config: &BaseDadosConfig) -> AppResult<EstadoMigracao> {
+    let mut conn = ligar_para_migrar(config).await?;
+    let ja_aplicadas = aplicadas(&mut conn).await?;
+    let Some((ultima, aplicada_em)) = ja_aplicadas.last().cloned() else {
+        return Err(AppError::NotFound("Nenhuma migração aplicada.".to_string()));
+    };
+    let Some(migracao) = migracoes().find(|m| m.version == ultima) else {
+        return Err(AppError::NotFound(format!("A migração {} não existe neste binário.", ultima)));
+    };
+    if !migracao.migration_type.is_reversible() {
+        return Err(AppError::Conflict(format!(
+            "A migração {} ({}) não tem script de reversão; reponha a cópia mercal2-antes-*.db feita antes de a aplicar ({})",
+            ultima, migracao.description, backup_service::config().dir.display()
+        )));
+    }
+
+    backup_service::copiar_antes_de_migrar(&mut conn, &format!("antes-reverter-{}", ultima)).await?;
+    let anterior = ja_aplicadas.iter().rev().nth(1).map(|(versao, _)| *versao).unwrap_or(0);
+    MIGRATOR.undo(&mut conn, anterior).await?;
+    conn.close().await?;
 
-    Ok(pool)
-}
\ No newline at end of file
+    Ok(EstadoMigracao {
+        versao: ultima,
+        descricao: migracao.description.to_string(),
+        aplicada_em: Some(aplicada_em),
+        reversivel: true,
+    })
+}
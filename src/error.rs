@@ -1,6 +1,24 @@
 // src/error.rs
-use axum::{http::StatusCode, response::IntoResponse, response::Html}; // Adicionar Html
+use crate::templates::ErroPage;
+use askama::Template;
+use axum::{http::StatusCode, response::IntoResponse, response::Html, Json}; // Adicionar Html
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use serde::Serialize;
 use thiserror::Error;
+use utoipa::ToSchema;
+
+/// Erro de validação associado a um campo específico de um formulário/payload.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub campo: String,
+    pub mensagem: String,
+}
+
+impl FieldError {
+    pub fn new(campo: impl Into<String>, mensagem: impl Into<String>) -> Self {
+        Self { campo: campo.into(), mensagem: mensagem.into() }
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -30,43 +48,201 @@ pub enum AppError {
 
     #[error("Não autorizado")]
     Unauthorized,
+
+    // Pedido à API sem sessão nem token válido
+    #[error("Autenticação necessária")]
+    Unauthenticated,
+
+    #[error("Utilizador '{0}' já existe")]
+    UserAlreadyExists(String),
+
+    #[error("Não encontrado: {0}")]
+    NotFound(String),
+
+    #[error("Conflito: {0}")]
+    Conflict(String),
+
+    // Demasiadas tentativas (ex.: login bloqueado temporariamente)
+    #[error("Demasiados pedidos: {0}")]
+    TooManyRequests(String),
+
+    #[error("Dados inválidos: {0:?}")]
+    Validation(Vec<FieldError>),
+
+    #[error("Erro no fornecedor de identidade (OIDC): {0}")]
+    Oidc(String),
+
+    // Alteração recusada no modo de manutenção (só leitura)
+    #[error("Em manutenção: {0}")]
+    EmManutencao(String),
+}
+
+impl AppError {
+    /// Atalho para um erro de validação num único campo.
+    pub fn validation(campo: impl Into<String>, mensagem: impl Into<String>) -> Self {
+        AppError::Validation(vec![FieldError::new(campo, mensagem)])
+    }
+
+    /// Código HTTP correspondente a cada variante.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::InvalidCredentials | AppError::Unauthenticated => StatusCode::UNAUTHORIZED,
+            AppError::Unauthorized => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::UserAlreadyExists(_) | AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Oidc(_) => StatusCode::BAD_GATEWAY,
+            AppError::EmManutencao(_) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Mensagem segura para mostrar ao utilizador (sem detalhes internos).
+    pub fn user_message(&self) -> String {
+        match self {
+            AppError::SqlxError(_) | AppError::SqlxMigrateError(_) => "Erro ao aceder aos dados.".to_string(),
+            AppError::EnvVarError(_) => "Erro de configuração.".to_string(),
+            AppError::PasswordHashingError => "Erro ao processar credenciais.".to_string(),
+            AppError::InvalidCredentials => "ID ou senha inválidos.".to_string(), // Mensagem genérica
+            AppError::SessionError(_) => "Erro na gestão da sua sessão.".to_string(),
+            AppError::Unauthorized => "Não tem permissão para aceder a este recurso.".to_string(),
+            AppError::Unauthenticated => "Autenticação necessária (sessão ou token de API/JWT).".to_string(),
+            AppError::UserAlreadyExists(_) => "Já existe um utilizador com este ID.".to_string(),
+            // NotFound/Conflict/TooManyRequests/EmManutencao já transportam mensagens pensadas para o utilizador
+            AppError::NotFound(msg) | AppError::Conflict(msg) | AppError::TooManyRequests(msg) | AppError::EmManutencao(msg) => msg.clone(),
+            AppError::Validation(erros) => erros
+                .iter()
+                .map(|e| e.mensagem.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            AppError::Oidc(_) => "O login institucional não está disponível. Entre com o ID e a senha.".to_string(),
+            AppError::InternalServerError => "Ocorreu um erro inesperado.".to_string(),
+        }
+    }
+
+    /// Código estável do erro para integrações (campo `code` do envelope da API).
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::InvalidCredentials => "invalid_credentials",
+            AppError::Unauthenticated => "unauthenticated",
+            AppError::Unauthorized => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::UserAlreadyExists(_) | AppError::Conflict(_) => "conflict",
+            AppError::TooManyRequests(_) => "too_many_requests",
+            AppError::Validation(_) => "validation",
+            AppError::Oidc(_) => "identity_provider",
+            AppError::EmManutencao(_) => "maintenance",
+            _ => "internal",
+        }
+    }
+}
+
+/// Mensagem de um erro já respondido com a página de erro, guardada nas extensões da resposta
+/// para que `web::mw_erros` volte a gerar a página com o utilizador da sessão.
+#[derive(Debug, Clone)]
+pub struct ErroPagina {
+    pub mensagem: String,
+}
+
+/// Página de erro (template `erro.html`) com o código de estado e a mensagem indicados.
+pub fn pagina_erro(status: StatusCode, mensagem: &str, utilizador: Option<String>) -> axum::response::Response {
+    let pagina = ErroPage { status: status.as_u16(), mensagem: mensagem.to_string(), utilizador };
+    let html = pagina.render().unwrap_or_else(|e| {
+        tracing::error!("Falha ao renderizar template ErroPage: {}", e);
+        format!("<!DOCTYPE html><html><body><h1>Erro {}</h1></body></html>", status.as_u16())
+    });
+    let mut resposta = (status, Html(html)).into_response();
+    resposta.extensions_mut().insert(ErroPagina { mensagem: mensagem.to_string() });
+    resposta
 }
 
 // Como converter AppError numa resposta HTTP
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
         // Loga o erro detalhado no servidor
-        tracing::error!("Erro processado: {:?}", self);
-
-        let (status, user_message) = match self {
-            AppError::SqlxError(_) | AppError::SqlxMigrateError(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Erro ao aceder aos dados.")
-            }
-            AppError::EnvVarError(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Erro de configuração.")
-            }
-            // *** ADICIONADO: Mensagem para erro de hash ***
-            AppError::PasswordHashingError => {
-                 (StatusCode::INTERNAL_SERVER_ERROR, "Erro ao processar credenciais.")
-            }
-             // *** ADICIONADO: Mensagem para credenciais inválidas (seguro) ***
-            AppError::InvalidCredentials => {
-                (StatusCode::UNAUTHORIZED, "ID ou senha inválidos.") // Mensagem genérica
-            }
-             // *** ADICIONADO: Mensagem para erro de sessão ***
-            AppError::SessionError(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Erro na gestão da sua sessão.")
-            }
-            _ => (StatusCode::INTERNAL_SERVER_ERROR, "Ocorreu um erro inesperado."),
+        let status = self.status_code();
+        if status.is_server_error() {
+            tracing::error!("Erro processado: {:?}", self);
+        } else {
+            tracing::warn!("Pedido recusado: {}", self);
+        }
+
+        pagina_erro(status, &self.user_message(), None)
+    }
+}
+
+/// Envelope de erro da API JSON.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EnvelopeErro {
+    pub error: CorpoErro,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CorpoErro {
+    /// Código estável (ver `AppError::code`): unauthenticated, forbidden, not_found, conflict, validation, internal...
+    pub code: String,
+    /// Mensagem para mostrar ao utilizador.
+    pub message: String,
+    /// Erros por campo (só nos erros de validação).
+    pub fields: Vec<FieldError>,
+}
+
+/// Erro da API JSON (/api/v1): o mesmo `AppError`, respondido no envelope
+/// `{"error": {"code", "message", "fields"}}` em vez da página HTML.
+#[derive(Debug)]
+pub struct ApiError(pub AppError);
+
+impl<E: Into<AppError>> From<E> for ApiError {
+    fn from(e: E) -> Self {
+        ApiError(e.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        let erro = self.0;
+        if erro.status_code().is_server_error() {
+            tracing::error!("Erro na API: {:?}", erro);
+        } else {
+            tracing::warn!("Pedido à API recusado: {}", erro);
+        }
+
+        let corpo = EnvelopeErro {
+            error: CorpoErro {
+                code: erro.code().to_string(),
+                message: erro.user_message(),
+                fields: match &erro {
+                    AppError::Validation(erros) => erros.clone(),
+                    _ => Vec::new(),
+                },
+            },
         };
+        (erro.status_code(), Json(corpo)).into_response()
+    }
+}
+
+// Pedidos mal formados à API (corpo JSON, query string ou caminho inválidos)
+impl From<JsonRejection> for AppError {
+    fn from(r: JsonRejection) -> Self {
+        AppError::validation("corpo", r.body_text())
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(r: QueryRejection) -> Self {
+        AppError::validation("query", r.body_text())
+    }
+}
 
-        // Retorna uma página HTML simples (ou poderia usar um template Askama de erro)
-         (status, Html(format!(r#"
-            <!DOCTYPE html><html><head><title>Erro</title><style>body{{font-family:sans-serif;}}</style></head>
-            <body><h1>Erro {status_code}</h1><p>{message}</p><a href="javascript:history.back()">Voltar</a></body></html>
-         "#, status_code=status.as_u16(), message=user_message))).into_response()
+impl From<PathRejection> for AppError {
+    fn from(r: PathRejection) -> Self {
+        AppError::validation("caminho", r.body_text())
     }
 }
 
+/// Result dos handlers da API JSON.
+pub type ApiResult<T> = Result<T, ApiError>;
+
 // Tipo Result padrão para a aplicação
 pub type AppResult<T = ()> = Result<T, AppError>;
\ No newline at end of file
@@ -0,0 +1,160 @@
+// src/i18n.rs
+//! Tradução do texto gerado no servidor (páginas e mensagens), em português e inglês.
+//! As mensagens vêm de `locales/<idioma>.toml` (embutidos no binário), com chaves `secção.nome`;
+//! uma chave em falta num idioma usa a do português, e em último caso aparece a própria chave.
+//!
+//! O idioma de cada pedido é escolhido por `web::mw_idioma` (preferência do utilizador, Accept-Language
+//! ou o idioma por omissão da configuração) e fica disponível durante todo o pedido: `t("chave")` nos
+//! handlers e serviços, e o filtro `|t` nos templates (ver `templates::filters`).
+//! Fora de um pedido (tarefas de fundo) usa-se o idioma por omissão.
+
+use serde::Deserialize;
+use std::{collections::HashMap, future::Future, str::FromStr, sync::OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Idioma {
+    #[default]
+    Pt,
+    En,
+}
+
+impl Idioma {
+    pub const TODOS: [Idioma; 2] = [Idioma::Pt, Idioma::En];
+
+    /// Código guardado (users.idioma, sessão) e usado no `<html lang>`.
+    pub fn codigo(&self) -> &'static str {
+        match self {
+            Idioma::Pt => "pt",
+            Idioma::En => "en",
+        }
+    }
+
+    /// Nome no próprio idioma (para o seletor).
+    pub fn nome(&self) -> &'static str {
+        match self {
+            Idioma::Pt => "Português",
+            Idioma::En => "English",
+        }
+    }
+
+    /// Primeiro idioma suportado do cabeçalho Accept-Language (ex: "en-GB,en;q=0.9,pt;q=0.8").
+    pub fn negociar(accept_language: &str) -> Option<Self> {
+        let mut pedidos: Vec<(f32, &str)> = accept_language
+            .split(',')
+            .filter_map(|parte| {
+                let mut campos = parte.trim().split(';');
+                let tag = campos.next()?.trim();
+                let q = campos
+                    .find_map(|c| c.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+                Some((q, tag))
+            })
+            .filter(|(q, _)| *q > 0.0)
+            .collect();
+        // Ordenação estável: com o mesmo q, fica a ordem do cabeçalho
+        pedidos.sort_by(|a, b| b.0.total_cmp(&a.0));
+        pedidos.into_iter().find_map(|(_, tag)| tag.parse().ok())
+    }
+}
+
+/// Aceita o código ou uma tag de idioma com região ("pt-BR", "en_GB").
+impl FromStr for Idioma {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let base = s.trim().split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+        match base.as_str() {
+            "pt" => Ok(Idioma::Pt),
+            "en" => Ok(Idioma::En),
+            _ => Err(format!("idioma não suportado: '{}' (use 'pt' ou 'en')", s)),
+        }
+    }
+}
+
+type Catalogo = HashMap<Idioma, HashMap<String, String>>;
+
+static CATALOGO: OnceLock<Catalogo> = OnceLock::new();
+static PADRAO: OnceLock<Idioma> = OnceLock::new();
+
+tokio::task_local! {
+    static IDIOMA: Idioma;
+}
+
+/// Lê um ficheiro de mensagens: tabelas TOML de um nível (`[secção]` com `nome = "texto"`).
+fn ler(idioma: Idioma, texto: &str) -> Result<HashMap<String, String>, String> {
+    let seccoes: HashMap<String, HashMap<String, String>> =
+        toml::from_str(texto).map_err(|e| format!("locales/{}.toml inválido: {}", idioma.codigo(), e))?;
+    Ok(seccoes
+        .into_iter()
+        .flat_map(|(seccao, mensagens)| mensagens.into_iter().map(move |(nome, texto)| (format!("{}.{}", seccao, nome), texto)))
+        .collect())
+}
+
+fn carregar_catalogo() -> Result<Catalogo, String> {
+    let mut catalogo = HashMap::new();
+    catalogo.insert(Idioma::Pt, ler(Idioma::Pt, include_str!("../locales/pt.toml"))?);
+    catalogo.insert(Idioma::En, ler(Idioma::En, include_str!("../locales/en.toml"))?);
+    Ok(catalogo)
+}
+
+/// Carrega as mensagens e define o idioma por omissão (chamado no arranque).
+/// Avisa das chaves do português que faltam nos outros idiomas.
+pub fn configurar(padrao: Idioma) -> Result<(), String> {
+    let catalogo = carregar_catalogo()?;
+    let pt = &catalogo[&Idioma::Pt];
+    for idioma in Idioma::TODOS.into_iter().filter(|i| *i != Idioma::Pt) {
+        let mut em_falta: Vec<&String> = pt.keys().filter(|k| !catalogo[&idioma].contains_key(*k)).collect();
+        if !em_falta.is_empty() {
+            em_falta.sort();
+            tracing::warn!("🌐 {} mensagem(ns) sem tradução em '{}' (usa-se o português): {:?}", em_falta.len(), idioma.codigo(), em_falta);
+        }
+    }
+    let _ = CATALOGO.set(catalogo);
+    let _ = PADRAO.set(padrao);
+    Ok(())
+}
+
+fn catalogo() -> &'static Catalogo {
+    CATALOGO.get_or_init(|| carregar_catalogo().expect("locales/*.toml inválidos"))
+}
+
+/// Idioma por omissão (configuração `i18n.padrao`).
+pub fn padrao() -> Idioma {
+    PADRAO.get().copied().unwrap_or_default()
+}
+
+/// Idioma do pedido em curso (ou o por omissão, fora de um pedido).
+pub fn atual() -> Idioma {
+    IDIOMA.try_with(|idioma| *idioma).unwrap_or_else(|_| padrao())
+}
+
+/// Executa `futuro` com o idioma indicado (ver `web::mw_idioma`).
+pub async fn com_idioma<F: Future>(idioma: Idioma, futuro: F) -> F::Output {
+    IDIOMA.scope(idioma, futuro).await
+}
+
+/// Mensagem `chave` no idioma indicado.
+pub fn traduzir(idioma: Idioma, chave: &str) -> String {
+    let catalogo = catalogo();
+    catalogo[&idioma]
+        .get(chave)
+        .or_else(|| catalogo[&Idioma::Pt].get(chave))
+        .cloned()
+        .unwrap_or_else(|| {
+            tracing::warn!("🌐 Mensagem sem tradução: '{}'", chave);
+            chave.to_string()
+        })
+}
+
+/// Mensagem `chave` no idioma do pedido.
+pub fn t(chave: &str) -> String {
+    traduzir(atual(), chave)
+}
+
+/// Como `t`, substituindo `{nome}` pelos valores indicados.
+pub fn tf(chave: &str, valores: &[(&str, &str)]) -> String {
+    valores
+        .iter()
+        .fold(t(chave), |texto, (nome, valor)| texto.replace(&format!("{{{}}}", nome), valor))
+}
@@ -1,19 +1,25 @@
 // src/main.rs
 
 // --- Declaração dos Módulos ---
+mod config;
 mod db;
 mod error;
+mod i18n;
 mod models;
+mod moeda;
 mod services;
 mod state;
 mod templates;
+mod tempo;
+mod validation;
 mod web;
 // mod ws;
 
 // --- Imports ---
 use crate::state::AppState;
 use axum::serve;
-use std::{env, net::SocketAddr};
+use axum_server::tls_rustls::RustlsConfig;
+use std::{env, net::SocketAddr, sync::Arc};
 use time::Duration;
 use tokio::net::TcpListener;
 use tower::ServiceBuilder;
@@ -29,6 +35,12 @@ async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
 
     // --- Configuração do Logging (Tracing) ---
+    // LOG_FORMAT=json: uma linha JSON por evento (com o span do pedido e o request_id), para agregadores de logs
+    let log_json = match env::var("LOG_FORMAT").unwrap_or_default().trim().to_lowercase().as_str() {
+        "" | "texto" | "text" => false,
+        "json" => true,
+        outro => return Err(anyhow::anyhow!("LOG_FORMAT inválido: '{}' (use 'texto' ou 'json')", outro)),
+    };
     tracing_subscriber::registry()
         .with(
             EnvFilter::try_from_default_env().unwrap_or_else(|_| {
@@ -37,79 +49,331 @@ async fn main() -> anyhow::Result<()> {
                     .into()
             }),
         )
-        .with(fmt::layer())
+        .with((!log_json).then(fmt::layer))
+        .with(log_json.then(|| fmt::layer().json().with_current_span(true).with_span_list(false)))
         .init();
 
     tracing::info!("🚀 Iniciando servidor Merca Simples...");
 
+    // --- Configuração da aplicação (config.toml + variáveis de ambiente, validada antes de tudo) ---
+    let (config, ficheiro_config) = config::Config::carregar()
+        .map_err(|e| anyhow::anyhow!("Configuração inválida: {}", e))?;
+    match &ficheiro_config {
+        Some(ficheiro) => tracing::info!("⚙️ Configuração lida de {} (as variáveis de ambiente têm prioridade).", ficheiro),
+        None => tracing::info!("⚙️ Sem ficheiro de configuração (config.toml); só variáveis de ambiente."),
+    }
+    tracing::info!(
+        "⚙️ Sessões expiram após {}h de inatividade; regra de fadiga de {} dia(s); roles de administração: {}.",
+        config.sessao.expira_horas, config.escala.fadiga_dias, config.roles.admin.join(", ")
+    );
+    i18n::configurar(config.i18n.padrao).map_err(|e| anyhow::anyhow!("Traduções inválidas: {}", e))?;
+    tracing::info!("🌐 Idioma por omissão: {}.", config.i18n.padrao.nome());
+    match config.i18n.fuso_horario {
+        Some(fuso) => {
+            tempo::configurar(fuso);
+            tracing::info!("🕒 Fuso horário: {}.", fuso);
+        }
+        None => tracing::info!("🕒 Fuso horário do servidor: {} (i18n.fuso_horario não definido).", tempo::fuso()),
+    }
+    match &config.estaticos.diretorio {
+        Some(diretorio) if !diretorio.is_dir() => {
+            tracing::warn!("⚠️ Diretório dos ficheiros estáticos não encontrado: {} (as páginas ficam sem estilos).", diretorio.display());
+        }
+        Some(diretorio) => tracing::info!("🗂️ Ficheiros estáticos lidos de {}.", diretorio.display()),
+        None => tracing::info!("🗂️ Ficheiros estáticos embutidos no binário."),
+    }
+
+    // --- Parâmetros de hashing das senhas (validados antes de aceitar pedidos) ---
+    let hash_config = services::auth_service::HashConfig::from_env()
+        .map_err(|e| anyhow::anyhow!("Configuração de hashing inválida: {}", e))?;
+    services::auth_service::configurar(hash_config);
+    let captcha = services::captcha_service::CaptchaConfig::from_env()
+        .map_err(|e| anyhow::anyhow!("Configuração de CAPTCHA inválida: {}", e))?;
+    let senha_max_dias = services::auth_service::validade_senha_from_env()
+        .map_err(|e| anyhow::anyhow!("Política de senhas inválida: {}", e))?;
+    let limite_atraso_horas = services::presence_service::limite_atraso_from_env()
+        .map_err(|e| anyhow::anyhow!("Configuração da presença inválida: {}", e))?;
+    let telegram = services::telegram_service::TelegramConfig::from_env()
+        .map_err(|e| anyhow::anyhow!("Configuração do Telegram inválida: {}", e))?;
+    let google_calendar = services::google_calendar_service::GoogleCalendarConfig::from_env()
+        .map_err(|e| anyhow::anyhow!("Configuração do Google Calendar inválida: {}", e))?;
+    let email = services::email_service::EmailConfig::from_config(&config.smtp)
+        .map_err(|e| anyhow::anyhow!("Configuração do email (SMTP) inválida: {}", e))?;
+    // (definida já aqui: as migrações fazem uma cópia da base de dados antes de alterar o esquema)
+    services::backup_service::configurar(
+        services::backup_service::BackupConfig::from_env()
+            .map_err(|e| anyhow::anyhow!("Configuração das cópias de segurança inválida: {}", e))?,
+    );
+    let jwt = services::jwt_service::JwtConfig::from_env()
+        .map_err(|e| anyhow::anyhow!("Configuração do JWT inválida: {}", e))?;
+    let limites = web::mw_limite::LimiteConfig::from_env()
+        .map_err(|e| anyhow::anyhow!("Configuração do limite de pedidos inválida: {}", e))?;
+
+    // `mercal2 migrate status|run|revert`: gestão das migrações da base de dados, e termina
+    if env::args().nth(1).as_deref() == Some("migrate") {
+        return migrate(&config.base_dados, env::args().nth(2).as_deref()).await;
+    }
+
     // --- Configuração da Base de Dados ---
-    let db_pool = match db::create_db_pool().await {
-        Ok(pool) => pool,
+    let db::Pools { escrita: db_pool, leitura: db_leitura } = match db::create_db_pools(&config.base_dados).await {
+        Ok(pools) => pools,
         Err(e) => {
             tracing::error!("❌ Falha crítica ao inicializar a base de dados: {}", e);
             return Err(anyhow::anyhow!("Falha ao conectar/migrar DB: {}", e));
         }
     };
 
+    // `mercal2 seed`: dados de demonstração numa base de dados vazia (desenvolvimento, demos), e termina
+    if env::args().nth(1).as_deref() == Some("seed") {
+        let resumo = services::seed_service::popular(&db_pool, config.escala.fadiga_dias)
+            .await
+            .map_err(|e| anyhow::anyhow!("Seed falhou: {}", e.user_message()))?;
+        println!(
+            "🌱 {} utilizadores, {} grupos, {} postos, {} dias de escala, {} trocas e {} marcações de presença criados.",
+            resumo.utilizadores, resumo.grupos, resumo.postos, resumo.dias_escala, resumo.trocas, resumo.presencas
+        );
+        println!("   Entre como 'admin' ou 'escalante' (ou um aluno, ex: '1001') com a senha '{}'.", services::seed_service::SENHA_DEMO);
+        return Ok(());
+    }
+
+    // --- Modo de manutenção (continua ativo depois de um reinício) ---
+    if let Some(manutencao) = services::manutencao_service::carregar(&db_pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("Falha ao ler o modo de manutenção: {}", e))?
+    {
+        tracing::warn!("🚧 Modo de manutenção ativo (desde {}, por {}): só leitura.", manutencao.ativada_em, manutencao.ativada_por);
+    }
+
     // --- Configuração das Sessões ---
     // SqliteStore::new() já retorna Result, então precisamos extrair o valor
     let session_store = SqliteStore::new(db_pool.clone())
         .with_table_name("sessions")
         .map_err(|e| anyhow::anyhow!("Falha ao criar session store: {}", e))?;
 
-    // Clone o store para a task de limpeza
-    let session_store_clone = session_store.clone();
-    tokio::spawn(async move {
-        // Usa ExpiredDeletion trait através do método continuously_delete_expired
-        if let Err(e) = session_store_clone
-            .continuously_delete_expired(tokio::time::Duration::from_secs(60 * 60))
-            .await
-        {
-            tracing::error!("Erro na task de limpeza de sessões: {:?}", e);
+    // --- Tarefas periódicas (ver agendador_service; estado em /admin/tarefas) ---
+    let store_limpeza = session_store.clone();
+    let (db_retencao, retencao) = (db_pool.clone(), config.retencao.clone());
+    let db_contabilidade = db_pool.clone();
+    let db_biblioteca = db_pool.clone();
+    services::agendador_service::Agendador::new()
+        .registar(
+            "sessoes.limpeza",
+            "Remove as sessões expiradas",
+            "0 0 * * * *", // De hora a hora
+            std::time::Duration::from_secs(5 * 60),
+            move || {
+                let store = store_limpeza.clone();
+                async move {
+                    store.delete_expired().await.map_err(|e| error::AppError::SessionError(e.to_string()))?;
+                    Ok("Sessões expiradas removidas.".to_string())
+                }
+            },
+        )
+        .map_err(|e| anyhow::anyhow!(e))?
+        .registar(
+            "retencao.purga",
+            "Apaga os registos fora do prazo de retenção (guarda os totais por mês)",
+            "0 30 3 * * *", // Todos os dias às 03:30
+            std::time::Duration::from_secs(15 * 60),
+            move || {
+                let (db, retencao) = (db_retencao.clone(), retencao.clone());
+                async move { services::retencao_service::purgar(&db, &retencao).await }
+            },
+        )
+        .map_err(|e| anyhow::anyhow!(e))?
+        .registar(
+            "contabilidade.verificacao",
+            "Compara os contadores de serviços com o livro de serviços (só avisa; reconciliar em /admin/contabilidade)",
+            "0 0 4 * * *", // Todos os dias às 04:00
+            std::time::Duration::from_secs(15 * 60),
+            move || {
+                let db = db_contabilidade.clone();
+                async move { services::contabilidade_service::verificar(&db).await }
+            },
+        )
+        .map_err(|e| anyhow::anyhow!(e))?
+        .registar(
+            "biblioteca.atrasos",
+            "Avisa (centro de notificações) quem tem livros da biblioteca por devolver depois do prazo",
+            "0 0 9 * * *", // Todos os dias às 09:00
+            std::time::Duration::from_secs(10 * 60),
+            move || {
+                let db = db_biblioteca.clone();
+                async move { services::biblioteca_service::avisar_atrasos(&db, tempo::hoje()).await }
+            },
+        )
+        .map_err(|e| anyhow::anyhow!(e))?
+        .iniciar(db_pool.clone())
+        .await
+        .map_err(|e| anyhow::anyhow!("Falha ao iniciar o agendador de tarefas: {}", e))?;
+
+    // As roles de administração configuradas têm de existir (senão ninguém as tem)
+    if let Ok(roles) = services::permission_service::listar_roles(&db_pool).await {
+        for role in config.roles.admin.iter().filter(|a| !roles.iter().any(|r| r.nome.eq_ignore_ascii_case(a))) {
+            tracing::warn!("⚠️ A role de administração '{}' (roles.admin) não existe.", role);
         }
-    });
-    tracing::info!("🧹 Tarefa de limpeza de sessões iniciada.");
+    }
+
+    // --- Webhooks: envio das entregas em fila e deteção de saídas atrasadas ---
+    services::webhook_service::iniciar_envio(db_pool.clone())
+        .map_err(|e| anyhow::anyhow!("Falha ao iniciar o envio de webhooks: {}", e))?;
+    services::presence_service::iniciar_verificacao_atrasos(db_pool.clone(), limite_atraso_horas);
+    tracing::info!("🪝 Envio de webhooks iniciado (saídas atrasadas após {}h).", limite_atraso_horas);
+    services::escala_service::iniciar_lembretes(db_pool.clone());
+    tracing::info!("⏰ Tarefa de lembretes de serviço iniciada.");
+
+    // --- Bot do Telegram (notificações e comandos) ---
+    match telegram {
+        Some(config) => {
+            tracing::info!("📨 Bot do Telegram ativo ({:?}).", config.bot_username.as_deref().unwrap_or("nome não indicado"));
+            services::telegram_service::configurar(config);
+            services::telegram_service::iniciar_bot(db_pool.clone());
+        }
+        None => tracing::info!("📨 Bot do Telegram desativado (TELEGRAM_BOT_TOKEN não definida)."),
+    }
+
+    // --- Google Calendar (serviços publicados no calendário de quem ligar a conta) ---
+    match google_calendar {
+        Some(config) => {
+            tracing::info!("📅 Sincronização com o Google Calendar ativa (a cada {} min).", config.intervalo.as_secs() / 60);
+            services::google_calendar_service::configurar(config);
+            services::google_calendar_service::iniciar_sincronizacao(db_pool.clone());
+        }
+        None => tracing::info!("📅 Google Calendar desativado (GOOGLE_CALENDAR_CLIENT_ID não definida)."),
+    }
+
+    // --- Emails (caixa de saída enviada por SMTP) ---
+    match email {
+        Some(config) => {
+            tracing::info!("✉️ Envio de emails ativo ({:?}).", config);
+            services::email_service::configurar(config);
+            services::email_service::iniciar_envio(db_pool.clone());
+        }
+        None => tracing::info!("✉️ Envio de emails desativado (smtp.host / SMTP_HOST não definida)."),
+    }
+
+    // --- Cópias de segurança da base de dados ---
+    let backups = services::backup_service::config();
+    if backups.intervalo_horas > 0 {
+        tracing::info!(
+            "💾 Cópias de segurança automáticas a cada {}h em {} (mantidas {}).",
+            backups.intervalo_horas, backups.dir.display(), backups.manter
+        );
+    } else {
+        tracing::info!("💾 Cópias de segurança automáticas desativadas (BACKUP_INTERVAL_HOURS=0); só manuais, em {}.", backups.dir.display());
+    }
+    services::backup_service::iniciar(db_pool.clone());
+
+    // --- Autenticação JWT da aplicação móvel (/api/auth) ---
+    match jwt {
+        Some(config) => {
+            tracing::info!(
+                "📱 Autenticação JWT ativa (access token {} min, refresh token {} dias).",
+                config.acesso_minutos, config.refresh_dias
+            );
+            services::jwt_service::configurar(config);
+        }
+        None => tracing::info!("📱 Autenticação JWT desativada (JWT_SECRET não definida)."),
+    }
+
+    // --- Limite de pedidos por cliente (utilizador ou IP) ---
+    tracing::info!(
+        "⛔ Limite de pedidos por minuto (0 = sem limite): geral {}, login {}, escritas na API {}.",
+        limites.geral, limites.login, limites.escrita
+    );
+    web::mw_limite::configurar(limites);
+    web::mw_limite::iniciar_limpeza();
 
     let secret_key_string = env::var("SESSION_SECRET")
         .map_err(|e| anyhow::anyhow!("!!! Variável de ambiente SESSION_SECRET não definida: {}", e))?;
-    if secret_key_string.len() < 64 {
-        tracing::warn!("⚠️ SESSION_SECRET é curta, considere usar uma chave mais longa e aleatória!");
-    }
-    let key = Key::from(secret_key_string.as_bytes());
+    // A chave assina o cookie de sessão (HMAC): um cookie alterado ou forjado é ignorado
+    let key = Key::try_from(secret_key_string.as_bytes())
+        .map_err(|_| anyhow::anyhow!("!!! SESSION_SECRET tem de ter pelo menos 64 bytes (ex: `openssl rand -hex 32`)."))?;
+    // Chave dos pseudónimos das sugestões anónimas (derivada do mesmo segredo)
+    services::sugestao_service::configurar(secret_key_string.as_bytes());
 
     // Cria a camada de sessão
-    let session_layer = SessionManagerLayer::new(session_store)
-        .with_secure(false)
+    let session_layer = SessionManagerLayer::new(session_store.clone())
+        .with_signed(key)
+        .with_secure(config.cookie_seguro())
         .with_http_only(true)
-        .with_expiry(Expiry::OnInactivity(Duration::days(1)));
+        .with_expiry(Expiry::OnInactivity(Duration::hours(config.sessao.expira_horas)));
 
     tracing::info!("🔑 Camada de sessão configurada.");
 
     // --- Criação do Estado da Aplicação ---
+    let addr = config.servidor.endereco;
     let app_state = AppState { 
+    config: Arc::new(config),
     db_pool,
+    db_leitura,
+    session_store,
     presence_state: state::PresenceWsState::default(),
+    visitantes_state: state::PresenceWsState::default(),
+    permissions: state::PermissionCache::default(),
+    oidc: services::oidc_service::OidcConfig::from_env().map(Arc::new),
+    captcha: captcha.map(Arc::new),
+    senha_max_dias,
 };
+    match &app_state.oidc {
+        Some(oidc) => tracing::info!("🏛️ Login institucional (OIDC) ativo: {}", oidc.issuer),
+        None => tracing::info!("🏛️ Login institucional (OIDC) desativado (variáveis OIDC_* não definidas)."),
+    }
+    match &app_state.captcha {
+        Some(captcha) => tracing::info!("🤖 CAPTCHA no login ativo ({:?}, após {} falhas).", captcha.provedor, captcha.apos_falhas),
+        None => tracing::info!("🤖 CAPTCHA no login desativado (CAPTCHA_PROVEDOR não definida)."),
+    }
+    match app_state.senha_max_dias {
+        Some(dias) => tracing::info!("⏳ Validade das senhas: {} dias.", dias),
+        None => tracing::info!("⏳ Validade das senhas desativada (PASSWORD_MAX_AGE_DAYS não definida)."),
+    }
 
     // --- Configuração do Endereço e Listener ---
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    tracing::info!("📡 Servidor escutando em http://{}", addr);
+    let tls = app_state.config.tls.clone();
+    tracing::info!("📡 Servidor escutando em {}://{}", if tls.ativo() { "https" } else { "http" }, addr);
     let listener = match TcpListener::bind(addr).await {
         Ok(l) => l,
         Err(e) => {
-            tracing::error!("❌ Falha ao iniciar listener na porta 3000: {}", e);
+            tracing::error!("❌ Falha ao iniciar listener em {}: {}", addr, e);
             return Err(e.into());
         }
     };
+    // HTTPS direto (sem proxy): o certificado é lido já, para falhar no arranque e não no primeiro pedido
+    let rustls = match (&tls.certificado, &tls.chave) {
+        (Some(certificado), Some(chave)) => Some(
+            RustlsConfig::from_pem_file(certificado, chave)
+                .await
+                .map_err(|e| anyhow::anyhow!("Certificado ou chave TLS inválidos: {}", e))?,
+        ),
+        _ => None,
+    };
+    if let Some(redirecionar) = tls.redirecionar_http {
+        web::https::iniciar_redirecionamento(redirecionar, addr.port())
+            .await
+            .map_err(|e| anyhow::anyhow!("Falha ao iniciar o redirecionamento HTTP em {}: {}", redirecionar, e))?;
+        tracing::info!("🔒 Pedidos HTTP em {} redirecionados para HTTPS.", redirecionar);
+    }
+    tracing::info!("🍪 Cookies de sessão {}.", if app_state.config.cookie_seguro() { "só por HTTPS (Secure)" } else { "também por HTTP" });
 
     // --- Criação do Router e Aplicação das Camadas (Middlewares) ---
     tracing::info!("🛠️ Construindo router e aplicando middlewares...");
     let app = web::routes::create_router(app_state.clone())
+        // Modo de manutenção: recusa as alterações (lê o utilizador da sessão, por isso também por dentro)
+        .layer(axum::middleware::from_fn_with_state(app_state.clone(), web::mw_manutencao::bloquear_escritas))
+        // Limite de pedidos: por dentro das sessões, para contar por utilizador autenticado
+        .layer(axum::middleware::from_fn(web::mw_limite::limitar))
+        // Páginas de erro com o utilizador da sessão: por dentro das sessões e do idioma
+        .layer(axum::middleware::from_fn_with_state(app_state.clone(), web::mw_erros::paginas_de_erro))
+        // Idioma das páginas (sessão ou Accept-Language): também por dentro das sessões
+        .layer(axum::middleware::from_fn(web::mw_idioma::escolher_idioma))
         .layer(
             ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
+                // ID do pedido (X-Request-Id): atribuído, gravado no span do pedido e devolvido na resposta
+                .layer(web::mw_request_id::atribuir())
+                .layer(TraceLayer::new_for_http().make_span_with(web::mw_request_id::span_pedido))
+                .layer(web::mw_request_id::propagar())
                 // CookieManagerLayer::new() não aceita argumentos
-                // A Key é configurada separadamente se necessário
+                // (cookies simples, ex: token CSRF; o de sessão é assinado com a Key pela session_layer)
                 .layer(CookieManagerLayer::new())
                 .layer(session_layer)
         );
@@ -117,10 +381,51 @@ async fn main() -> anyhow::Result<()> {
 
     // --- Início do Servidor ---
     tracing::info!("👂 Servidor pronto para aceitar conexões...");
-    if let Err(e) = serve(listener, app.into_make_service()).await {
+    // ConnectInfo: o IP do cliente é usado no limite de tentativas de login
+    let servico = app.into_make_service_with_connect_info::<SocketAddr>();
+    let resultado = match rustls {
+        Some(rustls) => axum_server::from_tcp_rustls(listener.into_std()?, rustls).serve(servico).await,
+        None => serve(listener, servico).await,
+    };
+    if let Err(e) = resultado {
         tracing::error!("❌ Erro fatal no servidor: {}", e);
         return Err(e.into());
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// `mercal2 migrate <status|run|revert>`: estado das migrações, aplicar as que faltam ou desfazer a última
+/// (estas duas com uma cópia da base de dados antes, em BACKUP_DIR).
+async fn migrate(config: &config::BaseDadosConfig, subcomando: Option<&str>) -> anyhow::Result<()> {
+    match subcomando {
+        Some("status") => {
+            let estado = db::estado_migracoes(config).await.map_err(|e| anyhow::anyhow!("{}", e))?;
+            for migracao in &estado {
+                let aplicada = match &migracao.aplicada_em {
+                    Some(em) => format!("aplicada em {}", em),
+                    None => "POR APLICAR".to_string(),
+                };
+                let reversivel = if migracao.reversivel { " (reversível)" } else { "" };
+                println!("{} {:<45} {}{}", migracao.versao, migracao.descricao, aplicada, reversivel);
+            }
+            let pendentes = estado.iter().filter(|m| m.aplicada_em.is_none()).count();
+            println!("{} migrações, {} por aplicar.", estado.len(), pendentes);
+        }
+        Some("run") => {
+            let aplicadas = db::migrar(config).await.map_err(|e| anyhow::anyhow!("{}", e))?;
+            println!("✅ {} migração(ões) aplicada(s).", aplicadas);
+        }
+        Some("revert") => {
+            let migracao = db::reverter(config).await.map_err(|e| anyhow::anyhow!("{}", e))?;
+            println!("↩️ Migração {} ({}) desfeita.", migracao.versao, migracao.descricao);
+        }
+        outro => {
+            return Err(anyhow::anyhow!(
+                "Subcomando inválido: '{}' (use `mercal2 migrate status`, `run` ou `revert`)",
+                outro.unwrap_or("")
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+// src/models/agenda.rs
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// Evento da agenda institucional (tabela `agenda_eventos`).
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct EventoAgenda {
+    pub id: i64,
+    /// formatura, cerimonia ou palestra
+    pub tipo: String,
+    pub titulo: String,
+    pub local: String,
+    pub descricao: String,
+    /// 'YYYY-MM-DD', no fuso da aplicação
+    pub data: String,
+    /// 'HH:MM'
+    pub hora_inicio: String,
+    /// 'HH:MM' (None = sem hora de fim indicada)
+    pub hora_fim: Option<String>,
+    /// Anos a que se destina, separados por vírgulas (vazio = todos)
+    pub anos: String,
+    pub obrigatorio: bool,
+    /// A turma inteira é esperada a bordo (assinalado na lista de presença)
+    pub presenca_turma: bool,
+}
+
+impl EventoAgenda {
+    /// Anos a que se destina (vazio = todos).
+    pub fn lista_anos(&self) -> Vec<i64> {
+        self.anos.split(',').filter_map(|a| a.trim().parse().ok()).collect()
+    }
+
+    /// Destina-se ao ano `ano`?
+    pub fn para_ano(&self, ano: i64) -> bool {
+        let anos = self.lista_anos();
+        anos.is_empty() || anos.contains(&ano)
+    }
+
+    /// "Todos os anos" ou "1º, 2º ano".
+    pub fn descricao_anos(&self) -> String {
+        let anos = self.lista_anos();
+        if anos.is_empty() {
+            "Todos os anos".to_string()
+        } else {
+            let anos: Vec<String> = anos.iter().map(|a| format!("{}º", a)).collect();
+            format!("{} ano", anos.join(", "))
+        }
+    }
+
+    /// "08:00" ou "08:00–10:00".
+    pub fn horario(&self) -> String {
+        match &self.hora_fim {
+            Some(fim) => format!("{}–{}", self.hora_inicio, fim),
+            None => self.hora_inicio.clone(),
+        }
+    }
+}
+
+/// Dados de um evento, já validados pelo handler (criação e alteração).
+#[derive(Debug, Clone)]
+pub struct DadosEvento {
+    pub tipo: String,
+    pub titulo: String,
+    pub local: String,
+    pub descricao: String,
+    pub data: String,
+    pub hora_inicio: String,
+    pub hora_fim: Option<String>,
+    pub anos: Vec<i64>,
+    pub obrigatorio: bool,
+    pub presenca_turma: bool,
+}
@@ -0,0 +1,11 @@
+// src/models/antiguidade.rs
+use sqlx::FromRow;
+
+/// Posição de um utilizador ativo na antiguidade do seu ano.
+#[derive(Debug, Clone, FromRow)]
+pub struct PosicaoAntiguidade {
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub antiguidade: Option<i64>, // 1 = o mais antigo; None = sem posição
+}
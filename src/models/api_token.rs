@@ -0,0 +1,12 @@
+// src/models/api_token.rs
+use sqlx::FromRow;
+
+/// Token de acesso à API de um utilizador (tabela `api_tokens`), sem o valor do token.
+#[derive(Debug, Clone, FromRow)]
+pub struct ApiToken {
+    pub id: i64,
+    pub nome: String,
+    pub prefixo: String,            // Início do token (para o reconhecer)
+    pub criado_em: String,          // Hora local, 'dd/mm/aaaa HH:MM'
+    pub ultimo_uso: Option<String>, // Hora local, 'dd/mm/aaaa HH:MM' (None = nunca usado)
+}
@@ -0,0 +1,58 @@
+// src/models/arranchamento.rs
+use sqlx::FromRow;
+
+/// Refeição servida numa organização (tabela `refeicoes`).
+#[derive(Debug, Clone, FromRow)]
+pub struct Refeicao {
+    pub id: i64,
+    pub nome: String,
+    pub ordem: i64,
+    pub corte_dias: i64,    // Dias antes em que fecham as marcações (1 = véspera)
+    pub corte_hora: String, // 'HH:MM'
+    pub ativa: bool,
+}
+
+impl Refeicao {
+    /// Hora de corte por extenso (ex: "véspera, 20:00").
+    pub fn descricao_corte(&self) -> String {
+        match self.corte_dias {
+            0 => format!("no dia, {}", self.corte_hora),
+            1 => format!("véspera, {}", self.corte_hora),
+            n => format!("{} dias antes, {}", n, self.corte_hora),
+        }
+    }
+}
+
+/// Uma refeição de um dia na página de arranchamento do utilizador.
+#[derive(Debug, Clone)]
+pub struct MarcacaoRefeicao {
+    pub refeicao_id: i64,
+    pub marcada: bool,
+    /// Ainda antes da hora de corte (pode marcar/desmarcar).
+    pub aberta: bool,
+}
+
+/// Um dia da página de arranchamento (refeições pela ordem de `refeicoes`).
+#[derive(Debug, Clone)]
+pub struct DiaArranchamento {
+    pub data: String, // 'YYYY-MM-DD'
+    pub refeicoes: Vec<MarcacaoRefeicao>,
+}
+
+/// Previsão de uma refeição num dia: quantos vão comer, por turma.
+#[derive(Debug, Clone)]
+pub struct PrevisaoRefeicao {
+    pub refeicao: Refeicao,
+    pub corte: String, // Hora de corte, 'dd/mm/aaaa HH:MM'
+    pub aberta: bool,
+    pub por_turma: Vec<i64>, // Pela ordem de `Previsao::turmas`
+    pub total: i64,
+}
+
+/// Previsão do dia para o rancho.
+#[derive(Debug, Clone)]
+pub struct Previsao {
+    pub data: String,
+    pub turmas: Vec<i64>, // Anos com marcações, por ordem
+    pub refeicoes: Vec<PrevisaoRefeicao>,
+}
@@ -0,0 +1,18 @@
+// src/models/atributo.rs
+use sqlx::FromRow;
+
+/// Definição de um campo extra dos utilizadores (criada pelo admin).
+#[derive(Debug, Clone, FromRow)]
+pub struct AtributoDef {
+    pub chave: String,
+    pub rotulo: String,
+    pub ordem: i64,
+}
+
+/// Campo extra com o valor de um utilizador (formulário de edição).
+#[derive(Debug, Clone)]
+pub struct AtributoCampo {
+    pub chave: String,
+    pub rotulo: String,
+    pub valor: String,
+}
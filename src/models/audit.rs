@@ -0,0 +1,23 @@
+// src/models/audit.rs
+use serde::Deserialize;
+use sqlx::FromRow;
+
+/// Uma linha da tabela `audit_log`.
+#[derive(Debug, Clone, FromRow)]
+pub struct AuditEntry {
+    pub actor_id: String,
+    pub acao: String,
+    pub alvo: Option<String>,
+    pub detalhes: Option<String>,
+    pub criado_em: String, // UTC, 'YYYY-MM-DD HH:MM:SS'
+}
+
+/// Filtros da página /admin/audit (todos opcionais, vindos da query string).
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub acao: Option<String>,
+    pub alvo: Option<String>,
+    pub de: Option<String>,  // YYYY-MM-DD
+    pub ate: Option<String>, // YYYY-MM-DD
+}
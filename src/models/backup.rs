@@ -0,0 +1,33 @@
+// src/models/backup.rs
+use sqlx::FromRow;
+
+/// Uma cópia de segurança da base de dados (tabela `backups`), para a página de administração.
+#[derive(Debug, Clone, FromRow)]
+pub struct Backup {
+    pub id: i64,
+    pub ficheiro: String,
+    pub origem: String, // 'automatico' ou 'manual'
+    pub criado_por: Option<String>,
+    pub estado: String, // 'ok' ou 'falhado'
+    pub tamanho: Option<i64>,
+    pub duracao_ms: Option<i64>,
+    pub erro: Option<String>,
+    pub criado_em: String, // Hora local, 'dd/mm/aaaa HH:MM'
+    pub removido: bool,    // Ficheiro já apagado pela rotação
+}
+
+impl Backup {
+    /// Pode ser descarregada (cópia bem-sucedida e ainda no disco)?
+    pub fn disponivel(&self) -> bool {
+        self.estado == "ok" && !self.removido
+    }
+
+    /// Tamanho para mostrar (ex: "1.4 MB").
+    pub fn tamanho_legivel(&self) -> String {
+        match self.tamanho {
+            Some(bytes) if bytes >= 1024 * 1024 => format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0)),
+            Some(bytes) => format!("{} KB", (bytes + 1023) / 1024),
+            None => "—".to_string(),
+        }
+    }
+}
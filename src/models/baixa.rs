@@ -0,0 +1,29 @@
+// src/models/baixa.rs
+use sqlx::FromRow;
+
+/// Baixa médica (tabela `baixas`), com o nome e a turma do utilizador.
+#[derive(Debug, Clone, FromRow)]
+pub struct Baixa {
+    pub id: i64,
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub data_inicio: String, // 'YYYY-MM-DD'
+    pub data_fim: String,    // 'YYYY-MM-DD', inclusive
+    pub restricoes: String,  // Códigos separados por vírgulas (ver `baixa_service::RESTRICOES`)
+    pub observacoes: String,
+    pub registada_por: String,
+    pub registada_em: String, // UTC
+}
+
+impl Baixa {
+    /// Em vigor no dia `hoje` ('YYYY-MM-DD').
+    pub fn ativa(&self, hoje: &str) -> bool {
+        self.data_inicio.as_str() <= hoje && hoje <= self.data_fim.as_str()
+    }
+
+    /// Códigos das restrições.
+    pub fn codigos(&self) -> impl Iterator<Item = &str> {
+        self.restricoes.split(',').map(str::trim).filter(|c| !c.is_empty())
+    }
+}
@@ -0,0 +1,45 @@
+// src/models/biblioteca.rs
+use sqlx::FromRow;
+
+/// Título do catálogo da biblioteca (tabela `livros`), com os exemplares emprestados.
+#[derive(Debug, Clone, FromRow)]
+pub struct Livro {
+    pub id: i64,
+    pub cota: String,
+    pub titulo: String,
+    pub autor: String,
+    pub exemplares: i64,
+    pub ativo: bool,
+    pub emprestados: i64, // Empréstimos por devolver
+}
+
+impl Livro {
+    /// Exemplares na estante.
+    pub fn disponiveis(&self) -> i64 {
+        (self.exemplares - self.emprestados).max(0)
+    }
+}
+
+/// Empréstimo de um livro (tabela `emprestimos`), com o título e os nomes.
+#[derive(Debug, Clone, FromRow)]
+pub struct Emprestimo {
+    pub id: i64,
+    pub livro_id: i64,
+    pub cota: String,
+    pub titulo: String,
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub emprestado_em: String, // UTC
+    pub devolver_ate: String,  // 'YYYY-MM-DD'
+    pub entregue_por: String,  // Nome (ou ID)
+    pub devolvido_em: Option<String>, // UTC; None = emprestado
+    pub recebido_por: Option<String>,
+}
+
+impl Emprestimo {
+    /// Por devolver e com o prazo antes de `hoje` ('YYYY-MM-DD').
+    pub fn atrasado(&self, hoje: &str) -> bool {
+        self.devolvido_em.is_none() && self.devolver_ate.as_str() < hoje
+    }
+}
@@ -0,0 +1,44 @@
+// src/models/cardapio.rs
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// O que é servido numa refeição de um dia.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PratoCardapio {
+    pub refeicao_id: i64,
+    pub refeicao: String,
+    pub descricao: String, // Vazio = ainda não publicado (só na página de edição)
+}
+
+/// Um dia do cardápio (refeições pela ordem do dia).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DiaCardapio {
+    pub data: String, // 'YYYY-MM-DD'
+    pub pratos: Vec<PratoCardapio>,
+}
+
+/// Cardápio de uma semana (segunda a domingo).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CardapioSemana {
+    pub inicio: String, // Segunda-feira, 'YYYY-MM-DD'
+    pub fim: String,    // Domingo
+    pub dias: Vec<DiaCardapio>,
+    /// Última alteração (UTC), se houver algum prato publicado.
+    pub atualizado_em: Option<String>,
+}
+
+impl CardapioSemana {
+    /// Nenhum prato publicado na semana.
+    pub fn vazio(&self) -> bool {
+        self.dias.iter().all(|d| d.pratos.iter().all(|p| p.descricao.is_empty()))
+    }
+}
+
+/// Semana com cardápio publicado (histórico).
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct SemanaPublicada {
+    pub inicio: String, // Segunda-feira, 'YYYY-MM-DD'
+    pub pratos: i64,
+    pub atualizado_em: String,
+}
@@ -0,0 +1,84 @@
+// src/models/cautela.rs
+use sqlx::FromRow;
+
+/// Material do catálogo (tabela `material`), com a quantidade cautelada neste momento.
+#[derive(Debug, Clone, FromRow)]
+pub struct Material {
+    pub id: i64,
+    pub nome: String,
+    pub referencia: String,
+    pub quantidade: i64, // Existências
+    pub em_uso: i64,     // Em cautelas abertas
+    pub ativo: bool,
+    pub sensivel: bool, // Entrega e devolução com confirmação dupla (armamento e afins)
+}
+
+impl Material {
+    /// Quantidade em armazém (que ainda se pode cautelar).
+    pub fn disponivel(&self) -> i64 {
+        (self.quantidade - self.em_uso).max(0)
+    }
+}
+
+/// Cautela (tabela `cautelas`), com o nome do material e do utilizador.
+#[derive(Debug, Clone, FromRow)]
+pub struct Cautela {
+    pub id: i64,
+    pub material: String,
+    pub referencia: String,
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub quantidade: i64,
+    pub devolucao_prevista: String, // 'YYYY-MM-DD'
+    pub observacoes: String,
+    pub entregue_por: String,
+    pub entregue_em: String,          // UTC
+    pub devolvida_em: Option<String>, // UTC; None = aberta
+    pub recebida_por: Option<String>,
+    pub sensivel: bool, // Entregue com confirmação dupla (ou material marcado como sensível)
+    pub verificacao_devolucao: String, // Estado verificado na devolução (material sensível)
+}
+
+impl Cautela {
+    /// Aberta e com a devolução prevista antes de `hoje` ('YYYY-MM-DD').
+    pub fn atrasada(&self, hoje: &str) -> bool {
+        self.devolvida_em.is_none() && self.devolucao_prevista.as_str() < hoje
+    }
+}
+
+/// Linha do registo encadeado do material sensível (tabela `cautela_registos`), com os nomes.
+#[derive(Debug, Clone, FromRow)]
+pub struct RegistoCautela {
+    pub id: i64,
+    pub cautela_id: i64,
+    pub evento: String, // 'entrega' ou 'devolucao'
+    pub operador_id: String,
+    pub operador: String,
+    pub confirmado_por: String,
+    pub confirmado: String,
+    pub detalhes: String,
+    pub criado_em: String, // UTC
+    pub hash_anterior: String,
+    pub hash: String,
+}
+
+impl RegistoCautela {
+    /// Início do hash (mostrado; o completo fica no título).
+    pub fn hash_curto(&self) -> &str {
+        self.hash.get(..12).unwrap_or(&self.hash)
+    }
+}
+
+/// Resultado da verificação do registo encadeado.
+#[derive(Debug, Clone, Default)]
+pub struct VerificacaoRegisto {
+    pub total: i64,
+    pub problemas: Vec<String>, // Vazio = cadeia íntegra
+}
+
+impl VerificacaoRegisto {
+    pub fn integra(&self) -> bool {
+        self.problemas.is_empty()
+    }
+}
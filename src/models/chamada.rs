@@ -0,0 +1,90 @@
+// src/models/chamada.rs
+use sqlx::FromRow;
+
+/// Contacto de emergência de um utilizador (tabela `contactos_emergencia`).
+#[derive(Debug, Clone, FromRow)]
+pub struct ContactoEmergencia {
+    pub nome: String,
+    pub parentesco: String,
+    pub telefone: String,
+    pub atualizado_em: String,
+}
+
+/// Plano de chamada de uma turma ou de um grupo, com o número de membros e o exercício em curso.
+#[derive(Debug, Clone, FromRow)]
+pub struct PlanoChamada {
+    pub id: i64,
+    pub nome: String,
+    pub turma: Option<String>,
+    pub grupo_id: Option<i64>,
+    pub grupo: Option<String>, // Nome do grupo
+    pub ramificacao: i64,
+    pub atualizado_em: String,
+    pub membros: i64,
+    pub exercicio_aberto: Option<i64>,
+}
+
+impl PlanoChamada {
+    /// A quem se aplica (ex: "Turma N244", "Grupo 1º Pelotão").
+    pub fn alvo(&self) -> String {
+        match (&self.turma, &self.grupo) {
+            (Some(turma), _) => format!("Turma {}", turma),
+            (None, Some(grupo)) => format!("Grupo {}", grupo),
+            (None, None) => "—".to_string(),
+        }
+    }
+}
+
+/// Membro na cascata de um plano, com o telefone, o contacto de emergência e (num exercício) a
+/// confirmação. `nivel` é a profundidade na cascata (0 = raiz).
+#[derive(Debug, Clone, FromRow)]
+pub struct NoChamada {
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub telefone: Option<String>,
+    pub chamador_id: Option<String>,
+    pub contacto_nome: Option<String>,
+    pub contacto_parentesco: Option<String>,
+    pub contacto_telefone: Option<String>,
+    pub confirmado_em: Option<String>,
+    pub nivel: i64,
+}
+
+impl NoChamada {
+    /// Recuo na cascata impressa e nas tabelas (px).
+    pub fn recuo(&self) -> i64 {
+        self.nivel * 20 + 8
+    }
+}
+
+/// Exercício de chamada, com as confirmações registadas.
+#[derive(Debug, Clone, FromRow)]
+pub struct ExercicioChamada {
+    pub id: i64,
+    pub plano_id: i64,
+    pub plano: String,
+    pub iniciado_por: String, // Nome
+    pub iniciado_em: String,
+    pub encerrado_em: Option<String>,
+    pub confirmados: i64,
+    pub total: i64,
+    pub ultima_confirmacao: Option<String>,
+}
+
+impl ExercicioChamada {
+    /// Ainda em curso (aceita confirmações).
+    pub fn aberto(&self) -> bool {
+        self.encerrado_em.is_none()
+    }
+}
+
+/// Lugar de um utilizador num plano: quem o chama, a quem liga e o exercício em curso.
+#[derive(Debug, Clone)]
+pub struct PosicaoChamada {
+    pub plano: String,
+    pub chamador: Option<NoChamada>, // None = raiz (avisado pelo oficial de dia)
+    pub liga_para: Vec<NoChamada>,
+    pub exercicio: Option<i64>, // Exercício em curso
+    pub confirmado: bool,       // Já confirmou a receção no exercício em curso
+}
@@ -0,0 +1,41 @@
+// src/models/claviculario.rs
+use sqlx::FromRow;
+
+/// Chave do claviculário (tabela `chaves`), com quem a tem neste momento.
+#[derive(Debug, Clone, FromRow)]
+pub struct Chave {
+    pub id: i64,
+    pub codigo: String,
+    pub local: String,
+    pub hora_limite: String, // 'HH:MM'
+    pub ativa: bool,
+    pub movimento_id: Option<i64>,    // Levantamento em aberto (None = no claviculário)
+    pub detentor_id: Option<String>,
+    pub detentor: Option<String>,     // Nome
+    pub levantada_em: Option<String>, // UTC
+}
+
+impl Chave {
+    /// Está no claviculário.
+    pub fn disponivel(&self) -> bool {
+        self.movimento_id.is_none()
+    }
+}
+
+/// Levantamento de uma chave (tabela `chave_movimentos`), com a chave e os nomes.
+#[derive(Debug, Clone, FromRow)]
+pub struct MovimentoChave {
+    pub id: i64,
+    pub chave_id: i64,
+    pub codigo: String,
+    pub local: String,
+    pub hora_limite: String,
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub observacoes: String,
+    pub levantada_em: String, // UTC
+    pub entregue_por: String, // Nome (ou ID)
+    pub devolvida_em: Option<String>, // UTC; None = chave fora
+    pub recebida_por: Option<String>,
+}
@@ -0,0 +1,33 @@
+// src/models/comitiva.rs
+use sqlx::FromRow;
+
+/// Comitiva (tabela `comitivas`), com o nome do responsável e o número de membros.
+#[derive(Debug, Clone, FromRow)]
+pub struct Comitiva {
+    pub id: i64,
+    pub nome: String,
+    pub tipo: String, // Ver `comitiva_service::TIPOS`
+    pub destino: String,
+    pub data_inicio: String, // 'YYYY-MM-DD'
+    pub data_fim: String,    // 'YYYY-MM-DD' (inclusive)
+    pub responsavel_id: String,
+    pub responsavel: String, // Nome do responsável
+    pub estado: String,      // 'aberta' | 'encerrada'
+    pub encerrada_em: Option<String>,
+    pub membros: i64,
+}
+
+impl Comitiva {
+    /// Ainda aberta (os membros estão fora da escala e, no período, fora na presença).
+    pub fn aberta(&self) -> bool {
+        self.estado == "aberta"
+    }
+}
+
+/// Membro de uma comitiva.
+#[derive(Debug, Clone, FromRow)]
+pub struct MembroComitiva {
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+}
@@ -0,0 +1,44 @@
+// src/models/conceito.rs
+use chrono::NaiveDate;
+use sqlx::FromRow;
+
+/// Lançamento do conceito (tabela `conceito_registos`), com o saldo acumulado do utilizador no período.
+#[derive(Debug, Clone, FromRow)]
+pub struct RegistoConceito {
+    pub id: i64,
+    pub data: String,   // 'YYYY-MM-DD'
+    pub origem: String, // Ver `conceito_service::ORIGENS`
+    pub pontos: i64,    // Com sinal
+    pub motivo: String,
+    pub processo_id: Option<i64>, // FATD de origem
+    pub registado_por: String,    // Nome de quem registou
+    pub saldo: i64,               // Conceito depois deste lançamento (no período)
+}
+
+impl RegistoConceito {
+    /// Criado pela decisão de uma FATD (não se apaga à mão).
+    pub fn de_fatd(&self) -> bool {
+        self.processo_id.is_some()
+    }
+}
+
+/// Posição de um utilizador na classificação da turma num período.
+#[derive(Debug, Clone, FromRow)]
+pub struct PosicaoConceito {
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub positivos: i64,
+    pub negativos: i64, // Com sinal (<= 0)
+    pub saldo: i64,
+    pub posicao: i64, // Na turma (empatados na mesma posição)
+}
+
+/// Período do conceito: um semestre.
+#[derive(Debug, Clone)]
+pub struct PeriodoConceito {
+    pub codigo: String, // Ex: "2026-2"
+    pub rotulo: String, // Ex: "2º semestre de 2026"
+    pub inicio: NaiveDate,
+    pub fim: NaiveDate,
+}
@@ -0,0 +1,28 @@
+// src/models/contabilidade.rs
+use sqlx::FromRow;
+
+/// Lançamento do livro de serviços (tabela `servico_ledger`), com o nome do militar.
+#[derive(Debug, Clone, FromRow)]
+pub struct LancamentoServico {
+    pub user_id: String,
+    pub name: String,
+    pub conta: String, // 'RN', 'RD' ou 'PUNICAO'
+    pub delta: i64,
+    pub motivo: String,
+    pub data: Option<String>,       // Dia do serviço
+    pub referencia: Option<String>, // Alocação ou troca
+    pub criado_em: String,          // UTC, 'YYYY-MM-DD HH:MM:SS'
+}
+
+/// Contadores de um utilizador comparados com as somas do livro (relatório de reconciliação).
+#[derive(Debug, Clone, FromRow)]
+pub struct Reconciliacao {
+    pub user_id: String,
+    pub name: String,
+    pub servicos_rn: i64,
+    pub livro_rn: i64,
+    pub servicos_rd: i64,
+    pub livro_rd: i64,
+    pub saldo_punicoes: i64,
+    pub livro_punicoes: i64,
+}
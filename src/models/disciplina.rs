@@ -0,0 +1,55 @@
+// src/models/disciplina.rs
+use sqlx::FromRow;
+
+/// Processo disciplinar (FATD, tabela `processos_disciplinares`), com o nome e a turma do visado.
+#[derive(Debug, Clone, FromRow)]
+pub struct ProcessoDisciplinar {
+    pub id: i64,
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub data_fato: String, // 'YYYY-MM-DD'
+    pub descricao: String,
+    pub estado: String,       // Ver `disciplina_service::ESTADOS`
+    pub prazo_defesa: String, // 'YYYY-MM-DD', inclusive
+    pub defesa: Option<String>,
+    pub defesa_em: Option<String>, // Hora local, 'dd/mm/aaaa HH:MM'
+    pub servicos: i64,             // Serviços de punição creditados
+    pub fundamentacao: Option<String>,
+    pub decidido_por: Option<String>, // Nome de quem decidiu
+    pub decidido_em: Option<String>,  // Hora local, 'dd/mm/aaaa HH:MM'
+    pub registado_por: String,        // Nome de quem registou
+    pub registado_em: String,         // Hora local, 'dd/mm/aaaa HH:MM'
+}
+
+impl ProcessoDisciplinar {
+    /// Ainda sem decisão.
+    pub fn aberto(&self) -> bool {
+        self.decidido_em.is_none()
+    }
+
+    /// À espera da defesa do visado.
+    pub fn aguarda_defesa(&self) -> bool {
+        self.estado == crate::services::disciplina_service::ESTADO_AGUARDA_DEFESA
+    }
+
+    /// À espera da decisão (com ou sem defesa).
+    pub fn aguarda_decisao(&self) -> bool {
+        self.estado == crate::services::disciplina_service::ESTADO_AGUARDA_DECISAO
+    }
+
+    /// O prazo da defesa já terminou em `hoje` ('YYYY-MM-DD').
+    pub fn prazo_terminado(&self, hoje: &str) -> bool {
+        self.prazo_defesa.as_str() < hoje
+    }
+
+    /// Descrição do estado ("Punido (2 serviços)" para as punições).
+    pub fn descricao_estado(&self) -> String {
+        let descricao = crate::services::disciplina_service::descrever_estado(&self.estado);
+        if self.estado == crate::services::disciplina_service::ESTADO_PUNIDO {
+            format!("{} ({} serviço(s))", descricao, self.servicos)
+        } else {
+            descricao.to_string()
+        }
+    }
+}
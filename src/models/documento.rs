@@ -0,0 +1,96 @@
+// src/models/documento.rs
+use sqlx::FromRow;
+
+/// Pasta do repositório de documentos (tabela `documento_pastas`), com o número de documentos.
+#[derive(Debug, Clone, FromRow)]
+pub struct PastaDocumentos {
+    pub id: i64,
+    pub categoria: String, // normas, ordens_servico, instrucoes ou outros
+    pub nome: String,
+    pub descricao: String,
+    pub roles: String, // Separadas por vírgulas (vazio = todas)
+    pub anos: String,  // Separados por vírgulas (vazio = todos)
+    pub documentos: i64,
+}
+
+impl PastaDocumentos {
+    /// Roles que a podem ver (vazio = todas).
+    pub fn lista_roles(&self) -> Vec<&str> {
+        self.roles.split(',').map(str::trim).filter(|r| !r.is_empty()).collect()
+    }
+
+    /// Anos que a podem ver (vazio = todos).
+    pub fn lista_anos(&self) -> Vec<i64> {
+        self.anos.split(',').filter_map(|a| a.trim().parse().ok()).collect()
+    }
+
+    /// Visível para quem tem estas roles e está neste ano (as duas regras têm de se cumprir).
+    pub fn visivel_para(&self, roles: &[String], ano: i64) -> bool {
+        let permitidas = self.lista_roles();
+        let anos = self.lista_anos();
+        (permitidas.is_empty() || roles.iter().any(|r| permitidas.contains(&r.as_str())))
+            && (anos.is_empty() || anos.contains(&ano))
+    }
+
+    /// "Todos" ou "Roles: x, y · 1º, 2º ano".
+    pub fn descricao_visibilidade(&self) -> String {
+        let mut partes = Vec::new();
+        let roles = self.lista_roles();
+        if !roles.is_empty() {
+            partes.push(format!("Roles: {}", roles.join(", ")));
+        }
+        let anos = self.lista_anos();
+        if !anos.is_empty() {
+            let anos: Vec<String> = anos.iter().map(|a| format!("{}º", a)).collect();
+            partes.push(format!("{} ano", anos.join(", ")));
+        }
+        if partes.is_empty() {
+            "Todos".to_string()
+        } else {
+            partes.join(" · ")
+        }
+    }
+}
+
+/// Documento de uma pasta (tabela `documentos`), com o número de descargas.
+#[derive(Debug, Clone, FromRow)]
+pub struct Documento {
+    pub id: i64,
+    pub pasta_id: i64,
+    pub titulo: String,
+    pub ficheiro: String,      // Nome no diretório dos documentos
+    pub nome_original: String, // Nome com que é descarregado
+    pub tamanho: i64,          // Bytes
+    pub enviado_por: String,   // Nome de quem enviou
+    pub enviado_em: String,    // Hora local, 'dd/mm/aaaa HH:MM'
+    pub downloads: i64,
+}
+
+impl Documento {
+    /// "350 KB" ou "2.4 MB".
+    pub fn tamanho_legivel(&self) -> String {
+        if self.tamanho < 1024 * 1024 {
+            format!("{} KB", (self.tamanho + 1023) / 1024)
+        } else {
+            format!("{:.1} MB", self.tamanho as f64 / (1024.0 * 1024.0))
+        }
+    }
+}
+
+/// Uma descarga de um documento (tabela `documento_downloads`).
+#[derive(Debug, Clone, FromRow)]
+pub struct DownloadDocumento {
+    pub user_id: String,
+    pub nome: String,            // Nome do utilizador
+    pub descarregado_em: String, // Hora local, 'dd/mm/aaaa HH:MM'
+}
+
+/// Dados de uma pasta, já validados pelo handler (criação e alteração).
+#[derive(Debug, Clone)]
+pub struct DadosPasta {
+    pub categoria: String,
+    pub nome: String,
+    pub descricao: String,
+    pub roles: Vec<String>,
+    pub anos: Vec<i64>,
+}
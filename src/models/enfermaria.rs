@@ -0,0 +1,47 @@
+// src/models/enfermaria.rs
+use sqlx::FromRow;
+
+/// Vaga da enfermaria (tabela `consulta_vagas`), com a consulta marcada nela (as canceladas não contam).
+#[derive(Debug, Clone, FromRow)]
+pub struct VagaConsulta {
+    pub id: i64,
+    pub data: String, // 'YYYY-MM-DD'
+    pub hora: String, // 'HH:MM', hora local
+    pub duracao: i64, // Minutos
+    pub consulta_id: Option<i64>,
+    pub user_id: Option<String>,
+    pub name: Option<String>,
+    pub turma: Option<String>,
+    pub motivo: Option<String>,
+    pub estado: Option<String>, // Ver `enfermaria_service::ESTADOS`
+    pub notas: Option<String>,
+    pub baixa_id: Option<i64>,
+}
+
+impl VagaConsulta {
+    /// Ninguém marcou a vaga.
+    pub fn livre(&self) -> bool {
+        self.consulta_id.is_none()
+    }
+}
+
+/// Consulta marcada por um utilizador (tabela `consultas`), com o dia e a hora da vaga.
+#[derive(Debug, Clone, FromRow)]
+pub struct Consulta {
+    pub id: i64,
+    pub data: String,
+    pub hora: String,
+    pub user_id: String,
+    pub motivo: String,
+    pub estado: String,
+    pub marcada_em: String,          // UTC
+    pub atendida_por: Option<String>, // Nome de quem atendeu (ou registou a falta/cancelou)
+    pub baixa_id: Option<i64>,
+}
+
+impl Consulta {
+    /// Ainda por decidir (marcada ou a decorrer).
+    pub fn pendente(&self) -> bool {
+        self.estado == "marcada" || self.estado == "na_enfermaria"
+    }
+}
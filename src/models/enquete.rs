@@ -0,0 +1,112 @@
+// src/models/enquete.rs
+use sqlx::FromRow;
+
+/// Enquete interna (tabela `enquetes`), com o número de participantes.
+#[derive(Debug, Clone, FromRow)]
+pub struct Enquete {
+    pub id: i64,
+    pub pergunta: String,
+    pub descricao: String,
+    pub multipla: bool,                // Pode escolher várias opções
+    pub anonima: bool,                 // Os votos não ficam ligados ao utilizador
+    pub prazo: String,                 // 'YYYY-MM-DD': último dia de votação
+    pub roles: String,                 // Separadas por vírgulas (vazio = todas)
+    pub anos: String,                  // Separados por vírgulas (vazio = todos)
+    pub criada_por: String,            // Nome (ou ID)
+    pub criada_em: String,             // UTC
+    pub encerrada_em: Option<String>,  // UTC; None = segue o prazo
+    pub participantes: i64,
+}
+
+impl Enquete {
+    /// Aceita votos neste dia ('YYYY-MM-DD'): dentro do prazo e não encerrada.
+    pub fn aberta(&self, hoje: &str) -> bool {
+        self.encerrada_em.is_none() && self.prazo.as_str() >= hoje
+    }
+
+    /// Roles que podem votar (vazio = todas).
+    pub fn lista_roles(&self) -> Vec<&str> {
+        self.roles.split(',').map(str::trim).filter(|r| !r.is_empty()).collect()
+    }
+
+    /// Anos que podem votar (vazio = todos).
+    pub fn lista_anos(&self) -> Vec<i64> {
+        self.anos.split(',').filter_map(|a| a.trim().parse().ok()).collect()
+    }
+
+    /// Dirigida a quem tem estas roles e está neste ano (as duas regras têm de se cumprir).
+    pub fn visivel_para(&self, roles: &[String], ano: i64) -> bool {
+        let permitidas = self.lista_roles();
+        let anos = self.lista_anos();
+        (permitidas.is_empty() || roles.iter().any(|r| permitidas.contains(&r.as_str())))
+            && (anos.is_empty() || anos.contains(&ano))
+    }
+
+    /// "Todos" ou "Roles: x, y · 1º, 2º ano".
+    pub fn descricao_publico(&self) -> String {
+        let mut partes = Vec::new();
+        let roles = self.lista_roles();
+        if !roles.is_empty() {
+            partes.push(format!("Roles: {}", roles.join(", ")));
+        }
+        let anos = self.lista_anos();
+        if !anos.is_empty() {
+            let anos: Vec<String> = anos.iter().map(|a| format!("{}º", a)).collect();
+            partes.push(format!("{} ano", anos.join(", ")));
+        }
+        if partes.is_empty() {
+            "Todos".to_string()
+        } else {
+            partes.join(" · ")
+        }
+    }
+}
+
+/// Opção de resposta de uma enquete (tabela `enquete_opcoes`), com os votos apurados.
+#[derive(Debug, Clone, FromRow)]
+pub struct OpcaoEnquete {
+    pub id: i64,
+    pub texto: String,
+    pub votos: i64,
+}
+
+impl OpcaoEnquete {
+    /// Percentagem dos participantes que escolheram esta opção (0 sem participantes).
+    pub fn percentagem(&self, participantes: &i64) -> i64 {
+        if *participantes == 0 {
+            0
+        } else {
+            (self.votos * 100 + participantes / 2) / participantes
+        }
+    }
+}
+
+/// Enquete por votar no painel do utilizador, com as opções.
+#[derive(Debug, Clone)]
+pub struct EnquetePorVotar {
+    pub enquete: Enquete,
+    pub opcoes: Vec<OpcaoEnquete>,
+}
+
+/// Voto nominal na exportação (um por opção escolhida).
+#[derive(Debug, Clone, FromRow)]
+pub struct VotoEnquete {
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub opcao: String,
+    pub votou_em: String, // UTC
+}
+
+/// Dados de uma enquete, já validados pelo handler (criação).
+#[derive(Debug, Clone)]
+pub struct DadosEnquete {
+    pub pergunta: String,
+    pub descricao: String,
+    pub opcoes: Vec<String>,
+    pub multipla: bool,
+    pub anonima: bool,
+    pub prazo: String, // 'YYYY-MM-DD'
+    pub roles: Vec<String>,
+    pub anos: Vec<i64>,
+}
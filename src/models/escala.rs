@@ -1,6 +1,8 @@
 // src/models/escala.rs
+use crate::validation::{Validador, Validate};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use utoipa::ToSchema;
 
 // --- Estruturas que espelham as Tabelas da DB ---
 
@@ -11,6 +13,8 @@ pub struct Posto {
     pub genero_restricao: String,
     pub turmas_permitidas: String, // Ex: "1,2" (Guardado como texto)
     pub peso: i64,
+    pub grupo_id: Option<i64>, // Se definido, só membros deste grupo podem ocupar o posto
+    pub diurno: bool,          // Serviço de dia: não vai para quem tem aulas obrigatórias (ver horario_service)
 }
 
 impl Posto {
@@ -34,7 +38,7 @@ pub struct Troca {
     pub data_resposta: Option<String>,
 }
 
-#[derive(Debug, FromRow, Serialize, Deserialize)]
+#[derive(Debug, FromRow, Serialize, Deserialize, ToSchema)]
 pub struct Indisponibilidade {
     pub id: i64,
     pub user_id: String,
@@ -69,25 +73,119 @@ pub struct Alocacao {
     // (Opcional) Poderíamos trazer o status da escala aqui, mas faremos via JOIN
 }
 
+// --- Estruturas de leitura (API JSON /api/v1) ---
+
+/// Alocação com os nomes do militar e do posto.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct AlocacaoDetalhe {
+    pub id: String,
+    pub data: String,
+    pub user_id: String,
+    pub militar: String,
+    pub turma: String,
+    pub posto_id: i64,
+    pub posto: String,
+    pub is_punicao: bool,
+    pub tag: Option<String>,
+}
+
+/// Um dia da escala (RN/RD, rascunho ou publicado) e as suas alocações.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DiaEscala {
+    pub data: String,
+    pub tipo_rotina: String,
+    pub status: String, // 'Rascunho' ou 'Publicada'
+    pub alocacoes: Vec<AlocacaoDetalhe>,
+}
+
+/// Pedido de troca com a data e o posto do serviço em causa.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct TrocaDetalhe {
+    pub id: String,
+    pub status: String,
+    pub tipo: String, // 'Cobertura' ou 'Permuta'
+    pub motivo: Option<String>,
+    pub solicitante_id: String,
+    pub substituto_id: String,
+    pub alocacao_id: String,
+    pub alocacao_substituto_id: Option<String>,
+    pub data: String,
+    pub posto: String,
+    pub criado_em: Option<String>,
+    pub data_resposta: Option<String>,
+}
+
 // Payload para Gerar em Lote (Admin)
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct GerarPeriodoRequest {
     pub data_inicio: String, // YYYY-MM-DD
     pub data_fim: String,    // YYYY-MM-DD
 }
 
 // Payload para Publicar (Admin)
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct PublicarRequest {
     pub data_inicio: String,
     pub data_fim: String,
 }
 
 // Payload para Pedir Troca (User)
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct PedidoTrocaPayload {
     pub alocacao_id: String,
     pub substituto_id: String,
     pub motivo: String, // Obrigatório agora
     pub alocacao_substituto_id: Option<String>,
 }
+
+// Payload para responder a um pedido de troca (substituto)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RespostaTrocaPayload {
+    pub acao: String, // "aceitar" ou "recusar"
+}
+
+// Payload para registar uma indisponibilidade (API)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct IndisponibilidadePayload {
+    pub user_id: Option<String>, // Omisso = o próprio
+    pub data_inicio: String,
+    pub data_fim: String,
+    pub motivo: Option<String>,
+}
+
+// --- Validação dos payloads (antes de chegar ao serviço) ---
+
+impl Validate for GerarPeriodoRequest {
+    fn validate(&self, v: &mut Validador) {
+        v.periodo("data_inicio", &self.data_inicio, "data_fim", &self.data_fim);
+    }
+}
+
+impl Validate for PublicarRequest {
+    fn validate(&self, v: &mut Validador) {
+        v.periodo("data_inicio", &self.data_inicio, "data_fim", &self.data_fim);
+    }
+}
+
+impl Validate for PedidoTrocaPayload {
+    fn validate(&self, v: &mut Validador) {
+        v.obrigatorio("alocacao_id", &self.alocacao_id, 64);
+        v.obrigatorio("substituto_id", &self.substituto_id, 10);
+        v.obrigatorio("motivo", &self.motivo, 500);
+    }
+}
+
+impl Validate for RespostaTrocaPayload {
+    fn validate(&self, v: &mut Validador) {
+        v.opcao("acao", &self.acao, &["aceitar", "recusar"]);
+    }
+}
+
+impl Validate for IndisponibilidadePayload {
+    fn validate(&self, v: &mut Validador) {
+        v.periodo("data_inicio", &self.data_inicio, "data_fim", &self.data_fim);
+        if self.motivo.as_deref().is_some_and(|m| m.trim().chars().count() > 200) {
+            v.erro("motivo", "Máximo de 200 caracteres.");
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// src/models/faxina.rs
+use sqlx::FromRow;
+
+/// Área de limpeza da escala de faxina (tabela `faxina_areas`).
+#[derive(Debug, Clone, FromRow)]
+pub struct AreaFaxina {
+    pub id: i64,
+    pub nome: String,
+    pub descricao: String,
+    pub rotacao: String,         // 'quarto' ou 'turma'
+    pub genero_restricao: String, // 'M', 'F' ou 'Misto'
+    pub ativa: bool,
+}
+
+/// Atribuição de uma área numa semana, com os responsáveis (nomes, separados por vírgulas).
+#[derive(Debug, Clone, FromRow)]
+pub struct AtribuicaoFaxina {
+    pub semana: String, // Segunda-feira ('YYYY-MM-DD')
+    pub area: String,
+    pub descricao: String,
+    pub grupo: String,   // Ex: "Quarto 12", "Turma 2B"
+    pub membros: String, // Nomes
+    pub minha: bool,     // O utilizador que consulta é um dos responsáveis
+}
+
+/// Uma semana da escala de faxina, com as atribuições.
+#[derive(Debug, Clone)]
+pub struct SemanaFaxina {
+    pub semana: String, // Segunda-feira ('YYYY-MM-DD')
+    pub fim: String,    // Domingo
+    pub status: String, // 'Rascunho' ou 'Publicada'
+    pub atribuicoes: Vec<AtribuicaoFaxina>,
+}
+
+impl SemanaFaxina {
+    pub fn publicada(&self) -> bool {
+        self.status == "Publicada"
+    }
+}
+
+/// Quantas faxinas (semanas publicadas) fez cada quarto ou turma.
+#[derive(Debug, Clone, FromRow)]
+pub struct ContagemFaxina {
+    pub grupo: String,
+    pub total: i64,
+    pub ultima: String, // Segunda-feira da última semana
+}
@@ -0,0 +1,25 @@
+// src/models/foto.rs
+use sqlx::FromRow;
+
+/// Fotografia de um utilizador (tabela `user_fotos`): um JPEG em `fotos.diretorio`.
+#[derive(Debug, Clone, FromRow)]
+pub struct FotoUser {
+    pub user_id: String,
+    pub ficheiro: String,    // '<uuid>.jpg', gerado no envio
+    pub largura: i64,        // Píxeis
+    pub altura: i64,
+    pub componentes: i64,    // 1 = cinzentos, 3 = cor
+    pub tamanho: i64,        // Bytes
+    pub enviada_em: String,  // UTC (hora local em `foto_service::obter`)
+}
+
+impl FotoUser {
+    /// Tamanho do ficheiro para mostrar (ex: "240 KB").
+    pub fn tamanho_legivel(&self) -> String {
+        if self.tamanho < 1024 * 1024 {
+            format!("{} KB", (self.tamanho + 1023) / 1024)
+        } else {
+            format!("{:.1} MB", self.tamanho as f64 / (1024.0 * 1024.0))
+        }
+    }
+}
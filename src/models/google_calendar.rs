@@ -0,0 +1,10 @@
+// src/models/google_calendar.rs
+use sqlx::FromRow;
+
+/// Estado da ligação de um utilizador ao Google Calendar (tabela `google_calendar_ligacoes`).
+#[derive(Debug, Clone, FromRow)]
+pub struct GoogleCalendarLigacao {
+    pub ligado_em: String,               // Hora local, 'dd/mm/aaaa HH:MM'
+    pub sincronizado_em: Option<String>, // Hora local, 'dd/mm/aaaa HH:MM'
+    pub ultimo_erro: Option<String>,
+}
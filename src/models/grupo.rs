@@ -0,0 +1,32 @@
+// src/models/grupo.rs
+use sqlx::FromRow;
+
+/// Tipos de grupo aceites (coluna `grupos.tipo`).
+pub const TIPOS_GRUPO: &[&str] = &["pelotao", "companhia", "equipa"];
+
+/// Um grupo, com o número de membros (calculado na query).
+#[derive(Debug, Clone, FromRow)]
+pub struct Grupo {
+    pub id: i64,
+    pub nome: String,
+    pub tipo: String,
+    pub descricao: String,
+    pub membros: i64,
+}
+
+/// Resumo de um membro para as listagens do grupo.
+#[derive(Debug, Clone, FromRow)]
+pub struct GrupoMembro {
+    pub id: String,
+    pub name: String,
+    pub turma: String,
+    pub ano: i64,
+}
+
+/// Posto da escala com a eventual restrição de grupo.
+#[derive(Debug, Clone, FromRow)]
+pub struct PostoGrupo {
+    pub id: i64,
+    pub nome: String,
+    pub grupo_id: Option<i64>,
+}
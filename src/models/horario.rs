@@ -0,0 +1,49 @@
+// src/models/horario.rs
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// Aula do horário semanal de uma turma (tabela `aulas`).
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Aula {
+    pub id: i64,
+    pub turma: String,
+    /// 1 = segunda ... 7 = domingo
+    pub dia_semana: i64,
+    /// 'HH:MM'
+    pub hora_inicio: String,
+    /// 'HH:MM'
+    pub hora_fim: String,
+    pub disciplina: String,
+    pub sala: String,
+    pub instrutor: String,
+    /// Impede a escala em postos diurnos nesse dia
+    pub obrigatoria: bool,
+}
+
+impl Aula {
+    /// "08:00–09:30".
+    pub fn horario(&self) -> String {
+        format!("{}–{}", self.hora_inicio, self.hora_fim)
+    }
+}
+
+/// Dados de uma aula, já validados pelo handler (criação e alteração).
+#[derive(Debug, Clone)]
+pub struct DadosAula {
+    pub turma: String,
+    pub dia_semana: i64,
+    pub hora_inicio: String,
+    pub hora_fim: String,
+    pub disciplina: String,
+    pub sala: String,
+    pub instrutor: String,
+    pub obrigatoria: bool,
+}
+
+/// Posto da escala e se é um serviço de dia (coincide com as aulas).
+#[derive(Debug, Clone, FromRow)]
+pub struct PostoDiurno {
+    pub id: i64,
+    pub nome: String,
+    pub diurno: bool,
+}
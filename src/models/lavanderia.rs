@@ -0,0 +1,26 @@
+// src/models/lavanderia.rs
+use sqlx::FromRow;
+
+/// Saco entregue na lavanderia (tabela `lavanderia_sacos`), com o nome e a turma do utilizador.
+#[derive(Debug, Clone, FromRow)]
+pub struct SacoLavanderia {
+    pub id: i64,
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub etiqueta: String,
+    pub observacoes: String,
+    pub entregue_em: String,          // UTC
+    pub recebido_por: String,         // Nome (ou ID)
+    pub pronto_em: Option<String>,    // UTC; None = ainda na lavagem
+    pub pronto_por: Option<String>,
+    pub retirado_em: Option<String>,  // UTC; None = na lavanderia
+    pub entregue_por: Option<String>,
+}
+
+impl SacoLavanderia {
+    /// Lavado e à espera de ser retirado.
+    pub fn pronto(&self) -> bool {
+        self.pronto_em.is_some() && self.retirado_em.is_none()
+    }
+}
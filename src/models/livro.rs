@@ -0,0 +1,32 @@
+// src/models/livro.rs
+use sqlx::FromRow;
+
+/// Ocorrência registada no livro (tabela `livro_ocorrencias`), com o nome do autor.
+#[derive(Debug, Clone, FromRow)]
+pub struct Ocorrencia {
+    pub id: i64,
+    pub data: String, // 'YYYY-MM-DD', dia do serviço
+    pub hora: String, // 'HH:MM'
+    pub autor_id: String,
+    pub autor: String,
+    pub texto: String,
+    pub criado_em: String,            // UTC
+    pub corrigido_em: Option<String>, // UTC
+}
+
+/// Fecho de um dia do livro: a passagem de serviço (tabela `livro_fechos`), com os nomes.
+#[derive(Debug, Clone, FromRow)]
+pub struct FechoLivro {
+    pub fechado_por: String,
+    pub recebido_por: Option<String>,
+    pub passagem: String,
+    pub fechado_em: String, // UTC
+}
+
+/// Dia do livro na lista dos dias recentes.
+#[derive(Debug, Clone, FromRow)]
+pub struct ResumoDiaLivro {
+    pub data: String,
+    pub ocorrencias: i64,
+    pub fechado: bool,
+}
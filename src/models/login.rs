@@ -0,0 +1,55 @@
+// src/models/login.rs
+use serde::Deserialize;
+use sqlx::FromRow;
+
+/// Uma linha da tabela `login_history`.
+#[derive(Debug, Clone, FromRow)]
+pub struct LoginRegisto {
+    pub user_id: String,
+    pub ip: String,
+    pub user_agent: Option<String>,
+    pub sucesso: bool,
+    pub motivo: Option<String>,
+    pub criado_em: String, // UTC, 'YYYY-MM-DD HH:MM:SS'
+}
+
+/// Filtros da página /admin/logins (todos opcionais, vindos da query string).
+#[derive(Debug, Default, Deserialize)]
+pub struct LoginFilter {
+    pub user: Option<String>,
+    pub ip: Option<String>,
+    pub resultado: Option<String>, // "sucesso" | "falha"
+    pub de: Option<String>,  // YYYY-MM-DD
+    pub ate: Option<String>, // YYYY-MM-DD
+}
+
+/// Uma sessão ativa de um utilizador (tabela `user_sessoes` + `sessions` ainda válida).
+#[derive(Debug, Clone, FromRow)]
+pub struct SessaoAtiva {
+    pub id: i64,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub criado_em: String,        // Hora local, 'dd/mm/aaaa HH:MM'
+    pub ultima_atividade: String, // Hora local, 'dd/mm/aaaa HH:MM'
+    pub atual: bool,              // É a sessão do pedido atual
+}
+
+/// Conta bloqueada pela administração (tabela `bloqueios_manuais`).
+#[derive(Debug, Clone, FromRow)]
+pub struct BloqueioManual {
+    pub user_id: String,
+    pub name: Option<String>,
+    pub motivo: String,
+    pub bloqueado_por: String,
+    pub bloqueado_em: String, // Hora local, 'dd/mm/aaaa HH:MM'
+    pub ate: Option<String>,  // Hora local; None = sem data de fim
+}
+
+/// ID de utilizador ou IP bloqueado pelo limite de tentativas falhadas.
+#[derive(Debug, Clone)]
+pub struct BloqueioAutomatico {
+    pub chave: String,        // ID de utilizador ou IP
+    pub name: Option<String>, // Nome (só para IDs que existem)
+    pub falhas: i64,          // Falhas dentro da janela
+    pub minutos_restantes: i64,
+}
@@ -0,0 +1,138 @@
+// src/models/loja.rs
+use sqlx::FromRow;
+
+pub const PAGAMENTO_DINHEIRO: &str = "dinheiro";
+pub const PAGAMENTO_CARTAO: &str = "cartao";
+pub const PAGAMENTO_CONTA: &str = "conta"; // Débito na conta corrente do comprador
+
+/// Formas de pagamento aceites no ponto de venda (valor, texto).
+pub const PAGAMENTOS: &[(&str, &str)] =
+    &[(PAGAMENTO_DINHEIRO, "Dinheiro"), (PAGAMENTO_CARTAO, "Cartão"), (PAGAMENTO_CONTA, "Conta corrente")];
+
+/// Texto de uma forma de pagamento (o próprio valor, se for desconhecido).
+pub fn nome_pagamento(pagamento: &str) -> &str {
+    PAGAMENTOS.iter().find(|(valor, _)| *valor == pagamento).map(|(_, nome)| *nome).unwrap_or(pagamento)
+}
+
+/// Produto da loja (tabela `produtos`).
+#[derive(Debug, Clone, FromRow)]
+pub struct Produto {
+    pub id: i64,
+    pub nome: String,
+    pub preco: i64, // Centavos
+    pub stock: i64,
+    pub ativo: bool,
+}
+
+/// Venda, com os nomes do comprador e do operador e o resumo dos itens.
+#[derive(Debug, Clone, FromRow)]
+pub struct Venda {
+    pub id: i64,
+    pub user_id: Option<String>,
+    pub comprador: Option<String>, // Nome do comprador (None = venda ao balcão)
+    pub operador: String,
+    pub total: i64, // Centavos
+    pub pagamento: String,
+    pub criado_em: String, // UTC
+    pub anulada: bool,
+    pub itens: String, // Ex: "2× Refrigerante, 1× Chocolate"
+}
+
+impl Venda {
+    pub fn forma_pagamento(&self) -> &str {
+        nome_pagamento(&self.pagamento)
+    }
+}
+
+/// Vendas de um produto num dia.
+#[derive(Debug, Clone, FromRow)]
+pub struct VendasProduto {
+    pub nome: String,
+    pub quantidade: i64,
+    pub total: i64, // Centavos
+}
+
+/// Vendas de um dia por forma de pagamento.
+#[derive(Debug, Clone, FromRow)]
+pub struct VendasPagamento {
+    pub pagamento: String,
+    pub vendas: i64,
+    pub total: i64, // Centavos
+}
+
+impl VendasPagamento {
+    pub fn forma_pagamento(&self) -> &str {
+        nome_pagamento(&self.pagamento)
+    }
+}
+
+/// Relatório de vendas de um dia (sem as anuladas, exceto na lista de vendas).
+#[derive(Debug, Clone)]
+pub struct RelatorioVendas {
+    pub data: String, // 'YYYY-MM-DD', no fuso da aplicação
+    pub vendas: Vec<Venda>,
+    pub por_produto: Vec<VendasProduto>,
+    pub por_pagamento: Vec<VendasPagamento>,
+    pub total: i64,
+    pub n_vendas: i64,
+}
+
+// --- CONTA CORRENTE ---
+
+pub const MOVIMENTO_CARREGAMENTO: &str = "carregamento";
+pub const MOVIMENTO_COMPRA: &str = "compra";
+pub const MOVIMENTO_ANULACAO: &str = "anulacao";
+
+/// Movimento da conta corrente de um utilizador (tabela `conta_movimentos`), com o resumo dos itens
+/// da venda (compras e anulações) e o nome do operador.
+#[derive(Debug, Clone, FromRow)]
+pub struct MovimentoConta {
+    pub tipo: String,
+    pub valor: i64, // Centavos: positivo = crédito, negativo = débito
+    pub venda_id: Option<i64>,
+    pub itens: String,
+    pub operador: String,
+    pub criado_em: String, // UTC
+}
+
+impl MovimentoConta {
+    pub fn descricao(&self) -> String {
+        match (self.tipo.as_str(), self.venda_id) {
+            (MOVIMENTO_CARREGAMENTO, _) => "Carregamento".to_string(),
+            (MOVIMENTO_COMPRA, Some(venda)) => format!("Compra #{}", venda),
+            (MOVIMENTO_ANULACAO, Some(venda)) => format!("Anulação da compra #{}", venda),
+            (tipo, _) => tipo.to_string(),
+        }
+    }
+}
+
+/// Extrato mensal da conta corrente de um utilizador.
+#[derive(Debug, Clone)]
+pub struct ExtratoConta {
+    pub mes: String, // 'YYYY-MM'
+    pub saldo_inicial: i64,
+    pub movimentos: Vec<MovimentoConta>, // Do mais antigo para o mais recente
+    pub creditos: i64,
+    pub debitos: i64, // Soma dos débitos (positiva)
+    pub saldo_final: i64,
+}
+
+impl ExtratoConta {
+    /// Mês do extrato como "10/2026".
+    pub fn mes_formatado(&self) -> String {
+        match self.mes.split_once('-') {
+            Some((ano, mes)) => format!("{}/{}", mes, ano),
+            None => self.mes.clone(),
+        }
+    }
+}
+
+/// Saldo da conta corrente de um utilizador (relatório de saldos negativos).
+#[derive(Debug, Clone, FromRow)]
+pub struct SaldoConta {
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub saldo: i64,
+    pub ultimo_movimento: String, // UTC
+}
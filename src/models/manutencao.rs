@@ -0,0 +1,10 @@
+// src/models/manutencao.rs
+use sqlx::FromRow;
+
+/// Modo de manutenção ativo (tabela `manutencao`).
+#[derive(Debug, Clone, FromRow)]
+pub struct Manutencao {
+    pub motivo: String,
+    pub ativada_por: String,
+    pub ativada_em: String, // UTC, 'YYYY-MM-DD HH:MM:SS'
+}
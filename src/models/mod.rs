@@ -1,3 +1,48 @@
 pub mod user;
 pub mod presence;
-pub mod escala;
\ No newline at end of file
+pub mod escala;
+pub mod audit;
+pub mod permission;
+pub mod grupo;
+pub mod atributo;
+pub mod login;
+pub mod notificacao;
+pub mod api_token;
+pub mod webhook;
+pub mod telegram;
+pub mod backup;pub mod google_calendar;
+pub mod tarefa;
+pub mod organizacao;
+pub mod retencao;
+pub mod manutencao;
+pub mod contabilidade;
+pub mod preferencias;
+pub mod arranchamento;
+pub mod cardapio;
+pub mod loja;
+pub mod livro;
+pub mod revista;
+pub mod quarto;
+pub mod cautela;
+pub mod baixa;
+pub mod visitante;
+pub mod agenda;
+pub mod documento;
+pub mod disciplina;
+pub mod antiguidade;
+pub mod horario;
+pub mod prova;
+pub mod uniforme;
+pub mod portaria;
+pub mod sugestao;
+pub mod faxina;
+pub mod conceito;
+pub mod comitiva;
+pub mod chamada;
+pub mod claviculario;
+pub mod tfm;
+pub mod enfermaria;
+pub mod biblioteca;
+pub mod lavanderia;
+pub mod enquete;
+pub mod foto;
@@ -0,0 +1,29 @@
+// src/models/notificacao.rs
+use serde::Deserialize;
+use sqlx::FromRow;
+
+/// Uma notificação de um utilizador (tabela `notificacoes`).
+#[derive(Debug, Clone, FromRow)]
+pub struct Notificacao {
+    pub id: i64,
+    pub tipo: String,
+    pub titulo: String,
+    pub mensagem: String,
+    pub criada_em: String, // Hora local, 'dd/mm/aaaa HH:MM'
+    pub lida: bool,
+}
+
+impl Notificacao {
+    /// Ícone do tipo (painel e /user/notificacoes).
+    pub fn icone(&self) -> &'static str {
+        crate::services::notificacao_service::icone(&self.tipo)
+    }
+}
+
+/// Filtros da página /user/notificacoes (query string).
+#[derive(Debug, Default, Deserialize)]
+pub struct NotificacoesFilter {
+    pub tipo: Option<String>,
+    #[serde(default)]
+    pub por_ler: bool,
+}
@@ -0,0 +1,13 @@
+// src/models/organizacao.rs
+use sqlx::FromRow;
+
+/// Organização (companhia/unidade) servida pela instância, com o número de utilizadores (calculado na query).
+#[derive(Debug, Clone, FromRow)]
+pub struct Organizacao {
+    pub id: i64,
+    pub codigo: String,
+    pub nome: String,
+    pub ativa: bool,
+    pub criado_em: String,
+    pub utilizadores: i64,
+}
@@ -0,0 +1,12 @@
+// src/models/permission.rs
+use sqlx::FromRow;
+
+/// Uma role definida na tabela `roles`.
+#[derive(Debug, Clone, FromRow)]
+pub struct RoleDef {
+    pub nome: String,
+    pub descricao: String,
+    pub permanente: bool, // Atribuível no perfil do utilizador
+    pub temporaria: bool, // Atribuível por período
+    pub sistema: bool,    // Não pode ser apagada
+}
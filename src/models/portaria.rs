@@ -0,0 +1,16 @@
+// src/models/portaria.rs
+use sqlx::FromRow;
+
+/// Entrada de uma viatura ou entrega na portaria (tabela `portaria`), com o nome dos operadores.
+#[derive(Debug, Clone, FromRow)]
+pub struct MovimentoPortaria {
+    pub id: i64,
+    pub tipo: String, // 'viatura' ou 'entrega'
+    pub matricula: String,
+    pub condutor: String,
+    pub motivo: String,
+    pub entrada_em: String,       // UTC
+    pub entrada_por: String,
+    pub saida_em: Option<String>, // UTC; None = ainda dentro
+    pub saida_por: Option<String>,
+}
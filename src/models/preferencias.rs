@@ -0,0 +1,26 @@
+// src/models/preferencias.rs
+use sqlx::FromRow;
+
+/// Preferências da interface de um utilizador (tabela `user_preferences`; ver `preferencias_service`).
+#[derive(Debug, Clone, FromRow)]
+pub struct Preferencias {
+    pub tema: String, // 'claro' | 'escuro' | 'sistema'
+    pub turma_presenca: Option<i64>,
+    pub por_pagina: Option<i64>,
+    pub avisos_escala: bool,
+    pub avisos_troca: bool,
+    pub avisos_lembrete: bool,
+}
+
+impl Default for Preferencias {
+    fn default() -> Self {
+        Self {
+            tema: "claro".to_string(),
+            turma_presenca: None,
+            por_pagina: None,
+            avisos_escala: true,
+            avisos_troca: true,
+            avisos_lembrete: true,
+        }
+    }
+}
@@ -1,10 +1,11 @@
 // src/models/presence.rs
-use chrono::{DateTime, Local}; // Usaremos DateTime<Local> para lógica interna
+use chrono::{DateTime, Utc}; // Datas em UTC; convertidas para o fuso da aplicação só ao mostrar (ver tempo)
 use serde::{Deserialize, Serialize}; // Para possíveis usos em JSON (ex: WebSockets)
 use sqlx::FromRow; // Para ler da base de dados
+use utoipa::ToSchema; // Documentação da API (OpenAPI)
 
 /// Representa uma linha lida diretamente da tabela `presenca`.
-/// As datas são guardadas como TEXT (String) na DB (formato ISO 8601/RFC3339).
+/// As datas são guardadas como TEXT (String) na DB (RFC3339 em UTC, ver `tempo::carimbo_utc`).
 #[derive(Debug, Clone, Default, FromRow)]
 pub struct PresenceEntry {
     pub user_id: String,
@@ -16,19 +17,24 @@ pub struct PresenceEntry {
 
 /// Representa os dados combinados de um utilizador e o seu estado de presença,
 /// formatado para exibição ou uso na lógica da aplicação.
-#[derive(Debug, Clone, Serialize, Deserialize)] // Serialize/Deserialize úteis para WebSockets
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)] // Serialize/Deserialize úteis para WebSockets
 pub struct PresencePerson {
     // Dados básicos do utilizador (virão da struct User)
     pub id: String,
     pub nome: String,
     pub turma: String,
     pub ano: i64, // Mantemos i64 por consistência com a DB
+    pub quarto: Option<String>,   // Alojamento (None = sem quarto atribuído)
+    pub corredor: Option<String>,
+    pub baixa: Option<String>,    // Restrições da baixa médica em vigor (None = sem baixa)
+    pub comitiva: Option<String>, // Comitiva em curso (fora sem marcação de saída; ver comitiva_service)
+    pub enfermaria: Option<String>, // Na enfermaria desde esta hora (consulta a decorrer; ver enfermaria_service)
     // ... (outros campos do User se necessário, ex: curso, genero)
 
     // Dados de presença processados
-    // Usamos DateTime<Local> para comparações e exibição formatada
-    pub ultima_saida: Option<DateTime<Local>>,
-    pub ultimo_retorno: Option<DateTime<Local>>,
+    // Em UTC, para comparações; a exibição converte com `tempo::local`
+    pub ultima_saida: Option<DateTime<Utc>>,
+    pub ultimo_retorno: Option<DateTime<Utc>>,
     pub usuario_saida: Option<String>, // Pode ser ID ou nome (depende da implementação)
     pub usuario_retorno: Option<String>, // Pode ser ID ou nome
 
@@ -37,7 +43,7 @@ pub struct PresencePerson {
 }
 
 /// Estrutura para as estatísticas de presença (ex: para uma turma).
-#[derive(Debug, Clone, Default, Serialize, Deserialize)] // Útil para WebSockets
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)] // Útil para WebSockets
 pub struct PresenceStats {
     pub fora: usize,  // Quantidade de pessoas fora
     pub dentro: usize, // Quantidade de pessoas a bordo
@@ -0,0 +1,16 @@
+// src/models/prova.rs
+use sqlx::FromRow;
+
+/// Prova de uma turma (tabela `provas`), com as indisponibilidades que criou.
+#[derive(Debug, Clone, FromRow)]
+pub struct Prova {
+    pub id: i64,
+    pub turma: String,
+    pub data: String, // 'YYYY-MM-DD'
+    pub disciplina: String,
+    pub observacoes: String,
+    pub registada_por: String, // Nome de quem registou
+    pub registada_em: String,  // UTC
+    /// Utilizadores fora da escala na véspera e no dia (indisponibilidades da prova)
+    pub bloqueados: i64,
+}
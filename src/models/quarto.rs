@@ -0,0 +1,48 @@
+// src/models/quarto.rs
+use crate::models::presence::PresencePerson;
+use sqlx::FromRow;
+
+/// Ocupante de um quarto (tabela `quarto_ocupantes`), com o nome e a turma.
+#[derive(Debug, Clone, FromRow)]
+pub struct Ocupante {
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub quarto_id: i64,
+    pub cama: i64,
+}
+
+/// Quarto da organização (tabela `quartos`), com os ocupantes pela ordem das camas.
+#[derive(Debug, Clone)]
+pub struct Quarto {
+    pub id: i64,
+    pub nome: String,
+    pub corredor: String, // Vazio = sem corredor
+    pub capacidade: i64,
+    pub ativo: bool,
+    pub ocupantes: Vec<Ocupante>,
+}
+
+impl Quarto {
+    /// Camas ainda sem ocupante (números de 1 a `capacidade`).
+    pub fn camas_livres(&self) -> Vec<i64> {
+        (1..=self.capacidade).filter(|c| !self.ocupantes.iter().any(|o| o.cama == *c)).collect()
+    }
+}
+
+/// Onde dorme um utilizador: quarto e corredor.
+#[derive(Debug, Clone, FromRow)]
+pub struct Alojamento {
+    pub user_id: String,
+    pub quarto: String,
+    pub corredor: String,
+}
+
+/// Linha do relatório de pernoite: os ocupantes de um quarto e os que estão fora.
+#[derive(Debug, Clone)]
+pub struct PernoiteQuarto {
+    pub corredor: String,
+    pub quarto: Option<String>, // None = utilizadores sem quarto atribuído
+    pub total: usize,
+    pub fora: Vec<PresencePerson>,
+}
@@ -0,0 +1,21 @@
+// src/models/retencao.rs
+use sqlx::FromRow;
+
+/// Política de retenção de uma tabela e o que a próxima limpeza apagaria (relatório de /admin/retencao).
+#[derive(Debug, Clone)]
+pub struct PoliticaRetencao {
+    pub tabela: &'static str,
+    pub descricao: &'static str,
+    pub dias: i64,              // 0 = guardar para sempre
+    pub limite: Option<String>, // Apaga o que for anterior a esta data (UTC, 'YYYY-MM-DD HH:MM:SS')
+    pub a_apagar: i64,
+}
+
+/// Total por mês de registos já apagados (tabela `retencao_resumos`).
+#[derive(Debug, Clone, FromRow)]
+pub struct ResumoRetencao {
+    pub tabela: String,
+    pub mes: String, // 'YYYY-MM'
+    pub chave: String,
+    pub total: i64,
+}
@@ -0,0 +1,78 @@
+// src/models/revista.rs
+use sqlx::FromRow;
+
+/// Item de uma lista de verificação (tabela `revista_itens`).
+#[derive(Debug, Clone, FromRow)]
+pub struct ItemRevista {
+    pub id: i64,
+    pub lista_id: i64,
+    pub descricao: String,
+    pub pontos: i64,
+    pub ativo: bool,
+}
+
+/// Lista de verificação das revistas, com os itens pela ordem.
+#[derive(Debug, Clone)]
+pub struct ListaRevista {
+    pub id: i64,
+    pub nome: String,
+    pub ativa: bool,
+    pub itens: Vec<ItemRevista>,
+}
+
+impl ListaRevista {
+    /// Pontuação máxima (soma dos pontos dos itens ativos).
+    pub fn maximo(&self) -> i64 {
+        self.itens.iter().filter(|i| i.ativo).map(|i| i.pontos).sum()
+    }
+}
+
+/// Revista registada, com os nomes e os itens em falta.
+#[derive(Debug, Clone, FromRow)]
+pub struct RevistaResumo {
+    pub data: String,
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub quarto: String,
+    pub lista: String,
+    pub pontuacao: i64,
+    pub maximo: i64,
+    pub falhas: String, // Ex: "Cama por fazer, Chão sujo"
+    pub observacoes: String,
+    pub inspetor: String,
+}
+
+impl RevistaResumo {
+    /// Pontuação em percentagem (0 a 100).
+    pub fn percentagem(&self) -> i64 {
+        if self.maximo > 0 { self.pontuacao * 100 / self.maximo } else { 100 }
+    }
+}
+
+/// Resultados das revistas de uma turma num período (ordem de mérito).
+#[derive(Debug, Clone, FromRow)]
+pub struct EstatisticaTurma {
+    pub turma: String,
+    pub revistas: i64,
+    pub inspecionados: i64, // Utilizadores diferentes
+    pub media: f64,         // Percentagem média (pontos obtidos / pontos possíveis)
+    pub falhas: i64,
+}
+
+impl EstatisticaTurma {
+    pub fn media_formatada(&self) -> String {
+        format!("{:.1}%", self.media)
+    }
+}
+
+/// Item em falta repetidamente no mesmo utilizador.
+#[derive(Debug, Clone, FromRow)]
+pub struct Reincidencia {
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub item: String,
+    pub vezes: i64,
+    pub ultima: String, // 'YYYY-MM-DD'
+}
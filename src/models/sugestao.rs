@@ -0,0 +1,18 @@
+// src/models/sugestao.rs
+use sqlx::FromRow;
+
+/// Sugestão da caixa de sugestões (tabela `sugestoes`). O pseudónimo das anónimas nunca é lido.
+#[derive(Debug, Clone, FromRow)]
+pub struct Sugestao {
+    pub id: i64,
+    pub assunto: String,
+    pub texto: String,
+    pub estado: String,            // 'recebida', 'em_analise' ou 'respondida'
+    pub autor_id: Option<String>,  // None = anónima
+    pub autor: Option<String>,     // Nome do autor (None = anónima)
+    pub resposta: String,
+    pub publicada: bool,
+    pub respondida_por: Option<String>, // Nome
+    pub respondida_em: Option<String>,  // UTC
+    pub criada_em: String,              // UTC
+}
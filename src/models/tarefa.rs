@@ -0,0 +1,31 @@
+// src/models/tarefa.rs
+use sqlx::FromRow;
+
+/// Última execução de uma tarefa periódica (tabela `tarefas_agendadas`).
+#[derive(Debug, Clone, Default, FromRow)]
+pub struct RegistoTarefa {
+    pub ultima_execucao: Option<String>, // UTC (RFC3339)
+    pub duracao_ms: Option<i64>,
+    pub estado: Option<String>, // 'ok' ou 'erro'
+    pub resultado: Option<String>,
+    pub execucoes: i64,
+    pub falhas: i64,
+    pub proxima_execucao: Option<String>, // UTC (RFC3339)
+}
+
+/// Uma tarefa registada no agendador e o seu estado, para a página de administração.
+#[derive(Debug, Clone)]
+pub struct TarefaEstado {
+    pub nome: String,
+    pub descricao: String,
+    pub expressao: String, // Expressão cron (com segundos), no fuso da aplicação
+    pub jitter_segundos: u64,
+    pub em_curso: bool,
+    pub ultima_execucao: Option<String>, // Hora local, 'dd/mm/aaaa HH:MM'
+    pub proxima_execucao: Option<String>, // Hora local, 'dd/mm/aaaa HH:MM'
+    pub duracao_ms: Option<i64>,
+    pub estado: Option<String>,
+    pub resultado: Option<String>,
+    pub execucoes: i64,
+    pub falhas: i64,
+}
@@ -0,0 +1,11 @@
+// src/models/telegram.rs
+use sqlx::FromRow;
+
+/// Estado da ligação de um utilizador ao bot do Telegram (tabela `telegram_ligacoes`).
+#[derive(Debug, Clone, FromRow)]
+pub struct TelegramLigacao {
+    pub ligado: bool,
+    pub ligado_em: Option<String>,        // Hora local, 'dd/mm/aaaa HH:MM'
+    pub codigo: Option<String>,           // Código por usar (só enquanto não expirar)
+    pub codigo_expira_em: Option<String>, // Hora local, 'HH:MM'
+}
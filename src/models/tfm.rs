@@ -0,0 +1,40 @@
+// src/models/tfm.rs
+use sqlx::FromRow;
+
+/// Resultado de um exercício do TFM (tabela `tfm_resultados`), com o nome e a turma do utilizador.
+#[derive(Debug, Clone, FromRow)]
+pub struct ResultadoTfm {
+    pub id: i64,
+    pub user_id: String,
+    pub name: String,
+    pub turma: String,
+    pub data: String,      // 'YYYY-MM-DD'
+    pub exercicio: String, // Ver `tfm_service::EXERCICIOS`
+    pub marca: String,     // Desempenho bruto (pode ser vazio)
+    pub pontuacao: i64,    // 0 a 100
+    pub aprovado: bool,
+    pub observacoes: String,
+    pub registado_por: String, // Nome de quem registou
+}
+
+/// Estatística de uma turma num exercício (última prova de cada utilizador no ano).
+#[derive(Debug, Clone, FromRow)]
+pub struct EstatisticaTfm {
+    pub turma: String,
+    pub exercicio: String,
+    pub avaliados: i64, // Utilizadores com resultado
+    pub aprovados: i64,
+    pub media: f64, // Pontuação média
+    pub minima: i64,
+    pub maxima: i64,
+}
+
+impl EstatisticaTfm {
+    /// Percentagem de aprovados (arredondada).
+    pub fn percentagem_aprovados(&self) -> i64 {
+        if self.avaliados == 0 {
+            return 0;
+        }
+        (self.aprovados as f64 * 100.0 / self.avaliados as f64).round() as i64
+    }
+}
@@ -0,0 +1,12 @@
+// src/models/uniforme.rs
+use sqlx::FromRow;
+
+/// Uniforme definido para um dia (tabela `uniformes_dia`).
+#[derive(Debug, Clone, FromRow)]
+pub struct UniformeDia {
+    pub data: String, // 'YYYY-MM-DD'
+    pub uniforme: String,
+    pub observacoes: String,
+    pub definido_por: String, // Nome de quem definiu
+    pub definido_em: String,  // UTC
+}
@@ -1,19 +1,25 @@
 use chrono::NaiveDateTime;
 // src/models/user.rs
 // use chrono::NaiveDateTime; // Remover esta linha se não usar mais NaiveDateTime aqui
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use utoipa::ToSchema;
 
 // Representa um utilizador lido da tabela 'users'
 #[derive(Debug, Clone, FromRow)]
 pub struct User {
     pub id: String,
     pub password_hash: String,
+    pub password_esquema: String, // 'bcrypt' (legado), 'argon2id' ou 'argon2id+pepper'
     pub name: String,
     pub turma: String,
     pub ano: i64, // SQLite INTEGER -> i64
     pub curso: String,
     pub genero: String, // "M" ou "F"
+    pub email: Option<String>,
+    pub telefone: Option<String>,
+    pub ativo: bool, // false = arquivado (ex: após a passagem de ano)
+    pub organizacao_id: i64,
     pub updated_at: Option<NaiveDateTime>,
     pub created_at: Option<NaiveDateTime>,
 }
@@ -24,10 +30,123 @@ pub struct LoginForm {
     #[serde(rename = "username")] // Mapeia do HTML 'username'
     pub id: String,               // Para o campo 'id' do User/DB
     pub password: String,
+    // Resposta do widget CAPTCHA (só presente quando é exigido; o nome do campo depende do fornecedor)
+    #[serde(default, rename = "h-captcha-response")]
+    pub hcaptcha_response: String,
+    #[serde(default, rename = "cf-turnstile-response")]
+    pub turnstile_response: String,
+}
+
+impl LoginForm {
+    /// Resposta do CAPTCHA submetida, seja qual for o fornecedor.
+    pub fn resposta_captcha(&self) -> &str {
+        if self.hcaptcha_response.is_empty() { &self.turnstile_response } else { &self.hcaptcha_response }
+    }
 }
 
 #[derive(Debug, Clone, FromRow)]
 pub struct UserRole {
     pub user_id: String,
     pub role: String,
-}
\ No newline at end of file
+}
+
+/// Atribuição de role temporária (tabela `user_temporary_roles`), com o nome do utilizador
+/// para exibição na página de administração.
+#[derive(Debug, Clone, FromRow)]
+pub struct TemporaryRoleGrant {
+    pub id: i64,
+    pub user_id: String,
+    pub user_name: String,
+    pub role: String,
+    pub start_datetime: String, // RFC3339 (UTC)
+    pub end_datetime: String,   // RFC3339 (UTC)
+}
+
+/// Pré-visualização (dry-run) da passagem de ano.
+#[derive(Debug, Clone, Default)]
+pub struct RolloverPreview {
+    pub promocoes: Vec<(i64, i64)>,     // (ano atual, nº de utilizadores) que passam para ano + 1
+    pub arquivados: Vec<RolloverUser>,  // Turma finalista, a arquivar
+}
+
+/// Utilizador afetado pela passagem de ano (listagem da pré-visualização).
+#[derive(Debug, Clone, FromRow)]
+pub struct RolloverUser {
+    pub id: String,
+    pub name: String,
+    pub turma: String,
+    pub ano: i64,
+}
+/// Sugestão devolvida pela pesquisa de utilizadores (autocomplete dos formulários).
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct UserSugestao {
+    pub id: String,
+    pub name: String,
+    pub turma: String,
+}
+
+/// Utilizador exposto pela API JSON (/api/v1): sem o hash nem o esquema da senha.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UserApi {
+    pub id: String,
+    pub name: String,
+    pub turma: String,
+    pub ano: i64,
+    pub curso: String,
+    pub genero: String,
+    pub email: Option<String>,
+    pub telefone: Option<String>,
+    pub ativo: bool,
+}
+
+impl From<User> for UserApi {
+    fn from(u: User) -> Self {
+        Self {
+            id: u.id,
+            name: u.name,
+            turma: u.turma,
+            ano: u.ano,
+            curso: u.curso,
+            genero: u.genero,
+            email: u.email,
+            telefone: u.telefone,
+            ativo: u.ativo,
+        }
+    }
+}
+
+/// Dados editáveis de um utilizador, já validados pelo handler (ver `user_service::update_user`).
+#[derive(Debug, Clone)]
+pub struct DadosUser<'a> {
+    pub name: &'a str,
+    pub turma: &'a str,
+    pub ano: i64,
+    pub curso: &'a str,
+    pub genero: &'a str,
+    pub email: Option<&'a str>,
+    pub telefone: Option<&'a str>,
+}
+
+/// Registos transferidos na fusão de um utilizador duplicado com o canónico.
+#[derive(Debug, Clone, Default)]
+pub struct FusaoResumo {
+    pub alocacoes: u64,
+    pub trocas: u64,
+    pub dividas: u64,
+    pub indisponibilidades: u64,
+    pub presenca: u64,
+    pub roles: u64,
+    pub roles_temporarias: u64,
+    pub grupos: u64,
+}
+
+impl FusaoResumo {
+    /// Texto curto para feedback e auditoria.
+    pub fn descricao(&self) -> String {
+        format!(
+            "alocações: {}; trocas: {}; dívidas: {}; indisponibilidades: {}; presença: {}; roles: {}; roles temporárias: {}; grupos: {}",
+            self.alocacoes, self.trocas, self.dividas, self.indisponibilidades,
+            self.presenca, self.roles, self.roles_temporarias, self.grupos
+        )
+    }
+}
@@ -0,0 +1,36 @@
+// src/models/visitante.rs
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Visita (tabela `visitantes`), com o nome do anfitrião e dos operadores.
+#[derive(Debug, Clone, FromRow)]
+pub struct Visita {
+    pub id: i64,
+    pub nome: String,
+    pub documento: String,
+    pub anfitriao_id: Option<String>, // None = anfitrião apagado
+    pub anfitriao: Option<String>,    // Nome do anfitrião
+    pub motivo: String,
+    pub entrada_em: String,           // UTC
+    pub entrada_por: String,
+    pub saida_em: Option<String>,     // UTC; None = ainda dentro
+    pub saida_por: Option<String>,
+}
+
+/// Ação enviada pelo cliente via WebSocket (página dos visitantes).
+#[derive(Debug, Deserialize)]
+pub struct VisitanteSocketAction {
+    pub action: String, // "saida"
+    pub id: i64,        // ID da visita
+}
+
+/// Atualização enviada a todas as páginas dos visitantes abertas da organização.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct VisitanteSocketUpdate {
+    pub success: bool,
+    pub message: String,
+    pub acao: String,       // "entrada" ou "saida"
+    pub id: i64,            // ID da visita
+    pub linha_html: String, // Entrada: a linha da tabela dos visitantes presentes
+    pub presentes: usize,   // Visitantes dentro depois da ação
+}
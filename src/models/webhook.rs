@@ -0,0 +1,39 @@
+// src/models/webhook.rs
+use sqlx::FromRow;
+
+/// Webhook configurado pela administração (tabela `webhooks`), sem o segredo.
+#[derive(Debug, Clone, FromRow)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    pub eventos: String, // Separados por vírgulas
+    pub ativo: bool,
+    pub criado_em: String, // Hora local, 'dd/mm/aaaa HH:MM'
+    pub criado_por: Option<String>,
+}
+
+impl Webhook {
+    /// Eventos subscritos, um a um.
+    pub fn lista_eventos(&self) -> Vec<&str> {
+        self.eventos.split(',').map(str::trim).filter(|e| !e.is_empty()).collect()
+    }
+
+    /// O webhook recebe este evento?
+    pub fn subscreve(&self, evento: &str) -> bool {
+        self.lista_eventos().contains(&evento)
+    }
+}
+
+/// Uma entrega de um evento a um webhook (tabela `webhook_entregas`), para o registo na administração.
+#[derive(Debug, Clone, FromRow)]
+pub struct WebhookEntrega {
+    pub id: i64,
+    pub url: String,
+    pub evento: String,
+    pub estado: String, // 'pendente', 'entregue' ou 'falhada'
+    pub tentativas: i64,
+    pub ultimo_status: Option<i64>,
+    pub ultimo_erro: Option<String>,
+    pub criado_em: String,                 // Hora local, 'dd/mm/aaaa HH:MM:SS'
+    pub proxima_tentativa: Option<String>, // Hora local (só nas pendentes)
+}
@@ -0,0 +1,38 @@
+// src/moeda.rs
+//! Valores em dinheiro (loja): guardados em centavos, como inteiros (sem erros de arredondamento),
+//! e mostrados/lidos como "R$ 1.234,50". Nos templates, `{{ produto.preco|moeda }}`.
+
+/// Formata centavos como "R$ 1.234,50" (negativos: "-R$ 3,00").
+pub fn formatar(centavos: i64) -> String {
+    let sinal = if centavos < 0 { "-" } else { "" };
+    let valor = centavos.unsigned_abs();
+    let inteiros = (valor / 100).to_string();
+    // Separador de milhares: um ponto a cada três algarismos, da direita para a esquerda
+    let mut agrupado = String::with_capacity(inteiros.len() + inteiros.len() / 3);
+    for (i, algarismo) in inteiros.chars().enumerate() {
+        if i > 0 && (inteiros.len() - i).is_multiple_of(3) {
+            agrupado.push('.');
+        }
+        agrupado.push(algarismo);
+    }
+    format!("{}R$ {},{:02}", sinal, agrupado, valor % 100)
+}
+
+/// Lê um valor escrito num formulário ("12", "12,5", "12,50", "12.50", "R$ 1.234,50") para centavos.
+/// Com vírgula, os pontos são separadores de milhares; sem ela, o ponto é o separador decimal.
+/// Só valores positivos ou zero, com no máximo duas casas decimais.
+pub fn ler(texto: &str) -> Option<i64> {
+    let texto = texto.trim().trim_start_matches("R$").trim();
+    let normalizado = if texto.contains(',') { texto.replace('.', "").replace(',', ".") } else { texto.to_string() };
+    let (inteiros, decimais) = normalizado.split_once('.').unwrap_or((&normalizado, ""));
+    if inteiros.is_empty() && decimais.is_empty() {
+        return None;
+    }
+    let todos_digitos = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if !todos_digitos(inteiros) || !todos_digitos(decimais) || decimais.len() > 2 {
+        return None;
+    }
+    let inteiros: i64 = if inteiros.is_empty() { 0 } else { inteiros.parse().ok()? };
+    let decimais: i64 = format!("{:0<2}", decimais).parse().ok()?;
+    inteiros.checked_mul(100)?.checked_add(decimais)
+}
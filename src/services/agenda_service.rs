@@ -0,0 +1,225 @@
+// src/services/agenda_service.rs
+//! Agenda institucional: formaturas, cerimónias e palestras, com o dia e a hora, os anos a que se
+//! destinam e se a presença é obrigatória. Aparece nos painéis, no feed iCal (/feeds/agenda.ics) e na
+//! API (/api/v1/agenda). Os eventos com `presenca_turma` esperam a turma inteira a bordo: no dia, a lista
+//! de presença dessas turmas assinala quem está fora. Um evento novo é notificado aos utilizadores dos
+//! anos a que se destina.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::agenda::{DadosEvento, EventoAgenda},
+    services::notificacao_service,
+    tempo,
+};
+use chrono::NaiveDate;
+use sqlx::SqlitePool;
+
+/// Tipos de evento (código, descrição).
+pub const TIPOS: &[(&str, &str)] = &[
+    ("formatura", "Formatura"),
+    ("cerimonia", "Cerimónia"),
+    ("palestra", "Palestra"),
+];
+/// Último ano que se pode indicar (como na ficha do utilizador).
+pub const MAX_ANO: i64 = 5;
+/// Próximos eventos mostrados nos painéis.
+pub const PROXIMOS_PAINEL: usize = 5;
+
+/// Descrição de um tipo de evento.
+pub fn descrever_tipo(tipo: &str) -> &str {
+    TIPOS.iter().find(|(c, _)| *c == tipo).map_or(tipo, |(_, d)| *d)
+}
+
+/// Eventos da organização entre `inicio` e `fim` (inclusive), por dia e hora.
+pub async fn listar(db_pool: &SqlitePool, organizacao_id: i64, inicio: NaiveDate, fim: NaiveDate) -> AppResult<Vec<EventoAgenda>> {
+    let eventos = sqlx::query_as::<_, EventoAgenda>(
+        r#"
+        SELECT id, tipo, titulo, local, descricao, data, hora_inicio, hora_fim, anos, obrigatorio, presenca_turma
+        FROM agenda_eventos
+        WHERE organizacao_id = ?1 AND data BETWEEN ?2 AND ?3
+        ORDER BY data, hora_inicio, id
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(inicio.to_string())
+    .bind(fim.to_string())
+    .fetch_all(db_pool)
+    .await?;
+    Ok(eventos)
+}
+
+/// Próximos eventos (a partir de `hoje`) para o ano `ano` (None = todos), no máximo `limite`.
+pub async fn proximos(db_pool: &SqlitePool, organizacao_id: i64, hoje: NaiveDate, ano: Option<i64>, limite: usize) -> AppResult<Vec<EventoAgenda>> {
+    let eventos = sqlx::query_as::<_, EventoAgenda>(
+        r#"
+        SELECT id, tipo, titulo, local, descricao, data, hora_inicio, hora_fim, anos, obrigatorio, presenca_turma
+        FROM agenda_eventos
+        WHERE organizacao_id = ?1 AND data >= ?2
+        ORDER BY data, hora_inicio, id
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(hoje.to_string())
+    .fetch_all(db_pool)
+    .await?;
+    Ok(eventos
+        .into_iter()
+        .filter(|e| ano.is_none_or(|ano| e.para_ano(ano)))
+        .take(limite)
+        .collect())
+}
+
+/// Eventos do dia `data` que esperam a turma do ano `ano` inteira a bordo (lista de presença).
+pub async fn presenca_esperada(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate, ano: i64) -> AppResult<Vec<EventoAgenda>> {
+    let eventos = listar(db_pool, organizacao_id, data, data).await?;
+    Ok(eventos.into_iter().filter(|e| e.presenca_turma && e.para_ano(ano)).collect())
+}
+
+/// Um evento pelo id (da organização).
+pub async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<EventoAgenda> {
+    sqlx::query_as::<_, EventoAgenda>(
+        r#"
+        SELECT id, tipo, titulo, local, descricao, data, hora_inicio, hora_fim, anos, obrigatorio, presenca_turma
+        FROM agenda_eventos
+        WHERE id = ?1 AND organizacao_id = ?2
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Evento {} não encontrado.", id)))
+}
+
+/// Tipo e anos conhecidos (o resto já foi validado pelo handler).
+fn validar(dados: &DadosEvento) -> AppResult<()> {
+    if !TIPOS.iter().any(|(c, _)| *c == dados.tipo) {
+        return Err(AppError::validation("tipo", format!("Tipo de evento desconhecido: '{}'.", dados.tipo)));
+    }
+    if let Some(ano) = dados.anos.iter().find(|a| !(1..=MAX_ANO).contains(*a)) {
+        return Err(AppError::validation("anos", format!("Ano inválido: {}.", ano)));
+    }
+    if dados.hora_fim.as_deref().is_some_and(|fim| fim <= dados.hora_inicio.as_str()) {
+        return Err(AppError::validation("hora_fim", "A hora de fim deve ser posterior à de início."));
+    }
+    Ok(())
+}
+
+/// Anos ordenados e sem repetições, separados por vírgulas (vazio = todos).
+fn anos_texto(anos: &[i64]) -> String {
+    let mut anos = anos.to_vec();
+    anos.sort_unstable();
+    anos.dedup();
+    anos.iter().map(i64::to_string).collect::<Vec<_>>().join(",")
+}
+
+/// Cria um evento. Devolve o ID.
+pub async fn criar(db_pool: &SqlitePool, organizacao_id: i64, dados: &DadosEvento, operador_id: &str) -> AppResult<i64> {
+    validar(dados)?;
+    let id = sqlx::query(
+        r#"
+        INSERT INTO agenda_eventos
+            (organizacao_id, tipo, titulo, local, descricao, data, hora_inicio, hora_fim, anos, obrigatorio, presenca_turma, criado_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(&dados.tipo)
+    .bind(dados.titulo.trim())
+    .bind(dados.local.trim())
+    .bind(dados.descricao.trim())
+    .bind(&dados.data)
+    .bind(&dados.hora_inicio)
+    .bind(&dados.hora_fim)
+    .bind(anos_texto(&dados.anos))
+    .bind(dados.obrigatorio)
+    .bind(dados.presenca_turma)
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+    tracing::info!("📅 Evento {} ({} de {}) criado por {}.", id, dados.titulo.trim(), dados.data, operador_id);
+    anunciar(db_pool, organizacao_id, id).await;
+    Ok(id)
+}
+
+/// Notifica um evento novo aos utilizadores ativos dos anos a que se destina (os já passados não).
+async fn anunciar(db_pool: &SqlitePool, organizacao_id: i64, id: i64) {
+    let evento = match obter(db_pool, organizacao_id, id).await {
+        Ok(evento) if tempo::ler_data(&evento.data).is_some_and(|d| d >= tempo::hoje()) => evento,
+        Ok(_) => return,
+        Err(e) => {
+            tracing::error!("Falha ao obter o evento {} para o anunciar: {:?}", id, e);
+            return;
+        }
+    };
+    let users: Vec<(String, i64)> = match sqlx::query_as("SELECT id, ano FROM users WHERE organizacao_id = ?1 AND ativo = 1")
+        .bind(organizacao_id)
+        .fetch_all(db_pool)
+        .await
+    {
+        Ok(users) => users,
+        Err(e) => {
+            tracing::error!("Falha ao obter os destinatários do evento {}: {:?}", id, e);
+            return;
+        }
+    };
+    let destinatarios: Vec<String> = users.into_iter().filter(|(_, ano)| evento.para_ano(*ano)).map(|(id, _)| id).collect();
+
+    let mut mensagem = format!(
+        "{}: {}, {} às {}",
+        descrever_tipo(&evento.tipo),
+        evento.titulo,
+        tempo::ler_data(&evento.data).map_or_else(|| evento.data.clone(), |d| d.format("%d/%m").to_string()),
+        evento.hora_inicio
+    );
+    if !evento.local.is_empty() {
+        mensagem.push_str(&format!(" ({})", evento.local));
+    }
+    mensagem.push('.');
+    if evento.obrigatorio {
+        mensagem.push_str(" Presença obrigatória.");
+    }
+    let titulo = if evento.obrigatorio { "Novo evento obrigatório" } else { "Novo evento na agenda" };
+    notificacao_service::notificar_varios(db_pool, &destinatarios, notificacao_service::TIPO_AGENDA, titulo, &mensagem).await;
+}
+
+/// Altera um evento. Devolve o evento como estava.
+pub async fn atualizar(db_pool: &SqlitePool, organizacao_id: i64, id: i64, dados: &DadosEvento) -> AppResult<EventoAgenda> {
+    let anterior = obter(db_pool, organizacao_id, id).await?;
+    validar(dados)?;
+    sqlx::query(
+        r#"
+        UPDATE agenda_eventos
+        SET tipo = ?2, titulo = ?3, local = ?4, descricao = ?5, data = ?6, hora_inicio = ?7, hora_fim = ?8,
+            anos = ?9, obrigatorio = ?10, presenca_turma = ?11
+        WHERE id = ?1
+        "#,
+    )
+    .bind(id)
+    .bind(&dados.tipo)
+    .bind(dados.titulo.trim())
+    .bind(dados.local.trim())
+    .bind(dados.descricao.trim())
+    .bind(&dados.data)
+    .bind(&dados.hora_inicio)
+    .bind(&dados.hora_fim)
+    .bind(anos_texto(&dados.anos))
+    .bind(dados.obrigatorio)
+    .bind(dados.presenca_turma)
+    .execute(db_pool)
+    .await?;
+    tracing::info!("📅 Evento {} ({}) alterado.", id, dados.titulo.trim());
+    Ok(anterior)
+}
+
+/// Apaga um evento (cancelado ou criado por engano). Devolve o evento apagado.
+pub async fn remover(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<EventoAgenda> {
+    let evento = obter(db_pool, organizacao_id, id).await?;
+    sqlx::query("DELETE FROM agenda_eventos WHERE id = ?1")
+        .bind(id)
+        .execute(db_pool)
+        .await?;
+    tracing::info!("🗑️ Evento {} ({}) apagado.", id, evento.titulo);
+    Ok(evento)
+}
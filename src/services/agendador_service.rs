@@ -0,0 +1,256 @@
+// src/services/agendador_service.rs
+//! Agendador das tarefas periódicas (limpeza de sessões, ...): cada tarefa tem um nome, uma expressão cron
+//! e um desvio aleatório máximo (jitter), e corre numa task própria. As horas da expressão são as do fuso
+//! da aplicação (ver `tempo`), com segundos: "seg min hora dia mês dia-da-semana" (ex: "0 0 18 * * *").
+//!
+//! As tarefas são registadas no arranque (`Agendador::registar`, em main.rs) e a última execução de cada uma
+//! fica na tabela `tarefas_agendadas` (página /admin/tarefas). Uma execução prevista que se perdeu com a
+//! aplicação parada é feita logo no arranque (uma vez só, mesmo que se tenham perdido várias).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::tarefa::{RegistoTarefa, TarefaEstado},
+    tempo,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use cron::Schedule;
+use futures_util::future::BoxFuture;
+use rand::Rng;
+use sqlx::SqlitePool;
+use std::{
+    future::Future,
+    str::FromStr,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+// Estado da última execução (coluna `estado`)
+pub const ESTADO_OK: &str = "ok";
+pub const ESTADO_ERRO: &str = "erro";
+
+type Execucao = Box<dyn Fn() -> BoxFuture<'static, AppResult<String>> + Send + Sync>;
+
+/// Uma tarefa registada.
+struct Tarefa {
+    nome: &'static str,
+    descricao: &'static str,
+    expressao: String,
+    horario: Schedule,
+    jitter: Duration,
+    executar: Execucao,
+    /// Uma execução de cada vez (a agendada e uma pedida na administração não se sobrepõem).
+    em_curso: Mutex<()>,
+}
+
+impl Tarefa {
+    /// Próxima execução prevista depois de `referencia`, já com o desvio aleatório.
+    fn proxima(&self, referencia: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let prevista = self.horario.after(&tempo::local(&referencia)).next()?.with_timezone(&Utc);
+        let jitter_ms = self.jitter.as_millis() as i64;
+        let desvio = if jitter_ms > 0 { rand::thread_rng().gen_range(0..=jitter_ms) } else { 0 };
+        Some(prevista + chrono::Duration::milliseconds(desvio))
+    }
+}
+
+static TAREFAS: OnceLock<Vec<Arc<Tarefa>>> = OnceLock::new();
+
+fn tarefas() -> &'static [Arc<Tarefa>] {
+    TAREFAS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Conjunto de tarefas a iniciar (montado em main.rs).
+#[derive(Default)]
+pub struct Agendador {
+    tarefas: Vec<Tarefa>,
+}
+
+impl Agendador {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Regista a tarefa `nome`, executada segundo `expressao` (cron com segundos, no fuso da aplicação)
+    /// com um atraso aleatório entre 0 e `jitter` (para não coincidir com outras tarefas à mesma hora).
+    /// `executar` devolve um resumo do que foi feito (mostrado na administração).
+    pub fn registar<F, Fut>(
+        mut self,
+        nome: &'static str,
+        descricao: &'static str,
+        expressao: &str,
+        jitter: Duration,
+        executar: F,
+    ) -> Result<Self, String>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = AppResult<String>> + Send + 'static,
+    {
+        let horario = Schedule::from_str(expressao)
+            .map_err(|e| format!("Expressão cron inválida na tarefa '{}' ('{}'): {}", nome, expressao, e))?;
+        if self.tarefas.iter().any(|t| t.nome == nome) {
+            return Err(format!("Tarefa '{}' registada duas vezes.", nome));
+        }
+        self.tarefas.push(Tarefa {
+            nome,
+            descricao,
+            expressao: expressao.to_string(),
+            horario,
+            jitter,
+            executar: Box::new(move || Box::pin(executar())),
+            em_curso: Mutex::const_new(()),
+        });
+        Ok(self)
+    }
+
+    /// Inicia uma task por tarefa registada (chamado uma vez, no arranque).
+    pub async fn iniciar(self, db_pool: SqlitePool) -> AppResult<()> {
+        let tarefas: Vec<Arc<Tarefa>> = self.tarefas.into_iter().map(Arc::new).collect();
+        if TAREFAS.set(tarefas.clone()).is_err() {
+            tracing::warn!("Agendador já iniciado; ignorado.");
+            return Ok(());
+        }
+        for tarefa in tarefas {
+            let ultima: Option<String> =
+                sqlx::query_scalar("SELECT ultima_execucao FROM tarefas_agendadas WHERE nome = ?1")
+                    .bind(tarefa.nome)
+                    .fetch_optional(&db_pool)
+                    .await?
+                    .flatten();
+            let ultima = ultima.as_deref().and_then(tempo::ler_utc);
+            tracing::info!("⏰ Tarefa '{}' agendada ({}).", tarefa.nome, tarefa.expressao);
+            tokio::spawn(ciclo(db_pool.clone(), tarefa, ultima));
+        }
+        Ok(())
+    }
+}
+
+/// Ciclo de uma tarefa: espera pela próxima execução prevista, executa, repete.
+/// Sem execuções anteriores, a primeira é a próxima prevista a partir de agora.
+async fn ciclo(db_pool: SqlitePool, tarefa: Arc<Tarefa>, ultima: Option<DateTime<Utc>>) {
+    let mut referencia = ultima.unwrap_or_else(Utc::now);
+    loop {
+        let Some(proxima) = tarefa.proxima(referencia) else {
+            tracing::warn!("⏰ A tarefa '{}' não tem mais execuções previstas ('{}').", tarefa.nome, tarefa.expressao);
+            return;
+        };
+        if let Err(e) = guardar_proxima(&db_pool, tarefa.nome, proxima).await {
+            tracing::error!("Erro ao guardar a próxima execução da tarefa '{}': {:?}", tarefa.nome, e);
+        }
+        tokio::time::sleep((proxima - Utc::now()).to_std().unwrap_or_default()).await;
+
+        // O erro já fica registado (tabela e log)
+        let _ = executar(&db_pool, &tarefa).await;
+        referencia = Utc::now();
+    }
+}
+
+/// Executa a tarefa e regista o resultado (também quando falha).
+async fn executar(db_pool: &SqlitePool, tarefa: &Tarefa) -> AppResult<String> {
+    let Ok(_guarda) = tarefa.em_curso.try_lock() else {
+        return Err(AppError::Conflict(format!("A tarefa '{}' já está a correr.", tarefa.nome)));
+    };
+    tracing::debug!("⏰ A executar a tarefa '{}'...", tarefa.nome);
+    let iniciada = tempo::carimbo_utc();
+    let inicio = Instant::now();
+    // Numa task à parte: um pânico na tarefa é registado como falha em vez de parar o ciclo
+    let resultado = match tokio::spawn((tarefa.executar)()).await {
+        Ok(resultado) => resultado,
+        Err(e) => {
+            tracing::error!("Pânico na tarefa '{}': {:?}", tarefa.nome, e);
+            Err(AppError::InternalServerError)
+        }
+    };
+    let duracao_ms = inicio.elapsed().as_millis() as i64;
+
+    let (estado, texto) = match &resultado {
+        Ok(resumo) => {
+            tracing::info!("⏰ Tarefa '{}' concluída em {} ms: {}", tarefa.nome, duracao_ms, resumo);
+            (ESTADO_OK, resumo.clone())
+        }
+        Err(e) => {
+            tracing::error!("Erro na tarefa '{}': {:?}", tarefa.nome, e);
+            (ESTADO_ERRO, e.to_string())
+        }
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO tarefas_agendadas (nome, ultima_execucao, duracao_ms, estado, resultado, execucoes, falhas)
+        VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6)
+        ON CONFLICT(nome) DO UPDATE SET
+            ultima_execucao = excluded.ultima_execucao,
+            duracao_ms = excluded.duracao_ms,
+            estado = excluded.estado,
+            resultado = excluded.resultado,
+            execucoes = execucoes + 1,
+            falhas = falhas + excluded.falhas
+        "#,
+    )
+    .bind(tarefa.nome)
+    .bind(&iniciada)
+    .bind(duracao_ms)
+    .bind(estado)
+    .bind(&texto)
+    .bind((estado == ESTADO_ERRO) as i64)
+    .execute(db_pool)
+    .await?;
+
+    resultado
+}
+
+async fn guardar_proxima(db_pool: &SqlitePool, nome: &str, proxima: DateTime<Utc>) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO tarefas_agendadas (nome, proxima_execucao) VALUES (?1, ?2)
+        ON CONFLICT(nome) DO UPDATE SET proxima_execucao = excluded.proxima_execucao
+        "#,
+    )
+    .bind(nome)
+    .bind(proxima.to_rfc3339_opts(SecondsFormat::Secs, true))
+    .execute(db_pool)
+    .await?;
+    Ok(())
+}
+
+/// Executa já a tarefa `nome` (pedido da administração), fora do horário; a próxima execução agendada mantém-se.
+/// Devolve o resumo da tarefa ou o erro (`Conflict` se já estiver a correr).
+pub async fn executar_agora(db_pool: &SqlitePool, nome: &str) -> AppResult<String> {
+    let tarefa = tarefas()
+        .iter()
+        .find(|t| t.nome == nome)
+        .ok_or_else(|| AppError::NotFound(format!("Tarefa '{}' não encontrada.", nome)))?;
+    executar(db_pool, tarefa).await
+}
+
+/// Tarefas registadas (pela ordem de registo) com o estado da última execução.
+pub async fn listar(db_pool: &SqlitePool) -> AppResult<Vec<TarefaEstado>> {
+    let mut estados = Vec::new();
+    for tarefa in tarefas() {
+        let registo = sqlx::query_as::<_, RegistoTarefa>(
+            r#"
+            SELECT ultima_execucao, duracao_ms, estado, resultado, execucoes, falhas, proxima_execucao
+            FROM tarefas_agendadas
+            WHERE nome = ?1
+            "#,
+        )
+        .bind(tarefa.nome)
+        .fetch_optional(db_pool)
+        .await?
+        .unwrap_or_default();
+
+        estados.push(TarefaEstado {
+            nome: tarefa.nome.to_string(),
+            descricao: tarefa.descricao.to_string(),
+            expressao: tarefa.expressao.clone(),
+            jitter_segundos: tarefa.jitter.as_secs(),
+            em_curso: tarefa.em_curso.try_lock().is_err(),
+            ultima_execucao: tempo::formatar_opcional(registo.ultima_execucao, tempo::FORMATO_DATA_HORA),
+            proxima_execucao: tempo::formatar_opcional(registo.proxima_execucao, tempo::FORMATO_DATA_HORA),
+            duracao_ms: registo.duracao_ms,
+            estado: registo.estado,
+            resultado: registo.resultado,
+            execucoes: registo.execucoes,
+            falhas: registo.falhas,
+        });
+    }
+    Ok(estados)
+}
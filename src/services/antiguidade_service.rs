@@ -0,0 +1,86 @@
+// src/services/antiguidade_service.rs
+//! Antiguidade: a ordem dos utilizadores ativos dentro de cada ano (1 = o mais antigo), definida pela
+//! administração (/admin/antiguidade). É o último critério de desempate da geração da escala (o mais
+//! moderno primeiro, ver `escala_service`) e a ordem do efetivo por turma impresso.
+//! Quem não tem posição (ex: entrou depois da última ordenação) fica a seguir aos ordenados.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::antiguidade::PosicaoAntiguidade,
+};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+
+/// Anos com utilizadores ativos na organização.
+pub async fn anos(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<i64>> {
+    let anos = sqlx::query_scalar("SELECT DISTINCT ano FROM users WHERE ativo = 1 AND organizacao_id = ?1 ORDER BY ano")
+        .bind(organizacao_id)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(anos)
+}
+
+/// Utilizadores ativos de um ano por antiguidade (os sem posição no fim, por ID).
+pub async fn listar(db_pool: &SqlitePool, organizacao_id: i64, ano: i64) -> AppResult<Vec<PosicaoAntiguidade>> {
+    let posicoes = sqlx::query_as::<_, PosicaoAntiguidade>(
+        r#"
+        SELECT id AS user_id, name, turma, antiguidade
+        FROM users
+        WHERE ativo = 1 AND organizacao_id = ?1 AND ano = ?2
+        ORDER BY antiguidade IS NULL, antiguidade, id
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(ano)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(posicoes)
+}
+
+/// Posição de cada utilizador ativo com antiguidade definida (para ordenar listagens).
+pub async fn posicoes(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<HashMap<String, i64>> {
+    let linhas: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT id, antiguidade FROM users WHERE ativo = 1 AND organizacao_id = ?1 AND antiguidade IS NOT NULL",
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(linhas.into_iter().collect())
+}
+
+/// Define a antiguidade de um ano pela ordem dos IDs (o primeiro é o mais antigo). Os utilizadores
+/// ativos do ano que não estão na lista ficam sem posição. Devolve (ordenados, sem posição).
+pub async fn definir(db_pool: &SqlitePool, organizacao_id: i64, ano: i64, ordem: &[String]) -> AppResult<(usize, usize)> {
+    let do_ano: HashSet<String> = listar(db_pool, organizacao_id, ano).await?.into_iter().map(|p| p.user_id).collect();
+    if do_ano.is_empty() {
+        return Err(AppError::validation("ano", format!("Não há utilizadores ativos no {}º ano.", ano)));
+    }
+    let mut vistos = HashSet::new();
+    for user_id in ordem {
+        if !do_ano.contains(user_id) {
+            return Err(AppError::validation("ordem", format!("'{}' não é um utilizador ativo do {}º ano.", user_id, ano)));
+        }
+        if !vistos.insert(user_id) {
+            return Err(AppError::validation("ordem", format!("'{}' aparece mais do que uma vez.", user_id)));
+        }
+    }
+
+    let mut tx = db_pool.begin().await?;
+    sqlx::query("UPDATE users SET antiguidade = NULL WHERE ativo = 1 AND organizacao_id = ?1 AND ano = ?2")
+        .bind(organizacao_id)
+        .bind(ano)
+        .execute(&mut *tx)
+        .await?;
+    for (posicao, user_id) in ordem.iter().enumerate() {
+        sqlx::query("UPDATE users SET antiguidade = ?2 WHERE id = ?1")
+            .bind(user_id)
+            .bind(posicao as i64 + 1)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    let sem_posicao = do_ano.len() - ordem.len();
+    tracing::info!("🎖️ Antiguidade do {}º ano (organização {}): {} ordenados, {} sem posição.", ano, organizacao_id, ordem.len(), sem_posicao);
+    Ok((ordem.len(), sem_posicao))
+}
@@ -0,0 +1,125 @@
+// src/services/api_token_service.rs
+//! Tokens de acesso à API JSON (/api/v1), alternativa à sessão para integrações.
+//! O token é aleatório e só existe em claro no momento da criação; a base de dados guarda o SHA-256
+//! (um hash rápido chega: o token tem 256 bits de entropia, não é uma senha escolhida por alguém).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::api_token::ApiToken,
+    tempo,
+};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+/// Prefixo de todos os tokens (facilita reconhecê-los em logs ou ficheiros de configuração).
+const PREFIXO_TOKEN: &str = "mercal_";
+/// Caracteres do token mostrados na listagem.
+const CARACTERES_PREFIXO: usize = 12;
+/// Tokens por utilizador.
+pub const MAX_TOKENS_USER: i64 = 10;
+/// Comprimento máximo do nome dado ao token.
+pub const MAX_NOME: usize = 60;
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Cria um token para o utilizador. Devolve o token em claro (a única vez que fica disponível).
+pub async fn criar(db_pool: &SqlitePool, user_id: &str, nome: &str) -> AppResult<String> {
+    let nome = nome.trim();
+    if nome.is_empty() {
+        return Err(AppError::validation("nome", "Indique um nome para o token."));
+    }
+    if nome.chars().count() > MAX_NOME {
+        return Err(AppError::validation("nome", format!("Máximo de {} caracteres.", MAX_NOME)));
+    }
+
+    let existentes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM api_tokens WHERE user_id = ?1")
+        .bind(user_id)
+        .fetch_one(db_pool)
+        .await?;
+    if existentes >= MAX_TOKENS_USER {
+        return Err(AppError::Conflict(format!(
+            "Já tem {} tokens. Revogue um que já não use antes de criar outro.",
+            MAX_TOKENS_USER
+        )));
+    }
+
+    let token = format!("{}{}{}", PREFIXO_TOKEN, Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let prefixo: String = token.chars().take(CARACTERES_PREFIXO).collect();
+    sqlx::query("INSERT INTO api_tokens (user_id, nome, prefixo, token_hash) VALUES (?1, ?2, ?3, ?4)")
+        .bind(user_id)
+        .bind(nome)
+        .bind(&prefixo)
+        .bind(hash_token(&token))
+        .execute(db_pool)
+        .await?;
+
+    tracing::info!("🔑 Token de API '{}' criado para {}.", nome, user_id);
+    Ok(token)
+}
+
+/// Tokens de um utilizador, do mais recente para o mais antigo.
+pub async fn listar_user(db_pool: &SqlitePool, user_id: &str) -> AppResult<Vec<ApiToken>> {
+    let mut tokens = sqlx::query_as::<_, ApiToken>(
+        r#"
+        SELECT id, nome, prefixo, criado_em, ultimo_uso
+        FROM api_tokens
+        WHERE user_id = ?1
+        ORDER BY id DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db_pool)
+    .await?;
+    for token in &mut tokens {
+        token.criado_em = tempo::formatar(&token.criado_em, tempo::FORMATO_DATA_HORA);
+        token.ultimo_uso = tempo::formatar_opcional(token.ultimo_uso.take(), tempo::FORMATO_DATA_HORA);
+    }
+    Ok(tokens)
+}
+
+/// Revoga (apaga) um token do utilizador. Devolve o nome do token revogado.
+pub async fn revogar(db_pool: &SqlitePool, user_id: &str, id: i64) -> AppResult<String> {
+    let nome: Option<String> = sqlx::query_scalar("DELETE FROM api_tokens WHERE id = ?1 AND user_id = ?2 RETURNING nome")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(db_pool)
+        .await?;
+    let nome = nome.ok_or_else(|| AppError::NotFound("Token não encontrado.".to_string()))?;
+    tracing::info!("🗑️ Token de API '{}' de {} revogado.", nome, user_id);
+    Ok(nome)
+}
+
+/// Identifica o dono de um token. None se o token não existir ou a conta estiver arquivada.
+/// O último uso só é escrito uma vez por minuto, como a atividade das sessões.
+pub async fn autenticar(db_pool: &SqlitePool, token: &str) -> AppResult<Option<String>> {
+    if !token.starts_with(PREFIXO_TOKEN) {
+        return Ok(None);
+    }
+    let hash = hash_token(token);
+    let user_id: Option<String> = sqlx::query_scalar(
+        r#"
+        SELECT t.user_id FROM api_tokens t
+        JOIN users u ON u.id = t.user_id
+        WHERE t.token_hash = ?1 AND u.ativo = 1
+        "#,
+    )
+    .bind(&hash)
+    .fetch_optional(db_pool)
+    .await?;
+
+    if user_id.is_some() {
+        sqlx::query(
+            r#"
+            UPDATE api_tokens SET ultimo_uso = datetime('now')
+            WHERE token_hash = ?1 AND (ultimo_uso IS NULL OR ultimo_uso < datetime('now', '-1 minute'))
+            "#,
+        )
+        .bind(&hash)
+        .execute(db_pool)
+        .await?;
+    }
+    Ok(user_id)
+}
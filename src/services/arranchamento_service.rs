@@ -0,0 +1,227 @@
+// src/services/arranchamento_service.rs
+//! Arranchamento: cada utilizador marca, para os próximos dias, as refeições em que vai comer; as marcações
+//! de uma refeição fecham à hora de corte (ex: na véspera às 20:00). O rancheiro (permissão `rancho`) vê a
+//! previsão do dia por refeição e turma, exporta-a em CSV e gere as refeições e os cortes da organização.
+//! As horas de corte são no fuso da aplicação (ver `tempo`).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::arranchamento::{DiaArranchamento, MarcacaoRefeicao, Previsao, PrevisaoRefeicao, Refeicao},
+    tempo,
+};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use sqlx::SqlitePool;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Dias (a partir de hoje) mostrados para marcar.
+pub const DIAS_ABERTOS: i64 = 7;
+/// Máximo de dias antes da refeição para a hora de corte.
+pub const MAX_CORTE_DIAS: i64 = 7;
+
+/// Refeições da organização, pela ordem do dia (só as ativas, se `so_ativas`).
+pub async fn listar_refeicoes(db_pool: &SqlitePool, organizacao_id: i64, so_ativas: bool) -> AppResult<Vec<Refeicao>> {
+    let refeicoes = sqlx::query_as::<_, Refeicao>(
+        r#"
+        SELECT id, nome, ordem, corte_dias, corte_hora, ativa
+        FROM refeicoes
+        WHERE organizacao_id = ?1 AND (?2 = 0 OR ativa = 1)
+        ORDER BY ordem, nome
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(so_ativas)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(refeicoes)
+}
+
+/// Cria uma refeição (dados já validados pelo formulário).
+pub async fn criar_refeicao(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    nome: &str,
+    ordem: i64,
+    corte_dias: i64,
+    corte_hora: &str,
+) -> AppResult<i64> {
+    let resultado = sqlx::query(
+        "INSERT INTO refeicoes (organizacao_id, nome, ordem, corte_dias, corte_hora) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(organizacao_id)
+    .bind(nome.trim())
+    .bind(ordem)
+    .bind(corte_dias)
+    .bind(corte_hora.trim())
+    .execute(db_pool)
+    .await;
+    match resultado {
+        Ok(r) => Ok(r.last_insert_rowid()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::validation("nome", "Já existe uma refeição com este nome."))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Altera uma refeição da organização. Devolve o nome (para a auditoria).
+pub async fn atualizar_refeicao(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    ordem: i64,
+    corte_dias: i64,
+    corte_hora: &str,
+    ativa: bool,
+) -> AppResult<String> {
+    let nome: Option<String> = sqlx::query_scalar(
+        r#"
+        UPDATE refeicoes SET ordem = ?3, corte_dias = ?4, corte_hora = ?5, ativa = ?6
+        WHERE id = ?1 AND organizacao_id = ?2
+        RETURNING nome
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .bind(ordem)
+    .bind(corte_dias)
+    .bind(corte_hora.trim())
+    .bind(ativa)
+    .fetch_optional(db_pool)
+    .await?;
+    nome.ok_or_else(|| AppError::NotFound(format!("Refeição {} não encontrada.", id)))
+}
+
+/// Momento (no fuso da aplicação) em que fecham as marcações da refeição no dia `data`.
+pub fn corte(refeicao: &Refeicao, data: NaiveDate) -> NaiveDateTime {
+    let hora = NaiveTime::parse_from_str(&refeicao.corte_hora, "%H:%M").unwrap_or(NaiveTime::MIN);
+    (data - Duration::days(refeicao.corte_dias)).and_time(hora)
+}
+
+/// Se ainda se pode marcar/desmarcar a refeição do dia `data`.
+pub fn aberta(refeicao: &Refeicao, data: NaiveDate) -> bool {
+    tempo::agora().naive_local() < corte(refeicao, data)
+}
+
+/// Dias mostrados para marcar (hoje e os seguintes).
+fn dias_abertos() -> Vec<NaiveDate> {
+    let hoje = tempo::hoje();
+    (0..DIAS_ABERTOS).map(|n| hoje + Duration::days(n)).collect()
+}
+
+/// Marcações do utilizador nos dias a marcar, com o estado (aberta/fechada) de cada refeição.
+pub async fn marcacoes_user(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: &str,
+) -> AppResult<(Vec<Refeicao>, Vec<DiaArranchamento>)> {
+    let refeicoes = listar_refeicoes(db_pool, organizacao_id, true).await?;
+    let dias = dias_abertos();
+    let (Some(inicio), Some(fim)) = (dias.first(), dias.last()) else {
+        return Ok((refeicoes, Vec::new()));
+    };
+    let marcadas: HashSet<(String, i64)> = sqlx::query_as::<_, (String, i64)>(
+        "SELECT data, refeicao_id FROM arranchamentos WHERE user_id = ?1 AND data BETWEEN ?2 AND ?3",
+    )
+    .bind(user_id)
+    .bind(inicio.to_string())
+    .bind(fim.to_string())
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let dias = dias
+        .into_iter()
+        .map(|dia| {
+            let data = dia.to_string();
+            let refeicoes = refeicoes
+                .iter()
+                .map(|r| MarcacaoRefeicao {
+                    refeicao_id: r.id,
+                    marcada: marcadas.contains(&(data.clone(), r.id)),
+                    aberta: aberta(r, dia),
+                })
+                .collect();
+            DiaArranchamento { data, refeicoes }
+        })
+        .collect();
+    Ok((refeicoes, dias))
+}
+
+/// Grava as marcações do utilizador nos dias a marcar: `marcadas` são as refeições (dia, refeição)
+/// escolhidas; as refeições abertas que não estão lá são desmarcadas. As já fechadas não mudam.
+/// Devolve quantas marcações foram acrescentadas e retiradas.
+pub async fn definir_marcacoes(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: &str,
+    marcadas: &HashSet<(NaiveDate, i64)>,
+) -> AppResult<(u64, u64)> {
+    let refeicoes = listar_refeicoes(db_pool, organizacao_id, true).await?;
+    let mut tx = db_pool.begin().await?;
+    let (mut acrescentadas, mut retiradas) = (0, 0);
+    for dia in dias_abertos() {
+        for refeicao in refeicoes.iter().filter(|r| aberta(r, dia)) {
+            if marcadas.contains(&(dia, refeicao.id)) {
+                acrescentadas += sqlx::query("INSERT OR IGNORE INTO arranchamentos (user_id, refeicao_id, data) VALUES (?1, ?2, ?3)")
+                    .bind(user_id)
+                    .bind(refeicao.id)
+                    .bind(dia.to_string())
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+            } else {
+                retiradas += sqlx::query("DELETE FROM arranchamentos WHERE user_id = ?1 AND refeicao_id = ?2 AND data = ?3")
+                    .bind(user_id)
+                    .bind(refeicao.id)
+                    .bind(dia.to_string())
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+            }
+        }
+    }
+    tx.commit().await?;
+    if acrescentadas + retiradas > 0 {
+        tracing::debug!("🍽️ Arranchamento de {}: +{} / -{}.", user_id, acrescentadas, retiradas);
+    }
+    Ok((acrescentadas, retiradas))
+}
+
+/// Previsão do dia: utilizadores ativos arranchados em cada refeição, por turma (ano).
+pub async fn previsao(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<Previsao> {
+    let refeicoes = listar_refeicoes(db_pool, organizacao_id, false).await?;
+    let contagens: Vec<(i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT a.refeicao_id, u.ano, COUNT(*)
+        FROM arranchamentos a
+        JOIN refeicoes r ON r.id = a.refeicao_id
+        JOIN users u ON u.id = a.user_id
+        WHERE r.organizacao_id = ?1 AND a.data = ?2 AND u.ativo = 1
+        GROUP BY a.refeicao_id, u.ano
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(data.to_string())
+    .fetch_all(db_pool)
+    .await?;
+
+    let turmas: Vec<i64> = contagens.iter().map(|(_, ano, _)| *ano).collect::<BTreeSet<_>>().into_iter().collect();
+    let por_refeicao: HashMap<(i64, i64), i64> = contagens.into_iter().map(|(r, ano, n)| ((r, ano), n)).collect();
+    let refeicoes = refeicoes
+        .into_iter()
+        // As inativas só entram se alguém já estava arranchado
+        .filter(|r| r.ativa || turmas.iter().any(|ano| por_refeicao.contains_key(&(r.id, *ano))))
+        .map(|refeicao| {
+            let por_turma: Vec<i64> = turmas.iter().map(|ano| por_refeicao.get(&(refeicao.id, *ano)).copied().unwrap_or(0)).collect();
+            PrevisaoRefeicao {
+                corte: corte(&refeicao, data).format(tempo::FORMATO_DATA_HORA).to_string(),
+                aberta: aberta(&refeicao, data),
+                total: por_turma.iter().sum(),
+                por_turma,
+                refeicao,
+            }
+        })
+        .collect();
+    Ok(Previsao { data: data.to_string(), turmas, refeicoes })
+}
@@ -0,0 +1,132 @@
+// src/services/atributo_service.rs
+use crate::{
+    error::{AppError, AppResult},
+    models::atributo::{AtributoCampo, AtributoDef},
+};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// Comprimento máximo do valor de um atributo.
+pub const MAX_VALOR: usize = 200;
+
+/// Lista as definições de atributos, pela ordem de apresentação.
+pub async fn listar_definicoes(db_pool: &SqlitePool) -> AppResult<Vec<AtributoDef>> {
+    let defs = sqlx::query_as::<_, AtributoDef>(
+        "SELECT chave, rotulo, ordem FROM atributo_definicoes ORDER BY ordem, rotulo",
+    )
+    .fetch_all(db_pool)
+    .await?;
+    Ok(defs)
+}
+
+/// Cria uma definição de atributo.
+pub async fn criar_definicao(db_pool: &SqlitePool, chave: &str, rotulo: &str, ordem: i64) -> AppResult<()> {
+    let chave = chave.trim().to_lowercase();
+    if chave.is_empty() || !chave.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(AppError::validation("chave", "Chave inválida (use letras, números ou '_')."));
+    }
+    let rotulo = rotulo.trim();
+    if rotulo.is_empty() {
+        return Err(AppError::validation("rotulo", "Indique o rótulo do campo."));
+    }
+
+    let result = sqlx::query("INSERT INTO atributo_definicoes (chave, rotulo, ordem) VALUES (?1, ?2, ?3)")
+        .bind(&chave)
+        .bind(rotulo)
+        .bind(ordem)
+        .execute(db_pool)
+        .await;
+
+    match result {
+        Ok(_) => {
+            tracing::info!("✅ Atributo '{}' criado.", chave);
+            Ok(())
+        }
+        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+            Err(AppError::Conflict(format!("O campo '{}' já existe.", chave)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Apaga uma definição e todos os valores guardados para ela.
+pub async fn remover_definicao(db_pool: &SqlitePool, chave: &str) -> AppResult<()> {
+    let mut tx = db_pool.begin().await?;
+    sqlx::query("DELETE FROM user_attributes WHERE chave = ?1").bind(chave).execute(&mut *tx).await?;
+    let result = sqlx::query("DELETE FROM atributo_definicoes WHERE chave = ?1").bind(chave).execute(&mut *tx).await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Campo '{}' não encontrado.", chave)));
+    }
+    tx.commit().await?;
+    tracing::info!("🗑️ Atributo '{}' apagado.", chave);
+    Ok(())
+}
+
+/// Campos extra de um utilizador: todas as definições, com o valor (vazio se não preenchido).
+pub async fn campos_user(db_pool: &SqlitePool, user_id: &str) -> AppResult<Vec<AtributoCampo>> {
+    let campos = sqlx::query_as::<_, (String, String, String)>(
+        r#"
+        SELECT d.chave, d.rotulo, COALESCE(ua.valor, '')
+        FROM atributo_definicoes d
+        LEFT JOIN user_attributes ua ON ua.chave = d.chave AND ua.user_id = ?1
+        ORDER BY d.ordem, d.rotulo
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .map(|(chave, rotulo, valor)| AtributoCampo { chave, rotulo, valor })
+    .collect();
+    Ok(campos)
+}
+
+/// Grava os valores dos campos extra de um utilizador (valor vazio apaga o campo).
+/// Chaves sem definição são ignoradas.
+pub async fn definir_valores(db_pool: &SqlitePool, user_id: &str, valores: &[(String, String)]) -> AppResult<()> {
+    if let Some((chave, _)) = valores.iter().find(|(_, v)| v.trim().chars().count() > MAX_VALOR) {
+        return Err(AppError::validation(
+            format!("attr_{}", chave),
+            format!("Máximo de {} caracteres.", MAX_VALOR),
+        ));
+    }
+
+    let mut tx = db_pool.begin().await?;
+    for (chave, valor) in valores {
+        let valor = valor.trim();
+        if valor.is_empty() {
+            sqlx::query("DELETE FROM user_attributes WHERE user_id = ?1 AND chave = ?2")
+                .bind(user_id)
+                .bind(chave)
+                .execute(&mut *tx)
+                .await?;
+        } else {
+            sqlx::query(
+                r#"
+                INSERT INTO user_attributes (user_id, chave, valor)
+                SELECT ?1, chave, ?3 FROM atributo_definicoes WHERE chave = ?2
+                ON CONFLICT (user_id, chave) DO UPDATE SET valor = excluded.valor
+                "#,
+            )
+            .bind(user_id)
+            .bind(chave)
+            .bind(valor)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Todos os valores guardados, por utilizador e chave (exportação CSV).
+pub async fn todos_valores(db_pool: &SqlitePool) -> AppResult<HashMap<String, HashMap<String, String>>> {
+    let linhas = sqlx::query_as::<_, (String, String, String)>("SELECT user_id, chave, valor FROM user_attributes")
+        .fetch_all(db_pool)
+        .await?;
+    let mut mapa: HashMap<String, HashMap<String, String>> = HashMap::new();
+    for (user_id, chave, valor) in linhas {
+        mapa.entry(user_id).or_default().insert(chave.to_lowercase(), valor);
+    }
+    Ok(mapa)
+}
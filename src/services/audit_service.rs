@@ -0,0 +1,300 @@
+// src/services/audit_service.rs
+use crate::{
+    error::AppResult,
+    models::audit::{AuditEntry, AuditFilter},
+};
+use sqlx::SqlitePool;
+
+// --- Tipos de ação registados ---
+pub const ACAO_USER_CRIADO: &str = "user.criado";
+pub const ACAO_USER_EDITADO: &str = "user.editado";
+pub const ACAO_USER_PASSWORD: &str = "user.password";
+pub const ACAO_USER_ROLES: &str = "user.roles";
+pub const ACAO_USER_FUNDIDO: &str = "user.fundido";
+pub const ACAO_USER_SESSOES: &str = "user.sessoes";
+pub const ACAO_USER_BLOQUEADO: &str = "user.bloqueado";
+pub const ACAO_USER_DESBLOQUEADO: &str = "user.desbloqueado";
+pub const ACAO_USER_FOTO_ENVIADA: &str = "user.foto_enviada";
+pub const ACAO_USER_FOTO_REMOVIDA: &str = "user.foto_removida";
+pub const ACAO_IP_DESBLOQUEADO: &str = "ip.desbloqueado";
+pub const ACAO_TEMP_ROLE_ATRIBUIDA: &str = "temp_role.atribuida";
+pub const ACAO_TEMP_ROLE_REVOGADA: &str = "temp_role.revogada";
+pub const ACAO_ROLE_CRIADA: &str = "role.criada";
+pub const ACAO_ROLE_REMOVIDA: &str = "role.removida";
+pub const ACAO_ROLE_PERMISSOES: &str = "role.permissoes";
+pub const ACAO_GRUPO_CRIADO: &str = "grupo.criado";
+pub const ACAO_GRUPO_REMOVIDO: &str = "grupo.removido";
+pub const ACAO_GRUPO_MEMBROS: &str = "grupo.membros";
+pub const ACAO_GRUPO_POSTO: &str = "grupo.posto";
+pub const ACAO_ATRIBUTO_CRIADO: &str = "atributo.criado";
+pub const ACAO_ATRIBUTO_REMOVIDO: &str = "atributo.removido";
+pub const ACAO_ROLLOVER: &str = "ano.rollover";
+pub const ACAO_CONTADORES_RECONCILIADOS: &str = "contadores.reconciliados";
+pub const ACAO_WEBHOOK_CRIADO: &str = "webhook.criado";
+pub const ACAO_WEBHOOK_ALTERADO: &str = "webhook.alterado";
+pub const ACAO_WEBHOOK_REMOVIDO: &str = "webhook.removido";
+pub const ACAO_BACKUP_CRIADO: &str = "backup.criado";
+pub const ACAO_BACKUP_DESCARREGADO: &str = "backup.descarregado";
+pub const ACAO_DADOS_EXPORTADOS: &str = "dados.exportados";
+pub const ACAO_DADOS_IMPORTADOS: &str = "dados.importados";
+pub const ACAO_TAREFA_EXECUTADA: &str = "tarefa.executada";
+pub const ACAO_ORGANIZACAO_CRIADA: &str = "organizacao.criada";
+pub const ACAO_ORGANIZACAO_ALTERADA: &str = "organizacao.alterada";
+pub const ACAO_MANUTENCAO_ALTERADA: &str = "manutencao.alterada";
+pub const ACAO_REFEICAO_CRIADA: &str = "refeicao.criada";
+pub const ACAO_REFEICAO_ALTERADA: &str = "refeicao.alterada";
+pub const ACAO_CARDAPIO_PUBLICADO: &str = "cardapio.publicado";
+pub const ACAO_PRODUTO_CRIADO: &str = "produto.criado";
+pub const ACAO_PRODUTO_ALTERADO: &str = "produto.alterado";
+pub const ACAO_VENDA_ANULADA: &str = "venda.anulada";
+pub const ACAO_CONTA_CARREGADA: &str = "conta.carregada";
+pub const ACAO_OCORRENCIA_CORRIGIDA: &str = "ocorrencia.corrigida";
+pub const ACAO_LIVRO_FECHADO: &str = "livro.fechado";
+pub const ACAO_LISTA_REVISTA_CRIADA: &str = "lista_revista.criada";
+pub const ACAO_LISTA_REVISTA_ALTERADA: &str = "lista_revista.alterada";
+pub const ACAO_QUARTO_CRIADO: &str = "quarto.criado";
+pub const ACAO_QUARTO_ALTERADO: &str = "quarto.alterado";
+pub const ACAO_QUARTO_ATRIBUIDO: &str = "quarto.atribuido";
+pub const ACAO_MATERIAL_CRIADO: &str = "material.criado";
+pub const ACAO_MATERIAL_ALTERADO: &str = "material.alterado";
+pub const ACAO_BAIXA_REGISTADA: &str = "baixa.registada";
+pub const ACAO_BAIXA_ALTA: &str = "baixa.alta";
+pub const ACAO_BAIXA_APAGADA: &str = "baixa.apagada";
+pub const ACAO_EVENTO_CRIADO: &str = "evento.criado";
+pub const ACAO_EVENTO_ALTERADO: &str = "evento.alterado";
+pub const ACAO_EVENTO_APAGADO: &str = "evento.apagado";
+pub const ACAO_PASTA_CRIADA: &str = "pasta.criada";
+pub const ACAO_PASTA_ALTERADA: &str = "pasta.alterada";
+pub const ACAO_PASTA_APAGADA: &str = "pasta.apagada";
+pub const ACAO_DOCUMENTO_ENVIADO: &str = "documento.enviado";
+pub const ACAO_DOCUMENTO_APAGADO: &str = "documento.apagado";
+pub const ACAO_PROCESSO_ABERTO: &str = "processo.aberto";
+pub const ACAO_PROCESSO_DEFESA: &str = "processo.defesa";
+pub const ACAO_PROCESSO_DECIDIDO: &str = "processo.decidido";
+pub const ACAO_ANTIGUIDADE_DEFINIDA: &str = "antiguidade.definida";
+pub const ACAO_AULA_CRIADA: &str = "aula.criada";
+pub const ACAO_AULA_ALTERADA: &str = "aula.alterada";
+pub const ACAO_AULA_APAGADA: &str = "aula.apagada";
+pub const ACAO_POSTOS_DIURNOS: &str = "postos.diurnos";
+pub const ACAO_PROVA_REGISTADA: &str = "prova.registada";
+pub const ACAO_PROVA_APAGADA: &str = "prova.apagada";
+pub const ACAO_UNIFORME_DEFINIDO: &str = "uniforme.definido";
+pub const ACAO_UNIFORME_APAGADO: &str = "uniforme.apagado";
+pub const ACAO_SUGESTAO_ESTADO: &str = "sugestao.estado";
+pub const ACAO_SUGESTAO_RESPONDIDA: &str = "sugestao.respondida";
+pub const ACAO_FAXINA_AREA_CRIADA: &str = "faxina.area_criada";
+pub const ACAO_FAXINA_AREA_ALTERADA: &str = "faxina.area_alterada";
+pub const ACAO_FAXINA_PUBLICADA: &str = "faxina.publicada";
+pub const ACAO_FAXINA_REABERTA: &str = "faxina.reaberta";
+pub const ACAO_CONCEITO_REGISTADO: &str = "conceito.registado";
+pub const ACAO_CONCEITO_APAGADO: &str = "conceito.apagado";
+pub const ACAO_COMITIVA_CRIADA: &str = "comitiva.criada";
+pub const ACAO_COMITIVA_MEMBROS: &str = "comitiva.membros";
+pub const ACAO_COMITIVA_ENCERRADA: &str = "comitiva.encerrada";
+pub const ACAO_CHAMADA_PLANO_CRIADO: &str = "chamada.plano_criado";
+pub const ACAO_CHAMADA_CASCATA: &str = "chamada.cascata";
+pub const ACAO_CHAMADA_PLANO_APAGADO: &str = "chamada.plano_apagado";
+pub const ACAO_CHAMADA_EXERCICIO_INICIADO: &str = "chamada.exercicio_iniciado";
+pub const ACAO_CHAMADA_EXERCICIO_ENCERRADO: &str = "chamada.exercicio_encerrado";
+pub const ACAO_CHAVE_CRIADA: &str = "chave.criada";
+pub const ACAO_CHAVE_ALTERADA: &str = "chave.alterada";
+pub const ACAO_TFM_REGISTADO: &str = "tfm.registado";
+pub const ACAO_TFM_APAGADO: &str = "tfm.apagado";
+pub const ACAO_CONSULTA_VAGAS_ABERTAS: &str = "consulta.vagas_abertas";
+pub const ACAO_CONSULTA_VAGA_APAGADA: &str = "consulta.vaga_apagada";
+pub const ACAO_CONSULTA_CANCELADA: &str = "consulta.cancelada";
+pub const ACAO_CONSULTA_FALTA: &str = "consulta.falta";
+pub const ACAO_CONSULTA_ATENDIDA: &str = "consulta.atendida";
+pub const ACAO_BIBLIOTECA_LIVRO_CRIADO: &str = "biblioteca.livro_criado";
+pub const ACAO_BIBLIOTECA_LIVRO_ALTERADO: &str = "biblioteca.livro_alterado";
+pub const ACAO_ENQUETE_CRIADA: &str = "enquete.criada";
+pub const ACAO_ENQUETE_ENCERRADA: &str = "enquete.encerrada";
+
+/// Todas as ações conhecidas (usado no filtro da página de auditoria).
+pub const ACOES: &[&str] = &[
+    ACAO_USER_CRIADO,
+    ACAO_USER_EDITADO,
+    ACAO_USER_PASSWORD,
+    ACAO_USER_ROLES,
+    ACAO_USER_FUNDIDO,
+    ACAO_USER_SESSOES,
+    ACAO_USER_BLOQUEADO,
+    ACAO_USER_DESBLOQUEADO,
+    ACAO_USER_FOTO_ENVIADA,
+    ACAO_USER_FOTO_REMOVIDA,
+    ACAO_IP_DESBLOQUEADO,
+    ACAO_TEMP_ROLE_ATRIBUIDA,
+    ACAO_TEMP_ROLE_REVOGADA,
+    ACAO_ROLE_CRIADA,
+    ACAO_ROLE_REMOVIDA,
+    ACAO_ROLE_PERMISSOES,
+    ACAO_GRUPO_CRIADO,
+    ACAO_GRUPO_REMOVIDO,
+    ACAO_GRUPO_MEMBROS,
+    ACAO_GRUPO_POSTO,
+    ACAO_ATRIBUTO_CRIADO,
+    ACAO_ATRIBUTO_REMOVIDO,
+    ACAO_ROLLOVER,
+    ACAO_CONTADORES_RECONCILIADOS,
+    ACAO_WEBHOOK_CRIADO,
+    ACAO_WEBHOOK_ALTERADO,
+    ACAO_WEBHOOK_REMOVIDO,
+    ACAO_BACKUP_CRIADO,
+    ACAO_BACKUP_DESCARREGADO,
+    ACAO_DADOS_EXPORTADOS,
+    ACAO_DADOS_IMPORTADOS,
+    ACAO_TAREFA_EXECUTADA,
+    ACAO_ORGANIZACAO_CRIADA,
+    ACAO_ORGANIZACAO_ALTERADA,
+    ACAO_MANUTENCAO_ALTERADA,
+    ACAO_REFEICAO_CRIADA,
+    ACAO_REFEICAO_ALTERADA,
+    ACAO_CARDAPIO_PUBLICADO,
+    ACAO_PRODUTO_CRIADO,
+    ACAO_PRODUTO_ALTERADO,
+    ACAO_VENDA_ANULADA,
+    ACAO_CONTA_CARREGADA,
+    ACAO_OCORRENCIA_CORRIGIDA,
+    ACAO_LIVRO_FECHADO,
+    ACAO_LISTA_REVISTA_CRIADA,
+    ACAO_LISTA_REVISTA_ALTERADA,
+    ACAO_QUARTO_CRIADO,
+    ACAO_QUARTO_ALTERADO,
+    ACAO_QUARTO_ATRIBUIDO,
+    ACAO_MATERIAL_CRIADO,
+    ACAO_MATERIAL_ALTERADO,
+    ACAO_BAIXA_REGISTADA,
+    ACAO_BAIXA_ALTA,
+    ACAO_BAIXA_APAGADA,
+    ACAO_EVENTO_CRIADO,
+    ACAO_EVENTO_ALTERADO,
+    ACAO_EVENTO_APAGADO,
+    ACAO_PASTA_CRIADA,
+    ACAO_PASTA_ALTERADA,
+    ACAO_PASTA_APAGADA,
+    ACAO_DOCUMENTO_ENVIADO,
+    ACAO_DOCUMENTO_APAGADO,
+    ACAO_PROCESSO_ABERTO,
+    ACAO_PROCESSO_DEFESA,
+    ACAO_PROCESSO_DECIDIDO,
+    ACAO_ANTIGUIDADE_DEFINIDA,
+    ACAO_AULA_CRIADA,
+    ACAO_AULA_ALTERADA,
+    ACAO_AULA_APAGADA,
+    ACAO_POSTOS_DIURNOS,
+    ACAO_PROVA_REGISTADA,
+    ACAO_PROVA_APAGADA,
+    ACAO_UNIFORME_DEFINIDO,
+    ACAO_UNIFORME_APAGADO,
+    ACAO_SUGESTAO_ESTADO,
+    ACAO_SUGESTAO_RESPONDIDA,
+    ACAO_FAXINA_AREA_CRIADA,
+    ACAO_FAXINA_AREA_ALTERADA,
+    ACAO_FAXINA_PUBLICADA,
+    ACAO_FAXINA_REABERTA,
+    ACAO_CONCEITO_REGISTADO,
+    ACAO_CONCEITO_APAGADO,
+    ACAO_COMITIVA_CRIADA,
+    ACAO_COMITIVA_MEMBROS,
+    ACAO_COMITIVA_ENCERRADA,
+    ACAO_CHAMADA_PLANO_CRIADO,
+    ACAO_CHAMADA_CASCATA,
+    ACAO_CHAMADA_PLANO_APAGADO,
+    ACAO_CHAMADA_EXERCICIO_INICIADO,
+    ACAO_CHAMADA_EXERCICIO_ENCERRADO,
+    ACAO_CHAVE_CRIADA,
+    ACAO_CHAVE_ALTERADA,
+    ACAO_TFM_REGISTADO,
+    ACAO_TFM_APAGADO,
+    ACAO_CONSULTA_VAGAS_ABERTAS,
+    ACAO_CONSULTA_VAGA_APAGADA,
+    ACAO_CONSULTA_CANCELADA,
+    ACAO_CONSULTA_FALTA,
+    ACAO_CONSULTA_ATENDIDA,
+    ACAO_BIBLIOTECA_LIVRO_CRIADO,
+    ACAO_BIBLIOTECA_LIVRO_ALTERADO,
+    ACAO_ENQUETE_CRIADA,
+    ACAO_ENQUETE_ENCERRADA,
+];
+
+/// Condições dos filtros da listagem (partilhadas pela página e pela contagem).
+const WHERE_FILTRO: &str = r#"
+        WHERE (?1 IS NULL OR actor_id = ?1)
+          AND (?2 IS NULL OR acao = ?2)
+          AND (?3 IS NULL OR alvo = ?3)
+          AND (?4 IS NULL OR date(criado_em) >= date(?4))
+          AND (?5 IS NULL OR date(criado_em) <= date(?5))
+"#;
+
+/// Regista uma ação administrativa.
+/// A auditoria nunca deve impedir a ação principal: em caso de falha apenas loga o erro.
+pub async fn registar(
+    db_pool: &SqlitePool,
+    actor_id: &str,
+    acao: &str,
+    alvo: Option<&str>,
+    detalhes: Option<&str>,
+) {
+    tracing::debug!("Auditoria: {} por {} (alvo: {:?})", acao, actor_id, alvo);
+    let result = sqlx::query(
+        "INSERT INTO audit_log (actor_id, acao, alvo, detalhes) VALUES (?1, ?2, ?3, ?4)",
+    )
+    .bind(actor_id)
+    .bind(acao)
+    .bind(alvo)
+    .bind(detalhes)
+    .execute(db_pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("Falha ao registar auditoria '{}' por {}: {:?}", acao, actor_id, e);
+    }
+}
+
+/// Lista uma página das entradas de auditoria (mais recentes primeiro) que correspondem aos filtros,
+/// com o total de entradas que correspondem (para a paginação).
+pub async fn listar(db_pool: &SqlitePool, filtro: &AuditFilter, limite: i64, offset: i64) -> AppResult<(Vec<AuditEntry>, i64)> {
+    // Filtros vazios na query string chegam como Some("") -> tratamos como None
+    let limpo = |v: &Option<String>| v.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+    let (actor, acao, alvo, de, ate) =
+        (limpo(&filtro.actor), limpo(&filtro.acao), limpo(&filtro.alvo), limpo(&filtro.de), limpo(&filtro.ate));
+
+    let entries = sqlx::query_as::<_, AuditEntry>(&format!(
+        "SELECT actor_id, acao, alvo, detalhes, criado_em FROM audit_log {} ORDER BY id DESC LIMIT ?6 OFFSET ?7",
+        WHERE_FILTRO
+    ))
+    .bind(&actor)
+    .bind(&acao)
+    .bind(&alvo)
+    .bind(&de)
+    .bind(&ate)
+    .bind(limite)
+    .bind(offset)
+    .fetch_all(db_pool)
+    .await?;
+
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM audit_log {}", WHERE_FILTRO))
+        .bind(&actor)
+        .bind(&acao)
+        .bind(&alvo)
+        .bind(&de)
+        .bind(&ate)
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok((entries, total))
+}
+
+/// Monta um resumo "campo: 'antes' → 'depois'" apenas com os campos que mudaram.
+pub fn resumo_diff(campos: &[(&str, String, String)]) -> Option<String> {
+    let partes: Vec<String> = campos
+        .iter()
+        .filter(|(_, antes, depois)| antes != depois)
+        .map(|(campo, antes, depois)| format!("{}: '{}' → '{}'", campo, antes, depois))
+        .collect();
+    if partes.is_empty() {
+        None
+    } else {
+        Some(partes.join("; "))
+    }
+}
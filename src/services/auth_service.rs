@@ -1,45 +1,244 @@
 // src/services/auth_service.rs
-use crate::{
-    error::{AppError, AppResult},
-    models::user::User, // User agora espera created_at: Option<String>
+use crate::error::{AppError, AppResult};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
 };
 use sqlx::SqlitePool;
+use std::sync::OnceLock;
 
+// Esquemas de hash (coluna users.password_esquema)
+pub const ESQUEMA_BCRYPT: &str = "bcrypt";
+pub const ESQUEMA_ARGON2ID: &str = "argon2id";
+pub const ESQUEMA_ARGON2ID_PEPPER: &str = "argon2id+pepper"; // Argon2id com o segredo do servidor
 
-// ... (verify_password e hash_password permanecem iguais) ...
-/// Verifica se a senha fornecida corresponde ao hash guardado.
-pub async fn verify_password(password: &str, stored_hash: &str) -> AppResult<bool> {
+/// Tamanho mínimo do pepper (bytes).
+const MIN_PEPPER: usize = 16;
+
+/// Parâmetros de hashing, lidos do ambiente e validados no arranque.
+#[derive(Clone)]
+pub struct HashConfig {
+    pub m_cost: u32, // Memória (KiB)
+    pub t_cost: u32, // Iterações
+    pub p_cost: u32, // Paralelismo
+    pepper: Option<Vec<u8>>,
+}
+
+impl std::fmt::Debug for HashConfig {
+    // Nunca escrever o pepper nos logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HashConfig")
+            .field("m_cost", &self.m_cost)
+            .field("t_cost", &self.t_cost)
+            .field("p_cost", &self.p_cost)
+            .field("pepper", &self.pepper.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+impl Default for HashConfig {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+            pepper: None,
+        }
+    }
+}
+
+impl HashConfig {
+    /// Lê PASSWORD_ARGON2_M_COST / _T_COST / _P_COST e PASSWORD_PEPPER (todos opcionais).
+    /// Valores inválidos impedem o arranque, em vez de falharem no primeiro login.
+    pub fn from_env() -> Result<Self, String> {
+        let padrao = Self::default();
+        let numero = |nome: &str, omissao: u32| -> Result<u32, String> {
+            match std::env::var(nome) {
+                Ok(v) if !v.trim().is_empty() => v.trim().parse().map_err(|_| format!("{} inválido: '{}'", nome, v)),
+                _ => Ok(omissao),
+            }
+        };
+        let config = Self {
+            m_cost: numero("PASSWORD_ARGON2_M_COST", padrao.m_cost)?,
+            t_cost: numero("PASSWORD_ARGON2_T_COST", padrao.t_cost)?,
+            p_cost: numero("PASSWORD_ARGON2_P_COST", padrao.p_cost)?,
+            pepper: std::env::var("PASSWORD_PEPPER").ok().filter(|p| !p.is_empty()).map(String::into_bytes),
+        };
+
+        if let Some(pepper) = &config.pepper {
+            if pepper.len() < MIN_PEPPER {
+                return Err(format!("PASSWORD_PEPPER demasiado curto (mínimo {} bytes)", MIN_PEPPER));
+            }
+        }
+        // Constrói o hasher uma vez para validar os parâmetros
+        config.hasher(config.pepper.is_some()).map_err(|e| format!("Parâmetros Argon2 inválidos: {}", e))?;
+        Ok(config)
+    }
+
+    fn hasher(&self, com_pepper: bool) -> Result<Argon2<'_>, argon2::Error> {
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, None)?;
+        match (&self.pepper, com_pepper) {
+            (Some(pepper), true) => Argon2::new_with_secret(pepper, Algorithm::Argon2id, Version::V0x13, params),
+            _ => Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params)),
+        }
+    }
+
+    /// Esquema com que os novos hashes são gerados.
+    pub fn esquema(&self) -> &'static str {
+        if self.pepper.is_some() {
+            ESQUEMA_ARGON2ID_PEPPER
+        } else {
+            ESQUEMA_ARGON2ID
+        }
+    }
+}
+
+static CONFIG: OnceLock<HashConfig> = OnceLock::new();
+
+/// Validade das senhas em dias (PASSWORD_MAX_AGE_DAYS). None = as senhas não expiram (por omissão).
+pub fn validade_senha_from_env() -> Result<Option<i64>, String> {
+    match std::env::var("PASSWORD_MAX_AGE_DAYS") {
+        Ok(v) if !v.trim().is_empty() => v
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|d| *d >= 1)
+            .map(Some)
+            .ok_or(format!("PASSWORD_MAX_AGE_DAYS inválido: '{}'", v)),
+        _ => Ok(None),
+    }
+}
+
+/// Define os parâmetros de hashing (chamado uma vez, no arranque).
+pub fn configurar(config: HashConfig) {
+    tracing::info!(
+        "🔐 Hashing de senhas: Argon2id (m={} KiB, t={}, p={}), pepper {}.",
+        config.m_cost,
+        config.t_cost,
+        config.p_cost,
+        if config.pepper.is_some() { "ativo" } else { "desativado" }
+    );
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("Configuração de hashing já definida; ignorada.");
+    }
+}
+
+fn config() -> &'static HashConfig {
+    CONFIG.get_or_init(HashConfig::default)
+}
+
+/// Esquema com que os novos hashes são gerados (gravar em users.password_esquema).
+pub fn esquema_atual() -> &'static str {
+    config().esquema()
+}
+
+/// Verifica se a senha fornecida corresponde ao hash guardado, segundo o esquema registado.
+pub async fn verify_password(password: &str, stored_hash: &str, esquema: &str) -> AppResult<bool> {
     let password = password.to_string();
     let stored_hash = stored_hash.to_string();
+    let esquema = esquema.to_string();
     tokio::task::spawn_blocking(move || {
-        tracing::debug!("Verificando hash bcrypt...");
-        bcrypt::verify(&password, &stored_hash)
+        if esquema == ESQUEMA_BCRYPT {
+            tracing::debug!("Verificando hash bcrypt (legado)...");
+            return bcrypt::verify(&password, &stored_hash).map_err(|e| {
+                tracing::error!("Erro bcrypt ao verificar senha: {:?}", e);
+                AppError::PasswordHashingError
+            });
+        }
+
+        tracing::debug!("Verificando hash {}...", esquema);
+        let com_pepper = esquema == ESQUEMA_ARGON2ID_PEPPER;
+        if com_pepper && config().pepper.is_none() {
+            tracing::error!("Hash com pepper na DB, mas PASSWORD_PEPPER não está definido!");
+            return Err(AppError::PasswordHashingError);
+        }
+        let parsed = PasswordHash::new(&stored_hash).map_err(|e| {
+            tracing::error!("Hash Argon2 inválido na DB: {:?}", e);
+            AppError::PasswordHashingError
+        })?;
+        // Os parâmetros (m, t, p) usados na verificação são os do próprio hash
+        let hasher = config().hasher(com_pepper).map_err(|e| {
+            tracing::error!("Erro Argon2 ao preparar verificação: {:?}", e);
+            AppError::PasswordHashingError
+        })?;
+        match hasher.verify_password(password.as_bytes(), &parsed) {
+            Ok(()) => Ok(true),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(e) => {
+                tracing::error!("Erro Argon2 ao verificar senha: {:?}", e);
+                Err(AppError::PasswordHashingError)
+            }
+        }
     })
     .await
     .map_err(|e| {
         tracing::error!("Erro na task spawn_blocking (verify_password): {:?}", e);
         AppError::InternalServerError
     })?
-    .map_err(|e| {
-        tracing::error!("Erro bcrypt ao verificar senha: {:?}", e);
-        AppError::PasswordHashingError
-    })
 }
 
-/// Gera um hash bcrypt para uma senha.
+/// Gera um hash Argon2id (salt aleatório, formato PHC) com os parâmetros configurados.
+/// O esquema correspondente é `esquema_atual()`.
 pub async fn hash_password(password: &str) -> AppResult<String> {
     let password = password.to_string();
     tokio::task::spawn_blocking(move || {
-        tracing::debug!("Gerando hash bcrypt...");
-        bcrypt::hash(&password, bcrypt::DEFAULT_COST)
+        tracing::debug!("Gerando hash Argon2id...");
+        let config = config();
+        let hasher = config.hasher(config.pepper.is_some()).map_err(|e| {
+            tracing::error!("Erro Argon2 ao preparar hash: {:?}", e);
+            AppError::PasswordHashingError
+        })?;
+        let salt = SaltString::generate(&mut OsRng);
+        hasher
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| {
+                tracing::error!("Erro Argon2 ao gerar hash: {:?}", e);
+                AppError::PasswordHashingError
+            })
     })
     .await
     .map_err(|e| {
         tracing::error!("Erro na task spawn_blocking (hash_password): {:?}", e);
         AppError::InternalServerError
     })?
-    .map_err(|e| {
-        tracing::error!("Erro bcrypt ao gerar hash: {:?}", e);
-        AppError::PasswordHashingError
-    })
-}
\ No newline at end of file
+}
+
+/// O hash guardado foi gerado com outro esquema ou outros parâmetros que os atuais?
+fn precisa_rehash(stored_hash: &str, esquema: &str) -> bool {
+    let config = config();
+    let params = format!("$m={},t={},p={}$", config.m_cost, config.t_cost, config.p_cost);
+    esquema != config.esquema() || !stored_hash.contains(&params)
+}
+
+/// Após um login bem-sucedido: se o hash guardado for de um esquema antigo (bcrypt, sem pepper)
+/// ou de parâmetros diferentes dos configurados, volta a calcular o hash com a senha
+/// (que só temos neste momento) e grava-o. Falhas apenas são logadas: o login já foi aceite.
+pub async fn rehash_se_necessario(db_pool: &SqlitePool, user_id: &str, password: &str, stored_hash: &str, esquema: &str) {
+    if !precisa_rehash(stored_hash, esquema) {
+        return;
+    }
+
+    let novo_hash = match hash_password(password).await {
+        Ok(h) => h,
+        Err(e) => {
+            tracing::error!("Falha ao recalcular o hash da senha de {}: {:?}", user_id, e);
+            return;
+        }
+    };
+    // A condição no hash antigo evita sobrepor uma alteração de senha concorrente
+    let result = sqlx::query(
+        "UPDATE users SET password_hash = ?1, password_esquema = ?2 WHERE id = ?3 AND password_hash = ?4",
+    )
+    .bind(&novo_hash)
+    .bind(esquema_atual())
+    .bind(user_id)
+    .bind(stored_hash)
+    .execute(db_pool)
+    .await;
+
+    match result {
+        Ok(_) => tracing::info!("🔐 Hash da senha de {} atualizado ({} → {}).", user_id, esquema, esquema_atual()),
+        Err(e) => tracing::error!("Falha ao gravar o novo hash de {}: {:?}", user_id, e),
+    }
+}
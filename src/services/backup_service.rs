@@ -0,0 +1,271 @@
+// src/services/backup_service.rs
+//! Cópias de segurança da base de dados: `VACUUM INTO` (cópia consistente com a aplicação a correr)
+//! para BACKUP_DIR, de BACKUP_INTERVAL_HOURS em BACKUP_INTERVAL_HOURS, mantendo as BACKUP_KEEP mais recentes.
+//! Cada cópia (automática ou pedida na administração) fica registada na tabela `backups`.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::backup::Backup,
+    tempo,
+};
+use sqlx::{SqliteExecutor, SqlitePool};
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+// Origem da cópia (coluna `origem`)
+pub const ORIGEM_AUTOMATICO: &str = "automatico";
+pub const ORIGEM_MANUAL: &str = "manual";
+
+const DIR_PADRAO: &str = "data/backups";
+const INTERVALO_HORAS_PADRAO: i64 = 24;
+const MANTER_PADRAO: i64 = 7;
+/// De quanto em quanto tempo a tarefa verifica se já é altura de uma nova cópia.
+const INTERVALO_VERIFICACAO: Duration = Duration::from_secs(10 * 60);
+
+/// Configuração das cópias de segurança (lida do ambiente no arranque).
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    pub intervalo_horas: i64, // 0 = só cópias manuais
+    pub manter: i64,          // Cópias bem-sucedidas guardadas no disco
+}
+
+impl BackupConfig {
+    /// Lê BACKUP_DIR (por omissão "data/backups"), BACKUP_INTERVAL_HOURS (24; 0 desativa as cópias automáticas)
+    /// e BACKUP_KEEP (7).
+    pub fn from_env() -> Result<Self, String> {
+        let var = |nome: &str| std::env::var(nome).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let numero = |nome: &str, padrao: i64, minimo: i64| match var(nome) {
+            Some(v) => v
+                .parse::<i64>()
+                .ok()
+                .filter(|n| *n >= minimo)
+                .ok_or(format!("{} inválido: '{}'", nome, v)),
+            None => Ok(padrao),
+        };
+        Ok(Self {
+            dir: PathBuf::from(var("BACKUP_DIR").unwrap_or_else(|| DIR_PADRAO.to_string())),
+            intervalo_horas: numero("BACKUP_INTERVAL_HOURS", INTERVALO_HORAS_PADRAO, 0)?,
+            manter: numero("BACKUP_KEEP", MANTER_PADRAO, 1)?,
+        })
+    }
+}
+
+static CONFIG: OnceLock<BackupConfig> = OnceLock::new();
+/// Uma cópia de cada vez (a automática e uma pedida pelo admin não se sobrepõem).
+static EM_CURSO: Mutex<()> = Mutex::const_new(());
+
+/// Define a configuração das cópias (chamado uma vez, no arranque).
+pub fn configurar(config: BackupConfig) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("Configuração das cópias de segurança já definida; ignorada.");
+    }
+}
+
+/// Configuração das cópias de segurança (a predefinida, se `configurar` não tiver sido chamado).
+pub fn config() -> &'static BackupConfig {
+    CONFIG.get_or_init(|| BackupConfig {
+        dir: PathBuf::from(DIR_PADRAO),
+        intervalo_horas: INTERVALO_HORAS_PADRAO,
+        manter: MANTER_PADRAO,
+    })
+}
+
+/// Faz uma cópia da base de dados agora e regista o resultado (também quando falha).
+/// Devolve o registo da cópia; em caso de falha, o erro com a mensagem para o admin.
+pub async fn criar(db_pool: &SqlitePool, origem: &str, criado_por: Option<&str>) -> AppResult<Backup> {
+    let config = config();
+    let _vez = EM_CURSO.lock().await;
+
+    let ficheiro = format!("mercal2-{}.db", tempo::agora().format("%Y%m%d-%H%M%S"));
+    let inicio = Instant::now();
+    let resultado = copiar(db_pool, &config.dir, &ficheiro).await;
+    let duracao_ms = inicio.elapsed().as_millis() as i64;
+
+    let (estado, tamanho, erro) = match &resultado {
+        Ok(tamanho) => ("ok", Some(*tamanho), None),
+        Err(e) => ("falhado", None, Some(e.clone())),
+    };
+    let id: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO backups (ficheiro, origem, criado_por, estado, tamanho, duracao_ms, erro)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        RETURNING id
+        "#,
+    )
+    .bind(&ficheiro)
+    .bind(origem)
+    .bind(criado_por)
+    .bind(estado)
+    .bind(tamanho)
+    .bind(duracao_ms)
+    .bind(&erro)
+    .fetch_one(db_pool)
+    .await?;
+
+    match resultado {
+        Ok(tamanho) => {
+            tracing::info!("💾 Cópia de segurança {} criada ({} bytes, {} ms).", ficheiro, tamanho, duracao_ms);
+            if let Err(e) = rodar(db_pool, config).await {
+                tracing::error!("Erro na rotação das cópias de segurança: {:?}", e);
+            }
+        }
+        Err(e) => {
+            tracing::error!("❌ Cópia de segurança {} falhou: {}", ficheiro, e);
+            return Err(AppError::Conflict(format!("A cópia de segurança falhou: {}", e)));
+        }
+    }
+    obter(db_pool, id).await?.ok_or(AppError::InternalServerError)
+}
+
+/// Cópia feita antes de alterar o esquema (`mercal2 migrate run|revert` e migrações no arranque), para voltar
+/// atrás se a migração correr mal. Fica em BACKUP_DIR mas fora da tabela `backups` (que pode ainda não existir)
+/// e da rotação. `etiqueta` identifica a operação no nome do ficheiro (ex: "antes-<versão>").
+/// Devolve o caminho da cópia.
+pub async fn copiar_antes_de_migrar(conn: impl SqliteExecutor<'_>, etiqueta: &str) -> AppResult<PathBuf> {
+    let config = config();
+    let ficheiro = format!("mercal2-{}-{}.db", etiqueta, tempo::agora().format("%Y%m%d-%H%M%S"));
+    match copiar(conn, &config.dir, &ficheiro).await {
+        Ok(tamanho) => {
+            tracing::info!("💾 Cópia antes de alterar o esquema: {} ({} bytes).", ficheiro, tamanho);
+            Ok(config.dir.join(ficheiro))
+        }
+        Err(e) => Err(AppError::Conflict(format!("A cópia antes da migração falhou: {}", e))),
+    }
+}
+
+/// `VACUUM INTO` para um ficheiro temporário, renomeado no fim (nunca fica uma cópia a meio com o nome final).
+/// Devolve o tamanho da cópia.
+async fn copiar(db: impl SqliteExecutor<'_>, dir: &Path, ficheiro: &str) -> Result<i64, String> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| format!("não foi possível criar {}: {}", dir.display(), e))?;
+    let destino = dir.join(ficheiro);
+    if tokio::fs::try_exists(&destino).await.unwrap_or(false) {
+        return Err(format!("{} já existe", ficheiro));
+    }
+    let temporario = dir.join(format!("{}.parcial", ficheiro));
+    let _ = tokio::fs::remove_file(&temporario).await; // Restos de uma cópia interrompida
+
+    sqlx::query("VACUUM INTO ?1")
+        .bind(temporario.to_string_lossy().to_string())
+        .execute(db)
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::fs::rename(&temporario, &destino).await.map_err(|e| e.to_string())?;
+    let tamanho = tokio::fs::metadata(&destino).await.map_err(|e| e.to_string())?.len();
+    Ok(tamanho as i64)
+}
+
+/// Apaga do disco as cópias bem-sucedidas além das `manter` mais recentes (o registo fica, marcado como removido).
+async fn rodar(db_pool: &SqlitePool, config: &BackupConfig) -> AppResult<()> {
+    let antigas: Vec<(i64, String)> = sqlx::query_as(
+        r#"
+        SELECT id, ficheiro FROM backups
+        WHERE estado = 'ok' AND removido_em IS NULL
+        ORDER BY id DESC
+        LIMIT -1 OFFSET ?1
+        "#,
+    )
+    .bind(config.manter)
+    .fetch_all(db_pool)
+    .await?;
+
+    for (id, ficheiro) in antigas {
+        match tokio::fs::remove_file(config.dir.join(&ficheiro)).await {
+            Ok(()) => tracing::info!("🗑️ Cópia de segurança antiga {} apagada.", ficheiro),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                tracing::warn!("Não foi possível apagar a cópia {}: {}", ficheiro, e);
+                continue;
+            }
+        }
+        sqlx::query("UPDATE backups SET removido_em = datetime('now') WHERE id = ?1")
+            .bind(id)
+            .execute(db_pool)
+            .await?;
+    }
+    Ok(())
+}
+
+const SELECT_BACKUP: &str = r#"
+    SELECT id, ficheiro, origem, criado_por, estado, tamanho, duracao_ms, erro,
+           criado_em, removido_em IS NOT NULL AS removido
+    FROM backups
+"#;
+
+/// Hora da cópia no fuso da aplicação (a coluna está em UTC).
+fn no_fuso(backup: Backup) -> Backup {
+    Backup { criado_em: tempo::formatar(&backup.criado_em, tempo::FORMATO_DATA_HORA), ..backup }
+}
+
+async fn obter(db_pool: &SqlitePool, id: i64) -> AppResult<Option<Backup>> {
+    let backup = sqlx::query_as::<_, Backup>(&format!("{} WHERE id = ?1", SELECT_BACKUP))
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?;
+    Ok(backup.map(no_fuso))
+}
+
+/// Cópias mais recentes (registo da página de administração).
+pub async fn listar(db_pool: &SqlitePool, limite: i64) -> AppResult<Vec<Backup>> {
+    let backups = sqlx::query_as::<_, Backup>(&format!("{} ORDER BY id DESC LIMIT ?1", SELECT_BACKUP))
+        .bind(limite)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(backups.into_iter().map(no_fuso).collect())
+}
+
+/// Conteúdo de uma cópia, para download: (nome do ficheiro, bytes).
+pub async fn conteudo(db_pool: &SqlitePool, id: i64) -> AppResult<(String, Vec<u8>)> {
+    let backup = obter(db_pool, id)
+        .await?
+        .filter(Backup::disponivel)
+        .ok_or_else(|| AppError::NotFound(format!("Cópia de segurança {} não disponível.", id)))?;
+    // O nome vem da base de dados mas foi gerado por `criar`: não sai de BACKUP_DIR
+    if backup.ficheiro.contains(['/', '\\']) || backup.ficheiro.starts_with('.') {
+        return Err(AppError::NotFound(format!("Cópia de segurança {} não disponível.", id)));
+    }
+    let bytes = tokio::fs::read(config().dir.join(&backup.ficheiro)).await.map_err(|e| {
+        tracing::error!("Erro ao ler a cópia {}: {}", backup.ficheiro, e);
+        AppError::NotFound(format!("O ficheiro da cópia {} não foi encontrado.", id))
+    })?;
+    Ok((backup.ficheiro, bytes))
+}
+
+/// Inicia a tarefa das cópias automáticas: faz uma cópia sempre que a última bem-sucedida tiver mais de
+/// `intervalo_horas` (também logo no arranque, se for o caso). Não faz nada se o intervalo for 0.
+pub fn iniciar(db_pool: SqlitePool) {
+    let intervalo_horas = config().intervalo_horas;
+    if intervalo_horas == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            match copia_em_falta(&db_pool, intervalo_horas).await {
+                Ok(true) => {
+                    // O erro já fica registado (tabela e log)
+                    let _ = criar(&db_pool, ORIGEM_AUTOMATICO, None).await;
+                }
+                Ok(false) => {}
+                Err(e) => tracing::error!("Erro ao verificar as cópias de segurança: {:?}", e),
+            }
+            tokio::time::sleep(INTERVALO_VERIFICACAO).await;
+        }
+    });
+}
+
+/// Já passou o intervalo desde a última cópia bem-sucedida?
+async fn copia_em_falta(db_pool: &SqlitePool, intervalo_horas: i64) -> AppResult<bool> {
+    let em_falta = sqlx::query_scalar(
+        "SELECT NOT EXISTS (SELECT 1 FROM backups WHERE estado = 'ok' AND criado_em > datetime('now', ?1))",
+    )
+    .bind(format!("-{} hours", intervalo_horas))
+    .fetch_one(db_pool)
+    .await?;
+    Ok(em_falta)
+}
@@ -0,0 +1,209 @@
+// src/services/baixa_service.rs
+//! Baixas médicas registadas pela secção de saúde: o período (datas inclusive) e as restrições.
+//! Enquanto uma baixa está em vigor o utilizador não entra na geração da escala nem pode aceitar
+//! trocas para esses dias (ver `escala_service`), e aparece assinalado na lista de presença.
+//! A alta antecipa o fim da baixa para a véspera.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::baixa::Baixa,
+};
+use chrono::{Duration, NaiveDate};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+
+/// Restrições que se podem indicar numa baixa (código, descrição).
+pub const RESTRICOES: &[(&str, &str)] = &[
+    ("servico", "Dispensa de serviço"),
+    ("servico_externo", "Dispensa de serviço externo"),
+    ("educacao_fisica", "Dispensa de educação física"),
+    ("formatura", "Dispensa de formatura"),
+    ("fardamento", "Dispensa de fardamento/calçado"),
+    ("repouso", "Repouso no alojamento"),
+];
+/// Duração máxima de uma baixa (dias).
+pub const MAX_DIAS: i64 = 365;
+/// Baixas terminadas mostradas no histórico.
+pub const TERMINADAS_RECENTES: i64 = 50;
+
+/// Descrição das restrições de uma baixa ("Baixa médica" se não tiver nenhuma indicada).
+pub fn descrever(baixa: &Baixa) -> String {
+    let descricoes: Vec<&str> = baixa
+        .codigos()
+        .map(|codigo| RESTRICOES.iter().find(|(c, _)| *c == codigo).map_or(codigo, |(_, d)| *d))
+        .collect();
+    if descricoes.is_empty() {
+        "Baixa médica".to_string()
+    } else {
+        descricoes.join("; ")
+    }
+}
+
+/// Baixas da organização. `?2` = só as deste utilizador; `?3`/`?4` = só as que terminam a partir de /
+/// antes deste dia.
+const SQL_BAIXAS: &str = r#"
+    SELECT b.id, b.user_id, u.name, u.turma, b.data_inicio, b.data_fim, b.restricoes, b.observacoes,
+           COALESCE(r.name, b.registada_por) AS registada_por, b.registada_em
+    FROM baixas b
+    JOIN users u ON u.id = b.user_id
+    LEFT JOIN users r ON r.id = b.registada_por
+    WHERE b.organizacao_id = ?1
+      AND (?2 IS NULL OR b.user_id = ?2)
+      AND (?3 IS NULL OR b.data_fim >= ?3)
+      AND (?4 IS NULL OR b.data_fim < ?4)
+    ORDER BY CASE WHEN ?3 IS NOT NULL THEN b.data_inicio END, b.data_fim DESC, u.name
+    LIMIT ?5
+"#;
+
+/// Baixas em vigor ou futuras (que ainda não terminaram em `hoje`), pela data de início.
+pub async fn vigentes(db_pool: &SqlitePool, organizacao_id: i64, user_id: Option<&str>, hoje: NaiveDate) -> AppResult<Vec<Baixa>> {
+    let baixas = sqlx::query_as::<_, Baixa>(SQL_BAIXAS)
+        .bind(organizacao_id)
+        .bind(user_id)
+        .bind(hoje.to_string())
+        .bind(None::<String>)
+        .bind(i64::MAX)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(baixas)
+}
+
+/// Últimas baixas terminadas antes de `hoje` (as mais recentes primeiro).
+pub async fn terminadas(db_pool: &SqlitePool, organizacao_id: i64, user_id: Option<&str>, hoje: NaiveDate, limite: i64) -> AppResult<Vec<Baixa>> {
+    let baixas = sqlx::query_as::<_, Baixa>(SQL_BAIXAS)
+        .bind(organizacao_id)
+        .bind(user_id)
+        .bind(None::<String>)
+        .bind(hoje.to_string())
+        .bind(limite)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(baixas)
+}
+
+/// Restrições das baixas em vigor em `hoje`, por utilizador (lista de presença).
+pub async fn ativas(db_pool: &SqlitePool, organizacao_id: i64, hoje: NaiveDate) -> AppResult<HashMap<String, String>> {
+    let dia = hoje.to_string();
+    let mut ativas: HashMap<String, String> = HashMap::new();
+    for baixa in vigentes(db_pool, organizacao_id, None, hoje).await? {
+        if !baixa.ativa(&dia) {
+            continue;
+        }
+        // Duas baixas sobrepostas: juntam-se as restrições
+        let descricao = descrever(&baixa);
+        ativas
+            .entry(baixa.user_id)
+            .and_modify(|d| {
+                d.push_str("; ");
+                d.push_str(&descricao);
+            })
+            .or_insert(descricao);
+    }
+    Ok(ativas)
+}
+
+/// O utilizador tem uma baixa em vigor no dia `data` ('YYYY-MM-DD')? Usado nas trocas da escala,
+/// dentro da transação do pedido.
+pub async fn de_baixa(conn: &mut SqliteConnection, user_id: &str, data: &str) -> AppResult<bool> {
+    let de_baixa = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM baixas WHERE user_id = ?1 AND ?2 BETWEEN data_inicio AND data_fim)")
+        .bind(user_id)
+        .bind(data)
+        .fetch_one(conn)
+        .await?;
+    Ok(de_baixa)
+}
+
+/// Uma baixa pelo id (da organização).
+async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Baixa> {
+    sqlx::query_as::<_, Baixa>(
+        r#"
+        SELECT b.id, b.user_id, u.name, u.turma, b.data_inicio, b.data_fim, b.restricoes, b.observacoes,
+               b.registada_por, b.registada_em
+        FROM baixas b JOIN users u ON u.id = b.user_id
+        WHERE b.id = ?1 AND b.organizacao_id = ?2
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Baixa {} não encontrada.", id)))
+}
+
+/// Regista uma baixa (período já validado; restrições da lista `RESTRICOES`). Devolve o ID.
+#[allow(clippy::too_many_arguments)]
+pub async fn registar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: &str,
+    data_inicio: NaiveDate,
+    data_fim: NaiveDate,
+    restricoes: &[String],
+    observacoes: &str,
+    operador_id: &str,
+) -> AppResult<i64> {
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(db_pool)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    if (data_fim - data_inicio).num_days() >= MAX_DIAS {
+        return Err(AppError::validation("data_fim", format!("Uma baixa não pode passar de {} dias.", MAX_DIAS)));
+    }
+    if let Some(codigo) = restricoes.iter().find(|r| !RESTRICOES.iter().any(|(c, _)| c == r)) {
+        return Err(AppError::validation("restricoes", format!("Restrição desconhecida: '{}'.", codigo)));
+    }
+
+    let id = sqlx::query(
+        r#"
+        INSERT INTO baixas (organizacao_id, user_id, data_inicio, data_fim, restricoes, observacoes, registada_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(data_inicio.to_string())
+    .bind(data_fim.to_string())
+    .bind(restricoes.join(","))
+    .bind(observacoes.trim())
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+    tracing::info!("🩺 Baixa {} de {} ({} a {}) registada por {}.", id, user_id, data_inicio, data_fim, operador_id);
+    Ok(id)
+}
+
+/// Dá alta a uma baixa em vigor: passa a terminar na véspera de `hoje`. Devolve a baixa como estava.
+pub async fn alta(db_pool: &SqlitePool, organizacao_id: i64, id: i64, hoje: NaiveDate) -> AppResult<Baixa> {
+    let baixa = obter(db_pool, organizacao_id, id).await?;
+    let hoje_txt = hoje.to_string();
+    if baixa.data_fim < hoje_txt {
+        return Err(AppError::Conflict(format!("A baixa {} já terminou.", id)));
+    }
+    if baixa.data_inicio >= hoje_txt {
+        return Err(AppError::Conflict(format!("A baixa {} ainda não começou; apague-a se já não se aplica.", id)));
+    }
+    let vespera = (hoje - Duration::days(1)).to_string();
+    sqlx::query("UPDATE baixas SET data_fim = ?2 WHERE id = ?1")
+        .bind(id)
+        .bind(&vespera)
+        .execute(db_pool)
+        .await?;
+    tracing::info!("🩺 Alta da baixa {} de {} (termina em {}).", id, baixa.user_id, vespera);
+    Ok(baixa)
+}
+
+/// Apaga uma baixa (registo errado ou que já não se aplica). Devolve a baixa apagada.
+pub async fn remover(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Baixa> {
+    let baixa = obter(db_pool, organizacao_id, id).await?;
+    sqlx::query("DELETE FROM baixas WHERE id = ?1")
+        .bind(id)
+        .execute(db_pool)
+        .await?;
+    tracing::info!("🗑️ Baixa {} de {} apagada.", id, baixa.user_id);
+    Ok(baixa)
+}
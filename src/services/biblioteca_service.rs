@@ -0,0 +1,279 @@
+// src/services/biblioteca_service.rs
+//! Biblioteca: o catálogo dos títulos (com o número de exemplares de cada um) e os empréstimos. Um título
+//! só se empresta enquanto houver exemplares na estante; o empréstimo guarda quem leva o livro, o operador
+//! que o entregou e o dia até ao qual tem de voltar, e a devolução quem o recebeu. Os empréstimos em
+//! atraso são lembrados ao utilizador no centro de notificações, no máximo uma vez por dia (tarefa
+//! "biblioteca.atrasos", em main.rs). Cada utilizador vê os seus empréstimos na página pessoal.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::biblioteca::{Emprestimo, Livro},
+    services::notificacao_service,
+    tempo,
+};
+use chrono::NaiveDate;
+use sqlx::SqlitePool;
+
+/// Dias de empréstimo propostos no formulário.
+pub const DIAS_EMPRESTIMO: i64 = 15;
+/// Prazo máximo de um empréstimo (dias).
+pub const MAX_DIAS_EMPRESTIMO: i64 = 90;
+/// Exemplares de um título, no máximo.
+pub const MAX_EXEMPLARES: i64 = 99;
+/// Empréstimos mostrados no histórico.
+pub const HISTORICO_RECENTE: i64 = 100;
+
+// --- CATÁLOGO ---
+
+/// Títulos da organização, por título (só os ativos, se `so_ativos`), com os exemplares emprestados.
+pub async fn listar_livros(db_pool: &SqlitePool, organizacao_id: i64, so_ativos: bool) -> AppResult<Vec<Livro>> {
+    let livros = sqlx::query_as::<_, Livro>(
+        r#"
+        SELECT l.id, l.cota, l.titulo, l.autor, l.exemplares, l.ativo,
+               (SELECT COUNT(*) FROM emprestimos e WHERE e.livro_id = l.id AND e.devolvido_em IS NULL) AS emprestados
+        FROM livros l
+        WHERE l.organizacao_id = ?1 AND (?2 = 0 OR l.ativo = 1)
+        ORDER BY l.titulo COLLATE NOCASE, l.cota COLLATE NOCASE
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(so_ativos)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(livros)
+}
+
+/// Acrescenta um título ao catálogo. Devolve o ID.
+pub async fn criar_livro(db_pool: &SqlitePool, organizacao_id: i64, cota: &str, titulo: &str, autor: &str, exemplares: i64) -> AppResult<i64> {
+    let resultado = sqlx::query("INSERT INTO livros (organizacao_id, cota, titulo, autor, exemplares) VALUES (?1, ?2, ?3, ?4, ?5)")
+        .bind(organizacao_id)
+        .bind(cota.trim())
+        .bind(titulo.trim())
+        .bind(autor.trim())
+        .bind(exemplares)
+        .execute(db_pool)
+        .await;
+    match resultado {
+        Ok(r) => Ok(r.last_insert_rowid()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::validation("cota", "Já existe um livro com esta cota."))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Altera o título, o autor, os exemplares e o estado de um livro. Os exemplares não descem abaixo dos
+/// que estão emprestados. Devolve o livro como estava.
+pub async fn atualizar_livro(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    titulo: &str,
+    autor: &str,
+    exemplares: i64,
+    ativo: bool,
+) -> AppResult<Livro> {
+    let anterior = listar_livros(db_pool, organizacao_id, false)
+        .await?
+        .into_iter()
+        .find(|l| l.id == id)
+        .ok_or_else(|| AppError::NotFound(format!("Livro {} não encontrado.", id)))?;
+    if exemplares < anterior.emprestados {
+        return Err(AppError::Conflict(format!(
+            "{} tem {} exemplar(es) emprestado(s): registe primeiro as devoluções.",
+            anterior.cota, anterior.emprestados
+        )));
+    }
+    sqlx::query("UPDATE livros SET titulo = ?2, autor = ?3, exemplares = ?4, ativo = ?5 WHERE id = ?1")
+        .bind(id)
+        .bind(titulo.trim())
+        .bind(autor.trim())
+        .bind(exemplares)
+        .bind(ativo)
+        .execute(db_pool)
+        .await?;
+    Ok(anterior)
+}
+
+// --- EMPRÉSTIMOS ---
+
+/// Empréstimos da organização. `?2` = só os deste livro; `?3` = só os deste utilizador; `?4` = só os por
+/// devolver (1) ou todos (0); `?6` = só este empréstimo.
+const SQL_EMPRESTIMOS: &str = r#"
+    SELECT e.id, e.livro_id, l.cota, l.titulo, e.user_id, u.name, u.turma, e.emprestado_em, e.devolver_ate,
+           COALESCE(o.name, e.entregue_por) AS entregue_por,
+           e.devolvido_em, COALESCE(r.name, e.recebido_por) AS recebido_por
+    FROM emprestimos e
+    JOIN livros l ON l.id = e.livro_id
+    JOIN users u ON u.id = e.user_id
+    LEFT JOIN users o ON o.id = e.entregue_por
+    LEFT JOIN users r ON r.id = e.recebido_por
+    WHERE e.organizacao_id = ?1
+      AND (?2 IS NULL OR e.livro_id = ?2)
+      AND (?3 IS NULL OR e.user_id = ?3)
+      AND (?4 = 0 OR e.devolvido_em IS NULL)
+      AND (?6 IS NULL OR e.id = ?6)
+    ORDER BY e.emprestado_em DESC, e.id DESC
+    LIMIT ?5
+"#;
+
+/// Empréstimos por devolver (de um utilizador, se indicado), os de prazo mais curto primeiro.
+pub async fn abertos(db_pool: &SqlitePool, organizacao_id: i64, user_id: Option<&str>) -> AppResult<Vec<Emprestimo>> {
+    let mut emprestimos = sqlx::query_as::<_, Emprestimo>(SQL_EMPRESTIMOS)
+        .bind(organizacao_id)
+        .bind(None::<i64>)
+        .bind(user_id)
+        .bind(true)
+        .bind(i64::MAX)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    emprestimos.sort_by(|a, b| a.devolver_ate.cmp(&b.devolver_ate));
+    Ok(emprestimos)
+}
+
+/// Últimos empréstimos (de um livro e/ou de um utilizador, se indicados), os mais recentes primeiro.
+pub async fn historico(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    livro_id: Option<i64>,
+    user_id: Option<&str>,
+    limite: i64,
+) -> AppResult<Vec<Emprestimo>> {
+    let emprestimos = sqlx::query_as::<_, Emprestimo>(SQL_EMPRESTIMOS)
+        .bind(organizacao_id)
+        .bind(livro_id)
+        .bind(user_id)
+        .bind(false)
+        .bind(limite)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(emprestimos)
+}
+
+/// Empresta um exemplar de um livro a um utilizador até `devolver_ate` (inclusive). Devolve o ID do
+/// empréstimo e o título.
+pub async fn emprestar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    livro_id: i64,
+    user_id: &str,
+    devolver_ate: NaiveDate,
+    hoje: NaiveDate,
+    operador_id: &str,
+) -> AppResult<(i64, String)> {
+    if devolver_ate < hoje {
+        return Err(AppError::validation("devolver_ate", "A devolução não pode ser antes de hoje."));
+    }
+    if (devolver_ate - hoje).num_days() > MAX_DIAS_EMPRESTIMO {
+        return Err(AppError::validation("devolver_ate", format!("No máximo {} dias de empréstimo.", MAX_DIAS_EMPRESTIMO)));
+    }
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(db_pool)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    let livro = listar_livros(db_pool, organizacao_id, true)
+        .await?
+        .into_iter()
+        .find(|l| l.id == livro_id)
+        .ok_or_else(|| AppError::validation("livro_id", "Livro não encontrado ou inativo."))?;
+    let esgotado = |livro: &Livro| {
+        AppError::validation("livro_id", format!("Não há exemplares de «{}» na estante.", livro.titulo))
+    };
+    if livro.disponiveis() == 0 {
+        return Err(esgotado(&livro));
+    }
+
+    // A contagem e a inserção numa só instrução: dois empréstimos ao mesmo tempo não levam o último exemplar
+    let id = sqlx::query(
+        r#"
+        INSERT INTO emprestimos (organizacao_id, livro_id, user_id, devolver_ate, entregue_por)
+        SELECT ?1, ?2, ?3, ?4, ?5
+        FROM livros l
+        WHERE l.id = ?2
+          AND l.exemplares > (SELECT COUNT(*) FROM emprestimos e WHERE e.livro_id = ?2 AND e.devolvido_em IS NULL)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(livro_id)
+    .bind(user_id)
+    .bind(devolver_ate.to_string())
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?;
+    if id.rows_affected() == 0 {
+        return Err(esgotado(&livro));
+    }
+    tracing::info!("📚 {} ({}) emprestado a {} até {} por {}.", livro.cota, livro.titulo, user_id, devolver_ate, operador_id);
+    Ok((id.last_insert_rowid(), livro.titulo))
+}
+
+/// Regista a devolução de um livro. Devolve o empréstimo (já fechado).
+pub async fn devolver(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<Emprestimo> {
+    // Duas devoluções ao mesmo tempo: só a primeira fecha o empréstimo
+    let fechado = sqlx::query(
+        "UPDATE emprestimos SET devolvido_em = datetime('now'), recebido_por = ?3 WHERE id = ?1 AND organizacao_id = ?2 AND devolvido_em IS NULL",
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    let emprestimo = sqlx::query_as::<_, Emprestimo>(SQL_EMPRESTIMOS)
+        .bind(organizacao_id)
+        .bind(None::<i64>)
+        .bind(None::<String>)
+        .bind(false)
+        .bind(1)
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Empréstimo {} não encontrado.", id)))?;
+    if fechado == 0 {
+        return Err(AppError::Conflict(format!("{} ({}) já foi devolvido.", emprestimo.cota, emprestimo.titulo)));
+    }
+    tracing::info!("📚 {} devolvido por {}, recebido por {}.", emprestimo.cota, emprestimo.user_id, operador_id);
+    Ok(emprestimo)
+}
+
+/// Avisa quem tem livros por devolver depois do prazo (todas as organizações), uma vez por dia e por
+/// empréstimo. Tarefa periódica "biblioteca.atrasos": devolve o resumo.
+pub async fn avisar_atrasos(db_pool: &SqlitePool, hoje: NaiveDate) -> AppResult<String> {
+    let hoje = hoje.to_string();
+    let atrasados: Vec<(i64, String, String, String)> = sqlx::query_as(
+        r#"
+        SELECT e.id, e.user_id, l.titulo, e.devolver_ate
+        FROM emprestimos e
+        JOIN livros l ON l.id = e.livro_id
+        WHERE e.devolvido_em IS NULL AND e.devolver_ate < ?1 AND (e.ultimo_aviso IS NULL OR e.ultimo_aviso < ?1)
+        ORDER BY e.id
+        "#,
+    )
+    .bind(&hoje)
+    .fetch_all(db_pool)
+    .await?;
+
+    for (id, user_id, titulo, devolver_ate) in &atrasados {
+        let prazo = tempo::ler_data(devolver_ate).map_or_else(|| devolver_ate.clone(), tempo::data_curta);
+        notificacao_service::notificar(
+            db_pool,
+            user_id,
+            notificacao_service::TIPO_BIBLIOTECA,
+            "Livro em atraso",
+            &format!("«{}» devia ter sido devolvido até {}. Devolva-o na biblioteca.", titulo, prazo),
+        )
+        .await;
+        sqlx::query("UPDATE emprestimos SET ultimo_aviso = ?2 WHERE id = ?1")
+            .bind(id)
+            .bind(&hoje)
+            .execute(db_pool)
+            .await?;
+    }
+    Ok(format!("{} aviso(s) de livros em atraso.", atrasados.len()))
+}
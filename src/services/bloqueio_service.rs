@@ -0,0 +1,232 @@
+// src/services/bloqueio_service.rs
+//! Limite de tentativas de login: janela deslizante de falhas guardada em SQLite
+//! (sobrevive a reinícios), por ID de utilizador e por IP; e bloqueios aplicados pela administração.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::login::{BloqueioAutomatico, BloqueioManual},
+    tempo,
+};
+use sqlx::SqlitePool;
+
+/// Duração da janela (e do bloqueio) em minutos.
+pub const JANELA_MINUTOS: i64 = 15;
+/// Falhas para o mesmo ID dentro da janela até bloquear esse ID.
+pub const MAX_FALHAS_USER: i64 = 5;
+/// Falhas do mesmo IP (qualquer ID) dentro da janela até bloquear esse IP.
+pub const MAX_FALHAS_IP: i64 = 20;
+
+/// Minutos restantes de bloqueio para uma coluna/valor, ou None se não estiver bloqueado.
+/// O bloqueio termina quando a falha nº `max` mais recente sair da janela.
+async fn minutos_restantes(db_pool: &SqlitePool, coluna: &str, valor: &str, max: i64) -> AppResult<Option<i64>> {
+    // `coluna` vem sempre de uma constante deste módulo (nunca de input do utilizador)
+    let sql = format!(
+        r#"
+        SELECT CAST((julianday(ocorreu_em, '+{janela} minutes') - julianday('now')) * 1440 AS INTEGER) + 1
+        FROM login_falhas
+        WHERE {coluna} = ?1 AND ocorreu_em > datetime('now', '-{janela} minutes')
+        ORDER BY ocorreu_em DESC
+        LIMIT 1 OFFSET ?2
+        "#,
+        janela = JANELA_MINUTOS,
+        coluna = coluna,
+    );
+    let restantes = sqlx::query_scalar::<_, i64>(&sql)
+        .bind(valor)
+        .bind(max - 1)
+        .fetch_optional(db_pool)
+        .await?;
+    Ok(restantes.map(|m| m.max(1)))
+}
+
+/// Verifica se o login está bloqueado para este ID ou IP.
+/// Devolve os minutos até poder tentar de novo (o maior dos dois bloqueios).
+pub async fn verificar(db_pool: &SqlitePool, user_id: &str, ip: &str) -> AppResult<Option<i64>> {
+    let por_user = minutos_restantes(db_pool, "user_id", user_id, MAX_FALHAS_USER).await?;
+    let por_ip = minutos_restantes(db_pool, "ip", ip, MAX_FALHAS_IP).await?;
+    Ok(por_user.max(por_ip))
+}
+
+/// Falhas recentes (dentro da janela) para este ID (se indicado) ou IP, a maior das duas contagens.
+/// Usado para exigir CAPTCHA antes de se chegar ao bloqueio.
+pub async fn falhas_recentes(db_pool: &SqlitePool, user_id: Option<&str>, ip: &str) -> AppResult<i64> {
+    let falhas = sqlx::query_scalar::<_, i64>(&format!(
+        r#"
+        SELECT MAX(
+            (SELECT COUNT(*) FROM login_falhas WHERE user_id = ?1 AND ocorreu_em > datetime('now', '-{janela} minutes')),
+            (SELECT COUNT(*) FROM login_falhas WHERE ip = ?2 AND ocorreu_em > datetime('now', '-{janela} minutes'))
+        )
+        "#,
+        janela = JANELA_MINUTOS,
+    ))
+    .bind(user_id)
+    .bind(ip)
+    .fetch_one(db_pool)
+    .await?;
+    Ok(falhas)
+}
+
+/// Regista uma tentativa falhada (e aproveita para limpar falhas que já saíram da janela).
+pub async fn registar_falha(db_pool: &SqlitePool, user_id: &str, ip: &str) -> AppResult<()> {
+    sqlx::query("INSERT INTO login_falhas (user_id, ip) VALUES (?1, ?2)")
+        .bind(user_id)
+        .bind(ip)
+        .execute(db_pool)
+        .await?;
+    sqlx::query(&format!("DELETE FROM login_falhas WHERE ocorreu_em <= datetime('now', '-{} minutes')", JANELA_MINUTOS))
+        .execute(db_pool)
+        .await?;
+    Ok(())
+}
+
+/// Limpa as falhas de um ID após um login bem-sucedido (as do IP mantêm-se).
+pub async fn limpar_user(db_pool: &SqlitePool, user_id: &str) -> AppResult<()> {
+    sqlx::query("DELETE FROM login_falhas WHERE user_id = ?1")
+        .bind(user_id)
+        .execute(db_pool)
+        .await?;
+    Ok(())
+}
+
+/// Limpa as falhas de um IP (desbloqueio manual). Devolve o número de falhas apagadas.
+pub async fn limpar_ip(db_pool: &SqlitePool, ip: &str) -> AppResult<u64> {
+    let apagadas = sqlx::query("DELETE FROM login_falhas WHERE ip = ?1")
+        .bind(ip)
+        .execute(db_pool)
+        .await?
+        .rows_affected();
+    Ok(apagadas)
+}
+
+/// IDs ou IPs (conforme `coluna`) bloqueados neste momento pelo limite de tentativas.
+async fn bloqueados_por(db_pool: &SqlitePool, coluna: &str, max: i64) -> AppResult<Vec<BloqueioAutomatico>> {
+    // `coluna` vem sempre de uma constante deste módulo (nunca de input do utilizador)
+    let sql = format!(
+        r#"
+        SELECT f.{coluna}, u.name, COUNT(*)
+        FROM login_falhas f
+        LEFT JOIN users u ON u.id = f.{coluna}
+        WHERE f.ocorreu_em > datetime('now', '-{janela} minutes')
+        GROUP BY f.{coluna}
+        HAVING COUNT(*) >= ?1
+        ORDER BY COUNT(*) DESC
+        "#,
+        janela = JANELA_MINUTOS,
+        coluna = coluna,
+    );
+    let linhas = sqlx::query_as::<_, (String, Option<String>, i64)>(&sql)
+        .bind(max)
+        .fetch_all(db_pool)
+        .await?;
+
+    let mut bloqueados = Vec::with_capacity(linhas.len());
+    for (chave, name, falhas) in linhas {
+        if let Some(minutos_restantes) = minutos_restantes(db_pool, coluna, &chave, max).await? {
+            bloqueados.push(BloqueioAutomatico { chave, name, falhas, minutos_restantes });
+        }
+    }
+    Ok(bloqueados)
+}
+
+/// Utilizadores bloqueados neste momento por excesso de falhas.
+pub async fn users_bloqueados(db_pool: &SqlitePool) -> AppResult<Vec<BloqueioAutomatico>> {
+    bloqueados_por(db_pool, "user_id", MAX_FALHAS_USER).await
+}
+
+/// IPs bloqueados neste momento por excesso de falhas.
+pub async fn ips_bloqueados(db_pool: &SqlitePool) -> AppResult<Vec<BloqueioAutomatico>> {
+    bloqueados_por(db_pool, "ip", MAX_FALHAS_IP).await
+}
+
+// --- Bloqueios pela administração ---
+
+const SELECT_BLOQUEIO_MANUAL: &str = r#"
+    SELECT b.user_id, u.name, b.motivo, b.bloqueado_por,
+           b.bloqueado_em, b.ate
+    FROM bloqueios_manuais b
+    LEFT JOIN users u ON u.id = b.user_id
+    WHERE (b.ate IS NULL OR b.ate > datetime('now'))
+"#;
+
+/// Datas do bloqueio no fuso da aplicação (as colunas estão em UTC).
+fn no_fuso(bloqueio: BloqueioManual) -> BloqueioManual {
+    BloqueioManual {
+        bloqueado_em: tempo::formatar(&bloqueio.bloqueado_em, tempo::FORMATO_DATA_HORA),
+        ate: tempo::formatar_opcional(bloqueio.ate.clone(), tempo::FORMATO_DATA_HORA),
+        ..bloqueio
+    }
+}
+
+/// Bloqueio da administração em vigor para um utilizador (os que já passaram a data de fim não contam).
+pub async fn bloqueio_manual(db_pool: &SqlitePool, user_id: &str) -> AppResult<Option<BloqueioManual>> {
+    let bloqueio = sqlx::query_as::<_, BloqueioManual>(&format!("{} AND b.user_id = ?1", SELECT_BLOQUEIO_MANUAL))
+        .bind(user_id)
+        .fetch_optional(db_pool)
+        .await?;
+    Ok(bloqueio.map(no_fuso))
+}
+
+/// Bloqueios da administração em vigor, dos mais recentes para os mais antigos.
+pub async fn bloqueios_manuais(db_pool: &SqlitePool) -> AppResult<Vec<BloqueioManual>> {
+    let bloqueios = sqlx::query_as::<_, BloqueioManual>(&format!("{} ORDER BY b.bloqueado_em DESC", SELECT_BLOQUEIO_MANUAL))
+        .fetch_all(db_pool)
+        .await?;
+    Ok(bloqueios.into_iter().map(no_fuso).collect())
+}
+
+/// Bloqueia uma conta por decisão da administração, por `dias` dias ou sem data de fim.
+/// Substitui um bloqueio anterior da mesma conta.
+pub async fn bloquear(db_pool: &SqlitePool, user_id: &str, motivo: &str, bloqueado_por: &str, dias: Option<i64>) -> AppResult<()> {
+    let motivo = motivo.trim();
+    if motivo.is_empty() {
+        return Err(AppError::validation("motivo", "Indique o motivo do bloqueio."));
+    }
+    if dias.is_some_and(|d| d < 1) {
+        return Err(AppError::validation("dias", "A duração tem de ser de pelo menos 1 dia."));
+    }
+
+    let existe = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE id = ?1")
+        .bind(user_id)
+        .fetch_one(db_pool)
+        .await?;
+    if existe == 0 {
+        return Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO bloqueios_manuais (user_id, motivo, bloqueado_por, bloqueado_em, ate)
+        VALUES (?1, ?2, ?3, datetime('now'), CASE WHEN ?4 IS NULL THEN NULL ELSE datetime('now', '+' || ?4 || ' days') END)
+        ON CONFLICT (user_id) DO UPDATE SET
+            motivo = excluded.motivo, bloqueado_por = excluded.bloqueado_por,
+            bloqueado_em = excluded.bloqueado_em, ate = excluded.ate
+        "#,
+    )
+    .bind(user_id)
+    .bind(motivo)
+    .bind(bloqueado_por)
+    .bind(dias)
+    .execute(db_pool)
+    .await?;
+    tracing::info!("⛔ Conta {} bloqueada por {} ({:?} dias).", user_id, bloqueado_por, dias);
+    Ok(())
+}
+
+/// Desbloqueia uma conta: retira o bloqueio da administração e as falhas de login do ID.
+/// Devolve false se a conta não estava bloqueada de nenhuma das formas.
+pub async fn desbloquear(db_pool: &SqlitePool, user_id: &str) -> AppResult<bool> {
+    let mut tx = db_pool.begin().await?;
+    let manuais = sqlx::query("DELETE FROM bloqueios_manuais WHERE user_id = ?1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    let falhas = sqlx::query("DELETE FROM login_falhas WHERE user_id = ?1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+    tracing::info!("🔓 Conta {} desbloqueada.", user_id);
+    Ok(manuais + falhas > 0)
+}
@@ -0,0 +1,153 @@
+// src/services/captcha_service.rs
+//! CAPTCHA (hCaptcha ou Cloudflare Turnstile) no login, exigido só depois de várias
+//! tentativas falhadas para o mesmo ID ou IP. Desativado se as variáveis CAPTCHA_* não estiverem definidas.
+
+use crate::error::{AppError, AppResult};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Tempo máximo de espera pela verificação do fornecedor.
+const TIMEOUT_FORNECEDOR: Duration = Duration::from_secs(5);
+/// Falhas (do ID ou do IP, dentro da janela do limite de tentativas) a partir das quais o CAPTCHA é exigido.
+const APOS_FALHAS_PADRAO: i64 = 3;
+
+/// Fornecedores suportados: o widget e a verificação diferem apenas nos URLs e nomes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provedor {
+    HCaptcha,
+    Turnstile,
+}
+
+impl Provedor {
+    fn url_verificacao(self) -> &'static str {
+        match self {
+            Provedor::HCaptcha => "https://api.hcaptcha.com/siteverify",
+            Provedor::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+        }
+    }
+
+    fn url_script(self) -> &'static str {
+        match self {
+            Provedor::HCaptcha => "https://js.hcaptcha.com/1/api.js",
+            Provedor::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/api.js",
+        }
+    }
+
+    /// Classe do `<div>` onde o script do fornecedor desenha o widget.
+    fn classe_widget(self) -> &'static str {
+        match self {
+            Provedor::HCaptcha => "h-captcha",
+            Provedor::Turnstile => "cf-turnstile",
+        }
+    }
+}
+
+/// Configuração do CAPTCHA (lida do ambiente no arranque).
+#[derive(Clone)]
+pub struct CaptchaConfig {
+    pub provedor: Provedor,
+    pub site_key: String,
+    secret: String,
+    pub apos_falhas: i64,
+    http: reqwest::Client,
+}
+
+// O secret não aparece nos logs
+impl std::fmt::Debug for CaptchaConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CaptchaConfig")
+            .field("provedor", &self.provedor)
+            .field("site_key", &self.site_key)
+            .field("apos_falhas", &self.apos_falhas)
+            .finish_non_exhaustive()
+    }
+}
+
+/// O que a página de login precisa para mostrar o widget.
+#[derive(Debug, Clone)]
+pub struct CaptchaWidget {
+    pub url_script: &'static str,
+    pub classe: &'static str,
+    pub site_key: String,
+}
+
+impl CaptchaConfig {
+    /// Lê CAPTCHA_PROVEDOR ("hcaptcha" ou "turnstile"), CAPTCHA_SITE_KEY, CAPTCHA_SECRET
+    /// e CAPTCHA_APOS_FALHAS (opcional, 3 por omissão).
+    /// Ok(None) (desativado) se CAPTCHA_PROVEDOR não estiver definida; erro se a configuração estiver incompleta.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let var = |nome: &str| std::env::var(nome).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let Some(provedor) = var("CAPTCHA_PROVEDOR") else {
+            return Ok(None);
+        };
+        let provedor = match provedor.to_lowercase().as_str() {
+            "hcaptcha" => Provedor::HCaptcha,
+            "turnstile" => Provedor::Turnstile,
+            outro => return Err(format!("CAPTCHA_PROVEDOR inválido: '{}' (use 'hcaptcha' ou 'turnstile')", outro)),
+        };
+        let apos_falhas = match var("CAPTCHA_APOS_FALHAS") {
+            Some(v) => v.parse::<i64>().ok().filter(|n| *n >= 1).ok_or(format!("CAPTCHA_APOS_FALHAS inválido: '{}'", v))?,
+            None => APOS_FALHAS_PADRAO,
+        };
+        let http = reqwest::Client::builder()
+            .timeout(TIMEOUT_FORNECEDOR)
+            .build()
+            .map_err(|e| format!("falha ao criar cliente HTTP: {}", e))?;
+        Ok(Some(Self {
+            provedor,
+            site_key: var("CAPTCHA_SITE_KEY").ok_or("CAPTCHA_SITE_KEY em falta")?,
+            secret: var("CAPTCHA_SECRET").ok_or("CAPTCHA_SECRET em falta")?,
+            apos_falhas,
+            http,
+        }))
+    }
+
+    pub fn widget(&self) -> CaptchaWidget {
+        CaptchaWidget {
+            url_script: self.provedor.url_script(),
+            classe: self.provedor.classe_widget(),
+            site_key: self.site_key.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RespostaVerificacao {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+/// Verifica junto do fornecedor a resposta do widget submetida com o formulário.
+/// Erro se o fornecedor não responder (o login é recusado: não se salta o CAPTCHA).
+pub async fn verificar(config: &CaptchaConfig, resposta: &str, ip: &str) -> AppResult<bool> {
+    if resposta.trim().is_empty() {
+        return Ok(false);
+    }
+    let verificacao: RespostaVerificacao = config
+        .http
+        .post(config.provedor.url_verificacao())
+        .form(&[
+            ("secret", config.secret.as_str()),
+            ("response", resposta.trim()),
+            ("remoteip", ip),
+        ])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| {
+            tracing::error!("CAPTCHA: fornecedor indisponível: {}", e);
+            AppError::InternalServerError
+        })?
+        .json()
+        .await
+        .map_err(|e| {
+            tracing::error!("CAPTCHA: resposta inválida do fornecedor: {}", e);
+            AppError::InternalServerError
+        })?;
+
+    if !verificacao.success {
+        tracing::debug!("CAPTCHA recusado: {:?}", verificacao.error_codes);
+    }
+    Ok(verificacao.success)
+}
@@ -0,0 +1,146 @@
+// src/services/cardapio_service.rs
+//! Cardápio semanal: o rancheiro (permissão `rancho`) publica o prato de cada refeição em cada dia da
+//! semana (segunda a domingo). O da semana atual aparece no painel do utilizador; todas as semanas,
+//! incluindo as passadas, estão em /rancho/cardapio e em /api/v1/cardapio.
+
+use crate::{
+    error::AppResult,
+    models::cardapio::{CardapioSemana, DiaCardapio, PratoCardapio, SemanaPublicada},
+    services::arranchamento_service,
+};
+use chrono::{Datelike, Duration, NaiveDate};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// Tamanho máximo da descrição de um prato.
+pub const MAX_DESCRICAO: usize = 200;
+/// Semanas mostradas no histórico.
+pub const SEMANAS_HISTORICO: i64 = 52;
+
+/// Descrição escrita no formulário para cada (dia, refeição).
+pub type Pratos = HashMap<(NaiveDate, i64), String>;
+
+/// Segunda-feira da semana de `data`.
+pub fn inicio_semana(data: NaiveDate) -> NaiveDate {
+    data - Duration::days(data.weekday().num_days_from_monday() as i64)
+}
+
+/// Dias da semana que começa em `inicio`.
+fn dias_semana(inicio: NaiveDate) -> Vec<NaiveDate> {
+    (0..7).map(|n| inicio + Duration::days(n)).collect()
+}
+
+/// Cardápio da semana de `data`. Com `completo` (página de edição), cada dia tem todas as refeições ativas,
+/// com a descrição vazia se ainda não houver prato; sem ele, só os pratos publicados.
+pub async fn semana(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate, completo: bool) -> AppResult<CardapioSemana> {
+    let inicio = inicio_semana(data);
+    let fim = inicio + Duration::days(6);
+    let refeicoes = arranchamento_service::listar_refeicoes(db_pool, organizacao_id, false).await?;
+    let linhas: Vec<(i64, String, String, String)> = sqlx::query_as(
+        r#"
+        SELECT refeicao_id, data, descricao, atualizado_em
+        FROM cardapios
+        WHERE organizacao_id = ?1 AND data BETWEEN ?2 AND ?3
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(inicio.to_string())
+    .bind(fim.to_string())
+    .fetch_all(db_pool)
+    .await?;
+
+    let atualizado_em = linhas.iter().map(|(_, _, _, quando)| quando.clone()).max();
+    let publicados: HashMap<(i64, String), String> =
+        linhas.into_iter().map(|(refeicao_id, data, descricao, _)| ((refeicao_id, data), descricao)).collect();
+
+    let dias = dias_semana(inicio)
+        .into_iter()
+        .map(|dia| {
+            let data = dia.to_string();
+            let pratos = refeicoes
+                .iter()
+                .filter_map(|r| {
+                    let descricao = publicados.get(&(r.id, data.clone())).cloned().unwrap_or_default();
+                    // As inativas só aparecem se já tinham prato
+                    let mostrar = if completo { r.ativa || !descricao.is_empty() } else { !descricao.is_empty() };
+                    mostrar.then(|| PratoCardapio { refeicao_id: r.id, refeicao: r.nome.clone(), descricao })
+                })
+                .collect();
+            DiaCardapio { data, pratos }
+        })
+        .collect();
+    Ok(CardapioSemana { inicio: inicio.to_string(), fim: fim.to_string(), dias, atualizado_em })
+}
+
+/// Grava os pratos da semana que começa em `inicio`: `pratos` tem a descrição de cada (dia, refeição) do
+/// formulário (vazia = retira o prato). Dias fora da semana e refeições de outra organização são ignorados.
+/// Devolve quantos pratos foram gravados (novos ou alterados) e retirados.
+pub async fn publicar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    inicio: NaiveDate,
+    pratos: &Pratos,
+    user_id: &str,
+) -> AppResult<(u64, u64)> {
+    let refeicoes = arranchamento_service::listar_refeicoes(db_pool, organizacao_id, false).await?;
+    let mut tx = db_pool.begin().await?;
+    let (mut gravados, mut retirados) = (0, 0);
+    for dia in dias_semana(inicio) {
+        for refeicao in &refeicoes {
+            let Some(descricao) = pratos.get(&(dia, refeicao.id)).map(|d| d.trim()) else {
+                continue;
+            };
+            if descricao.is_empty() {
+                retirados += sqlx::query("DELETE FROM cardapios WHERE refeicao_id = ?1 AND data = ?2")
+                    .bind(refeicao.id)
+                    .bind(dia.to_string())
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+            } else {
+                // Só conta (e marca como atualizado) o que mudou
+                gravados += sqlx::query(
+                    r#"
+                    INSERT INTO cardapios (organizacao_id, refeicao_id, data, descricao, publicado_por)
+                    VALUES (?1, ?2, ?3, ?4, ?5)
+                    ON CONFLICT (refeicao_id, data) DO UPDATE SET
+                        descricao = excluded.descricao,
+                        publicado_por = excluded.publicado_por,
+                        atualizado_em = datetime('now')
+                    WHERE cardapios.descricao <> excluded.descricao
+                    "#,
+                )
+                .bind(organizacao_id)
+                .bind(refeicao.id)
+                .bind(dia.to_string())
+                .bind(descricao)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok((gravados, retirados))
+}
+
+/// Semanas com cardápio publicado, das mais recentes para as mais antigas.
+pub async fn historico(db_pool: &SqlitePool, organizacao_id: i64, limite: i64) -> AppResult<Vec<SemanaPublicada>> {
+    // date(d, 'weekday 0', '-6 days'): o domingo seguinte (ou o próprio) menos 6 dias = a segunda-feira
+    let semanas = sqlx::query_as::<_, SemanaPublicada>(
+        r#"
+        SELECT date(data, 'weekday 0', '-6 days') AS inicio, COUNT(*) AS pratos, MAX(atualizado_em) AS atualizado_em
+        FROM cardapios
+        WHERE organizacao_id = ?1
+        GROUP BY 1
+        ORDER BY 1 DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(limite)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(semanas)
+}
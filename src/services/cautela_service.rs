@@ -0,0 +1,541 @@
+// src/services/cautela_service.rs
+//! Cautelas de material: o catálogo (com as existências) e as entregas a utilizadores com a data
+//! prevista de devolução. Só se cautela o que está em armazém (existências menos as cautelas abertas);
+//! a devolução fecha a cautela e devolve o material ao armazém. As cautelas abertas depois da data
+//! prevista estão atrasadas.
+//!
+//! Material sensível (armamento e afins): a entrega e a devolução exigem a senha de quem está no armazém
+//! e a do utilizador (duas pessoas diferentes), e a devolução a verificação da quantidade, da referência
+//! e do estado. Cada movimento fica em `cautela_registos`, onde cada linha leva o hash da anterior:
+//! `verificar_registo` recalcula a cadeia e confirma que nenhuma cautela sensível ficou sem registo.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::cautela::{Cautela, Material, RegistoCautela, VerificacaoRegisto},
+    services::{auth_service, user_service},
+};
+use chrono::{NaiveDate, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// Quantidade máxima numa cautela.
+pub const MAX_QUANTIDADE: i64 = 100;
+/// Existências máximas de um material.
+pub const MAX_EXISTENCIAS: i64 = 100_000;
+/// Cautelas devolvidas mostradas no histórico.
+pub const DEVOLVIDAS_RECENTES: i64 = 50;
+/// Tamanho máximo do estado verificado na devolução.
+pub const MAX_ESTADO: usize = 500;
+/// Linhas do registo encadeado mostradas (a verificação percorre todas).
+pub const REGISTOS_RECENTES: i64 = 200;
+/// `hash_anterior` da primeira linha do registo de cada organização.
+const GENESE: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Senhas da confirmação dupla (material sensível): quem está no armazém e o utilizador.
+#[derive(Debug, Default)]
+pub struct ConfirmacaoDupla<'a> {
+    pub senha_operador: &'a str,
+    pub senha_utilizador: &'a str,
+}
+
+/// Verificação do material sensível na devolução.
+#[derive(Debug, Default)]
+pub struct VerificacaoDevolucao<'a> {
+    pub quantidade_conferida: bool,
+    pub referencia_conferida: bool,
+    pub estado: &'a str,
+}
+
+// --- CATÁLOGO ---
+
+/// Material da organização, por nome (só o ativo, se `so_ativo`), com a quantidade cautelada.
+pub async fn listar_material(db_pool: &SqlitePool, organizacao_id: i64, so_ativo: bool) -> AppResult<Vec<Material>> {
+    let material = sqlx::query_as::<_, Material>(
+        r#"
+        SELECT m.id, m.nome, m.referencia, m.quantidade, m.ativo, m.sensivel,
+               COALESCE((SELECT SUM(c.quantidade) FROM cautelas c WHERE c.material_id = m.id AND c.devolvida_em IS NULL), 0) AS em_uso
+        FROM material m
+        WHERE m.organizacao_id = ?1 AND (?2 = 0 OR m.ativo = 1)
+        ORDER BY m.nome
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(so_ativo)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(material)
+}
+
+/// Acrescenta material ao catálogo. Devolve o ID.
+pub async fn criar_material(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    nome: &str,
+    referencia: &str,
+    quantidade: i64,
+    sensivel: bool,
+) -> AppResult<i64> {
+    let resultado = sqlx::query("INSERT INTO material (organizacao_id, nome, referencia, quantidade, sensivel) VALUES (?1, ?2, ?3, ?4, ?5)")
+        .bind(organizacao_id)
+        .bind(nome.trim())
+        .bind(referencia.trim())
+        .bind(quantidade)
+        .bind(sensivel)
+        .execute(db_pool)
+        .await;
+    match resultado {
+        Ok(r) => Ok(r.last_insert_rowid()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::validation("nome", "Já existe material com este nome."))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Altera as existências, o estado e a marca de sensível de um material. As existências não podem ficar
+/// abaixo do que está cautelado. Devolve o material como estava.
+pub async fn atualizar_material(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    quantidade: i64,
+    ativo: bool,
+    sensivel: bool,
+) -> AppResult<Material> {
+    let anterior = listar_material(db_pool, organizacao_id, false)
+        .await?
+        .into_iter()
+        .find(|m| m.id == id)
+        .ok_or_else(|| AppError::NotFound(format!("Material {} não encontrado.", id)))?;
+    // A condição repete a verificação: uma cautela entretanto registada não deixa baixar de mais
+    let alterado = sqlx::query(
+        r#"
+        UPDATE material SET quantidade = ?2, ativo = ?3, sensivel = ?4
+        WHERE id = ?1
+          AND ?2 >= COALESCE((SELECT SUM(c.quantidade) FROM cautelas c WHERE c.material_id = ?1 AND c.devolvida_em IS NULL), 0)
+        "#,
+    )
+    .bind(id)
+    .bind(quantidade)
+    .bind(ativo)
+    .bind(sensivel)
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    if alterado == 0 {
+        return Err(AppError::validation(
+            "quantidade",
+            format!("{}: há {} cautelado(s); as existências não podem ser menos.", anterior.nome, anterior.em_uso),
+        ));
+    }
+    Ok(anterior)
+}
+
+// --- CAUTELAS ---
+
+/// Cautelas da organização. `?2` = só as deste utilizador; `?3` = só as abertas (1) ou só as
+/// devolvidas (0); `?4` = só as com devolução prevista antes deste dia; `?6` = só esta cautela.
+const SQL_CAUTELAS: &str = r#"
+    SELECT c.id, m.nome AS material, m.referencia, c.user_id, u.name, u.turma, c.quantidade,
+           c.devolucao_prevista, c.observacoes, COALESCE(e.name, c.entregue_por) AS entregue_por, c.entregue_em,
+           c.devolvida_em, COALESCE(r.name, c.recebida_por) AS recebida_por,
+           (m.sensivel OR c.confirmada_por IS NOT NULL) AS sensivel, c.verificacao_devolucao
+    FROM cautelas c
+    JOIN material m ON m.id = c.material_id
+    JOIN users u ON u.id = c.user_id
+    LEFT JOIN users e ON e.id = c.entregue_por
+    LEFT JOIN users r ON r.id = c.recebida_por
+    WHERE c.organizacao_id = ?1
+      AND (?2 IS NULL OR c.user_id = ?2)
+      AND (c.devolvida_em IS NULL) = ?3
+      AND (?4 IS NULL OR c.devolucao_prevista < ?4)
+      AND (?6 IS NULL OR c.id = ?6)
+    ORDER BY CASE WHEN ?3 THEN c.devolucao_prevista END, c.devolvida_em DESC, c.id
+    LIMIT ?5
+"#;
+
+/// Cautelas abertas (de um utilizador, se indicado), pela data prevista de devolução.
+pub async fn abertas(db_pool: &SqlitePool, organizacao_id: i64, user_id: Option<&str>) -> AppResult<Vec<Cautela>> {
+    let cautelas = sqlx::query_as::<_, Cautela>(SQL_CAUTELAS)
+        .bind(organizacao_id)
+        .bind(user_id)
+        .bind(true)
+        .bind(None::<String>)
+        .bind(i64::MAX)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(cautelas)
+}
+
+/// Cautelas abertas com a devolução prevista antes de `hoje` (as mais atrasadas primeiro).
+pub async fn atrasadas(db_pool: &SqlitePool, organizacao_id: i64, hoje: NaiveDate) -> AppResult<Vec<Cautela>> {
+    let cautelas = sqlx::query_as::<_, Cautela>(SQL_CAUTELAS)
+        .bind(organizacao_id)
+        .bind(None::<String>)
+        .bind(true)
+        .bind(hoje.to_string())
+        .bind(i64::MAX)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(cautelas)
+}
+
+/// Últimas cautelas devolvidas (de um utilizador, se indicado).
+pub async fn devolvidas(db_pool: &SqlitePool, organizacao_id: i64, user_id: Option<&str>, limite: i64) -> AppResult<Vec<Cautela>> {
+    let cautelas = sqlx::query_as::<_, Cautela>(SQL_CAUTELAS)
+        .bind(organizacao_id)
+        .bind(user_id)
+        .bind(false)
+        .bind(None::<String>)
+        .bind(limite)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(cautelas)
+}
+
+/// Uma cautela aberta pelo id (da organização).
+pub async fn aberta(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Cautela> {
+    sqlx::query_as::<_, Cautela>(SQL_CAUTELAS)
+        .bind(organizacao_id)
+        .bind(None::<String>)
+        .bind(true)
+        .bind(None::<String>)
+        .bind(i64::MAX)
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Cautela {} não encontrada ou já devolvida.", id)))
+}
+
+/// Falha (erro de validação em `campo`) se a senha não é a do utilizador.
+async fn confirmar_senha(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str, senha: &str, campo: &str) -> AppResult<()> {
+    if senha.is_empty() {
+        return Err(AppError::validation(campo, "Material sensível: a senha é obrigatória."));
+    }
+    let user = user_service::find_user_by_id(db_pool, user_id)
+        .await?
+        .filter(|u| u.organizacao_id == organizacao_id && u.ativo);
+    let certa = match user {
+        Some(user) => auth_service::verify_password(senha, &user.password_hash, &user.password_esquema).await?,
+        None => false,
+    };
+    if !certa {
+        tracing::warn!("🎒 Confirmação de material sensível falhada: senha errada de {}.", user_id);
+        return Err(AppError::validation(campo, format!("Senha de {} incorreta.", user_id)));
+    }
+    Ok(())
+}
+
+/// Confirmação dupla: quem está no armazém e o utilizador são pessoas diferentes e ambos dão a senha.
+async fn confirmar_dupla(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    operador_id: &str,
+    user_id: &str,
+    confirmacao: &ConfirmacaoDupla<'_>,
+) -> AppResult<()> {
+    if operador_id == user_id {
+        return Err(AppError::validation(
+            "user",
+            "Material sensível: quem entrega e quem recebe têm de ser pessoas diferentes.",
+        ));
+    }
+    confirmar_senha(db_pool, organizacao_id, operador_id, confirmacao.senha_operador, "senha_operador").await?;
+    confirmar_senha(db_pool, organizacao_id, user_id, confirmacao.senha_utilizador, "senha_utilizador").await
+}
+
+/// Entrega material a um utilizador. Devolve o ID da cautela e o nome do material.
+#[allow(clippy::too_many_arguments)]
+pub async fn emitir(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    material_id: i64,
+    user_id: &str,
+    quantidade: i64,
+    devolucao_prevista: NaiveDate,
+    observacoes: &str,
+    operador_id: &str,
+    confirmacao: &ConfirmacaoDupla<'_>,
+) -> AppResult<(i64, String)> {
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(db_pool)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    let material = listar_material(db_pool, organizacao_id, true)
+        .await?
+        .into_iter()
+        .find(|m| m.id == material_id)
+        .ok_or_else(|| AppError::validation("material_id", "Material não encontrado ou inativo."))?;
+    if material.sensivel {
+        confirmar_dupla(db_pool, organizacao_id, operador_id, user_id, confirmacao).await?;
+    }
+
+    // Só entra se ainda houver em armazém no momento da escrita (duas entregas ao mesmo tempo)
+    let mut tx = db_pool.begin().await?;
+    let resultado = sqlx::query(
+        r#"
+        INSERT INTO cautelas (organizacao_id, material_id, user_id, quantidade, devolucao_prevista, observacoes, entregue_por, confirmada_por)
+        SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8
+        WHERE (SELECT quantidade FROM material WHERE id = ?2)
+              - COALESCE((SELECT SUM(c.quantidade) FROM cautelas c WHERE c.material_id = ?2 AND c.devolvida_em IS NULL), 0) >= ?4
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(material_id)
+    .bind(user_id)
+    .bind(quantidade)
+    .bind(devolucao_prevista.to_string())
+    .bind(observacoes.trim())
+    .bind(operador_id)
+    .bind(material.sensivel.then_some(user_id))
+    .execute(&mut *tx)
+    .await?;
+    if resultado.rows_affected() == 0 {
+        return Err(AppError::validation(
+            "quantidade",
+            format!("{}: só há {} em armazém.", material.nome, material.disponivel()),
+        ));
+    }
+    let id = resultado.last_insert_rowid();
+    if material.sensivel {
+        let mut detalhes = format!("{} x {}", quantidade, material.nome);
+        if !material.referencia.is_empty() {
+            detalhes.push_str(&format!(" ({})", material.referencia));
+        }
+        if !observacoes.trim().is_empty() {
+            detalhes.push_str(&format!("; {}", observacoes.trim()));
+        }
+        acrescentar_registo(&mut tx, organizacao_id, id, "entrega", operador_id, user_id, &detalhes).await?;
+    }
+    tx.commit().await?;
+    tracing::info!("🎒 Cautela {}: {} x {} entregue a {} por {}.", id, quantidade, material.nome, user_id, operador_id);
+    Ok((id, material.nome))
+}
+
+/// Regista a devolução de uma cautela aberta. O material sensível exige a verificação e a confirmação
+/// dupla (ignoradas no restante). Devolve o utilizador e o material.
+pub async fn devolver(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    operador_id: &str,
+    verificacao: &VerificacaoDevolucao<'_>,
+    confirmacao: &ConfirmacaoDupla<'_>,
+) -> AppResult<(String, String)> {
+    let cautela: Option<(String, String, bool, bool, i64, String)> = sqlx::query_as(
+        r#"
+        SELECT c.user_id, m.nome, c.devolvida_em IS NOT NULL, (m.sensivel OR c.confirmada_por IS NOT NULL), c.quantidade, m.referencia
+        FROM cautelas c JOIN material m ON m.id = c.material_id
+        WHERE c.id = ?1 AND c.organizacao_id = ?2
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?;
+    let Some((user_id, material, devolvida, sensivel, quantidade, referencia)) = cautela else {
+        return Err(AppError::NotFound(format!("Cautela {} não encontrada.", id)));
+    };
+    let ja_devolvida = AppError::Conflict(format!("A cautela {} já foi devolvida.", id));
+    if devolvida {
+        return Err(ja_devolvida);
+    }
+    let estado = verificacao.estado.trim();
+    if sensivel {
+        if !verificacao.quantidade_conferida {
+            return Err(AppError::validation("quantidade_conferida", format!("Confirme que foram devolvidos {} x {}.", quantidade, material)));
+        }
+        if !verificacao.referencia_conferida && !referencia.is_empty() {
+            return Err(AppError::validation("referencia_conferida", format!("Confirme a referência ({}).", referencia)));
+        }
+        if estado.is_empty() {
+            return Err(AppError::validation("estado", "Descreva o estado do material devolvido."));
+        }
+        if estado.chars().count() > MAX_ESTADO {
+            return Err(AppError::validation("estado", format!("Máximo de {} caracteres.", MAX_ESTADO)));
+        }
+        confirmar_dupla(db_pool, organizacao_id, operador_id, &user_id, confirmacao).await?;
+    }
+
+    // Duas devoluções ao mesmo tempo: só a primeira fecha a cautela
+    let mut tx = db_pool.begin().await?;
+    let fechada = sqlx::query(
+        r#"
+        UPDATE cautelas SET devolvida_em = datetime('now'), recebida_por = ?2,
+               devolucao_confirmada_por = ?3, verificacao_devolucao = ?4
+        WHERE id = ?1 AND devolvida_em IS NULL
+        "#,
+    )
+    .bind(id)
+    .bind(operador_id)
+    .bind(sensivel.then_some(user_id.as_str()))
+    .bind(if sensivel { estado } else { "" })
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if fechada == 0 {
+        return Err(ja_devolvida);
+    }
+    if sensivel {
+        let detalhes = format!("{} x {}; estado: {}", quantidade, material, estado);
+        acrescentar_registo(&mut tx, organizacao_id, id, "devolucao", operador_id, &user_id, &detalhes).await?;
+    }
+    tx.commit().await?;
+    tracing::info!("🎒 Cautela {} ({} de {}) devolvida, recebida por {}.", id, material, user_id, operador_id);
+    Ok((user_id, material))
+}
+
+// --- REGISTO ENCADEADO (MATERIAL SENSÍVEL) ---
+
+/// Hash de uma linha do registo: SHA-256 do hash anterior e dos campos, separados por quebras de linha.
+#[allow(clippy::too_many_arguments)]
+fn hash_registo(
+    hash_anterior: &str,
+    organizacao_id: i64,
+    cautela_id: i64,
+    evento: &str,
+    operador_id: &str,
+    confirmado_por: &str,
+    detalhes: &str,
+    criado_em: &str,
+) -> String {
+    let conteudo = [
+        hash_anterior,
+        &organizacao_id.to_string(),
+        &cautela_id.to_string(),
+        evento,
+        operador_id,
+        confirmado_por,
+        detalhes,
+        criado_em,
+    ]
+    .join("\n");
+    format!("{:x}", Sha256::digest(conteudo.as_bytes()))
+}
+
+/// Acrescenta uma linha ao registo, encadeada na última da organização. Sempre na transação da cautela
+/// (que já escreveu, pelo que tem a escrita da DB: duas linhas ao mesmo tempo não leem o mesmo hash).
+async fn acrescentar_registo(
+    conn: &mut SqliteConnection,
+    organizacao_id: i64,
+    cautela_id: i64,
+    evento: &str,
+    operador_id: &str,
+    confirmado_por: &str,
+    detalhes: &str,
+) -> AppResult<()> {
+    let anterior: Option<String> = sqlx::query_scalar("SELECT hash FROM cautela_registos WHERE organizacao_id = ?1 ORDER BY id DESC LIMIT 1")
+        .bind(organizacao_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let anterior = anterior.unwrap_or_else(|| GENESE.to_string());
+    let criado_em = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let hash = hash_registo(&anterior, organizacao_id, cautela_id, evento, operador_id, confirmado_por, detalhes, &criado_em);
+    sqlx::query(
+        r#"
+        INSERT INTO cautela_registos
+            (organizacao_id, cautela_id, evento, operador_id, confirmado_por, detalhes, criado_em, hash_anterior, hash)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(cautela_id)
+    .bind(evento)
+    .bind(operador_id)
+    .bind(confirmado_por)
+    .bind(detalhes)
+    .bind(&criado_em)
+    .bind(&anterior)
+    .bind(&hash)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Linhas do registo da organização, pela ordem da cadeia. `?2` = só as últimas (LIMIT).
+const SQL_REGISTOS: &str = r#"
+    SELECT * FROM (
+        SELECT r.id, r.cautela_id, r.evento, r.operador_id, COALESCE(o.name, r.operador_id) AS operador,
+               r.confirmado_por, COALESCE(u.name, r.confirmado_por) AS confirmado, r.detalhes, r.criado_em,
+               r.hash_anterior, r.hash
+        FROM cautela_registos r
+        LEFT JOIN users o ON o.id = r.operador_id
+        LEFT JOIN users u ON u.id = r.confirmado_por
+        WHERE r.organizacao_id = ?1
+        ORDER BY r.id DESC
+        LIMIT ?2
+    ) ORDER BY id
+"#;
+
+/// Últimas linhas do registo (as mais recentes primeiro).
+pub async fn registos(db_pool: &SqlitePool, organizacao_id: i64, limite: i64) -> AppResult<Vec<RegistoCautela>> {
+    let mut registos = sqlx::query_as::<_, RegistoCautela>(SQL_REGISTOS)
+        .bind(organizacao_id)
+        .bind(limite)
+        .fetch_all(db_pool)
+        .await?;
+    registos.reverse();
+    Ok(registos)
+}
+
+/// Verifica o registo da organização: cada linha liga-se à anterior e o hash corresponde ao conteúdo
+/// (uma linha alterada ou removida quebra a cadeia), e cada cautela sensível tem a entrega e, se já
+/// foi devolvida, a devolução registadas (uma remoção no fim da cadeia não a quebra).
+pub async fn verificar_registo(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<VerificacaoRegisto> {
+    let registos = sqlx::query_as::<_, RegistoCautela>(SQL_REGISTOS)
+        .bind(organizacao_id)
+        .bind(i64::MAX)
+        .fetch_all(db_pool)
+        .await?;
+    let mut verificacao = VerificacaoRegisto { total: registos.len() as i64, problemas: Vec::new() };
+    let mut anterior = GENESE.to_string();
+    for r in &registos {
+        if r.hash_anterior != anterior {
+            verificacao.problemas.push(format!("Registo {}: não se liga ao anterior (linha removida ou alterada antes dele).", r.id));
+        }
+        let hash = hash_registo(
+            &r.hash_anterior,
+            organizacao_id,
+            r.cautela_id,
+            &r.evento,
+            &r.operador_id,
+            &r.confirmado_por,
+            &r.detalhes,
+            &r.criado_em,
+        );
+        if hash != r.hash {
+            verificacao.problemas.push(format!("Registo {}: o conteúdo foi alterado (hash não corresponde).", r.id));
+        }
+        anterior = r.hash.clone();
+    }
+
+    let em_falta: Vec<(i64, String)> = sqlx::query_as(
+        r#"
+        SELECT c.id, 'entrega' FROM cautelas c
+        WHERE c.organizacao_id = ?1 AND c.confirmada_por IS NOT NULL
+          AND NOT EXISTS (SELECT 1 FROM cautela_registos r WHERE r.cautela_id = c.id AND r.evento = 'entrega')
+        UNION ALL
+        SELECT c.id, 'devolução' FROM cautelas c
+        WHERE c.organizacao_id = ?1 AND c.devolucao_confirmada_por IS NOT NULL
+          AND NOT EXISTS (SELECT 1 FROM cautela_registos r WHERE r.cautela_id = c.id AND r.evento = 'devolucao')
+        ORDER BY 1
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    for (cautela_id, evento) in em_falta {
+        verificacao.problemas.push(format!("Cautela {}: falta o registo da {}.", cautela_id, evento));
+    }
+    if !verificacao.integra() {
+        tracing::error!("🎒 Registo do material sensível (organização {}) com {} problema(s).", organizacao_id, verificacao.problemas.len());
+    }
+    Ok(verificacao)
+}
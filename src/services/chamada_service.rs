@@ -0,0 +1,463 @@
+// src/services/chamada_service.rs
+//! Plano de chamada (/chamada): cada utilizador indica o seu contacto de emergência, e a cascata de
+//! telefonemas de uma turma ou de um grupo diz quem liga a quem. Ao criar (ou regenerar) o plano, os
+//! membros ficam por antiguidade e cada um liga a `ramificacao` dos seguintes (o primeiro é a raiz,
+//! avisada pelo oficial de dia); quem gere o plano pode depois mudar o chamador de cada membro.
+//! Num exercício, os membros são avisados e cada confirmação de receção (do próprio ou registada por quem
+//! gere) fica com a hora, para se ver quanto tempo a cascata demorou e quem não foi alcançado.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::chamada::{ContactoEmergencia, ExercicioChamada, NoChamada, PlanoChamada, PosicaoChamada},
+    services::{grupo_service, horario_service, notificacao_service},
+    tempo,
+};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{HashMap, HashSet};
+
+/// Chamadas por membro ao gerar a cascata, por omissão e no máximo.
+pub const RAMIFICACAO_PADRAO: i64 = 3;
+pub const MAX_RAMIFICACAO: i64 = 10;
+/// Exercícios mostrados no histórico de um plano.
+pub const EXERCICIOS_RECENTES: i64 = 10;
+
+// --- CONTACTO DE EMERGÊNCIA ---
+
+/// Contacto de emergência de um utilizador (None se ainda não o indicou).
+pub async fn contacto(db_pool: &SqlitePool, user_id: &str) -> AppResult<Option<ContactoEmergencia>> {
+    let contacto = sqlx::query_as::<_, ContactoEmergencia>(
+        "SELECT nome, parentesco, telefone, atualizado_em FROM contactos_emergencia WHERE user_id = ?1",
+    )
+    .bind(user_id)
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(contacto)
+}
+
+/// Guarda (ou substitui) o contacto de emergência de um utilizador.
+pub async fn guardar_contacto(db_pool: &SqlitePool, user_id: &str, nome: &str, parentesco: &str, telefone: &str) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO contactos_emergencia (user_id, nome, parentesco, telefone) VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT (user_id) DO UPDATE SET
+            nome = excluded.nome, parentesco = excluded.parentesco, telefone = excluded.telefone,
+            atualizado_em = datetime('now')
+        "#,
+    )
+    .bind(user_id)
+    .bind(nome.trim())
+    .bind(parentesco.trim())
+    .bind(telefone.trim())
+    .execute(db_pool)
+    .await?;
+    tracing::info!("📞 Contacto de emergência de {} atualizado.", user_id);
+    Ok(())
+}
+
+// --- PLANOS ---
+
+/// Planos da organização num só SELECT (`?2` = só este plano).
+const SQL_PLANOS: &str = r#"
+    SELECT p.id, p.nome, p.turma, p.grupo_id, g.nome AS grupo, p.ramificacao, p.atualizado_em,
+           (SELECT COUNT(*) FROM chamada_ligacoes l WHERE l.plano_id = p.id) AS membros,
+           (SELECT e.id FROM chamada_exercicios e WHERE e.plano_id = p.id AND e.encerrado_em IS NULL) AS exercicio_aberto
+    FROM chamada_planos p
+    LEFT JOIN grupos g ON g.id = p.grupo_id
+    WHERE p.organizacao_id = ?1 AND (?2 IS NULL OR p.id = ?2)
+    ORDER BY p.nome
+"#;
+
+/// Planos de chamada da organização, por nome.
+pub async fn planos(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<PlanoChamada>> {
+    let planos = sqlx::query_as::<_, PlanoChamada>(SQL_PLANOS)
+        .bind(organizacao_id)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(planos)
+}
+
+/// Um plano pelo id (da organização).
+pub async fn obter_plano(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<PlanoChamada> {
+    sqlx::query_as::<_, PlanoChamada>(SQL_PLANOS)
+        .bind(organizacao_id)
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Plano de chamada {} não encontrado.", id)))
+}
+
+/// Utilizadores ativos da turma ou do grupo, por antiguidade (os sem posição no fim, por ID).
+async fn membros_alvo(db_pool: &SqlitePool, organizacao_id: i64, turma: Option<&str>, grupo_id: Option<i64>) -> AppResult<Vec<String>> {
+    let membros = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT u.id FROM users u
+        WHERE u.organizacao_id = ?1 AND u.ativo = 1
+          AND (?2 IS NULL OR u.turma = ?2)
+          AND (?3 IS NULL OR u.id IN (SELECT user_id FROM grupo_membros WHERE grupo_id = ?3))
+        ORDER BY u.ano DESC, u.antiguidade IS NULL, u.antiguidade, u.id
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(turma)
+    .bind(grupo_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(membros)
+}
+
+/// Refaz a cascata do plano com `membros` (já ordenados): o primeiro é a raiz e cada membro liga a
+/// `ramificacao` dos seguintes, por níveis.
+async fn gerar_cascata(conn: &mut SqliteConnection, plano_id: i64, membros: &[String], ramificacao: i64) -> AppResult<()> {
+    sqlx::query("DELETE FROM chamada_ligacoes WHERE plano_id = ?1").bind(plano_id).execute(&mut *conn).await?;
+    let ramificacao = ramificacao.max(1) as usize;
+    for (i, user_id) in membros.iter().enumerate() {
+        let chamador = if i == 0 { None } else { Some(&membros[(i - 1) / ramificacao]) };
+        sqlx::query("INSERT INTO chamada_ligacoes (plano_id, user_id, chamador_id, ordem) VALUES (?1, ?2, ?3, ?4)")
+            .bind(plano_id)
+            .bind(user_id)
+            .bind(chamador)
+            .bind(i as i64)
+            .execute(&mut *conn)
+            .await?;
+    }
+    sqlx::query("UPDATE chamada_planos SET atualizado_em = datetime('now') WHERE id = ?1")
+        .bind(plano_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Cria o plano de chamada de uma turma ou de um grupo (um dos dois) e gera a cascata. Devolve o ID.
+#[allow(clippy::too_many_arguments)]
+pub async fn criar_plano(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    nome: &str,
+    turma: Option<&str>,
+    grupo_id: Option<i64>,
+    ramificacao: i64,
+    operador_id: &str,
+) -> AppResult<i64> {
+    let nome = nome.trim();
+    match (turma, grupo_id) {
+        (Some(turma), None) => {
+            if !horario_service::turmas(db_pool, organizacao_id).await?.iter().any(|t| t == turma) {
+                return Err(AppError::validation("alvo", format!("A turma '{}' não tem utilizadores ativos.", turma)));
+            }
+        }
+        (None, Some(grupo_id)) => {
+            if grupo_service::find_grupo(db_pool, organizacao_id, grupo_id).await?.is_none() {
+                return Err(AppError::validation("alvo", "Grupo não encontrado."));
+            }
+        }
+        _ => return Err(AppError::validation("alvo", "Escolha uma turma ou um grupo.")),
+    }
+    if !(1..=MAX_RAMIFICACAO).contains(&ramificacao) {
+        return Err(AppError::validation("ramificacao", format!("De 1 a {} chamadas por membro.", MAX_RAMIFICACAO)));
+    }
+    let repetido: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM chamada_planos WHERE organizacao_id = ?1 AND nome = ?2)")
+        .bind(organizacao_id)
+        .bind(nome)
+        .fetch_one(db_pool)
+        .await?;
+    if repetido {
+        return Err(AppError::validation("nome", format!("Já existe um plano chamado \"{}\".", nome)));
+    }
+    let membros = membros_alvo(db_pool, organizacao_id, turma, grupo_id).await?;
+    if membros.is_empty() {
+        return Err(AppError::validation("alvo", "O grupo não tem membros ativos."));
+    }
+
+    let mut tx = db_pool.begin().await?;
+    let id = sqlx::query(
+        "INSERT INTO chamada_planos (organizacao_id, nome, turma, grupo_id, ramificacao, criado_por) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )
+    .bind(organizacao_id)
+    .bind(nome)
+    .bind(turma)
+    .bind(grupo_id)
+    .bind(ramificacao)
+    .bind(operador_id)
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+    gerar_cascata(&mut tx, id, &membros, ramificacao).await?;
+    tx.commit().await?;
+    tracing::info!("📞 Plano de chamada {} ({}) criado por {} com {} membros.", id, nome, operador_id, membros.len());
+    Ok(id)
+}
+
+/// Falha (conflito) se o plano tem um exercício em curso (a cascata não muda a meio).
+fn exigir_sem_exercicio(plano: &PlanoChamada) -> AppResult<()> {
+    match plano.exercicio_aberto {
+        Some(_) => Err(AppError::Conflict(format!("O plano \"{}\" tem um exercício em curso: encerre-o primeiro.", plano.nome))),
+        None => Ok(()),
+    }
+}
+
+/// Regenera a cascata com os membros atuais da turma ou do grupo (quem entrou ou saiu desde a criação);
+/// as alterações feitas à mão perdem-se. Devolve o número de membros.
+pub async fn regenerar(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<usize> {
+    let plano = obter_plano(db_pool, organizacao_id, id).await?;
+    exigir_sem_exercicio(&plano)?;
+    let membros = membros_alvo(db_pool, organizacao_id, plano.turma.as_deref(), plano.grupo_id).await?;
+    let mut tx = db_pool.begin().await?;
+    gerar_cascata(&mut tx, id, &membros, plano.ramificacao).await?;
+    tx.commit().await?;
+    tracing::info!("📞 Plano de chamada {} regenerado com {} membros.", id, membros.len());
+    Ok(membros.len())
+}
+
+/// Define o chamador de cada membro (pares membro -> chamador; vazio = raiz). Os chamadores têm de ser
+/// membros do plano, ninguém se chama a si próprio e a cascata não pode ter ciclos (todos chegam a
+/// uma raiz).
+pub async fn definir_chamadores(db_pool: &SqlitePool, organizacao_id: i64, id: i64, pares: &[(String, String)]) -> AppResult<()> {
+    let plano = obter_plano(db_pool, organizacao_id, id).await?;
+    exigir_sem_exercicio(&plano)?;
+    let membros: HashSet<String> = cascata(db_pool, id, None).await?.into_iter().map(|n| n.user_id).collect();
+    let mut chamadores: HashMap<&str, Option<&str>> = HashMap::new();
+    for (user_id, chamador) in pares {
+        let chamador = Some(chamador.trim()).filter(|c| !c.is_empty());
+        if !membros.contains(user_id) {
+            return Err(AppError::validation("cascata", format!("{} não é membro do plano.", user_id)));
+        }
+        if let Some(chamador) = chamador {
+            if !membros.contains(chamador) {
+                return Err(AppError::validation("cascata", format!("O chamador de {} ({}) não é membro do plano.", user_id, chamador)));
+            }
+            if chamador == user_id {
+                return Err(AppError::validation("cascata", format!("{} não pode ligar a si próprio.", user_id)));
+            }
+        }
+        chamadores.insert(user_id.as_str(), chamador);
+    }
+    // Subindo pelos chamadores, cada membro chega a uma raiz em menos passos do que há membros
+    for inicio in chamadores.keys() {
+        let mut atual = *inicio;
+        let mut passos = 0;
+        while let Some(Some(chamador)) = chamadores.get(atual) {
+            atual = chamador;
+            passos += 1;
+            if passos > chamadores.len() {
+                // Depois de tantos passos, `atual` já está dentro do ciclo
+                return Err(AppError::validation("cascata", format!("A cascata tem um ciclo (passa por {}).", atual)));
+            }
+        }
+    }
+    if !chamadores.values().any(Option::is_none) {
+        return Err(AppError::validation("cascata", "A cascata precisa de pelo menos uma raiz (sem chamador)."));
+    }
+
+    let mut tx = db_pool.begin().await?;
+    for (user_id, chamador) in &chamadores {
+        sqlx::query("UPDATE chamada_ligacoes SET chamador_id = ?3 WHERE plano_id = ?1 AND user_id = ?2")
+            .bind(id)
+            .bind(user_id)
+            .bind(chamador)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query("UPDATE chamada_planos SET atualizado_em = datetime('now') WHERE id = ?1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!("📞 Cascata do plano de chamada {} alterada ({} membros).", id, chamadores.len());
+    Ok(())
+}
+
+/// Apaga um plano (com a cascata e os exercícios). Devolve o plano apagado.
+pub async fn apagar_plano(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<PlanoChamada> {
+    let plano = obter_plano(db_pool, organizacao_id, id).await?;
+    sqlx::query("DELETE FROM chamada_planos WHERE id = ?1").bind(id).execute(db_pool).await?;
+    tracing::info!("🗑️ Plano de chamada {} ({}) apagado.", id, plano.nome);
+    Ok(plano)
+}
+
+/// Ordena a cascata por profundidade (cada membro seguido de quem ele chama, pela ordem) e calcula o
+/// nível. Quem tem um chamador fora do plano conta como raiz.
+fn ordenar_cascata(nos: Vec<NoChamada>) -> Vec<NoChamada> {
+    let membros: HashSet<String> = nos.iter().map(|n| n.user_id.clone()).collect();
+    let mut filhos: HashMap<Option<String>, Vec<NoChamada>> = HashMap::new();
+    for no in nos {
+        let chamador = no.chamador_id.clone().filter(|c| membros.contains(c));
+        filhos.entry(chamador).or_default().push(no);
+    }
+    let mut ordenados = Vec::with_capacity(membros.len());
+    let mut pilha: Vec<(NoChamada, i64)> = filhos.remove(&None).unwrap_or_default().into_iter().rev().map(|n| (n, 0)).collect();
+    while let Some((mut no, nivel)) = pilha.pop() {
+        no.nivel = nivel;
+        if let Some(seguintes) = filhos.remove(&Some(no.user_id.clone())) {
+            pilha.extend(seguintes.into_iter().rev().map(|n| (n, nivel + 1)));
+        }
+        ordenados.push(no);
+    }
+    // Um ciclo (só possível com dados alterados fora da aplicação) fica no fim, como raízes
+    ordenados.extend(filhos.into_values().flatten());
+    ordenados
+}
+
+/// Cascata de um plano, por profundidade, com os telefones, os contactos de emergência e, se for indicado
+/// o exercício, as confirmações.
+pub async fn cascata(db_pool: &SqlitePool, plano_id: i64, exercicio_id: Option<i64>) -> AppResult<Vec<NoChamada>> {
+    let nos = sqlx::query_as::<_, NoChamada>(
+        r#"
+        SELECT l.user_id, u.name, u.turma, u.telefone, l.chamador_id,
+               ce.nome AS contacto_nome, ce.parentesco AS contacto_parentesco, ce.telefone AS contacto_telefone,
+               c.confirmado_em, 0 AS nivel
+        FROM chamada_ligacoes l
+        JOIN users u ON u.id = l.user_id
+        LEFT JOIN contactos_emergencia ce ON ce.user_id = l.user_id
+        LEFT JOIN chamada_confirmacoes c ON c.exercicio_id = ?2 AND c.user_id = l.user_id
+        WHERE l.plano_id = ?1
+        ORDER BY l.ordem, l.user_id
+        "#,
+    )
+    .bind(plano_id)
+    .bind(exercicio_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(ordenar_cascata(nos))
+}
+
+/// Lugar do utilizador em cada plano de que é membro: quem o chama, a quem liga e o exercício em curso.
+pub async fn posicoes(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str) -> AppResult<Vec<PosicaoChamada>> {
+    let mut posicoes = Vec::new();
+    for plano in planos(db_pool, organizacao_id).await? {
+        let nos = cascata(db_pool, plano.id, plano.exercicio_aberto).await?;
+        let Some(proprio) = nos.iter().find(|n| n.user_id == user_id) else {
+            continue;
+        };
+        posicoes.push(PosicaoChamada {
+            plano: plano.nome.clone(),
+            chamador: nos.iter().find(|n| Some(&n.user_id) == proprio.chamador_id.as_ref()).cloned(),
+            liga_para: nos.iter().filter(|n| n.chamador_id.as_deref() == Some(user_id)).cloned().collect(),
+            exercicio: plano.exercicio_aberto,
+            confirmado: proprio.confirmado_em.is_some(),
+        });
+    }
+    Ok(posicoes)
+}
+
+// --- EXERCÍCIOS ---
+
+/// Exercícios da organização (`?2` = só este exercício, `?3` = só os deste plano), os mais recentes
+/// primeiro.
+const SQL_EXERCICIOS: &str = r#"
+    SELECT e.id, e.plano_id, p.nome AS plano, COALESCE(u.name, e.iniciado_por) AS iniciado_por,
+           e.iniciado_em, e.encerrado_em, e.membros AS total,
+           (SELECT COUNT(*) FROM chamada_confirmacoes c WHERE c.exercicio_id = e.id) AS confirmados,
+           (SELECT MAX(c.confirmado_em) FROM chamada_confirmacoes c WHERE c.exercicio_id = e.id) AS ultima_confirmacao
+    FROM chamada_exercicios e
+    JOIN chamada_planos p ON p.id = e.plano_id
+    LEFT JOIN users u ON u.id = e.iniciado_por
+    WHERE p.organizacao_id = ?1 AND (?2 IS NULL OR e.id = ?2) AND (?3 IS NULL OR e.plano_id = ?3)
+    ORDER BY e.id DESC
+    LIMIT ?4
+"#;
+
+/// Últimos exercícios de um plano.
+pub async fn exercicios(db_pool: &SqlitePool, organizacao_id: i64, plano_id: i64, limite: i64) -> AppResult<Vec<ExercicioChamada>> {
+    let exercicios = sqlx::query_as::<_, ExercicioChamada>(SQL_EXERCICIOS)
+        .bind(organizacao_id)
+        .bind(None::<i64>)
+        .bind(plano_id)
+        .bind(limite)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(exercicios)
+}
+
+/// Um exercício pelo id (da organização).
+pub async fn obter_exercicio(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<ExercicioChamada> {
+    sqlx::query_as::<_, ExercicioChamada>(SQL_EXERCICIOS)
+        .bind(organizacao_id)
+        .bind(id)
+        .bind(None::<i64>)
+        .bind(1)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Exercício de chamada {} não encontrado.", id)))
+}
+
+/// Inicia um exercício do plano e avisa os membros (confirmam a receção em /chamada). Devolve o ID.
+pub async fn iniciar_exercicio(db_pool: &SqlitePool, organizacao_id: i64, plano_id: i64, operador_id: &str) -> AppResult<i64> {
+    let plano = obter_plano(db_pool, organizacao_id, plano_id).await?;
+    exigir_sem_exercicio(&plano)?;
+    if plano.membros == 0 {
+        return Err(AppError::Conflict(format!("O plano \"{}\" não tem membros: regenere a cascata.", plano.nome)));
+    }
+    let id = sqlx::query("INSERT INTO chamada_exercicios (plano_id, membros, iniciado_por) VALUES (?1, ?2, ?3)")
+        .bind(plano_id)
+        .bind(plano.membros)
+        .bind(operador_id)
+        .execute(db_pool)
+        .await?
+        .last_insert_rowid();
+    tracing::info!("📞 Exercício de chamada {} do plano {} iniciado por {}.", id, plano.nome, operador_id);
+
+    for no in cascata(db_pool, plano_id, None).await? {
+        notificacao_service::notificar(
+            db_pool,
+            &no.user_id,
+            notificacao_service::TIPO_CHAMADA,
+            &format!("Exercício de chamada: {}", plano.nome),
+            "Está em curso um exercício do plano de chamada. Ligue a quem lhe compete e confirme a receção em /chamada.",
+        )
+        .await;
+    }
+    Ok(id)
+}
+
+/// Regista a confirmação de receção de `user_id` num exercício em curso (pelo próprio ou por quem gere).
+/// Devolve false se já estava confirmado.
+pub async fn confirmar(db_pool: &SqlitePool, organizacao_id: i64, exercicio_id: i64, user_id: &str, operador_id: &str) -> AppResult<bool> {
+    let exercicio = obter_exercicio(db_pool, organizacao_id, exercicio_id).await?;
+    if !exercicio.aberto() {
+        return Err(AppError::Conflict(format!("O exercício {} já foi encerrado.", exercicio_id)));
+    }
+    let membro: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM chamada_ligacoes WHERE plano_id = ?1 AND user_id = ?2)")
+        .bind(exercicio.plano_id)
+        .bind(user_id)
+        .fetch_one(db_pool)
+        .await?;
+    if !membro {
+        return Err(AppError::Conflict(format!("{} não faz parte do plano \"{}\".", user_id, exercicio.plano)));
+    }
+    let registada = sqlx::query("INSERT OR IGNORE INTO chamada_confirmacoes (exercicio_id, user_id, registado_por) VALUES (?1, ?2, ?3)")
+        .bind(exercicio_id)
+        .bind(user_id)
+        .bind(operador_id)
+        .execute(db_pool)
+        .await?
+        .rows_affected()
+        > 0;
+    if registada {
+        tracing::info!("📞 Exercício {}: receção de {} confirmada por {}.", exercicio_id, user_id, operador_id);
+    }
+    Ok(registada)
+}
+
+/// Encerra um exercício em curso. Devolve o exercício (com as confirmações).
+pub async fn encerrar_exercicio(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<ExercicioChamada> {
+    let exercicio = obter_exercicio(db_pool, organizacao_id, id).await?;
+    if !exercicio.aberto() {
+        return Err(AppError::Conflict(format!("O exercício {} já foi encerrado.", id)));
+    }
+    sqlx::query("UPDATE chamada_exercicios SET encerrado_em = datetime('now'), encerrado_por = ?2 WHERE id = ?1")
+        .bind(id)
+        .bind(operador_id)
+        .execute(db_pool)
+        .await?;
+    tracing::info!(
+        "📞 Exercício de chamada {} encerrado por {}: {}/{} confirmações.",
+        id, operador_id, exercicio.confirmados, exercicio.total
+    );
+    Ok(exercicio)
+}
+
+/// Minutos entre o início do exercício e uma confirmação (ambos como guardados na base de dados).
+pub fn minutos_desde(inicio: &str, momento: &str) -> Option<i64> {
+    Some((tempo::ler_utc(momento)? - tempo::ler_utc(inicio)?).num_minutes())
+}
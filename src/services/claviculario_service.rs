@@ -0,0 +1,239 @@
+// src/services/claviculario_service.rs
+//! Claviculário: o registo das chaves de cada sala ou área e os levantamentos. Cada chave só pode estar
+//! fora uma vez; o levantamento guarda quem a levou e o operador que a entregou, e a devolução quem a
+//! recebeu. Uma chave que continua fora depois da hora limite do dia em que foi levantada está em atraso
+//! (a hora é de cada chave, ex: salas de aula às 18:00, ginásio às 22:00).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::claviculario::{Chave, MovimentoChave},
+    tempo,
+};
+use chrono::{NaiveDateTime, NaiveTime};
+use sqlx::SqlitePool;
+
+/// Movimentos mostrados no histórico.
+pub const HISTORICO_RECENTE: i64 = 100;
+/// Hora limite proposta para uma chave nova.
+pub const HORA_LIMITE_PADRAO: &str = "22:00";
+
+// --- REGISTO DAS CHAVES ---
+
+/// Chaves da organização, por código (só as ativas, se `so_ativas`), com quem as tem.
+pub async fn listar_chaves(db_pool: &SqlitePool, organizacao_id: i64, so_ativas: bool) -> AppResult<Vec<Chave>> {
+    let chaves = sqlx::query_as::<_, Chave>(
+        r#"
+        SELECT k.id, k.codigo, k.local, k.hora_limite, k.ativa,
+               m.id AS movimento_id, m.user_id AS detentor_id, u.name AS detentor, m.levantada_em
+        FROM chaves k
+        LEFT JOIN chave_movimentos m ON m.chave_id = k.id AND m.devolvida_em IS NULL
+        LEFT JOIN users u ON u.id = m.user_id
+        WHERE k.organizacao_id = ?1 AND (?2 = 0 OR k.ativa = 1)
+        ORDER BY k.codigo COLLATE NOCASE
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(so_ativas)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(chaves)
+}
+
+/// Acrescenta uma chave ao registo. Devolve o ID.
+pub async fn criar_chave(db_pool: &SqlitePool, organizacao_id: i64, codigo: &str, local: &str, hora_limite: &str) -> AppResult<i64> {
+    let resultado = sqlx::query("INSERT INTO chaves (organizacao_id, codigo, local, hora_limite) VALUES (?1, ?2, ?3, ?4)")
+        .bind(organizacao_id)
+        .bind(codigo.trim())
+        .bind(local.trim())
+        .bind(hora_limite.trim())
+        .execute(db_pool)
+        .await;
+    match resultado {
+        Ok(r) => Ok(r.last_insert_rowid()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::validation("codigo", "Já existe uma chave com este código."))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Altera o local, a hora limite e o estado de uma chave. Devolve a chave como estava.
+pub async fn atualizar_chave(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    local: &str,
+    hora_limite: &str,
+    ativa: bool,
+) -> AppResult<Chave> {
+    let anterior = listar_chaves(db_pool, organizacao_id, false)
+        .await?
+        .into_iter()
+        .find(|k| k.id == id)
+        .ok_or_else(|| AppError::NotFound(format!("Chave {} não encontrada.", id)))?;
+    sqlx::query("UPDATE chaves SET local = ?2, hora_limite = ?3, ativa = ?4 WHERE id = ?1")
+        .bind(id)
+        .bind(local.trim())
+        .bind(hora_limite.trim())
+        .bind(ativa)
+        .execute(db_pool)
+        .await?;
+    Ok(anterior)
+}
+
+// --- LEVANTAMENTOS ---
+
+/// Levantamentos da organização. `?2` = só os desta chave; `?3` = só os deste utilizador; `?4` = só as
+/// chaves fora (1) ou todos (0); `?6` = só este levantamento.
+const SQL_MOVIMENTOS: &str = r#"
+    SELECT m.id, m.chave_id, k.codigo, k.local, k.hora_limite, m.user_id, u.name, u.turma, m.observacoes,
+           m.levantada_em, COALESCE(e.name, m.entregue_por) AS entregue_por,
+           m.devolvida_em, COALESCE(r.name, m.recebida_por) AS recebida_por
+    FROM chave_movimentos m
+    JOIN chaves k ON k.id = m.chave_id
+    JOIN users u ON u.id = m.user_id
+    LEFT JOIN users e ON e.id = m.entregue_por
+    LEFT JOIN users r ON r.id = m.recebida_por
+    WHERE m.organizacao_id = ?1
+      AND (?2 IS NULL OR m.chave_id = ?2)
+      AND (?3 IS NULL OR m.user_id = ?3)
+      AND (?4 = 0 OR m.devolvida_em IS NULL)
+      AND (?6 IS NULL OR m.id = ?6)
+    ORDER BY m.levantada_em DESC, m.id DESC
+    LIMIT ?5
+"#;
+
+/// Chaves fora (de um utilizador, se indicado), as levantadas há mais tempo primeiro.
+pub async fn fora(db_pool: &SqlitePool, organizacao_id: i64, user_id: Option<&str>) -> AppResult<Vec<MovimentoChave>> {
+    let mut movimentos = sqlx::query_as::<_, MovimentoChave>(SQL_MOVIMENTOS)
+        .bind(organizacao_id)
+        .bind(None::<i64>)
+        .bind(user_id)
+        .bind(true)
+        .bind(i64::MAX)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    movimentos.reverse();
+    Ok(movimentos)
+}
+
+/// Últimos levantamentos (de uma chave e/ou de um utilizador, se indicados), os mais recentes primeiro.
+pub async fn historico(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    chave_id: Option<i64>,
+    user_id: Option<&str>,
+    limite: i64,
+) -> AppResult<Vec<MovimentoChave>> {
+    let movimentos = sqlx::query_as::<_, MovimentoChave>(SQL_MOVIMENTOS)
+        .bind(organizacao_id)
+        .bind(chave_id)
+        .bind(user_id)
+        .bind(false)
+        .bind(limite)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(movimentos)
+}
+
+/// Momento (no fuso da aplicação) até ao qual a chave tinha de voltar: a hora limite do dia em que foi
+/// levantada. Uma chave levantada depois da hora limite fica logo em atraso.
+pub fn prazo(levantada_em: &str, hora_limite: &str) -> Option<NaiveDateTime> {
+    let levantada = tempo::local(&tempo::ler_utc(levantada_em)?);
+    let hora = NaiveTime::parse_from_str(hora_limite, "%H:%M").ok()?;
+    Some(levantada.date_naive().and_time(hora))
+}
+
+/// A chave continua fora depois do prazo (`agora` no fuso da aplicação).
+pub fn em_atraso(movimento: &MovimentoChave, agora: NaiveDateTime) -> bool {
+    movimento.devolvida_em.is_none() && prazo(&movimento.levantada_em, &movimento.hora_limite).is_some_and(|p| agora > p)
+}
+
+/// Chaves fora depois da hora limite (as levantadas há mais tempo primeiro).
+pub async fn atrasadas(db_pool: &SqlitePool, organizacao_id: i64, agora: NaiveDateTime) -> AppResult<Vec<MovimentoChave>> {
+    let movimentos = fora(db_pool, organizacao_id, None).await?;
+    Ok(movimentos.into_iter().filter(|m| em_atraso(m, agora)).collect())
+}
+
+/// Entrega uma chave a um utilizador. Devolve o ID do levantamento e o código da chave.
+pub async fn levantar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    chave_id: i64,
+    user_id: &str,
+    observacoes: &str,
+    operador_id: &str,
+) -> AppResult<(i64, String)> {
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(db_pool)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    let chave = listar_chaves(db_pool, organizacao_id, true)
+        .await?
+        .into_iter()
+        .find(|k| k.id == chave_id)
+        .ok_or_else(|| AppError::validation("chave_id", "Chave não encontrada ou inativa."))?;
+    let ja_fora = |chave: &Chave| {
+        AppError::validation(
+            "chave_id",
+            format!("A chave {} está com {}: registe primeiro a devolução.", chave.codigo, chave.detentor_id.as_deref().unwrap_or("outro utilizador")),
+        )
+    };
+    if !chave.disponivel() {
+        return Err(ja_fora(&chave));
+    }
+
+    // O índice único das chaves fora decide entre dois levantamentos ao mesmo tempo
+    let resultado = sqlx::query(
+        "INSERT INTO chave_movimentos (organizacao_id, chave_id, user_id, observacoes, entregue_por) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(organizacao_id)
+    .bind(chave_id)
+    .bind(user_id)
+    .bind(observacoes.trim())
+    .bind(operador_id)
+    .execute(db_pool)
+    .await;
+    let id = match resultado {
+        Ok(r) => r.last_insert_rowid(),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Err(ja_fora(&chave)),
+        Err(e) => return Err(e.into()),
+    };
+    tracing::info!("🔑 Chave {} ({}) entregue a {} por {}.", chave.codigo, chave.local, user_id, operador_id);
+    Ok((id, chave.codigo))
+}
+
+/// Regista a devolução de uma chave. Devolve o levantamento (já fechado).
+pub async fn devolver(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<MovimentoChave> {
+    // Duas devoluções ao mesmo tempo: só a primeira fecha o levantamento
+    let fechado = sqlx::query(
+        "UPDATE chave_movimentos SET devolvida_em = datetime('now'), recebida_por = ?3 WHERE id = ?1 AND organizacao_id = ?2 AND devolvida_em IS NULL",
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    let movimento = sqlx::query_as::<_, MovimentoChave>(SQL_MOVIMENTOS)
+        .bind(organizacao_id)
+        .bind(None::<i64>)
+        .bind(None::<String>)
+        .bind(false)
+        .bind(1)
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Levantamento {} não encontrado.", id)))?;
+    if fechado == 0 {
+        return Err(AppError::Conflict(format!("A chave {} já foi devolvida.", movimento.codigo)));
+    }
+    tracing::info!("🔑 Chave {} devolvida por {}, recebida por {}.", movimento.codigo, movimento.user_id, operador_id);
+    Ok(movimento)
+}
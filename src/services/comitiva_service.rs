@@ -0,0 +1,358 @@
+// src/services/comitiva_service.rs
+//! Comitivas (/comitivas): um grupo que sai junto (competição, visita) entre duas datas, com um oficial
+//! responsável. Criar a comitiva regista, na mesma transação, uma indisponibilidade do período para cada
+//! membro (com `comitiva_id`, fora da escala); acrescentar ou retirar um membro faz o mesmo só para ele.
+//! Nos dias do período, enquanto a comitiva está aberta, os membros aparecem fora na presença (ver
+//! `em_curso`), sem marcação de saída. Encerrar a comitiva apaga as indisponibilidades e a presença volta
+//! a ser a das marcações.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::comitiva::{Comitiva, MembroComitiva},
+    services::notificacao_service,
+    tempo,
+};
+use chrono::NaiveDate;
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+
+/// Tipos de comitiva (código, descrição).
+pub const TIPOS: &[(&str, &str)] = &[("competicao", "Competição"), ("visita", "Visita"), ("outra", "Outra")];
+
+/// Comitivas encerradas mostradas no histórico.
+pub const ENCERRADAS_RECENTES: i64 = 30;
+
+/// Descrição de um tipo.
+pub fn descrever_tipo(tipo: &str) -> &str {
+    TIPOS.iter().find(|(c, _)| *c == tipo).map_or(tipo, |(_, d)| *d)
+}
+
+/// Comitivas da organização num estado, as mais recentes primeiro.
+const SQL_COMITIVAS: &str = r#"
+    SELECT c.id, c.nome, c.tipo, c.destino, c.data_inicio, c.data_fim, c.responsavel_id,
+           COALESCE(r.name, c.responsavel_id) AS responsavel, c.estado, c.encerrada_em,
+           (SELECT COUNT(*) FROM comitiva_membros m WHERE m.comitiva_id = c.id) AS membros
+    FROM comitivas c
+    LEFT JOIN users r ON r.id = c.responsavel_id
+    WHERE c.organizacao_id = ?1 AND c.estado = ?2
+    ORDER BY c.data_inicio DESC, c.id DESC
+    LIMIT ?3
+"#;
+
+/// Comitivas abertas.
+pub async fn abertas(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<Comitiva>> {
+    let comitivas = sqlx::query_as::<_, Comitiva>(SQL_COMITIVAS)
+        .bind(organizacao_id)
+        .bind("aberta")
+        .bind(i64::MAX)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(comitivas)
+}
+
+/// Últimas comitivas encerradas.
+pub async fn encerradas(db_pool: &SqlitePool, organizacao_id: i64, limite: i64) -> AppResult<Vec<Comitiva>> {
+    let comitivas = sqlx::query_as::<_, Comitiva>(SQL_COMITIVAS)
+        .bind(organizacao_id)
+        .bind("encerrada")
+        .bind(limite)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(comitivas)
+}
+
+/// Uma comitiva pelo id (da organização).
+pub async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Comitiva> {
+    sqlx::query_as::<_, Comitiva>(
+        r#"
+        SELECT c.id, c.nome, c.tipo, c.destino, c.data_inicio, c.data_fim, c.responsavel_id,
+               COALESCE(r.name, c.responsavel_id) AS responsavel, c.estado, c.encerrada_em,
+               (SELECT COUNT(*) FROM comitiva_membros m WHERE m.comitiva_id = c.id) AS membros
+        FROM comitivas c
+        LEFT JOIN users r ON r.id = c.responsavel_id
+        WHERE c.id = ?1 AND c.organizacao_id = ?2
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Comitiva {} não encontrada.", id)))
+}
+
+/// Membros de uma comitiva, por turma e nome.
+pub async fn membros(db_pool: &SqlitePool, comitiva_id: i64) -> AppResult<Vec<MembroComitiva>> {
+    let membros = sqlx::query_as::<_, MembroComitiva>(
+        r#"
+        SELECT m.user_id, u.name, u.turma
+        FROM comitiva_membros m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.comitiva_id = ?1
+        ORDER BY u.turma, u.name
+        "#,
+    )
+    .bind(comitiva_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(membros)
+}
+
+/// Verifica os membros a juntar (no campo `campo` do formulário): utilizadores ativos da organização e
+/// sem outra comitiva aberta com o período sobreposto a [inicio, fim].
+async fn validar_membros(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    comitiva_id: Option<i64>,
+    campo: &str,
+    ids: &[String],
+    inicio: &str,
+    fim: &str,
+) -> AppResult<()> {
+    let mut erros: Vec<String> = Vec::new();
+    for id in ids {
+        let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+            .bind(id)
+            .bind(organizacao_id)
+            .fetch_one(db_pool)
+            .await?;
+        if !existe {
+            erros.push(format!("Utilizador '{}' não encontrado.", id));
+            continue;
+        }
+        let outra: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT c.nome FROM comitiva_membros m
+            JOIN comitivas c ON c.id = m.comitiva_id
+            WHERE m.user_id = ?1 AND c.organizacao_id = ?2 AND c.estado = 'aberta'
+              AND c.id <> COALESCE(?3, 0) AND c.data_inicio <= ?5 AND c.data_fim >= ?4
+            LIMIT 1
+            "#,
+        )
+        .bind(id)
+        .bind(organizacao_id)
+        .bind(comitiva_id)
+        .bind(inicio)
+        .bind(fim)
+        .fetch_optional(db_pool)
+        .await?;
+        if let Some(outra) = outra {
+            erros.push(format!("{} já está na comitiva \"{}\" nesse período.", id, outra));
+        }
+    }
+    if erros.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::validation(campo, erros.join(" ")))
+    }
+}
+
+/// Junta `user_id` à comitiva, com a indisponibilidade do período, na transação.
+async fn juntar(conn: &mut SqliteConnection, comitiva_id: i64, nome: &str, inicio: &str, fim: &str, user_id: &str) -> AppResult<()> {
+    sqlx::query("INSERT INTO comitiva_membros (comitiva_id, user_id) VALUES (?1, ?2)")
+        .bind(comitiva_id)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        "INSERT INTO indisponibilidades (user_id, data_inicio, data_fim, motivo, comitiva_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(user_id)
+    .bind(inicio)
+    .bind(fim)
+    .bind(format!("Comitiva: {}", nome))
+    .bind(comitiva_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Avisa os utilizadores de que fazem parte da comitiva.
+async fn avisar(db_pool: &SqlitePool, comitiva: &Comitiva, user_ids: &[String]) {
+    let periodo = match (tempo::ler_data(&comitiva.data_inicio), tempo::ler_data(&comitiva.data_fim)) {
+        (Some(inicio), Some(fim)) => format!("de {} a {}", tempo::data_curta(inicio), tempo::data_curta(fim)),
+        _ => format!("de {} a {}", comitiva.data_inicio, comitiva.data_fim),
+    };
+    for user_id in user_ids {
+        notificacao_service::notificar(
+            db_pool,
+            user_id,
+            notificacao_service::TIPO_COMITIVA,
+            &format!("Comitiva: {}", comitiva.nome),
+            &format!(
+                "Faz parte da comitiva {}{} (responsável: {}). Fica fora da escala nesse período.",
+                periodo,
+                if comitiva.destino.is_empty() { String::new() } else { format!(" a {}", comitiva.destino) },
+                comitiva.responsavel
+            ),
+        )
+        .await;
+    }
+}
+
+/// Cria uma comitiva com o responsável e os membros (IDs), as indisponibilidades do período e avisa-os.
+/// O período não pode já ter terminado. Devolve o ID.
+#[allow(clippy::too_many_arguments)]
+pub async fn criar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    nome: &str,
+    tipo: &str,
+    destino: &str,
+    inicio: NaiveDate,
+    fim: NaiveDate,
+    responsavel_id: &str,
+    membros: &[String],
+    hoje: NaiveDate,
+    operador_id: &str,
+) -> AppResult<i64> {
+    if !TIPOS.iter().any(|(c, _)| *c == tipo) {
+        return Err(AppError::validation("tipo", "Tipo de comitiva inválido."));
+    }
+    if fim < inicio {
+        return Err(AppError::validation("data_fim", "A data fim deve ser igual ou posterior ao início."));
+    }
+    if fim < hoje {
+        return Err(AppError::validation("data_fim", "O período da comitiva já terminou."));
+    }
+    let (inicio, fim) = (inicio.to_string(), fim.to_string());
+    validar_membros(db_pool, organizacao_id, None, "responsavel", &[responsavel_id.to_string()], &inicio, &fim).await?;
+    // O responsável também é membro (e não se repete)
+    let mut todos = vec![responsavel_id.to_string()];
+    for id in membros {
+        if !todos.contains(id) {
+            todos.push(id.clone());
+        }
+    }
+    validar_membros(db_pool, organizacao_id, None, "membros", &todos[1..], &inicio, &fim).await?;
+
+    let mut tx = db_pool.begin().await?;
+    let id = sqlx::query(
+        r#"
+        INSERT INTO comitivas (organizacao_id, nome, tipo, destino, data_inicio, data_fim, responsavel_id, criada_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(nome.trim())
+    .bind(tipo)
+    .bind(destino.trim())
+    .bind(&inicio)
+    .bind(&fim)
+    .bind(responsavel_id)
+    .bind(operador_id)
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+    for user_id in &todos {
+        juntar(&mut tx, id, nome.trim(), &inicio, &fim, user_id).await?;
+    }
+    tx.commit().await?;
+
+    tracing::info!(
+        "🚌 Comitiva {} ({}, {} a {}) criada por {} com {} membros.",
+        id, nome.trim(), inicio, fim, operador_id, todos.len()
+    );
+    let comitiva = obter(db_pool, organizacao_id, id).await?;
+    avisar(db_pool, &comitiva, &todos).await;
+    Ok(id)
+}
+
+/// Falha (conflito) se a comitiva já foi encerrada.
+fn exigir_aberta(comitiva: &Comitiva) -> AppResult<()> {
+    if comitiva.aberta() {
+        Ok(())
+    } else {
+        Err(AppError::Conflict(format!("A comitiva \"{}\" já foi encerrada.", comitiva.nome)))
+    }
+}
+
+/// Junta membros (IDs) a uma comitiva aberta, com as indisponibilidades, e avisa-os. Os que já são
+/// membros são ignorados. Devolve quantos entraram.
+pub async fn adicionar_membros(db_pool: &SqlitePool, organizacao_id: i64, id: i64, user_ids: &[String]) -> AppResult<usize> {
+    let comitiva = obter(db_pool, organizacao_id, id).await?;
+    exigir_aberta(&comitiva)?;
+    let atuais: Vec<String> = membros(db_pool, id).await?.into_iter().map(|m| m.user_id).collect();
+    let mut novos: Vec<String> = Vec::new();
+    for user_id in user_ids {
+        if !atuais.contains(user_id) && !novos.contains(user_id) {
+            novos.push(user_id.clone());
+        }
+    }
+    validar_membros(db_pool, organizacao_id, Some(id), "membros", &novos, &comitiva.data_inicio, &comitiva.data_fim).await?;
+
+    let mut tx = db_pool.begin().await?;
+    for user_id in &novos {
+        juntar(&mut tx, id, &comitiva.nome, &comitiva.data_inicio, &comitiva.data_fim, user_id).await?;
+    }
+    tx.commit().await?;
+    tracing::info!("🚌 Comitiva {}: {} membros acrescentados.", id, novos.len());
+    avisar(db_pool, &comitiva, &novos).await;
+    Ok(novos.len())
+}
+
+/// Retira um membro de uma comitiva aberta e apaga a sua indisponibilidade (volta à escala e à presença
+/// das marcações). O responsável não sai. Devolve a comitiva.
+pub async fn remover_membro(db_pool: &SqlitePool, organizacao_id: i64, id: i64, user_id: &str) -> AppResult<Comitiva> {
+    let comitiva = obter(db_pool, organizacao_id, id).await?;
+    exigir_aberta(&comitiva)?;
+    if comitiva.responsavel_id == user_id {
+        return Err(AppError::Conflict(format!("{} é o responsável da comitiva e não pode sair.", comitiva.responsavel)));
+    }
+    let mut tx = db_pool.begin().await?;
+    let removido = sqlx::query("DELETE FROM comitiva_membros WHERE comitiva_id = ?1 AND user_id = ?2")
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if removido == 0 {
+        return Err(AppError::NotFound(format!("{} não é membro da comitiva {}.", user_id, id)));
+    }
+    sqlx::query("DELETE FROM indisponibilidades WHERE comitiva_id = ?1 AND user_id = ?2")
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!("🚌 Comitiva {}: {} retirado.", id, user_id);
+    Ok(comitiva)
+}
+
+/// Encerra uma comitiva aberta e apaga as indisponibilidades que criou. Devolve a comitiva (antes de
+/// encerrada) e quantas indisponibilidades foram apagadas.
+pub async fn encerrar(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<(Comitiva, u64)> {
+    let comitiva = obter(db_pool, organizacao_id, id).await?;
+    exigir_aberta(&comitiva)?;
+    let mut tx = db_pool.begin().await?;
+    sqlx::query("UPDATE comitivas SET estado = 'encerrada', encerrada_por = ?2, encerrada_em = datetime('now') WHERE id = ?1")
+        .bind(id)
+        .bind(operador_id)
+        .execute(&mut *tx)
+        .await?;
+    let libertados = sqlx::query("DELETE FROM indisponibilidades WHERE comitiva_id = ?1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+    tracing::info!("🚌 Comitiva {} ({}) encerrada por {}: {} indisponibilidades apagadas.", id, comitiva.nome, operador_id, libertados);
+    Ok((comitiva, libertados))
+}
+
+/// Utilizadores numa comitiva aberta que decorre em `hoje` (user_id -> nome da comitiva). Usado na
+/// presença: estão fora sem marcação de saída.
+pub async fn em_curso(db_pool: &SqlitePool, organizacao_id: i64, hoje: NaiveDate) -> AppResult<HashMap<String, String>> {
+    let linhas: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT m.user_id, c.nome
+        FROM comitiva_membros m
+        JOIN comitivas c ON c.id = m.comitiva_id
+        WHERE c.organizacao_id = ?1 AND c.estado = 'aberta' AND ?2 BETWEEN c.data_inicio AND c.data_fim
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(hoje.to_string())
+    .fetch_all(db_pool)
+    .await?;
+    Ok(linhas.into_iter().collect())
+}
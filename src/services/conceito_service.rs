@@ -0,0 +1,275 @@
+// src/services/conceito_service.rs
+//! Conceito (comportamento): lançamentos positivos (elogio, mérito) e negativos (demérito e a punição de
+//! uma FATD) com pontos. O conceito de um período (semestre) é a soma dos pontos com a data nesse período,
+//! e cada lançamento mostra o saldo acumulado. A decisão de punição de um processo lança aqui
+//! `PONTOS_POR_SERVICO` negativos por serviço, na mesma transação (ver `disciplina_service::decidir`).
+//! A classificação por turma ordena o efetivo ativo pelo conceito do período.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::conceito::{PeriodoConceito, PosicaoConceito, RegistoConceito},
+    services::notificacao_service,
+    tempo,
+};
+use chrono::{Datelike, Duration, NaiveDate};
+use sqlx::{SqliteConnection, SqlitePool};
+
+pub const ORIGEM_ELOGIO: &str = "elogio";
+pub const ORIGEM_MERITO: &str = "merito";
+pub const ORIGEM_DEMERITO: &str = "demerito";
+pub const ORIGEM_FATD: &str = "fatd";
+
+/// Origens (código, descrição, positiva).
+pub const ORIGENS: &[(&str, &str, bool)] = &[
+    (ORIGEM_ELOGIO, "Elogio", true),
+    (ORIGEM_MERITO, "Mérito", true),
+    (ORIGEM_DEMERITO, "Demérito", false),
+    (ORIGEM_FATD, "Punição (FATD)", false),
+];
+/// Origens registadas à mão (a FATD vem da decisão do processo).
+pub const ORIGENS_MANUAIS: &[&str] = &[ORIGEM_ELOGIO, ORIGEM_MERITO, ORIGEM_DEMERITO];
+
+/// Pontos negativos por serviço de punição de uma FATD.
+pub const PONTOS_POR_SERVICO: i64 = 5;
+/// Pontos de um lançamento manual, no máximo.
+pub const MAX_PONTOS: i64 = 50;
+/// Semestres no seletor de período.
+pub const PERIODOS_MOSTRADOS: usize = 6;
+
+/// Descrição de uma origem.
+pub fn descrever_origem(origem: &str) -> &str {
+    ORIGENS.iter().find(|(c, _, _)| *c == origem).map_or(origem, |(_, d, _)| *d)
+}
+
+/// Semestre `semestre` (1 ou 2) de `ano`.
+fn semestre(ano: i32, semestre: u32) -> Option<PeriodoConceito> {
+    let (inicio, fim) = match semestre {
+        1 => (NaiveDate::from_ymd_opt(ano, 1, 1)?, NaiveDate::from_ymd_opt(ano, 6, 30)?),
+        2 => (NaiveDate::from_ymd_opt(ano, 7, 1)?, NaiveDate::from_ymd_opt(ano, 12, 31)?),
+        _ => return None,
+    };
+    Some(PeriodoConceito {
+        codigo: format!("{}-{}", ano, semestre),
+        rotulo: format!("{}º semestre de {}", semestre, ano),
+        inicio,
+        fim,
+    })
+}
+
+/// Semestre a que pertence `data`.
+pub fn periodo_de(data: NaiveDate) -> PeriodoConceito {
+    let numero = if data.month() <= 6 { 1 } else { 2 };
+    // O ano de uma data válida tem sempre os dois semestres
+    semestre(data.year(), numero).expect("semestre de uma data válida")
+}
+
+/// Período pelo código ("AAAA-1" ou "AAAA-2").
+pub fn ler_periodo(codigo: &str) -> Option<PeriodoConceito> {
+    let (ano, numero) = codigo.trim().split_once('-')?;
+    semestre(ano.parse().ok()?, numero.parse().ok()?)
+}
+
+/// O semestre de `hoje` e os `PERIODOS_MOSTRADOS - 1` anteriores, do mais recente para o mais antigo.
+pub fn periodos_recentes(hoje: NaiveDate) -> Vec<PeriodoConceito> {
+    let mut periodos = vec![periodo_de(hoje)];
+    while periodos.len() < PERIODOS_MOSTRADOS {
+        let anterior = periodos[periodos.len() - 1].inicio - Duration::days(1);
+        periodos.push(periodo_de(anterior));
+    }
+    periodos
+}
+
+/// Lançamentos de `user_id` no período, por data, com o saldo acumulado.
+pub async fn registos(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str, periodo: &PeriodoConceito) -> AppResult<Vec<RegistoConceito>> {
+    let registos = sqlx::query_as::<_, RegistoConceito>(
+        r#"
+        SELECT c.id, c.data, c.origem, c.pontos, c.motivo, c.processo_id,
+               COALESCE(r.name, c.registado_por) AS registado_por,
+               SUM(c.pontos) OVER (ORDER BY c.data, c.id) AS saldo
+        FROM conceito_registos c
+        LEFT JOIN users r ON r.id = c.registado_por
+        WHERE c.organizacao_id = ?1 AND c.user_id = ?2 AND c.data BETWEEN ?3 AND ?4
+        ORDER BY c.data, c.id
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(periodo.inicio.to_string())
+    .bind(periodo.fim.to_string())
+    .fetch_all(db_pool)
+    .await?;
+    Ok(registos)
+}
+
+/// Regista um elogio, mérito ou demérito de `pontos` (sem sinal) pelo fato de `data` (até `hoje`) e
+/// avisa o utilizador. Devolve o ID.
+#[allow(clippy::too_many_arguments)]
+pub async fn registar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: &str,
+    data: NaiveDate,
+    origem: &str,
+    pontos: i64,
+    motivo: &str,
+    hoje: NaiveDate,
+    operador_id: &str,
+) -> AppResult<i64> {
+    if !ORIGENS_MANUAIS.contains(&origem) {
+        return Err(AppError::validation("origem", "Tipo de lançamento inválido."));
+    }
+    if !(1..=MAX_PONTOS).contains(&pontos) {
+        return Err(AppError::validation("pontos", format!("De 1 a {} pontos.", MAX_PONTOS)));
+    }
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(db_pool)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    if user_id == operador_id {
+        return Err(AppError::validation("user", "Não pode lançar no seu próprio conceito."));
+    }
+    if data > hoje {
+        return Err(AppError::validation("data", "O fato não pode ser numa data futura."));
+    }
+
+    let positiva = ORIGENS.iter().any(|(c, _, p)| *c == origem && *p);
+    let pontos = if positiva { pontos } else { -pontos };
+    let id = sqlx::query(
+        r#"
+        INSERT INTO conceito_registos (organizacao_id, user_id, data, origem, pontos, motivo, registado_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(data.to_string())
+    .bind(origem)
+    .bind(pontos)
+    .bind(motivo.trim())
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+    tracing::info!("🎖️ Conceito de {}: {} ({:+} pontos) lançado por {}.", user_id, origem, pontos, operador_id);
+
+    notificacao_service::notificar(
+        db_pool,
+        user_id,
+        notificacao_service::TIPO_CONCEITO,
+        &format!("{} no conceito", descrever_origem(origem)),
+        &format!("{:+} pontos ({}): {}", pontos, tempo::data_curta(data), motivo.trim()),
+    )
+    .await;
+    Ok(id)
+}
+
+/// Lança no conceito a punição de uma FATD (`servicos` serviços), na transação da decisão. Devolve os
+/// pontos lançados (negativos).
+pub async fn lancar_fatd(
+    conn: &mut SqliteConnection,
+    organizacao_id: i64,
+    user_id: &str,
+    data_fato: &str,
+    processo_id: i64,
+    servicos: i64,
+    operador_id: &str,
+) -> AppResult<i64> {
+    let pontos = -(servicos * PONTOS_POR_SERVICO);
+    sqlx::query(
+        r#"
+        INSERT INTO conceito_registos (organizacao_id, user_id, data, origem, pontos, motivo, processo_id, registado_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(data_fato)
+    .bind(ORIGEM_FATD)
+    .bind(pontos)
+    .bind(format!("Processo disciplinar {}: punição com {} serviço(s)", processo_id, servicos))
+    .bind(processo_id)
+    .bind(operador_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(pontos)
+}
+
+/// Apaga um lançamento registado à mão (os de uma FATD ficam com o processo). Devolve o lançamento.
+pub async fn remover(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<(String, String, i64)> {
+    let (user_id, origem, pontos, processo_id): (String, String, i64, Option<i64>) =
+        sqlx::query_as("SELECT user_id, origem, pontos, processo_id FROM conceito_registos WHERE id = ?1 AND organizacao_id = ?2")
+            .bind(id)
+            .bind(organizacao_id)
+            .fetch_optional(db_pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Lançamento de conceito {} não encontrado.", id)))?;
+    if let Some(processo_id) = processo_id {
+        return Err(AppError::Conflict(format!(
+            "O lançamento {} decorre do processo disciplinar {} e não pode ser apagado.",
+            id, processo_id
+        )));
+    }
+    sqlx::query("DELETE FROM conceito_registos WHERE id = ?1").bind(id).execute(db_pool).await?;
+    Ok((user_id, origem, pontos))
+}
+
+/// Conceito de `user_id` no período (soma dos pontos).
+pub async fn saldo(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str, periodo: &PeriodoConceito) -> AppResult<i64> {
+    let saldo: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(pontos), 0) FROM conceito_registos WHERE organizacao_id = ?1 AND user_id = ?2 AND data BETWEEN ?3 AND ?4",
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(periodo.inicio.to_string())
+    .bind(periodo.fim.to_string())
+    .fetch_one(db_pool)
+    .await?;
+    Ok(saldo)
+}
+
+/// Classificação do efetivo ativo pelo conceito do período, por turma (todas, ou só `turma`); quem não
+/// tem lançamentos conta com 0.
+pub async fn classificacao(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    periodo: &PeriodoConceito,
+    turma: Option<&str>,
+) -> AppResult<Vec<PosicaoConceito>> {
+    let posicoes = sqlx::query_as::<_, PosicaoConceito>(
+        r#"
+        SELECT u.id AS user_id, u.name, u.turma,
+               COALESCE(SUM(CASE WHEN c.pontos > 0 THEN c.pontos END), 0) AS positivos,
+               COALESCE(SUM(CASE WHEN c.pontos < 0 THEN c.pontos END), 0) AS negativos,
+               COALESCE(SUM(c.pontos), 0) AS saldo,
+               RANK() OVER (PARTITION BY u.turma ORDER BY COALESCE(SUM(c.pontos), 0) DESC) AS posicao
+        FROM users u
+        LEFT JOIN conceito_registos c
+               ON c.user_id = u.id AND c.organizacao_id = u.organizacao_id AND c.data BETWEEN ?2 AND ?3
+        WHERE u.organizacao_id = ?1 AND u.ativo = 1 AND (?4 IS NULL OR u.turma = ?4)
+        GROUP BY u.id
+        ORDER BY u.turma, saldo DESC, u.name
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(periodo.inicio.to_string())
+    .bind(periodo.fim.to_string())
+    .bind(turma)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(posicoes)
+}
+
+/// Turmas do efetivo ativo (filtro da classificação).
+pub async fn turmas(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<String>> {
+    let turmas = sqlx::query_scalar::<_, String>(
+        "SELECT DISTINCT turma FROM users WHERE organizacao_id = ?1 AND ativo = 1 AND turma <> '' ORDER BY turma",
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(turmas)
+}
@@ -0,0 +1,212 @@
+// src/services/contabilidade_service.rs
+//! Contabilidade dos serviços: cada serviço creditado ou debitado é uma linha do livro `servico_ledger`
+//! (só se acrescenta). Os contadores `users.servicos_rn` / `servicos_rd` / `saldo_punicoes`, usados pela
+//! geração da escala, são a soma do livro: `lancar` atualiza os dois na mesma transação, e
+//! `reconciliar` reescreve os contadores a partir do livro se alguma vez divergirem.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::contabilidade::{LancamentoServico, Reconciliacao},
+    services::escala_service::TipoRotina,
+};
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+/// Serviço atribuído pela geração da escala (ou punição cumprida).
+pub const MOTIVO_ALOCACAO: &str = "alocacao";
+/// Serviço devolvido ao regenerar um dia em rascunho.
+pub const MOTIVO_REGENERACAO: &str = "regeneracao";
+/// Serviço passado de um militar para outro numa cobertura aprovada.
+pub const MOTIVO_TROCA: &str = "troca";
+/// Punição aplicada num processo disciplinar (a referência é o ID do processo).
+pub const MOTIVO_FATD: &str = "fatd";
+/// Saldo passado de um utilizador duplicado para o canónico na fusão (a referência é o outro utilizador).
+pub const MOTIVO_FUSAO: &str = "fusao";
+
+/// Contador afetado por um lançamento.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conta {
+    RN,
+    RD,
+    /// Serviços de punição em dívida (-1 = punição cumprida).
+    Punicao,
+}
+
+impl Conta {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Conta::RN => "RN",
+            Conta::RD => "RD",
+            Conta::Punicao => "PUNICAO",
+        }
+    }
+
+    /// Conta pelo código guardado no livro (`as_str`).
+    pub fn de_str(conta: &str) -> Option<Self> {
+        match conta {
+            "RN" => Some(Conta::RN),
+            "RD" => Some(Conta::RD),
+            "PUNICAO" => Some(Conta::Punicao),
+            _ => None,
+        }
+    }
+}
+
+impl From<TipoRotina> for Conta {
+    fn from(tipo: TipoRotina) -> Self {
+        match tipo {
+            TipoRotina::RN => Conta::RN,
+            TipoRotina::RD => Conta::RD,
+        }
+    }
+}
+
+/// Regista um lançamento no livro e soma `delta` ao contador, sempre na transação de quem chama:
+/// se falhar, a operação inteira é desfeita em vez de deixar o livro e os contadores diferentes.
+pub async fn lancar(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    conta: Conta,
+    delta: i64,
+    motivo: &str,
+    data: Option<&str>,
+    referencia: Option<&str>,
+) -> AppResult<()> {
+    let resultado = sqlx::query(
+        r#"
+        INSERT INTO servico_ledger (id, organizacao_id, user_id, conta, delta, motivo, data, referencia)
+        SELECT ?1, organizacao_id, id, ?3, ?4, ?5, ?6, ?7 FROM users WHERE id = ?2
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(user_id)
+    .bind(conta.as_str())
+    .bind(delta)
+    .bind(motivo)
+    .bind(data)
+    .bind(referencia)
+    .execute(&mut *conn)
+    .await?;
+    if resultado.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+
+    // Uma instrução fixa por coluna (verificada na compilação)
+    match conta {
+        Conta::RN => sqlx::query!("UPDATE users SET servicos_rn = COALESCE(servicos_rn, 0) + ?1 WHERE id = ?2", delta, user_id),
+        Conta::RD => sqlx::query!("UPDATE users SET servicos_rd = COALESCE(servicos_rd, 0) + ?1 WHERE id = ?2", delta, user_id),
+        Conta::Punicao => {
+            sqlx::query!("UPDATE users SET saldo_punicoes = COALESCE(saldo_punicoes, 0) + ?1 WHERE id = ?2", delta, user_id)
+        }
+    }
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Contadores e somas do livro de cada utilizador da organização (`?1`); `?2` = só os que divergem.
+const SQL_RECONCILIACAO: &str = r#"
+    WITH livro AS (
+        SELECT user_id,
+               SUM(CASE WHEN conta = 'RN' THEN delta ELSE 0 END) AS rn,
+               SUM(CASE WHEN conta = 'RD' THEN delta ELSE 0 END) AS rd,
+               SUM(CASE WHEN conta = 'PUNICAO' THEN delta ELSE 0 END) AS punicoes
+        FROM servico_ledger
+        GROUP BY user_id
+    )
+    SELECT u.id AS user_id, u.name,
+           COALESCE(u.servicos_rn, 0) AS servicos_rn, COALESCE(l.rn, 0) AS livro_rn,
+           COALESCE(u.servicos_rd, 0) AS servicos_rd, COALESCE(l.rd, 0) AS livro_rd,
+           COALESCE(u.saldo_punicoes, 0) AS saldo_punicoes, COALESCE(l.punicoes, 0) AS livro_punicoes
+    FROM users u
+    LEFT JOIN livro l ON l.user_id = u.id
+    WHERE u.organizacao_id = ?1
+      AND (?2 = 0
+           OR COALESCE(u.servicos_rn, 0) <> COALESCE(l.rn, 0)
+           OR COALESCE(u.servicos_rd, 0) <> COALESCE(l.rd, 0)
+           OR COALESCE(u.saldo_punicoes, 0) <> COALESCE(l.punicoes, 0))
+    ORDER BY u.name
+"#;
+
+/// Utilizadores da organização cujos contadores não batem certo com o livro.
+pub async fn divergencias(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<Reconciliacao>> {
+    let divergencias = sqlx::query_as::<_, Reconciliacao>(SQL_RECONCILIACAO)
+        .bind(organizacao_id)
+        .bind(true)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(divergencias)
+}
+
+/// Reescreve os contadores divergentes da organização com as somas do livro (o livro é que conta).
+/// Devolve o número de utilizadores corrigidos.
+pub async fn reconciliar(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<u64> {
+    let mut tx = db_pool.begin().await?;
+    let divergentes = sqlx::query_as::<_, Reconciliacao>(SQL_RECONCILIACAO)
+        .bind(organizacao_id)
+        .bind(true)
+        .fetch_all(&mut *tx)
+        .await?;
+    for r in &divergentes {
+        sqlx::query("UPDATE users SET servicos_rn = ?1, servicos_rd = ?2, saldo_punicoes = ?3 WHERE id = ?4")
+            .bind(r.livro_rn)
+            .bind(r.livro_rd)
+            .bind(r.livro_punicoes)
+            .bind(&r.user_id)
+            .execute(&mut *tx)
+            .await?;
+        tracing::warn!(
+            "📒 Contadores de {} reconciliados com o livro: RN {} -> {}, RD {} -> {}, punições {} -> {}.",
+            r.user_id, r.servicos_rn, r.livro_rn, r.servicos_rd, r.livro_rd, r.saldo_punicoes, r.livro_punicoes
+        );
+    }
+    tx.commit().await?;
+    Ok(divergentes.len() as u64)
+}
+
+/// Verificação de todas as organizações, para a tarefa agendada: só conta (e regista) as divergências.
+pub async fn verificar(db_pool: &SqlitePool) -> AppResult<String> {
+    let organizacoes: Vec<i64> = sqlx::query_scalar("SELECT id FROM organizacoes ORDER BY id")
+        .fetch_all(db_pool)
+        .await?;
+    let mut total = 0;
+    for organizacao_id in organizacoes {
+        let divergencias = divergencias(db_pool, organizacao_id).await?;
+        if !divergencias.is_empty() {
+            tracing::warn!(
+                "📒 {} utilizador(es) da organização {} com contadores diferentes do livro de serviços.",
+                divergencias.len(), organizacao_id
+            );
+        }
+        total += divergencias.len();
+    }
+    Ok(match total {
+        0 => "Contadores de acordo com o livro de serviços.".to_string(),
+        n => format!("{} utilizador(es) com contadores diferentes do livro; reconcilie em /admin/contabilidade.", n),
+    })
+}
+
+/// Últimos lançamentos da organização (de um utilizador, se indicado), dos mais recentes para os mais antigos.
+pub async fn lancamentos(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: Option<&str>,
+    limite: i64,
+) -> AppResult<Vec<LancamentoServico>> {
+    let lancamentos = sqlx::query_as::<_, LancamentoServico>(
+        r#"
+        SELECT l.user_id, COALESCE(u.name, l.user_id) AS name, l.conta, l.delta, l.motivo, l.data, l.referencia, l.criado_em
+        FROM servico_ledger l
+        LEFT JOIN users u ON u.id = l.user_id
+        WHERE l.organizacao_id = ?1 AND (?2 IS NULL OR l.user_id = ?2)
+        ORDER BY l.criado_em DESC, l.rowid DESC
+        LIMIT ?3
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(limite)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(lancamentos)
+}
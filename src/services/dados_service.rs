@@ -0,0 +1,306 @@
+// src/services/dados_service.rs
+//! Exportação/importação de todos os dados (para migrar entre instalações): um pacote JSON versionado
+//! com as linhas das tabelas do domínio (utilizadores, roles, grupos, postos, escala, trocas, presença,
+//! indisponibilidades), coluna a coluna.
+//!
+//! A importação acrescenta ou atualiza as linhas (pela chave primária) numa só transação, e só a confirma
+//! se a integridade referencial se mantiver; não apaga o que já existe na instalação.
+
+use crate::{
+    error::{AppError, AppResult},
+    tempo,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::{sqlite::SqliteRow, Column, Row, SqliteConnection, SqlitePool, TypeInfo, ValueRef};
+use std::collections::BTreeMap;
+
+/// Identifica o ficheiro como pacote de dados do Mercal2.
+pub const FORMATO: &str = "mercal2-dados";
+/// Versão do formato do pacote (muda se a estrutura do JSON mudar).
+pub const VERSAO: u32 = 1;
+
+/// Tabelas do pacote, por ordem de dependência (as referenciadas antes das que as referenciam).
+pub const TABELAS: &[&str] = &[
+    "organizacoes",
+    "roles",
+    "users",
+    "user_roles",
+    "user_temporary_roles",
+    "user_fotos",
+    "grupos",
+    "grupo_membros",
+    "postos",
+    "escalas",
+    "alocacoes",
+    "trocas",
+    "presenca",
+    "provas",
+    "uniformes_dia",
+    "comitivas",
+    "comitiva_membros",
+    "indisponibilidades",
+    "servico_ledger",
+    "refeicoes",
+    "arranchamentos",
+    "cardapios",
+    "produtos",
+    "vendas",
+    "venda_itens",
+    "conta_movimentos",
+    "livro_ocorrencias",
+    "livro_fechos",
+    "revista_listas",
+    "revista_itens",
+    "revistas",
+    "revista_falhas",
+    "quartos",
+    "quarto_ocupantes",
+    "material",
+    "cautelas",
+    "cautela_registos",
+    "chaves",
+    "chave_movimentos",
+    "tfm_resultados",
+    "livros",
+    "emprestimos",
+    "lavanderia_sacos",
+    "enquetes",
+    "enquete_opcoes",
+    "enquete_participacoes",
+    "enquete_votos",
+    "baixas",
+    "consulta_vagas",
+    "consultas",
+    "visitantes",
+    "portaria",
+    "agenda_eventos",
+    "documento_pastas",
+    "documentos",
+    "documento_downloads",
+    "processos_disciplinares",
+    "aulas",
+    "sugestoes",
+    "faxina_areas",
+    "faxina_semanas",
+    "faxina_atribuicoes",
+    "faxina_membros",
+    "conceito_registos",
+    "contactos_emergencia",
+    "chamada_planos",
+    "chamada_ligacoes",
+    "chamada_exercicios",
+    "chamada_confirmacoes",
+];
+
+/// Violações de integridade mostradas na mensagem de erro da importação.
+const MAX_VIOLACOES_MOSTRADAS: usize = 10;
+
+/// Pacote exportado: uma lista de linhas (objeto coluna -> valor) por tabela.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pacote {
+    pub formato: String,
+    pub versao: u32,
+    /// Última migração aplicada na instalação de origem (versão do esquema).
+    pub migracao: i64,
+    pub exportado_em: String,
+    pub tabelas: BTreeMap<String, Vec<Map<String, Value>>>,
+}
+
+impl Pacote {
+    /// Linhas por tabela (para o resumo).
+    pub fn contagens(&self) -> Vec<(String, usize)> {
+        self.tabelas.iter().map(|(tabela, linhas)| (tabela.clone(), linhas.len())).collect()
+    }
+}
+
+/// Última migração aplicada a esta base de dados.
+async fn migracao_atual(db_pool: &SqlitePool) -> AppResult<i64> {
+    let versao: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+        .fetch_one(db_pool)
+        .await?;
+    Ok(versao.unwrap_or(0))
+}
+
+/// Número de linhas de cada tabela do pacote nesta instalação.
+pub async fn contagens(db_pool: &SqlitePool) -> AppResult<Vec<(String, i64)>> {
+    let mut contagens = Vec::with_capacity(TABELAS.len());
+    for tabela in TABELAS {
+        let n: i64 = sqlx::query_scalar(&format!(r#"SELECT COUNT(*) FROM "{}""#, tabela))
+            .fetch_one(db_pool)
+            .await?;
+        contagens.push((tabela.to_string(), n));
+    }
+    Ok(contagens)
+}
+
+// --- Exportação ---
+
+/// Exporta todas as tabelas do pacote.
+pub async fn exportar(db_pool: &SqlitePool) -> AppResult<Pacote> {
+    let mut tabelas = BTreeMap::new();
+    for tabela in TABELAS {
+        let linhas = sqlx::query(&format!(r#"SELECT * FROM "{}" ORDER BY rowid"#, tabela))
+            .fetch_all(db_pool)
+            .await?;
+        let linhas = linhas.iter().map(linha_json).collect::<AppResult<Vec<_>>>()?;
+        tabelas.insert(tabela.to_string(), linhas);
+    }
+    Ok(Pacote {
+        formato: FORMATO.to_string(),
+        versao: VERSAO,
+        migracao: migracao_atual(db_pool).await?,
+        exportado_em: tempo::agora().to_rfc3339(),
+        tabelas,
+    })
+}
+
+/// Uma linha como objeto JSON, pelo tipo guardado em cada valor (o SQLite não impõe o tipo da coluna).
+fn linha_json(row: &SqliteRow) -> AppResult<Map<String, Value>> {
+    let mut linha = Map::new();
+    for coluna in row.columns() {
+        let i = coluna.ordinal();
+        let raw = row.try_get_raw(i)?;
+        let valor = if raw.is_null() {
+            Value::Null
+        } else {
+            match raw.type_info().name() {
+                "INTEGER" => Value::from(row.try_get::<i64, _>(i)?),
+                "REAL" => Value::from(row.try_get::<f64, _>(i)?),
+                "TEXT" => Value::from(row.try_get::<String, _>(i)?),
+                outro => {
+                    tracing::error!("Exportação: valor {} não suportado em {}", outro, coluna.name());
+                    return Err(AppError::InternalServerError);
+                }
+            }
+        };
+        linha.insert(coluna.name().to_string(), valor);
+    }
+    Ok(linha)
+}
+
+// --- Importação ---
+
+/// Lê um pacote (JSON) e verifica se pode ser importado nesta instalação.
+pub async fn ler_pacote(db_pool: &SqlitePool, json: &str) -> AppResult<Pacote> {
+    let pacote: Pacote = serde_json::from_str(json)
+        .map_err(|e| AppError::validation("ficheiro", format!("O ficheiro não é um pacote de dados válido: {}", e)))?;
+    if pacote.formato != FORMATO {
+        return Err(AppError::validation("ficheiro", "O ficheiro não é um pacote de dados do Mercal2."));
+    }
+    if pacote.versao != VERSAO {
+        return Err(AppError::validation(
+            "ficheiro",
+            format!("Versão do pacote não suportada ({}; esta instalação lê a {}).", pacote.versao, VERSAO),
+        ));
+    }
+    let atual = migracao_atual(db_pool).await?;
+    if pacote.migracao > atual {
+        return Err(AppError::validation(
+            "ficheiro",
+            format!("O pacote vem de uma instalação mais recente (esquema {}; este é {}). Atualize primeiro.", pacote.migracao, atual),
+        ));
+    }
+    if let Some(tabela) = pacote.tabelas.keys().find(|t| !TABELAS.contains(&t.as_str())) {
+        return Err(AppError::validation("ficheiro", format!("Tabela desconhecida no pacote: '{}'.", tabela)));
+    }
+    Ok(pacote)
+}
+
+/// Importa o pacote: acrescenta ou atualiza as linhas (pela chave primária) numa transação, que só é
+/// confirmada se não houver referências para linhas inexistentes. Devolve as linhas importadas por tabela.
+pub async fn importar(db_pool: &SqlitePool, pacote: &Pacote) -> AppResult<Vec<(String, usize)>> {
+    let mut tx = db_pool.begin().await?;
+    // As chaves estrangeiras são verificadas no fim (abaixo), não linha a linha
+    sqlx::query("PRAGMA defer_foreign_keys = ON").execute(&mut *tx).await?;
+
+    let mut importadas = Vec::new();
+    for tabela in TABELAS {
+        let Some(linhas) = pacote.tabelas.get(*tabela) else { continue };
+        importar_tabela(&mut tx, tabela, linhas).await?;
+        importadas.push((tabela.to_string(), linhas.len()));
+    }
+
+    let violacoes = violacoes_integridade(&mut tx).await?;
+    if !violacoes.is_empty() {
+        let total = violacoes.len();
+        let mut mensagem = violacoes.into_iter().take(MAX_VIOLACOES_MOSTRADAS).collect::<Vec<_>>().join("; ");
+        if total > MAX_VIOLACOES_MOSTRADAS {
+            mensagem.push_str(&format!("; e mais {}", total - MAX_VIOLACOES_MOSTRADAS));
+        }
+        // A transação é desfeita ao sair (nada fica importado)
+        return Err(AppError::Conflict(format!("Importação cancelada, referências inexistentes: {}.", mensagem)));
+    }
+
+    tx.commit().await?;
+    Ok(importadas)
+}
+
+async fn importar_tabela(conn: &mut SqliteConnection, tabela: &str, linhas: &[Map<String, Value>]) -> AppResult<()> {
+    // (nome, posição na chave primária; 0 = não faz parte)
+    let colunas: Vec<(String, i64)> = sqlx::query_as(&format!(r#"SELECT name, pk FROM pragma_table_info('{}')"#, tabela))
+        .fetch_all(&mut *conn)
+        .await?;
+    let chave: Vec<&str> = colunas.iter().filter(|(_, pk)| *pk > 0).map(|(nome, _)| nome.as_str()).collect();
+
+    for (n, linha) in linhas.iter().enumerate() {
+        let erro_linha = |mensagem: String| AppError::validation("ficheiro", format!("{} (linha {}): {}", tabela, n + 1, mensagem));
+        if let Some(desconhecida) = linha.keys().find(|c| !colunas.iter().any(|(nome, _)| nome == *c)) {
+            return Err(erro_linha(format!("coluna desconhecida '{}'", desconhecida)));
+        }
+        let nomes: Vec<&String> = linha.keys().collect();
+        let mut sql = format!(
+            r#"INSERT INTO "{}" ({}) VALUES ({})"#,
+            tabela,
+            nomes.iter().map(|c| format!(r#""{}""#, c)).collect::<Vec<_>>().join(", "),
+            (1..=nomes.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", "),
+        );
+        let atualizar: Vec<String> = nomes
+            .iter()
+            .filter(|c| !chave.contains(&c.as_str()))
+            .map(|c| format!(r#""{0}" = excluded."{0}""#, c))
+            .collect();
+        if !chave.is_empty() {
+            let conflito = chave.iter().map(|c| format!(r#""{}""#, c)).collect::<Vec<_>>().join(", ");
+            if atualizar.is_empty() {
+                sql.push_str(&format!(" ON CONFLICT ({}) DO NOTHING", conflito));
+            } else {
+                sql.push_str(&format!(" ON CONFLICT ({}) DO UPDATE SET {}", conflito, atualizar.join(", ")));
+            }
+        }
+
+        let mut query = sqlx::query(&sql);
+        for (coluna, valor) in linha {
+            query = match valor {
+                Value::Null => query.bind(None::<String>),
+                Value::Bool(b) => query.bind(*b),
+                Value::Number(num) => match num.as_i64() {
+                    Some(i) => query.bind(i),
+                    None => query.bind(num.as_f64()),
+                },
+                Value::String(s) => query.bind(s.clone()),
+                Value::Array(_) | Value::Object(_) => {
+                    return Err(erro_linha(format!("valor inválido na coluna '{}'", coluna)));
+                }
+            };
+        }
+        query.execute(&mut *conn).await.map_err(|e| erro_linha(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Referências (chaves estrangeiras) das tabelas do pacote para linhas que não existem.
+async fn violacoes_integridade(conn: &mut SqliteConnection) -> AppResult<Vec<String>> {
+    let violacoes: Vec<(String, Option<i64>, String)> =
+        sqlx::query_as(r#"SELECT "table", rowid, parent FROM pragma_foreign_key_check"#)
+            .fetch_all(&mut *conn)
+            .await?;
+    Ok(violacoes
+        .into_iter()
+        .filter(|(tabela, _, _)| TABELAS.contains(&tabela.as_str()))
+        .map(|(tabela, rowid, pai)| match rowid {
+            Some(rowid) => format!("{} (rowid {}) → {}", tabela, rowid, pai),
+            None => format!("{} → {}", tabela, pai),
+        })
+        .collect())
+}
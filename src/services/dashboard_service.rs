@@ -0,0 +1,86 @@
+// src/services/dashboard_service.rs
+//! Contagens do painel de administração, sempre de uma organização.
+use crate::{error::AppResult, tempo};
+use sqlx::SqlitePool;
+
+/// Número de utilizadores ativos por ano, ordenado por ano.
+pub async fn users_por_ano(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<(i64, i64)>> {
+    let linhas = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT ano, COUNT(*) FROM users WHERE ativo = 1 AND organizacao_id = ?1 GROUP BY ano ORDER BY ano",
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(linhas)
+}
+
+/// Dias de escala gerados mas ainda não publicados (a partir de hoje).
+pub async fn dias_rascunho(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<i64> {
+    let total = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM escalas WHERE status = 'Rascunho' AND data >= ?1 AND organizacao_id = ?2",
+    )
+    .bind(tempo::hoje().to_string())
+    .bind(organizacao_id)
+    .fetch_one(db_pool)
+    .await?;
+    Ok(total)
+}
+
+/// Trocas pendentes: (à espera do substituto, à espera do escalante).
+pub async fn trocas_pendentes(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<(i64, i64)> {
+    let contagem = sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        SELECT
+            COALESCE(SUM(t.status = 'Pendente'), 0),
+            COALESCE(SUM(t.status = 'AguardandoEscalante'), 0)
+        FROM trocas t
+        JOIN alocacoes a ON a.id = t.alocacao_id
+        WHERE a.organizacao_id = ?1
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_one(db_pool)
+    .await?;
+    Ok(contagem)
+}
+
+/// Utilizadores ativos atualmente fora (última saída posterior ao último retorno, ou numa comitiva em
+/// curso). As datas da presença são RFC3339 na hora local, por isso comparam-se como texto.
+pub async fn pessoas_fora(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<i64> {
+    let total = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*)
+        FROM users u
+        LEFT JOIN presenca p ON p.user_id = u.id
+        WHERE u.ativo = 1 AND u.organizacao_id = ?1
+          AND (
+            (p.ultima_saida IS NOT NULL AND (p.ultimo_retorno IS NULL OR p.ultima_saida > p.ultimo_retorno))
+            OR EXISTS (
+                SELECT 1 FROM comitiva_membros m
+                JOIN comitivas c ON c.id = m.comitiva_id
+                WHERE m.user_id = u.id AND c.estado = 'aberta' AND ?2 BETWEEN c.data_inicio AND c.data_fim
+            )
+          )
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(tempo::hoje().to_string())
+    .fetch_one(db_pool)
+    .await?;
+    Ok(total)
+}
+
+/// Punições por cumprir: (nº de utilizadores com saldo, total de serviços em dívida).
+pub async fn punicoes_pendentes(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<(i64, i64)> {
+    let resumo = sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        SELECT COUNT(*), COALESCE(SUM(saldo_punicoes), 0)
+        FROM users
+        WHERE ativo = 1 AND saldo_punicoes > 0 AND organizacao_id = ?1
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_one(db_pool)
+    .await?;
+    Ok(resumo)
+}
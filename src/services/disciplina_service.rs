@@ -0,0 +1,340 @@
+// src/services/disciplina_service.rs
+//! Processos disciplinares (FATD): a transgressão é registada contra um utilizador, que é avisado e tem
+//! `PRAZO_DEFESA_DIAS` para apresentar a defesa; depois (com defesa ou com o prazo terminado) o processo
+//! aguarda a decisão: punição, justificação ou arquivamento.
+//! A punição credita os serviços no saldo de punições pelo livro de serviços (`contabilidade_service`,
+//! motivo `fatd`, referência = ID do processo), na mesma transação da decisão: cada punição da escala
+//! tem assim um processo de origem. Na mesma transação, a punição desconta no conceito do visado
+//! (`conceito_service::lancar_fatd`).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::{contabilidade::LancamentoServico, disciplina::ProcessoDisciplinar},
+    services::{
+        conceito_service,
+        contabilidade_service::{self, Conta},
+        notificacao_service,
+    },
+    tempo,
+};
+use chrono::{Duration, NaiveDate};
+use sqlx::SqlitePool;
+
+// Estados de um processo (coluna `estado`)
+pub const ESTADO_AGUARDA_DEFESA: &str = "aguarda_defesa";
+pub const ESTADO_AGUARDA_DECISAO: &str = "aguarda_decisao";
+pub const ESTADO_PUNIDO: &str = "punido";
+pub const ESTADO_JUSTIFICADO: &str = "justificado";
+pub const ESTADO_ARQUIVADO: &str = "arquivado";
+
+/// Estados (e a descrição), pela ordem do processo.
+pub const ESTADOS: &[(&str, &str)] = &[
+    (ESTADO_AGUARDA_DEFESA, "Aguarda defesa"),
+    (ESTADO_AGUARDA_DECISAO, "Aguarda decisão"),
+    (ESTADO_PUNIDO, "Punido"),
+    (ESTADO_JUSTIFICADO, "Justificado"),
+    (ESTADO_ARQUIVADO, "Arquivado"),
+];
+/// Decisões possíveis (os estados finais).
+pub const DECISOES: &[&str] = &[ESTADO_PUNIDO, ESTADO_JUSTIFICADO, ESTADO_ARQUIVADO];
+
+/// Dias para a defesa, contados a partir do registo (inclusive).
+pub const PRAZO_DEFESA_DIAS: i64 = 3;
+/// Serviços de punição que uma decisão pode aplicar.
+pub const MAX_SERVICOS: i64 = 10;
+/// Processos decididos mostrados na listagem.
+pub const DECIDIDOS_RECENTES: i64 = 50;
+
+/// Descrição de um estado (o próprio código, se for desconhecido).
+pub fn descrever_estado(estado: &str) -> &str {
+    ESTADOS.iter().find(|(e, _)| *e == estado).map_or(estado, |(_, d)| *d)
+}
+
+/// Processos da organização. `?2` = só neste estado; `?3` = só deste utilizador; `?4` = só os abertos (1)
+/// ou só os decididos (0); os abertos pela ordem de registo, os decididos dos mais recentes para os antigos.
+const SQL_PROCESSOS: &str = r#"
+    SELECT p.id, p.user_id, u.name, u.turma, p.data_fato, p.descricao, p.estado, p.prazo_defesa,
+           p.defesa, p.defesa_em, p.servicos, p.fundamentacao,
+           COALESCE(d.name, p.decidido_por) AS decidido_por, p.decidido_em,
+           COALESCE(r.name, p.registado_por) AS registado_por, p.registado_em
+    FROM processos_disciplinares p
+    JOIN users u ON u.id = p.user_id
+    LEFT JOIN users d ON d.id = p.decidido_por
+    LEFT JOIN users r ON r.id = p.registado_por
+    WHERE p.organizacao_id = ?1
+      AND (?2 IS NULL OR p.estado = ?2)
+      AND (?3 IS NULL OR p.user_id = ?3)
+      AND (?4 IS NULL OR (p.decidido_em IS NULL) = ?4)
+    ORDER BY CASE WHEN p.decidido_em IS NULL THEN p.id END, p.decidido_em DESC, p.id DESC
+    LIMIT ?5
+"#;
+
+/// Acerta as horas lidas da base de dados para a hora local.
+fn hora_local(p: ProcessoDisciplinar) -> ProcessoDisciplinar {
+    ProcessoDisciplinar {
+        defesa_em: tempo::formatar_opcional(p.defesa_em, tempo::FORMATO_DATA_HORA),
+        decidido_em: tempo::formatar_opcional(p.decidido_em, tempo::FORMATO_DATA_HORA),
+        registado_em: tempo::formatar(&p.registado_em, tempo::FORMATO_DATA_HORA),
+        ..p
+    }
+}
+
+/// Processos da organização, com filtros opcionais: `abertos` = só os abertos (true) ou os decididos (false).
+pub async fn listar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    estado: Option<&str>,
+    user_id: Option<&str>,
+    abertos: Option<bool>,
+    limite: i64,
+) -> AppResult<Vec<ProcessoDisciplinar>> {
+    let processos = sqlx::query_as::<_, ProcessoDisciplinar>(SQL_PROCESSOS)
+        .bind(organizacao_id)
+        .bind(estado)
+        .bind(user_id)
+        .bind(abertos)
+        .bind(limite)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(processos.into_iter().map(hora_local).collect())
+}
+
+/// Um processo pelo id (da organização).
+pub async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<ProcessoDisciplinar> {
+    let processo = sqlx::query_as::<_, ProcessoDisciplinar>(
+        r#"
+        SELECT p.id, p.user_id, u.name, u.turma, p.data_fato, p.descricao, p.estado, p.prazo_defesa,
+               p.defesa, p.defesa_em, p.servicos, p.fundamentacao,
+               COALESCE(d.name, p.decidido_por) AS decidido_por, p.decidido_em,
+               COALESCE(r.name, p.registado_por) AS registado_por, p.registado_em
+        FROM processos_disciplinares p
+        JOIN users u ON u.id = p.user_id
+        LEFT JOIN users d ON d.id = p.decidido_por
+        LEFT JOIN users r ON r.id = p.registado_por
+        WHERE p.id = ?1 AND p.organizacao_id = ?2
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Processo {} não encontrado.", id)))?;
+    Ok(hora_local(processo))
+}
+
+/// Abre um processo contra `user_id` pelo fato de `data_fato` (até `hoje`) e avisa o visado. Devolve o ID.
+pub async fn abrir(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: &str,
+    data_fato: NaiveDate,
+    descricao: &str,
+    hoje: NaiveDate,
+    operador_id: &str,
+) -> AppResult<i64> {
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(db_pool)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    if user_id == operador_id {
+        return Err(AppError::validation("user", "Não pode abrir um processo contra si próprio."));
+    }
+    if data_fato > hoje {
+        return Err(AppError::validation("data_fato", "O fato não pode ser numa data futura."));
+    }
+
+    let prazo = hoje + Duration::days(PRAZO_DEFESA_DIAS);
+    let id = sqlx::query(
+        r#"
+        INSERT INTO processos_disciplinares (organizacao_id, user_id, data_fato, descricao, prazo_defesa, registado_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(data_fato.to_string())
+    .bind(descricao.trim())
+    .bind(prazo.to_string())
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+    tracing::info!("⚖️ Processo disciplinar {} aberto contra {} por {} (defesa até {}).", id, user_id, operador_id, prazo);
+
+    notificacao_service::notificar(
+        db_pool,
+        user_id,
+        notificacao_service::TIPO_DISCIPLINA,
+        &format!("Processo disciplinar {}", id),
+        &format!(
+            "Foi registada uma transgressão de {} contra si. Pode apresentar a defesa até {} em /disciplina/{}.",
+            tempo::data_curta(data_fato),
+            tempo::data_curta(prazo),
+            id
+        ),
+    )
+    .await;
+    Ok(id)
+}
+
+/// O visado apresenta a defesa (dentro do prazo): o processo passa a aguardar a decisão.
+pub async fn apresentar_defesa(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    user_id: &str,
+    defesa: &str,
+    hoje: NaiveDate,
+) -> AppResult<ProcessoDisciplinar> {
+    let processo = obter(db_pool, organizacao_id, id).await?;
+    if processo.user_id != user_id {
+        return Err(AppError::NotFound(format!("Processo {} não encontrado.", id)));
+    }
+    if !processo.aguarda_defesa() {
+        return Err(AppError::Conflict(format!("A defesa do processo {} já foi encerrada.", id)));
+    }
+    if processo.prazo_terminado(&hoje.to_string()) {
+        return Err(AppError::Conflict(format!("O prazo da defesa do processo {} terminou.", id)));
+    }
+    // A condição repete a verificação do estado: a defesa não passa por cima de um encerramento entretanto feito
+    let resultado = sqlx::query(
+        "UPDATE processos_disciplinares SET estado = ?2, defesa = ?3, defesa_em = datetime('now') WHERE id = ?1 AND estado = ?4",
+    )
+    .bind(id)
+    .bind(ESTADO_AGUARDA_DECISAO)
+    .bind(defesa.trim())
+    .bind(ESTADO_AGUARDA_DEFESA)
+    .execute(db_pool)
+    .await?;
+    if resultado.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!("A defesa do processo {} já foi encerrada.", id)));
+    }
+    tracing::info!("⚖️ Defesa apresentada por {} no processo {}.", user_id, id);
+    Ok(processo)
+}
+
+/// Encerra a fase da defesa sem defesa apresentada (só depois de terminado o prazo).
+pub async fn encerrar_defesa(db_pool: &SqlitePool, organizacao_id: i64, id: i64, hoje: NaiveDate) -> AppResult<ProcessoDisciplinar> {
+    let processo = obter(db_pool, organizacao_id, id).await?;
+    if !processo.aguarda_defesa() {
+        return Err(AppError::Conflict(format!("O processo {} já não aguarda a defesa.", id)));
+    }
+    if !processo.prazo_terminado(&hoje.to_string()) {
+        return Err(AppError::Conflict(format!(
+            "O prazo da defesa do processo {} só termina a {}.",
+            id,
+            tempo::ler_data(&processo.prazo_defesa).map_or(processo.prazo_defesa.clone(), tempo::data_curta)
+        )));
+    }
+    let resultado = sqlx::query("UPDATE processos_disciplinares SET estado = ?2, defesa_em = datetime('now') WHERE id = ?1 AND estado = ?3")
+        .bind(id)
+        .bind(ESTADO_AGUARDA_DECISAO)
+        .bind(ESTADO_AGUARDA_DEFESA)
+        .execute(db_pool)
+        .await?;
+    if resultado.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!("O processo {} já não aguarda a defesa.", id)));
+    }
+    tracing::info!("⚖️ Processo {} passa à decisão sem defesa (prazo terminado).", id);
+    Ok(processo)
+}
+
+/// Decide um processo que aguarda decisão. Na punição (`servicos` de 1 a `MAX_SERVICOS`) os serviços são
+/// creditados no saldo de punições do visado e descontados no conceito, na mesma transação. Avisa o visado
+/// e devolve o processo decidido.
+pub async fn decidir(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    decisao: &str,
+    servicos: i64,
+    fundamentacao: &str,
+    operador_id: &str,
+) -> AppResult<ProcessoDisciplinar> {
+    if !DECISOES.contains(&decisao) {
+        return Err(AppError::validation("decisao", "Decisão inválida."));
+    }
+    let servicos = if decisao == ESTADO_PUNIDO { servicos } else { 0 };
+    if decisao == ESTADO_PUNIDO && !(1..=MAX_SERVICOS).contains(&servicos) {
+        return Err(AppError::validation("servicos", format!("A punição tem de ser de 1 a {} serviço(s).", MAX_SERVICOS)));
+    }
+    let processo = obter(db_pool, organizacao_id, id).await?;
+    if !processo.aguarda_decisao() {
+        return Err(AppError::Conflict(format!("O processo {} não aguarda decisão ({}).", id, processo.descricao_estado())));
+    }
+
+    let mut tx = db_pool.begin().await?;
+    let resultado = sqlx::query(
+        r#"
+        UPDATE processos_disciplinares
+        SET estado = ?2, servicos = ?3, fundamentacao = ?4, decidido_por = ?5, decidido_em = datetime('now')
+        WHERE id = ?1 AND estado = ?6
+        "#,
+    )
+    .bind(id)
+    .bind(decisao)
+    .bind(servicos)
+    .bind(fundamentacao.trim())
+    .bind(operador_id)
+    .bind(ESTADO_AGUARDA_DECISAO)
+    .execute(&mut *tx)
+    .await?;
+    if resultado.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!("O processo {} já foi decidido.", id)));
+    }
+    let mut pontos = 0;
+    if servicos > 0 {
+        contabilidade_service::lancar(
+            &mut tx,
+            &processo.user_id,
+            Conta::Punicao,
+            servicos,
+            contabilidade_service::MOTIVO_FATD,
+            Some(&processo.data_fato),
+            Some(&id.to_string()),
+        )
+        .await?;
+        pontos = conceito_service::lancar_fatd(&mut tx, organizacao_id, &processo.user_id, &processo.data_fato, id, servicos, operador_id).await?;
+    }
+    tx.commit().await?;
+    tracing::info!("⚖️ Processo {} decidido por {}: {} ({} serviço(s) de punição).", id, operador_id, decisao, servicos);
+
+    let processo = obter(db_pool, organizacao_id, id).await?;
+    let mensagem = if servicos > 0 {
+        format!("Decisão: punição com {} serviço(s), a cumprir nas próximas escalas ({} pontos no conceito).", servicos, pontos)
+    } else {
+        format!("Decisão: {}.", descrever_estado(decisao).to_lowercase())
+    };
+    notificacao_service::notificar(
+        db_pool,
+        &processo.user_id,
+        notificacao_service::TIPO_DISCIPLINA,
+        &format!("Processo disciplinar {} decidido", id),
+        &mensagem,
+    )
+    .await;
+    Ok(processo)
+}
+
+/// Lançamentos do livro de serviços com origem no processo (a punição creditada).
+pub async fn lancamentos(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Vec<LancamentoServico>> {
+    let lancamentos = sqlx::query_as::<_, LancamentoServico>(
+        r#"
+        SELECT l.user_id, COALESCE(u.name, l.user_id) AS name, l.conta, l.delta, l.motivo, l.data, l.referencia, l.criado_em
+        FROM servico_ledger l
+        LEFT JOIN users u ON u.id = l.user_id
+        WHERE l.organizacao_id = ?1 AND l.motivo = ?2 AND l.referencia = ?3
+        ORDER BY l.criado_em, l.rowid
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(contabilidade_service::MOTIVO_FATD)
+    .bind(id.to_string())
+    .fetch_all(db_pool)
+    .await?;
+    Ok(lancamentos)
+}
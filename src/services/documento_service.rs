@@ -0,0 +1,309 @@
+// src/services/documento_service.rs
+//! Repositório de documentos: normas, ordens de serviço e instruções em PDF, organizados em pastas com
+//! uma categoria e regras de visibilidade (roles e anos; vazio = todos). Quem tem a permissão
+//! "documentos" cria as pastas, envia e apaga documentos e vê tudo; os restantes só as pastas que as
+//! regras lhes abrem. Os ficheiros ficam em `documentos.diretorio` e cada descarga fica registada.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::documento::{DadosPasta, Documento, DownloadDocumento, PastaDocumentos},
+    tempo,
+};
+use sqlx::SqlitePool;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Categorias das pastas (código, descrição), pela ordem da página.
+pub const CATEGORIAS: &[(&str, &str)] = &[
+    ("normas", "Normas"),
+    ("ordens_servico", "Ordens de serviço"),
+    ("instrucoes", "Instruções"),
+    ("outros", "Outros"),
+];
+/// Último ano que se pode indicar (como na ficha do utilizador).
+pub const MAX_ANO: i64 = 5;
+
+/// Descrição de uma categoria.
+pub fn descrever_categoria(categoria: &str) -> &str {
+    CATEGORIAS.iter().find(|(c, _)| *c == categoria).map_or(categoria, |(_, d)| *d)
+}
+
+/// Colunas de `PastaDocumentos` (com o número de documentos).
+const SELECT_PASTA: &str = r#"
+    SELECT p.id, p.categoria, p.nome, p.descricao, p.roles, p.anos,
+           (SELECT COUNT(*) FROM documentos d WHERE d.pasta_id = p.id) AS documentos
+    FROM documento_pastas p
+"#;
+
+/// Colunas de `Documento` (com quem enviou e o número de descargas).
+const SELECT_DOCUMENTO: &str = r#"
+    SELECT d.id, d.pasta_id, d.titulo, d.ficheiro, d.nome_original, d.tamanho,
+           COALESCE(u.name, d.enviado_por) AS enviado_por, d.enviado_em,
+           (SELECT COUNT(*) FROM documento_downloads dd WHERE dd.documento_id = d.id) AS downloads
+    FROM documentos d
+    LEFT JOIN users u ON u.id = d.enviado_por
+"#;
+
+/// Pastas da organização, por categoria e nome. Com `roles`/`ano`, só as visíveis para esse utilizador
+/// (None = todas, para quem gere o repositório).
+pub async fn listar_pastas(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    visiveis_para: Option<(&[String], i64)>,
+) -> AppResult<Vec<PastaDocumentos>> {
+    let pastas = sqlx::query_as::<_, PastaDocumentos>(&format!(
+        "{} WHERE p.organizacao_id = ?1 ORDER BY p.categoria, p.nome COLLATE NOCASE",
+        SELECT_PASTA
+    ))
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(match visiveis_para {
+        Some((roles, ano)) => pastas.into_iter().filter(|p| p.visivel_para(roles, ano)).collect(),
+        None => pastas,
+    })
+}
+
+/// Uma pasta pelo id (da organização).
+pub async fn obter_pasta(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<PastaDocumentos> {
+    sqlx::query_as::<_, PastaDocumentos>(&format!("{} WHERE p.id = ?1 AND p.organizacao_id = ?2", SELECT_PASTA))
+        .bind(id)
+        .bind(organizacao_id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Pasta {} não encontrada.", id)))
+}
+
+/// Categoria e anos conhecidos (o resto já foi validado pelo handler).
+fn validar_pasta(dados: &DadosPasta) -> AppResult<()> {
+    if !CATEGORIAS.iter().any(|(c, _)| *c == dados.categoria) {
+        return Err(AppError::validation("categoria", format!("Categoria desconhecida: '{}'.", dados.categoria)));
+    }
+    if let Some(ano) = dados.anos.iter().find(|a| !(1..=MAX_ANO).contains(*a)) {
+        return Err(AppError::validation("anos", format!("Ano inválido: {}.", ano)));
+    }
+    Ok(())
+}
+
+/// Lista separada por vírgulas, ordenada e sem repetidos.
+fn lista_texto<T: Ord + ToString + Clone>(valores: &[T]) -> String {
+    let mut valores = valores.to_vec();
+    valores.sort_unstable();
+    valores.dedup();
+    valores.iter().map(T::to_string).collect::<Vec<_>>().join(",")
+}
+
+/// O nome de uma pasta é único na organização.
+fn nome_repetido(e: sqlx::Error, nome: &str) -> AppError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            AppError::validation("nome", format!("Já existe uma pasta '{}'.", nome.trim()))
+        }
+        _ => e.into(),
+    }
+}
+
+/// Cria uma pasta. Devolve o ID.
+pub async fn criar_pasta(db_pool: &SqlitePool, organizacao_id: i64, dados: &DadosPasta, operador_id: &str) -> AppResult<i64> {
+    validar_pasta(dados)?;
+    let id = sqlx::query(
+        r#"
+        INSERT INTO documento_pastas (organizacao_id, categoria, nome, descricao, roles, anos, criado_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(&dados.categoria)
+    .bind(dados.nome.trim())
+    .bind(dados.descricao.trim())
+    .bind(lista_texto(&dados.roles))
+    .bind(lista_texto(&dados.anos))
+    .bind(operador_id)
+    .execute(db_pool)
+    .await
+    .map_err(|e| nome_repetido(e, &dados.nome))?
+    .last_insert_rowid();
+    tracing::info!("📁 Pasta de documentos {} ({}) criada por {}.", id, dados.nome.trim(), operador_id);
+    Ok(id)
+}
+
+/// Altera uma pasta (categoria, nome, descrição e visibilidade). Devolve a pasta como estava.
+pub async fn atualizar_pasta(db_pool: &SqlitePool, organizacao_id: i64, id: i64, dados: &DadosPasta) -> AppResult<PastaDocumentos> {
+    let anterior = obter_pasta(db_pool, organizacao_id, id).await?;
+    validar_pasta(dados)?;
+    sqlx::query("UPDATE documento_pastas SET categoria = ?2, nome = ?3, descricao = ?4, roles = ?5, anos = ?6 WHERE id = ?1")
+        .bind(id)
+        .bind(&dados.categoria)
+        .bind(dados.nome.trim())
+        .bind(dados.descricao.trim())
+        .bind(lista_texto(&dados.roles))
+        .bind(lista_texto(&dados.anos))
+        .execute(db_pool)
+        .await
+        .map_err(|e| nome_repetido(e, &dados.nome))?;
+    Ok(anterior)
+}
+
+/// Apaga uma pasta vazia. Devolve a pasta apagada.
+pub async fn remover_pasta(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<PastaDocumentos> {
+    let pasta = obter_pasta(db_pool, organizacao_id, id).await?;
+    if pasta.documentos > 0 {
+        return Err(AppError::Conflict(format!(
+            "A pasta '{}' tem {} documento(s): apague-os primeiro.",
+            pasta.nome, pasta.documentos
+        )));
+    }
+    sqlx::query("DELETE FROM documento_pastas WHERE id = ?1").bind(id).execute(db_pool).await?;
+    Ok(pasta)
+}
+
+/// Acerta a hora do envio para a hora local.
+fn hora_local(documento: Documento) -> Documento {
+    Documento { enviado_em: tempo::formatar(&documento.enviado_em, tempo::FORMATO_DATA_HORA), ..documento }
+}
+
+/// Documentos de uma pasta, por título.
+pub async fn listar_documentos(db_pool: &SqlitePool, organizacao_id: i64, pasta_id: i64) -> AppResult<Vec<Documento>> {
+    let documentos = sqlx::query_as::<_, Documento>(&format!(
+        "{} WHERE d.pasta_id = ?1 AND d.organizacao_id = ?2 ORDER BY d.titulo COLLATE NOCASE",
+        SELECT_DOCUMENTO
+    ))
+    .bind(pasta_id)
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(documentos.into_iter().map(hora_local).collect())
+}
+
+/// Um documento pelo id (da organização).
+pub async fn obter_documento(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Documento> {
+    sqlx::query_as::<_, Documento>(&format!("{} WHERE d.id = ?1 AND d.organizacao_id = ?2", SELECT_DOCUMENTO))
+        .bind(id)
+        .bind(organizacao_id)
+        .fetch_optional(db_pool)
+        .await?
+        .map(hora_local)
+        .ok_or_else(|| AppError::NotFound(format!("Documento {} não encontrado.", id)))
+}
+
+/// O nome vem da base de dados mas foi gerado por `enviar`: não sai do diretório dos documentos.
+fn caminho(dir: &Path, ficheiro: &str) -> AppResult<std::path::PathBuf> {
+    if ficheiro.contains(['/', '\\']) || ficheiro.starts_with('.') {
+        return Err(AppError::NotFound("Ficheiro do documento inválido.".to_string()));
+    }
+    Ok(dir.join(ficheiro))
+}
+
+/// Guarda um PDF enviado na pasta indicada. Devolve o documento criado.
+#[allow(clippy::too_many_arguments)]
+pub async fn enviar(
+    db_pool: &SqlitePool,
+    dir: &Path,
+    organizacao_id: i64,
+    pasta_id: i64,
+    titulo: &str,
+    nome_original: &str,
+    conteudo: &[u8],
+    operador_id: &str,
+) -> AppResult<Documento> {
+    obter_pasta(db_pool, organizacao_id, pasta_id).await?;
+    if !conteudo.starts_with(b"%PDF-") {
+        return Err(AppError::validation("ficheiro", "O ficheiro não é um PDF."));
+    }
+
+    let ficheiro = format!("{}.pdf", Uuid::new_v4());
+    tokio::fs::create_dir_all(dir).await.map_err(|e| {
+        tracing::error!("Erro ao criar o diretório dos documentos {}: {}", dir.display(), e);
+        AppError::InternalServerError
+    })?;
+    let destino = caminho(dir, &ficheiro)?;
+    tokio::fs::write(&destino, conteudo).await.map_err(|e| {
+        tracing::error!("Erro ao gravar o documento {}: {}", destino.display(), e);
+        AppError::InternalServerError
+    })?;
+
+    let resultado = sqlx::query(
+        r#"
+        INSERT INTO documentos (organizacao_id, pasta_id, titulo, ficheiro, nome_original, tamanho, enviado_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(pasta_id)
+    .bind(titulo.trim())
+    .bind(&ficheiro)
+    .bind(nome_original.trim())
+    .bind(conteudo.len() as i64)
+    .bind(operador_id)
+    .execute(db_pool)
+    .await;
+    let id = match resultado {
+        Ok(r) => r.last_insert_rowid(),
+        Err(e) => {
+            // Sem registo, o ficheiro ficaria órfão no diretório
+            let _ = tokio::fs::remove_file(&destino).await;
+            return Err(e.into());
+        }
+    };
+    tracing::info!("📄 Documento {} ({}, {} bytes) enviado para a pasta {} por {}.", id, titulo.trim(), conteudo.len(), pasta_id, operador_id);
+    obter_documento(db_pool, organizacao_id, id).await
+}
+
+/// Conteúdo do PDF de um documento.
+pub async fn conteudo(dir: &Path, documento: &Documento) -> AppResult<Vec<u8>> {
+    let origem = caminho(dir, &documento.ficheiro)?;
+    tokio::fs::read(&origem).await.map_err(|e| {
+        tracing::error!("Erro ao ler o documento {}: {}", origem.display(), e);
+        AppError::NotFound(format!("O ficheiro do documento {} não foi encontrado.", documento.id))
+    })
+}
+
+/// Regista a descarga de um documento. Como a auditoria, nunca faz falhar a descarga.
+pub async fn registar_download(db_pool: &SqlitePool, documento_id: i64, user_id: &str) {
+    if let Err(e) = sqlx::query("INSERT INTO documento_downloads (documento_id, user_id) VALUES (?1, ?2)")
+        .bind(documento_id)
+        .bind(user_id)
+        .execute(db_pool)
+        .await
+    {
+        tracing::error!("Falha ao registar a descarga do documento {} por {}: {:?}", documento_id, user_id, e);
+    }
+}
+
+/// Descargas de um documento, por páginas (mais recentes primeiro), e o total.
+pub async fn listar_downloads(db_pool: &SqlitePool, documento_id: i64, limite: i64, offset: i64) -> AppResult<(Vec<DownloadDocumento>, i64)> {
+    let mut downloads = sqlx::query_as::<_, DownloadDocumento>(
+        r#"
+        SELECT dd.user_id, COALESCE(u.name, dd.user_id) AS nome, dd.descarregado_em
+        FROM documento_downloads dd
+        LEFT JOIN users u ON u.id = dd.user_id
+        WHERE dd.documento_id = ?1
+        ORDER BY dd.id DESC
+        LIMIT ?2 OFFSET ?3
+        "#,
+    )
+    .bind(documento_id)
+    .bind(limite)
+    .bind(offset)
+    .fetch_all(db_pool)
+    .await?;
+    for download in &mut downloads {
+        download.descarregado_em = tempo::formatar(&download.descarregado_em, tempo::FORMATO_DATA_HORA);
+    }
+
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM documento_downloads WHERE documento_id = ?1")
+        .bind(documento_id)
+        .fetch_one(db_pool)
+        .await?;
+    Ok((downloads, total))
+}
+
+/// Apaga um documento (com o registo das descargas e o ficheiro). Devolve o documento apagado.
+pub async fn remover_documento(db_pool: &SqlitePool, dir: &Path, organizacao_id: i64, id: i64) -> AppResult<Documento> {
+    let documento = obter_documento(db_pool, organizacao_id, id).await?;
+    sqlx::query("DELETE FROM documentos WHERE id = ?1").bind(id).execute(db_pool).await?;
+    if let Err(e) = tokio::fs::remove_file(caminho(dir, &documento.ficheiro)?).await {
+        tracing::warn!("Documento {} apagado, mas o ficheiro {} não: {}", id, documento.ficheiro, e);
+    }
+    Ok(documento)
+}
@@ -0,0 +1,347 @@
+// src/services/email_service.rs
+//! Emails (SMTP): os emails são gerados a partir de modelos (templates/email/*.txt) e ficam numa
+//! caixa de saída (`emails_saida`); uma tarefa em segundo plano entrega-os ao servidor SMTP, com
+//! novas tentativas e espera crescente. Desativado se o servidor SMTP não estiver configurado
+//! (secção `[smtp]` da configuração ou SMTP_HOST).
+//!
+//! Os avisos (escala, trocas) respeitam a preferência do utilizador (`users.emails_ativos`);
+//! os emails de segurança (senha redefinida) são sempre enviados.
+
+use crate::{
+    config::SmtpConfig,
+    error::{AppError, AppResult},
+    services::preferencias_service,
+    templates::{EmailEscalaPublicada, EmailSenhaRedefinida, EmailTrocaDecidida},
+};
+use askama::Template;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use sqlx::SqlitePool;
+use std::{sync::OnceLock, time::Duration};
+
+// Tipos de email (coluna `tipo`)
+pub const TIPO_SENHA: &str = "senha";
+pub const TIPO_ESCALA: &str = "escala";
+pub const TIPO_TROCA: &str = "troca";
+
+/// Porta SMTP por omissão (submissão com STARTTLS).
+const PORTA_PADRAO: u16 = 587;
+/// Tempo máximo de cada envio ao servidor SMTP.
+const TIMEOUT_SMTP: Duration = Duration::from_secs(20);
+/// Tentativas de envio antes de o email ficar como falhado.
+const MAX_TENTATIVAS: i64 = 5;
+/// Espera após a primeira falha; duplica a cada tentativa (1m, 2m, 4m, 8m).
+const ESPERA_BASE_SEGUNDOS: i64 = 60;
+/// Intervalo entre passagens da tarefa de envio.
+const INTERVALO_ENVIO: Duration = Duration::from_secs(15);
+/// Emails enviados por passagem.
+const LOTE_ENVIO: i64 = 20;
+/// Dias que os emails enviados ou falhados ficam na caixa de saída.
+const DIAS_REGISTO: i64 = 30;
+/// Intervalo entre limpezas da caixa de saída.
+const INTERVALO_LIMPEZA: Duration = Duration::from_secs(60 * 60);
+/// Comprimento máximo guardado da mensagem de erro de uma tentativa.
+const MAX_ERRO: usize = 300;
+
+/// Segurança da ligação ao servidor SMTP (SMTP_TLS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegurancaSmtp {
+    StartTls, // "starttls" (por omissão)
+    Tls,      // "tls" (ligação cifrada desde o início, normalmente na porta 465)
+    Nenhuma,  // "none" (só para servidores locais/de teste)
+}
+
+/// Configuração do servidor de email (construída no arranque a partir da configuração da aplicação).
+pub struct EmailConfig {
+    pub remetente: Mailbox,
+    transporte: AsyncSmtpTransport<Tokio1Executor>,
+    host: String,
+    porta: u16,
+    seguranca: SegurancaSmtp,
+}
+
+// As credenciais não aparecem nos logs
+impl std::fmt::Debug for EmailConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmailConfig")
+            .field("remetente", &self.remetente.to_string())
+            .field("host", &self.host)
+            .field("porta", &self.porta)
+            .field("seguranca", &self.seguranca)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EmailConfig {
+    /// Usa host, remetente, porta (opcional, 587), tls (opcional: "starttls", "tls" ou "none")
+    /// e utilizador/senha (opcionais, juntos) da secção `[smtp]` (ou SMTP_HOST, SMTP_FROM, ...).
+    /// Ok(None) (desativado) se o host não estiver definido; erro se a configuração estiver incompleta.
+    pub fn from_config(smtp: &SmtpConfig) -> Result<Option<Self>, String> {
+        let valor = |v: &Option<String>| v.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+        let Some(host) = valor(&smtp.host) else {
+            return Ok(None);
+        };
+        let remetente = valor(&smtp.remetente)
+            .ok_or("smtp.remetente (SMTP_FROM) em falta")?
+            .parse::<Mailbox>()
+            .map_err(|e| format!("smtp.remetente (SMTP_FROM) inválido: {}", e))?;
+        let seguranca = match valor(&smtp.tls).map(|v| v.to_lowercase()).as_deref() {
+            None | Some("starttls") => SegurancaSmtp::StartTls,
+            Some("tls") => SegurancaSmtp::Tls,
+            Some("none") => SegurancaSmtp::Nenhuma,
+            Some(outro) => return Err(format!("smtp.tls (SMTP_TLS) inválido: '{}' (use 'starttls', 'tls' ou 'none')", outro)),
+        };
+        let porta = smtp.porta.unwrap_or(PORTA_PADRAO);
+
+        let builder = match seguranca {
+            SegurancaSmtp::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+                .map_err(|e| format!("smtp.host (SMTP_HOST) inválido: {}", e))?,
+            SegurancaSmtp::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)
+                .map_err(|e| format!("smtp.host (SMTP_HOST) inválido: {}", e))?,
+            SegurancaSmtp::Nenhuma => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+        };
+        let mut builder = builder.port(porta).timeout(Some(TIMEOUT_SMTP));
+        match (valor(&smtp.utilizador), valor(&smtp.senha)) {
+            (Some(user), Some(password)) => builder = builder.credentials(Credentials::new(user, password)),
+            (None, None) => {}
+            _ => return Err("smtp.utilizador e smtp.senha (SMTP_USER e SMTP_PASSWORD) têm de ser definidos juntos".to_string()),
+        }
+
+        Ok(Some(Self {
+            remetente,
+            transporte: builder.build(),
+            host,
+            porta,
+            seguranca,
+        }))
+    }
+}
+
+static CONFIG: OnceLock<EmailConfig> = OnceLock::new();
+
+/// Define a configuração do email (chamado uma vez, no arranque, só se estiver ativo).
+pub fn configurar(config: EmailConfig) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("Configuração de email já definida; ignorada.");
+    }
+}
+
+/// Configuração do email, se estiver ativo.
+pub fn config() -> Option<&'static EmailConfig> {
+    CONFIG.get()
+}
+
+// --- Modelos ---
+
+/// Um email gerado a partir de um modelo (templates/email/*.txt).
+pub trait ModeloEmail: Template {
+    /// Tipo do email (coluna `tipo`); só os de segurança ignoram a preferência do utilizador.
+    const TIPO: &'static str;
+    fn assunto(&self) -> String;
+}
+
+impl ModeloEmail for EmailSenhaRedefinida {
+    const TIPO: &'static str = TIPO_SENHA;
+    fn assunto(&self) -> String {
+        "Senha redefinida".to_string()
+    }
+}
+
+impl ModeloEmail for EmailEscalaPublicada {
+    const TIPO: &'static str = TIPO_ESCALA;
+    fn assunto(&self) -> String {
+        "Escala de serviço publicada".to_string()
+    }
+}
+
+impl ModeloEmail for EmailTrocaDecidida {
+    const TIPO: &'static str = TIPO_TROCA;
+    fn assunto(&self) -> String {
+        let decisao = if self.aprovada { "aprovada" } else { "recusada" };
+        format!("Troca {} ({})", decisao, self.data)
+    }
+}
+
+// --- Caixa de saída ---
+
+/// Põe um email na caixa de saída do utilizador, se o email estiver ativo, o utilizador tiver endereço
+/// e (exceto emails de segurança) não tiver desativado os avisos, nem os deste tipo nas preferências.
+/// Tal como as notificações, nunca faz falhar a ação que o originou: em caso de erro apenas loga.
+pub async fn enfileirar<M: ModeloEmail>(db_pool: &SqlitePool, user_id: &str, modelo: &M) {
+    if config().is_none() {
+        return;
+    }
+    if let Err(e) = enfileirar_impl(db_pool, user_id, modelo).await {
+        tracing::error!("Falha ao criar o email '{}' para {}: {:?}", M::TIPO, user_id, e);
+    }
+}
+
+async fn enfileirar_impl<M: ModeloEmail>(db_pool: &SqlitePool, user_id: &str, modelo: &M) -> AppResult<()> {
+    let destino: Option<(Option<String>, bool)> = sqlx::query_as("SELECT email, emails_ativos FROM users WHERE id = ?1")
+        .bind(user_id)
+        .fetch_optional(db_pool)
+        .await?;
+    let Some((Some(email), emails_ativos)) = destino else {
+        tracing::debug!("✉️ Email '{}' para {} ignorado (sem endereço).", M::TIPO, user_id);
+        return Ok(());
+    };
+    if !emails_ativos && M::TIPO != TIPO_SENHA {
+        tracing::debug!("✉️ Email '{}' para {} ignorado (avisos por email desativados).", M::TIPO, user_id);
+        return Ok(());
+    }
+    if !preferencias_service::aceita_aviso(db_pool, user_id, M::TIPO).await {
+        tracing::debug!("✉️ Email '{}' para {} ignorado (avisos deste tipo desligados nas preferências).", M::TIPO, user_id);
+        return Ok(());
+    }
+
+    let corpo = modelo.render().map_err(|e| {
+        tracing::error!("Falha ao gerar o email '{}': {}", M::TIPO, e);
+        AppError::InternalServerError
+    })?;
+    sqlx::query("INSERT INTO emails_saida (user_id, destinatario, tipo, assunto, corpo) VALUES (?1, ?2, ?3, ?4, ?5)")
+        .bind(user_id)
+        .bind(&email)
+        .bind(M::TIPO)
+        .bind(modelo.assunto())
+        .bind(corpo.trim())
+        .execute(db_pool)
+        .await?;
+    tracing::debug!("✉️ Email '{}' para {} na caixa de saída.", M::TIPO, user_id);
+    Ok(())
+}
+
+/// Endereço registado e preferência de avisos por email do utilizador.
+pub async fn preferencia(db_pool: &SqlitePool, user_id: &str) -> AppResult<(Option<String>, bool)> {
+    let preferencia = sqlx::query_as("SELECT email, emails_ativos FROM users WHERE id = ?1")
+        .bind(user_id)
+        .fetch_one(db_pool)
+        .await?;
+    Ok(preferencia)
+}
+
+/// Ativa ou desativa os avisos por email do utilizador.
+pub async fn definir_preferencia(db_pool: &SqlitePool, user_id: &str, ativos: bool) -> AppResult<()> {
+    sqlx::query("UPDATE users SET emails_ativos = ?1 WHERE id = ?2")
+        .bind(ativos)
+        .bind(user_id)
+        .execute(db_pool)
+        .await?;
+    tracing::info!("✉️ Avisos por email de {} {}.", user_id, if ativos { "ativados" } else { "desativados" });
+    Ok(())
+}
+
+// --- Envio ---
+
+/// Inicia a tarefa que envia os emails pendentes (e limpa a caixa de saída). Não faz nada se o email não estiver configurado.
+pub fn iniciar_envio(db_pool: SqlitePool) {
+    let Some(config) = config() else { return };
+    tokio::spawn(async move {
+        let mut ultima_limpeza: Option<std::time::Instant> = None;
+        loop {
+            if let Err(e) = enviar_pendentes(&db_pool, config).await {
+                tracing::error!("Erro no envio de emails: {:?}", e);
+            }
+            if ultima_limpeza.is_none_or(|t| t.elapsed() >= INTERVALO_LIMPEZA) {
+                if let Err(e) = limpar_registo(&db_pool).await {
+                    tracing::error!("Erro ao limpar a caixa de saída: {:?}", e);
+                }
+                ultima_limpeza = Some(std::time::Instant::now());
+            }
+            tokio::time::sleep(INTERVALO_ENVIO).await;
+        }
+    });
+}
+
+/// Email pendente na caixa de saída.
+#[derive(sqlx::FromRow)]
+struct EmailPendente {
+    id: i64,
+    destinatario: String,
+    tipo: String,
+    assunto: String,
+    corpo: String,
+    tentativas: i64,
+}
+
+async fn enviar_pendentes(db_pool: &SqlitePool, config: &EmailConfig) -> AppResult<()> {
+    let pendentes = sqlx::query_as::<_, EmailPendente>(
+        r#"
+        SELECT id, destinatario, tipo, assunto, corpo, tentativas
+        FROM emails_saida
+        WHERE estado = 'pendente' AND proxima_tentativa <= datetime('now')
+        ORDER BY id
+        LIMIT ?1
+        "#,
+    )
+    .bind(LOTE_ENVIO)
+    .fetch_all(db_pool)
+    .await?;
+
+    for email in pendentes {
+        let tentativas = email.tentativas + 1;
+        match enviar(config, &email).await {
+            Ok(()) => {
+                sqlx::query(
+                    "UPDATE emails_saida SET estado = 'enviado', tentativas = ?1, ultimo_erro = NULL, enviado_em = datetime('now') WHERE id = ?2",
+                )
+                .bind(tentativas)
+                .bind(email.id)
+                .execute(db_pool)
+                .await?;
+                tracing::debug!("✉️ Email {} ('{}') enviado para {}.", email.id, email.tipo, email.destinatario);
+            }
+            Err(erro) => {
+                let erro: String = erro.chars().take(MAX_ERRO).collect();
+                let estado = if tentativas >= MAX_TENTATIVAS { "falhado" } else { "pendente" };
+                let espera = ESPERA_BASE_SEGUNDOS << (tentativas - 1).min(10);
+                sqlx::query(
+                    r#"
+                    UPDATE emails_saida
+                    SET estado = ?1, tentativas = ?2, ultimo_erro = ?3, proxima_tentativa = datetime('now', ?4)
+                    WHERE id = ?5
+                    "#,
+                )
+                .bind(estado)
+                .bind(tentativas)
+                .bind(&erro)
+                .bind(format!("+{} seconds", espera))
+                .bind(email.id)
+                .execute(db_pool)
+                .await?;
+                if estado == "falhado" {
+                    tracing::error!("❌ Email {} para {} falhou {} vezes; desistindo: {}", email.id, email.destinatario, tentativas, erro);
+                } else {
+                    tracing::warn!("Email {} para {} falhou (tentativa {}), nova tentativa em {}s: {}", email.id, email.destinatario, tentativas, espera, erro);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn enviar(config: &EmailConfig, email: &EmailPendente) -> Result<(), String> {
+    let destinatario = email
+        .destinatario
+        .parse::<Mailbox>()
+        .map_err(|e| format!("endereço inválido: {}", e))?;
+    let mensagem = Message::builder()
+        .from(config.remetente.clone())
+        .to(destinatario)
+        .subject(&email.assunto)
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.corpo.clone())
+        .map_err(|e| e.to_string())?;
+    config.transporte.send(mensagem).await.map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Apaga da caixa de saída os emails enviados ou falhados há mais de `DIAS_REGISTO` dias.
+async fn limpar_registo(db_pool: &SqlitePool) -> AppResult<()> {
+    sqlx::query("DELETE FROM emails_saida WHERE estado != 'pendente' AND criado_em < datetime('now', ?1)")
+        .bind(format!("-{} days", DIAS_REGISTO))
+        .execute(db_pool)
+        .await?;
+    Ok(())
+}
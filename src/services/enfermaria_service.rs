@@ -0,0 +1,462 @@
+// src/services/enfermaria_service.rs
+//! Consultas da enfermaria. A secção de saúde abre vagas (dia, hora local e duração) e cada utilizador
+//! marca uma consulta numa vaga livre, uma de cada vez, e pode cancelá-la até à hora. Na agenda do dia a
+//! secção regista a chegada (a consulta fica 'na_enfermaria' e o utilizador aparece assim na lista de
+//! presença), a falta ou o fim da consulta; se resultar uma dispensa, a baixa é registada com as
+//! restrições indicadas (ver `baixa_service`) e fica ligada à consulta.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::enfermaria::{Consulta, VagaConsulta},
+    services::{baixa_service, notificacao_service},
+    tempo,
+};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+pub const ESTADO_MARCADA: &str = "marcada";
+pub const ESTADO_NA_ENFERMARIA: &str = "na_enfermaria";
+pub const ESTADO_ATENDIDA: &str = "atendida";
+pub const ESTADO_FALTOU: &str = "faltou";
+pub const ESTADO_CANCELADA: &str = "cancelada";
+
+/// Estados de uma consulta (código, descrição).
+pub const ESTADOS: &[(&str, &str)] = &[
+    (ESTADO_MARCADA, "Marcada"),
+    (ESTADO_NA_ENFERMARIA, "Na enfermaria"),
+    (ESTADO_ATENDIDA, "Atendida"),
+    (ESTADO_FALTOU, "Faltou"),
+    (ESTADO_CANCELADA, "Cancelada"),
+];
+
+/// Dias à frente com vagas para marcar.
+pub const DIAS_MARCACAO: i64 = 14;
+/// Vagas abertas de uma vez, no máximo.
+pub const MAX_VAGAS_DIA: i64 = 48;
+/// Duração de uma vaga (minutos): mínimo e máximo.
+pub const DURACAO_MINIMA: i64 = 5;
+pub const DURACAO_MAXIMA: i64 = 120;
+/// Consultas no histórico do utilizador.
+pub const HISTORICO_RECENTE: i64 = 20;
+
+/// Descrição de um estado.
+pub fn descrever_estado(estado: &str) -> &str {
+    ESTADOS.iter().find(|(c, _)| *c == estado).map_or(estado, |(_, d)| *d)
+}
+
+/// Início de uma vaga (dia e hora locais).
+pub fn inicio(data: &str, hora: &str) -> Option<NaiveDateTime> {
+    let data = tempo::ler_data(data)?;
+    let hora = NaiveTime::parse_from_str(hora, "%H:%M").ok()?;
+    Some(data.and_time(hora))
+}
+
+/// Vagas de `de` a `ate` (inclusive), por dia e hora, com a consulta marcada em cada uma.
+const SQL_VAGAS: &str = r#"
+    SELECT v.id, v.data, v.hora, v.duracao, c.id AS consulta_id, c.user_id, u.name, u.turma, c.motivo, c.estado,
+           c.notas, c.baixa_id
+    FROM consulta_vagas v
+    LEFT JOIN consultas c ON c.vaga_id = v.id AND c.estado <> 'cancelada'
+    LEFT JOIN users u ON u.id = c.user_id
+    WHERE v.organizacao_id = ?1 AND v.data BETWEEN ?2 AND ?3
+    ORDER BY v.data, v.hora
+"#;
+
+/// Consultas da organização. `?2` = só esta consulta; `?3` = só as deste utilizador.
+const SQL_CONSULTAS: &str = r#"
+    SELECT c.id, v.data, v.hora, c.user_id, c.motivo, c.estado, c.marcada_em,
+           COALESCE(a.name, c.atendida_por) AS atendida_por, c.baixa_id
+    FROM consultas c
+    JOIN consulta_vagas v ON v.id = c.vaga_id
+    LEFT JOIN users a ON a.id = c.atendida_por
+    WHERE c.organizacao_id = ?1 AND (?2 IS NULL OR c.id = ?2) AND (?3 IS NULL OR c.user_id = ?3)
+    ORDER BY v.data DESC, v.hora DESC, c.id DESC
+    LIMIT ?4
+"#;
+
+/// Agenda de um dia: todas as vagas, livres ou com a consulta.
+pub async fn agenda(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<Vec<VagaConsulta>> {
+    let vagas = sqlx::query_as::<_, VagaConsulta>(SQL_VAGAS)
+        .bind(organizacao_id)
+        .bind(data.to_string())
+        .bind(data.to_string())
+        .fetch_all(db_pool)
+        .await?;
+    Ok(vagas)
+}
+
+/// Vagas livres depois de `agora` (no fuso da aplicação) e nos próximos `DIAS_MARCACAO` dias.
+pub async fn vagas_livres(db_pool: &SqlitePool, organizacao_id: i64, agora: NaiveDateTime) -> AppResult<Vec<VagaConsulta>> {
+    let hoje = agora.date();
+    let vagas = sqlx::query_as::<_, VagaConsulta>(SQL_VAGAS)
+        .bind(organizacao_id)
+        .bind(hoje.to_string())
+        .bind((hoje + Duration::days(DIAS_MARCACAO)).to_string())
+        .fetch_all(db_pool)
+        .await?;
+    Ok(vagas
+        .into_iter()
+        .filter(|v| v.livre() && inicio(&v.data, &v.hora).is_some_and(|i| i > agora))
+        .collect())
+}
+
+/// Consultas de um utilizador, as mais recentes primeiro.
+pub async fn consultas_do_user(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str, limite: i64) -> AppResult<Vec<Consulta>> {
+    let consultas = sqlx::query_as::<_, Consulta>(SQL_CONSULTAS)
+        .bind(organizacao_id)
+        .bind(None::<i64>)
+        .bind(user_id)
+        .bind(limite)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(consultas)
+}
+
+/// Uma consulta pelo id (da organização).
+async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Consulta> {
+    sqlx::query_as::<_, Consulta>(SQL_CONSULTAS)
+        .bind(organizacao_id)
+        .bind(id)
+        .bind(None::<String>)
+        .bind(1)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Consulta {} não encontrada.", id)))
+}
+
+/// Uma vaga pelo id (da organização), com a consulta marcada nela.
+async fn obter_vaga(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Option<VagaConsulta>> {
+    let vaga = sqlx::query_as::<_, VagaConsulta>(
+        r#"
+        SELECT v.id, v.data, v.hora, v.duracao, c.id AS consulta_id, c.user_id, u.name, u.turma, c.motivo, c.estado,
+               c.notas, c.baixa_id
+        FROM consulta_vagas v
+        LEFT JOIN consultas c ON c.vaga_id = v.id AND c.estado <> 'cancelada'
+        LEFT JOIN users u ON u.id = c.user_id
+        WHERE v.id = ?1 AND v.organizacao_id = ?2
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(vaga)
+}
+
+/// Abre as vagas de `data` das `de` às `ate`, de `duracao` em `duracao` minutos (a última acaba até às
+/// `ate`). As horas que já tinham vaga ficam como estão. Devolve as vagas criadas e as que já existiam.
+#[allow(clippy::too_many_arguments)]
+pub async fn abrir_vagas(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    data: NaiveDate,
+    de: NaiveTime,
+    ate: NaiveTime,
+    duracao: i64,
+    hoje: NaiveDate,
+    operador_id: &str,
+) -> AppResult<(u64, u64)> {
+    if data < hoje {
+        return Err(AppError::validation("data", "Não se abrem vagas em dias que já passaram."));
+    }
+    if !(DURACAO_MINIMA..=DURACAO_MAXIMA).contains(&duracao) {
+        return Err(AppError::validation("duracao", format!("De {} a {} minutos.", DURACAO_MINIMA, DURACAO_MAXIMA)));
+    }
+    let passo = Duration::minutes(duracao);
+    let mut horas = Vec::new();
+    let mut hora = de;
+    // `NaiveTime` dá a volta à meia-noite: a última vaga é a que ainda acaba até às `ate` no mesmo dia
+    while hora + passo <= ate && hora + passo > hora {
+        horas.push(hora.format("%H:%M").to_string());
+        hora += passo;
+    }
+    if horas.is_empty() {
+        return Err(AppError::validation("ate", "O período não chega para uma vaga."));
+    }
+    if horas.len() as i64 > MAX_VAGAS_DIA {
+        return Err(AppError::validation("ate", format!("No máximo {} vagas de cada vez.", MAX_VAGAS_DIA)));
+    }
+
+    let mut tx = db_pool.begin().await?;
+    let mut criadas = 0;
+    for hora in &horas {
+        criadas += sqlx::query(
+            "INSERT OR IGNORE INTO consulta_vagas (organizacao_id, data, hora, duracao, criada_por) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(organizacao_id)
+        .bind(data.to_string())
+        .bind(hora)
+        .bind(duracao)
+        .bind(operador_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    tx.commit().await?;
+    tracing::info!("🏥 {} vaga(s) da enfermaria abertas em {} por {}.", criadas, data, operador_id);
+    Ok((criadas, horas.len() as u64 - criadas))
+}
+
+/// Apaga uma vaga sem consulta (as canceladas nela vão com ela). Devolve a vaga.
+pub async fn remover_vaga(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<VagaConsulta> {
+    let vaga = obter_vaga(db_pool, organizacao_id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Vaga {} não encontrada.", id)))?;
+    if let Some(user_id) = &vaga.user_id {
+        return Err(AppError::Conflict(format!(
+            "A vaga das {} tem uma consulta de {}: cancele-a primeiro.",
+            vaga.hora, user_id
+        )));
+    }
+    // Uma consulta marcada entretanto impede a remoção
+    let apagada = sqlx::query(
+        "DELETE FROM consulta_vagas WHERE id = ?1 AND NOT EXISTS (SELECT 1 FROM consultas WHERE vaga_id = ?1 AND estado <> 'cancelada')",
+    )
+    .bind(id)
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    if apagada == 0 {
+        return Err(AppError::Conflict(format!("A vaga das {} acabou de ser marcada.", vaga.hora)));
+    }
+    Ok(vaga)
+}
+
+/// Marca uma consulta de `user_id` numa vaga livre depois de `agora`. Devolve a consulta.
+pub async fn marcar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    vaga_id: i64,
+    user_id: &str,
+    motivo: &str,
+    agora: NaiveDateTime,
+) -> AppResult<Consulta> {
+    let vaga = obter_vaga(db_pool, organizacao_id, vaga_id)
+        .await?
+        .ok_or_else(|| AppError::validation("vaga_id", "Vaga não encontrada."))?;
+    if inicio(&vaga.data, &vaga.hora).is_none_or(|i| i <= agora) {
+        return Err(AppError::validation("vaga_id", "Esta vaga já passou."));
+    }
+    if !vaga.livre() {
+        return Err(AppError::validation("vaga_id", "Esta vaga já foi marcada; escolha outra."));
+    }
+    let pendente = consultas_do_user(db_pool, organizacao_id, user_id, HISTORICO_RECENTE)
+        .await?
+        .into_iter()
+        .find(Consulta::pendente);
+    if let Some(c) = pendente {
+        return Err(AppError::validation(
+            "vaga_id",
+            format!("Já tem uma consulta marcada para {} às {}: cancele-a para marcar outra.", formatar_dia(&c.data), c.hora),
+        ));
+    }
+
+    // Os índices únicos decidem entre duas marcações ao mesmo tempo (na vaga ou do mesmo utilizador)
+    let resultado = sqlx::query("INSERT INTO consultas (organizacao_id, vaga_id, user_id, motivo) VALUES (?1, ?2, ?3, ?4)")
+        .bind(organizacao_id)
+        .bind(vaga_id)
+        .bind(user_id)
+        .bind(motivo.trim())
+        .execute(db_pool)
+        .await;
+    let id = match resultado {
+        Ok(r) => r.last_insert_rowid(),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(AppError::validation("vaga_id", "Esta vaga acabou de ser marcada; escolha outra."));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    tracing::info!("🏥 Consulta {} de {} marcada para {} às {}.", id, user_id, vaga.data, vaga.hora);
+    obter(db_pool, organizacao_id, id).await
+}
+
+/// Cancela uma consulta marcada. O próprio (`dono`) só até à hora da consulta; a secção de saúde
+/// (`dono` = None) a qualquer altura, e o utilizador é avisado. Devolve a consulta.
+pub async fn cancelar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    dono: Option<&str>,
+    agora: NaiveDateTime,
+    operador_id: &str,
+) -> AppResult<Consulta> {
+    let consulta = obter(db_pool, organizacao_id, id).await?;
+    if dono.is_some_and(|d| d != consulta.user_id) {
+        return Err(AppError::NotFound(format!("Consulta {} não encontrada.", id)));
+    }
+    if consulta.estado != ESTADO_MARCADA {
+        return Err(AppError::Conflict(format!("A consulta {} já não está marcada.", id)));
+    }
+    if dono.is_some() && inicio(&consulta.data, &consulta.hora).is_none_or(|i| i <= agora) {
+        return Err(AppError::Conflict("A hora da consulta já passou: fale com a enfermaria.".to_string()));
+    }
+    mudar_estado(db_pool, id, ESTADO_MARCADA, ESTADO_CANCELADA, operador_id).await?;
+    tracing::info!("🏥 Consulta {} de {} cancelada por {}.", id, consulta.user_id, operador_id);
+    if dono.is_none() && consulta.user_id != operador_id {
+        notificacao_service::notificar(
+            db_pool,
+            &consulta.user_id,
+            notificacao_service::TIPO_ENFERMARIA,
+            "Consulta cancelada",
+            &format!("A enfermaria cancelou a sua consulta de {} às {}.", formatar_dia(&consulta.data), consulta.hora),
+        )
+        .await;
+    }
+    Ok(consulta)
+}
+
+/// Passa a consulta de `de` para `para` (só se ainda estiver em `de`; duas ações ao mesmo tempo: só a
+/// primeira conta).
+async fn mudar_estado(db_pool: &SqlitePool, id: i64, de: &str, para: &str, operador_id: &str) -> AppResult<()> {
+    let chegada = para == ESTADO_NA_ENFERMARIA;
+    let alterada = sqlx::query(
+        r#"
+        UPDATE consultas
+        SET estado = ?3,
+            chegada_em = CASE WHEN ?5 THEN datetime('now') ELSE chegada_em END,
+            atendida_em = CASE WHEN ?5 THEN atendida_em ELSE datetime('now') END,
+            atendida_por = ?4
+        WHERE id = ?1 AND estado = ?2
+        "#,
+    )
+    .bind(id)
+    .bind(de)
+    .bind(para)
+    .bind(operador_id)
+    .bind(chegada)
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    if alterada == 0 {
+        return Err(AppError::Conflict(format!("A consulta {} mudou entretanto; atualize a agenda.", id)));
+    }
+    Ok(())
+}
+
+/// Regista a chegada do utilizador à enfermaria (fica assinalado na lista de presença).
+pub async fn registar_chegada(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<Consulta> {
+    let consulta = obter(db_pool, organizacao_id, id).await?;
+    if consulta.estado != ESTADO_MARCADA {
+        return Err(AppError::Conflict(format!("A consulta {} já não está marcada.", id)));
+    }
+    mudar_estado(db_pool, id, ESTADO_MARCADA, ESTADO_NA_ENFERMARIA, operador_id).await?;
+    Ok(consulta)
+}
+
+/// Regista a falta a uma consulta marcada.
+pub async fn registar_falta(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<Consulta> {
+    let consulta = obter(db_pool, organizacao_id, id).await?;
+    if consulta.estado != ESTADO_MARCADA {
+        return Err(AppError::Conflict(format!("A consulta {} já não está marcada.", id)));
+    }
+    mudar_estado(db_pool, id, ESTADO_MARCADA, ESTADO_FALTOU, operador_id).await?;
+    Ok(consulta)
+}
+
+/// Dispensa que resulta de uma consulta: até que dia (inclusive) e as restrições.
+pub struct Dispensa<'a> {
+    pub ate: NaiveDate,
+    pub restricoes: &'a [String],
+}
+
+/// Termina uma consulta (marcada ou a decorrer) com as notas da secção de saúde. Com `dispensa`, regista
+/// a baixa de `hoje` até ao dia indicado e liga-a à consulta. Devolve a consulta e o id da baixa.
+pub async fn concluir(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    notas: &str,
+    dispensa: Option<Dispensa<'_>>,
+    hoje: NaiveDate,
+    operador_id: &str,
+) -> AppResult<(Consulta, Option<i64>)> {
+    let consulta = obter(db_pool, organizacao_id, id).await?;
+    if !consulta.pendente() {
+        return Err(AppError::Conflict(format!(
+            "A consulta {} já não está por decidir ({}).",
+            id,
+            descrever_estado(&consulta.estado).to_lowercase()
+        )));
+    }
+    let baixa_id = match &dispensa {
+        Some(dispensa) => {
+            if dispensa.ate < hoje {
+                return Err(AppError::validation("dispensa_ate", "A dispensa não pode terminar antes de hoje."));
+            }
+            let observacoes = format!("Consulta de enfermaria {} ({} às {}). {}", id, formatar_dia(&consulta.data), consulta.hora, notas.trim());
+            let baixa_id = baixa_service::registar(
+                db_pool,
+                organizacao_id,
+                &consulta.user_id,
+                hoje,
+                dispensa.ate,
+                dispensa.restricoes,
+                observacoes.trim(),
+                operador_id,
+            )
+            .await?;
+            Some(baixa_id)
+        }
+        None => None,
+    };
+
+    let alterada = sqlx::query(
+        r#"
+        UPDATE consultas
+        SET estado = 'atendida', atendida_em = datetime('now'), atendida_por = ?2, notas = ?3, baixa_id = ?4
+        WHERE id = ?1 AND estado IN ('marcada', 'na_enfermaria')
+        "#,
+    )
+    .bind(id)
+    .bind(operador_id)
+    .bind(notas.trim())
+    .bind(baixa_id)
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    if alterada == 0 {
+        // Terminada entretanto por outra pessoa: a baixa acabada de registar não fica sem consulta
+        if let Some(baixa_id) = baixa_id {
+            baixa_service::remover(db_pool, organizacao_id, baixa_id).await?;
+        }
+        return Err(AppError::Conflict(format!("A consulta {} mudou entretanto; atualize a agenda.", id)));
+    }
+    tracing::info!("🏥 Consulta {} de {} atendida por {} (baixa: {:?}).", id, consulta.user_id, operador_id, baixa_id);
+    if let Some(dispensa) = &dispensa {
+        notificacao_service::notificar(
+            db_pool,
+            &consulta.user_id,
+            notificacao_service::TIPO_ENFERMARIA,
+            "Dispensa registada",
+            &format!("Da consulta de {}: dispensado até {}.", formatar_dia(&consulta.data), tempo::data_curta(dispensa.ate)),
+        )
+        .await;
+    }
+    Ok((consulta, baixa_id))
+}
+
+/// Quem está agora na enfermaria, com a hora (local) da chegada (lista de presença).
+pub async fn na_enfermaria(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<HashMap<String, String>> {
+    let linhas: Vec<(String, Option<String>)> =
+        sqlx::query_as("SELECT user_id, chegada_em FROM consultas WHERE organizacao_id = ?1 AND estado = 'na_enfermaria'")
+            .bind(organizacao_id)
+            .fetch_all(db_pool)
+            .await?;
+    Ok(linhas
+        .into_iter()
+        .map(|(user_id, chegada)| {
+            let hora = chegada
+                .as_deref()
+                .and_then(tempo::ler_utc)
+                .map(|c| tempo::local(&c).format("%H:%M").to_string())
+                .unwrap_or_default();
+            (user_id, hora)
+        })
+        .collect())
+}
+
+/// Dia de uma vaga para as mensagens (ex: "16/10").
+pub fn formatar_dia(data: &str) -> String {
+    tempo::ler_data(data).map_or_else(|| data.to_string(), tempo::data_curta)
+}
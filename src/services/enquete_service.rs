@@ -0,0 +1,242 @@
+// src/services/enquete_service.rs
+//! Enquetes internas: uma pergunta com opções de resposta única ou múltipla, dirigida a roles e anos
+//! (vazio = todos, como as pastas dos documentos) e aberta até ao fim do dia do prazo, ou até ser
+//! encerrada. Cada utilizador vota uma vez, no painel. O apuramento é feito aqui, nos contadores das
+//! opções; nas enquetes anónimas só fica registado quem participou, nunca o que escolheu.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::enquete::{DadosEnquete, Enquete, EnquetePorVotar, OpcaoEnquete, VotoEnquete},
+    services::documento_service::MAX_ANO,
+};
+use sqlx::SqlitePool;
+
+/// Opções de resposta de uma enquete (mínimo e máximo).
+pub const MIN_OPCOES: usize = 2;
+pub const MAX_OPCOES: usize = 10;
+
+/// Enquetes da organização, as mais recentes primeiro. `?2` = só esta enquete.
+const SQL_ENQUETES: &str = r#"
+    SELECT e.id, e.pergunta, e.descricao, e.multipla, e.anonima, e.prazo, e.roles, e.anos,
+           COALESCE(u.name, e.criada_por) AS criada_por, e.criada_em, e.encerrada_em,
+           (SELECT COUNT(*) FROM enquete_participacoes p WHERE p.enquete_id = e.id) AS participantes
+    FROM enquetes e
+    LEFT JOIN users u ON u.id = e.criada_por
+    WHERE e.organizacao_id = ?1
+      AND (?2 IS NULL OR e.id = ?2)
+    ORDER BY e.criada_em DESC, e.id DESC
+"#;
+
+/// Lista separada por vírgulas, ordenada e sem repetidos.
+fn lista_texto<T: Ord + ToString + Clone>(valores: &[T]) -> String {
+    let mut valores = valores.to_vec();
+    valores.sort_unstable();
+    valores.dedup();
+    valores.iter().map(T::to_string).collect::<Vec<_>>().join(",")
+}
+
+/// Todas as enquetes da organização, as mais recentes primeiro.
+pub async fn listar(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<Enquete>> {
+    let enquetes = sqlx::query_as::<_, Enquete>(SQL_ENQUETES)
+        .bind(organizacao_id)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(enquetes)
+}
+
+/// Uma enquete pelo id (da organização).
+pub async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Enquete> {
+    sqlx::query_as::<_, Enquete>(SQL_ENQUETES)
+        .bind(organizacao_id)
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Enquete {} não encontrada.", id)))
+}
+
+/// Opções de uma enquete, pela ordem em que foram criadas, com os votos apurados.
+pub async fn opcoes(db_pool: &SqlitePool, enquete_id: i64) -> AppResult<Vec<OpcaoEnquete>> {
+    let opcoes = sqlx::query_as::<_, OpcaoEnquete>(
+        "SELECT id, texto, votos FROM enquete_opcoes WHERE enquete_id = ?1 ORDER BY ordem, id",
+    )
+    .bind(enquete_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(opcoes)
+}
+
+/// Cria uma enquete com as opções. Devolve o ID.
+pub async fn criar(db_pool: &SqlitePool, organizacao_id: i64, dados: &DadosEnquete, operador_id: &str) -> AppResult<i64> {
+    if !(MIN_OPCOES..=MAX_OPCOES).contains(&dados.opcoes.len()) {
+        return Err(AppError::validation("opcoes", format!("Indique entre {} e {} opções.", MIN_OPCOES, MAX_OPCOES)));
+    }
+    if let Some(ano) = dados.anos.iter().find(|a| !(1..=MAX_ANO).contains(*a)) {
+        return Err(AppError::validation("anos", format!("Ano inválido: {}.", ano)));
+    }
+
+    let mut tx = db_pool.begin().await?;
+    let id = sqlx::query(
+        r#"
+        INSERT INTO enquetes (organizacao_id, pergunta, descricao, multipla, anonima, prazo, roles, anos, criada_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(dados.pergunta.trim())
+    .bind(dados.descricao.trim())
+    .bind(dados.multipla)
+    .bind(dados.anonima)
+    .bind(&dados.prazo)
+    .bind(lista_texto(&dados.roles))
+    .bind(lista_texto(&dados.anos))
+    .bind(operador_id)
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+    for (ordem, texto) in dados.opcoes.iter().enumerate() {
+        sqlx::query("INSERT INTO enquete_opcoes (enquete_id, texto, ordem) VALUES (?1, ?2, ?3)")
+            .bind(id)
+            .bind(texto.trim())
+            .bind(ordem as i64)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    tracing::info!("🗳️ Enquete {} ({}) criada por {}.", id, dados.pergunta.trim(), operador_id);
+    Ok(id)
+}
+
+/// Encerra uma enquete antes do prazo (deixa de aceitar votos). Devolve a enquete.
+pub async fn encerrar(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<Enquete> {
+    let encerrada = sqlx::query(
+        "UPDATE enquetes SET encerrada_em = datetime('now'), encerrada_por = ?3 WHERE id = ?1 AND organizacao_id = ?2 AND encerrada_em IS NULL",
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    let enquete = obter(db_pool, organizacao_id, id).await?;
+    if encerrada == 0 {
+        return Err(AppError::Conflict(format!("A enquete {} já estava encerrada.", id)));
+    }
+    tracing::info!("🗳️ Enquete {} encerrada por {} ({} participantes).", id, operador_id, enquete.participantes);
+    Ok(enquete)
+}
+
+/// Enquetes abertas dirigidas ao utilizador (roles e ano) em que ainda não votou, com as opções.
+pub async fn por_votar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: &str,
+    roles: &[String],
+    ano: i64,
+    hoje: &str,
+) -> AppResult<Vec<EnquetePorVotar>> {
+    let votadas: Vec<i64> = sqlx::query_scalar("SELECT enquete_id FROM enquete_participacoes WHERE user_id = ?1")
+        .bind(user_id)
+        .fetch_all(db_pool)
+        .await?;
+    let mut por_votar = Vec::new();
+    for enquete in listar(db_pool, organizacao_id).await? {
+        if enquete.aberta(hoje) && enquete.visivel_para(roles, ano) && !votadas.contains(&enquete.id) {
+            let opcoes = opcoes(db_pool, enquete.id).await?;
+            por_votar.push(EnquetePorVotar { enquete, opcoes });
+        }
+    }
+    // As que fecham primeiro no topo
+    por_votar.sort_by(|a, b| a.enquete.prazo.cmp(&b.enquete.prazo));
+    Ok(por_votar)
+}
+
+/// Regista o voto de um utilizador (uma ou várias opções, conforme a enquete). Devolve a enquete.
+#[allow(clippy::too_many_arguments)]
+pub async fn votar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    user_id: &str,
+    roles: &[String],
+    ano: i64,
+    escolhidas: &[i64],
+    hoje: &str,
+) -> AppResult<Enquete> {
+    let enquete = obter(db_pool, organizacao_id, id).await?;
+    if !enquete.visivel_para(roles, ano) {
+        return Err(AppError::NotFound(format!("Enquete {} não encontrada.", id)));
+    }
+    if !enquete.aberta(hoje) {
+        return Err(AppError::Conflict(format!("A enquete \"{}\" já está encerrada.", enquete.pergunta)));
+    }
+    let mut escolhidas = escolhidas.to_vec();
+    escolhidas.sort_unstable();
+    escolhidas.dedup();
+    if escolhidas.is_empty() {
+        return Err(AppError::Conflict("Escolha pelo menos uma opção.".to_string()));
+    }
+    if !enquete.multipla && escolhidas.len() > 1 {
+        return Err(AppError::Conflict("Esta enquete só aceita uma opção.".to_string()));
+    }
+    let validas: Vec<i64> = opcoes(db_pool, id).await?.iter().map(|o| o.id).collect();
+    if escolhidas.iter().any(|o| !validas.contains(o)) {
+        return Err(AppError::Conflict("Opção inválida.".to_string()));
+    }
+
+    // A chave (enquete, utilizador) decide entre dois votos ao mesmo tempo: só o primeiro conta
+    let mut tx = db_pool.begin().await?;
+    let resultado = sqlx::query("INSERT INTO enquete_participacoes (enquete_id, user_id) VALUES (?1, ?2)")
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await;
+    match resultado {
+        Ok(_) => {}
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(AppError::Conflict(format!("Já votou na enquete \"{}\".", enquete.pergunta)));
+        }
+        Err(e) => return Err(e.into()),
+    }
+    for opcao_id in &escolhidas {
+        sqlx::query("UPDATE enquete_opcoes SET votos = votos + 1 WHERE id = ?1 AND enquete_id = ?2")
+            .bind(opcao_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        // Nas anónimas a escolha não fica ligada ao utilizador
+        if !enquete.anonima {
+            sqlx::query("INSERT INTO enquete_votos (enquete_id, opcao_id, user_id) VALUES (?1, ?2, ?3)")
+                .bind(id)
+                .bind(opcao_id)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+    tx.commit().await?;
+    tracing::info!("🗳️ Voto de {} na enquete {} ({} opção(ões)).", user_id, id, escolhidas.len());
+    Ok(enquete)
+}
+
+/// Votos nominais de uma enquete (um por opção escolhida), por utilizador. Vazio nas anónimas.
+pub async fn votos(db_pool: &SqlitePool, enquete: &Enquete) -> AppResult<Vec<VotoEnquete>> {
+    if enquete.anonima {
+        return Ok(Vec::new());
+    }
+    let votos = sqlx::query_as::<_, VotoEnquete>(
+        r#"
+        SELECT v.user_id, u.name, u.turma, o.texto AS opcao, p.votou_em
+        FROM enquete_votos v
+        JOIN users u ON u.id = v.user_id
+        JOIN enquete_opcoes o ON o.id = v.opcao_id
+        JOIN enquete_participacoes p ON p.enquete_id = v.enquete_id AND p.user_id = v.user_id
+        WHERE v.enquete_id = ?1
+        ORDER BY u.turma, u.name, o.ordem
+        "#,
+    )
+    .bind(enquete.id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(votos)
+}
@@ -1,29 +1,114 @@
 // src/services/escala_service.rs
-use crate::models::escala::{Posto, Candidato};
+// Escalas, postos e alocações são de uma organização: as funções públicas recebem a organização
+// do pedido (`OrganizacaoId`) e nunca veem ou alteram dias de outra.
+use crate::{
+    error::AppError,
+    models::escala::{AlocacaoDetalhe, Candidato, DiaEscala, Posto, TrocaDetalhe},
+    services::{baixa_service, contabilidade_service::{self, Conta}, email_service, evento_service, horario_service, notificacao_service, webhook_service},
+    templates::{EmailEscalaPublicada, EmailTrocaDecidida},
+    tempo,
+};
 use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap};
 use uuid::Uuid;
-use chrono::{NaiveDate, Datelike, Duration}; // Importante para calcular dias da semana
+use chrono::{NaiveDate, Datelike, Duration, Timelike}; // Importante para calcular dias da semana
 
+/// Erros das operações da escala: cada variante corresponde a uma resposta própria
+/// (ver a conversão em `AppError`), em vez de os handlers adivinharem pelo texto.
+#[derive(Debug, thiserror::Error)]
+pub enum EscalaError {
+    /// O dia já foi publicado: tem de ser reaberto com a errata antes de ser alterado.
+    #[error("O dia {0} já está PUBLICADO. Use a Errata para reabrir antes de regenerar.")]
+    Publicada(String),
+
+    /// Nenhum militar pode ocupar o posto nesse dia (restrições de ano, género, grupo, aulas, fadiga...).
+    #[error("Ninguém disponível no dia {data} para o posto '{posto}' (Ano exigido: {anos}). Verifique efetivo ou restrições.")]
+    SemCandidatos { data: String, posto: String, anos: String },
+
+    /// O substituto tem outro serviço a menos de `dias` dias do serviço trocado.
+    #[error("O substituto viola a regra de fadiga (tem outro serviço a menos de {dias} dia(s) de {data}).")]
+    Fadiga { data: String, dias: i64 },
+
+    #[error("{0}")]
+    NaoEncontrado(String),
+
+    /// Dados do pedido inválidos (associados a um campo do formulário/payload).
+    #[error("{mensagem}")]
+    Invalido { campo: &'static str, mensagem: String },
+
+    /// O pedido contraria o estado atual (troca já respondida, serviço de punição, ...).
+    #[error("{0}")]
+    Conflito(String),
+
+    #[error("Não autorizado")]
+    SemPermissao,
+
+    #[error("Erro na base de dados: {0}")]
+    Db(#[from] sqlx::Error),
+
+    /// Erro de outro serviço usado pela escala (ex: o livro de serviços).
+    #[error(transparent)]
+    Outro(#[from] AppError),
+}
+
+impl EscalaError {
+    fn invalido(campo: &'static str, mensagem: impl Into<String>) -> Self {
+        EscalaError::Invalido { campo, mensagem: mensagem.into() }
+    }
+}
+
+impl From<EscalaError> for AppError {
+    fn from(e: EscalaError) -> Self {
+        match e {
+            EscalaError::Publicada(_) | EscalaError::SemCandidatos { .. } | EscalaError::Fadiga { .. } | EscalaError::Conflito(_) => {
+                AppError::Conflict(e.to_string())
+            }
+            EscalaError::NaoEncontrado(msg) => AppError::NotFound(msg),
+            EscalaError::Invalido { campo, mensagem } => AppError::validation(campo, mensagem),
+            EscalaError::SemPermissao => AppError::Unauthorized,
+            EscalaError::Db(e) => AppError::SqlxError(e),
+            EscalaError::Outro(e) => e,
+        }
+    }
+}
+
+pub type EscalaResult<T> = Result<T, EscalaError>;
+
+#[derive(Clone, Copy)]
 pub enum TipoRotina { RN, RD }
 
 impl TipoRotina {
     pub fn as_str(&self) -> &'static str {
         match self { TipoRotina::RN => "RN", TipoRotina::RD => "RD" }
     }
+
+    /// A partir de `escalas.tipo_rotina` (tudo o que não é "RN" conta como RD).
+    fn de_str(tipo: &str) -> Self {
+        if tipo == "RN" { TipoRotina::RN } else { TipoRotina::RD }
+    }
 }
 
+// --- CONTADORES (users.servicos_rn / servicos_rd / saldo_punicoes) ---
+// Nunca alterados diretamente: cada serviço creditado ou debitado é lançado no livro de serviços
+// (ver contabilidade_service), que atualiza o contador na mesma transação.
+
 // --- FUNÇÃO PRINCIPAL: GERAR PERÍODO ---
+/// `fadiga_dias`: ver `config::EscalaConfig`.
 pub async fn gerar_escala_periodo(
     pool: &SqlitePool,
+    organizacao_id: i64,
     inicio_str: &str,
-    fim_str: &str
-) -> Result<String, String> {
+    fim_str: &str,
+    fadiga_dias: i64,
+) -> EscalaResult<String> {
     
     // Converter strings para Datas
-    let inicio = NaiveDate::parse_from_str(inicio_str, "%Y-%m-%d").map_err(|_| "Data início inválida")?;
-    let fim = NaiveDate::parse_from_str(fim_str, "%Y-%m-%d").map_err(|_| "Data fim inválida")?;
+    let inicio = NaiveDate::parse_from_str(inicio_str, "%Y-%m-%d")
+        .map_err(|_| EscalaError::invalido("data_inicio", "Data início inválida"))?;
+    let fim = NaiveDate::parse_from_str(fim_str, "%Y-%m-%d")
+        .map_err(|_| EscalaError::invalido("data_fim", "Data fim inválida"))?;
 
-    if fim < inicio { return Err("Data fim deve ser depois do início".into()); }
+    if fim < inicio { return Err(EscalaError::invalido("data_fim", "Data fim deve ser depois do início")); }
 
     let mut data_atual = inicio;
     let mut dias_gerados = 0;
@@ -42,102 +127,149 @@ pub async fn gerar_escala_periodo(
         // 2. Tentar gerar o dia
         // Nota: Precisamos passar a pool diretamente. A transação será por dia para não bloquear tudo se um falhar.
         // (Ou podíamos fazer uma transação gigante, mas por dia é mais seguro para debug)
-        match gerar_escala_diaria(pool, &data_str, tipo).await {
-            Ok(_) => dias_gerados += 1,
-            Err(e) => {
-                // Se der erro num dia (ex: ninguém disponível), paramos e avisamos? 
-                // Ou continuamos? Vamos parar para o Admin corrigir.
-                return Err(format!("Falha ao gerar dia {}: {}", data_str, e));
-            }
-        }
+        // Se der erro num dia (ex: ninguém disponível), paramos para o Admin corrigir
+        // (os erros já indicam o dia)
+        gerar_escala_diaria(pool, organizacao_id, &data_str, tipo, fadiga_dias).await?;
+        dias_gerados += 1;
 
         data_atual += Duration::days(1);
     }
 
+    avisar_estado_escala(organizacao_id, "Rascunho", inicio_str, fim_str);
     Ok(format!("Período gerado com sucesso! {} dias processados.", dias_gerados))
 }
 
+/// Avisa as páginas abertas (SSE) da organização de que o estado da escala mudou no período.
+fn avisar_estado_escala(organizacao_id: i64, status: &str, inicio: &str, fim: &str) {
+    evento_service::publicar_organizacao(
+        organizacao_id,
+        evento_service::EVENTO_ESCALA,
+        serde_json::json!({ "status": status, "inicio": inicio, "fim": fim }),
+    );
+}
+
+/// Avisa as duas partes de uma troca (SSE) de que o seu estado mudou.
+fn avisar_estado_troca(troca_id: &str, solicitante_id: &str, substituto_id: &str, status: &str) {
+    let dados = serde_json::json!({ "troca_id": troca_id, "status": status });
+    evento_service::publicar(Some(solicitante_id), evento_service::EVENTO_TROCA, dados.clone());
+    evento_service::publicar(Some(substituto_id), evento_service::EVENTO_TROCA, dados);
+}
+
 // --- GERAÇÃO DIÁRIA (Com limpeza de Rascunho) ---
 pub async fn gerar_escala_diaria(
     pool: &SqlitePool, 
+    organizacao_id: i64,
     data_alvo: &str, 
-    tipo: TipoRotina
-) -> Result<String, String> {
-    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    tipo: TipoRotina,
+    fadiga_dias: i64,
+) -> EscalaResult<String> {
+    let mut tx = pool.begin().await?;
 
     // 1. VERIFICAR STATUS E LIMPAR DADOS ANTERIORES (Regeneração)
     // Se já houver escala para este dia, verificamos se podemos mexer nela.
-    let status: Option<String> = sqlx::query_scalar("SELECT status FROM escalas WHERE data = ?")
+    let status: Option<String> = sqlx::query_scalar("SELECT status FROM escalas WHERE organizacao_id = ? AND data = ?")
+        .bind(organizacao_id)
         .bind(data_alvo)
         .fetch_optional(&mut *tx)
         .await
-        .map_err(|e| e.to_string())?;
+        ?;
 
     if let Some(s) = status {
         if s == "Publicada" {
-            return Err(format!("O dia {} já está PUBLICADO. Use a Errata para reabrir antes de regenerar.", data_alvo));
+            return Err(EscalaError::Publicada(data_alvo.to_string()));
         }
         
         // Se for Rascunho, limpamos tudo para gerar de novo (Reset Limpo)
         // a) Devolver pontos aos usuários (desfazer contabilidade)
-        let alocados = sqlx::query!(
-            r#"SELECT user_id, is_punicao, e.tipo_rotina 
+        let alocados = sqlx::query_as::<_, (String, String, Option<bool>, String)>(
+            r#"SELECT a.id, a.user_id, a.is_punicao, e.tipo_rotina 
                FROM alocacoes a 
-               JOIN escalas e ON a.data = e.data 
-               WHERE a.data = ?"#, 
-            data_alvo
-        ).fetch_all(&mut *tx).await.map_err(|e| e.to_string())?;
-
-        for row in alocados {
-            if row.is_punicao.unwrap_or(false) { // Era punição? Devolve a dívida (+1 no saldo)
-                 sqlx::query("UPDATE users SET saldo_punicoes = saldo_punicoes + 1 WHERE id = ?")
-                    .bind(row.user_id).execute(&mut *tx).await.ok();
-            } else { // Era serviço normal? Remove o ponto da contagem (-1 no serviço)
-                 let col = if row.tipo_rotina == "RN" { "servicos_rn" } else { "servicos_rd" };
-                 let sql = format!("UPDATE users SET {} = {} - 1 WHERE id = ?", col, col);
-                 sqlx::query(&sql).bind(row.user_id).execute(&mut *tx).await.ok();
-            }
+               JOIN escalas e ON e.organizacao_id = a.organizacao_id AND e.data = a.data 
+               WHERE a.organizacao_id = ? AND a.data = ?"#,
+        )
+        .bind(organizacao_id)
+        .bind(data_alvo)
+        .fetch_all(&mut *tx).await?;
+
+        for (alocacao_id, user_id, is_punicao, tipo_rotina) in alocados {
+            // Era punição? Devolve a dívida (+1 no saldo). Era serviço normal? Remove o ponto da contagem (-1 no serviço)
+            let (conta, delta) = if is_punicao.unwrap_or(false) {
+                (Conta::Punicao, 1)
+            } else {
+                (TipoRotina::de_str(&tipo_rotina).into(), -1)
+            };
+            contabilidade_service::lancar(
+                &mut tx, &user_id, conta, delta, contabilidade_service::MOTIVO_REGENERACAO, Some(data_alvo), Some(&alocacao_id),
+            ).await?;
         }
         
         // b) Apagar as alocações antigas deste dia
-        sqlx::query("DELETE FROM alocacoes WHERE data = ?")
+        sqlx::query("DELETE FROM alocacoes WHERE organizacao_id = ? AND data = ?")
+            .bind(organizacao_id)
             .bind(data_alvo)
-            .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+            .execute(&mut *tx).await?;
     }
 
     // 2. CRIAR/ATUALIZAR CABEÇALHO (Sempre Rascunho ao gerar)
-    sqlx::query("INSERT OR REPLACE INTO escalas (data, tipo_rotina, status) VALUES (?, ?, 'Rascunho')")
+    sqlx::query("INSERT OR REPLACE INTO escalas (organizacao_id, data, tipo_rotina, status) VALUES (?, ?, ?, 'Rascunho')")
+        .bind(organizacao_id)
         .bind(data_alvo)
         .bind(tipo.as_str())
-        .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+        .execute(&mut *tx).await?;
 
-    // 3. ALGORITMO DE ALOCAÇÃO
-    let postos = sqlx::query_as::<_, Posto>("SELECT * FROM postos")
-        .fetch_all(&mut *tx).await.map_err(|e| e.to_string())?;
+    // 3. ALGORITMO DE ALOCAÇÃO (só postos e efetivo da organização)
+    let postos = sqlx::query_as::<_, Posto>("SELECT * FROM postos WHERE organizacao_id = ?")
+        .bind(organizacao_id)
+        .fetch_all(&mut *tx).await?;
+    // Dia da semana do horário das aulas (postos diurnos)
+    let dia_semana = NaiveDate::parse_from_str(data_alvo, "%Y-%m-%d")
+        .map(horario_service::dia_semana)
+        .map_err(|_| EscalaError::invalido("data", "Data inválida"))?;
     
     for posto in postos {
-        let coluna_servico = match tipo { TipoRotina::RN => "servicos_rn", TipoRotina::RD => "servicos_rd" };
-        
         // QUERY: Trazemos 'u.ano' para validar a hierarquia numérica
-        let query = format!(
+        // (ordenados por dívida, depois pelo contador de serviços do tipo de rotina do dia e, no empate,
+        // pela antiguidade: o mais moderno primeiro, e os sem posição antes de todos)
+        let candidatos = sqlx::query_as::<_, Candidato>(
             r#"
             SELECT u.id, u.name, u.genero, u.turma, u.ano, u.servicos_rn, u.servicos_rd, u.saldo_punicoes 
             FROM users u
-            WHERE (u.genero = ? OR ? = 'Misto')
+            WHERE u.ativo = 1 AND u.organizacao_id = ?
+            AND (u.genero = ? OR ? = 'Misto')
             AND NOT EXISTS (
                 SELECT 1 FROM indisponibilidades i 
                 WHERE i.user_id = u.id AND ? BETWEEN i.data_inicio AND i.data_fim
             )
-            ORDER BY u.saldo_punicoes DESC, u.{} ASC
-            "#, 
-            coluna_servico
-        );
-
-        let candidatos = sqlx::query_as::<_, Candidato>(&query)
+            AND NOT EXISTS (
+                SELECT 1 FROM baixas b
+                WHERE b.user_id = u.id AND ? BETWEEN b.data_inicio AND b.data_fim
+            )
+            AND (? IS NULL OR EXISTS (
+                SELECT 1 FROM grupo_membros gm WHERE gm.grupo_id = ? AND gm.user_id = u.id
+            ))
+            AND (? = 0 OR NOT EXISTS (
+                SELECT 1 FROM aulas au
+                WHERE au.organizacao_id = u.organizacao_id AND au.turma = u.turma
+                AND au.dia_semana = ? AND au.obrigatoria = 1
+            ))
+            ORDER BY u.saldo_punicoes DESC,
+                     CASE WHEN ? = 'RN' THEN u.servicos_rn ELSE u.servicos_rd END ASC,
+                     u.antiguidade IS NOT NULL, u.antiguidade DESC
+            "#,
+        )
+            .bind(organizacao_id)
             .bind(&posto.genero_restricao)
             .bind(&posto.genero_restricao)
             .bind(data_alvo)
-            .fetch_all(&mut *tx).await.map_err(|e| e.to_string())?;
+            .bind(data_alvo) // Baixas médicas em vigor nesse dia (ver baixa_service)
+            // REGRA 0: Posto restrito a um grupo (pelotão/companhia/equipa)
+            .bind(posto.grupo_id)
+            .bind(posto.grupo_id)
+            // REGRA 0b: Posto diurno não vai para quem tem aulas obrigatórias nesse dia (horário da turma)
+            .bind(posto.diurno)
+            .bind(dia_semana)
+            .bind(tipo.as_str())
+            .fetch_all(&mut *tx).await?;
 
         let mut escolhido: Option<Candidato> = None;
 
@@ -146,17 +278,19 @@ pub async fn gerar_escala_diaria(
             // O posto tem "1,2" -> O user tem ano 1 -> OK
             if !posto.aceita_ano(user.ano) { continue; }
 
-            // REGRA 2: FADIGA (24h)
+            // REGRA 2: FADIGA (nenhum outro serviço a menos de `fadiga_dias` dias)
             let conflito: bool = sqlx::query_scalar(
                 r#"SELECT EXISTS(
                     SELECT 1 FROM alocacoes 
                     WHERE user_id = ? 
-                    AND date(data) BETWEEN date(?, '-1 day') AND date(?, '+1 day')
+                    AND date(data) BETWEEN date(?, ?) AND date(?, ?)
                 )"#
             )
             .bind(&user.id)
             .bind(data_alvo)
+            .bind(format!("-{} days", fadiga_dias))
             .bind(data_alvo)
+            .bind(format!("+{} days", fadiga_dias))
             .fetch_one(&mut *tx).await.unwrap_or(false);
 
             if !conflito { 
@@ -170,76 +304,244 @@ pub async fn gerar_escala_diaria(
             let uuid = Uuid::new_v4().to_string();
             
             // Gravar Alocação
-            sqlx::query("INSERT INTO alocacoes (id, user_id, posto_id, data, is_punicao) VALUES (?, ?, ?, ?, ?)")
-                .bind(uuid)
+            sqlx::query("INSERT INTO alocacoes (id, organizacao_id, user_id, posto_id, data, is_punicao) VALUES (?, ?, ?, ?, ?, ?)")
+                .bind(&uuid)
+                .bind(organizacao_id)
                 .bind(&user.id)
                 .bind(posto.id)
                 .bind(data_alvo)
                 .bind(is_punicao)
-                .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+                .execute(&mut *tx).await?;
             
             // Atualizar Contadores
-            if is_punicao {
-                sqlx::query("UPDATE users SET saldo_punicoes = saldo_punicoes - 1 WHERE id = ?")
-                    .bind(&user.id).execute(&mut *tx).await.ok();
-            } else {
-                let sql_up = format!("UPDATE users SET {} = {} + 1 WHERE id = ?", coluna_servico, coluna_servico);
-                sqlx::query(&sql_up).bind(&user.id).execute(&mut *tx).await.ok();
-            }
+            let (conta, delta) = if is_punicao { (Conta::Punicao, -1) } else { (tipo.into(), 1) };
+            contabilidade_service::lancar(
+                &mut tx, &user.id, conta, delta, contabilidade_service::MOTIVO_ALOCACAO, Some(data_alvo), Some(&uuid),
+            ).await?;
         } else {
              // Se ninguém servir, abortamos para o admin saber que falta gente
-             return Err(format!("ERRO CRÍTICO: Ninguém disponível para o posto '{}' (Ano exigido: {}). Verifique efetivo ou restrições.", posto.nome, posto.turmas_permitidas));
+             return Err(EscalaError::SemCandidatos {
+                 data: data_alvo.to_string(),
+                 posto: posto.nome,
+                 anos: posto.turmas_permitidas,
+             });
         }
     }
 
-    tx.commit().await.map_err(|e| e.to_string())?;
+    tx.commit().await?;
     Ok(format!("Escala para {} gerada com sucesso.", data_alvo))
 }
 
 // --- PUBLICAR PERÍODO ---
-pub async fn publicar_escala(pool: &SqlitePool, inicio: &str, fim: &str) -> Result<String, String> {
+pub async fn publicar_escala(pool: &SqlitePool, organizacao_id: i64, inicio: &str, fim: &str) -> EscalaResult<String> {
     // Muda tudo o que é Rascunho para Publicada nesse intervalo
-    let res = sqlx::query(
-        "UPDATE escalas SET status = 'Publicada' WHERE data BETWEEN ? AND ? AND status = 'Rascunho'"
+    let publicados: Vec<String> = sqlx::query_scalar(
+        "UPDATE escalas SET status = 'Publicada', publicada_em = datetime('now') WHERE organizacao_id = ? AND data BETWEEN ? AND ? AND status = 'Rascunho' RETURNING data"
     )
+    .bind(organizacao_id)
     .bind(inicio)
     .bind(fim)
-    .execute(pool).await.map_err(|e| e.to_string())?;
+    .fetch_all(pool).await?;
+
+    if publicados.is_empty() {
+        return Err(EscalaError::NaoEncontrado("Nenhuma escala 'Rascunho' encontrada neste período para publicar.".into()));
+    }
+    webhook_service::emitir(
+        pool,
+        webhook_service::EVENTO_ESCALA_PUBLICADA,
+        serde_json::json!({ "organizacao_id": organizacao_id, "inicio": inicio, "fim": fim, "dias": publicados.len() }),
+    )
+    .await;
+    avisar_estado_escala(organizacao_id, "Publicada", inicio, fim);
+    avisar_publicacao(pool, organizacao_id, inicio, fim, &publicados).await;
+    Ok(format!("{} dias de escala foram tornados OFICIAIS (Publicados).", publicados.len()))
+}
+
+/// Avisa cada escalado dos serviços que tem nos dias acabados de publicar (uma notificação por pessoa).
+async fn avisar_publicacao(pool: &SqlitePool, organizacao_id: i64, inicio: &str, fim: &str, publicados: &[String]) {
+    let alocacoes = match alocacoes_periodo(pool, organizacao_id, inicio, fim, None, false).await {
+        Ok(alocacoes) => alocacoes,
+        Err(e) => {
+            tracing::error!("Falha ao obter as alocações publicadas ({} a {}): {:?}", inicio, fim, e);
+            return;
+        }
+    };
+    // user_id -> (nome, serviços)
+    let mut por_user: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+    for a in alocacoes.into_iter().filter(|a| publicados.contains(&a.data)) {
+        let servico = format!("{} ({})", data_curta(&a.data), a.posto);
+        por_user.entry(a.user_id).or_insert_with(|| (a.militar, Vec::new())).1.push(servico);
+    }
+    for (user_id, (nome, servicos)) in por_user {
+        let mensagem = format!("Está escalado(a) para: {}.", servicos.join(", "));
+        notificacao_service::notificar(pool, &user_id, notificacao_service::TIPO_ESCALA, "Escala publicada", &mensagem).await;
+        email_service::enfileirar(pool, &user_id, &EmailEscalaPublicada { nome, servicos }).await;
+    }
+}
+
+/// Alocação envolvida num pedido de troca (com o estado e o tipo de rotina do dia).
+#[derive(sqlx::FromRow)]
+struct AlocacaoTroca {
+    status: Option<String>,
+    tipo_rotina: String,
+    data: String,
+    user_id: String,
+    is_punicao: Option<bool>,
+}
+
+/// Troca a aprovar pelo escalante.
+#[derive(sqlx::FromRow)]
+struct TrocaAprovar {
+    tipo: Option<String>,
+    solicitante_id: String,
+    substituto_id: String,
+    alocacao_id: String,
+    alocacao_substituto_id: Option<String>,
+    tipo_rotina_origem: String,
+    data: String,
+}
+
+/// Partes e serviço de uma troca (avisos do andamento e da decisão).
+#[derive(sqlx::FromRow)]
+struct PartesTroca {
+    solicitante_id: String,
+    solicitante: String,
+    substituto_id: String,
+    substituto: String,
+    data: String,
+    posto: String,
+}
+
+/// Partes e serviço da troca, para os avisos (em caso de erro apenas loga: o aviso não é enviado).
+async fn partes_troca(pool: &SqlitePool, troca_id: &str) -> Option<PartesTroca> {
+    let dados = sqlx::query_as::<_, PartesTroca>(
+        r#"
+        SELECT t.solicitante_id, s.name AS solicitante, t.substituto_id, sub.name AS substituto, a.data, p.nome AS posto
+        FROM trocas t
+        JOIN alocacoes a ON a.id = t.alocacao_id
+        JOIN postos p ON p.id = a.posto_id
+        JOIN users s ON s.id = t.solicitante_id
+        JOIN users sub ON sub.id = t.substituto_id
+        WHERE t.id = ?1
+        "#,
+    )
+    .bind(troca_id)
+    .fetch_optional(pool)
+    .await;
+    dados.unwrap_or_else(|e| {
+        tracing::error!("Falha ao obter os dados da troca {} para os avisos: {:?}", troca_id, e);
+        None
+    })
+}
+
+/// Avisa quem pediu a troca de que o substituto a aceitou (falta a aprovação do escalante).
+async fn avisar_troca_aceite(pool: &SqlitePool, troca_id: &str) {
+    let Some(partes) = partes_troca(pool, troca_id).await else { return };
+    let mensagem = format!(
+        "{} aceitou a troca do serviço de {} ({}). Aguarda a aprovação do escalante.",
+        partes.substituto, data_curta(&partes.data), partes.posto
+    );
+    notificacao_service::notificar(pool, &partes.solicitante_id, notificacao_service::TIPO_TROCA, "Troca aceite", &mensagem).await;
+}
+
+/// Avisa da decisão sobre uma troca (notificação e email): se aprovada, as duas partes; se recusada,
+/// quem a pediu.
+async fn avisar_decisao_troca(pool: &SqlitePool, troca_id: &str, aprovada: bool) {
+    let Some(PartesTroca { solicitante_id, solicitante, substituto_id, substituto, data, posto }) =
+        partes_troca(pool, troca_id).await
+    else {
+        return;
+    };
+    let data = data_curta(&data);
+
+    let (titulo, mensagem) = if aprovada {
+        ("Troca aprovada", format!("A troca do serviço de {} ({}) com {} foi aprovada.", data, posto, substituto))
+    } else {
+        ("Troca recusada", format!("A troca do serviço de {} ({}) com {} foi recusada.", data, posto, substituto))
+    };
+    notificacao_service::notificar(pool, &solicitante_id, notificacao_service::TIPO_TROCA, titulo, &mensagem).await;
+    if aprovada {
+        let mensagem = format!("Fica com o serviço de {} ({}), trocado com {}.", data, posto, solicitante);
+        notificacao_service::notificar(pool, &substituto_id, notificacao_service::TIPO_TROCA, titulo, &mensagem).await;
+    }
 
-    if res.rows_affected() == 0 {
-        return Err("Nenhuma escala 'Rascunho' encontrada neste período para publicar.".into());
+    let para_solicitante = EmailTrocaDecidida {
+        nome: solicitante.clone(),
+        aprovada,
+        para_solicitante: true,
+        outro: substituto.clone(),
+        data: data.clone(),
+        posto: posto.clone(),
+    };
+    email_service::enfileirar(pool, &solicitante_id, &para_solicitante).await;
+    if aprovada {
+        let para_substituto = EmailTrocaDecidida {
+            nome: substituto,
+            aprovada,
+            para_solicitante: false,
+            outro: solicitante,
+            data,
+            posto,
+        };
+        email_service::enfileirar(pool, &substituto_id, &para_substituto).await;
     }
-    Ok(format!("{} dias de escala foram tornados OFICIAIS (Publicados).", res.rows_affected()))
 }
 
+/// 'YYYY-MM-DD' -> 'dd/mm' (para as mensagens).
+fn data_curta(data: &str) -> String {
+    NaiveDate::parse_from_str(data, "%Y-%m-%d")
+        .map(|d| d.format("%d/%m").to_string())
+        .unwrap_or_else(|_| data.to_string())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn solicitar_troca(
     pool: &SqlitePool, 
+    organizacao_id: i64,
     solicitante_id: &str, 
     alocacao_id: &str, 
     substituto_id: &str,
     alocacao_substituto_id: Option<String>,
-    motivo: &str
-) -> Result<String, String> {
-    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    motivo: &str,
+    fadiga_dias: i64,
+) -> EscalaResult<String> {
+    let mut tx = pool.begin().await?;
 
-    // 1. Buscar dados da Alocação Original
-    let origem = sqlx::query!(
+    // 1. Buscar dados da Alocação Original (da organização)
+    let origem = sqlx::query_as::<_, AlocacaoTroca>(
         r#"SELECT e.status, e.tipo_rotina, a.data, a.user_id, a.is_punicao 
-           FROM alocacoes a JOIN escalas e ON a.data = e.data WHERE a.id = ?"#,
-        alocacao_id
-    ).fetch_optional(&mut *tx).await.map_err(|e| e.to_string())?;
+           FROM alocacoes a JOIN escalas e ON e.organizacao_id = a.organizacao_id AND e.data = a.data
+           WHERE a.id = ? AND a.organizacao_id = ?"#,
+    )
+    .bind(alocacao_id)
+    .bind(organizacao_id)
+    .fetch_optional(&mut *tx).await?;
+
+    let origem = origem.ok_or_else(|| EscalaError::NaoEncontrado("Alocação original não encontrada".into()))?;
 
-    let origem = origem.ok_or("Alocação original não encontrada")?;
+    // O substituto tem de ser da mesma organização
+    let substituto_existe: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = ? AND organizacao_id = ?)")
+        .bind(substituto_id)
+        .bind(organizacao_id)
+        .fetch_one(&mut *tx).await?;
+    if !substituto_existe {
+        return Err(EscalaError::NaoEncontrado(format!("Utilizador '{}' não encontrado.", substituto_id)));
+    }
 
     // Regras Básicas
     if origem.status.unwrap_or_default() == "Publicada" {
-        return Err("Escala já publicada.".into());
+        return Err(EscalaError::Conflito("Escala já publicada.".into()));
     }
     if origem.user_id == substituto_id {
-        return Err("Você não pode trocar consigo mesmo (já é o titular desta vaga).".into());
+        return Err(EscalaError::invalido("substituto_id", "Você não pode trocar consigo mesmo (já é o titular desta vaga)."));
     }
     if origem.is_punicao.unwrap_or(false) {
-        return Err("Serviços de PUNIÇÃO não podem ser trocados.".into());
+        return Err(EscalaError::Conflito("Serviços de PUNIÇÃO não podem ser trocados.".into()));
+    }
+
+    // Quem está de baixa médica nesse dia não pode ficar com o serviço
+    if baixa_service::de_baixa(&mut tx, substituto_id, &origem.data).await? {
+        return Err(EscalaError::Conflito(format!("O substituto está de baixa médica em {}.", data_curta(&origem.data))));
     }
 
     // 2. Definir Tipo de Troca
@@ -248,23 +550,31 @@ pub async fn solicitar_troca(
 
     if let Some(id_reciproco) = alocacao_substituto_id {
         // --- LÓGICA DE PERMUTA ---
-        let destino = sqlx::query!(
-            r#"SELECT e.tipo_rotina, a.user_id, a.is_punicao 
-               FROM alocacoes a JOIN escalas e ON a.data = e.data WHERE a.id = ?"#,
-            id_reciproco
-        ).fetch_optional(&mut *tx).await.map_err(|e| e.to_string())?;
+        let destino = sqlx::query_as::<_, AlocacaoTroca>(
+            r#"SELECT e.status, e.tipo_rotina, a.data, a.user_id, a.is_punicao 
+               FROM alocacoes a JOIN escalas e ON e.organizacao_id = a.organizacao_id AND e.data = a.data
+               WHERE a.id = ? AND a.organizacao_id = ?"#,
+        )
+        .bind(&id_reciproco)
+        .bind(organizacao_id)
+        .fetch_optional(&mut *tx).await?;
 
-        let destino = destino.ok_or("Alocação do substituto não encontrada")?;
+        let destino = destino.ok_or_else(|| EscalaError::NaoEncontrado("Alocação do substituto não encontrada".into()))?;
 
         if destino.user_id != substituto_id {
-            return Err("A alocação indicada para troca não pertence ao substituto.".into());
+            return Err(EscalaError::invalido("alocacao_substituto_id", "A alocação indicada para troca não pertence ao substituto."));
         }
         if destino.is_punicao.unwrap_or(false) {
-            return Err("O substituto está cumprindo PUNIÇÃO e não pode permutar.".into());
+            return Err(EscalaError::Conflito("O substituto está cumprindo PUNIÇÃO e não pode permutar.".into()));
         }
         
+        // Na permuta, o solicitante fica com o serviço do substituto
+        if baixa_service::de_baixa(&mut tx, solicitante_id, &destino.data).await? {
+            return Err(EscalaError::Conflito(format!("Está de baixa médica em {}: não pode ficar com esse serviço.", data_curta(&destino.data))));
+        }
+
         if origem.tipo_rotina != destino.tipo_rotina {
-            return Err("Permuta só é permitida entre dias do mesmo tipo (RN x RN ou RD x RD). Para tipos diferentes, use Cobertura.".into());
+            return Err(EscalaError::invalido("alocacao_substituto_id", "Permuta só é permitida entre dias do mesmo tipo (RN x RN ou RD x RD). Para tipos diferentes, use Cobertura."));
         }
 
         tipo_troca = "Permuta";
@@ -275,17 +585,19 @@ pub async fn solicitar_troca(
         // CORREÇÃO AQUI: Adicionado ::<_, i64> para tipar o retorno do SELECT 1
         let conflito = sqlx::query_scalar::<_, i64>(
             r#"SELECT 1 FROM alocacoes 
-               WHERE user_id = ? AND date(data) BETWEEN date(?, '-1 day') AND date(?, '+1 day')"#
+               WHERE user_id = ? AND date(data) BETWEEN date(?, ?) AND date(?, ?)"#
         )
         .bind(substituto_id)
         .bind(&origem.data)
+        .bind(format!("-{} days", fadiga_dias))
         .bind(&origem.data)
+        .bind(format!("+{} days", fadiga_dias))
         .fetch_optional(&mut *tx)
         .await
-        .map_err(|e| e.to_string())?;
+        ?;
 
         if conflito.is_some() {
-            return Err("O substituto viola a regra de fadiga (24h) para cobrir este dia.".into());
+            return Err(EscalaError::Fadiga { data: origem.data.clone(), dias: fadiga_dias });
         }
     }
 
@@ -296,50 +608,65 @@ pub async fn solicitar_troca(
            (id, solicitante_id, substituto_id, alocacao_id, status, motivo, tipo, alocacao_substituto_id) 
            VALUES (?, ?, ?, ?, 'Pendente', ?, ?, ?)"#
     )
-    .bind(uuid)
+    .bind(&uuid)
     .bind(solicitante_id)
     .bind(substituto_id)
     .bind(alocacao_id)
     .bind(motivo)
     .bind(tipo_troca)
     .bind(id_troca_reciproca)
-    .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+    .execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    avisar_estado_troca(&uuid, solicitante_id, substituto_id, "Pendente");
 
-    tx.commit().await.map_err(|e| e.to_string())?;
+    let solicitante: String = sqlx::query_scalar("SELECT name FROM users WHERE id = ?")
+        .bind(solicitante_id)
+        .fetch_optional(pool).await?
+        .unwrap_or_else(|| solicitante_id.to_string());
+    let mensagem = format!(
+        "{} pede-lhe uma {} para o serviço de {}. Responda no seu painel.",
+        solicitante, tipo_troca.to_lowercase(), data_curta(&origem.data)
+    );
+    notificacao_service::notificar(pool, substituto_id, notificacao_service::TIPO_TROCA, "Pedido de troca", &mensagem).await;
     Ok(format!("Pedido de {} realizado com sucesso!", tipo_troca))
 }
 
 
-pub async fn aprovar_troca(pool: &SqlitePool, troca_id: &str) -> Result<String, String> {
-    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+pub async fn aprovar_troca(pool: &SqlitePool, organizacao_id: i64, troca_id: &str) -> EscalaResult<String> {
+    let mut tx = pool.begin().await?;
 
-    // Buscar dados da Troca
-    let troca = sqlx::query!(
-        r#"SELECT t.*, e.tipo_rotina as tipo_rotina_origem
+    // Buscar dados da Troca (de uma alocação da organização)
+    let troca = sqlx::query_as::<_, TrocaAprovar>(
+        r#"SELECT t.tipo, t.solicitante_id, t.substituto_id, t.alocacao_id, t.alocacao_substituto_id,
+                  e.tipo_rotina as tipo_rotina_origem, a.data
            FROM trocas t 
            JOIN alocacoes a ON t.alocacao_id = a.id 
-           JOIN escalas e ON a.data = e.data
-           WHERE t.id = ?"#,
-        troca_id
-    ).fetch_optional(&mut *tx).await.map_err(|e| e.to_string())?;
+           JOIN escalas e ON e.organizacao_id = a.organizacao_id AND e.data = a.data
+           WHERE t.id = ? AND a.organizacao_id = ?"#,
+    )
+    .bind(troca_id)
+    .bind(organizacao_id)
+    .fetch_optional(&mut *tx).await?;
 
-    let t = troca.ok_or("Troca não encontrada")?;
+    let t = troca.ok_or_else(|| EscalaError::NaoEncontrado("Troca não encontrada".into()))?;
 
     if t.tipo.as_deref() == Some("Permuta") {
         // --- EXECUÇÃO DE PERMUTA (Troca Simples, Sem Contadores) ---
         let id_origem = t.alocacao_id;
-        let id_destino = t.alocacao_substituto_id.ok_or("Erro: Permuta sem alocação recíproca definida")?;
+        let id_destino = t.alocacao_substituto_id
+            .ok_or_else(|| EscalaError::Conflito("Erro: Permuta sem alocação recíproca definida".into()))?;
 
         // Troca os IDs nas alocações
         // 1. Coloca Substituto na Origem
         sqlx::query("UPDATE alocacoes SET user_id = ? WHERE id = ?")
             .bind(&t.substituto_id).bind(&id_origem)
-            .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+            .execute(&mut *tx).await?;
         
         // 2. Coloca Solicitante no Destino
         sqlx::query("UPDATE alocacoes SET user_id = ? WHERE id = ?")
             .bind(&t.solicitante_id).bind(&id_destino)
-            .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+            .execute(&mut *tx).await?;
         
         // Não mexe em contadores (servicos_rn/rd) pois trocaram "elas por elas"
 
@@ -349,87 +676,103 @@ pub async fn aprovar_troca(pool: &SqlitePool, troca_id: &str) -> Result<String,
         // 1. Atualiza Alocação
         sqlx::query("UPDATE alocacoes SET user_id = ? WHERE id = ?")
             .bind(&t.substituto_id).bind(&t.alocacao_id)
-            .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+            .execute(&mut *tx).await?;
 
         // 2. Atualiza Contadores
         // Quem SAI (Solicitante) -> Diminui 1
         // Quem ENTRA (Substituto) -> Aumenta 1
-        let col = if t.tipo_rotina_origem == "RN" { "servicos_rn" } else { "servicos_rd" };
-        
-        let sql_dec = format!("UPDATE users SET {} = {} - 1 WHERE id = ?", col, col);
-        let sql_inc = format!("UPDATE users SET {} = {} + 1 WHERE id = ?", col, col);
-
-        sqlx::query(&sql_dec).bind(&t.solicitante_id).execute(&mut *tx).await.map_err(|e| e.to_string())?;
-        sqlx::query(&sql_inc).bind(&t.substituto_id).execute(&mut *tx).await.map_err(|e| e.to_string())?;
+        let conta = TipoRotina::de_str(&t.tipo_rotina_origem).into();
+        for (user_id, delta) in [(&t.solicitante_id, -1), (&t.substituto_id, 1)] {
+            contabilidade_service::lancar(
+                &mut tx, user_id, conta, delta, contabilidade_service::MOTIVO_TROCA, Some(&t.data), Some(troca_id),
+            ).await?;
+        }
     }
 
     // Finalizar
     sqlx::query("UPDATE trocas SET status = 'Aprovada', data_resposta = datetime('now') WHERE id = ?")
-        .bind(troca_id).execute(&mut *tx).await.map_err(|e| e.to_string())?;
+        .bind(troca_id).execute(&mut *tx).await?;
 
-    tx.commit().await.map_err(|e| e.to_string())?;
+    tx.commit().await?;
+    avisar_estado_troca(troca_id, &t.solicitante_id, &t.substituto_id, "Aprovada");
+    webhook_service::emitir(
+        pool,
+        webhook_service::EVENTO_TROCA_APROVADA,
+        serde_json::json!({
+            "troca_id": troca_id,
+            "tipo": t.tipo,
+            "solicitante_id": t.solicitante_id,
+            "substituto_id": t.substituto_id,
+        }),
+    )
+    .await;
+    avisar_decisao_troca(pool, troca_id, true).await;
     Ok("Troca aprovada e processada com sucesso.".into())
 }
 
 
 // Helper interno para não duplicar código na resposta
-async fn aprovar_troca_impl_completa(pool: &SqlitePool, troca_id: &str) -> Result<String, String> {
-    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+async fn aprovar_troca_impl_completa(pool: &SqlitePool, troca_id: &str) -> EscalaResult<String> {
+    let mut tx = pool.begin().await?;
     let dados = sqlx::query!(
         r#"SELECT t.solicitante_id, t.substituto_id, t.alocacao_id, a.data as "data!", e.tipo_rotina, a.is_punicao
            FROM trocas t JOIN alocacoes a ON t.alocacao_id = a.id JOIN escalas e ON a.data = e.data
            WHERE t.id = ? AND t.status = 'Pendente'"#,
         troca_id
-    ).fetch_optional(&mut *tx).await.map_err(|e| e.to_string())?;
+    ).fetch_optional(&mut *tx).await?;
     
-    let d = match dados { Some(v) => v, None => return Err("Troca inválida".into()) };
+    let d = match dados { Some(v) => v, None => return Err(EscalaError::NaoEncontrado("Troca inválida".into())) };
     
     // Fadiga check double-check (is_punicao é Option<bool>)
     let conflito: bool = sqlx::query_scalar(r#"SELECT EXISTS(SELECT 1 FROM alocacoes WHERE user_id = ? AND date(data) BETWEEN date(?, '-1 day') AND date(?, '+1 day'))"#)
         .bind(&d.substituto_id).bind(&d.data).bind(&d.data)
         .fetch_one(&mut *tx).await.unwrap_or(false);
-    if conflito { return Err("Substituto com fadiga".into()); }
+    if conflito { return Err(EscalaError::Fadiga { data: d.data.clone(), dias: 1 }); }
 
-    sqlx::query("UPDATE alocacoes SET user_id = ? WHERE id = ?").bind(&d.substituto_id).bind(&d.alocacao_id).execute(&mut *tx).await.ok();
+    sqlx::query("UPDATE alocacoes SET user_id = ? WHERE id = ?").bind(&d.substituto_id).bind(&d.alocacao_id).execute(&mut *tx).await?;
     
     if !d.is_punicao.unwrap_or(false) { // is_punicao é Option<bool>
-        let col = if d.tipo_rotina == "RN" { "servicos_rn" } else { "servicos_rd" };
-        let s_dec = format!("UPDATE users SET {} = {} - 1 WHERE id = ?", col, col);
-        let s_inc = format!("UPDATE users SET {} = {} + 1 WHERE id = ?", col, col);
-        sqlx::query(&s_dec).bind(&d.solicitante_id).execute(&mut *tx).await.ok();
-        sqlx::query(&s_inc).bind(&d.substituto_id).execute(&mut *tx).await.ok();
+        let conta = TipoRotina::de_str(&d.tipo_rotina).into();
+        for (user_id, delta) in [(&d.solicitante_id, -1), (&d.substituto_id, 1)] {
+            contabilidade_service::lancar(
+                &mut tx, user_id, conta, delta, contabilidade_service::MOTIVO_TROCA, Some(&d.data), Some(troca_id),
+            ).await?;
+        }
     }
-    sqlx::query("UPDATE trocas SET status = 'Aprovada', data_resposta = datetime('now') WHERE id = ?").bind(troca_id).execute(&mut *tx).await.ok();
-    tx.commit().await.map_err(|e| e.to_string())?;
+    sqlx::query("UPDATE trocas SET status = 'Aprovada', data_resposta = datetime('now') WHERE id = ?").bind(troca_id).execute(&mut *tx).await?;
+    tx.commit().await?;
     Ok("Troca Aprovada".into())
 }
 
-pub async fn errata_dia(pool: &SqlitePool, data: &str) -> Result<String, String> {
-    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+pub async fn errata_dia(pool: &SqlitePool, organizacao_id: i64, data: &str) -> EscalaResult<String> {
+    let mut tx = pool.begin().await?;
 
     // 1. Verificar o status atual
-    let status: Option<String> = sqlx::query_scalar("SELECT status FROM escalas WHERE data = ?")
+    let status: Option<String> = sqlx::query_scalar("SELECT status FROM escalas WHERE organizacao_id = ? AND data = ?")
+        .bind(organizacao_id)
         .bind(data)
         .fetch_optional(&mut *tx)
         .await
-        .map_err(|e| e.to_string())?;
+        ?;
 
     match status {
         Some(s) if s == "Publicada" => {
             // 2. Reverter status para 'Rascunho'
             // Isto permite que o admin volte a ver os botões de "Trocar" e "Gerar"
-            sqlx::query("UPDATE escalas SET status = 'Rascunho' WHERE data = ?")
+            sqlx::query("UPDATE escalas SET status = 'Rascunho', publicada_em = NULL WHERE organizacao_id = ? AND data = ?")
+                .bind(organizacao_id)
                 .bind(data)
                 .execute(&mut *tx)
                 .await
-                .map_err(|e| e.to_string())?;
+                ?;
             
-            tx.commit().await.map_err(|e| e.to_string())?;
+            tx.commit().await?;
+            avisar_estado_escala(organizacao_id, "Rascunho", data, data);
             
             Ok(format!("O dia {} foi reaberto em modo RASCUNHO. Pode agora fazer alterações manuais ou regenerar.", data))
         },
-        Some(_) => Err(format!("O dia {} ainda não está publicado. Não é necessário criar errata.", data)),
-        None => Err(format!("Não existe escala gerada para o dia {}.", data)),
+        Some(_) => Err(EscalaError::Conflito(format!("O dia {} ainda não está publicado. Não é necessário criar errata.", data))),
+        None => Err(EscalaError::NaoEncontrado(format!("Não existe escala gerada para o dia {}.", data))),
     }
 }
 
@@ -438,27 +781,27 @@ pub async fn responder_troca_usuario(
     troca_id: &str,
     user_id: &str, // ID de quem está a responder (segurança)
     acao: &str,    // "aceitar" ou "recusar"
-) -> Result<String, String> {
-    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+) -> EscalaResult<String> {
+    let mut tx = pool.begin().await?;
 
     // 1. Validar se o pedido existe e é para este utilizador
     let troca = sqlx::query!(
-        "SELECT substituto_id, status FROM trocas WHERE id = ?",
+        "SELECT solicitante_id, substituto_id, status FROM trocas WHERE id = ?",
         troca_id
     )
-    .fetch_optional(&mut *tx).await.map_err(|e| e.to_string())?;
+    .fetch_optional(&mut *tx).await?;
 
     let troca = match troca {
         Some(t) => t,
-        None => return Err("Pedido de troca não encontrado.".into()),
+        None => return Err(EscalaError::NaoEncontrado("Pedido de troca não encontrado.".into())),
     };
 
     if troca.substituto_id != user_id {
-        return Err("Não tem permissão para responder a este pedido.".into());
+        return Err(EscalaError::SemPermissao);
     }
 
     if troca.status.as_deref() != Some("Pendente") {
-        return Err("Este pedido já foi respondido ou processado.".into());
+        return Err(EscalaError::Conflito("Este pedido já foi respondido ou processado.".into()));
     }
 
     // 2. Processar Ação
@@ -466,17 +809,248 @@ pub async fn responder_troca_usuario(
         // Muda para um estado que o Escalante veja (ex: 'AguardandoEscalante')
         sqlx::query("UPDATE trocas SET status = 'AguardandoEscalante' WHERE id = ?")
             .bind(troca_id)
-            .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+            .execute(&mut *tx).await?;
         
-        tx.commit().await.map_err(|e| e.to_string())?;
+        tx.commit().await?;
+        avisar_estado_troca(troca_id, &troca.solicitante_id, &troca.substituto_id, "AguardandoEscalante");
+        avisar_troca_aceite(pool, troca_id).await;
         Ok("Confirmou a troca! Agora aguarde a aprovação final do Escalante.".into())
     } else {
         // Recusa e fecha o processo
         sqlx::query("UPDATE trocas SET status = 'Recusada', data_resposta = datetime('now') WHERE id = ?")
             .bind(troca_id)
-            .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+            .execute(&mut *tx).await?;
             
-        tx.commit().await.map_err(|e| e.to_string())?;
+        tx.commit().await?;
+        avisar_estado_troca(troca_id, &troca.solicitante_id, &troca.substituto_id, "Recusada");
+        webhook_service::emitir(
+            pool,
+            webhook_service::EVENTO_TROCA_RECUSADA,
+            serde_json::json!({
+                "troca_id": troca_id,
+                "solicitante_id": troca.solicitante_id,
+                "substituto_id": troca.substituto_id,
+            }),
+        )
+        .await;
+        avisar_decisao_troca(pool, troca_id, false).await;
         Ok("Pedido de troca recusado.".into())
     }
-}
\ No newline at end of file
+}
+
+// --- LEMBRETES DE SERVIÇO ---
+
+/// Hora local a partir da qual se lembram os serviços do dia seguinte.
+const HORA_LEMBRETE: u32 = 18;
+/// Intervalo entre verificações de lembretes por enviar.
+const INTERVALO_LEMBRETES: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+/// Dias que o registo de lembretes enviados é mantido.
+const DIAS_REGISTO_LEMBRETES: i64 = 30;
+
+/// Inicia a tarefa que envia, na véspera, o lembrete de cada serviço publicado.
+pub fn iniciar_lembretes(pool: SqlitePool) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = enviar_lembretes(&pool).await {
+                tracing::error!("Erro no envio de lembretes de serviço: {:?}", e);
+            }
+            tokio::time::sleep(INTERVALO_LEMBRETES).await;
+        }
+    });
+}
+
+/// Lembra (notificação) quem está de serviço amanhã, uma vez por alocação, a partir da `HORA_LEMBRETE`.
+pub async fn enviar_lembretes(pool: &SqlitePool) -> EscalaResult<usize> {
+    let agora = tempo::agora();
+    if agora.hour() < HORA_LEMBRETE {
+        return Ok(0);
+    }
+    let amanha = (agora.date_naive() + Duration::days(1)).format("%Y-%m-%d").to_string();
+
+    let por_lembrar: Vec<(String, String, String)> = sqlx::query_as(
+        r#"
+        SELECT a.id, a.user_id, p.nome
+        FROM alocacoes a
+        JOIN escalas e ON e.organizacao_id = a.organizacao_id AND e.data = a.data
+        JOIN postos p ON p.id = a.posto_id
+        LEFT JOIN lembretes_servico l ON l.alocacao_id = a.id AND l.user_id = a.user_id
+        WHERE a.data = ?1 AND e.status = 'Publicada' AND l.alocacao_id IS NULL
+        "#,
+    )
+    .bind(&amanha)
+    .fetch_all(pool)
+    .await?;
+
+    let mut enviados = 0;
+    for (alocacao_id, user_id, posto) in por_lembrar {
+        let registado = sqlx::query("INSERT OR IGNORE INTO lembretes_servico (alocacao_id, user_id) VALUES (?1, ?2)")
+            .bind(&alocacao_id)
+            .bind(&user_id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        if registado == 0 {
+            continue;
+        }
+        let mensagem = format!("Amanhã ({}) está de serviço: {}.", data_curta(&amanha), posto);
+        notificacao_service::notificar(pool, &user_id, notificacao_service::TIPO_LEMBRETE, "Serviço amanhã", &mensagem).await;
+        enviados += 1;
+    }
+
+    sqlx::query("DELETE FROM lembretes_servico WHERE enviado_em < datetime('now', ?1)")
+        .bind(format!("-{} days", DIAS_REGISTO_LEMBRETES))
+        .execute(pool)
+        .await?;
+    if enviados > 0 {
+        tracing::info!("⏰ {} lembrete(s) de serviço enviados para {}.", enviados, amanha);
+    }
+    Ok(enviados)
+}
+
+// --- CONSULTAS (API JSON /api/v1) ---
+
+/// Alocações da organização no período [inicio, fim], opcionalmente só de um utilizador.
+/// Sem `incluir_rascunho`, só entram os dias publicados.
+pub async fn alocacoes_periodo(
+    pool: &SqlitePool,
+    organizacao_id: i64,
+    inicio: &str,
+    fim: &str,
+    user_id: Option<&str>,
+    incluir_rascunho: bool,
+) -> EscalaResult<Vec<AlocacaoDetalhe>> {
+    let alocacoes = sqlx::query_as::<_, AlocacaoDetalhe>(
+        r#"
+        SELECT a.id, a.data, a.user_id, u.name AS militar, u.turma, a.posto_id, p.nome AS posto,
+               COALESCE(a.is_punicao, 0) AS is_punicao, a.tag
+        FROM alocacoes a
+        JOIN escalas e ON e.organizacao_id = a.organizacao_id AND e.data = a.data
+        JOIN users u ON u.id = a.user_id
+        JOIN postos p ON p.id = a.posto_id
+        WHERE a.organizacao_id = ?5
+          AND a.data BETWEEN ?1 AND ?2
+          AND (?3 IS NULL OR a.user_id = ?3)
+          AND (?4 OR COALESCE(e.status, 'Rascunho') = 'Publicada')
+        ORDER BY a.data ASC, p.peso DESC, p.nome ASC
+        "#,
+    )
+    .bind(inicio)
+    .bind(fim)
+    .bind(user_id)
+    .bind(incluir_rascunho)
+    .bind(organizacao_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(alocacoes)
+}
+
+/// Há escala gerada (rascunho ou publicada) para o dia na organização?
+pub async fn existe_dia(pool: &SqlitePool, organizacao_id: i64, data: &str) -> EscalaResult<bool> {
+    let existe = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM escalas WHERE organizacao_id = ?2 AND data = ?1)")
+        .bind(data)
+        .bind(organizacao_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(existe)
+}
+
+/// Dias de escala da organização no período [inicio, fim], com as respetivas alocações.
+/// Sem `incluir_rascunho`, só entram os dias publicados.
+pub async fn dias_periodo(
+    pool: &SqlitePool,
+    organizacao_id: i64,
+    inicio: &str,
+    fim: &str,
+    incluir_rascunho: bool,
+) -> EscalaResult<Vec<DiaEscala>> {
+    let dias = sqlx::query_as::<_, (String, String, String)>(
+        r#"
+        SELECT data, tipo_rotina, COALESCE(status, 'Rascunho')
+        FROM escalas
+        WHERE organizacao_id = ?4 AND data BETWEEN ?1 AND ?2 AND (?3 OR COALESCE(status, 'Rascunho') = 'Publicada')
+        ORDER BY data ASC
+        "#,
+    )
+    .bind(inicio)
+    .bind(fim)
+    .bind(incluir_rascunho)
+    .bind(organizacao_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut por_dia: HashMap<String, Vec<AlocacaoDetalhe>> = HashMap::new();
+    for alocacao in alocacoes_periodo(pool, organizacao_id, inicio, fim, None, incluir_rascunho).await? {
+        por_dia.entry(alocacao.data.clone()).or_default().push(alocacao);
+    }
+    Ok(dias
+        .into_iter()
+        .map(|(data, tipo_rotina, status)| {
+            let alocacoes = por_dia.remove(&data).unwrap_or_default();
+            DiaEscala { data, tipo_rotina, status, alocacoes }
+        })
+        .collect())
+}
+
+/// Últimos `limite` dias publicados da organização (os publicados mais recentemente primeiro), cada um com a
+/// data/hora da publicação ('YYYY-MM-DD HH:MM:SS', UTC). Para o feed Atom.
+pub async fn publicacoes_recentes(pool: &SqlitePool, organizacao_id: i64, limite: i64) -> EscalaResult<Vec<(String, DiaEscala)>> {
+    let publicados = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT data, publicada_em
+        FROM escalas
+        WHERE organizacao_id = ?2 AND status = 'Publicada' AND publicada_em IS NOT NULL
+        ORDER BY publicada_em DESC, data DESC
+        LIMIT ?1
+        "#,
+    )
+    .bind(limite)
+    .bind(organizacao_id)
+    .fetch_all(pool)
+    .await?;
+
+    let (Some(inicio), Some(fim)) = (
+        publicados.iter().map(|(data, _)| data).min(),
+        publicados.iter().map(|(data, _)| data).max(),
+    ) else {
+        return Ok(Vec::new());
+    };
+    let mut dias: HashMap<String, DiaEscala> = dias_periodo(pool, organizacao_id, inicio, fim, false)
+        .await?
+        .into_iter()
+        .map(|dia| (dia.data.clone(), dia))
+        .collect();
+    Ok(publicados
+        .into_iter()
+        .filter_map(|(data, publicada_em)| dias.remove(&data).map(|dia| (publicada_em, dia)))
+        .collect())
+}
+
+/// Pedidos de troca da organização (mais recentes primeiro), opcionalmente só os que envolvem um utilizador
+/// (como solicitante ou substituto) e/ou num estado.
+pub async fn listar_trocas(
+    pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: Option<&str>,
+    status: Option<&str>,
+) -> EscalaResult<Vec<TrocaDetalhe>> {
+    let trocas = sqlx::query_as::<_, TrocaDetalhe>(
+        r#"
+        SELECT t.id, COALESCE(t.status, 'Pendente') AS status, COALESCE(t.tipo, 'Cobertura') AS tipo, t.motivo,
+               t.solicitante_id, t.substituto_id, t.alocacao_id, t.alocacao_substituto_id,
+               a.data, p.nome AS posto, t.criado_em, t.data_resposta
+        FROM trocas t
+        JOIN alocacoes a ON a.id = t.alocacao_id
+        JOIN postos p ON p.id = a.posto_id
+        WHERE a.organizacao_id = ?3
+          AND (?1 IS NULL OR t.solicitante_id = ?1 OR t.substituto_id = ?1)
+          AND (?2 IS NULL OR t.status = ?2)
+        ORDER BY t.criado_em DESC
+        "#,
+    )
+    .bind(user_id)
+    .bind(status)
+    .bind(organizacao_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(trocas)
+}
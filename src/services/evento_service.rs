@@ -0,0 +1,56 @@
+// src/services/evento_service.rs
+//! Eventos em tempo real: os serviços publicam aqui as alterações (notificações, estado da escala,
+//! trocas) e cada ligação `GET /events` (Server-Sent Events) recebe as que são para o seu utilizador.
+//! Canal em memória (tokio broadcast): quem não estiver ligado perde os eventos, que servem apenas
+//! para atualizar as páginas abertas; a informação fica sempre na base de dados.
+
+use serde_json::Value;
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+
+// Tipos de evento (campo `event:` do SSE)
+pub const EVENTO_NOTIFICACAO: &str = "notificacao";
+pub const EVENTO_ESCALA: &str = "escala";
+pub const EVENTO_TROCA: &str = "troca";
+
+/// Eventos guardados para ligações mais lentas; quem ficar para trás perde os mais antigos.
+const CAPACIDADE_CANAL: usize = 256;
+
+/// Evento publicado pelos serviços.
+#[derive(Debug, Clone)]
+pub struct Evento {
+    pub user_id: Option<String>, // Destinatário (None = todos os utilizadores ligados)
+    pub organizacao_id: Option<i64>, // Só para os utilizadores desta organização (None = qualquer)
+    pub tipo: &'static str,
+    pub dados: Value,
+}
+
+impl Evento {
+    /// O evento é para este utilizador (a trabalhar nesta organização)?
+    pub fn para(&self, user_id: &str, organizacao_id: i64) -> bool {
+        self.user_id.as_deref().is_none_or(|destino| destino == user_id)
+            && self.organizacao_id.is_none_or(|destino| destino == organizacao_id)
+    }
+}
+
+static CANAL: OnceLock<broadcast::Sender<Evento>> = OnceLock::new();
+
+fn canal() -> &'static broadcast::Sender<Evento> {
+    CANAL.get_or_init(|| broadcast::channel(CAPACIDADE_CANAL).0)
+}
+
+/// Publica um evento para um utilizador (ou para todos, se `user_id` for None).
+/// Sem ninguém ligado o evento é simplesmente descartado.
+pub fn publicar(user_id: Option<&str>, tipo: &'static str, dados: Value) {
+    let _ = canal().send(Evento { user_id: user_id.map(str::to_string), organizacao_id: None, tipo, dados });
+}
+
+/// Publica um evento para todos os utilizadores ligados de uma organização (ex: estado da escala).
+pub fn publicar_organizacao(organizacao_id: i64, tipo: &'static str, dados: Value) {
+    let _ = canal().send(Evento { user_id: None, organizacao_id: Some(organizacao_id), tipo, dados });
+}
+
+/// Recebe os eventos publicados a partir de agora (uma subscrição por ligação SSE).
+pub fn subscrever() -> broadcast::Receiver<Evento> {
+    canal().subscribe()
+}
@@ -0,0 +1,497 @@
+// src/services/faxina_service.rs
+//! Escala de faxina, separada da escala de serviço: cada área de limpeza (com rotação por quarto ou por
+//! turma e, opcionalmente, só de um género) é atribuída por semana a um quarto ou a uma turma. A geração
+//! roda pelos contadores próprios da faxina (quem fez menos, nesta área e no total, e há mais tempo) e não
+//! mexe nos contadores de serviço. Tal como a escala, cada semana fica em rascunho até ser publicada (os
+//! responsáveis são notificados) e uma semana publicada só volta a rascunho por errata.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::faxina::{AreaFaxina, AtribuicaoFaxina, ContagemFaxina, SemanaFaxina},
+    services::notificacao_service,
+    tempo,
+};
+use chrono::{Datelike, Duration, NaiveDate};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+
+/// Rotações de uma área (código, descrição).
+pub const ROTACOES: &[(&str, &str)] = &[
+    ("quarto", "Por quarto"),
+    ("turma", "Por turma"),
+];
+
+/// Restrições de género de uma área (código, descrição).
+pub const GENEROS: &[(&str, &str)] = &[
+    ("Misto", "Todos"),
+    ("M", "Só masculino"),
+    ("F", "Só feminino"),
+];
+
+/// Semanas geradas de uma vez, no máximo.
+pub const MAX_SEMANAS: i64 = 8;
+
+/// Descrição de uma rotação.
+pub fn descrever_rotacao(rotacao: &str) -> &str {
+    ROTACOES.iter().find(|(c, _)| *c == rotacao).map_or(rotacao, |(_, d)| *d)
+}
+
+/// Segunda-feira da semana de `data`.
+pub fn semana_de(data: NaiveDate) -> NaiveDate {
+    data - Duration::days(data.weekday().num_days_from_monday() as i64)
+}
+
+/// Áreas da organização, as ativas primeiro e por nome.
+pub async fn listar_areas(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<AreaFaxina>> {
+    let areas = sqlx::query_as::<_, AreaFaxina>(
+        r#"
+        SELECT id, nome, descricao, rotacao, genero_restricao, ativa
+        FROM faxina_areas
+        WHERE organizacao_id = ?1
+        ORDER BY ativa DESC, nome
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(areas)
+}
+
+/// Falha se a rotação ou a restrição de género não existem.
+fn validar_area(rotacao: &str, genero: &str) -> AppResult<()> {
+    if !ROTACOES.iter().any(|(c, _)| *c == rotacao) {
+        return Err(AppError::validation("rotacao", "Rotação inválida."));
+    }
+    if !GENEROS.iter().any(|(c, _)| *c == genero) {
+        return Err(AppError::validation("genero_restricao", "Restrição de género inválida."));
+    }
+    Ok(())
+}
+
+/// Cria uma área de limpeza (campos já validados). Devolve o id.
+pub async fn criar_area(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    nome: &str,
+    descricao: &str,
+    rotacao: &str,
+    genero: &str,
+) -> AppResult<i64> {
+    validar_area(rotacao, genero)?;
+    let resultado = sqlx::query(
+        "INSERT INTO faxina_areas (organizacao_id, nome, descricao, rotacao, genero_restricao) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(organizacao_id)
+    .bind(nome.trim())
+    .bind(descricao.trim())
+    .bind(rotacao)
+    .bind(genero)
+    .execute(db_pool)
+    .await;
+    match resultado {
+        Ok(r) => Ok(r.last_insert_rowid()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::validation("nome", "Já existe uma área com este nome."))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Altera a descrição, a rotação, a restrição de género e o estado de uma área (vale para as semanas
+/// geradas a seguir). Devolve o nome da área.
+pub async fn atualizar_area(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    descricao: &str,
+    rotacao: &str,
+    genero: &str,
+    ativa: bool,
+) -> AppResult<String> {
+    validar_area(rotacao, genero)?;
+    sqlx::query_scalar(
+        r#"
+        UPDATE faxina_areas SET descricao = ?3, rotacao = ?4, genero_restricao = ?5, ativa = ?6
+        WHERE id = ?1 AND organizacao_id = ?2
+        RETURNING nome
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .bind(descricao.trim())
+    .bind(rotacao)
+    .bind(genero)
+    .bind(ativa)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Área de faxina {} não encontrada.", id)))
+}
+
+/// Estado de uma semana (None = ainda não gerada).
+async fn estado_semana(db_pool: &SqlitePool, organizacao_id: i64, semana: NaiveDate) -> AppResult<Option<String>> {
+    let status = sqlx::query_scalar("SELECT status FROM faxina_semanas WHERE organizacao_id = ?1 AND semana = ?2")
+        .bind(organizacao_id)
+        .bind(semana.to_string())
+        .fetch_optional(db_pool)
+        .await?;
+    Ok(status)
+}
+
+/// Quem pode fazer faxina na semana: ativo e sem indisponibilidade nem baixa que cubra a semana toda.
+#[derive(sqlx::FromRow)]
+struct Disponivel {
+    id: String,
+    genero: String,
+    turma: String,
+    quarto_id: Option<i64>,
+}
+
+/// Quarto ou turma candidato a uma área.
+struct Grupo {
+    chave: String, // "q:<id>" ou "t:<turma>" (contadores)
+    nome: String,
+    quarto_id: Option<i64>,
+    turma: Option<String>,
+    membros: Vec<String>,
+}
+
+/// Contadores da rotação: atribuições anteriores à semana gerada (também as de rascunho, para que
+/// várias semanas geradas de uma vez rodem entre si).
+#[derive(Default)]
+struct Contadores {
+    total: HashMap<String, i64>,
+    por_area: HashMap<(i64, String), i64>,
+    ultima: HashMap<String, String>,
+}
+
+impl Contadores {
+    fn contar(&mut self, area_id: i64, chave: &str, semana: &str) {
+        *self.total.entry(chave.to_string()).or_default() += 1;
+        *self.por_area.entry((area_id, chave.to_string())).or_default() += 1;
+        let ultima = self.ultima.entry(chave.to_string()).or_default();
+        if semana > ultima.as_str() {
+            *ultima = semana.to_string();
+        }
+    }
+}
+
+/// Gera (ou volta a gerar) em rascunho as `semanas` semanas a partir da de `inicio`. As semanas já
+/// publicadas são mantidas. Devolve (semanas geradas, semanas publicadas mantidas).
+pub async fn gerar_semanas(db_pool: &SqlitePool, organizacao_id: i64, inicio: NaiveDate, semanas: i64) -> AppResult<(usize, usize)> {
+    let inicio = semana_de(inicio);
+    if inicio < semana_de(tempo::hoje()) {
+        return Err(AppError::validation("inicio", "A semana já passou."));
+    }
+    if !(1..=MAX_SEMANAS).contains(&semanas) {
+        return Err(AppError::validation("semanas", format!("Entre 1 e {} semanas.", MAX_SEMANAS)));
+    }
+    let (mut geradas, mut mantidas) = (0, 0);
+    for i in 0..semanas {
+        let semana = inicio + Duration::weeks(i);
+        if estado_semana(db_pool, organizacao_id, semana).await?.as_deref() == Some("Publicada") {
+            mantidas += 1;
+            continue;
+        }
+        gerar_semana(db_pool, organizacao_id, semana).await?;
+        geradas += 1;
+    }
+    Ok((geradas, mantidas))
+}
+
+/// Gera em rascunho a semana que começa em `semana` (segunda-feira): cada área ativa vai para o quarto
+/// ou a turma com menos faxinas (no total e nesta área) e há mais tempo, de preferência um que ainda
+/// não tenha outra área nessa semana. Ficam responsáveis os membros disponíveis do género da área.
+pub async fn gerar_semana(db_pool: &SqlitePool, organizacao_id: i64, semana: NaiveDate) -> AppResult<()> {
+    let semana_txt = semana.to_string();
+    let fim = (semana + Duration::days(6)).to_string();
+    let mut tx = db_pool.begin().await?;
+
+    let status: Option<String> = sqlx::query_scalar("SELECT status FROM faxina_semanas WHERE organizacao_id = ?1 AND semana = ?2")
+        .bind(organizacao_id)
+        .bind(&semana_txt)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if status.as_deref() == Some("Publicada") {
+        return Err(AppError::Conflict(format!(
+            "A faxina da semana de {} já está publicada (use a errata para a reabrir).",
+            tempo::data_curta(semana)
+        )));
+    }
+    sqlx::query("DELETE FROM faxina_atribuicoes WHERE organizacao_id = ?1 AND semana = ?2")
+        .bind(organizacao_id)
+        .bind(&semana_txt)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO faxina_semanas (organizacao_id, semana) VALUES (?1, ?2)
+        ON CONFLICT (organizacao_id, semana) DO UPDATE SET
+            status = 'Rascunho', gerada_em = datetime('now'), publicada_em = NULL
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(&semana_txt)
+    .execute(&mut *tx)
+    .await?;
+
+    let areas = sqlx::query_as::<_, AreaFaxina>(
+        "SELECT id, nome, descricao, rotacao, genero_restricao, ativa FROM faxina_areas WHERE organizacao_id = ?1 AND ativa = 1 ORDER BY nome",
+    )
+    .bind(organizacao_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    if areas.is_empty() {
+        return Err(AppError::validation("inicio", "Não há áreas de faxina ativas."));
+    }
+
+    let disponiveis = sqlx::query_as::<_, Disponivel>(
+        r#"
+        SELECT u.id, u.genero, u.turma, q.id AS quarto_id
+        FROM users u
+        LEFT JOIN quarto_ocupantes o ON o.user_id = u.id
+        LEFT JOIN quartos q ON q.id = o.quarto_id AND q.ativo = 1
+        WHERE u.ativo = 1 AND u.organizacao_id = ?1
+          AND NOT EXISTS (
+              SELECT 1 FROM indisponibilidades i
+              WHERE i.user_id = u.id AND i.data_inicio <= ?2 AND i.data_fim >= ?3
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM baixas b
+              WHERE b.user_id = u.id AND b.data_inicio <= ?2 AND b.data_fim >= ?3
+          )
+        ORDER BY u.name
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(&semana_txt)
+    .bind(&fim)
+    .fetch_all(&mut *tx)
+    .await?;
+    let quartos: Vec<(i64, String)> =
+        sqlx::query_as("SELECT id, nome FROM quartos WHERE organizacao_id = ?1 AND ativo = 1 ORDER BY nome")
+            .bind(organizacao_id)
+            .fetch_all(&mut *tx)
+            .await?;
+    let mut turmas: Vec<String> = disponiveis.iter().map(|d| d.turma.clone()).filter(|t| !t.is_empty()).collect();
+    turmas.sort();
+    turmas.dedup();
+
+    let mut contadores = Contadores::default();
+    let anteriores: Vec<(i64, Option<i64>, Option<String>, String)> = sqlx::query_as(
+        "SELECT area_id, quarto_id, turma, semana FROM faxina_atribuicoes WHERE organizacao_id = ?1 AND semana < ?2",
+    )
+    .bind(organizacao_id)
+    .bind(&semana_txt)
+    .fetch_all(&mut *tx)
+    .await?;
+    for (area_id, quarto_id, turma, semana_anterior) in &anteriores {
+        match (quarto_id, turma) {
+            (Some(q), _) => contadores.contar(*area_id, &format!("q:{}", q), semana_anterior),
+            (None, Some(t)) => contadores.contar(*area_id, &format!("t:{}", t), semana_anterior),
+            (None, None) => {}
+        }
+    }
+
+    let mut usados: HashSet<String> = HashSet::new();
+    for area in &areas {
+        let do_genero = |d: &&Disponivel| area.genero_restricao == "Misto" || d.genero == area.genero_restricao;
+        let mut grupos: Vec<Grupo> = if area.rotacao == "quarto" {
+            quartos
+                .iter()
+                .map(|(id, nome)| Grupo {
+                    chave: format!("q:{}", id),
+                    nome: nome.clone(),
+                    quarto_id: Some(*id),
+                    turma: None,
+                    membros: disponiveis.iter().filter(|d| d.quarto_id == Some(*id)).filter(do_genero).map(|d| d.id.clone()).collect(),
+                })
+                .collect()
+        } else {
+            turmas
+                .iter()
+                .map(|t| Grupo {
+                    chave: format!("t:{}", t),
+                    nome: format!("Turma {}", t),
+                    quarto_id: None,
+                    turma: Some(t.clone()),
+                    membros: disponiveis.iter().filter(|d| d.turma == *t).filter(do_genero).map(|d| d.id.clone()).collect(),
+                })
+                .collect()
+        };
+        grupos.retain(|g| !g.membros.is_empty());
+        let Some(grupo) = grupos.into_iter().min_by(|a, b| {
+            let ordem = |g: &Grupo| {
+                (
+                    usados.contains(&g.chave),
+                    contadores.total.get(&g.chave).copied().unwrap_or(0),
+                    contadores.por_area.get(&(area.id, g.chave.clone())).copied().unwrap_or(0),
+                    contadores.ultima.get(&g.chave).cloned().unwrap_or_default(),
+                )
+            };
+            ordem(a).cmp(&ordem(b)).then_with(|| a.nome.cmp(&b.nome))
+        }) else {
+            let rotacao = if area.rotacao == "quarto" { "quarto" } else { "turma" };
+            return Err(AppError::Conflict(format!(
+                "Nenhum(a) {} com pessoas disponíveis para a área '{}' na semana de {}.",
+                rotacao,
+                area.nome,
+                tempo::data_curta(semana)
+            )));
+        };
+
+        let atribuicao_id = sqlx::query(
+            "INSERT INTO faxina_atribuicoes (organizacao_id, semana, area_id, quarto_id, turma, grupo) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .bind(organizacao_id)
+        .bind(&semana_txt)
+        .bind(area.id)
+        .bind(grupo.quarto_id)
+        .bind(&grupo.turma)
+        .bind(&grupo.nome)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        for user_id in &grupo.membros {
+            sqlx::query("INSERT INTO faxina_membros (atribuicao_id, user_id) VALUES (?1, ?2)")
+                .bind(atribuicao_id)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        contadores.contar(area.id, &grupo.chave, &semana_txt);
+        usados.insert(grupo.chave);
+    }
+
+    tx.commit().await?;
+    tracing::info!("🧹 Faxina da semana de {} gerada (organização {}): {} áreas.", semana, organizacao_id, areas.len());
+    Ok(())
+}
+
+/// Publica a semana (rascunho) e notifica cada responsável da área que lhe calhou.
+pub async fn publicar(db_pool: &SqlitePool, organizacao_id: i64, semana: NaiveDate) -> AppResult<()> {
+    let publicada = sqlx::query(
+        "UPDATE faxina_semanas SET status = 'Publicada', publicada_em = datetime('now') WHERE organizacao_id = ?1 AND semana = ?2 AND status = 'Rascunho'",
+    )
+    .bind(organizacao_id)
+    .bind(semana.to_string())
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    if publicada == 0 {
+        return match estado_semana(db_pool, organizacao_id, semana).await? {
+            Some(_) => Err(AppError::Conflict(format!("A faxina da semana de {} já está publicada.", tempo::data_curta(semana)))),
+            None => Err(AppError::NotFound(format!("A faxina da semana de {} ainda não foi gerada.", tempo::data_curta(semana)))),
+        };
+    }
+
+    let responsaveis: Vec<(String, String, String)> = sqlx::query_as(
+        r#"
+        SELECT m.user_id, ar.nome, a.grupo
+        FROM faxina_membros m
+        JOIN faxina_atribuicoes a ON a.id = m.atribuicao_id
+        JOIN faxina_areas ar ON ar.id = a.area_id
+        WHERE a.organizacao_id = ?1 AND a.semana = ?2
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(semana.to_string())
+    .fetch_all(db_pool)
+    .await?;
+    let titulo = format!("Faxina da semana de {}", tempo::data_curta(semana));
+    for (user_id, area, grupo) in &responsaveis {
+        let mensagem = format!("{}: {} ({}).", titulo, area, grupo);
+        notificacao_service::notificar(db_pool, user_id, notificacao_service::TIPO_ESCALA, &titulo, &mensagem).await;
+    }
+    tracing::info!("🧹 Faxina da semana de {} publicada (organização {}): {} notificados.", semana, organizacao_id, responsaveis.len());
+    Ok(())
+}
+
+/// Errata: reabre em rascunho uma semana publicada (para a voltar a gerar).
+pub async fn reabrir(db_pool: &SqlitePool, organizacao_id: i64, semana: NaiveDate) -> AppResult<()> {
+    match estado_semana(db_pool, organizacao_id, semana).await?.as_deref() {
+        Some("Publicada") => {
+            sqlx::query("UPDATE faxina_semanas SET status = 'Rascunho', publicada_em = NULL WHERE organizacao_id = ?1 AND semana = ?2")
+                .bind(organizacao_id)
+                .bind(semana.to_string())
+                .execute(db_pool)
+                .await?;
+            Ok(())
+        }
+        Some(_) => Err(AppError::Conflict(format!(
+            "A faxina da semana de {} ainda não está publicada.",
+            tempo::data_curta(semana)
+        ))),
+        None => Err(AppError::NotFound(format!("A faxina da semana de {} ainda não foi gerada.", tempo::data_curta(semana)))),
+    }
+}
+
+/// Semanas a partir da de `desde`, com as atribuições; `minha` marca as do utilizador `user_id`. Sem
+/// `incluir_rascunhos`, só as publicadas.
+pub async fn semanas(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    desde: NaiveDate,
+    user_id: &str,
+    incluir_rascunhos: bool,
+) -> AppResult<Vec<SemanaFaxina>> {
+    let desde = semana_de(desde).to_string();
+    let estados: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT semana, status FROM faxina_semanas
+        WHERE organizacao_id = ?1 AND semana >= ?2 AND (?3 = 1 OR status = 'Publicada')
+        ORDER BY semana
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(&desde)
+    .bind(incluir_rascunhos)
+    .fetch_all(db_pool)
+    .await?;
+    let atribuicoes = sqlx::query_as::<_, AtribuicaoFaxina>(
+        r#"
+        SELECT a.semana, ar.nome AS area, ar.descricao, a.grupo,
+               COALESCE(GROUP_CONCAT(u.name, ', '), '') AS membros,
+               COALESCE(MAX(m.user_id = ?3), 0) AS minha
+        FROM faxina_atribuicoes a
+        JOIN faxina_areas ar ON ar.id = a.area_id
+        LEFT JOIN faxina_membros m ON m.atribuicao_id = a.id
+        LEFT JOIN users u ON u.id = m.user_id
+        WHERE a.organizacao_id = ?1 AND a.semana >= ?2
+        GROUP BY a.id
+        ORDER BY a.semana, ar.nome
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(&desde)
+    .bind(user_id)
+    .fetch_all(db_pool)
+    .await?;
+
+    Ok(estados
+        .into_iter()
+        .map(|(semana, status)| SemanaFaxina {
+            fim: tempo::ler_data(&semana).map(|d| (d + Duration::days(6)).to_string()).unwrap_or_default(),
+            atribuicoes: atribuicoes.iter().filter(|a| a.semana == semana).cloned().collect(),
+            semana,
+            status,
+        })
+        .collect())
+}
+
+/// Faxinas publicadas por quarto ou turma (contadores da rotação), os que fizeram mais primeiro.
+pub async fn contagem(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<ContagemFaxina>> {
+    let contagem = sqlx::query_as::<_, ContagemFaxina>(
+        r#"
+        SELECT a.grupo, COUNT(*) AS total, MAX(a.semana) AS ultima
+        FROM faxina_atribuicoes a
+        JOIN faxina_semanas s ON s.organizacao_id = a.organizacao_id AND s.semana = a.semana
+        WHERE a.organizacao_id = ?1 AND s.status = 'Publicada'
+        GROUP BY a.grupo
+        ORDER BY total DESC, a.grupo
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(contagem)
+}
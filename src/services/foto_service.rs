@@ -0,0 +1,189 @@
+// src/services/foto_service.rs
+//! Fotografias dos utilizadores: uma por utilizador, em JPEG, enviada pelos administradores na ficha
+//! do utilizador. Os ficheiros ficam em `fotos.diretorio` com um nome gerado; as dimensões e o número
+//! de componentes de cor são lidos do próprio JPEG no envio, porque o PDF do efetivo (ver
+//! `pdf_service`) inclui o ficheiro tal como está, sem o descodificar.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::foto::FotoUser,
+    services::user_service,
+    tempo,
+};
+use sqlx::SqlitePool;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+/// Tamanho máximo de uma fotografia (o PDF de uma turma inclui todas).
+pub const MAX_BYTES: usize = 5 * 1024 * 1024;
+
+/// Colunas de `FotoUser`.
+const SELECT_FOTO: &str = r#"
+    SELECT f.user_id, f.ficheiro, f.largura, f.altura, f.componentes, f.tamanho, f.enviada_em
+    FROM user_fotos f
+    JOIN users u ON u.id = f.user_id
+"#;
+
+/// Largura, altura e componentes de cor de um JPEG, lidos do cabeçalho do frame (SOF). Só os
+/// formatos que os leitores de PDF mostram: baseline, estendido e progressivo (Huffman).
+fn dimensoes_jpeg(conteudo: &[u8]) -> Option<(i64, i64, i64)> {
+    if !conteudo.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    while pos + 4 <= conteudo.len() {
+        if conteudo[pos] != 0xFF {
+            return None;
+        }
+        let marcador = conteudo[pos + 1];
+        // Enchimento (0xFF repetidos) e marcadores sem segmento
+        if marcador == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marcador == 0x01 || (0xD0..=0xD7).contains(&marcador) {
+            pos += 2;
+            continue;
+        }
+        let tamanho = u16::from_be_bytes([conteudo[pos + 2], conteudo[pos + 3]]) as usize;
+        match marcador {
+            0xC0..=0xC2 => {
+                let sof = conteudo.get(pos + 4..pos + 10)?;
+                let altura = u16::from_be_bytes([sof[1], sof[2]]) as i64;
+                let largura = u16::from_be_bytes([sof[3], sof[4]]) as i64;
+                return Some((largura, altura, sof[5] as i64));
+            }
+            // Outros SOF (aritmético, sem perdas) ou fim da imagem antes do frame
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xD9 | 0xDA => return None,
+            _ => pos += 2 + tamanho,
+        }
+    }
+    None
+}
+
+/// O nome vem da base de dados mas foi gerado por `enviar`: não sai do diretório das fotografias.
+fn caminho(dir: &Path, ficheiro: &str) -> AppResult<PathBuf> {
+    if ficheiro.contains(['/', '\\']) || ficheiro.starts_with('.') {
+        return Err(AppError::NotFound("Ficheiro da fotografia inválido.".to_string()));
+    }
+    Ok(dir.join(ficheiro))
+}
+
+/// Fotografia de um utilizador da organização (None = sem fotografia), com a hora do envio local.
+pub async fn obter(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str) -> AppResult<Option<FotoUser>> {
+    let foto = sqlx::query_as::<_, FotoUser>(&format!("{} WHERE f.user_id = ?1 AND u.organizacao_id = ?2", SELECT_FOTO))
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_optional(db_pool)
+        .await?;
+    Ok(foto.map(|f| FotoUser { enviada_em: tempo::formatar(&f.enviada_em, tempo::FORMATO_DATA_HORA), ..f }))
+}
+
+/// Fotografias dos utilizadores da organização, por ID do utilizador.
+pub async fn da_organizacao(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<HashMap<String, FotoUser>> {
+    let fotos = sqlx::query_as::<_, FotoUser>(&format!("{} WHERE u.organizacao_id = ?1", SELECT_FOTO))
+        .bind(organizacao_id)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(fotos.into_iter().map(|f| (f.user_id.clone(), f)).collect())
+}
+
+/// Conteúdo (JPEG) de uma fotografia.
+pub async fn conteudo(dir: &Path, foto: &FotoUser) -> AppResult<Vec<u8>> {
+    let origem = caminho(dir, &foto.ficheiro)?;
+    tokio::fs::read(&origem).await.map_err(|e| {
+        tracing::error!("Erro ao ler a fotografia {}: {}", origem.display(), e);
+        AppError::NotFound(format!("O ficheiro da fotografia de {} não foi encontrado.", foto.user_id))
+    })
+}
+
+/// Guarda a fotografia de um utilizador, substituindo a anterior (cujo ficheiro é apagado).
+pub async fn enviar(
+    db_pool: &SqlitePool,
+    dir: &Path,
+    organizacao_id: i64,
+    user_id: &str,
+    conteudo: &[u8],
+    operador_id: &str,
+) -> AppResult<FotoUser> {
+    match user_service::find_user_by_id(db_pool, user_id).await? {
+        Some(user) if user.organizacao_id == organizacao_id => {}
+        _ => return Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id))),
+    }
+    if conteudo.len() > MAX_BYTES {
+        return Err(AppError::validation(
+            "foto",
+            format!("A fotografia tem mais de {} MB.", MAX_BYTES / (1024 * 1024)),
+        ));
+    }
+    let (largura, altura, componentes) = dimensoes_jpeg(conteudo)
+        .ok_or_else(|| AppError::validation("foto", "O ficheiro não é um JPEG suportado."))?;
+    if largura == 0 || altura == 0 {
+        return Err(AppError::validation("foto", "O JPEG não indica as dimensões da imagem."));
+    }
+    // CMYK (e outros) não são mostrados da mesma forma por todos os leitores de PDF
+    if componentes != 1 && componentes != 3 {
+        return Err(AppError::validation("foto", "A fotografia tem de estar em RGB ou em tons de cinzento."));
+    }
+    let anterior = obter(db_pool, organizacao_id, user_id).await?;
+
+    let ficheiro = format!("{}.jpg", Uuid::new_v4());
+    tokio::fs::create_dir_all(dir).await.map_err(|e| {
+        tracing::error!("Erro ao criar o diretório das fotografias {}: {}", dir.display(), e);
+        AppError::InternalServerError
+    })?;
+    let destino = caminho(dir, &ficheiro)?;
+    tokio::fs::write(&destino, conteudo).await.map_err(|e| {
+        tracing::error!("Erro ao gravar a fotografia {}: {}", destino.display(), e);
+        AppError::InternalServerError
+    })?;
+
+    let resultado = sqlx::query(
+        r#"
+        INSERT INTO user_fotos (user_id, ficheiro, largura, altura, componentes, tamanho, enviada_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        ON CONFLICT (user_id) DO UPDATE SET
+            ficheiro = excluded.ficheiro, largura = excluded.largura, altura = excluded.altura,
+            componentes = excluded.componentes, tamanho = excluded.tamanho,
+            enviada_por = excluded.enviada_por, enviada_em = datetime('now')
+        "#,
+    )
+    .bind(user_id)
+    .bind(&ficheiro)
+    .bind(largura)
+    .bind(altura)
+    .bind(componentes)
+    .bind(conteudo.len() as i64)
+    .bind(operador_id)
+    .execute(db_pool)
+    .await;
+    if let Err(e) = resultado {
+        // Sem registo, o ficheiro ficaria órfão no diretório
+        let _ = tokio::fs::remove_file(&destino).await;
+        return Err(e.into());
+    }
+    if let Some(anterior) = anterior {
+        if let Err(e) = tokio::fs::remove_file(caminho(dir, &anterior.ficheiro)?).await {
+            tracing::warn!("Fotografia anterior de {} ({}) não apagada: {}", user_id, anterior.ficheiro, e);
+        }
+    }
+    tracing::info!("📷 Fotografia de {} ({}x{}, {} bytes) enviada por {}.", user_id, largura, altura, conteudo.len(), operador_id);
+    obter(db_pool, organizacao_id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Fotografia de '{}' não encontrada.", user_id)))
+}
+
+/// Apaga a fotografia de um utilizador (registo e ficheiro). Devolve a fotografia apagada.
+pub async fn remover(db_pool: &SqlitePool, dir: &Path, organizacao_id: i64, user_id: &str) -> AppResult<FotoUser> {
+    let foto = obter(db_pool, organizacao_id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("O utilizador '{}' não tem fotografia.", user_id)))?;
+    sqlx::query("DELETE FROM user_fotos WHERE user_id = ?1").bind(user_id).execute(db_pool).await?;
+    if let Err(e) = tokio::fs::remove_file(caminho(dir, &foto.ficheiro)?).await {
+        tracing::warn!("Ficheiro da fotografia de {} ({}) não apagado: {}", user_id, foto.ficheiro, e);
+    }
+    Ok(foto)
+}
@@ -0,0 +1,542 @@
+// src/services/google_calendar_service.rs
+//! Integração com o Google Calendar: quem liga a conta (OAuth, no painel) passa a ter os seus serviços
+//! publicados como eventos de dia inteiro no calendário. Uma tarefa de sincronização cria, atualiza e
+//! apaga os eventos quando a escala muda (publicação, errata, trocas aprovadas) e periodicamente.
+//! Só os dias de hoje em diante são sincronizados; os eventos de dias passados ficam como estão.
+//! Desativado se GOOGLE_CALENDAR_CLIENT_ID não estiver definida.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::google_calendar::GoogleCalendarLigacao,
+    services::{escala_service, evento_service, organizacao_service},
+    tempo,
+};
+use chrono::{Duration as ChronoDuration, NaiveDate};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::{collections::HashMap, sync::OnceLock, time::Duration};
+use tokio::sync::broadcast::error::RecvError;
+
+const URL_AUTORIZACAO: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const URL_TOKEN: &str = "https://oauth2.googleapis.com/token";
+const URL_REVOGAR: &str = "https://oauth2.googleapis.com/revoke";
+const URL_API: &str = "https://www.googleapis.com/calendar/v3";
+/// Só os eventos (não dá acesso às definições nem a outros calendários).
+const ESCOPO: &str = "https://www.googleapis.com/auth/calendar.events";
+/// Tempo máximo de um pedido ao Google.
+const TIMEOUT_API: Duration = Duration::from_secs(15);
+const INTERVALO_MINUTOS_PADRAO: u64 = 30;
+/// Dias à frente sincronizados.
+const DIAS_HORIZONTE: i64 = 90;
+/// Espera depois de uma alteração à escala, para juntar as que vêm seguidas (ex: publicar um mês).
+const ESPERA_ALTERACOES: Duration = Duration::from_secs(5);
+/// O access token é renovado um pouco antes de expirar.
+const MARGEM_EXPIRACAO_SEGUNDOS: i64 = 60;
+
+/// Configuração do cliente OAuth (lida do ambiente no arranque).
+pub struct GoogleCalendarConfig {
+    client_id: String,
+    client_secret: String,
+    pub redirect_url: String, // Ex: https://mercal.exemplo.pt/user/google/callback
+    pub intervalo: Duration,
+    http: reqwest::Client,
+}
+
+// O segredo não aparece nos logs
+impl std::fmt::Debug for GoogleCalendarConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GoogleCalendarConfig")
+            .field("client_id", &self.client_id)
+            .field("redirect_url", &self.redirect_url)
+            .field("intervalo", &self.intervalo)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GoogleCalendarConfig {
+    /// Lê GOOGLE_CALENDAR_CLIENT_ID, GOOGLE_CALENDAR_CLIENT_SECRET, GOOGLE_CALENDAR_REDIRECT_URL e
+    /// GOOGLE_CALENDAR_SYNC_MINUTES (30). Ok(None) (desativado) se GOOGLE_CALENDAR_CLIENT_ID não estiver definida.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let var = |nome: &str| std::env::var(nome).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let Some(client_id) = var("GOOGLE_CALENDAR_CLIENT_ID") else {
+            return Ok(None);
+        };
+        let obrigatoria = |nome: &str| var(nome).ok_or(format!("{} não definida (obrigatória com GOOGLE_CALENDAR_CLIENT_ID)", nome));
+        let minutos = match var("GOOGLE_CALENDAR_SYNC_MINUTES") {
+            Some(v) => v.parse::<u64>().ok().filter(|m| *m >= 1).ok_or(format!("GOOGLE_CALENDAR_SYNC_MINUTES inválido: '{}'", v))?,
+            None => INTERVALO_MINUTOS_PADRAO,
+        };
+        let http = reqwest::Client::builder()
+            .timeout(TIMEOUT_API)
+            .build()
+            .map_err(|e| format!("falha ao criar cliente HTTP: {}", e))?;
+        Ok(Some(Self {
+            client_id,
+            client_secret: obrigatoria("GOOGLE_CALENDAR_CLIENT_SECRET")?,
+            redirect_url: obrigatoria("GOOGLE_CALENDAR_REDIRECT_URL")?,
+            intervalo: Duration::from_secs(minutos * 60),
+            http,
+        }))
+    }
+}
+
+static CONFIG: OnceLock<GoogleCalendarConfig> = OnceLock::new();
+
+/// Define a configuração (chamado uma vez, no arranque, só se estiver ativa).
+pub fn configurar(config: GoogleCalendarConfig) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("Configuração do Google Calendar já definida; ignorada.");
+    }
+}
+
+/// Configuração, se a integração estiver ativa.
+pub fn config() -> Option<&'static GoogleCalendarConfig> {
+    CONFIG.get()
+}
+
+fn erro_google(contexto: &str, e: impl std::fmt::Display) -> AppError {
+    tracing::warn!("Google Calendar ({}): {}", contexto, e);
+    AppError::Conflict(format!("O Google recusou o pedido ({}). Tente novamente.", contexto))
+}
+
+// --- Ligação das contas (OAuth) ---
+
+/// URL para onde o browser é enviado para autorizar o acesso ao calendário.
+/// `prompt=consent` garante um refresh token mesmo que a conta já tenha autorizado antes.
+pub fn url_autorizacao(config: &GoogleCalendarConfig, state: &str) -> AppResult<String> {
+    let url = reqwest::Url::parse_with_params(
+        URL_AUTORIZACAO,
+        &[
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("scope", ESCOPO),
+            ("access_type", "offline"),
+            ("prompt", "consent"),
+            ("state", state),
+        ],
+    )
+    .map_err(|e| erro_google("URL de autorização", e))?;
+    Ok(url.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct RespostaToken {
+    access_token: String,
+    expires_in: i64,
+    refresh_token: Option<String>,
+}
+
+/// Troca o código de autorização pelos tokens e guarda a ligação (substitui uma anterior).
+pub async fn ligar(db_pool: &SqlitePool, config: &GoogleCalendarConfig, user_id: &str, code: &str) -> AppResult<()> {
+    let resposta: RespostaToken = config
+        .http
+        .post(URL_TOKEN)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+        ])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| erro_google("token", e))?
+        .json()
+        .await
+        .map_err(|e| erro_google("token (JSON)", e))?;
+    let refresh_token = resposta
+        .refresh_token
+        .ok_or_else(|| erro_google("token", "resposta sem refresh token"))?;
+
+    // Voltar a ligar mantém os eventos conhecidos: os que não existirem na conta autorizada são recriados
+    sqlx::query(
+        r#"
+        INSERT INTO google_calendar_ligacoes (user_id, refresh_token, access_token, access_expira_em)
+        VALUES (?1, ?2, ?3, datetime('now', ?4))
+        ON CONFLICT(user_id) DO UPDATE SET
+            refresh_token = excluded.refresh_token,
+            access_token = excluded.access_token,
+            access_expira_em = excluded.access_expira_em,
+            ligado_em = datetime('now'),
+            ultimo_erro = NULL
+        "#,
+    )
+    .bind(user_id)
+    .bind(refresh_token)
+    .bind(resposta.access_token)
+    .bind(format!("+{} seconds", resposta.expires_in))
+    .execute(db_pool)
+    .await?;
+    tracing::info!("📅 Conta {} ligada ao Google Calendar.", user_id);
+    Ok(())
+}
+
+/// Estado da ligação de um utilizador (None = não ligado).
+pub async fn ligacao_user(db_pool: &SqlitePool, user_id: &str) -> AppResult<Option<GoogleCalendarLigacao>> {
+    let ligacao = sqlx::query_as::<_, GoogleCalendarLigacao>(
+        r#"
+        SELECT ligado_em, sincronizado_em, ultimo_erro
+        FROM google_calendar_ligacoes
+        WHERE user_id = ?1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(ligacao.map(|l| GoogleCalendarLigacao {
+        ligado_em: tempo::formatar(&l.ligado_em, tempo::FORMATO_DATA_HORA),
+        sincronizado_em: tempo::formatar_opcional(l.sincronizado_em, tempo::FORMATO_DATA_HORA),
+        ..l
+    }))
+}
+
+/// Desliga a conta: apaga os eventos futuros criados (para não ficarem serviços desatualizados no
+/// calendário) e revoga a autorização no Google. Só a remoção da ligação pode falhar; o resto apenas loga.
+/// Devolve false se não estava ligada.
+pub async fn desligar(db_pool: &SqlitePool, user_id: &str) -> AppResult<bool> {
+    let Some(ligacao) = carregar_ligacao(db_pool, user_id).await? else {
+        return Ok(false);
+    };
+    if let Some(config) = config() {
+        let mut cliente = Cliente { config, db_pool, ligacao };
+        match cliente.access_token().await {
+            Ok(_) => {
+                let hoje = tempo::hoje().to_string();
+                for (alocacao_id, evento) in eventos_conhecidos(db_pool, user_id).await? {
+                    if evento.data >= hoje {
+                        if let Err(e) = cliente.apagar_evento(&evento.event_id).await {
+                            tracing::warn!("Google Calendar: falha ao apagar o evento de {} ({}): {:?}", user_id, alocacao_id, e);
+                        }
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Google Calendar: sem acesso ao calendário de {} ao desligar: {:?}", user_id, e),
+        }
+        let revogacao = config.http.post(URL_REVOGAR).form(&[("token", cliente.ligacao.refresh_token.as_str())]).send().await;
+        if let Err(e) = revogacao {
+            tracing::warn!("Google Calendar: falha ao revogar a autorização de {}: {}", user_id, e.without_url());
+        }
+    }
+    sqlx::query("DELETE FROM google_calendar_ligacoes WHERE user_id = ?1")
+        .bind(user_id)
+        .execute(db_pool)
+        .await?;
+    tracing::info!("📅 Google Calendar desligado da conta de {}.", user_id);
+    Ok(true)
+}
+
+// --- Sincronização ---
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct Ligacao {
+    user_id: String,
+    refresh_token: String,
+    access_token: Option<String>,
+    access_valido: bool,
+    calendario_id: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct EventoConhecido {
+    alocacao_id: String,
+    event_id: String,
+    data: String,
+    assinatura: String,
+}
+
+async fn carregar_ligacao(db_pool: &SqlitePool, user_id: &str) -> AppResult<Option<Ligacao>> {
+    let ligacao = sqlx::query_as::<_, Ligacao>(
+        r#"
+        SELECT user_id, refresh_token, access_token,
+               COALESCE(access_expira_em > datetime('now', ?2), 0) AS access_valido, calendario_id
+        FROM google_calendar_ligacoes
+        WHERE user_id = ?1
+        "#,
+    )
+    .bind(user_id)
+    .bind(format!("+{} seconds", MARGEM_EXPIRACAO_SEGUNDOS))
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(ligacao)
+}
+
+/// Eventos criados no calendário do utilizador, por alocação.
+async fn eventos_conhecidos(db_pool: &SqlitePool, user_id: &str) -> AppResult<HashMap<String, EventoConhecido>> {
+    let eventos = sqlx::query_as::<_, EventoConhecido>(
+        "SELECT alocacao_id, event_id, data, assinatura FROM google_calendar_eventos WHERE user_id = ?1",
+    )
+    .bind(user_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(eventos.into_iter().map(|e| (e.alocacao_id.clone(), e)).collect())
+}
+
+/// Pedidos à API do Calendar em nome de um utilizador.
+struct Cliente<'a> {
+    config: &'a GoogleCalendarConfig,
+    db_pool: &'a SqlitePool,
+    ligacao: Ligacao,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventoCriado {
+    id: String,
+}
+
+impl Cliente<'_> {
+    /// Access token válido, renovado com o refresh token se tiver expirado.
+    async fn access_token(&mut self) -> AppResult<String> {
+        if let (true, Some(token)) = (self.ligacao.access_valido, &self.ligacao.access_token) {
+            return Ok(token.clone());
+        }
+        let resposta: RespostaToken = self
+            .config
+            .http
+            .post(URL_TOKEN)
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", self.ligacao.refresh_token.as_str()),
+                ("client_id", self.config.client_id.as_str()),
+                ("client_secret", self.config.client_secret.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| erro_google("renovação do token", e.without_url()))?
+            .json()
+            .await
+            .map_err(|e| erro_google("renovação do token (JSON)", e))?;
+        sqlx::query(
+            "UPDATE google_calendar_ligacoes SET access_token = ?1, access_expira_em = datetime('now', ?2) WHERE user_id = ?3",
+        )
+        .bind(&resposta.access_token)
+        .bind(format!("+{} seconds", resposta.expires_in))
+        .bind(&self.ligacao.user_id)
+        .execute(self.db_pool)
+        .await?;
+        self.ligacao.access_token = Some(resposta.access_token.clone());
+        self.ligacao.access_valido = true;
+        Ok(resposta.access_token)
+    }
+
+    fn url_eventos(&self) -> String {
+        format!("{}/calendars/{}/events", URL_API, self.ligacao.calendario_id)
+    }
+
+    /// Cria o evento, ou substitui-o se `event_id` for indicado (se já não existir, ex: apagado à mão,
+    /// é criado de novo). Devolve o ID do evento.
+    async fn gravar_evento(&mut self, event_id: Option<&str>, corpo: &serde_json::Value) -> AppResult<String> {
+        let token = self.access_token().await?;
+        if let Some(id) = event_id {
+            let resposta = self
+                .config
+                .http
+                .put(format!("{}/{}", self.url_eventos(), id))
+                .bearer_auth(&token)
+                .json(corpo)
+                .send()
+                .await
+                .map_err(|e| erro_google("gravar evento", e.without_url()))?;
+            if !matches!(resposta.status(), reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE) {
+                let gravado: EventoCriado = resposta
+                    .error_for_status()
+                    .map_err(|e| erro_google("gravar evento", e.without_url()))?
+                    .json()
+                    .await
+                    .map_err(|e| erro_google("gravar evento (JSON)", e))?;
+                return Ok(gravado.id);
+            }
+        }
+        let criado: EventoCriado = self
+            .config
+            .http
+            .post(self.url_eventos())
+            .bearer_auth(&token)
+            .json(corpo)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| erro_google("criar evento", e.without_url()))?
+            .json()
+            .await
+            .map_err(|e| erro_google("criar evento (JSON)", e))?;
+        Ok(criado.id)
+    }
+
+    /// Apaga o evento (um evento que já não existe, ex: apagado à mão, conta como apagado).
+    async fn apagar_evento(&mut self, event_id: &str) -> AppResult<()> {
+        let token = self.access_token().await?;
+        let resposta = self
+            .config
+            .http
+            .delete(format!("{}/{}", self.url_eventos(), event_id))
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| erro_google("apagar evento", e.without_url()))?;
+        if matches!(resposta.status(), reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE) {
+            return Ok(());
+        }
+        resposta.error_for_status().map_err(|e| erro_google("apagar evento", e.without_url()))?;
+        Ok(())
+    }
+}
+
+/// Evento de dia inteiro de um serviço.
+fn corpo_evento(alocacao: &crate::models::escala::AlocacaoDetalhe) -> serde_json::Value {
+    let dia = NaiveDate::parse_from_str(&alocacao.data, "%Y-%m-%d").unwrap_or_else(|_| tempo::hoje());
+    let mut descricao = format!("Serviço na escala publicada do Mercal: {}.", alocacao.posto);
+    if let Some(tag) = &alocacao.tag {
+        descricao.push_str(&format!(" ({})", tag));
+    }
+    serde_json::json!({
+        "summary": format!("Serviço: {}", alocacao.posto),
+        "description": descricao,
+        "start": { "date": dia.to_string() },
+        "end": { "date": (dia + ChronoDuration::days(1)).to_string() },
+        "extendedProperties": { "private": { "mercal2_alocacao": alocacao.id } },
+    })
+}
+
+/// Resume os dados mostrados no evento: se mudar, o evento é regravado.
+fn assinatura(alocacao: &crate::models::escala::AlocacaoDetalhe) -> String {
+    format!("{}|{}|{}", alocacao.data, alocacao.posto, alocacao.tag.as_deref().unwrap_or_default())
+}
+
+/// Põe o calendário do utilizador de acordo com os seus serviços publicados de hoje em diante.
+/// Devolve (criados/atualizados, apagados).
+pub async fn sincronizar_user(db_pool: &SqlitePool, user_id: &str) -> AppResult<(usize, usize)> {
+    let config = config().ok_or_else(|| AppError::NotFound("A integração com o Google Calendar não está ativa.".to_string()))?;
+    let Some(ligacao) = carregar_ligacao(db_pool, user_id).await? else {
+        return Ok((0, 0));
+    };
+    let resultado = sincronizar(config, db_pool, ligacao).await;
+    let erro = resultado.as_ref().err().map(|e| e.user_message());
+    sqlx::query(
+        r#"
+        UPDATE google_calendar_ligacoes
+        SET ultimo_erro = ?1, sincronizado_em = CASE WHEN ?1 IS NULL THEN datetime('now') ELSE sincronizado_em END
+        WHERE user_id = ?2
+        "#,
+    )
+    .bind(erro)
+    .bind(user_id)
+    .execute(db_pool)
+    .await?;
+    resultado
+}
+
+async fn sincronizar(config: &GoogleCalendarConfig, db_pool: &SqlitePool, ligacao: Ligacao) -> AppResult<(usize, usize)> {
+    let user_id = ligacao.user_id.clone();
+    let (organizacao_id, _) = organizacao_service::do_utilizador(db_pool, &user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)))?;
+    let hoje = tempo::hoje();
+    let servicos = escala_service::alocacoes_periodo(
+        db_pool,
+        organizacao_id,
+        &hoje.to_string(),
+        &(hoje + ChronoDuration::days(DIAS_HORIZONTE)).to_string(),
+        Some(&user_id),
+        false,
+    )
+    .await?;
+    let mut conhecidos = eventos_conhecidos(db_pool, &user_id).await?;
+    let mut cliente = Cliente { config, db_pool, ligacao };
+    let (mut gravados, mut apagados) = (0, 0);
+
+    for alocacao in &servicos {
+        let assinatura = assinatura(alocacao);
+        let existente = conhecidos.remove(&alocacao.id);
+        if existente.as_ref().is_some_and(|e| e.assinatura == assinatura) {
+            continue;
+        }
+        let event_id = cliente
+            .gravar_evento(existente.as_ref().map(|e| e.event_id.as_str()), &corpo_evento(alocacao))
+            .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO google_calendar_eventos (user_id, alocacao_id, event_id, data, assinatura)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(user_id, alocacao_id) DO UPDATE SET
+                event_id = excluded.event_id, data = excluded.data, assinatura = excluded.assinatura
+            "#,
+        )
+        .bind(&user_id)
+        .bind(&alocacao.id)
+        .bind(&event_id)
+        .bind(&alocacao.data)
+        .bind(&assinatura)
+        .execute(db_pool)
+        .await?;
+        gravados += 1;
+    }
+
+    // O que sobra deixou de ser serviço do utilizador (troca, errata, nova geração)
+    let hoje = hoje.to_string();
+    for (alocacao_id, evento) in conhecidos {
+        if evento.data >= hoje {
+            cliente.apagar_evento(&evento.event_id).await?;
+            apagados += 1;
+        }
+        sqlx::query("DELETE FROM google_calendar_eventos WHERE user_id = ?1 AND alocacao_id = ?2")
+            .bind(&user_id)
+            .bind(&alocacao_id)
+            .execute(db_pool)
+            .await?;
+    }
+
+    if gravados + apagados > 0 {
+        tracing::info!("📅 Google Calendar de {}: {} evento(s) gravado(s), {} apagado(s).", user_id, gravados, apagados);
+    }
+    Ok((gravados, apagados))
+}
+
+/// Sincroniza todas as contas ligadas (os erros de uma não impedem as outras).
+async fn sincronizar_todos(db_pool: &SqlitePool) {
+    let users: Vec<String> = match sqlx::query_scalar("SELECT user_id FROM google_calendar_ligacoes").fetch_all(db_pool).await {
+        Ok(users) => users,
+        Err(e) => {
+            tracing::error!("Google Calendar: falha ao obter as contas ligadas: {:?}", e);
+            return;
+        }
+    };
+    for user_id in users {
+        if let Err(e) = sincronizar_user(db_pool, &user_id).await {
+            tracing::warn!("Google Calendar: falha ao sincronizar {}: {:?}", user_id, e);
+        }
+    }
+}
+
+/// Inicia a tarefa de sincronização: corre no arranque, a cada GOOGLE_CALENDAR_SYNC_MINUTES e
+/// depois de cada alteração à escala ou troca. Não faz nada se a integração não estiver ativa.
+pub fn iniciar_sincronizacao(db_pool: SqlitePool) {
+    let Some(config) = config() else { return };
+    let mut eventos = evento_service::subscrever();
+    tokio::spawn(async move {
+        loop {
+            sincronizar_todos(&db_pool).await;
+            let alteracao = async {
+                loop {
+                    match eventos.recv().await {
+                        Ok(evento) if evento.tipo == evento_service::EVENTO_ESCALA || evento.tipo == evento_service::EVENTO_TROCA => return,
+                        Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                        // O canal nunca fecha (o emissor é estático); por segurança, passa a ser só periódico
+                        Err(RecvError::Closed) => std::future::pending::<()>().await,
+                    }
+                }
+            };
+            let alterou = tokio::select! {
+                _ = tokio::time::sleep(config.intervalo) => false,
+                _ = alteracao => true,
+            };
+            if alterou {
+                tokio::time::sleep(ESPERA_ALTERACOES).await;
+                // As alterações entretanto recebidas ficam cobertas pela próxima sincronização
+                eventos = eventos.resubscribe();
+            }
+        }
+    });
+}
@@ -0,0 +1,198 @@
+// src/services/grupo_service.rs
+use crate::{
+    error::{AppError, AppResult},
+    models::grupo::{Grupo, GrupoMembro, PostoGrupo, TIPOS_GRUPO},
+};
+use sqlx::SqlitePool;
+
+/// Lista os grupos da organização com o respetivo número de membros.
+pub async fn listar_grupos(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<Grupo>> {
+    let grupos = sqlx::query_as::<_, Grupo>(
+        r#"
+        SELECT g.id, g.nome, g.tipo, g.descricao, COUNT(gm.user_id) AS membros
+        FROM grupos g
+        LEFT JOIN grupo_membros gm ON gm.grupo_id = g.id
+        WHERE g.organizacao_id = ?1
+        GROUP BY g.id
+        ORDER BY g.tipo, g.nome
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(grupos)
+}
+
+/// Busca um grupo da organização pelo ID (None se não existir ou for de outra organização).
+pub async fn find_grupo(db_pool: &SqlitePool, organizacao_id: i64, grupo_id: i64) -> AppResult<Option<Grupo>> {
+    let grupo = sqlx::query_as::<_, Grupo>(
+        r#"
+        SELECT g.id, g.nome, g.tipo, g.descricao, COUNT(gm.user_id) AS membros
+        FROM grupos g
+        LEFT JOIN grupo_membros gm ON gm.grupo_id = g.id
+        WHERE g.id = ?1 AND g.organizacao_id = ?2
+        GROUP BY g.id
+        "#,
+    )
+    .bind(grupo_id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(grupo)
+}
+
+/// Cria um grupo na organização e devolve o seu ID.
+pub async fn criar_grupo(db_pool: &SqlitePool, organizacao_id: i64, nome: &str, tipo: &str, descricao: &str) -> AppResult<i64> {
+    let nome = nome.trim();
+    if nome.is_empty() {
+        return Err(AppError::validation("nome", "Indique o nome do grupo."));
+    }
+    if !TIPOS_GRUPO.contains(&tipo) {
+        return Err(AppError::validation("tipo", "Tipo de grupo inválido."));
+    }
+
+    let result = sqlx::query("INSERT INTO grupos (organizacao_id, nome, tipo, descricao) VALUES (?1, ?2, ?3, ?4)")
+        .bind(organizacao_id)
+        .bind(nome)
+        .bind(tipo)
+        .bind(descricao.trim())
+        .execute(db_pool)
+        .await;
+
+    match result {
+        Ok(r) => {
+            tracing::info!("✅ Grupo '{}' ({}) criado.", nome, tipo);
+            Ok(r.last_insert_rowid())
+        }
+        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+            Err(AppError::Conflict(format!("Já existe um grupo com o nome '{}'.", nome)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Apaga um grupo da organização (membros e restrições de postos são libertados).
+pub async fn apagar_grupo(db_pool: &SqlitePool, organizacao_id: i64, grupo_id: i64) -> AppResult<String> {
+    if find_grupo(db_pool, organizacao_id, grupo_id).await?.is_none() {
+        return Err(AppError::NotFound("Grupo não encontrado.".into()));
+    }
+    let mut tx = db_pool.begin().await?;
+    sqlx::query("UPDATE postos SET grupo_id = NULL WHERE grupo_id = ?1")
+        .bind(grupo_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM grupo_membros WHERE grupo_id = ?1")
+        .bind(grupo_id)
+        .execute(&mut *tx)
+        .await?;
+    let nome = sqlx::query_scalar::<_, String>("DELETE FROM grupos WHERE id = ?1 RETURNING nome")
+        .bind(grupo_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Grupo não encontrado.".into()))?;
+    tx.commit().await?;
+
+    tracing::info!("🗑️ Grupo '{}' apagado.", nome);
+    Ok(nome)
+}
+
+/// Lista os membros de um grupo.
+pub async fn listar_membros(db_pool: &SqlitePool, grupo_id: i64) -> AppResult<Vec<GrupoMembro>> {
+    let membros = sqlx::query_as::<_, GrupoMembro>(
+        r#"
+        SELECT u.id, u.name, u.turma, u.ano
+        FROM grupo_membros gm
+        JOIN users u ON u.id = gm.user_id
+        WHERE gm.grupo_id = ?1
+        ORDER BY u.id
+        "#,
+    )
+    .bind(grupo_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(membros)
+}
+
+/// IDs dos membros de um grupo (para ações em lote).
+pub async fn ids_membros(db_pool: &SqlitePool, grupo_id: i64) -> AppResult<Vec<String>> {
+    let ids = sqlx::query_scalar::<_, String>("SELECT user_id FROM grupo_membros WHERE grupo_id = ?1 ORDER BY user_id")
+        .bind(grupo_id)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(ids)
+}
+
+/// Adiciona vários utilizadores da organização a um grupo numa transação.
+/// Devolve (número de membros novos, IDs que não correspondem a nenhum utilizador da organização).
+pub async fn adicionar_membros(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    grupo_id: i64,
+    user_ids: &[String],
+) -> AppResult<(u64, Vec<String>)> {
+    let mut tx = db_pool.begin().await?;
+    let mut adicionados = 0;
+    let mut desconhecidos = Vec::new();
+
+    for user_id in user_ids {
+        let existe: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2)")
+            .bind(user_id)
+            .bind(organizacao_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if !existe {
+            desconhecidos.push(user_id.clone());
+            continue;
+        }
+        adicionados += sqlx::query("INSERT OR IGNORE INTO grupo_membros (grupo_id, user_id) VALUES (?1, ?2)")
+            .bind(grupo_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    }
+
+    tx.commit().await?;
+    Ok((adicionados, desconhecidos))
+}
+
+/// Remove um utilizador de um grupo.
+pub async fn remover_membro(db_pool: &SqlitePool, grupo_id: i64, user_id: &str) -> AppResult<()> {
+    let result = sqlx::query("DELETE FROM grupo_membros WHERE grupo_id = ?1 AND user_id = ?2")
+        .bind(grupo_id)
+        .bind(user_id)
+        .execute(db_pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("'{}' não é membro deste grupo.", user_id)));
+    }
+    Ok(())
+}
+
+/// Lista os postos da escala da organização com a restrição de grupo atual.
+pub async fn listar_postos(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<PostoGrupo>> {
+    let postos = sqlx::query_as::<_, PostoGrupo>("SELECT id, nome, grupo_id FROM postos WHERE organizacao_id = ?1 ORDER BY nome")
+        .bind(organizacao_id)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(postos)
+}
+
+/// Restringe (ou liberta, com `None`) um posto da escala da organização a um grupo (da mesma organização).
+pub async fn definir_grupo_posto(db_pool: &SqlitePool, organizacao_id: i64, posto_id: i64, grupo_id: Option<i64>) -> AppResult<()> {
+    if let Some(grupo_id) = grupo_id {
+        if find_grupo(db_pool, organizacao_id, grupo_id).await?.is_none() {
+            return Err(AppError::NotFound("Grupo não encontrado.".into()));
+        }
+    }
+    let result = sqlx::query("UPDATE postos SET grupo_id = ?1 WHERE id = ?2 AND organizacao_id = ?3")
+        .bind(grupo_id)
+        .bind(posto_id)
+        .bind(organizacao_id)
+        .execute(db_pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Posto não encontrado.".into()));
+    }
+    Ok(())
+}
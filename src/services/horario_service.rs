@@ -0,0 +1,201 @@
+// src/services/horario_service.rs
+//! Horário das aulas: o plano semanal de cada turma (dia da semana, hora, disciplina, sala e instrutor).
+//! Todos o consultam em /horario e no painel (a turma do próprio); gerir exige a permissão "horario".
+//! Os postos marcados como diurnos coincidem com as aulas: a geração da escala não os atribui a quem
+//! tem uma aula obrigatória nesse dia da semana (ver `escala_service`).
+
+use crate::{
+    error::{AppError, AppResult},
+    i18n,
+    models::horario::{Aula, DadosAula, PostoDiurno},
+};
+use chrono::{Datelike, NaiveDate};
+use sqlx::SqlitePool;
+
+/// Dias da semana das aulas (1 = segunda ... 7 = domingo).
+pub const DIAS: [i64; 7] = [1, 2, 3, 4, 5, 6, 7];
+
+/// Dia da semana de uma data (1 = segunda ... 7 = domingo).
+pub fn dia_semana(data: NaiveDate) -> i64 {
+    i64::from(data.weekday().number_from_monday())
+}
+
+/// Nome de um dia da semana (ex: "Segunda"), no idioma do pedido.
+pub fn descrever_dia(dia: i64) -> String {
+    i18n::t(&format!("datas.dia_{}", dia))
+}
+
+/// Turmas com utilizadores ativos na organização.
+pub async fn turmas(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<String>> {
+    let turmas = sqlx::query_scalar("SELECT DISTINCT turma FROM users WHERE ativo = 1 AND organizacao_id = ?1 ORDER BY turma")
+        .bind(organizacao_id)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(turmas)
+}
+
+/// Aulas de uma turma, por dia da semana e hora. `dia` = só as desse dia da semana.
+pub async fn listar(db_pool: &SqlitePool, organizacao_id: i64, turma: &str, dia: Option<i64>) -> AppResult<Vec<Aula>> {
+    let aulas = sqlx::query_as::<_, Aula>(
+        r#"
+        SELECT id, turma, dia_semana, hora_inicio, hora_fim, disciplina, sala, instrutor, obrigatoria
+        FROM aulas
+        WHERE organizacao_id = ?1 AND turma = ?2 AND (?3 IS NULL OR dia_semana = ?3)
+        ORDER BY dia_semana, hora_inicio, id
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(turma)
+    .bind(dia)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(aulas)
+}
+
+/// Uma aula pelo id (da organização).
+pub async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Aula> {
+    sqlx::query_as::<_, Aula>(
+        r#"
+        SELECT id, turma, dia_semana, hora_inicio, hora_fim, disciplina, sala, instrutor, obrigatoria
+        FROM aulas
+        WHERE id = ?1 AND organizacao_id = ?2
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Aula {} não encontrada.", id)))
+}
+
+/// Turma conhecida, dia e horas coerentes e sem sobreposição com outra aula da turma (o resto já foi validado pelo
+/// handler). `id` = a aula alterada (não conta como sobreposição).
+async fn validar(db_pool: &SqlitePool, organizacao_id: i64, dados: &DadosAula, id: Option<i64>) -> AppResult<()> {
+    if !turmas(db_pool, organizacao_id).await?.contains(&dados.turma) {
+        return Err(AppError::validation("turma", format!("A turma '{}' não tem utilizadores ativos.", dados.turma)));
+    }
+    if !DIAS.contains(&dados.dia_semana) {
+        return Err(AppError::validation("dia_semana", "Dia da semana inválido."));
+    }
+    if dados.hora_fim <= dados.hora_inicio {
+        return Err(AppError::validation("hora_fim", "A hora de fim deve ser posterior à de início."));
+    }
+    let sobreposta: Option<String> = sqlx::query_scalar(
+        r#"
+        SELECT disciplina || ' (' || hora_inicio || '–' || hora_fim || ')'
+        FROM aulas
+        WHERE organizacao_id = ?1 AND turma = ?2 AND dia_semana = ?3
+          AND hora_inicio < ?5 AND hora_fim > ?4
+          AND (?6 IS NULL OR id != ?6)
+        ORDER BY hora_inicio
+        LIMIT 1
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(&dados.turma)
+    .bind(dados.dia_semana)
+    .bind(&dados.hora_inicio)
+    .bind(&dados.hora_fim)
+    .bind(id)
+    .fetch_optional(db_pool)
+    .await?;
+    if let Some(aula) = sobreposta {
+        return Err(AppError::validation("hora_inicio", format!("Sobrepõe-se a {} da turma {}.", aula, dados.turma)));
+    }
+    Ok(())
+}
+
+/// Cria uma aula. Devolve o ID.
+pub async fn criar(db_pool: &SqlitePool, organizacao_id: i64, dados: &DadosAula, operador_id: &str) -> AppResult<i64> {
+    validar(db_pool, organizacao_id, dados, None).await?;
+    let id = sqlx::query(
+        r#"
+        INSERT INTO aulas
+            (organizacao_id, turma, dia_semana, hora_inicio, hora_fim, disciplina, sala, instrutor, obrigatoria, criado_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(&dados.turma)
+    .bind(dados.dia_semana)
+    .bind(&dados.hora_inicio)
+    .bind(&dados.hora_fim)
+    .bind(dados.disciplina.trim())
+    .bind(dados.sala.trim())
+    .bind(dados.instrutor.trim())
+    .bind(dados.obrigatoria)
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+    tracing::info!("🏫 Aula {} ({} da turma {}, {} {}) criada por {}.", id, dados.disciplina.trim(), dados.turma, descrever_dia(dados.dia_semana), dados.hora_inicio, operador_id);
+    Ok(id)
+}
+
+/// Altera uma aula. Devolve a aula como estava.
+pub async fn atualizar(db_pool: &SqlitePool, organizacao_id: i64, id: i64, dados: &DadosAula) -> AppResult<Aula> {
+    let anterior = obter(db_pool, organizacao_id, id).await?;
+    validar(db_pool, organizacao_id, dados, Some(id)).await?;
+    sqlx::query(
+        r#"
+        UPDATE aulas
+        SET turma = ?2, dia_semana = ?3, hora_inicio = ?4, hora_fim = ?5, disciplina = ?6, sala = ?7,
+            instrutor = ?8, obrigatoria = ?9
+        WHERE id = ?1
+        "#,
+    )
+    .bind(id)
+    .bind(&dados.turma)
+    .bind(dados.dia_semana)
+    .bind(&dados.hora_inicio)
+    .bind(&dados.hora_fim)
+    .bind(dados.disciplina.trim())
+    .bind(dados.sala.trim())
+    .bind(dados.instrutor.trim())
+    .bind(dados.obrigatoria)
+    .execute(db_pool)
+    .await?;
+    tracing::info!("🏫 Aula {} ({} da turma {}) alterada.", id, dados.disciplina.trim(), dados.turma);
+    Ok(anterior)
+}
+
+/// Apaga uma aula. Devolve a aula apagada.
+pub async fn remover(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Aula> {
+    let aula = obter(db_pool, organizacao_id, id).await?;
+    sqlx::query("DELETE FROM aulas WHERE id = ?1")
+        .bind(id)
+        .execute(db_pool)
+        .await?;
+    tracing::info!("🗑️ Aula {} ({} da turma {}) apagada.", id, aula.disciplina, aula.turma);
+    Ok(aula)
+}
+
+/// Postos da escala da organização e se são serviços de dia.
+pub async fn postos(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<PostoDiurno>> {
+    let postos = sqlx::query_as::<_, PostoDiurno>("SELECT id, nome, diurno FROM postos WHERE organizacao_id = ?1 ORDER BY nome")
+        .bind(organizacao_id)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(postos)
+}
+
+/// Marca como diurnos exatamente os postos `diurnos` da organização (os outros deixam de o ser).
+/// Devolve os nomes dos postos diurnos.
+pub async fn definir_diurnos(db_pool: &SqlitePool, organizacao_id: i64, diurnos: &[i64]) -> AppResult<Vec<String>> {
+    let postos = postos(db_pool, organizacao_id).await?;
+    if let Some(id) = diurnos.iter().find(|id| !postos.iter().any(|p| p.id == **id)) {
+        return Err(AppError::NotFound(format!("Posto {} não encontrado.", id)));
+    }
+    let mut tx = db_pool.begin().await?;
+    for posto in &postos {
+        sqlx::query("UPDATE postos SET diurno = ?2 WHERE id = ?1")
+            .bind(posto.id)
+            .bind(diurnos.contains(&posto.id))
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    let nomes: Vec<String> = postos.into_iter().filter(|p| diurnos.contains(&p.id)).map(|p| p.nome).collect();
+    tracing::info!("🏫 Postos diurnos da organização {}: {:?}.", organizacao_id, nomes);
+    Ok(nomes)
+}
@@ -0,0 +1,97 @@
+// src/services/indisponibilidade_service.rs
+//! Indisponibilidades (dispensas, licenças): períodos em que o utilizador não é escalado
+//! (ver `escala_service::gerar_escala_diaria`). As baixas médicas têm registo próprio (`baixa_service`).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::escala::Indisponibilidade,
+};
+use sqlx::SqlitePool;
+
+/// Indisponibilidades de um utilizador (ou de todos os da organização, com `user_id = None`)
+/// que terminam a partir de `desde`.
+pub async fn listar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: Option<&str>,
+    desde: &str,
+) -> AppResult<Vec<Indisponibilidade>> {
+    let indisponibilidades = sqlx::query_as::<_, Indisponibilidade>(
+        r#"
+        SELECT i.id, i.user_id, i.data_inicio, i.data_fim, i.motivo
+        FROM indisponibilidades i
+        JOIN users u ON u.id = i.user_id
+        WHERE u.organizacao_id = ?3 AND (?1 IS NULL OR i.user_id = ?1) AND i.data_fim >= ?2
+        ORDER BY i.data_inicio, i.user_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(desde)
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(indisponibilidades)
+}
+
+/// Uma indisponibilidade pelo id (de um utilizador da organização).
+pub async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Indisponibilidade> {
+    sqlx::query_as::<_, Indisponibilidade>(
+        r#"
+        SELECT i.id, i.user_id, i.data_inicio, i.data_fim, i.motivo
+        FROM indisponibilidades i
+        JOIN users u ON u.id = i.user_id
+        WHERE i.id = ?1 AND u.organizacao_id = ?2
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Indisponibilidade {} não encontrada.", id)))
+}
+
+/// Regista uma indisponibilidade (datas já validadas, 'YYYY-MM-DD'). Devolve o id criado.
+pub async fn criar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: &str,
+    data_inicio: &str,
+    data_fim: &str,
+    motivo: Option<&str>,
+) -> AppResult<i64> {
+    let existe: Option<String> = sqlx::query_scalar("SELECT id FROM users WHERE id = ?1 AND organizacao_id = ?2")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_optional(db_pool)
+        .await?;
+    if existe.is_none() {
+        return Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+
+    let id = sqlx::query(
+        "INSERT INTO indisponibilidades (user_id, data_inicio, data_fim, motivo) VALUES (?1, ?2, ?3, ?4)",
+    )
+    .bind(user_id)
+    .bind(data_inicio)
+    .bind(data_fim)
+    .bind(motivo.map(str::trim).filter(|m| !m.is_empty()))
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+
+    tracing::info!("📋 Indisponibilidade {} de {} ({} a {}) registada.", id, user_id, data_inicio, data_fim);
+    Ok(id)
+}
+
+/// Apaga uma indisponibilidade (a organização é verificada antes, com `obter`).
+pub async fn remover(db_pool: &SqlitePool, id: i64) -> AppResult<()> {
+    let result = sqlx::query("DELETE FROM indisponibilidades WHERE id = ?1")
+        .bind(id)
+        .execute(db_pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Indisponibilidade {} não encontrada.", id)));
+    }
+    tracing::info!("🗑️ Indisponibilidade {} apagada.", id);
+    Ok(())
+}
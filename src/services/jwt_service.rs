@@ -0,0 +1,246 @@
+// src/services/jwt_service.rs
+//! Autenticação JWT da aplicação móvel (/api/auth), alternativa às sessões por cookie:
+//! - access token: JWT (HS256) de curta duração, enviado como `Authorization: Bearer` em /api/*;
+//!   não fica guardado, por isso só deixa de valer quando expira.
+//! - refresh token: aleatório, guardado como SHA-256 na tabela `refresh_tokens`, revogável; cada
+//!   renovação roda-o (o antigo fica revogado). Reutilizar um token já rodado revoga a família inteira.
+
+use crate::error::{AppError, AppResult};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// Emissor (`iss`) dos tokens: um JWT de outro sistema com o mesmo segredo não é aceite.
+const EMISSOR: &str = "mercal2";
+/// Prefixo dos refresh tokens (distinto do dos tokens de API, `mercal_`).
+const PREFIXO_REFRESH: &str = "mercalr_";
+/// Comprimento mínimo de JWT_SECRET (256 bits para o HS256).
+const MIN_SEGREDO: usize = 32;
+const ACESSO_MINUTOS_PADRAO: i64 = 15;
+const REFRESH_DIAS_PADRAO: i64 = 30;
+/// Refresh tokens expirados/revogados são apagados passado este tempo (ficam para detetar reutilizações).
+const RETENCAO_DIAS: i64 = 7;
+
+/// Configuração do JWT (lida do ambiente no arranque).
+pub struct JwtConfig {
+    codificar: EncodingKey,
+    descodificar: DecodingKey,
+    pub acesso_minutos: i64,
+    pub refresh_dias: i64,
+}
+
+impl JwtConfig {
+    /// Lê JWT_SECRET (pelo menos 32 bytes; sem ela, a autenticação JWT fica desativada),
+    /// JWT_ACCESS_MINUTES (15) e JWT_REFRESH_DAYS (30).
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let var = |nome: &str| std::env::var(nome).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let Some(segredo) = var("JWT_SECRET") else {
+            return Ok(None);
+        };
+        if segredo.len() < MIN_SEGREDO {
+            return Err(format!("JWT_SECRET tem de ter pelo menos {} bytes (ex: `openssl rand -hex 32`)", MIN_SEGREDO));
+        }
+        let numero = |nome: &str, padrao: i64| match var(nome) {
+            Some(v) => v.parse::<i64>().ok().filter(|n| *n >= 1).ok_or(format!("{} inválido: '{}'", nome, v)),
+            None => Ok(padrao),
+        };
+        Ok(Some(Self {
+            codificar: EncodingKey::from_secret(segredo.as_bytes()),
+            descodificar: DecodingKey::from_secret(segredo.as_bytes()),
+            acesso_minutos: numero("JWT_ACCESS_MINUTES", ACESSO_MINUTOS_PADRAO)?,
+            refresh_dias: numero("JWT_REFRESH_DAYS", REFRESH_DIAS_PADRAO)?,
+        }))
+    }
+}
+
+static CONFIG: OnceLock<JwtConfig> = OnceLock::new();
+
+/// Define a configuração do JWT (chamado uma vez, no arranque, só se estiver ativo).
+pub fn configurar(config: JwtConfig) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("Configuração do JWT já definida; ignorada.");
+    }
+}
+
+/// Configuração do JWT, se estiver ativo.
+pub fn config() -> Option<&'static JwtConfig> {
+    CONFIG.get()
+}
+
+/// Configuração ativa, ou o erro devolvido por /api/auth quando o JWT está desativado.
+pub fn config_ativa() -> AppResult<&'static JwtConfig> {
+    config().ok_or_else(|| AppError::NotFound("A autenticação por JWT não está ativa (JWT_SECRET não definida).".to_string()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String, // ID do utilizador
+    iss: String,
+    iat: i64,
+    exp: i64,
+}
+
+/// Par de tokens devolvido no login e em cada renovação.
+#[derive(Debug)]
+pub struct Tokens {
+    pub access_token: String,
+    pub expira_em_segundos: i64,
+    pub refresh_token: String,
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Assina um access token para o utilizador.
+fn assinar(config: &JwtConfig, user_id: &str) -> AppResult<String> {
+    let agora = chrono::Utc::now().timestamp();
+    let claims = Claims {
+        sub: user_id.to_string(),
+        iss: EMISSOR.to_string(),
+        iat: agora,
+        exp: agora + config.acesso_minutos * 60,
+    };
+    jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &config.codificar).map_err(|e| {
+        tracing::error!("Erro ao assinar JWT: {}", e);
+        AppError::InternalServerError
+    })
+}
+
+/// Cria um refresh token na família e o access token que o acompanha.
+async fn emitir_na_familia(
+    conn: &mut SqliteConnection,
+    config: &JwtConfig,
+    user_id: &str,
+    familia: &str,
+    dispositivo: Option<&str>,
+) -> AppResult<Tokens> {
+    let refresh_token = format!("{}{}{}", PREFIXO_REFRESH, Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    sqlx::query(
+        r#"
+        INSERT INTO refresh_tokens (user_id, token_hash, familia, dispositivo, expira_em)
+        VALUES (?1, ?2, ?3, ?4, datetime('now', ?5))
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&refresh_token))
+    .bind(familia)
+    .bind(dispositivo)
+    .bind(format!("+{} days", config.refresh_dias))
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Tokens {
+        access_token: assinar(config, user_id)?,
+        expira_em_segundos: config.acesso_minutos * 60,
+        refresh_token,
+    })
+}
+
+/// Emite os tokens de um login (a identidade já foi verificada). Começa uma família nova.
+pub async fn emitir(db_pool: &SqlitePool, user_id: &str, dispositivo: Option<&str>) -> AppResult<Tokens> {
+    let config = config_ativa()?;
+    // Limpeza oportunista dos tokens que já não servem para nada
+    sqlx::query(
+        r#"
+        DELETE FROM refresh_tokens
+        WHERE COALESCE(revogado_em, expira_em) < datetime('now', ?1)
+        "#,
+    )
+    .bind(format!("-{} days", RETENCAO_DIAS))
+    .execute(db_pool)
+    .await?;
+
+    let mut conn = db_pool.acquire().await?;
+    let tokens = emitir_na_familia(&mut conn, config, user_id, &Uuid::new_v4().to_string(), dispositivo).await?;
+    tracing::info!("📱 Tokens JWT emitidos para {}.", user_id);
+    Ok(tokens)
+}
+
+/// Troca um refresh token válido por um par novo (o usado fica revogado). Devolve o dono e os tokens.
+/// Um token já revogado revoga a família toda: quem o apresenta pode tê-lo roubado.
+pub async fn renovar(db_pool: &SqlitePool, refresh_token: &str) -> AppResult<(String, Tokens)> {
+    let config = config_ativa()?;
+    if !refresh_token.starts_with(PREFIXO_REFRESH) {
+        return Err(AppError::Unauthenticated);
+    }
+    let mut tx = db_pool.begin().await?;
+    let registo: Option<(i64, String, String, Option<String>, bool, bool)> = sqlx::query_as(
+        r#"
+        SELECT r.id, r.user_id, r.familia, r.dispositivo,
+               r.revogado_em IS NOT NULL, r.expira_em < datetime('now') OR u.ativo = 0
+        FROM refresh_tokens r
+        JOIN users u ON u.id = r.user_id
+        WHERE r.token_hash = ?1
+        "#,
+    )
+    .bind(hash_token(refresh_token))
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((id, user_id, familia, dispositivo, revogado, invalido)) = registo else {
+        return Err(AppError::Unauthenticated);
+    };
+    if revogado {
+        tracing::warn!("🚨 Refresh token revogado reutilizado ({}): a revogar a família {}.", user_id, familia);
+        sqlx::query("UPDATE refresh_tokens SET revogado_em = datetime('now') WHERE familia = ?1 AND revogado_em IS NULL")
+            .bind(&familia)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        return Err(AppError::Unauthenticated);
+    }
+    if invalido {
+        return Err(AppError::Unauthenticated);
+    }
+
+    sqlx::query("UPDATE refresh_tokens SET revogado_em = datetime('now') WHERE id = ?1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    let tokens = emitir_na_familia(&mut tx, config, &user_id, &familia, dispositivo.as_deref()).await?;
+    tx.commit().await?;
+    tracing::debug!("📱 Tokens JWT de {} renovados.", user_id);
+    Ok((user_id, tokens))
+}
+
+/// Logout do dispositivo: revoga a família do refresh token. Devolve false se o token não existir.
+pub async fn revogar(db_pool: &SqlitePool, refresh_token: &str) -> AppResult<bool> {
+    let revogados = sqlx::query(
+        r#"
+        UPDATE refresh_tokens SET revogado_em = datetime('now')
+        WHERE revogado_em IS NULL
+          AND familia = (SELECT familia FROM refresh_tokens WHERE token_hash = ?1)
+        "#,
+    )
+    .bind(hash_token(refresh_token))
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    Ok(revogados > 0)
+}
+
+/// Identifica o dono de um access token (JWT). None se estiver desativado, inválido, expirado
+/// ou se a conta tiver sido arquivada entretanto.
+pub async fn autenticar(db_pool: &SqlitePool, token: &str) -> AppResult<Option<String>> {
+    let Some(config) = config() else {
+        return Ok(None);
+    };
+    let mut validacao = Validation::new(Algorithm::HS256);
+    validacao.set_issuer(&[EMISSOR]);
+    let claims = match jsonwebtoken::decode::<Claims>(token, &config.descodificar, &validacao) {
+        Ok(dados) => dados.claims,
+        Err(e) => {
+            tracing::debug!("JWT recusado: {}", e);
+            return Ok(None);
+        }
+    };
+    let ativo: Option<bool> = sqlx::query_scalar("SELECT ativo FROM users WHERE id = ?1")
+        .bind(&claims.sub)
+        .fetch_optional(db_pool)
+        .await?;
+    Ok(ativo.unwrap_or(false).then_some(claims.sub))
+}
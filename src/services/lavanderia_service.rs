@@ -0,0 +1,184 @@
+// src/services/lavanderia_service.rs
+//! Lavanderia: os sacos entregues por cada utilizador, identificados pelo número da etiqueta. A entrega
+//! guarda o operador que recebeu o saco; quando está lavado, passa a pronto e o utilizador é avisado no
+//! centro de notificações para o vir retirar; a retirada guarda quem o devolveu. Uma etiqueta só pode
+//! estar num saco por retirar. O relatório lista os sacos prontos há N dias ou mais que ninguém retirou.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::lavanderia::SacoLavanderia,
+    services::notificacao_service,
+};
+use sqlx::SqlitePool;
+
+/// Dias propostos no relatório dos sacos por retirar.
+pub const DIAS_RELATORIO: i64 = 7;
+/// Dias do relatório, no máximo.
+pub const MAX_DIAS_RELATORIO: i64 = 365;
+/// Sacos mostrados no histórico.
+pub const HISTORICO_RECENTE: i64 = 100;
+
+/// Sacos da organização. `?2` = só os deste utilizador; `?3` = só os por retirar (1) ou todos (0);
+/// `?4` = só este saco.
+const SQL_SACOS: &str = r#"
+    SELECT s.id, s.user_id, u.name, u.turma, s.etiqueta, s.observacoes, s.entregue_em,
+           COALESCE(r.name, s.recebido_por) AS recebido_por, s.pronto_em, COALESCE(p.name, s.pronto_por) AS pronto_por,
+           s.retirado_em, COALESCE(e.name, s.entregue_por) AS entregue_por
+    FROM lavanderia_sacos s
+    JOIN users u ON u.id = s.user_id
+    LEFT JOIN users r ON r.id = s.recebido_por
+    LEFT JOIN users p ON p.id = s.pronto_por
+    LEFT JOIN users e ON e.id = s.entregue_por
+    WHERE s.organizacao_id = ?1
+      AND (?2 IS NULL OR s.user_id = ?2)
+      AND (?3 = 0 OR s.retirado_em IS NULL)
+      AND (?4 IS NULL OR s.id = ?4)
+    ORDER BY s.entregue_em DESC, s.id DESC
+    LIMIT ?5
+"#;
+
+/// Um saco pelo id (da organização).
+async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<SacoLavanderia> {
+    sqlx::query_as::<_, SacoLavanderia>(SQL_SACOS)
+        .bind(organizacao_id)
+        .bind(None::<String>)
+        .bind(false)
+        .bind(id)
+        .bind(1)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Saco {} não encontrado.", id)))
+}
+
+/// Sacos por retirar, os entregues há mais tempo primeiro.
+pub async fn abertos(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<SacoLavanderia>> {
+    let mut sacos = sqlx::query_as::<_, SacoLavanderia>(SQL_SACOS)
+        .bind(organizacao_id)
+        .bind(None::<String>)
+        .bind(true)
+        .bind(None::<i64>)
+        .bind(i64::MAX)
+        .fetch_all(db_pool)
+        .await?;
+    sacos.reverse();
+    Ok(sacos)
+}
+
+/// Últimos sacos (de um utilizador, se indicado), os mais recentes primeiro.
+pub async fn historico(db_pool: &SqlitePool, organizacao_id: i64, user_id: Option<&str>, limite: i64) -> AppResult<Vec<SacoLavanderia>> {
+    let sacos = sqlx::query_as::<_, SacoLavanderia>(SQL_SACOS)
+        .bind(organizacao_id)
+        .bind(user_id)
+        .bind(false)
+        .bind(None::<i64>)
+        .bind(limite)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(sacos)
+}
+
+/// Sacos prontos há `dias` dias ou mais e ainda por retirar, os prontos há mais tempo primeiro.
+pub async fn por_retirar(db_pool: &SqlitePool, organizacao_id: i64, dias: i64) -> AppResult<Vec<SacoLavanderia>> {
+    let limite: String = sqlx::query_scalar("SELECT datetime('now', '-' || ?1 || ' days')")
+        .bind(dias)
+        .fetch_one(db_pool)
+        .await?;
+    let mut sacos: Vec<SacoLavanderia> = abertos(db_pool, organizacao_id)
+        .await?
+        .into_iter()
+        .filter(|s| s.pronto_em.as_deref().is_some_and(|p| p <= limite.as_str()))
+        .collect();
+    sacos.sort_by(|a, b| a.pronto_em.cmp(&b.pronto_em));
+    Ok(sacos)
+}
+
+/// Regista a entrega de um saco por um utilizador. Devolve o ID.
+pub async fn receber(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: &str,
+    etiqueta: &str,
+    observacoes: &str,
+    operador_id: &str,
+) -> AppResult<i64> {
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(db_pool)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+
+    // O índice único das etiquetas por retirar decide entre duas entregas ao mesmo tempo
+    let resultado = sqlx::query(
+        "INSERT INTO lavanderia_sacos (organizacao_id, user_id, etiqueta, observacoes, recebido_por) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(etiqueta.trim())
+    .bind(observacoes.trim())
+    .bind(operador_id)
+    .execute(db_pool)
+    .await;
+    let id = match resultado {
+        Ok(r) => r.last_insert_rowid(),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(AppError::validation(
+                "etiqueta",
+                format!("A etiqueta {} está num saco por retirar: registe primeiro a retirada.", etiqueta.trim()),
+            ));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    tracing::info!("🧺 Saco {} de {} recebido por {}.", etiqueta.trim(), user_id, operador_id);
+    Ok(id)
+}
+
+/// Marca um saco como pronto e avisa o utilizador para o vir retirar. Devolve o saco.
+pub async fn marcar_pronto(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<SacoLavanderia> {
+    // Duas marcações ao mesmo tempo: só a primeira avisa
+    let marcado = sqlx::query(
+        "UPDATE lavanderia_sacos SET pronto_em = datetime('now'), pronto_por = ?3 WHERE id = ?1 AND organizacao_id = ?2 AND pronto_em IS NULL AND retirado_em IS NULL",
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    let saco = obter(db_pool, organizacao_id, id).await?;
+    if marcado == 0 {
+        return Err(AppError::Conflict(format!("O saco {} já estava pronto ou foi retirado.", saco.etiqueta)));
+    }
+    tracing::info!("🧺 Saco {} de {} pronto ({}).", saco.etiqueta, saco.user_id, operador_id);
+    notificacao_service::notificar(
+        db_pool,
+        &saco.user_id,
+        notificacao_service::TIPO_LAVANDERIA,
+        "Roupa pronta para retirar",
+        &format!("O seu saco (etiqueta {}) está pronto para retirar na lavanderia.", saco.etiqueta),
+    )
+    .await;
+    Ok(saco)
+}
+
+/// Regista a retirada de um saco (pronto ou não). Devolve o saco (já fechado).
+pub async fn retirar(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<SacoLavanderia> {
+    // Duas retiradas ao mesmo tempo: só a primeira fecha o saco
+    let fechado = sqlx::query(
+        "UPDATE lavanderia_sacos SET retirado_em = datetime('now'), entregue_por = ?3 WHERE id = ?1 AND organizacao_id = ?2 AND retirado_em IS NULL",
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    let saco = obter(db_pool, organizacao_id, id).await?;
+    if fechado == 0 {
+        return Err(AppError::Conflict(format!("O saco {} já foi retirado.", saco.etiqueta)));
+    }
+    tracing::info!("🧺 Saco {} retirado por {}, entregue por {}.", saco.etiqueta, saco.user_id, operador_id);
+    Ok(saco)
+}
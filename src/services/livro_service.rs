@@ -0,0 +1,241 @@
+// src/services/livro_service.rs
+//! Livro de ocorrências do chefe de dia. Cada ocorrência pertence a um dia do serviço (o mesmo dia da
+//! escala) e fica com a hora da ocorrência e a data/hora do registo. Só se escreve no livro de hoje ou
+//! de ontem (o serviço passa a meia-noite) e enquanto o dia estiver aberto: ao fechá-lo, o chefe de dia
+//! escreve a passagem de serviço e, a partir daí, nem as ocorrências nem a passagem mudam.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::livro::{FechoLivro, Ocorrencia, ResumoDiaLivro},
+    tempo,
+};
+use chrono::{Duration, NaiveDate};
+use sqlx::SqlitePool;
+
+/// Tamanho máximo do texto de uma ocorrência.
+pub const MAX_TEXTO: usize = 2000;
+/// Tamanho máximo da passagem de serviço.
+pub const MAX_PASSAGEM: usize = 4000;
+/// Dias mostrados na lista dos dias recentes.
+pub const DIAS_RECENTES: i64 = 30;
+/// Resultados mostrados numa pesquisa.
+pub const MAX_RESULTADOS: i64 = 200;
+
+/// Se o livro do dia `data` ainda aceita registos (hoje ou ontem), sem contar com o fecho.
+pub fn dia_do_servico(data: NaiveDate) -> bool {
+    let hoje = tempo::hoje();
+    data == hoje || data == hoje - Duration::days(1)
+}
+
+/// Ocorrências da organização, pela ordem do registo. `?2`/`?3` = intervalo de dias (inclusive);
+/// `?4` = só as que contêm este texto.
+const SQL_OCORRENCIAS: &str = r#"
+    SELECT o.id, o.data, o.hora, o.autor_id, COALESCE(u.name, o.autor_id) AS autor, o.texto, o.criado_em, o.corrigido_em
+    FROM livro_ocorrencias o
+    LEFT JOIN users u ON u.id = o.autor_id
+    WHERE o.organizacao_id = ?1 AND o.data BETWEEN ?2 AND ?3
+      AND (?4 IS NULL OR o.texto LIKE '%' || ?4 || '%')
+    ORDER BY o.data, o.criado_em, o.id
+    LIMIT ?5
+"#;
+
+/// Ocorrências de um dia.
+pub async fn ocorrencias_dia(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<Vec<Ocorrencia>> {
+    let ocorrencias = sqlx::query_as::<_, Ocorrencia>(SQL_OCORRENCIAS)
+        .bind(organizacao_id)
+        .bind(data.to_string())
+        .bind(data.to_string())
+        .bind(None::<String>)
+        .bind(i64::MAX)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(ocorrencias)
+}
+
+/// Pesquisa as ocorrências entre `de` e `ate` (inclusive), opcionalmente com um texto.
+pub async fn pesquisar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    de: NaiveDate,
+    ate: NaiveDate,
+    texto: Option<&str>,
+) -> AppResult<Vec<Ocorrencia>> {
+    let ocorrencias = sqlx::query_as::<_, Ocorrencia>(SQL_OCORRENCIAS)
+        .bind(organizacao_id)
+        .bind(de.to_string())
+        .bind(ate.to_string())
+        .bind(texto)
+        .bind(MAX_RESULTADOS)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(ocorrencias)
+}
+
+/// Fecho (passagem de serviço) de um dia, se já foi fechado.
+pub async fn fecho_dia(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<Option<FechoLivro>> {
+    let fecho = sqlx::query_as::<_, FechoLivro>(
+        r#"
+        SELECT COALESCE(p.name, f.fechado_por) AS fechado_por, COALESCE(r.name, f.recebido_por) AS recebido_por,
+               f.passagem, f.fechado_em
+        FROM livro_fechos f
+        LEFT JOIN users p ON p.id = f.fechado_por
+        LEFT JOIN users r ON r.id = f.recebido_por
+        WHERE f.organizacao_id = ?1 AND f.data = ?2
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(data.to_string())
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(fecho)
+}
+
+/// Falha se o livro do dia já não aceita alterações (fora do serviço ou já fechado).
+async fn verificar_aberto(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<()> {
+    if !dia_do_servico(data) {
+        return Err(AppError::Conflict(format!("O livro de {} já não aceita registos (só o de hoje e o de ontem).", data)));
+    }
+    let fechado: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM livro_fechos WHERE organizacao_id = ?1 AND data = ?2)")
+        .bind(organizacao_id)
+        .bind(data.to_string())
+        .fetch_one(db_pool)
+        .await?;
+    if fechado {
+        return Err(AppError::Conflict(format!("O livro de {} já foi fechado.", data)));
+    }
+    Ok(())
+}
+
+/// Regista uma ocorrência no livro do dia `data` (texto e hora já validados). Devolve o ID.
+pub async fn registar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    data: NaiveDate,
+    hora: &str,
+    autor_id: &str,
+    texto: &str,
+) -> AppResult<i64> {
+    verificar_aberto(db_pool, organizacao_id, data).await?;
+    // A condição repete a do fecho: um fecho entretanto registado não deixa entrar a ocorrência
+    let resultado = sqlx::query(
+        r#"
+        INSERT INTO livro_ocorrencias (organizacao_id, data, hora, autor_id, texto)
+        SELECT ?1, ?2, ?3, ?4, ?5
+        WHERE NOT EXISTS (SELECT 1 FROM livro_fechos WHERE organizacao_id = ?1 AND data = ?2)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(data.to_string())
+    .bind(hora)
+    .bind(autor_id)
+    .bind(texto.trim())
+    .execute(db_pool)
+    .await?;
+    if resultado.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!("O livro de {} já foi fechado.", data)));
+    }
+    let id = resultado.last_insert_rowid();
+    tracing::info!("📖 Ocorrência {} registada por {} no livro de {}.", id, autor_id, data);
+    Ok(id)
+}
+
+/// Corrige o texto de uma ocorrência, só pelo autor e enquanto o dia estiver aberto.
+/// Devolve o dia e o texto anterior.
+pub async fn corrigir(db_pool: &SqlitePool, organizacao_id: i64, id: i64, autor_id: &str, texto: &str) -> AppResult<(NaiveDate, String)> {
+    let ocorrencia: Option<(String, String, String)> =
+        sqlx::query_as("SELECT data, autor_id, texto FROM livro_ocorrencias WHERE id = ?1 AND organizacao_id = ?2")
+            .bind(id)
+            .bind(organizacao_id)
+            .fetch_optional(db_pool)
+            .await?;
+    let Some((data, autor, anterior)) = ocorrencia else {
+        return Err(AppError::NotFound(format!("Ocorrência {} não encontrada.", id)));
+    };
+    let data = tempo::ler_data(&data).ok_or_else(|| AppError::NotFound(format!("Ocorrência {} não encontrada.", id)))?;
+    if autor != autor_id {
+        return Err(AppError::Conflict("Só o autor pode corrigir uma ocorrência.".to_string()));
+    }
+    verificar_aberto(db_pool, organizacao_id, data).await?;
+    let corrigida = sqlx::query(
+        r#"
+        UPDATE livro_ocorrencias SET texto = ?2, corrigido_em = datetime('now')
+        WHERE id = ?1
+          AND NOT EXISTS (SELECT 1 FROM livro_fechos f WHERE f.organizacao_id = livro_ocorrencias.organizacao_id AND f.data = livro_ocorrencias.data)
+        "#,
+    )
+    .bind(id)
+    .bind(texto.trim())
+    .execute(db_pool)
+    .await?
+    .rows_affected();
+    if corrigida == 0 {
+        return Err(AppError::Conflict(format!("O livro de {} já foi fechado.", data)));
+    }
+    Ok((data, anterior))
+}
+
+/// Fecha o livro do dia `data` com a passagem de serviço. `recebido_por` é o chefe de dia que entra.
+pub async fn fechar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    data: NaiveDate,
+    fechado_por: &str,
+    recebido_por: Option<&str>,
+    passagem: &str,
+) -> AppResult<()> {
+    verificar_aberto(db_pool, organizacao_id, data).await?;
+    if let Some(recebido_por) = recebido_por {
+        let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+            .bind(recebido_por)
+            .bind(organizacao_id)
+            .fetch_one(db_pool)
+            .await?;
+        if !existe {
+            return Err(AppError::validation("recebido_por", format!("Utilizador '{}' não encontrado.", recebido_por)));
+        }
+    }
+    let resultado = sqlx::query(
+        "INSERT INTO livro_fechos (organizacao_id, data, fechado_por, recebido_por, passagem) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(organizacao_id)
+    .bind(data.to_string())
+    .bind(fechado_por)
+    .bind(recebido_por)
+    .bind(passagem.trim())
+    .execute(db_pool)
+    .await;
+    match resultado {
+        Ok(_) => {
+            tracing::info!("📕 Livro de {} fechado por {}.", data, fechado_por);
+            Ok(())
+        }
+        // Dois fechos ao mesmo tempo: o segundo encontra a chave já ocupada
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::Conflict(format!("O livro de {} já foi fechado.", data)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Dias do livro mais recentes (com ocorrências ou fechados), do mais recente para o mais antigo.
+pub async fn dias_recentes(db_pool: &SqlitePool, organizacao_id: i64, limite: i64) -> AppResult<Vec<ResumoDiaLivro>> {
+    let dias = sqlx::query_as::<_, ResumoDiaLivro>(
+        r#"
+        SELECT d.data,
+               (SELECT COUNT(*) FROM livro_ocorrencias o WHERE o.organizacao_id = ?1 AND o.data = d.data) AS ocorrencias,
+               EXISTS (SELECT 1 FROM livro_fechos f WHERE f.organizacao_id = ?1 AND f.data = d.data) AS fechado
+        FROM (
+            SELECT data FROM livro_ocorrencias WHERE organizacao_id = ?1
+            UNION
+            SELECT data FROM livro_fechos WHERE organizacao_id = ?1
+        ) d
+        ORDER BY d.data DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(limite)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(dias)
+}
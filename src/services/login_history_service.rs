@@ -0,0 +1,115 @@
+// src/services/login_history_service.rs
+use crate::{
+    error::AppResult,
+    models::login::{LoginFilter, LoginRegisto},
+};
+use sqlx::SqlitePool;
+
+// Motivos de falha (coluna `motivo`)
+pub const MOTIVO_SENHA_INVALIDA: &str = "senha_invalida";
+pub const MOTIVO_USER_DESCONHECIDO: &str = "user_desconhecido";
+pub const MOTIVO_CONTA_ARQUIVADA: &str = "conta_arquivada";
+pub const MOTIVO_BLOQUEADO: &str = "bloqueado";
+pub const MOTIVO_BLOQUEIO_ADMIN: &str = "bloqueio_admin";
+pub const MOTIVO_OIDC_SEM_CONTA: &str = "oidc_sem_conta";
+pub const MOTIVO_ORGANIZACAO_INATIVA: &str = "organizacao_inativa";
+
+/// Comprimento máximo guardado do User-Agent.
+const MAX_USER_AGENT: usize = 300;
+/// Condições dos filtros da listagem de administração (partilhadas pela página e pela contagem).
+const WHERE_FILTRO: &str = r#"
+        WHERE (?1 IS NULL OR user_id = ?1 COLLATE NOCASE)
+          AND (?2 IS NULL OR ip = ?2)
+          AND (?3 IS NULL OR sucesso = ?3)
+          AND (?4 IS NULL OR date(criado_em) >= date(?4))
+          AND (?5 IS NULL OR date(criado_em) <= date(?5))
+"#;
+
+/// Regista uma tentativa de login (`motivo` = None para sucesso).
+/// Tal como a auditoria, nunca impede o login: em caso de falha apenas loga o erro.
+pub async fn registar(db_pool: &SqlitePool, user_id: &str, ip: &str, user_agent: Option<&str>, motivo: Option<&str>) {
+    let user_agent: Option<String> = user_agent.map(|ua| ua.chars().take(MAX_USER_AGENT).collect());
+    let result = sqlx::query(
+        "INSERT INTO login_history (user_id, ip, user_agent, sucesso, motivo) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(user_id)
+    .bind(ip)
+    .bind(user_agent)
+    .bind(motivo.is_none())
+    .bind(motivo)
+    .execute(db_pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("Falha ao registar login de {} ({}): {:?}", user_id, ip, e);
+    }
+}
+
+/// O utilizador já entrou antes, mas nunca a partir deste IP? (aviso de acesso a partir de um IP novo)
+/// Chamado antes de registar o login atual. O primeiro login de sempre não conta como IP novo.
+pub async fn ip_novo(db_pool: &SqlitePool, user_id: &str, ip: &str) -> AppResult<bool> {
+    let (anteriores, deste_ip) = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT COUNT(*), COALESCE(SUM(ip = ?2), 0) FROM login_history WHERE user_id = ?1 AND sucesso = 1",
+    )
+    .bind(user_id)
+    .bind(ip)
+    .fetch_one(db_pool)
+    .await?;
+    Ok(anteriores > 0 && deste_ip == 0)
+}
+
+/// Logins mais recentes de um utilizador (página do próprio).
+pub async fn recentes_user(db_pool: &SqlitePool, user_id: &str, limite: i64) -> AppResult<Vec<LoginRegisto>> {
+    let registos = sqlx::query_as::<_, LoginRegisto>(
+        r#"
+        SELECT user_id, ip, user_agent, sucesso, motivo, criado_em
+        FROM login_history
+        WHERE user_id = ?1
+        ORDER BY id DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(user_id)
+    .bind(limite)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(registos)
+}
+
+/// Lista uma página das tentativas (mais recentes primeiro) que correspondem aos filtros,
+/// com o total de tentativas que correspondem (para a paginação).
+pub async fn listar(db_pool: &SqlitePool, filtro: &LoginFilter, limite: i64, offset: i64) -> AppResult<(Vec<LoginRegisto>, i64)> {
+    // Filtros vazios na query string chegam como Some("") -> tratamos como None
+    let limpo = |v: &Option<String>| v.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+    let sucesso = match limpo(&filtro.resultado).as_deref() {
+        Some("sucesso") => Some(true),
+        Some("falha") => Some(false),
+        _ => None,
+    };
+    let (user, ip, de, ate) = (limpo(&filtro.user), limpo(&filtro.ip), limpo(&filtro.de), limpo(&filtro.ate));
+
+    let registos = sqlx::query_as::<_, LoginRegisto>(&format!(
+        "SELECT user_id, ip, user_agent, sucesso, motivo, criado_em FROM login_history {} ORDER BY id DESC LIMIT ?6 OFFSET ?7",
+        WHERE_FILTRO
+    ))
+    .bind(&user)
+    .bind(&ip)
+    .bind(sucesso)
+    .bind(&de)
+    .bind(&ate)
+    .bind(limite)
+    .bind(offset)
+    .fetch_all(db_pool)
+    .await?;
+
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM login_history {}", WHERE_FILTRO))
+        .bind(&user)
+        .bind(&ip)
+        .bind(sucesso)
+        .bind(&de)
+        .bind(&ate)
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok((registos, total))
+}
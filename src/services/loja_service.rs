@@ -0,0 +1,457 @@
+// src/services/loja_service.rs
+//! Loja (cantina): produtos com preço e stock, geridos pela administração, e vendas registadas pelo
+//! operador (permissão `loja`) no ponto de venda. Cada venda abate o stock na mesma transação (não se
+//! vende o que não há); anular uma venda do dia repõe-no. Os valores são em centavos (ver `crate::moeda`).
+//! O histórico de compras de cada utilizador e o relatório diário leem as vendas; as anuladas não contam.
+//!
+//! Conta corrente: cada utilizador tem um saldo, a soma dos seus movimentos (`conta_movimentos`). O operador
+//! regista os carregamentos; uma venda paga com a conta debita-a na mesma transação e a sua anulação
+//! credita-a de novo (o movimento original fica, como num extrato). O saldo pode ficar negativo: a
+//! administração vê quem deve no relatório de saldos negativos.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::loja::{
+        ExtratoConta, MovimentoConta, Produto, RelatorioVendas, SaldoConta, Venda, VendasPagamento, VendasProduto,
+        MOVIMENTO_ANULACAO, MOVIMENTO_CARREGAMENTO, MOVIMENTO_COMPRA, PAGAMENTO_CONTA,
+    },
+    tempo,
+};
+use chrono::{Duration, Months, NaiveDate};
+use sqlx::{FromRow, Sqlite, SqlitePool, Transaction};
+
+/// Quantidade máxima de um produto numa venda.
+pub const MAX_QUANTIDADE: i64 = 99;
+/// Compras mostradas no histórico de um utilizador.
+pub const COMPRAS_HISTORICO: i64 = 100;
+/// Valor máximo de um carregamento da conta corrente (centavos).
+pub const MAX_CARREGAMENTO: i64 = 100_000;
+
+// --- PRODUTOS ---
+
+/// Produtos da organização, por nome (só os ativos, se `so_ativos`).
+pub async fn listar_produtos(db_pool: &SqlitePool, organizacao_id: i64, so_ativos: bool) -> AppResult<Vec<Produto>> {
+    let produtos = sqlx::query_as::<_, Produto>(
+        r#"
+        SELECT id, nome, preco, stock, ativo
+        FROM produtos
+        WHERE organizacao_id = ?1 AND (?2 = 0 OR ativo = 1)
+        ORDER BY nome
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(so_ativos)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(produtos)
+}
+
+/// Cria um produto (dados já validados pelo formulário).
+pub async fn criar_produto(db_pool: &SqlitePool, organizacao_id: i64, nome: &str, preco: i64, stock: i64) -> AppResult<i64> {
+    let resultado = sqlx::query("INSERT INTO produtos (organizacao_id, nome, preco, stock) VALUES (?1, ?2, ?3, ?4)")
+        .bind(organizacao_id)
+        .bind(nome.trim())
+        .bind(preco)
+        .bind(stock)
+        .execute(db_pool)
+        .await;
+    match resultado {
+        Ok(r) => Ok(r.last_insert_rowid()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::validation("nome", "Já existe um produto com este nome."))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Altera o preço, o stock (contagem do inventário) ou o estado de um produto da organização.
+/// Devolve o produto como estava antes (para a auditoria).
+pub async fn atualizar_produto(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    preco: i64,
+    stock: i64,
+    ativo: bool,
+) -> AppResult<Produto> {
+    let mut tx = db_pool.begin().await?;
+    let anterior = sqlx::query_as::<_, Produto>(
+        "SELECT id, nome, preco, stock, ativo FROM produtos WHERE id = ?1 AND organizacao_id = ?2",
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Produto {} não encontrado.", id)))?;
+    sqlx::query("UPDATE produtos SET preco = ?2, stock = ?3, ativo = ?4 WHERE id = ?1")
+        .bind(id)
+        .bind(preco)
+        .bind(stock)
+        .bind(ativo)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(anterior)
+}
+
+// --- VENDAS ---
+
+/// Regista uma venda de `itens` (produto, quantidade) e abate o stock. `comprador` é o utilizador a quem
+/// se vende (None = ao balcão; obrigatório no pagamento com a conta corrente, que é debitada). Falha, sem
+/// alterar nada, se um produto não existir, estiver inativo ou não tiver stock suficiente.
+/// Devolve o ID e o total da venda.
+pub async fn registar_venda(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    operador_id: &str,
+    comprador: Option<&str>,
+    itens: &[(i64, i64)],
+    pagamento: &str,
+) -> AppResult<(i64, i64)> {
+    if itens.is_empty() {
+        return Err(AppError::validation("itens", "Indique a quantidade de pelo menos um produto."));
+    }
+    if pagamento == PAGAMENTO_CONTA && comprador.is_none() {
+        return Err(AppError::validation("comprador", "O pagamento com a conta corrente exige o comprador."));
+    }
+    let mut tx = db_pool.begin().await?;
+
+    if let Some(comprador) = comprador {
+        let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+            .bind(comprador)
+            .bind(organizacao_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if !existe {
+            return Err(AppError::validation("comprador", format!("Utilizador '{}' não encontrado.", comprador)));
+        }
+    }
+
+    let venda_id = sqlx::query("INSERT INTO vendas (organizacao_id, user_id, operador_id, pagamento) VALUES (?1, ?2, ?3, ?4)")
+        .bind(organizacao_id)
+        .bind(comprador)
+        .bind(operador_id)
+        .bind(pagamento)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+    let mut total = 0;
+    for &(produto_id, quantidade) in itens {
+        let produto = sqlx::query_as::<_, Produto>(
+            "SELECT id, nome, preco, stock, ativo FROM produtos WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1",
+        )
+        .bind(produto_id)
+        .bind(organizacao_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::validation("itens", format!("Produto {} não encontrado ou inativo.", produto_id)))?;
+
+        // Abate o stock só se chegar (a condição evita vender a mais em duas vendas simultâneas)
+        let abatido = sqlx::query("UPDATE produtos SET stock = stock - ?2 WHERE id = ?1 AND stock >= ?2")
+            .bind(produto_id)
+            .bind(quantidade)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if abatido == 0 {
+            return Err(AppError::validation(
+                "itens",
+                format!("Stock insuficiente de '{}' (restam {}).", produto.nome, produto.stock),
+            ));
+        }
+
+        sqlx::query("INSERT INTO venda_itens (venda_id, produto_id, quantidade, preco) VALUES (?1, ?2, ?3, ?4)")
+            .bind(venda_id)
+            .bind(produto_id)
+            .bind(quantidade)
+            .bind(produto.preco)
+            .execute(&mut *tx)
+            .await?;
+        total += produto.preco * quantidade;
+    }
+
+    sqlx::query("UPDATE vendas SET total = ?2 WHERE id = ?1")
+        .bind(venda_id)
+        .bind(total)
+        .execute(&mut *tx)
+        .await?;
+    if let (PAGAMENTO_CONTA, Some(comprador)) = (pagamento, comprador) {
+        inserir_movimento(&mut tx, organizacao_id, comprador, MOVIMENTO_COMPRA, -total, Some(venda_id), operador_id).await?;
+    }
+    tx.commit().await?;
+    tracing::info!("🛒 Venda {} registada por {}: {} centavos ({}).", venda_id, operador_id, total, pagamento);
+    Ok((venda_id, total))
+}
+
+/// Dados de uma venda lidos para a anular.
+#[derive(FromRow)]
+struct VendaAnular {
+    total: i64,
+    criado_em: String,
+    anulada_em: Option<String>,
+    user_id: Option<String>,
+    pagamento: String,
+}
+
+/// Anula uma venda de hoje e repõe o stock dos produtos (e, se foi paga com a conta corrente, credita-a).
+/// Devolve o total da venda.
+pub async fn anular_venda(db_pool: &SqlitePool, organizacao_id: i64, venda_id: i64, operador_id: &str) -> AppResult<i64> {
+    let mut tx = db_pool.begin().await?;
+    let venda = sqlx::query_as::<_, VendaAnular>(
+        "SELECT total, criado_em, anulada_em, user_id, pagamento FROM vendas WHERE id = ?1 AND organizacao_id = ?2",
+    )
+    .bind(venda_id)
+    .bind(organizacao_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(VendaAnular { total, criado_em, anulada_em, user_id: comprador, pagamento }) = venda else {
+        return Err(AppError::NotFound(format!("Venda {} não encontrada.", venda_id)));
+    };
+    if anulada_em.is_some() {
+        return Err(AppError::Conflict(format!("A venda {} já foi anulada.", venda_id)));
+    }
+    // Só as de hoje: o relatório de um dia já fechado não muda
+    if criado_em < tempo::inicio_dia_utc(tempo::hoje()) {
+        return Err(AppError::Conflict("Só se podem anular as vendas de hoje.".to_string()));
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE produtos SET stock = stock + (SELECT i.quantidade FROM venda_itens i WHERE i.venda_id = ?1 AND i.produto_id = produtos.id)
+        WHERE id IN (SELECT produto_id FROM venda_itens WHERE venda_id = ?1)
+        "#,
+    )
+    .bind(venda_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("UPDATE vendas SET anulada_em = datetime('now'), anulada_por = ?2 WHERE id = ?1")
+        .bind(venda_id)
+        .bind(operador_id)
+        .execute(&mut *tx)
+        .await?;
+    if let (PAGAMENTO_CONTA, Some(comprador)) = (pagamento.as_str(), comprador) {
+        inserir_movimento(&mut tx, organizacao_id, &comprador, MOVIMENTO_ANULACAO, total, Some(venda_id), operador_id).await?;
+    }
+    tx.commit().await?;
+    Ok(total)
+}
+
+/// Vendas da organização, das mais recentes para as mais antigas. `?2` = só as deste comprador;
+/// `?3`/`?4` = só as registadas nesse intervalo (UTC, o fim exclusivo).
+const SQL_VENDAS: &str = r#"
+    SELECT v.id, v.user_id, u.name AS comprador, COALESCE(o.name, v.operador_id) AS operador,
+           v.total, v.pagamento, v.criado_em, v.anulada_em IS NOT NULL AS anulada,
+           COALESCE((SELECT GROUP_CONCAT(i.quantidade || '× ' || p.nome, ', ')
+                     FROM venda_itens i JOIN produtos p ON p.id = i.produto_id
+                     WHERE i.venda_id = v.id), '') AS itens
+    FROM vendas v
+    LEFT JOIN users u ON u.id = v.user_id
+    LEFT JOIN users o ON o.id = v.operador_id
+    WHERE v.organizacao_id = ?1
+      AND (?2 IS NULL OR v.user_id = ?2)
+      AND (?3 IS NULL OR v.criado_em >= ?3)
+      AND (?4 IS NULL OR v.criado_em < ?4)
+    ORDER BY v.criado_em DESC, v.id DESC
+    LIMIT ?5
+"#;
+
+/// Limites UTC do dia `data` (no fuso da aplicação), para as colunas `criado_em`.
+fn limites_dia(data: NaiveDate) -> (String, String) {
+    (tempo::inicio_dia_utc(data), tempo::inicio_dia_utc(data + Duration::days(1)))
+}
+
+/// Vendas do dia `data` (incluindo as anuladas).
+pub async fn vendas_dia(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<Vec<Venda>> {
+    let (inicio, fim) = limites_dia(data);
+    let vendas = sqlx::query_as::<_, Venda>(SQL_VENDAS)
+        .bind(organizacao_id)
+        .bind(None::<String>)
+        .bind(inicio)
+        .bind(fim)
+        .bind(i64::MAX)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(vendas)
+}
+
+/// Últimas compras de um utilizador (incluindo as anuladas).
+pub async fn compras_user(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str, limite: i64) -> AppResult<Vec<Venda>> {
+    let compras = sqlx::query_as::<_, Venda>(SQL_VENDAS)
+        .bind(organizacao_id)
+        .bind(user_id)
+        .bind(None::<String>)
+        .bind(None::<String>)
+        .bind(limite)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(compras)
+}
+
+/// Relatório de vendas do dia `data`: totais por produto e por forma de pagamento, e a lista das vendas.
+pub async fn relatorio_dia(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<RelatorioVendas> {
+    let (inicio, fim) = limites_dia(data);
+    let por_produto = sqlx::query_as::<_, VendasProduto>(
+        r#"
+        SELECT p.nome, SUM(i.quantidade) AS quantidade, SUM(i.quantidade * i.preco) AS total
+        FROM venda_itens i
+        JOIN vendas v ON v.id = i.venda_id
+        JOIN produtos p ON p.id = i.produto_id
+        WHERE v.organizacao_id = ?1 AND v.anulada_em IS NULL AND v.criado_em >= ?2 AND v.criado_em < ?3
+        GROUP BY p.id
+        ORDER BY total DESC, p.nome
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(&inicio)
+    .bind(&fim)
+    .fetch_all(db_pool)
+    .await?;
+    let por_pagamento = sqlx::query_as::<_, VendasPagamento>(
+        r#"
+        SELECT pagamento, COUNT(*) AS vendas, SUM(total) AS total
+        FROM vendas
+        WHERE organizacao_id = ?1 AND anulada_em IS NULL AND criado_em >= ?2 AND criado_em < ?3
+        GROUP BY pagamento
+        ORDER BY total DESC
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(&inicio)
+    .bind(&fim)
+    .fetch_all(db_pool)
+    .await?;
+
+    Ok(RelatorioVendas {
+        data: data.to_string(),
+        vendas: vendas_dia(db_pool, organizacao_id, data).await?,
+        total: por_pagamento.iter().map(|p| p.total).sum(),
+        n_vendas: por_pagamento.iter().map(|p| p.vendas).sum(),
+        por_produto,
+        por_pagamento,
+    })
+}
+
+// --- CONTA CORRENTE ---
+
+/// Regista um movimento na conta corrente de um utilizador (dentro da transação de quem o chama).
+async fn inserir_movimento(
+    tx: &mut Transaction<'_, Sqlite>,
+    organizacao_id: i64,
+    user_id: &str,
+    tipo: &str,
+    valor: i64,
+    venda_id: Option<i64>,
+    operador_id: &str,
+) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO conta_movimentos (organizacao_id, user_id, tipo, valor, venda_id, operador_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(tipo)
+    .bind(valor)
+    .bind(venda_id)
+    .bind(operador_id)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Saldo da conta corrente: a soma dos movimentos do utilizador `?2`.
+const SQL_SALDO: &str = "SELECT COALESCE(SUM(valor), 0) FROM conta_movimentos WHERE organizacao_id = ?1 AND user_id = ?2";
+
+/// Saldo atual da conta corrente de um utilizador (centavos).
+pub async fn saldo(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str) -> AppResult<i64> {
+    let saldo: i64 = sqlx::query_scalar(SQL_SALDO)
+        .bind(organizacao_id)
+        .bind(user_id)
+        .fetch_one(db_pool)
+        .await?;
+    Ok(saldo)
+}
+
+/// Carrega `valor` centavos na conta corrente de um utilizador ativo da organização. Devolve o novo saldo.
+pub async fn carregar_conta(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str, valor: i64, operador_id: &str) -> AppResult<i64> {
+    let mut tx = db_pool.begin().await?;
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    inserir_movimento(&mut tx, organizacao_id, user_id, MOVIMENTO_CARREGAMENTO, valor, None, operador_id).await?;
+    let saldo: i64 = sqlx::query_scalar(SQL_SALDO)
+        .bind(organizacao_id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!("💳 Conta de {} carregada por {}: {} centavos (saldo {}).", user_id, operador_id, valor, saldo);
+    Ok(saldo)
+}
+
+/// Extrato do mês que começa em `mes` (dia 1, no fuso da aplicação): saldo no início do mês, os
+/// movimentos do mês e o saldo no fim.
+pub async fn extrato_mes(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str, mes: NaiveDate) -> AppResult<ExtratoConta> {
+    let inicio = tempo::inicio_dia_utc(mes);
+    let fim = tempo::inicio_dia_utc(mes + Months::new(1));
+    let saldo_inicial: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(valor), 0) FROM conta_movimentos WHERE organizacao_id = ?1 AND user_id = ?2 AND criado_em < ?3",
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(&inicio)
+    .fetch_one(db_pool)
+    .await?;
+    let movimentos = sqlx::query_as::<_, MovimentoConta>(
+        r#"
+        SELECT m.tipo, m.valor, m.venda_id, COALESCE(o.name, m.operador_id) AS operador, m.criado_em,
+               COALESCE((SELECT GROUP_CONCAT(i.quantidade || '× ' || p.nome, ', ')
+                         FROM venda_itens i JOIN produtos p ON p.id = i.produto_id
+                         WHERE i.venda_id = m.venda_id), '') AS itens
+        FROM conta_movimentos m
+        LEFT JOIN users o ON o.id = m.operador_id
+        WHERE m.organizacao_id = ?1 AND m.user_id = ?2 AND m.criado_em >= ?3 AND m.criado_em < ?4
+        ORDER BY m.criado_em, m.id
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(&inicio)
+    .bind(&fim)
+    .fetch_all(db_pool)
+    .await?;
+
+    let creditos: i64 = movimentos.iter().filter(|m| m.valor > 0).map(|m| m.valor).sum();
+    let debitos: i64 = movimentos.iter().filter(|m| m.valor < 0).map(|m| -m.valor).sum();
+    Ok(ExtratoConta {
+        mes: mes.format("%Y-%m").to_string(),
+        saldo_final: saldo_inicial + creditos - debitos,
+        saldo_inicial,
+        movimentos,
+        creditos,
+        debitos,
+    })
+}
+
+/// Utilizadores da organização com saldo negativo, dos que devem mais para os que devem menos.
+pub async fn saldos_negativos(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<SaldoConta>> {
+    let saldos = sqlx::query_as::<_, SaldoConta>(
+        r#"
+        SELECT m.user_id, u.name, u.turma, SUM(m.valor) AS saldo, MAX(m.criado_em) AS ultimo_movimento
+        FROM conta_movimentos m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.organizacao_id = ?1
+        GROUP BY m.user_id
+        HAVING SUM(m.valor) < 0
+        ORDER BY saldo, u.name
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(saldos)
+}
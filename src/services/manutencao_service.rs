@@ -0,0 +1,59 @@
+// src/services/manutencao_service.rs
+//! Modo de manutenção (só leitura), para a passagem de ano e os restauros: as páginas continuam a abrir,
+//! com um aviso, mas as alterações de quem não é administrador respondem 503 (ver `web::mw_manutencao`).
+//! O estado está na tabela `manutencao` e em memória (lido a cada pedido e pelo layout das páginas).
+
+use crate::{
+    error::AppResult,
+    models::manutencao::Manutencao,
+    tempo,
+};
+use sqlx::SqlitePool;
+use std::sync::RwLock;
+
+static ATUAL: RwLock<Option<Manutencao>> = RwLock::new(None);
+
+/// Modo de manutenção ativo (None = funcionamento normal), com a hora no fuso da aplicação.
+pub fn atual() -> Option<Manutencao> {
+    ATUAL.read().map(|atual| atual.clone()).unwrap_or(None)
+}
+
+fn definir(manutencao: Option<Manutencao>) {
+    if let Ok(mut atual) = ATUAL.write() {
+        *atual = manutencao.map(|m| Manutencao { ativada_em: tempo::formatar(&m.ativada_em, tempo::FORMATO_DATA_HORA), ..m });
+    }
+}
+
+/// Lê o estado guardado (no arranque: a manutenção continua ativa depois de um reinício).
+pub async fn carregar(db_pool: &SqlitePool) -> AppResult<Option<Manutencao>> {
+    let manutencao = sqlx::query_as::<_, Manutencao>("SELECT motivo, ativada_por, ativada_em FROM manutencao WHERE id = 1")
+        .fetch_optional(db_pool)
+        .await?;
+    definir(manutencao);
+    Ok(atual())
+}
+
+/// Liga o modo de manutenção (ou atualiza o motivo, se já estiver ligado).
+pub async fn ativar(db_pool: &SqlitePool, motivo: &str, ativada_por: &str) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO manutencao (id, motivo, ativada_por) VALUES (1, ?1, ?2)
+        ON CONFLICT (id) DO UPDATE SET motivo = excluded.motivo
+        "#,
+    )
+    .bind(motivo.trim())
+    .bind(ativada_por)
+    .execute(db_pool)
+    .await?;
+    tracing::warn!("🚧 Modo de manutenção ativado por {}.", ativada_por);
+    carregar(db_pool).await?;
+    Ok(())
+}
+
+/// Desliga o modo de manutenção.
+pub async fn desativar(db_pool: &SqlitePool) -> AppResult<()> {
+    sqlx::query("DELETE FROM manutencao").execute(db_pool).await?;
+    tracing::info!("✅ Modo de manutenção desativado.");
+    definir(None);
+    Ok(())
+}
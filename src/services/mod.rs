@@ -2,4 +2,63 @@
 pub mod auth_service;
 pub mod user_service;
 pub mod presence_service;
-pub mod escala_service;
\ No newline at end of file
+pub mod escala_service;
+pub mod audit_service;
+pub mod permission_service;
+pub mod grupo_service;
+pub mod dashboard_service;
+pub mod atributo_service;
+pub mod bloqueio_service;
+pub mod login_history_service;
+pub mod sessao_service;
+pub mod oidc_service;
+pub mod captcha_service;
+pub mod notificacao_service;
+pub mod api_token_service;
+pub mod indisponibilidade_service;
+pub mod webhook_service;
+pub mod telegram_service;
+pub mod email_service;
+pub mod evento_service;
+pub mod saude_service;
+pub mod backup_service;
+pub mod dados_service;
+pub mod jwt_service;
+pub mod google_calendar_service;
+pub mod seed_service;
+pub mod agendador_service;
+pub mod organizacao_service;
+pub mod retencao_service;
+pub mod manutencao_service;
+pub mod contabilidade_service;
+pub mod preferencias_service;
+pub mod arranchamento_service;
+pub mod cardapio_service;
+pub mod loja_service;
+pub mod livro_service;
+pub mod revista_service;
+pub mod quarto_service;
+pub mod cautela_service;
+pub mod baixa_service;
+pub mod visitante_service;
+pub mod agenda_service;
+pub mod documento_service;
+pub mod disciplina_service;
+pub mod antiguidade_service;
+pub mod horario_service;
+pub mod prova_service;
+pub mod uniforme_service;
+pub mod portaria_service;
+pub mod sugestao_service;
+pub mod faxina_service;
+pub mod conceito_service;
+pub mod comitiva_service;
+pub mod chamada_service;
+pub mod claviculario_service;
+pub mod tfm_service;
+pub mod enfermaria_service;
+pub mod biblioteca_service;
+pub mod lavanderia_service;
+pub mod enquete_service;
+pub mod foto_service;
+pub mod pdf_service;
@@ -0,0 +1,192 @@
+// src/services/notificacao_service.rs
+//! Notificações para os utilizadores: as recentes no painel (/user), todas em /user/notificacoes e o
+//! número das por ler na barra de navegação (ver `web::navegacao`).
+//! Serviço partilhado: cada funcionalidade publica aqui com o seu tipo, em vez de criar os próprios avisos.
+//! Quem ligou a conta ao Telegram recebe-as também lá (ver `telegram_service`), se não tiver desligado
+//! os avisos desse tipo nas preferências; as páginas abertas recebem-nas em tempo real (ver `evento_service`).
+
+use crate::{error::AppResult, models::notificacao::{Notificacao, NotificacoesFilter}, services::{evento_service, preferencias_service, telegram_service}, tempo};
+use sqlx::SqlitePool;
+
+// Tipos de notificação (coluna `tipo`)
+pub const TIPO_SEGURANCA: &str = "seguranca";
+pub const TIPO_TROCA: &str = "troca";
+pub const TIPO_ESCALA: &str = "escala";
+pub const TIPO_LEMBRETE: &str = "lembrete";
+pub const TIPO_VISITA: &str = "visita";
+pub const TIPO_AGENDA: &str = "agenda";
+pub const TIPO_DISCIPLINA: &str = "disciplina";
+pub const TIPO_SUGESTAO: &str = "sugestao";
+pub const TIPO_CONCEITO: &str = "conceito";
+pub const TIPO_COMITIVA: &str = "comitiva";
+pub const TIPO_CHAMADA: &str = "chamada";
+pub const TIPO_ENFERMARIA: &str = "enfermaria";
+pub const TIPO_BIBLIOTECA: &str = "biblioteca";
+pub const TIPO_LAVANDERIA: &str = "lavanderia";
+
+/// Tipos (e a descrição), pela ordem do filtro de /user/notificacoes.
+pub const TIPOS: &[(&str, &str)] = &[
+    (TIPO_SEGURANCA, "Segurança"),
+    (TIPO_TROCA, "Trocas"),
+    (TIPO_ESCALA, "Escala"),
+    (TIPO_LEMBRETE, "Lembretes"),
+    (TIPO_VISITA, "Visitas"),
+    (TIPO_AGENDA, "Agenda"),
+    (TIPO_DISCIPLINA, "Disciplina"),
+    (TIPO_SUGESTAO, "Sugestões"),
+    (TIPO_CONCEITO, "Conceito"),
+    (TIPO_COMITIVA, "Comitivas"),
+    (TIPO_CHAMADA, "Plano de chamada"),
+    (TIPO_ENFERMARIA, "Enfermaria"),
+    (TIPO_BIBLIOTECA, "Biblioteca"),
+    (TIPO_LAVANDERIA, "Lavanderia"),
+];
+
+/// Ícone de um tipo de notificação.
+pub fn icone(tipo: &str) -> &'static str {
+    match tipo {
+        TIPO_SEGURANCA => "🔐",
+        TIPO_TROCA => "🔁",
+        TIPO_ESCALA => "📅",
+        TIPO_LEMBRETE => "⏰",
+        TIPO_VISITA => "🚪",
+        TIPO_AGENDA => "🎓",
+        TIPO_DISCIPLINA => "⚖️",
+        TIPO_SUGESTAO => "💡",
+        TIPO_CONCEITO => "🎖️",
+        TIPO_COMITIVA => "🚌",
+        TIPO_CHAMADA => "📞",
+        TIPO_ENFERMARIA => "🏥",
+        TIPO_BIBLIOTECA => "📚",
+        TIPO_LAVANDERIA => "🧺",
+        _ => "📣",
+    }
+}
+
+/// Cria uma notificação para um utilizador.
+/// Tal como a auditoria, nunca faz falhar a ação que a originou: em caso de erro apenas loga.
+pub async fn notificar(db_pool: &SqlitePool, user_id: &str, tipo: &str, titulo: &str, mensagem: &str) {
+    let result = sqlx::query("INSERT INTO notificacoes (user_id, tipo, titulo, mensagem) VALUES (?1, ?2, ?3, ?4)")
+        .bind(user_id)
+        .bind(tipo)
+        .bind(titulo)
+        .bind(mensagem)
+        .execute(db_pool)
+        .await;
+
+    match result {
+        Ok(_) => tracing::debug!("🔔 Notificação '{}' para {}: {}", tipo, user_id, titulo),
+        Err(e) => tracing::error!("Falha ao criar notificação '{}' para {}: {:?}", tipo, user_id, e),
+    }
+    evento_service::publicar(
+        Some(user_id),
+        evento_service::EVENTO_NOTIFICACAO,
+        serde_json::json!({ "tipo": tipo, "titulo": titulo, "mensagem": mensagem }),
+    );
+    if preferencias_service::aceita_aviso(db_pool, user_id, tipo).await {
+        telegram_service::encaminhar(db_pool, user_id, titulo, mensagem).await;
+    }
+}
+
+/// Notifica vários utilizadores (ex: todos os de um ano) com a mesma notificação.
+pub async fn notificar_varios(db_pool: &SqlitePool, user_ids: &[String], tipo: &str, titulo: &str, mensagem: &str) {
+    for user_id in user_ids {
+        notificar(db_pool, user_id, tipo, titulo, mensagem).await;
+    }
+}
+
+/// Acerta a hora das notificações lidas da base de dados para a hora local.
+fn hora_local(notificacoes: &mut [Notificacao]) {
+    for notificacao in notificacoes {
+        notificacao.criada_em = tempo::formatar(&notificacao.criada_em, tempo::FORMATO_DATA_HORA);
+    }
+}
+
+/// Notificações mais recentes de um utilizador (lidas e por ler).
+pub async fn recentes_user(db_pool: &SqlitePool, user_id: &str, limite: i64) -> AppResult<Vec<Notificacao>> {
+    let mut notificacoes = sqlx::query_as::<_, Notificacao>(
+        r#"
+        SELECT id, tipo, titulo, mensagem, criada_em, lida_em IS NOT NULL AS lida
+        FROM notificacoes
+        WHERE user_id = ?1
+        ORDER BY id DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(user_id)
+    .bind(limite)
+    .fetch_all(db_pool)
+    .await?;
+    hora_local(&mut notificacoes);
+    Ok(notificacoes)
+}
+
+/// Condições dos filtros de /user/notificacoes (?2 = tipo, ?3 = só as por ler; NULL = sem filtro).
+const WHERE_FILTRO: &str = "WHERE user_id = ?1 AND (?2 IS NULL OR tipo = ?2) AND (?3 = 0 OR lida_em IS NULL)";
+
+/// Notificações de um utilizador com os filtros, por páginas (mais recentes primeiro), e o total.
+pub async fn listar_user(
+    db_pool: &SqlitePool,
+    user_id: &str,
+    filtro: &NotificacoesFilter,
+    limite: i64,
+    offset: i64,
+) -> AppResult<(Vec<Notificacao>, i64)> {
+    let tipo = filtro.tipo.as_deref().map(str::trim).filter(|s| !s.is_empty());
+
+    let mut notificacoes = sqlx::query_as::<_, Notificacao>(&format!(
+        "SELECT id, tipo, titulo, mensagem, criada_em, lida_em IS NOT NULL AS lida FROM notificacoes {} ORDER BY id DESC LIMIT ?4 OFFSET ?5",
+        WHERE_FILTRO
+    ))
+    .bind(user_id)
+    .bind(tipo)
+    .bind(filtro.por_ler)
+    .bind(limite)
+    .bind(offset)
+    .fetch_all(db_pool)
+    .await?;
+    hora_local(&mut notificacoes);
+
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM notificacoes {}", WHERE_FILTRO))
+        .bind(user_id)
+        .bind(tipo)
+        .bind(filtro.por_ler)
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok((notificacoes, total))
+}
+
+/// Número de notificações por ler (contador da barra de navegação).
+pub async fn por_ler(db_pool: &SqlitePool, user_id: &str) -> AppResult<i64> {
+    let total = sqlx::query_scalar("SELECT COUNT(*) FROM notificacoes WHERE user_id = ?1 AND lida_em IS NULL")
+        .bind(user_id)
+        .fetch_one(db_pool)
+        .await?;
+    Ok(total)
+}
+
+/// Marca uma notificação do utilizador como lida. Devolve `false` se não existir (ou for de outro
+/// utilizador); marcar uma já lida não muda a hora da leitura.
+pub async fn marcar_lida(db_pool: &SqlitePool, user_id: &str, id: i64) -> AppResult<bool> {
+    let existe = sqlx::query(
+        "UPDATE notificacoes SET lida_em = COALESCE(lida_em, datetime('now')) WHERE id = ?1 AND user_id = ?2",
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(db_pool)
+    .await?
+    .rows_affected()
+        > 0;
+    Ok(existe)
+}
+
+/// Marca todas as notificações do utilizador como lidas. Devolve quantas estavam por ler.
+pub async fn marcar_todas_lidas(db_pool: &SqlitePool, user_id: &str) -> AppResult<u64> {
+    let marcadas = sqlx::query("UPDATE notificacoes SET lida_em = datetime('now') WHERE user_id = ?1 AND lida_em IS NULL")
+        .bind(user_id)
+        .execute(db_pool)
+        .await?
+        .rows_affected();
+    Ok(marcadas)
+}
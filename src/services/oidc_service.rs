@@ -0,0 +1,217 @@
+// src/services/oidc_service.rs
+//! Login com a conta institucional via OpenID Connect (fluxo authorization code).
+//! Ativo apenas se as variáveis OIDC_* estiverem definidas; caso contrário (ou se o
+//! fornecedor estiver em baixo) mantém-se o login local com ID e senha.
+
+use crate::error::{AppError, AppResult};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+/// Tempo máximo de espera pelo fornecedor (o utilizador está parado no browser).
+const TIMEOUT_FORNECEDOR: Duration = Duration::from_secs(5);
+
+/// Configuração do cliente OIDC (lida do ambiente no arranque).
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String, // Ex: https://mercal.exemplo.pt/auth/oidc/callback
+    http: reqwest::Client,
+}
+
+impl OidcConfig {
+    /// Lê OIDC_ISSUER, OIDC_CLIENT_ID, OIDC_CLIENT_SECRET e OIDC_REDIRECT_URL.
+    /// Devolve None (OIDC desativado) se alguma faltar.
+    pub fn from_env() -> Option<Self> {
+        let var = |nome: &str| std::env::var(nome).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let issuer = var("OIDC_ISSUER")?;
+        let http = reqwest::Client::builder()
+            .timeout(TIMEOUT_FORNECEDOR)
+            .build()
+            .map_err(|e| tracing::error!("❌ Falha ao criar cliente HTTP para OIDC: {}", e))
+            .ok()?;
+        Some(Self {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id: var("OIDC_CLIENT_ID")?,
+            client_secret: var("OIDC_CLIENT_SECRET")?,
+            redirect_url: var("OIDC_REDIRECT_URL")?,
+            http,
+        })
+    }
+}
+
+/// Endpoints anunciados pelo fornecedor em `/.well-known/openid-configuration`.
+#[derive(Debug, Deserialize)]
+pub struct Descoberta {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+/// Identidade confirmada pelo fornecedor (claims do ID token já validadas).
+#[derive(Debug, Clone)]
+pub struct IdentidadeOidc {
+    pub sub: String,
+    pub email: Option<String>,
+    pub preferred_username: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RespostaToken {
+    id_token: String,
+}
+
+/// `aud` pode vir como string ou lista.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Audiencia {
+    Uma(String),
+    Varias(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaimsIdToken {
+    iss: String,
+    aud: Audiencia,
+    exp: i64,
+    nonce: Option<String>,
+    sub: String,
+    email: Option<String>,
+    preferred_username: Option<String>,
+}
+
+fn erro_fornecedor(contexto: &str, e: impl std::fmt::Display) -> AppError {
+    AppError::Oidc(format!("{}: {}", contexto, e))
+}
+
+/// Obtém os endpoints do fornecedor (falha rápido se estiver indisponível).
+pub async fn descobrir(config: &OidcConfig) -> AppResult<Descoberta> {
+    let url = format!("{}/.well-known/openid-configuration", config.issuer);
+    let descoberta: Descoberta = config
+        .http
+        .get(&url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| erro_fornecedor("descoberta", e))?
+        .json()
+        .await
+        .map_err(|e| erro_fornecedor("descoberta (JSON)", e))?;
+
+    if descoberta.issuer.trim_end_matches('/') != config.issuer {
+        return Err(AppError::Oidc(format!("issuer anunciado '{}' não corresponde ao configurado", descoberta.issuer)));
+    }
+    Ok(descoberta)
+}
+
+/// URL para onde o browser é enviado para autenticar no fornecedor.
+pub fn url_autorizacao(config: &OidcConfig, descoberta: &Descoberta, state: &str, nonce: &str) -> AppResult<String> {
+    let url = reqwest::Url::parse_with_params(
+        &descoberta.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("scope", "openid email profile"),
+            ("state", state),
+            ("nonce", nonce),
+        ],
+    )
+    .map_err(|e| erro_fornecedor("authorization_endpoint inválido", e))?;
+    Ok(url.to_string())
+}
+
+/// Troca o código de autorização pelo ID token e valida as claims (issuer, audiência, validade, nonce).
+/// O token vem diretamente do token endpoint (TLS), pelo que a assinatura não é verificada aqui
+/// (OIDC Core 3.1.3.7, ponto 6).
+pub async fn trocar_codigo(config: &OidcConfig, descoberta: &Descoberta, code: &str, nonce: &str) -> AppResult<IdentidadeOidc> {
+    let resposta: RespostaToken = config
+        .http
+        .post(&descoberta.token_endpoint)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+        ])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| erro_fornecedor("token", e))?
+        .json()
+        .await
+        .map_err(|e| erro_fornecedor("token (JSON)", e))?;
+
+    // JWT: header.payload.assinatura (base64url sem padding)
+    let payload = resposta
+        .id_token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| AppError::Oidc("id_token mal formado".to_string()))?;
+    let bytes = URL_SAFE_NO_PAD.decode(payload).map_err(|e| erro_fornecedor("id_token (base64)", e))?;
+    let claims: ClaimsIdToken = serde_json::from_slice(&bytes).map_err(|e| erro_fornecedor("id_token (claims)", e))?;
+
+    if claims.iss.trim_end_matches('/') != config.issuer {
+        return Err(AppError::Oidc(format!("iss inesperado: {}", claims.iss)));
+    }
+    let audiencia_ok = match &claims.aud {
+        Audiencia::Uma(aud) => *aud == config.client_id,
+        Audiencia::Varias(auds) => auds.contains(&config.client_id),
+    };
+    if !audiencia_ok {
+        return Err(AppError::Oidc("aud não inclui este cliente".to_string()));
+    }
+    if claims.exp < chrono::Utc::now().timestamp() {
+        return Err(AppError::Oidc("id_token expirado".to_string()));
+    }
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(AppError::Oidc("nonce não corresponde".to_string()));
+    }
+
+    Ok(IdentidadeOidc { sub: claims.sub, email: claims.email, preferred_username: claims.preferred_username })
+}
+
+/// Utilizador local da identidade institucional. Se ainda não houver ligação, tenta associar
+/// pelo ID (`preferred_username`) e depois pelo email (só se corresponder a um único utilizador).
+pub async fn resolver_utilizador(db_pool: &SqlitePool, identidade: &IdentidadeOidc) -> AppResult<Option<String>> {
+    let ligado: Option<String> = sqlx::query_scalar("SELECT user_id FROM user_identidades_oidc WHERE sub = ?1")
+        .bind(&identidade.sub)
+        .fetch_optional(db_pool)
+        .await?;
+    if ligado.is_some() {
+        return Ok(ligado);
+    }
+
+    let mut candidato: Option<String> = None;
+    if let Some(username) = identidade.preferred_username.as_deref() {
+        candidato = sqlx::query_scalar("SELECT id FROM users WHERE id = ?1 COLLATE NOCASE")
+            .bind(username.trim())
+            .fetch_optional(db_pool)
+            .await?;
+    }
+    if candidato.is_none() {
+        if let Some(email) = identidade.email.as_deref() {
+            let por_email: Vec<String> = sqlx::query_scalar("SELECT id FROM users WHERE email = ?1 COLLATE NOCASE LIMIT 2")
+                .bind(email.trim())
+                .fetch_all(db_pool)
+                .await?;
+            if por_email.len() == 1 {
+                candidato = por_email.into_iter().next();
+            }
+        }
+    }
+
+    if let Some(user_id) = &candidato {
+        sqlx::query("INSERT OR IGNORE INTO user_identidades_oidc (sub, user_id) VALUES (?1, ?2)")
+            .bind(&identidade.sub)
+            .bind(user_id)
+            .execute(db_pool)
+            .await?;
+        tracing::info!("🔗 Conta institucional {} ligada ao utilizador {}.", identidade.sub, user_id);
+    }
+    Ok(candidato)
+}
@@ -0,0 +1,92 @@
+// src/services/organizacao_service.rs
+//! Organizações (companhias/unidades) servidas pela instância. Utilizadores, postos, grupos e escalas
+//! pertencem a uma organização; os serviços recebem a organização do pedido (`OrganizacaoId`, ver mw_auth)
+//! e só veem os dados dessa. Gestão na área de super-admin (/superadmin).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::organizacao::Organizacao,
+};
+use sqlx::SqlitePool;
+
+/// Organização criada pela migração, com os dados anteriores ao suporte de várias organizações.
+pub const ORGANIZACAO_PRINCIPAL: i64 = 1;
+
+const SELECT_ORGANIZACOES: &str = r#"
+    SELECT o.id, o.codigo, o.nome, o.ativa, o.criado_em,
+           (SELECT COUNT(*) FROM users u WHERE u.organizacao_id = o.id) AS utilizadores
+    FROM organizacoes o
+"#;
+
+/// Todas as organizações, por nome.
+pub async fn listar(db_pool: &SqlitePool) -> AppResult<Vec<Organizacao>> {
+    let organizacoes = sqlx::query_as::<_, Organizacao>(&format!("{} ORDER BY o.nome", SELECT_ORGANIZACOES))
+        .fetch_all(db_pool)
+        .await?;
+    Ok(organizacoes)
+}
+
+/// Busca uma organização pelo ID.
+pub async fn find(db_pool: &SqlitePool, id: i64) -> AppResult<Option<Organizacao>> {
+    let organizacao = sqlx::query_as::<_, Organizacao>(&format!("{} WHERE o.id = ?1", SELECT_ORGANIZACOES))
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?;
+    Ok(organizacao)
+}
+
+/// Organização de um utilizador e se está ativa (None se o utilizador não existir).
+pub async fn do_utilizador(db_pool: &SqlitePool, user_id: &str) -> AppResult<Option<(i64, bool)>> {
+    let organizacao = sqlx::query_as::<_, (i64, bool)>(
+        "SELECT o.id, o.ativa FROM users u JOIN organizacoes o ON o.id = u.organizacao_id WHERE u.id = ?1",
+    )
+    .bind(user_id)
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(organizacao)
+}
+
+/// Cria uma organização e devolve o seu ID. O código é um identificador curto (letras, números, '-' ou '_').
+pub async fn criar(db_pool: &SqlitePool, codigo: &str, nome: &str) -> AppResult<i64> {
+    let codigo = codigo.trim();
+    let nome = nome.trim();
+    if codigo.is_empty() || !codigo.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(AppError::validation("codigo", "Código inválido (use letras, números, '-' ou '_')."));
+    }
+    if nome.is_empty() {
+        return Err(AppError::validation("nome", "Indique o nome da organização."));
+    }
+
+    let result = sqlx::query("INSERT INTO organizacoes (codigo, nome) VALUES (?1, ?2)")
+        .bind(codigo)
+        .bind(nome)
+        .execute(db_pool)
+        .await;
+
+    match result {
+        Ok(r) => {
+            tracing::info!("✅ Organização '{}' ({}) criada.", nome, codigo);
+            Ok(r.last_insert_rowid())
+        }
+        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+            Err(AppError::Conflict(format!("Já existe uma organização com o código '{}'.", codigo)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Ativa ou desativa uma organização (desativada, os seus utilizadores deixam de ter acesso).
+/// Devolve o nome da organização.
+pub async fn definir_ativa(db_pool: &SqlitePool, id: i64, ativa: bool) -> AppResult<String> {
+    if id == ORGANIZACAO_PRINCIPAL && !ativa {
+        return Err(AppError::Conflict("A organização principal não pode ser desativada.".into()));
+    }
+    let nome = sqlx::query_scalar::<_, String>("UPDATE organizacoes SET ativa = ?1 WHERE id = ?2 RETURNING nome")
+        .bind(ativa)
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organização não encontrada.".into()))?;
+    tracing::info!("Organização '{}' {}.", nome, if ativa { "ativada" } else { "desativada" });
+    Ok(nome)
+}
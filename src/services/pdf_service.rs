@@ -0,0 +1,202 @@
+// src/services/pdf_service.rs
+//! Geração de PDFs simples (A4 ao alto): texto em Helvetica, linhas, retângulos e fotografias JPEG.
+//! Usado no efetivo por turma (/admin/users/roster.pdf). Não há composição de texto: quem desenha
+//! indica as coordenadas (em pontos, origem no canto inferior esquerdo) e corta o que não cabe
+//! (ver `ajustar`). O texto é escrito em WinAnsiEncoding, que cobre os acentos do português.
+//! Os JPEG são incluídos tal como foram enviados (DCTDecode), sem os descodificar.
+
+use std::fmt::Write as _;
+
+/// Dimensões de uma página A4, em pontos.
+pub const LARGURA_A4: f32 = 595.0;
+pub const ALTURA_A4: f32 = 842.0;
+
+/// Largura média de um carácter de Helvetica, em fração do tamanho da letra (para `ajustar`).
+const LARGURA_MEDIA: f32 = 0.55;
+
+/// Fotografia JPEG incluída no documento.
+struct Imagem {
+    jpeg: Vec<u8>,
+    largura: i64,
+    altura: i64,
+    componentes: i64, // 1 = cinzentos, 3 = cor
+}
+
+/// Documento em construção: as páginas (operadores de desenho) e as imagens que usam.
+#[derive(Default)]
+pub struct Pdf {
+    paginas: Vec<Vec<u8>>,
+    imagens: Vec<Imagem>,
+}
+
+/// Carácter em Windows-1252 (a codificação das fontes base); o que não existe passa a '?'.
+fn cp1252(c: char) -> u8 {
+    match c as u32 {
+        0x20..=0x7E | 0xA0..=0xFF => c as u32 as u8,
+        _ => match c {
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            _ => b'?',
+        },
+    }
+}
+
+/// Texto como string literal de PDF: "(...)", com `(`, `)` e `\` escapados.
+fn literal(texto: &str) -> Vec<u8> {
+    let mut bytes = vec![b'('];
+    for c in texto.chars() {
+        let b = cp1252(c);
+        if matches!(b, b'(' | b')' | b'\\') {
+            bytes.push(b'\\');
+        }
+        bytes.push(b);
+    }
+    bytes.push(b')');
+    bytes
+}
+
+/// Corta um texto para caber (aproximadamente) na largura indicada, com reticências.
+pub fn ajustar(texto: &str, tamanho: f32, largura: f32) -> String {
+    let max = (largura / (tamanho * LARGURA_MEDIA)) as usize;
+    if texto.chars().count() <= max {
+        return texto.to_string();
+    }
+    let mut cortado: String = texto.chars().take(max.saturating_sub(1)).collect();
+    cortado.push('…');
+    cortado
+}
+
+impl Pdf {
+    /// Acrescenta uma imagem JPEG (com as dimensões e componentes lidos no envio). Devolve o índice
+    /// a usar em `imagem`.
+    pub fn incluir_jpeg(&mut self, jpeg: Vec<u8>, largura: i64, altura: i64, componentes: i64) -> usize {
+        self.imagens.push(Imagem { jpeg, largura, altura, componentes });
+        self.imagens.len() - 1
+    }
+
+    /// Começa uma página nova; o desenho seguinte vai para ela.
+    pub fn nova_pagina(&mut self) {
+        self.paginas.push(Vec::new());
+    }
+
+    /// Número de páginas até agora.
+    pub fn paginas(&self) -> usize {
+        self.paginas.len()
+    }
+
+    /// Operadores da página atual (cria a primeira, se ainda não houver).
+    fn pagina(&mut self) -> &mut Vec<u8> {
+        if self.paginas.is_empty() {
+            self.nova_pagina();
+        }
+        self.paginas.last_mut().expect("há sempre uma página")
+    }
+
+    /// Texto com a linha de base em (x, y); `cinzento` de 0 (preto) a 1 (branco).
+    pub fn texto(&mut self, x: f32, y: f32, tamanho: f32, negrito: bool, cinzento: f32, texto: &str) {
+        let fonte = if negrito { "F2" } else { "F1" };
+        let pagina = self.pagina();
+        pagina.extend_from_slice(format!("BT {:.2} g /{} {:.1} Tf {:.2} {:.2} Td ", cinzento, fonte, tamanho, x, y).as_bytes());
+        pagina.extend(literal(texto));
+        pagina.extend_from_slice(b" Tj ET 0 g\n");
+    }
+
+    /// Linha de (x1, y1) a (x2, y2), com a espessura indicada.
+    pub fn linha(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, espessura: f32) {
+        let operadores = format!("{:.2} w {:.2} {:.2} m {:.2} {:.2} l S\n", espessura, x1, y1, x2, y2);
+        self.pagina().extend_from_slice(operadores.as_bytes());
+    }
+
+    /// Retângulo com o canto inferior esquerdo em (x, y): contorno fino ou, com `preenchimento`
+    /// (0 = preto, 1 = branco), só o fundo.
+    pub fn retangulo(&mut self, x: f32, y: f32, largura: f32, altura: f32, preenchimento: Option<f32>) {
+        let operadores = match preenchimento {
+            Some(cinzento) => format!("{:.2} g {:.2} {:.2} {:.2} {:.2} re f 0 g\n", cinzento, x, y, largura, altura),
+            None => format!("0.5 w {:.2} {:.2} {:.2} {:.2} re S\n", x, y, largura, altura),
+        };
+        self.pagina().extend_from_slice(operadores.as_bytes());
+    }
+
+    /// Desenha a imagem `indice` dentro da caixa indicada, centrada e sem a deformar.
+    pub fn imagem(&mut self, indice: usize, x: f32, y: f32, largura: f32, altura: f32) {
+        let Some(imagem) = self.imagens.get(indice) else {
+            return;
+        };
+        let escala = (largura / imagem.largura as f32).min(altura / imagem.altura as f32);
+        let (l, a) = (imagem.largura as f32 * escala, imagem.altura as f32 * escala);
+        let (ix, iy) = (x + (largura - l) / 2.0, y + (altura - a) / 2.0);
+        let operadores = format!("q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q\n", l, a, ix, iy, indice);
+        self.pagina().extend_from_slice(operadores.as_bytes());
+    }
+
+    /// O ficheiro PDF: catálogo, árvore de páginas, as duas fontes, as imagens e cada página com o
+    /// seu conteúdo, seguidos da tabela de referências cruzadas.
+    pub fn gerar(mut self) -> Vec<u8> {
+        if self.paginas.is_empty() {
+            self.nova_pagina();
+        }
+        // Objetos: 1 catálogo, 2 páginas, 3-4 fontes, depois as imagens e um par (página, conteúdo) por página
+        let primeira_pagina = 5 + self.imagens.len();
+        let mut objetos: Vec<Vec<u8>> = Vec::new();
+        objetos.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        let kids: Vec<String> = (0..self.paginas.len()).map(|i| format!("{} 0 R", primeira_pagina + 2 * i)).collect();
+        objetos.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), self.paginas.len()).into_bytes());
+        for fonte in ["Helvetica", "Helvetica-Bold"] {
+            objetos.push(
+                format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", fonte).into_bytes(),
+            );
+        }
+        let mut xobjects = String::new();
+        for (i, imagem) in self.imagens.iter().enumerate() {
+            let _ = write!(xobjects, " /Im{} {} 0 R", i, 5 + i);
+            let espaco = if imagem.componentes == 1 { "DeviceGray" } else { "DeviceRGB" };
+            let mut objeto = format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+                imagem.largura, imagem.altura, espaco, imagem.jpeg.len()
+            )
+            .into_bytes();
+            objeto.extend_from_slice(&imagem.jpeg);
+            objeto.extend_from_slice(b"\nendstream");
+            objetos.push(objeto);
+        }
+        let recursos = format!("<< /Font << /F1 3 0 R /F2 4 0 R >> /XObject <<{} >> >>", xobjects);
+        for (i, conteudo) in self.paginas.iter().enumerate() {
+            objetos.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources {} /Contents {} 0 R >>",
+                    LARGURA_A4, ALTURA_A4, recursos, primeira_pagina + 2 * i + 1
+                )
+                .into_bytes(),
+            );
+            let mut objeto = format!("<< /Length {} >>\nstream\n", conteudo.len()).into_bytes();
+            objeto.extend_from_slice(conteudo);
+            objeto.extend_from_slice(b"\nendstream");
+            objetos.push(objeto);
+        }
+
+        // Cabeçalho com bytes acima de 127: os leitores tratam o ficheiro como binário
+        let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut posicoes = Vec::with_capacity(objetos.len());
+        for (i, objeto) in objetos.iter().enumerate() {
+            posicoes.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend_from_slice(objeto);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        let mut tabela = format!("xref\n0 {}\n0000000000 65535 f \n", objetos.len() + 1);
+        for posicao in posicoes {
+            let _ = writeln!(tabela, "{:010} 00000 n ", posicao);
+        }
+        let _ = writeln!(tabela, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF", objetos.len() + 1, xref);
+        pdf.extend_from_slice(tabela.as_bytes());
+        pdf
+    }
+}
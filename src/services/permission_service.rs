@@ -0,0 +1,269 @@
+// src/services/permission_service.rs
+use crate::{
+    error::{AppError, AppResult},
+    models::permission::RoleDef,
+    services::user_service,
+    state::{MatrizPermissoes, PermissionCache},
+};
+use sqlx::SqlitePool;
+
+// --- Permissões conhecidas (verificadas pelos middlewares/handlers) ---
+pub const PERM_ADMIN: &str = "admin";
+pub const PERM_PRESENCA: &str = "presenca";
+pub const PERM_ESCALA_GERIR: &str = "escala.gerir";
+pub const PERM_USERS_PESQUISAR: &str = "users.pesquisar";
+pub const PERM_RANCHO: &str = "rancho";
+pub const PERM_LOJA: &str = "loja";
+pub const PERM_LIVRO: &str = "livro";
+pub const PERM_LIVRO_ESCREVER: &str = "livro.escrever";
+pub const PERM_REVISTA: &str = "revista";
+pub const PERM_CAUTELA: &str = "cautela";
+pub const PERM_BAIXAS: &str = "baixas";
+pub const PERM_AGENDA: &str = "agenda";
+pub const PERM_DOCUMENTOS: &str = "documentos";
+pub const PERM_DISCIPLINA: &str = "disciplina";
+pub const PERM_HORARIO: &str = "horario";
+pub const PERM_UNIFORME: &str = "uniforme";
+pub const PERM_PORTARIA: &str = "portaria";
+pub const PERM_SUGESTOES: &str = "sugestoes";
+pub const PERM_CONCEITO: &str = "conceito";
+pub const PERM_COMITIVAS: &str = "comitivas";
+pub const PERM_CHAMADA: &str = "chamada";
+pub const PERM_CLAVICULARIO: &str = "claviculario";
+pub const PERM_TFM: &str = "tfm";
+pub const PERM_ENFERMARIA: &str = "enfermaria";
+pub const PERM_BIBLIOTECA: &str = "biblioteca";
+pub const PERM_LAVANDERIA: &str = "lavanderia";
+pub const PERM_ENQUETES: &str = "enquetes";
+pub const PERM_SUPERADMIN: &str = "superadmin";
+
+/// Role de sistema com a administração da instância (ver a migração das organizações).
+pub const ROLE_SUPERADMIN: &str = "superadmin";
+
+/// Todas as permissões, com a descrição mostrada na matriz do admin.
+pub const PERMISSOES: &[(&str, &str)] = &[
+    (PERM_ADMIN, "Área de administração"),
+    (PERM_PRESENCA, "Módulo de presença"),
+    (PERM_ESCALA_GERIR, "Painel do escalante"),
+    (PERM_USERS_PESQUISAR, "Pesquisa de utilizadores (sugestões nos formulários)"),
+    (PERM_RANCHO, "Rancho: previsão do arranchamento e refeições"),
+    (PERM_LOJA, "Loja: ponto de venda e compras dos utilizadores"),
+    (PERM_LIVRO, "Livro de ocorrências: consulta e pesquisa"),
+    (PERM_LIVRO_ESCREVER, "Livro de ocorrências: registar ocorrências e fechar o dia (chefe de dia)"),
+    (PERM_REVISTA, "Revistas: listas de verificação, registo e estatísticas por turma"),
+    (PERM_CAUTELA, "Cautelas: catálogo do material, entregas, devoluções e atrasos"),
+    (PERM_BAIXAS, "Baixas médicas: registo das dispensas (secção de saúde)"),
+    (PERM_AGENDA, "Agenda: criar, alterar e apagar formaturas, cerimónias e palestras"),
+    (PERM_DOCUMENTOS, "Documentos: pastas, visibilidade, envio de PDFs e registo das descargas"),
+    (PERM_DISCIPLINA, "Disciplina: registo e decisão dos processos (FATD) e punições"),
+    (PERM_HORARIO, "Horário: aulas das turmas e postos diurnos da escala"),
+    (PERM_UNIFORME, "Uniforme do dia: definir o uniforme de cada dia (chefe de dia)"),
+    (PERM_PORTARIA, "Portaria: entradas e saídas de viaturas e entregas (polícia)"),
+    (PERM_SUGESTOES, "Sugestões: triagem e resposta (o autor das anónimas nunca é mostrado)"),
+    (PERM_CONCEITO, "Conceito: elogios, méritos e deméritos, e a classificação por turma"),
+    (PERM_COMITIVAS, "Comitivas: criar, gerir os membros e encerrar (fora da escala e da presença)"),
+    (PERM_CHAMADA, "Plano de chamada: cascatas por turma/grupo, impressão e exercícios"),
+    (PERM_CLAVICULARIO, "Claviculário: registo das chaves, levantamentos, devoluções e atrasos"),
+    (PERM_TFM, "TFM: registar os resultados do teste físico e as estatísticas por turma"),
+    (PERM_ENFERMARIA, "Enfermaria: vagas, agenda das consultas e dispensas que delas resultem"),
+    (PERM_BIBLIOTECA, "Biblioteca: catálogo, empréstimos, devoluções e atrasos"),
+    (PERM_LAVANDERIA, "Lavanderia: entrega e retirada dos sacos, aviso de pronto e sacos por retirar"),
+    (PERM_ENQUETES, "Enquetes: criar, encerrar e exportar os resultados (o voto das anónimas nunca é mostrado)"),
+    (PERM_SUPERADMIN, "Administração da instância (todas as organizações)"),
+];
+
+/// Lista todas as roles definidas, por nome.
+pub async fn listar_roles(db_pool: &SqlitePool) -> AppResult<Vec<RoleDef>> {
+    let roles = sqlx::query_as::<_, RoleDef>(
+        "SELECT nome, descricao, permanente, temporaria, sistema FROM roles ORDER BY nome",
+    )
+    .fetch_all(db_pool)
+    .await?;
+    Ok(roles)
+}
+
+/// Nomes das roles atribuíveis no perfil do utilizador.
+pub async fn roles_permanentes(db_pool: &SqlitePool) -> AppResult<Vec<String>> {
+    let nomes = sqlx::query_scalar::<_, String>("SELECT nome FROM roles WHERE permanente = 1 ORDER BY nome")
+        .fetch_all(db_pool)
+        .await?;
+    Ok(nomes)
+}
+
+/// Nomes das roles que podem ser atribuídas temporariamente.
+pub async fn roles_temporarias(db_pool: &SqlitePool) -> AppResult<Vec<String>> {
+    let nomes = sqlx::query_scalar::<_, String>("SELECT nome FROM roles WHERE temporaria = 1 ORDER BY nome")
+        .fetch_all(db_pool)
+        .await?;
+    Ok(nomes)
+}
+
+/// Pares (role, permissão) atualmente concedidos.
+pub async fn listar_matriz(db_pool: &SqlitePool) -> AppResult<Vec<(String, String)>> {
+    let pares = sqlx::query_as::<_, (String, String)>("SELECT role, permissao FROM role_permissoes")
+        .fetch_all(db_pool)
+        .await?;
+    Ok(pares)
+}
+
+/// Cria uma nova role.
+pub async fn criar_role(
+    db_pool: &SqlitePool,
+    nome: &str,
+    descricao: &str,
+    permanente: bool,
+    temporaria: bool,
+) -> AppResult<()> {
+    let nome = nome.trim();
+    if nome.is_empty() || !nome.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+        return Err(AppError::validation("nome", "Nome da role inválido (use letras, números, '_' ou '.')."));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO roles (nome, descricao, permanente, temporaria) VALUES (?1, ?2, ?3, ?4)",
+    )
+    .bind(nome)
+    .bind(descricao.trim())
+    .bind(permanente)
+    .bind(temporaria)
+    .execute(db_pool)
+    .await;
+
+    match result {
+        Ok(_) => {
+            tracing::info!("✅ Role '{}' criada.", nome);
+            Ok(())
+        }
+        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+            Err(AppError::Conflict(format!("A role '{}' já existe.", nome)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Apaga uma role (e as respetivas atribuições permanentes). Roles de sistema são recusadas.
+pub async fn remover_role(db_pool: &SqlitePool, cache: &PermissionCache, nome: &str) -> AppResult<()> {
+    let sistema = sqlx::query_scalar::<_, bool>("SELECT sistema FROM roles WHERE nome = ?1")
+        .bind(nome)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Role '{}' não encontrada.", nome)))?;
+
+    if sistema {
+        return Err(AppError::Conflict(format!("A role '{}' é de sistema e não pode ser apagada.", nome)));
+    }
+
+    let mut tx = db_pool.begin().await?;
+    sqlx::query("DELETE FROM role_permissoes WHERE role = ?1").bind(nome).execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM user_roles WHERE role = ?1").bind(nome).execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM roles WHERE nome = ?1").bind(nome).execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    invalidar_cache(cache).await;
+    tracing::info!("🗑️ Role '{}' apagada.", nome);
+    Ok(())
+}
+
+/// Substitui toda a matriz de permissões numa transação.
+/// As `roles_admin` (ver `config::RolesConfig`) mantêm sempre a permissão de administração e a role
+/// `superadmin` a de super-admin (para não ficar ninguém sem acesso à área da instância).
+pub async fn definir_matriz(
+    db_pool: &SqlitePool,
+    cache: &PermissionCache,
+    pares: &[(String, String)],
+    roles_admin: &[String],
+) -> AppResult<()> {
+    let mut tx = db_pool.begin().await?;
+    sqlx::query("DELETE FROM role_permissoes").execute(&mut *tx).await?;
+
+    for (role, permissao) in pares {
+        if !PERMISSOES.iter().any(|(p, _)| p == permissao) {
+            return Err(AppError::validation("matriz", format!("Permissão desconhecida: '{}'.", permissao)));
+        }
+        // Ignora roles inexistentes (ex: apagada noutro separador entretanto)
+        sqlx::query(
+            "INSERT OR IGNORE INTO role_permissoes (role, permissao) SELECT nome, ?2 FROM roles WHERE nome = ?1",
+        )
+        .bind(role)
+        .bind(permissao)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for role in roles_admin {
+        sqlx::query("INSERT OR IGNORE INTO role_permissoes (role, permissao) SELECT nome, ?2 FROM roles WHERE nome = ?1")
+            .bind(role)
+            .bind(PERM_ADMIN)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query("INSERT OR IGNORE INTO role_permissoes (role, permissao) SELECT nome, ?2 FROM roles WHERE nome = ?1")
+        .bind(ROLE_SUPERADMIN)
+        .bind(PERM_SUPERADMIN)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    invalidar_cache(cache).await;
+    tracing::info!("✅ Matriz de permissões atualizada ({} entradas).", pares.len());
+    Ok(())
+}
+
+/// Esquece a matriz em memória; a próxima verificação volta a lê-la da base de dados.
+pub async fn invalidar_cache(cache: &PermissionCache) {
+    *cache.matriz.write().await = None;
+}
+
+/// Roles que concedem a permissão indicada (lidas da cache, carregando-a se necessário).
+async fn roles_com_permissao(
+    db_pool: &SqlitePool,
+    cache: &PermissionCache,
+    permissao: &str,
+) -> AppResult<Vec<String>> {
+    if let Some(matriz) = cache.matriz.read().await.as_ref() {
+        return Ok(matriz.get(permissao).cloned().unwrap_or_default());
+    }
+
+    let mut guard = cache.matriz.write().await;
+    // Outra task pode ter carregado a matriz enquanto esperávamos pelo lock
+    if guard.is_none() {
+        tracing::debug!("Carregando matriz de permissões da base de dados");
+        let mut matriz = MatrizPermissoes::new();
+        for (role, perm) in listar_matriz(db_pool).await? {
+            matriz.entry(perm).or_default().push(role);
+        }
+        *guard = Some(matriz);
+    }
+    Ok(guard
+        .as_ref()
+        .and_then(|m| m.get(permissao).cloned())
+        .unwrap_or_default())
+}
+
+/// Verifica se alguma das roles (já carregadas, ver `CurrentUser`) concede a permissão.
+/// Só vai à base de dados se a matriz ainda não estiver em memória.
+pub async fn roles_tem_permissao(
+    db_pool: &SqlitePool,
+    cache: &PermissionCache,
+    roles: &[String],
+    permissao: &str,
+) -> AppResult<bool> {
+    let concedem = roles_com_permissao(db_pool, cache, permissao).await?;
+    Ok(roles.iter().any(|role| concedem.iter().any(|c| c.eq_ignore_ascii_case(role))))
+}
+
+/// Verifica se o utilizador tem a permissão através de alguma role (permanente ou temporária ativa).
+pub async fn user_has_permission(
+    db_pool: &SqlitePool,
+    cache: &PermissionCache,
+    user_id: &str,
+    permissao: &str,
+) -> AppResult<bool> {
+    let roles = roles_com_permissao(db_pool, cache, permissao).await?;
+    if roles.is_empty() {
+        // Ninguém tem a permissão (check_user_role_any trataria lista vazia como "acesso livre")
+        return Ok(false);
+    }
+    let roles: Vec<&str> = roles.iter().map(String::as_str).collect();
+    user_service::check_user_role_any(db_pool, user_id, &roles).await
+}
@@ -0,0 +1,146 @@
+// src/services/portaria_service.rs
+//! Controlo de portaria: a entrada de viaturas e entregas (matrícula, condutor e motivo) e a saída, o que
+//! está dentro neste momento e os movimentos de um dia (com exportação). Separado da presença (pessoal) e
+//! dos visitantes (pessoas recebidas por um utilizador).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::portaria::MovimentoPortaria,
+    tempo,
+};
+use chrono::{Duration, NaiveDate};
+use sqlx::SqlitePool;
+
+/// Tipos de entrada (código, descrição).
+pub const TIPOS: &[(&str, &str)] = &[
+    ("viatura", "Viatura"),
+    ("entrega", "Entrega"),
+];
+
+/// Descrição de um tipo de entrada.
+pub fn descrever_tipo(tipo: &str) -> &str {
+    TIPOS.iter().find(|(c, _)| *c == tipo).map_or(tipo, |(_, d)| *d)
+}
+
+/// Matrícula normalizada: maiúsculas, sem espaços nas pontas nem repetidos.
+pub fn normalizar_matricula(matricula: &str) -> String {
+    matricula.split_whitespace().collect::<Vec<_>>().join(" ").to_uppercase()
+}
+
+/// Movimentos da organização. `?2` = só os que ainda estão dentro; `?3`/`?4` = só os com a entrada
+/// neste intervalo (UTC); `?5` = só este movimento.
+const SQL_MOVIMENTOS: &str = r#"
+    SELECT p.id, p.tipo, p.matricula, p.condutor, p.motivo,
+           p.entrada_em, COALESCE(e.name, p.entrada_por) AS entrada_por,
+           p.saida_em, COALESCE(s.name, p.saida_por) AS saida_por
+    FROM portaria p
+    LEFT JOIN users e ON e.id = p.entrada_por
+    LEFT JOIN users s ON s.id = p.saida_por
+    WHERE p.organizacao_id = ?1
+      AND (?2 = 0 OR p.saida_em IS NULL)
+      AND (?3 IS NULL OR p.entrada_em >= ?3)
+      AND (?4 IS NULL OR p.entrada_em < ?4)
+      AND (?5 IS NULL OR p.id = ?5)
+    ORDER BY p.entrada_em, p.id
+"#;
+
+/// Viaturas e entregas dentro neste momento (pela hora de entrada).
+pub async fn dentro(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<MovimentoPortaria>> {
+    let movimentos = sqlx::query_as::<_, MovimentoPortaria>(SQL_MOVIMENTOS)
+        .bind(organizacao_id)
+        .bind(true)
+        .bind(None::<String>)
+        .bind(None::<String>)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(movimentos)
+}
+
+/// Movimentos com a entrada no dia `data` (no fuso configurado).
+pub async fn do_dia(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<Vec<MovimentoPortaria>> {
+    let movimentos = sqlx::query_as::<_, MovimentoPortaria>(SQL_MOVIMENTOS)
+        .bind(organizacao_id)
+        .bind(false)
+        .bind(tempo::inicio_dia_utc(data))
+        .bind(tempo::inicio_dia_utc(data + Duration::days(1)))
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(movimentos)
+}
+
+/// Um movimento pelo id (da organização).
+pub async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<MovimentoPortaria> {
+    sqlx::query_as::<_, MovimentoPortaria>(SQL_MOVIMENTOS)
+        .bind(organizacao_id)
+        .bind(false)
+        .bind(None::<String>)
+        .bind(None::<String>)
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Registo de portaria {} não encontrado.", id)))
+}
+
+/// Regista a entrada de uma viatura ou entrega (campos já validados). Devolve o movimento criado.
+pub async fn registar_entrada(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    tipo: &str,
+    matricula: &str,
+    condutor: &str,
+    motivo: &str,
+    operador_id: &str,
+) -> AppResult<MovimentoPortaria> {
+    if !TIPOS.iter().any(|(c, _)| *c == tipo) {
+        return Err(AppError::validation("tipo", "Tipo de entrada inválido."));
+    }
+    let matricula = normalizar_matricula(matricula);
+    // A mesma viatura não entra duas vezes sem ter saído
+    let dentro: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM portaria WHERE organizacao_id = ?1 AND matricula = ?2 AND saida_em IS NULL)",
+    )
+    .bind(organizacao_id)
+    .bind(&matricula)
+    .fetch_one(db_pool)
+    .await?;
+    if dentro {
+        return Err(AppError::validation("matricula", format!("A viatura {} já está dentro (registe primeiro a saída).", matricula)));
+    }
+
+    let id = sqlx::query(
+        r#"
+        INSERT INTO portaria (organizacao_id, tipo, matricula, condutor, motivo, entrada_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(tipo)
+    .bind(&matricula)
+    .bind(condutor.trim())
+    .bind(motivo.trim())
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+    tracing::info!("🚗 {} {} ({}) entrou com {}, registado por {}.", descrever_tipo(tipo), matricula, id, condutor.trim(), operador_id);
+    obter(db_pool, organizacao_id, id).await
+}
+
+/// Regista a saída de uma viatura ou entrega. Devolve o movimento (já com a saída).
+pub async fn registar_saida(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<MovimentoPortaria> {
+    let movimento = obter(db_pool, organizacao_id, id).await?;
+    // Duas saídas ao mesmo tempo (dois postos): só a primeira conta
+    let saiu = sqlx::query("UPDATE portaria SET saida_em = datetime('now'), saida_por = ?2 WHERE id = ?1 AND saida_em IS NULL")
+        .bind(id)
+        .bind(operador_id)
+        .execute(db_pool)
+        .await?
+        .rows_affected();
+    if saiu == 0 {
+        return Err(AppError::Conflict(format!("A saída de {} já foi registada.", movimento.matricula)));
+    }
+    tracing::info!("🚗 {} ({}) saiu, registado por {}.", movimento.matricula, id, operador_id);
+    obter(db_pool, organizacao_id, id).await
+}
@@ -0,0 +1,76 @@
+// src/services/preferencias_service.rs
+//! Preferências da interface de cada utilizador (tema, turma da presença, linhas por página, avisos),
+//! guardadas em `user_preferences` para serem as mesmas em todos os dispositivos. São carregadas com o
+//! `CurrentUser` (ver `mw_auth`); quem não tem linha usa os valores por omissão de `Preferencias`.
+
+use crate::{
+    error::AppResult,
+    models::preferencias::Preferencias,
+    services::notificacao_service,
+};
+use sqlx::SqlitePool;
+
+/// Temas da interface ('sistema' segue o do sistema operativo).
+pub const TEMAS: &[&str] = &["claro", "escuro", "sistema"];
+
+/// Preferências do utilizador (as por omissão, se nunca as alterou).
+pub async fn obter(db_pool: &SqlitePool, user_id: &str) -> AppResult<Preferencias> {
+    let preferencias = sqlx::query_as::<_, Preferencias>(
+        r#"
+        SELECT tema, turma_presenca, por_pagina, avisos_escala, avisos_troca, avisos_lembrete
+        FROM user_preferences WHERE user_id = ?1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(preferencias.unwrap_or_default())
+}
+
+/// Guarda as preferências do utilizador (já validadas pelo formulário).
+pub async fn guardar(db_pool: &SqlitePool, user_id: &str, preferencias: &Preferencias) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_preferences (user_id, tema, turma_presenca, por_pagina, avisos_escala, avisos_troca, avisos_lembrete)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        ON CONFLICT (user_id) DO UPDATE SET
+            tema = excluded.tema,
+            turma_presenca = excluded.turma_presenca,
+            por_pagina = excluded.por_pagina,
+            avisos_escala = excluded.avisos_escala,
+            avisos_troca = excluded.avisos_troca,
+            avisos_lembrete = excluded.avisos_lembrete,
+            atualizado_em = datetime('now')
+        "#,
+    )
+    .bind(user_id)
+    .bind(&preferencias.tema)
+    .bind(preferencias.turma_presenca)
+    .bind(preferencias.por_pagina)
+    .bind(preferencias.avisos_escala)
+    .bind(preferencias.avisos_troca)
+    .bind(preferencias.avisos_lembrete)
+    .execute(db_pool)
+    .await?;
+    tracing::debug!("⚙️ Preferências de {} guardadas.", user_id);
+    Ok(())
+}
+
+/// Se o utilizador quer receber fora do painel (Telegram, email) os avisos do tipo `tipo`
+/// (tipos de `notificacao_service`, que os emails também usam). Os restantes (segurança, senha) vão sempre.
+/// Em caso de erro na leitura, envia (um aviso a mais é melhor do que um aviso perdido).
+pub async fn aceita_aviso(db_pool: &SqlitePool, user_id: &str, tipo: &str) -> bool {
+    let preferencias = match obter(db_pool, user_id).await {
+        Ok(preferencias) => preferencias,
+        Err(e) => {
+            tracing::error!("Falha ao ler as preferências de avisos de {}: {:?}", user_id, e);
+            return true;
+        }
+    };
+    match tipo {
+        notificacao_service::TIPO_ESCALA => preferencias.avisos_escala,
+        notificacao_service::TIPO_TROCA => preferencias.avisos_troca,
+        notificacao_service::TIPO_LEMBRETE => preferencias.avisos_lembrete,
+        _ => true,
+    }
+}
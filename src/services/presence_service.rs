@@ -5,9 +5,10 @@ use crate::{
         presence::{PresenceEntry, PresencePerson, PresenceStats}, // Modelos de presença
         user::User, // Modelo User para obter dados básicos
     },
-    services::user_service, // Para buscar todos os users de uma turma
+    services::{baixa_service, comitiva_service, enfermaria_service, grupo_service, quarto_service, user_service, webhook_service}, // Users de uma turma/grupo; quartos; baixas; comitivas; enfermaria; eventos de atraso
+    tempo,
 };
-use chrono::{DateTime, Local}; // Para trabalhar com data/hora local
+use chrono::{DateTime, Utc}; // Marcações guardadas e comparadas em UTC
 use sqlx::SqlitePool;
 use std::collections::HashMap; // Para mapear entradas de presença por user_id
 
@@ -18,8 +19,8 @@ pub async fn marcar_saida(
     user_id: &str,
     operator_id: &str, // ID do operador que fez a marcação
 ) -> AppResult<()> {
-    // Obtém a data/hora atual e formata como string ISO 8601/RFC3339
-    let now_str = Local::now().to_rfc3339();
+    // Obtém a data/hora atual (UTC) e formata como string RFC3339
+    let now_str = tempo::carimbo_utc();
     tracing::debug!(
         "Marcando SAÍDA para user {} por {} em {}",
         user_id,
@@ -53,7 +54,7 @@ pub async fn marcar_retorno(
     user_id: &str,
     operator_id: &str, // ID do operador que fez a marcação
 ) -> AppResult<()> {
-    let now_str = Local::now().to_rfc3339();
+    let now_str = tempo::carimbo_utc();
     tracing::debug!(
         "Marcando RETORNO para user {} por {} em {}",
         user_id,
@@ -79,9 +80,10 @@ pub async fn marcar_retorno(
     Ok(())
 }
 
-/// Busca a lista combinada de utilizadores e estado de presença para uma turma.
+/// Busca a lista combinada de utilizadores e estado de presença para uma turma da organização.
 pub async fn get_presence_list_for_turma(
     db_pool: &SqlitePool,
+    organizacao_id: i64,
     turma_num: i64, // Usar i64 para corresponder ao 'ano' na DB
 ) -> AppResult<Vec<PresencePerson>> {
     tracing::debug!("Buscando lista de presença para turma {}", turma_num);
@@ -89,10 +91,10 @@ pub async fn get_presence_list_for_turma(
     // 1. Busca todos os utilizadores da turma especificada
     //    (Idealmente, user_service teria uma função find_users_by_turma)
     //    Por agora, buscamos todos e filtramos. Cuidado com a performance se houver muitos users.
-    let all_users = user_service::find_all_users(db_pool).await?;
+    let all_users = user_service::find_all_users(db_pool, organizacao_id).await?;
     let users_in_turma: Vec<User> = all_users
         .into_iter()
-        .filter(|u| u.ano == turma_num)
+        .filter(|u| u.ativo && u.ano == turma_num)
         .collect();
 
     if users_in_turma.is_empty() {
@@ -100,6 +102,53 @@ pub async fn get_presence_list_for_turma(
         return Ok(Vec::new()); // Retorna lista vazia se a turma não tiver alunos
     }
 
+    let presence_list = montar_lista_presenca(db_pool, users_in_turma).await?;
+    tracing::debug!("Lista de presença para turma {} carregada ({} pessoas).", turma_num, presence_list.len());
+    Ok(presence_list)
+}
+
+/// Busca a lista de presença dos membros de um grupo (pelotão, companhia, equipa) da organização.
+pub async fn get_presence_list_for_grupo(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    grupo_id: i64,
+) -> AppResult<Vec<PresencePerson>> {
+    tracing::debug!("Buscando lista de presença para grupo {}", grupo_id);
+
+    let membros = grupo_service::ids_membros(db_pool, grupo_id).await?;
+    let users_in_grupo: Vec<User> = user_service::find_all_users(db_pool, organizacao_id)
+        .await?
+        .into_iter()
+        .filter(|u| u.ativo && membros.contains(&u.id))
+        .collect();
+
+    let presence_list = montar_lista_presenca(db_pool, users_in_grupo).await?;
+    tracing::debug!("Lista de presença para grupo {} carregada ({} pessoas).", grupo_id, presence_list.len());
+    Ok(presence_list)
+}
+
+/// Busca a lista de presença de todos os utilizadores ativos da organização (relatório de pernoite).
+pub async fn get_presence_list_for_organizacao(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<PresencePerson>> {
+    let users: Vec<User> = user_service::find_all_users(db_pool, organizacao_id)
+        .await?
+        .into_iter()
+        .filter(|u| u.ativo)
+        .collect();
+    montar_lista_presenca(db_pool, users).await
+}
+
+/// Estado de presença de um utilizador.
+pub async fn presenca_de(db_pool: &SqlitePool, user: User) -> AppResult<PresencePerson> {
+    let mut lista = montar_lista_presenca(db_pool, vec![user]).await?;
+    lista.pop().ok_or(AppError::InternalServerError)
+}
+
+/// Combina uma lista de utilizadores com o respetivo estado de presença.
+async fn montar_lista_presenca(db_pool: &SqlitePool, users_in_turma: Vec<User>) -> AppResult<Vec<PresencePerson>> {
+    if users_in_turma.is_empty() {
+        return Ok(Vec::new());
+    }
+
     // Extrai os IDs dos utilizadores da turma para a query de presença
     let user_ids: Vec<String> = users_in_turma.iter().map(|u| u.id.clone()).collect();
 
@@ -119,6 +168,15 @@ pub async fn get_presence_list_for_turma(
     .fetch_all(db_pool)
     .await?;
 
+    // Quarto e corredor de cada um (todos da mesma organização)
+    let alojamentos = quarto_service::alojamentos(db_pool, users_in_turma[0].organizacao_id).await?;
+    // Baixas médicas em vigor hoje (assinaladas na lista)
+    let baixas = baixa_service::ativas(db_pool, users_in_turma[0].organizacao_id, tempo::hoje()).await?;
+    // Comitivas em curso: os membros contam como fora enquanto a comitiva estiver aberta
+    let comitivas = comitiva_service::em_curso(db_pool, users_in_turma[0].organizacao_id, tempo::hoje()).await?;
+    // Consultas a decorrer: assinaladas na lista (a bordo, na enfermaria)
+    let enfermaria = enfermaria_service::na_enfermaria(db_pool, users_in_turma[0].organizacao_id).await?;
+
     // Mapeia as entradas de presença por user_id para acesso rápido
     let presence_map: HashMap<String, PresenceEntry> = all_presence_entries
         .into_iter()
@@ -131,29 +189,36 @@ pub async fn get_presence_list_for_turma(
         // Obtém a entrada de presença para este user (ou default se não existir)
         let entry = presence_map.get(&user.id).cloned().unwrap_or_default();
 
-        // Tenta fazer o parse das strings de data/hora para DateTime<Local>
+        // Tenta fazer o parse das strings de data/hora para DateTime<Utc>
         let ultima_saida_dt = entry.ultima_saida.as_ref().and_then(|s| {
             DateTime::parse_from_rfc3339(s)
-                .map(|dt| dt.with_timezone(&Local)) // Converte para timezone local
+                .map(|dt| dt.with_timezone(&Utc)) // Registos antigos podem ter outro fuso
                 .map_err(|e| tracing::warn!("Erro ao parsear ultima_saida para {}: {}", user.id, e)) // Loga erro de parse
                 .ok() // Descarta o erro, resultando em None se falhar
         });
         let ultimo_retorno_dt = entry.ultimo_retorno.as_ref().and_then(|s| {
              DateTime::parse_from_rfc3339(s)
-                .map(|dt| dt.with_timezone(&Local))
+                .map(|dt| dt.with_timezone(&Utc))
                 .map_err(|e| tracing::warn!("Erro ao parsear ultimo_retorno para {}: {}", user.id, e))
                 .ok()
         });
 
 
-        // Calcula se está fora
-        let esta_fora = match (&ultima_saida_dt, &ultimo_retorno_dt) {
-            (Some(saida), Some(retorno)) => saida > retorno, // Compara DateTime<Local>
+        // Calcula se está fora (numa comitiva em curso está sempre)
+        let comitiva = comitivas.get(&user.id).cloned();
+        let esta_fora = comitiva.is_some() || match (&ultima_saida_dt, &ultimo_retorno_dt) {
+            (Some(saida), Some(retorno)) => saida > retorno, // Compara DateTime<Utc>
             (Some(_), None) => true, // Tem saída mas não tem retorno -> Fora
             _ => false, // Sem saída OU retorno mais recente -> Dentro
         };
 
+        let alojamento = alojamentos.get(&user.id);
         presence_list.push(PresencePerson {
+            quarto: alojamento.map(|a| a.quarto.clone()),
+            corredor: alojamento.map(|a| a.corredor.clone()),
+            baixa: baixas.get(&user.id).cloned(),
+            comitiva,
+            enfermaria: enfermaria.get(&user.id).cloned(),
             id: user.id,
             nome: user.name,
             turma: user.turma,
@@ -169,7 +234,6 @@ pub async fn get_presence_list_for_turma(
     // Ordena a lista pelo ID do utilizador
     presence_list.sort_by(|a, b| a.id.cmp(&b.id));
 
-    tracing::debug!("Lista de presença para turma {} carregada ({} pessoas).", turma_num, presence_list.len());
     Ok(presence_list)
 }
 
@@ -188,4 +252,81 @@ pub fn calcular_stats(pessoas: &[PresencePerson]) -> PresenceStats {
         dentro: total - fora,
         total,
     }
-}
\ No newline at end of file
+}
+
+/// Horas fora sem retorno até a saída contar como atrasada, se PRESENCE_OVERDUE_HOURS não estiver definida.
+const LIMITE_ATRASO_HORAS_PADRAO: i64 = 12;
+/// Intervalo entre verificações de saídas atrasadas.
+const INTERVALO_ATRASOS: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// Horas fora (sem retorno) a partir das quais a saída conta como atrasada (PRESENCE_OVERDUE_HOURS).
+pub fn limite_atraso_from_env() -> Result<i64, String> {
+    match std::env::var("PRESENCE_OVERDUE_HOURS") {
+        Ok(v) if !v.trim().is_empty() => v
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|h| *h >= 1)
+            .ok_or(format!("PRESENCE_OVERDUE_HOURS inválido: '{}'", v)),
+        _ => Ok(LIMITE_ATRASO_HORAS_PADRAO),
+    }
+}
+
+/// Inicia a tarefa que procura periodicamente saídas atrasadas (ver `avisar_atrasos`).
+pub fn iniciar_verificacao_atrasos(db_pool: SqlitePool, limite_horas: i64) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = avisar_atrasos(&db_pool, limite_horas).await {
+                tracing::error!("Erro na verificação de saídas atrasadas: {:?}", e);
+            }
+            tokio::time::sleep(INTERVALO_ATRASOS).await;
+        }
+    });
+}
+
+/// Emite `presenca.atrasada` (webhooks) para quem saiu há mais de `limite_horas` sem retorno.
+/// Cada saída só é comunicada uma vez (`presenca.atraso_avisado` guarda a saída já avisada).
+pub async fn avisar_atrasos(db_pool: &SqlitePool, limite_horas: i64) -> AppResult<usize> {
+    let candidatos: Vec<(String, String, String, String, Option<String>)> = sqlx::query_as(
+        r#"
+        SELECT p.user_id, u.name, u.turma, p.ultima_saida, p.ultimo_retorno
+        FROM presenca p
+        JOIN users u ON u.id = p.user_id
+        WHERE p.ultima_saida IS NOT NULL AND (p.atraso_avisado IS NULL OR p.atraso_avisado != p.ultima_saida)
+        "#,
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let limite = Utc::now() - chrono::Duration::hours(limite_horas);
+    let mut avisados = 0;
+    for (user_id, nome, turma, ultima_saida, ultimo_retorno) in candidatos {
+        let Ok(saida) = DateTime::parse_from_rfc3339(&ultima_saida) else { continue };
+        let retorno = ultimo_retorno.as_deref().and_then(|r| DateTime::parse_from_rfc3339(r).ok());
+        let esta_fora = retorno.is_none_or(|r| saida > r);
+        if !esta_fora || saida > limite {
+            continue;
+        }
+
+        webhook_service::emitir(
+            db_pool,
+            webhook_service::EVENTO_PRESENCA_ATRASADA,
+            serde_json::json!({
+                "user_id": user_id,
+                "nome": nome,
+                "turma": turma,
+                "saida": ultima_saida,
+                "limite_horas": limite_horas,
+            }),
+        )
+        .await;
+        sqlx::query("UPDATE presenca SET atraso_avisado = ?1 WHERE user_id = ?2 AND ultima_saida = ?1")
+            .bind(&ultima_saida)
+            .bind(&user_id)
+            .execute(db_pool)
+            .await?;
+        tracing::info!("⏰ Saída de {} ({}) atrasada: fora desde {}.", user_id, nome, ultima_saida);
+        avisados += 1;
+    }
+    Ok(avisados)
+}
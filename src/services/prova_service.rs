@@ -0,0 +1,185 @@
+// src/services/prova_service.rs
+//! Calendário das provas (/admin/provas): a administração regista a data e a disciplina da prova de
+//! uma turma e, na mesma transação, os utilizadores ativos da turma ficam indisponíveis para a escala na
+//! véspera e no dia (indisponibilidades com `prova_id`). Apagar a prova apaga essas indisponibilidades.
+//! Quem entrar na turma depois do registo não fica abrangido.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::prova::Prova,
+    services::horario_service,
+};
+use chrono::{Duration, NaiveDate};
+use sqlx::SqlitePool;
+
+/// Provas realizadas mostradas no histórico.
+pub const REALIZADAS_RECENTES: i64 = 30;
+
+/// Provas da organização. `?2`/`?3` = só as a partir de / antes deste dia.
+const SQL_PROVAS: &str = r#"
+    SELECT p.id, p.turma, p.data, p.disciplina, p.observacoes,
+           COALESCE(r.name, p.registada_por) AS registada_por, p.registada_em,
+           (SELECT COUNT(*) FROM indisponibilidades i WHERE i.prova_id = p.id) AS bloqueados
+    FROM provas p
+    LEFT JOIN users r ON r.id = p.registada_por
+    WHERE p.organizacao_id = ?1
+      AND (?2 IS NULL OR p.data >= ?2)
+      AND (?3 IS NULL OR p.data < ?3)
+    ORDER BY CASE WHEN ?2 IS NOT NULL THEN p.data END, p.data DESC, p.turma, p.disciplina
+    LIMIT ?4
+"#;
+
+/// Provas a partir de `hoje`, por data.
+pub async fn proximas(db_pool: &SqlitePool, organizacao_id: i64, hoje: NaiveDate) -> AppResult<Vec<Prova>> {
+    let provas = sqlx::query_as::<_, Prova>(SQL_PROVAS)
+        .bind(organizacao_id)
+        .bind(hoje.to_string())
+        .bind(None::<String>)
+        .bind(i64::MAX)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(provas)
+}
+
+/// Últimas provas antes de `hoje` (as mais recentes primeiro).
+pub async fn realizadas(db_pool: &SqlitePool, organizacao_id: i64, hoje: NaiveDate, limite: i64) -> AppResult<Vec<Prova>> {
+    let provas = sqlx::query_as::<_, Prova>(SQL_PROVAS)
+        .bind(organizacao_id)
+        .bind(None::<String>)
+        .bind(hoje.to_string())
+        .bind(limite)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(provas)
+}
+
+/// Uma prova pelo id (da organização).
+async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Prova> {
+    sqlx::query_as::<_, Prova>(
+        r#"
+        SELECT p.id, p.turma, p.data, p.disciplina, p.observacoes, p.registada_por, p.registada_em,
+               (SELECT COUNT(*) FROM indisponibilidades i WHERE i.prova_id = p.id) AS bloqueados
+        FROM provas p
+        WHERE p.id = ?1 AND p.organizacao_id = ?2
+        "#,
+    )
+    .bind(id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Prova {} não encontrada.", id)))
+}
+
+/// Prova registada: o ID, os utilizadores fora da escala e os que já estavam escalados na véspera ou
+/// no dia (a escala desses dias tem de ser regenerada ou os serviços trocados).
+#[derive(Debug)]
+pub struct ProvaRegistada {
+    pub id: i64,
+    pub bloqueados: u64,
+    pub ja_escalados: Vec<String>,
+}
+
+/// Regista uma prova da turma e as indisponibilidades da véspera e do dia para os seus utilizadores
+/// ativos. A data não pode já ter passado.
+#[allow(clippy::too_many_arguments)]
+pub async fn registar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    turma: &str,
+    data: NaiveDate,
+    disciplina: &str,
+    observacoes: &str,
+    hoje: NaiveDate,
+    operador_id: &str,
+) -> AppResult<ProvaRegistada> {
+    if !horario_service::turmas(db_pool, organizacao_id).await?.iter().any(|t| t == turma) {
+        return Err(AppError::validation("turma", format!("A turma '{}' não tem utilizadores ativos.", turma)));
+    }
+    if data < hoje {
+        return Err(AppError::validation("data", "A data da prova já passou."));
+    }
+    let disciplina = disciplina.trim();
+    let repetida: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM provas WHERE organizacao_id = ?1 AND turma = ?2 AND data = ?3 AND disciplina = ?4)",
+    )
+    .bind(organizacao_id)
+    .bind(turma)
+    .bind(data.to_string())
+    .bind(disciplina)
+    .fetch_one(db_pool)
+    .await?;
+    if repetida {
+        return Err(AppError::validation("disciplina", format!("A prova de {} da turma {} já está registada nesse dia.", disciplina, turma)));
+    }
+
+    let vespera = (data - Duration::days(1)).to_string();
+    let dia = data.to_string();
+    let mut tx = db_pool.begin().await?;
+    let id = sqlx::query(
+        "INSERT INTO provas (organizacao_id, turma, data, disciplina, observacoes, registada_por) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )
+    .bind(organizacao_id)
+    .bind(turma)
+    .bind(&dia)
+    .bind(disciplina)
+    .bind(observacoes.trim())
+    .bind(operador_id)
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+    let bloqueados = sqlx::query(
+        r#"
+        INSERT INTO indisponibilidades (user_id, data_inicio, data_fim, motivo, prova_id)
+        SELECT id, ?3, ?4, ?5, ?6 FROM users
+        WHERE organizacao_id = ?1 AND turma = ?2 AND ativo = 1
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(turma)
+    .bind(&vespera)
+    .bind(&dia)
+    .bind(format!("Prova de {} ({})", disciplina, data.format("%d/%m")))
+    .bind(id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    let ja_escalados: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT DISTINCT u.name FROM alocacoes a
+        JOIN users u ON u.id = a.user_id
+        WHERE u.organizacao_id = ?1 AND u.turma = ?2 AND u.ativo = 1 AND a.data BETWEEN ?3 AND ?4
+        ORDER BY u.name
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(turma)
+    .bind(&vespera)
+    .bind(&dia)
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    tracing::info!(
+        "📝 Prova {} ({} da turma {} em {}) registada por {}: {} utilizadores fora da escala, {} já escalados.",
+        id, disciplina, turma, dia, operador_id, bloqueados, ja_escalados.len()
+    );
+    Ok(ProvaRegistada { id, bloqueados, ja_escalados })
+}
+
+/// Apaga uma prova (cancelada ou registada por engano) e as indisponibilidades que criou.
+/// Devolve a prova apagada.
+pub async fn remover(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Prova> {
+    let prova = obter(db_pool, organizacao_id, id).await?;
+    let mut tx = db_pool.begin().await?;
+    sqlx::query("DELETE FROM indisponibilidades WHERE prova_id = ?1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM provas WHERE id = ?1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!("🗑️ Prova {} ({} da turma {}) apagada com {} indisponibilidades.", id, prova.disciplina, prova.turma, prova.bloqueados);
+    Ok(prova)
+}
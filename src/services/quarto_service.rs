@@ -0,0 +1,282 @@
+// src/services/quarto_service.rs
+//! Alojamento: os quartos da organização (por corredor, com o número de camas) e a cama de cada
+//! utilizador. Uma nova atribuição substitui a anterior (mudança de quarto); a cama, se não for
+//! indicada, é a primeira livre. Serve a página de presença e o relatório de pernoite (agrupados por
+//! quarto/corredor) e as revistas (o quarto de quem é revistado).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::{
+        presence::PresencePerson,
+        quarto::{Alojamento, Ocupante, PernoiteQuarto, Quarto},
+    },
+};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// Camas máximas de um quarto.
+pub const MAX_CAPACIDADE: i64 = 40;
+
+/// Quartos da organização, por corredor e nome, com os ocupantes.
+pub async fn listar(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<Quarto>> {
+    let quartos = sqlx::query_as::<_, (i64, String, String, i64, bool)>(
+        "SELECT id, nome, corredor, capacidade, ativo FROM quartos WHERE organizacao_id = ?1 ORDER BY corredor, nome",
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    let ocupantes = sqlx::query_as::<_, Ocupante>(
+        r#"
+        SELECT o.user_id, u.name, u.turma, o.quarto_id, o.cama
+        FROM quarto_ocupantes o
+        JOIN quartos q ON q.id = o.quarto_id
+        JOIN users u ON u.id = o.user_id
+        WHERE q.organizacao_id = ?1
+        ORDER BY o.cama
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+
+    Ok(quartos
+        .into_iter()
+        .map(|(id, nome, corredor, capacidade, ativo)| Quarto {
+            ocupantes: ocupantes.iter().filter(|o| o.quarto_id == id).cloned().collect(),
+            id,
+            nome,
+            corredor,
+            capacidade,
+            ativo,
+        })
+        .collect())
+}
+
+/// Cria um quarto. Devolve o ID.
+pub async fn criar(db_pool: &SqlitePool, organizacao_id: i64, nome: &str, corredor: &str, capacidade: i64) -> AppResult<i64> {
+    let resultado = sqlx::query("INSERT INTO quartos (organizacao_id, nome, corredor, capacidade) VALUES (?1, ?2, ?3, ?4)")
+        .bind(organizacao_id)
+        .bind(nome.trim())
+        .bind(corredor.trim())
+        .bind(capacidade)
+        .execute(db_pool)
+        .await;
+    match resultado {
+        Ok(r) => Ok(r.last_insert_rowid()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::validation("nome", "Já existe um quarto com este nome."))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Altera o corredor, as camas e o estado de um quarto. As camas não podem ficar abaixo da mais alta
+/// ocupada. Devolve o nome do quarto.
+pub async fn atualizar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    corredor: &str,
+    capacidade: i64,
+    ativo: bool,
+) -> AppResult<String> {
+    let nome: String = sqlx::query_scalar("SELECT nome FROM quartos WHERE id = ?1 AND organizacao_id = ?2")
+        .bind(id)
+        .bind(organizacao_id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Quarto {} não encontrado.", id)))?;
+    let cama_ocupada: Option<i64> = sqlx::query_scalar("SELECT MAX(cama) FROM quarto_ocupantes WHERE quarto_id = ?1")
+        .bind(id)
+        .fetch_one(db_pool)
+        .await?;
+    if let Some(cama) = cama_ocupada.filter(|c| *c > capacidade) {
+        return Err(AppError::validation(
+            "capacidade",
+            format!("O quarto {} tem a cama {} ocupada; mude primeiro o ocupante.", nome, cama),
+        ));
+    }
+    sqlx::query("UPDATE quartos SET corredor = ?2, capacidade = ?3, ativo = ?4 WHERE id = ?1")
+        .bind(id)
+        .bind(corredor.trim())
+        .bind(capacidade)
+        .bind(ativo)
+        .execute(db_pool)
+        .await?;
+    Ok(nome)
+}
+
+/// Atribui ao utilizador a cama `cama` (ou a primeira livre) do quarto `quarto_id`, substituindo a
+/// atribuição anterior. Devolve o nome do quarto, a cama e o quarto anterior (se mudou de quarto).
+pub async fn atribuir(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: &str,
+    quarto_id: i64,
+    cama: Option<i64>,
+    operador_id: &str,
+) -> AppResult<(String, i64, Option<String>)> {
+    let mut tx = db_pool.begin().await?;
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    let quarto: Option<(String, i64, bool)> =
+        sqlx::query_as("SELECT nome, capacidade, ativo FROM quartos WHERE id = ?1 AND organizacao_id = ?2")
+            .bind(quarto_id)
+            .bind(organizacao_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    let Some((nome, capacidade, ativo)) = quarto else {
+        return Err(AppError::validation("quarto_id", "Quarto não encontrado."));
+    };
+    if !ativo {
+        return Err(AppError::validation("quarto_id", format!("O quarto {} está inativo.", nome)));
+    }
+    let anterior: Option<String> = sqlx::query_scalar(
+        "SELECT q.nome FROM quarto_ocupantes o JOIN quartos q ON q.id = o.quarto_id WHERE o.user_id = ?1 AND o.quarto_id <> ?2",
+    )
+    .bind(user_id)
+    .bind(quarto_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    // Camas ocupadas por outros (a do próprio, se já está no quarto, fica livre para a troca)
+    let ocupadas: Vec<i64> = sqlx::query_scalar("SELECT cama FROM quarto_ocupantes WHERE quarto_id = ?1 AND user_id <> ?2")
+        .bind(quarto_id)
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    let cama = match cama {
+        Some(c) if !(1..=capacidade).contains(&c) => {
+            return Err(AppError::validation("cama", format!("O quarto {} tem as camas 1 a {}.", nome, capacidade)));
+        }
+        Some(c) if ocupadas.contains(&c) => {
+            return Err(AppError::validation("cama", format!("A cama {} do quarto {} está ocupada.", c, nome)));
+        }
+        Some(c) => c,
+        None => (1..=capacidade)
+            .find(|c| !ocupadas.contains(c))
+            .ok_or_else(|| AppError::validation("quarto_id", format!("O quarto {} está cheio.", nome)))?,
+    };
+
+    let resultado = sqlx::query(
+        r#"
+        INSERT INTO quarto_ocupantes (user_id, quarto_id, cama, atribuido_por) VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT (user_id) DO UPDATE SET
+            quarto_id = excluded.quarto_id, cama = excluded.cama,
+            atribuido_por = excluded.atribuido_por, atribuido_em = datetime('now')
+        "#,
+    )
+    .bind(user_id)
+    .bind(quarto_id)
+    .bind(cama)
+    .bind(operador_id)
+    .execute(&mut *tx)
+    .await;
+    match resultado {
+        Ok(_) => {}
+        // Outra atribuição ocupou a cama entretanto
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(AppError::Conflict(format!("A cama {} do quarto {} acabou de ser ocupada.", cama, nome)));
+        }
+        Err(e) => return Err(e.into()),
+    }
+    tx.commit().await?;
+    tracing::info!("🛏️ {} atribuído ao quarto {} (cama {}) por {}.", user_id, nome, cama, operador_id);
+    Ok((nome, cama, anterior))
+}
+
+/// Retira o utilizador do quarto. Devolve o quarto de onde saiu (None se não tinha quarto).
+pub async fn remover(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str) -> AppResult<Option<String>> {
+    let quarto: Option<(i64, String)> = sqlx::query_as(
+        "SELECT q.id, q.nome FROM quarto_ocupantes o JOIN quartos q ON q.id = o.quarto_id WHERE o.user_id = ?1 AND q.organizacao_id = ?2",
+    )
+    .bind(user_id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?;
+    let Some((quarto_id, nome)) = quarto else {
+        return Ok(None);
+    };
+    sqlx::query("DELETE FROM quarto_ocupantes WHERE user_id = ?1 AND quarto_id = ?2")
+        .bind(user_id)
+        .bind(quarto_id)
+        .execute(db_pool)
+        .await?;
+    Ok(Some(nome))
+}
+
+/// Utilizadores ativos da organização ainda sem quarto (ID, nome, turma), por ID.
+pub async fn sem_quarto(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<(String, String, String)>> {
+    let users = sqlx::query_as(
+        r#"
+        SELECT u.id, u.name, u.turma FROM users u
+        WHERE u.organizacao_id = ?1 AND u.ativo = 1
+          AND NOT EXISTS (SELECT 1 FROM quarto_ocupantes o WHERE o.user_id = u.id)
+        ORDER BY u.id
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(users)
+}
+
+/// Quarto, corredor e cama de cada utilizador alojado da organização, por ID.
+pub async fn alojamentos(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<HashMap<String, Alojamento>> {
+    let alojamentos = sqlx::query_as::<_, Alojamento>(
+        r#"
+        SELECT o.user_id, q.nome AS quarto, q.corredor
+        FROM quarto_ocupantes o
+        JOIN quartos q ON q.id = o.quarto_id
+        WHERE q.organizacao_id = ?1
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(alojamentos.into_iter().map(|a| (a.user_id.clone(), a)).collect())
+}
+
+/// Nome do quarto atribuído ao utilizador, se tiver.
+pub async fn quarto_de(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str) -> AppResult<Option<String>> {
+    let quarto = sqlx::query_scalar(
+        "SELECT q.nome FROM quarto_ocupantes o JOIN quartos q ON q.id = o.quarto_id WHERE o.user_id = ?1 AND q.organizacao_id = ?2",
+    )
+    .bind(user_id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(quarto)
+}
+
+/// Ordena uma lista de presença por corredor, quarto e ID (os sem quarto no fim).
+pub fn ordenar_por_quarto(pessoas: &mut [PresencePerson]) {
+    pessoas.sort_by(|a, b| {
+        (a.quarto.is_none(), &a.corredor, &a.quarto, &a.id).cmp(&(b.quarto.is_none(), &b.corredor, &b.quarto, &b.id))
+    });
+}
+
+/// Relatório de pernoite: por quarto (já ordenado com `ordenar_por_quarto`), quantos lá dormem e
+/// quem está fora. Os utilizadores sem quarto ficam numa linha à parte, no fim.
+pub fn pernoite(pessoas: &[PresencePerson]) -> Vec<PernoiteQuarto> {
+    let mut linhas: Vec<PernoiteQuarto> = Vec::new();
+    for p in pessoas {
+        let corredor = p.corredor.clone().unwrap_or_default();
+        if !matches!(linhas.last(), Some(l) if l.quarto == p.quarto && l.corredor == corredor) {
+            linhas.push(PernoiteQuarto { corredor, quarto: p.quarto.clone(), total: 0, fora: Vec::new() });
+        }
+        if let Some(linha) = linhas.last_mut() {
+            linha.total += 1;
+            if p.esta_fora {
+                linha.fora.push(p.clone());
+            }
+        }
+    }
+    linhas
+}
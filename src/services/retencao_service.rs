@@ -0,0 +1,145 @@
+// src/services/retencao_service.rs
+//! Retenção dos registos que crescem sem limite (auditoria, histórico de logins, trocas resolvidas).
+//! A tarefa diária `retencao.purga` apaga as linhas mais antigas do que os prazos de `[retencao]`
+//! (ver config.rs), depois de somar os totais por mês em `retencao_resumos`. Com `simular`, só conta.
+//! A presença não entra aqui: a tabela `presenca` guarda só o último estado de cada utilizador.
+
+use crate::{
+    config::RetencaoConfig,
+    error::AppResult,
+    models::retencao::{PoliticaRetencao, ResumoRetencao},
+};
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+/// Uma tabela sujeita a retenção (todas têm `criado_em` em UTC, 'YYYY-MM-DD HH:MM:SS').
+struct Tabela {
+    nome: &'static str,
+    descricao: &'static str,
+    /// Linhas que nunca se apagam, seja qual for a idade.
+    guardar: &'static str,
+    /// Expressão SQL do que é contado no resumo mensal.
+    chave: &'static str,
+}
+
+const AUDITORIA: Tabela = Tabela {
+    nome: "audit_log",
+    descricao: "Registo de auditoria",
+    guardar: "0",
+    chave: "acao",
+};
+
+const LOGINS: Tabela = Tabela {
+    nome: "login_history",
+    descricao: "Histórico de logins",
+    guardar: "0",
+    chave: "CASE WHEN sucesso THEN 'sucesso' ELSE 'falha:' || COALESCE(motivo, '?') END",
+};
+
+const TROCAS: Tabela = Tabela {
+    nome: "trocas",
+    descricao: "Trocas resolvidas",
+    // As pendentes e as que deram origem a uma dívida ainda por pagar
+    guardar: "status = 'Pendente' OR id IN (SELECT origem_troca_id FROM dividas WHERE status = 'PENDENTE' AND origem_troca_id IS NOT NULL)",
+    chave: "COALESCE(tipo, 'Cobertura') || ':' || COALESCE(status, '?')",
+};
+
+/// As tabelas com o prazo configurado para cada uma (0 = guardar para sempre).
+fn politicas(config: &RetencaoConfig) -> [(&'static Tabela, i64); 3] {
+    [(&AUDITORIA, config.auditoria_dias), (&LOGINS, config.logins_dias), (&TROCAS, config.trocas_dias)]
+}
+
+/// Data (UTC) antes da qual os registos são apagados.
+fn limite(dias: i64) -> String {
+    (Utc::now() - Duration::days(dias)).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+async fn contar(db_pool: &SqlitePool, tabela: &Tabela, limite: &str) -> AppResult<i64> {
+    let total = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM {} WHERE criado_em < ?1 AND NOT ({})",
+        tabela.nome, tabela.guardar
+    ))
+    .bind(limite)
+    .fetch_one(db_pool)
+    .await?;
+    Ok(total)
+}
+
+/// O que a próxima limpeza apagaria em cada tabela (simulação, nada é apagado).
+pub async fn relatorio(db_pool: &SqlitePool, config: &RetencaoConfig) -> AppResult<Vec<PoliticaRetencao>> {
+    let mut relatorio = Vec::new();
+    for (tabela, dias) in politicas(config) {
+        let (limite, a_apagar) = if dias > 0 {
+            let limite = limite(dias);
+            let a_apagar = contar(db_pool, tabela, &limite).await?;
+            (Some(limite), a_apagar)
+        } else {
+            (None, 0)
+        };
+        relatorio.push(PoliticaRetencao { tabela: tabela.nome, descricao: tabela.descricao, dias, limite, a_apagar });
+    }
+    Ok(relatorio)
+}
+
+/// Apaga os registos fora do prazo, tabela a tabela, cada uma numa transação: primeiro soma-os em
+/// `retencao_resumos` (por mês e chave), depois apaga-os. Devolve o resumo para a página das tarefas.
+pub async fn purgar(db_pool: &SqlitePool, config: &RetencaoConfig) -> AppResult<String> {
+    let mut partes = Vec::new();
+    for (tabela, dias) in politicas(config).into_iter().filter(|(_, dias)| *dias > 0) {
+        let limite = limite(dias);
+        if config.simular {
+            let a_apagar = contar(db_pool, tabela, &limite).await?;
+            partes.push(format!("{}: {} a apagar", tabela.nome, a_apagar));
+            continue;
+        }
+
+        let mut tx = db_pool.begin().await?;
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO retencao_resumos (tabela, mes, chave, total)
+            SELECT ?1, substr(criado_em, 1, 7), {}, COUNT(*)
+            FROM {} WHERE criado_em < ?2 AND NOT ({})
+            GROUP BY 1, 2, 3
+            ON CONFLICT (tabela, mes, chave) DO UPDATE SET
+                total = total + excluded.total,
+                atualizado_em = datetime('now')
+            "#,
+            tabela.chave, tabela.nome, tabela.guardar
+        ))
+        .bind(tabela.nome)
+        .bind(&limite)
+        .execute(&mut *tx)
+        .await?;
+        let apagados = sqlx::query(&format!(
+            "DELETE FROM {} WHERE criado_em < ?1 AND NOT ({})",
+            tabela.nome, tabela.guardar
+        ))
+        .bind(&limite)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+
+        if apagados > 0 {
+            tracing::info!("🧹 Retenção: {} registo(s) de {} anteriores a {} apagados.", apagados, tabela.nome, limite);
+        }
+        partes.push(format!("{}: {} apagado(s)", tabela.nome, apagados));
+    }
+
+    Ok(match (partes.is_empty(), config.simular) {
+        (true, _) => "Retenção desativada (nenhum prazo definido).".to_string(),
+        (false, true) => format!("Simulação, nada apagado — {}.", partes.join("; ")),
+        (false, false) => format!("{}.", partes.join("; ")),
+    })
+}
+
+/// Totais dos registos já apagados, dos meses mais recentes para os mais antigos.
+pub async fn resumos(db_pool: &SqlitePool, limite: i64) -> AppResult<Vec<ResumoRetencao>> {
+    let resumos = sqlx::query_as::<_, ResumoRetencao>(
+        "SELECT tabela, mes, chave, total FROM retencao_resumos ORDER BY mes DESC, tabela, total DESC LIMIT ?1",
+    )
+    .bind(limite)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(resumos)
+}
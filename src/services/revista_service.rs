@@ -0,0 +1,302 @@
+// src/services/revista_service.rs
+//! Revistas aos quartos: listas de verificação (itens com pontos), o registo de cada revista a um
+//! utilizador com os itens em falta, as reincidências (o mesmo item em falta várias vezes num período)
+//! e as estatísticas por turma, que servem a ordem de mérito semanal. A pontuação de uma revista é a
+//! soma dos pontos dos itens cumpridos; o máximo fica guardado com ela (a lista pode mudar depois).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::revista::{EstatisticaTurma, ItemRevista, ListaRevista, Reincidencia, RevistaResumo},
+};
+use chrono::{Duration, NaiveDate};
+use sqlx::SqlitePool;
+
+/// Pontos máximos de um item.
+pub const MAX_PONTOS: i64 = 10;
+/// Revistas mostradas na lista das mais recentes.
+pub const REVISTAS_RECENTES: i64 = 50;
+/// Período (dias) em que um item em falta repetido conta como reincidência.
+pub const JANELA_REINCIDENCIA_DIAS: i64 = 30;
+/// Vezes em falta, dentro da janela, a partir das quais há reincidência.
+pub const MIN_REINCIDENCIA: i64 = 2;
+
+// --- LISTAS DE VERIFICAÇÃO ---
+
+/// Listas da organização, por nome, com os itens (só as ativas, se `so_ativas`).
+pub async fn listar_listas(db_pool: &SqlitePool, organizacao_id: i64, so_ativas: bool) -> AppResult<Vec<ListaRevista>> {
+    let listas = sqlx::query_as::<_, (i64, String, bool)>(
+        "SELECT id, nome, ativa FROM revista_listas WHERE organizacao_id = ?1 AND (?2 = 0 OR ativa = 1) ORDER BY nome",
+    )
+    .bind(organizacao_id)
+    .bind(so_ativas)
+    .fetch_all(db_pool)
+    .await?;
+    let itens = sqlx::query_as::<_, ItemRevista>(
+        r#"
+        SELECT i.id, i.lista_id, i.descricao, i.pontos, i.ativo
+        FROM revista_itens i
+        JOIN revista_listas l ON l.id = i.lista_id
+        WHERE l.organizacao_id = ?1
+        ORDER BY i.ordem, i.id
+        "#,
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+
+    Ok(listas
+        .into_iter()
+        .map(|(id, nome, ativa)| ListaRevista {
+            itens: itens.iter().filter(|i| i.lista_id == id).cloned().collect(),
+            id,
+            nome,
+            ativa,
+        })
+        .collect())
+}
+
+/// Cria uma lista de verificação (ainda sem itens).
+pub async fn criar_lista(db_pool: &SqlitePool, organizacao_id: i64, nome: &str) -> AppResult<i64> {
+    let resultado = sqlx::query("INSERT INTO revista_listas (organizacao_id, nome) VALUES (?1, ?2)")
+        .bind(organizacao_id)
+        .bind(nome.trim())
+        .execute(db_pool)
+        .await;
+    match resultado {
+        Ok(r) => Ok(r.last_insert_rowid()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::validation("nome", "Já existe uma lista com este nome."))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Nome de uma lista da organização (NotFound se não existir).
+async fn nome_lista(db_pool: &SqlitePool, organizacao_id: i64, lista_id: i64) -> AppResult<String> {
+    sqlx::query_scalar("SELECT nome FROM revista_listas WHERE id = ?1 AND organizacao_id = ?2")
+        .bind(lista_id)
+        .bind(organizacao_id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Lista {} não encontrada.", lista_id)))
+}
+
+/// Acrescenta um item ao fim de uma lista. Devolve o nome da lista.
+pub async fn adicionar_item(db_pool: &SqlitePool, organizacao_id: i64, lista_id: i64, descricao: &str, pontos: i64) -> AppResult<String> {
+    let lista = nome_lista(db_pool, organizacao_id, lista_id).await?;
+    sqlx::query(
+        r#"
+        INSERT INTO revista_itens (lista_id, descricao, pontos, ordem)
+        VALUES (?1, ?2, ?3, (SELECT COALESCE(MAX(ordem), 0) + 1 FROM revista_itens WHERE lista_id = ?1))
+        "#,
+    )
+    .bind(lista_id)
+    .bind(descricao.trim())
+    .bind(pontos)
+    .execute(db_pool)
+    .await?;
+    Ok(lista)
+}
+
+/// Ativa ou desativa uma lista. Devolve o nome.
+pub async fn definir_lista_ativa(db_pool: &SqlitePool, organizacao_id: i64, lista_id: i64, ativa: bool) -> AppResult<String> {
+    let lista = nome_lista(db_pool, organizacao_id, lista_id).await?;
+    sqlx::query("UPDATE revista_listas SET ativa = ?2 WHERE id = ?1")
+        .bind(lista_id)
+        .bind(ativa)
+        .execute(db_pool)
+        .await?;
+    Ok(lista)
+}
+
+/// Ativa ou desativa um item (um item desativado deixa de contar nas novas revistas).
+/// Devolve a descrição do item.
+pub async fn definir_item_ativo(db_pool: &SqlitePool, organizacao_id: i64, item_id: i64, ativo: bool) -> AppResult<String> {
+    let descricao: String = sqlx::query_scalar(
+        r#"
+        SELECT i.descricao FROM revista_itens i JOIN revista_listas l ON l.id = i.lista_id
+        WHERE i.id = ?1 AND l.organizacao_id = ?2
+        "#,
+    )
+    .bind(item_id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Item {} não encontrado.", item_id)))?;
+    sqlx::query("UPDATE revista_itens SET ativo = ?2 WHERE id = ?1")
+        .bind(item_id)
+        .bind(ativo)
+        .execute(db_pool)
+        .await?;
+    Ok(descricao)
+}
+
+// --- REVISTAS ---
+
+/// Resultado do registo de uma revista.
+#[derive(Debug)]
+pub struct RevistaRegistada {
+    pub id: i64,
+    pub pontuacao: i64,
+    pub maximo: i64,
+    /// Itens agora em falta que já o estavam (descrição, vezes na janela de reincidência).
+    pub reincidencias: Vec<(String, i64)>,
+}
+
+/// Regista a revista de um utilizador com a lista `lista_id`; `falhas` são os itens em falta (só contam
+/// os itens ativos da lista). Falha se a lista não estiver ativa ou o utilizador não existir.
+#[allow(clippy::too_many_arguments)]
+pub async fn registar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    lista_id: i64,
+    user_id: &str,
+    quarto: &str,
+    data: NaiveDate,
+    falhas: &[i64],
+    observacoes: &str,
+    inspetor_id: &str,
+) -> AppResult<RevistaRegistada> {
+    let lista = listar_listas(db_pool, organizacao_id, true)
+        .await?
+        .into_iter()
+        .find(|l| l.id == lista_id)
+        .ok_or_else(|| AppError::validation("lista_id", "Lista não encontrada ou inativa."))?;
+    let itens: Vec<&ItemRevista> = lista.itens.iter().filter(|i| i.ativo).collect();
+    if itens.is_empty() {
+        return Err(AppError::validation("lista_id", "A lista ainda não tem itens."));
+    }
+    let em_falta: Vec<&ItemRevista> = itens.iter().copied().filter(|i| falhas.contains(&i.id)).collect();
+    let maximo: i64 = itens.iter().map(|i| i.pontos).sum();
+    let pontuacao = maximo - em_falta.iter().map(|i| i.pontos).sum::<i64>();
+
+    let mut tx = db_pool.begin().await?;
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    let id = sqlx::query(
+        r#"
+        INSERT INTO revistas (organizacao_id, lista_id, user_id, quarto, data, pontuacao, maximo, observacoes, inspetor_id)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(lista_id)
+    .bind(user_id)
+    .bind(quarto.trim())
+    .bind(data.to_string())
+    .bind(pontuacao)
+    .bind(maximo)
+    .bind(observacoes.trim())
+    .bind(inspetor_id)
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+
+    let mut reincidencias = Vec::new();
+    for item in &em_falta {
+        sqlx::query("INSERT INTO revista_falhas (revista_id, item_id) VALUES (?1, ?2)")
+            .bind(id)
+            .bind(item.id)
+            .execute(&mut *tx)
+            .await?;
+        let vezes: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM revista_falhas f JOIN revistas r ON r.id = f.revista_id
+            WHERE r.user_id = ?1 AND f.item_id = ?2 AND r.data > ?3 AND r.data <= ?4
+            "#,
+        )
+        .bind(user_id)
+        .bind(item.id)
+        .bind((data - Duration::days(JANELA_REINCIDENCIA_DIAS)).to_string())
+        .bind(data.to_string())
+        .fetch_one(&mut *tx)
+        .await?;
+        if vezes >= MIN_REINCIDENCIA {
+            reincidencias.push((item.descricao.clone(), vezes));
+        }
+    }
+    tx.commit().await?;
+    tracing::info!("🧹 Revista {} a {} registada por {}: {}/{}.", id, user_id, inspetor_id, pontuacao, maximo);
+    Ok(RevistaRegistada { id, pontuacao, maximo, reincidencias })
+}
+
+/// Revistas mais recentes da organização (as últimas registadas primeiro).
+pub async fn recentes(db_pool: &SqlitePool, organizacao_id: i64, limite: i64) -> AppResult<Vec<RevistaResumo>> {
+    let revistas = sqlx::query_as::<_, RevistaResumo>(
+        r#"
+        SELECT r.data, r.user_id, u.name, u.turma, r.quarto, l.nome AS lista, r.pontuacao, r.maximo,
+               COALESCE((SELECT GROUP_CONCAT(i.descricao, ', ')
+                         FROM revista_falhas f JOIN revista_itens i ON i.id = f.item_id
+                         WHERE f.revista_id = r.id), '') AS falhas,
+               r.observacoes, COALESCE(o.name, r.inspetor_id) AS inspetor
+        FROM revistas r
+        JOIN users u ON u.id = r.user_id
+        JOIN revista_listas l ON l.id = r.lista_id
+        LEFT JOIN users o ON o.id = r.inspetor_id
+        WHERE r.organizacao_id = ?1
+        ORDER BY r.data DESC, r.id DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(limite)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(revistas)
+}
+
+// --- ESTATÍSTICAS ---
+
+/// Resultados por turma entre `de` e `ate` (inclusive), da melhor média para a pior.
+pub async fn estatisticas_turmas(db_pool: &SqlitePool, organizacao_id: i64, de: NaiveDate, ate: NaiveDate) -> AppResult<Vec<EstatisticaTurma>> {
+    let turmas = sqlx::query_as::<_, EstatisticaTurma>(
+        r#"
+        SELECT u.turma, COUNT(*) AS revistas, COUNT(DISTINCT r.user_id) AS inspecionados,
+               CASE WHEN SUM(r.maximo) > 0 THEN 100.0 * SUM(r.pontuacao) / SUM(r.maximo) ELSE 100.0 END AS media,
+               (SELECT COUNT(*) FROM revista_falhas f JOIN revistas r2 ON r2.id = f.revista_id JOIN users u2 ON u2.id = r2.user_id
+                WHERE r2.organizacao_id = ?1 AND r2.data BETWEEN ?2 AND ?3 AND u2.turma = u.turma) AS falhas
+        FROM revistas r
+        JOIN users u ON u.id = r.user_id
+        WHERE r.organizacao_id = ?1 AND r.data BETWEEN ?2 AND ?3
+        GROUP BY u.turma
+        ORDER BY media DESC, u.turma
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(de.to_string())
+    .bind(ate.to_string())
+    .fetch_all(db_pool)
+    .await?;
+    Ok(turmas)
+}
+
+/// Itens em falta pelo menos `MIN_REINCIDENCIA` vezes no mesmo utilizador nos `JANELA_REINCIDENCIA_DIAS`
+/// dias até `ate`, dos mais repetidos para os menos.
+pub async fn reincidencias(db_pool: &SqlitePool, organizacao_id: i64, ate: NaiveDate) -> AppResult<Vec<Reincidencia>> {
+    let reincidencias = sqlx::query_as::<_, Reincidencia>(
+        r#"
+        SELECT r.user_id, u.name, u.turma, i.descricao AS item, COUNT(*) AS vezes, MAX(r.data) AS ultima
+        FROM revista_falhas f
+        JOIN revistas r ON r.id = f.revista_id
+        JOIN revista_itens i ON i.id = f.item_id
+        JOIN users u ON u.id = r.user_id
+        WHERE r.organizacao_id = ?1 AND r.data > ?2 AND r.data <= ?3
+        GROUP BY r.user_id, f.item_id
+        HAVING COUNT(*) >= ?4
+        ORDER BY vezes DESC, ultima DESC, u.name
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind((ate - Duration::days(JANELA_REINCIDENCIA_DIAS)).to_string())
+    .bind(ate.to_string())
+    .bind(MIN_REINCIDENCIA)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(reincidencias)
+}
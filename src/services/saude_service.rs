@@ -0,0 +1,108 @@
+// src/services/saude_service.rs
+//! Verificações de prontidão para proxies e orquestradores (ver `GET /readyz`):
+//! base de dados acessível, migrações todas aplicadas e store das sessões a responder.
+
+use crate::db::MIGRATOR;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::{collections::{BTreeMap, HashSet}, fmt::Display, future::Future, time::Duration};
+use tower_sessions::{session::Id, SessionStore};
+use tower_sessions_sqlx_store::SqliteStore;
+
+/// Tempo máximo de cada verificação (menor que o busy_timeout da base de dados, para não prender a sonda).
+const TIMEOUT_VERIFICACAO: Duration = Duration::from_secs(3);
+
+pub const STATUS_OK: &str = "ok";
+pub const STATUS_FALHA: &str = "falha";
+
+/// Estado de um componente.
+#[derive(Debug, Serialize)]
+pub struct Componente {
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detalhe: Option<String>,
+}
+
+impl Componente {
+    fn ok(detalhe: Option<String>) -> Self {
+        Self { status: STATUS_OK, detalhe }
+    }
+
+    fn falha(detalhe: impl Display) -> Self {
+        Self { status: STATUS_FALHA, detalhe: Some(detalhe.to_string()) }
+    }
+}
+
+/// Resultado de /readyz: "ok" só se todos os componentes estiverem ok.
+#[derive(Debug, Serialize)]
+pub struct Prontidao {
+    pub status: &'static str,
+    pub componentes: BTreeMap<&'static str, Componente>,
+}
+
+impl Prontidao {
+    pub fn pronto(&self) -> bool {
+        self.status == STATUS_OK
+    }
+}
+
+/// Verifica a base de dados, as migrações e o store das sessões.
+pub async fn verificar(db_pool: &SqlitePool, session_store: &SqliteStore) -> Prontidao {
+    let mut componentes = BTreeMap::new();
+    componentes.insert("base_dados", com_timeout(verificar_base_dados(db_pool)).await);
+    componentes.insert("migracoes", com_timeout(verificar_migracoes(db_pool)).await);
+    componentes.insert("sessoes", com_timeout(verificar_sessoes(session_store)).await);
+
+    let status = if componentes.values().all(|c| c.status == STATUS_OK) { STATUS_OK } else { STATUS_FALHA };
+    if status != STATUS_OK {
+        tracing::warn!("🩺 /readyz: não pronto: {:?}", componentes);
+    }
+    Prontidao { status, componentes }
+}
+
+async fn com_timeout(verificacao: impl Future<Output = Componente>) -> Componente {
+    tokio::time::timeout(TIMEOUT_VERIFICACAO, verificacao)
+        .await
+        .unwrap_or_else(|_| Componente::falha(format!("sem resposta em {}s", TIMEOUT_VERIFICACAO.as_secs())))
+}
+
+async fn verificar_base_dados(db_pool: &SqlitePool) -> Componente {
+    match sqlx::query_scalar::<_, i64>("SELECT 1").fetch_one(db_pool).await {
+        Ok(_) => Componente::ok(None),
+        Err(e) => Componente::falha(e),
+    }
+}
+
+/// Todas as migrações embutidas no binário estão aplicadas (e nenhuma falhou)?
+async fn verificar_migracoes(db_pool: &SqlitePool) -> Componente {
+    let aplicadas: Vec<(i64, bool)> = match sqlx::query_as("SELECT version, success FROM _sqlx_migrations")
+        .fetch_all(db_pool)
+        .await
+    {
+        Ok(aplicadas) => aplicadas,
+        Err(e) => return Componente::falha(e),
+    };
+    if let Some((versao, _)) = aplicadas.iter().find(|(_, sucesso)| !sucesso) {
+        return Componente::falha(format!("migração {} falhou", versao));
+    }
+
+    let aplicadas: HashSet<i64> = aplicadas.into_iter().map(|(versao, _)| versao).collect();
+    let pendentes: Vec<String> = MIGRATOR
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration() && !aplicadas.contains(&m.version))
+        .map(|m| m.version.to_string())
+        .collect();
+    if pendentes.is_empty() {
+        Componente::ok(Some(format!("{} aplicadas", aplicadas.len())))
+    } else {
+        Componente::falha(format!("{} por aplicar: {}", pendentes.len(), pendentes.join(", ")))
+    }
+}
+
+/// Lê do store uma sessão que não existe: basta que a consulta responda.
+async fn verificar_sessoes(session_store: &SqliteStore) -> Componente {
+    match session_store.load(&Id::default()).await {
+        Ok(_) => Componente::ok(None),
+        Err(e) => Componente::falha(e),
+    }
+}
@@ -0,0 +1,216 @@
+// src/services/seed_service.rs
+//! Dados de demonstração para desenvolvimento local e demos (`mercal2 seed`, ver main.rs):
+//! 3 anos de alunos, grupos, postos, duas semanas de escala gerada (a primeira publicada),
+//! algumas trocas em estados diferentes e marcações de presença.
+//! Só corre numa base de dados sem utilizadores, para nunca misturar dados fictícios com reais.
+//! Tudo fica na organização principal; o administrador de demonstração é também super-admin.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{
+        auth_service, escala_service, grupo_service, organizacao_service::ORGANIZACAO_PRINCIPAL, presence_service,
+        user_service,
+    },
+    tempo,
+};
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+/// Senha de todas as contas de demonstração.
+pub const SENHA_DEMO: &str = "mercal2demo";
+/// Alunos criados por ano.
+const ALUNOS_POR_ANO: usize = 20;
+/// Dias de escala gerados a partir de hoje (a primeira metade fica publicada).
+const DIAS_ESCALA: i64 = 14;
+
+const NOMES_M: [&str; 10] = ["João", "Miguel", "Tiago", "Rafael", "Diogo", "Pedro", "André", "Rui", "Bruno", "Gonçalo"];
+const NOMES_F: [&str; 6] = ["Inês", "Beatriz", "Mariana", "Catarina", "Sofia", "Ana"];
+const APELIDOS: [&str; 12] = [
+    "Silva", "Santos", "Ferreira", "Pereira", "Oliveira", "Costa", "Rodrigues", "Martins", "Sousa", "Fernandes", "Gomes", "Lopes",
+];
+const CURSOS: [&str; 3] = ["Infantaria", "Artilharia", "Engenharia"];
+
+/// (nome, restrição de género, anos permitidos)
+const POSTOS: [(&str, &str, &str); 4] = [
+    ("Sentinela do Portão", "Misto", "1,2"),
+    ("Plantão ao Alojamento Masculino", "M", "1,2,3"),
+    ("Plantão ao Alojamento Feminino", "F", "1,2,3"),
+    ("Adjunto ao Oficial de Dia", "Misto", "3"),
+];
+
+/// O que foi criado, para o resumo no terminal.
+#[derive(Debug, Default)]
+pub struct Resumo {
+    pub utilizadores: usize,
+    pub grupos: usize,
+    pub postos: usize,
+    pub dias_escala: i64,
+    pub trocas: usize,
+    pub presencas: usize,
+}
+
+/// Popula uma base de dados vazia com os dados de demonstração.
+pub async fn popular(db_pool: &SqlitePool, fadiga_dias: i64) -> AppResult<Resumo> {
+    let existentes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users").fetch_one(db_pool).await?;
+    if existentes > 0 {
+        return Err(AppError::Conflict(format!(
+            "A base de dados já tem {} utilizador(es); o seed só corre numa base de dados vazia.",
+            existentes
+        )));
+    }
+    let mut resumo = Resumo::default();
+
+    // 1. Utilizadores (um só hash para todos: o Argon2 é lento de propósito)
+    let hash = auth_service::hash_password(SENHA_DEMO).await?;
+    let mut tx = db_pool.begin().await?;
+    let quadro: [(&str, &str, &[&str]); 2] = [
+        ("admin", "Administrador", &["admin", "superadmin"]),
+        ("escalante", "Escalante de Serviço", &["escalante"]),
+    ];
+    for (id, nome, roles) in quadro {
+        inserir_user(&mut tx, &hash, id, nome, "Quadro", 0, "Quadro", "M").await?;
+        for role in roles {
+            sqlx::query("INSERT INTO user_roles (user_id, role) VALUES (?1, ?2)").bind(id).bind(role).execute(&mut *tx).await?;
+        }
+        resumo.utilizadores += 1;
+    }
+    let mut por_ano: Vec<Vec<String>> = vec![Vec::new(); 3];
+    for ano in 1..=3i64 {
+        for n in 0..ALUNOS_POR_ANO {
+            // Cerca de um terço são mulheres (precisas para o plantão feminino)
+            let (primeiro, genero) = if n % 3 == 0 {
+                (NOMES_F[(n + ano as usize) % NOMES_F.len()], "F")
+            } else {
+                (NOMES_M[(n + ano as usize) % NOMES_M.len()], "M")
+            };
+            let apelido = APELIDOS[(n * 7 + ano as usize * 3) % APELIDOS.len()];
+            let id = format!("{}{:03}", ano, n + 1);
+            let turma = format!("{}{}", ano, if n % 2 == 0 { "A" } else { "B" });
+            let curso = CURSOS[n % CURSOS.len()];
+            inserir_user(&mut tx, &hash, &id, &format!("{} {}", primeiro, apelido), &turma, ano, curso, genero).await?;
+            por_ano[(ano - 1) as usize].push(id);
+            resumo.utilizadores += 1;
+        }
+    }
+
+    // 2. Postos
+    for (nome, genero, anos) in POSTOS {
+        sqlx::query("INSERT INTO postos (nome, genero_restricao, turmas_permitidas) VALUES (?1, ?2, ?3)")
+            .bind(nome)
+            .bind(genero)
+            .bind(anos)
+            .execute(&mut *tx)
+            .await?;
+        resumo.postos += 1;
+    }
+    tx.commit().await?;
+
+    // O chefe de dia (role temporária, nas próximas 24h) pode marcar presenças
+    let agora = Utc::now();
+    user_service::grant_temporary_role(db_pool, &por_ano[2][0], "chefe_de_dia", agora, agora + Duration::days(1)).await?;
+
+    // 3. Um pelotão por ano
+    for (i, membros) in por_ano.iter().enumerate() {
+        let grupo_id = grupo_service::criar_grupo(
+            db_pool, ORGANIZACAO_PRINCIPAL, &format!("{}º Pelotão", i + 1), "pelotao", &format!("Alunos do {}º ano", i + 1),
+        )
+        .await?;
+        grupo_service::adicionar_membros(db_pool, ORGANIZACAO_PRINCIPAL, grupo_id, membros).await?;
+        resumo.grupos += 1;
+    }
+
+    // 4. Duas semanas de escala; a primeira fica publicada
+    let inicio = tempo::hoje();
+    let fim = inicio + Duration::days(DIAS_ESCALA - 1);
+    let fim_publicada = inicio + Duration::days(DIAS_ESCALA / 2 - 1);
+    escala_service::gerar_escala_periodo(db_pool, ORGANIZACAO_PRINCIPAL, &inicio.to_string(), &fim.to_string(), fadiga_dias).await?;
+    escala_service::publicar_escala(db_pool, ORGANIZACAO_PRINCIPAL, &inicio.to_string(), &fim_publicada.to_string()).await?;
+    resumo.dias_escala = DIAS_ESCALA;
+
+    // 5. Trocas na semana em rascunho: uma pendente, uma aceite pelo substituto, uma aprovada
+    let rascunho: Vec<(String, String, i64)> = sqlx::query_as(
+        r#"
+        SELECT a.id, a.user_id, u.ano
+        FROM alocacoes a
+        JOIN escalas e ON e.organizacao_id = a.organizacao_id AND e.data = a.data
+        JOIN users u ON u.id = a.user_id
+        WHERE e.status = 'Rascunho' AND a.is_punicao = 0
+        ORDER BY a.data, a.posto_id
+        "#,
+    )
+    .fetch_all(db_pool)
+    .await?;
+    for (alocacao_id, solicitante, ano) in rascunho.into_iter().step_by(3) {
+        if resumo.trocas == 3 {
+            break;
+        }
+        // Um colega do mesmo ano sem serviços perto desse dia (o serviço valida a regra de fadiga)
+        let mut troca_id = None;
+        for substituto in por_ano[(ano - 1).clamp(0, 2) as usize].iter().filter(|s| **s != solicitante) {
+            let pedido = escala_service::solicitar_troca(
+                db_pool, ORGANIZACAO_PRINCIPAL, &solicitante, &alocacao_id, substituto, None, "Assunto familiar", fadiga_dias,
+            )
+            .await;
+            if pedido.is_ok() {
+                troca_id = sqlx::query_scalar::<_, String>("SELECT id FROM trocas WHERE alocacao_id = ?1 AND substituto_id = ?2")
+                    .bind(&alocacao_id)
+                    .bind(substituto)
+                    .fetch_optional(db_pool)
+                    .await?
+                    .map(|id| (id, substituto.clone()));
+                break;
+            }
+        }
+        let Some((troca_id, substituto)) = troca_id else { continue };
+        if resumo.trocas >= 1 {
+            escala_service::responder_troca_usuario(db_pool, &troca_id, &substituto, "aceitar").await?;
+        }
+        if resumo.trocas == 2 {
+            escala_service::aprovar_troca(db_pool, ORGANIZACAO_PRINCIPAL, &troca_id).await?;
+        }
+        resumo.trocas += 1;
+    }
+
+    // 6. Presença: metade do 1º ano saiu, e parte já voltou
+    for (n, user_id) in por_ano[0].iter().take(ALUNOS_POR_ANO / 2).enumerate() {
+        presence_service::marcar_saida(db_pool, user_id, "admin").await?;
+        if n % 2 == 0 {
+            presence_service::marcar_retorno(db_pool, user_id, "admin").await?;
+        }
+        resumo.presencas += 1;
+    }
+
+    tracing::info!("🌱 Dados de demonstração criados: {:?}", resumo);
+    Ok(resumo)
+}
+
+/// Insere um utilizador com o hash já calculado (sem os avisos/webhooks de `user_service::create_user`).
+#[allow(clippy::too_many_arguments)]
+async fn inserir_user(
+    tx: &mut sqlx::SqliteConnection,
+    hash: &str,
+    id: &str,
+    nome: &str,
+    turma: &str,
+    ano: i64,
+    curso: &str,
+    genero: &str,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO users (id, password_hash, password_esquema, name, turma, ano, curso, genero, password_alterada_em)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, datetime('now'))
+        "#,
+    )
+    .bind(id)
+    .bind(hash)
+    .bind(auth_service::esquema_atual())
+    .bind(nome)
+    .bind(turma)
+    .bind(ano)
+    .bind(curso)
+    .bind(genero)
+    .execute(tx)
+    .await?;
+    Ok(())
+}
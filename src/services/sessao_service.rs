@@ -0,0 +1,125 @@
+// src/services/sessao_service.rs
+//! Sessões ativas por utilizador. O tower-sessions guarda os dados serializados (sem índice por
+//! utilizador); a tabela `user_sessoes` faz essa ligação. Revogar = apagar a linha em `sessions`,
+//! o que termina a sessão no pedido seguinte.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::login::SessaoAtiva,
+    tempo,
+};
+use sqlx::SqlitePool;
+
+/// Comprimento máximo guardado do User-Agent.
+const MAX_USER_AGENT: usize = 300;
+
+/// Regista (ou atualiza) a sessão do pedido atual. Chamado pelo middleware de autenticação:
+/// a atividade só é escrita uma vez por minuto para não gravar em cada pedido.
+/// Falhas apenas são logadas (nunca bloqueiam o pedido).
+pub async fn tocar(db_pool: &SqlitePool, session_id: &str, user_id: &str, ip: Option<&str>, user_agent: Option<&str>) {
+    let user_agent: Option<String> = user_agent.map(|ua| ua.chars().take(MAX_USER_AGENT).collect());
+    let result = sqlx::query(
+        r#"
+        INSERT INTO user_sessoes (session_id, user_id, ip, user_agent) VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT (session_id) DO UPDATE SET
+            ultima_atividade = datetime('now'),
+            ip = COALESCE(excluded.ip, ip)
+        WHERE ultima_atividade < datetime('now', '-1 minute')
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .bind(ip)
+    .bind(user_agent)
+    .execute(db_pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!("Falha ao registar atividade da sessão de {}: {:?}", user_id, e);
+    }
+}
+
+/// Remove a sessão do índice (logout).
+pub async fn remover(db_pool: &SqlitePool, session_id: &str) -> AppResult<()> {
+    sqlx::query("DELETE FROM user_sessoes WHERE session_id = ?1")
+        .bind(session_id)
+        .execute(db_pool)
+        .await?;
+    Ok(())
+}
+
+/// Sessões ainda válidas de um utilizador, da mais recente para a mais antiga.
+/// `sessao_atual` marca a sessão de quem está a ver a lista.
+pub async fn listar_user(db_pool: &SqlitePool, user_id: &str, sessao_atual: Option<&str>) -> AppResult<Vec<SessaoAtiva>> {
+    // Limpa entradas cujas sessões já expiraram/foram apagadas pelo tower-sessions
+    sqlx::query("DELETE FROM user_sessoes WHERE session_id NOT IN (SELECT id FROM sessions)")
+        .execute(db_pool)
+        .await?;
+
+    let mut sessoes = sqlx::query_as::<_, SessaoAtiva>(
+        r#"
+        SELECT us.id, us.ip, us.user_agent, us.criado_em, us.ultima_atividade,
+               (us.session_id = ?2) AS atual
+        FROM user_sessoes us
+        JOIN sessions s ON s.id = us.session_id
+        WHERE us.user_id = ?1 AND s.expiry_date > strftime('%Y-%m-%dT%H:%M:%SZ', 'now') -- expiry_date: texto RFC3339 (UTC)
+        ORDER BY us.ultima_atividade DESC
+        "#,
+    )
+    .bind(user_id)
+    .bind(sessao_atual.unwrap_or_default())
+    .fetch_all(db_pool)
+    .await?;
+    for sessao in &mut sessoes {
+        sessao.criado_em = tempo::formatar(&sessao.criado_em, tempo::FORMATO_DATA_HORA);
+        sessao.ultima_atividade = tempo::formatar(&sessao.ultima_atividade, tempo::FORMATO_DATA_HORA);
+    }
+    Ok(sessoes)
+}
+
+/// Revoga uma sessão de um utilizador (pelo id numérico da listagem).
+pub async fn revogar(db_pool: &SqlitePool, user_id: &str, id: i64) -> AppResult<()> {
+    let mut tx = db_pool.begin().await?;
+    let session_id: Option<String> = sqlx::query_scalar("SELECT session_id FROM user_sessoes WHERE id = ?1 AND user_id = ?2")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(session_id) = session_id else {
+        return Err(AppError::NotFound("Sessão não encontrada (já terminou?).".to_string()));
+    };
+
+    sqlx::query("DELETE FROM sessions WHERE id = ?1").bind(&session_id).execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM user_sessoes WHERE id = ?1").bind(id).execute(&mut *tx).await?;
+    tx.commit().await?;
+    tracing::info!("🔒 Sessão {} de {} revogada.", id, user_id);
+    Ok(())
+}
+
+/// Revoga todas as sessões de um utilizador, exceto (opcionalmente) a do pedido atual.
+/// Devolve o número de sessões terminadas.
+pub async fn revogar_todas(db_pool: &SqlitePool, user_id: &str, exceto: Option<&str>) -> AppResult<u64> {
+    let exceto = exceto.unwrap_or_default();
+    let mut tx = db_pool.begin().await?;
+    let apagadas = sqlx::query(
+        "DELETE FROM sessions WHERE id IN (SELECT session_id FROM user_sessoes WHERE user_id = ?1 AND session_id <> ?2)",
+    )
+    .bind(user_id)
+    .bind(exceto)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    sqlx::query("DELETE FROM user_sessoes WHERE user_id = ?1 AND session_id <> ?2")
+        .bind(user_id)
+        .bind(exceto)
+        .execute(&mut *tx)
+        .await?;
+    // A aplicação móvel também: os refresh tokens deixam de servir (os access tokens expiram sozinhos)
+    sqlx::query("UPDATE refresh_tokens SET revogado_em = datetime('now') WHERE user_id = ?1 AND revogado_em IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!("🔒 {} sessões de {} revogadas.", apagadas, user_id);
+    Ok(apagadas)
+}
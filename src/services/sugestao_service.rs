@@ -0,0 +1,208 @@
+// src/services/sugestao_service.rs
+//! Caixa de sugestões: os utilizadores enviam sugestões, identificadas ou anónimas; a triagem (permissão
+//! "sugestoes") passa-as de recebida a em análise e responde, podendo publicar a resposta para todos
+//! (sem o autor). O autor de uma sugestão anónima não fica na DB: só o pseudónimo, o HMAC-SHA256 do ID
+//! com uma chave derivada de SESSION_SECRET, que deixa o próprio ver as suas e não se reverte sem a
+//! chave. Trocar SESSION_SECRET desliga as sugestões anónimas antigas dos autores (continuam na triagem).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::sugestao::Sugestao,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use std::sync::OnceLock;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const ESTADO_RECEBIDA: &str = "recebida";
+pub const ESTADO_EM_ANALISE: &str = "em_analise";
+pub const ESTADO_RESPONDIDA: &str = "respondida";
+
+/// Estados da triagem (código, descrição), pela ordem.
+pub const ESTADOS: &[(&str, &str)] = &[
+    (ESTADO_RECEBIDA, "Recebida"),
+    (ESTADO_EM_ANALISE, "Em análise"),
+    (ESTADO_RESPONDIDA, "Respondida"),
+];
+
+/// Sugestões mostradas na triagem e respostas publicadas mostradas a todos.
+pub const LIMITE_LISTA: i64 = 200;
+
+/// Descrição de um estado.
+pub fn descrever_estado(estado: &str) -> &str {
+    ESTADOS.iter().find(|(c, _)| *c == estado).map_or(estado, |(_, d)| *d)
+}
+
+static CHAVE: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Define a chave dos pseudónimos a partir do segredo das sessões (chamado uma vez, no arranque).
+/// A chave é derivada (HMAC com um rótulo) para não usar o mesmo segredo para dois fins.
+pub fn configurar(segredo: &[u8]) {
+    let Ok(mut mac) = HmacSha256::new_from_slice(segredo) else {
+        tracing::error!("Chave das sugestões anónimas inválida.");
+        return;
+    };
+    mac.update(b"mercal2:sugestoes:pseudonimo");
+    if CHAVE.set(mac.finalize().into_bytes().to_vec()).is_err() {
+        tracing::warn!("Chave das sugestões anónimas já definida; ignorada.");
+    }
+}
+
+/// Pseudónimo do utilizador nas sugestões anónimas.
+pub fn pseudonimo(user_id: &str) -> AppResult<String> {
+    let chave = CHAVE.get().ok_or_else(|| {
+        tracing::error!("Chave das sugestões anónimas não definida (sugestao_service::configurar).");
+        AppError::InternalServerError
+    })?;
+    let mut mac = HmacSha256::new_from_slice(chave).map_err(|_| AppError::InternalServerError)?;
+    mac.update(user_id.as_bytes());
+    Ok(format!("{:x}", mac.finalize().into_bytes()))
+}
+
+/// Sugestões da organização. `?2` = só neste estado; `?3`/`?4` = só as deste autor (ID ou
+/// pseudónimo); `?5` = só as com a resposta publicada; `?6` = só esta sugestão.
+const SQL_SUGESTOES: &str = r#"
+    SELECT s.id, s.assunto, s.texto, s.estado, s.autor_id, COALESCE(a.name, s.autor_id) AS autor,
+           s.resposta, s.publicada, COALESCE(r.name, s.respondida_por) AS respondida_por, s.respondida_em,
+           s.criada_em
+    FROM sugestoes s
+    LEFT JOIN users a ON a.id = s.autor_id
+    LEFT JOIN users r ON r.id = s.respondida_por
+    WHERE s.organizacao_id = ?1
+      AND (?2 IS NULL OR s.estado = ?2)
+      AND (?3 IS NULL OR s.autor_id = ?3 OR s.autor_anonimo = ?4)
+      AND (?5 = 0 OR s.publicada = 1)
+      AND (?6 IS NULL OR s.id = ?6)
+    ORDER BY COALESCE(CASE WHEN ?5 THEN s.respondida_em END, s.criada_em) DESC, s.id DESC
+    LIMIT ?7
+"#;
+
+/// Sugestões para a triagem (num estado, se indicado), as mais recentes primeiro.
+pub async fn para_triagem(db_pool: &SqlitePool, organizacao_id: i64, estado: Option<&str>) -> AppResult<Vec<Sugestao>> {
+    let sugestoes = sqlx::query_as::<_, Sugestao>(SQL_SUGESTOES)
+        .bind(organizacao_id)
+        .bind(estado)
+        .bind(None::<String>)
+        .bind(None::<String>)
+        .bind(false)
+        .bind(None::<i64>)
+        .bind(LIMITE_LISTA)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(sugestoes)
+}
+
+/// Sugestões enviadas pelo utilizador (identificadas e anónimas).
+pub async fn do_autor(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str) -> AppResult<Vec<Sugestao>> {
+    let sugestoes = sqlx::query_as::<_, Sugestao>(SQL_SUGESTOES)
+        .bind(organizacao_id)
+        .bind(None::<String>)
+        .bind(user_id)
+        .bind(pseudonimo(user_id)?)
+        .bind(false)
+        .bind(None::<i64>)
+        .bind(i64::MAX)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(sugestoes)
+}
+
+/// Sugestões com a resposta publicada, as respondidas mais recentemente primeiro.
+pub async fn publicadas(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<Sugestao>> {
+    let sugestoes = sqlx::query_as::<_, Sugestao>(SQL_SUGESTOES)
+        .bind(organizacao_id)
+        .bind(None::<String>)
+        .bind(None::<String>)
+        .bind(None::<String>)
+        .bind(true)
+        .bind(None::<i64>)
+        .bind(LIMITE_LISTA)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(sugestoes)
+}
+
+/// Uma sugestão pelo id (da organização).
+pub async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Sugestao> {
+    sqlx::query_as::<_, Sugestao>(SQL_SUGESTOES)
+        .bind(organizacao_id)
+        .bind(None::<String>)
+        .bind(None::<String>)
+        .bind(None::<String>)
+        .bind(false)
+        .bind(id)
+        .bind(1_i64)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Sugestão {} não encontrada.", id)))
+}
+
+/// Regista uma sugestão (campos já validados). Anónima: guarda só o pseudónimo. Devolve o ID.
+pub async fn enviar(db_pool: &SqlitePool, organizacao_id: i64, user_id: &str, assunto: &str, texto: &str, anonima: bool) -> AppResult<i64> {
+    let (autor_id, autor_anonimo) = if anonima { (None, Some(pseudonimo(user_id)?)) } else { (Some(user_id), None) };
+    let id = sqlx::query(
+        "INSERT INTO sugestoes (organizacao_id, autor_id, autor_anonimo, assunto, texto) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(organizacao_id)
+    .bind(autor_id)
+    .bind(autor_anonimo)
+    .bind(assunto.trim())
+    .bind(texto.trim())
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+    // O autor de uma anónima também não vai para o log
+    match autor_id {
+        Some(autor) => tracing::info!("💡 Sugestão {} enviada por {}.", id, autor),
+        None => tracing::info!("💡 Sugestão {} enviada (anónima).", id),
+    }
+    Ok(id)
+}
+
+/// Passa uma sugestão a recebida ou em análise (respondida só com a resposta). Devolve-a como estava.
+pub async fn alterar_estado(db_pool: &SqlitePool, organizacao_id: i64, id: i64, estado: &str) -> AppResult<Sugestao> {
+    if estado == ESTADO_RESPONDIDA {
+        return Err(AppError::validation("estado", "Para a dar como respondida, escreva a resposta."));
+    }
+    if !ESTADOS.iter().any(|(c, _)| *c == estado) {
+        return Err(AppError::validation("estado", "Estado inválido."));
+    }
+    let anterior = obter(db_pool, organizacao_id, id).await?;
+    // Voltar atrás de respondida retira a publicação (a resposta fica guardada)
+    sqlx::query("UPDATE sugestoes SET estado = ?2, publicada = 0 WHERE id = ?1")
+        .bind(id)
+        .bind(estado)
+        .execute(db_pool)
+        .await?;
+    Ok(anterior)
+}
+
+/// Responde (ou altera a resposta) e dá a sugestão como respondida; `publicar` mostra a resposta a
+/// todos. Devolve a sugestão já respondida.
+pub async fn responder(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    id: i64,
+    resposta: &str,
+    publicar: bool,
+    operador_id: &str,
+) -> AppResult<Sugestao> {
+    obter(db_pool, organizacao_id, id).await?;
+    sqlx::query(
+        r#"
+        UPDATE sugestoes SET estado = 'respondida', resposta = ?2, publicada = ?3,
+               respondida_por = ?4, respondida_em = datetime('now')
+        WHERE id = ?1
+        "#,
+    )
+    .bind(id)
+    .bind(resposta.trim())
+    .bind(publicar)
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?;
+    tracing::info!("💡 Sugestão {} respondida por {}{}.", id, operador_id, if publicar { " (publicada)" } else { "" });
+    obter(db_pool, organizacao_id, id).await
+}
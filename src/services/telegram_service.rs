@@ -0,0 +1,377 @@
+// src/services/telegram_service.rs
+//! Bot do Telegram: reencaminha as notificações (ver `notificacao_service::notificar`) para quem ligou
+//! a conta a um chat, e responde a comandos simples (/proximoservico).
+//! A ligação faz-se no painel: o utilizador gera um código e envia-o ao bot (`/start <código>`),
+//! o que prova que o chat é seu. Desativado se TELEGRAM_BOT_TOKEN não estiver definida.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::telegram::TelegramLigacao,
+    services::{escala_service, organizacao_service},
+    tempo,
+};
+use chrono::{Duration as ChronoDuration, NaiveDate};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::{sync::OnceLock, time::Duration};
+use uuid::Uuid;
+
+/// API oficial; TELEGRAM_API_URL permite usar um servidor Bot API próprio.
+const API_URL_PADRAO: &str = "https://api.telegram.org";
+/// Espera máxima de cada pedido getUpdates (long polling).
+const ESPERA_ATUALIZACOES_SEGUNDOS: u64 = 25;
+/// Tempo máximo de um pedido à API (tem de exceder a espera do long polling).
+const TIMEOUT_API: Duration = Duration::from_secs(ESPERA_ATUALIZACOES_SEGUNDOS + 10);
+/// Pausa antes de voltar a contactar a API depois de um erro.
+const PAUSA_APOS_ERRO: Duration = Duration::from_secs(30);
+/// Validade do código de ligação.
+const VALIDADE_CODIGO_MINUTOS: i64 = 15;
+/// Dias à frente consultados por /proximoservico.
+const DIAS_PROXIMO_SERVICO: i64 = 90;
+
+const AJUDA: &str = "Comandos disponíveis:\n\
+                     /proximoservico - o seu próximo serviço na escala publicada\n\
+                     /desligar - deixar de receber notificações neste chat\n\
+                     /ajuda - esta mensagem";
+
+/// Configuração do bot (lida do ambiente no arranque).
+pub struct TelegramConfig {
+    token: String,
+    pub bot_username: Option<String>, // Para o link t.me no painel (TELEGRAM_BOT_USERNAME, opcional)
+    api_url: String,
+    http: reqwest::Client,
+}
+
+// O token não aparece nos logs
+impl std::fmt::Debug for TelegramConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TelegramConfig")
+            .field("bot_username", &self.bot_username)
+            .field("api_url", &self.api_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TelegramConfig {
+    /// Lê TELEGRAM_BOT_TOKEN, TELEGRAM_BOT_USERNAME (opcional) e TELEGRAM_API_URL (opcional).
+    /// Ok(None) (desativado) se TELEGRAM_BOT_TOKEN não estiver definida.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let var = |nome: &str| std::env::var(nome).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let Some(token) = var("TELEGRAM_BOT_TOKEN") else {
+            return Ok(None);
+        };
+        let http = reqwest::Client::builder()
+            .timeout(TIMEOUT_API)
+            .build()
+            .map_err(|e| format!("falha ao criar cliente HTTP: {}", e))?;
+        Ok(Some(Self {
+            token,
+            bot_username: var("TELEGRAM_BOT_USERNAME").map(|u| u.trim_start_matches('@').to_string()),
+            api_url: var("TELEGRAM_API_URL").unwrap_or_else(|| API_URL_PADRAO.to_string()).trim_end_matches('/').to_string(),
+            http,
+        }))
+    }
+
+    fn url_metodo(&self, metodo: &str) -> String {
+        format!("{}/bot{}/{}", self.api_url, self.token, metodo)
+    }
+}
+
+static CONFIG: OnceLock<TelegramConfig> = OnceLock::new();
+
+/// Define a configuração do bot (chamado uma vez, no arranque, só se estiver ativo).
+pub fn configurar(config: TelegramConfig) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("Configuração do Telegram já definida; ignorada.");
+    }
+}
+
+/// Configuração do bot, se estiver ativo.
+pub fn config() -> Option<&'static TelegramConfig> {
+    CONFIG.get()
+}
+
+// --- Ligação das contas ---
+
+/// Estado da ligação de um utilizador (None = nunca pediu ligação ou desligou).
+pub async fn ligacao_user(db_pool: &SqlitePool, user_id: &str) -> AppResult<Option<TelegramLigacao>> {
+    let ligacao = sqlx::query_as::<_, TelegramLigacao>(
+        r#"
+        SELECT chat_id IS NOT NULL AS ligado,
+               ligado_em,
+               CASE WHEN codigo_expira_em > datetime('now') THEN codigo END AS codigo,
+               CASE WHEN codigo_expira_em > datetime('now') THEN codigo_expira_em END AS codigo_expira_em
+        FROM telegram_ligacoes
+        WHERE user_id = ?1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(ligacao.map(|l| TelegramLigacao {
+        ligado_em: tempo::formatar_opcional(l.ligado_em, tempo::FORMATO_DATA_HORA),
+        codigo_expira_em: tempo::formatar_opcional(l.codigo_expira_em, "%H:%M"),
+        ..l
+    }))
+}
+
+/// Gera um novo código de ligação para o utilizador (substitui o anterior, se houver).
+pub async fn gerar_codigo(db_pool: &SqlitePool, user_id: &str) -> AppResult<String> {
+    let codigo = Uuid::new_v4().simple().to_string()[..8].to_uppercase();
+    sqlx::query(
+        r#"
+        INSERT INTO telegram_ligacoes (user_id, codigo, codigo_expira_em)
+        VALUES (?1, ?2, datetime('now', ?3))
+        ON CONFLICT(user_id) DO UPDATE SET
+            codigo = excluded.codigo,
+            codigo_expira_em = excluded.codigo_expira_em
+        "#,
+    )
+    .bind(user_id)
+    .bind(&codigo)
+    .bind(format!("+{} minutes", VALIDADE_CODIGO_MINUTOS))
+    .execute(db_pool)
+    .await?;
+    Ok(codigo)
+}
+
+/// Desliga a conta do Telegram. Devolve false se não estava ligada.
+pub async fn desligar(db_pool: &SqlitePool, user_id: &str) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM telegram_ligacoes WHERE user_id = ?1")
+        .bind(user_id)
+        .execute(db_pool)
+        .await?;
+    if result.rows_affected() > 0 {
+        tracing::info!("📨 Telegram desligado da conta de {}.", user_id);
+    }
+    Ok(result.rows_affected() > 0)
+}
+
+/// Usa um código de ligação: associa o chat à conta que o gerou. Devolve o ID da conta.
+/// Um chat só fica ligado a uma conta: ligar outra substitui a anterior.
+async fn usar_codigo(db_pool: &SqlitePool, codigo: &str, chat_id: i64) -> AppResult<String> {
+    let mut tx = db_pool.begin().await?;
+    let user_id: Option<String> = sqlx::query_scalar(
+        "SELECT user_id FROM telegram_ligacoes WHERE codigo = ?1 AND codigo_expira_em > datetime('now')",
+    )
+    .bind(codigo.trim().to_uppercase())
+    .fetch_optional(&mut *tx)
+    .await?;
+    let user_id = user_id.ok_or_else(|| AppError::NotFound("Código inválido ou expirado.".to_string()))?;
+
+    sqlx::query("DELETE FROM telegram_ligacoes WHERE chat_id = ?1 AND user_id != ?2")
+        .bind(chat_id)
+        .bind(&user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        r#"
+        UPDATE telegram_ligacoes
+        SET chat_id = ?1, codigo = NULL, codigo_expira_em = NULL, ligado_em = datetime('now')
+        WHERE user_id = ?2
+        "#,
+    )
+    .bind(chat_id)
+    .bind(&user_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    tracing::info!("📨 Conta {} ligada ao Telegram.", user_id);
+    Ok(user_id)
+}
+
+// --- Envio ---
+
+/// Envia uma notificação ao chat do utilizador, se o bot estiver ativo e a conta ligada.
+/// Não espera pelo Telegram (o envio corre numa tarefa à parte) e nunca falha: apenas loga.
+pub async fn encaminhar(db_pool: &SqlitePool, user_id: &str, titulo: &str, mensagem: &str) {
+    let Some(config) = config() else { return };
+    let chat_id: Option<i64> = match sqlx::query_scalar("SELECT chat_id FROM telegram_ligacoes WHERE user_id = ?1 AND chat_id IS NOT NULL")
+        .bind(user_id)
+        .fetch_optional(db_pool)
+        .await
+    {
+        Ok(chat_id) => chat_id,
+        Err(e) => {
+            tracing::error!("Falha ao obter o chat do Telegram de {}: {:?}", user_id, e);
+            return;
+        }
+    };
+    let Some(chat_id) = chat_id else { return };
+
+    let texto = format!("{}\n\n{}", titulo, mensagem);
+    let user_id = user_id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = enviar_mensagem(config, chat_id, &texto).await {
+            tracing::warn!("Falha ao enviar notificação para o Telegram de {}: {}", user_id, e);
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+struct RespostaApi<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+/// Chama um método da API. Os erros nunca incluem o URL (que contém o token).
+async fn chamar<T: serde::de::DeserializeOwned>(
+    config: &TelegramConfig,
+    metodo: &str,
+    corpo: serde_json::Value,
+) -> Result<T, String> {
+    let resposta: RespostaApi<T> = config
+        .http
+        .post(config.url_metodo(metodo))
+        .json(&corpo)
+        .send()
+        .await
+        .map_err(|e| e.without_url().to_string())?
+        .json()
+        .await
+        .map_err(|e| e.without_url().to_string())?;
+    match (resposta.ok, resposta.result) {
+        (true, Some(result)) => Ok(result),
+        _ => Err(resposta.description.unwrap_or_else(|| format!("{} falhou", metodo))),
+    }
+}
+
+async fn enviar_mensagem(config: &TelegramConfig, chat_id: i64, texto: &str) -> Result<(), String> {
+    chamar::<serde_json::Value>(config, "sendMessage", serde_json::json!({ "chat_id": chat_id, "text": texto }))
+        .await
+        .map(|_| ())
+}
+
+// --- Comandos ---
+
+#[derive(Debug, Deserialize)]
+struct Atualizacao {
+    update_id: i64,
+    message: Option<Mensagem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Mensagem {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+/// Inicia a tarefa que recebe as mensagens enviadas ao bot (long polling) e responde aos comandos.
+/// Não faz nada se o bot não estiver configurado.
+pub fn iniciar_bot(db_pool: SqlitePool) {
+    let Some(config) = config() else { return };
+    tokio::spawn(async move {
+        let mut offset: i64 = 0;
+        loop {
+            let atualizacoes = chamar::<Vec<Atualizacao>>(
+                config,
+                "getUpdates",
+                serde_json::json!({
+                    "offset": offset,
+                    "timeout": ESPERA_ATUALIZACOES_SEGUNDOS,
+                    "allowed_updates": ["message"],
+                }),
+            )
+            .await;
+            let atualizacoes = match atualizacoes {
+                Ok(atualizacoes) => atualizacoes,
+                Err(e) => {
+                    tracing::warn!("Telegram: falha ao obter mensagens: {}", e);
+                    tokio::time::sleep(PAUSA_APOS_ERRO).await;
+                    continue;
+                }
+            };
+
+            for atualizacao in atualizacoes {
+                offset = atualizacao.update_id + 1;
+                let Some(Mensagem { chat, text: Some(texto) }) = atualizacao.message else { continue };
+                let resposta = responder_comando(&db_pool, chat.id, &texto).await;
+                if let Err(e) = enviar_mensagem(config, chat.id, &resposta).await {
+                    tracing::warn!("Telegram: falha ao responder no chat {}: {}", chat.id, e);
+                }
+            }
+        }
+    });
+}
+
+/// Resposta do bot a uma mensagem recebida.
+async fn responder_comando(db_pool: &SqlitePool, chat_id: i64, texto: &str) -> String {
+    let mut partes = texto.split_whitespace();
+    // Em grupos os comandos podem vir como "/comando@NomeDoBot"
+    let comando = partes.next().unwrap_or_default().split('@').next().unwrap_or_default().to_lowercase();
+    let argumento = partes.next();
+
+    let resultado = match comando.as_str() {
+        "/start" => match argumento {
+            Some(codigo) => usar_codigo(db_pool, codigo, chat_id)
+                .await
+                .map(|user_id| format!("✅ Conta {} ligada. Vai passar a receber aqui as notificações do Mercal.\n\n{}", user_id, AJUDA)),
+            None => Ok(format!(
+                "Olá! Para receber notificações, gere um código de ligação no seu painel (Telegram → Ligar) e envie /start <código>.\n\n{}",
+                AJUDA
+            )),
+        },
+        "/proximoservico" => proximo_servico(db_pool, chat_id).await,
+        "/desligar" => desligar_chat(db_pool, chat_id).await,
+        _ => Ok(AJUDA.to_string()),
+    };
+
+    resultado.unwrap_or_else(|e| match e {
+        AppError::NotFound(msg) | AppError::Conflict(msg) => msg,
+        e => {
+            tracing::error!("Telegram: erro ao tratar '{}' do chat {}: {:?}", comando, chat_id, e);
+            "Ocorreu um erro. Tente novamente mais tarde.".to_string()
+        }
+    })
+}
+
+/// Conta ligada a um chat.
+async fn user_do_chat(db_pool: &SqlitePool, chat_id: i64) -> AppResult<String> {
+    let user_id: Option<String> = sqlx::query_scalar("SELECT user_id FROM telegram_ligacoes WHERE chat_id = ?1")
+        .bind(chat_id)
+        .fetch_optional(db_pool)
+        .await?;
+    user_id.ok_or_else(|| AppError::NotFound("Este chat não está ligado a nenhuma conta. Envie /start <código> (o código gera-se no painel).".to_string()))
+}
+
+async fn proximo_servico(db_pool: &SqlitePool, chat_id: i64) -> AppResult<String> {
+    let user_id = user_do_chat(db_pool, chat_id).await?;
+    let (organizacao_id, _) = organizacao_service::do_utilizador(db_pool, &user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)))?;
+    let hoje = tempo::hoje();
+    let fim = hoje + ChronoDuration::days(DIAS_PROXIMO_SERVICO);
+    let alocacoes = escala_service::alocacoes_periodo(
+        db_pool,
+        organizacao_id,
+        &hoje.format("%Y-%m-%d").to_string(),
+        &fim.format("%Y-%m-%d").to_string(),
+        Some(&user_id),
+        false,
+    )
+    .await?;
+
+    Ok(match alocacoes.first() {
+        Some(a) => {
+            let data = NaiveDate::parse_from_str(&a.data, "%Y-%m-%d")
+                .map(|d| d.format("%d/%m/%Y").to_string())
+                .unwrap_or_else(|_| a.data.clone());
+            let punicao = if a.is_punicao { " (punição)" } else { "" };
+            format!("📅 Próximo serviço: {} — {}{}", data, a.posto, punicao)
+        }
+        None => format!("Não tem serviços na escala publicada dos próximos {} dias.", DIAS_PROXIMO_SERVICO),
+    })
+}
+
+async fn desligar_chat(db_pool: &SqlitePool, chat_id: i64) -> AppResult<String> {
+    let user_id = user_do_chat(db_pool, chat_id).await?;
+    desligar(db_pool, &user_id).await?;
+    Ok("Chat desligado. Já não vai receber notificações aqui.".to_string())
+}
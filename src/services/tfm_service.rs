@@ -0,0 +1,185 @@
+// src/services/tfm_service.rs
+//! Teste de Forma Física (TFM): resultados por utilizador e exercício, com a marca, a pontuação (0 a 100)
+//! e a menção dada por quem avaliou. As estatísticas de um ano contam, por turma e exercício, a última
+//! prova de cada utilizador (quem repete o exercício conta com o resultado mais recente). Cada
+//! utilizador vê o seu histórico na página pessoal.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::tfm::{EstatisticaTfm, ResultadoTfm},
+};
+use chrono::NaiveDate;
+use sqlx::SqlitePool;
+
+/// Exercícios (código, descrição); os códigos estão no CHECK da migração.
+pub const EXERCICIOS: &[(&str, &str)] = &[
+    ("corrida", "Corrida (12 minutos)"),
+    ("flexoes", "Flexões de braços"),
+    ("abdominais", "Abdominais"),
+    ("barra", "Flexões na barra"),
+    ("natacao", "Natação (50 metros)"),
+];
+
+/// Pontuação máxima de um exercício.
+pub const MAX_PONTUACAO: i64 = 100;
+/// Resultados na listagem da página do TFM.
+pub const LISTAGEM_RECENTE: i64 = 100;
+/// Resultados no histórico da página pessoal.
+pub const HISTORICO_PERFIL: i64 = 20;
+
+/// Descrição de um exercício.
+pub fn descrever_exercicio(exercicio: &str) -> &str {
+    EXERCICIOS.iter().find(|(c, _)| *c == exercicio).map_or(exercicio, |(_, d)| *d)
+}
+
+/// Resultados mais recentes (os `limite` últimos), de um utilizador e/ou de uma turma.
+pub async fn resultados(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: Option<&str>,
+    turma: Option<&str>,
+    limite: i64,
+) -> AppResult<Vec<ResultadoTfm>> {
+    let resultados = sqlx::query_as::<_, ResultadoTfm>(
+        r#"
+        SELECT t.id, t.user_id, u.name, u.turma, t.data, t.exercicio, t.marca, t.pontuacao, t.aprovado,
+               t.observacoes, COALESCE(r.name, t.registado_por) AS registado_por
+        FROM tfm_resultados t
+        JOIN users u ON u.id = t.user_id
+        LEFT JOIN users r ON r.id = t.registado_por
+        WHERE t.organizacao_id = ?1 AND (?2 IS NULL OR t.user_id = ?2) AND (?3 IS NULL OR u.turma = ?3)
+        ORDER BY t.data DESC, t.id DESC
+        LIMIT ?4
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(turma)
+    .bind(limite)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(resultados)
+}
+
+/// Regista o resultado de `user_id` num exercício da prova de `data` (até `hoje`). Devolve o ID.
+#[allow(clippy::too_many_arguments)]
+pub async fn registar(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_id: &str,
+    data: NaiveDate,
+    exercicio: &str,
+    marca: &str,
+    pontuacao: i64,
+    aprovado: bool,
+    observacoes: &str,
+    hoje: NaiveDate,
+    operador_id: &str,
+) -> AppResult<i64> {
+    if !EXERCICIOS.iter().any(|(c, _)| *c == exercicio) {
+        return Err(AppError::validation("exercicio", "Exercício inválido."));
+    }
+    if !(0..=MAX_PONTUACAO).contains(&pontuacao) {
+        return Err(AppError::validation("pontuacao", format!("De 0 a {} pontos.", MAX_PONTUACAO)));
+    }
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(user_id)
+        .bind(organizacao_id)
+        .fetch_one(db_pool)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("user", format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    if data > hoje {
+        return Err(AppError::validation("data", "A prova não pode ser numa data futura."));
+    }
+
+    let id = sqlx::query(
+        r#"
+        INSERT INTO tfm_resultados (organizacao_id, user_id, data, exercicio, marca, pontuacao, aprovado, observacoes, registado_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(user_id)
+    .bind(data.to_string())
+    .bind(exercicio)
+    .bind(marca.trim())
+    .bind(pontuacao)
+    .bind(aprovado)
+    .bind(observacoes.trim())
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+    tracing::info!(
+        "🏃 TFM de {}: {} com {} pontos ({}) registado por {}.",
+        user_id,
+        exercicio,
+        pontuacao,
+        if aprovado { "aprovado" } else { "reprovado" },
+        operador_id
+    );
+    Ok(id)
+}
+
+/// Apaga um resultado registado por engano. Devolve o utilizador, o exercício e a data.
+pub async fn remover(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<(String, String, String)> {
+    let resultado: (String, String, String) =
+        sqlx::query_as("SELECT user_id, exercicio, data FROM tfm_resultados WHERE id = ?1 AND organizacao_id = ?2")
+            .bind(id)
+            .bind(organizacao_id)
+            .fetch_optional(db_pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Resultado do TFM {} não encontrado.", id)))?;
+    sqlx::query("DELETE FROM tfm_resultados WHERE id = ?1").bind(id).execute(db_pool).await?;
+    Ok(resultado)
+}
+
+/// Estatísticas por turma e exercício no `ano` (todas as turmas, ou só `turma`), com a última prova de
+/// cada utilizador ativo em cada exercício.
+pub async fn estatisticas(db_pool: &SqlitePool, organizacao_id: i64, ano: i32, turma: Option<&str>) -> AppResult<Vec<EstatisticaTfm>> {
+    let estatisticas = sqlx::query_as::<_, EstatisticaTfm>(
+        r#"
+        WITH ultimos AS (
+            SELECT t.user_id, t.exercicio, t.pontuacao, t.aprovado,
+                   ROW_NUMBER() OVER (PARTITION BY t.user_id, t.exercicio ORDER BY t.data DESC, t.id DESC) AS n
+            FROM tfm_resultados t
+            WHERE t.organizacao_id = ?1 AND t.data BETWEEN ?2 AND ?3
+        )
+        SELECT u.turma, l.exercicio,
+               COUNT(*) AS avaliados,
+               SUM(l.aprovado) AS aprovados,
+               AVG(l.pontuacao) AS media,
+               MIN(l.pontuacao) AS minima,
+               MAX(l.pontuacao) AS maxima
+        FROM ultimos l
+        JOIN users u ON u.id = l.user_id
+        WHERE l.n = 1 AND u.ativo = 1 AND (?4 IS NULL OR u.turma = ?4)
+        GROUP BY u.turma, l.exercicio
+        ORDER BY u.turma, l.exercicio
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(format!("{:04}-01-01", ano))
+    .bind(format!("{:04}-12-31", ano))
+    .bind(turma)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(estatisticas)
+}
+
+/// Anos com resultados (seletor das estatísticas), do mais recente para o mais antigo; inclui sempre `atual`.
+pub async fn anos(db_pool: &SqlitePool, organizacao_id: i64, atual: i32) -> AppResult<Vec<i32>> {
+    let mut anos: Vec<i32> = sqlx::query_scalar(
+        "SELECT DISTINCT CAST(substr(data, 1, 4) AS INTEGER) FROM tfm_resultados WHERE organizacao_id = ?1",
+    )
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    if !anos.contains(&atual) {
+        anos.push(atual);
+    }
+    anos.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(anos)
+}
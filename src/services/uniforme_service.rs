@@ -0,0 +1,105 @@
+// src/services/uniforme_service.rs
+//! Uniforme do dia (/uniforme): o chefe de dia ou a administração define o uniforme de cada dia, com
+//! observações opcionais (um por dia; definir outra vez substitui). Mostrado no dashboard, na página da
+//! escala e no boletim diário.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::uniforme::UniformeDia,
+};
+use chrono::NaiveDate;
+use sqlx::SqlitePool;
+
+/// Uniformes já usados sugeridos no formulário.
+const SUGESTOES: i64 = 20;
+
+/// Uniformes definidos entre `inicio` e `fim` ('YYYY-MM-DD', inclusive), por data.
+pub async fn periodo(db_pool: &SqlitePool, organizacao_id: i64, inicio: &str, fim: &str) -> AppResult<Vec<UniformeDia>> {
+    let uniformes = sqlx::query_as::<_, UniformeDia>(
+        r#"
+        SELECT u.data, u.uniforme, u.observacoes, COALESCE(d.name, u.definido_por) AS definido_por, u.definido_em
+        FROM uniformes_dia u
+        LEFT JOIN users d ON d.id = u.definido_por
+        WHERE u.organizacao_id = ?1 AND u.data BETWEEN ?2 AND ?3
+        ORDER BY u.data
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(inicio)
+    .bind(fim)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(uniformes)
+}
+
+/// Uniforme de um dia (None = ainda não definido).
+pub async fn do_dia(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<Option<UniformeDia>> {
+    let data = data.to_string();
+    Ok(periodo(db_pool, organizacao_id, &data, &data).await?.into_iter().next())
+}
+
+/// Uniformes já definidos, os mais recentes primeiro (sugestões do formulário).
+pub async fn sugestoes(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<String>> {
+    let uniformes = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT uniforme FROM uniformes_dia
+        WHERE organizacao_id = ?1
+        GROUP BY uniforme
+        ORDER BY MAX(data) DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(SUGESTOES)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(uniformes)
+}
+
+/// Define (ou substitui) o uniforme de `data`. Um dia que já passou não se altera.
+pub async fn definir(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    data: NaiveDate,
+    uniforme: &str,
+    observacoes: &str,
+    hoje: NaiveDate,
+    operador_id: &str,
+) -> AppResult<()> {
+    if data < hoje {
+        return Err(AppError::validation("data", "O dia já passou."));
+    }
+    sqlx::query(
+        r#"
+        INSERT INTO uniformes_dia (organizacao_id, data, uniforme, observacoes, definido_por)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT (organizacao_id, data) DO UPDATE SET
+            uniforme = excluded.uniforme,
+            observacoes = excluded.observacoes,
+            definido_por = excluded.definido_por,
+            definido_em = datetime('now')
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(data.to_string())
+    .bind(uniforme.trim())
+    .bind(observacoes.trim())
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?;
+    tracing::info!("👔 Uniforme de {} definido por {}: {}", data, operador_id, uniforme.trim());
+    Ok(())
+}
+
+/// Apaga o uniforme de `data` (volta a "não definido"). Devolve o que estava definido.
+pub async fn remover(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<UniformeDia> {
+    let uniforme = do_dia(db_pool, organizacao_id, data)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Não há uniforme definido para {}.", data)))?;
+    sqlx::query("DELETE FROM uniformes_dia WHERE organizacao_id = ?1 AND data = ?2")
+        .bind(organizacao_id)
+        .bind(data.to_string())
+        .execute(db_pool)
+        .await?;
+    Ok(uniforme)
+}
@@ -1,45 +1,55 @@
 // src/services/user_service.rs
 use crate::{
     error::{AppError, AppResult},
-    models::user::User, // Modelo User completo
+    i18n::Idioma,
+    models::user::{DadosUser, FusaoResumo, RolloverPreview, RolloverUser, TemporaryRoleGrant, User, UserSugestao}, // Modelo User completo
+    services::{contabilidade_service::{self, Conta}, sugestao_service},
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use sqlx::SqlitePool;
 
-pub const DEFINED_ROLES: &[&str] = &[
-    "admin",
-    "rancheiro",
-    "escalante",
-    "monal",
-    "adal",
-    "comal",
-    "loja",
-    // Adicionar outras roles permanentes aqui se necessário no futuro
-];
+// As roles disponíveis (e o que cada uma permite) vivem nas tabelas `roles` / `role_permissoes`;
+// ver `permission_service`.
+
+/// Garante que todas as roles indicadas existem e podem ser atribuídas de forma permanente.
+async fn validar_roles_permanentes(db_pool: &SqlitePool, roles: &[String]) -> AppResult<()> {
+    let definidas = crate::services::permission_service::roles_permanentes(db_pool).await?;
+    for role in roles.iter().map(|r| r.trim()).filter(|r| !r.is_empty()) {
+        if !definidas.iter().any(|d| d.eq_ignore_ascii_case(role)) {
+            tracing::warn!("Tentativa de atribuir role inválida ('{}')", role);
+            return Err(AppError::validation("roles", format!("Role desconhecida: '{}'.", role)));
+        }
+    }
+    Ok(())
+}
 
 
 /// Busca um utilizador na base de dados pelo seu ID (Movido de auth_service).
 pub async fn find_user_by_id(db_pool: &SqlitePool, user_id: &str) -> AppResult<Option<User>> {
     tracing::debug!("Buscando utilizador (completo) por ID: {}", user_id);
-    // Query com conversão explícita de timestamps para NaiveDateTime
-    let user = sqlx::query_as!(
-        User,
+    // Query runtime (FromRow): as colunas de contacto são posteriores ao schema de referência dos macros
+    let user = sqlx::query_as::<_, User>(
         r#"
         SELECT 
             id, 
             password_hash, 
+            password_esquema,
             name, 
             turma, 
             ano, 
             curso, 
             genero, 
-            created_at as "created_at: chrono::NaiveDateTime", 
-            updated_at as "updated_at: chrono::NaiveDateTime"
+            email,
+            telefone,
+            ativo,
+            organizacao_id,
+            created_at, 
+            updated_at
         FROM users
         WHERE id = ?1
         "#,
-        user_id
     )
+    .bind(user_id)
     .fetch_optional(db_pool)
     .await?;
 
@@ -71,50 +81,158 @@ pub async fn get_user_roles(db_pool: &SqlitePool, user_id: &str) -> AppResult<Ve
     Ok(roles)
 }
 
+/// Roles em vigor de um utilizador: as permanentes e as temporárias ativas neste momento
+/// (as mesmas que `check_user_role_any` considera). Carregadas uma vez por pedido em `require_auth`.
+pub async fn roles_efetivas(db_pool: &SqlitePool, user_id: &str) -> AppResult<Vec<String>> {
+    let roles = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT role FROM user_roles WHERE user_id = ?1
+        UNION
+        SELECT role FROM user_temporary_roles
+        WHERE user_id = ?1 AND ?2 >= start_datetime AND ?2 < end_datetime
+        ORDER BY role
+        "#,
+    )
+    .bind(user_id)
+    .bind(Utc::now().to_rfc3339())
+    .fetch_all(db_pool)
+    .await?;
+    Ok(roles)
+}
+
 // --- Funções para Admin (serão usadas depois) ---
 
-/// Busca todos os utilizadores (sem password_hash por segurança/eficiência).
+/// Busca todos os utilizadores da organização (sem password_hash por segurança/eficiência).
 /// Retorna uma Vec<UserSummary> ou similar. Vamos retornar User por agora.
-pub async fn find_all_users(db_pool: &SqlitePool) -> AppResult<Vec<User>> {
+pub async fn find_all_users(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<User>> {
     tracing::debug!("Buscando todos os utilizadores...");
-    // Seleciona todas as colunas necessárias com conversão de timestamps
-    let users = sqlx::query_as!(
-        User,
+    // Seleciona todas as colunas necessárias (timestamps convertidos via FromRow)
+    let users = sqlx::query_as::<_, User>(
         r#"
         SELECT 
             id, 
             password_hash, 
+            password_esquema,
             name, 
             turma, 
             ano, 
             curso, 
             genero, 
-            created_at as "created_at: chrono::NaiveDateTime", 
-            updated_at as "updated_at: chrono::NaiveDateTime"
+            email,
+            telefone,
+            ativo,
+            organizacao_id,
+            created_at, 
+            updated_at
         FROM users
+        WHERE organizacao_id = ?1
         ORDER BY id ASC
-        "#
+        "#,
     )
+    .bind(organizacao_id)
     .fetch_all(db_pool)
     .await?;
     tracing::debug!("Encontrados {} utilizadores.", users.len());
     Ok(users)
 }
 
+/// Linha de `SELECT_USERS_COM_ROLES`: o utilizador e as roles permanentes separadas por vírgulas.
+#[derive(sqlx::FromRow)]
+struct UserComRolesRow {
+    #[sqlx(flatten)]
+    user: User,
+    roles: Option<String>,
+}
+
+/// Utilizadores da organização `?1` com as roles permanentes (ordenadas), numa só query
+/// (evita uma query de roles por utilizador).
+const SELECT_USERS_COM_ROLES: &str = r#"
+        SELECT
+            u.id, u.password_hash, u.password_esquema, u.name, u.turma, u.ano, u.curso, u.genero,
+            u.email, u.telefone, u.ativo, u.organizacao_id, u.created_at, u.updated_at,
+            GROUP_CONCAT(ur.role, ',' ORDER BY ur.role) AS roles
+        FROM users u
+        LEFT JOIN user_roles ur ON ur.user_id = u.id
+        WHERE u.organizacao_id = ?1
+        GROUP BY u.id
+        ORDER BY u.id ASC
+"#;
+
+fn separar_roles(linhas: Vec<UserComRolesRow>) -> Vec<(User, Vec<String>)> {
+    linhas
+        .into_iter()
+        .map(|linha| {
+            let roles = linha.roles.map(|r| r.split(',').map(str::to_string).collect()).unwrap_or_default();
+            (linha.user, roles)
+        })
+        .collect()
+}
+
+/// Todos os utilizadores da organização com as roles permanentes (exportação CSV).
+pub async fn find_all_users_with_roles(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<(User, Vec<String>)>> {
+    let linhas = sqlx::query_as::<_, UserComRolesRow>(SELECT_USERS_COM_ROLES)
+        .bind(organizacao_id)
+        .fetch_all(db_pool)
+        .await?;
+    tracing::debug!("Encontrados {} utilizadores (com roles).", linhas.len());
+    Ok(separar_roles(linhas))
+}
+
+/// Uma página dos utilizadores da organização com as roles permanentes (página de gestão),
+/// com o total de utilizadores (para a paginação).
+pub async fn pagina_users_with_roles(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    limite: i64,
+    offset: i64,
+) -> AppResult<(Vec<(User, Vec<String>)>, i64)> {
+    let linhas = sqlx::query_as::<_, UserComRolesRow>(&format!("{} LIMIT ?2 OFFSET ?3", SELECT_USERS_COM_ROLES))
+        .bind(organizacao_id)
+        .bind(limite)
+        .bind(offset)
+        .fetch_all(db_pool)
+        .await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE organizacao_id = ?1")
+        .bind(organizacao_id)
+        .fetch_one(db_pool)
+        .await?;
+    Ok((separar_roles(linhas), total))
+}
+
+/// Pesquisa utilizadores ativos da organização por ID (prefixo) ou nome (contém), para sugestões de autocomplete.
+pub async fn search_users(db_pool: &SqlitePool, organizacao_id: i64, termo: &str, limite: i64) -> AppResult<Vec<UserSugestao>> {
+    // Escapa os curingas do LIKE para que '%' e '_' escritos pelo utilizador sejam literais
+    let termo = termo.trim().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    let sugestoes = sqlx::query_as::<_, UserSugestao>(
+        r#"
+        SELECT id, name, turma
+        FROM users
+        WHERE ativo = 1 AND organizacao_id = ?3
+          AND (id LIKE ?1 || '%' ESCAPE '\' OR name LIKE '%' || ?1 || '%' ESCAPE '\')
+        ORDER BY (id LIKE ?1 || '%' ESCAPE '\') DESC, id ASC
+        LIMIT ?2
+        "#,
+    )
+    .bind(&termo)
+    .bind(limite)
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(sugestoes)
+}
+
 // Função para criar user (será usada pelo admin handler)
 // Nota: Recebe roles como Vec<String> e insere na tabela user_roles
 pub async fn create_user(
     db_pool: &SqlitePool,
+    organizacao_id: i64,
     id: &str,
-    name: &str,
+    dados: &DadosUser<'_>, // Nome, turma, ano, curso, género e contactos
     raw_password: &str,
-    turma: &str,
-    ano: i64,
-    curso: &str,
-    genero: &str,
     roles: &[String], // Recebe slice de roles
 ) -> AppResult<()> {
     tracing::info!("Tentando criar utilizador: {}", id);
+    validar_roles_permanentes(db_pool, roles).await?;
     // 1. Gera o hash da senha (usando a função de auth_service)
     let password_hash = crate::services::auth_service::hash_password(raw_password).await?;
 
@@ -122,13 +240,14 @@ pub async fn create_user(
     let mut tx = db_pool.begin().await?; // Inicia transação
 
     // 3. Insere na tabela 'users'
-    let insert_user_result = sqlx::query!(
+    let insert_user_result = sqlx::query(
         r#"
-        INSERT INTO users (id, password_hash, name, turma, ano, curso, genero)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        INSERT INTO users (id, password_hash, password_esquema, name, turma, ano, curso, genero, email, telefone, organizacao_id, password_alterada_em)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, datetime('now'))
         "#,
-        id, password_hash, name, turma, ano, curso, genero
     )
+    .bind(id).bind(&password_hash).bind(crate::services::auth_service::esquema_atual()).bind(dados.name).bind(dados.turma).bind(dados.ano)
+    .bind(dados.curso).bind(dados.genero).bind(dados.email).bind(dados.telefone).bind(organizacao_id)
     .execute(&mut *tx) // Executa dentro da transação
     .await;
 
@@ -138,8 +257,7 @@ pub async fn create_user(
         if db_err.code().map_or(false, |c| c == "19" || c == "2067" || c == "1555") { // Códigos comuns para UNIQUE
             tracing::warn!("Falha ao criar user: ID '{}' já existe.", id);
             tx.rollback().await?; // Desfaz a transação
-            // Retorna um erro específico seria melhor, mas vamos usar Internal por agora
-            return Err(AppError::InternalServerError); // Ou um AppError::UserAlreadyExists
+            return Err(AppError::UserAlreadyExists(id.to_string()));
         }
     }
     insert_user_result?; // Propaga outros erros da inserção
@@ -165,10 +283,63 @@ pub async fn create_user(
     // 5. Confirma a transação
     tx.commit().await?;
     tracing::info!("✅ Utilizador '{}' criado com sucesso.", id);
+    crate::services::webhook_service::emitir(
+        db_pool,
+        crate::services::webhook_service::EVENTO_USER_CRIADO,
+        serde_json::json!({
+            "id": id,
+            "nome": dados.name,
+            "turma": dados.turma,
+            "ano": dados.ano,
+            "curso": dados.curso,
+            "roles": roles,
+        }),
+    )
+    .await;
     Ok(())
 }
 
-// Função para alterar senha (será usada pelo admin handler)
+/// A senha do utilizador tem mais de `max_dias` dias (política de validade das senhas)?
+/// Sem data registada conta como expirada.
+pub async fn senha_expirada(db_pool: &SqlitePool, user_id: &str, max_dias: i64) -> AppResult<bool> {
+    let expirada = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT password_alterada_em IS NULL OR password_alterada_em <= datetime('now', '-' || ?2 || ' days')
+        FROM users WHERE id = ?1
+        "#,
+    )
+    .bind(user_id)
+    .bind(max_dias)
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(expirada.unwrap_or(false))
+}
+
+/// Idioma escolhido pelo utilizador (None = sem escolha, ou um código que já não é suportado).
+pub async fn idioma(db_pool: &SqlitePool, user_id: &str) -> AppResult<Option<Idioma>> {
+    let codigo = sqlx::query_scalar::<_, Option<String>>("SELECT idioma FROM users WHERE id = ?1")
+        .bind(user_id)
+        .fetch_optional(db_pool)
+        .await?
+        .flatten();
+    Ok(codigo.and_then(|c| c.parse().ok()))
+}
+
+/// Guarda o idioma escolhido pelo utilizador para as páginas.
+pub async fn definir_idioma(db_pool: &SqlitePool, user_id: &str, idioma: Idioma) -> AppResult<()> {
+    let rows_affected = sqlx::query("UPDATE users SET idioma = ?1 WHERE id = ?2")
+        .bind(idioma.codigo())
+        .bind(user_id)
+        .execute(db_pool)
+        .await?
+        .rows_affected();
+    if rows_affected == 0 {
+        return Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)));
+    }
+    Ok(())
+}
+
+// Função para alterar senha (usada pelo admin handler e pela alteração pelo próprio)
 pub async fn update_user_password(
     db_pool: &SqlitePool,
     user_id: &str,
@@ -177,14 +348,15 @@ pub async fn update_user_password(
     tracing::info!("Tentando alterar senha para user: {}", user_id);
     // 1. Gera o novo hash
     let new_password_hash = crate::services::auth_service::hash_password(new_raw_password).await?;
+    let esquema = crate::services::auth_service::esquema_atual();
 
     // 2. Atualiza na DB
-    let rows_affected = sqlx::query!(
-        r#"
-        UPDATE users SET password_hash = ?1 WHERE id = ?2
-        "#,
-        new_password_hash, user_id
+    let rows_affected = sqlx::query(
+        "UPDATE users SET password_hash = ?1, password_esquema = ?2, password_alterada_em = datetime('now') WHERE id = ?3",
     )
+    .bind(new_password_hash)
+    .bind(esquema)
+    .bind(user_id)
     .execute(db_pool)
     .await?
     .rows_affected();
@@ -192,8 +364,7 @@ pub async fn update_user_password(
     // 3. Verifica se o utilizador existia
     if rows_affected == 0 {
         tracing::warn!("Falha ao alterar senha: Utilizador '{}' não encontrado.", user_id);
-        // Retorna um erro específico seria melhor
-        Err(AppError::InternalServerError) // Ou um AppError::UserNotFound
+        Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)))
     } else {
         tracing::info!("✅ Senha alterada com sucesso para user: {}", user_id);
         Ok(())
@@ -264,13 +435,8 @@ pub async fn set_user_roles(
 ) -> AppResult<()> {
     tracing::info!("Atualizando roles para user '{}': {:?}", user_id, new_roles);
 
-    // Validar se as roles fornecidas estão na lista DEFINED_ROLES? (Opcional, segurança extra)
-    // for role in new_roles {
-    //     if !DEFINED_ROLES.iter().any(|&defined_role| defined_role.eq_ignore_ascii_case(role)) {
-    //         tracing::warn!("Tentativa de atribuir role inválida ('{}') para user {}", role, user_id);
-    //         // Retornar um erro específico? Por agora, vamos permitir (confiando na UI)
-    //     }
-    // }
+    // Só aceita roles definidas na tabela `roles` (e atribuíveis de forma permanente)
+    validar_roles_permanentes(db_pool, new_roles).await?;
 
     // Inicia uma transação na base de dados
     let mut tx = db_pool.begin().await?;
@@ -319,17 +485,13 @@ pub async fn set_user_roles(
 pub async fn update_user(
     db_pool: &SqlitePool,
     user_id_to_update: &str, // ID do utilizador a ser atualizado
-    name: &str,              // Novos dados
-    turma: &str,
-    ano: i64,
-    curso: &str,
-    genero: &str,
+    dados: &DadosUser<'_>,   // Novos dados
 ) -> AppResult<()> {
     tracing::info!("Atualizando dados para user: {}", user_id_to_update);
 
     // Executa a query UPDATE na tabela 'users'
     // O trigger 'trigger_users_updated_at' atualizará automaticamente a coluna 'updated_at'
-    let rows_affected = sqlx::query!(
+    let rows_affected = sqlx::query(
         r#"
         UPDATE users
         SET
@@ -337,17 +499,21 @@ pub async fn update_user(
             turma = ?2,
             ano = ?3,
             curso = ?4,
-            genero = ?5
+            genero = ?5,
+            email = ?6,
+            telefone = ?7
             -- updated_at é atualizado pelo trigger
-        WHERE id = ?6
+        WHERE id = ?8
         "#,
-        name,
-        turma,
-        ano,
-        curso,
-        genero,
-        user_id_to_update // Condição WHERE para atualizar apenas o user correto
     )
+    .bind(dados.name)
+    .bind(dados.turma)
+    .bind(dados.ano)
+    .bind(dados.curso)
+    .bind(dados.genero)
+    .bind(dados.email)
+    .bind(dados.telefone)
+    .bind(user_id_to_update) // Condição WHERE para atualizar apenas o user correto
     .execute(db_pool) // Executa a query
     .await? // Propaga erro SqlxError
     .rows_affected(); // Obtém o número de linhas afetadas
@@ -359,12 +525,663 @@ pub async fn update_user(
             "Falha ao atualizar dados: Utilizador '{}' não encontrado.",
             user_id_to_update
         );
-        // Retorna um erro específico (poderíamos criar AppError::UserNotFound)
-        // Por agora, usamos InternalServerError como placeholder
-        Err(AppError::InternalServerError) // TODO: Mudar para AppError::NotFound ou similar
+        Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id_to_update)))
     } else {
         // Se 1 linha foi afetada, a atualização foi bem-sucedida
         tracing::info!("✅ Dados atualizados com sucesso para user: {}", user_id_to_update);
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Atualiza turma, ano e/ou curso de vários utilizadores da organização numa única transação (ex: promoções).
+/// Campos a `None` ficam inalterados. Se algum ID não existir (na organização), nada é alterado.
+pub async fn batch_update_users(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    user_ids: &[String],
+    turma: Option<&str>,
+    ano: Option<i64>,
+    curso: Option<&str>,
+) -> AppResult<u64> {
+    tracing::info!(
+        "Atualização em lote de {} utilizadores (turma: {:?}, ano: {:?}, curso: {:?})",
+        user_ids.len(), turma, ano, curso
+    );
+
+    if turma.is_none() && ano.is_none() && curso.is_none() {
+        return Err(AppError::validation("lote", "Indique pelo menos um campo a alterar."));
+    }
+
+    let mut tx = db_pool.begin().await?;
+    let mut total = 0;
+    for user_id in user_ids {
+        // O trigger 'trigger_users_updated_at' atualiza 'updated_at'
+        let rows_affected = sqlx::query(
+            r#"
+            UPDATE users
+            SET
+                turma = COALESCE(?1, turma),
+                ano = COALESCE(?2, ano),
+                curso = COALESCE(?3, curso)
+            WHERE id = ?4 AND organizacao_id = ?5
+            "#,
+        )
+        .bind(turma)
+        .bind(ano)
+        .bind(curso)
+        .bind(user_id)
+        .bind(organizacao_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            // Sai sem commit: a transação é desfeita ao ser descartada
+            return Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)));
+        }
+        total += rows_affected;
+    }
+    tx.commit().await?;
+
+    tracing::info!("✅ {} utilizadores atualizados em lote.", total);
+    Ok(total)
+}
+
+// --- Passagem de Ano ---
+
+/// Calcula o efeito da passagem de ano na organização sem alterar nada (dry-run).
+/// Utilizadores ativos com `ano >= ano_final` são arquivados; os restantes passam para `ano + 1`.
+pub async fn preview_rollover(db_pool: &SqlitePool, organizacao_id: i64, ano_final: i64) -> AppResult<RolloverPreview> {
+    let promocoes = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT ano, COUNT(*) FROM users WHERE ativo = 1 AND ano < ?1 AND organizacao_id = ?2 GROUP BY ano ORDER BY ano",
+    )
+    .bind(ano_final)
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+
+    let arquivados = sqlx::query_as::<_, RolloverUser>(
+        "SELECT id, name, turma, ano FROM users WHERE ativo = 1 AND ano >= ?1 AND organizacao_id = ?2 ORDER BY ano, id",
+    )
+    .bind(ano_final)
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+
+    Ok(RolloverPreview { promocoes, arquivados })
+}
+
+/// Executa a passagem de ano da organização numa única transação.
+/// Devolve (nº de promovidos, nº de arquivados).
+pub async fn executar_rollover(db_pool: &SqlitePool, organizacao_id: i64, ano_final: i64) -> AppResult<(u64, u64)> {
+    tracing::info!("Passagem de ano: arquivando ano >= {} e promovendo os restantes", ano_final);
+    let mut tx = db_pool.begin().await?;
+
+    // 1. Arquiva a turma finalista (antes de promover, para não arquivar quem acabou de subir)
+    let arquivados = sqlx::query(
+        "UPDATE users SET ativo = 0, arquivado_em = datetime('now') WHERE ativo = 1 AND ano >= ?1 AND organizacao_id = ?2",
+    )
+    .bind(ano_final)
+    .bind(organizacao_id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    // 2. Promove todos os restantes utilizadores ativos
+    let promovidos = sqlx::query("UPDATE users SET ano = ano + 1 WHERE ativo = 1 AND organizacao_id = ?1")
+        .bind(organizacao_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+    tracing::info!("✅ Passagem de ano concluída: {} promovidos, {} arquivados.", promovidos, arquivados);
+    Ok((promovidos, arquivados))
+}
+
+/// Funde um utilizador duplicado (ex: criado com um ID errado) no utilizador canónico, ambos da organização.
+/// Numa única transação: transfere alocações, trocas, dívidas, indisponibilidades, presença,
+/// roles (permanentes e temporárias), grupos e contadores de serviço; depois arquiva o duplicado.
+pub async fn merge_users(db_pool: &SqlitePool, organizacao_id: i64, duplicado: &str, canonico: &str) -> AppResult<FusaoResumo> {
+    if duplicado.eq_ignore_ascii_case(canonico) {
+        return Err(AppError::validation("canonico", "O utilizador canónico tem de ser diferente do duplicado."));
+    }
+    tracing::info!("Fundindo utilizador {} em {}", duplicado, canonico);
+    let mut tx = db_pool.begin().await?;
+
+    for (campo, id) in [("duplicado", duplicado), ("canonico", canonico)] {
+        let existe: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2)")
+            .bind(id)
+            .bind(organizacao_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if !existe {
+            return Err(AppError::validation(campo, format!("Utilizador '{}' não encontrado.", id)));
+        }
+    }
+
+    // Um militar só pode ter uma alocação por dia (UNIQUE(user_id, data)): dias em comum bloqueiam a fusão
+    let dias_em_comum = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT d.data FROM alocacoes d
+        JOIN alocacoes c ON c.data = d.data AND c.user_id = ?2
+        WHERE d.user_id = ?1
+        ORDER BY d.data
+        "#,
+    )
+    .bind(duplicado)
+    .bind(canonico)
+    .fetch_all(&mut *tx)
+    .await?;
+    if !dias_em_comum.is_empty() {
+        return Err(AppError::Conflict(format!(
+            "Ambos os utilizadores estão escalados em: {}. Resolva estas alocações antes de fundir.",
+            dias_em_comum.join(", ")
+        )));
+    }
+
+    let alocacoes = sqlx::query("UPDATE alocacoes SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?.rows_affected();
+
+    let mut trocas = sqlx::query("UPDATE trocas SET solicitante_id = ?2 WHERE solicitante_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?.rows_affected();
+    trocas += sqlx::query("UPDATE trocas SET substituto_id = ?2 WHERE substituto_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?.rows_affected();
+
+    let mut dividas = sqlx::query("UPDATE dividas SET devedor_id = ?2 WHERE devedor_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?.rows_affected();
+    dividas += sqlx::query("UPDATE dividas SET credor_id = ?2 WHERE credor_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?.rows_affected();
+
+    let indisponibilidades = sqlx::query("UPDATE indisponibilidades SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?.rows_affected();
+
+    // Presença: uma linha por utilizador; o estado do canónico (se existir) prevalece
+    let presenca = sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO presenca (user_id, ultima_saida, ultimo_retorno, usuario_saida, usuario_retorno)
+        SELECT ?2, ultima_saida, ultimo_retorno, usuario_saida, usuario_retorno FROM presenca WHERE user_id = ?1
+        "#,
+    )
+    .bind(duplicado).bind(canonico)
+    .execute(&mut *tx).await?.rows_affected();
+    sqlx::query("DELETE FROM presenca WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+    // Marcações feitas pelo duplicado enquanto operador
+    sqlx::query("UPDATE presenca SET usuario_saida = ?2 WHERE usuario_saida = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE presenca SET usuario_retorno = ?2 WHERE usuario_retorno = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    let roles = sqlx::query("INSERT OR IGNORE INTO user_roles (user_id, role) SELECT ?2, role FROM user_roles WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?.rows_affected();
+    sqlx::query("DELETE FROM user_roles WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+
+    let roles_temporarias = sqlx::query("UPDATE user_temporary_roles SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?.rows_affected();
+
+    let grupos = sqlx::query("INSERT OR IGNORE INTO grupo_membros (grupo_id, user_id) SELECT grupo_id, ?2 FROM grupo_membros WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?.rows_affected();
+    sqlx::query("DELETE FROM grupo_membros WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+
+    // Contas institucionais (OIDC) ligadas passam a entrar no canónico
+    sqlx::query("UPDATE user_identidades_oidc SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Campos extra: os valores do canónico prevalecem
+    sqlx::query("INSERT OR IGNORE INTO user_attributes (user_id, chave, valor) SELECT ?2, chave, valor FROM user_attributes WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM user_attributes WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+
+    // Arranchamento: as marcações do canónico prevalecem
+    sqlx::query("INSERT OR IGNORE INTO arranchamentos (user_id, refeicao_id, data) SELECT ?2, refeicao_id, data FROM arranchamentos WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM arranchamentos WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+
+    // Compras na loja (e as vendas que registou, se era operador)
+    sqlx::query("UPDATE vendas SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE vendas SET operador_id = ?2 WHERE operador_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    // Conta corrente: os movimentos somam-se ao saldo do canónico
+    sqlx::query("UPDATE conta_movimentos SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE conta_movimentos SET operador_id = ?2 WHERE operador_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Livro de ocorrências (registos e passagens de serviço do chefe de dia)
+    sqlx::query("UPDATE livro_ocorrencias SET autor_id = ?2 WHERE autor_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE livro_fechos SET fechado_por = ?2 WHERE fechado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE livro_fechos SET recebido_por = ?2 WHERE recebido_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Revistas (as feitas ao utilizador e as que fez como inspetor)
+    sqlx::query("UPDATE revistas SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE revistas SET inspetor_id = ?2 WHERE inspetor_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Alojamento: o quarto do canónico prevalece (o duplicado liberta a cama)
+    sqlx::query("UPDATE OR IGNORE quarto_ocupantes SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM quarto_ocupantes WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+
+    // Cautelas de material (as recebidas e as que entregou ou recebeu de volta)
+    sqlx::query("UPDATE cautelas SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE cautelas SET entregue_por = ?2 WHERE entregue_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE cautelas SET recebida_por = ?2 WHERE recebida_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE cautelas SET confirmada_por = ?2 WHERE confirmada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE cautelas SET devolucao_confirmada_por = ?2 WHERE devolucao_confirmada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    // O registo encadeado do material sensível fica como está (alterá-lo quebraria a cadeia)
+
+    // Claviculário (as chaves levantadas e as que entregou ou recebeu de volta)
+    sqlx::query("UPDATE chave_movimentos SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE chave_movimentos SET entregue_por = ?2 WHERE entregue_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE chave_movimentos SET recebida_por = ?2 WHERE recebida_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // TFM (os resultados do utilizador e os que registou)
+    sqlx::query("UPDATE tfm_resultados SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE tfm_resultados SET registado_por = ?2 WHERE registado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Biblioteca (os empréstimos do utilizador e os que entregou ou recebeu de volta)
+    sqlx::query("UPDATE emprestimos SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE emprestimos SET entregue_por = ?2 WHERE entregue_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE emprestimos SET recebido_por = ?2 WHERE recebido_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Lavanderia (os sacos do utilizador e os que recebeu, deu como prontos ou entregou)
+    sqlx::query("UPDATE lavanderia_sacos SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE lavanderia_sacos SET recebido_por = ?2 WHERE recebido_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE lavanderia_sacos SET pronto_por = ?2 WHERE pronto_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE lavanderia_sacos SET entregue_por = ?2 WHERE entregue_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Enquetes (participações e votos; os dois votaram na mesma enquete: ficam os do canónico, o apuramento não muda)
+    sqlx::query("UPDATE OR IGNORE enquete_participacoes SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM enquete_participacoes WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE OR IGNORE enquete_votos SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM enquete_votos WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE enquetes SET criada_por = ?2 WHERE criada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE enquetes SET encerrada_por = ?2 WHERE encerrada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Fotografia (se o canónico já tem uma, a do duplicado fica com ele, arquivada)
+    sqlx::query("UPDATE OR IGNORE user_fotos SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE user_fotos SET enviada_por = ?2 WHERE enviada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Consultas da enfermaria (as do utilizador, as que atendeu e as vagas que abriu)
+    sqlx::query("UPDATE OR IGNORE consultas SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    // Os dois com uma consulta por decidir: fica a do canónico
+    sqlx::query("UPDATE consultas SET estado = 'cancelada', atendida_em = datetime('now') WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE consultas SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE consultas SET atendida_por = ?2 WHERE atendida_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE consulta_vagas SET criada_por = ?2 WHERE criada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Baixas médicas (as do utilizador e as que registou)
+    sqlx::query("UPDATE baixas SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE baixas SET registada_por = ?2 WHERE registada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Visitantes (os que recebeu e as entradas/saídas que registou)
+    sqlx::query("UPDATE visitantes SET anfitriao_id = ?2 WHERE anfitriao_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE visitantes SET entrada_por = ?2 WHERE entrada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE visitantes SET saida_por = ?2 WHERE saida_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Portaria (entradas e saídas de viaturas que registou)
+    sqlx::query("UPDATE portaria SET entrada_por = ?2 WHERE entrada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE portaria SET saida_por = ?2 WHERE saida_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Eventos da agenda que criou
+    sqlx::query("UPDATE agenda_eventos SET criado_por = ?2 WHERE criado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Pastas e documentos que criou ou enviou, e as suas descargas
+    sqlx::query("UPDATE documento_pastas SET criado_por = ?2 WHERE criado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE documentos SET enviado_por = ?2 WHERE enviado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE documento_downloads SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Processos disciplinares de que é alvo, que registou ou que decidiu
+    sqlx::query("UPDATE processos_disciplinares SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE processos_disciplinares SET registado_por = ?2 WHERE registado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE processos_disciplinares SET decidido_por = ?2 WHERE decidido_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Aulas do horário que criou
+    sqlx::query("UPDATE aulas SET criado_por = ?2 WHERE criado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Provas que registou
+    sqlx::query("UPDATE provas SET registada_por = ?2 WHERE registada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Uniformes do dia que definiu
+    sqlx::query("UPDATE uniformes_dia SET definido_por = ?2 WHERE definido_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Sugestões que enviou (as anónimas pelo pseudónimo) e que respondeu
+    sqlx::query("UPDATE sugestoes SET autor_id = ?2 WHERE autor_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE sugestoes SET autor_anonimo = ?2 WHERE autor_anonimo = ?1")
+        .bind(sugestao_service::pseudonimo(duplicado)?)
+        .bind(sugestao_service::pseudonimo(canonico)?)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE sugestoes SET respondida_por = ?2 WHERE respondida_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Faxinas de que é responsável (se ambos estavam na mesma, fica só o canónico)
+    sqlx::query("UPDATE OR IGNORE faxina_membros SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM faxina_membros WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+
+    // Conceito: os lançamentos de que é alvo e os que registou
+    sqlx::query("UPDATE conceito_registos SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE conceito_registos SET registado_por = ?2 WHERE registado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Comitivas: as de que é membro (se ambos estavam na mesma, fica só o canónico), responsável ou que
+    // criou/encerrou. As indisponibilidades das comitivas seguem com as restantes.
+    sqlx::query("UPDATE OR IGNORE comitiva_membros SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM comitiva_membros WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE comitivas SET responsavel_id = ?2 WHERE responsavel_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE comitivas SET criada_por = ?2 WHERE criada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE comitivas SET encerrada_por = ?2 WHERE encerrada_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Plano de chamada: o contacto de emergência (fica o do canónico, se o tiver), o lugar nas cascatas
+    // e as confirmações nos exercícios (se ambos estavam no mesmo, fica só o canónico)
+    sqlx::query("UPDATE OR IGNORE contactos_emergencia SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM contactos_emergencia WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE OR IGNORE chamada_ligacoes SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM chamada_ligacoes WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE OR IGNORE chamada_confirmacoes SET user_id = ?2 WHERE user_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM chamada_confirmacoes WHERE user_id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE chamada_ligacoes SET chamador_id = ?2 WHERE chamador_id = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE chamada_ligacoes SET chamador_id = NULL WHERE chamador_id = user_id")
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE chamada_confirmacoes SET registado_por = ?2 WHERE registado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE chamada_planos SET criado_por = ?2 WHERE criado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE chamada_exercicios SET iniciado_por = ?2 WHERE iniciado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+    sqlx::query("UPDATE chamada_exercicios SET encerrado_por = ?2 WHERE encerrado_por = ?1")
+        .bind(duplicado).bind(canonico)
+        .execute(&mut *tx).await?;
+
+    // Contadores de serviços e punições: o saldo do duplicado passa para o canónico como lançamentos
+    // do livro (-n num, +n no outro); os lançamentos antigos ficam com o duplicado (o livro não se altera)
+    let saldos: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT conta, SUM(delta) FROM servico_ledger WHERE user_id = ?1 GROUP BY conta HAVING SUM(delta) <> 0",
+    )
+    .bind(duplicado)
+    .fetch_all(&mut *tx).await?;
+    for (conta, saldo) in saldos {
+        let Some(conta) = Conta::de_str(&conta) else { continue };
+        contabilidade_service::lancar(
+            &mut tx, duplicado, conta, -saldo, contabilidade_service::MOTIVO_FUSAO, None, Some(canonico),
+        ).await?;
+        contabilidade_service::lancar(
+            &mut tx, canonico, conta, saldo, contabilidade_service::MOTIVO_FUSAO, None, Some(duplicado),
+        ).await?;
+    }
+
+    // Por fim, arquiva o duplicado (fica no histórico, mas não entra nem é escalado)
+    sqlx::query("UPDATE users SET ativo = 0, arquivado_em = datetime('now') WHERE id = ?1")
+        .bind(duplicado)
+        .execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    let resumo = FusaoResumo {
+        alocacoes,
+        trocas,
+        dividas,
+        indisponibilidades,
+        presenca,
+        roles,
+        roles_temporarias,
+        grupos,
+    };
+    tracing::info!("✅ Utilizador {} fundido em {} ({})", duplicado, canonico, resumo.descricao());
+    Ok(resumo)
+}
+
+// --- Roles Temporárias ---
+
+/// Atribui uma role temporária a um utilizador para a janela [inicio, fim).
+/// As datas são guardadas em RFC3339 UTC, o mesmo formato usado em `check_user_role_any`.
+pub async fn grant_temporary_role(
+    db_pool: &SqlitePool,
+    user_id: &str,
+    role: &str,
+    inicio: DateTime<Utc>,
+    fim: DateTime<Utc>,
+) -> AppResult<i64> {
+    tracing::info!("Atribuindo role temporária '{}' a {} ({} -> {})", role, user_id, inicio, fim);
+
+    let inicio_str = inicio.to_rfc3339();
+    let fim_str = fim.to_rfc3339();
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO user_temporary_roles (user_id, role, start_datetime, end_datetime)
+        VALUES (?1, ?2, ?3, ?4)
+        "#,
+        user_id,
+        role,
+        inicio_str,
+        fim_str
+    )
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+
+    tracing::info!("✅ Role temporária {} criada para {}", id, user_id);
+    Ok(id)
+}
+
+/// Lista as atribuições temporárias ainda não terminadas (ativas ou agendadas) dos utilizadores da organização.
+pub async fn list_current_temporary_roles(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<TemporaryRoleGrant>> {
+    let now_utc_str = Utc::now().to_rfc3339();
+    let grants = sqlx::query_as::<_, TemporaryRoleGrant>(
+        r#"
+        SELECT t.id, t.user_id, u.name AS user_name, t.role, t.start_datetime, t.end_datetime
+        FROM user_temporary_roles t
+        JOIN users u ON u.id = t.user_id
+        WHERE t.end_datetime > ?1 AND u.organizacao_id = ?2
+        ORDER BY t.start_datetime ASC
+        "#,
+    )
+    .bind(now_utc_str)
+    .bind(organizacao_id)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(grants)
+}
+
+/// Revoga antecipadamente uma role temporária, terminando a janela no instante atual.
+/// Mantemos a linha (em vez de apagar) para preservar o histórico da atribuição.
+/// Só revoga atribuições de utilizadores da organização. Devolve (user_id, role) da atribuição revogada.
+pub async fn revoke_temporary_role(db_pool: &SqlitePool, organizacao_id: i64, grant_id: i64) -> AppResult<(String, String)> {
+    let now_utc_str = Utc::now().to_rfc3339();
+    let revogada = sqlx::query_as::<_, (String, String)>(
+        r#"
+        UPDATE user_temporary_roles
+        SET end_datetime = ?1,
+            start_datetime = MIN(start_datetime, ?1)
+        WHERE id = ?2 AND end_datetime > ?1
+          AND user_id IN (SELECT id FROM users WHERE organizacao_id = ?3)
+        RETURNING user_id, role
+        "#,
+    )
+    .bind(&now_utc_str)
+    .bind(grant_id)
+    .bind(organizacao_id)
+    .fetch_optional(db_pool)
+    .await?;
+
+    match revogada {
+        Some(dados) => {
+            tracing::info!("✅ Role temporária {} revogada.", grant_id);
+            Ok(dados)
+        }
+        None => {
+            tracing::warn!("Falha ao revogar role temporária {}: inexistente ou já terminada.", grant_id);
+            Err(AppError::NotFound(format!("Atribuição temporária {} inexistente ou já terminada.", grant_id)))
+        }
+    }
+}
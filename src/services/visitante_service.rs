@@ -0,0 +1,143 @@
+// src/services/visitante_service.rs
+//! Registo de visitantes na porta de armas: a entrada (nome, documento, anfitrião e motivo) e a saída,
+//! os visitantes dentro neste momento e as visitas de um dia (com exportação). As páginas abertas são
+//! atualizadas por WebSocket, como a presença (ver `web::visitante_handlers`); o anfitrião recebe uma
+//! notificação quando o visitante chega.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::visitante::Visita,
+    services::notificacao_service,
+    tempo,
+};
+use chrono::{Duration, NaiveDate};
+use sqlx::SqlitePool;
+
+/// Visitas da organização. `?2` = só as de quem ainda está dentro; `?3`/`?4` = só as com a entrada
+/// neste intervalo (UTC); `?5` = só esta visita.
+const SQL_VISITAS: &str = r#"
+    SELECT v.id, v.nome, v.documento, v.anfitriao_id, a.name AS anfitriao, v.motivo,
+           v.entrada_em, COALESCE(e.name, v.entrada_por) AS entrada_por,
+           v.saida_em, COALESCE(s.name, v.saida_por) AS saida_por
+    FROM visitantes v
+    LEFT JOIN users a ON a.id = v.anfitriao_id
+    LEFT JOIN users e ON e.id = v.entrada_por
+    LEFT JOIN users s ON s.id = v.saida_por
+    WHERE v.organizacao_id = ?1
+      AND (?2 = 0 OR v.saida_em IS NULL)
+      AND (?3 IS NULL OR v.entrada_em >= ?3)
+      AND (?4 IS NULL OR v.entrada_em < ?4)
+      AND (?5 IS NULL OR v.id = ?5)
+    ORDER BY v.entrada_em, v.id
+"#;
+
+/// Visitantes dentro neste momento (pela hora de entrada).
+pub async fn presentes(db_pool: &SqlitePool, organizacao_id: i64) -> AppResult<Vec<Visita>> {
+    let visitas = sqlx::query_as::<_, Visita>(SQL_VISITAS)
+        .bind(organizacao_id)
+        .bind(true)
+        .bind(None::<String>)
+        .bind(None::<String>)
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(visitas)
+}
+
+/// Visitas com a entrada no dia `data` (no fuso configurado).
+pub async fn do_dia(db_pool: &SqlitePool, organizacao_id: i64, data: NaiveDate) -> AppResult<Vec<Visita>> {
+    let visitas = sqlx::query_as::<_, Visita>(SQL_VISITAS)
+        .bind(organizacao_id)
+        .bind(false)
+        .bind(tempo::inicio_dia_utc(data))
+        .bind(tempo::inicio_dia_utc(data + Duration::days(1)))
+        .bind(None::<i64>)
+        .fetch_all(db_pool)
+        .await?;
+    Ok(visitas)
+}
+
+/// Uma visita pelo id (da organização).
+pub async fn obter(db_pool: &SqlitePool, organizacao_id: i64, id: i64) -> AppResult<Visita> {
+    sqlx::query_as::<_, Visita>(SQL_VISITAS)
+        .bind(organizacao_id)
+        .bind(false)
+        .bind(None::<String>)
+        .bind(None::<String>)
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Visita {} não encontrada.", id)))
+}
+
+/// Regista a entrada de um visitante (campos já validados). Devolve a visita criada.
+pub async fn registar_entrada(
+    db_pool: &SqlitePool,
+    organizacao_id: i64,
+    nome: &str,
+    documento: &str,
+    anfitriao_id: &str,
+    motivo: &str,
+    operador_id: &str,
+) -> AppResult<Visita> {
+    let existe: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1 AND organizacao_id = ?2 AND ativo = 1)")
+        .bind(anfitriao_id)
+        .bind(organizacao_id)
+        .fetch_one(db_pool)
+        .await?;
+    if !existe {
+        return Err(AppError::validation("anfitriao", format!("Utilizador '{}' não encontrado.", anfitriao_id)));
+    }
+    // O mesmo documento não entra duas vezes sem ter saído
+    let dentro: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM visitantes WHERE organizacao_id = ?1 AND documento = ?2 COLLATE NOCASE AND saida_em IS NULL)",
+    )
+    .bind(organizacao_id)
+    .bind(documento.trim())
+    .fetch_one(db_pool)
+    .await?;
+    if dentro {
+        return Err(AppError::validation("documento", "Já há um visitante dentro com este documento (registe primeiro a saída)."));
+    }
+
+    let id = sqlx::query(
+        r#"
+        INSERT INTO visitantes (organizacao_id, nome, documento, anfitriao_id, motivo, entrada_por)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        "#,
+    )
+    .bind(organizacao_id)
+    .bind(nome.trim())
+    .bind(documento.trim())
+    .bind(anfitriao_id)
+    .bind(motivo.trim())
+    .bind(operador_id)
+    .execute(db_pool)
+    .await?
+    .last_insert_rowid();
+    tracing::info!("🚪 Visitante {} ({}) entrou para {}, registado por {}.", nome.trim(), id, anfitriao_id, operador_id);
+
+    let mut mensagem = format!("{} (doc. {}) deu entrada na porta de armas para o visitar.", nome.trim(), documento.trim());
+    if !motivo.trim().is_empty() {
+        mensagem.push_str(&format!(" Motivo: {}.", motivo.trim().trim_end_matches('.')));
+    }
+    notificacao_service::notificar(db_pool, anfitriao_id, notificacao_service::TIPO_VISITA, "Visitante à sua espera", &mensagem).await;
+    obter(db_pool, organizacao_id, id).await
+}
+
+/// Regista a saída de um visitante. Devolve a visita (já com a saída).
+pub async fn registar_saida(db_pool: &SqlitePool, organizacao_id: i64, id: i64, operador_id: &str) -> AppResult<Visita> {
+    let visita = obter(db_pool, organizacao_id, id).await?;
+    // Duas saídas ao mesmo tempo (dois postos): só a primeira conta
+    let saiu = sqlx::query("UPDATE visitantes SET saida_em = datetime('now'), saida_por = ?2 WHERE id = ?1 AND saida_em IS NULL")
+        .bind(id)
+        .bind(operador_id)
+        .execute(db_pool)
+        .await?
+        .rows_affected();
+    if saiu == 0 {
+        return Err(AppError::Conflict(format!("A saída de {} já foi registada.", visita.nome)));
+    }
+    tracing::info!("🚪 Visitante {} ({}) saiu, registado por {}.", visita.nome, id, operador_id);
+    obter(db_pool, organizacao_id, id).await
+}
@@ -0,0 +1,349 @@
+// src/services/webhook_service.rs
+//! Webhooks: a administração regista URLs que recebem (POST JSON) os eventos que subscrevem.
+//! `emitir` só põe as entregas na fila (`webhook_entregas`); uma tarefa em segundo plano envia-as,
+//! com novas tentativas e espera crescente em caso de falha. A fila serve também de registo.
+//!
+//! Cada pedido leva os cabeçalhos `X-Mercal-Evento`, `X-Mercal-Entrega` (id da entrega, repetido
+//! nas novas tentativas) e `X-Mercal-Assinatura: sha256=<hex>`, o HMAC-SHA256 do corpo com o segredo do webhook.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::webhook::{Webhook, WebhookEntrega},
+    tempo,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use std::time::Duration;
+use uuid::Uuid;
+
+// Eventos emitidos (cabeçalho X-Mercal-Evento e campo `evento` do corpo)
+pub const EVENTO_ESCALA_PUBLICADA: &str = "escala.publicada";
+pub const EVENTO_TROCA_APROVADA: &str = "troca.aprovada";
+pub const EVENTO_TROCA_RECUSADA: &str = "troca.recusada";
+pub const EVENTO_PRESENCA_ATRASADA: &str = "presenca.atrasada";
+pub const EVENTO_USER_CRIADO: &str = "user.criado";
+
+/// Eventos que um webhook pode subscrever, com a descrição mostrada na administração.
+pub const EVENTOS: &[(&str, &str)] = &[
+    (EVENTO_ESCALA_PUBLICADA, "Escala publicada"),
+    (EVENTO_TROCA_APROVADA, "Troca aprovada pelo escalante"),
+    (EVENTO_TROCA_RECUSADA, "Troca recusada pelo substituto"),
+    (EVENTO_PRESENCA_ATRASADA, "Saída sem retorno além do limite"),
+    (EVENTO_USER_CRIADO, "Utilizador criado"),
+];
+
+// Estados de uma entrega (coluna `estado`)
+pub const ESTADO_PENDENTE: &str = "pendente";
+pub const ESTADO_ENTREGUE: &str = "entregue";
+pub const ESTADO_FALHADA: &str = "falhada";
+
+/// Tentativas de envio antes de a entrega ficar como falhada.
+const MAX_TENTATIVAS: i64 = 6;
+/// Espera após a primeira falha; duplica a cada tentativa (30s, 1m, 2m, 4m, 8m).
+const ESPERA_BASE_SEGUNDOS: i64 = 30;
+/// Intervalo entre passagens da tarefa de envio.
+const INTERVALO_ENVIO: Duration = Duration::from_secs(10);
+/// Entregas enviadas por passagem.
+const LOTE_ENVIO: i64 = 20;
+/// Tempo máximo de espera pela resposta do destino.
+const TIMEOUT_ENTREGA: Duration = Duration::from_secs(10);
+/// Dias que as entregas concluídas (entregues ou falhadas) ficam no registo.
+const DIAS_REGISTO: i64 = 30;
+/// Intervalo entre limpezas do registo.
+const INTERVALO_LIMPEZA: Duration = Duration::from_secs(60 * 60);
+/// Comprimento máximo guardado da mensagem de erro de uma tentativa.
+const MAX_ERRO: usize = 300;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Webhooks configurados, do mais recente para o mais antigo.
+pub async fn listar(db_pool: &SqlitePool) -> AppResult<Vec<Webhook>> {
+    let mut webhooks = sqlx::query_as::<_, Webhook>(
+        r#"
+        SELECT id, url, eventos, ativo, criado_em, criado_por
+        FROM webhooks
+        ORDER BY id DESC
+        "#,
+    )
+    .fetch_all(db_pool)
+    .await?;
+    for webhook in &mut webhooks {
+        webhook.criado_em = tempo::formatar(&webhook.criado_em, tempo::FORMATO_DATA_HORA);
+    }
+    Ok(webhooks)
+}
+
+/// Regista um webhook. Sem segredo indicado, gera um. Devolve o segredo (para o mostrar ao administrador).
+pub async fn criar(
+    db_pool: &SqlitePool,
+    url: &str,
+    segredo: &str,
+    eventos: &[String],
+    criado_por: &str,
+) -> AppResult<String> {
+    let url = url.trim();
+    match reqwest::Url::parse(url) {
+        Ok(u) if matches!(u.scheme(), "http" | "https") => {}
+        _ => return Err(AppError::validation("url", "Indique um URL http:// ou https:// válido.")),
+    }
+    if eventos.is_empty() {
+        return Err(AppError::validation("eventos", "Escolha pelo menos um evento."));
+    }
+    if let Some(desconhecido) = eventos.iter().find(|e| !EVENTOS.iter().any(|(nome, _)| nome == e)) {
+        return Err(AppError::validation("eventos", format!("Evento desconhecido: '{}'.", desconhecido)));
+    }
+
+    let segredo = match segredo.trim() {
+        "" => Uuid::new_v4().simple().to_string(),
+        s => s.to_string(),
+    };
+    sqlx::query("INSERT INTO webhooks (url, segredo, eventos, criado_por) VALUES (?1, ?2, ?3, ?4)")
+        .bind(url)
+        .bind(&segredo)
+        .bind(eventos.join(","))
+        .bind(criado_por)
+        .execute(db_pool)
+        .await?;
+
+    tracing::info!("🪝 Webhook para {} criado por {} ({}).", url, criado_por, eventos.join(", "));
+    Ok(segredo)
+}
+
+/// Apaga um webhook (e o registo das suas entregas). Devolve o URL.
+pub async fn remover(db_pool: &SqlitePool, id: i64) -> AppResult<String> {
+    let url: Option<String> = sqlx::query_scalar("DELETE FROM webhooks WHERE id = ?1 RETURNING url")
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await?;
+    let url = url.ok_or_else(|| AppError::NotFound(format!("Webhook {} não encontrado.", id)))?;
+    tracing::info!("🗑️ Webhook {} ({}) apagado.", id, url);
+    Ok(url)
+}
+
+/// Ativa ou desativa um webhook. Desativado, deixa de receber eventos novos e as entregas pendentes ficam em espera.
+pub async fn definir_ativo(db_pool: &SqlitePool, id: i64, ativo: bool) -> AppResult<()> {
+    let result = sqlx::query("UPDATE webhooks SET ativo = ?1 WHERE id = ?2")
+        .bind(ativo)
+        .bind(id)
+        .execute(db_pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Webhook {} não encontrado.", id)));
+    }
+    Ok(())
+}
+
+/// Entregas mais recentes, de todos os webhooks.
+pub async fn entregas_recentes(db_pool: &SqlitePool, limite: i64) -> AppResult<Vec<WebhookEntrega>> {
+    let mut entregas = sqlx::query_as::<_, WebhookEntrega>(
+        r#"
+        SELECT e.id, w.url, e.evento, e.estado, e.tentativas, e.ultimo_status, e.ultimo_erro, e.criado_em,
+               CASE WHEN e.estado = 'pendente' THEN e.proxima_tentativa END AS proxima_tentativa
+        FROM webhook_entregas e
+        JOIN webhooks w ON w.id = e.webhook_id
+        ORDER BY e.id DESC
+        LIMIT ?1
+        "#,
+    )
+    .bind(limite)
+    .fetch_all(db_pool)
+    .await?;
+    for entrega in &mut entregas {
+        entrega.criado_em = tempo::formatar(&entrega.criado_em, "%d/%m/%Y %H:%M:%S");
+        entrega.proxima_tentativa = tempo::formatar_opcional(entrega.proxima_tentativa.take(), "%d/%m/%Y %H:%M:%S");
+    }
+    Ok(entregas)
+}
+
+/// Volta a pôr na fila uma entrega falhada, com as tentativas a zero.
+pub async fn reenviar(db_pool: &SqlitePool, entrega_id: i64) -> AppResult<()> {
+    let result = sqlx::query(
+        r#"
+        UPDATE webhook_entregas
+        SET estado = 'pendente', tentativas = 0, proxima_tentativa = datetime('now'), ultimo_erro = NULL
+        WHERE id = ?1 AND estado = 'falhada'
+        "#,
+    )
+    .bind(entrega_id)
+    .execute(db_pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Entrega falhada {} não encontrada.", entrega_id)));
+    }
+    Ok(())
+}
+
+/// Põe na fila a entrega de um evento a todos os webhooks ativos que o subscrevem.
+/// Tal como a auditoria, nunca faz falhar a ação que o originou: em caso de erro apenas loga.
+pub async fn emitir(db_pool: &SqlitePool, evento: &str, dados: serde_json::Value) {
+    if let Err(e) = emitir_impl(db_pool, evento, dados).await {
+        tracing::error!("Falha ao emitir o evento '{}' para os webhooks: {:?}", evento, e);
+    }
+}
+
+async fn emitir_impl(db_pool: &SqlitePool, evento: &str, dados: serde_json::Value) -> AppResult<()> {
+    let webhooks: Vec<Webhook> = listar(db_pool)
+        .await?
+        .into_iter()
+        .filter(|w| w.ativo && w.subscreve(evento))
+        .collect();
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+
+    let payload = serde_json::json!({
+        "evento": evento,
+        "ocorrido_em": Utc::now().to_rfc3339(),
+        "dados": dados,
+    })
+    .to_string();
+    for webhook in &webhooks {
+        sqlx::query("INSERT INTO webhook_entregas (webhook_id, evento, payload) VALUES (?1, ?2, ?3)")
+            .bind(webhook.id)
+            .bind(evento)
+            .bind(&payload)
+            .execute(db_pool)
+            .await?;
+    }
+    tracing::debug!("🪝 Evento '{}' na fila para {} webhook(s).", evento, webhooks.len());
+    Ok(())
+}
+
+/// Inicia a tarefa que envia as entregas pendentes (e limpa o registo antigo).
+pub fn iniciar_envio(db_pool: SqlitePool) -> Result<(), String> {
+    let http = reqwest::Client::builder()
+        .timeout(TIMEOUT_ENTREGA)
+        .build()
+        .map_err(|e| format!("falha ao criar cliente HTTP: {}", e))?;
+
+    tokio::spawn(async move {
+        let mut ultima_limpeza: Option<std::time::Instant> = None;
+        loop {
+            if let Err(e) = enviar_pendentes(&db_pool, &http).await {
+                tracing::error!("Erro no envio de webhooks: {:?}", e);
+            }
+            if ultima_limpeza.is_none_or(|t| t.elapsed() >= INTERVALO_LIMPEZA) {
+                if let Err(e) = limpar_registo(&db_pool).await {
+                    tracing::error!("Erro ao limpar o registo de webhooks: {:?}", e);
+                }
+                ultima_limpeza = Some(std::time::Instant::now());
+            }
+            tokio::time::sleep(INTERVALO_ENVIO).await;
+        }
+    });
+    Ok(())
+}
+
+/// Entrega pendente, com os dados do webhook necessários ao envio.
+#[derive(sqlx::FromRow)]
+struct EntregaPendente {
+    id: i64,
+    evento: String,
+    payload: String,
+    tentativas: i64,
+    url: String,
+    segredo: String,
+}
+
+async fn enviar_pendentes(db_pool: &SqlitePool, http: &reqwest::Client) -> AppResult<()> {
+    let pendentes = sqlx::query_as::<_, EntregaPendente>(
+        r#"
+        SELECT e.id, e.evento, e.payload, e.tentativas, w.url, w.segredo
+        FROM webhook_entregas e
+        JOIN webhooks w ON w.id = e.webhook_id
+        WHERE e.estado = 'pendente' AND e.proxima_tentativa <= datetime('now') AND w.ativo = 1
+        ORDER BY e.id
+        LIMIT ?1
+        "#,
+    )
+    .bind(LOTE_ENVIO)
+    .fetch_all(db_pool)
+    .await?;
+
+    for entrega in pendentes {
+        let tentativas = entrega.tentativas + 1;
+        match enviar(http, &entrega).await {
+            Ok(status) => {
+                sqlx::query(
+                    r#"
+                    UPDATE webhook_entregas
+                    SET estado = ?1, tentativas = ?2, ultimo_status = ?3, ultimo_erro = NULL, entregue_em = datetime('now')
+                    WHERE id = ?4
+                    "#,
+                )
+                .bind(ESTADO_ENTREGUE)
+                .bind(tentativas)
+                .bind(status)
+                .bind(entrega.id)
+                .execute(db_pool)
+                .await?;
+                tracing::debug!("🪝 Entrega {} ('{}') enviada para {} ({}).", entrega.id, entrega.evento, entrega.url, status);
+            }
+            Err((status, erro)) => {
+                let erro: String = erro.chars().take(MAX_ERRO).collect();
+                let estado = if tentativas >= MAX_TENTATIVAS { ESTADO_FALHADA } else { ESTADO_PENDENTE };
+                let espera = ESPERA_BASE_SEGUNDOS << (tentativas - 1).min(10);
+                sqlx::query(
+                    r#"
+                    UPDATE webhook_entregas
+                    SET estado = ?1, tentativas = ?2, ultimo_status = ?3, ultimo_erro = ?4,
+                        proxima_tentativa = datetime('now', ?5)
+                    WHERE id = ?6
+                    "#,
+                )
+                .bind(estado)
+                .bind(tentativas)
+                .bind(status)
+                .bind(&erro)
+                .bind(format!("+{} seconds", espera))
+                .bind(entrega.id)
+                .execute(db_pool)
+                .await?;
+                if estado == ESTADO_FALHADA {
+                    tracing::error!("❌ Entrega {} ('{}') para {} falhou {} vezes; desistindo: {}", entrega.id, entrega.evento, entrega.url, tentativas, erro);
+                } else {
+                    tracing::warn!("Entrega {} ('{}') para {} falhou (tentativa {}), nova tentativa em {}s: {}", entrega.id, entrega.evento, entrega.url, tentativas, espera, erro);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Envia uma entrega. Ok com o código HTTP se o destino respondeu 2xx; caso contrário, o código (se houve resposta) e o erro.
+async fn enviar(http: &reqwest::Client, entrega: &EntregaPendente) -> Result<i64, (Option<i64>, String)> {
+    let mut mac = HmacSha256::new_from_slice(entrega.segredo.as_bytes())
+        .map_err(|e| (None, format!("segredo inválido: {}", e)))?;
+    mac.update(entrega.payload.as_bytes());
+    let assinatura = format!("sha256={:x}", mac.finalize().into_bytes());
+
+    let resposta = http
+        .post(&entrega.url)
+        .header("Content-Type", "application/json")
+        .header("User-Agent", "Mercal2-Webhooks")
+        .header("X-Mercal-Evento", entrega.evento.as_str())
+        .header("X-Mercal-Entrega", entrega.id.to_string())
+        .header("X-Mercal-Assinatura", assinatura)
+        .body(entrega.payload.clone())
+        .send()
+        .await
+        .map_err(|e| (None, e.to_string()))?;
+
+    let status = resposta.status();
+    if status.is_success() {
+        Ok(status.as_u16() as i64)
+    } else {
+        Err((Some(status.as_u16() as i64), format!("resposta HTTP {}", status)))
+    }
+}
+
+/// Apaga do registo as entregas concluídas há mais de `DIAS_REGISTO` dias.
+async fn limpar_registo(db_pool: &SqlitePool) -> AppResult<()> {
+    sqlx::query("DELETE FROM webhook_entregas WHERE estado != 'pendente' AND criado_em < datetime('now', ?1)")
+        .bind(format!("-{} days", DIAS_REGISTO))
+        .execute(db_pool)
+        .await?;
+    Ok(())
+}
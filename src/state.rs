@@ -1,9 +1,12 @@
 // src/state.rs
 use axum::extract::ws::{Message, WebSocket}; // Adicionar imports WebSocket
 use futures_util::stream::SplitSink; // Adicionar SplitSink
+use crate::config::Config;
+use crate::services::{captcha_service::CaptchaConfig, oidc_service::OidcConfig};
 use sqlx::SqlitePool;
 use std::{collections::HashMap, sync::Arc}; // Adicionar Arc, HashMap
-use tokio::sync::{mpsc, Mutex}; // Adicionar mpsc, Mutex
+use tokio::sync::{mpsc, Mutex, RwLock}; // Adicionar mpsc, Mutex
+use tower_sessions_sqlx_store::SqliteStore;
 use uuid::Uuid; // Adicionar Uuid
 
 // Tipo para o 'sender' de uma conexão WebSocket individual
@@ -13,18 +16,18 @@ type WsTx = mpsc::Sender<Message>;
 #[derive(Debug, Clone, Default)]
 pub struct PresenceWsState {
     // Usamos Arc<Mutex<...>> para permitir acesso seguro de múltiplos threads/tasks
-    // O HashMap guarda o ID da conexão (Uuid) e o canal (Sender) para enviar mensagens
-    pub connections: Arc<Mutex<HashMap<Uuid, WsTx>>>,
+    // O HashMap guarda o ID da conexão (Uuid), a organização do operador e o canal (Sender) para enviar mensagens
+    pub connections: Arc<Mutex<HashMap<Uuid, (i64, WsTx)>>>,
 }
 
 impl PresenceWsState {
-    /// Envia uma mensagem para TODAS as conexões ativas.
-    pub async fn broadcast(&self, message_text: String) {
+    /// Envia uma mensagem para TODAS as conexões ativas da organização.
+    pub async fn broadcast(&self, organizacao_id: i64, message_text: String) {
         let connections = self.connections.lock().await;
         let message = Message::Text(message_text.into()); // Cria a mensagem WebSocket
 
         // Itera sobre os senders no HashMap
-        for tx in connections.values() {
+        for (_, tx) in connections.values().filter(|(org, _)| *org == organizacao_id) {
             // Tenta enviar a mensagem. Se falhar (ex: cliente desconectado), ignora o erro.
             // Usar tx.send().await pode bloquear um pouco se o buffer estiver cheio.
             // Para alta performance, considerar tx.try_send() ou spawns.
@@ -33,13 +36,39 @@ impl PresenceWsState {
     }
 }
 
+/// Matriz de permissões: permissão -> roles que a concedem.
+pub type MatrizPermissoes = HashMap<String, Vec<String>>;
+
+/// Cache da matriz de permissões.
+/// `None` significa que tem de ser (re)carregada da base de dados; ver `permission_service`.
+#[derive(Debug, Clone, Default)]
+pub struct PermissionCache {
+    pub matriz: Arc<RwLock<Option<MatrizPermissoes>>>,
+}
 
 // Atualiza o AppState para incluir o estado do WebSocket
 #[derive(Clone)]
 pub struct AppState {
+    // Configuração da aplicação (config.toml + variáveis de ambiente), validada no arranque
+    pub config: Arc<Config>,
+    // Pool de escrita (uma só ligação; ver `db`): tudo o que escreve, e por omissão também as leituras
     pub db_pool: SqlitePool,
+    // Pool só de leitura, para as páginas mais consultadas (não esperam por escritas longas)
+    pub db_leitura: SqlitePool,
+    // Store das sessões (o mesmo da SessionManagerLayer; verificado por /readyz)
+    pub session_store: SqliteStore,
     // Adiciona o estado das conexões WebSocket de presença
     pub presence_state: PresenceWsState,
+    // Conexões WebSocket das páginas dos visitantes (mesmo mecanismo da presença)
+    pub visitantes_state: PresenceWsState,
+    // Matriz de permissões em memória (invalidada quando o admin a altera)
+    pub permissions: PermissionCache,
+    // Login institucional (None = desativado, só login local)
+    pub oidc: Option<Arc<OidcConfig>>,
+    // CAPTCHA no login após várias falhas (None = desativado)
+    pub captcha: Option<Arc<CaptchaConfig>>,
+    // Validade das senhas em dias (None = não expiram)
+    pub senha_max_dias: Option<i64>,
 }
 
 // Permite extrair o pool da DB diretamente
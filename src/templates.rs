@@ -1,45 +1,1357 @@
 // src/templates.rs
 use askama::Template;
 use crate::models::{
+    api_token::ApiToken, // Tokens de API do utilizador (UserTokensPage)
+    atributo::{AtributoCampo, AtributoDef}, // Campos extra dos utilizadores
+    audit::AuditEntry, // Necessário para AdminAuditPage
+    grupo::{Grupo, GrupoMembro, PostoGrupo}, // Páginas de grupos e seletor da presença
+    notificacao::Notificacao, // Notificações no painel (UserPage) e em UserNotificacoesPage
+    telegram::TelegramLigacao, // Ligação ao bot do Telegram (UserPage)
+    google_calendar::GoogleCalendarLigacao, // Ligação ao Google Calendar (UserPage)
+    login::{BloqueioAutomatico, BloqueioManual, LoginRegisto, SessaoAtiva}, // AdminLoginsPage / AdminBloqueiosPage; sessões ativas (UserPage / AdminSessoesPage)
     presence::{PresencePerson, PresenceStats}, // Necessário para PresencePage
-    user::User, // Necessário para AdminEditUserPage
+    user::{RolloverPreview, User}, // Necessário para AdminEditUserPage / AdminRolloverPage
+    foto::FotoUser, // Fotografia na AdminEditUserPage
+    webhook::{Webhook, WebhookEntrega}, // AdminWebhooksPage
+    backup::Backup, // AdminBackupsPage
+    tarefa::TarefaEstado, // AdminTarefasPage
+    manutencao::Manutencao, // SuperadminOrganizacoesPage
+    organizacao::Organizacao, // SuperadminOrganizacoesPage
+    retencao::{PoliticaRetencao, ResumoRetencao}, // AdminRetencaoPage
+    contabilidade::{LancamentoServico, Reconciliacao}, // AdminContabilidadePage
+    preferencias::Preferencias, // UserPreferenciasPage
+    arranchamento::{DiaArranchamento, Previsao, Refeicao}, // ArranchamentoPage / RanchoPage
+    cardapio::{CardapioSemana, SemanaPublicada}, // CardapioPage / UserPage
+    loja::{ExtratoConta, Produto, RelatorioVendas, SaldoConta, Venda}, // Páginas da loja
+    livro::{FechoLivro, Ocorrencia, ResumoDiaLivro}, // LivroPage / LivroPesquisaPage
+    escala::DiaEscala, // Dia da escala ligado ao livro (LivroPage) e no boletim (BoletimDiaPage)
+    revista::{EstatisticaTurma, ListaRevista, Reincidencia, RevistaResumo}, // Páginas das revistas
+    quarto::{PernoiteQuarto, Quarto}, // AdminQuartosPage / PernoitePage
+    cautela::{Cautela, Material, RegistoCautela, VerificacaoRegisto}, // Páginas das cautelas; cautelas abertas (UserPage)
+    claviculario::{Chave, MovimentoChave}, // Páginas do claviculário; chaves em posse (UserPage)
+    biblioteca::{Emprestimo, Livro}, // Páginas da biblioteca; os meus empréstimos (UserPage)
+    lavanderia::SacoLavanderia, // Páginas da lavanderia
+    enquete::{Enquete, EnquetePorVotar, OpcaoEnquete, VotoEnquete}, // Páginas das enquetes; enquetes por votar (UserPage)
+    baixa::Baixa, // BaixasPage
+    enfermaria::{Consulta, VagaConsulta}, // EnfermariaPage / EnfermariaAgendaPage
+    visitante::Visita, // VisitantesPage / VisitanteLinha
+    agenda::EventoAgenda, // AgendaPage; próximos eventos nos painéis e na presença
+    documento::{Documento, DownloadDocumento, PastaDocumentos}, // DocumentosPage / DocumentoDownloadsPage
+    disciplina::ProcessoDisciplinar, // Páginas da disciplina; processos abertos (UserPage)
+    antiguidade::PosicaoAntiguidade, // AdminAntiguidadePage
+    horario::{Aula, PostoDiurno}, // HorarioPage; aulas da turma (UserPage)
+    prova::Prova, // AdminProvasPage
+    uniforme::UniformeDia, // UniformePage; uniforme do dia (UserPage, escala e boletim)
+    portaria::MovimentoPortaria, // PortariaPage
+    sugestao::Sugestao, // SugestoesPage / SugestoesTriagemPage
+    faxina::{AreaFaxina, ContagemFaxina, SemanaFaxina}, // FaxinaPage / FaxinaGerirPage
+    conceito::{PeriodoConceito, PosicaoConceito, RegistoConceito}, // ConceitoPage / ConceitoClassificacaoPage
+    comitiva::{Comitiva, MembroComitiva}, // ComitivasPage / ComitivaPage
+    chamada::{ContactoEmergencia, ExercicioChamada, NoChamada, PlanoChamada, PosicaoChamada}, // Páginas do plano de chamada
+    tfm::{EstatisticaTfm, ResultadoTfm}, // TfmPage / TfmEstatisticasPage; histórico do TFM (UserPage)
 };
+use crate::services::captcha_service::CaptchaWidget; // Widget do CAPTCHA (LoginPage)
+use crate::validation::FormState; // Erros por campo nos formulários reapresentados
+use crate::i18n::Idioma; // Seletor de idioma (UserPage)
+use crate::web::paginacao::Navegacao; // Navegação das listagens paginadas (partial paginacao.html)
+use std::collections::{HashMap, HashSet}; // Posições da antiguidade e fotografias (AdminRosterPage)
+
+/// Filtros usados nos templates: `{{ "user.painel"|t }}` e `{{ "user.bem_vindo"|tf("nome", name) }}`
+/// (ver `crate::i18n`), e os das datas, no idioma do pedido. O texto é escapado como qualquer outro valor.
+pub mod filters {
+    use crate::tempo;
+    use std::fmt::Display;
+
+    pub fn t<T: Display>(chave: T, _: &dyn askama::Values) -> askama::Result<String> {
+        Ok(crate::i18n::t(&chave.to_string()))
+    }
+
+    pub fn tf<T: Display, V: Display>(chave: T, _: &dyn askama::Values, nome: &str, valor: V) -> askama::Result<String> {
+        Ok(crate::i18n::tf(&chave.to_string(), &[(nome, &valor.to_string())]))
+    }
+
+    // Datas de um dia ('YYYY-MM-DD'), ver `crate::tempo`: `{{ dia.data|data_longa }}` -> "Sábado, 25/10".
+    // Um valor que não seja uma data é mostrado tal como está.
+
+    fn dia(valor: impl Display, formatar: fn(chrono::NaiveDate) -> String) -> askama::Result<String> {
+        let texto = valor.to_string();
+        Ok(tempo::ler_data(&texto).map(formatar).unwrap_or(texto))
+    }
+
+    pub fn dia_semana<T: Display>(data: T, _: &dyn askama::Values) -> askama::Result<String> {
+        dia(data, tempo::dia_semana)
+    }
+
+    pub fn dia_mes<T: Display>(data: T, _: &dyn askama::Values) -> askama::Result<String> {
+        dia(data, |d| d.format("%d").to_string())
+    }
+
+    pub fn mes<T: Display>(data: T, _: &dyn askama::Values) -> askama::Result<String> {
+        dia(data, tempo::mes_abreviado)
+    }
+
+    pub fn data_curta<T: Display>(data: T, _: &dyn askama::Values) -> askama::Result<String> {
+        dia(data, tempo::data_curta)
+    }
+
+    pub fn data_longa<T: Display>(data: T, _: &dyn askama::Values) -> askama::Result<String> {
+        dia(data, tempo::data_longa)
+    }
+
+    pub fn relativa<T: Display>(data: T, _: &dyn askama::Values) -> askama::Result<String> {
+        dia(data, tempo::relativa)
+    }
+
+    /// Data/hora da base de dados (UTC) no fuso configurado, 'dd/mm/aaaa HH:MM' (ver `tempo::formatar`).
+    pub fn data_hora<T: Display>(valor: T, _: &dyn askama::Values) -> askama::Result<String> {
+        Ok(tempo::formatar(&valor.to_string(), tempo::FORMATO_DATA_HORA))
+    }
+
+    /// Centavos como "R$ 12,50" (ver `crate::moeda`).
+    pub fn moeda<T: std::borrow::Borrow<i64>>(centavos: T, _: &dyn askama::Values) -> askama::Result<String> {
+        Ok(crate::moeda::formatar(*centavos.borrow()))
+    }
+}
+
+// --- ERROS ---
+
+/// Página de erro (ver `AppError::into_response` e `web::mw_erros`).
+#[derive(Template)]
+#[template(path = "erro.html")]
+pub struct ErroPage {
+    pub status: u16,
+    pub mensagem: String,
+    pub utilizador: Option<String>, // "Nome (ID)" com sessão iniciada
+}
+
+impl ErroPage {
+    /// Chave das mensagens do código de estado (401/403/404/500/503 têm texto próprio).
+    fn chave(&self, nome: &str) -> String {
+        match self.status {
+            401 | 403 | 404 | 500 | 503 => format!("erro.{}_{}", nome, self.status),
+            _ => format!("erro.{}", nome),
+        }
+    }
+
+    pub fn titulo(&self) -> String {
+        crate::i18n::t(&self.chave("titulo"))
+    }
+
+    pub fn descricao(&self) -> String {
+        crate::i18n::t(&self.chave("descricao"))
+    }
+}
 
 // --- LOGIN ---
 
 #[derive(Template)]
-#[template(path = "login.html")]
-pub struct LoginPage {
-    pub error: Option<String>,
+#[template(path = "login.html")]
+pub struct LoginPage {
+    pub error: Option<String>,
+    pub oidc_ativo: bool, // Mostra o botão "Entrar com a conta institucional"
+    pub captcha: Option<CaptchaWidget>, // Widget CAPTCHA (só depois de várias tentativas falhadas)
+}
+
+// --- DASHBOARD (USER) ---
+
+#[derive(Debug, Clone)]
+pub struct MeuServico {
+    pub data: String, // 'YYYY-MM-DD' (formatada no template)
+    pub posto: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct NotificacaoTroca {
+    pub troca_id: String,
+    pub solicitante: String,
+    pub data: String, // 'YYYY-MM-DD'
+    pub posto: String,
+    pub motivo: String,
+}
+
+/// Um login recente, formatado para a página do utilizador.
+#[derive(Debug, Clone)]
+pub struct LoginExibicao {
+    pub quando: String, // UTC, como na base de dados (filtro `data_hora`)
+    pub ip: String,
+    pub dispositivo: String, // User-Agent (ou '-')
+    pub sucesso: bool,
+}
+
+#[derive(Template)]
+#[template(path = "user_page.html")]
+pub struct UserPage {
+    pub user_id: String,
+    pub name: String,
+    pub meus_servicos: Vec<MeuServico>,
+    pub trocas_pendentes: Vec<NotificacaoTroca>,
+    pub logins_recentes: Vec<LoginExibicao>,
+    pub sessoes: Vec<SessaoAtiva>,
+    pub notificacoes: Vec<Notificacao>,
+    pub telegram_ativo: bool,                 // Bot configurado (mostra o cartão do Telegram)
+    pub telegram_bot: Option<String>,         // Nome do bot, para o link t.me
+    pub telegram: Option<TelegramLigacao>,
+    pub google_ativo: bool,                   // Integração configurada (mostra o cartão do Google Calendar)
+    pub google: Option<GoogleCalendarLigacao>,
+    pub email_ativo: bool,                    // Envio de emails configurado (mostra o cartão do email)
+    pub email: Option<String>,                // Endereço registado
+    pub emails_avisos: bool,                  // Recebe avisos (escala, trocas) por email
+    pub idioma: Idioma,                       // Idioma das páginas (escolhido ou negociado)
+    pub cardapio: Option<CardapioSemana>,     // Cardápio da semana atual (None = nada publicado)
+    pub hoje: String,                         // 'YYYY-MM-DD', para destacar o dia no cardápio
+    pub cautelas_abertas: Vec<Cautela>,       // Material cautelado ao utilizador
+    pub chaves_em_posse: Vec<MovimentoChave>, // Chaves do claviculário levantadas pelo utilizador
+    pub tfm: Vec<ResultadoTfm>,               // Últimos resultados do TFM do utilizador
+    pub emprestimos: Vec<Emprestimo>,         // Livros da biblioteca emprestados ao utilizador
+    pub enquetes: Vec<EnquetePorVotar>,       // Enquetes abertas dirigidas ao utilizador, por votar
+    pub processos_abertos: Vec<ProcessoDisciplinar>, // Processos disciplinares por decidir
+    pub proximos_eventos: Vec<EventoAgenda>,  // Agenda institucional para o ano do utilizador
+    pub aulas: Vec<Aula>,                     // Horário semanal da turma do utilizador
+    pub dia_semana: i64,                      // Dia da semana de hoje (1 = segunda), destacado no horário
+    pub uniformes: Vec<UniformeDia>,          // Uniforme de hoje e de amanhã (os já definidos)
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl UserPage {
+    /// Dias da semana com aulas no horário da turma.
+    pub fn dias_com_aulas(&self) -> Vec<i64> {
+        let mut dias: Vec<i64> = self.aulas.iter().map(|a| a.dia_semana).collect();
+        dias.dedup();
+        dias
+    }
+
+    /// Aulas de um dia da semana.
+    pub fn aulas_do_dia(&self, dia: &i64) -> Vec<&Aula> {
+        self.aulas.iter().filter(|a| a.dia_semana == *dia).collect()
+    }
+
+    /// Nome de um dia da semana.
+    pub fn descrever_dia(&self, dia: &i64) -> String {
+        crate::services::horario_service::descrever_dia(*dia)
+    }
+
+    /// Descrição do exercício de um resultado do TFM.
+    pub fn descrever_exercicio(&self, exercicio: &str) -> String {
+        crate::services::tfm_service::descrever_exercicio(exercicio).to_string()
+    }
+
+    /// Há notificações por ler (mostra o botão "Marcar todas como lidas").
+    pub fn tem_por_ler(&self) -> bool {
+        self.notificacoes.iter().any(|n| !n.lida)
+    }
+
+    /// Opções do seletor de idioma.
+    pub fn idiomas(&self) -> [Idioma; 2] {
+        Idioma::TODOS
+    }
+}
+
+/// Todas as notificações do próprio utilizador (/user/notificacoes).
+#[derive(Template)]
+#[template(path = "user_notificacoes.html")]
+pub struct UserNotificacoesPage {
+    pub notificacoes: Vec<Notificacao>,
+    pub paginacao: Navegacao,
+    pub por_ler: i64,                         // Total por ler (mostra o botão "Marcar todas como lidas")
+    pub tipos: &'static [(&'static str, &'static str)],
+    pub filtro_tipo: String,
+    pub filtro_por_ler: bool,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "user_senha.html")]
+pub struct UserSenhaPage {
+    pub expirada: bool,         // Chegou aqui por a senha ter expirado
+    pub max_dias: Option<i64>,  // Validade configurada (para a explicação)
+    pub form: FormState,
+}
+
+#[derive(Template)]
+#[template(path = "user_tokens.html")]
+pub struct UserTokensPage {
+    pub tokens: Vec<ApiToken>,
+    pub max_tokens: i64,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Preferências da interface do próprio utilizador (/user/preferencias).
+#[derive(Template)]
+#[template(path = "user_preferencias.html")]
+pub struct UserPreferenciasPage {
+    pub preferencias: Preferencias,
+    pub telegram_ativo: bool,
+    pub email_ativo: bool,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl UserPreferenciasPage {
+    pub fn opcoes_por_pagina(&self) -> &'static [i64] {
+        &[25, 50, 100, 200]
+    }
+}
+
+// --- ARRANCHAMENTO ---
+
+/// Refeições dos próximos dias, para o utilizador marcar (/arranchamento).
+#[derive(Template)]
+#[template(path = "arranchamento.html")]
+pub struct ArranchamentoPage {
+    pub refeicoes: Vec<Refeicao>, // Colunas (os dias trazem as marcações pela mesma ordem)
+    pub dias: Vec<DiaArranchamento>,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Página do rancheiro: previsão do dia e refeições da organização (/rancho).
+#[derive(Template)]
+#[template(path = "rancho.html")]
+pub struct RanchoPage {
+    pub previsao: Previsao,
+    pub refeicoes: Vec<Refeicao>,
+    pub max_corte_dias: i64,
+    pub form: FormState, // Formulário de nova refeição
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Página do rancheiro para publicar o cardápio de uma semana, com o histórico (/rancho/cardapio).
+#[derive(Template)]
+#[template(path = "cardapio.html")]
+pub struct CardapioPage {
+    pub cardapio: CardapioSemana, // Todas as refeições ativas de cada dia (descrição vazia = sem prato)
+    pub semana_anterior: String,
+    pub semana_seguinte: String,
+    pub historico: Vec<SemanaPublicada>,
+    pub max_descricao: usize,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+// --- LOJA ---
+
+/// Ponto de venda do operador da loja (/loja).
+#[derive(Template)]
+#[template(path = "loja.html")]
+pub struct LojaPage {
+    pub produtos: Vec<Produto>, // Só os ativos
+    pub pagamentos: &'static [(&'static str, &'static str)],
+    pub vendas_hoje: Vec<Venda>,
+    pub total_hoje: i64, // Centavos, sem as anuladas
+    pub max_quantidade: i64,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Compras de um utilizador na loja: as do próprio (/user/compras) ou as de quem o operador procurar (/loja/compras).
+#[derive(Template)]
+#[template(path = "loja_compras.html")]
+pub struct LojaComprasPage {
+    pub user_id: String, // Vazio = ainda não foi procurado ninguém
+    pub name: String,
+    pub compras: Vec<Venda>,
+    pub total: i64, // Centavos, sem as anuladas
+    pub proprio: bool,
+    pub error_message: Option<String>,
+}
+
+/// Conta corrente de um utilizador na loja: saldo e extrato mensal, a do próprio (/user/conta) ou a de quem
+/// o operador procurar (/loja/conta, com o formulário de carregamento).
+#[derive(Template)]
+#[template(path = "loja_conta.html")]
+pub struct LojaContaPage {
+    pub user_id: String, // Vazio = ainda não foi procurado ninguém
+    pub name: String,
+    pub saldo: i64, // Centavos, saldo atual
+    pub extrato: Option<ExtratoConta>,
+    pub mes_anterior: String,          // 'YYYY-MM'
+    pub mes_seguinte: Option<String>, // None no mês atual
+    pub proprio: bool,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Produtos da loja na administração (/admin/loja).
+#[derive(Template)]
+#[template(path = "admin_loja.html")]
+pub struct AdminLojaPage {
+    pub produtos: Vec<Produto>,
+    pub form: FormState, // Formulário de novo produto
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Relatório diário de vendas da loja (/admin/loja/relatorio).
+#[derive(Template)]
+#[template(path = "admin_loja_relatorio.html")]
+pub struct AdminLojaRelatorioPage {
+    pub relatorio: RelatorioVendas,
+}
+
+/// Saldos negativos da conta corrente da loja (/admin/loja/saldos).
+#[derive(Template)]
+#[template(path = "admin_loja_saldos.html")]
+pub struct AdminLojaSaldosPage {
+    pub saldos: Vec<SaldoConta>,
+    pub total: i64, // Centavos (negativo)
+}
+
+// --- LIVRO DE OCORRÊNCIAS ---
+
+/// Livro de ocorrências de um dia (/livro?data=).
+#[derive(Template)]
+#[template(path = "livro.html")]
+pub struct LivroPage {
+    pub data: String, // 'YYYY-MM-DD'
+    pub anterior: String,
+    pub seguinte: Option<String>, // None no dia de hoje
+    pub ocorrencias: Vec<Ocorrencia>,
+    pub fecho: Option<FechoLivro>,          // Passagem de serviço do dia (None = aberto)
+    pub fecho_anterior: Option<FechoLivro>, // Passagem recebida do dia anterior
+    pub escala: Option<DiaEscala>,
+    pub ontem_aberto: Option<String>, // Livro de ontem por fechar (só no de hoje)
+    pub pode_escrever: bool,
+    pub user_id: String, // Só o autor corrige uma ocorrência
+    pub hora_atual: String,
+    pub dias_recentes: Vec<ResumoDiaLivro>,
+    pub form: FormState, // Nova ocorrência e fecho do dia
+    pub max_texto: usize,
+    pub max_passagem: usize,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Pesquisa no livro de ocorrências (/livro/pesquisa).
+#[derive(Template)]
+#[template(path = "livro_pesquisa.html")]
+pub struct LivroPesquisaPage {
+    pub de: String,
+    pub ate: String,
+    pub q: String,
+    pub resultados: Vec<Ocorrencia>,
+    pub limitado: bool, // Há mais resultados do que os mostrados
+}
+
+// --- REVISTAS ---
+
+/// Registo de revistas e revistas recentes (/revistas).
+#[derive(Template)]
+#[template(path = "revistas.html")]
+pub struct RevistasPage {
+    pub listas: Vec<ListaRevista>,     // Listas ativas (para escolher)
+    pub lista: Option<ListaRevista>,   // Lista do formulário (None = ainda não há listas)
+    pub recentes: Vec<RevistaResumo>,
+    pub hoje: String,
+    pub form: FormState,
+    pub max_quarto: usize,
+    pub max_observacoes: usize,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Listas de verificação das revistas (/revistas/listas).
+#[derive(Template)]
+#[template(path = "revistas_listas.html")]
+pub struct RevistaListasPage {
+    pub listas: Vec<ListaRevista>,
+    pub form: FormState, // Nova lista
+    pub max_pontos: i64,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Estatísticas das revistas por turma e reincidências (/revistas/estatisticas).
+#[derive(Template)]
+#[template(path = "revistas_estatisticas.html")]
+pub struct RevistaEstatisticasPage {
+    pub de: String,
+    pub ate: String,
+    pub turmas: Vec<EstatisticaTurma>, // Da melhor média para a pior
+    pub reincidencias: Vec<Reincidencia>,
+    pub janela_dias: i64,
+}
+
+// --- CAUTELAS DE MATERIAL ---
+
+/// Entrega de material e cautelas abertas (/cautelas).
+#[derive(Template)]
+#[template(path = "cautelas.html")]
+pub struct CautelasPage {
+    pub material: Vec<Material>,    // Material ativo (para escolher)
+    pub abertas: Vec<Cautela>,      // Pela data prevista de devolução
+    pub devolvidas: Vec<Cautela>,   // Só com um utilizador escolhido
+    pub user: String,               // Filtro (vazio = todos)
+    pub hoje: String,
+    pub prazo: String,              // Devolução prevista proposta
+    pub form: FormState,
+    pub max_quantidade: i64,
+    pub max_observacoes: usize,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Relatório das cautelas atrasadas (/cautelas/atrasadas).
+#[derive(Template)]
+#[template(path = "cautelas_atrasadas.html")]
+pub struct CautelasAtrasadasPage {
+    pub atrasadas: Vec<Cautela>, // As mais atrasadas primeiro
+    pub hoje: String,
+}
+
+/// Verificação da devolução de uma cautela (/cautelas/{id}/devolver).
+#[derive(Template)]
+#[template(path = "cautela_devolucao.html")]
+pub struct CautelaDevolucaoPage {
+    pub cautela: Cautela,
+    pub form: FormState, // Verificação (as senhas não voltam)
+    pub max_estado: usize,
+}
+
+/// Registo encadeado do material sensível (/cautelas/registo).
+#[derive(Template)]
+#[template(path = "cautelas_registo.html")]
+pub struct CautelasRegistoPage {
+    pub verificacao: VerificacaoRegisto, // Da cadeia inteira
+    pub registos: Vec<RegistoCautela>,   // As mais recentes primeiro
+    pub limite: i64,
+}
+
+/// Catálogo do material (/cautelas/material).
+#[derive(Template)]
+#[template(path = "cautelas_material.html")]
+pub struct CautelaMaterialPage {
+    pub material: Vec<Material>,
+    pub form: FormState, // Novo material
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+// --- CLAVICULÁRIO ---
+
+/// Quadro das chaves e levantamento (/claviculario).
+#[derive(Template)]
+#[template(path = "claviculario.html")]
+pub struct ClavicularioPage {
+    pub chaves: Vec<Chave>,             // Ativas, com quem as tem
+    pub agora: chrono::NaiveDateTime,   // No fuso da aplicação, para assinalar os atrasos
+    pub form: FormState,
+    pub max_observacoes: usize,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl ClavicularioPage {
+    /// A chave está fora depois da hora limite.
+    pub fn atrasada(&self, chave: &Chave) -> bool {
+        chave
+            .levantada_em
+            .as_deref()
+            .and_then(|levantada| crate::services::claviculario_service::prazo(levantada, &chave.hora_limite))
+            .is_some_and(|prazo| self.agora > prazo)
+    }
+}
+
+/// Chaves fora depois da hora limite (/claviculario/atrasadas).
+#[derive(Template)]
+#[template(path = "claviculario_atrasadas.html")]
+pub struct ClavicularioAtrasadasPage {
+    pub atrasadas: Vec<MovimentoChave>, // As levantadas há mais tempo primeiro
+    pub agora: String,                  // Momento do relatório (formatado)
+}
+
+impl ClavicularioAtrasadasPage {
+    /// Prazo de devolução (formatado).
+    pub fn prazo(&self, movimento: &MovimentoChave) -> String {
+        crate::services::claviculario_service::prazo(&movimento.levantada_em, &movimento.hora_limite)
+            .map_or_else(|| "—".to_string(), |prazo| prazo.format(crate::tempo::FORMATO_DATA_HORA).to_string())
+    }
+}
+
+/// Histórico dos levantamentos (/claviculario/historico).
+#[derive(Template)]
+#[template(path = "claviculario_historico.html")]
+pub struct ClavicularioHistoricoPage {
+    pub movimentos: Vec<MovimentoChave>, // Os mais recentes primeiro
+    pub chaves: Vec<Chave>,              // Para o filtro
+    pub chave: Option<i64>,              // Filtro (None = todas)
+    pub user: String,                    // Filtro (vazio = todos)
+    pub limite: i64,
+}
+
+/// Registo das chaves (/claviculario/chaves).
+#[derive(Template)]
+#[template(path = "claviculario_chaves.html")]
+pub struct ClavicularioChavesPage {
+    pub chaves: Vec<Chave>,
+    pub form: FormState, // Nova chave
+    pub hora_limite_padrao: &'static str,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+// --- BIBLIOTECA ---
+
+/// Empréstimo de livros e empréstimos por devolver (/biblioteca).
+#[derive(Template)]
+#[template(path = "biblioteca.html")]
+pub struct BibliotecaPage {
+    pub livros: Vec<Livro>,             // Ativos, com os exemplares emprestados
+    pub abertos: Vec<Emprestimo>,       // Por devolver, os de prazo mais curto primeiro
+    pub hoje: String,                   // 'YYYY-MM-DD', para assinalar os atrasos
+    pub devolver_ate: String,           // Prazo proposto no formulário
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl BibliotecaPage {
+    /// Empréstimos em atraso.
+    pub fn atrasados(&self) -> usize {
+        self.abertos.iter().filter(|e| e.atrasado(&self.hoje)).count()
+    }
+}
+
+/// Histórico dos empréstimos (/biblioteca/historico).
+#[derive(Template)]
+#[template(path = "biblioteca_historico.html")]
+pub struct BibliotecaHistoricoPage {
+    pub emprestimos: Vec<Emprestimo>, // Os mais recentes primeiro
+    pub livros: Vec<Livro>,           // Para o filtro
+    pub livro: Option<i64>,           // Filtro (None = todos)
+    pub user: String,                 // Filtro (vazio = todos)
+    pub hoje: String,
+    pub limite: i64,
+}
+
+/// Catálogo da biblioteca (/biblioteca/livros).
+#[derive(Template)]
+#[template(path = "biblioteca_livros.html")]
+pub struct BibliotecaLivrosPage {
+    pub livros: Vec<Livro>,
+    pub form: FormState, // Novo livro
+    pub max_exemplares: i64,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+// --- LAVANDERIA ---
+
+/// Entrega dos sacos e sacos por retirar (/lavanderia).
+#[derive(Template)]
+#[template(path = "lavanderia.html")]
+pub struct LavanderiaPage {
+    pub abertos: Vec<SacoLavanderia>, // Por retirar, os entregues há mais tempo primeiro
+    pub form: FormState,
+    pub max_observacoes: usize,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl LavanderiaPage {
+    /// Sacos prontos à espera de serem retirados.
+    pub fn prontos(&self) -> usize {
+        self.abertos.iter().filter(|s| s.pronto()).count()
+    }
+}
+
+/// Sacos prontos há N dias ou mais sem serem retirados (/lavanderia/relatorio).
+#[derive(Template)]
+#[template(path = "lavanderia_relatorio.html")]
+pub struct LavanderiaRelatorioPage {
+    pub sacos: Vec<SacoLavanderia>, // Os prontos há mais tempo primeiro
+    pub dias: i64,
+    pub max_dias: i64,
+    pub agora: String,              // Momento do relatório (formatado)
+}
+
+/// Histórico dos sacos (/lavanderia/historico).
+#[derive(Template)]
+#[template(path = "lavanderia_historico.html")]
+pub struct LavanderiaHistoricoPage {
+    pub sacos: Vec<SacoLavanderia>, // Os mais recentes primeiro
+    pub user: String,               // Filtro (vazio = todos)
+    pub limite: i64,
+}
+
+// --- ENQUETES ---
+
+/// Enquetes da organização e criação (/enquetes).
+#[derive(Template)]
+#[template(path = "enquetes.html")]
+pub struct EnquetesPage {
+    pub enquetes: Vec<Enquete>, // As mais recentes primeiro
+    pub roles: Vec<String>,     // Roles existentes (a quem se dirige)
+    pub max_ano: i64,
+    pub hoje: String,           // 'YYYY-MM-DD', para distinguir as abertas
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl EnquetesPage {
+    /// Role marcada no formulário.
+    pub fn role_marcada(&self, role: &str) -> bool {
+        self.form.valor("roles").split(',').any(|r| r.trim() == role)
+    }
+
+    /// Ano marcado no formulário.
+    pub fn ano_marcado(&self, ano: &i64) -> bool {
+        self.form.valor("anos").split(',').any(|a| a.trim() == ano.to_string())
+    }
+}
+
+/// Resultados apurados de uma enquete (/enquetes/{id}).
+#[derive(Template)]
+#[template(path = "enquete.html")]
+pub struct EnquetePage {
+    pub enquete: Enquete,
+    pub opcoes: Vec<OpcaoEnquete>, // Com os votos apurados
+    pub votos: Vec<VotoEnquete>,   // Nominais (vazio nas anónimas)
+    pub hoje: String,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+// --- ENFERMARIA ---
+
+/// Consultas do próprio e vagas livres para marcar (/enfermaria).
+#[derive(Template)]
+#[template(path = "enfermaria.html")]
+pub struct EnfermariaPage {
+    pub consultas: Vec<Consulta>,  // As do utilizador, as mais recentes primeiro
+    pub vagas: Vec<VagaConsulta>,  // Livres, por dia e hora
+    pub pode_gerir: bool,          // Permissão "enfermaria": link para a agenda
+    pub dias_marcacao: i64,
+    pub max_motivo: usize,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl EnfermariaPage {
+    /// Consulta por decidir do utilizador (só pode ter uma).
+    pub fn pendente(&self) -> Option<&Consulta> {
+        self.consultas.iter().find(|c| c.pendente())
+    }
+
+    /// Dias com vagas livres, pela ordem.
+    pub fn dias(&self) -> Vec<String> {
+        let mut dias: Vec<String> = self.vagas.iter().map(|v| v.data.clone()).collect();
+        dias.dedup();
+        dias
+    }
+
+    /// Vagas livres de um dia.
+    pub fn vagas_do_dia(&self, dia: &str) -> Vec<&VagaConsulta> {
+        self.vagas.iter().filter(|v| v.data == dia).collect()
+    }
+
+    /// Descrição do estado de uma consulta.
+    pub fn descrever_estado(&self, estado: &str) -> String {
+        crate::services::enfermaria_service::descrever_estado(estado).to_string()
+    }
+}
+
+/// Agenda de um dia da enfermaria e abertura de vagas (/enfermaria/agenda).
+#[derive(Template)]
+#[template(path = "enfermaria_agenda.html")]
+pub struct EnfermariaAgendaPage {
+    pub data: String,               // Dia da agenda ('YYYY-MM-DD')
+    pub anterior: String,
+    pub seguinte: String,
+    pub hoje: String,
+    pub vagas: Vec<VagaConsulta>,   // Do dia, por hora
+    pub restricoes: &'static [(&'static str, &'static str)], // Da dispensa (ver baixa_service)
+    pub pode_dispensar: bool,       // Permissão "baixas": regista a dispensa ao terminar a consulta
+    pub duracao_minima: i64,
+    pub duracao_maxima: i64,
+    pub form: FormState,            // Abertura de vagas
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl EnfermariaAgendaPage {
+    /// Descrição do estado de uma consulta.
+    pub fn descrever_estado(&self, estado: &str) -> String {
+        crate::services::enfermaria_service::descrever_estado(estado).to_string()
+    }
+
+    /// Consultas marcadas no dia (as vagas livres não contam).
+    pub fn marcadas(&self) -> usize {
+        self.vagas.iter().filter(|v| !v.livre()).count()
+    }
+}
+
+// --- BAIXAS MÉDICAS ---
+
+/// Registo das baixas médicas (/baixas).
+#[derive(Template)]
+#[template(path = "baixas.html")]
+pub struct BaixasPage {
+    pub vigentes: Vec<Baixa>,   // Em vigor e futuras, pela data de início
+    pub terminadas: Vec<Baixa>, // As mais recentes primeiro
+    pub user: String,           // Filtro (vazio = todos)
+    pub hoje: String,
+    pub restricoes: &'static [(&'static str, &'static str)],
+    pub form: FormState,
+    pub max_observacoes: usize,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl BaixasPage {
+    /// Restrição marcada no formulário reapresentado.
+    pub fn restricao_marcada(&self, codigo: &str) -> bool {
+        self.form.valor("restricoes").split(',').any(|c| c == codigo)
+    }
+
+    /// Descrição das restrições de uma baixa.
+    pub fn descrever(&self, baixa: &Baixa) -> String {
+        crate::services::baixa_service::descrever(baixa)
+    }
+}
+
+// --- VISITANTES ---
+
+/// Visitantes na porta de armas (/presence/visitantes).
+#[derive(Template)]
+#[template(path = "visitantes.html")]
+pub struct VisitantesPage {
+    pub presentes: Vec<Visita>, // Dentro agora, pela hora de entrada
+    pub visitas: Vec<Visita>,   // Entradas no dia escolhido
+    pub data: String,           // Dia das visitas ('YYYY-MM-DD')
+    pub hoje: String,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Controlo de portaria: viaturas e entregas (/portaria).
+#[derive(Template)]
+#[template(path = "portaria.html")]
+pub struct PortariaPage {
+    pub dentro: Vec<MovimentoPortaria>,     // Dentro agora, pela hora de entrada
+    pub movimentos: Vec<MovimentoPortaria>, // Entradas no dia escolhido
+    pub tipos: &'static [(&'static str, &'static str)],
+    pub data: String,                       // Dia dos movimentos ('YYYY-MM-DD')
+    pub hoje: String,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl PortariaPage {
+    /// Descrição do tipo de entrada.
+    pub fn descrever_tipo(&self, tipo: &str) -> String {
+        crate::services::portaria_service::descrever_tipo(tipo).to_string()
+    }
+}
+
+/// Linha de um visitante dentro, enviada pelo WebSocket quando entra.
+#[derive(Template)]
+#[template(path = "visitante_linha.html")]
+pub struct VisitanteLinha<'a> {
+    pub v: &'a Visita,
+}
+
+// --- AGENDA INSTITUCIONAL ---
+
+/// Formaturas, cerimónias e palestras (/agenda).
+#[derive(Template)]
+#[template(path = "agenda.html")]
+pub struct AgendaPage {
+    pub eventos: Vec<EventoAgenda>, // Do período mostrado, por dia e hora
+    pub desde: String,
+    pub hoje: String,
+    pub ano: Option<i64>,     // Ano do utilizador (destaca os eventos que lhe dizem respeito)
+    pub pode_gerir: bool,     // Permissão "agenda": formulário e ações
+    pub editar: Option<i64>,  // O formulário altera este evento (None = novo)
+    pub tipos: &'static [(&'static str, &'static str)],
+    pub max_ano: i64,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl AgendaPage {
+    /// Ano marcado no formulário.
+    pub fn ano_marcado(&self, ano: &i64) -> bool {
+        self.form.valor("anos").split(',').any(|a| a.trim() == ano.to_string())
+    }
+
+    /// O evento destina-se ao ano do utilizador.
+    pub fn para_mim(&self, evento: &EventoAgenda) -> bool {
+        self.ano.is_some_and(|ano| evento.para_ano(ano))
+    }
+
+    /// Descrição do tipo de evento.
+    pub fn descrever_tipo(&self, tipo: &str) -> String {
+        crate::services::agenda_service::descrever_tipo(tipo).to_string()
+    }
+}
+
+/// Calendário das provas (/admin/provas).
+#[derive(Template)]
+#[template(path = "admin_provas.html")]
+pub struct AdminProvasPage {
+    pub proximas: Vec<Prova>,   // A partir de hoje, por data
+    pub realizadas: Vec<Prova>, // As mais recentes primeiro
+    pub turmas: Vec<String>,    // Turmas com utilizadores ativos (formulário)
+    pub hoje: String,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Horário semanal das aulas de uma turma (/horario).
+#[derive(Template)]
+#[template(path = "horario.html")]
+pub struct HorarioPage {
+    pub turmas: Vec<String>,      // Turmas com utilizadores ativos (seletor)
+    pub turma: String,            // Turma mostrada
+    pub e_a_minha: bool,          // A turma mostrada é a do utilizador
+    pub aulas: Vec<Aula>,         // Da turma, por dia da semana e hora
+    pub postos: Vec<PostoDiurno>, // Postos da escala (só a quem gere o horário)
+    pub hoje: i64,                // Dia da semana de hoje (1 = segunda)
+    pub pode_gerir: bool,         // Permissão "horario": formulário, ações e postos diurnos
+    pub editar: Option<i64>,      // O formulário altera esta aula (None = nova)
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl HorarioPage {
+    /// Todos os dias da semana (seletor do formulário).
+    pub fn dias(&self) -> [i64; 7] {
+        crate::services::horario_service::DIAS
+    }
+
+    /// Dias da semana com aulas.
+    pub fn dias_com_aulas(&self) -> Vec<i64> {
+        let mut dias: Vec<i64> = self.aulas.iter().map(|a| a.dia_semana).collect();
+        dias.dedup();
+        dias
+    }
+
+    /// Aulas de um dia da semana.
+    pub fn aulas_do_dia(&self, dia: &i64) -> Vec<&Aula> {
+        self.aulas.iter().filter(|a| a.dia_semana == *dia).collect()
+    }
+
+    /// Nome de um dia da semana.
+    pub fn descrever_dia(&self, dia: &i64) -> String {
+        crate::services::horario_service::descrever_dia(*dia)
+    }
+
+    /// Turma escolhida no formulário (por omissão, a mostrada).
+    pub fn turma_marcada(&self, turma: &str) -> bool {
+        match self.form.valor("turma") {
+            "" => self.turma == turma,
+            valor => valor == turma,
+        }
+    }
+}
+
+/// Uniforme do dia (/uniforme).
+#[derive(Template)]
+#[template(path = "uniforme.html")]
+pub struct UniformePage {
+    pub uniformes: Vec<UniformeDia>, // A partir de hoje, por data
+    pub sugestoes: Vec<String>,      // Uniformes já usados (datalist do formulário)
+    pub hoje: String,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Caixa de sugestões (/sugestoes).
+#[derive(Template)]
+#[template(path = "sugestoes.html")]
+pub struct SugestoesPage {
+    pub minhas: Vec<Sugestao>,     // Enviadas pelo utilizador (também as anónimas)
+    pub publicadas: Vec<Sugestao>, // Com a resposta publicada
+    pub pode_triar: bool,          // Permissão "sugestoes": link para a triagem
+    pub form: FormState,
+    pub max_assunto: usize,
+    pub max_texto: usize,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl SugestoesPage {
+    /// Descrição do estado.
+    pub fn descrever_estado(&self, estado: &str) -> String {
+        crate::services::sugestao_service::descrever_estado(estado).to_string()
+    }
+}
+
+/// Triagem das sugestões (/sugestoes/triagem).
+#[derive(Template)]
+#[template(path = "sugestoes_triagem.html")]
+pub struct SugestoesTriagemPage {
+    pub sugestoes: Vec<Sugestao>, // As mais recentes primeiro
+    pub estados: &'static [(&'static str, &'static str)],
+    pub estado: String, // Filtro (vazio = todos)
+    pub limite: i64,
+    pub max_texto: usize,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl SugestoesTriagemPage {
+    /// Descrição do estado.
+    pub fn descrever_estado(&self, estado: &str) -> String {
+        crate::services::sugestao_service::descrever_estado(estado).to_string()
+    }
+}
+
+/// Escala de faxina (/faxina).
+#[derive(Template)]
+#[template(path = "faxina.html")]
+pub struct FaxinaPage {
+    pub semanas: Vec<SemanaFaxina>, // A partir desta semana (os rascunhos só para quem gere)
+    pub pode_gerir: bool,           // Permissão "escala.gerir": publicar, errata e link para gerir
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Gestão da faxina (/faxina/gerir): áreas, geração e contadores.
+#[derive(Template)]
+#[template(path = "faxina_gerir.html")]
+pub struct FaxinaGerirPage {
+    pub areas: Vec<AreaFaxina>,
+    pub contagem: Vec<ContagemFaxina>, // Faxinas publicadas por quarto/turma
+    pub rotacoes: &'static [(&'static str, &'static str)],
+    pub generos: &'static [(&'static str, &'static str)],
+    pub max_semanas: i64,
+    pub semana_atual: String, // Mínimo do início da geração
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Conceito de um utilizador num semestre (/conceito).
+#[derive(Template)]
+#[template(path = "conceito.html")]
+pub struct ConceitoPage {
+    pub user_id: String,
+    pub nome: String,
+    pub e_o_proprio: bool,
+    pub registos: Vec<RegistoConceito>, // Por data, com o saldo acumulado
+    pub saldo: i64,                     // Conceito do semestre
+    pub periodo: PeriodoConceito,
+    pub periodos: Vec<PeriodoConceito>, // Seletor (o atual e os anteriores)
+    pub pode_gerir: bool,               // Permissão "conceito": formulário, apagar e classificação
+    pub origens: &'static [&'static str], // Registadas à mão
+    pub pontos_por_servico: i64,
+    pub max_pontos: i64,
+    pub max_motivo: usize,
+    pub hoje: String,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl ConceitoPage {
+    /// Descrição da origem de um lançamento.
+    pub fn descrever_origem(&self, origem: &str) -> String {
+        crate::services::conceito_service::descrever_origem(origem).to_string()
+    }
+}
+
+/// Classificação do conceito por turma (/conceito/classificacao), para impressão.
+#[derive(Template)]
+#[template(path = "conceito_classificacao.html")]
+pub struct ConceitoClassificacaoPage {
+    pub posicoes: Vec<PosicaoConceito>, // Por turma e conceito
+    pub turmas: Vec<String>,            // Filtro
+    pub turma: String,                  // Vazio = todas
+    pub periodo: PeriodoConceito,
+    pub periodos: Vec<PeriodoConceito>,
+    pub gerado_em: String,
+}
+
+impl ConceitoClassificacaoPage {
+    /// Turmas presentes na classificação, pela ordem.
+    pub fn turmas_classificadas(&self) -> Vec<String> {
+        let mut turmas: Vec<String> = self.posicoes.iter().map(|p| p.turma.clone()).collect();
+        turmas.dedup();
+        turmas
+    }
+
+    /// Posições de uma turma.
+    pub fn da_turma(&self, turma: &str) -> Vec<&PosicaoConceito> {
+        self.posicoes.iter().filter(|p| p.turma == turma).collect()
+    }
+
+    /// Conceito médio de uma turma (uma casa decimal).
+    pub fn media(&self, turma: &str) -> String {
+        let posicoes = self.da_turma(turma);
+        if posicoes.is_empty() {
+            return "0".to_string();
+        }
+        let soma: i64 = posicoes.iter().map(|p| p.saldo).sum();
+        format!("{:.1}", soma as f64 / posicoes.len() as f64)
+    }
+}
+
+// --- TFM ---
+
+/// Resultados do TFM e registo (/tfm).
+#[derive(Template)]
+#[template(path = "tfm.html")]
+pub struct TfmPage {
+    pub resultados: Vec<ResultadoTfm>, // Os mais recentes primeiro
+    pub turmas: Vec<String>,           // Filtro
+    pub user: String,                  // Filtro (vazio = todos)
+    pub turma: String,                 // Filtro (vazio = todas)
+    pub limite: i64,
+    pub exercicios: &'static [(&'static str, &'static str)],
+    pub max_pontuacao: i64,
+    pub max_marca: usize,
+    pub max_observacoes: usize,
+    pub hoje: String,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl TfmPage {
+    /// Descrição do exercício de um resultado.
+    pub fn descrever_exercicio(&self, exercicio: &str) -> String {
+        crate::services::tfm_service::descrever_exercicio(exercicio).to_string()
+    }
+}
+
+/// Estatísticas do TFM por turma e exercício num ano (/tfm/estatisticas), para impressão.
+#[derive(Template)]
+#[template(path = "tfm_estatisticas.html")]
+pub struct TfmEstatisticasPage {
+    pub estatisticas: Vec<EstatisticaTfm>, // Por turma e exercício
+    pub turmas: Vec<String>,               // Filtro
+    pub turma: String,                     // Vazio = todas
+    pub ano: i32,
+    pub anos: Vec<i32>,                    // Seletor (com resultados, e o atual)
+    pub exercicios: &'static [(&'static str, &'static str)],
+    pub gerado_em: String,
+}
+
+impl TfmEstatisticasPage {
+    /// Turmas com resultados, pela ordem.
+    pub fn turmas_avaliadas(&self) -> Vec<String> {
+        let mut turmas: Vec<String> = self.estatisticas.iter().map(|e| e.turma.clone()).collect();
+        turmas.dedup();
+        turmas
+    }
+
+    /// Estatística de uma turma num exercício (None = ninguém avaliado).
+    pub fn de(&self, turma: &str, exercicio: &str) -> Option<&EstatisticaTfm> {
+        self.estatisticas.iter().find(|e| e.turma == turma && e.exercicio == exercicio)
+    }
+}
+
+/// Comitivas abertas e encerradas, e o formulário de criação (/comitivas).
+#[derive(Template)]
+#[template(path = "comitivas.html")]
+pub struct ComitivasPage {
+    pub abertas: Vec<Comitiva>,
+    pub encerradas: Vec<Comitiva>, // As mais recentes
+    pub tipos: &'static [(&'static str, &'static str)],
+    pub hoje: String,
+    pub max_nome: usize,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
 }
 
-// --- DASHBOARD (USER) ---
+impl ComitivasPage {
+    /// Descrição do tipo de uma comitiva.
+    pub fn descrever_tipo(&self, tipo: &str) -> String {
+        crate::services::comitiva_service::descrever_tipo(tipo).to_string()
+    }
+}
 
-#[derive(Debug, Clone)]
-pub struct MeuServico {
-    pub data: String,
-    pub dia_semana: String,
-    pub dia_mes: String,
-    pub mes_extenso: String,
-    pub posto: String,
+/// Uma comitiva: os membros, acrescentar/retirar e encerrar (/comitivas/{id}).
+#[derive(Template)]
+#[template(path = "comitiva.html")]
+pub struct ComitivaPage {
+    pub comitiva: Comitiva,
+    pub membros: Vec<MembroComitiva>, // Por turma e nome
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-pub struct NotificacaoTroca {
-    pub troca_id: String,
-    pub solicitante: String,
-    pub data: String,
-    pub posto: String,
-    pub motivo: String,
+impl ComitivaPage {
+    /// Descrição do tipo da comitiva.
+    pub fn descrever_tipo(&self) -> String {
+        crate::services::comitiva_service::descrever_tipo(&self.comitiva.tipo).to_string()
+    }
 }
 
+/// Plano de chamada do utilizador (/chamada): o contacto de emergência, o seu lugar em cada cascata e,
+/// para quem gere, os planos e a criação de um novo.
 #[derive(Template)]
-#[template(path = "user_page.html")]
-pub struct UserPage {
-    pub user_id: String,
-    pub name: String,
-    pub meus_servicos: Vec<MeuServico>,
-    pub trocas_pendentes: Vec<NotificacaoTroca>,
+#[template(path = "chamada.html")]
+pub struct ChamadaPage {
+    pub contacto: Option<ContactoEmergencia>,
+    pub posicoes: Vec<PosicaoChamada>,
+    pub pode_gerir: bool, // Permissão "chamada"
+    pub planos: Vec<PlanoChamada>,
+    pub turmas: Vec<String>,
+    pub grupos: Vec<Grupo>,
+    pub max_ramificacao: i64,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Um plano de chamada (/chamada/planos/{id}): a cascata (com o chamador de cada membro editável) e os
+/// exercícios.
+#[derive(Template)]
+#[template(path = "chamada_plano.html")]
+pub struct ChamadaPlanoPage {
+    pub plano: PlanoChamada,
+    pub nos: Vec<NoChamada>, // Por profundidade
+    pub exercicios: Vec<ExercicioChamada>,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl ChamadaPlanoPage {
+    /// Tempo da cascata num exercício: até à última confirmação.
+    pub fn duracao(&self, exercicio: &ExercicioChamada) -> String {
+        exercicio
+            .ultima_confirmacao
+            .as_deref()
+            .and_then(|ultima| crate::services::chamada_service::minutos_desde(&exercicio.iniciado_em, ultima))
+            .map_or_else(|| "—".to_string(), |minutos| format!("{} min", minutos))
+    }
+}
+
+/// Plano de chamada para impressão (/chamada/planos/{id}/imprimir).
+#[derive(Template)]
+#[template(path = "chamada_imprimir.html")]
+pub struct ChamadaImprimirPage {
+    pub plano: PlanoChamada,
+    pub nos: Vec<NoChamada>, // Por profundidade
+    pub gerado_em: String,
+}
+
+/// Exercício de chamada (/chamada/exercicios/{id}): quem já confirmou e quando.
+#[derive(Template)]
+#[template(path = "chamada_exercicio.html")]
+pub struct ChamadaExercicioPage {
+    pub exercicio: ExercicioChamada,
+    pub nos: Vec<NoChamada>, // Por profundidade, com as confirmações
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl ChamadaExercicioPage {
+    /// Minutos desde o início do exercício até à confirmação do membro.
+    pub fn minutos(&self, no: &NoChamada) -> String {
+        no.confirmado_em
+            .as_deref()
+            .and_then(|quando| crate::services::chamada_service::minutos_desde(&self.exercicio.iniciado_em, quando))
+            .map_or_else(String::new, |minutos| format!("+{} min", minutos))
+    }
+}
+
+/// Repositório de documentos (/documentos).
+#[derive(Template)]
+#[template(path = "documentos.html")]
+pub struct DocumentosPage {
+    pub pastas: Vec<PastaDocumentos>,   // As que o utilizador pode ver (todas, para quem gere)
+    pub pasta: Option<PastaDocumentos>, // Pasta aberta
+    pub documentos: Vec<Documento>,     // Da pasta aberta
+    pub pode_gerir: bool,               // Permissão "documentos": formulários, envio e ações
+    pub editar: Option<i64>,            // O formulário altera esta pasta (None = nova)
+    pub categorias: &'static [(&'static str, &'static str)],
+    pub roles: Vec<String>,             // Roles existentes (regras de visibilidade)
+    pub max_ano: i64,
+    pub max_upload_mb: usize,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl DocumentosPage {
+    /// Pastas de uma categoria.
+    pub fn pastas_da(&self, categoria: &str) -> Vec<&PastaDocumentos> {
+        self.pastas.iter().filter(|p| p.categoria == categoria).collect()
+    }
+
+    /// Pasta aberta?
+    pub fn aberta(&self, pasta: &PastaDocumentos) -> bool {
+        self.pasta.as_ref().is_some_and(|p| p.id == pasta.id)
+    }
+
+    /// Role marcada no formulário.
+    pub fn role_marcada(&self, role: &str) -> bool {
+        self.form.valor("roles").split(',').any(|r| r.trim() == role)
+    }
+
+    /// Ano marcado no formulário.
+    pub fn ano_marcado(&self, ano: &i64) -> bool {
+        self.form.valor("anos").split(',').any(|a| a.trim() == ano.to_string())
+    }
+
+    /// Descrição da categoria.
+    pub fn descrever_categoria(&self, categoria: &str) -> String {
+        crate::services::documento_service::descrever_categoria(categoria).to_string()
+    }
+}
+
+/// Quem descarregou um documento (/documentos/{id}/downloads).
+#[derive(Template)]
+#[template(path = "documento_downloads.html")]
+pub struct DocumentoDownloadsPage {
+    pub documento: Documento,
+    pub pasta: PastaDocumentos,
+    pub downloads: Vec<DownloadDocumento>,
+    pub paginacao: Navegacao,
+}
+
+// --- DISCIPLINA ---
+
+/// Processos disciplinares (/disciplina): os abertos, os decididos recentemente e o registo de novos.
+#[derive(Template)]
+#[template(path = "disciplina.html")]
+pub struct DisciplinaPage {
+    pub abertos: Vec<ProcessoDisciplinar>,   // Pela ordem de registo
+    pub decididos: Vec<ProcessoDisciplinar>, // Os mais recentes primeiro
+    pub estado: String,                      // Filtro (vazio = todos)
+    pub user: String,                        // Filtro (vazio = todos)
+    pub estados: &'static [(&'static str, &'static str)],
+    pub hoje: String,
+    pub prazo_defesa_dias: i64,
+    pub form: FormState,
+    pub max_descricao: usize,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Um processo disciplinar (/disciplina/{id}): para o visado (defesa) e para quem decide.
+#[derive(Template)]
+#[template(path = "processo_disciplinar.html")]
+pub struct ProcessoDisciplinarPage {
+    pub processo: ProcessoDisciplinar,
+    pub lancamentos: Vec<LancamentoServico>, // Punição creditada no livro de serviços
+    pub pode_decidir: bool,
+    pub e_o_visado: bool,
+    pub hoje: String,
+    pub max_servicos: i64,
+    pub max_texto: usize,
+    pub form: FormState,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
 }
 
 // --- ESCALAS ---
@@ -57,11 +1369,11 @@ pub struct AlocacaoExibicao {
 
 #[derive(Debug, Clone)]
 pub struct EscalaDiaView {
-    pub data: String,
-    pub data_formatada: String,
+    pub data: String, // 'YYYY-MM-DD' (formatada no template)
     pub tipo: String,
     pub status: String,
     pub alocacoes: Vec<AlocacaoExibicao>,
+    pub uniforme: Option<UniformeDia>, // Uniforme do dia (None = não definido)
 }
 
 #[derive(Template)]
@@ -70,17 +1382,20 @@ pub struct EscalaTemplate {
     pub dias_publicados: Vec<EscalaDiaView>,
     pub dias_rascunho: Vec<EscalaDiaView>,
     pub is_admin: bool,
+    pub pode_ver_livro: bool,
     pub user_atual_id: String,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, sqlx::FromRow)]
 pub struct UserPunido {
     pub id: String,
     pub name: String,
     pub saldo: i64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, sqlx::FromRow)]
 pub struct TrocaPendenteAdmin {
     pub id: String,
     pub solicitante: String,
@@ -96,8 +1411,73 @@ pub struct TrocaPendenteAdmin {
 #[template(path = "presence.html")]
 pub struct PresencePage<'a> {
     pub turma_selecionada: i64,
+    pub grupo_selecionado: Option<i64>, // Some = vista por grupo em vez de turma
+    pub grupos: Vec<Grupo>,
+    pub por_quarto: bool, // Lista ordenada e agrupada por corredor/quarto
     pub pessoas: &'a [PresencePerson],
     pub stats: &'a PresenceStats,
+    pub eventos_turma: Vec<EventoAgenda>, // Eventos de hoje que esperam a turma inteira a bordo
+}
+
+impl<'a> PresencePage<'a> {
+    pub fn grupo_ativo(&self, grupo_id: &i64) -> bool {
+        self.grupo_selecionado == Some(*grupo_id)
+    }
+
+    /// Quem está fora (para os eventos que esperam a turma a bordo); os de baixa e os das comitivas
+    /// vêm indicados.
+    pub fn fora_do_evento(&self) -> Vec<String> {
+        self.pessoas
+            .iter()
+            .filter(|p| p.esta_fora)
+            .map(|p| match (&p.baixa, &p.comitiva) {
+                (Some(_), _) => format!("{} (baixa)", p.nome),
+                (None, Some(comitiva)) => format!("{} (comitiva {})", p.nome, comitiva),
+                (None, None) => p.nome.clone(),
+            })
+            .collect()
+    }
+
+    /// Parâmetro que mantém o agrupamento por quarto nos links da turma e do grupo.
+    pub fn sufixo_agrupar(&self) -> &'static str {
+        if self.por_quarto { "&agrupar=quarto" } else { "" }
+    }
+
+    /// Título do grupo (corredor e quarto) se a linha `i` começa um novo quarto.
+    pub fn cabecalho_quarto(&self, i: &usize) -> Option<String> {
+        let i = *i;
+        let p = self.pessoas.get(i)?;
+        let anterior = i.checked_sub(1).and_then(|j| self.pessoas.get(j));
+        if anterior.is_some_and(|a| a.quarto == p.quarto && a.corredor == p.corredor) {
+            return None;
+        }
+        Some(match (&p.quarto, p.corredor.as_deref()) {
+            (Some(quarto), Some(corredor)) if !corredor.is_empty() => format!("{} · Quarto {}", corredor, quarto),
+            (Some(quarto), _) => format!("Quarto {}", quarto),
+            (None, _) => "Sem quarto".to_string(),
+        })
+    }
+}
+
+/// Alojamento: quartos, ocupantes e atribuição (/admin/quartos).
+#[derive(Template)]
+#[template(path = "admin_quartos.html")]
+pub struct AdminQuartosPage {
+    pub quartos: Vec<Quarto>, // Por corredor e nome
+    pub sem_quarto: Vec<(String, String, String)>, // Utilizadores ativos sem quarto (ID, nome, turma)
+    pub form: FormState, // Novo quarto
+    pub max_capacidade: i64,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Relatório de pernoite (/presence/pernoite).
+#[derive(Template)]
+#[template(path = "pernoite.html")]
+pub struct PernoitePage {
+    pub linhas: Vec<PernoiteQuarto>, // Por corredor e quarto; os sem quarto no fim
+    pub stats: PresenceStats,
+    pub gerado_em: String,
 }
 
 // --- ADMINISTRAÇÃO DE UTILIZADORES ---
@@ -110,23 +1490,59 @@ pub struct UserWithRoles {
     pub ano: i64,
     pub curso: String,
     pub genero: String,
+    pub email: Option<String>,
+    pub telefone: Option<String>,
+    pub ativo: bool,
     pub roles: Vec<String>,
 }
 
+#[derive(Template)]
+#[template(path = "admin_dashboard.html")]
+pub struct AdminDashboardPage {
+    pub organizacao: String,            // Nome da organização em que o admin está a trabalhar
+    pub superadmin: bool,               // Mostra as áreas da instância (organizações, roles, cópias, ...)
+    pub users_por_ano: Vec<(i64, i64)>, // (ano, nº de utilizadores ativos)
+    pub total_users: i64,
+    pub dias_rascunho: i64,
+    pub trocas_substituto: i64,  // À espera da resposta do substituto
+    pub trocas_escalante: i64,   // À espera de aprovação do escalante
+    pub pessoas_fora: i64,
+    pub punidos: i64,
+    pub servicos_punicao: i64,
+    pub proximos_eventos: Vec<EventoAgenda>, // Agenda institucional (todos os anos)
+}
+
 #[derive(Template)]
 #[template(path = "admin_users.html")]
 pub struct AdminUsersPage {
     pub users: Vec<UserWithRoles>,
+    pub superadmin: bool,               // Links das áreas da instância (roles, auditoria, logins, ...)
+    pub paginacao: Navegacao,
+    pub roles_disponiveis: Vec<String>, // Checkboxes do formulário de criação
+    pub grupos: Vec<Grupo>,             // Seleção por grupo na edição em lote
+    pub form_criar: FormState,          // Erros/valores do formulário de criação (quando reapresentado)
     pub success_message: Option<String>,
     pub error_message: Option<String>,
 }
 
+impl AdminUsersPage {
+    /// Role marcada no formulário de criação reapresentado.
+    pub fn role_marcada(&self, role: &str) -> bool {
+        self.form_criar.valor("roles").split(',').any(|r| r.eq_ignore_ascii_case(role))
+    }
+}
+
 #[derive(Template)]
 #[template(path = "admin_edit_user.html")]
 pub struct AdminEditUserPage<'a> {
     pub user: Option<&'a User>,
     pub current_user_roles: &'a [String],
-    pub all_defined_roles: &'a [&'static str],
+    pub all_defined_roles: &'a [String],
+    pub atributos: Vec<AtributoCampo>, // Campos extra definidos em /admin/atributos
+    pub foto: Option<FotoUser>, // Fotografia (efetivo por turma)
+    pub form: FormState, // Erros por campo (quando o formulário é reapresentado)
+    pub success_message: Option<String>, // Envio/remoção da fotografia
+    pub foto_erro: Option<String>, // Envio da fotografia recusado (mostrado junto à fotografia)
     pub error_message: Option<String>,
 }
 
@@ -136,6 +1552,284 @@ impl<'a> AdminEditUserPage<'a> {
             .iter()
             .any(|r| r.eq_ignore_ascii_case(role))
     }
+
+    /// Erro de validação de um campo extra (os inputs chamam-se `attr_<chave>` nos erros).
+    pub fn erro_atributo(&self, chave: &str) -> Option<&str> {
+        self.form.erro(&format!("attr_{}", chave))
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin_sessoes.html")]
+pub struct AdminSessoesPage {
+    pub user: User,
+    pub sessoes: Vec<SessaoAtiva>,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_atributos.html")]
+pub struct AdminAtributosPage {
+    pub definicoes: Vec<AtributoDef>,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_webhooks.html")]
+pub struct AdminWebhooksPage {
+    pub webhooks: Vec<Webhook>,
+    pub entregas: Vec<WebhookEntrega>,
+    pub eventos: &'static [(&'static str, &'static str)], // (evento, descrição) para as caixas do formulário
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_backups.html")]
+pub struct AdminBackupsPage {
+    pub backups: Vec<Backup>,
+    pub dir: String,          // BACKUP_DIR
+    pub intervalo_horas: i64, // 0 = só cópias manuais
+    pub manter: i64,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_tarefas.html")]
+pub struct AdminTarefasPage {
+    pub tarefas: Vec<TarefaEstado>,
+    pub fuso: String, // Fuso das expressões cron (i18n.fuso_horario)
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_retencao.html")]
+pub struct AdminRetencaoPage {
+    pub politicas: Vec<PoliticaRetencao>, // Com o que a próxima limpeza apagaria
+    pub resumos: Vec<ResumoRetencao>,     // Totais do que já foi apagado
+    pub simular: bool,                    // retencao.simular: a tarefa só conta
+}
+
+#[derive(Template)]
+#[template(path = "superadmin_organizacoes.html")]
+pub struct SuperadminOrganizacoesPage {
+    pub organizacoes: Vec<Organizacao>,
+    pub atual: i64, // Organização em que o super-admin está a trabalhar
+    pub manutencao: Option<Manutencao>, // Modo de manutenção (None = desligado)
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_dados.html")]
+pub struct AdminDadosPage {
+    pub contagens: Vec<(String, i64)>, // (tabela, linhas) nesta instalação
+    pub versao: u32,                   // Versão do formato do pacote
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TemporaryRoleView {
+    pub id: i64,
+    pub user_id: String,
+    pub user_name: String,
+    pub role: String,
+    pub inicio: String, // Formatado para exibição (hora local)
+    pub fim: String,
+    pub ativa: bool,    // false = agendada (ainda não começou)
+}
+
+#[derive(Template)]
+#[template(path = "admin_temp_roles.html")]
+pub struct AdminTempRolesPage {
+    pub grants: Vec<TemporaryRoleView>,
+    pub roles: Vec<String>,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Uma linha da matriz de permissões (uma role e as permissões que concede).
+#[derive(Clone, Debug)]
+pub struct RoleMatrixRow {
+    pub nome: String,
+    pub descricao: String,
+    pub permanente: bool,
+    pub temporaria: bool,
+    pub sistema: bool,
+    pub permissoes: Vec<String>,
+}
+
+impl RoleMatrixRow {
+    pub fn tem(&self, permissao: &str) -> bool {
+        self.permissoes.iter().any(|p| p == permissao)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin_roles.html")]
+pub struct AdminRolesPage {
+    pub linhas: Vec<RoleMatrixRow>,
+    pub permissoes: &'static [(&'static str, &'static str)],
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_grupos.html")]
+pub struct AdminGruposPage {
+    pub grupos: Vec<Grupo>,
+    pub tipos: &'static [&'static str],
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_grupo.html")]
+pub struct AdminGrupoPage {
+    pub grupo: Grupo,
+    pub membros: Vec<GrupoMembro>,
+    pub postos: Vec<PostoGrupo>,
+    pub roles_temporarias: Vec<String>,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl AdminGrupoPage {
+    /// O posto está restrito a este grupo?
+    pub fn posto_do_grupo(&self, posto: &PostoGrupo) -> bool {
+        posto.grupo_id == Some(self.grupo.id)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin_rollover.html")]
+pub struct AdminRolloverPage {
+    pub ano_final: i64,
+    pub preview: RolloverPreview,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_contabilidade.html")]
+pub struct AdminContabilidadePage {
+    pub divergencias: Vec<Reconciliacao>,      // Contadores diferentes do livro de serviços
+    pub lancamentos: Vec<LancamentoServico>,   // Últimos lançamentos (do utilizador filtrado, se houver)
+    pub user: String,                          // Filtro por utilizador (vazio = todos)
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// Uma turma na lista de efetivos para impressão.
+#[derive(Debug, Clone)]
+pub struct TurmaRoster {
+    pub turma: String,
+    pub users: Vec<User>, // Ativos, por ano e antiguidade
+}
+
+#[derive(Template)]
+#[template(path = "admin_roster.html")]
+pub struct AdminRosterPage {
+    pub turmas: Vec<TurmaRoster>,
+    pub todas_turmas: Vec<String>, // Opções do filtro
+    pub filtro_turma: String,
+    pub antiguidade: HashMap<String, i64>, // Posição de cada utilizador no seu ano
+    pub fotos: HashSet<String>,            // Utilizadores com fotografia
+    pub gerado_em: String,
+}
+
+/// Boletim diário (/escala/{data}/boletim), formatado para impressão ("Guardar como PDF" no diálogo
+/// de impressão do browser).
+#[derive(Template)]
+#[template(path = "boletim_dia.html")]
+pub struct BoletimDiaPage {
+    pub dia: DiaEscala,
+    pub uniforme: Option<UniformeDia>,
+    pub eventos: Vec<EventoAgenda>, // Agenda do dia
+    pub gerado_em: String,
+}
+
+/// Antiguidade dos utilizadores de um ano (/admin/antiguidade).
+#[derive(Template)]
+#[template(path = "admin_antiguidade.html")]
+pub struct AdminAntiguidadePage {
+    pub anos: Vec<i64>, // Anos com utilizadores ativos
+    pub ano: i64,
+    pub posicoes: Vec<PosicaoAntiguidade>, // Por antiguidade (os sem posição no fim)
+    pub form: FormState,                   // Lista de IDs reapresentada com o erro
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl AdminAntiguidadePage {
+    /// Lista de IDs para o formulário: a submetida (se houve erro) ou a ordem atual.
+    pub fn ordem(&self) -> String {
+        if self.form.erros.is_empty() {
+            self.posicoes.iter().map(|p| p.user_id.as_str()).collect::<Vec<_>>().join("\n")
+        } else {
+            self.form.valor("ordem").to_string()
+        }
+    }
+
+    /// Utilizadores do ano ainda sem posição.
+    pub fn sem_posicao(&self) -> usize {
+        self.posicoes.iter().filter(|p| p.antiguidade.is_none()).count()
+    }
+}
+
+impl AdminRosterPage {
+    /// Posição do utilizador na antiguidade do ano ("-" se não tiver).
+    pub fn antiguidade(&self, user_id: &str) -> String {
+        self.antiguidade.get(user_id).map_or_else(|| "-".to_string(), |p| p.to_string())
+    }
+
+    /// O utilizador tem fotografia (senão, a célula fica vazia).
+    pub fn tem_foto(&self, user_id: &str) -> bool {
+        self.fotos.contains(user_id)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin_logins.html")]
+pub struct AdminLoginsPage {
+    pub registos: Vec<LoginRegisto>,
+    pub paginacao: Navegacao,
+    // Valores atuais dos filtros (para manter o formulário preenchido)
+    pub filtro_user: String,
+    pub filtro_ip: String,
+    pub filtro_resultado: String,
+    pub filtro_de: String,
+    pub filtro_ate: String,
+}
+
+#[derive(Template)]
+#[template(path = "admin_bloqueios.html")]
+pub struct AdminBloqueiosPage {
+    pub manuais: Vec<BloqueioManual>,
+    pub users: Vec<BloqueioAutomatico>, // Bloqueados pelo limite de tentativas
+    pub ips: Vec<BloqueioAutomatico>,
+    pub janela_minutos: i64,
+    pub success_message: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_audit.html")]
+pub struct AdminAuditPage {
+    pub entries: Vec<AuditEntry>,
+    pub paginacao: Navegacao,
+    pub acoes: &'static [&'static str],
+    // Valores atuais dos filtros (para manter o formulário preenchido)
+    pub filtro_actor: String,
+    pub filtro_acao: String,
+    pub filtro_alvo: String,
+    pub filtro_de: String,
+    pub filtro_ate: String,
 }
 
 #[derive(Template)]
@@ -144,4 +1838,50 @@ pub struct AdminEscalaPage {
     pub user_name: String,
     pub punidos: Vec<UserPunido>,
     pub trocas_pendentes: Vec<TrocaPendenteAdmin>,
-}
\ No newline at end of file
+}
+// --- FEEDS (Atom, ver feed_handlers) ---
+
+/// Entrada do feed: um dia da escala publicado.
+#[derive(Debug, Clone)]
+pub struct EntradaFeed {
+    pub data: String,        // 'YYYY-MM-DD'
+    pub titulo: String,
+    pub atualizado: String,  // RFC 3339 (publicação)
+    pub servicos: Vec<String>, // "Posto: Militar (turma)"
+}
+
+#[derive(Template)]
+#[template(path = "feed_escala.xml")]
+pub struct FeedEscala {
+    pub base: String,       // URL base (esquema e host do pedido), para os links absolutos
+    pub atualizado: String, // RFC 3339 (publicação mais recente)
+    pub entradas: Vec<EntradaFeed>,
+}
+
+// --- EMAILS (texto simples, ver email_service) ---
+
+#[derive(Template)]
+#[template(path = "email/senha_redefinida.txt")]
+pub struct EmailSenhaRedefinida {
+    pub nome: String,
+    pub user_id: String,
+    pub sessoes_terminadas: u64,
+}
+
+#[derive(Template)]
+#[template(path = "email/escala_publicada.txt")]
+pub struct EmailEscalaPublicada {
+    pub nome: String,
+    pub servicos: Vec<String>, // "dd/mm (Posto)"
+}
+
+#[derive(Template)]
+#[template(path = "email/troca_decidida.txt")]
+pub struct EmailTrocaDecidida {
+    pub nome: String,
+    pub aprovada: bool,
+    pub para_solicitante: bool, // Destinatário é quem pediu a troca (senão, o substituto)
+    pub outro: String,          // Nome da outra parte
+    pub data: String,           // 'dd/mm'
+    pub posto: String,
+}
@@ -0,0 +1,140 @@
+// src/tempo.rs
+//! Datas e horas da aplicação no fuso horário da unidade (`i18n.fuso_horario`), e não no do servidor.
+//! As horas são guardadas em UTC (`datetime('now')` no SQLite, `Utc::now()` em RFC3339) e só são
+//! convertidas para o fuso configurado ao mostrar; "hoje" (escala, serviços) é o dia no fuso configurado.
+//! Por isso as queries não usam `strftime(..., 'localtime')` (o fuso do servidor): devolvem UTC e
+//! o texto é formatado com `formatar` / `formatar_opcional`.
+//! As datas de um dia ('YYYY-MM-DD') são mostradas no idioma do pedido com `dia_semana`, `data_longa`,
+//! `relativa`, etc., também disponíveis nos templates como filtros (ver `templates::filters`).
+
+use crate::i18n;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::sync::OnceLock;
+
+/// Formato das datas/horas mostradas nas páginas.
+pub const FORMATO_DATA_HORA: &str = "%d/%m/%Y %H:%M";
+
+static FUSO: OnceLock<Tz> = OnceLock::new();
+
+/// Define o fuso horário da aplicação (chamado no arranque).
+pub fn configurar(fuso: Tz) {
+    let _ = FUSO.set(fuso);
+}
+
+/// Fuso horário do sistema operativo do servidor (o comportamento sem `i18n.fuso_horario`).
+/// Se não for possível determiná-lo, usa-se UTC.
+pub fn fuso_do_servidor() -> Tz {
+    match iana_time_zone::get_timezone() {
+        Ok(nome) => nome.parse().unwrap_or_else(|_| {
+            tracing::warn!("🕒 Fuso horário do servidor desconhecido ('{}'); a usar UTC.", nome);
+            Tz::UTC
+        }),
+        Err(e) => {
+            tracing::warn!("🕒 Não foi possível determinar o fuso horário do servidor ({}); a usar UTC.", e);
+            Tz::UTC
+        }
+    }
+}
+
+/// Fuso horário configurado.
+pub fn fuso() -> Tz {
+    *FUSO.get_or_init(fuso_do_servidor)
+}
+
+/// Data/hora atual em UTC, no formato guardado na base de dados quando a coluna é RFC3339
+/// (ex: presença): largura fixa, para que a ordem do texto seja a ordem cronológica.
+pub fn carimbo_utc() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Data/hora atual no fuso configurado.
+pub fn agora() -> DateTime<Tz> {
+    Utc::now().with_timezone(&fuso())
+}
+
+/// Dia de hoje no fuso configurado.
+pub fn hoje() -> NaiveDate {
+    agora().date_naive()
+}
+
+/// Converte uma data/hora (qualquer fuso) para o fuso configurado.
+pub fn local<T: TimeZone>(dt: &DateTime<T>) -> DateTime<Tz> {
+    dt.with_timezone(&fuso())
+}
+
+/// Interpreta uma data/hora lida da base de dados: `YYYY-MM-DD HH:MM:SS` do SQLite (UTC)
+/// ou RFC3339 (com o fuso indicado, como nas marcações de presença).
+pub fn ler_utc(texto: &str) -> Option<DateTime<Utc>> {
+    let texto = texto.trim();
+    DateTime::parse_from_rfc3339(texto)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(texto, "%Y-%m-%d %H:%M:%S").ok().map(|dt| Utc.from_utc_datetime(&dt)))
+}
+
+/// Formata no fuso configurado uma data/hora lida da base de dados (ver `ler_utc`).
+/// Um valor que não seja uma data/hora é devolvido tal como está.
+pub fn formatar(texto: &str, formato: &str) -> String {
+    match ler_utc(texto) {
+        Some(dt) => local(&dt).format(formato).to_string(),
+        None => texto.to_string(),
+    }
+}
+
+/// Como `formatar`, para colunas que podem ser NULL.
+pub fn formatar_opcional(texto: Option<String>, formato: &str) -> Option<String> {
+    texto.map(|t| formatar(&t, formato))
+}
+
+/// Início do dia `data` (meia-noite no fuso configurado) em UTC, no formato de `datetime('now')`:
+/// as colunas UTC de um dia local são `>= inicio_dia_utc(dia)` e `< inicio_dia_utc(dia + 1)`.
+pub fn inicio_dia_utc(data: NaiveDate) -> String {
+    let meia_noite = data.and_time(NaiveTime::MIN);
+    fuso()
+        .from_local_datetime(&meia_noite)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc).naive_utc())
+        .unwrap_or(meia_noite)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+// --- APRESENTAÇÃO DAS DATAS (idioma do pedido) ---
+
+/// Interpreta um dia ('YYYY-MM-DD'; de uma data/hora só conta a parte da data).
+pub fn ler_data(texto: &str) -> Option<NaiveDate> {
+    let texto = texto.trim();
+    NaiveDate::parse_from_str(texto.get(..10).unwrap_or(texto), "%Y-%m-%d").ok()
+}
+
+/// Nome do dia da semana (ex: "Segunda").
+pub fn dia_semana(data: NaiveDate) -> String {
+    i18n::t(&format!("datas.dia_{}", data.weekday().number_from_monday()))
+}
+
+/// Nome abreviado do mês (ex: "Out").
+pub fn mes_abreviado(data: NaiveDate) -> String {
+    i18n::t(&format!("datas.mes_{}", data.month()))
+}
+
+/// Dia e mês (ex: "25/10").
+pub fn data_curta(data: NaiveDate) -> String {
+    data.format("%d/%m").to_string()
+}
+
+/// Dia da semana, dia e mês (ex: "Sábado, 25/10").
+pub fn data_longa(data: NaiveDate) -> String {
+    format!("{}, {}", dia_semana(data), data_curta(data))
+}
+
+/// Distância ao dia de hoje no fuso configurado (ex: "hoje", "amanhã", "em 3 dias", "há 2 dias").
+pub fn relativa(data: NaiveDate) -> String {
+    match (data - hoje()).num_days() {
+        0 => i18n::t("datas.hoje"),
+        1 => i18n::t("datas.amanha"),
+        -1 => i18n::t("datas.ontem"),
+        n if n > 0 => i18n::tf("datas.em_dias", &[("n", &n.to_string())]),
+        n => i18n::tf("datas.ha_dias", &[("n", &(-n).to_string())]),
+    }
+}
@@ -0,0 +1,170 @@
+// src/validation.rs
+//! Camada de validação dos dados que chegam dos formulários e da API.
+//! Cada formulário/payload implementa `Validate`, acumulando num `Validador`
+//! todos os erros encontrados (um por campo), em vez de parar no primeiro.
+
+use crate::error::{AppError, AppResult, FieldError};
+use chrono::{NaiveDate, NaiveTime};
+use std::collections::HashMap;
+
+/// Implementado pelos formulários/payloads que precisam de validação no servidor.
+pub trait Validate {
+    /// Regista no validador os problemas encontrados nos dados.
+    fn validate(&self, v: &mut Validador);
+}
+
+/// Valida os dados e devolve `AppError::Validation` com todos os erros de campo.
+pub fn validar<T: Validate>(dados: &T) -> AppResult<()> {
+    let mut v = Validador::default();
+    dados.validate(&mut v);
+    v.resultado()
+}
+
+/// Acumula erros de validação por campo (apenas o primeiro erro de cada campo é guardado).
+#[derive(Debug, Default)]
+pub struct Validador {
+    erros: Vec<FieldError>,
+}
+
+impl Validador {
+    /// Regista um erro num campo, se esse campo ainda não tiver nenhum.
+    pub fn erro(&mut self, campo: &str, mensagem: impl Into<String>) {
+        if !self.erros.iter().any(|e| e.campo == campo) {
+            self.erros.push(FieldError::new(campo, mensagem));
+        }
+    }
+
+    /// Campo de texto obrigatório, com comprimento máximo (em caracteres).
+    pub fn obrigatorio(&mut self, campo: &str, valor: &str, max: usize) {
+        let valor = valor.trim();
+        if valor.is_empty() {
+            self.erro(campo, "Campo obrigatório.");
+        } else if valor.chars().count() > max {
+            self.erro(campo, format!("Máximo de {} caracteres.", max));
+        }
+    }
+
+    /// Comprimento mínimo (ex: senhas). Não faz trim.
+    pub fn minimo(&mut self, campo: &str, valor: &str, min: usize) {
+        if valor.chars().count() < min {
+            self.erro(campo, format!("Mínimo de {} caracteres.", min));
+        }
+    }
+
+    /// Valor numérico dentro de um intervalo (inclusive).
+    pub fn intervalo(&mut self, campo: &str, valor: i64, min: i64, max: i64) {
+        if !(min..=max).contains(&valor) {
+            self.erro(campo, format!("Valor entre {} e {}.", min, max));
+        }
+    }
+
+    /// Valor tem de ser uma das opções indicadas.
+    pub fn opcao(&mut self, campo: &str, valor: &str, opcoes: &[&str]) {
+        if !opcoes.contains(&valor) {
+            self.erro(campo, "Opção inválida.");
+        }
+    }
+
+    /// Email opcional (vazio = sem email).
+    pub fn email(&mut self, campo: &str, valor: &str) {
+        let valor = valor.trim();
+        if valor.is_empty() {
+            return;
+        }
+        let valido = valor.len() <= 254
+            && valor.split_once('@').is_some_and(|(local, dominio)| !local.is_empty() && dominio.contains('.'));
+        if !valido {
+            self.erro(campo, "Email inválido.");
+        }
+    }
+
+    /// Telefone opcional: 8 a 15 dígitos, aceitando espaços, '+', '-' e parênteses.
+    pub fn telefone(&mut self, campo: &str, valor: &str) {
+        let valor = valor.trim();
+        if valor.is_empty() {
+            return;
+        }
+        let digitos = valor.chars().filter(|c| c.is_ascii_digit()).count();
+        let valido = (8..=15).contains(&digitos)
+            && valor.chars().all(|c| c.is_ascii_digit() || " +-()".contains(c));
+        if !valido {
+            self.erro(campo, "Telefone inválido (8 a 15 dígitos).");
+        }
+    }
+
+    /// Data no formato YYYY-MM-DD. Devolve a data para validações entre campos.
+    pub fn data(&mut self, campo: &str, valor: &str) -> Option<NaiveDate> {
+        let data = NaiveDate::parse_from_str(valor.trim(), "%Y-%m-%d").ok();
+        if data.is_none() {
+            self.erro(campo, "Data inválida.");
+        }
+        data
+    }
+
+    /// Hora no formato HH:MM.
+    pub fn hora(&mut self, campo: &str, valor: &str) -> Option<NaiveTime> {
+        let hora = NaiveTime::parse_from_str(valor.trim(), "%H:%M").ok();
+        if hora.is_none() {
+            self.erro(campo, "Hora inválida (HH:MM).");
+        }
+        hora
+    }
+
+    /// Valor em dinheiro (ex: "12,50"), zero ou positivo. Devolve os centavos (ver `crate::moeda`).
+    pub fn moeda(&mut self, campo: &str, valor: &str) -> Option<i64> {
+        let centavos = crate::moeda::ler(valor);
+        if centavos.is_none() {
+            self.erro(campo, "Valor inválido (ex.: 12,50).");
+        }
+        centavos
+    }
+
+    /// Período [inicio, fim] válido, com o fim igual ou posterior ao início.
+    pub fn periodo(&mut self, campo_inicio: &str, inicio: &str, campo_fim: &str, fim: &str) {
+        let inicio = self.data(campo_inicio, inicio);
+        let fim = self.data(campo_fim, fim);
+        if let (Some(inicio), Some(fim)) = (inicio, fim) {
+            if fim < inicio {
+                self.erro(campo_fim, "A data fim deve ser igual ou posterior ao início.");
+            }
+        }
+    }
+
+    pub fn resultado(self) -> AppResult<()> {
+        if self.erros.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.erros))
+        }
+    }
+}
+
+/// Estado de um formulário reapresentado após falha de validação:
+/// os erros de cada campo e os valores submetidos (para não os perder).
+#[derive(Debug, Default)]
+pub struct FormState {
+    pub erros: Vec<FieldError>,
+    valores: HashMap<&'static str, String>,
+}
+
+impl FormState {
+    pub fn com_erros(erros: Vec<FieldError>) -> Self {
+        Self { erros, valores: HashMap::new() }
+    }
+
+    /// Guarda o valor submetido num campo (builder).
+    pub fn com_valor(mut self, campo: &'static str, valor: impl Into<String>) -> Self {
+        self.valores.insert(campo, valor.into());
+        self
+    }
+
+    /// Mensagem de erro do campo, se houver (usado nos templates junto ao input).
+    pub fn erro(&self, campo: &str) -> Option<&str> {
+        self.erros.iter().find(|e| e.campo == campo).map(|e| e.mensagem.as_str())
+    }
+
+    /// Valor submetido no campo (vazio se não houver).
+    pub fn valor(&self, campo: &str) -> &str {
+        self.valores.get(campo).map(String::as_str).unwrap_or_default()
+    }
+}
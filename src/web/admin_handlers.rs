@@ -1,23 +1,33 @@
 // src/web/admin_handlers.rs
 use crate::{
-    error::{AppError, AppResult},
+    error::{AppError, AppResult, FieldError},
     // models::user::User, // Removido (não usado diretamente aqui)
-    services::user_service, // Funções de gestão de users
+    models::{audit::AuditFilter, grupo::{Grupo, TIPOS_GRUPO}, login::LoginFilter, user::{DadosUser, User}},
+    services::{agenda_service, agendador_service, antiguidade_service, atributo_service, audit_service, backup_service, bloqueio_service, contabilidade_service, dados_service, dashboard_service, email_service, foto_service, grupo_service, login_history_service, manutencao_service, notificacao_service, organizacao_service, pdf_service, permission_service, retencao_service, sessao_service, user_service, webhook_service}, // Funções de gestão de users, permissões e auditoria
     state::AppState,
     // Structs Askama e wrapper UserWithRoles
-    templates::{AdminEditUserPage, AdminUsersPage, UserWithRoles},
-    // web::mw_auth::UserId, // Removido (não usado diretamente aqui)
+    templates::{
+        AdminAtributosPage, AdminAuditPage, AdminBackupsPage, AdminBloqueiosPage, AdminContabilidadePage, AdminDadosPage, AdminDashboardPage, AdminEditUserPage, AdminGrupoPage, AdminGruposPage, AdminLoginsPage, AdminRetencaoPage, AdminRolesPage, AdminRolloverPage, AdminRosterPage, AdminSessoesPage, AdminTarefasPage, AdminTempRolesPage, AdminUsersPage, AdminWebhooksPage, EmailSenhaRedefinida, RoleMatrixRow, SuperadminOrganizacoesPage,
+        TemporaryRoleView, TurmaRoster, UserWithRoles,
+    },
+    tempo,
+    validation::{validar, FormState, Validador, Validate},
+    web::{flash::{self, Flash}, mw_auth::{CurrentUser, OrganizacaoId, UserId, ORGANIZACAO_KEY}, paginacao::Paginacao}, // Feedback via sessão; ID do admin autenticado (autor das ações auditadas)
 };
 // Adicionar imports necessários
 use askama::Template; // Para render()
 use axum::{
-    extract::{Form, Path, Query, State}, // Adicionar Query para feedback
-    response::{Html, IntoResponse, Redirect}, // Adicionar Html
+    body::Bytes,
+    extract::{Extension, Path, Query, State}, // Adicionar Query para feedback
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response}, // Adicionar Html
 };
+// Form do axum-extra: aceita chaves repetidas (ex: várias checkboxes 'roles') como Vec<String>
+use axum_extra::extract::Form;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use serde::Deserialize;
-use std::collections::HashMap; // Para processar form
-// Adicionar import urlencoding
-use urlencoding;
+use std::collections::{BTreeMap, HashMap}; // Para processar form / agrupar por turma
+use tower_sessions::Session; // Mensagens flash (feedback após redirect)
 
 // --- Structs para os Formulários ---
 #[derive(Deserialize, Debug)]
@@ -29,6 +39,10 @@ pub struct CreateUserForm {
     ano: i64,
     curso: String,
     genero: String,
+    #[serde(default)]
+    email: String, // Opcional (vazio = sem email)
+    #[serde(default)]
+    telefone: String, // Opcional
     // Espera diretamente um vetor para múltiplos valores com o mesmo nome 'roles'
     #[serde(default)] // Usa vetor vazio se nenhum 'roles' for enviado
     roles: Vec<String>,
@@ -46,7 +60,44 @@ pub struct EditUserForm {
     curso: String,
     genero: String,
     #[serde(default)]
+    email: String,
+    #[serde(default)]
+    telefone: String,
+    #[serde(default)]
     roles: Vec<String>,
+    // Campos extra: pares paralelos (input hidden com a chave + input com o valor)
+    #[serde(default)]
+    attr_chave: Vec<String>,
+    #[serde(default)]
+    attr_valor: Vec<String>,
+}
+
+impl EditUserForm {
+    /// Pares (chave, valor) dos campos extra submetidos.
+    fn atributos(&self) -> Vec<(String, String)> {
+        self.attr_chave.iter().cloned().zip(self.attr_valor.iter().cloned()).collect()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BatchEditUsersForm {
+    #[serde(default)]
+    ids: Vec<String>, // Checkboxes da tabela de utilizadores
+    #[serde(default)]
+    grupo_id: String, // Alternativa: todos os membros de um grupo
+    // Campos vazios = não alterar
+    #[serde(default)]
+    turma: String,
+    #[serde(default)]
+    ano: String,
+    #[serde(default)]
+    curso: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MergeUsersForm {
+    duplicado: String, // ID criado por engano (será arquivado)
+    canonico: String,  // ID que fica com todo o histórico
 }
 
 #[derive(Deserialize, Debug)]
@@ -56,58 +107,305 @@ pub struct ChangePasswordForm {
 }
 
 #[derive(Deserialize, Debug)]
-pub struct FeedbackParams {
-    success: Option<String>,
-    error: Option<String>,
+pub struct GrantTemporaryRoleForm {
+    user_id: String,
+    role: String,
+    inicio: String, // <input type="datetime-local"> -> "YYYY-MM-DDTHH:MM" (hora local)
+    fim: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateRoleForm {
+    nome: String,
+    #[serde(default)]
+    descricao: String,
+    permanente: Option<String>, // Checkbox: presente = marcada
+    temporaria: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RoleMatrixForm {
+    // Cada checkbox marcada envia "role|permissao"
+    #[serde(default)]
+    matriz: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateGrupoForm {
+    nome: String,
+    tipo: String,
+    #[serde(default)]
+    descricao: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateAtributoForm {
+    chave: String,
+    rotulo: String,
+    #[serde(default)]
+    ordem: String, // Opcional (vazio = 0)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BloquearContaForm {
+    user_id: String,
+    motivo: String,
+    #[serde(default)]
+    dias: String, // Opcional (vazio = até ser desbloqueada)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DesbloquearIpForm {
+    ip: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AddMembrosForm {
+    user_ids: String, // IDs separados por espaço, vírgula ou linha
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PostoGrupoForm {
+    posto_id: i64,
+    restringir: String, // "1" = restringir ao grupo, "0" = libertar
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GrantGrupoTempRoleForm {
+    role: String,
+    inicio: String,
+    fim: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RosterParams {
+    turma: Option<String>, // Vazio/ausente = todas as turmas
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RolloverParams {
+    ano_final: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ContabilidadeParams {
+    #[serde(default)]
+    user: String, // Vazio = lançamentos de todos
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RolloverForm {
+    ano_final: i64,
+    #[serde(default)]
+    confirmacao: String, // Tem de ser "CONFIRMAR"
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateWebhookForm {
+    url: String,
+    #[serde(default)]
+    segredo: String, // Opcional (vazio = gerado)
+    #[serde(default)] // Uma checkbox 'eventos' por evento subscrito
+    eventos: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WebhookAtivoForm {
+    ativo: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateOrganizacaoForm {
+    codigo: String,
+    nome: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OrganizacaoAtivaForm {
+    ativa: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ManutencaoForm {
+    ativa: bool,
+    #[serde(default)]
+    motivo: String,
+}
+
+// --- Validação dos formulários ---
+
+/// Converte um campo opcional do formulário: vazio (ou só espaços) -> None.
+fn campo_opcional(valor: &str) -> Option<&str> {
+    let valor = valor.trim();
+    if valor.is_empty() { None } else { Some(valor) }
+}
+
+impl Validate for CreateUserForm {
+    fn validate(&self, v: &mut Validador) {
+        v.obrigatorio("id", &self.id, 10);
+        v.obrigatorio("name", &self.name, 100);
+        v.minimo("password", &self.password, 4);
+        v.obrigatorio("turma", &self.turma, 20);
+        v.intervalo("ano", self.ano, 1, 5);
+        v.obrigatorio("curso", &self.curso, 10);
+        v.opcao("genero", &self.genero, &["M", "F"]);
+        v.email("email", &self.email);
+        v.telefone("telefone", &self.telefone);
+    }
+}
+
+impl Validate for EditUserForm {
+    fn validate(&self, v: &mut Validador) {
+        v.obrigatorio("name", &self.name, 100);
+        v.obrigatorio("turma", &self.turma, 20);
+        v.intervalo("ano", self.ano, 1, 5);
+        v.obrigatorio("curso", &self.curso, 10);
+        v.opcao("genero", &self.genero, &["M", "F"]);
+        v.email("email", &self.email);
+        v.telefone("telefone", &self.telefone);
+        for (chave, valor) in self.atributos() {
+            if valor.trim().chars().count() > atributo_service::MAX_VALOR {
+                v.erro(&format!("attr_{}", chave), format!("Máximo de {} caracteres.", atributo_service::MAX_VALOR));
+            }
+        }
+    }
+}
+
+/// Utilizador da organização do pedido (NotFound se não existir ou for de outra organização).
+async fn user_da_organizacao(state: &AppState, organizacao_id: i64, user_id: &str) -> AppResult<User> {
+    user_service::find_user_by_id(&state.db_pool, user_id)
+        .await?
+        .filter(|u| u.organizacao_id == organizacao_id)
+        .ok_or_else(|| AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)))
+}
+
+/// Só um super-admin atribui ou retira roles com a permissão `superadmin` (administração da instância).
+async fn verificar_roles_superadmin(state: &AppState, atual: &CurrentUser, antes: &[String], depois: &[String]) -> AppResult<()> {
+    if atual.superadmin {
+        return Ok(());
+    }
+    for role in antes.iter().chain(depois) {
+        let mudou = antes.iter().any(|r| r.eq_ignore_ascii_case(role)) != depois.iter().any(|r| r.eq_ignore_ascii_case(role));
+        if mudou
+            && permission_service::roles_tem_permissao(
+                &state.db_pool,
+                &state.permissions,
+                std::slice::from_ref(role),
+                permission_service::PERM_SUPERADMIN,
+            )
+            .await?
+        {
+            tracing::warn!("{} sem permissão para alterar a role '{}' (super-admin).", atual.id, role);
+            return Err(AppError::Unauthorized);
+        }
+    }
+    Ok(())
+}
+
+/// Grupo da organização do pedido (NotFound se não existir ou for de outra organização).
+async fn grupo_da_organizacao(state: &AppState, organizacao_id: i64, grupo_id: i64) -> AppResult<Grupo> {
+    grupo_service::find_grupo(&state.db_pool, organizacao_id, grupo_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Grupo não encontrado.".into()))
 }
 
 // --- Handlers ---
 
+/// Handler para GET /admin - Painel com os números principais e atalhos para cada área
+pub async fn show_admin_dashboard(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin: Carregando painel...");
+
+    let organizacao_id = atual.organizacao_id;
+    let organizacao = organizacao_service::find(&state.db_pool, organizacao_id)
+        .await?
+        .map(|o| o.nome)
+        .unwrap_or_default();
+
+    let users_por_ano = dashboard_service::users_por_ano(&state.db_pool, organizacao_id).await?;
+    let (trocas_substituto, trocas_escalante) = dashboard_service::trocas_pendentes(&state.db_pool, organizacao_id).await?;
+    let (punidos, servicos_punicao) = dashboard_service::punicoes_pendentes(&state.db_pool, organizacao_id).await?;
+
+    let template = AdminDashboardPage {
+        organizacao,
+        superadmin: atual.superadmin,
+        total_users: users_por_ano.iter().map(|(_, total)| total).sum(),
+        users_por_ano,
+        dias_rascunho: dashboard_service::dias_rascunho(&state.db_pool, organizacao_id).await?,
+        trocas_substituto,
+        trocas_escalante,
+        pessoas_fora: dashboard_service::pessoas_fora(&state.db_pool, organizacao_id).await?,
+        punidos,
+        servicos_punicao,
+        proximos_eventos: agenda_service::proximos(&state.db_leitura, organizacao_id, tempo::hoje(), None, agenda_service::PROXIMOS_PAINEL).await?,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminDashboardPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
 /// Handler para GET /admin/users - Mostra a página de gestão
 pub async fn show_admin_users_page(
     State(state): State<AppState>, // Acesso ao pool da DB
-    Query(params): Query<FeedbackParams>, // Recebe feedback via query params
+    atual: CurrentUser,
+    flash: Flash, // Feedback guardado na sessão pelo último redirect
+    paginacao: Paginacao,
 ) -> AppResult<impl IntoResponse> { // Manter impl IntoResponse
     tracing::debug!("GET /admin/users: Carregando página de gestão...");
 
-    // 1. Busca todos os utilizadores da base de dados
-    let users_result = user_service::find_all_users(&state.db_pool).await;
-    let users = match users_result {
-        Ok(u) => u,
+    let template = montar_pagina_users(&state, &atual, &paginacao, flash.success, flash.error, FormState::default()).await;
+
+    // Renderiza o template explicitamente e trata erro
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()), // Retorna Ok(Html(...))
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminUsersPage: {}", e);
+            Err(AppError::InternalServerError) // Retorna Err se render falhar
+        }
+    }
+}
+
+/// Carrega os dados da página de gestão de utilizadores.
+/// `form_criar` traz os erros/valores do formulário de criação quando este é reapresentado.
+async fn montar_pagina_users(
+    state: &AppState,
+    atual: &CurrentUser, // Organização da página; super-admin vê os links da instância
+
+    paginacao: &Paginacao,
+    success_message: Option<String>,
+    error_message: Option<String>,
+    form_criar: FormState,
+) -> AdminUsersPage {
+    let organizacao_id = atual.organizacao_id;
+    // 1. Busca a página pedida de utilizadores, já com as roles (uma só query)
+    let (users, total) = match user_service::pagina_users_with_roles(&state.db_pool, organizacao_id, paginacao.limite(), paginacao.offset()).await {
+        Ok(pagina) => pagina,
         Err(e) => {
             tracing::error!("Erro ao buscar todos os utilizadores: {:?}", e);
             // Renderiza mesmo com erro na busca
-            let template = AdminUsersPage {
+            return AdminUsersPage {
                 users: vec![], // Lista vazia
+                superadmin: atual.superadmin,
+                paginacao: paginacao.navegacao(0),
+                roles_disponiveis: vec![],
+                grupos: vec![],
+                form_criar,
                 success_message: None,
                 error_message: Some("Falha ao carregar lista de utilizadores.".to_string()),
             };
-            // Tenta renderizar, retorna erro interno se falhar
-            return match template.render() {
-                 Ok(html) => Ok(Html(html).into_response()),
-                 Err(render_e) => {
-                    tracing::error!("Falha ao renderizar template AdminUsersPage (com erro de busca): {}", render_e);
-                    Err(AppError::InternalServerError)
-                 }
-            };
         }
     };
 
-    // 2. Para cada utilizador, busca as suas roles
+    // 2. Cria a struct combinada para o template
     let mut users_with_roles = Vec::new();
-    for user in users {
-        let roles = match user_service::get_user_roles(&state.db_pool, &user.id).await {
-            Ok(r) => r,
-            Err(e) => {
-                tracing::warn!(
-                    "Erro ao buscar roles para user {}: {:?}. Mostrando sem roles.",
-                    user.id,
-                    e
-                );
-                vec![] // Mostra lista vazia de roles se houver erro
-            }
-        };
-        // Cria a struct combinada para o template
+    for (user, roles) in users {
         users_with_roles.push(UserWithRoles {
             id: user.id,
             name: user.name,
@@ -115,212 +413,831 @@ pub async fn show_admin_users_page(
             ano: user.ano,
             curso: user.curso,
             genero: user.genero,
+            email: user.email,
+            telefone: user.telefone,
+            ativo: user.ativo,
             roles, // Adiciona o Vec<String> de roles
         });
     }
 
-    // 3. Cria a struct do template Askama, passando a lista e feedback
-    let template = AdminUsersPage {
-        users: users_with_roles,
-        success_message: params.success, // Vem da query string (?success=...)
-        error_message: params.error,     // Vem da query string (?error=...)
-    };
+    // 3. Roles disponíveis para o formulário de criação
+    let roles_disponiveis = permission_service::roles_permanentes(&state.db_pool).await.unwrap_or_else(|e| {
+        tracing::error!("Erro ao buscar roles definidas: {:?}", e);
+        vec![]
+    });
 
-    // 4. Renderiza o template explicitamente e trata erro
-    match template.render() {
-        Ok(html) => Ok(Html(html).into_response()), // Retorna Ok(Html(...))
-        Err(e) => {
-            tracing::error!("Falha ao renderizar template AdminUsersPage: {}", e);
-            Err(AppError::InternalServerError) // Retorna Err se render falhar
-        }
+    // Grupos (para a ação em lote por grupo)
+    let grupos = grupo_service::listar_grupos(&state.db_pool, organizacao_id).await.unwrap_or_else(|e| {
+        tracing::error!("Erro ao buscar grupos: {:?}", e);
+        vec![]
+    });
+
+    AdminUsersPage {
+        users: users_with_roles,
+        superadmin: atual.superadmin,
+        paginacao: paginacao.navegacao(total),
+        roles_disponiveis,
+        grupos,
+        form_criar,
+        success_message, // Mensagens flash (ou do formulário reapresentado)
+        error_message,
     }
 }
 
 /// Handler para POST /admin/users/create - Cria um novo utilizador
-
+/// Em caso de dados inválidos, a página é reapresentada (422) com os erros junto a cada campo.
 pub async fn handle_create_user(
     State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    atual: CurrentUser,
     Form(form): Form<CreateUserForm>, // Usa struct corrigida
-) -> AppResult<Redirect> {
+) -> AppResult<Response> {
 
     tracing::info!("POST /admin/users/create: Tentando criar user {}", form.id);
-
-    // Validações básicas (pode adicionar mais)
-    if form.id.trim().is_empty()
-        || form.name.trim().is_empty()
-        || form.password.len() < 4 // Exemplo: Mínimo 4 caracteres
-        || form.turma.trim().is_empty()
-        || form.curso.trim().is_empty()
-        || (form.genero != "M" && form.genero != "F") // Garante M ou F
-    {
-        tracing::warn!("Criação falhou: Dados inválidos no formulário.");
-        let error_msg = urlencoding::encode("Dados inválidos. Verifique todos os campos (senha mín. 4 caracteres).");
-        // Criar URL numa variável antes
-        let redirect_url = format!("/admin/users?error={}", error_msg);
-        // Retorna Ok(Redirect) mesmo em caso de erro de validação (padrão Post/Redirect/Get)
-        return Ok(Redirect::to(&redirect_url));
-    }
+    let organizacao_id = atual.organizacao_id;
 
     // Usa form.roles diretamente (já é Vec<String>)
     let roles = &form.roles;
     tracing::debug!("Roles selecionadas para {}: {:?}", form.id, roles);
+    verificar_roles_superadmin(&state, &atual, &[], roles).await?;
 
+    let dados = DadosUser {
+        name: form.name.trim(),
+        turma: form.turma.trim(),
+        ano: form.ano,
+        curso: form.curso.trim(),
+        genero: &form.genero,
+        email: campo_opcional(&form.email),
+        telefone: campo_opcional(&form.telefone),
+    };
+    let resultado = match validar(&form) {
+        // Chama o serviço para criar o utilizador na DB
+        Ok(()) => user_service::create_user(
+            &state.db_pool,
+            organizacao_id,
+            form.id.trim(),
+            &dados,
+            &form.password, // Passa a senha "raw"
+            roles, // Passa &Vec<String> (converte para &[String])
+        )
+        .await,
+        Err(e) => Err(e),
+    };
 
-    // Chama o serviço para criar o utilizador na DB
-    match user_service::create_user(
-        &state.db_pool,
-        &form.id,
-        &form.name,
-        &form.password, // Passa a senha "raw"
-        &form.turma,
-        form.ano,
-        &form.curso,
-        &form.genero,
-        roles, // Passa &Vec<String> (converte para &[String])
-    )
-    .await
-    {
+    match resultado {
         Ok(_) => {
             // Sucesso! Redireciona com mensagem de sucesso
             tracing::info!("Utilizador {} criado com sucesso.", form.id);
-            let success_msg = urlencoding::encode(&format!("Utilizador '{}' criado com sucesso.", form.id)).to_string();
-            // Criar URL numa variável antes
-            let redirect_url = format!("/admin/users?success={}", success_msg);
-            Ok(Redirect::to(&redirect_url)) // Passa a referência da variável
+            let detalhes = format!(
+                "nome: '{}'; turma: '{}'; ano: {}; curso: '{}'; genero: '{}'; roles: [{}]",
+                form.name, form.turma, form.ano, form.curso, form.genero, roles.join(", ")
+            );
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_USER_CRIADO, Some(&form.id), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(&session, "/admin/users", format!("Utilizador '{}' criado com sucesso.", form.id)).await.into_response())
         }
         Err(e) => {
-            // Erro ao criar (ex: ID já existe, erro DB)
-            tracing::error!("Erro ao criar utilizador {}: {:?}", form.id, e);
-            // Tenta dar uma mensagem mais específica
-            let error_detail = match e {
-                // TODO: Fazer user_service retornar erro específico para ID duplicado
-                _ => "ID de utilizador já existe ou ocorreu um erro na base de dados.".to_string(),
+            // Erros de dados (campos inválidos, ID já existente): reapresenta o formulário
+            let erros = match e {
+                AppError::Validation(erros) => erros,
+                AppError::UserAlreadyExists(id) => vec![FieldError::new(
+                    "id",
+                    format!("Já existe um utilizador com o ID '{}'.", id),
+                )],
+                e => {
+                    // Erro ao criar (erro DB): padrão PRG com mensagem genérica
+                    tracing::error!("Erro ao criar utilizador {}: {:?}", form.id, e);
+                    return Ok(flash::redirect_error(&session, "/admin/users", "Ocorreu um erro na base de dados ao criar o utilizador.").await.into_response());
+                }
             };
-            let error_msg = urlencoding::encode(&error_detail);
-            // Criar URL numa variável antes
-            let redirect_url = format!("/admin/users?error={}", error_msg);
-            // Retorna Ok(Redirect) mesmo em caso de erro na DB (padrão PRG)
-            Ok(Redirect::to(&redirect_url))
+            tracing::warn!("Criação de {} recusada: {:?}", form.id, erros);
+
+            let form_criar = FormState::com_erros(erros)
+                .com_valor("id", &form.id)
+                .com_valor("name", &form.name)
+                .com_valor("turma", &form.turma)
+                .com_valor("ano", form.ano.to_string())
+                .com_valor("curso", &form.curso)
+                .com_valor("genero", &form.genero)
+                .com_valor("email", &form.email)
+                .com_valor("telefone", &form.telefone)
+                .com_valor("roles", form.roles.join(","));
+            let template = montar_pagina_users(
+                &state,
+                &atual,
+                &Paginacao::nova("/admin/users"),
+                None,
+                Some("Não foi possível criar o utilizador. Corrija os campos assinalados.".to_string()),
+                form_criar,
+            )
+            .await;
+            match template.render() {
+                Ok(html) => Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(html)).into_response()),
+                Err(e) => {
+                    tracing::error!("Falha ao renderizar template AdminUsersPage: {}", e);
+                    Err(AppError::InternalServerError)
+                }
+            }
         }
     }
 }
 
-/// Handler para POST /admin/users/change_password - Altera a senha de um utilizador
-pub async fn handle_change_password(
-    State(state): State<AppState>, // Acesso ao pool da DB
-    Form(form): Form<ChangePasswordForm>, // Dados do formulário
-) -> AppResult<Redirect> { // Retorna AppResult<Redirect>
+/// Handler para POST /admin/users/batch - Altera turma/ano/curso de vários utilizadores de uma vez
+pub async fn handle_batch_edit_users(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Form(form): Form<BatchEditUsersForm>,
+) -> AppResult<Redirect> {
+    // Seleção: checkboxes ou, se escolhido, todos os membros do grupo
+    let ids = match form.grupo_id.trim().parse::<i64>() {
+        Ok(grupo_id) => {
+            let grupo = grupo_da_organizacao(&state, organizacao_id, grupo_id).await?;
+            grupo_service::ids_membros(&state.db_pool, grupo.id).await?
+        }
+        Err(_) => form.ids.clone(),
+    };
+    tracing::info!("POST /admin/users/batch: {} utilizadores selecionados", ids.len());
+    if ids.is_empty() {
+        return Ok(flash::redirect_error(&session, "/admin/users", "Selecione pelo menos um utilizador (ou um grupo com membros).").await);
+    }
 
-    tracing::info!("POST /admin/users/change_password: Tentando alterar senha para {}", form.id);
+    let turma = campo_opcional(&form.turma);
+    let curso = campo_opcional(&form.curso);
+    let ano = match campo_opcional(&form.ano).map(str::parse::<i64>) {
+        None => None,
+        Some(Ok(a)) if (1..=5).contains(&a) => Some(a),
+        Some(_) => return Ok(flash::redirect_error(&session, "/admin/users", "Ano inválido (1 a 5).").await),
+    };
 
-    // Validações básicas
-    if form.id.trim().is_empty() || form.new_password.len() < 4 {
-        tracing::warn!("Alteração de senha falhou: Dados inválidos.");
-        let error_msg = urlencoding::encode("ID ou nova senha inválidos.");
-        let redirect_url = format!("/admin/users?error={}", error_msg);
-        return Ok(Redirect::to(&redirect_url));
-    }
+    // Estado anterior, para o registo de auditoria de cada utilizador
+    let antes: HashMap<String, User> = user_service::find_all_users(&state.db_pool, organizacao_id)
+        .await?
+        .into_iter()
+        .filter(|u| ids.contains(&u.id))
+        .map(|u| (u.id.clone(), u))
+        .collect();
 
-    // Chama o serviço para alterar a senha na DB
-    match user_service::update_user_password(&state.db_pool, &form.id, &form.new_password).await {
-        Ok(_) => {
-            // Sucesso!
-            tracing::info!("Senha alterada com sucesso para {}", form.id);
-            let success_msg = urlencoding::encode(&format!("Senha para '{}' alterada com sucesso.", form.id)).to_string();
-            let redirect_url = format!("/admin/users?success={}", success_msg);
-            Ok(Redirect::to(&redirect_url))
+    match user_service::batch_update_users(&state.db_pool, organizacao_id, &ids, turma, ano, curso).await {
+        Ok(total) => {
+            for (id, user) in &antes {
+                let diff = audit_service::resumo_diff(&[
+                    ("turma", user.turma.clone(), turma.unwrap_or(&user.turma).to_string()),
+                    ("ano", user.ano.to_string(), ano.unwrap_or(user.ano).to_string()),
+                    ("curso", user.curso.clone(), curso.unwrap_or(&user.curso).to_string()),
+                ]);
+                if let Some(diff) = diff {
+                    let detalhes = format!("{} (edição em lote)", diff);
+                    audit_service::registar(
+                        &state.db_pool, &actor.0, audit_service::ACAO_USER_EDITADO, Some(id), Some(&detalhes),
+                    ).await;
+                }
+            }
+            Ok(flash::redirect_success(&session, "/admin/users", format!("{} utilizador(es) atualizado(s).", total)).await)
         }
         Err(e) => {
-            // Erro (ex: user não encontrado, erro DB)
-            tracing::error!("Erro ao alterar senha para {}: {:?}", form.id, e);
-            // Tenta dar uma mensagem mais específica
-             let error_detail = match e {
-                 // TODO: Fazer user_service retornar erro específico para UserNotFound
-                 _ => "Utilizador não encontrado ou erro na base de dados.".to_string(),
+            tracing::warn!("Edição em lote falhou: {:?}", e);
+            let error_detail = match e {
+                AppError::NotFound(_) | AppError::Validation(_) => e.user_message(),
+                _ => "Erro na base de dados; nenhum utilizador foi alterado.".to_string(),
             };
-            let error_msg = urlencoding::encode(&error_detail);
-            let redirect_url = format!("/admin/users?error={}", error_msg);
-            Ok(Redirect::to(&redirect_url))
+            Ok(flash::redirect_error(&session, "/admin/users", &error_detail).await)
         }
     }
 }
 
-pub async fn show_edit_user_form(
-    State(state): State<AppState>, // Acesso ao pool da DB
-    Path(user_id): Path<String>, // <<< Extrai o ID da URL (ex: /admin/users/edit/1001)
-) -> AppResult<impl IntoResponse> {
-    tracing::debug!("GET /admin/users/edit/{} : Mostrando formulário", user_id);
+/// Escapa um valor para CSV (aspas se tiver separador, aspas ou quebra de linha).
+pub(crate) fn csv_campo(valor: &str) -> String {
+    if valor.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", valor.replace('"', "\"\""))
+    } else {
+        valor.to_string()
+    }
+}
 
-    // 1. Busca os dados atuais do utilizador
-    let user_result = user_service::find_user_by_id(&state.db_pool, &user_id).await;
+/// Handler para GET /admin/users/export.csv - Exporta os utilizadores (com os campos extra) em CSV
+pub async fn handle_export_users_csv(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+) -> AppResult<Response> {
+    tracing::info!("GET /admin/users/export.csv");
 
-    // Trata caso de utilizador não encontrado ou erro na DB
-    let user = match user_result {
-        Ok(Some(u)) => u,
-        Ok(None) => {
-            tracing::warn!("Tentativa de editar utilizador inexistente: {}", user_id);
-            // Renderiza o template com mensagem de erro (ou retorna NotFound)
-            let template = AdminEditUserPage {
-                user: None, // Passa None para indicar erro
-                current_user_roles: &[],
-                all_defined_roles: &user_service::DEFINED_ROLES,
-                error_message: Some(format!("Utilizador '{}' não encontrado.", user_id)),
-            };
-            return match template.render() {
-                Ok(html) => Ok(Html(html).into_response()),
-                Err(_) => Err(AppError::InternalServerError), // Erro ao renderizar erro
-            };
-        }
-        Err(e) => {
-            tracing::error!("Erro ao buscar user {} para edição: {:?}", user_id, e);
-            // Renderiza o template com mensagem de erro genérica
-             let template = AdminEditUserPage {
-                user: None,
-                current_user_roles: &[],
-                all_defined_roles: &user_service::DEFINED_ROLES,
-                error_message: Some("Erro ao carregar dados do utilizador.".to_string()),
-            };
-             return match template.render() {
-                 Ok(html) => Ok(Html(html).into_response()),
-                 Err(_) => Err(AppError::InternalServerError),
-             };
-        }
-    };
+    let mut users = user_service::find_all_users_with_roles(&state.db_pool, organizacao_id).await?;
+    let definicoes = atributo_service::listar_definicoes(&state.db_pool).await?;
+    let valores = atributo_service::todos_valores(&state.db_pool).await?;
+    // Por ano e antiguidade (os sem posição no fim do ano, pela ordem original)
+    let antiguidade = antiguidade_service::posicoes(&state.db_pool, organizacao_id).await?;
+    users.sort_by_key(|(u, _)| {
+        let posicao = antiguidade.get(&u.id).copied();
+        (u.ano, posicao.is_none(), posicao)
+    });
 
-    // 2. Busca as roles atuais do utilizador
-    let current_roles = match user_service::get_user_roles(&state.db_pool, &user_id).await {
-        Ok(roles) => roles,
-        Err(e) => {
-            tracing::error!("Erro ao buscar roles de {} para edição: {:?}", user_id, e);
-            // Continua, mas mostra erro no template? Ou retorna erro 500?
-            // Vamos continuar e mostrar mensagem no template.
-            let template = AdminEditUserPage {
-                user: Some(&user), // Passa o user encontrado
-                current_user_roles: &[], // Lista vazia
-                all_defined_roles: &user_service::DEFINED_ROLES,
-                error_message: Some("Erro ao carregar roles atuais do utilizador.".to_string()),
-            };
-             return match template.render() {
-                 Ok(html) => Ok(Html(html).into_response()),
-                 Err(_) => Err(AppError::InternalServerError),
-             };
-        }
-    };
+    let mut cabecalho: Vec<String> = ["id", "nome", "turma", "ano", "antiguidade", "curso", "genero", "email", "telefone", "ativo", "roles"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    cabecalho.extend(definicoes.iter().map(|d| csv_campo(&d.rotulo)));
 
-    // 3. Prepara os dados e renderiza o template de edição
-    let template = AdminEditUserPage {
-        user: Some(&user), // Passa referência ao user encontrado
-        current_user_roles: &current_roles, // Passa slice das roles atuais
-        all_defined_roles: &user_service::DEFINED_ROLES, // Passa slice da constante
-        error_message: None, // Sem erro nesta fase
-    };
+    // BOM UTF-8: o Excel abre os acentos corretamente
+    let mut csv = String::from("\u{feff}");
+    csv.push_str(&cabecalho.join(","));
+    csv.push_str("\r\n");
 
-    match template.render() {
-        Ok(html) => Ok(Html(html).into_response()),
-        Err(e) => {
-            tracing::error!("Falha ao renderizar template AdminEditUserPage para {}: {}", user_id, e);
-            Err(AppError::InternalServerError)
-        }
+    for (user, roles) in users {
+        let extra = valores.get(&user.id);
+        let mut linha = vec![
+            csv_campo(&user.id),
+            csv_campo(&user.name),
+            csv_campo(&user.turma),
+            user.ano.to_string(),
+            antiguidade.get(&user.id).map(|p| p.to_string()).unwrap_or_default(),
+            csv_campo(&user.curso),
+            csv_campo(&user.genero),
+            csv_campo(user.email.as_deref().unwrap_or_default()),
+            csv_campo(user.telefone.as_deref().unwrap_or_default()),
+            if user.ativo { "1" } else { "0" }.to_string(),
+            csv_campo(&roles.join(" ")),
+        ];
+        linha.extend(definicoes.iter().map(|d| {
+            csv_campo(extra.and_then(|m| m.get(&d.chave.to_lowercase())).map(String::as_str).unwrap_or_default())
+        }));
+        csv.push_str(&linha.join(","));
+        csv.push_str("\r\n");
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"utilizadores.csv\""),
+        ],
+        csv,
+    )
+        .into_response())
+}
+
+/// Handler para GET /admin/users/{id}/sessoes - Sessões ativas de um utilizador
+pub async fn show_sessoes_user_page(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(user_id): Path<String>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/users/{}/sessoes", user_id);
+
+    let user = user_da_organizacao(&state, organizacao_id, &user_id).await?;
+
+    let template = AdminSessoesPage {
+        // Sem sessão "atual": o admin está a ver as sessões de outra pessoa (ou as suas, noutra página)
+        sessoes: sessao_service::listar_user(&state.db_pool, &user.id, None).await?,
+        user,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminSessoesPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/users/{id}/sessoes/{sessao_id}/revogar - Termina uma sessão
+pub async fn handle_revogar_sessao_user(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path((user_id, sessao_id)): Path<(String, i64)>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/users/{}/sessoes/{}/revogar", user_id, sessao_id);
+    let url = format!("/admin/users/{}/sessoes", user_id);
+    user_da_organizacao(&state, organizacao_id, &user_id).await?;
+
+    match sessao_service::revogar(&state.db_pool, &user_id, sessao_id).await {
+        Ok(()) => {
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_USER_SESSOES, Some(&user_id), Some("1 sessão terminada"),
+            ).await;
+            Ok(flash::redirect_success(&session, &url, "Sessão terminada.").await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao revogar sessão {} de {}: {:?}", sessao_id, user_id, e);
+            Ok(flash::redirect_error(&session, &url, e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/users/{id}/sessoes/revogar_todas - Termina todas as sessões do utilizador
+pub async fn handle_revogar_sessoes_user(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(user_id): Path<String>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/users/{}/sessoes/revogar_todas", user_id);
+    let url = format!("/admin/users/{}/sessoes", user_id);
+    user_da_organizacao(&state, organizacao_id, &user_id).await?;
+
+    // O admin a terminar as próprias sessões mantém a atual
+    let atual = if actor.0 == user_id { session.id().map(|id| id.to_string()) } else { None };
+    match sessao_service::revogar_todas(&state.db_pool, &user_id, atual.as_deref()).await {
+        Ok(n) => {
+            let detalhes = format!("{} sessões terminadas", n);
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_USER_SESSOES, Some(&user_id), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(&session, &url, format!("{} sessão(ões) terminada(s).", n)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao revogar sessões de {}: {:?}", user_id, e);
+            Ok(flash::redirect_error(&session, &url, e.user_message()).await)
+        }
+    }
+}
+
+/// Termina as sessões de um utilizador depois de lhe alterar a senha ou retirar privilégios,
+/// obrigando-o a autenticar-se de novo. O admin que altera a própria conta mantém a sessão atual.
+/// Uma falha fica só no log (a alteração em si já foi gravada); devolve o número de sessões terminadas.
+async fn invalidar_sessoes(state: &AppState, session: &Session, actor: &UserId, user_id: &str) -> u64 {
+    let atual = if actor.0 == user_id { session.id().map(|id| id.to_string()) } else { None };
+    match sessao_service::revogar_todas(&state.db_pool, user_id, atual.as_deref()).await {
+        Ok(n) => n,
+        Err(e) => {
+            tracing::error!("Erro ao terminar as sessões de {}: {:?}", user_id, e);
+            0
+        }
+    }
+}
+
+/// Avisa o utilizador (notificação no painel) de uma alteração de segurança feita por um admin à sua conta.
+/// Não avisa quando o admin altera a própria conta.
+async fn notificar_seguranca(state: &AppState, actor: &UserId, user_id: &str, titulo: &str, mensagem: &str) {
+    if !actor.0.eq_ignore_ascii_case(user_id) {
+        notificacao_service::notificar(&state.db_pool, user_id, notificacao_service::TIPO_SEGURANCA, titulo, mensagem).await;
+    }
+}
+
+/// Avisa o utilizador por email de que a senha foi redefinida (sempre, mesmo que tenha desativado os avisos).
+async fn enviar_email_senha(state: &AppState, user_id: &str, sessoes_terminadas: u64) {
+    match user_service::find_user_by_id(&state.db_pool, user_id).await {
+        Ok(Some(user)) => {
+            let email = EmailSenhaRedefinida { nome: user.name, user_id: user.id, sessoes_terminadas };
+            email_service::enfileirar(&state.db_pool, user_id, &email).await;
+        }
+        Ok(None) => {}
+        Err(e) => tracing::error!("Erro ao obter {} para o email de senha redefinida: {:?}", user_id, e),
+    }
+}
+
+/// Efetivo por turma, comum à página e ao PDF.
+struct Efetivo {
+    turmas: Vec<TurmaRoster>,          // As do filtro
+    todas_turmas: Vec<String>,         // Opções do filtro
+    antiguidade: HashMap<String, i64>, // Posição de cada utilizador no seu ano
+}
+
+/// Utilizadores ativos por turma (todas, ou só a do filtro), cada turma por ano e antiguidade (os sem
+/// posição no fim do ano, por ID).
+async fn efetivo_por_turma(state: &AppState, organizacao_id: i64, filtro_turma: &str) -> AppResult<Efetivo> {
+    let mut por_turma: BTreeMap<String, Vec<User>> = BTreeMap::new();
+    for user in user_service::find_all_users(&state.db_pool, organizacao_id).await? {
+        if user.ativo {
+            por_turma.entry(user.turma.clone()).or_default().push(user);
+        }
+    }
+    let antiguidade = antiguidade_service::posicoes(&state.db_pool, organizacao_id).await?;
+    for users in por_turma.values_mut() {
+        users.sort_by_key(|u| {
+            let posicao = antiguidade.get(&u.id).copied();
+            (u.ano, posicao.is_none(), posicao, u.id.clone())
+        });
+    }
+    let todas_turmas: Vec<String> = por_turma.keys().cloned().collect();
+    let turmas = por_turma
+        .into_iter()
+        .filter(|(turma, _)| filtro_turma.is_empty() || turma.eq_ignore_ascii_case(filtro_turma))
+        .map(|(turma, users)| TurmaRoster { turma, users })
+        .collect();
+    Ok(Efetivo { turmas, todas_turmas, antiguidade })
+}
+
+/// Handler para GET /admin/users/roster - Lista de efetivos por turma, com fotografia, formatada para
+/// impressão (uma turma por página, por ano e antiguidade); o PDF está em /admin/users/roster.pdf
+pub async fn show_roster_page(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Query(params): Query<RosterParams>,
+) -> AppResult<impl IntoResponse> {
+    let filtro_turma = params.turma.unwrap_or_default().trim().to_string();
+    tracing::debug!("GET /admin/users/roster: turma '{}'", filtro_turma);
+
+    let Efetivo { turmas, todas_turmas, antiguidade } = efetivo_por_turma(&state, organizacao_id, &filtro_turma).await?;
+    let fotos = foto_service::da_organizacao(&state.db_pool, organizacao_id).await?.into_keys().collect();
+
+    let template = AdminRosterPage {
+        turmas,
+        todas_turmas,
+        filtro_turma,
+        antiguidade,
+        fotos,
+        gerado_em: tempo::agora().format(tempo::FORMATO_DATA_HORA).to_string(),
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminRosterPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Linhas do efetivo por página do PDF (cada uma com a fotografia).
+const ROSTER_PDF_LINHAS: usize = 10;
+/// Colunas do PDF do efetivo: (título, x em pontos).
+const ROSTER_PDF_COLUNAS: &[(&str, f32)] = &[
+    ("#", 40.0), ("Foto", 62.0), ("Nome", 115.0), ("ID", 330.0), ("Ano", 400.0), ("Antig.", 435.0), ("Curso", 480.0),
+];
+
+/// Handler para GET /admin/users/roster.pdf - Efetivo por turma em PDF, para os quadros e a pasta do
+/// chefe de dia: uma turma por página (continua na seguinte se não couber), com fotografia, nome, ID,
+/// ano, antiguidade e curso
+pub async fn handle_roster_pdf(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Query(params): Query<RosterParams>,
+) -> AppResult<Response> {
+    let filtro_turma = params.turma.unwrap_or_default().trim().to_string();
+    tracing::debug!("GET /admin/users/roster.pdf: turma '{}'", filtro_turma);
+
+    let Efetivo { turmas, antiguidade, .. } = efetivo_por_turma(&state, organizacao_id, &filtro_turma).await?;
+    let fotos = foto_service::da_organizacao(&state.db_pool, organizacao_id).await?;
+    let gerado_em = tempo::agora().format(tempo::FORMATO_DATA_HORA).to_string();
+
+    // Cada fotografia entra uma vez no PDF; sem ficheiro, o utilizador aparece como "sem foto"
+    let mut pdf = pdf_service::Pdf::default();
+    let mut imagens: HashMap<&str, usize> = HashMap::new();
+    for user in turmas.iter().flat_map(|t| &t.users) {
+        if let Some(foto) = fotos.get(&user.id) {
+            match foto_service::conteudo(&state.config.fotos.diretorio, foto).await {
+                Ok(jpeg) => {
+                    let indice = pdf.incluir_jpeg(jpeg, foto.largura, foto.altura, foto.componentes);
+                    imagens.insert(&user.id, indice);
+                }
+                Err(e) => tracing::warn!("Efetivo em PDF sem a fotografia de {}: {:?}", user.id, e),
+            }
+        }
+    }
+
+    let margem = 40.0;
+    let direita = pdf_service::LARGURA_A4 - margem;
+    let (foto_l, foto_a, linha_a) = (45.0, 60.0, 66.0);
+    for t in &turmas {
+        for (pagina, users) in t.users.chunks(ROSTER_PDF_LINHAS).enumerate() {
+            pdf.nova_pagina();
+            let mut y = pdf_service::ALTURA_A4 - 55.0;
+            let titulo = if pagina == 0 {
+                format!("Efetivo — Turma {} ({} efetivos)", t.turma, t.users.len())
+            } else {
+                format!("Efetivo — Turma {} (continuação)", t.turma)
+            };
+            pdf.texto(margem, y, 16.0, true, 0.0, &pdf_service::ajustar(&titulo, 16.0, direita - margem));
+            y -= 30.0;
+            pdf.retangulo(margem, y - 5.0, direita - margem, 18.0, Some(0.9));
+            for (nome, x) in ROSTER_PDF_COLUNAS {
+                pdf.texto(*x, y, 9.0, true, 0.0, nome);
+            }
+            y -= 5.0;
+            pdf.linha(margem, y, direita, y, 0.5);
+
+            for (i, user) in users.iter().enumerate() {
+                let topo = y;
+                y -= linha_a;
+                let base = y + linha_a / 2.0 - 4.0; // Texto a meio da linha
+                pdf.texto(40.0, base, 9.0, false, 0.0, &(pagina * ROSTER_PDF_LINHAS + i + 1).to_string());
+                match imagens.get(user.id.as_str()) {
+                    Some(&indice) => pdf.imagem(indice, 62.0, topo - 3.0 - foto_a, foto_l, foto_a),
+                    None => {
+                        pdf.retangulo(62.0, topo - 3.0 - foto_a, foto_l, foto_a, None);
+                        pdf.texto(68.0, topo - 3.0 - foto_a / 2.0 - 3.0, 7.0, false, 0.5, "sem foto");
+                    }
+                }
+                pdf.texto(115.0, base, 10.0, true, 0.0, &pdf_service::ajustar(&user.name, 10.0, 210.0));
+                pdf.texto(330.0, base, 10.0, false, 0.0, &pdf_service::ajustar(&user.id, 10.0, 65.0));
+                pdf.texto(400.0, base, 10.0, false, 0.0, &format!("{}º", user.ano));
+                let posicao = antiguidade.get(&user.id).map_or_else(|| "-".to_string(), |p| p.to_string());
+                pdf.texto(435.0, base, 10.0, false, 0.0, &posicao);
+                pdf.texto(480.0, base, 10.0, false, 0.0, &pdf_service::ajustar(&user.curso, 10.0, direita - 480.0));
+                pdf.linha(margem, y, direita, y, 0.25);
+            }
+            let rodape = format!("Gerado em {} · Página {}", gerado_em, pdf.paginas());
+            pdf.texto(margem, 30.0, 8.0, false, 0.45, &rodape);
+        }
+    }
+    if turmas.is_empty() {
+        let mensagem = if filtro_turma.is_empty() {
+            "Nenhum utilizador ativo.".to_string()
+        } else {
+            format!("Nenhum utilizador ativo na turma {}.", filtro_turma)
+        };
+        pdf.texto(margem, pdf_service::ALTURA_A4 - 55.0, 12.0, false, 0.0, &mensagem);
+    }
+
+    // Só caracteres seguros no nome do ficheiro
+    let sufixo: String = filtro_turma
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') { c } else { '_' })
+        .collect();
+    let nome = if sufixo.is_empty() { "efetivo.pdf".to_string() } else { format!("efetivo_{}.pdf", sufixo) };
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", nome)),
+        ],
+        pdf.gerar(),
+    )
+        .into_response())
+}
+
+/// Handler para GET /admin/users/{id}/foto - Fotografia do utilizador (JPEG)
+pub async fn show_foto_user(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(user_id): Path<String>,
+) -> AppResult<Response> {
+    let foto = foto_service::obter(&state.db_pool, organizacao_id, &user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("O utilizador '{}' não tem fotografia.", user_id)))?;
+    let bytes = foto_service::conteudo(&state.config.fotos.diretorio, &foto).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/jpeg"),
+            (header::CACHE_CONTROL, "private, no-cache"),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+/// Handler para POST /admin/users/{id}/foto/enviar - Envia (ou substitui) a fotografia: o JPEG vem no corpo do pedido
+pub async fn handle_enviar_foto_user(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(user_id): Path<String>,
+    corpo: Bytes,
+) -> AppResult<Response> {
+    tracing::info!("POST /admin/users/{}/foto/enviar por {} ({} bytes)", user_id, actor.0, corpo.len());
+    let destino = format!("/admin/users/edit/{}", user_id);
+    if corpo.is_empty() {
+        return Ok(flash::redirect_error(&session, &destino, "Escolha uma fotografia (JPEG).").await.into_response());
+    }
+    let resultado = foto_service::enviar(
+        &state.db_pool,
+        &state.config.fotos.diretorio,
+        organizacao_id,
+        &user_id,
+        &corpo,
+        &actor.0,
+    )
+    .await;
+    match resultado {
+        Ok(foto) => {
+            let detalhes = format!("{}x{}, {}", foto.largura, foto.altura, foto.tamanho_legivel());
+            audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_USER_FOTO_ENVIADA, Some(&user_id), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, &destino, "Fotografia guardada.").await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let mensagem = erros.iter().map(|e| e.mensagem.clone()).collect::<Vec<_>>().join(" ");
+            Ok(flash::redirect_error(&session, &destino, mensagem).await.into_response())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /admin/users/{id}/foto/remover - Apaga a fotografia
+pub async fn handle_remover_foto_user(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(user_id): Path<String>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/users/{}/foto/remover por {}", user_id, actor.0);
+    let destino = format!("/admin/users/edit/{}", user_id);
+    match foto_service::remover(&state.db_pool, &state.config.fotos.diretorio, organizacao_id, &user_id).await {
+        Ok(_) => {
+            audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_USER_FOTO_REMOVIDA, Some(&user_id), None).await;
+            Ok(flash::redirect_success(&session, &destino, "Fotografia apagada.").await)
+        }
+        Err(AppError::NotFound(mensagem)) => Ok(flash::redirect_error(&session, &destino, mensagem).await),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /admin/users/merge - Funde um utilizador duplicado no canónico
+pub async fn handle_merge_users(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Form(form): Form<MergeUsersForm>,
+) -> AppResult<Redirect> {
+    let duplicado = form.duplicado.trim();
+    let canonico = form.canonico.trim();
+    tracing::info!("POST /admin/users/merge: {} -> {}", duplicado, canonico);
+
+    if duplicado.is_empty() || canonico.is_empty() {
+        return Ok(flash::redirect_error(&session, "/admin/users", "Indique o ID duplicado e o ID canónico.").await);
+    }
+
+    match user_service::merge_users(&state.db_pool, organizacao_id, duplicado, canonico).await {
+        Ok(resumo) => {
+            let detalhes = format!("duplicado '{}' fundido e arquivado; {}", duplicado, resumo.descricao());
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_USER_FUNDIDO, Some(canonico), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(
+                &session,
+                "/admin/users",
+                format!("'{}' fundido em '{}' ({}).", duplicado, canonico, resumo.descricao()),
+            ).await)
+        }
+        Err(e) => {
+            tracing::warn!("Fusão de {} em {} falhou: {:?}", duplicado, canonico, e);
+            let error_detail = match e {
+                AppError::Validation(_) | AppError::Conflict(_) => e.user_message(),
+                _ => "Erro na base de dados; nenhum registo foi alterado.".to_string(),
+            };
+            Ok(flash::redirect_error(&session, "/admin/users", error_detail).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/users/change_password - Altera a senha de um utilizador
+pub async fn handle_change_password(
+    State(state): State<AppState>, // Acesso ao pool da DB
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Form(form): Form<ChangePasswordForm>, // Dados do formulário
+) -> AppResult<Redirect> { // Retorna AppResult<Redirect>
+
+    tracing::info!("POST /admin/users/change_password: Tentando alterar senha para {}", form.id);
+
+    // Validações básicas
+    if form.id.trim().is_empty() || form.new_password.len() < 4 {
+        tracing::warn!("Alteração de senha falhou: Dados inválidos.");
+        return Ok(flash::redirect_error(&session, "/admin/users", "ID ou nova senha inválidos.").await);
+    }
+    if let Err(e) = user_da_organizacao(&state, organizacao_id, &form.id).await {
+        return Ok(flash::redirect_error(&session, "/admin/users", e.user_message()).await);
+    }
+
+    // Chama o serviço para alterar a senha na DB
+    match user_service::update_user_password(&state.db_pool, &form.id, &form.new_password).await {
+        Ok(_) => {
+            // Sucesso!
+            tracing::info!("Senha alterada com sucesso para {}", form.id);
+            let terminadas = invalidar_sessoes(&state, &session, &actor, &form.id).await;
+            notificar_seguranca(
+                &state, &actor, &form.id, "Senha alterada",
+                "A sua senha foi alterada pela administração e as sessões abertas foram terminadas. Se não pediu esta alteração, contacte a administração.",
+            ).await;
+            enviar_email_senha(&state, &form.id, terminadas).await;
+            let detalhes = format!("{} sessões terminadas", terminadas);
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_USER_PASSWORD, Some(&form.id), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(
+                &session,
+                "/admin/users",
+                format!("Senha para '{}' alterada com sucesso ({} sessão(ões) terminada(s)).", form.id, terminadas),
+            ).await)
+        }
+        Err(e) => {
+            // Erro (ex: user não encontrado, erro DB)
+            tracing::error!("Erro ao alterar senha para {}: {:?}", form.id, e);
+            // Tenta dar uma mensagem mais específica
+            let error_detail = match e {
+                AppError::NotFound(msg) => msg,
+                _ => "Erro ao alterar a senha na base de dados.".to_string(),
+            };
+            Ok(flash::redirect_error(&session, "/admin/users", error_detail).await)
+        }
+    }
+}
+
+pub async fn show_edit_user_form(
+    State(state): State<AppState>, // Acesso ao pool da DB
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(user_id): Path<String>, // <<< Extrai o ID da URL (ex: /admin/users/edit/1001)
+    flash: Flash, // Resultado do envio da fotografia
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/users/edit/{} : Mostrando formulário", user_id);
+
+    // Roles atribuíveis (checkboxes do formulário)
+    let all_defined_roles = permission_service::roles_permanentes(&state.db_pool).await?;
+
+    // 1. Busca os dados atuais do utilizador
+    let user_result = user_service::find_user_by_id(&state.db_pool, &user_id).await;
+
+    // Trata caso de utilizador não encontrado ou erro na DB
+    let user = match user_result {
+        Ok(Some(u)) if u.organizacao_id == organizacao_id => u,
+        Ok(_) => { // Inexistente ou de outra organização
+            tracing::warn!("Tentativa de editar utilizador inexistente: {}", user_id);
+            // Renderiza o template com mensagem de erro (ou retorna NotFound)
+            let template = AdminEditUserPage {
+                user: None, // Passa None para indicar erro
+                current_user_roles: &[],
+                all_defined_roles: &all_defined_roles,
+                atributos: vec![],
+                foto: None,
+                form: FormState::default(),
+                success_message: None,
+                foto_erro: None,
+                error_message: Some(format!("Utilizador '{}' não encontrado.", user_id)),
+            };
+            return match template.render() {
+                Ok(html) => Ok(Html(html).into_response()),
+                Err(_) => Err(AppError::InternalServerError), // Erro ao renderizar erro
+            };
+        }
+        Err(e) => {
+            tracing::error!("Erro ao buscar user {} para edição: {:?}", user_id, e);
+            // Renderiza o template com mensagem de erro genérica
+             let template = AdminEditUserPage {
+                user: None,
+                current_user_roles: &[],
+                all_defined_roles: &all_defined_roles,
+                atributos: vec![],
+                foto: None,
+                form: FormState::default(),
+                success_message: None,
+                foto_erro: None,
+                error_message: Some("Erro ao carregar dados do utilizador.".to_string()),
+            };
+             return match template.render() {
+                 Ok(html) => Ok(Html(html).into_response()),
+                 Err(_) => Err(AppError::InternalServerError),
+             };
+        }
+    };
+
+    let foto = foto_service::obter(&state.db_pool, organizacao_id, &user_id).await?;
+
+    // 2. Busca as roles atuais do utilizador
+    let current_roles = match user_service::get_user_roles(&state.db_pool, &user_id).await {
+        Ok(roles) => roles,
+        Err(e) => {
+            tracing::error!("Erro ao buscar roles de {} para edição: {:?}", user_id, e);
+            // Continua, mas mostra erro no template? Ou retorna erro 500?
+            // Vamos continuar e mostrar mensagem no template.
+            let template = AdminEditUserPage {
+                user: Some(&user), // Passa o user encontrado
+                current_user_roles: &[], // Lista vazia
+                all_defined_roles: &all_defined_roles,
+                atributos: vec![],
+                foto,
+                form: FormState::default(),
+                success_message: None,
+                foto_erro: None,
+                error_message: Some("Erro ao carregar roles atuais do utilizador.".to_string()),
+            };
+             return match template.render() {
+                 Ok(html) => Ok(Html(html).into_response()),
+                 Err(_) => Err(AppError::InternalServerError),
+             };
+        }
+    };
+
+    // 3. Prepara os dados e renderiza o template de edição
+    let template = AdminEditUserPage {
+        user: Some(&user), // Passa referência ao user encontrado
+        current_user_roles: &current_roles, // Passa slice das roles atuais
+        all_defined_roles: &all_defined_roles, // Roles atribuíveis (tabela `roles`)
+        atributos: atributo_service::campos_user(&state.db_pool, &user_id).await?, // Campos extra
+        foto,
+        form: FormState::default(),
+        success_message: flash.success,
+        foto_erro: flash.error,
+        error_message: None, // Sem erro nesta fase
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminEditUserPage para {}: {}", user_id, e);
+            Err(AppError::InternalServerError)
+        }
     }
 }
 
@@ -328,44 +1245,48 @@ pub async fn show_edit_user_form(
 // <<< ADICIONADO: Handler para POST /admin/users/edit/:id - Processa a edição >>>
 pub async fn handle_edit_user(
     State(state): State<AppState>, // Acesso ao pool da DB
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    atual: CurrentUser,
     Path(user_id): Path<String>, // ID do utilizador vindo da URL
     Form(form): Form<EditUserForm>, // Dados do formulário
-) -> AppResult<Redirect> { // Redireciona para /admin/users com feedback
+) -> AppResult<Response> { // Redireciona para /admin/users com feedback
 
     tracing::info!("POST /admin/users/edit/{}: Processando edição...", user_id);
+    user_da_organizacao(&state, atual.organizacao_id, &user_id).await?;
 
-    // Validações básicas (pode adicionar mais)
-     if form.name.trim().is_empty()
-        || form.turma.trim().is_empty()
-        || form.curso.trim().is_empty()
-        || (form.genero != "M" && form.genero != "F")
-    {
-        tracing::warn!("Edição falhou para {}: Dados inválidos no formulário.", user_id);
-        let error_msg = urlencoding::encode("Dados inválidos. Verifique todos os campos.");
-        // Redireciona DE VOLTA para a página de edição com erro
-        // (Alternativa: redirecionar para /admin/users com erro genérico)
-        let redirect_url = format!("/admin/users/edit/{}?error={}", user_id, error_msg);
-        return Ok(Redirect::to(&redirect_url));
+    // Validação por campo: em caso de erro, reapresenta o formulário com os valores submetidos
+    if let Err(AppError::Validation(erros)) = validar(&form) {
+        tracing::warn!("Edição falhou para {}: Dados inválidos no formulário: {:?}", user_id, erros);
+        return reapresentar_edicao(&state, &user_id, &form, erros).await;
     }
 
+    // Guarda o estado anterior para o resumo de auditoria
+    let user_antes = user_service::find_user_by_id(&state.db_pool, &user_id).await.ok().flatten();
+    let roles_antes = user_service::get_user_roles(&state.db_pool, &user_id).await.unwrap_or_default();
+    let atributos_antes = atributo_service::campos_user(&state.db_pool, &user_id).await.unwrap_or_default();
+    verificar_roles_superadmin(&state, &atual, &roles_antes, &form.roles).await?;
+
     // Chama o serviço para atualizar os dados básicos do utilizador
-    let update_user_result = user_service::update_user(
-        &state.db_pool, &user_id, &form.name, &form.turma,
-        form.ano, &form.curso, &form.genero
-    ).await;
+    let dados = DadosUser {
+        name: &form.name,
+        turma: &form.turma,
+        ano: form.ano,
+        curso: &form.curso,
+        genero: &form.genero,
+        email: campo_opcional(&form.email),
+        telefone: campo_opcional(&form.telefone),
+    };
+    let update_user_result = user_service::update_user(&state.db_pool, &user_id, &dados).await;
 
     if let Err(e) = update_user_result {
         tracing::error!("Erro ao atualizar dados do user {}: {:?}", user_id, e);
         // Tenta dar uma mensagem mais específica
         let error_detail = match e {
-             // Assumindo InternalServerError para UserNotFound
-             AppError::InternalServerError => "Utilizador não encontrado.".to_string(),
+            AppError::NotFound(msg) => msg,
             _ => "Erro ao atualizar dados na base de dados.".to_string(),
         };
-        let error_msg = urlencoding::encode(&error_detail);
-        // Redireciona de volta para a PÁGINA DE EDIÇÃO com erro
-        let redirect_url = format!("/admin/users/edit/{}?error={}", user_id, error_msg);
-        return Ok(Redirect::to(&redirect_url));
+        return Ok(flash::redirect_error(&session, "/admin/users", error_detail).await.into_response());
     }
 
      // Chama o serviço para atualizar as roles permanentes
@@ -374,16 +1295,1562 @@ pub async fn handle_edit_user(
 
      if let Err(e) = update_roles_result {
          tracing::error!("Erro ao atualizar roles do user {}: {:?}", user_id, e);
-         let error_msg = urlencoding::encode("Erro ao atualizar roles na base de dados.");
-         // Redireciona de volta para a PÁGINA DE EDIÇÃO com erro
-         let redirect_url = format!("/admin/users/edit/{}?error={}", user_id, error_msg);
-         return Ok(Redirect::to(&redirect_url));
+         let error_detail = match e {
+             AppError::Validation(erros) => return reapresentar_edicao(&state, &user_id, &form, erros).await,
+             _ => "Erro ao atualizar roles na base de dados.".to_string(),
+         };
+         return Ok(flash::redirect_error(&session, "/admin/users", error_detail).await.into_response());
      }
 
-    // Se chegou aqui, ambas as atualizações foram bem-sucedidas
+    // Campos extra (atributos definidos pelo admin)
+    let atributos = form.atributos();
+    if let Err(e) = atributo_service::definir_valores(&state.db_pool, &user_id, &atributos).await {
+        tracing::error!("Erro ao atualizar campos extra do user {}: {:?}", user_id, e);
+        if let AppError::Validation(erros) = e {
+            return reapresentar_edicao(&state, &user_id, &form, erros).await;
+        }
+        return Ok(flash::redirect_error(&session, "/admin/users", "Erro ao gravar os campos extra na base de dados.").await.into_response());
+    }
+
+    // Se chegou aqui, todas as atualizações foram bem-sucedidas
     tracing::info!("✅ Dados e roles atualizados com sucesso para user {}", user_id);
-    let success_msg = urlencoding::encode(&format!("Dados do utilizador '{}' atualizados.", user_id)).to_string();
-    // Redireciona para a LISTA com mensagem de sucesso
-    let redirect_url = format!("/admin/users?success={}", success_msg);
-    Ok(Redirect::to(&redirect_url))
-}
\ No newline at end of file
+
+    if let Some(antes) = user_antes {
+        let mut campos = vec![
+            ("nome", antes.name, form.name.clone()),
+            ("turma", antes.turma, form.turma.clone()),
+            ("ano", antes.ano.to_string(), form.ano.to_string()),
+            ("curso", antes.curso, form.curso.clone()),
+            ("genero", antes.genero, form.genero.clone()),
+            ("email", antes.email.unwrap_or_default(), campo_opcional(&form.email).unwrap_or_default().to_string()),
+            ("telefone", antes.telefone.unwrap_or_default(), campo_opcional(&form.telefone).unwrap_or_default().to_string()),
+        ];
+        for campo in &atributos_antes {
+            if let Some((_, depois)) = atributos.iter().find(|(chave, _)| chave.eq_ignore_ascii_case(&campo.chave)) {
+                campos.push((campo.chave.as_str(), campo.valor.clone(), depois.trim().to_string()));
+            }
+        }
+        let diff = audit_service::resumo_diff(&campos);
+        if let Some(diff) = diff {
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_USER_EDITADO, Some(&user_id), Some(&diff),
+            ).await;
+        }
+    }
+
+    let mut roles_depois: Vec<String> = form.roles.iter()
+        .map(|r| r.trim().to_lowercase())
+        .filter(|r| !r.is_empty())
+        .collect();
+    roles_depois.sort();
+    let roles_antes: Vec<String> = roles_antes.iter().map(|r| r.to_lowercase()).collect();
+    let mut mensagem = format!("Dados do utilizador '{}' atualizados.", user_id);
+    if roles_antes != roles_depois {
+        let mut diff = format!("roles: [{}] → [{}]", roles_antes.join(", "), roles_depois.join(", "));
+        // Retirar uma role obriga a nova autenticação (as sessões abertas tinham os privilégios antigos)
+        if roles_antes.iter().any(|r| !roles_depois.contains(r)) {
+            let terminadas = invalidar_sessoes(&state, &session, &actor, &user_id).await;
+            diff.push_str(&format!("; {} sessões terminadas", terminadas));
+            mensagem.push_str(&format!(" {} sessão(ões) terminada(s).", terminadas));
+        }
+        audit_service::registar(
+            &state.db_pool, &actor.0, audit_service::ACAO_USER_ROLES, Some(&user_id), Some(&diff),
+        ).await;
+        let mensagem = format!(
+            "As suas permissões foram alteradas pela administração: [{}] → [{}].",
+            roles_antes.join(", "), roles_depois.join(", ")
+        );
+        notificar_seguranca(&state, &actor, &user_id, "Permissões alteradas", &mensagem).await;
+    }
+    Ok(flash::redirect_success(&session, "/admin/users", mensagem).await.into_response())
+}
+
+/// Reapresenta o formulário de edição (422) com os valores submetidos e os erros junto a cada campo.
+async fn reapresentar_edicao(
+    state: &AppState,
+    user_id: &str,
+    form: &EditUserForm,
+    erros: Vec<FieldError>,
+) -> AppResult<Response> {
+    let all_defined_roles = permission_service::roles_permanentes(&state.db_pool).await?;
+    let mut user = user_service::find_user_by_id(&state.db_pool, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)))?;
+
+    // Mostra o que foi submetido, não o que está guardado
+    user.name = form.name.clone();
+    user.turma = form.turma.clone();
+    user.ano = form.ano;
+    user.curso = form.curso.clone();
+    user.genero = form.genero.clone();
+    user.email = Some(form.email.clone());
+    user.telefone = Some(form.telefone.clone());
+    let submetidos = form.atributos();
+    let atributos = atributo_service::campos_user(&state.db_pool, user_id)
+        .await?
+        .into_iter()
+        .map(|mut campo| {
+            if let Some((_, valor)) = submetidos.iter().find(|(chave, _)| chave.eq_ignore_ascii_case(&campo.chave)) {
+                campo.valor = valor.clone();
+            }
+            campo
+        })
+        .collect();
+
+    let template = AdminEditUserPage {
+        user: Some(&user),
+        current_user_roles: &form.roles,
+        all_defined_roles: &all_defined_roles,
+        atributos,
+        foto: foto_service::obter(&state.db_pool, user.organizacao_id, user_id).await?,
+        form: FormState::com_erros(erros),
+        success_message: None,
+        foto_erro: None,
+        error_message: None,
+    };
+    match template.render() {
+        Ok(html) => Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminEditUserPage para {}: {}", user_id, e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+// --- Roles Temporárias ---
+
+/// Converte o valor de um `<input type="datetime-local">` (hora no fuso da aplicação) para UTC.
+fn parse_datetime_local(valor: &str) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(valor, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(valor, "%Y-%m-%dT%H:%M:%S"))
+        .ok()?;
+    tempo::fuso()
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Formata uma data RFC3339 guardada na DB para exibição no fuso da aplicação.
+fn format_rfc3339_local(valor: &str) -> String {
+    tempo::formatar(valor, tempo::FORMATO_DATA_HORA)
+}
+
+/// Handler para GET /admin/temp_roles - Lista as roles temporárias e o formulário de atribuição
+pub async fn show_temp_roles_page(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/temp_roles: Carregando página...");
+
+    let (grants, error_message) = match user_service::list_current_temporary_roles(&state.db_pool, organizacao_id).await {
+        Ok(g) => (g, flash.error),
+        Err(e) => {
+            tracing::error!("Erro ao buscar roles temporárias: {:?}", e);
+            (vec![], Some("Falha ao carregar roles temporárias.".to_string()))
+        }
+    };
+
+    let agora = Utc::now();
+    let grants = grants
+        .into_iter()
+        .map(|g| {
+            let ativa = DateTime::parse_from_rfc3339(&g.start_datetime)
+                .map(|inicio| inicio <= agora)
+                .unwrap_or(false);
+            TemporaryRoleView {
+                id: g.id,
+                user_id: g.user_id,
+                user_name: g.user_name,
+                role: g.role,
+                inicio: format_rfc3339_local(&g.start_datetime),
+                fim: format_rfc3339_local(&g.end_datetime),
+                ativa,
+            }
+        })
+        .collect();
+
+    let template = AdminTempRolesPage {
+        grants,
+        roles: permission_service::roles_temporarias(&state.db_pool).await?,
+        success_message: flash.success,
+        error_message,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminTempRolesPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/temp_roles/grant - Atribui uma role temporária
+pub async fn handle_grant_temp_role(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Form(form): Form<GrantTemporaryRoleForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/temp_roles/grant: role '{}' para {}", form.role, form.user_id);
+
+    let user_id = form.user_id.trim();
+    if user_id.is_empty() {
+        return Ok(flash::redirect_error(&session, "/admin/temp_roles", "Indique o ID do utilizador.").await);
+    }
+    let roles_temporarias = permission_service::roles_temporarias(&state.db_pool).await?;
+    if !roles_temporarias.iter().any(|r| r.eq_ignore_ascii_case(&form.role)) {
+        return Ok(flash::redirect_error(&session, "/admin/temp_roles", "Role inválida.").await);
+    }
+    let (inicio, fim) = match (parse_datetime_local(&form.inicio), parse_datetime_local(&form.fim)) {
+        (Some(i), Some(f)) => (i, f),
+        _ => return Ok(flash::redirect_error(&session, "/admin/temp_roles", "Datas de início/fim inválidas.").await),
+    };
+    if fim <= inicio {
+        return Ok(flash::redirect_error(&session, "/admin/temp_roles", "O fim deve ser posterior ao início.").await);
+    }
+    if fim <= Utc::now() {
+        return Ok(flash::redirect_error(&session, "/admin/temp_roles", "A janela indicada já terminou.").await);
+    }
+
+    match user_service::find_user_by_id(&state.db_pool, user_id).await {
+        Ok(Some(u)) if u.organizacao_id == organizacao_id => {}
+        Ok(_) => return Ok(flash::redirect_error(&session, "/admin/temp_roles", format!("Utilizador '{}' não encontrado.", user_id)).await),
+        Err(e) => {
+            tracing::error!("Erro ao buscar utilizador {}: {:?}", user_id, e);
+            return Ok(flash::redirect_error(&session, "/admin/temp_roles", "Erro ao validar o utilizador.").await);
+        }
+    }
+
+    match user_service::grant_temporary_role(&state.db_pool, user_id, &form.role, inicio, fim).await {
+        Ok(_) => {
+            let detalhes = format!(
+                "role '{}' de {} até {}",
+                form.role,
+                inicio.with_timezone(&tempo::fuso()).format(tempo::FORMATO_DATA_HORA),
+                fim.with_timezone(&tempo::fuso()).format(tempo::FORMATO_DATA_HORA)
+            );
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_TEMP_ROLE_ATRIBUIDA, Some(user_id), Some(&detalhes),
+            ).await;
+            let mensagem = format!("Foi-lhe atribuída pela administração a {}.", detalhes);
+            notificar_seguranca(&state, &actor, user_id, "Permissões alteradas", &mensagem).await;
+            Ok(flash::redirect_success(&session, "/admin/temp_roles", format!(
+                "Role '{}' atribuída temporariamente a '{}'.",
+                form.role, user_id
+            )).await)
+        }
+        Err(e) => {
+            tracing::error!("Erro ao atribuir role temporária a {}: {:?}", user_id, e);
+            Ok(flash::redirect_error(&session, "/admin/temp_roles", "Erro ao gravar a atribuição na base de dados.").await)
+        }
+    }
+}
+
+/// Handler para POST /admin/temp_roles/{id}/revoke - Termina antecipadamente uma atribuição
+pub async fn handle_revoke_temp_role(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(grant_id): Path<i64>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/temp_roles/{}/revoke", grant_id);
+
+    match user_service::revoke_temporary_role(&state.db_pool, organizacao_id, grant_id).await {
+        Ok((alvo, role)) => {
+            let terminadas = invalidar_sessoes(&state, &session, &actor, &alvo).await;
+            let detalhes = format!("role '{}' (atribuição #{}); {} sessões terminadas", role, grant_id, terminadas);
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_TEMP_ROLE_REVOGADA, Some(&alvo), Some(&detalhes),
+            ).await;
+            let mensagem = format!("A role temporária '{}' foi-lhe retirada pela administração.", role);
+            notificar_seguranca(&state, &actor, &alvo, "Permissões alteradas", &mensagem).await;
+            Ok(flash::redirect_success(&session, "/admin/temp_roles", "Role temporária revogada.").await)
+        }
+        Err(e) => {
+            tracing::error!("Erro ao revogar role temporária {}: {:?}", grant_id, e);
+            Ok(flash::redirect_error(&session, "/admin/temp_roles", "Atribuição não encontrada ou já terminada.").await)
+        }
+    }
+}
+
+
+// --- Roles e Permissões ---
+
+/// Handler para GET /admin/roles - Mostra a matriz role x permissão
+pub async fn show_roles_page(
+    State(state): State<AppState>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/roles: Carregando matriz de permissões...");
+
+    let roles = permission_service::listar_roles(&state.db_pool).await?;
+    let matriz = permission_service::listar_matriz(&state.db_pool).await?;
+
+    let linhas = roles
+        .into_iter()
+        .map(|r| {
+            let permissoes = matriz
+                .iter()
+                .filter(|(role, _)| role.eq_ignore_ascii_case(&r.nome))
+                .map(|(_, perm)| perm.clone())
+                .collect();
+            RoleMatrixRow {
+                nome: r.nome,
+                descricao: r.descricao,
+                permanente: r.permanente,
+                temporaria: r.temporaria,
+                sistema: r.sistema,
+                permissoes,
+            }
+        })
+        .collect();
+
+    let template = AdminRolesPage {
+        linhas,
+        permissoes: permission_service::PERMISSOES,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminRolesPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/roles/matriz - Grava a matriz completa
+pub async fn handle_save_role_matrix(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Form(form): Form<RoleMatrixForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/roles/matriz: {} entradas", form.matriz.len());
+
+    let pares: Vec<(String, String)> = form
+        .matriz
+        .iter()
+        .filter_map(|v| v.split_once('|'))
+        .map(|(role, perm)| (role.to_string(), perm.to_string()))
+        .collect();
+
+    match permission_service::definir_matriz(&state.db_pool, &state.permissions, &pares, &state.config.roles.admin).await {
+        Ok(()) => {
+            let mut resumo: Vec<String> = pares.iter().map(|(r, p)| format!("{}:{}", r, p)).collect();
+            resumo.sort();
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_ROLE_PERMISSOES, None, Some(&resumo.join(", ")),
+            ).await;
+            Ok(flash::redirect_success(&session, "/admin/roles", "Matriz de permissões atualizada.").await)
+        }
+        Err(e) => {
+            tracing::error!("Erro ao gravar matriz de permissões: {:?}", e);
+            Ok(flash::redirect_error(&session, "/admin/roles", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/roles/create - Cria uma role nova (sem permissões)
+pub async fn handle_create_role(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Form(form): Form<CreateRoleForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/roles/create: '{}'", form.nome);
+
+    let nome = form.nome.trim().to_lowercase();
+    let permanente = form.permanente.is_some();
+    let temporaria = form.temporaria.is_some();
+
+    match permission_service::criar_role(&state.db_pool, &nome, &form.descricao, permanente, temporaria).await {
+        Ok(()) => {
+            let detalhes = format!("permanente: {}; temporaria: {}", permanente, temporaria);
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_ROLE_CRIADA, Some(&nome), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(&session, "/admin/roles", format!("Role '{}' criada.", nome)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao criar role '{}': {:?}", nome, e);
+            Ok(flash::redirect_error(&session, "/admin/roles", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/roles/{nome}/delete - Apaga uma role que não seja de sistema
+pub async fn handle_delete_role(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Path(nome): Path<String>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/roles/{}/delete", nome);
+
+    match permission_service::remover_role(&state.db_pool, &state.permissions, &nome).await {
+        Ok(()) => {
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_ROLE_REMOVIDA, Some(&nome), None,
+            ).await;
+            Ok(flash::redirect_success(&session, "/admin/roles", format!("Role '{}' apagada.", nome)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao apagar role '{}': {:?}", nome, e);
+            Ok(flash::redirect_error(&session, "/admin/roles", e.user_message()).await)
+        }
+    }
+}
+
+
+// --- Grupos (pelotões, companhias, equipas) ---
+
+/// Handler para GET /admin/grupos - Lista os grupos e o formulário de criação
+pub async fn show_grupos_page(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/grupos: Carregando página...");
+
+    let template = AdminGruposPage {
+        grupos: grupo_service::listar_grupos(&state.db_pool, organizacao_id).await?,
+        tipos: TIPOS_GRUPO,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminGruposPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/grupos/create - Cria um grupo
+pub async fn handle_create_grupo(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Form(form): Form<CreateGrupoForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/grupos/create: '{}' ({})", form.nome, form.tipo);
+
+    match grupo_service::criar_grupo(&state.db_pool, organizacao_id, &form.nome, &form.tipo, &form.descricao).await {
+        Ok(grupo_id) => {
+            let detalhes = format!("nome: '{}'; tipo: {}", form.nome.trim(), form.tipo);
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_GRUPO_CRIADO, Some(&grupo_id.to_string()), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(&session, &format!("/admin/grupos/{}", grupo_id), "Grupo criado. Adicione os membros abaixo.").await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao criar grupo '{}': {:?}", form.nome, e);
+            Ok(flash::redirect_error(&session, "/admin/grupos", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/grupos/{id}/delete - Apaga um grupo
+pub async fn handle_delete_grupo(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(grupo_id): Path<i64>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/grupos/{}/delete", grupo_id);
+
+    match grupo_service::apagar_grupo(&state.db_pool, organizacao_id, grupo_id).await {
+        Ok(nome) => {
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_GRUPO_REMOVIDO, Some(&grupo_id.to_string()), Some(&nome),
+            ).await;
+            Ok(flash::redirect_success(&session, "/admin/grupos", format!("Grupo '{}' apagado.", nome)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao apagar grupo {}: {:?}", grupo_id, e);
+            Ok(flash::redirect_error(&session, "/admin/grupos", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para GET /admin/grupos/{id} - Membros, ações em lote e restrições de escala do grupo
+pub async fn show_grupo_page(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(grupo_id): Path<i64>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/grupos/{}: Carregando página...", grupo_id);
+
+    let grupo = grupo_da_organizacao(&state, organizacao_id, grupo_id).await?;
+
+    let template = AdminGrupoPage {
+        grupo,
+        membros: grupo_service::listar_membros(&state.db_pool, grupo_id).await?,
+        postos: grupo_service::listar_postos(&state.db_pool, organizacao_id).await?,
+        roles_temporarias: permission_service::roles_temporarias(&state.db_pool).await?,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminGrupoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/grupos/{id}/membros/add - Adiciona vários membros de uma vez
+pub async fn handle_add_grupo_membros(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(grupo_id): Path<i64>,
+    Form(form): Form<AddMembrosForm>,
+) -> AppResult<Redirect> {
+    let ids: Vec<String> = form
+        .user_ids
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect();
+    tracing::info!("POST /admin/grupos/{}/membros/add: {} IDs", grupo_id, ids.len());
+
+    let base_url = format!("/admin/grupos/{}", grupo_id);
+    grupo_da_organizacao(&state, organizacao_id, grupo_id).await?;
+    if ids.is_empty() {
+        return Ok(flash::redirect_error(&session, &base_url, "Indique pelo menos um ID.").await);
+    }
+
+    match grupo_service::adicionar_membros(&state.db_pool, organizacao_id, grupo_id, &ids).await {
+        Ok((adicionados, desconhecidos)) => {
+            if adicionados > 0 {
+                let detalhes = format!("adicionados: {}", ids.iter().filter(|id| !desconhecidos.contains(id)).cloned().collect::<Vec<_>>().join(", "));
+                audit_service::registar(
+                    &state.db_pool, &actor.0, audit_service::ACAO_GRUPO_MEMBROS, Some(&grupo_id.to_string()), Some(&detalhes),
+                ).await;
+            }
+            if desconhecidos.is_empty() {
+                Ok(flash::redirect_success(&session, &base_url, format!("{} membro(s) adicionado(s).", adicionados)).await)
+            } else {
+                Ok(flash::redirect_error(&session, &base_url, format!(
+                    "{} membro(s) adicionado(s). IDs inexistentes ignorados: {}",
+                    adicionados,
+                    desconhecidos.join(", ")
+                )).await)
+            }
+        }
+        Err(e) => {
+            tracing::error!("Erro ao adicionar membros ao grupo {}: {:?}", grupo_id, e);
+            Ok(flash::redirect_error(&session, &base_url, "Erro ao adicionar membros na base de dados.").await)
+        }
+    }
+}
+
+/// Handler para POST /admin/grupos/{id}/membros/{user_id}/remove - Remove um membro
+pub async fn handle_remove_grupo_membro(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path((grupo_id, user_id)): Path<(i64, String)>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/grupos/{}/membros/{}/remove", grupo_id, user_id);
+
+    grupo_da_organizacao(&state, organizacao_id, grupo_id).await?;
+
+    let base_url = format!("/admin/grupos/{}", grupo_id);
+    match grupo_service::remover_membro(&state.db_pool, grupo_id, &user_id).await {
+        Ok(()) => {
+            let detalhes = format!("removido: {}", user_id);
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_GRUPO_MEMBROS, Some(&grupo_id.to_string()), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(&session, &base_url, format!("'{}' removido do grupo.", user_id)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao remover {} do grupo {}: {:?}", user_id, grupo_id, e);
+            Ok(flash::redirect_error(&session, &base_url, e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/grupos/{id}/postos - Restringe/liberta um posto da escala
+pub async fn handle_grupo_posto(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(grupo_id): Path<i64>,
+    Form(form): Form<PostoGrupoForm>,
+) -> AppResult<Redirect> {
+    let restringir = form.restringir == "1";
+    tracing::info!("POST /admin/grupos/{}/postos: posto {} restringir={}", grupo_id, form.posto_id, restringir);
+
+    let base_url = format!("/admin/grupos/{}", grupo_id);
+    let novo_grupo = if restringir { Some(grupo_id) } else { None };
+    match grupo_service::definir_grupo_posto(&state.db_pool, organizacao_id, form.posto_id, novo_grupo).await {
+        Ok(()) => {
+            let detalhes = format!(
+                "posto {} {}",
+                form.posto_id,
+                if restringir { "restrito ao grupo" } else { "libertado" }
+            );
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_GRUPO_POSTO, Some(&grupo_id.to_string()), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(&session, &base_url, "Restrição do posto atualizada.").await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao atualizar posto {}: {:?}", form.posto_id, e);
+            Ok(flash::redirect_error(&session, &base_url, e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/grupos/{id}/temp_role - Atribui uma role temporária a todos os membros
+pub async fn handle_grupo_temp_role(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Path(grupo_id): Path<i64>,
+    Form(form): Form<GrantGrupoTempRoleForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/grupos/{}/temp_role: role '{}'", grupo_id, form.role);
+
+    let base_url = format!("/admin/grupos/{}", grupo_id);
+    grupo_da_organizacao(&state, organizacao_id, grupo_id).await?;
+
+    let roles_temporarias = permission_service::roles_temporarias(&state.db_pool).await?;
+    if !roles_temporarias.iter().any(|r| r.eq_ignore_ascii_case(&form.role)) {
+        return Ok(flash::redirect_error(&session, &base_url, "Role inválida.").await);
+    }
+    let (inicio, fim) = match (parse_datetime_local(&form.inicio), parse_datetime_local(&form.fim)) {
+        (Some(i), Some(f)) => (i, f),
+        _ => return Ok(flash::redirect_error(&session, &base_url, "Datas de início/fim inválidas.").await),
+    };
+    if fim <= inicio || fim <= Utc::now() {
+        return Ok(flash::redirect_error(&session, &base_url, "O fim deve ser posterior ao início e ainda não ter passado.").await);
+    }
+
+    let membros = grupo_service::ids_membros(&state.db_pool, grupo_id).await?;
+    if membros.is_empty() {
+        return Ok(flash::redirect_error(&session, &base_url, "O grupo não tem membros.").await);
+    }
+
+    let detalhes = format!(
+        "role '{}' de {} até {} (grupo #{})",
+        form.role,
+        inicio.with_timezone(&tempo::fuso()).format(tempo::FORMATO_DATA_HORA),
+        fim.with_timezone(&tempo::fuso()).format(tempo::FORMATO_DATA_HORA),
+        grupo_id
+    );
+    for user_id in &membros {
+        if let Err(e) = user_service::grant_temporary_role(&state.db_pool, user_id, &form.role, inicio, fim).await {
+            tracing::error!("Erro ao atribuir role temporária a {}: {:?}", user_id, e);
+            return Ok(flash::redirect_error(&session, &base_url, format!("Erro ao gravar a atribuição para '{}'. As anteriores foram mantidas.", user_id)).await);
+        }
+        audit_service::registar(
+            &state.db_pool, &actor.0, audit_service::ACAO_TEMP_ROLE_ATRIBUIDA, Some(user_id), Some(&detalhes),
+        ).await;
+        let mensagem = format!("Foi-lhe atribuída pela administração a {}.", detalhes);
+        notificar_seguranca(&state, &actor, user_id, "Permissões alteradas", &mensagem).await;
+    }
+
+    Ok(flash::redirect_success(&session, &base_url, format!(
+        "Role '{}' atribuída a {} membro(s) do grupo.",
+        form.role,
+        membros.len()
+    )).await)
+}
+
+
+// --- Campos Extra (atributos dos utilizadores) ---
+
+/// Handler para GET /admin/atributos - Lista as definições de campos extra
+pub async fn show_atributos_page(
+    State(state): State<AppState>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/atributos: Carregando página...");
+
+    let template = AdminAtributosPage {
+        definicoes: atributo_service::listar_definicoes(&state.db_pool).await?,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminAtributosPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/atributos/create - Define um novo campo extra
+pub async fn handle_create_atributo(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Form(form): Form<CreateAtributoForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/atributos/create: '{}'", form.chave);
+
+    let ordem = form.ordem.trim().parse::<i64>().unwrap_or(0);
+    match atributo_service::criar_definicao(&state.db_pool, &form.chave, &form.rotulo, ordem).await {
+        Ok(()) => {
+            let detalhes = format!("rótulo: '{}'", form.rotulo.trim());
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_ATRIBUTO_CRIADO, Some(&form.chave.trim().to_lowercase()), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(&session, "/admin/atributos", format!("Campo '{}' criado.", form.rotulo.trim())).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao criar atributo '{}': {:?}", form.chave, e);
+            Ok(flash::redirect_error(&session, "/admin/atributos", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/atributos/{chave}/delete - Apaga um campo extra (e os valores guardados)
+pub async fn handle_delete_atributo(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Path(chave): Path<String>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/atributos/{}/delete", chave);
+
+    match atributo_service::remover_definicao(&state.db_pool, &chave).await {
+        Ok(()) => {
+            audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_ATRIBUTO_REMOVIDO, Some(&chave), None).await;
+            Ok(flash::redirect_success(&session, "/admin/atributos", format!("Campo '{}' apagado.", chave)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao apagar atributo '{}': {:?}", chave, e);
+            Ok(flash::redirect_error(&session, "/admin/atributos", e.user_message()).await)
+        }
+    }
+}
+
+// --- Passagem de Ano ---
+
+/// Último ano do curso por defeito (a presença trabalha com 1º a 3º ano).
+const ANO_FINAL_PADRAO: i64 = 3;
+
+/// Handler para GET /admin/rollover - Pré-visualização (dry-run) da passagem de ano
+pub async fn show_rollover_page(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Query(params): Query<RolloverParams>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    let ano_final = params.ano_final.unwrap_or(ANO_FINAL_PADRAO).clamp(1, 5);
+    tracing::debug!("GET /admin/rollover: pré-visualização com ano final {}", ano_final);
+
+    let template = AdminRolloverPage {
+        ano_final,
+        preview: user_service::preview_rollover(&state.db_pool, organizacao_id, ano_final).await?,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminRolloverPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/rollover - Executa a passagem de ano (uma transação + registo de auditoria)
+pub async fn handle_rollover(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Form(form): Form<RolloverForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/rollover: ano final {}", form.ano_final);
+
+    let base_url = format!("/admin/rollover?ano_final={}", form.ano_final);
+    if form.confirmacao.trim() != "CONFIRMAR" {
+        return Ok(flash::redirect_error(&session, &base_url, "Escreva CONFIRMAR para executar a passagem de ano.").await);
+    }
+    if !(1..=5).contains(&form.ano_final) {
+        return Ok(flash::redirect_error(&session, &base_url, "Ano final inválido (1 a 5).").await);
+    }
+
+    match user_service::executar_rollover(&state.db_pool, organizacao_id, form.ano_final).await {
+        Ok((promovidos, arquivados)) => {
+            let detalhes = format!(
+                "ano final: {}; promovidos: {}; arquivados: {}",
+                form.ano_final, promovidos, arquivados
+            );
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_ROLLOVER, None, Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(&session, &base_url, format!(
+                "Passagem de ano concluída: {} promovido(s), {} arquivado(s).",
+                promovidos, arquivados
+            )).await)
+        }
+        Err(e) => {
+            tracing::error!("Erro na passagem de ano: {:?}", e);
+            Ok(flash::redirect_error(&session, &base_url, "Erro na base de dados; nenhuma alteração foi feita.").await)
+        }
+    }
+}
+
+// --- Contabilidade dos serviços ---
+
+/// Lançamentos mostrados na página da contabilidade.
+const LIMITE_LANCAMENTOS: i64 = 200;
+
+/// Handler para GET /admin/contabilidade - Livro de serviços e relatório de reconciliação
+/// (utilizadores com contadores diferentes das somas do livro)
+pub async fn show_contabilidade_page(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    Query(params): Query<ContabilidadeParams>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/contabilidade: Carregando livro de serviços...");
+
+    let user = campo_opcional(&params.user);
+    let template = AdminContabilidadePage {
+        divergencias: contabilidade_service::divergencias(&state.db_pool, organizacao_id).await?,
+        lancamentos: contabilidade_service::lancamentos(&state.db_pool, organizacao_id, user, LIMITE_LANCAMENTOS).await?,
+        user: user.unwrap_or_default().to_string(),
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminContabilidadePage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/contabilidade/reconciliar - Reescreve os contadores divergentes com as somas do livro
+pub async fn handle_reconciliar(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/contabilidade/reconciliar");
+
+    match contabilidade_service::reconciliar(&state.db_pool, organizacao_id).await {
+        Ok(0) => Ok(flash::redirect_success(&session, "/admin/contabilidade", "Os contadores já estavam de acordo com o livro.").await),
+        Ok(corrigidos) => {
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_CONTADORES_RECONCILIADOS, None,
+                Some(&format!("{} utilizador(es)", corrigidos)),
+            ).await;
+            Ok(flash::redirect_success(&session, "/admin/contabilidade", format!(
+                "Contadores de {} utilizador(es) reconciliados com o livro.", corrigidos
+            )).await)
+        }
+        Err(e) => {
+            tracing::error!("Erro ao reconciliar os contadores: {:?}", e);
+            Ok(flash::redirect_error(&session, "/admin/contabilidade", "Erro na base de dados; nenhuma alteração foi feita.").await)
+        }
+    }
+}
+
+// --- Auditoria ---
+
+/// Handler para GET /admin/logins - Histórico de logins com filtros (utilizador, IP, resultado, datas)
+pub async fn show_logins_page(
+    State(state): State<AppState>,
+    Query(filtro): Query<LoginFilter>,
+    paginacao: Paginacao,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/logins: filtros {:?}, página {}", filtro, paginacao.pagina);
+
+    let (registos, total) =
+        login_history_service::listar(&state.db_pool, &filtro, paginacao.limite(), paginacao.offset()).await?;
+
+    let template = AdminLoginsPage {
+        registos,
+        paginacao: paginacao.navegacao(total),
+        filtro_user: filtro.user.unwrap_or_default(),
+        filtro_ip: filtro.ip.unwrap_or_default(),
+        filtro_resultado: filtro.resultado.unwrap_or_default(),
+        filtro_de: filtro.de.unwrap_or_default(),
+        filtro_ate: filtro.ate.unwrap_or_default(),
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminLoginsPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+// --- Bloqueios de Contas ---
+
+/// Handler para GET /admin/bloqueios - Contas bloqueadas (pela administração ou por tentativas falhadas) e IPs bloqueados
+pub async fn show_bloqueios_page(
+    State(state): State<AppState>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/bloqueios: Carregando página...");
+
+    let template = AdminBloqueiosPage {
+        manuais: bloqueio_service::bloqueios_manuais(&state.db_pool).await?,
+        users: bloqueio_service::users_bloqueados(&state.db_pool).await?,
+        ips: bloqueio_service::ips_bloqueados(&state.db_pool).await?,
+        janela_minutos: bloqueio_service::JANELA_MINUTOS,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminBloqueiosPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/bloqueios/bloquear - Bloqueia uma conta (ex: medida disciplinar) e termina as suas sessões
+pub async fn handle_bloquear_conta(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Form(form): Form<BloquearContaForm>,
+) -> AppResult<Redirect> {
+    let user_id = form.user_id.trim();
+    tracing::info!("POST /admin/bloqueios/bloquear: {}", user_id);
+
+    if user_id.eq_ignore_ascii_case(&actor.0) {
+        return Ok(flash::redirect_error(&session, "/admin/bloqueios", "Não pode bloquear a própria conta.").await);
+    }
+    let dias = match form.dias.trim() {
+        "" => None,
+        d => match d.parse::<i64>() {
+            Ok(d) => Some(d),
+            Err(_) => return Ok(flash::redirect_error(&session, "/admin/bloqueios", "Duração inválida.").await),
+        },
+    };
+
+    match bloqueio_service::bloquear(&state.db_pool, user_id, &form.motivo, &actor.0, dias).await {
+        Ok(()) => {
+            let terminadas = invalidar_sessoes(&state, &session, &actor, user_id).await;
+            let duracao = dias.map_or("sem data de fim".to_string(), |d| format!("{} dia(s)", d));
+            let detalhes = format!("motivo: '{}'; {}; {} sessões terminadas", form.motivo.trim(), duracao, terminadas);
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_USER_BLOQUEADO, Some(user_id), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(&session, "/admin/bloqueios", format!("Conta '{}' bloqueada ({}).", user_id, duracao)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao bloquear a conta {}: {:?}", user_id, e);
+            Ok(flash::redirect_error(&session, "/admin/bloqueios", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/bloqueios/{user_id}/desbloquear - Retira o bloqueio da administração e as falhas de login
+pub async fn handle_desbloquear_conta(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Path(user_id): Path<String>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/bloqueios/{}/desbloquear", user_id);
+
+    match bloqueio_service::desbloquear(&state.db_pool, &user_id).await {
+        Ok(true) => {
+            audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_USER_DESBLOQUEADO, Some(&user_id), None).await;
+            Ok(flash::redirect_success(&session, "/admin/bloqueios", format!("Conta '{}' desbloqueada.", user_id)).await)
+        }
+        Ok(false) => Ok(flash::redirect_error(&session, "/admin/bloqueios", format!("A conta '{}' não estava bloqueada.", user_id)).await),
+        Err(e) => {
+            tracing::warn!("Erro ao desbloquear a conta {}: {:?}", user_id, e);
+            Ok(flash::redirect_error(&session, "/admin/bloqueios", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/bloqueios/ip/desbloquear - Limpa as falhas de login de um IP (ex: rede partilhada)
+pub async fn handle_desbloquear_ip(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Form(form): Form<DesbloquearIpForm>,
+) -> AppResult<Redirect> {
+    let ip = form.ip.trim();
+    tracing::info!("POST /admin/bloqueios/ip/desbloquear: {}", ip);
+
+    match bloqueio_service::limpar_ip(&state.db_pool, ip).await {
+        Ok(0) => Ok(flash::redirect_error(&session, "/admin/bloqueios", format!("O IP {} não estava bloqueado.", ip)).await),
+        Ok(falhas) => {
+            let detalhes = format!("{} falhas apagadas", falhas);
+            audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_IP_DESBLOQUEADO, Some(ip), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/admin/bloqueios", format!("IP {} desbloqueado.", ip)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao desbloquear o IP {}: {:?}", ip, e);
+            Ok(flash::redirect_error(&session, "/admin/bloqueios", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para GET /admin/audit - Lista o registo de auditoria com filtros
+pub async fn show_audit_page(
+    State(state): State<AppState>,
+    Query(filtro): Query<AuditFilter>,
+    paginacao: Paginacao,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/audit: filtros {:?}, página {}", filtro, paginacao.pagina);
+
+    let (entries, total) = audit_service::listar(&state.db_pool, &filtro, paginacao.limite(), paginacao.offset()).await?;
+
+    let template = AdminAuditPage {
+        entries,
+        paginacao: paginacao.navegacao(total),
+        acoes: audit_service::ACOES,
+        filtro_actor: filtro.actor.unwrap_or_default(),
+        filtro_acao: filtro.acao.unwrap_or_default(),
+        filtro_alvo: filtro.alvo.unwrap_or_default(),
+        filtro_de: filtro.de.unwrap_or_default(),
+        filtro_ate: filtro.ate.unwrap_or_default(),
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminAuditPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+
+// --- Webhooks ---
+
+/// Entregas mostradas no registo da página de webhooks.
+const LIMITE_ENTREGAS: i64 = 100;
+
+/// Handler para GET /admin/webhooks - Lista os webhooks e o registo de entregas
+pub async fn show_webhooks_page(
+    State(state): State<AppState>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/webhooks: Carregando página...");
+
+    let template = AdminWebhooksPage {
+        webhooks: webhook_service::listar(&state.db_pool).await?,
+        entregas: webhook_service::entregas_recentes(&state.db_pool, LIMITE_ENTREGAS).await?,
+        eventos: webhook_service::EVENTOS,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminWebhooksPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/webhooks/create - Regista um webhook (o segredo é mostrado uma vez)
+pub async fn handle_create_webhook(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Form(form): Form<CreateWebhookForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/webhooks/create: {} ({:?})", form.url, form.eventos);
+
+    match webhook_service::criar(&state.db_pool, &form.url, &form.segredo, &form.eventos, &actor.0).await {
+        Ok(segredo) => {
+            let detalhes = format!("eventos: {}", form.eventos.join(", "));
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_WEBHOOK_CRIADO, Some(form.url.trim()), Some(&detalhes),
+            ).await;
+            Ok(flash::redirect_success(&session, "/admin/webhooks", format!(
+                "Webhook criado. Segredo da assinatura (guarde-o agora, não volta a ser mostrado): {}",
+                segredo
+            )).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao criar webhook para '{}': {:?}", form.url, e);
+            Ok(flash::redirect_error(&session, "/admin/webhooks", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/webhooks/{id}/ativo - Ativa ou desativa um webhook
+pub async fn handle_webhook_ativo(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Path(id): Path<i64>,
+    Form(form): Form<WebhookAtivoForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/webhooks/{}/ativo: {}", id, form.ativo);
+
+    match webhook_service::definir_ativo(&state.db_pool, id, form.ativo).await {
+        Ok(()) => {
+            let estado = if form.ativo { "ativado" } else { "desativado" };
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_WEBHOOK_ALTERADO, Some(&id.to_string()), Some(estado),
+            ).await;
+            Ok(flash::redirect_success(&session, "/admin/webhooks", format!("Webhook {} {}.", id, estado)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao alterar o webhook {}: {:?}", id, e);
+            Ok(flash::redirect_error(&session, "/admin/webhooks", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/webhooks/{id}/delete - Apaga um webhook e o registo das entregas
+pub async fn handle_delete_webhook(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/webhooks/{}/delete", id);
+
+    match webhook_service::remover(&state.db_pool, id).await {
+        Ok(url) => {
+            audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_WEBHOOK_REMOVIDO, Some(&url), None).await;
+            Ok(flash::redirect_success(&session, "/admin/webhooks", format!("Webhook para {} apagado.", url)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao apagar o webhook {}: {:?}", id, e);
+            Ok(flash::redirect_error(&session, "/admin/webhooks", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /admin/webhooks/entregas/{id}/reenviar - Volta a pôr na fila uma entrega falhada
+pub async fn handle_reenviar_entrega(
+    State(state): State<AppState>,
+    session: Session,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/webhooks/entregas/{}/reenviar", id);
+
+    match webhook_service::reenviar(&state.db_pool, id).await {
+        Ok(()) => Ok(flash::redirect_success(&session, "/admin/webhooks", format!("Entrega {} de novo na fila.", id)).await),
+        Err(e) => {
+            tracing::warn!("Erro ao reenviar a entrega {}: {:?}", id, e);
+            Ok(flash::redirect_error(&session, "/admin/webhooks", e.user_message()).await)
+        }
+    }
+}
+
+
+// --- Cópias de segurança ---
+
+/// Cópias mostradas no registo da página.
+const LIMITE_BACKUPS: i64 = 50;
+
+/// Handler para GET /admin/backups - Configuração e registo das cópias de segurança
+pub async fn show_backups_page(
+    State(state): State<AppState>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/backups: Carregando página...");
+
+    let config = backup_service::config();
+    let template = AdminBackupsPage {
+        backups: backup_service::listar(&state.db_pool, LIMITE_BACKUPS).await?,
+        dir: config.dir.display().to_string(),
+        intervalo_horas: config.intervalo_horas,
+        manter: config.manter,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminBackupsPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/backups/criar - Faz uma cópia de segurança agora
+pub async fn handle_criar_backup(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/backups/criar por {}", actor.0);
+
+    match backup_service::criar(&state.db_pool, backup_service::ORIGEM_MANUAL, Some(&actor.0)).await {
+        Ok(backup) => {
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_BACKUP_CRIADO, Some(&backup.ficheiro), Some(&backup.tamanho_legivel()),
+            ).await;
+            Ok(flash::redirect_success(
+                &session,
+                "/admin/backups",
+                format!("Cópia de segurança {} criada ({}).", backup.ficheiro, backup.tamanho_legivel()),
+            ).await)
+        }
+        Err(e) => Ok(flash::redirect_error(&session, "/admin/backups", e.user_message()).await),
+    }
+}
+
+/// Handler para GET /admin/backups/{id}/download - Descarrega uma cópia (contém os dados todos: fica na auditoria)
+pub async fn handle_download_backup(
+    State(state): State<AppState>,
+    Extension(actor): Extension<UserId>,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    tracing::info!("GET /admin/backups/{}/download por {}", id, actor.0);
+
+    let (ficheiro, bytes) = backup_service::conteudo(&state.db_pool, id).await?;
+    audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_BACKUP_DESCARREGADO, Some(&ficheiro), None).await;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/vnd.sqlite3".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", ficheiro)),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+
+// --- Tarefas periódicas ---
+
+/// Handler para GET /admin/tarefas - Tarefas do agendador: horário, última execução e próxima prevista
+pub async fn show_tarefas_page(
+    State(state): State<AppState>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/tarefas: Carregando página...");
+
+    let template = AdminTarefasPage {
+        tarefas: agendador_service::listar(&state.db_pool).await?,
+        fuso: tempo::fuso().to_string(),
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminTarefasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /admin/tarefas/{nome}/executar - Executa uma tarefa já (espera pelo fim)
+pub async fn handle_executar_tarefa(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Path(nome): Path<String>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/tarefas/{}/executar por {}", nome, actor.0);
+
+    match agendador_service::executar_agora(&state.db_pool, &nome).await {
+        Ok(resumo) => {
+            audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_TAREFA_EXECUTADA, Some(&nome), Some(&resumo)).await;
+            Ok(flash::redirect_success(&session, "/admin/tarefas", format!("Tarefa '{}' executada: {}", nome, resumo)).await)
+        }
+        Err(e @ (AppError::NotFound(_) | AppError::Conflict(_))) => {
+            Ok(flash::redirect_error(&session, "/admin/tarefas", e.user_message()).await)
+        }
+        Err(e) => {
+            // A falha fica registada na tabela da tarefa
+            audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_TAREFA_EXECUTADA, Some(&nome), Some("falhou")).await;
+            tracing::warn!("Execução manual da tarefa '{}' falhou: {:?}", nome, e);
+            Ok(flash::redirect_error(&session, "/admin/tarefas", format!("A tarefa '{}' falhou (ver o resultado na tabela).", nome)).await)
+        }
+    }
+}
+
+// --- Retenção dos registos ---
+
+/// Meses de resumos mostrados na página de retenção (linhas).
+const LIMITE_RESUMOS: i64 = 200;
+
+/// Handler para GET /admin/retencao - Prazos de retenção, o que a próxima limpeza apagaria (simulação)
+/// e os totais do que já foi apagado. A limpeza é a tarefa `retencao.purga` (/admin/tarefas).
+pub async fn show_retencao_page(
+    State(state): State<AppState>,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/retencao: Carregando página...");
+
+    let config = &state.config.retencao;
+    let template = AdminRetencaoPage {
+        politicas: retencao_service::relatorio(&state.db_pool, config).await?,
+        resumos: retencao_service::resumos(&state.db_pool, LIMITE_RESUMOS).await?,
+        simular: config.simular,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminRetencaoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+// --- Exportação / importação de dados ---
+
+/// Handler para GET /admin/dados - Exportar e importar todos os dados (pacote JSON)
+pub async fn show_dados_page(
+    State(state): State<AppState>,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /admin/dados: Carregando página...");
+
+    let template = AdminDadosPage {
+        contagens: dados_service::contagens(&state.db_pool).await?,
+        versao: dados_service::VERSAO,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminDadosPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /admin/dados/export.json - Descarrega o pacote com todos os dados (fica na auditoria)
+pub async fn handle_exportar_dados(
+    State(state): State<AppState>,
+    Extension(actor): Extension<UserId>,
+) -> AppResult<Response> {
+    tracing::info!("GET /admin/dados/export.json por {}", actor.0);
+
+    let pacote = dados_service::exportar(&state.db_pool).await?;
+    let json = serde_json::to_vec_pretty(&pacote).map_err(|e| {
+        tracing::error!("Erro ao serializar o pacote de dados: {}", e);
+        AppError::InternalServerError
+    })?;
+    let ficheiro = format!("mercal2-dados-{}.json", tempo::agora().format("%Y%m%d-%H%M%S"));
+    let resumo = resumo_contagens(&pacote.contagens());
+    audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_DADOS_EXPORTADOS, Some(&ficheiro), Some(&resumo)).await;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", ficheiro)),
+        ],
+        json,
+    )
+        .into_response())
+}
+
+/// Handler para POST /admin/dados/import - Importa um pacote (enviado como corpo JSON pela página)
+pub async fn handle_importar_dados(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    corpo: String,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /admin/dados/import por {} ({} bytes)", actor.0, corpo.len());
+
+    let resultado = match dados_service::ler_pacote(&state.db_pool, &corpo).await {
+        Ok(pacote) => dados_service::importar(&state.db_pool, &pacote).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(importadas) => {
+            // Roles e atribuições podem ter mudado
+            permission_service::invalidar_cache(&state.permissions).await;
+            let resumo = resumo_contagens(&importadas);
+            audit_service::registar(&state.db_pool, &actor.0, audit_service::ACAO_DADOS_IMPORTADOS, None, Some(&resumo)).await;
+            Ok(flash::redirect_success(&session, "/admin/dados", format!("Dados importados: {}.", resumo)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Importação de dados recusada: {}", e);
+            Ok(flash::redirect_error(&session, "/admin/dados", e.user_message()).await)
+        }
+    }
+}
+
+/// "tabela: n, ..." (apenas as tabelas com linhas).
+fn resumo_contagens(contagens: &[(String, usize)]) -> String {
+    let partes: Vec<String> = contagens
+        .iter()
+        .filter(|(_, n)| *n > 0)
+        .map(|(tabela, n)| format!("{}: {}", tabela, n))
+        .collect();
+    if partes.is_empty() {
+        "nenhuma linha".to_string()
+    } else {
+        partes.join(", ")
+    }
+}
+
+
+// --- Organizações (super-admin) ---
+
+/// Handler para GET /superadmin - Lista as organizações da instância
+pub async fn show_organizacoes_page(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    flash: Flash,
+) -> AppResult<impl IntoResponse> {
+    tracing::debug!("GET /superadmin: Carregando organizações...");
+
+    let template = SuperadminOrganizacoesPage {
+        organizacoes: organizacao_service::listar(&state.db_pool).await?,
+        atual: organizacao_id,
+        manutencao: manutencao_service::atual(),
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template SuperadminOrganizacoesPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /superadmin/organizacoes/create - Cria uma organização (sem utilizadores;
+/// os admins são criados em /admin/users depois de entrar nela)
+pub async fn handle_create_organizacao(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Form(form): Form<CreateOrganizacaoForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /superadmin/organizacoes/create: {} ({})", form.nome, form.codigo);
+
+    match organizacao_service::criar(&state.db_pool, &form.codigo, &form.nome).await {
+        Ok(id) => {
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_ORGANIZACAO_CRIADA, Some(&id.to_string()), Some(form.nome.trim()),
+            ).await;
+            Ok(flash::redirect_success(&session, "/superadmin", format!(
+                "Organização '{}' criada. Entre nela para criar os seus administradores.",
+                form.nome.trim()
+            )).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao criar a organização '{}': {:?}", form.codigo, e);
+            Ok(flash::redirect_error(&session, "/superadmin", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /superadmin/organizacoes/{id}/ativa - Ativa ou desativa uma organização
+pub async fn handle_organizacao_ativa(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Path(id): Path<i64>,
+    Form(form): Form<OrganizacaoAtivaForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /superadmin/organizacoes/{}/ativa: {}", id, form.ativa);
+
+    match organizacao_service::definir_ativa(&state.db_pool, id, form.ativa).await {
+        Ok(nome) => {
+            let estado = if form.ativa { "ativada" } else { "desativada" };
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_ORGANIZACAO_ALTERADA, Some(&id.to_string()), Some(estado),
+            ).await;
+            Ok(flash::redirect_success(&session, "/superadmin", format!("Organização '{}' {}.", nome, estado)).await)
+        }
+        Err(e) => {
+            tracing::warn!("Erro ao alterar a organização {}: {:?}", id, e);
+            Ok(flash::redirect_error(&session, "/superadmin", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /superadmin/manutencao - Liga ou desliga o modo de manutenção (só leitura
+/// para todos exceto os super-admins; ver `web::mw_manutencao`)
+pub async fn handle_manutencao(
+    State(state): State<AppState>,
+    session: Session,
+    Extension(actor): Extension<UserId>,
+    Form(form): Form<ManutencaoForm>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /superadmin/manutencao: {}", form.ativa);
+
+    let resultado = if form.ativa {
+        manutencao_service::ativar(&state.db_pool, &form.motivo, &actor.0).await
+    } else {
+        manutencao_service::desativar(&state.db_pool).await
+    };
+    match resultado {
+        Ok(()) => {
+            let estado = if form.ativa { "ativado" } else { "desativado" };
+            let detalhe = match form.motivo.trim() {
+                motivo if form.ativa && !motivo.is_empty() => format!("{}: {}", estado, motivo),
+                _ => estado.to_string(),
+            };
+            audit_service::registar(
+                &state.db_pool, &actor.0, audit_service::ACAO_MANUTENCAO_ALTERADA, None, Some(&detalhe),
+            ).await;
+            Ok(flash::redirect_success(&session, "/superadmin", format!("Modo de manutenção {}.", estado)).await)
+        }
+        Err(e) => {
+            tracing::error!("Erro ao alterar o modo de manutenção: {:?}", e);
+            Ok(flash::redirect_error(&session, "/superadmin", e.user_message()).await)
+        }
+    }
+}
+
+/// Handler para POST /superadmin/organizacoes/{id}/entrar - Passa a trabalhar noutra organização
+/// (guardada na sessão; as páginas de admin, escala e presença passam a mostrar os dados dela)
+pub async fn handle_entrar_organizacao(
+    State(state): State<AppState>,
+    session: Session,
+    Path(id): Path<i64>,
+) -> AppResult<Redirect> {
+    tracing::info!("POST /superadmin/organizacoes/{}/entrar", id);
+
+    let Some(organizacao) = organizacao_service::find(&state.db_pool, id).await? else {
+        return Ok(flash::redirect_error(&session, "/superadmin", format!("Organização {} não encontrada.", id)).await);
+    };
+
+    if let Err(e) = session.insert(ORGANIZACAO_KEY, organizacao.id).await {
+        tracing::error!("Erro ao guardar a organização na sessão: {:?}", e);
+        return Err(AppError::InternalServerError);
+    }
+
+    Ok(flash::redirect_success(&session, "/admin", format!("A trabalhar na organização '{}'.", organizacao.nome)).await)
+}
@@ -0,0 +1,268 @@
+// src/web/agenda_handlers.rs
+//! Agenda institucional (/agenda): todos os utilizadores consultam as formaturas, cerimónias e palestras;
+//! criar, alterar e apagar exige a permissão "agenda". Os eventos aparecem também nos painéis, no feed
+//! iCal e na API.
+
+use crate::{
+    error::{AppError, AppResult, FieldError},
+    models::agenda::DadosEvento,
+    services::{agenda_service, audit_service, permission_service, user_service},
+    state::AppState,
+    templates::AgendaPage,
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::{Duration, NaiveDate};
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Dias mostrados a partir de `?desde=`.
+const PERIODO_DIAS: i64 = 90;
+/// Tamanho máximo do título e do local.
+const MAX_TITULO: usize = 120;
+/// Tamanho máximo da descrição.
+const MAX_DESCRICAO: usize = 1000;
+
+#[derive(Deserialize, Debug)]
+pub struct AgendaQuery {
+    desde: Option<String>, // Primeiro dia mostrado (omissão: hoje)
+    editar: Option<i64>,   // Preenche o formulário com este evento
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EventoForm {
+    #[serde(default)]
+    tipo: String,
+    #[serde(default)]
+    titulo: String,
+    #[serde(default)]
+    local: String,
+    #[serde(default)]
+    descricao: String,
+    #[serde(default)]
+    data: String,
+    #[serde(default)]
+    hora_inicio: String,
+    #[serde(default)]
+    hora_fim: String,
+    #[serde(default)]
+    anos: Vec<String>, // Checkboxes (nenhum = todos os anos)
+    obrigatorio: Option<String>,    // Checkbox: presente = marcada
+    presenca_turma: Option<String>, // Checkbox: presente = marcada
+}
+
+impl EventoForm {
+    /// Valida o formulário e devolve os dados do evento.
+    fn validar(&self) -> AppResult<DadosEvento> {
+        let mut v = Validador::default();
+        v.obrigatorio("titulo", &self.titulo, MAX_TITULO);
+        if self.local.trim().chars().count() > MAX_TITULO {
+            v.erro("local", format!("Máximo de {} caracteres.", MAX_TITULO));
+        }
+        if self.descricao.trim().chars().count() > MAX_DESCRICAO {
+            v.erro("descricao", format!("Máximo de {} caracteres.", MAX_DESCRICAO));
+        }
+        let data = v.data("data", &self.data);
+        let inicio = v.hora("hora_inicio", &self.hora_inicio);
+        let fim = match self.hora_fim.trim() {
+            "" => None,
+            hora => v.hora("hora_fim", hora),
+        };
+        let anos: Vec<i64> = self.anos.iter().filter_map(|a| a.trim().parse().ok()).collect();
+        if anos.len() != self.anos.len() {
+            v.erro("anos", "Ano inválido.");
+        }
+        v.resultado()?;
+        let (Some(data), Some(inicio)) = (data, inicio) else {
+            return Err(AppError::validation("data", "Data inválida."));
+        };
+        Ok(DadosEvento {
+            tipo: self.tipo.clone(),
+            titulo: self.titulo.clone(),
+            local: self.local.clone(),
+            descricao: self.descricao.clone(),
+            data: data.to_string(),
+            hora_inicio: inicio.format("%H:%M").to_string(),
+            hora_fim: fim.map(|f| f.format("%H:%M").to_string()),
+            anos,
+            obrigatorio: self.obrigatorio.is_some(),
+            presenca_turma: self.presenca_turma.is_some(),
+        })
+    }
+
+    /// Formulário reapresentado com os erros e os valores enviados.
+    fn com_erros(self, erros: Vec<FieldError>) -> FormState {
+        let mut form = FormState::com_erros(erros)
+            .com_valor("tipo", self.tipo)
+            .com_valor("titulo", self.titulo)
+            .com_valor("local", self.local)
+            .com_valor("descricao", self.descricao)
+            .com_valor("data", self.data)
+            .com_valor("hora_inicio", self.hora_inicio)
+            .com_valor("hora_fim", self.hora_fim)
+            .com_valor("anos", self.anos.join(","));
+        if self.obrigatorio.is_some() {
+            form = form.com_valor("obrigatorio", "1");
+        }
+        if self.presenca_turma.is_some() {
+            form = form.com_valor("presenca_turma", "1");
+        }
+        form
+    }
+}
+
+/// Falha (403) se o utilizador não pode gerir a agenda.
+async fn exigir_gestao(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if atual.tem_permissao(state, permission_service::PERM_AGENDA).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Agenda: {} sem permissão '{}'.", atual.id, permission_service::PERM_AGENDA);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Renderiza os eventos a partir de `desde` e, a quem gere a agenda, o formulário (novo evento ou
+/// alteração de `editar`).
+async fn pagina_agenda(
+    state: &AppState,
+    atual: &CurrentUser,
+    desde: NaiveDate,
+    editar: Option<i64>,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let ano = user_service::find_user_by_id(&state.db_leitura, &atual.id).await?.map(|u| u.ano);
+    let template = AgendaPage {
+        eventos: agenda_service::listar(&state.db_leitura, organizacao_id, desde, desde + Duration::days(PERIODO_DIAS)).await?,
+        desde: desde.to_string(),
+        hoje: tempo::hoje().to_string(),
+        ano,
+        pode_gerir: atual.tem_permissao(state, permission_service::PERM_AGENDA).await?,
+        editar,
+        tipos: agenda_service::TIPOS,
+        max_ano: agenda_service::MAX_ANO,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AgendaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /agenda?desde=&editar= - Próximos eventos (e gestão, com a permissão)
+pub async fn show_agenda(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<AgendaQuery>,
+) -> AppResult<Response> {
+    let desde = params.desde.as_deref().and_then(tempo::ler_data).unwrap_or_else(tempo::hoje);
+    // Alteração: o formulário vem preenchido com o evento
+    let mut form = FormState::default();
+    if let Some(id) = params.editar {
+        exigir_gestao(&state, &atual).await?;
+        let evento = agenda_service::obter(&state.db_leitura, atual.organizacao_id, id).await?;
+        form = form
+            .com_valor("tipo", evento.tipo)
+            .com_valor("titulo", evento.titulo)
+            .com_valor("local", evento.local)
+            .com_valor("descricao", evento.descricao)
+            .com_valor("data", evento.data)
+            .com_valor("hora_inicio", evento.hora_inicio)
+            .com_valor("hora_fim", evento.hora_fim.unwrap_or_default())
+            .com_valor("anos", evento.anos);
+        if evento.obrigatorio {
+            form = form.com_valor("obrigatorio", "1");
+        }
+        if evento.presenca_turma {
+            form = form.com_valor("presenca_turma", "1");
+        }
+    }
+    pagina_agenda(&state, &atual, desde, params.editar, StatusCode::OK, form, flash).await
+}
+
+/// Handler para POST /agenda - Cria um evento
+pub async fn handle_criar_evento(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<EventoForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let resultado = match form.validar() {
+        Ok(dados) => agenda_service::criar(&state.db_pool, atual.organizacao_id, &dados, &atual.id).await.map(|id| (id, dados)),
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((id, dados)) => {
+            let detalhes = format!("{} {} ({} {})", agenda_service::descrever_tipo(&dados.tipo), dados.titulo.trim(), dados.data, dados.hora_inicio);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_EVENTO_CRIADO, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Evento '{}' criado para {}.", dados.titulo.trim(), dados.data);
+            Ok(flash::redirect_success(&session, "/agenda", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = form.com_erros(erros);
+            pagina_agenda(&state, &atual, tempo::hoje(), None, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /agenda/{id} - Altera um evento
+pub async fn handle_atualizar_evento(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<EventoForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let resultado = match form.validar() {
+        Ok(dados) => agenda_service::atualizar(&state.db_pool, atual.organizacao_id, id, &dados).await.map(|anterior| (anterior, dados)),
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((anterior, dados)) => {
+            let detalhes = format!(
+                "{} ({} {}) -> {} ({} {})",
+                anterior.titulo, anterior.data, anterior.hora_inicio, dados.titulo.trim(), dados.data, dados.hora_inicio
+            );
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_EVENTO_ALTERADO, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/agenda", format!("Evento '{}' atualizado.", dados.titulo.trim())).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = form.com_erros(erros);
+            pagina_agenda(&state, &atual, tempo::hoje(), Some(id), StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /agenda/{id}/remover - Apaga um evento cancelado
+pub async fn handle_remover_evento(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let evento = agenda_service::remover(&state.db_pool, atual.organizacao_id, id).await?;
+    let detalhes = format!("{} ({} {})", evento.titulo, evento.data, evento.hora_inicio);
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_EVENTO_APAGADO, Some(&id.to_string()), Some(&detalhes)).await;
+    Ok(flash::redirect_success(&session, "/agenda", format!("Evento '{}' apagado.", evento.titulo)).await.into_response())
+}
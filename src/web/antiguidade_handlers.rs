@@ -0,0 +1,102 @@
+// src/web/antiguidade_handlers.rs
+//! Antiguidade dos utilizadores de cada ano (/admin/antiguidade): a administração cola a lista de IDs
+//! pela ordem oficial (o primeiro é o mais antigo). Desempata a escala e ordena o efetivo impresso.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{antiguidade_service, audit_service},
+    state::AppState,
+    templates::AdminAntiguidadePage,
+    validation::FormState,
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+#[derive(Deserialize, Debug)]
+pub struct AntiguidadeQuery {
+    ano: Option<i64>, // Ausente = o primeiro ano com utilizadores
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AntiguidadeForm {
+    ano: i64,
+    #[serde(default)]
+    ordem: String, // IDs separados por linhas, espaços ou vírgulas
+}
+
+/// Renderiza a antiguidade de um ano (o primeiro com utilizadores, se não for indicado).
+async fn pagina_antiguidade(
+    state: &AppState,
+    organizacao_id: i64,
+    ano: Option<i64>,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let anos = antiguidade_service::anos(&state.db_leitura, organizacao_id).await?;
+    let ano = ano.or_else(|| anos.first().copied()).unwrap_or(1);
+    let template = AdminAntiguidadePage {
+        posicoes: antiguidade_service::listar(&state.db_leitura, organizacao_id, ano).await?,
+        anos,
+        ano,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminAntiguidadePage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /admin/antiguidade?ano= - Antiguidade de um ano
+pub async fn show_antiguidade(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<AntiguidadeQuery>,
+) -> AppResult<Response> {
+    pagina_antiguidade(&state, atual.organizacao_id, params.ano, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /admin/antiguidade - Define a antiguidade de um ano pela ordem dos IDs
+pub async fn handle_definir_antiguidade(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<AntiguidadeForm>,
+) -> AppResult<Response> {
+    let ordem: Vec<String> = form
+        .ordem
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect();
+    match antiguidade_service::definir(&state.db_pool, atual.organizacao_id, form.ano, &ordem).await {
+        Ok((ordenados, sem_posicao)) => {
+            let detalhes = format!("{}º ano: {} ordenados, {} sem posição", form.ano, ordenados, sem_posicao);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_ANTIGUIDADE_DEFINIDA, Some(&form.ano.to_string()), Some(&detalhes)).await;
+            let mensagem = match sem_posicao {
+                0 => format!("Antiguidade do {}º ano guardada ({} utilizadores).", form.ano, ordenados),
+                n => format!("Antiguidade do {}º ano guardada: {} ordenados, {} sem posição.", form.ano, ordenados, n),
+            };
+            Ok(flash::redirect_success(&session, &format!("/admin/antiguidade?ano={}", form.ano), mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros).com_valor("ordem", form.ordem);
+            pagina_antiguidade(&state, atual.organizacao_id, Some(form.ano), StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,183 @@
+// src/web/api_auth_handlers.rs
+//! Autenticação da aplicação móvel (/api/auth): login com ID e senha que devolve um access token (JWT)
+//! e um refresh token, renovação e logout. Rotas públicas; os tokens emitidos são aceites por `mw_api`
+//! em todas as rotas /api/*. Ver `jwt_service`.
+
+use crate::{
+    error::{ApiResult, AppError, AppResult, EnvelopeErro},
+    services::{auth_service, bloqueio_service, jwt_service, login_history_service, user_service},
+    state::AppState,
+    validation::{validar, Validador, Validate},
+    web::api_v1_handlers::ApiJson,
+};
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use utoipa::ToSchema;
+
+/// Credenciais do login.
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct LoginApiPayload {
+    pub id: String,
+    pub password: String,
+}
+
+impl Validate for LoginApiPayload {
+    fn validate(&self, v: &mut Validador) {
+        v.obrigatorio("id", &self.id, 64);
+        v.obrigatorio("password", &self.password, 256);
+    }
+}
+
+/// Refresh token a renovar ou revogar.
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct RefreshPayload {
+    pub refresh_token: String,
+}
+
+/// Tokens emitidos no login e em cada renovação.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct TokensApi {
+    /// JWT para `Authorization: Bearer` em /api/*
+    pub access_token: String,
+    /// Sempre "Bearer"
+    pub token_type: &'static str,
+    /// Validade do access token, em segundos
+    pub expires_in: i64,
+    /// Para obter um novo par em /api/auth/refresh (só pode ser usado uma vez)
+    pub refresh_token: String,
+}
+
+impl From<jwt_service::Tokens> for TokensApi {
+    fn from(tokens: jwt_service::Tokens) -> Self {
+        Self {
+            access_token: tokens.access_token,
+            token_type: "Bearer",
+            expires_in: tokens.expira_em_segundos,
+            refresh_token: tokens.refresh_token,
+        }
+    }
+}
+
+/// Recusa contas bloqueadas pela administração e, se houver política de validade, com a senha expirada
+/// (só pode ser alterada no site).
+async fn verificar_conta(state: &AppState, user_id: &str) -> AppResult<()> {
+    if bloqueio_service::bloqueio_manual(&state.db_pool, user_id).await?.is_some() {
+        tracing::warn!("API auth: {} recusado (conta bloqueada).", user_id);
+        return Err(AppError::Unauthorized);
+    }
+    if let Some(max_dias) = state.senha_max_dias {
+        if user_service::senha_expirada(&state.db_pool, user_id, max_dias).await? {
+            return Err(AppError::Conflict("A senha expirou. Altere-a no site antes de entrar na aplicação.".to_string()));
+        }
+    }
+    Ok(())
+}
+
+// POST /api/auth/login
+#[utoipa::path(post, path = "/api/auth/login", tag = "autenticacao",
+    summary = "Login da aplicação móvel (devolve access e refresh token)",
+    request_body = LoginApiPayload,
+    security(()),
+    responses(
+        (status = 200, body = TokensApi),
+        (status = 401, description = "ID ou senha inválidos", body = EnvelopeErro),
+        (status = 403, description = "Conta bloqueada pela administração", body = EnvelopeErro),
+        (status = 409, description = "Senha expirada", body = EnvelopeErro),
+        (status = 429, description = "Demasiadas tentativas falhadas", body = EnvelopeErro),
+    ))]
+pub async fn login(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    ApiJson(payload): ApiJson<LoginApiPayload>,
+) -> ApiResult<Json<TokensApi>> {
+    validar(&payload)?;
+    jwt_service::config_ativa()?;
+    let id = payload.id.trim();
+    let ip = addr.ip().to_string();
+    let user_agent = headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok());
+    let db = &state.db_pool;
+
+    // As mesmas proteções do login no site (o CAPTCHA não se aplica: o limite de falhas continua)
+    if let Some(minutos) = bloqueio_service::verificar(db, id, &ip).await? {
+        login_history_service::registar(db, id, &ip, user_agent, Some(login_history_service::MOTIVO_BLOQUEADO)).await;
+        return Err(AppError::TooManyRequests(format!(
+            "Demasiadas tentativas falhadas. Tente novamente dentro de {} minuto(s).", minutos
+        )).into());
+    }
+
+    let Some(user) = user_service::find_user_by_id(db, id).await? else {
+        login_history_service::registar(db, id, &ip, user_agent, Some(login_history_service::MOTIVO_USER_DESCONHECIDO)).await;
+        bloqueio_service::registar_falha(db, id, &ip).await?;
+        return Err(AppError::InvalidCredentials.into());
+    };
+    if !auth_service::verify_password(&payload.password, &user.password_hash, &user.password_esquema).await? {
+        login_history_service::registar(db, &user.id, &ip, user_agent, Some(login_history_service::MOTIVO_SENHA_INVALIDA)).await;
+        bloqueio_service::registar_falha(db, &user.id, &ip).await?;
+        return Err(AppError::InvalidCredentials.into());
+    }
+    if !user.ativo {
+        login_history_service::registar(db, &user.id, &ip, user_agent, Some(login_history_service::MOTIVO_CONTA_ARQUIVADA)).await;
+        return Err(AppError::InvalidCredentials.into());
+    }
+    auth_service::rehash_se_necessario(db, &user.id, &payload.password, &user.password_hash, &user.password_esquema).await;
+    if let Err(e) = verificar_conta(&state, &user.id).await {
+        if matches!(e, AppError::Unauthorized) {
+            login_history_service::registar(db, &user.id, &ip, user_agent, Some(login_history_service::MOTIVO_BLOQUEIO_ADMIN)).await;
+        }
+        return Err(e.into());
+    }
+
+    bloqueio_service::limpar_user(db, &user.id).await?;
+    login_history_service::registar(db, &user.id, &ip, user_agent, None).await;
+    let tokens = jwt_service::emitir(db, &user.id, user_agent).await?;
+    Ok(Json(tokens.into()))
+}
+
+// POST /api/auth/refresh
+#[utoipa::path(post, path = "/api/auth/refresh", tag = "autenticacao",
+    summary = "Troca o refresh token por um par novo",
+    description = "O refresh token usado deixa de ser válido. Reutilizar um refresh token já trocado revoga todos os tokens desse login.",
+    request_body = RefreshPayload,
+    security(()),
+    responses(
+        (status = 200, body = TokensApi),
+        (status = 401, description = "Refresh token inválido, expirado ou revogado", body = EnvelopeErro),
+        (status = 403, description = "Conta bloqueada pela administração", body = EnvelopeErro),
+    ))]
+pub async fn refresh(
+    State(state): State<AppState>,
+    ApiJson(payload): ApiJson<RefreshPayload>,
+) -> ApiResult<Json<TokensApi>> {
+    let (user_id, tokens) = jwt_service::renovar(&state.db_pool, payload.refresh_token.trim()).await?;
+    if let Err(e) = verificar_conta(&state, &user_id).await {
+        // O par acabado de emitir não chega a ser entregue
+        jwt_service::revogar(&state.db_pool, &tokens.refresh_token).await?;
+        return Err(e.into());
+    }
+    Ok(Json(tokens.into()))
+}
+
+// POST /api/auth/logout
+#[utoipa::path(post, path = "/api/auth/logout", tag = "autenticacao",
+    summary = "Revoga o refresh token (e os obtidos a partir do mesmo login)",
+    description = "O access token em uso continua válido até expirar.",
+    request_body = RefreshPayload,
+    security(()),
+    responses(
+        (status = 204, description = "Revogado (ou já não existia)"),
+    ))]
+pub async fn logout(
+    State(state): State<AppState>,
+    ApiJson(payload): ApiJson<RefreshPayload>,
+) -> ApiResult<StatusCode> {
+    if jwt_service::revogar(&state.db_pool, payload.refresh_token.trim()).await? {
+        tracing::info!("📱 Logout da aplicação (refresh token revogado).");
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
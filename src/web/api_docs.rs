@@ -0,0 +1,74 @@
+// src/web/api_docs.rs
+//! Especificação OpenAPI 3 da API JSON (/api/v1), gerada a partir das anotações `#[utoipa::path]`
+//! dos handlers. Servida em /api/openapi.json, com a Swagger UI em /api/docs (só para administradores).
+
+use crate::web::{api_auth_handlers, api_v1_handlers};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+/// Nome do cookie de sessão (o nome por omissão do tower-sessions).
+const COOKIE_SESSAO: &str = "id";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Mercal2 API",
+        description = "API JSON para integrações. Autenticação por token (`Authorization: Bearer <token>`, \
+                       criado em /user/tokens ou o JWT devolvido por /api/auth/login) ou pela sessão do browser. Os erros seguem sempre o envelope \
+                       `{\"error\": {\"code\", \"message\", \"fields\"}}`."
+    ),
+    paths(
+        api_auth_handlers::login,
+        api_auth_handlers::refresh,
+        api_auth_handlers::logout,
+        api_v1_handlers::me,
+        api_v1_handlers::listar_users,
+        api_v1_handlers::obter_user,
+        api_v1_handlers::listar_escala,
+        api_v1_handlers::gerar_escala,
+        api_v1_handlers::publicar_escala,
+        api_v1_handlers::listar_alocacoes,
+        api_v1_handlers::listar_trocas,
+        api_v1_handlers::solicitar_troca,
+        api_v1_handlers::responder_troca,
+        api_v1_handlers::aprovar_troca,
+        api_v1_handlers::listar_presenca,
+        api_v1_handlers::marcar_saida,
+        api_v1_handlers::marcar_retorno,
+        api_v1_handlers::listar_indisponibilidades,
+        api_v1_handlers::criar_indisponibilidade,
+        api_v1_handlers::remover_indisponibilidade,
+        api_v1_handlers::obter_cardapio,
+        api_v1_handlers::listar_semanas_cardapio,
+        api_v1_handlers::listar_agenda,
+    ),
+    modifiers(&Autenticacao),
+    security(("token" = []), ("sessao" = [])),
+    tags(
+        (name = "autenticacao", description = "Login da aplicação móvel (JWT e refresh token)"),
+        (name = "utilizadores", description = "Contas"),
+        (name = "escala", description = "Escala de serviço e alocações"),
+        (name = "trocas", description = "Pedidos de troca de serviço"),
+        (name = "presenca", description = "Controlo de saídas e retornos"),
+        (name = "indisponibilidades", description = "Baixas e dispensas"),
+        (name = "cardapio", description = "Cardápio semanal do rancho"),
+        (name = "agenda", description = "Agenda institucional (formaturas, cerimónias, palestras)"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Regista os dois esquemas de autenticação aceites por `mw_api`.
+struct Autenticacao;
+
+impl Modify for Autenticacao {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme("sessao", SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(COOKIE_SESSAO))));
+    }
+}
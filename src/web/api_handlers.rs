@@ -0,0 +1,50 @@
+// src/web/api_handlers.rs
+use crate::{
+    error::{AppError, AppResult},
+    models::user::UserSugestao,
+    services::{permission_service, user_service},
+    state::AppState,
+    web::mw_auth::{OrganizacaoId, UserId},
+};
+use axum::extract::{Extension, Json, Query, State};
+use serde::Deserialize;
+
+/// Número máximo de sugestões devolvidas pela pesquisa.
+const LIMITE_SUGESTOES: i64 = 10;
+/// Termos mais curtos não são pesquisados (evita devolver meia base de dados).
+const MIN_CARACTERES: usize = 2;
+
+#[derive(Deserialize, Debug)]
+pub struct SearchParams {
+    #[serde(default)]
+    q: String,
+}
+
+/// Handler para GET /api/users/search?q= - Sugestões de utilizadores (id, nome, turma) para autocomplete
+pub async fn search_users(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    Query(params): Query<SearchParams>,
+) -> AppResult<Json<Vec<UserSugestao>>> {
+    let pode_pesquisar = permission_service::user_has_permission(
+        &state.db_pool,
+        &state.permissions,
+        &user_id.0,
+        permission_service::PERM_USERS_PESQUISAR,
+    )
+    .await?;
+    if !pode_pesquisar {
+        tracing::warn!("Pesquisa de utilizadores negada para {}", user_id.0);
+        return Err(AppError::Unauthorized);
+    }
+
+    let termo = params.q.trim();
+    if termo.chars().count() < MIN_CARACTERES {
+        return Ok(Json(vec![]));
+    }
+
+    let sugestoes = user_service::search_users(&state.db_pool, organizacao.0, termo, LIMITE_SUGESTOES).await?;
+    tracing::debug!("GET /api/users/search: '{}' -> {} sugestões", termo, sugestoes.len());
+    Ok(Json(sugestoes))
+}
@@ -0,0 +1,699 @@
+// src/web/api_v1_handlers.rs
+//! API JSON versionada (/api/v1) para integrações: utilizadores, escala, alocações, trocas,
+//! presença, indisponibilidades, cardápio e agenda. Autenticação por sessão ou token (`mw_api`); cada handler
+//! verifica a sua permissão. Todos os erros seguem o envelope `{"error": {"code", "message", "fields"}}`.
+//! As anotações `#[utoipa::path]` geram a especificação OpenAPI (ver `api_docs`).
+
+use crate::{
+    error::{ApiError, ApiResult, AppError, AppResult, EnvelopeErro},
+    models::{
+        escala::{
+            AlocacaoDetalhe, DiaEscala, GerarPeriodoRequest, Indisponibilidade, IndisponibilidadePayload,
+            PedidoTrocaPayload, PublicarRequest, RespostaTrocaPayload, TrocaDetalhe,
+        },
+        cardapio::{CardapioSemana, SemanaPublicada},
+        agenda::EventoAgenda,
+        presence::{PresencePerson, PresenceSocketAction, PresenceStats},
+        user::UserApi,
+    },
+    services::{agenda_service, cardapio_service, escala_service, indisponibilidade_service, permission_service, presence_service, user_service},
+    state::AppState,
+    tempo,
+    validation::{validar, Validador, Validate},
+    web::{mw_auth::{OrganizacaoId, UserId}, presence_handlers},
+};
+use axum::{
+    extract::{Extension, FromRequest, FromRequestParts, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Dias devolvidos por omissão nas consultas por período (a partir de hoje).
+const DIAS_PERIODO_PADRAO: i64 = 30;
+/// Período máximo de uma consulta.
+const MAX_DIAS_PERIODO: i64 = 366;
+
+// --- Extratores com rejeição no envelope da API ---
+
+/// `Json` cujo erro (corpo inválido) segue o envelope da API.
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(ApiError))]
+pub struct ApiJson<T>(pub T);
+
+/// `Query` cujo erro segue o envelope da API.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(ApiError))]
+pub struct ApiQuery<T>(T);
+
+/// `Path` cujo erro segue o envelope da API.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(ApiError))]
+pub struct ApiPath<T>(T);
+
+/// Resposta das ações cujo resultado é apenas uma mensagem.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Mensagem {
+    pub mensagem: String,
+}
+
+impl From<String> for Mensagem {
+    fn from(mensagem: String) -> Self {
+        Self { mensagem }
+    }
+}
+
+async fn tem_permissao(state: &AppState, user_id: &str, permissao: &str) -> AppResult<bool> {
+    permission_service::user_has_permission(&state.db_pool, &state.permissions, user_id, permissao).await
+}
+
+/// Recusa (403) se o utilizador não tiver a permissão.
+async fn exigir_permissao(state: &AppState, user_id: &str, permissao: &str) -> AppResult<()> {
+    if tem_permissao(state, user_id, permissao).await? {
+        Ok(())
+    } else {
+        tracing::warn!("API: {} sem a permissão '{}'.", user_id, permissao);
+        Err(AppError::Unauthorized)
+    }
+}
+
+// --- Utilizadores ---
+
+// GET /api/v1/me
+#[utoipa::path(get, path = "/api/v1/me", tag = "utilizadores",
+    summary = "Utilizador autenticado",
+    responses(
+        (status = 200, body = UserApi),
+        (status = 401, description = "Sem sessão nem token válido", body = EnvelopeErro),
+    ))]
+pub async fn me(State(state): State<AppState>, Extension(user_id): Extension<UserId>) -> ApiResult<Json<UserApi>> {
+    let user = user_service::find_user_by_id(&state.db_pool, &user_id.0)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id.0)))?;
+    Ok(Json(user.into()))
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UsersQuery {
+    /// Filtra por ID ou nome (contém, sem distinguir maiúsculas)
+    #[serde(default)]
+    q: String,
+    /// Inclui as contas arquivadas
+    #[serde(default)]
+    arquivados: bool,
+}
+
+// GET /api/v1/users?q=&arquivados=
+#[utoipa::path(get, path = "/api/v1/users", tag = "utilizadores",
+    summary = "Lista os utilizadores (permissão users.pesquisar)",
+    params(UsersQuery),
+    responses(
+        (status = 200, body = Vec<UserApi>),
+        (status = 403, description = "Sem permissão", body = EnvelopeErro),
+    ))]
+pub async fn listar_users(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiQuery(params): ApiQuery<UsersQuery>,
+) -> ApiResult<Json<Vec<UserApi>>> {
+    exigir_permissao(&state, &user_id.0, permission_service::PERM_USERS_PESQUISAR).await?;
+
+    let termo = params.q.trim().to_lowercase();
+    let users = user_service::find_all_users(&state.db_leitura, organizacao.0)
+        .await?
+        .into_iter()
+        .filter(|u| params.arquivados || u.ativo)
+        .filter(|u| termo.is_empty() || u.id.to_lowercase().contains(&termo) || u.name.to_lowercase().contains(&termo))
+        .map(UserApi::from)
+        .collect();
+    Ok(Json(users))
+}
+
+// GET /api/v1/users/{id}
+#[utoipa::path(get, path = "/api/v1/users/{id}", tag = "utilizadores",
+    summary = "Um utilizador (o próprio, ou qualquer um com users.pesquisar)",
+    params(("id" = String, Path, description = "ID do utilizador")),
+    responses(
+        (status = 200, body = UserApi),
+        (status = 403, description = "Sem permissão", body = EnvelopeErro),
+        (status = 404, description = "Não encontrado", body = EnvelopeErro),
+    ))]
+pub async fn obter_user(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiPath(id): ApiPath<String>,
+) -> ApiResult<Json<UserApi>> {
+    if id != user_id.0 {
+        exigir_permissao(&state, &user_id.0, permission_service::PERM_USERS_PESQUISAR).await?;
+    }
+    let user = user_service::find_user_by_id(&state.db_pool, &id)
+        .await?
+        .filter(|u| u.organizacao_id == organizacao.0)
+        .ok_or_else(|| AppError::NotFound(format!("Utilizador '{}' não encontrado.", id)))?;
+    Ok(Json(user.into()))
+}
+
+// --- Escala e alocações ---
+
+/// Período das consultas (`inicio`/`fim` em 'YYYY-MM-DD'; por omissão, os próximos 30 dias).
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PeriodoQuery {
+    /// Primeiro dia (YYYY-MM-DD); por omissão, hoje
+    inicio: Option<String>,
+    /// Último dia (YYYY-MM-DD); por omissão, daqui a 30 dias
+    fim: Option<String>,
+    /// Só as alocações deste utilizador (ignorado em /escala e /agenda)
+    user_id: Option<String>,
+}
+
+impl Validate for PeriodoQuery {
+    fn validate(&self, v: &mut Validador) {
+        let (inicio, fim) = self.limites();
+        if let (Some(inicio), Some(fim)) = (v.data("inicio", &inicio), v.data("fim", &fim)) {
+            if fim < inicio {
+                v.erro("fim", "A data fim deve ser igual ou posterior ao início.");
+            } else if (fim - inicio).num_days() > MAX_DIAS_PERIODO {
+                v.erro("fim", format!("O período não pode exceder {} dias.", MAX_DIAS_PERIODO));
+            }
+        }
+    }
+}
+
+impl PeriodoQuery {
+    /// Período validado, com os valores por omissão (também usado pelo GraphQL).
+    pub(crate) fn validado(inicio: Option<String>, fim: Option<String>) -> AppResult<(String, String)> {
+        let periodo = Self { inicio, fim, user_id: None };
+        validar(&periodo)?;
+        Ok(periodo.limites())
+    }
+
+    /// Início e fim pedidos, com os valores por omissão.
+    fn limites(&self) -> (String, String) {
+        let hoje = tempo::hoje();
+        let inicio = self.inicio.clone().unwrap_or_else(|| hoje.to_string()).trim().to_string();
+        let fim = self
+            .fim
+            .clone()
+            .unwrap_or_else(|| (hoje + Duration::days(DIAS_PERIODO_PADRAO)).to_string())
+            .trim()
+            .to_string();
+        (inicio, fim)
+    }
+}
+
+// GET /api/v1/escala?inicio=&fim= (os rascunhos só são visíveis para quem gere a escala)
+#[utoipa::path(get, path = "/api/v1/escala", tag = "escala",
+    summary = "Dias de escala e alocações do período",
+    description = "Os dias em rascunho só são devolvidos a quem tem a permissão escala.gerir.",
+    params(PeriodoQuery),
+    responses(
+        (status = 200, body = Vec<DiaEscala>),
+        (status = 422, description = "Período inválido", body = EnvelopeErro),
+    ))]
+pub async fn listar_escala(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiQuery(params): ApiQuery<PeriodoQuery>,
+) -> ApiResult<Json<Vec<DiaEscala>>> {
+    validar(&params)?;
+    let (inicio, fim) = params.limites();
+    let gestor = tem_permissao(&state, &user_id.0, permission_service::PERM_ESCALA_GERIR).await?;
+    let dias = escala_service::dias_periodo(&state.db_leitura, organizacao.0, &inicio, &fim, gestor).await?;
+    Ok(Json(dias))
+}
+
+// POST /api/v1/escala/gerar
+#[utoipa::path(post, path = "/api/v1/escala/gerar", tag = "escala",
+    summary = "Gera a escala (rascunho) do período (escala.gerir)",
+    request_body = GerarPeriodoRequest,
+    responses(
+        (status = 200, body = Mensagem),
+        (status = 403, description = "Sem permissão", body = EnvelopeErro),
+        (status = 422, description = "Período inválido", body = EnvelopeErro),
+    ))]
+pub async fn gerar_escala(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiJson(payload): ApiJson<GerarPeriodoRequest>,
+) -> ApiResult<Json<Mensagem>> {
+    exigir_permissao(&state, &user_id.0, permission_service::PERM_ESCALA_GERIR).await?;
+    validar(&payload)?;
+    let msg = escala_service::gerar_escala_periodo(&state.db_pool, organizacao.0, &payload.data_inicio, &payload.data_fim, state.config.escala.fadiga_dias).await?;
+    Ok(Json(msg.into()))
+}
+
+// POST /api/v1/escala/publicar
+#[utoipa::path(post, path = "/api/v1/escala/publicar", tag = "escala",
+    summary = "Publica a escala do período (escala.gerir)",
+    request_body = PublicarRequest,
+    responses(
+        (status = 200, body = Mensagem),
+        (status = 403, description = "Sem permissão", body = EnvelopeErro),
+        (status = 422, description = "Período inválido", body = EnvelopeErro),
+    ))]
+pub async fn publicar_escala(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiJson(payload): ApiJson<PublicarRequest>,
+) -> ApiResult<Json<Mensagem>> {
+    exigir_permissao(&state, &user_id.0, permission_service::PERM_ESCALA_GERIR).await?;
+    validar(&payload)?;
+    let msg = escala_service::publicar_escala(&state.db_pool, organizacao.0, &payload.data_inicio, &payload.data_fim).await?;
+    Ok(Json(msg.into()))
+}
+
+// GET /api/v1/alocacoes?inicio=&fim=&user_id=
+#[utoipa::path(get, path = "/api/v1/alocacoes", tag = "escala",
+    summary = "Alocações do período",
+    description = "As alocações de dias em rascunho só são devolvidas a quem tem a permissão escala.gerir.",
+    params(PeriodoQuery),
+    responses(
+        (status = 200, body = Vec<AlocacaoDetalhe>),
+        (status = 422, description = "Período inválido", body = EnvelopeErro),
+    ))]
+pub async fn listar_alocacoes(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiQuery(params): ApiQuery<PeriodoQuery>,
+) -> ApiResult<Json<Vec<AlocacaoDetalhe>>> {
+    validar(&params)?;
+    let (inicio, fim) = params.limites();
+    let gestor = tem_permissao(&state, &user_id.0, permission_service::PERM_ESCALA_GERIR).await?;
+    let alocacoes = escala_service::alocacoes_periodo(
+        &state.db_leitura,
+        organizacao.0,
+        &inicio,
+        &fim,
+        params.user_id.as_deref(),
+        gestor,
+    )
+    .await?;
+    Ok(Json(alocacoes))
+}
+
+// --- Trocas ---
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TrocasQuery {
+    /// Estado (Pendente, AguardandoEscalante, Aprovada, Recusada)
+    status: Option<String>,
+    /// Só as trocas deste utilizador (apenas para escala.gerir)
+    user_id: Option<String>,
+}
+
+// GET /api/v1/trocas?status=&user_id= (quem não gere a escala só vê as suas)
+#[utoipa::path(get, path = "/api/v1/trocas", tag = "trocas",
+    summary = "Pedidos de troca",
+    description = "Sem a permissão escala.gerir, só são devolvidas as trocas em que o utilizador é solicitante ou substituto.",
+    params(TrocasQuery),
+    responses((status = 200, body = Vec<TrocaDetalhe>)))]
+pub async fn listar_trocas(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiQuery(params): ApiQuery<TrocasQuery>,
+) -> ApiResult<Json<Vec<TrocaDetalhe>>> {
+    let gestor = tem_permissao(&state, &user_id.0, permission_service::PERM_ESCALA_GERIR).await?;
+    let filtro_user = if gestor { params.user_id.as_deref() } else { Some(user_id.0.as_str()) };
+    let trocas = escala_service::listar_trocas(&state.db_leitura, organizacao.0, filtro_user, params.status.as_deref()).await?;
+    Ok(Json(trocas))
+}
+
+// POST /api/v1/trocas
+#[utoipa::path(post, path = "/api/v1/trocas", tag = "trocas",
+    summary = "Pede a troca de um serviço próprio",
+    request_body = PedidoTrocaPayload,
+    responses(
+        (status = 201, body = Mensagem),
+        (status = 404, description = "Alocação não encontrada", body = EnvelopeErro),
+        (status = 409, description = "Troca impossível (regras da escala)", body = EnvelopeErro),
+        (status = 422, description = "Dados inválidos", body = EnvelopeErro),
+    ))]
+pub async fn solicitar_troca(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiJson(payload): ApiJson<PedidoTrocaPayload>,
+) -> ApiResult<(StatusCode, Json<Mensagem>)> {
+    validar(&payload)?;
+    let msg = escala_service::solicitar_troca(
+        &state.db_pool,
+        organizacao.0,
+        &user_id.0,
+        &payload.alocacao_id,
+        &payload.substituto_id,
+        payload.alocacao_substituto_id,
+        &payload.motivo,
+        state.config.escala.fadiga_dias,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(msg.into())))
+}
+
+// POST /api/v1/trocas/{id}/resposta (pelo substituto)
+#[utoipa::path(post, path = "/api/v1/trocas/{id}/resposta", tag = "trocas",
+    summary = "Aceita ou recusa um pedido de troca (substituto)",
+    params(("id" = String, Path, description = "ID da troca")),
+    request_body = RespostaTrocaPayload,
+    responses(
+        (status = 200, body = Mensagem),
+        (status = 403, description = "Não é o substituto", body = EnvelopeErro),
+        (status = 404, description = "Troca não encontrada", body = EnvelopeErro),
+        (status = 409, description = "Pedido já respondido", body = EnvelopeErro),
+    ))]
+pub async fn responder_troca(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(troca_id): ApiPath<String>,
+    ApiJson(payload): ApiJson<RespostaTrocaPayload>,
+) -> ApiResult<Json<Mensagem>> {
+    validar(&payload)?;
+    let msg = escala_service::responder_troca_usuario(&state.db_pool, &troca_id, &user_id.0, &payload.acao).await?;
+    Ok(Json(msg.into()))
+}
+
+// POST /api/v1/trocas/{id}/aprovar (escalante)
+#[utoipa::path(post, path = "/api/v1/trocas/{id}/aprovar", tag = "trocas",
+    summary = "Aprova uma troca aceite pelo substituto (escala.gerir)",
+    params(("id" = String, Path, description = "ID da troca")),
+    responses(
+        (status = 200, body = Mensagem),
+        (status = 403, description = "Sem permissão", body = EnvelopeErro),
+        (status = 404, description = "Troca não encontrada", body = EnvelopeErro),
+        (status = 409, description = "Troca impossível (regras da escala)", body = EnvelopeErro),
+    ))]
+pub async fn aprovar_troca(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiPath(troca_id): ApiPath<String>,
+) -> ApiResult<Json<Mensagem>> {
+    exigir_permissao(&state, &user_id.0, permission_service::PERM_ESCALA_GERIR).await?;
+    let msg = escala_service::aprovar_troca(&state.db_pool, organizacao.0, &troca_id).await?;
+    Ok(Json(msg.into()))
+}
+
+// --- Presença ---
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PresencaQuery {
+    /// Ano/turma
+    turma: Option<i64>,
+    /// ID do grupo (tem prioridade sobre a turma)
+    grupo: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PresencaLista {
+    pub pessoas: Vec<PresencePerson>,
+    pub stats: PresenceStats,
+}
+
+/// Resultado de uma marcação de saída/retorno.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PresencaMarcacao {
+    pub user_id: String,
+    pub esta_fora: bool,
+    pub mensagem: String,
+    pub stats: PresenceStats,
+}
+
+// GET /api/v1/presenca?turma= | ?grupo=
+#[utoipa::path(get, path = "/api/v1/presenca", tag = "presenca",
+    summary = "Estado de presença de uma turma ou grupo (presenca)",
+    params(PresencaQuery),
+    responses(
+        (status = 200, body = PresencaLista),
+        (status = 403, description = "Sem permissão", body = EnvelopeErro),
+        (status = 422, description = "Falta a turma ou o grupo", body = EnvelopeErro),
+    ))]
+pub async fn listar_presenca(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiQuery(params): ApiQuery<PresencaQuery>,
+) -> ApiResult<Json<PresencaLista>> {
+    exigir_permissao(&state, &user_id.0, permission_service::PERM_PRESENCA).await?;
+    let pessoas = match (params.grupo, params.turma) {
+        (Some(grupo_id), _) => presence_service::get_presence_list_for_grupo(&state.db_leitura, organizacao.0, grupo_id).await?,
+        (None, Some(turma)) => presence_service::get_presence_list_for_turma(&state.db_leitura, organizacao.0, turma).await?,
+        (None, None) => return Err(AppError::validation("turma", "Indique a turma ou o grupo.").into()),
+    };
+    let stats = presence_service::calcular_stats(&pessoas);
+    Ok(Json(PresencaLista { pessoas, stats }))
+}
+
+/// Marca saída/retorno e avisa as páginas de presença abertas (como o WebSocket).
+async fn marcar_presenca(
+    state: &AppState,
+    organizacao_id: i64,
+    operador_id: &str,
+    alvo: String,
+    acao: &str,
+) -> ApiResult<Json<PresencaMarcacao>> {
+    exigir_permissao(state, operador_id, permission_service::PERM_PRESENCA).await?;
+    let da_organizacao = user_service::find_user_by_id(&state.db_pool, &alvo).await?.is_some_and(|u| u.organizacao_id == organizacao_id);
+    if !da_organizacao {
+        return Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", alvo)).into());
+    }
+    let operador = user_service::find_user_by_id(&state.db_pool, operador_id)
+        .await?
+        .map_or_else(|| operador_id.to_string(), |u| u.name);
+
+    let action = PresenceSocketAction { action: acao.to_string(), user_id: alvo };
+    let update = presence_handlers::marcar_e_difundir(state, organizacao_id, &action, &operador).await;
+    if !update.success {
+        return Err(AppError::InternalServerError.into());
+    }
+    Ok(Json(PresencaMarcacao {
+        user_id: update.user_id,
+        esta_fora: update.esta_fora,
+        mensagem: update.message,
+        stats: update.stats,
+    }))
+}
+
+// POST /api/v1/presenca/{user_id}/saida
+#[utoipa::path(post, path = "/api/v1/presenca/{user_id}/saida", tag = "presenca",
+    summary = "Marca a saída de um utilizador (presenca)",
+    params(("user_id" = String, Path, description = "ID do utilizador")),
+    responses(
+        (status = 200, body = PresencaMarcacao),
+        (status = 403, description = "Sem permissão", body = EnvelopeErro),
+        (status = 404, description = "Utilizador não encontrado", body = EnvelopeErro),
+    ))]
+pub async fn marcar_saida(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiPath(alvo): ApiPath<String>,
+) -> ApiResult<Json<PresencaMarcacao>> {
+    marcar_presenca(&state, organizacao.0, &user_id.0, alvo, "saida").await
+}
+
+// POST /api/v1/presenca/{user_id}/retorno
+#[utoipa::path(post, path = "/api/v1/presenca/{user_id}/retorno", tag = "presenca",
+    summary = "Marca o retorno de um utilizador (presenca)",
+    params(("user_id" = String, Path, description = "ID do utilizador")),
+    responses(
+        (status = 200, body = PresencaMarcacao),
+        (status = 403, description = "Sem permissão", body = EnvelopeErro),
+        (status = 404, description = "Utilizador não encontrado", body = EnvelopeErro),
+    ))]
+pub async fn marcar_retorno(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiPath(alvo): ApiPath<String>,
+) -> ApiResult<Json<PresencaMarcacao>> {
+    marcar_presenca(&state, organizacao.0, &user_id.0, alvo, "retorno").await
+}
+
+// --- Indisponibilidades ---
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IndisponibilidadesQuery {
+    /// Só as deste utilizador (apenas para escala.gerir; os restantes só veem as suas)
+    user_id: Option<String>,
+    /// Só as que terminam a partir deste dia (YYYY-MM-DD); por omissão, hoje
+    desde: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Criado {
+    pub id: i64,
+}
+
+// GET /api/v1/indisponibilidades?user_id=&desde= (quem não gere a escala só vê as suas)
+#[utoipa::path(get, path = "/api/v1/indisponibilidades", tag = "indisponibilidades",
+    summary = "Indisponibilidades (baixas, dispensas)",
+    params(IndisponibilidadesQuery),
+    responses(
+        (status = 200, body = Vec<Indisponibilidade>),
+        (status = 422, description = "Data inválida", body = EnvelopeErro),
+    ))]
+pub async fn listar_indisponibilidades(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiQuery(params): ApiQuery<IndisponibilidadesQuery>,
+) -> ApiResult<Json<Vec<Indisponibilidade>>> {
+    let desde = params.desde.unwrap_or_else(|| tempo::hoje().to_string());
+    let mut v = Validador::default();
+    v.data("desde", &desde);
+    v.resultado()?;
+
+    let gestor = tem_permissao(&state, &user_id.0, permission_service::PERM_ESCALA_GERIR).await?;
+    let filtro_user = if gestor { params.user_id.as_deref() } else { Some(user_id.0.as_str()) };
+    let lista = indisponibilidade_service::listar(&state.db_leitura, organizacao.0, filtro_user, desde.trim()).await?;
+    Ok(Json(lista))
+}
+
+// POST /api/v1/indisponibilidades (a própria, ou de outro utilizador por quem gere a escala)
+#[utoipa::path(post, path = "/api/v1/indisponibilidades", tag = "indisponibilidades",
+    summary = "Regista uma indisponibilidade",
+    description = "Sem user_id, é registada para o próprio; para outro utilizador exige escala.gerir.",
+    request_body = IndisponibilidadePayload,
+    responses(
+        (status = 201, body = Criado),
+        (status = 403, description = "Sem permissão", body = EnvelopeErro),
+        (status = 404, description = "Utilizador não encontrado", body = EnvelopeErro),
+        (status = 422, description = "Dados inválidos", body = EnvelopeErro),
+    ))]
+pub async fn criar_indisponibilidade(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiJson(payload): ApiJson<IndisponibilidadePayload>,
+) -> ApiResult<(StatusCode, Json<Criado>)> {
+    validar(&payload)?;
+    let alvo = payload.user_id.as_deref().map(str::trim).filter(|id| !id.is_empty()).unwrap_or(&user_id.0);
+    if alvo != user_id.0 {
+        exigir_permissao(&state, &user_id.0, permission_service::PERM_ESCALA_GERIR).await?;
+    }
+    let id = indisponibilidade_service::criar(
+        &state.db_pool,
+        organizacao.0,
+        alvo,
+        payload.data_inicio.trim(),
+        payload.data_fim.trim(),
+        payload.motivo.as_deref(),
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(Criado { id })))
+}
+
+// DELETE /api/v1/indisponibilidades/{id}
+#[utoipa::path(delete, path = "/api/v1/indisponibilidades/{id}", tag = "indisponibilidades",
+    summary = "Apaga uma indisponibilidade (a própria, ou qualquer uma com escala.gerir)",
+    params(("id" = i64, Path, description = "ID da indisponibilidade")),
+    responses(
+        (status = 204, description = "Apagada"),
+        (status = 403, description = "Sem permissão", body = EnvelopeErro),
+        (status = 404, description = "Não encontrada", body = EnvelopeErro),
+    ))]
+pub async fn remover_indisponibilidade(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiPath(id): ApiPath<i64>,
+) -> ApiResult<StatusCode> {
+    let indisponibilidade = indisponibilidade_service::obter(&state.db_pool, organizacao.0, id).await?;
+    if indisponibilidade.user_id != user_id.0 {
+        exigir_permissao(&state, &user_id.0, permission_service::PERM_ESCALA_GERIR).await?;
+    }
+    indisponibilidade_service::remover(&state.db_pool, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// --- Cardápio ---
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CardapioQuery {
+    /// Qualquer dia da semana (YYYY-MM-DD); por omissão, a semana atual
+    semana: Option<String>,
+}
+
+// GET /api/v1/cardapio?semana=
+#[utoipa::path(get, path = "/api/v1/cardapio", tag = "cardapio",
+    summary = "Cardápio de uma semana (segunda a domingo)",
+    description = "Só os pratos publicados; os dias sem nenhum vêm com a lista vazia.",
+    params(CardapioQuery),
+    responses(
+        (status = 200, body = CardapioSemana),
+        (status = 422, description = "Data inválida", body = EnvelopeErro),
+    ))]
+pub async fn obter_cardapio(
+    State(state): State<AppState>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiQuery(params): ApiQuery<CardapioQuery>,
+) -> ApiResult<Json<CardapioSemana>> {
+    let semana = match params.semana.as_deref() {
+        Some(semana) => {
+            let mut v = Validador::default();
+            let data = v.data("semana", semana);
+            v.resultado()?;
+            data.unwrap_or_else(tempo::hoje)
+        }
+        None => tempo::hoje(),
+    };
+    let cardapio = cardapio_service::semana(&state.db_leitura, organizacao.0, semana, false).await?;
+    Ok(Json(cardapio))
+}
+
+// GET /api/v1/cardapio/semanas
+#[utoipa::path(get, path = "/api/v1/cardapio/semanas", tag = "cardapio",
+    summary = "Semanas com cardápio publicado (histórico, das mais recentes para as mais antigas)",
+    responses(
+        (status = 200, body = Vec<SemanaPublicada>),
+    ))]
+pub async fn listar_semanas_cardapio(
+    State(state): State<AppState>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+) -> ApiResult<Json<Vec<SemanaPublicada>>> {
+    let semanas = cardapio_service::historico(&state.db_leitura, organizacao.0, cardapio_service::SEMANAS_HISTORICO).await?;
+    Ok(Json(semanas))
+}
+
+// --- Agenda ---
+
+// GET /api/v1/agenda?inicio=&fim=
+#[utoipa::path(get, path = "/api/v1/agenda", tag = "agenda",
+    summary = "Formaturas, cerimónias e palestras do período",
+    description = "Todos os eventos da organização; `anos` vazio = destinado a todos os anos.",
+    params(PeriodoQuery),
+    responses(
+        (status = 200, body = Vec<EventoAgenda>),
+        (status = 422, description = "Período inválido", body = EnvelopeErro),
+    ))]
+pub async fn listar_agenda(
+    State(state): State<AppState>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiQuery(params): ApiQuery<PeriodoQuery>,
+) -> ApiResult<Json<Vec<EventoAgenda>>> {
+    validar(&params)?;
+    let (inicio, fim) = params.limites();
+    let hoje = tempo::hoje();
+    let (inicio, fim) = (tempo::ler_data(&inicio).unwrap_or(hoje), tempo::ler_data(&fim).unwrap_or(hoje));
+    let eventos = agenda_service::listar(&state.db_leitura, organizacao.0, inicio, fim).await?;
+    Ok(Json(eventos))
+}
+
+/// Rotas sem correspondência dentro de /api/v1 (404 no envelope da API, não a página HTML).
+pub async fn nao_encontrado() -> ApiError {
+    AppError::NotFound("Recurso da API não encontrado.".to_string()).into()
+}
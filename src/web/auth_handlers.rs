@@ -2,78 +2,191 @@
 use crate::{
     error::{AppError, AppResult}, // Usar AppError e AppResult
     models::user::LoginForm,      // Usar LoginForm do models
-    services::{auth_service, user_service},     // Usar o serviço de autenticação
+    services::{auth_service, bloqueio_service, captcha_service, login_history_service, notificacao_service, oidc_service, sessao_service, user_service}, // Autenticação, limite de tentativas e histórico
     state::AppState,
     templates::LoginPage,
+    web::{csrf::{self, CsrfForm}, flash, mw_auth, mw_idioma::IDIOMA_KEY, mw_senha},
 };
 use askama::Template; // Trait Template para render()
 use axum::{
-    extract::{Form, State},
-    response::{Html, IntoResponse, Redirect}, // Usar Html para erros de render
+    extract::{ConnectInfo, Form, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response}, // Usar Html para erros de render
 };
+use serde::Deserialize;
+use std::net::SocketAddr; // IP do cliente (via ConnectInfo)
+use tower_cookies::Cookies; // Cookie do token CSRF
 use tower_sessions::Session; // Importar Session para gestão de login
+use uuid::Uuid; // state/nonce do pedido OIDC
 
 // GET /login (como antes, mas verifica sessão e renderiza explicitamente)
-pub async fn show_login_form(session: Session) -> impl IntoResponse {
+pub async fn show_login_form(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>, // IP do cliente (CAPTCHA após várias falhas)
+    session: Session,
+) -> AppResult<Response> {
     // Verifica se já existe um 'user_id' na sessão
     if session.get::<String>("user_id").await.ok().flatten().is_some() {
         tracing::debug!("GET /login: Utilizador já logado, redirecionando para /user");
         // Se sim, redireciona para a página do utilizador (será criada)
-        return Redirect::to("/user").into_response();
+        return Ok(Redirect::to("/user").into_response());
     }
 
     // Se não está logado, renderiza a página de login
-    let template = LoginPage { error: None };
+    let com_captcha = captcha_exigido(&state, None, &addr.ip().to_string()).await?;
+    pagina_login(&state, StatusCode::OK, None, com_captcha)
+}
+
+/// Autentica a sessão de um utilizador já verificado (senha ou conta institucional),
+/// exceto se a conta estiver bloqueada pela administração ou a sua organização desativada.
+async fn iniciar_sessao(state: &AppState, session: &Session, user_id: &str, ip: &str, user_agent: Option<&str>) -> AppResult<Response> {
+    // Só é revelado depois de a identidade estar confirmada (não diz a terceiros que o ID existe)
+    if let Some(bloqueio) = bloqueio_service::bloqueio_manual(&state.db_pool, user_id).await? {
+        tracing::warn!("Login recusado para {}: conta bloqueada pela administração.", user_id);
+        login_history_service::registar(&state.db_pool, user_id, ip, user_agent, Some(login_history_service::MOTIVO_BLOQUEIO_ADMIN)).await;
+        let mensagem = match bloqueio.ate {
+            Some(ate) => format!("Conta bloqueada pela administração até {}. Contacte a administração.", ate),
+            None => "Conta bloqueada pela administração. Contacte a administração.".to_string(),
+        };
+        return pagina_login_erro(state, StatusCode::FORBIDDEN, mensagem);
+    }
+    let roles = user_service::roles_efetivas(&state.db_pool, user_id).await?;
+    if mw_auth::resolver_organizacao(state, None, user_id, &roles).await?.is_none() {
+        tracing::warn!("Login recusado para {}: organização desativada.", user_id);
+        login_history_service::registar(&state.db_pool, user_id, ip, user_agent, Some(login_history_service::MOTIVO_ORGANIZACAO_INATIVA)).await;
+        return pagina_login_erro(state, StatusCode::FORBIDDEN, "A sua organização está desativada. Contacte a administração.".to_string());
+    }
+
+    session.cycle_id().await // Gera novo ID de sessão (segurança)
+        .map_err(|e| AppError::SessionError(format!("Falha ao rodar ID: {}", e)))?;
+    session.insert("user_id", user_id).await // Guarda o ID na sessão
+        .map_err(|e| AppError::SessionError(format!("Falha ao inserir na sessão: {}", e)))?;
+    // O idioma escolhido pelo utilizador passa a valer para as páginas (ver mw_idioma)
+    if let Some(idioma) = user_service::idioma(&state.db_pool, user_id).await? {
+        session.insert(IDIOMA_KEY, idioma.codigo()).await
+            .map_err(|e| AppError::SessionError(format!("Falha ao inserir na sessão: {}", e)))?;
+    }
+    bloqueio_service::limpar_user(&state.db_pool, user_id).await?;
+    // Avisa o dono da conta de um acesso a partir de um IP nunca usado (verificado antes de registar este)
+    let ip_novo = login_history_service::ip_novo(&state.db_pool, user_id, ip).await.unwrap_or_else(|e| {
+        tracing::warn!("Falha ao verificar IPs anteriores de {}: {:?}", user_id, e);
+        false
+    });
+    login_history_service::registar(&state.db_pool, user_id, ip, user_agent, None).await;
+    if ip_novo {
+        let mensagem = format!(
+            "Novo acesso à sua conta a partir do IP {} ({}). Se não foi você, altere a senha e termine as outras sessões.",
+            ip, user_agent.unwrap_or("dispositivo desconhecido")
+        );
+        notificacao_service::notificar(&state.db_pool, user_id, notificacao_service::TIPO_SEGURANCA, "Acesso a partir de um IP novo", &mensagem).await;
+    }
+
+    tracing::info!("✅ Login bem-sucedido para: {}", user_id);
+    Ok(Redirect::to("/user").into_response())
+}
+
+/// Renderiza a página de login com o status indicado, a mensagem de erro (se houver) e, se pedido, o widget CAPTCHA.
+fn pagina_login(state: &AppState, status: StatusCode, erro: Option<String>, com_captcha: bool) -> AppResult<Response> {
+    let captcha = state.captcha.as_deref().filter(|_| com_captcha).map(|c| c.widget());
+    let template = LoginPage { error: erro, oidc_ativo: state.oidc.is_some(), captcha };
     match template.render() {
-        Ok(html) => Html(html).into_response(),
+        Ok(html) => Ok((status, Html(html)).into_response()),
         Err(e) => {
             tracing::error!("Falha ao renderizar template de login: {}", e);
-            (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                "Erro ao carregar a página.",
-            )
-                .into_response()
+            Err(AppError::InternalServerError)
         }
     }
 }
 
+/// Renderiza a página de login com uma mensagem de erro e o status indicado.
+fn pagina_login_erro(state: &AppState, status: StatusCode, mensagem: String) -> AppResult<Response> {
+    pagina_login(state, status, Some(mensagem), false)
+}
+
+/// O CAPTCHA está configurado e já houve falhas suficientes para este ID (se conhecido) ou IP?
+async fn captcha_exigido(state: &AppState, user_id: Option<&str>, ip: &str) -> AppResult<bool> {
+    match state.captcha.as_deref() {
+        Some(config) => Ok(bloqueio_service::falhas_recentes(&state.db_pool, user_id, ip).await? >= config.apos_falhas),
+        None => Ok(false),
+    }
+}
+
 // POST /login (Lógica de processamento do formulário)
 pub async fn handle_login(
     State(state): State<AppState>, // Acesso ao AppState (db_pool)
+    ConnectInfo(addr): ConnectInfo<SocketAddr>, // IP do cliente (limite de tentativas e histórico)
+    headers: HeaderMap,            // User-Agent (histórico de logins)
     session: Session,              // Acesso à sessão
     Form(form): Form<LoginForm>,   // Dados do formulário (id, password)
-) -> AppResult<impl IntoResponse> { // Retorna AppResult com Redirect ou LoginPage com erro
+) -> AppResult<Response> { // Retorna AppResult com Redirect ou LoginPage com erro
 
     tracing::info!("Tentativa de login para ID: {}", form.id);
+    let ip = addr.ip().to_string();
+    let user_agent = headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok());
+    let db = &state.db_pool;
+
+    // 0. Demasiadas falhas recentes para este ID ou IP? Recusa antes de verificar a senha
+    if let Some(minutos) = bloqueio_service::verificar(&state.db_pool, &form.id, &ip).await? {
+        tracing::warn!("Login bloqueado para {} (IP {}): {} min restantes.", form.id, ip, minutos);
+        login_history_service::registar(db, &form.id, &ip, user_agent, Some(login_history_service::MOTIVO_BLOQUEADO)).await;
+        return pagina_login_erro(
+            &state,
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("Demasiadas tentativas falhadas. Tente novamente dentro de {} minuto(s).", minutos),
+        );
+    }
+
+    // 0b. Depois de várias falhas (ainda sem bloqueio), exige o CAPTCHA antes de verificar a senha
+    if let Some(config) = state.captcha.as_deref() {
+        if captcha_exigido(&state, Some(&form.id), &ip).await? {
+            let valido = match captcha_service::verificar(config, form.resposta_captcha(), &ip).await {
+                Ok(valido) => valido,
+                Err(_) => {
+                    return pagina_login(&state, StatusCode::SERVICE_UNAVAILABLE,
+                        Some("Não foi possível validar o CAPTCHA. Tente novamente dentro de momentos.".to_string()), true);
+                }
+            };
+            if !valido {
+                tracing::warn!("Login de {} (IP {}) sem CAPTCHA válido.", form.id, ip);
+                return pagina_login(&state, StatusCode::BAD_REQUEST,
+                    Some("Confirme o CAPTCHA para continuar.".to_string()), true);
+            }
+        }
+    }
 
     // 1. Tenta encontrar o utilizador na base de dados pelo ID (username)
     match user_service::find_user_by_id(&state.db_pool, &form.id).await {
         Ok(Some(user)) => { // Utilizador encontrado
             tracing::debug!("Utilizador {} encontrado, verificando senha...", form.id);
             // 2. Verifica se a senha fornecida corresponde ao hash guardado
-            match auth_service::verify_password(&form.password, &user.password_hash).await {
+            match auth_service::verify_password(&form.password, &user.password_hash, &user.password_esquema).await {
+                Ok(true) if !user.ativo => { // Senha correta, mas conta arquivada
+                    tracing::warn!("Login recusado para {}: conta arquivada.", form.id);
+                    login_history_service::registar(db, &user.id, &ip, user_agent, Some(login_history_service::MOTIVO_CONTA_ARQUIVADA)).await;
+                    pagina_login_erro(&state, StatusCode::OK, "Conta arquivada. Contacte a administração.".to_string())
+                }
                 Ok(true) => { // Senha correta
-                    // 3. Autentica a sessão
-                    session.cycle_id().await // Gera novo ID de sessão (segurança)
-                        .map_err(|e| AppError::SessionError(format!("Falha ao rodar ID: {}", e)))?;
-                    session.insert("user_id", &user.id).await // Guarda o ID na sessão
-                        .map_err(|e| AppError::SessionError(format!("Falha ao inserir na sessão: {}", e)))?;
-
-                    tracing::info!("✅ Login bem-sucedido para: {}", user.id);
-                    // 4. Redireciona para a página do utilizador
-                    Ok(Redirect::to("/user").into_response()) // Ok com Redirect
+                    // Hashes antigos (bcrypt, outros parâmetros) são atualizados (só agora temos a senha em claro)
+                    auth_service::rehash_se_necessario(db, &user.id, &form.password, &user.password_hash, &user.password_esquema).await;
+                    // 3. Autentica a sessão e redireciona para a página do utilizador
+                    let resposta = iniciar_sessao(&state, &session, &user.id, &ip, user_agent).await?;
+                    // Senha fora do prazo (se houver política): obriga a alterá-la antes de continuar
+                    if let Some(max_dias) = state.senha_max_dias {
+                        if user_service::senha_expirada(db, &user.id, max_dias).await? {
+                            tracing::info!("Senha de {} expirada (mais de {} dias).", user.id, max_dias);
+                            session.insert(mw_senha::SENHA_EXPIRADA_KEY, true).await
+                                .map_err(|e| AppError::SessionError(format!("Falha ao inserir na sessão: {}", e)))?;
+                        }
+                    }
+                    Ok(resposta)
                 }
                 Ok(false) => { // Senha incorreta
                     tracing::warn!("Senha incorreta para ID: {}", form.id);
-                    // Renderiza novamente a página de login com mensagem de erro
-                    let template = LoginPage { error: Some("ID ou senha inválidos.".to_string()) };
-                    match template.render() {
-                        Ok(html) => Ok(Html(html).into_response()), // Ok com LoginPage + erro
-                        Err(e) => { // Erro ao renderizar a própria página de erro
-                            tracing::error!("Falha ao renderizar template de login com erro: {}", e);
-                            Err(AppError::InternalServerError) // Retorna erro interno
-                        }
-                    }
+                    login_history_service::registar(db, &user.id, &ip, user_agent, Some(login_history_service::MOTIVO_SENHA_INVALIDA)).await;
+                    bloqueio_service::registar_falha(&state.db_pool, &form.id, &ip).await?;
+                    // Renderiza novamente a página de login com mensagem de erro (e o CAPTCHA, se passou a ser exigido)
+                    let com_captcha = captcha_exigido(&state, Some(&form.id), &ip).await?;
+                    pagina_login(&state, StatusCode::OK, Some("ID ou senha inválidos.".to_string()), com_captcha)
                 }
                 Err(e) => { // Erro ao verificar a senha (ex: hash inválido, erro bcrypt)
                     tracing::error!("Erro ao verificar senha para {}: {:?}", form.id, e);
@@ -83,15 +196,11 @@ pub async fn handle_login(
         }
         Ok(None) => { // Utilizador não encontrado
             tracing::warn!("Utilizador não encontrado: {}", form.id);
+            login_history_service::registar(db, &form.id, &ip, user_agent, Some(login_history_service::MOTIVO_USER_DESCONHECIDO)).await;
+            bloqueio_service::registar_falha(&state.db_pool, &form.id, &ip).await?;
             // Renderiza novamente a página de login com mensagem de erro genérica
-            let template = LoginPage { error: Some("ID ou senha inválidos.".to_string()) };
-             match template.render() {
-                Ok(html) => Ok(Html(html).into_response()), // Ok com LoginPage + erro
-                Err(e) => {
-                    tracing::error!("Falha ao renderizar template de login com erro: {}", e);
-                    Err(AppError::InternalServerError)
-                }
-            }
+            let com_captcha = captcha_exigido(&state, Some(&form.id), &ip).await?;
+            pagina_login(&state, StatusCode::OK, Some("ID ou senha inválidos.".to_string()), com_captcha)
         }
         Err(e) => { // Erro ao buscar utilizador na DB
             tracing::error!("Erro ao buscar utilizador {}: {:?}", form.id, e);
@@ -100,13 +209,125 @@ pub async fn handle_login(
     }
 }
 
-// GET /logout
-pub async fn handle_logout(session: Session) -> AppResult<Redirect> { // Retorna AppResult<Redirect>
+// --- Login institucional (OIDC) ---
+
+/// Chave da sessão com o `state` e o `nonce` do pedido OIDC em curso.
+const OIDC_PENDENTE_KEY: &str = "oidc_pendente";
+
+#[derive(Deserialize, Debug)]
+pub struct OidcCallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>, // Ex: 'access_denied' se o utilizador cancelar no fornecedor
+}
+
+// GET /auth/oidc/login - Redireciona para o fornecedor de identidade
+pub async fn handle_oidc_login(State(state): State<AppState>, session: Session) -> AppResult<Response> {
+    let Some(config) = state.oidc.as_deref() else {
+        return Ok(Redirect::to("/login").into_response());
+    };
+
+    // Fornecedor em baixo: volta ao login local com a explicação
+    let descoberta = match oidc_service::descobrir(config).await {
+        Ok(d) => d,
+        Err(e) => {
+            tracing::warn!("OIDC indisponível: {:?}", e);
+            return pagina_login_erro(&state, StatusCode::OK, e.user_message());
+        }
+    };
+
+    let pedido_state = Uuid::new_v4().to_string();
+    let nonce = Uuid::new_v4().to_string();
+    session.insert(OIDC_PENDENTE_KEY, (&pedido_state, &nonce)).await
+        .map_err(|e| AppError::SessionError(format!("Falha ao guardar pedido OIDC: {}", e)))?;
+
+    let url = oidc_service::url_autorizacao(config, &descoberta, &pedido_state, &nonce)?;
+    tracing::debug!("GET /auth/oidc/login: redirecionando para o fornecedor");
+    Ok(Redirect::to(&url).into_response())
+}
+
+// GET /auth/oidc/callback - Regresso do fornecedor (code + state)
+pub async fn handle_oidc_callback(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    session: Session,
+    Query(params): Query<OidcCallbackParams>,
+) -> AppResult<Response> {
+    let Some(config) = state.oidc.as_deref() else {
+        return Ok(Redirect::to("/login").into_response());
+    };
+    let ip = addr.ip().to_string();
+    let user_agent = headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok());
+
+    // O pedido só pode ser usado uma vez
+    let pendente: Option<(String, String)> = session.remove(OIDC_PENDENTE_KEY).await.ok().flatten();
+
+    if let Some(erro) = params.error {
+        tracing::warn!("OIDC: fornecedor devolveu erro '{}'", erro);
+        return pagina_login_erro(&state, StatusCode::OK, "Login institucional cancelado ou recusado.".to_string());
+    }
+    let (Some((pedido_state, nonce)), Some(code), Some(recebido_state)) = (pendente, params.code, params.state) else {
+        return pagina_login_erro(&state, StatusCode::BAD_REQUEST, "Pedido de login institucional inválido ou expirado. Tente novamente.".to_string());
+    };
+    if recebido_state != pedido_state {
+        tracing::warn!("OIDC: state não corresponde (IP {})", ip);
+        return pagina_login_erro(&state, StatusCode::BAD_REQUEST, "Pedido de login institucional inválido ou expirado. Tente novamente.".to_string());
+    }
+
+    let identidade = match oidc_service::descobrir(config).await {
+        Ok(descoberta) => oidc_service::trocar_codigo(config, &descoberta, &code, &nonce).await,
+        Err(e) => Err(e),
+    };
+    let identidade = match identidade {
+        Ok(i) => i,
+        Err(e) => {
+            tracing::warn!("OIDC: falha ao validar o login: {:?}", e);
+            return pagina_login_erro(&state, StatusCode::OK, e.user_message());
+        }
+    };
+
+    let Some(user_id) = oidc_service::resolver_utilizador(&state.db_pool, &identidade).await? else {
+        let tentado = identidade.email.as_deref().unwrap_or(&identidade.sub);
+        tracing::warn!("OIDC: conta institucional sem utilizador local ({})", tentado);
+        login_history_service::registar(&state.db_pool, tentado, &ip, user_agent, Some(login_history_service::MOTIVO_OIDC_SEM_CONTA)).await;
+        return pagina_login_erro(
+            &state,
+            StatusCode::OK,
+            "A conta institucional não está associada a nenhum utilizador. Contacte a administração.".to_string(),
+        );
+    };
+
+    match user_service::find_user_by_id(&state.db_pool, &user_id).await? {
+        Some(user) if user.ativo => iniciar_sessao(&state, &session, &user.id, &ip, user_agent).await,
+        Some(user) => {
+            tracing::warn!("Login institucional recusado para {}: conta arquivada.", user.id);
+            login_history_service::registar(&state.db_pool, &user.id, &ip, user_agent, Some(login_history_service::MOTIVO_CONTA_ARQUIVADA)).await;
+            pagina_login_erro(&state, StatusCode::OK, "Conta arquivada. Contacte a administração.".to_string())
+        }
+        None => Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id))),
+    }
+}
+
+// POST /logout (com token CSRF: um link ou imagem noutro site não consegue terminar a sessão)
+pub async fn handle_logout(
+    State(state): State<AppState>,
+    session: Session,
+    cookies: Cookies,
+    Form(form): Form<CsrfForm>,
+) -> AppResult<Redirect> { // Retorna AppResult<Redirect>
     let user_id: Option<String> = session.get("user_id").await.ok().flatten();
+    if user_id.is_some() && !csrf::verificar(&session, &form.csrf_token).await {
+        tracing::warn!("Logout recusado para {:?}: token CSRF inválido.", user_id);
+        return Ok(flash::redirect_error(&session, "/user", "Pedido inválido. Tente sair novamente.").await);
+    }
 
-    // Apaga todos os dados da sessão atual
-    session.delete().await
-        .map_err(|e| AppError::SessionError(format!("Falha ao apagar sessão: {}", e)))?;
+    // Retira a sessão da lista de sessões ativas do utilizador
+    if let Some(session_id) = session.id() {
+        sessao_service::remover(&state.db_pool, &session_id.to_string()).await?;
+    }
+
+    terminar_sessao(&session, &cookies).await?;
 
     if let Some(id) = user_id {
         tracing::info!("🚪 Utilizador '{}' desligado.", id);
@@ -116,4 +337,34 @@ pub async fn handle_logout(session: Session) -> AppResult<Redirect> { // Retorna
 
     // Redireciona para a página de login
     Ok(Redirect::to("/login"))
+}
+
+// POST /logout/todas - Termina todas as sessões do utilizador (incluindo a atual)
+pub async fn handle_logout_todas(
+    State(state): State<AppState>,
+    session: Session,
+    cookies: Cookies,
+    Form(form): Form<CsrfForm>,
+) -> AppResult<Redirect> {
+    let Some(user_id) = session.get::<String>("user_id").await.ok().flatten() else {
+        return Ok(Redirect::to("/login"));
+    };
+    if !csrf::verificar(&session, &form.csrf_token).await {
+        tracing::warn!("Logout global recusado para {}: token CSRF inválido.", user_id);
+        return Ok(flash::redirect_error(&session, "/user", "Pedido inválido. Tente novamente.").await);
+    }
+
+    let terminadas = sessao_service::revogar_todas(&state.db_pool, &user_id, None).await?;
+    terminar_sessao(&session, &cookies).await?;
+    tracing::info!("🚪 Utilizador '{}' desligado de todos os dispositivos ({} sessões).", user_id, terminadas);
+
+    Ok(Redirect::to("/login"))
+}
+
+/// Apaga os dados da sessão atual e o cookie do token CSRF.
+async fn terminar_sessao(session: &Session, cookies: &Cookies) -> AppResult<()> {
+    session.delete().await
+        .map_err(|e| AppError::SessionError(format!("Falha ao apagar sessão: {}", e)))?;
+    csrf::limpar_cookie(cookies);
+    Ok(())
 }
\ No newline at end of file
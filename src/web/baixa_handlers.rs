@@ -0,0 +1,178 @@
+// src/web/baixa_handlers.rs
+//! Baixas médicas (/baixas, permissão "baixas"): a secção de saúde regista o período e as restrições,
+//! dá alta ou apaga registos errados. As baixas em vigor tiram o utilizador da geração da escala e
+//! aparecem na lista de presença.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, baixa_service},
+    state::AppState,
+    templates::BaixasPage,
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo das observações de uma baixa.
+const MAX_OBSERVACOES: usize = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct BaixasQuery {
+    #[serde(default)]
+    user: String, // Só as baixas deste utilizador (vazio = todas)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BaixaForm {
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    data_inicio: String,
+    #[serde(default)]
+    data_fim: String,
+    #[serde(default)]
+    restricoes: Vec<String>, // Checkboxes (uma chave por restrição marcada)
+    #[serde(default)]
+    observacoes: String,
+}
+
+/// Renderiza as baixas em vigor e futuras, as terminadas recentemente e o formulário de registo.
+async fn pagina_baixas(
+    state: &AppState,
+    atual: &CurrentUser,
+    user: &str,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let filtro = Some(user).filter(|u| !u.is_empty());
+    let hoje = tempo::hoje();
+    let template = BaixasPage {
+        vigentes: baixa_service::vigentes(&state.db_leitura, organizacao_id, filtro, hoje).await?,
+        terminadas: baixa_service::terminadas(&state.db_leitura, organizacao_id, filtro, hoje, baixa_service::TERMINADAS_RECENTES).await?,
+        user: user.to_string(),
+        hoje: hoje.to_string(),
+        restricoes: baixa_service::RESTRICOES,
+        form,
+        max_observacoes: MAX_OBSERVACOES,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template BaixasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /baixas?user= - Baixas em vigor e registo de novas
+pub async fn show_baixas(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<BaixasQuery>,
+) -> AppResult<Response> {
+    pagina_baixas(&state, &atual, params.user.trim(), StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /baixas - Regista uma baixa médica
+pub async fn handle_registar_baixa(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<BaixaForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("user", &form.user, 10);
+    v.periodo("data_inicio", &form.data_inicio, "data_fim", &form.data_fim);
+    if form.observacoes.trim().chars().count() > MAX_OBSERVACOES {
+        v.erro("observacoes", format!("Máximo de {} caracteres.", MAX_OBSERVACOES));
+    }
+    let periodo = (tempo::ler_data(&form.data_inicio), tempo::ler_data(&form.data_fim));
+    let resultado = match (v.resultado(), periodo) {
+        (Ok(()), (Some(inicio), Some(fim))) => {
+            baixa_service::registar(
+                &state.db_pool,
+                atual.organizacao_id,
+                form.user.trim(),
+                inicio,
+                fim,
+                &form.restricoes,
+                &form.observacoes,
+                &atual.id,
+            )
+            .await
+        }
+        (Err(e), _) => Err(e),
+        (Ok(()), _) => Err(AppError::validation("data_inicio", "Data inválida.")),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!(
+                "{}: {} a {} ({})",
+                form.user.trim(),
+                form.data_inicio.trim(),
+                form.data_fim.trim(),
+                if form.restricoes.is_empty() { "sem restrições indicadas".to_string() } else { form.restricoes.join(", ") }
+            );
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_BAIXA_REGISTADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Baixa {} registada: {} de {} a {}.", id, form.user.trim(), form.data_inicio.trim(), form.data_fim.trim());
+            Ok(flash::redirect_success(&session, "/baixas", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("user", form.user)
+                .com_valor("data_inicio", form.data_inicio)
+                .com_valor("data_fim", form.data_fim)
+                .com_valor("restricoes", form.restricoes.join(","))
+                .com_valor("observacoes", form.observacoes);
+            pagina_baixas(&state, &atual, "", StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /baixas/{id}/alta - Dá alta (a baixa termina na véspera)
+pub async fn handle_alta_baixa(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    match baixa_service::alta(&state.db_pool, atual.organizacao_id, id, tempo::hoje()).await {
+        Ok(baixa) => {
+            let detalhes = format!("{}: baixa de {} a {} terminada", baixa.user_id, baixa.data_inicio, baixa.data_fim);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_BAIXA_ALTA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Alta dada a {} ({}).", baixa.name, baixa.user_id);
+            Ok(flash::redirect_success(&session, "/baixas", mensagem).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/baixas", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /baixas/{id}/remover - Apaga uma baixa registada por engano
+pub async fn handle_remover_baixa(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    let baixa = baixa_service::remover(&state.db_pool, atual.organizacao_id, id).await?;
+    let detalhes = format!("{}: {} a {}", baixa.user_id, baixa.data_inicio, baixa.data_fim);
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_BAIXA_APAGADA, Some(&id.to_string()), Some(&detalhes)).await;
+    let mensagem = format!("Baixa {} de {} apagada.", id, baixa.name);
+    Ok(flash::redirect_success(&session, "/baixas", mensagem).await.into_response())
+}
@@ -0,0 +1,300 @@
+// src/web/biblioteca_handlers.rs
+//! Biblioteca (/biblioteca, permissão "biblioteca"): emprestar um livro a um utilizador com o prazo de
+//! devolução, registar a devolução, o histórico dos empréstimos e o catálogo. Os atrasos são avisados
+//! pela tarefa "biblioteca.atrasos"; cada utilizador vê os seus empréstimos na página pessoal.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, biblioteca_service},
+    state::AppState,
+    templates::{BibliotecaHistoricoPage, BibliotecaLivrosPage, BibliotecaPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::Duration;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo da cota de um livro.
+const MAX_COTA: usize = 20;
+/// Tamanho máximo do título de um livro.
+const MAX_TITULO: usize = 150;
+/// Tamanho máximo do autor de um livro.
+const MAX_AUTOR: usize = 100;
+
+#[derive(Deserialize, Debug)]
+pub struct HistoricoQuery {
+    #[serde(default)]
+    livro: String, // Só os empréstimos deste livro (vazio = todos)
+    #[serde(default)]
+    user: String, // Só os empréstimos deste utilizador
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EmprestimoForm {
+    livro_id: i64,
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    devolver_ate: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LivroForm {
+    #[serde(default)]
+    cota: String, // Só na criação
+    #[serde(default)]
+    titulo: String,
+    #[serde(default)]
+    autor: String,
+    #[serde(default)]
+    exemplares: String,
+    ativo: Option<String>, // Checkbox (só na alteração): presente = marcada
+}
+
+/// Renderiza o formulário de empréstimo e os empréstimos por devolver.
+async fn pagina_biblioteca(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let hoje = tempo::hoje();
+    let template = BibliotecaPage {
+        livros: biblioteca_service::listar_livros(&state.db_leitura, organizacao_id, true).await?,
+        abertos: biblioteca_service::abertos(&state.db_leitura, organizacao_id, None).await?,
+        hoje: hoje.to_string(),
+        devolver_ate: (hoje + Duration::days(biblioteca_service::DIAS_EMPRESTIMO)).to_string(),
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template BibliotecaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /biblioteca - Empréstimo e livros por devolver
+pub async fn show_biblioteca(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_biblioteca(&state, atual.organizacao_id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /biblioteca - Empresta um livro a um utilizador
+pub async fn handle_emprestar_livro(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<EmprestimoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("user", &form.user, 10);
+    let devolver_ate = v.data("devolver_ate", &form.devolver_ate);
+    let resultado = match (v.resultado(), devolver_ate) {
+        (Ok(()), Some(devolver_ate)) => {
+            biblioteca_service::emprestar(
+                &state.db_pool,
+                atual.organizacao_id,
+                form.livro_id,
+                form.user.trim(),
+                devolver_ate,
+                tempo::hoje(),
+                &atual.id,
+            )
+            .await
+        }
+        (Err(e), _) => Err(e),
+        (Ok(()), None) => Err(AppError::validation("devolver_ate", "Data inválida.")),
+    };
+    match resultado {
+        Ok((_, titulo)) => {
+            let mensagem = format!("«{}» emprestado a {}.", titulo, form.user.trim());
+            Ok(flash::redirect_success(&session, "/biblioteca", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("livro_id", form.livro_id.to_string())
+                .com_valor("user", form.user)
+                .com_valor("devolver_ate", form.devolver_ate);
+            pagina_biblioteca(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /biblioteca/{id}/devolver - Regista a devolução de um livro
+pub async fn handle_devolver_livro(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    match biblioteca_service::devolver(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(emprestimo) => {
+            let mensagem = format!("«{}» devolvido por {}.", emprestimo.titulo, emprestimo.user_id);
+            Ok(flash::redirect_success(&session, "/biblioteca", mensagem).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/biblioteca", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /biblioteca/historico?livro=&user= - Últimos empréstimos
+pub async fn show_historico_biblioteca(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<HistoricoQuery>,
+) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let user = params.user.trim();
+    let livro = params.livro.trim().parse::<i64>().ok();
+    let template = BibliotecaHistoricoPage {
+        emprestimos: biblioteca_service::historico(
+            &state.db_leitura,
+            organizacao_id,
+            livro,
+            Some(user).filter(|u| !u.is_empty()),
+            biblioteca_service::HISTORICO_RECENTE,
+        )
+        .await?,
+        livros: biblioteca_service::listar_livros(&state.db_leitura, organizacao_id, false).await?,
+        livro,
+        user: user.to_string(),
+        hoje: tempo::hoje().to_string(),
+        limite: biblioteca_service::HISTORICO_RECENTE,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template BibliotecaHistoricoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+// --- CATÁLOGO ---
+
+/// Renderiza o catálogo (com os erros do formulário de novo livro, se houver).
+async fn pagina_livros(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = BibliotecaLivrosPage {
+        livros: biblioteca_service::listar_livros(&state.db_leitura, organizacao_id, false).await?,
+        form,
+        max_exemplares: biblioteca_service::MAX_EXEMPLARES,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template BibliotecaLivrosPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /biblioteca/livros - Catálogo (título, autor, exemplares, estado)
+pub async fn show_livros(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_livros(&state, atual.organizacao_id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Exemplares indicados no formulário (1 se vazio).
+fn ler_exemplares(v: &mut Validador, texto: &str) -> i64 {
+    let exemplares = match texto.trim() {
+        "" => 1,
+        texto => texto.parse::<i64>().unwrap_or(-1),
+    };
+    v.intervalo("exemplares", exemplares, 0, biblioteca_service::MAX_EXEMPLARES);
+    exemplares
+}
+
+/// Handler para POST /biblioteca/livros - Acrescenta um livro ao catálogo
+pub async fn handle_criar_livro(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<LivroForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("cota", &form.cota, MAX_COTA);
+    v.obrigatorio("titulo", &form.titulo, MAX_TITULO);
+    if form.autor.trim().chars().count() > MAX_AUTOR {
+        v.erro("autor", format!("Máximo de {} caracteres.", MAX_AUTOR));
+    }
+    let exemplares = ler_exemplares(&mut v, &form.exemplares);
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            biblioteca_service::criar_livro(&state.db_pool, atual.organizacao_id, &form.cota, &form.titulo, &form.autor, exemplares).await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!("{}: {} ({} exemplar(es))", form.cota.trim(), form.titulo.trim(), exemplares);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_BIBLIOTECA_LIVRO_CRIADO, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/biblioteca/livros", format!("Livro {} criado.", form.cota.trim())).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("cota", form.cota)
+                .com_valor("titulo", form.titulo)
+                .com_valor("autor", form.autor)
+                .com_valor("exemplares", form.exemplares);
+            pagina_livros(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /biblioteca/livros/{id} - Altera o título, o autor, os exemplares ou o estado de um livro
+pub async fn handle_atualizar_livro(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<LivroForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("titulo", &form.titulo, MAX_TITULO);
+    if form.autor.trim().chars().count() > MAX_AUTOR {
+        v.erro("autor", format!("Máximo de {} caracteres.", MAX_AUTOR));
+    }
+    let exemplares = ler_exemplares(&mut v, &form.exemplares);
+    if let Err(e) = v.resultado() {
+        return Ok(flash::redirect_error(&session, "/biblioteca/livros", e.user_message()).await.into_response());
+    }
+    let ativo = form.ativo.is_some();
+    let titulo = form.titulo.trim();
+    let autor = form.autor.trim();
+    let anterior = match biblioteca_service::atualizar_livro(&state.db_pool, atual.organizacao_id, id, titulo, autor, exemplares, ativo).await {
+        Ok(anterior) => anterior,
+        Err(e @ AppError::Conflict(_)) => {
+            return Ok(flash::redirect_error(&session, "/biblioteca/livros", e.user_message()).await.into_response());
+        }
+        Err(e) => return Err(e),
+    };
+    let mut alteracoes = Vec::new();
+    if anterior.titulo != titulo {
+        alteracoes.push(format!("título '{}' -> '{}'", anterior.titulo, titulo));
+    }
+    if anterior.autor != autor {
+        alteracoes.push(format!("autor '{}' -> '{}'", anterior.autor, autor));
+    }
+    if anterior.exemplares != exemplares {
+        alteracoes.push(format!("exemplares {} -> {}", anterior.exemplares, exemplares));
+    }
+    if anterior.ativo != ativo {
+        alteracoes.push(if ativo { "ativado" } else { "desativado" }.to_string());
+    }
+    if alteracoes.is_empty() {
+        return Ok(flash::redirect_success(&session, "/biblioteca/livros", "Sem alterações.").await.into_response());
+    }
+    let detalhes = format!("{}: {}", anterior.cota, alteracoes.join(", "));
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_BIBLIOTECA_LIVRO_ALTERADO, Some(&id.to_string()), Some(&detalhes)).await;
+    Ok(flash::redirect_success(&session, "/biblioteca/livros", format!("Livro {} atualizado.", anterior.cota)).await.into_response())
+}
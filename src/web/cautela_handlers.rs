@@ -0,0 +1,382 @@
+// src/web/cautela_handlers.rs
+//! Cautelas de material (/cautelas, permissão "cautela"): entregar material a um utilizador, registar
+//! a devolução, o relatório das cautelas atrasadas e o catálogo do material. Cada utilizador vê as
+//! suas cautelas abertas na página pessoal. O material sensível entrega-se com a senha de quem entrega e
+//! a do utilizador, devolve-se na página de verificação, e os movimentos ficam no registo encadeado.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, cautela_service},
+    state::AppState,
+    templates::{CautelaDevolucaoPage, CautelaMaterialPage, CautelasAtrasadasPage, CautelasPage, CautelasRegistoPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::Duration;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Prazo de devolução proposto no formulário (dias a partir de hoje).
+const PRAZO_PADRAO_DIAS: i64 = 7;
+/// Tamanho máximo do nome e da referência de um material.
+const MAX_NOME: usize = 80;
+/// Tamanho máximo das observações de uma cautela.
+const MAX_OBSERVACOES: usize = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct CautelasQuery {
+    #[serde(default)]
+    user: String, // Só as cautelas deste utilizador (vazio = todas as abertas)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CautelaForm {
+    material_id: i64,
+    #[serde(default)]
+    user: String,
+    quantidade: i64,
+    #[serde(default)]
+    devolucao_prevista: String,
+    #[serde(default)]
+    observacoes: String,
+    #[serde(default)]
+    senha_operador: String, // Só material sensível
+    #[serde(default)]
+    senha_utilizador: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DevolucaoForm {
+    // Só material sensível (a devolução do restante é um botão)
+    quantidade_conferida: Option<String>, // Checkbox: presente = marcada
+    referencia_conferida: Option<String>,
+    #[serde(default)]
+    estado: String,
+    #[serde(default)]
+    senha_operador: String,
+    #[serde(default)]
+    senha_utilizador: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MaterialForm {
+    #[serde(default)]
+    nome: String, // Só na criação
+    #[serde(default)]
+    referencia: String, // Só na criação
+    quantidade: i64,
+    ativo: Option<String>, // Checkbox (só na alteração): presente = marcada
+    sensivel: Option<String>, // Checkbox
+}
+
+/// Renderiza as cautelas abertas (de um utilizador, se indicado) e o formulário de entrega.
+async fn pagina_cautelas(
+    state: &AppState,
+    atual: &CurrentUser,
+    user: &str,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let filtro = Some(user).filter(|u| !u.is_empty());
+    let hoje = tempo::hoje();
+    let template = CautelasPage {
+        material: cautela_service::listar_material(&state.db_leitura, organizacao_id, true).await?,
+        abertas: cautela_service::abertas(&state.db_leitura, organizacao_id, filtro).await?,
+        devolvidas: match filtro {
+            Some(user) => cautela_service::devolvidas(&state.db_leitura, organizacao_id, Some(user), cautela_service::DEVOLVIDAS_RECENTES).await?,
+            None => Vec::new(),
+        },
+        user: user.to_string(),
+        hoje: hoje.to_string(),
+        prazo: (hoje + Duration::days(PRAZO_PADRAO_DIAS)).to_string(),
+        form,
+        max_quantidade: cautela_service::MAX_QUANTIDADE,
+        max_observacoes: MAX_OBSERVACOES,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template CautelasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /cautelas?user= - Cautelas abertas e entrega de material
+pub async fn show_cautelas(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<CautelasQuery>,
+) -> AppResult<Response> {
+    pagina_cautelas(&state, &atual, params.user.trim(), StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /cautelas - Entrega material a um utilizador
+pub async fn handle_emitir_cautela(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<CautelaForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("user", &form.user, 10);
+    v.intervalo("quantidade", form.quantidade, 1, cautela_service::MAX_QUANTIDADE);
+    let prevista = v.data("devolucao_prevista", &form.devolucao_prevista);
+    if let Some(prevista) = prevista.filter(|d| *d < tempo::hoje()) {
+        v.erro("devolucao_prevista", format!("A devolução prevista ({}) já passou.", prevista));
+    }
+    if form.observacoes.trim().chars().count() > MAX_OBSERVACOES {
+        v.erro("observacoes", format!("Máximo de {} caracteres.", MAX_OBSERVACOES));
+    }
+    let resultado = match (v.resultado(), prevista) {
+        (Ok(()), Some(prevista)) => {
+            cautela_service::emitir(
+                &state.db_pool,
+                atual.organizacao_id,
+                form.material_id,
+                form.user.trim(),
+                form.quantidade,
+                prevista,
+                &form.observacoes,
+                &atual.id,
+                &cautela_service::ConfirmacaoDupla {
+                    senha_operador: &form.senha_operador,
+                    senha_utilizador: &form.senha_utilizador,
+                },
+            )
+            .await
+        }
+        (Err(e), _) => Err(e),
+        (Ok(()), None) => Err(AppError::validation("devolucao_prevista", "Data inválida.")),
+    };
+    match resultado {
+        Ok((id, material)) => {
+            let mensagem = format!(
+                "Cautela {}: {} x {} entregue a {}, a devolver até {}.",
+                id,
+                form.quantidade,
+                material,
+                form.user.trim(),
+                form.devolucao_prevista.trim()
+            );
+            Ok(flash::redirect_success(&session, "/cautelas", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("material_id", form.material_id.to_string())
+                .com_valor("user", form.user)
+                .com_valor("quantidade", form.quantidade.to_string())
+                .com_valor("devolucao_prevista", form.devolucao_prevista)
+                .com_valor("observacoes", form.observacoes);
+            pagina_cautelas(&state, &atual, "", StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Renderiza a verificação da devolução de uma cautela aberta.
+async fn pagina_devolucao(state: &AppState, organizacao_id: i64, id: i64, status: StatusCode, form: FormState) -> AppResult<Response> {
+    let template = CautelaDevolucaoPage {
+        cautela: cautela_service::aberta(&state.db_leitura, organizacao_id, id).await?,
+        form,
+        max_estado: cautela_service::MAX_ESTADO,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template CautelaDevolucaoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /cautelas/{id}/devolver - Verificação da devolução (material sensível)
+pub async fn show_devolver_cautela(State(state): State<AppState>, atual: CurrentUser, Path(id): Path<i64>) -> AppResult<Response> {
+    pagina_devolucao(&state, atual.organizacao_id, id, StatusCode::OK, FormState::default()).await
+}
+
+/// Handler para POST /cautelas/{id}/devolver - Regista a devolução de uma cautela
+pub async fn handle_devolver_cautela(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<DevolucaoForm>,
+) -> AppResult<Response> {
+    let verificacao = cautela_service::VerificacaoDevolucao {
+        quantidade_conferida: form.quantidade_conferida.is_some(),
+        referencia_conferida: form.referencia_conferida.is_some(),
+        estado: &form.estado,
+    };
+    let confirmacao = cautela_service::ConfirmacaoDupla {
+        senha_operador: &form.senha_operador,
+        senha_utilizador: &form.senha_utilizador,
+    };
+    match cautela_service::devolver(&state.db_pool, atual.organizacao_id, id, &atual.id, &verificacao, &confirmacao).await {
+        Ok((user_id, material)) => {
+            let mensagem = format!("Cautela {} devolvida: {} de {}.", id, material, user_id);
+            Ok(flash::redirect_success(&session, "/cautelas", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            // As senhas nunca voltam ao formulário
+            let mut form_state = FormState::com_erros(erros).com_valor("estado", form.estado.clone());
+            if form.quantidade_conferida.is_some() {
+                form_state = form_state.com_valor("quantidade_conferida", "on");
+            }
+            if form.referencia_conferida.is_some() {
+                form_state = form_state.com_valor("referencia_conferida", "on");
+            }
+            pagina_devolucao(&state, atual.organizacao_id, id, StatusCode::UNPROCESSABLE_ENTITY, form_state).await
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/cautelas", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /cautelas/registo - Registo encadeado do material sensível e a sua verificação
+pub async fn show_registo_cautelas(State(state): State<AppState>, atual: CurrentUser) -> AppResult<Response> {
+    let template = CautelasRegistoPage {
+        verificacao: cautela_service::verificar_registo(&state.db_leitura, atual.organizacao_id).await?,
+        registos: cautela_service::registos(&state.db_leitura, atual.organizacao_id, cautela_service::REGISTOS_RECENTES).await?,
+        limite: cautela_service::REGISTOS_RECENTES,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template CautelasRegistoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /cautelas/atrasadas - Cautelas abertas depois da data prevista
+pub async fn show_cautelas_atrasadas(State(state): State<AppState>, atual: CurrentUser) -> AppResult<Response> {
+    let hoje = tempo::hoje();
+    let template = CautelasAtrasadasPage {
+        atrasadas: cautela_service::atrasadas(&state.db_leitura, atual.organizacao_id, hoje).await?,
+        hoje: hoje.to_string(),
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template CautelasAtrasadasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+// --- CATÁLOGO DO MATERIAL ---
+
+/// Renderiza o catálogo (com os erros do formulário de novo material, se houver).
+async fn pagina_material(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = CautelaMaterialPage {
+        material: cautela_service::listar_material(&state.db_leitura, organizacao_id, false).await?,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template CautelaMaterialPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /cautelas/material - Catálogo do material (existências, em uso, estado)
+pub async fn show_material(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_material(&state, atual.organizacao_id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /cautelas/material - Acrescenta material ao catálogo
+pub async fn handle_criar_material(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<MaterialForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("nome", &form.nome, MAX_NOME);
+    if form.referencia.trim().chars().count() > MAX_NOME {
+        v.erro("referencia", format!("Máximo de {} caracteres.", MAX_NOME));
+    }
+    v.intervalo("quantidade", form.quantidade, 0, cautela_service::MAX_EXISTENCIAS);
+    let sensivel = form.sensivel.is_some();
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            cautela_service::criar_material(&state.db_pool, atual.organizacao_id, &form.nome, &form.referencia, form.quantidade, sensivel)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!("{} (existências {}{})", form.nome.trim(), form.quantidade, if sensivel { ", sensível" } else { "" });
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_MATERIAL_CRIADO, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/cautelas/material", format!("Material '{}' criado.", form.nome.trim())).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("nome", form.nome)
+                .com_valor("referencia", form.referencia)
+                .com_valor("quantidade", form.quantidade.to_string())
+                .com_valor("sensivel", if sensivel { "on" } else { "" });
+            pagina_material(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /cautelas/material/{id} - Altera as existências ou o estado de um material
+pub async fn handle_atualizar_material(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<MaterialForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.intervalo("quantidade", form.quantidade, 0, cautela_service::MAX_EXISTENCIAS);
+    let ativo = form.ativo.is_some();
+    let sensivel = form.sensivel.is_some();
+    let resultado = match v.resultado() {
+        Ok(()) => cautela_service::atualizar_material(&state.db_pool, atual.organizacao_id, id, form.quantidade, ativo, sensivel).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(anterior) => {
+            let mut alteracoes = Vec::new();
+            if anterior.quantidade != form.quantidade {
+                alteracoes.push(format!("existências {} -> {}", anterior.quantidade, form.quantidade));
+            }
+            if anterior.ativo != ativo {
+                alteracoes.push(if ativo { "ativado" } else { "desativado" }.to_string());
+            }
+            if anterior.sensivel != sensivel {
+                alteracoes.push(if sensivel { "marcado como sensível" } else { "deixou de ser sensível" }.to_string());
+            }
+            if alteracoes.is_empty() {
+                return Ok(flash::redirect_success(&session, "/cautelas/material", "Sem alterações.").await.into_response());
+            }
+            let detalhes = format!("{}: {}", anterior.nome, alteracoes.join(", "));
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_MATERIAL_ALTERADO, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/cautelas/material", format!("Material '{}' atualizado.", anterior.nome)).await.into_response())
+        }
+        Err(e @ AppError::Validation(_)) => Ok(flash::redirect_error(&session, "/cautelas/material", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,391 @@
+// src/web/chamada_handlers.rs
+//! Plano de chamada (/chamada): cada utilizador indica o seu contacto de emergência, vê quem o chama e a
+//! quem liga em cada plano e confirma a receção num exercício. Com a permissão "chamada": criar os planos
+//! por turma ou grupo, ajustar a cascata, imprimir o plano e conduzir os exercícios.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, chamada_service, grupo_service, horario_service, permission_service},
+    state::AppState,
+    templates::{ChamadaExercicioPage, ChamadaImprimirPage, ChamadaPage, ChamadaPlanoPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do nome (do plano e do contacto).
+const MAX_NOME: usize = 100;
+/// Tamanho máximo do parentesco do contacto.
+const MAX_PARENTESCO: usize = 50;
+
+#[derive(Deserialize, Debug)]
+pub struct ContactoForm {
+    #[serde(default)]
+    contacto_nome: String,
+    #[serde(default)]
+    contacto_parentesco: String,
+    #[serde(default)]
+    contacto_telefone: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PlanoForm {
+    #[serde(default)]
+    nome: String,
+    #[serde(default)]
+    alvo: String, // "turma:<turma>" ou "grupo:<id>"
+    #[serde(default)]
+    ramificacao: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CascataForm {
+    // Pares paralelos: membro e o seu chamador (vazio = raiz)
+    #[serde(default)]
+    user_id: Vec<String>,
+    #[serde(default)]
+    chamador: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ConfirmarForm {
+    #[serde(default)]
+    user: String, // Vazio = o próprio; outro = confirmação recebida por quem gere
+}
+
+/// Pode gerir os planos e os exercícios?
+async fn pode_gerir(state: &AppState, atual: &CurrentUser) -> AppResult<bool> {
+    atual.tem_permissao(state, permission_service::PERM_CHAMADA).await
+}
+
+/// Falha (403) se o utilizador não gere o plano de chamada.
+async fn exigir_gestao(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if pode_gerir(state, atual).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Plano de chamada: {} sem permissão '{}'.", atual.id, permission_service::PERM_CHAMADA);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Renderiza a página do utilizador (e, para quem gere, os planos).
+async fn pagina_chamada(state: &AppState, atual: &CurrentUser, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let pode_gerir = pode_gerir(state, atual).await?;
+    let (planos, turmas, grupos) = if pode_gerir {
+        (
+            chamada_service::planos(&state.db_leitura, organizacao_id).await?,
+            horario_service::turmas(&state.db_leitura, organizacao_id).await?,
+            grupo_service::listar_grupos(&state.db_leitura, organizacao_id).await?,
+        )
+    } else {
+        (Vec::new(), Vec::new(), Vec::new())
+    };
+    let template = ChamadaPage {
+        contacto: chamada_service::contacto(&state.db_leitura, &atual.id).await?,
+        posicoes: chamada_service::posicoes(&state.db_leitura, organizacao_id, &atual.id).await?,
+        pode_gerir,
+        planos,
+        turmas,
+        grupos,
+        max_ramificacao: chamada_service::MAX_RAMIFICACAO,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ChamadaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /chamada - Contacto de emergência, o meu lugar nas cascatas e os planos
+pub async fn show_chamada(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    let mut form = FormState::default().com_valor("ramificacao", chamada_service::RAMIFICACAO_PADRAO.to_string());
+    if let Some(contacto) = chamada_service::contacto(&state.db_leitura, &atual.id).await? {
+        form = form
+            .com_valor("contacto_nome", contacto.nome)
+            .com_valor("contacto_parentesco", contacto.parentesco)
+            .com_valor("contacto_telefone", contacto.telefone);
+    }
+    pagina_chamada(&state, &atual, StatusCode::OK, form, flash).await
+}
+
+/// Handler para POST /chamada/contacto - Guarda o contacto de emergência do próprio
+pub async fn handle_guardar_contacto(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<ContactoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("contacto_nome", &form.contacto_nome, MAX_NOME);
+    if form.contacto_parentesco.trim().chars().count() > MAX_PARENTESCO {
+        v.erro("contacto_parentesco", format!("Máximo de {} caracteres.", MAX_PARENTESCO));
+    }
+    v.obrigatorio("contacto_telefone", &form.contacto_telefone, 20);
+    v.telefone("contacto_telefone", &form.contacto_telefone);
+    if let Err(AppError::Validation(erros)) = v.resultado() {
+        let form_state = FormState::com_erros(erros)
+            .com_valor("contacto_nome", form.contacto_nome)
+            .com_valor("contacto_parentesco", form.contacto_parentesco)
+            .com_valor("contacto_telefone", form.contacto_telefone)
+            .com_valor("ramificacao", chamada_service::RAMIFICACAO_PADRAO.to_string());
+        return pagina_chamada(&state, &atual, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await;
+    }
+    chamada_service::guardar_contacto(&state.db_pool, &atual.id, &form.contacto_nome, &form.contacto_parentesco, &form.contacto_telefone).await?;
+    Ok(flash::redirect_success(&session, "/chamada", "Contacto de emergência guardado.").await.into_response())
+}
+
+/// Handler para POST /chamada/planos - Cria o plano de uma turma ou de um grupo (com a cascata gerada)
+pub async fn handle_criar_plano(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<PlanoForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let mut v = Validador::default();
+    v.obrigatorio("nome", &form.nome, MAX_NOME);
+    let ramificacao = form.ramificacao.trim().parse::<i64>().unwrap_or(0);
+    v.intervalo("ramificacao", ramificacao, 1, chamada_service::MAX_RAMIFICACAO);
+    let (turma, grupo_id) = match form.alvo.split_once(':') {
+        Some(("turma", turma)) => (Some(turma), None),
+        Some(("grupo", id)) => (None, id.parse::<i64>().ok()),
+        _ => (None, None),
+    };
+    if turma.is_none() && grupo_id.is_none() {
+        v.erro("alvo", "Escolha uma turma ou um grupo.");
+    }
+    let resultado = match v.resultado() {
+        Ok(()) => chamada_service::criar_plano(&state.db_pool, atual.organizacao_id, &form.nome, turma, grupo_id, ramificacao, &atual.id).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!("{} ({})", form.nome.trim(), form.alvo);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CHAMADA_PLANO_CRIADO, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Plano de chamada \"{}\" criado. Reveja a cascata e imprima-o.", form.nome.trim());
+            Ok(flash::redirect_success(&session, &format!("/chamada/planos/{}", id), mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("nome", form.nome)
+                .com_valor("alvo", form.alvo)
+                .com_valor("ramificacao", form.ramificacao);
+            pagina_chamada(&state, &atual, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Renderiza um plano com a cascata e os exercícios.
+async fn pagina_plano(state: &AppState, atual: &CurrentUser, id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let plano = chamada_service::obter_plano(&state.db_leitura, atual.organizacao_id, id).await?;
+    let template = ChamadaPlanoPage {
+        nos: chamada_service::cascata(&state.db_leitura, id, None).await?,
+        exercicios: chamada_service::exercicios(&state.db_leitura, atual.organizacao_id, id, chamada_service::EXERCICIOS_RECENTES).await?,
+        plano,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ChamadaPlanoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /chamada/planos/{id} - Cascata e exercícios de um plano
+pub async fn show_plano(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    pagina_plano(&state, &atual, id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /chamada/planos/{id}/cascata - Altera o chamador dos membros
+pub async fn handle_definir_cascata(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<CascataForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let destino = format!("/chamada/planos/{}", id);
+    let pares: Vec<(String, String)> = form.user_id.into_iter().zip(form.chamador).collect();
+    match chamada_service::definir_chamadores(&state.db_pool, atual.organizacao_id, id, &pares).await {
+        Ok(()) => {
+            let detalhes = format!("{} membros", pares.len());
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CHAMADA_CASCATA, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, &destino, "Cascata guardada.").await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            pagina_plano(&state, &atual, id, StatusCode::UNPROCESSABLE_ENTITY, FormState::com_erros(erros), Flash::default()).await
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, &destino, mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /chamada/planos/{id}/regenerar - Refaz a cascata com os membros atuais
+pub async fn handle_regenerar_plano(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let destino = format!("/chamada/planos/{}", id);
+    match chamada_service::regenerar(&state.db_pool, atual.organizacao_id, id).await {
+        Ok(membros) => {
+            let detalhes = format!("regenerada com {} membros", membros);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CHAMADA_CASCATA, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, &destino, format!("Cascata regenerada com {} membros.", membros)).await.into_response())
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, &destino, mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /chamada/planos/{id}/apagar - Apaga um plano (com os exercícios)
+pub async fn handle_apagar_plano(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let plano = chamada_service::apagar_plano(&state.db_pool, atual.organizacao_id, id).await?;
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CHAMADA_PLANO_APAGADO, Some(&id.to_string()), Some(&plano.nome)).await;
+    Ok(flash::redirect_success(&session, "/chamada", format!("Plano \"{}\" apagado.", plano.nome)).await.into_response())
+}
+
+/// Handler para GET /chamada/planos/{id}/imprimir - Plano de chamada para impressão
+pub async fn show_imprimir_plano(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let template = ChamadaImprimirPage {
+        plano: chamada_service::obter_plano(&state.db_leitura, atual.organizacao_id, id).await?,
+        nos: chamada_service::cascata(&state.db_leitura, id, None).await?,
+        gerado_em: tempo::agora().format(tempo::FORMATO_DATA_HORA).to_string(),
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ChamadaImprimirPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /chamada/planos/{id}/exercicio - Inicia um exercício (avisa os membros)
+pub async fn handle_iniciar_exercicio(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    match chamada_service::iniciar_exercicio(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(exercicio_id) => {
+            let detalhes = format!("plano {}", id);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CHAMADA_EXERCICIO_INICIADO, Some(&exercicio_id.to_string()), Some(&detalhes)).await;
+            let mensagem = "Exercício iniciado: os membros foram avisados. Registe as confirmações recebidas.";
+            Ok(flash::redirect_success(&session, &format!("/chamada/exercicios/{}", exercicio_id), mensagem).await.into_response())
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, &format!("/chamada/planos/{}", id), mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /chamada/exercicios/{id} - Progresso de um exercício
+pub async fn show_exercicio(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let exercicio = chamada_service::obter_exercicio(&state.db_leitura, atual.organizacao_id, id).await?;
+    let template = ChamadaExercicioPage {
+        nos: chamada_service::cascata(&state.db_leitura, exercicio.plano_id, Some(id)).await?,
+        exercicio,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ChamadaExercicioPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /chamada/exercicios/{id}/confirmar - Confirma a receção (o próprio, ou quem gere
+/// por um membro)
+pub async fn handle_confirmar(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<ConfirmarForm>,
+) -> AppResult<Response> {
+    let (user_id, destino) = match form.user.trim() {
+        "" => (atual.id.clone(), "/chamada".to_string()),
+        outro if outro == atual.id => (atual.id.clone(), "/chamada".to_string()),
+        outro => {
+            exigir_gestao(&state, &atual).await?;
+            (outro.to_string(), format!("/chamada/exercicios/{}", id))
+        }
+    };
+    match chamada_service::confirmar(&state.db_pool, atual.organizacao_id, id, &user_id, &atual.id).await {
+        Ok(true) => Ok(flash::redirect_success(&session, &destino, format!("Receção de {} confirmada.", user_id)).await.into_response()),
+        Ok(false) => Ok(flash::redirect_success(&session, &destino, format!("A receção de {} já estava confirmada.", user_id)).await.into_response()),
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, &destino, mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /chamada/exercicios/{id}/encerrar - Encerra o exercício
+pub async fn handle_encerrar_exercicio(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    match chamada_service::encerrar_exercicio(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(exercicio) => {
+            let detalhes = format!("{}: {}/{} confirmações", exercicio.plano, exercicio.confirmados, exercicio.total);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CHAMADA_EXERCICIO_ENCERRADO, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Exercício encerrado: {} de {} membros confirmaram.", exercicio.confirmados, exercicio.total);
+            Ok(flash::redirect_success(&session, &format!("/chamada/planos/{}", exercicio.plano_id), mensagem).await.into_response())
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, &format!("/chamada/exercicios/{}", id), mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,275 @@
+// src/web/claviculario_handlers.rs
+//! Claviculário (/claviculario, permissão "claviculario"): entregar uma chave a um utilizador, registar a
+//! devolução, a lista das chaves fora depois da hora limite, o histórico dos levantamentos e o registo
+//! das chaves. Cada utilizador vê as chaves que tem na página pessoal.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, claviculario_service},
+    state::AppState,
+    templates::{ClavicularioAtrasadasPage, ClavicularioChavesPage, ClavicularioHistoricoPage, ClavicularioPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do código de uma chave.
+const MAX_CODIGO: usize = 20;
+/// Tamanho máximo do local (sala ou área) de uma chave.
+const MAX_LOCAL: usize = 80;
+/// Tamanho máximo das observações de um levantamento.
+const MAX_OBSERVACOES: usize = 200;
+
+#[derive(Deserialize, Debug)]
+pub struct HistoricoQuery {
+    #[serde(default)]
+    chave: String, // Só os levantamentos desta chave (vazio = todas)
+    #[serde(default)]
+    user: String, // Só os levantamentos deste utilizador
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LevantamentoForm {
+    chave_id: i64,
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    observacoes: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChaveForm {
+    #[serde(default)]
+    codigo: String, // Só na criação
+    #[serde(default)]
+    local: String,
+    #[serde(default)]
+    hora_limite: String,
+    ativa: Option<String>, // Checkbox (só na alteração): presente = marcada
+}
+
+/// Renderiza o quadro das chaves e o formulário de levantamento.
+async fn pagina_claviculario(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = ClavicularioPage {
+        chaves: claviculario_service::listar_chaves(&state.db_leitura, organizacao_id, true).await?,
+        agora: tempo::agora().naive_local(),
+        form,
+        max_observacoes: MAX_OBSERVACOES,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ClavicularioPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /claviculario - Quadro das chaves e levantamento
+pub async fn show_claviculario(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_claviculario(&state, atual.organizacao_id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /claviculario - Entrega uma chave a um utilizador
+pub async fn handle_levantar_chave(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<LevantamentoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("user", &form.user, 10);
+    if form.observacoes.trim().chars().count() > MAX_OBSERVACOES {
+        v.erro("observacoes", format!("Máximo de {} caracteres.", MAX_OBSERVACOES));
+    }
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            claviculario_service::levantar(&state.db_pool, atual.organizacao_id, form.chave_id, form.user.trim(), &form.observacoes, &atual.id)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((_, codigo)) => {
+            let mensagem = format!("Chave {} entregue a {}.", codigo, form.user.trim());
+            Ok(flash::redirect_success(&session, "/claviculario", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("chave_id", form.chave_id.to_string())
+                .com_valor("user", form.user)
+                .com_valor("observacoes", form.observacoes);
+            pagina_claviculario(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /claviculario/{id}/devolver - Regista a devolução de uma chave
+pub async fn handle_devolver_chave(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    match claviculario_service::devolver(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(movimento) => {
+            let mensagem = format!("Chave {} devolvida por {}.", movimento.codigo, movimento.user_id);
+            Ok(flash::redirect_success(&session, "/claviculario", mensagem).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/claviculario", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /claviculario/atrasadas - Chaves fora depois da hora limite
+pub async fn show_chaves_atrasadas(State(state): State<AppState>, atual: CurrentUser) -> AppResult<Response> {
+    let agora = tempo::agora();
+    let template = ClavicularioAtrasadasPage {
+        atrasadas: claviculario_service::atrasadas(&state.db_leitura, atual.organizacao_id, agora.naive_local()).await?,
+        agora: agora.format(tempo::FORMATO_DATA_HORA).to_string(),
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ClavicularioAtrasadasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /claviculario/historico?chave=&user= - Últimos levantamentos
+pub async fn show_historico_chaves(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<HistoricoQuery>,
+) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let user = params.user.trim();
+    let chave = params.chave.trim().parse::<i64>().ok();
+    let template = ClavicularioHistoricoPage {
+        movimentos: claviculario_service::historico(
+            &state.db_leitura,
+            organizacao_id,
+            chave,
+            Some(user).filter(|u| !u.is_empty()),
+            claviculario_service::HISTORICO_RECENTE,
+        )
+        .await?,
+        chaves: claviculario_service::listar_chaves(&state.db_leitura, organizacao_id, false).await?,
+        chave,
+        user: user.to_string(),
+        limite: claviculario_service::HISTORICO_RECENTE,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ClavicularioHistoricoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+// --- REGISTO DAS CHAVES ---
+
+/// Renderiza o registo das chaves (com os erros do formulário de nova chave, se houver).
+async fn pagina_chaves(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = ClavicularioChavesPage {
+        chaves: claviculario_service::listar_chaves(&state.db_leitura, organizacao_id, false).await?,
+        form,
+        hora_limite_padrao: claviculario_service::HORA_LIMITE_PADRAO,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ClavicularioChavesPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /claviculario/chaves - Registo das chaves (local, hora limite, estado)
+pub async fn show_chaves(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_chaves(&state, atual.organizacao_id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /claviculario/chaves - Acrescenta uma chave ao registo
+pub async fn handle_criar_chave(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<ChaveForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("codigo", &form.codigo, MAX_CODIGO);
+    v.obrigatorio("local", &form.local, MAX_LOCAL);
+    v.hora("hora_limite", &form.hora_limite);
+    let resultado = match v.resultado() {
+        Ok(()) => claviculario_service::criar_chave(&state.db_pool, atual.organizacao_id, &form.codigo, &form.local, &form.hora_limite).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!("{} ({}, até às {})", form.codigo.trim(), form.local.trim(), form.hora_limite.trim());
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CHAVE_CRIADA, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/claviculario/chaves", format!("Chave {} criada.", form.codigo.trim())).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("codigo", form.codigo)
+                .com_valor("local", form.local)
+                .com_valor("hora_limite", form.hora_limite);
+            pagina_chaves(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /claviculario/chaves/{id} - Altera o local, a hora limite ou o estado de uma chave
+pub async fn handle_atualizar_chave(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<ChaveForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("local", &form.local, MAX_LOCAL);
+    v.hora("hora_limite", &form.hora_limite);
+    if let Err(e) = v.resultado() {
+        return Ok(flash::redirect_error(&session, "/claviculario/chaves", e.user_message()).await.into_response());
+    }
+    let ativa = form.ativa.is_some();
+    let local = form.local.trim();
+    let hora_limite = form.hora_limite.trim();
+    let anterior = claviculario_service::atualizar_chave(&state.db_pool, atual.organizacao_id, id, local, hora_limite, ativa).await?;
+    let mut alteracoes = Vec::new();
+    if anterior.local != local {
+        alteracoes.push(format!("local '{}' -> '{}'", anterior.local, local));
+    }
+    if anterior.hora_limite != hora_limite {
+        alteracoes.push(format!("hora limite {} -> {}", anterior.hora_limite, hora_limite));
+    }
+    if anterior.ativa != ativa {
+        alteracoes.push(if ativa { "ativada" } else { "desativada" }.to_string());
+    }
+    if alteracoes.is_empty() {
+        return Ok(flash::redirect_success(&session, "/claviculario/chaves", "Sem alterações.").await.into_response());
+    }
+    let detalhes = format!("{}: {}", anterior.codigo, alteracoes.join(", "));
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CHAVE_ALTERADA, Some(&id.to_string()), Some(&detalhes)).await;
+    Ok(flash::redirect_success(&session, "/claviculario/chaves", format!("Chave {} atualizada.", anterior.codigo)).await.into_response())
+}
@@ -0,0 +1,267 @@
+// src/web/comitiva_handlers.rs
+//! Comitivas (/comitivas, permissão "comitivas"): criar a comitiva de uma competição ou visita com o
+//! responsável e os membros, acrescentar ou retirar membros enquanto está aberta e encerrá-la no regresso.
+//! As indisponibilidades e o estado na presença ficam com `comitiva_service`.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, comitiva_service, permission_service},
+    state::AppState,
+    templates::{ComitivaPage, ComitivasPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do nome e do destino de uma comitiva.
+const MAX_NOME: usize = 100;
+
+#[derive(Deserialize, Debug)]
+pub struct ComitivaForm {
+    #[serde(default)]
+    nome: String,
+    #[serde(default)]
+    tipo: String,
+    #[serde(default)]
+    destino: String,
+    #[serde(default)]
+    data_inicio: String,
+    #[serde(default)]
+    data_fim: String,
+    #[serde(default)]
+    responsavel: String,
+    #[serde(default)]
+    membros: String, // IDs separados por espaços, vírgulas ou linhas
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MembrosForm {
+    #[serde(default)]
+    membros: String,
+}
+
+/// Falha (403) se o utilizador não gere as comitivas.
+async fn exigir_gestao(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if atual.tem_permissao(state, permission_service::PERM_COMITIVAS).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Comitivas: {} sem permissão '{}'.", atual.id, permission_service::PERM_COMITIVAS);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// IDs de uma lista (espaços, vírgulas, pontos e vírgulas ou linhas).
+fn ler_ids(lista: &str) -> Vec<String> {
+    lista
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Renderiza as comitivas abertas, as encerradas recentemente e o formulário de criação.
+async fn pagina_comitivas(state: &AppState, atual: &CurrentUser, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let template = ComitivasPage {
+        abertas: comitiva_service::abertas(&state.db_leitura, organizacao_id).await?,
+        encerradas: comitiva_service::encerradas(&state.db_leitura, organizacao_id, comitiva_service::ENCERRADAS_RECENTES).await?,
+        tipos: comitiva_service::TIPOS,
+        hoje: tempo::hoje().to_string(),
+        max_nome: MAX_NOME,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ComitivasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Renderiza uma comitiva com os membros.
+async fn pagina_comitiva(state: &AppState, atual: &CurrentUser, id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = ComitivaPage {
+        comitiva: comitiva_service::obter(&state.db_leitura, atual.organizacao_id, id).await?,
+        membros: comitiva_service::membros(&state.db_leitura, id).await?,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ComitivaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /comitivas - Comitivas e criação de uma nova
+pub async fn show_comitivas(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let form = FormState::default().com_valor("tipo", "competicao");
+    pagina_comitivas(&state, &atual, StatusCode::OK, form, flash).await
+}
+
+/// Handler para POST /comitivas - Cria uma comitiva (membros fora da escala no período)
+pub async fn handle_criar_comitiva(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<ComitivaForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let mut v = Validador::default();
+    v.obrigatorio("nome", &form.nome, MAX_NOME);
+    v.obrigatorio("responsavel", &form.responsavel, 10);
+    if form.destino.trim().chars().count() > MAX_NOME {
+        v.erro("destino", format!("Máximo de {} caracteres.", MAX_NOME));
+    }
+    v.periodo("data_inicio", &form.data_inicio, "data_fim", &form.data_fim);
+    let periodo = (tempo::ler_data(&form.data_inicio), tempo::ler_data(&form.data_fim));
+    let membros = ler_ids(&form.membros);
+    let resultado = match (v.resultado(), periodo) {
+        (Ok(()), (Some(inicio), Some(fim))) => {
+            comitiva_service::criar(
+                &state.db_pool,
+                atual.organizacao_id,
+                &form.nome,
+                form.tipo.trim(),
+                &form.destino,
+                inicio,
+                fim,
+                form.responsavel.trim(),
+                &membros,
+                tempo::hoje(),
+                &atual.id,
+            )
+            .await
+        }
+        (Err(e), _) => Err(e),
+        (Ok(()), _) => Err(AppError::validation("data_inicio", "Data inválida.")),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!(
+                "{} ({} a {}), responsável {}, membros: {}",
+                form.nome.trim(),
+                form.data_inicio.trim(),
+                form.data_fim.trim(),
+                form.responsavel.trim(),
+                membros.join(", ")
+            );
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_COMITIVA_CRIADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Comitiva \"{}\" criada: os membros ficam fora da escala e, no período, fora na presença.", form.nome.trim());
+            Ok(flash::redirect_success(&session, &format!("/comitivas/{}", id), mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("nome", form.nome)
+                .com_valor("tipo", form.tipo)
+                .com_valor("destino", form.destino)
+                .com_valor("data_inicio", form.data_inicio)
+                .com_valor("data_fim", form.data_fim)
+                .com_valor("responsavel", form.responsavel)
+                .com_valor("membros", form.membros);
+            pagina_comitivas(&state, &atual, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /comitivas/{id} - Membros de uma comitiva
+pub async fn show_comitiva(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    pagina_comitiva(&state, &atual, id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /comitivas/{id}/membros - Acrescenta membros a uma comitiva aberta
+pub async fn handle_adicionar_membros(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<MembrosForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let destino = format!("/comitivas/{}", id);
+    let ids = ler_ids(&form.membros);
+    if ids.is_empty() {
+        return Ok(flash::redirect_error(&session, &destino, "Indique pelo menos um ID.").await.into_response());
+    }
+    match comitiva_service::adicionar_membros(&state.db_pool, atual.organizacao_id, id, &ids).await {
+        Ok(acrescentados) => {
+            let detalhes = format!("+ {}", ids.join(", "));
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_COMITIVA_MEMBROS, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = match acrescentados {
+                0 => "Todos os indicados já eram membros.".to_string(),
+                n => format!("{} membro(s) acrescentado(s) à comitiva.", n),
+            };
+            Ok(flash::redirect_success(&session, &destino, mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros).com_valor("membros", form.membros);
+            pagina_comitiva(&state, &atual, id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, &destino, mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /comitivas/{id}/membros/{user_id}/remover - Retira um membro (volta à escala)
+pub async fn handle_remover_membro(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path((id, user_id)): Path<(i64, String)>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let destino = format!("/comitivas/{}", id);
+    match comitiva_service::remover_membro(&state.db_pool, atual.organizacao_id, id, &user_id).await {
+        Ok(comitiva) => {
+            let detalhes = format!("- {}", user_id);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_COMITIVA_MEMBROS, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("{} retirado da comitiva \"{}\".", user_id, comitiva.nome);
+            Ok(flash::redirect_success(&session, &destino, mensagem).await.into_response())
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, &destino, mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /comitivas/{id}/encerrar - Encerra a comitiva (membros de volta à escala e à presença)
+pub async fn handle_encerrar_comitiva(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    match comitiva_service::encerrar(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok((comitiva, libertados)) => {
+            let detalhes = format!("{}: {} indisponibilidades apagadas", comitiva.nome, libertados);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_COMITIVA_ENCERRADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Comitiva \"{}\" encerrada: os {} membros voltam à escala e à presença.", comitiva.nome, comitiva.membros);
+            Ok(flash::redirect_success(&session, "/comitivas", mensagem).await.into_response())
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, &format!("/comitivas/{}", id), mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
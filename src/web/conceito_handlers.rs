@@ -0,0 +1,256 @@
+// src/web/conceito_handlers.rs
+//! Conceito (/conceito): cada utilizador vê os seus lançamentos e o conceito de cada semestre. Com a
+//! permissão "conceito": consultar o de qualquer utilizador, registar elogios, méritos e deméritos, apagar
+//! os registados por engano e a classificação por turma (/conceito/classificacao, para impressão).
+//! Os lançamentos das FATD vêm da decisão do processo (ver `disciplina_service`).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::conceito::PeriodoConceito,
+    services::{audit_service, conceito_service, permission_service, user_service},
+    state::AppState,
+    templates::{ConceitoClassificacaoPage, ConceitoPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do motivo.
+const MAX_MOTIVO: usize = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct ConceitoQuery {
+    #[serde(default)]
+    user: String, // Conceito deste utilizador (vazio = o próprio)
+    #[serde(default)]
+    periodo: String, // Semestre ("AAAA-1"/"AAAA-2"; vazio = o atual)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ClassificacaoQuery {
+    #[serde(default)]
+    periodo: String,
+    #[serde(default)]
+    turma: String, // Só esta turma (vazio = todas)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ConceitoForm {
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    data: String,
+    #[serde(default)]
+    origem: String,
+    #[serde(default)]
+    pontos: String,
+    #[serde(default)]
+    motivo: String,
+}
+
+/// Pode lançar no conceito e ver a classificação?
+async fn pode_gerir(state: &AppState, atual: &CurrentUser) -> AppResult<bool> {
+    atual.tem_permissao(state, permission_service::PERM_CONCEITO).await
+}
+
+/// Falha (403) se o utilizador não gere o conceito.
+async fn exigir_gestao(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if pode_gerir(state, atual).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Conceito: {} sem permissão '{}'.", atual.id, permission_service::PERM_CONCEITO);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Semestre pedido (`?periodo=`), ou o atual.
+fn periodo_pedido(codigo: &str) -> PeriodoConceito {
+    conceito_service::ler_periodo(codigo).unwrap_or_else(|| conceito_service::periodo_de(tempo::hoje()))
+}
+
+/// Endereço do conceito de um utilizador num semestre.
+fn url_conceito(user_id: &str, periodo: &PeriodoConceito) -> String {
+    format!("/conceito?user={}&periodo={}", user_id, periodo.codigo)
+}
+
+/// Renderiza os lançamentos de um utilizador no semestre (e o formulário, para quem gere). Sem a
+/// permissão, só o próprio.
+async fn pagina_conceito(
+    state: &AppState,
+    atual: &CurrentUser,
+    params: &ConceitoQuery,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let pode_gerir = pode_gerir(state, atual).await?;
+    let user_id = match params.user.trim() {
+        "" => atual.id.clone(),
+        outro if outro == atual.id || pode_gerir => outro.to_string(),
+        outro => {
+            tracing::warn!("Conceito: {} tentou ver o conceito de {} sem permissão.", atual.id, outro);
+            return Err(AppError::Unauthorized);
+        }
+    };
+    let nome = user_service::find_user_by_id(&state.db_leitura, &user_id)
+        .await?
+        .filter(|u| u.organizacao_id == atual.organizacao_id)
+        .map(|u| u.name)
+        .ok_or_else(|| AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)))?;
+    let periodo = periodo_pedido(&params.periodo);
+    let template = ConceitoPage {
+        registos: conceito_service::registos(&state.db_leitura, atual.organizacao_id, &user_id, &periodo).await?,
+        saldo: conceito_service::saldo(&state.db_leitura, atual.organizacao_id, &user_id, &periodo).await?,
+        e_o_proprio: user_id == atual.id,
+        user_id,
+        nome,
+        periodo,
+        periodos: conceito_service::periodos_recentes(tempo::hoje()),
+        pode_gerir,
+        origens: conceito_service::ORIGENS_MANUAIS,
+        pontos_por_servico: conceito_service::PONTOS_POR_SERVICO,
+        max_pontos: conceito_service::MAX_PONTOS,
+        max_motivo: MAX_MOTIVO,
+        hoje: tempo::hoje().to_string(),
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ConceitoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /conceito?user=&periodo= - Lançamentos e conceito do semestre
+pub async fn show_conceito(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<ConceitoQuery>,
+) -> AppResult<Response> {
+    let form = FormState::default()
+        .com_valor("user", params.user.trim())
+        .com_valor("data", tempo::hoje().to_string())
+        .com_valor("origem", conceito_service::ORIGEM_ELOGIO);
+    pagina_conceito(&state, &atual, &params, StatusCode::OK, form, flash).await
+}
+
+/// Handler para POST /conceito - Regista um elogio, mérito ou demérito
+pub async fn handle_registar_conceito(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<ConceitoForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let mut v = Validador::default();
+    v.obrigatorio("user", &form.user, 10);
+    v.obrigatorio("motivo", &form.motivo, MAX_MOTIVO);
+    let pontos = form.pontos.trim().parse::<i64>().unwrap_or(0);
+    v.intervalo("pontos", pontos, 1, conceito_service::MAX_PONTOS);
+    let data = v.data("data", &form.data);
+    let resultado = match (v.resultado(), data) {
+        (Ok(()), Some(data)) => conceito_service::registar(
+            &state.db_pool,
+            atual.organizacao_id,
+            form.user.trim(),
+            data,
+            form.origem.trim(),
+            pontos,
+            &form.motivo,
+            tempo::hoje(),
+            &atual.id,
+        )
+        .await
+        .map(|id| (id, data)),
+        (Err(e), _) => Err(e),
+        (Ok(()), None) => Err(AppError::validation("data", "Data inválida.")),
+    };
+    match resultado {
+        Ok((id, data)) => {
+            let origem = conceito_service::descrever_origem(form.origem.trim());
+            let detalhes = format!("{}: {} de {} pontos ({})", form.user.trim(), origem, pontos, form.motivo.trim());
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CONCEITO_REGISTADO, Some(&id.to_string()), Some(&detalhes)).await;
+            let destino = url_conceito(form.user.trim(), &conceito_service::periodo_de(data));
+            let mensagem = format!("{} registado no conceito de {}.", origem, form.user.trim());
+            Ok(flash::redirect_success(&session, &destino, mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            // A página mostra o conceito do utilizador indicado, se existir (senão, o do próprio)
+            let existe = user_service::find_user_by_id(&state.db_leitura, form.user.trim())
+                .await?
+                .is_some_and(|u| u.organizacao_id == atual.organizacao_id);
+            let params = ConceitoQuery {
+                user: if existe { form.user.trim().to_string() } else { String::new() },
+                periodo: String::new(),
+            };
+            let form_state = FormState::com_erros(erros)
+                .com_valor("user", form.user)
+                .com_valor("data", form.data)
+                .com_valor("origem", form.origem)
+                .com_valor("pontos", form.pontos)
+                .com_valor("motivo", form.motivo);
+            pagina_conceito(&state, &atual, &params, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /conceito/{id}/remover - Apaga um lançamento registado por engano
+pub async fn handle_remover_conceito(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    match conceito_service::remover(&state.db_pool, atual.organizacao_id, id).await {
+        Ok((user_id, origem, pontos)) => {
+            let detalhes = format!("{}: {} de {:+} pontos", user_id, conceito_service::descrever_origem(&origem), pontos);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CONCEITO_APAGADO, Some(&id.to_string()), Some(&detalhes)).await;
+            let destino = format!("/conceito?user={}", user_id);
+            Ok(flash::redirect_success(&session, &destino, format!("Lançamento {} apagado.", id)).await.into_response())
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, "/conceito", mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /conceito/classificacao?periodo=&turma= - Classificação por turma no semestre
+pub async fn show_classificacao(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<ClassificacaoQuery>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let periodo = periodo_pedido(&params.periodo);
+    let turma = Some(params.turma.trim()).filter(|t| !t.is_empty());
+    let template = ConceitoClassificacaoPage {
+        posicoes: conceito_service::classificacao(&state.db_leitura, atual.organizacao_id, &periodo, turma).await?,
+        turmas: conceito_service::turmas(&state.db_leitura, atual.organizacao_id).await?,
+        turma: params.turma.trim().to_string(),
+        periodo,
+        periodos: conceito_service::periodos_recentes(tempo::hoje()),
+        gerado_em: tempo::agora().format(tempo::FORMATO_DATA_HORA).to_string(),
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ConceitoClassificacaoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
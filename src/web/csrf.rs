@@ -0,0 +1,72 @@
+// src/web/csrf.rs
+//! Proteção CSRF para as ações que não podem ser disparadas por outro site (ex.: logout).
+//! Cada sessão autenticada tem um token aleatório, guardado na sessão e copiado para um cookie
+//! legível pelo JavaScript da página (`SameSite=Strict`). Os formulários marcados com `data-csrf`
+//! enviam o valor do cookie no campo `csrf_token`; o servidor compara-o com o da sessão.
+//! Um site externo não consegue ler o cookie, logo não consegue forjar o campo.
+
+use serde::Deserialize;
+use tower_cookies::{
+    cookie::{Cookie, SameSite},
+    Cookies,
+};
+use tower_sessions::Session;
+use uuid::Uuid;
+
+/// Chave do token na sessão e nome do cookie lido pelas páginas.
+const TOKEN_KEY: &str = "csrf_token";
+
+/// Formulário com o token CSRF (campo `csrf_token`).
+#[derive(Deserialize, Debug, Default)]
+pub struct CsrfForm {
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// Garante que a sessão tem um token e que o cookie da página o reflete (`Secure` como o da sessão).
+/// Chamado pelo middleware de autenticação; uma falha só deixa os formulários protegidos sem token.
+pub async fn garantir(session: &Session, cookies: &Cookies, seguro: bool) {
+    let token = match session.get::<String>(TOKEN_KEY).await {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            let token = Uuid::new_v4().simple().to_string();
+            if let Err(e) = session.insert(TOKEN_KEY, &token).await {
+                tracing::warn!("Falha ao guardar token CSRF na sessão: {:?}", e);
+                return;
+            }
+            token
+        }
+        Err(e) => {
+            tracing::warn!("Falha ao ler token CSRF da sessão: {:?}", e);
+            return;
+        }
+    };
+
+    if cookies.get(TOKEN_KEY).map(|c| c.value().to_string()).as_deref() != Some(token.as_str()) {
+        let cookie = Cookie::build((TOKEN_KEY, token))
+            .path("/")
+            .same_site(SameSite::Strict)
+            .secure(seguro)
+            .http_only(false) // Lido pelo JavaScript da página ao submeter
+            .build();
+        cookies.add(cookie);
+    }
+}
+
+/// Verifica o token enviado num formulário contra o da sessão.
+pub async fn verificar(session: &Session, enviado: &str) -> bool {
+    match session.get::<String>(TOKEN_KEY).await {
+        Ok(Some(token)) => !enviado.is_empty() && iguais(token.as_bytes(), enviado.as_bytes()),
+        _ => false,
+    }
+}
+
+/// Comparação em tempo constante (não revela quantos caracteres coincidem).
+fn iguais(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Apaga o cookie do token (logout).
+pub fn limpar_cookie(cookies: &Cookies) {
+    cookies.remove(Cookie::build(TOKEN_KEY).path("/").build());
+}
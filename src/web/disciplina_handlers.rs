@@ -0,0 +1,309 @@
+// src/web/disciplina_handlers.rs
+//! Processos disciplinares (FATD). Com a permissão "disciplina": registar a transgressão contra um
+//! utilizador (/disciplina), encerrar a defesa depois do prazo e decidir (punição, justificação ou
+//! arquivamento). O visado vê o seu processo em /disciplina/{id} e apresenta aí a defesa.
+//! A punição é creditada no saldo de punições pelo livro de serviços (ver `disciplina_service`).
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, disciplina_service, permission_service},
+    state::AppState,
+    templates::{DisciplinaPage, ProcessoDisciplinarPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo da descrição do fato, da defesa e da fundamentação.
+const MAX_TEXTO: usize = 2000;
+
+#[derive(Deserialize, Debug)]
+pub struct DisciplinaQuery {
+    #[serde(default)]
+    estado: String, // Só os processos neste estado (vazio = todos)
+    #[serde(default)]
+    user: String, // Só os processos deste utilizador (vazio = todos)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProcessoForm {
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    data_fato: String,
+    #[serde(default)]
+    descricao: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DefesaForm {
+    #[serde(default)]
+    defesa: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DecisaoForm {
+    #[serde(default)]
+    decisao: String,
+    #[serde(default)]
+    servicos: String,
+    #[serde(default)]
+    fundamentacao: String,
+}
+
+/// Pode registar e decidir processos?
+async fn pode_decidir(state: &AppState, atual: &CurrentUser) -> AppResult<bool> {
+    atual.tem_permissao(state, permission_service::PERM_DISCIPLINA).await
+}
+
+/// Falha (403) se o utilizador não pode registar nem decidir processos.
+async fn exigir_gestao(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if pode_decidir(state, atual).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Disciplina: {} sem permissão '{}'.", atual.id, permission_service::PERM_DISCIPLINA);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Endereço de um processo.
+fn url_processo(id: i64) -> String {
+    format!("/disciplina/{}", id)
+}
+
+/// Renderiza os processos abertos, os decididos recentemente e o formulário de registo.
+async fn pagina_disciplina(
+    state: &AppState,
+    atual: &CurrentUser,
+    params: &DisciplinaQuery,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let estado = Some(params.estado.trim()).filter(|e| !e.is_empty());
+    let user = Some(params.user.trim()).filter(|u| !u.is_empty());
+    let template = DisciplinaPage {
+        abertos: disciplina_service::listar(&state.db_leitura, organizacao_id, estado, user, Some(true), i64::MAX).await?,
+        decididos: disciplina_service::listar(
+            &state.db_leitura, organizacao_id, estado, user, Some(false), disciplina_service::DECIDIDOS_RECENTES,
+        )
+        .await?,
+        estado: params.estado.trim().to_string(),
+        user: params.user.trim().to_string(),
+        estados: disciplina_service::ESTADOS,
+        hoje: tempo::hoje().to_string(),
+        prazo_defesa_dias: disciplina_service::PRAZO_DEFESA_DIAS,
+        form,
+        max_descricao: MAX_TEXTO,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template DisciplinaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Renderiza um processo; só o visado e quem pode decidir o veem (os outros recebem 404).
+async fn pagina_processo(
+    state: &AppState,
+    atual: &CurrentUser,
+    id: i64,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let processo = disciplina_service::obter(&state.db_leitura, atual.organizacao_id, id).await?;
+    let pode_decidir = pode_decidir(state, atual).await?;
+    let e_o_visado = processo.user_id == atual.id;
+    if !pode_decidir && !e_o_visado {
+        return Err(AppError::NotFound(format!("Processo {} não encontrado.", id)));
+    }
+    let template = ProcessoDisciplinarPage {
+        lancamentos: disciplina_service::lancamentos(&state.db_leitura, atual.organizacao_id, id).await?,
+        processo,
+        pode_decidir,
+        e_o_visado,
+        hoje: tempo::hoje().to_string(),
+        max_servicos: disciplina_service::MAX_SERVICOS,
+        max_texto: MAX_TEXTO,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ProcessoDisciplinarPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /disciplina?estado=&user= - Processos e registo de novos
+pub async fn show_disciplina(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<DisciplinaQuery>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    pagina_disciplina(&state, &atual, &params, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /disciplina - Abre um processo (o visado é avisado para apresentar a defesa)
+pub async fn handle_abrir_processo(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<ProcessoForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let mut v = Validador::default();
+    v.obrigatorio("user", &form.user, 10);
+    let data_fato = v.data("data_fato", &form.data_fato);
+    v.obrigatorio("descricao", &form.descricao, MAX_TEXTO);
+    let resultado = match (v.resultado(), data_fato) {
+        (Ok(()), Some(data_fato)) => {
+            disciplina_service::abrir(
+                &state.db_pool,
+                atual.organizacao_id,
+                form.user.trim(),
+                data_fato,
+                &form.descricao,
+                tempo::hoje(),
+                &atual.id,
+            )
+            .await
+        }
+        (Err(e), _) => Err(e),
+        (Ok(()), None) => Err(AppError::validation("data_fato", "Data inválida.")),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!("{}: fato de {}", form.user.trim(), form.data_fato.trim());
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PROCESSO_ABERTO, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Processo {} aberto contra {}: o visado foi avisado para apresentar a defesa.", id, form.user.trim());
+            Ok(flash::redirect_success(&session, &url_processo(id), mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("user", form.user)
+                .com_valor("data_fato", form.data_fato)
+                .com_valor("descricao", form.descricao);
+            let params = DisciplinaQuery { estado: String::new(), user: String::new() };
+            pagina_disciplina(&state, &atual, &params, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /disciplina/{id} - O processo (visado ou quem decide)
+pub async fn show_processo(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    pagina_processo(&state, &atual, id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /disciplina/{id}/defesa - O visado apresenta a defesa (dentro do prazo)
+pub async fn handle_defesa(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<DefesaForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("defesa", &form.defesa, MAX_TEXTO);
+    if let Err(AppError::Validation(erros)) = v.resultado() {
+        let form_state = FormState::com_erros(erros).com_valor("defesa", form.defesa);
+        return pagina_processo(&state, &atual, id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await;
+    }
+    match disciplina_service::apresentar_defesa(&state.db_pool, atual.organizacao_id, id, &atual.id, &form.defesa, tempo::hoje()).await {
+        Ok(_) => {
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PROCESSO_DEFESA, Some(&id.to_string()), Some("defesa apresentada")).await;
+            Ok(flash::redirect_success(&session, &url_processo(id), "Defesa apresentada: o processo aguarda a decisão.").await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, &url_processo(id), e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /disciplina/{id}/sem-defesa - Passa à decisão sem defesa (prazo terminado)
+pub async fn handle_encerrar_defesa(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    match disciplina_service::encerrar_defesa(&state.db_pool, atual.organizacao_id, id, tempo::hoje()).await {
+        Ok(processo) => {
+            let detalhes = format!("{}: sem defesa (prazo até {})", processo.user_id, processo.prazo_defesa);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PROCESSO_DEFESA, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, &url_processo(id), "Defesa encerrada sem defesa apresentada: o processo aguarda a decisão.").await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, &url_processo(id), e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /disciplina/{id}/decisao - Decide o processo (a punição credita o saldo de punições)
+pub async fn handle_decidir(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<DecisaoForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let mut v = Validador::default();
+    v.opcao("decisao", &form.decisao, disciplina_service::DECISOES);
+    v.obrigatorio("fundamentacao", &form.fundamentacao, MAX_TEXTO);
+    let servicos = if form.decisao == disciplina_service::ESTADO_PUNIDO {
+        let servicos = form.servicos.trim().parse::<i64>().unwrap_or(0);
+        v.intervalo("servicos", servicos, 1, disciplina_service::MAX_SERVICOS);
+        servicos
+    } else {
+        0
+    };
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            disciplina_service::decidir(&state.db_pool, atual.organizacao_id, id, &form.decisao, servicos, &form.fundamentacao, &atual.id).await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(processo) => {
+            let detalhes = format!("{}: {}", processo.user_id, processo.descricao_estado());
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PROCESSO_DECIDIDO, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Processo {} decidido: {}.", id, processo.descricao_estado());
+            Ok(flash::redirect_success(&session, &url_processo(id), mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("decisao", form.decisao)
+                .com_valor("servicos", form.servicos)
+                .com_valor("fundamentacao", form.fundamentacao);
+            pagina_processo(&state, &atual, id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, &url_processo(id), e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
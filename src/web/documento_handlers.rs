@@ -0,0 +1,406 @@
+// src/web/documento_handlers.rs
+//! Repositório de documentos (/documentos): cada utilizador vê as pastas que as regras de visibilidade
+//! (roles e anos) lhe abrem e descarrega os PDFs, ficando cada descarga registada. Com a permissão
+//! "documentos": criar e alterar pastas, enviar e apagar documentos e consultar quem os descarregou.
+//! O envio do PDF vem no corpo do pedido (rotas de envio de ficheiros, com limites próprios).
+
+use crate::{
+    error::{AppError, AppResult, FieldError},
+    models::documento::{DadosPasta, PastaDocumentos},
+    services::{audit_service, documento_service, permission_service, user_service},
+    state::AppState,
+    templates::{DocumentoDownloadsPage, DocumentosPage},
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser, paginacao::Paginacao},
+};
+use askama::Template;
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do nome da pasta e do título do documento.
+const MAX_NOME: usize = 120;
+/// Tamanho máximo da descrição da pasta.
+const MAX_DESCRICAO: usize = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct DocumentosQuery {
+    pasta: Option<i64>,  // Pasta aberta
+    editar: Option<i64>, // Preenche o formulário com esta pasta
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PastaForm {
+    #[serde(default)]
+    categoria: String,
+    #[serde(default)]
+    nome: String,
+    #[serde(default)]
+    descricao: String,
+    #[serde(default)]
+    roles: Vec<String>, // Checkboxes (nenhuma = todas)
+    #[serde(default)]
+    anos: Vec<String>, // Checkboxes (nenhum = todos)
+}
+
+impl PastaForm {
+    /// Valida o formulário (as roles têm de existir) e devolve os dados da pasta.
+    fn validar(&self, roles_existentes: &[String]) -> AppResult<DadosPasta> {
+        let mut v = Validador::default();
+        v.obrigatorio("nome", &self.nome, MAX_NOME);
+        if self.descricao.trim().chars().count() > MAX_DESCRICAO {
+            v.erro("descricao", format!("Máximo de {} caracteres.", MAX_DESCRICAO));
+        }
+        if let Some(role) = self.roles.iter().find(|r| !roles_existentes.contains(r)) {
+            v.erro("roles", format!("Role desconhecida: '{}'.", role));
+        }
+        let anos: Vec<i64> = self.anos.iter().filter_map(|a| a.trim().parse().ok()).collect();
+        if anos.len() != self.anos.len() {
+            v.erro("anos", "Ano inválido.");
+        }
+        v.resultado()?;
+        Ok(DadosPasta {
+            categoria: self.categoria.clone(),
+            nome: self.nome.clone(),
+            descricao: self.descricao.clone(),
+            roles: self.roles.clone(),
+            anos,
+        })
+    }
+
+    /// Formulário reapresentado com os erros e os valores enviados.
+    fn com_erros(self, erros: Vec<FieldError>) -> FormState {
+        FormState::com_erros(erros)
+            .com_valor("categoria", self.categoria)
+            .com_valor("nome", self.nome)
+            .com_valor("descricao", self.descricao)
+            .com_valor("roles", self.roles.join(","))
+            .com_valor("anos", self.anos.join(","))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EnvioQuery {
+    pasta: i64,
+    #[serde(default)]
+    titulo: String,
+    #[serde(default)]
+    nome: String, // Nome do ficheiro escolhido (o da descarga)
+}
+
+/// Pode gerir o repositório (pastas, envios, registo das descargas)?
+async fn pode_gerir(state: &AppState, atual: &CurrentUser) -> AppResult<bool> {
+    atual.tem_permissao(state, permission_service::PERM_DOCUMENTOS).await
+}
+
+/// Falha (403) se o utilizador não pode gerir o repositório.
+async fn exigir_gestao(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if pode_gerir(state, atual).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Documentos: {} sem permissão '{}'.", atual.id, permission_service::PERM_DOCUMENTOS);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Pastas que o utilizador pode abrir (todas, para quem gere o repositório).
+async fn pastas_acessiveis(state: &AppState, atual: &CurrentUser, gerir: bool) -> AppResult<Vec<PastaDocumentos>> {
+    if gerir {
+        return documento_service::listar_pastas(&state.db_leitura, atual.organizacao_id, None).await;
+    }
+    let ano = user_service::find_user_by_id(&state.db_leitura, &atual.id).await?.map_or(0, |u| u.ano);
+    documento_service::listar_pastas(&state.db_leitura, atual.organizacao_id, Some((&atual.roles, ano))).await
+}
+
+/// URL da página com a pasta aberta.
+fn url_pasta(pasta_id: i64) -> String {
+    format!("/documentos?pasta={}", pasta_id)
+}
+
+/// Renderiza as pastas visíveis, os documentos da pasta aberta e, a quem gere o repositório, o formulário
+/// das pastas (nova ou alteração de `editar`).
+async fn pagina_documentos(
+    state: &AppState,
+    atual: &CurrentUser,
+    pasta_id: Option<i64>,
+    editar: Option<i64>,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let gerir = pode_gerir(state, atual).await?;
+    let pastas = pastas_acessiveis(state, atual, gerir).await?;
+    // Uma pasta que não pode ver é tratada como inexistente
+    let pasta = match pasta_id {
+        Some(id) => Some(
+            pastas
+                .iter()
+                .find(|p| p.id == id)
+                .cloned()
+                .ok_or_else(|| AppError::NotFound(format!("Pasta {} não encontrada.", id)))?,
+        ),
+        None => None,
+    };
+    let documentos = match &pasta {
+        Some(pasta) => documento_service::listar_documentos(&state.db_leitura, atual.organizacao_id, pasta.id).await?,
+        None => Vec::new(),
+    };
+    let roles = if gerir {
+        permission_service::listar_roles(&state.db_leitura).await?.into_iter().map(|r| r.nome).collect()
+    } else {
+        Vec::new()
+    };
+
+    let template = DocumentosPage {
+        pastas,
+        pasta,
+        documentos,
+        pode_gerir: gerir,
+        editar,
+        categorias: documento_service::CATEGORIAS,
+        roles,
+        max_ano: documento_service::MAX_ANO,
+        max_upload_mb: state.config.pedidos.upload_max_mb,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template DocumentosPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /documentos?pasta=&editar= - Pastas visíveis e documentos da pasta aberta
+pub async fn show_documentos(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<DocumentosQuery>,
+) -> AppResult<Response> {
+    // Alteração: o formulário vem preenchido com a pasta
+    let mut form = FormState::default();
+    if let Some(id) = params.editar {
+        exigir_gestao(&state, &atual).await?;
+        let pasta = documento_service::obter_pasta(&state.db_leitura, atual.organizacao_id, id).await?;
+        form = form
+            .com_valor("categoria", pasta.categoria)
+            .com_valor("nome", pasta.nome)
+            .com_valor("descricao", pasta.descricao)
+            .com_valor("roles", pasta.roles)
+            .com_valor("anos", pasta.anos);
+    }
+    pagina_documentos(&state, &atual, params.pasta, params.editar, StatusCode::OK, form, flash).await
+}
+
+/// Handler para POST /documentos/pastas - Cria uma pasta
+pub async fn handle_criar_pasta(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<PastaForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let roles: Vec<String> = permission_service::listar_roles(&state.db_pool).await?.into_iter().map(|r| r.nome).collect();
+    let resultado = match form.validar(&roles) {
+        Ok(dados) => documento_service::criar_pasta(&state.db_pool, atual.organizacao_id, &dados, &atual.id).await.map(|id| (id, dados)),
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((id, dados)) => {
+            let detalhes = format!("{} ({})", dados.nome.trim(), documento_service::descrever_categoria(&dados.categoria));
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PASTA_CRIADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Pasta '{}' criada.", dados.nome.trim());
+            Ok(flash::redirect_success(&session, &url_pasta(id), mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = form.com_erros(erros);
+            pagina_documentos(&state, &atual, None, None, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /documentos/pastas/{id} - Altera uma pasta (incluindo quem a pode ver)
+pub async fn handle_atualizar_pasta(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<PastaForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let roles: Vec<String> = permission_service::listar_roles(&state.db_pool).await?.into_iter().map(|r| r.nome).collect();
+    let resultado = match form.validar(&roles) {
+        Ok(dados) => documento_service::atualizar_pasta(&state.db_pool, atual.organizacao_id, id, &dados).await.map(|anterior| (anterior, dados)),
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((anterior, dados)) => {
+            let detalhes = audit_service::resumo_diff(&[
+                ("categoria", anterior.categoria, dados.categoria.clone()),
+                ("nome", anterior.nome, dados.nome.trim().to_string()),
+                ("roles", anterior.roles, dados.roles.join(",")),
+                ("anos", anterior.anos, dados.anos.iter().map(i64::to_string).collect::<Vec<_>>().join(",")),
+            ]);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PASTA_ALTERADA, Some(&id.to_string()), detalhes.as_deref()).await;
+            Ok(flash::redirect_success(&session, &url_pasta(id), format!("Pasta '{}' atualizada.", dados.nome.trim())).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = form.com_erros(erros);
+            pagina_documentos(&state, &atual, Some(id), Some(id), StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /documentos/pastas/{id}/remover - Apaga uma pasta vazia
+pub async fn handle_remover_pasta(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    match documento_service::remover_pasta(&state.db_pool, atual.organizacao_id, id).await {
+        Ok(pasta) => {
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PASTA_APAGADA, Some(&id.to_string()), Some(&pasta.nome)).await;
+            Ok(flash::redirect_success(&session, "/documentos", format!("Pasta '{}' apagada.", pasta.nome)).await.into_response())
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, &url_pasta(id), mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /documentos/enviar?pasta=&titulo=&nome= - Envia um PDF (no corpo do pedido)
+pub async fn handle_enviar_documento(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Query(params): Query<EnvioQuery>,
+    corpo: Bytes,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    tracing::info!("POST /documentos/enviar por {} ({} bytes, pasta {})", atual.id, corpo.len(), params.pasta);
+
+    // Sem título, usa-se o nome do ficheiro (sem a extensão)
+    let nome = params.nome.trim();
+    let titulo = match params.titulo.trim() {
+        "" => nome.trim_end_matches(".pdf").trim_end_matches(".PDF").to_string(),
+        titulo => titulo.to_string(),
+    };
+    let mut v = Validador::default();
+    v.obrigatorio("titulo", &titulo, MAX_NOME);
+    if corpo.is_empty() {
+        v.erro("ficheiro", "Escolha um ficheiro PDF.");
+    }
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            let nome_original = if nome.is_empty() { format!("{}.pdf", titulo) } else { nome.to_string() };
+            documento_service::enviar(
+                &state.db_pool,
+                &state.config.documentos.diretorio,
+                atual.organizacao_id,
+                params.pasta,
+                &titulo,
+                &nome_original,
+                &corpo,
+                &atual.id,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    let destino = url_pasta(params.pasta);
+    match resultado {
+        Ok(documento) => {
+            let detalhes = format!("{} ({}, pasta {})", documento.titulo, documento.tamanho_legivel(), documento.pasta_id);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_DOCUMENTO_ENVIADO, Some(&documento.id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, &destino, format!("Documento '{}' enviado.", documento.titulo)).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let mensagem = erros.iter().map(|e| e.mensagem.clone()).collect::<Vec<_>>().join(" ");
+            Ok(flash::redirect_error(&session, &destino, mensagem).await.into_response())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /documentos/{id}/download - Abre o PDF (se a pasta for visível) e regista a descarga
+pub async fn handle_download_documento(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    let documento = documento_service::obter_documento(&state.db_leitura, atual.organizacao_id, id).await?;
+    let gerir = pode_gerir(&state, &atual).await?;
+    if !pastas_acessiveis(&state, &atual, gerir).await?.iter().any(|p| p.id == documento.pasta_id) {
+        tracing::warn!("Documentos: {} sem acesso ao documento {}.", atual.id, id);
+        return Err(AppError::NotFound(format!("Documento {} não encontrado.", id)));
+    }
+    let bytes = documento_service::conteudo(&state.config.documentos.diretorio, &documento).await?;
+    documento_service::registar_download(&state.db_pool, id, &atual.id).await;
+
+    // Só caracteres seguros no cabeçalho (o nome original pode ter aspas ou acentos)
+    let nome: String = documento
+        .nome_original
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ' ') { c } else { '_' })
+        .collect();
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, format!("inline; filename=\"{}\"", nome)),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+/// Handler para GET /documentos/{id}/downloads - Quem descarregou o documento (gestão)
+pub async fn show_downloads_documento(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    paginacao: Paginacao,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let documento = documento_service::obter_documento(&state.db_leitura, atual.organizacao_id, id).await?;
+    let pasta = documento_service::obter_pasta(&state.db_leitura, atual.organizacao_id, documento.pasta_id).await?;
+    let (downloads, total) =
+        documento_service::listar_downloads(&state.db_leitura, id, paginacao.limite(), paginacao.offset()).await?;
+
+    let template = DocumentoDownloadsPage { documento, pasta, downloads, paginacao: paginacao.navegacao(total) };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template DocumentoDownloadsPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /documentos/{id}/remover - Apaga um documento (e o ficheiro)
+pub async fn handle_remover_documento(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let documento =
+        documento_service::remover_documento(&state.db_pool, &state.config.documentos.diretorio, atual.organizacao_id, id).await?;
+    let detalhes = format!("{} (pasta {})", documento.titulo, documento.pasta_id);
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_DOCUMENTO_APAGADO, Some(&id.to_string()), Some(&detalhes)).await;
+    let destino = url_pasta(documento.pasta_id);
+    Ok(flash::redirect_success(&session, &destino, format!("Documento '{}' apagado.", documento.titulo)).await.into_response())
+}
@@ -0,0 +1,390 @@
+// src/web/enfermaria_handlers.rs
+//! Enfermaria (/enfermaria): cada utilizador marca uma consulta numa vaga livre e pode cancelá-la até à
+//! hora. Com a permissão "enfermaria": a agenda de cada dia (/enfermaria/agenda), abrir e apagar vagas e
+//! registar a chegada (assinalada na lista de presença), a falta, o cancelamento ou o fim da consulta,
+//! com a dispensa que dela resulte (baixa registada; exige também a permissão "baixas").
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, baixa_service, enfermaria_service::{self, Dispensa}, permission_service},
+    state::AppState,
+    templates::{EnfermariaAgendaPage, EnfermariaPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::{Duration, NaiveDate};
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do motivo de uma consulta.
+const MAX_MOTIVO: usize = 200;
+/// Tamanho máximo das notas da secção de saúde.
+const MAX_NOTAS: usize = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct AgendaQuery {
+    #[serde(default)]
+    data: String, // Dia da agenda (vazio = hoje)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MarcacaoForm {
+    #[serde(default)]
+    vaga_id: String,
+    #[serde(default)]
+    motivo: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VagasForm {
+    #[serde(default)]
+    data: String,
+    #[serde(default)]
+    de: String,
+    #[serde(default)]
+    ate: String,
+    #[serde(default)]
+    duracao: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ConclusaoForm {
+    #[serde(default)]
+    notas: String,
+    #[serde(default)]
+    dispensa_ate: String, // Vazio = sem dispensa
+    #[serde(default)]
+    restricoes: Vec<String>, // Checkboxes da dispensa
+}
+
+/// Pode ver a agenda e gerir as consultas?
+async fn pode_gerir(state: &AppState, atual: &CurrentUser) -> AppResult<bool> {
+    atual.tem_permissao(state, permission_service::PERM_ENFERMARIA).await
+}
+
+/// Falha (403) se o utilizador não gere a enfermaria.
+async fn exigir_gestao(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if pode_gerir(state, atual).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Enfermaria: {} sem permissão '{}'.", atual.id, permission_service::PERM_ENFERMARIA);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Endereço da agenda de um dia.
+fn url_agenda(data: &str) -> String {
+    format!("/enfermaria/agenda?data={}", data)
+}
+
+/// Renderiza as consultas do utilizador e as vagas livres.
+async fn pagina_enfermaria(state: &AppState, atual: &CurrentUser, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let template = EnfermariaPage {
+        consultas: enfermaria_service::consultas_do_user(&state.db_leitura, organizacao_id, &atual.id, enfermaria_service::HISTORICO_RECENTE)
+            .await?,
+        vagas: enfermaria_service::vagas_livres(&state.db_leitura, organizacao_id, tempo::agora().naive_local()).await?,
+        pode_gerir: pode_gerir(state, atual).await?,
+        dias_marcacao: enfermaria_service::DIAS_MARCACAO,
+        max_motivo: MAX_MOTIVO,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template EnfermariaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /enfermaria - As minhas consultas e as vagas livres
+pub async fn show_enfermaria(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_enfermaria(&state, &atual, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /enfermaria/marcar - Marca uma consulta numa vaga livre
+pub async fn handle_marcar_consulta(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<MarcacaoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    let vaga_id = form.vaga_id.trim().parse::<i64>().ok();
+    if vaga_id.is_none() {
+        v.erro("vaga_id", "Escolha uma vaga.");
+    }
+    if form.motivo.trim().chars().count() > MAX_MOTIVO {
+        v.erro("motivo", format!("Máximo de {} caracteres.", MAX_MOTIVO));
+    }
+    let resultado = match (v.resultado(), vaga_id) {
+        (Ok(()), Some(vaga_id)) => {
+            enfermaria_service::marcar(&state.db_pool, atual.organizacao_id, vaga_id, &atual.id, &form.motivo, tempo::agora().naive_local())
+                .await
+        }
+        (Err(e), _) => Err(e),
+        (Ok(()), None) => Err(AppError::validation("vaga_id", "Escolha uma vaga.")),
+    };
+    match resultado {
+        Ok(consulta) => {
+            let dia = enfermaria_service::formatar_dia(&consulta.data);
+            let mensagem = format!("Consulta marcada para {} às {}.", dia, consulta.hora);
+            Ok(flash::redirect_success(&session, "/enfermaria", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("vaga_id", form.vaga_id)
+                .com_valor("motivo", form.motivo);
+            pagina_enfermaria(&state, &atual, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /enfermaria/{id}/cancelar - O próprio cancela a sua consulta (até à hora)
+pub async fn handle_cancelar_consulta(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    let agora = tempo::agora().naive_local();
+    match enfermaria_service::cancelar(&state.db_pool, atual.organizacao_id, id, Some(&atual.id), agora, &atual.id).await {
+        Ok(_) => Ok(flash::redirect_success(&session, "/enfermaria", "Consulta cancelada.").await.into_response()),
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/enfermaria", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+// --- AGENDA (secção de saúde) ---
+
+/// Renderiza a agenda de um dia (com os erros do formulário de abertura de vagas, se houver).
+async fn pagina_agenda(state: &AppState, atual: &CurrentUser, data: NaiveDate, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = EnfermariaAgendaPage {
+        data: data.to_string(),
+        anterior: (data - Duration::days(1)).to_string(),
+        seguinte: (data + Duration::days(1)).to_string(),
+        hoje: tempo::hoje().to_string(),
+        vagas: enfermaria_service::agenda(&state.db_leitura, atual.organizacao_id, data).await?,
+        restricoes: baixa_service::RESTRICOES,
+        pode_dispensar: atual.tem_permissao(state, permission_service::PERM_BAIXAS).await?,
+        duracao_minima: enfermaria_service::DURACAO_MINIMA,
+        duracao_maxima: enfermaria_service::DURACAO_MAXIMA,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template EnfermariaAgendaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /enfermaria/agenda?data= - Agenda de um dia
+pub async fn show_agenda(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<AgendaQuery>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let data = tempo::ler_data(&params.data).unwrap_or_else(tempo::hoje);
+    let form = FormState::default()
+        .com_valor("data", data.to_string())
+        .com_valor("duracao", "20");
+    pagina_agenda(&state, &atual, data, StatusCode::OK, form, flash).await
+}
+
+/// Handler para POST /enfermaria/vagas - Abre as vagas de um dia (das/às, de N em N minutos)
+pub async fn handle_abrir_vagas(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<VagasForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let mut v = Validador::default();
+    let data = v.data("data", &form.data);
+    let de = v.hora("de", &form.de);
+    let ate = v.hora("ate", &form.ate);
+    let duracao = form.duracao.trim().parse::<i64>().unwrap_or(0);
+    v.intervalo("duracao", duracao, enfermaria_service::DURACAO_MINIMA, enfermaria_service::DURACAO_MAXIMA);
+    let resultado = match (v.resultado(), data, de, ate) {
+        (Ok(()), Some(data), Some(de), Some(ate)) => {
+            enfermaria_service::abrir_vagas(&state.db_pool, atual.organizacao_id, data, de, ate, duracao, tempo::hoje(), &atual.id)
+                .await
+                .map(|contagem| (data, contagem))
+        }
+        (Err(e), ..) => Err(e),
+        (Ok(()), ..) => Err(AppError::validation("data", "Data inválida.")),
+    };
+    match resultado {
+        Ok((data, (criadas, existentes))) => {
+            let detalhes = format!("{}: das {} às {}, de {} em {} minutos ({} criadas)", data, form.de.trim(), form.ate.trim(), duracao, duracao, criadas);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CONSULTA_VAGAS_ABERTAS, None, Some(&detalhes)).await;
+            let mut mensagem = format!("{} vaga(s) aberta(s).", criadas);
+            if existentes > 0 {
+                mensagem.push_str(&format!(" {} hora(s) já tinham vaga.", existentes));
+            }
+            Ok(flash::redirect_success(&session, &url_agenda(&data.to_string()), mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("data", form.data.clone())
+                .com_valor("de", form.de)
+                .com_valor("ate", form.ate)
+                .com_valor("duracao", form.duracao);
+            let dia = tempo::ler_data(&form.data).unwrap_or_else(tempo::hoje);
+            pagina_agenda(&state, &atual, dia, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /enfermaria/vagas/{id}/remover - Apaga uma vaga sem consulta
+pub async fn handle_remover_vaga(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    match enfermaria_service::remover_vaga(&state.db_pool, atual.organizacao_id, id).await {
+        Ok(vaga) => {
+            let detalhes = format!("{} às {}", vaga.data, vaga.hora);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CONSULTA_VAGA_APAGADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Vaga das {} apagada.", vaga.hora);
+            Ok(flash::redirect_success(&session, &url_agenda(&vaga.data), mensagem).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/enfermaria/agenda", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /enfermaria/agenda/{id}/chegada - O utilizador chegou à enfermaria
+pub async fn handle_chegada(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    match enfermaria_service::registar_chegada(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(consulta) => {
+            let mensagem = format!("{} na enfermaria.", consulta.user_id);
+            Ok(flash::redirect_success(&session, &url_agenda(&consulta.data), mensagem).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/enfermaria/agenda", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /enfermaria/agenda/{id}/falta - O utilizador não compareceu
+pub async fn handle_falta(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    match enfermaria_service::registar_falta(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(consulta) => {
+            let detalhes = format!("{}: {} às {}", consulta.user_id, consulta.data, consulta.hora);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CONSULTA_FALTA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Falta de {} registada.", consulta.user_id);
+            Ok(flash::redirect_success(&session, &url_agenda(&consulta.data), mensagem).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/enfermaria/agenda", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /enfermaria/agenda/{id}/cancelar - A secção de saúde cancela uma consulta
+pub async fn handle_cancelar_agenda(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let agora = tempo::agora().naive_local();
+    match enfermaria_service::cancelar(&state.db_pool, atual.organizacao_id, id, None, agora, &atual.id).await {
+        Ok(consulta) => {
+            let detalhes = format!("{}: {} às {}", consulta.user_id, consulta.data, consulta.hora);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CONSULTA_CANCELADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Consulta de {} cancelada; o utilizador foi avisado.", consulta.user_id);
+            Ok(flash::redirect_success(&session, &url_agenda(&consulta.data), mensagem).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/enfermaria/agenda", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /enfermaria/agenda/{id}/concluir - Termina a consulta, com a dispensa se houver
+pub async fn handle_concluir(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<ConclusaoForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let mut v = Validador::default();
+    if form.notas.trim().chars().count() > MAX_NOTAS {
+        v.erro("notas", format!("Máximo de {} caracteres.", MAX_NOTAS));
+    }
+    let ate = match form.dispensa_ate.trim() {
+        "" => {
+            if !form.restricoes.is_empty() {
+                v.erro("dispensa_ate", "Indique até quando vai a dispensa.");
+            }
+            None
+        }
+        texto => v.data("dispensa_ate", texto),
+    };
+    if ate.is_some() && !atual.tem_permissao(&state, permission_service::PERM_BAIXAS).await? {
+        v.erro("dispensa_ate", "Registar a dispensa exige a permissão das baixas.");
+    }
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            let dispensa = ate.map(|ate| Dispensa { ate, restricoes: &form.restricoes });
+            enfermaria_service::concluir(&state.db_pool, atual.organizacao_id, id, &form.notas, dispensa, tempo::hoje(), &atual.id).await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((consulta, baixa_id)) => {
+            let mut detalhes = format!("{}: {} às {}", consulta.user_id, consulta.data, consulta.hora);
+            if let (Some(baixa_id), Some(ate)) = (baixa_id, ate) {
+                detalhes.push_str(&format!(", dispensa até {} (baixa {})", ate, baixa_id));
+                let detalhes_baixa = format!("{}: {} a {} (consulta {})", consulta.user_id, tempo::hoje(), ate, id);
+                audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_BAIXA_REGISTADA, Some(&baixa_id.to_string()), Some(&detalhes_baixa)).await;
+            }
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CONSULTA_ATENDIDA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = match baixa_id {
+                Some(baixa_id) => format!("Consulta de {} terminada; baixa {} registada.", consulta.user_id, baixa_id),
+                None => format!("Consulta de {} terminada.", consulta.user_id),
+            };
+            Ok(flash::redirect_success(&session, &url_agenda(&consulta.data), mensagem).await.into_response())
+        }
+        // Um formulário por consulta na agenda: os erros vêm na mensagem
+        Err(e @ (AppError::Validation(_) | AppError::Conflict(_))) => {
+            Ok(flash::redirect_error(&session, "/enfermaria/agenda", e.user_message()).await.into_response())
+        }
+        Err(e) => Err(e),
+    }
+}
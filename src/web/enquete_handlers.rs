@@ -0,0 +1,287 @@
+// src/web/enquete_handlers.rs
+//! Enquetes internas (/enquetes): com a permissão "enquetes", criar enquetes (resposta única ou múltipla,
+//! anónimas ou não, com prazo e dirigidas a roles e anos), encerrá-las, ver os resultados apurados e
+//! exportá-los em CSV. Os utilizadores votam no painel (/user) nas que lhes são dirigidas.
+
+use crate::{
+    error::{AppError, AppResult, FieldError},
+    models::enquete::DadosEnquete,
+    services::{audit_service, documento_service, enquete_service, permission_service, user_service},
+    state::AppState,
+    templates::{EnquetePage, EnquetesPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{admin_handlers::csv_campo, flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo da pergunta e de cada opção.
+const MAX_PERGUNTA: usize = 200;
+/// Tamanho máximo da descrição.
+const MAX_DESCRICAO: usize = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct EnqueteForm {
+    #[serde(default)]
+    pergunta: String,
+    #[serde(default)]
+    descricao: String,
+    #[serde(default)]
+    opcoes: String, // Uma opção por linha
+    multipla: Option<String>, // Checkbox: presente = marcada
+    anonima: Option<String>,  // Checkbox: presente = marcada
+    #[serde(default)]
+    prazo: String,
+    #[serde(default)]
+    roles: Vec<String>, // Checkboxes (nenhuma = todas)
+    #[serde(default)]
+    anos: Vec<String>, // Checkboxes (nenhum = todos)
+}
+
+impl EnqueteForm {
+    /// Opções indicadas (linhas não vazias).
+    fn lista_opcoes(&self) -> Vec<String> {
+        self.opcoes.lines().map(str::trim).filter(|o| !o.is_empty()).map(str::to_string).collect()
+    }
+
+    /// Valida o formulário (as roles têm de existir, o prazo não pode já ter passado) e devolve os dados.
+    fn validar(&self, roles_existentes: &[String]) -> AppResult<DadosEnquete> {
+        let mut v = Validador::default();
+        v.obrigatorio("pergunta", &self.pergunta, MAX_PERGUNTA);
+        if self.descricao.trim().chars().count() > MAX_DESCRICAO {
+            v.erro("descricao", format!("Máximo de {} caracteres.", MAX_DESCRICAO));
+        }
+        let opcoes = self.lista_opcoes();
+        if !(enquete_service::MIN_OPCOES..=enquete_service::MAX_OPCOES).contains(&opcoes.len()) {
+            v.erro("opcoes", format!("Indique entre {} e {} opções, uma por linha.", enquete_service::MIN_OPCOES, enquete_service::MAX_OPCOES));
+        } else if opcoes.iter().any(|o| o.chars().count() > MAX_PERGUNTA) {
+            v.erro("opcoes", format!("Cada opção tem no máximo {} caracteres.", MAX_PERGUNTA));
+        } else if (1..opcoes.len()).any(|i| opcoes[..i].contains(&opcoes[i])) {
+            v.erro("opcoes", "Há opções repetidas.");
+        }
+        if let Some(prazo) = v.data("prazo", &self.prazo) {
+            if prazo < tempo::hoje() {
+                v.erro("prazo", "O prazo já passou.");
+            }
+        }
+        if let Some(role) = self.roles.iter().find(|r| !roles_existentes.contains(r)) {
+            v.erro("roles", format!("Role desconhecida: '{}'.", role));
+        }
+        let anos: Vec<i64> = self.anos.iter().filter_map(|a| a.trim().parse().ok()).collect();
+        if anos.len() != self.anos.len() {
+            v.erro("anos", "Ano inválido.");
+        }
+        v.resultado()?;
+        Ok(DadosEnquete {
+            pergunta: self.pergunta.clone(),
+            descricao: self.descricao.clone(),
+            opcoes,
+            multipla: self.multipla.is_some(),
+            anonima: self.anonima.is_some(),
+            prazo: self.prazo.trim().to_string(),
+            roles: self.roles.clone(),
+            anos,
+        })
+    }
+
+    /// Formulário reapresentado com os erros e os valores enviados.
+    fn com_erros(self, erros: Vec<FieldError>) -> FormState {
+        FormState::com_erros(erros)
+            .com_valor("pergunta", self.pergunta)
+            .com_valor("descricao", self.descricao)
+            .com_valor("opcoes", self.opcoes)
+            .com_valor("multipla", if self.multipla.is_some() { "on" } else { "" })
+            .com_valor("anonima", if self.anonima.is_some() { "on" } else { "" })
+            .com_valor("prazo", self.prazo)
+            .com_valor("roles", self.roles.join(","))
+            .com_valor("anos", self.anos.join(","))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VotoForm {
+    #[serde(default)]
+    opcao: Vec<String>, // Radio (resposta única) ou checkboxes (múltipla)
+}
+
+/// Falha (403) se o utilizador não pode gerir as enquetes.
+async fn exigir_gestao(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if atual.tem_permissao(state, permission_service::PERM_ENQUETES).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Enquetes: {} sem permissão '{}'.", atual.id, permission_service::PERM_ENQUETES);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Renderiza as enquetes e o formulário de criação.
+async fn pagina_enquetes(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = EnquetesPage {
+        enquetes: enquete_service::listar(&state.db_leitura, organizacao_id).await?,
+        roles: permission_service::listar_roles(&state.db_leitura).await?.into_iter().map(|r| r.nome).collect(),
+        max_ano: documento_service::MAX_ANO,
+        hoje: tempo::hoje().to_string(),
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template EnquetesPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /enquetes - Enquetes da organização e criação
+pub async fn show_enquetes(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    pagina_enquetes(&state, atual.organizacao_id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /enquetes - Cria uma enquete
+pub async fn handle_criar_enquete(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<EnqueteForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let roles: Vec<String> = permission_service::listar_roles(&state.db_pool).await?.into_iter().map(|r| r.nome).collect();
+    let resultado = match form.validar(&roles) {
+        Ok(dados) => enquete_service::criar(&state.db_pool, atual.organizacao_id, &dados, &atual.id).await.map(|id| (id, dados)),
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((id, dados)) => {
+            let detalhes = format!("{} (até {})", dados.pergunta.trim(), dados.prazo);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_ENQUETE_CRIADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Enquete \"{}\" criada.", dados.pergunta.trim());
+            Ok(flash::redirect_success(&session, &format!("/enquetes/{}", id), mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            pagina_enquetes(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form.com_erros(erros), Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /enquetes/{id} - Resultados apurados de uma enquete
+pub async fn show_enquete(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let enquete = enquete_service::obter(&state.db_leitura, atual.organizacao_id, id).await?;
+    let template = EnquetePage {
+        opcoes: enquete_service::opcoes(&state.db_leitura, id).await?,
+        votos: enquete_service::votos(&state.db_leitura, &enquete).await?,
+        enquete,
+        hoje: tempo::hoje().to_string(),
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template EnquetePage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /enquetes/{id}/encerrar - Encerra a votação antes do prazo
+pub async fn handle_encerrar_enquete(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let url = format!("/enquetes/{}", id);
+    match enquete_service::encerrar(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(enquete) => {
+            let detalhes = format!("{} ({} participantes)", enquete.pergunta, enquete.participantes);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_ENQUETE_ENCERRADA, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, &url, "Enquete encerrada.").await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, &url, e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /enquetes/{id}/export.csv - Resultados (e os votos, se não for anónima) em CSV
+pub async fn handle_export_enquete(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let enquete = enquete_service::obter(&state.db_leitura, atual.organizacao_id, id).await?;
+    let opcoes = enquete_service::opcoes(&state.db_leitura, id).await?;
+    let votos = enquete_service::votos(&state.db_leitura, &enquete).await?;
+
+    // BOM UTF-8: o Excel abre os acentos corretamente
+    let mut csv = String::from("\u{feff}");
+    csv.push_str("opcao,votos,percentagem\r\n");
+    for o in &opcoes {
+        let linha = [csv_campo(&o.texto), o.votos.to_string(), o.percentagem(&enquete.participantes).to_string()];
+        csv.push_str(&linha.join(","));
+        csv.push_str("\r\n");
+    }
+    csv.push_str(&format!("participantes,{},\r\n", enquete.participantes));
+    // Nas anónimas não há votos nominais a exportar
+    if !enquete.anonima {
+        csv.push_str("\r\nuser_id,nome,turma,opcao,votou_em\r\n");
+        for v in &votos {
+            let linha = [
+                csv_campo(&v.user_id),
+                csv_campo(&v.name),
+                csv_campo(&v.turma),
+                csv_campo(&v.opcao),
+                tempo::formatar(&v.votou_em, tempo::FORMATO_DATA_HORA),
+            ];
+            csv.push_str(&linha.join(","));
+            csv.push_str("\r\n");
+        }
+    }
+
+    let disposicao = format!("attachment; filename=\"enquete_{}.csv\"", id);
+    Ok((
+        [(header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()), (header::CONTENT_DISPOSITION, disposicao)],
+        csv,
+    )
+        .into_response())
+}
+
+/// Handler para POST /enquetes/{id}/votar - Voto do utilizador (a partir do painel)
+pub async fn handle_votar_enquete(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<VotoForm>,
+) -> AppResult<Response> {
+    let escolhidas: Vec<i64> = form.opcao.iter().filter_map(|o| o.trim().parse().ok()).collect();
+    let ano = user_service::find_user_by_id(&state.db_leitura, &atual.id).await?.map_or(0, |u| u.ano);
+    let hoje = tempo::hoje().to_string();
+    match enquete_service::votar(&state.db_pool, atual.organizacao_id, id, &atual.id, &atual.roles, ano, &escolhidas, &hoje).await {
+        Ok(enquete) => {
+            let mensagem = format!("Voto registado na enquete \"{}\". Obrigado!", enquete.pergunta);
+            Ok(flash::redirect_success(&session, "/user", mensagem).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/user", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
@@ -1,95 +1,153 @@
 // src/web/escala_handlers.rs
 use axum::{
-    extract::{Json, Path, State}, http::StatusCode, response::{Html, IntoResponse, Redirect}
+    extract::{Json, Path, State}, http::StatusCode, response::{Html, IntoResponse, Response}
 };
 use crate::{
+    error::{AppError, AppResult},
     state::AppState,
-    services::escala_service,
+    tempo,
+    validation::validar,
+    services::{agenda_service, escala_service, permission_service, uniforme_service},
     models::escala::{PedidoTrocaPayload, GerarPeriodoRequest, PublicarRequest},
-    templates::{EscalaTemplate, EscalaDiaView, AlocacaoExibicao, AdminEscalaPage, UserPunido, TrocaPendenteAdmin},
+    templates::{EscalaTemplate, EscalaDiaView, AlocacaoExibicao, AdminEscalaPage, BoletimDiaPage, UserPunido, TrocaPendenteAdmin},
+    web::{flash, formato::Formato, mw_auth::{CurrentUser, OrganizacaoId}},
 };
 use tower_sessions::Session;
-use chrono::Datelike;
 use std::collections::BTreeMap;
 use askama::Template;
 
+/// Último dia considerado em /escala (sem limite: todos os dias a partir de hoje).
+const FIM_SEM_LIMITE: &str = "9999-12-31";
+
+/// Linha da página da escala: o dia e, se existir, uma das suas alocações (LEFT JOIN).
+#[derive(sqlx::FromRow)]
+struct LinhaEscala {
+    data: String,
+    tipo_rotina: String,
+    status: Option<String>,
+    aloc_id: Option<String>,
+    user_id: Option<String>,
+    militar: Option<String>,
+    posto: Option<String>,
+    turma: Option<String>,
+    is_punicao: Option<bool>,
+}
+
 // --- HANDLER DA PÁGINA PRINCIPAL (GET /escala/) ---
+/// Página da escala (dias a partir de hoje), ou a lista de dias em JSON (`Accept: application/json` ou `?format=json`).
 pub async fn handle_pagina_escala(
     State(state): State<AppState>,
     session: Session,
-) -> impl IntoResponse {
-    let user_atual_id = session.get::<String>("user_id")
-        .await.ok().flatten().unwrap_or_default();
+    atual: CurrentUser,
+    formato: Formato,
+) -> Response {
+    let hoje = tempo::hoje().to_string();
+    responder_dias(&state, &session, &atual, formato, &hoje, FIM_SEM_LIMITE).await
+}
+
+// --- HANDLER DE UM DIA (GET /escala/{data}) ---
+/// A escala de um dia, na página (só esse dia) ou em JSON; 404 se o dia não tiver escala.
+pub async fn handle_dia_escala(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    formato: Formato,
+    Path(data): Path<String>,
+) -> Response {
+    if chrono::NaiveDate::parse_from_str(&data, "%Y-%m-%d").is_err() {
+        return formato.erro(AppError::validation("data", format!("Data inválida: '{}' (use AAAA-MM-DD).", data)));
+    }
+    if formato == Formato::Json {
+        // As prévias também aparecem na página (são a base das trocas)
+        return match escala_service::dias_periodo(&state.db_leitura, atual.organizacao_id, &data, &data, true).await {
+            Ok(dias) => match dias.into_iter().next() {
+                Some(dia) => Json(dia).into_response(),
+                None => formato.erro(AppError::NotFound(format!("Não há escala para {}.", data))),
+            },
+            Err(e) => formato.erro(e.into()),
+        };
+    }
+    if !escala_service::existe_dia(&state.db_leitura, atual.organizacao_id, &data).await.unwrap_or(false) {
+        return formato.erro(AppError::NotFound(format!("Não há escala para {}.", data)));
+    }
+    responder_dias(&state, &session, &atual, formato, &data, &data).await
+}
+
+/// Dias de escala entre `inicio` e `fim` (inclusive), no formato pedido.
+async fn responder_dias(
+    state: &AppState,
+    session: &Session,
+    atual: &CurrentUser,
+    formato: Formato,
+    inicio: &str,
+    fim: &str,
+) -> Response {
+    if formato == Formato::Json {
+        // Como na página: as prévias (rascunhos) são visíveis para todos, para as trocas
+        return match escala_service::dias_periodo(&state.db_leitura, atual.organizacao_id, inicio, fim, true).await {
+            Ok(dias) => Json(dias).into_response(),
+            Err(e) => formato.erro(e.into()),
+        };
+    }
+
+    let user_atual_id = atual.id.clone();
     
-    // 1. Verificar se é Admin
-    let is_admin = if !user_atual_id.is_empty() {
-        sqlx::query_scalar!(
-            "SELECT COUNT(*) FROM user_roles WHERE user_id = ? AND role = 'admin'", 
-            user_atual_id
-        )
-        .fetch_one(&state.db_pool)
-        .await
-        .unwrap_or(0) > 0
-    } else { 
-        false 
-    };
+    // 1. Verificar se pode gerir a escala (matriz de permissões, com as roles já carregadas)
+    let is_admin = atual.tem_permissao(state, permission_service::PERM_ESCALA_GERIR).await.unwrap_or(false);
+    // Link de cada dia publicado para o livro de ocorrências desse dia
+    let pode_ver_livro = atual.tem_permissao(state, permission_service::PERM_LIVRO).await.unwrap_or(false);
 
     // 2. Buscar dados da BD
-    let hoje = chrono::Local::now().date_naive();
-    
-    // NOTA: A sintaxe 'as "nome?"' força o SQLx a tratar o campo como Option<String>
-    // Isso é crucial para LEFT JOINs onde os dados podem não existir.
-    let rows = sqlx::query!(
+    // Os campos da alocação são Option (LEFT JOIN: dias ainda sem alocações)
+    let rows = sqlx::query_as::<_, LinhaEscala>(
         r#"
         SELECT 
             e.data, 
             e.tipo_rotina, 
             e.status,
-            a.id as "aloc_id?", 
-            a.user_id as "user_id?", 
-            u.name as "militar?", 
-            p.nome as "posto?", 
-            u.turma as "turma?", 
-            a.is_punicao as "is_punicao?"
+            a.id as aloc_id, 
+            a.user_id, 
+            u.name as militar, 
+            p.nome as posto, 
+            u.turma, 
+            a.is_punicao
         FROM escalas e
-        LEFT JOIN alocacoes a ON e.data = a.data
+        LEFT JOIN alocacoes a ON a.organizacao_id = e.organizacao_id AND a.data = e.data
         LEFT JOIN users u ON a.user_id = u.id
         LEFT JOIN postos p ON a.posto_id = p.id
-        WHERE e.data >= ? 
+        WHERE e.organizacao_id = ? AND e.data BETWEEN ? AND ?
         ORDER BY e.data ASC, p.peso DESC, p.nome ASC
         "#,
-        hoje
-    ).fetch_all(&state.db_pool).await.unwrap_or_default();
+    )
+    .bind(atual.organizacao_id)
+    .bind(inicio)
+    .bind(fim)
+    .fetch_all(&state.db_leitura).await.unwrap_or_default();
+    // Uniforme de cada dia (falha de leitura = não mostrado)
+    let mut uniformes: BTreeMap<String, _> = uniforme_service::periodo(&state.db_leitura, atual.organizacao_id, inicio, fim)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|u| (u.data.clone(), u))
+        .collect();
 
     // 3. Processar e Agrupar
     let mut dias_map: BTreeMap<String, EscalaDiaView> = BTreeMap::new();
 
     for row in rows {
-        // e.data, e.status, e.tipo_rotina são da tabela principal (não Option)
-        let data_key = row.data.clone().unwrap_or_else(|| hoje.to_string());
+        // e.data, e.tipo_rotina são da tabela principal (não Option)
+        let data_key = row.data.clone();
         let entry = dias_map.entry(data_key.clone()).or_insert_with(|| {
-            let d = chrono::NaiveDate::parse_from_str(&data_key, "%Y-%m-%d").unwrap_or(hoje);
-            
-            let dia_semana = match d.weekday() {
-                chrono::Weekday::Mon => "Segunda", 
-                chrono::Weekday::Tue => "Terça",
-                chrono::Weekday::Wed => "Quarta", 
-                chrono::Weekday::Thu => "Quinta",
-                chrono::Weekday::Fri => "Sexta", 
-                chrono::Weekday::Sat => "Sábado",
-                chrono::Weekday::Sun => "Domingo",
-            };
-            
             // garantir que temos Strings (fornecer valores padrão se forem Option)
             let status = row.status.clone().unwrap_or_else(|| "Rascunho".to_string());
             let tipo = row.tipo_rotina.clone();
 
             EscalaDiaView {
                 data: data_key.clone(),
-                data_formatada: format!("{}, {}", dia_semana, d.format("%d/%m")),
                 tipo,
                 status,
                 alocacoes: Vec::new(),
+                uniforme: uniformes.remove(&data_key),
             }
         });
 
@@ -120,11 +178,15 @@ pub async fn handle_pagina_escala(
         }
     }
 
+    let flash = flash::consumir(session).await;
     let template = EscalaTemplate {
         dias_publicados,
         dias_rascunho,
         is_admin,
+        pode_ver_livro,
         user_atual_id,
+        success_message: flash.success,
+        error_message: flash.error,
     };
 
     match template.render() {
@@ -136,126 +198,167 @@ pub async fn handle_pagina_escala(
     }
 }
 
+// --- BOLETIM DIÁRIO (GET /escala/{data}/boletim) ---
+/// Boletim de um dia para impressão: uniforme, serviço (postos e militares) e agenda do dia.
+pub async fn handle_boletim_dia(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Path(data): Path<String>,
+) -> AppResult<Response> {
+    let dia = chrono::NaiveDate::parse_from_str(&data, "%Y-%m-%d")
+        .map_err(|_| AppError::validation("data", format!("Data inválida: '{}' (use AAAA-MM-DD).", data)))?;
+    let escala = escala_service::dias_periodo(&state.db_leitura, atual.organizacao_id, &data, &data, true)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFound(format!("Não há escala para {}.", data)))?;
+    let template = BoletimDiaPage {
+        dia: escala,
+        uniforme: uniforme_service::do_dia(&state.db_leitura, atual.organizacao_id, dia).await?,
+        eventos: agenda_service::listar(&state.db_leitura, atual.organizacao_id, dia, dia).await?,
+        gerado_em: tempo::agora().format(tempo::FORMATO_DATA_HORA).to_string(),
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template BoletimDiaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
 // --- HANDLERS DA API ---
 
+/// Converte um erro da escala (ou da validação do payload) numa resposta de texto simples (consumida pelos
+/// `fetch` das páginas de escala), com o código HTTP de cada variante: 404 (não encontrado), 409 (dia publicado,
+/// ninguém disponível, fadiga, ...), 403, 500 (base de dados).
+/// Erros de validação seguem em JSON (`{"erros": [{"campo", "mensagem"}]}`) para o JS os mostrar junto aos campos.
+fn escala_error_response(e: impl Into<AppError>) -> axum::response::Response {
+    let e = e.into();
+    if e.status_code().is_server_error() {
+        tracing::error!("Erro na API de escala: {:?}", e);
+    } else {
+        tracing::warn!("Pedido de escala recusado: {}", e);
+    }
+    if let AppError::Validation(erros) = &e {
+        return (e.status_code(), Json(serde_json::json!({ "erros": erros }))).into_response();
+    }
+    (e.status_code(), e.user_message()).into_response()
+}
+
 pub async fn handle_gerar_periodo(
     State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
     Json(payload): Json<GerarPeriodoRequest>,
 ) -> impl IntoResponse {
-    match escala_service::gerar_escala_periodo(&state.db_pool, &payload.data_inicio, &payload.data_fim).await {
+    if let Err(e) = validar(&payload) {
+        return escala_error_response(e);
+    }
+    match escala_service::gerar_escala_periodo(&state.db_pool, organizacao_id, &payload.data_inicio, &payload.data_fim, state.config.escala.fadiga_dias).await {
         Ok(msg) => (StatusCode::OK, msg).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        Err(e) => escala_error_response(e),
     }
 }
 
 pub async fn handle_publicar_periodo(
     State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
     Json(payload): Json<PublicarRequest>,
 ) -> impl IntoResponse {
-    match escala_service::publicar_escala(&state.db_pool, &payload.data_inicio, &payload.data_fim).await {
+    if let Err(e) = validar(&payload) {
+        return escala_error_response(e);
+    }
+    match escala_service::publicar_escala(&state.db_pool, organizacao_id, &payload.data_inicio, &payload.data_fim).await {
         Ok(msg) => (StatusCode::OK, msg).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        Err(e) => escala_error_response(e),
     }
 }
 
 pub async fn handle_solicitar_troca(
     State(state): State<AppState>,
-    session: Session,
+    atual: CurrentUser,
     Json(payload): Json<PedidoTrocaPayload>,
 ) -> impl IntoResponse {
-    let user_id = match session.get::<String>("user_id").await {
-        Ok(Some(id)) => id,
-        _ => return (StatusCode::UNAUTHORIZED, "Login necessário").into_response(),
-    };
+    let user_id = atual.id;
+
+    if let Err(e) = validar(&payload) {
+        return escala_error_response(e);
+    }
 
     // Passamos payload.alocacao_substituto_id (que deve ser Option<String> na struct)
     match escala_service::solicitar_troca(
         &state.db_pool, 
+        atual.organizacao_id,
         &user_id, 
         &payload.alocacao_id, 
         &payload.substituto_id, 
         payload.alocacao_substituto_id, // <--- Passando o novo campo
-        &payload.motivo
+        &payload.motivo,
+        state.config.escala.fadiga_dias,
     ).await {
         Ok(msg) => (StatusCode::OK, msg).into_response(),
-        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) => escala_error_response(e),
     }
 }
 
 pub async fn handle_aprovar_troca(
     State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
     Path(troca_id): Path<String>,
 ) -> impl IntoResponse {
-    match escala_service::aprovar_troca(&state.db_pool, &troca_id).await {
+    match escala_service::aprovar_troca(&state.db_pool, organizacao_id, &troca_id).await {
         Ok(msg) => (StatusCode::OK, msg).into_response(),
-        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) => escala_error_response(e),
     }
 }
 
 pub async fn handle_errata(
     State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
     Path(data): Path<String>,
 ) -> impl IntoResponse {
-    match escala_service::errata_dia(&state.db_pool, &data).await {
+    match escala_service::errata_dia(&state.db_pool, organizacao_id, &data).await {
         Ok(msg) => (StatusCode::OK, msg).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        Err(e) => escala_error_response(e),
     }
 }
 
 pub async fn handle_admin_escala_page(
     State(state): State<AppState>,
     session: Session,
+    atual: CurrentUser,
 ) -> impl IntoResponse {
-    // 1. Verificar se há sessão (Login)
-    let user_id = match session.get::<String>("user_id").await {
-        Ok(Some(id)) => id,
-        _ => return Redirect::to("/").into_response(),
-    };
+    // 1. Verificar Permissão (matriz de permissões; utilizador e roles vêm de require_auth)
+    let pode_gerir = atual.tem_permissao(&state, permission_service::PERM_ESCALA_GERIR).await.unwrap_or(false);
 
-    // 2. Verificar Permissão e Buscar Nome (SIMPLIFICAÇÃO: 1 Query Única)
-    // Busca o nome APENAS se o usuário tiver a role 'admin' ou 'escalante'.
-    // Se não retornar nada, significa que não tem permissão.
-    let acesso = sqlx::query!(
-        r#"
-        SELECT u.name 
-        FROM users u
-        JOIN user_roles ur ON u.id = ur.user_id
-        WHERE u.id = ? AND ur.role IN ('admin', 'escalante')
-        LIMIT 1
-        "#,
-        user_id
-    )
-    .fetch_optional(&state.db_pool)
-    .await
-    .unwrap_or(None);
+    if !pode_gerir {
+        return flash::redirect_error(&session, "/escala/", "Acesso negado. Apenas Escalantes.").await.into_response();
+    }
 
-    let user_name = match acesso {
-        Some(registro) => registro.name,
-        None => return (StatusCode::FORBIDDEN, "Acesso negado. Apenas Escalantes.").into_response(),
-    };
+    let CurrentUser { name: user_name, organizacao_id, .. } = atual;
 
     // 3. Buscar Lista de Punidos (Quem deve serviço)
     // Ordenado por quem deve mais.
-    let punidos = sqlx::query_as!(
-        UserPunido,
+    let punidos = sqlx::query_as::<_, UserPunido>(
         r#"
-        SELECT id, name, saldo_punicoes as "saldo!"
+        SELECT id, name, saldo_punicoes as saldo
         FROM users 
-        WHERE saldo_punicoes > 0 
+        WHERE organizacao_id = ? AND saldo_punicoes > 0 
         ORDER BY saldo_punicoes DESC, name ASC
         "#
     )
-    .fetch_all(&state.db_pool)
+    .bind(organizacao_id)
+    .fetch_all(&state.db_leitura)
     .await
     .unwrap_or_default();
 
     // 4. Buscar Trocas Pendentes de Aprovação
     // JOINs necessários para transformar IDs em Nomes legíveis
-    let trocas_rows = sqlx::query!(
+    let trocas_pendentes = sqlx::query_as::<_, TrocaPendenteAdmin>(
         r#"
         SELECT 
             t.id, 
-            t.motivo, 
+            COALESCE(t.motivo, '') as motivo, 
             u1.name as solicitante, 
             u2.name as substituto, 
             e.data, 
@@ -264,26 +367,17 @@ pub async fn handle_admin_escala_page(
         JOIN users u1 ON t.solicitante_id = u1.id
         JOIN users u2 ON t.substituto_id = u2.id
         JOIN alocacoes a ON t.alocacao_id = a.id
-        JOIN escalas e ON a.data = e.data
+        JOIN escalas e ON e.organizacao_id = a.organizacao_id AND e.data = a.data
         JOIN postos p ON a.posto_id = p.id
-        WHERE t.status = 'AguardandoEscalante'
+        WHERE a.organizacao_id = ? AND t.status = 'AguardandoEscalante'
         ORDER BY e.data ASC
         "#
     )
-    .fetch_all(&state.db_pool)
+    .bind(organizacao_id)
+    .fetch_all(&state.db_leitura)
     .await
     .unwrap_or_default();
 
-    // Converter resultados do banco para a struct do Template
-    let trocas_pendentes = trocas_rows.into_iter().map(|row| TrocaPendenteAdmin {
-        id: row.id,
-        solicitante: row.solicitante,
-        substituto: row.substituto,
-        data: row.data.unwrap_or_else(|| "".to_string()),
-        posto: row.posto,
-        motivo: row.motivo.unwrap_or_else(|| "".to_string()),
-    }).collect();
-
     // 5. Renderizar Template
     let template = AdminEscalaPage {
         user_name,
@@ -0,0 +1,111 @@
+// src/web/estaticos.rs
+//! Ficheiros estáticos (CSS, JS, imagens) em /static. Por omissão vêm de `static/`, embutido no binário
+//! (rust-embed; nas compilações de desenvolvimento é lido do disco a cada pedido, para editar sem recompilar).
+//! Com `estaticos.diretorio` (ver `config::EstaticosConfig`) são lidos desse diretório pelo `ServeDir`,
+//! que trata de Last-Modified/If-Modified-Since e de pedidos Range.
+//! Em ambos os casos junta-se o Cache-Control configurado e um ETag, com 304 a um If-None-Match que coincida.
+//! (Os templates não precisam de nada disto: o askama compila-os para dentro do binário.)
+
+use crate::config::EstaticosConfig;
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use rust_embed::RustEmbed;
+use sha2::{Digest, Sha256};
+use tower_http::services::ServeDir;
+
+/// Conteúdo de `static/` no momento da compilação.
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct Embutidos;
+
+/// Router com os ficheiros de `config.diretorio` (para montar em /static).
+pub fn router<S: Clone + Send + Sync + 'static>(config: &EstaticosConfig) -> Router<S> {
+    let cache = if config.cache_segundos == 0 {
+        "no-cache".to_string()
+    } else {
+        format!("public, max-age={}", config.cache_segundos)
+    };
+    let cache = HeaderValue::from_str(&cache).unwrap_or(HeaderValue::from_static("no-cache"));
+    let router = match &config.diretorio {
+        Some(diretorio) => Router::new().fallback_service(ServeDir::new(diretorio)),
+        None => Router::new().route("/{*caminho}", get(servir_embutido)),
+    };
+    router.layer(middleware::from_fn_with_state(cache, cabecalhos_cache))
+}
+
+/// Handler para GET /static/{*caminho} - Ficheiro embutido no binário
+async fn servir_embutido(Path(caminho): Path<String>) -> Response {
+    let Some(ficheiro) = Embutidos::get(&caminho) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let hash: String = ficheiro.metadata.sha256_hash()[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    let mut resposta = ficheiro.data.into_owned().into_response();
+    let headers = resposta.headers_mut();
+    if let Ok(tipo) = HeaderValue::from_str(ficheiro.metadata.mimetype()) {
+        headers.insert(header::CONTENT_TYPE, tipo);
+    }
+    if let Ok(etag) = HeaderValue::from_str(&format!("\"{}\"", hash)) {
+        headers.insert(header::ETAG, etag);
+    }
+    let modificado = ficheiro.metadata.last_modified().and_then(|s| chrono::DateTime::from_timestamp(s as i64, 0));
+    if let Some(modificado) = modificado {
+        if let Ok(valor) = HeaderValue::from_str(&modificado.format("%a, %d %b %Y %H:%M:%S GMT").to_string()) {
+            headers.insert(header::LAST_MODIFIED, valor);
+        }
+    }
+    resposta
+}
+
+/// ETag fraco de uma resposta 200 do ServeDir (o conteúdo só muda com o tamanho ou a data de modificação).
+fn etag(resposta: &Response) -> Option<HeaderValue> {
+    let headers = resposta.headers();
+    let tamanho = headers.get(header::CONTENT_LENGTH)?.to_str().ok()?;
+    let modificado = headers.get(header::LAST_MODIFIED)?.to_str().ok()?;
+    let hash = format!("{:x}", Sha256::digest(format!("{}|{}", tamanho, modificado).as_bytes()));
+    HeaderValue::from_str(&format!("W/\"{}\"", &hash[..16])).ok()
+}
+
+/// Middleware: Cache-Control e ETag nas respostas com sucesso; 304 se o cliente já tiver esta versão.
+async fn cabecalhos_cache(State(cache): State<HeaderValue>, request: Request, next: Next) -> Response {
+    let condicional = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let leitura = matches!(*request.method(), Method::GET | Method::HEAD);
+    let mut resposta = next.run(request).await;
+    let status = resposta.status();
+    if status != StatusCode::OK && status != StatusCode::NOT_MODIFIED {
+        return resposta;
+    }
+    resposta.headers_mut().insert(header::CACHE_CONTROL, cache);
+    if status != StatusCode::OK {
+        return resposta;
+    }
+
+    // Os ficheiros embutidos já trazem o ETag (hash do conteúdo)
+    let Some(etag) = resposta.headers().get(header::ETAG).cloned().or_else(|| etag(&resposta)) else {
+        return resposta;
+    };
+    let coincide = condicional.is_some_and(|valor| {
+        valor.split(',').map(str::trim).any(|v| v == "*" || v.as_bytes() == etag.as_bytes())
+    });
+    if leitura && coincide {
+        let mut nao_modificado = StatusCode::NOT_MODIFIED.into_response();
+        for nome in [header::CACHE_CONTROL, header::LAST_MODIFIED] {
+            if let Some(valor) = resposta.headers().get(&nome) {
+                nao_modificado.headers_mut().insert(nome, valor.clone());
+            }
+        }
+        nao_modificado.headers_mut().insert(header::ETAG, etag);
+        return nao_modificado;
+    }
+    resposta.headers_mut().insert(header::ETAG, etag);
+    resposta
+}
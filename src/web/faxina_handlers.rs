@@ -0,0 +1,303 @@
+// src/web/faxina_handlers.rs
+//! Escala de faxina (/faxina): todos veem as semanas publicadas (com a sua área em destaque); quem gere
+//! a escala (permissão "escala.gerir") vê também os rascunhos, publica e faz errata, e em /faxina/gerir
+//! define as áreas de limpeza, gera as semanas e consulta os contadores da rotação.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, faxina_service, permission_service},
+    state::AppState,
+    templates::{FaxinaGerirPage, FaxinaPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::{Duration, NaiveDate};
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do nome e da descrição de uma área.
+const MAX_NOME: usize = 80;
+const MAX_DESCRICAO: usize = 300;
+
+#[derive(Deserialize, Debug)]
+pub struct AreaForm {
+    #[serde(default)]
+    nome: String,
+    #[serde(default)]
+    descricao: String,
+    #[serde(default)]
+    rotacao: String,
+    #[serde(default)]
+    genero_restricao: String,
+    #[serde(default)]
+    ativa: Option<String>, // Checkbox (só na alteração)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GerarFaxinaForm {
+    #[serde(default)]
+    inicio: String,
+    #[serde(default)]
+    semanas: String,
+}
+
+/// Pode gerir a escala (e, com ela, a faxina)?
+async fn pode_gerir(state: &AppState, atual: &CurrentUser) -> AppResult<bool> {
+    atual.tem_permissao(state, permission_service::PERM_ESCALA_GERIR).await
+}
+
+/// Falha (403) se o utilizador não gere a escala.
+async fn exigir_gerir(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if pode_gerir(state, atual).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Faxina: {} sem permissão '{}'.", atual.id, permission_service::PERM_ESCALA_GERIR);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Semana do caminho (`/faxina/{semana}/...`): uma data qualquer dessa semana.
+fn ler_semana(semana: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(semana, "%Y-%m-%d")
+        .map(faxina_service::semana_de)
+        .map_err(|_| AppError::validation("semana", format!("Semana inválida: '{}' (use AAAA-MM-DD).", semana)))
+}
+
+/// Handler para GET /faxina - Semanas publicadas a partir desta (e os rascunhos, para quem gere)
+pub async fn show_faxina(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    let pode_gerir = pode_gerir(&state, &atual).await?;
+    let template = FaxinaPage {
+        semanas: faxina_service::semanas(&state.db_leitura, atual.organizacao_id, tempo::hoje(), &atual.id, pode_gerir).await?,
+        pode_gerir,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template FaxinaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Renderiza as áreas, a geração e os contadores.
+async fn pagina_gerir(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = FaxinaGerirPage {
+        areas: faxina_service::listar_areas(&state.db_leitura, organizacao_id).await?,
+        contagem: faxina_service::contagem(&state.db_leitura, organizacao_id).await?,
+        rotacoes: faxina_service::ROTACOES,
+        generos: faxina_service::GENEROS,
+        max_semanas: faxina_service::MAX_SEMANAS,
+        semana_atual: faxina_service::semana_de(tempo::hoje()).to_string(),
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template FaxinaGerirPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Segunda-feira da próxima semana (início sugerido da geração).
+fn semana_seguinte() -> NaiveDate {
+    faxina_service::semana_de(tempo::hoje()) + Duration::weeks(1)
+}
+
+/// Formulário de geração por omissão: 4 semanas a partir da seguinte.
+fn form_por_omissao() -> FormState {
+    FormState::default()
+        .com_valor("inicio", semana_seguinte().to_string())
+        .com_valor("semanas", "4")
+        .com_valor("rotacao", "quarto")
+        .com_valor("genero_restricao", "Misto")
+}
+
+/// Handler para GET /faxina/gerir - Áreas de limpeza, geração das semanas e contadores
+pub async fn show_gerir_faxina(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    exigir_gerir(&state, &atual).await?;
+    pagina_gerir(&state, atual.organizacao_id, StatusCode::OK, form_por_omissao(), flash).await
+}
+
+/// Handler para POST /faxina/areas - Cria uma área de limpeza
+pub async fn handle_criar_area(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<AreaForm>,
+) -> AppResult<Response> {
+    exigir_gerir(&state, &atual).await?;
+    let mut v = Validador::default();
+    v.obrigatorio("nome", &form.nome, MAX_NOME);
+    if form.descricao.trim().chars().count() > MAX_DESCRICAO {
+        v.erro("descricao", format!("Máximo de {} caracteres.", MAX_DESCRICAO));
+    }
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            faxina_service::criar_area(
+                &state.db_pool,
+                atual.organizacao_id,
+                &form.nome,
+                &form.descricao,
+                form.rotacao.trim(),
+                form.genero_restricao.trim(),
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!("{} ({})", form.nome.trim(), faxina_service::descrever_rotacao(form.rotacao.trim()));
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_FAXINA_AREA_CRIADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Área '{}' criada.", form.nome.trim());
+            Ok(flash::redirect_success(&session, "/faxina/gerir", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("nome", form.nome)
+                .com_valor("descricao", form.descricao)
+                .com_valor("rotacao", form.rotacao)
+                .com_valor("genero_restricao", form.genero_restricao)
+                .com_valor("inicio", semana_seguinte().to_string())
+                .com_valor("semanas", "4");
+            pagina_gerir(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /faxina/areas/{id} - Altera uma área (descrição, rotação, género, ativa)
+pub async fn handle_atualizar_area(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<AreaForm>,
+) -> AppResult<Response> {
+    exigir_gerir(&state, &atual).await?;
+    if form.descricao.trim().chars().count() > MAX_DESCRICAO {
+        let mensagem = format!("A descrição tem no máximo {} caracteres.", MAX_DESCRICAO);
+        return Ok(flash::redirect_error(&session, "/faxina/gerir", mensagem).await.into_response());
+    }
+    let ativa = form.ativa.is_some();
+    let resultado = faxina_service::atualizar_area(
+        &state.db_pool,
+        atual.organizacao_id,
+        id,
+        &form.descricao,
+        form.rotacao.trim(),
+        form.genero_restricao.trim(),
+        ativa,
+    )
+    .await;
+    match resultado {
+        Ok(nome) => {
+            let detalhes = format!(
+                "{}: {}, género {}, {}",
+                nome,
+                faxina_service::descrever_rotacao(form.rotacao.trim()),
+                form.genero_restricao.trim(),
+                if ativa { "ativa" } else { "inativa" }
+            );
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_FAXINA_AREA_ALTERADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Área '{}' alterada (vale para as próximas gerações).", nome);
+            Ok(flash::redirect_success(&session, "/faxina/gerir", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let mensagem = erros.iter().map(|e| e.mensagem.clone()).collect::<Vec<_>>().join(" ");
+            Ok(flash::redirect_error(&session, "/faxina/gerir", mensagem).await.into_response())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /faxina/gerar - Gera em rascunho as semanas pedidas (as publicadas ficam)
+pub async fn handle_gerar_faxina(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<GerarFaxinaForm>,
+) -> AppResult<Response> {
+    exigir_gerir(&state, &atual).await?;
+    let mut v = Validador::default();
+    let inicio = v.data("inicio", &form.inicio);
+    let semanas = form.semanas.trim().parse::<i64>().unwrap_or(0);
+    v.intervalo("semanas", semanas, 1, faxina_service::MAX_SEMANAS);
+    let resultado = match (v.resultado(), inicio) {
+        (Ok(()), Some(inicio)) => faxina_service::gerar_semanas(&state.db_pool, atual.organizacao_id, inicio, semanas).await,
+        (Err(e), _) => Err(e),
+        (Ok(()), None) => Err(AppError::validation("inicio", "Data inválida.")),
+    };
+    match resultado {
+        Ok((geradas, mantidas)) => {
+            let mut mensagem = format!("{} semana(s) de faxina geradas em rascunho.", geradas);
+            if mantidas > 0 {
+                mensagem.push_str(&format!(" {} já publicada(s) mantida(s).", mantidas));
+            }
+            Ok(flash::redirect_success(&session, "/faxina", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("inicio", form.inicio)
+                .com_valor("semanas", form.semanas)
+                .com_valor("rotacao", "quarto")
+                .com_valor("genero_restricao", "Misto");
+            pagina_gerir(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, "/faxina/gerir", mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /faxina/{semana}/publicar - Publica a semana e notifica os responsáveis
+pub async fn handle_publicar_faxina(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(semana): Path<String>,
+) -> AppResult<Response> {
+    exigir_gerir(&state, &atual).await?;
+    let semana = ler_semana(&semana)?;
+    match faxina_service::publicar(&state.db_pool, atual.organizacao_id, semana).await {
+        Ok(()) => {
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_FAXINA_PUBLICADA, Some(&semana.to_string()), None).await;
+            let mensagem = format!("Faxina da semana de {} publicada.", tempo::data_curta(semana));
+            Ok(flash::redirect_success(&session, "/faxina", mensagem).await.into_response())
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, "/faxina", mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /faxina/{semana}/errata - Reabre em rascunho uma semana publicada
+pub async fn handle_errata_faxina(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(semana): Path<String>,
+) -> AppResult<Response> {
+    exigir_gerir(&state, &atual).await?;
+    let semana = ler_semana(&semana)?;
+    match faxina_service::reabrir(&state.db_pool, atual.organizacao_id, semana).await {
+        Ok(()) => {
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_FAXINA_REABERTA, Some(&semana.to_string()), None).await;
+            let mensagem = format!("Faxina da semana de {} reaberta em rascunho: pode voltar a gerá-la.", tempo::data_curta(semana));
+            Ok(flash::redirect_success(&session, "/faxina", mensagem).await.into_response())
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, "/faxina", mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,230 @@
+// src/web/feed_handlers.rs
+//! Feeds Atom para leitores de feeds e integrações (ex: reencaminhar para um webhook do Discord/Slack):
+//! /feeds/escala.atom lista os dias da escala publicados, os mais recentes primeiro. Quando existir um
+//! mural de avisos, os avisos entram no mesmo feed. /feeds/agenda.ics publica em iCal (RFC 5545) os
+//! eventos da agenda institucional para o ano do utilizador, para subscrever numa aplicação de calendário.
+//! Os leitores de feeds não enviam cabeçalhos, por isso, além da sessão, aceita-se um token de API
+//! em `?token=` (criado em /user/tokens).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::agenda::EventoAgenda,
+    services::{agenda_service, api_token_service, bloqueio_service, escala_service, organizacao_service, user_service},
+    state::AppState,
+    templates::{EntradaFeed, FeedEscala},
+    tempo,
+};
+use askama::Template;
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Dias publicados incluídos no feed.
+const MAX_ENTRADAS: i64 = 50;
+/// Eventos da agenda incluídos no iCal: dos últimos 30 dias até daqui a um ano.
+const AGENDA_DIAS_ANTES: i64 = 30;
+const AGENDA_DIAS_DEPOIS: i64 = 365;
+/// Duração dos eventos sem hora de fim (minutos), para aparecerem no calendário.
+const DURACAO_PADRAO_MIN: i64 = 60;
+
+#[derive(Deserialize, Debug)]
+pub struct FeedQuery {
+    token: Option<String>,
+}
+
+/// Dono do token de `?token=` ou, sem ele, o utilizador da sessão, e a sua organização (a escala do feed).
+async fn autenticar(state: &AppState, session: &Session, token: Option<&str>) -> AppResult<(String, i64)> {
+    let user_id = match token.map(str::trim).filter(|t| !t.is_empty()) {
+        Some(token) => api_token_service::autenticar(&state.db_pool, token).await?,
+        None => session.get::<String>("user_id").await.ok().flatten(),
+    };
+    let user_id = user_id.ok_or(AppError::Unauthenticated)?;
+    if bloqueio_service::bloqueio_manual(&state.db_pool, &user_id).await?.is_some() {
+        tracing::warn!("Feed: {} recusado (conta bloqueada).", user_id);
+        return Err(AppError::Unauthorized);
+    }
+    match organizacao_service::do_utilizador(&state.db_pool, &user_id).await? {
+        Some((organizacao_id, true)) => Ok((user_id, organizacao_id)),
+        Some((_, false)) => {
+            tracing::warn!("Feed: {} recusado (organização desativada).", user_id);
+            Err(AppError::Unauthorized)
+        }
+        None => Err(AppError::Unauthenticated),
+    }
+}
+
+/// Esquema e host pelos quais o cliente chegou (os links do feed têm de ser absolutos).
+fn url_base(headers: &HeaderMap) -> String {
+    let valor = |nome: &str| headers.get(nome).and_then(|v| v.to_str().ok()).map(str::trim).filter(|v| !v.is_empty());
+    let esquema = valor("x-forwarded-proto").unwrap_or("http");
+    let host = valor("x-forwarded-host").or(valor(header::HOST.as_str())).unwrap_or("localhost");
+    format!("{}://{}", esquema, host)
+}
+
+/// 'YYYY-MM-DD HH:MM:SS' (UTC, SQLite) -> RFC 3339.
+fn rfc3339(data_hora: &str) -> String {
+    NaiveDateTime::parse_from_str(data_hora, "%Y-%m-%d %H:%M:%S")
+        .map(|d| d.and_utc().to_rfc3339())
+        .unwrap_or_else(|_| Utc::now().to_rfc3339())
+}
+
+// GET /feeds/escala.atom[?token=]
+pub async fn handle_feed_escala(
+    State(state): State<AppState>,
+    session: Session,
+    headers: HeaderMap,
+    Query(params): Query<FeedQuery>,
+) -> AppResult<Response> {
+    let (user_id, organizacao_id) = autenticar(&state, &session, params.token.as_deref()).await?;
+    tracing::debug!("Feed da escala pedido por {}.", user_id);
+
+    let publicacoes = escala_service::publicacoes_recentes(&state.db_leitura, organizacao_id, MAX_ENTRADAS).await?;
+    let atualizado = publicacoes
+        .first()
+        .map(|(publicada_em, _)| rfc3339(publicada_em))
+        .unwrap_or_else(|| Utc::now().to_rfc3339());
+    let entradas = publicacoes
+        .into_iter()
+        .map(|(publicada_em, dia)| {
+            let data = NaiveDate::parse_from_str(&dia.data, "%Y-%m-%d")
+                .map(|d| d.format("%d/%m/%Y").to_string())
+                .unwrap_or_else(|_| dia.data.clone());
+            EntradaFeed {
+                titulo: format!("Escala de {} ({}) publicada", data, dia.tipo_rotina),
+                atualizado: rfc3339(&publicada_em),
+                servicos: dia
+                    .alocacoes
+                    .iter()
+                    .map(|a| format!("{}: {} ({})", a.posto, a.militar, a.turma))
+                    .collect(),
+                data: dia.data,
+            }
+        })
+        .collect();
+
+    let feed = FeedEscala { base: url_base(&headers), atualizado, entradas };
+    let xml = feed.render().map_err(|e| {
+        tracing::error!("Erro ao renderizar o feed da escala: {}", e);
+        AppError::InternalServerError
+    })?;
+    Ok(([(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")], xml).into_response())
+}
+
+/// Texto de uma propriedade iCal (escapa `\`, `;`, `,` e as mudanças de linha).
+fn ical_texto(texto: &str) -> String {
+    texto
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Acrescenta uma linha iCal, dobrada em linhas de até 75 bytes (as seguintes começam por espaço).
+fn ical_linha(ics: &mut String, linha: &str) {
+    let mut resto = linha;
+    let mut limite = 75;
+    while resto.len() > limite {
+        let mut corte = limite;
+        while !resto.is_char_boundary(corte) {
+            corte -= 1;
+        }
+        ics.push_str(&resto[..corte]);
+        ics.push_str("\r\n ");
+        resto = &resto[corte..];
+        limite = 74; // O espaço inicial conta
+    }
+    ics.push_str(resto);
+    ics.push_str("\r\n");
+}
+
+/// Dia e hora locais ('YYYY-MM-DD', 'HH:MM') -> UTC no formato iCal ('20250101T080000Z').
+fn ical_utc(data: &str, hora: &str) -> Option<String> {
+    let data = NaiveDate::parse_from_str(data, "%Y-%m-%d").ok()?;
+    let hora = NaiveTime::parse_from_str(hora, "%H:%M").ok()?;
+    let local = tempo::fuso().from_local_datetime(&data.and_time(hora)).earliest()?;
+    Some(local.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string())
+}
+
+/// VEVENT de um evento da agenda.
+fn ical_evento(ics: &mut String, evento: &EventoAgenda, host: &str, carimbo: &str) {
+    let Some(inicio) = ical_utc(&evento.data, &evento.hora_inicio) else {
+        tracing::warn!("iCal: evento {} com dia/hora inválidos; omitido.", evento.id);
+        return;
+    };
+    let fim = evento
+        .hora_fim
+        .as_deref()
+        .and_then(|fim| ical_utc(&evento.data, fim))
+        .or_else(|| {
+            let hora = NaiveTime::parse_from_str(&evento.hora_inicio, "%H:%M").ok()? + Duration::minutes(DURACAO_PADRAO_MIN);
+            ical_utc(&evento.data, &hora.format("%H:%M").to_string())
+        });
+    let mut descricao = format!(
+        "{} · {} · Presença {}",
+        agenda_service::descrever_tipo(&evento.tipo),
+        evento.descricao_anos(),
+        if evento.obrigatorio { "obrigatória" } else { "facultativa" }
+    );
+    if !evento.descricao.is_empty() {
+        descricao.push('\n');
+        descricao.push_str(&evento.descricao);
+    }
+
+    ical_linha(ics, "BEGIN:VEVENT");
+    ical_linha(ics, &format!("UID:agenda-{}@{}", evento.id, host));
+    ical_linha(ics, &format!("DTSTAMP:{}", carimbo));
+    ical_linha(ics, &format!("DTSTART:{}", inicio));
+    if let Some(fim) = fim {
+        ical_linha(ics, &format!("DTEND:{}", fim));
+    }
+    ical_linha(ics, &format!("SUMMARY:{}", ical_texto(&evento.titulo)));
+    if !evento.local.is_empty() {
+        ical_linha(ics, &format!("LOCATION:{}", ical_texto(&evento.local)));
+    }
+    ical_linha(ics, &format!("DESCRIPTION:{}", ical_texto(&descricao)));
+    ical_linha(ics, &format!("CATEGORIES:{}", ical_texto(agenda_service::descrever_tipo(&evento.tipo))));
+    ical_linha(ics, "END:VEVENT");
+}
+
+// GET /feeds/agenda.ics[?token=]
+pub async fn handle_feed_agenda(
+    State(state): State<AppState>,
+    session: Session,
+    headers: HeaderMap,
+    Query(params): Query<FeedQuery>,
+) -> AppResult<Response> {
+    let (user_id, organizacao_id) = autenticar(&state, &session, params.token.as_deref()).await?;
+    tracing::debug!("iCal da agenda pedido por {}.", user_id);
+
+    let ano = user_service::find_user_by_id(&state.db_leitura, &user_id).await?.map(|u| u.ano);
+    let hoje = tempo::hoje();
+    let eventos = agenda_service::listar(
+        &state.db_leitura,
+        organizacao_id,
+        hoje - Duration::days(AGENDA_DIAS_ANTES),
+        hoje + Duration::days(AGENDA_DIAS_DEPOIS),
+    )
+    .await?;
+
+    let base = url_base(&headers);
+    let host = base.split("://").nth(1).unwrap_or("localhost");
+    let carimbo = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut ics = String::new();
+    ical_linha(&mut ics, "BEGIN:VCALENDAR");
+    ical_linha(&mut ics, "VERSION:2.0");
+    ical_linha(&mut ics, "PRODID:-//Mercal2//Agenda//PT");
+    ical_linha(&mut ics, "CALSCALE:GREGORIAN");
+    ical_linha(&mut ics, "X-WR-CALNAME:Agenda institucional");
+    for evento in eventos.iter().filter(|e| ano.is_none_or(|ano| e.para_ano(ano))) {
+        ical_evento(&mut ics, evento, host, &carimbo);
+    }
+    ical_linha(&mut ics, "END:VCALENDAR");
+
+    Ok(([(header::CONTENT_TYPE, "text/calendar; charset=utf-8")], ics).into_response())
+}
@@ -0,0 +1,68 @@
+// src/web/flash.rs
+//! Mensagens "flash" de feedback (sucesso/erro).
+//! São guardadas na sessão antes de um redirect e consumidas (removidas) na próxima página renderizada,
+//! em vez de viajarem na query string (`?success=...`), onde poluíam o URL e podiam ser forjadas.
+
+use axum::{
+    extract::FromRequestParts,
+    http::request::Parts,
+    response::Redirect,
+};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+
+const FLASH_KEY: &str = "flash";
+
+/// Feedback pendente para o utilizador. Usado como extractor nos handlers GET:
+/// ao extrair, a mensagem é removida da sessão (só é mostrada uma vez).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Flash {
+    pub success: Option<String>,
+    pub error: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for Flash
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Sem sessão (ou erro ao lê-la) = sem mensagens; nunca bloqueia a página
+        let Ok(session) = Session::from_request_parts(parts, state).await else {
+            return Ok(Flash::default());
+        };
+        Ok(consumir(&session).await)
+    }
+}
+
+/// Lê e remove o feedback pendente (o que o extractor faz), para handlers que só o mostram em alguns casos
+/// (ex.: a resposta JSON da mesma rota não o deve gastar).
+pub async fn consumir(session: &Session) -> Flash {
+    match session.remove::<Flash>(FLASH_KEY).await {
+        Ok(flash) => flash.unwrap_or_default(),
+        Err(e) => {
+            tracing::warn!("Falha ao ler mensagem flash da sessão: {:?}", e);
+            Flash::default()
+        }
+    }
+}
+
+/// Guarda o feedback na sessão. Uma falha aqui só perde a mensagem (fica registada no log).
+async fn guardar(session: &Session, flash: Flash) {
+    if let Err(e) = session.insert(FLASH_KEY, flash).await {
+        tracing::warn!("Falha ao guardar mensagem flash na sessão: {:?}", e);
+    }
+}
+
+/// Guarda uma mensagem de sucesso e redireciona (padrão Post/Redirect/Get).
+pub async fn redirect_success(session: &Session, url: &str, mensagem: impl Into<String>) -> Redirect {
+    guardar(session, Flash { success: Some(mensagem.into()), error: None }).await;
+    Redirect::to(url)
+}
+
+/// Guarda uma mensagem de erro e redireciona (padrão Post/Redirect/Get).
+pub async fn redirect_error(session: &Session, url: &str, mensagem: impl Into<String>) -> Redirect {
+    guardar(session, Flash { success: None, error: Some(mensagem.into()) }).await;
+    Redirect::to(url)
+}
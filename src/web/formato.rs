@@ -0,0 +1,80 @@
+// src/web/formato.rs
+//! Negociação do formato da resposta: a mesma rota devolve a página (Askama) ou a representação JSON,
+//! conforme `?format=json|html` ou, na falta deste, o cabeçalho `Accept`.
+
+use crate::error::{ApiError, AppError};
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::{header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+/// Formato pedido pelo cliente. Usado como extractor nos handlers GET que servem os dois formatos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Formato {
+    Html,
+    Json,
+}
+
+#[derive(Deserialize)]
+struct FormatoQuery {
+    format: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for Formato
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // 1. `?format=` tem prioridade (links e testes no browser)
+        if let Ok(Query(query)) = Query::<FormatoQuery>::try_from_uri(&parts.uri) {
+            match query.format.as_deref().map(str::trim) {
+                Some(f) if f.eq_ignore_ascii_case("json") => return Ok(Formato::Json),
+                Some(f) if f.eq_ignore_ascii_case("html") => return Ok(Formato::Html),
+                _ => {}
+            }
+        }
+        // 2. JSON só se for preferido ao HTML: os browsers pedem text/html (e */*), o `curl` simples só */*
+        let accept = parts
+            .headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        if qualidade(accept, "application/json") > qualidade(accept, "text/html") {
+            Ok(Formato::Json)
+        } else {
+            Ok(Formato::Html)
+        }
+    }
+}
+
+/// Maior `q` dado a um tipo (exato) no cabeçalho `Accept`; 0 se não for pedido.
+fn qualidade(accept: &str, tipo: &str) -> f32 {
+    accept
+        .split(',')
+        .filter_map(|item| {
+            let mut partes = item.split(';');
+            if !partes.next()?.trim().eq_ignore_ascii_case(tipo) {
+                return None;
+            }
+            let q = partes
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.trim().parse().ok())
+                .unwrap_or(1.0);
+            Some(q)
+        })
+        .fold(0.0, f32::max)
+}
+
+impl Formato {
+    /// Resposta de erro no formato pedido: página de erro ou o envelope JSON da API.
+    pub fn erro(self, e: AppError) -> Response {
+        match self {
+            Formato::Html => e.into_response(),
+            Formato::Json => ApiError(e).into_response(),
+        }
+    }
+}
@@ -0,0 +1,495 @@
+// src/web/graphql.rs
+//! GraphQL (/api/graphql) para relatórios: consultas que cruzam livremente a escala, as trocas,
+//! a presença e os utilizadores, sobre os serviços existentes. Só leitura (sem mutações).
+//! Autenticação por `mw_api` (sessão ou token), como a /api/v1, e as mesmas regras de acesso:
+//! os campos e consultas sensíveis têm guardas por permissão (`Permissao`) ou só servem o próprio.
+//! POST executa uma consulta (`{"query", "variables", "operationName"}`); GET devolve o esquema (SDL).
+
+use crate::{
+    error::AppError,
+    models::{
+        escala::{AlocacaoDetalhe, DiaEscala, Indisponibilidade, TrocaDetalhe},
+        presence::PresencePerson,
+        user::UserApi,
+    },
+    services::{escala_service, indisponibilidade_service, permission_service, presence_service, user_service},
+    state::AppState,
+    tempo,
+    web::{
+        api_v1_handlers::{ApiJson, PeriodoQuery},
+        mw_auth::{OrganizacaoId, UserId},
+    },
+};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, ErrorExtensions, Guard, Object, Schema};
+use axum::{
+    extract::{Extension, State},
+    Json,
+};
+use std::sync::OnceLock;
+
+/// Profundidade máxima de uma consulta (utilizador → alocações → utilizador → ...).
+const MAX_PROFUNDIDADE: usize = 8;
+/// Complexidade máxima (número de campos pedidos, contando os aninhados).
+const MAX_COMPLEXIDADE: usize = 500;
+
+pub type Esquema = Schema<Consulta, EmptyMutation, EmptySubscription>;
+
+static ESQUEMA: OnceLock<Esquema> = OnceLock::new();
+
+/// O esquema (construído uma vez).
+fn esquema() -> &'static Esquema {
+    ESQUEMA.get_or_init(|| {
+        Schema::build(Consulta, EmptyMutation, EmptySubscription)
+            .limit_depth(MAX_PROFUNDIDADE)
+            .limit_complexity(MAX_COMPLEXIDADE)
+            .finish()
+    })
+}
+
+// --- Contexto, erros e guardas ---
+
+/// Dados de cada pedido: o estado da aplicação, quem consulta e a sua organização
+/// (as consultas só veem os dados dessa organização).
+struct Contexto {
+    state: AppState,
+    user_id: String,
+    organizacao_id: i64,
+}
+
+fn contexto<'a>(ctx: &Context<'a>) -> &'a Contexto {
+    ctx.data_unchecked::<Contexto>()
+}
+
+/// Erro GraphQL com a mensagem para o utilizador e o `code` do envelope da API nas extensões
+/// (nunca o detalhe interno, ex: erros da base de dados).
+fn erro(e: impl Into<AppError>) -> async_graphql::Error {
+    let e = e.into();
+    let code = e.code();
+    async_graphql::Error::new(e.user_message()).extend_with(|_, ext| ext.set("code", code))
+}
+
+type Resultado<T> = async_graphql::Result<T>;
+
+impl Contexto {
+    async fn tem_permissao(&self, permissao: &str) -> Resultado<bool> {
+        permission_service::user_has_permission(&self.state.db_pool, &self.state.permissions, &self.user_id, permissao)
+            .await
+            .map_err(erro)
+    }
+
+    async fn exigir_permissao(&self, permissao: &str) -> Resultado<()> {
+        if self.tem_permissao(permissao).await? {
+            Ok(())
+        } else {
+            tracing::warn!("GraphQL: {} sem a permissão '{}'.", self.user_id, permissao);
+            Err(erro(AppError::Unauthorized))
+        }
+    }
+
+    /// Dados do próprio, ou de qualquer utilizador com a permissão.
+    async fn exigir_proprio_ou(&self, alvo: &str, permissao: &str) -> Resultado<()> {
+        if alvo == self.user_id {
+            Ok(())
+        } else {
+            self.exigir_permissao(permissao).await
+        }
+    }
+
+    /// Quem gere a escala também vê os dias em rascunho.
+    async fn gestor(&self) -> Resultado<bool> {
+        self.tem_permissao(permission_service::PERM_ESCALA_GERIR).await
+    }
+
+    async fn utilizador(&self, id: &str) -> Resultado<Option<Utilizador>> {
+        let user = user_service::find_user_by_id(&self.state.db_pool, id).await.map_err(erro)?;
+        Ok(user.filter(|u| u.organizacao_id == self.organizacao_id).map(|u| Utilizador(u.into())))
+    }
+}
+
+/// Guarda de campo: exige uma permissão a quem consulta.
+struct Permissao(&'static str);
+
+impl Guard for Permissao {
+    async fn check(&self, ctx: &Context<'_>) -> Resultado<()> {
+        contexto(ctx).exigir_permissao(self.0).await
+    }
+}
+
+// --- Consultas ---
+
+pub struct Consulta;
+
+#[Object]
+impl Consulta {
+    /// O utilizador autenticado.
+    async fn me(&self, ctx: &Context<'_>) -> Resultado<Option<Utilizador>> {
+        let c = contexto(ctx);
+        c.utilizador(&c.user_id).await
+    }
+
+    /// Um utilizador (o próprio, ou qualquer um com users.pesquisar).
+    async fn utilizador(&self, ctx: &Context<'_>, id: String) -> Resultado<Option<Utilizador>> {
+        let c = contexto(ctx);
+        c.exigir_proprio_ou(&id, permission_service::PERM_USERS_PESQUISAR).await?;
+        c.utilizador(&id).await
+    }
+
+    /// Utilizadores, filtrados por ID ou nome (users.pesquisar).
+    #[graphql(guard = "Permissao(permission_service::PERM_USERS_PESQUISAR)")]
+    async fn utilizadores(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] q: String,
+        #[graphql(default)] arquivados: bool,
+    ) -> Resultado<Vec<Utilizador>> {
+        let termo = q.trim().to_lowercase();
+        let c = contexto(ctx);
+        let users = user_service::find_all_users(&c.state.db_pool, c.organizacao_id).await.map_err(erro)?;
+        Ok(users
+            .into_iter()
+            .filter(|u| arquivados || u.ativo)
+            .filter(|u| termo.is_empty() || u.id.to_lowercase().contains(&termo) || u.name.to_lowercase().contains(&termo))
+            .map(|u| Utilizador(u.into()))
+            .collect())
+    }
+
+    /// Dias de escala do período (por omissão, os próximos 30 dias; rascunhos só para escala.gerir).
+    async fn escala(&self, ctx: &Context<'_>, inicio: Option<String>, fim: Option<String>) -> Resultado<Vec<Dia>> {
+        let c = contexto(ctx);
+        let (inicio, fim) = PeriodoQuery::validado(inicio, fim).map_err(erro)?;
+        let dias = escala_service::dias_periodo(&c.state.db_pool, c.organizacao_id, &inicio, &fim, c.gestor().await?)
+            .await
+            .map_err(erro)?;
+        Ok(dias.into_iter().map(Dia).collect())
+    }
+
+    /// Alocações do período, opcionalmente de um utilizador.
+    async fn alocacoes(
+        &self,
+        ctx: &Context<'_>,
+        inicio: Option<String>,
+        fim: Option<String>,
+        user_id: Option<String>,
+    ) -> Resultado<Vec<Alocacao>> {
+        let c = contexto(ctx);
+        let (inicio, fim) = PeriodoQuery::validado(inicio, fim).map_err(erro)?;
+        let alocacoes = escala_service::alocacoes_periodo(
+            &c.state.db_pool,
+            c.organizacao_id,
+            &inicio,
+            &fim,
+            user_id.as_deref(),
+            c.gestor().await?,
+        )
+        .await
+        .map_err(erro)?;
+        Ok(alocacoes.into_iter().map(Alocacao).collect())
+    }
+
+    /// Pedidos de troca (sem escala.gerir, só os do próprio).
+    async fn trocas(&self, ctx: &Context<'_>, status: Option<String>, user_id: Option<String>) -> Resultado<Vec<Troca>> {
+        let c = contexto(ctx);
+        let filtro_user = if c.gestor().await? { user_id } else { Some(c.user_id.clone()) };
+        let trocas = escala_service::listar_trocas(&c.state.db_pool, c.organizacao_id, filtro_user.as_deref(), status.as_deref())
+            .await
+            .map_err(erro)?;
+        Ok(trocas.into_iter().map(Troca).collect())
+    }
+
+    /// Estado de presença de uma turma (ano) ou de um grupo (presenca).
+    #[graphql(guard = "Permissao(permission_service::PERM_PRESENCA)")]
+    async fn presenca(&self, ctx: &Context<'_>, turma: Option<i64>, grupo: Option<i64>) -> Resultado<Vec<Presenca>> {
+        let c = contexto(ctx);
+        let db = &c.state.db_pool;
+        let pessoas = match (grupo, turma) {
+            (Some(grupo_id), _) => presence_service::get_presence_list_for_grupo(db, c.organizacao_id, grupo_id).await,
+            (None, Some(turma)) => presence_service::get_presence_list_for_turma(db, c.organizacao_id, turma).await,
+            (None, None) => Err(AppError::validation("turma", "Indique a turma ou o grupo.")),
+        }
+        .map_err(erro)?;
+        Ok(pessoas.into_iter().map(Presenca).collect())
+    }
+
+    /// Indisponibilidades que terminam a partir de `desde` (por omissão, hoje; sem escala.gerir, só as do próprio).
+    async fn indisponibilidades(
+        &self,
+        ctx: &Context<'_>,
+        user_id: Option<String>,
+        desde: Option<String>,
+    ) -> Resultado<Vec<IndisponibilidadeGql>> {
+        let c = contexto(ctx);
+        let filtro_user = if c.gestor().await? { user_id } else { Some(c.user_id.clone()) };
+        indisponibilidades(c, filtro_user.as_deref(), desde).await
+    }
+}
+
+async fn indisponibilidades(
+    c: &Contexto,
+    user_id: Option<&str>,
+    desde: Option<String>,
+) -> Resultado<Vec<IndisponibilidadeGql>> {
+    let desde = desde.unwrap_or_else(|| tempo::hoje().to_string());
+    let mut v = crate::validation::Validador::default();
+    v.data("desde", &desde);
+    v.resultado().map_err(erro)?;
+    let lista = indisponibilidade_service::listar(&c.state.db_pool, c.organizacao_id, user_id, desde.trim()).await.map_err(erro)?;
+    Ok(lista.into_iter().map(IndisponibilidadeGql).collect())
+}
+
+// --- Tipos ---
+
+/// Utilizador (sem credenciais). Os contactos só são visíveis para o próprio ou com users.pesquisar.
+pub struct Utilizador(UserApi);
+
+#[Object]
+impl Utilizador {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+    async fn nome(&self) -> &str {
+        &self.0.name
+    }
+    async fn turma(&self) -> &str {
+        &self.0.turma
+    }
+    async fn ano(&self) -> i64 {
+        self.0.ano
+    }
+    async fn curso(&self) -> &str {
+        &self.0.curso
+    }
+    async fn genero(&self) -> &str {
+        &self.0.genero
+    }
+    async fn ativo(&self) -> bool {
+        self.0.ativo
+    }
+    async fn email(&self, ctx: &Context<'_>) -> Resultado<Option<&str>> {
+        contexto(ctx).exigir_proprio_ou(&self.0.id, permission_service::PERM_USERS_PESQUISAR).await?;
+        Ok(self.0.email.as_deref())
+    }
+    async fn telefone(&self, ctx: &Context<'_>) -> Resultado<Option<&str>> {
+        contexto(ctx).exigir_proprio_ou(&self.0.id, permission_service::PERM_USERS_PESQUISAR).await?;
+        Ok(self.0.telefone.as_deref())
+    }
+
+    /// Serviços do utilizador no período (por omissão, os próximos 30 dias).
+    async fn alocacoes(&self, ctx: &Context<'_>, inicio: Option<String>, fim: Option<String>) -> Resultado<Vec<Alocacao>> {
+        let c = contexto(ctx);
+        let (inicio, fim) = PeriodoQuery::validado(inicio, fim).map_err(erro)?;
+        let alocacoes = escala_service::alocacoes_periodo(
+            &c.state.db_pool,
+            c.organizacao_id,
+            &inicio,
+            &fim,
+            Some(&self.0.id),
+            c.gestor().await?,
+        )
+        .await
+        .map_err(erro)?;
+        Ok(alocacoes.into_iter().map(Alocacao).collect())
+    }
+
+    /// Trocas em que é solicitante ou substituto (o próprio, ou com escala.gerir).
+    async fn trocas(&self, ctx: &Context<'_>, status: Option<String>) -> Resultado<Vec<Troca>> {
+        let c = contexto(ctx);
+        c.exigir_proprio_ou(&self.0.id, permission_service::PERM_ESCALA_GERIR).await?;
+        let trocas = escala_service::listar_trocas(&c.state.db_pool, c.organizacao_id, Some(&self.0.id), status.as_deref())
+            .await
+            .map_err(erro)?;
+        Ok(trocas.into_iter().map(Troca).collect())
+    }
+
+    /// Indisponibilidades (o próprio, ou com escala.gerir).
+    async fn indisponibilidades(&self, ctx: &Context<'_>, desde: Option<String>) -> Resultado<Vec<IndisponibilidadeGql>> {
+        let c = contexto(ctx);
+        c.exigir_proprio_ou(&self.0.id, permission_service::PERM_ESCALA_GERIR).await?;
+        indisponibilidades(c, Some(&self.0.id), desde).await
+    }
+
+    /// Estado de presença (presenca).
+    #[graphql(guard = "Permissao(permission_service::PERM_PRESENCA)")]
+    async fn presenca(&self, ctx: &Context<'_>) -> Resultado<Option<Presenca>> {
+        let db = &contexto(ctx).state.db_pool;
+        let Some(user) = user_service::find_user_by_id(db, &self.0.id).await.map_err(erro)? else {
+            return Ok(None);
+        };
+        let pessoa = presence_service::presenca_de(db, user).await.map_err(erro)?;
+        Ok(Some(Presenca(pessoa)))
+    }
+}
+
+/// Um dia da escala e as suas alocações.
+pub struct Dia(DiaEscala);
+
+#[Object]
+impl Dia {
+    /// YYYY-MM-DD
+    async fn data(&self) -> &str {
+        &self.0.data
+    }
+    /// RN ou RD
+    async fn tipo_rotina(&self) -> &str {
+        &self.0.tipo_rotina
+    }
+    /// Rascunho ou Publicada
+    async fn status(&self) -> &str {
+        &self.0.status
+    }
+    async fn alocacoes(&self) -> Vec<Alocacao> {
+        self.0.alocacoes.iter().cloned().map(Alocacao).collect()
+    }
+}
+
+/// Um serviço (militar num posto, num dia).
+pub struct Alocacao(AlocacaoDetalhe);
+
+#[Object]
+impl Alocacao {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+    async fn data(&self) -> &str {
+        &self.0.data
+    }
+    async fn posto(&self) -> &str {
+        &self.0.posto
+    }
+    async fn is_punicao(&self) -> bool {
+        self.0.is_punicao
+    }
+    async fn tag(&self) -> Option<&str> {
+        self.0.tag.as_deref()
+    }
+    async fn user_id(&self) -> &str {
+        &self.0.user_id
+    }
+    async fn utilizador(&self, ctx: &Context<'_>) -> Resultado<Option<Utilizador>> {
+        contexto(ctx).utilizador(&self.0.user_id).await
+    }
+}
+
+/// Pedido de troca de um serviço.
+pub struct Troca(TrocaDetalhe);
+
+#[Object]
+impl Troca {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+    /// Pendente, AguardandoEscalante, Aprovada ou Recusada
+    async fn status(&self) -> &str {
+        &self.0.status
+    }
+    /// Cobertura ou Permuta
+    async fn tipo(&self) -> &str {
+        &self.0.tipo
+    }
+    async fn motivo(&self) -> Option<&str> {
+        self.0.motivo.as_deref()
+    }
+    /// Dia do serviço trocado
+    async fn data(&self) -> &str {
+        &self.0.data
+    }
+    async fn posto(&self) -> &str {
+        &self.0.posto
+    }
+    async fn criado_em(&self) -> Option<&str> {
+        self.0.criado_em.as_deref()
+    }
+    async fn data_resposta(&self) -> Option<&str> {
+        self.0.data_resposta.as_deref()
+    }
+    async fn solicitante(&self, ctx: &Context<'_>) -> Resultado<Option<Utilizador>> {
+        contexto(ctx).utilizador(&self.0.solicitante_id).await
+    }
+    async fn substituto(&self, ctx: &Context<'_>) -> Resultado<Option<Utilizador>> {
+        contexto(ctx).utilizador(&self.0.substituto_id).await
+    }
+}
+
+/// Estado de presença de um utilizador.
+pub struct Presenca(PresencePerson);
+
+#[Object]
+impl Presenca {
+    async fn user_id(&self) -> &str {
+        &self.0.id
+    }
+    async fn esta_fora(&self) -> bool {
+        self.0.esta_fora
+    }
+    /// Quarto atribuído (alojamento)
+    async fn quarto(&self) -> Option<&str> {
+        self.0.quarto.as_deref()
+    }
+    async fn corredor(&self) -> Option<&str> {
+        self.0.corredor.as_deref()
+    }
+    /// Restrições da baixa médica em vigor hoje (null = sem baixa)
+    async fn baixa(&self) -> Option<&str> {
+        self.0.baixa.as_deref()
+    }
+    /// Comitiva em curso (null = nenhuma); conta como fora
+    async fn comitiva(&self) -> Option<&str> {
+        self.0.comitiva.as_deref()
+    }
+    /// Hora da chegada à enfermaria, se lá estiver numa consulta (null = não)
+    async fn enfermaria(&self) -> Option<&str> {
+        self.0.enfermaria.as_deref()
+    }
+    /// RFC 3339
+    async fn ultima_saida(&self) -> Option<String> {
+        self.0.ultima_saida.map(|d| d.to_rfc3339())
+    }
+    /// RFC 3339
+    async fn ultimo_retorno(&self) -> Option<String> {
+        self.0.ultimo_retorno.map(|d| d.to_rfc3339())
+    }
+    async fn utilizador(&self, ctx: &Context<'_>) -> Resultado<Option<Utilizador>> {
+        contexto(ctx).utilizador(&self.0.id).await
+    }
+}
+
+/// Baixa ou dispensa de um utilizador.
+pub struct IndisponibilidadeGql(Indisponibilidade);
+
+#[Object(name = "Indisponibilidade")]
+impl IndisponibilidadeGql {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+    async fn user_id(&self) -> &str {
+        &self.0.user_id
+    }
+    /// YYYY-MM-DD
+    async fn data_inicio(&self) -> &str {
+        &self.0.data_inicio
+    }
+    /// YYYY-MM-DD
+    async fn data_fim(&self) -> &str {
+        &self.0.data_fim
+    }
+    async fn motivo(&self) -> Option<&str> {
+        self.0.motivo.as_deref()
+    }
+}
+
+// --- Handlers ---
+
+// POST /api/graphql
+pub async fn executar(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    Extension(organizacao): Extension<OrganizacaoId>,
+    ApiJson(pedido): ApiJson<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    let pedido = pedido.data(Contexto { state, user_id: user_id.0, organizacao_id: organizacao.0 });
+    Json(esquema().execute(pedido).await)
+}
+
+// GET /api/graphql (o esquema em SDL, para gerar clientes)
+pub async fn mostrar_esquema() -> String {
+    esquema().sdl()
+}
@@ -0,0 +1,293 @@
+// src/web/horario_handlers.rs
+//! Horário das aulas (/horario): todos consultam o plano semanal de uma turma (por omissão, a sua);
+//! criar, alterar e apagar aulas e marcar os postos diurnos da escala exige a permissão "horario".
+
+use crate::{
+    error::{AppError, AppResult, FieldError},
+    models::horario::DadosAula,
+    services::{audit_service, horario_service, permission_service, user_service},
+    state::AppState,
+    templates::HorarioPage,
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo da disciplina, da sala e do instrutor.
+const MAX_TEXTO: usize = 120;
+
+#[derive(Deserialize, Debug)]
+pub struct HorarioQuery {
+    turma: Option<String>, // Omissão: a turma do utilizador
+    editar: Option<i64>,   // Preenche o formulário com esta aula
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AulaForm {
+    #[serde(default)]
+    turma: String,
+    #[serde(default)]
+    dia_semana: String,
+    #[serde(default)]
+    hora_inicio: String,
+    #[serde(default)]
+    hora_fim: String,
+    #[serde(default)]
+    disciplina: String,
+    #[serde(default)]
+    sala: String,
+    #[serde(default)]
+    instrutor: String,
+    obrigatoria: Option<String>, // Checkbox: presente = marcada
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PostosDiurnosForm {
+    #[serde(default)]
+    diurnos: Vec<i64>, // Checkboxes dos postos (nenhum = nenhum posto diurno)
+    #[serde(default)]
+    turma: String,     // Turma mostrada (para voltar à mesma página)
+}
+
+impl AulaForm {
+    /// Valida o formulário e devolve os dados da aula.
+    fn validar(&self) -> AppResult<DadosAula> {
+        let mut v = Validador::default();
+        v.obrigatorio("turma", &self.turma, MAX_TEXTO);
+        v.obrigatorio("disciplina", &self.disciplina, MAX_TEXTO);
+        for (campo, valor) in [("sala", &self.sala), ("instrutor", &self.instrutor)] {
+            if valor.trim().chars().count() > MAX_TEXTO {
+                v.erro(campo, format!("Máximo de {} caracteres.", MAX_TEXTO));
+            }
+        }
+        let dia = self.dia_semana.trim().parse::<i64>().ok();
+        if dia.is_none() {
+            v.erro("dia_semana", "Dia da semana inválido.");
+        }
+        let inicio = v.hora("hora_inicio", &self.hora_inicio);
+        let fim = v.hora("hora_fim", &self.hora_fim);
+        v.resultado()?;
+        let (Some(dia), Some(inicio), Some(fim)) = (dia, inicio, fim) else {
+            return Err(AppError::validation("hora_inicio", "Hora inválida."));
+        };
+        Ok(DadosAula {
+            turma: self.turma.trim().to_string(),
+            dia_semana: dia,
+            hora_inicio: inicio.format("%H:%M").to_string(),
+            hora_fim: fim.format("%H:%M").to_string(),
+            disciplina: self.disciplina.clone(),
+            sala: self.sala.clone(),
+            instrutor: self.instrutor.clone(),
+            obrigatoria: self.obrigatoria.is_some(),
+        })
+    }
+
+    /// Formulário reapresentado com os erros e os valores enviados.
+    fn com_erros(self, erros: Vec<FieldError>) -> FormState {
+        let mut form = FormState::com_erros(erros)
+            .com_valor("turma", self.turma)
+            .com_valor("dia_semana", self.dia_semana)
+            .com_valor("hora_inicio", self.hora_inicio)
+            .com_valor("hora_fim", self.hora_fim)
+            .com_valor("disciplina", self.disciplina)
+            .com_valor("sala", self.sala)
+            .com_valor("instrutor", self.instrutor);
+        if self.obrigatoria.is_some() {
+            form = form.com_valor("obrigatoria", "1");
+        }
+        form
+    }
+}
+
+/// Falha (403) se o utilizador não pode gerir o horário.
+async fn exigir_gestao(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if atual.tem_permissao(state, permission_service::PERM_HORARIO).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Horário: {} sem permissão '{}'.", atual.id, permission_service::PERM_HORARIO);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Página do horário de uma turma.
+fn url_turma(turma: &str) -> String {
+    let query: String = form_urlencoded::Serializer::new(String::new()).append_pair("turma", turma).finish();
+    format!("/horario?{}", query)
+}
+
+/// Renderiza o horário de `turma` (None = a do utilizador) e, a quem gere o horário, o formulário
+/// (nova aula ou alteração de `editar`) e os postos diurnos.
+async fn pagina_horario(
+    state: &AppState,
+    atual: &CurrentUser,
+    turma: Option<String>,
+    editar: Option<i64>,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let turmas = horario_service::turmas(&state.db_leitura, organizacao_id).await?;
+    let minha_turma = user_service::find_user_by_id(&state.db_leitura, &atual.id).await?.map(|u| u.turma);
+    let turma = turma
+        .filter(|t| !t.is_empty())
+        .or_else(|| minha_turma.clone())
+        .or_else(|| turmas.first().cloned())
+        .unwrap_or_default();
+    let pode_gerir = atual.tem_permissao(state, permission_service::PERM_HORARIO).await?;
+    let template = HorarioPage {
+        aulas: horario_service::listar(&state.db_leitura, organizacao_id, &turma, None).await?,
+        postos: if pode_gerir { horario_service::postos(&state.db_leitura, organizacao_id).await? } else { Vec::new() },
+        turmas,
+        e_a_minha: minha_turma.as_deref() == Some(turma.as_str()),
+        turma,
+        hoje: horario_service::dia_semana(tempo::hoje()),
+        pode_gerir,
+        editar,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template HorarioPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /horario?turma=&editar= - Horário semanal de uma turma (e gestão, com a permissão)
+pub async fn show_horario(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<HorarioQuery>,
+) -> AppResult<Response> {
+    let mut turma = params.turma;
+    // Alteração: o formulário vem preenchido com a aula; nova aula: obrigatória, na turma mostrada
+    let form = match params.editar {
+        Some(id) => {
+            exigir_gestao(&state, &atual).await?;
+            let aula = horario_service::obter(&state.db_leitura, atual.organizacao_id, id).await?;
+            turma = Some(aula.turma.clone());
+            let form = FormState::default()
+                .com_valor("turma", aula.turma)
+                .com_valor("dia_semana", aula.dia_semana.to_string())
+                .com_valor("hora_inicio", aula.hora_inicio)
+                .com_valor("hora_fim", aula.hora_fim)
+                .com_valor("disciplina", aula.disciplina)
+                .com_valor("sala", aula.sala)
+                .com_valor("instrutor", aula.instrutor);
+            if aula.obrigatoria { form.com_valor("obrigatoria", "1") } else { form }
+        }
+        None => FormState::default().com_valor("obrigatoria", "1"),
+    };
+    pagina_horario(&state, &atual, turma, params.editar, StatusCode::OK, form, flash).await
+}
+
+/// Handler para POST /horario - Cria uma aula
+pub async fn handle_criar_aula(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<AulaForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let resultado = match form.validar() {
+        Ok(dados) => horario_service::criar(&state.db_pool, atual.organizacao_id, &dados, &atual.id).await.map(|id| (id, dados)),
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((id, dados)) => {
+            let detalhes = format!(
+                "{} da turma {} ({} {}–{})",
+                dados.disciplina.trim(), dados.turma, horario_service::descrever_dia(dados.dia_semana), dados.hora_inicio, dados.hora_fim
+            );
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_AULA_CRIADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Aula de {} criada no horário da turma {}.", dados.disciplina.trim(), dados.turma);
+            Ok(flash::redirect_success(&session, &url_turma(&dados.turma), mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let turma = Some(form.turma.trim().to_string());
+            let form_state = form.com_erros(erros);
+            pagina_horario(&state, &atual, turma, None, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /horario/{id} - Altera uma aula
+pub async fn handle_atualizar_aula(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<AulaForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let resultado = match form.validar() {
+        Ok(dados) => horario_service::atualizar(&state.db_pool, atual.organizacao_id, id, &dados).await.map(|anterior| (anterior, dados)),
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((anterior, dados)) => {
+            let detalhes = format!(
+                "{} da turma {} ({} {}) -> {} da turma {} ({} {})",
+                anterior.disciplina, anterior.turma, horario_service::descrever_dia(anterior.dia_semana), anterior.horario(),
+                dados.disciplina.trim(), dados.turma, horario_service::descrever_dia(dados.dia_semana), dados.hora_inicio
+            );
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_AULA_ALTERADA, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Aula de {} atualizada.", dados.disciplina.trim());
+            Ok(flash::redirect_success(&session, &url_turma(&dados.turma), mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let turma = Some(form.turma.trim().to_string());
+            let form_state = form.com_erros(erros);
+            pagina_horario(&state, &atual, turma, Some(id), StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /horario/{id}/remover - Apaga uma aula
+pub async fn handle_remover_aula(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let aula = horario_service::remover(&state.db_pool, atual.organizacao_id, id).await?;
+    let detalhes = format!("{} da turma {} ({} {})", aula.disciplina, aula.turma, horario_service::descrever_dia(aula.dia_semana), aula.horario());
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_AULA_APAGADA, Some(&id.to_string()), Some(&detalhes)).await;
+    let mensagem = format!("Aula de {} apagada do horário da turma {}.", aula.disciplina, aula.turma);
+    Ok(flash::redirect_success(&session, &url_turma(&aula.turma), mensagem).await.into_response())
+}
+
+/// Handler para POST /horario/postos - Marca os postos diurnos da escala
+pub async fn handle_postos_diurnos(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<PostosDiurnosForm>,
+) -> AppResult<Response> {
+    exigir_gestao(&state, &atual).await?;
+    let nomes = horario_service::definir_diurnos(&state.db_pool, atual.organizacao_id, &form.diurnos).await?;
+    let detalhes = if nomes.is_empty() { "nenhum".to_string() } else { nomes.join(", ") };
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_POSTOS_DIURNOS, None, Some(&detalhes)).await;
+    let mensagem = match nomes.len() {
+        0 => "Nenhum posto diurno: a escala ignora o horário das aulas.".to_string(),
+        n => format!("{} posto(s) diurno(s): {}.", n, nomes.join(", ")),
+    };
+    Ok(flash::redirect_success(&session, &url_turma(&form.turma), mensagem).await.into_response())
+}
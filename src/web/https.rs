@@ -0,0 +1,42 @@
+// src/web/https.rs
+//! Listener HTTP auxiliar quando o servidor serve HTTPS diretamente (ver `config::TlsConfig`):
+//! responde a tudo com um redirecionamento permanente para o mesmo endereço em HTTPS.
+
+use axum::{
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect},
+    Router,
+};
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+/// URL HTTPS equivalente ao pedido (o host vem do cabeçalho `Host`, sem a porta HTTP).
+fn url_https(headers: &HeaderMap, uri: &Uri, porta_https: u16) -> Option<String> {
+    let host = headers.get(header::HOST).and_then(|v| v.to_str().ok())?;
+    // "exemplo.pt:80" -> "exemplo.pt" (sem mexer em IPv6: "[::1]:80" -> "[::1]")
+    let host = match host.rsplit_once(':') {
+        Some((nome, porta)) if !porta.contains(']') => nome,
+        _ => host,
+    };
+    let porta = if porta_https == 443 { String::new() } else { format!(":{}", porta_https) };
+    let caminho = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    Some(format!("https://{}{}{}", host, porta, caminho))
+}
+
+/// Inicia o listener em `endereco` que redireciona para HTTPS na `porta_https`.
+pub async fn iniciar_redirecionamento(endereco: SocketAddr, porta_https: u16) -> std::io::Result<()> {
+    let listener = TcpListener::bind(endereco).await?;
+    let app = Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        match url_https(&headers, &uri, porta_https) {
+            Some(url) => Redirect::permanent(&url).into_response(),
+            // Sem Host não há para onde redirecionar (clientes HTTP/1.0 muito antigos)
+            None => (StatusCode::BAD_REQUEST, "Use HTTPS.").into_response(),
+        }
+    });
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            tracing::error!("Erro no listener de redirecionamento para HTTPS: {}", e);
+        }
+    });
+    Ok(())
+}
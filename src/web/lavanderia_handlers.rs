@@ -0,0 +1,197 @@
+// src/web/lavanderia_handlers.rs
+//! Lavanderia (/lavanderia, permissão "lavanderia"): receber o saco de um utilizador (com o número da
+//! etiqueta), dar o saco como pronto (o utilizador é avisado para o vir retirar), registar a retirada, o
+//! relatório dos sacos prontos há N dias sem serem retirados e o histórico.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::lavanderia_service,
+    state::AppState,
+    templates::{LavanderiaHistoricoPage, LavanderiaPage, LavanderiaRelatorioPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do número da etiqueta.
+const MAX_ETIQUETA: usize = 20;
+/// Tamanho máximo das observações de uma entrega.
+const MAX_OBSERVACOES: usize = 200;
+
+#[derive(Deserialize, Debug)]
+pub struct RelatorioQuery {
+    #[serde(default)]
+    dias: String, // Prontos há pelo menos estes dias (vazio = `DIAS_RELATORIO`)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HistoricoQuery {
+    #[serde(default)]
+    user: String, // Só os sacos deste utilizador
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SacoForm {
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    etiqueta: String,
+    #[serde(default)]
+    observacoes: String,
+}
+
+/// Renderiza o formulário de entrega e os sacos por retirar.
+async fn pagina_lavanderia(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = LavanderiaPage {
+        abertos: lavanderia_service::abertos(&state.db_leitura, organizacao_id).await?,
+        form,
+        max_observacoes: MAX_OBSERVACOES,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template LavanderiaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /lavanderia - Entrega dos sacos e sacos por retirar
+pub async fn show_lavanderia(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_lavanderia(&state, atual.organizacao_id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /lavanderia - Recebe o saco de um utilizador
+pub async fn handle_receber_saco(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<SacoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("user", &form.user, 10);
+    v.obrigatorio("etiqueta", &form.etiqueta, MAX_ETIQUETA);
+    if form.observacoes.trim().chars().count() > MAX_OBSERVACOES {
+        v.erro("observacoes", format!("Máximo de {} caracteres.", MAX_OBSERVACOES));
+    }
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            lavanderia_service::receber(&state.db_pool, atual.organizacao_id, form.user.trim(), &form.etiqueta, &form.observacoes, &atual.id)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(_) => {
+            let mensagem = format!("Saco {} de {} recebido.", form.etiqueta.trim(), form.user.trim());
+            Ok(flash::redirect_success(&session, "/lavanderia", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("user", form.user)
+                .com_valor("etiqueta", form.etiqueta)
+                .com_valor("observacoes", form.observacoes);
+            pagina_lavanderia(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /lavanderia/{id}/pronto - O saco está lavado (avisa o utilizador)
+pub async fn handle_saco_pronto(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    match lavanderia_service::marcar_pronto(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(saco) => {
+            let mensagem = format!("Saco {} pronto; {} foi avisado para o retirar.", saco.etiqueta, saco.user_id);
+            Ok(flash::redirect_success(&session, "/lavanderia", mensagem).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/lavanderia", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /lavanderia/{id}/retirar - O utilizador levou o saco
+pub async fn handle_retirar_saco(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    match lavanderia_service::retirar(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(saco) => {
+            let mensagem = format!("Saco {} retirado por {}.", saco.etiqueta, saco.user_id);
+            Ok(flash::redirect_success(&session, "/lavanderia", mensagem).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/lavanderia", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /lavanderia/relatorio?dias= - Sacos prontos há N dias ou mais sem serem retirados
+pub async fn show_relatorio_lavanderia(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<RelatorioQuery>,
+) -> AppResult<Response> {
+    let dias = params
+        .dias
+        .trim()
+        .parse::<i64>()
+        .unwrap_or(lavanderia_service::DIAS_RELATORIO)
+        .clamp(0, lavanderia_service::MAX_DIAS_RELATORIO);
+    let template = LavanderiaRelatorioPage {
+        sacos: lavanderia_service::por_retirar(&state.db_leitura, atual.organizacao_id, dias).await?,
+        dias,
+        max_dias: lavanderia_service::MAX_DIAS_RELATORIO,
+        agora: tempo::agora().format(tempo::FORMATO_DATA_HORA).to_string(),
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template LavanderiaRelatorioPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /lavanderia/historico?user= - Últimos sacos
+pub async fn show_historico_lavanderia(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<HistoricoQuery>,
+) -> AppResult<Response> {
+    let user = params.user.trim();
+    let template = LavanderiaHistoricoPage {
+        sacos: lavanderia_service::historico(
+            &state.db_leitura,
+            atual.organizacao_id,
+            Some(user).filter(|u| !u.is_empty()),
+            lavanderia_service::HISTORICO_RECENTE,
+        )
+        .await?,
+        user: user.to_string(),
+        limite: lavanderia_service::HISTORICO_RECENTE,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template LavanderiaHistoricoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
@@ -0,0 +1,266 @@
+// src/web/livro_handlers.rs
+//! Livro de ocorrências (/livro, permissão "livro"): o livro de cada dia, ligado ao dia da escala e à
+//! passagem de serviço, e a pesquisa por datas e texto. Registar, corrigir e fechar o dia exige a
+//! permissão "livro.escrever" (o chefe de dia, durante o serviço).
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, escala_service, livro_service, permission_service},
+    state::AppState,
+    templates::{LivroPage, LivroPesquisaPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::{Duration, NaiveDate};
+use serde::Deserialize;
+use tower_sessions::Session;
+
+#[derive(Deserialize, Debug)]
+pub struct LivroQuery {
+    data: Option<String>, // Por omissão, hoje
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PesquisaQuery {
+    de: Option<String>,  // Por omissão, há 30 dias
+    ate: Option<String>, // Por omissão, hoje
+    #[serde(default)]
+    q: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OcorrenciaForm {
+    hora: String,
+    #[serde(default)]
+    texto: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CorrecaoForm {
+    #[serde(default)]
+    texto: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FechoForm {
+    #[serde(default)]
+    passagem: String,
+    #[serde(default)]
+    recebido_por: String, // ID do chefe de dia que entra (opcional)
+}
+
+/// Dia do caminho (/livro/{data}/...), no formato YYYY-MM-DD.
+fn ler_dia(data: &str) -> AppResult<NaiveDate> {
+    tempo::ler_data(data).ok_or_else(|| AppError::validation("data", format!("Data inválida: '{}' (use AAAA-MM-DD).", data)))
+}
+
+/// Endereço do livro de um dia (destino dos redirecionamentos).
+fn url_dia(data: NaiveDate) -> String {
+    format!("/livro?data={}", data)
+}
+
+/// Falha (403) se o utilizador não pode escrever no livro.
+async fn exigir_escrita(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if atual.tem_permissao(state, permission_service::PERM_LIVRO_ESCREVER).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Livro: {} sem permissão '{}'.", atual.id, permission_service::PERM_LIVRO_ESCREVER);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Renderiza o livro do dia `data`: ocorrências, passagem de serviço (a do dia e a recebida do dia
+/// anterior), o dia da escala e, a quem pode escrever com o dia aberto, os formulários.
+async fn pagina_livro(
+    state: &AppState,
+    atual: &CurrentUser,
+    data: NaiveDate,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let hoje = tempo::hoje();
+    let ontem = hoje - Duration::days(1);
+    let fecho = livro_service::fecho_dia(&state.db_leitura, organizacao_id, data).await?;
+    let escreve = atual.tem_permissao(state, permission_service::PERM_LIVRO_ESCREVER).await?;
+
+    // No livro de hoje, lembra o de ontem se ficou aberto com ocorrências (o serviço passa a meia-noite)
+    let mut ontem_aberto = None;
+    if data == hoje && livro_service::fecho_dia(&state.db_leitura, organizacao_id, ontem).await?.is_none()
+        && !livro_service::ocorrencias_dia(&state.db_leitura, organizacao_id, ontem).await?.is_empty()
+    {
+        ontem_aberto = Some(ontem.to_string());
+    }
+
+    let dia = data.to_string();
+    let template = LivroPage {
+        anterior: (data - Duration::days(1)).to_string(),
+        seguinte: (data < hoje).then(|| (data + Duration::days(1)).to_string()),
+        ocorrencias: livro_service::ocorrencias_dia(&state.db_leitura, organizacao_id, data).await?,
+        fecho_anterior: livro_service::fecho_dia(&state.db_leitura, organizacao_id, data - Duration::days(1)).await?,
+        escala: escala_service::dias_periodo(&state.db_leitura, organizacao_id, &dia, &dia, true).await?.into_iter().next(),
+        pode_escrever: escreve && fecho.is_none() && livro_service::dia_do_servico(data),
+        fecho,
+        ontem_aberto,
+        user_id: atual.id.clone(),
+        hora_atual: tempo::agora().format("%H:%M").to_string(),
+        dias_recentes: livro_service::dias_recentes(&state.db_leitura, organizacao_id, livro_service::DIAS_RECENTES).await?,
+        form,
+        max_texto: livro_service::MAX_TEXTO,
+        max_passagem: livro_service::MAX_PASSAGEM,
+        data: dia,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template LivroPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /livro?data= - Livro de um dia (por omissão, hoje)
+pub async fn show_livro(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<LivroQuery>,
+) -> AppResult<Response> {
+    let data = params.data.as_deref().and_then(tempo::ler_data).unwrap_or_else(tempo::hoje);
+    pagina_livro(&state, &atual, data, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /livro/{data}/ocorrencias - Regista uma ocorrência no livro do dia
+pub async fn handle_registar_ocorrencia(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(data): Path<String>,
+    Form(form): Form<OcorrenciaForm>,
+) -> AppResult<Response> {
+    exigir_escrita(&state, &atual).await?;
+    let data = ler_dia(&data)?;
+    let mut v = Validador::default();
+    v.hora("hora", &form.hora);
+    v.obrigatorio("texto", &form.texto, livro_service::MAX_TEXTO);
+    let resultado = match v.resultado() {
+        Ok(()) => livro_service::registar(&state.db_pool, atual.organizacao_id, data, form.hora.trim(), &atual.id, &form.texto).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(_) => Ok(flash::redirect_success(&session, &url_dia(data), "Ocorrência registada.").await.into_response()),
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros).com_valor("hora", form.hora).com_valor("texto", form.texto);
+            pagina_livro(&state, &atual, data, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, &url_dia(data), e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /livro/ocorrencias/{id}/corrigir - Corrige o texto de uma ocorrência (só o autor, dia aberto)
+pub async fn handle_corrigir_ocorrencia(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<CorrecaoForm>,
+) -> AppResult<Response> {
+    exigir_escrita(&state, &atual).await?;
+    let mut v = Validador::default();
+    v.obrigatorio("texto", &form.texto, livro_service::MAX_TEXTO);
+    let resultado = match v.resultado() {
+        Ok(()) => livro_service::corrigir(&state.db_pool, atual.organizacao_id, id, &atual.id, &form.texto).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((data, anterior)) => {
+            let detalhes = format!("Livro de {}; texto anterior: {}", data, anterior);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_OCORRENCIA_CORRIGIDA, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, &url_dia(data), "Ocorrência corrigida.").await.into_response())
+        }
+        Err(e @ (AppError::Validation(_) | AppError::Conflict(_))) => {
+            Ok(flash::redirect_error(&session, "/livro", e.user_message()).await.into_response())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /livro/{data}/fechar - Fecha o livro do dia com a passagem de serviço
+pub async fn handle_fechar_livro(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(data): Path<String>,
+    Form(form): Form<FechoForm>,
+) -> AppResult<Response> {
+    exigir_escrita(&state, &atual).await?;
+    let data = ler_dia(&data)?;
+    let mut v = Validador::default();
+    v.obrigatorio("passagem", &form.passagem, livro_service::MAX_PASSAGEM);
+    let recebido_por = Some(form.recebido_por.trim()).filter(|r| !r.is_empty());
+    if recebido_por == Some(atual.id.as_str()) {
+        v.erro("recebido_por", "A passagem é para o chefe de dia que entra.");
+    }
+    let resultado = match v.resultado() {
+        Ok(()) => livro_service::fechar(&state.db_pool, atual.organizacao_id, data, &atual.id, recebido_por, &form.passagem).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(()) => {
+            let detalhes = match recebido_por {
+                Some(recebido_por) => format!("Livro de {}, passagem a {}", data, recebido_por),
+                None => format!("Livro de {}", data),
+            };
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_LIVRO_FECHADO, Some(&data.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, &url_dia(data), "Livro fechado; a passagem de serviço ficou registada.").await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("passagem", form.passagem)
+                .com_valor("recebido_por", form.recebido_por);
+            pagina_livro(&state, &atual, data, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, &url_dia(data), e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /livro/pesquisa?de=&ate=&q= - Pesquisa as ocorrências por datas e texto
+pub async fn show_pesquisa_livro(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<PesquisaQuery>,
+) -> AppResult<Response> {
+    let ate = params.ate.as_deref().and_then(tempo::ler_data).unwrap_or_else(tempo::hoje);
+    let de = params.de.as_deref().and_then(tempo::ler_data).unwrap_or(ate - Duration::days(livro_service::DIAS_RECENTES));
+    let (de, ate) = if de > ate { (ate, de) } else { (de, ate) };
+    let texto = params.q.trim();
+    let resultados =
+        livro_service::pesquisar(&state.db_leitura, atual.organizacao_id, de, ate, Some(texto).filter(|t| !t.is_empty())).await?;
+    let template = LivroPesquisaPage {
+        de: de.to_string(),
+        ate: ate.to_string(),
+        q: texto.to_string(),
+        limitado: resultados.len() as i64 >= livro_service::MAX_RESULTADOS,
+        resultados,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template LivroPesquisaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
@@ -0,0 +1,441 @@
+// src/web/loja_handlers.rs
+//! Loja (cantina): ponto de venda do operador (/loja, permissão "loja"), compras e conta corrente de
+//! cada utilizador (/user/compras e /user/conta e, para o operador, /loja/compras e /loja/conta, onde
+//! regista os carregamentos) e, na administração, os produtos, o relatório diário de vendas e os saldos
+//! negativos (/admin/loja).
+
+use crate::{
+    error::{AppError, AppResult},
+    models::loja::{PAGAMENTOS, PAGAMENTO_CONTA},
+    moeda,
+    services::{audit_service, loja_service, user_service},
+    state::AppState,
+    templates::{AdminLojaPage, AdminLojaRelatorioPage, AdminLojaSaldosPage, LojaComprasPage, LojaContaPage, LojaPage},
+    tempo,
+    validation::{validar, FormState, Validador, Validate},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::{Datelike, Months, NaiveDate};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tower_sessions::Session;
+
+#[derive(Deserialize, Debug)]
+pub struct ComprasQuery {
+    #[serde(default)]
+    user: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ContaQuery {
+    #[serde(default)]
+    user: String, // Só no /loja/conta
+    mes: Option<String>, // 'YYYY-MM'; por omissão, o mês atual
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CarregamentoForm {
+    user: String,
+    valor: String, // Ex: "20,00"
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RelatorioQuery {
+    data: Option<String>, // Por omissão, hoje
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProdutoForm {
+    #[serde(default)]
+    nome: String,
+    preco: String, // Ex: "3,50"
+    stock: i64,
+    ativo: Option<String>, // Checkbox (só na alteração): presente = marcada
+}
+
+impl Validate for ProdutoForm {
+    fn validate(&self, v: &mut Validador) {
+        v.moeda("preco", &self.preco);
+        v.intervalo("stock", self.stock, 0, 1_000_000);
+    }
+}
+
+// --- PONTO DE VENDA (operador) ---
+
+/// Handler para GET /loja - Produtos à venda e vendas de hoje
+pub async fn show_loja(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    let vendas_hoje = loja_service::vendas_dia(&state.db_leitura, atual.organizacao_id, tempo::hoje()).await?;
+    let template = LojaPage {
+        produtos: loja_service::listar_produtos(&state.db_leitura, atual.organizacao_id, true).await?,
+        pagamentos: PAGAMENTOS,
+        total_hoje: vendas_hoje.iter().filter(|v| !v.anulada).map(|v| v.total).sum(),
+        vendas_hoje,
+        max_quantidade: loja_service::MAX_QUANTIDADE,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template LojaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /loja/vendas - Regista uma venda (campos "qtd_<produto_id>", "comprador" e "pagamento")
+pub async fn handle_venda(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(campos): Form<HashMap<String, String>>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    let pagamento = campos.get("pagamento").map(|p| p.trim()).unwrap_or_default();
+    let opcoes: Vec<&str> = PAGAMENTOS.iter().map(|(valor, _)| *valor).collect();
+    v.opcao("pagamento", pagamento, &opcoes);
+    let mut itens: Vec<(i64, i64)> = Vec::new();
+    for (campo, valor) in &campos {
+        let Some(produto_id) = campo.strip_prefix("qtd_").and_then(|id| id.parse::<i64>().ok()) else {
+            continue;
+        };
+        match valor.trim() {
+            "" | "0" => {}
+            valor => match valor.parse::<i64>() {
+                Ok(quantidade) if (1..=loja_service::MAX_QUANTIDADE).contains(&quantidade) => itens.push((produto_id, quantidade)),
+                _ => v.erro("itens", format!("Quantidade inválida (1 a {}).", loja_service::MAX_QUANTIDADE)),
+            },
+        }
+    }
+    itens.sort_unstable();
+    let comprador = campos.get("comprador").map(|c| c.trim()).filter(|c| !c.is_empty());
+
+    let resultado = match v.resultado() {
+        Ok(()) => loja_service::registar_venda(&state.db_pool, atual.organizacao_id, &atual.id, comprador, &itens, pagamento).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((venda_id, total)) => {
+            let mut mensagem = format!("Venda #{} registada: {}.", venda_id, moeda::formatar(total));
+            if let (PAGAMENTO_CONTA, Some(comprador)) = (pagamento, comprador) {
+                let saldo = loja_service::saldo(&state.db_pool, atual.organizacao_id, comprador).await?;
+                mensagem.push_str(&format!(" Saldo da conta de {}: {}.", comprador, moeda::formatar(saldo)));
+            }
+            Ok(flash::redirect_success(&session, "/loja", mensagem).await.into_response())
+        }
+        Err(e @ AppError::Validation(_)) => Ok(flash::redirect_error(&session, "/loja", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /loja/vendas/{id}/anular - Anula uma venda de hoje (repõe o stock)
+pub async fn handle_anular_venda(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    match loja_service::anular_venda(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(total) => {
+            let detalhes = format!("Venda de {}", moeda::formatar(total));
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_VENDA_ANULADA, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/loja", format!("Venda #{} anulada; o stock foi reposto.", id)).await.into_response())
+        }
+        Err(e @ AppError::Conflict(_)) => Ok(flash::redirect_error(&session, "/loja", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+// --- COMPRAS DE UM UTILIZADOR ---
+
+/// Handler para GET /loja/compras?user= - Compras de um utilizador (operador da loja)
+pub async fn show_compras_loja(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<ComprasQuery>,
+) -> AppResult<Response> {
+    let id = params.user.trim();
+    let mut template = LojaComprasPage {
+        user_id: id.to_string(),
+        name: String::new(),
+        compras: Vec::new(),
+        total: 0,
+        proprio: false,
+        error_message: None,
+    };
+    if !id.is_empty() {
+        match user_service::find_user_by_id(&state.db_leitura, id).await?.filter(|u| u.organizacao_id == atual.organizacao_id) {
+            Some(user) => {
+                template.compras = loja_service::compras_user(&state.db_leitura, atual.organizacao_id, &user.id, loja_service::COMPRAS_HISTORICO).await?;
+                template.total = template.compras.iter().filter(|c| !c.anulada).map(|c| c.total).sum();
+                template.name = user.name;
+            }
+            None => template.error_message = Some(format!("Utilizador '{}' não encontrado.", id)),
+        }
+    }
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template LojaComprasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /user/compras - As minhas compras na loja
+pub async fn show_minhas_compras(State(state): State<AppState>, atual: CurrentUser) -> AppResult<Response> {
+    let compras = loja_service::compras_user(&state.db_leitura, atual.organizacao_id, &atual.id, loja_service::COMPRAS_HISTORICO).await?;
+    let template = LojaComprasPage {
+        total: compras.iter().filter(|c| !c.anulada).map(|c| c.total).sum(),
+        user_id: atual.id,
+        name: atual.name,
+        compras,
+        proprio: true,
+        error_message: None,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template LojaComprasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+// --- CONTA CORRENTE ---
+
+/// Primeiro dia do mês pedido ('YYYY-MM'), ou do mês atual.
+fn mes_pedido(mes: Option<&str>) -> NaiveDate {
+    let atual = tempo::hoje().with_day(1).unwrap_or_else(tempo::hoje);
+    mes.and_then(|m| tempo::ler_data(&format!("{}-01", m.trim()))).unwrap_or(atual)
+}
+
+/// Renderiza a conta corrente de um utilizador: o saldo e o extrato do mês `mes`.
+/// `user_id` vazio (operador que ainda não procurou ninguém) mostra só a pesquisa.
+async fn pagina_conta(
+    state: &AppState,
+    organizacao_id: i64,
+    user_id: &str,
+    mes: NaiveDate,
+    proprio: bool,
+    flash: Flash,
+) -> AppResult<Response> {
+    let mes_atual = tempo::hoje().with_day(1).unwrap_or(mes);
+    let mut template = LojaContaPage {
+        user_id: user_id.to_string(),
+        name: String::new(),
+        saldo: 0,
+        extrato: None,
+        mes_anterior: (mes - Months::new(1)).format("%Y-%m").to_string(),
+        mes_seguinte: (mes < mes_atual).then(|| (mes + Months::new(1)).format("%Y-%m").to_string()),
+        proprio,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    if !user_id.is_empty() {
+        match user_service::find_user_by_id(&state.db_leitura, user_id).await?.filter(|u| u.organizacao_id == organizacao_id) {
+            Some(user) => {
+                template.saldo = loja_service::saldo(&state.db_leitura, organizacao_id, &user.id).await?;
+                template.extrato = Some(loja_service::extrato_mes(&state.db_leitura, organizacao_id, &user.id, mes).await?);
+                template.name = user.name;
+            }
+            None => template.error_message = Some(format!("Utilizador '{}' não encontrado.", user_id)),
+        }
+    }
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template LojaContaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /loja/conta?user=&mes= - Conta corrente de um utilizador (operador da loja)
+pub async fn show_conta_loja(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<ContaQuery>,
+) -> AppResult<Response> {
+    let mes = mes_pedido(params.mes.as_deref());
+    pagina_conta(&state, atual.organizacao_id, params.user.trim(), mes, false, flash).await
+}
+
+/// Handler para POST /loja/conta/carregar - Carrega a conta corrente de um utilizador
+pub async fn handle_carregar_conta(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<CarregamentoForm>,
+) -> AppResult<Response> {
+    let user_id = form.user.trim();
+    // Volta à conta do utilizador (os IDs são alfanuméricos; outro valor nem chega a ser procurado)
+    let destino = if user_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        format!("/loja/conta?user={}", user_id)
+    } else {
+        "/loja/conta".to_string()
+    };
+    let mut v = Validador::default();
+    v.obrigatorio("user", user_id, 10);
+    let valor = v.moeda("valor", &form.valor).unwrap_or_default();
+    if valor == 0 || valor > loja_service::MAX_CARREGAMENTO {
+        v.erro("valor", format!("O carregamento deve ser entre R$ 0,01 e {}.", moeda::formatar(loja_service::MAX_CARREGAMENTO)));
+    }
+    let resultado = match v.resultado() {
+        Ok(()) => loja_service::carregar_conta(&state.db_pool, atual.organizacao_id, user_id, valor, &atual.id).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(saldo) => {
+            let detalhes = format!("{} (saldo {})", moeda::formatar(valor), moeda::formatar(saldo));
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CONTA_CARREGADA, Some(user_id), Some(&detalhes)).await;
+            let mensagem = format!("Conta carregada com {}. Saldo: {}.", moeda::formatar(valor), moeda::formatar(saldo));
+            Ok(flash::redirect_success(&session, &destino, mensagem).await.into_response())
+        }
+        Err(e @ AppError::Validation(_)) => Ok(flash::redirect_error(&session, &destino, e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /user/conta?mes= - A minha conta corrente na loja
+pub async fn show_minha_conta(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<ContaQuery>,
+) -> AppResult<Response> {
+    let mes = mes_pedido(params.mes.as_deref());
+    pagina_conta(&state, atual.organizacao_id, &atual.id, mes, true, flash).await
+}
+
+// --- ADMINISTRAÇÃO: PRODUTOS E RELATÓRIOS ---
+
+/// Renderiza a página dos produtos (com os erros do formulário de novo produto, se houver).
+async fn pagina_produtos(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = AdminLojaPage {
+        produtos: loja_service::listar_produtos(&state.db_leitura, organizacao_id, false).await?,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminLojaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /admin/loja - Produtos da loja (preço, stock, estado)
+pub async fn show_admin_loja(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_produtos(&state, atual.organizacao_id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /admin/loja/produtos - Cria um produto
+pub async fn handle_criar_produto(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<ProdutoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("nome", &form.nome, 60);
+    form.validate(&mut v);
+    let preco = moeda::ler(&form.preco).unwrap_or_default();
+    let resultado = match v.resultado() {
+        Ok(()) => loja_service::criar_produto(&state.db_pool, atual.organizacao_id, &form.nome, preco, form.stock).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!("{} ({}, stock {})", form.nome.trim(), moeda::formatar(preco), form.stock);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PRODUTO_CRIADO, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/admin/loja", format!("Produto '{}' criado.", form.nome.trim())).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("nome", form.nome)
+                .com_valor("preco", form.preco)
+                .com_valor("stock", form.stock.to_string());
+            pagina_produtos(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /admin/loja/produtos/{id} - Altera o preço, o stock ou o estado de um produto
+pub async fn handle_atualizar_produto(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<ProdutoForm>,
+) -> AppResult<Response> {
+    if let Err(e) = validar(&form) {
+        return Ok(flash::redirect_error(&session, "/admin/loja", e.user_message()).await.into_response());
+    }
+    let preco = moeda::ler(&form.preco).unwrap_or_default();
+    let ativo = form.ativo.is_some();
+    let anterior = loja_service::atualizar_produto(&state.db_pool, atual.organizacao_id, id, preco, form.stock, ativo).await?;
+
+    let mut alteracoes = Vec::new();
+    if anterior.preco != preco {
+        alteracoes.push(format!("preço {} -> {}", moeda::formatar(anterior.preco), moeda::formatar(preco)));
+    }
+    if anterior.stock != form.stock {
+        alteracoes.push(format!("stock {} -> {}", anterior.stock, form.stock));
+    }
+    if anterior.ativo != ativo {
+        alteracoes.push(if ativo { "ativado" } else { "desativado" }.to_string());
+    }
+    if alteracoes.is_empty() {
+        return Ok(flash::redirect_success(&session, "/admin/loja", "Nenhuma alteração.").await.into_response());
+    }
+    let detalhes = format!("{}: {}", anterior.nome, alteracoes.join(", "));
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PRODUTO_ALTERADO, Some(&id.to_string()), Some(&detalhes)).await;
+    Ok(flash::redirect_success(&session, "/admin/loja", format!("Produto '{}' atualizado.", anterior.nome)).await.into_response())
+}
+
+/// Handler para GET /admin/loja/relatorio?data= - Vendas de um dia (por omissão, hoje)
+pub async fn show_relatorio_loja(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<RelatorioQuery>,
+) -> AppResult<Response> {
+    let data = params.data.as_deref().and_then(tempo::ler_data).unwrap_or_else(tempo::hoje);
+    let template = AdminLojaRelatorioPage {
+        relatorio: loja_service::relatorio_dia(&state.db_leitura, atual.organizacao_id, data).await?,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminLojaRelatorioPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /admin/loja/saldos - Utilizadores com saldo negativo na conta corrente
+pub async fn show_saldos_negativos(State(state): State<AppState>, atual: CurrentUser) -> AppResult<Response> {
+    let saldos = loja_service::saldos_negativos(&state.db_leitura, atual.organizacao_id).await?;
+    let template = AdminLojaSaldosPage {
+        total: saldos.iter().map(|s| s.saldo).sum(),
+        saldos,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminLojaSaldosPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
@@ -1,10 +1,58 @@
 // src/web/mod.rs
 pub mod admin_handlers;
+pub mod api_auth_handlers;
+pub mod api_docs;
+pub mod api_handlers;
+pub mod api_v1_handlers;
+pub mod csrf;
+pub mod estaticos;
+pub mod feed_handlers;
+pub mod flash;
+pub mod formato;
+pub mod graphql;
+pub mod https;
 pub mod auth_handlers; 
+pub mod mw_api;
 pub mod mw_auth;
+pub mod mw_erros;
+pub mod mw_idioma;
 pub mod mw_admin;
+pub mod mw_limite;
+pub mod mw_manutencao;
+pub mod mw_senha;
 pub mod mw_presence;
+pub mod mw_request_id;
+pub mod navegacao;
+pub mod paginacao;
 pub mod routes; 
 pub mod user_handlers;
 pub mod presence_handlers;
+pub mod rancho_handlers;
+pub mod loja_handlers;
+pub mod livro_handlers;
+pub mod revista_handlers;
+pub mod quarto_handlers;
+pub mod cautela_handlers;
+pub mod baixa_handlers;
+pub mod visitante_handlers;
+pub mod agenda_handlers;
+pub mod documento_handlers;
+pub mod disciplina_handlers;
+pub mod antiguidade_handlers;
+pub mod horario_handlers;
+pub mod prova_handlers;
+pub mod uniforme_handlers;
+pub mod portaria_handlers;
+pub mod sugestao_handlers;
+pub mod faxina_handlers;
+pub mod conceito_handlers;
+pub mod comitiva_handlers;
+pub mod chamada_handlers;
+pub mod claviculario_handlers;
+pub mod tfm_handlers;
+pub mod enfermaria_handlers;
+pub mod biblioteca_handlers;
+pub mod lavanderia_handlers;
+pub mod enquete_handlers;
 pub mod escala_handlers;
+pub mod saude_handlers;
\ No newline at end of file
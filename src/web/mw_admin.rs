@@ -1,50 +1,85 @@
 // src/web/mw_admin.rs
 use crate::{
     error::AppError,        // Nosso tipo de erro
-    services::user_service, // Para buscar roles
+    services::permission_service, // Matriz de permissões
     state::AppState,        // Para aceder ao db_pool
-    web::mw_auth::UserId,   // Para obter o user_id das extensões
+    web::mw_auth::CurrentUser, // Utilizador e roles carregados por require_auth
 };
 use axum::{
-    extract::{Extension, Request, State}, // Usar Request e State
+    extract::{Request, State}, // Usar Request e State
     middleware::Next,                    // Próximo handler
     response::{IntoResponse, Response}, // REMOVER Redirect daqui
 };
 use tower_sessions::Session; // Para aceder à sessão
 
-/// Middleware que verifica se o utilizador logado tem a role "admin".
+/// Middleware que verifica se o utilizador logado tem a permissão "admin"
+/// (concedida pelas roles configuradas na matriz de permissões).
 /// Deve ser executado *depois* do middleware `require_auth`.
 // *** CORRIGIDO: Remover o genérico <B> da assinatura ***
 pub async fn require_admin(
     State(state): State<AppState>,           // Obtém o AppState (com db_pool)
-    Extension(user_id_ext): Extension<UserId>, // Obtém UserId posto por require_auth
+    atual: CurrentUser,                    // Posto por require_auth (com as roles já carregadas)
     request: Request,                      // A requisição (sem genérico)
     next: Next,                            // O próximo passo
 ) -> Result<Response, AppError> { // Retorna Response ou AppError
 
-    let user_id = user_id_ext.0; // Extrai o ID
-    tracing::debug!("Admin MW: Verificando role 'admin' para {}", user_id);
+    let user_id = &atual.id;
+    tracing::debug!("Admin MW: Verificando permissão 'admin' para {}", user_id);
 
-    // Busca as roles do utilizador na base de dados
-    match user_service::get_user_roles(&state.db_pool, &user_id).await {
-        Ok(roles) => {
-            // Verifica se a lista de roles contém "admin" (case-insensitive)
-            if roles.iter().any(|r| r.eq_ignore_ascii_case("admin")) {
-                tracing::debug!("Admin MW: Acesso admin concedido para {}", user_id);
-                // Tem a role "admin", continua para o handler final
-                Ok(next.run(request).await) // Passa a request (sem genérico)
-            } else {
-                // Não tem a role "admin"
-                tracing::warn!("Admin MW: Acesso negado para {} (sem role admin).", user_id);
-                // *** CORRIGIDO: Retorna AppError::Unauthorized (precisa existir em error.rs) ***
-                Err(AppError::Unauthorized)
-            }
+    match atual.tem_permissao(&state, permission_service::PERM_ADMIN).await {
+        Ok(true) => {
+            tracing::debug!("Admin MW: Acesso admin concedido para {}", user_id);
+            Ok(next.run(request).await) // Passa a request (sem genérico)
+        }
+        Ok(false) => {
+            tracing::warn!("Admin MW: Acesso negado para {} (sem permissão admin).", user_id);
+            Err(AppError::Unauthorized)
         }
         Err(e) => {
-            // Erro ao buscar roles na DB
-            tracing::error!("Admin MW: Erro ao buscar roles para {}: {:?}", user_id, e);
+            // Erro ao consultar a matriz/roles na DB
+            tracing::error!("Admin MW: Erro ao verificar permissões de {}: {:?}", user_id, e);
             Err(e) // Retorna o erro da DB
         }
     }
 }
 
+/// Middleware que exige uma permissão da matriz (ex.: "escala.gerir") para as rotas de uma área.
+/// Usado com um closure que fixa a permissão:
+/// `middleware::from_fn_with_state(state, |s: State<AppState>, r: Request, n: Next| require_permission(PERM_X, s, r, n))`.
+/// Deve ser executado *depois* do middleware `require_auth`.
+pub async fn require_permission(
+    permissao: &'static str,
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    // Posto por require_auth (ausente só numa rota fora dele: erro de configuração do router)
+    let atual = request.extensions().get::<CurrentUser>().cloned().ok_or(AppError::Unauthenticated)?;
+    match atual.tem_permissao(&state, permissao).await {
+        Ok(true) => Ok(next.run(request).await),
+        Ok(false) => {
+            tracing::warn!("Permissão MW: Acesso negado para {} (sem permissão '{}').", atual.id, permissao);
+            Err(AppError::Unauthorized)
+        }
+        Err(e) => {
+            tracing::error!("Permissão MW: Erro ao verificar a permissão '{}' de {}: {:?}", permissao, atual.id, e);
+            Err(e)
+        }
+    }
+}
+
+/// Middleware que só deixa passar super-admins (permissão "superadmin").
+/// Protege as áreas da instância: organizações, roles, auditoria, cópias, ...
+/// Deve ser executado *depois* do middleware `require_auth`.
+pub async fn require_superadmin(
+    atual: CurrentUser, // `superadmin` já foi resolvido por require_auth
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if atual.superadmin {
+        Ok(next.run(request).await)
+    } else {
+        tracing::warn!("Superadmin MW: Acesso negado para {} (sem permissão superadmin).", atual.id);
+        Err(AppError::Unauthorized)
+    }
+}
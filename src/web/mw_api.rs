@@ -0,0 +1,91 @@
+// src/web/mw_api.rs
+use crate::{
+    error::{ApiError, AppError},
+    services::{api_token_service, bloqueio_service, jwt_service, sessao_service, user_service},
+    state::AppState,
+    web::{
+        mw_auth::{self, OrganizacaoId, UserId},
+        mw_limite, mw_senha,
+    },
+};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use std::net::SocketAddr;
+use tower_sessions::Session;
+
+/// Middleware de autenticação da API JSON (/api/*): aceita um token (`Authorization: Bearer <token>`,
+/// de API ou o JWT da aplicação móvel) ou, sem esse cabeçalho, a sessão do browser. Ao contrário de `require_auth`, nunca redireciona:
+/// responde 401 no envelope de erro da API.
+/// Pedidos com sessão não precisam de token CSRF: a API só aceita corpos JSON, que outro site
+/// não consegue enviar sem autorização CORS.
+pub async fn require_api_auth(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    session: Session,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let autorizacao = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .map(|v| v.to_str().unwrap_or_default().to_string());
+
+    let por_token = autorizacao.is_some();
+    let user_id = match autorizacao {
+        Some(valor) => {
+            let token = valor.strip_prefix("Bearer ").map(str::trim).unwrap_or_default();
+            let user_id = match api_token_service::autenticar(&state.db_pool, token).await? {
+                Some(user_id) => Some(user_id),
+                None => jwt_service::autenticar(&state.db_pool, token).await?,
+            };
+            let Some(user_id) = user_id else {
+                tracing::warn!("API MW: token inválido ou revogado (IP {}).", addr.ip());
+                mw_limite::token_recusado(token);
+                return Err(AppError::Unauthenticated.into());
+            };
+            // O token não contorna um bloqueio da administração
+            if bloqueio_service::bloqueio_manual(&state.db_pool, &user_id).await?.is_some() {
+                tracing::warn!("API MW: token de {} recusado (conta bloqueada).", user_id);
+                mw_limite::token_recusado(token);
+                return Err(AppError::Unauthorized.into());
+            }
+            // Daqui em diante o limite de pedidos conta este token no balde do utilizador
+            mw_limite::token_validado(token, &user_id);
+            tracing::debug!("API MW: {} autenticado por token.", user_id);
+            user_id
+        }
+        None => {
+            let user_id = session
+                .get::<String>("user_id")
+                .await
+                .map_err(|e| AppError::SessionError(format!("Erro ao verificar sessão: {}", e)))?
+                .ok_or(AppError::Unauthenticated)?;
+            // Com a senha expirada, a sessão só serve para a alterar (ver mw_senha)
+            if session.get::<bool>(mw_senha::SENHA_EXPIRADA_KEY).await.ok().flatten().unwrap_or(false) {
+                tracing::warn!("API MW: sessão de {} com a senha expirada.", user_id);
+                return Err(AppError::Unauthorized.into());
+            }
+            if let Some(session_id) = session.id() {
+                let user_agent = request.headers().get(header::USER_AGENT).and_then(|v| v.to_str().ok());
+                sessao_service::tocar(&state.db_pool, &session_id.to_string(), &user_id, Some(&addr.ip().to_string()), user_agent).await;
+            }
+            user_id
+        }
+    };
+
+    // Organização do pedido (a escolhida na sessão só vale sem token)
+    let roles = user_service::roles_efetivas(&state.db_leitura, &user_id).await?;
+    let sessao = (!por_token).then_some(&session);
+    let Some((organizacao_id, _)) = mw_auth::resolver_organizacao(&state, sessao, &user_id, &roles).await? else {
+        tracing::warn!("API MW: {} sem acesso (organização desativada ou utilizador inexistente).", user_id);
+        return Err(AppError::Unauthorized.into());
+    };
+
+    request.extensions_mut().insert(UserId(user_id));
+    request.extensions_mut().insert(OrganizacaoId(organizacao_id));
+    Ok(next.run(request).await)
+}
@@ -1,15 +1,29 @@
 // src/web/mw_auth.rs
-use crate::error::AppError; // Nosso tipo de erro
+use crate::error::{AppError, AppResult}; // Nosso tipo de erro
+use crate::models::preferencias::Preferencias; // Preferências da interface do utilizador
+use crate::services::{organizacao_service, permission_service, preferencias_service, sessao_service, user_service}; // Sessões, matriz de permissões, utilizadores
+use crate::state::AppState;
+use crate::web::csrf; // Token CSRF das ações protegidas (logout)
+use crate::web::navegacao::{self, Navegacao}; // Barra de navegação das páginas
 use axum::{
-    extract::Request, // Usar Request em vez de Parts para ter extensões
+    extract::{ConnectInfo, FromRequestParts, Request, State}, // Usar Request em vez de Parts para ter extensões
+    http::{header, request::Parts, Method},
     middleware::Next, // Para chamar o próximo handler/middleware
     response::{IntoResponse, Response, Redirect}, // Tipos de resposta
 };
+use std::net::SocketAddr;
+use tower_cookies::Cookies; // Cookie com o token CSRF para as páginas
 use tower_sessions::Session; // Para aceder à sessão
 
+/// Chave de sessão com a organização escolhida por um super-admin (ver `resolver_organizacao`).
+pub const ORGANIZACAO_KEY: &str = "organizacao_id";
+
 // Middleware que verifica se o utilizador está logado
 pub async fn require_auth(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>, // IP do cliente (lista de sessões ativas)
     session: Session,                // Extrai a sessão atual
+    cookies: Cookies,                // Para entregar o token CSRF às páginas
     mut request: Request,            // A requisição original (mutável para adicionar extensões)
     next: Next,                    // O próximo passo
 ) -> Result<Response, AppError> { // Retorna ou a resposta do 'next' ou um erro
@@ -20,12 +34,37 @@ pub async fn require_auth(
             // Utilizador está logado!
             tracing::debug!("Autenticação MW: Utilizador '{}' autenticado. Prosseguindo...", user_id);
 
-            // Opcional: Adiciona o user_id às extensões da requisição
+            // Mantém o índice de sessões ativas (dispositivo, última atividade)
+            if let Some(session_id) = session.id() {
+                let user_agent = request.headers().get(header::USER_AGENT).and_then(|v| v.to_str().ok());
+                sessao_service::tocar(&state.db_pool, &session_id.to_string(), &user_id, Some(&addr.ip().to_string()), user_agent).await;
+            }
+
+            csrf::garantir(&session, &cookies, state.config.cookie_seguro()).await;
+
+            // Nome e roles lidos uma vez aqui; handlers e middlewares seguintes usam o CurrentUser
+            // (None também com a organização desativada: o login explica o motivo)
+            let Some(atual) = CurrentUser::carregar(&state, &session, &user_id).await? else {
+                tracing::warn!("Autenticação MW: sessão de '{}', que já não existe ou está sem acesso. Redirecionando para /login", user_id);
+                let _ = session.flush().await;
+                return Ok(Redirect::to("/login").into_response());
+            };
+
+            // Barra de navegação das páginas (só as áreas a que tem acesso; os POST não mostram páginas)
+            let navegacao = if request.method() == Method::GET {
+                navegacao::montar(&state, &atual, request.uri().path()).await
+            } else {
+                Navegacao::default()
+            };
+
+            // Adiciona o utilizador às extensões da requisição
             // para que os handlers protegidos possam aceder facilmente
             request.extensions_mut().insert(UserId(user_id));
+            request.extensions_mut().insert(OrganizacaoId(atual.organizacao_id));
+            request.extensions_mut().insert(atual);
 
             // Chama o próximo middleware ou o handler final e retorna a sua resposta
-            Ok(next.run(request).await)
+            Ok(navegacao::com_navegacao(navegacao, next.run(request)).await)
         }
         Ok(None) => {
             // Não há 'user_id' na sessão -> Não está logado
@@ -45,4 +84,90 @@ pub async fn require_auth(
 
 // Struct simples para guardar o user_id nas extensões da requisição (opcional)
 #[derive(Clone, Debug)]
-pub struct UserId(pub String);
\ No newline at end of file
+pub struct UserId(pub String);
+
+/// Organização em que o pedido trabalha (posta por `require_auth` e `require_api_auth`).
+/// Extrator para os handlers: os serviços só devolvem/alteram dados desta organização.
+#[derive(Clone, Copy, Debug)]
+pub struct OrganizacaoId(pub i64);
+
+impl<S: Send + Sync> FromRequestParts<S> for OrganizacaoId {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<OrganizacaoId>().copied().ok_or(AppError::Unauthenticated)
+    }
+}
+
+/// Organização do pedido de `user_id` (com as roles já carregadas): a sua, ou, para um super-admin, a escolhida
+/// na área de super-admin (chave de sessão `ORGANIZACAO_KEY`). Devolve (organização, é super-admin), ou None
+/// se o utilizador não existir ou a sua organização estiver desativada (os super-admins entram sempre).
+pub async fn resolver_organizacao(
+    state: &AppState,
+    session: Option<&Session>,
+    user_id: &str,
+    roles: &[String],
+) -> AppResult<Option<(i64, bool)>> {
+    let Some((propria, ativa)) = organizacao_service::do_utilizador(&state.db_leitura, user_id).await? else {
+        return Ok(None);
+    };
+    let superadmin =
+        permission_service::roles_tem_permissao(&state.db_leitura, &state.permissions, roles, permission_service::PERM_SUPERADMIN)
+            .await?;
+    if !superadmin {
+        return Ok(ativa.then_some((propria, false)));
+    }
+    let escolhida = match session {
+        Some(session) => session.get::<i64>(ORGANIZACAO_KEY).await.ok().flatten(),
+        None => None,
+    };
+    Ok(Some((escolhida.unwrap_or(propria), true)))
+}
+
+/// Utilizador autenticado, com as roles em vigor (permanentes + temporárias ativas), carregado uma vez
+/// por `require_auth`. Extrator para os handlers e middlewares que correm depois dele.
+#[derive(Clone, Debug)]
+pub struct CurrentUser {
+    pub id: String,
+    pub name: String,
+    pub roles: Vec<String>,
+    /// Organização em que está a trabalhar (ver `resolver_organizacao`).
+    pub organizacao_id: i64,
+    /// Administra a instância (área /superadmin, todas as organizações).
+    pub superadmin: bool,
+    /// Preferências da interface (tema, turma da presença, linhas por página, ...).
+    pub preferencias: Preferencias,
+}
+
+impl CurrentUser {
+    /// None se o utilizador já não existir ou a sua organização estiver desativada.
+    async fn carregar(state: &AppState, session: &Session, user_id: &str) -> AppResult<Option<Self>> {
+        let name: Option<String> = sqlx::query_scalar("SELECT name FROM users WHERE id = ?1")
+            .bind(user_id)
+            .fetch_optional(&state.db_leitura)
+            .await?;
+        let Some(name) = name else {
+            return Ok(None);
+        };
+        let roles = user_service::roles_efetivas(&state.db_leitura, user_id).await?;
+        let Some((organizacao_id, superadmin)) = resolver_organizacao(state, Some(session), user_id, &roles).await? else {
+            return Ok(None);
+        };
+        let preferencias = preferencias_service::obter(&state.db_leitura, user_id).await?;
+        Ok(Some(Self { id: user_id.to_string(), name, roles, organizacao_id, superadmin, preferencias }))
+    }
+
+    /// Se alguma das suas roles concede a permissão (matriz em memória, sem voltar às roles na DB).
+    pub async fn tem_permissao(&self, state: &AppState, permissao: &str) -> AppResult<bool> {
+        permission_service::roles_tem_permissao(&state.db_leitura, &state.permissions, &self.roles, permissao).await
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Ausente só numa rota fora de `require_auth` (erro de configuração do router)
+        parts.extensions.get::<CurrentUser>().cloned().ok_or(AppError::Unauthenticated)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,46 @@
+// src/web/mw_erros.rs
+use crate::{
+    error::{self, AppError, ErroPagina},
+    i18n,
+    services::user_service,
+    state::AppState,
+};
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use tower_sessions::Session;
+
+/// Middleware das páginas de erro: `AppError::into_response` não conhece o pedido, por isso a página sai
+/// sem utilizador; com sessão iniciada, volta a ser gerada aqui com o nome de quem está autenticado.
+/// Deve ser executado *dentro* da camada de sessão e do idioma (`mw_idioma`).
+pub async fn paginas_de_erro(State(state): State<AppState>, session: Session, request: Request, next: Next) -> Response {
+    let resposta = next.run(request).await;
+    let Some(erro) = resposta.extensions().get::<ErroPagina>().cloned() else {
+        return resposta;
+    };
+    let Some(user_id) = session.get::<String>("user_id").await.ok().flatten() else {
+        return resposta;
+    };
+
+    let utilizador = match user_service::find_user_by_id(&state.db_pool, &user_id).await {
+        Ok(Some(user)) => format!("{} ({})", user.name, user.id),
+        _ => user_id,
+    };
+    let (partes, _) = resposta.into_parts();
+    let mut pagina = error::pagina_erro(partes.status, &erro.mensagem, Some(utilizador));
+    // Mantém os cabeçalhos da resposta original (ex: Set-Cookie), exceto os do corpo
+    for (nome, valor) in partes.headers.iter() {
+        if nome != header::CONTENT_LENGTH && nome != header::CONTENT_TYPE {
+            pagina.headers_mut().append(nome, valor.clone());
+        }
+    }
+    pagina
+}
+
+/// Fallback do router: página 404 para qualquer endereço sem rota.
+pub async fn nao_encontrado() -> AppError {
+    AppError::NotFound(i18n::t("erro.pagina_inexistente"))
+}
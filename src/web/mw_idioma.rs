@@ -0,0 +1,31 @@
+// src/web/mw_idioma.rs
+use crate::i18n::{self, Idioma};
+use axum::{
+    extract::Request,
+    http::header::ACCEPT_LANGUAGE,
+    middleware::Next,
+    response::Response,
+};
+use tower_sessions::Session;
+
+/// Chave da sessão com o idioma escolhido pelo utilizador (preenchida no login e em /user/idioma).
+pub const IDIOMA_KEY: &str = "idioma";
+
+/// Middleware do idioma das páginas: a escolha do utilizador (sessão), senão o primeiro idioma suportado
+/// do Accept-Language do browser, senão o por omissão da configuração.
+/// O pedido corre com esse idioma (`i18n::t`, filtro `|t`), que fica também nas extensões do pedido.
+/// Deve ser executado *dentro* da camada de sessão.
+pub async fn escolher_idioma(session: Session, mut request: Request, next: Next) -> Response {
+    let escolhido = session.get::<String>(IDIOMA_KEY).await.ok().flatten().and_then(|codigo| codigo.parse().ok());
+    let idioma = escolhido
+        .or_else(|| {
+            request
+                .headers()
+                .get(ACCEPT_LANGUAGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(Idioma::negociar)
+        })
+        .unwrap_or_else(i18n::padrao);
+    request.extensions_mut().insert(idioma);
+    i18n::com_idioma(idioma, next.run(request)).await
+}
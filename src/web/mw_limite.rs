@@ -0,0 +1,268 @@
+// src/web/mw_limite.rs
+//! Limite de pedidos (rate limiting) para todo o router: um balde de fichas por cliente e por classe de rota,
+//! com limites mais apertados para o login e para as escritas na API. O cliente é o utilizador autenticado
+//! (sessão, ou token `Authorization: Bearer` já validado por `mw_api`) ou, sem autenticação, o IP: um token
+//! desconhecido ou inválido conta no balde do IP (trocar de token não dá um balde novo).
+//! Acima do limite responde 429 com `Retry-After` (JSON em /api/*, página de erro no resto).
+
+use crate::error::{ApiError, AppError};
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{header, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use tower_sessions::Session;
+
+const GERAL_PADRAO: u32 = 300;
+const LOGIN_PADRAO: u32 = 10;
+const ESCRITA_PADRAO: u32 = 60;
+/// Os limites são por minuto: um balde parado este tempo está cheio e pode ser esquecido.
+const JANELA: Duration = Duration::from_secs(60);
+/// De quanto em quanto tempo os baldes cheios são apagados da memória.
+const INTERVALO_LIMPEZA: Duration = Duration::from_secs(5 * 60);
+/// Tempo durante o qual um token validado por `mw_api` conta no balde do seu utilizador.
+const VALIDADE_TOKEN: Duration = Duration::from_secs(10 * 60);
+
+/// Rotas que nunca são limitadas (sondas de orquestradores).
+const ISENTAS: &[&str] = &["/healthz", "/readyz"];
+/// Prefixos nunca limitados (ficheiros estáticos: cada página pede vários).
+const ISENTAS_PREFIXOS: &[&str] = &["/static/"];
+/// Rotas POST que só leem (limite `geral`, não o das escritas).
+const SO_LEITURA: &[&str] = &["/api/graphql"];
+/// Rotas de autenticação (limite `login`, sempre por IP).
+const ROTAS_LOGIN: &[&str] = &["/login", "/api/auth/login", "/api/auth/refresh", "/auth/oidc/login", "/auth/oidc/callback"];
+
+/// Limites por minuto de cada classe de rota (0 = sem limite).
+#[derive(Debug, Clone)]
+pub struct LimiteConfig {
+    pub geral: u32,
+    pub login: u32,
+    pub escrita: u32,
+}
+
+impl LimiteConfig {
+    /// Lê RATE_LIMIT_PER_MINUTE (300, todos os pedidos), RATE_LIMIT_LOGIN_PER_MINUTE (10, POST de login)
+    /// e RATE_LIMIT_WRITE_PER_MINUTE (60, escritas em /api/* e /escala/*). 0 desativa o limite.
+    pub fn from_env() -> Result<Self, String> {
+        let numero = |nome: &str, padrao: u32| match std::env::var(nome).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()) {
+            Some(v) => v.parse::<u32>().map_err(|_| format!("{} inválido: '{}'", nome, v)),
+            None => Ok(padrao),
+        };
+        Ok(Self {
+            geral: numero("RATE_LIMIT_PER_MINUTE", GERAL_PADRAO)?,
+            login: numero("RATE_LIMIT_LOGIN_PER_MINUTE", LOGIN_PADRAO)?,
+            escrita: numero("RATE_LIMIT_WRITE_PER_MINUTE", ESCRITA_PADRAO)?,
+        })
+    }
+}
+
+static CONFIG: OnceLock<LimiteConfig> = OnceLock::new();
+static BALDES: OnceLock<Mutex<HashMap<(Classe, String), Balde>>> = OnceLock::new();
+/// Tokens validados por `mw_api` (pelo hash): o utilizador e quando foi validado pela última vez.
+static TOKENS: OnceLock<Mutex<HashMap<String, (String, Instant)>>> = OnceLock::new();
+
+/// Define os limites (chamado uma vez, no arranque).
+pub fn configurar(config: LimiteConfig) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("Configuração do limite de pedidos já definida; ignorada.");
+    }
+}
+
+/// Limites em vigor (os predefinidos, se `configurar` não tiver sido chamado).
+pub fn config() -> &'static LimiteConfig {
+    CONFIG.get_or_init(|| LimiteConfig { geral: GERAL_PADRAO, login: LOGIN_PADRAO, escrita: ESCRITA_PADRAO })
+}
+
+fn baldes() -> &'static Mutex<HashMap<(Classe, String), Balde>> {
+    BALDES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn tokens() -> &'static Mutex<HashMap<String, (String, Instant)>> {
+    TOKENS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Hash de um token (nunca se guarda o token em memória).
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.trim().as_bytes()))
+}
+
+/// O token foi validado (por `mw_api`): os pedidos seguintes com ele contam no balde do utilizador.
+pub fn token_validado(token: &str, user_id: &str) {
+    let mut tokens = tokens().lock().unwrap_or_else(|e| e.into_inner());
+    tokens.insert(hash_token(token), (user_id.to_string(), Instant::now()));
+}
+
+/// O token foi recusado (inválido, revogado ou conta bloqueada): volta a contar no balde do IP.
+pub fn token_recusado(token: &str) {
+    let mut tokens = tokens().lock().unwrap_or_else(|e| e.into_inner());
+    tokens.remove(&hash_token(token));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Classe {
+    Geral,
+    Login,
+    Escrita,
+}
+
+impl Classe {
+    fn do_pedido(method: &Method, path: &str) -> Self {
+        let leitura = matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) || SO_LEITURA.contains(&path);
+        if !leitura && ROTAS_LOGIN.contains(&path) {
+            Classe::Login
+        } else if !leitura && (path.starts_with("/api/") || path.starts_with("/escala/")) {
+            Classe::Escrita
+        } else {
+            Classe::Geral
+        }
+    }
+
+    fn limite(self, config: &LimiteConfig) -> u32 {
+        match self {
+            Classe::Geral => config.geral,
+            Classe::Login => config.login,
+            Classe::Escrita => config.escrita,
+        }
+    }
+}
+
+/// Balde de fichas: enche `limite` fichas por minuto, até `limite`; cada pedido gasta uma.
+#[derive(Debug)]
+struct Balde {
+    fichas: f64,
+    atualizado: Instant,
+}
+
+impl Balde {
+    /// Gasta uma ficha; sem fichas, devolve os segundos até haver uma.
+    fn gastar(&mut self, limite: u32, agora: Instant) -> Result<(), u64> {
+        let por_segundo = limite as f64 / JANELA.as_secs_f64();
+        let decorrido = agora.duration_since(self.atualizado).as_secs_f64();
+        self.fichas = (self.fichas + decorrido * por_segundo).min(limite as f64);
+        self.atualizado = agora;
+        if self.fichas >= 1.0 {
+            self.fichas -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - self.fichas) / por_segundo).ceil().max(1.0) as u64)
+        }
+    }
+}
+
+/// Quem faz o pedido, pelo que se sabe sem `await`.
+enum Cliente {
+    Chave(String),
+    /// Depende da sessão (ler a sessão é assíncrono); o IP fica para o caso de não haver utilizador.
+    Sessao(Option<Session>, String),
+}
+
+/// O utilizador de um token já validado (sem ir à base de dados), o utilizador da sessão ou o IP.
+/// Não é assíncrona de propósito: o corpo do pedido não é `Sync`, o pedido não pode ficar emprestado
+/// numa future.
+fn cliente(request: &Request, classe: Classe) -> Cliente {
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_default();
+    // O login é sempre por IP (ainda não há utilizador; limita a adivinhação de senhas)
+    if classe == Classe::Login {
+        return Cliente::Chave(format!("ip:{}", ip));
+    }
+    if let Some(token) = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        // Só um token que `mw_api` já validou tem balde próprio; qualquer outro conta no do IP
+        let tokens = tokens().lock().unwrap_or_else(|e| e.into_inner());
+        return match tokens.get(&hash_token(token)) {
+            Some((user_id, validado)) if validado.elapsed() < VALIDADE_TOKEN => Cliente::Chave(format!("user:{}", user_id)),
+            _ => Cliente::Chave(format!("ip:{}", ip)),
+        };
+    }
+    Cliente::Sessao(request.extensions().get::<Session>().cloned(), ip)
+}
+
+impl Cliente {
+    async fn chave(self) -> String {
+        match self {
+            Cliente::Chave(chave) => chave,
+            Cliente::Sessao(session, ip) => {
+                if let Some(session) = session {
+                    if let Ok(Some(user_id)) = session.get::<String>("user_id").await {
+                        return format!("user:{}", user_id);
+                    }
+                }
+                format!("ip:{}", ip)
+            }
+        }
+    }
+}
+
+/// Middleware: aplica o limite da classe da rota ao cliente (depois da camada de sessões).
+pub async fn limitar(request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    if ISENTAS.contains(&path.as_str()) || ISENTAS_PREFIXOS.iter().any(|p| path.starts_with(p)) {
+        return next.run(request).await;
+    }
+    let classe = Classe::do_pedido(request.method(), &path);
+    let limite = classe.limite(config());
+    if limite == 0 {
+        return next.run(request).await;
+    }
+
+    let chave = cliente(&request, classe).chave().await;
+    let resultado = {
+        let mut baldes = baldes().lock().unwrap_or_else(|e| e.into_inner());
+        let agora = Instant::now();
+        baldes
+            .entry((classe, chave.clone()))
+            .or_insert(Balde { fichas: limite as f64, atualizado: agora })
+            .gastar(limite, agora)
+    };
+
+    match resultado {
+        Ok(()) => next.run(request).await,
+        Err(segundos) => {
+            tracing::warn!("⛔ Limite de pedidos ({:?}, {}/min) excedido por {} em {}.", classe, limite, chave, path);
+            let erro = AppError::TooManyRequests(format!(
+                "Demasiados pedidos. Tente novamente dentro de {} segundo(s).", segundos
+            ));
+            let mut resposta = if path.starts_with("/api/") {
+                ApiError(erro).into_response()
+            } else {
+                erro.into_response()
+            };
+            resposta.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(segundos));
+            resposta
+        }
+    }
+}
+
+/// Inicia a tarefa que apaga da memória os baldes parados (já cheios).
+pub fn iniciar_limpeza() {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(INTERVALO_LIMPEZA).await;
+            let agora = Instant::now();
+            baldes()
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .retain(|_, balde| agora.duration_since(balde.atualizado) < JANELA);
+            tokens()
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .retain(|_, (_, validado)| agora.duration_since(*validado) < VALIDADE_TOKEN);
+        }
+    });
+}
@@ -0,0 +1,57 @@
+// src/web/mw_manutencao.rs
+//! Modo de manutenção (ver `manutencao_service`): as leituras passam, as alterações respondem 503
+//! (JSON em /api/*, página de erro no resto). Os super-admins continuam a poder alterar tudo
+//! (são eles que fazem a passagem de ano e os restauros, e que desligam o modo).
+
+use crate::{
+    error::{ApiError, AppError},
+    services::{manutencao_service, permission_service},
+    state::AppState,
+};
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tower_sessions::Session;
+
+/// Rotas POST sempre permitidas: entrar e sair (um super-admin tem de conseguir entrar) e as que só leem.
+const PERMITIDAS: &[&str] = &["/login", "/logout", "/api/auth/login", "/api/auth/refresh", "/api/graphql"];
+/// Prefixos sempre permitidos (login externo).
+const PERMITIDAS_PREFIXOS: &[&str] = &["/auth/oidc/"];
+
+/// Middleware: recusa as alterações enquanto o modo de manutenção estiver ativo (depois da camada de sessões).
+pub async fn bloquear_escritas(State(state): State<AppState>, session: Session, request: Request, next: Next) -> Response {
+    let Some(manutencao) = manutencao_service::atual() else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    if matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        || PERMITIDAS.contains(&path)
+        || PERMITIDAS_PREFIXOS.iter().any(|p| path.starts_with(p))
+    {
+        return next.run(request).await;
+    }
+
+    if let Ok(Some(user_id)) = session.get::<String>("user_id").await {
+        match permission_service::user_has_permission(&state.db_leitura, &state.permissions, &user_id, permission_service::PERM_SUPERADMIN).await {
+            Ok(true) => return next.run(request).await,
+            Ok(false) => {}
+            Err(e) => tracing::error!("Manutenção MW: erro ao verificar permissões de {}: {:?}", user_id, e),
+        }
+    }
+
+    tracing::info!("🚧 Alteração recusada (modo de manutenção): {} {}", request.method(), path);
+    let mensagem = if manutencao.motivo.is_empty() {
+        "A aplicação está em manutenção: as alterações estão suspensas. Tente novamente mais tarde.".to_string()
+    } else {
+        format!("A aplicação está em manutenção ({}): as alterações estão suspensas. Tente novamente mais tarde.", manutencao.motivo)
+    };
+    let erro = AppError::EmManutencao(mensagem);
+    if path.starts_with("/api/") {
+        ApiError(erro).into_response()
+    } else {
+        erro.into_response()
+    }
+}
@@ -2,35 +2,30 @@
 use crate::{
     error::AppError,
     // *** CORRIGIDO: Usar user_service diretamente ***
-    services::user_service, // Para chamar check_user_role_any
+    services::permission_service, // Matriz de permissões (com cache)
     state::AppState,
-    web::mw_auth::UserId,   // Para obter user_id das extensões
+    web::mw_auth::CurrentUser, // Utilizador e roles carregados por require_auth
 };
 use axum::{
-    extract::{Extension, Request, State}, // Usar Request e State
+    extract::{Request, State}, // Usar Request e State
     middleware::Next,
     response::Response, // Retornar Response ou AppError
 };
 
-pub const ROLES_QUE_ACEDEM_PRESENCA: &[&str] = &["admin", "policia", "chefe_de_dia"];
-
 /// Middleware que verifica se o utilizador logado tem permissão para aceder à Presença.
 /// Deve ser executado *depois* do middleware `require_auth`.
 pub async fn require_presence_access(
     State(state): State<AppState>,           // Obtém o AppState (com db_pool)
-    Extension(user_id_ext): Extension<UserId>, // Obtém UserId posto por require_auth
+    atual: CurrentUser,                    // Posto por require_auth (com as roles já carregadas)
     request: Request,                      // A requisição (sem genérico <B>)
     next: Next,                            // O próximo passo
 ) -> Result<Response, AppError> { // Retorna Response ou AppError
 
-    let user_id = user_id_ext.0; // Extrai o ID
+    let user_id = &atual.id;
     tracing::debug!("Presence MW: Verificando acesso para {}", user_id);
 
-    // Define as roles que permitem acesso ao módulo de presença
-    let required_roles = ROLES_QUE_ACEDEM_PRESENCA; // Ajuste conforme necessário
-
-    // Chama a função centralizada para verificar se o user tem alguma destas roles (permanente ou temporária ativa)
-    match user_service::check_user_role_any(&state.db_pool, &user_id, &required_roles).await {
+    // As roles com acesso vêm da matriz de permissões (permanentes ou temporárias ativas)
+    match atual.tem_permissao(&state, permission_service::PERM_PRESENCA).await {
         Ok(true) => {
             // Permissão concedida
             tracing::debug!("Presence MW: Acesso concedido para {}", user_id);
@@ -39,7 +34,7 @@ pub async fn require_presence_access(
         }
         Ok(false) => {
             // Permissão negada
-            tracing::warn!("Presence MW: Acesso negado para {} (sem roles requeridas: {:?}).", user_id, required_roles);
+            tracing::warn!("Presence MW: Acesso negado para {} (sem permissão '{}').", user_id, permission_service::PERM_PRESENCA);
             // Retorna erro Unauthorized (que o error.rs trata como redirect/forbidden)
             Err(AppError::Unauthorized)
         }
@@ -0,0 +1,28 @@
+// src/web/mw_request_id.rs
+//! ID do pedido (cabeçalho `X-Request-Id`): gerado à entrada (ou aceite do proxy, se já vier),
+//! gravado no span de cada pedido e devolvido na resposta. Todos os logs feitos durante o pedido
+//! (incluindo as tasks de uma ligação WebSocket) levam o mesmo `request_id`.
+
+use axum::{body::Body, http::Request};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tracing::Span;
+
+/// Atribui um UUID aos pedidos que chegam sem `X-Request-Id` (camada exterior, antes do TraceLayer).
+pub fn atribuir() -> SetRequestIdLayer<MakeRequestUuid> {
+    SetRequestIdLayer::x_request_id(MakeRequestUuid)
+}
+
+/// Copia o `X-Request-Id` do pedido para a resposta.
+pub fn propagar() -> PropagateRequestIdLayer {
+    PropagateRequestIdLayer::x_request_id()
+}
+
+/// Span de cada pedido (usado pelo TraceLayer), com o ID do pedido.
+pub fn span_pedido(req: &Request<Body>) -> Span {
+    let request_id = req
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("-");
+    tracing::info_span!("pedido", request_id = %request_id, method = %req.method(), uri = %req.uri())
+}
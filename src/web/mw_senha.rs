@@ -0,0 +1,21 @@
+// src/web/mw_senha.rs
+use axum::{
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use tower_sessions::Session;
+
+/// Chave da sessão marcada no login quando a senha passou do prazo de validade.
+pub const SENHA_EXPIRADA_KEY: &str = "senha_expirada";
+
+/// Middleware da política de validade das senhas: com a senha expirada, qualquer página
+/// autenticada redireciona para /user/senha até a senha ser alterada.
+/// Deve ser executado *depois* do middleware `require_auth`; /user/senha fica fora dele.
+pub async fn exigir_senha_valida(session: Session, request: Request, next: Next) -> Response {
+    if session.get::<bool>(SENHA_EXPIRADA_KEY).await.ok().flatten().unwrap_or(false) {
+        tracing::debug!("Senha MW: senha expirada, redirecionando {} para /user/senha", request.uri());
+        return Redirect::to("/user/senha").into_response();
+    }
+    next.run(request).await
+}
@@ -0,0 +1,110 @@
+// src/web/navegacao.rs
+//! Barra de navegação das páginas (base.html), montada no servidor: só entram os links das áreas a que o
+//! utilizador tem acesso (matriz de permissões). `require_auth` monta-a e o pedido corre com ela, como o
+//! idioma (ver `i18n::com_idioma`); o template lê-a com `atual()`. Fora de uma sessão fica vazia.
+//! Leva também o número de notificações por ler (o sino, com link para /user/notificacoes).
+
+use crate::{services::{notificacao_service, permission_service}, state::AppState, web::mw_auth::CurrentUser};
+use std::future::Future;
+
+/// Link da barra de navegação.
+#[derive(Debug, Clone)]
+pub struct LinkNav {
+    pub href: &'static str,
+    /// Chave da mensagem do texto (secção `[nav]` dos locales).
+    pub chave: &'static str,
+    /// Página atual (ou uma das suas subpáginas).
+    pub ativo: bool,
+}
+
+/// Navegação do pedido em curso.
+#[derive(Debug, Clone, Default)]
+pub struct Navegacao {
+    pub links: Vec<LinkNav>,
+    /// Há um utilizador autenticado (mostra o botão de sair).
+    pub autenticado: bool,
+    /// Notificações por ler do utilizador.
+    pub por_ler: i64,
+    /// Tema escolhido nas preferências (vazio fora de uma sessão = o claro).
+    pub tema: String,
+}
+
+/// Quem vê cada link.
+enum Acesso {
+    Todos,
+    Permissao(&'static str),
+    Superadmin,
+}
+
+/// Links da barra, por ordem.
+const ENTRADAS: &[(&str, &str, Acesso)] = &[
+    ("/user", "nav.dashboard", Acesso::Todos),
+    ("/escala", "nav.escalas", Acesso::Todos),
+    ("/escala/admin", "nav.gerir_escala", Acesso::Permissao(permission_service::PERM_ESCALA_GERIR)),
+    ("/faxina", "nav.faxina", Acesso::Todos),
+    ("/arranchamento", "nav.arranchamento", Acesso::Todos),
+    ("/agenda", "nav.agenda", Acesso::Todos),
+    ("/horario", "nav.horario", Acesso::Todos),
+    ("/documentos", "nav.documentos", Acesso::Todos),
+    ("/sugestoes", "nav.sugestoes", Acesso::Todos),
+    ("/conceito", "nav.conceito", Acesso::Todos),
+    ("/chamada", "nav.chamada", Acesso::Todos),
+    ("/enfermaria", "nav.enfermaria", Acesso::Todos),
+    ("/presence", "nav.presenca", Acesso::Permissao(permission_service::PERM_PRESENCA)),
+    ("/presence/visitantes", "nav.visitantes", Acesso::Permissao(permission_service::PERM_PRESENCA)),
+    ("/portaria", "nav.portaria", Acesso::Permissao(permission_service::PERM_PORTARIA)),
+    ("/comitivas", "nav.comitivas", Acesso::Permissao(permission_service::PERM_COMITIVAS)),
+    ("/rancho", "nav.rancho", Acesso::Permissao(permission_service::PERM_RANCHO)),
+    ("/loja", "nav.loja", Acesso::Permissao(permission_service::PERM_LOJA)),
+    ("/livro", "nav.livro", Acesso::Permissao(permission_service::PERM_LIVRO)),
+    ("/uniforme", "nav.uniforme", Acesso::Permissao(permission_service::PERM_UNIFORME)),
+    ("/revistas", "nav.revistas", Acesso::Permissao(permission_service::PERM_REVISTA)),
+    ("/cautelas", "nav.cautelas", Acesso::Permissao(permission_service::PERM_CAUTELA)),
+    ("/claviculario", "nav.claviculario", Acesso::Permissao(permission_service::PERM_CLAVICULARIO)),
+    ("/tfm", "nav.tfm", Acesso::Permissao(permission_service::PERM_TFM)),
+    ("/biblioteca", "nav.biblioteca", Acesso::Permissao(permission_service::PERM_BIBLIOTECA)),
+    ("/lavanderia", "nav.lavanderia", Acesso::Permissao(permission_service::PERM_LAVANDERIA)),
+    ("/enquetes", "nav.enquetes", Acesso::Permissao(permission_service::PERM_ENQUETES)),
+    ("/baixas", "nav.baixas", Acesso::Permissao(permission_service::PERM_BAIXAS)),
+    ("/disciplina", "nav.disciplina", Acesso::Permissao(permission_service::PERM_DISCIPLINA)),
+    ("/admin", "nav.administracao", Acesso::Permissao(permission_service::PERM_ADMIN)),
+    ("/superadmin", "nav.instancia", Acesso::Superadmin),
+];
+
+tokio::task_local! {
+    static NAVEGACAO: Navegacao;
+}
+
+/// Monta a navegação de `atual` para a página `path` (o link ativo é o de prefixo mais longo).
+pub async fn montar(state: &AppState, atual: &CurrentUser, path: &str) -> Navegacao {
+    let mut links = Vec::new();
+    for (href, chave, acesso) in ENTRADAS {
+        let visivel = match acesso {
+            Acesso::Todos => true,
+            Acesso::Superadmin => atual.superadmin,
+            Acesso::Permissao(permissao) => atual.tem_permissao(state, permissao).await.unwrap_or(false),
+        };
+        if visivel {
+            links.push(LinkNav { href, chave, ativo: false });
+        }
+    }
+    let corresponde = |href: &str| path == href || path.starts_with(&format!("{}/", href));
+    if let Some(ativo) = links.iter_mut().filter(|l| corresponde(l.href)).max_by_key(|l| l.href.len()) {
+        ativo.ativo = true;
+    }
+    let por_ler = notificacao_service::por_ler(&state.db_leitura, &atual.id).await.unwrap_or_else(|e| {
+        tracing::warn!("Falha ao contar as notificações por ler de {}: {:?}", atual.id, e);
+        0
+    });
+    Navegacao { links, autenticado: true, por_ler, tema: atual.preferencias.tema.clone() }
+}
+
+/// Navegação do pedido em curso (vazia fora de uma sessão).
+pub fn atual() -> Navegacao {
+    NAVEGACAO.try_with(Navegacao::clone).unwrap_or_default()
+}
+
+/// Executa `futuro` com a navegação indicada (ver `mw_auth::require_auth`).
+pub async fn com_navegacao<F: Future>(navegacao: Navegacao, futuro: F) -> F::Output {
+    NAVEGACAO.scope(navegacao, futuro).await
+}
@@ -0,0 +1,177 @@
+// src/web/paginacao.rs
+//! Paginação das listagens (utilizadores, auditoria, histórico de logins, ...): `?pagina=N&por_pagina=M`.
+//! O extractor `Paginacao` lê a página pedida e guarda o resto da query string (os filtros), para que os
+//! links de navegação (`Navegacao`, partial `paginacao.html`) mantenham os filtros aplicados.
+//! Sem `por_pagina` na query, vale o das preferências do utilizador (se as tiver alterado).
+
+use crate::web::mw_auth::CurrentUser;
+use axum::{
+    extract::{FromRequestParts, OriginalUri},
+    http::request::Parts,
+};
+use serde::Serialize;
+
+/// Linhas por página quando `por_pagina` não é indicado (nem está nas preferências do utilizador).
+pub const POR_PAGINA_PADRAO: i64 = 50;
+/// Máximo de linhas por página que um pedido pode escolher.
+pub const MAX_POR_PAGINA: i64 = 200;
+/// Páginas mostradas de cada lado da atual na navegação (as restantes ficam em "…").
+const VIZINHAS: i64 = 2;
+
+/// Página pedida. Usado como extractor nos handlers das listagens; valores inválidos ou fora dos limites
+/// dão a primeira página / o tamanho por omissão (nunca rejeita o pedido).
+#[derive(Debug, Clone)]
+pub struct Paginacao {
+    /// Página pedida, a partir de 1.
+    pub pagina: i64,
+    pub por_pagina: i64,
+    /// Linhas por página quando não indicado (omitido nos links de navegação).
+    padrao: i64,
+    caminho: String,
+    /// Restantes parâmetros da query string (filtros), por ordem.
+    outros: Vec<(String, String)>,
+}
+
+impl Default for Paginacao {
+    fn default() -> Self {
+        Self { pagina: 1, por_pagina: POR_PAGINA_PADRAO, padrao: POR_PAGINA_PADRAO, caminho: String::new(), outros: Vec::new() }
+    }
+}
+
+impl<S> FromRequestParts<S> for Paginacao
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Nas rotas aninhadas (`/admin`) `parts.uri` já não tem o prefixo: os links usam o URI original
+        let uri = parts.extensions.get::<OriginalUri>().map_or(&parts.uri, |original| &original.0);
+        // Preferência do utilizador (posto por `require_auth`; as rotas da API não a têm)
+        let padrao = parts
+            .extensions
+            .get::<CurrentUser>()
+            .and_then(|atual| atual.preferencias.por_pagina)
+            .filter(|n| (1..=MAX_POR_PAGINA).contains(n))
+            .unwrap_or(POR_PAGINA_PADRAO);
+        let mut paginacao =
+            Paginacao { por_pagina: padrao, padrao, caminho: uri.path().to_string(), ..Default::default() };
+        for (nome, valor) in form_urlencoded::parse(uri.query().unwrap_or_default().as_bytes()) {
+            match nome.as_ref() {
+                "pagina" => paginacao.pagina = valor.trim().parse::<i64>().ok().filter(|p| *p >= 1).unwrap_or(1),
+                "por_pagina" => {
+                    paginacao.por_pagina = valor
+                        .trim()
+                        .parse::<i64>()
+                        .ok()
+                        .filter(|n| (1..=MAX_POR_PAGINA).contains(n))
+                        .unwrap_or(padrao)
+                }
+                _ => paginacao.outros.push((nome.into_owned(), valor.into_owned())),
+            }
+        }
+        Ok(paginacao)
+    }
+}
+
+impl Paginacao {
+    /// Primeira página de `caminho`, sem filtros (quando a listagem é mostrada fora de um GET,
+    /// ex: formulário reapresentado após um POST).
+    pub fn nova(caminho: &str) -> Self {
+        Self { caminho: caminho.to_string(), ..Default::default() }
+    }
+
+    /// `LIMIT` da query.
+    pub fn limite(&self) -> i64 {
+        self.por_pagina
+    }
+
+    /// `OFFSET` da query.
+    pub fn offset(&self) -> i64 {
+        (self.pagina - 1).saturating_mul(self.por_pagina)
+    }
+
+    /// URL da página `pagina`, com os mesmos filtros e tamanho de página.
+    fn url(&self, pagina: i64) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        query.extend_pairs(&self.outros);
+        if pagina > 1 {
+            query.append_pair("pagina", &pagina.to_string());
+        }
+        if self.por_pagina != self.padrao {
+            query.append_pair("por_pagina", &self.por_pagina.to_string());
+        }
+        let query = query.finish();
+        if query.is_empty() {
+            self.caminho.clone()
+        } else {
+            format!("{}?{}", self.caminho, query)
+        }
+    }
+
+    /// Navegação para o template, sabendo o total de linhas (de todas as páginas).
+    pub fn navegacao(&self, total: i64) -> Navegacao {
+        let total_paginas = ((total + self.por_pagina - 1) / self.por_pagina).max(1);
+        let pagina = self.pagina.min(total_paginas);
+        let mut links = Vec::new();
+        for numero in 1..=total_paginas {
+            let perto = (numero - pagina).abs() <= VIZINHAS;
+            if numero == 1 || numero == total_paginas || perto {
+                links.push(LinkPagina { numero: Some(numero), url: self.url(numero), atual: numero == self.pagina });
+            } else if links.last().is_some_and(|l: &LinkPagina| l.numero.is_some()) {
+                links.push(LinkPagina { numero: None, url: String::new(), atual: false });
+            }
+        }
+        Navegacao {
+            pagina: self.pagina,
+            por_pagina: self.por_pagina,
+            total,
+            total_paginas,
+            anterior: (self.pagina > 1).then(|| self.url((self.pagina - 1).min(total_paginas))),
+            seguinte: (self.pagina < total_paginas).then(|| self.url(self.pagina + 1)),
+            links,
+        }
+    }
+}
+
+/// Navegação entre páginas de uma listagem (partial `paginacao.html`; também devolvida nas respostas JSON).
+#[derive(Debug, Clone, Serialize)]
+pub struct Navegacao {
+    pub pagina: i64,
+    pub por_pagina: i64,
+    /// Linhas de todas as páginas.
+    pub total: i64,
+    pub total_paginas: i64,
+    pub anterior: Option<String>,
+    pub seguinte: Option<String>,
+    /// Primeira, última e as páginas à volta da atual; `numero: None` = "…".
+    pub links: Vec<LinkPagina>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkPagina {
+    pub numero: Option<i64>,
+    pub url: String,
+    pub atual: bool,
+}
+
+impl Navegacao {
+    /// Posição da primeira linha desta página (1 = primeira), para "A mostrar X–Y de Z".
+    pub fn primeira(&self) -> i64 {
+        if self.total == 0 {
+            0
+        } else {
+            ((self.pagina - 1) * self.por_pagina + 1).min(self.total)
+        }
+    }
+
+    /// Posição da última linha desta página.
+    pub fn ultima(&self) -> i64 {
+        (self.pagina * self.por_pagina).min(self.total)
+    }
+
+    /// Há mais de uma página (senão a navegação não é mostrada).
+    pub fn varias(&self) -> bool {
+        self.total_paginas > 1
+    }
+}
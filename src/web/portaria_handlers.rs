@@ -0,0 +1,191 @@
+// src/web/portaria_handlers.rs
+//! Portaria (/portaria, permissão "portaria"): registo da entrada e da saída de viaturas e entregas, o que
+//! está dentro e os movimentos do dia (com exportação em CSV). Separado da presença e dos visitantes.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::portaria_service,
+    state::AppState,
+    templates::PortariaPage,
+    tempo,
+    validation::{FormState, Validador},
+    web::{
+        admin_handlers::csv_campo,
+        flash::{self, Flash},
+        mw_auth::CurrentUser,
+    },
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo da matrícula, do condutor e do motivo.
+const MAX_MATRICULA: usize = 20;
+const MAX_CONDUTOR: usize = 100;
+const MAX_MOTIVO: usize = 200;
+
+#[derive(Deserialize, Debug)]
+pub struct PortariaQuery {
+    data: Option<String>, // Dia dos movimentos (omissão: hoje)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EntradaPortariaForm {
+    #[serde(default)]
+    tipo: String,
+    #[serde(default)]
+    matricula: String,
+    #[serde(default)]
+    condutor: String,
+    #[serde(default)]
+    motivo: String,
+}
+
+/// Dia pedido (`?data=`), ou hoje.
+fn dia_pedido(data: Option<&str>) -> chrono::NaiveDate {
+    data.and_then(tempo::ler_data).unwrap_or_else(tempo::hoje)
+}
+
+/// Renderiza o que está dentro, os movimentos do dia e o formulário de entrada.
+async fn pagina_portaria(
+    state: &AppState,
+    atual: &CurrentUser,
+    data: chrono::NaiveDate,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let template = PortariaPage {
+        dentro: portaria_service::dentro(&state.db_leitura, atual.organizacao_id).await?,
+        movimentos: portaria_service::do_dia(&state.db_leitura, atual.organizacao_id, data).await?,
+        tipos: portaria_service::TIPOS,
+        data: data.to_string(),
+        hoje: tempo::hoje().to_string(),
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template PortariaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /portaria?data= - Viaturas e entregas dentro e movimentos do dia
+pub async fn show_portaria(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<PortariaQuery>,
+) -> AppResult<Response> {
+    let data = dia_pedido(params.data.as_deref());
+    let form = FormState::default().com_valor("tipo", "viatura");
+    pagina_portaria(&state, &atual, data, StatusCode::OK, form, flash).await
+}
+
+/// Handler para POST /portaria - Regista a entrada de uma viatura ou entrega
+pub async fn handle_entrada_portaria(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<EntradaPortariaForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("matricula", &form.matricula, MAX_MATRICULA);
+    v.obrigatorio("condutor", &form.condutor, MAX_CONDUTOR);
+    if form.motivo.trim().chars().count() > MAX_MOTIVO {
+        v.erro("motivo", format!("Máximo de {} caracteres.", MAX_MOTIVO));
+    }
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            portaria_service::registar_entrada(
+                &state.db_pool,
+                atual.organizacao_id,
+                form.tipo.trim(),
+                &form.matricula,
+                &form.condutor,
+                &form.motivo,
+                &atual.id,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(movimento) => {
+            let mensagem = format!("Entrada de {} ({}) registada.", movimento.matricula, movimento.condutor);
+            Ok(flash::redirect_success(&session, "/portaria", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("tipo", form.tipo)
+                .com_valor("matricula", form.matricula)
+                .com_valor("condutor", form.condutor)
+                .com_valor("motivo", form.motivo);
+            pagina_portaria(&state, &atual, tempo::hoje(), StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /portaria/{id}/saida - Regista a saída
+pub async fn handle_saida_portaria(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    match portaria_service::registar_saida(&state.db_pool, atual.organizacao_id, id, &atual.id).await {
+        Ok(movimento) => {
+            let mensagem = format!("Saída de {} registada.", movimento.matricula);
+            Ok(flash::redirect_success(&session, "/portaria", mensagem).await.into_response())
+        }
+        Err(AppError::Conflict(mensagem)) => Ok(flash::redirect_error(&session, "/portaria", mensagem).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /portaria/export.csv?data= - Movimentos do dia em CSV
+pub async fn handle_export_portaria(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<PortariaQuery>,
+) -> AppResult<Response> {
+    let data = dia_pedido(params.data.as_deref());
+    let movimentos = portaria_service::do_dia(&state.db_leitura, atual.organizacao_id, data).await?;
+
+    // BOM UTF-8: o Excel abre os acentos corretamente
+    let mut csv = String::from("\u{feff}");
+    csv.push_str("id,tipo,matricula,condutor,motivo,entrada,registada_por,saida,saida_registada_por\r\n");
+    for m in &movimentos {
+        let linha = [
+            m.id.to_string(),
+            m.tipo.clone(),
+            csv_campo(&m.matricula),
+            csv_campo(&m.condutor),
+            csv_campo(&m.motivo),
+            tempo::formatar(&m.entrada_em, tempo::FORMATO_DATA_HORA),
+            csv_campo(&m.entrada_por),
+            m.saida_em.as_deref().map(|s| tempo::formatar(s, tempo::FORMATO_DATA_HORA)).unwrap_or_default(),
+            csv_campo(m.saida_por.as_deref().unwrap_or_default()),
+        ];
+        csv.push_str(&linha.join(","));
+        csv.push_str("\r\n");
+    }
+
+    let disposicao = format!("attachment; filename=\"portaria_{}.csv\"", data);
+    Ok((
+        [(header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()), (header::CONTENT_DISPOSITION, disposicao)],
+        csv,
+    )
+        .into_response())
+}
@@ -5,24 +5,26 @@ use crate::{
         PresencePerson, PresenceSocketAction, PresenceSocketUpdate, PresenceStats,
     }, // Modelos
     models::user::User,          // Para buscar ano do user
-    services::{presence_service, user_service}, // Serviços
+    services::{agenda_service, grupo_service, manutencao_service, presence_service, quarto_service, user_service}, // Serviços
     state::AppState,            // Estado da aplicação (com PresenceWsState)
-    templates::PresencePage,    // Template Askama
-    web::mw_auth::UserId,       // Para ID do operador
+    templates::{PernoitePage, PresencePage}, // Templates Askama
+    tempo,                      // Fuso horário da aplicação
+    web::mw_auth::{CurrentUser, OrganizacaoId}, // Operador (id e nome) e a sua organização
 };
 use askama::Template;
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade}, // Tipos WebSocket
-        Query, State, Extension, // Extratores Axum
+        Query, State, // Extratores Axum
     },
     response::{Html, IntoResponse}, // Tipos de Resposta
 };
-use chrono::{DateTime, Local}; // Para formatar datas
+use chrono::{DateTime, Utc}; // Para formatar datas (no fuso da aplicação)
 use futures_util::{stream::{SplitSink, SplitStream, StreamExt}, SinkExt}; // Para manipular WS stream
 use serde::Deserialize;
 use std::sync::Arc; // Para clonar AppState
 use tokio::sync::{mpsc, Mutex}; // Para canal WS
+use tracing::Instrument; // Span do pedido nas tasks da ligação WS
 use uuid::Uuid; // Para IDs de conexão
 
 // --- Handler HTTP (GET /presence) ---
@@ -32,6 +34,10 @@ use uuid::Uuid; // Para IDs de conexão
 pub struct PresenceQuery {
     // Usa Option<i64> para default se não fornecido
     turma: Option<i64>,
+    // Se indicado, mostra os membros do grupo em vez da turma
+    grupo: Option<i64>,
+    // "quarto": agrupa a lista por corredor/quarto
+    agrupar: Option<String>,
 }
 
 /// Handler para servir a página HTML de controlo de presença.
@@ -39,23 +45,46 @@ pub struct PresenceQuery {
 pub async fn presence_page_handler(
     State(state): State<AppState>, // Obtém AppState
     // Extension(user_id_ext): Extension<UserId>, // Poderia obter UserId do operador
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+    atual: CurrentUser,
     Query(params): Query<PresenceQuery>, // Obtém "?turma="
 ) -> AppResult<impl IntoResponse> {
-    // Define a turma a ser exibida (default para 1 se não especificado)
-    let turma_selecionada = params.turma.unwrap_or(1);
+    // Define a turma a ser exibida (se não especificada: a das preferências do utilizador, ou 1)
+    let turma_selecionada = params.turma.or(atual.preferencias.turma_presenca).unwrap_or(1);
     tracing::debug!("GET /presence: Carregando turma {}", turma_selecionada);
 
-    // Busca a lista de pessoas e o estado de presença para a turma
-    let pessoas = presence_service::get_presence_list_for_turma(&state.db_pool, turma_selecionada).await?;
+    // Busca a lista de pessoas e o estado de presença para o grupo ou a turma
+    let grupo_selecionado = params.grupo;
+    let mut pessoas = match grupo_selecionado {
+        Some(grupo_id) => presence_service::get_presence_list_for_grupo(&state.db_leitura, organizacao_id, grupo_id).await?,
+        None => presence_service::get_presence_list_for_turma(&state.db_leitura, organizacao_id, turma_selecionada).await?,
+    };
+    let por_quarto = params.agrupar.as_deref() == Some("quarto");
+    if por_quarto {
+        quarto_service::ordenar_por_quarto(&mut pessoas);
+    }
+
+    // Grupos disponíveis para o seletor
+    let grupos = grupo_service::listar_grupos(&state.db_leitura, organizacao_id).await?;
 
     // Calcula as estatísticas
     let stats = presence_service::calcular_stats(&pessoas);
 
+    // Eventos da agenda de hoje que esperam a turma inteira a bordo (só na vista por turma)
+    let eventos_turma = match grupo_selecionado {
+        Some(_) => Vec::new(),
+        None => agenda_service::presenca_esperada(&state.db_leitura, organizacao_id, tempo::hoje(), turma_selecionada).await?,
+    };
+
     // Cria a struct do template Askama
     let template = PresencePage {
         turma_selecionada,
+        grupo_selecionado,
+        grupos,
+        por_quarto,
         pessoas: &pessoas, // Passa como slice
         stats: &stats,     // Passa como referência
+        eventos_turma,
     };
 
     // Renderiza o template
@@ -68,6 +97,28 @@ pub async fn presence_page_handler(
     }
 }
 
+/// Handler para GET /presence/pernoite - Relatório de pernoite: por corredor e quarto, quantos lá
+/// dormem e quem está fora neste momento (todas as turmas).
+pub async fn pernoite_handler(
+    State(state): State<AppState>,
+    OrganizacaoId(organizacao_id): OrganizacaoId,
+) -> AppResult<impl IntoResponse> {
+    let mut pessoas = presence_service::get_presence_list_for_organizacao(&state.db_leitura, organizacao_id).await?;
+    quarto_service::ordenar_por_quarto(&mut pessoas);
+    let template = PernoitePage {
+        stats: presence_service::calcular_stats(&pessoas),
+        linhas: quarto_service::pernoite(&pessoas),
+        gerado_em: tempo::agora().format("%d/%m/%Y %H:%M").to_string(),
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template PernoitePage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
 
 // --- Handlers WebSocket (GET /presence/ws) ---
 
@@ -76,16 +127,18 @@ pub async fn presence_page_handler(
 pub async fn presence_websocket_handler(
     ws: WebSocketUpgrade,          // Extrator para upgrade WS
     State(state): State<AppState>, // AppState (com db_pool e presence_state)
-    Extension(user_id_ext): Extension<UserId>, // ID do operador (posto por require_auth)
+    operador: CurrentUser,         // Operador (posto por require_auth, já com o nome)
 ) -> impl IntoResponse {
-    let operator_id = user_id_ext.0; // Obtém o ID
-    tracing::info!("Tentativa de upgrade WebSocket para Presença por {}", operator_id);
-    // Inicia o processo de upgrade, passando o estado e ID do operador para a função `handle_socket`
-    ws.on_upgrade(move |socket| handle_socket(socket, state, operator_id))
+    tracing::info!("Tentativa de upgrade WebSocket para Presença por {}", operador.id);
+    // Inicia o processo de upgrade, passando o estado e ID do operador para a função `handle_socket`.
+    // A ligação corre noutra task: leva o span do pedido, para os logs terem o mesmo request_id.
+    let span = tracing::Span::current();
+    ws.on_upgrade(move |socket| handle_socket(socket, state, operador).instrument(span))
 }
 
 /// Função que gere uma conexão WebSocket individual.
-async fn handle_socket(socket: WebSocket, state: AppState, operator_id: String) {
+async fn handle_socket(socket: WebSocket, state: AppState, operador: CurrentUser) {
+    let CurrentUser { id: operator_id, name: operator_name, organizacao_id, .. } = operador;
     let conn_id = Uuid::new_v4(); // Gera ID único para esta conexão
     tracing::info!("🔌 Nova conexão WS Presença: {} (Operador: {})", conn_id, operator_id);
 
@@ -98,7 +151,7 @@ async fn handle_socket(socket: WebSocket, state: AppState, operator_id: String)
     let (tx, mut rx) = mpsc::channel::<Message>(32); // Buffer de 32 mensagens
 
     // Guarda o 'sender' (tx) no estado global para que outras tasks possam enviar msgs a este cliente
-    state.presence_state.connections.lock().await.insert(conn_id, tx.clone());
+    state.presence_state.connections.lock().await.insert(conn_id, (organizacao_id, tx.clone()));
 
     // --- Task 1: Enviar mensagens do canal MPSC para o cliente ---
     let state_clone_send = state.clone(); // Clona state para a task
@@ -115,21 +168,13 @@ async fn handle_socket(socket: WebSocket, state: AppState, operator_id: String)
         }
         // Quando o loop termina (canal fechado), remove a conexão do estado
         state_clone_send.presence_state.connections.lock().await.remove(&conn_id_send);
-    });
+    }.in_current_span()); // Mantém o span do pedido (request_id) nos logs da task
 
 
     // --- Task 2: Receber mensagens do cliente e processá-las ---
     let state_clone_recv = state.clone(); // Clona state para a task
     let conn_id_recv = conn_id;
-    let operator_id_recv = operator_id.clone(); // Clona ID do operador
     let mut recv_task = tokio::spawn(async move {
-        // Busca o nome do operador (para logs e mensagens de broadcast) uma vez
-        let operator_name = user_service::find_user_by_id(&state_clone_recv.db_pool, &operator_id_recv)
-            .await
-            .ok() // Ignora erro de busca, usa ID como fallback
-            .flatten() // Option<Option<User>> -> Option<User>
-            .map_or(operator_id_recv.clone(), |u| u.name); // Pega nome ou ID
-
         // Loop enquanto houver mensagens do cliente
         while let Some(Ok(msg)) = ws_receiver.next().await {
             match msg {
@@ -138,24 +183,13 @@ async fn handle_socket(socket: WebSocket, state: AppState, operator_id: String)
                     // Tenta deserializar a ação enviada pelo cliente
                     match serde_json::from_str::<PresenceSocketAction>(&text) {
                         Ok(action) => {
-                            // Processa a ação (chama o serviço e prepara broadcast)
-                            let update_result = process_presence_action(
+                            // Processa a ação (chama o serviço) e envia o update a todos
+                            marcar_e_difundir(
                                 &state_clone_recv, // Passa AppState
+                                organizacao_id,    // Só utilizadores (e páginas abertas) da organização do operador
                                 &action,           // Ação recebida
                                 &operator_name,    // Nome do operador
                             ).await;
-
-                            // Serializa a mensagem de update (sucesso ou erro) para JSON
-                            match serde_json::to_string(&update_result) {
-                                Ok(broadcast_msg_text) => {
-                                    // Envia a atualização para TODOS os clientes conectados
-                                    tracing::debug!("-> WS Presença Enviando Broadcast: {}", broadcast_msg_text);
-                                    state_clone_recv.presence_state.broadcast(broadcast_msg_text).await;
-                                }
-                                Err(e) => {
-                                    tracing::error!("Erro ao serializar update WS Presença: {:?}", e);
-                                }
-                            }
                         }
                         Err(e) => {
                             tracing::warn!("Mensagem WS Presença inválida (JSON parse falhou): {}, Erro: {}", text, e);
@@ -172,7 +206,7 @@ async fn handle_socket(socket: WebSocket, state: AppState, operator_id: String)
             }
         }
         // Fim do loop (cliente desconectou ou enviou Close)
-    });
+    }.in_current_span());
 
 
     // Espera que uma das tasks termine (ou dê erro)
@@ -188,15 +222,49 @@ async fn handle_socket(socket: WebSocket, state: AppState, operator_id: String)
 }
 
 
+/// Processa uma ação de presença (WebSocket ou API JSON) e envia o resultado a todas
+/// as páginas de presença abertas da organização. Devolve o update enviado.
+pub(crate) async fn marcar_e_difundir(
+    state: &AppState,
+    organizacao_id: i64,
+    action: &PresenceSocketAction,
+    operator_name: &str,
+) -> PresenceSocketUpdate {
+    let update_result = process_presence_action(state, organizacao_id, action, operator_name).await;
+
+    // Serializa a mensagem de update (sucesso ou erro) para JSON
+    match serde_json::to_string(&update_result) {
+        Ok(broadcast_msg_text) => {
+            // Envia a atualização para TODOS os clientes conectados
+            tracing::debug!("-> WS Presença Enviando Broadcast: {}", broadcast_msg_text);
+            state.presence_state.broadcast(organizacao_id, broadcast_msg_text).await;
+        }
+        Err(e) => {
+            tracing::error!("Erro ao serializar update WS Presença: {:?}", e);
+        }
+    }
+    update_result
+}
+
 /// Função auxiliar para processar uma ação recebida via WebSocket.
 async fn process_presence_action(
     state: &AppState,
+    organizacao_id: i64,
     action: &PresenceSocketAction,
     operator_name: &str, // Usar nome para mensagens
 ) -> PresenceSocketUpdate { // Retorna sempre um PresenceSocketUpdate (sucesso ou erro)
 
-    // 1. Tenta executar a ação na base de dados
+    // 1. Tenta executar a ação na base de dados (só para utilizadores da organização do operador)
+    let da_organizacao = matches!(
+        user_service::find_user_by_id(&state.db_pool, &action.user_id).await,
+        Ok(Some(ref u)) if u.organizacao_id == organizacao_id
+    );
     let db_result = match action.action.as_str() {
+        // O WebSocket não passa pelo middleware da manutenção: a marcação é recusada aqui
+        _ if manutencao_service::atual().is_some() => Err(AppError::EmManutencao(
+            "A aplicação está em manutenção: as marcações estão suspensas.".to_string(),
+        )),
+        _ if !da_organizacao => Err(AppError::NotFound(format!("Utilizador '{}' não encontrado.", action.user_id))),
         "saida" => presence_service::marcar_saida(&state.db_pool, &action.user_id, operator_name).await,
         "retorno" => presence_service::marcar_retorno(&state.db_pool, &action.user_id, operator_name).await,
         _ => {
@@ -221,7 +289,7 @@ async fn process_presence_action(
             match user_service::find_user_by_id(&state.db_pool, &action.user_id).await {
                 Ok(Some(user)) => {
                     // Busca a lista atualizada da turma para calcular stats e obter dados formatados
-                    match presence_service::get_presence_list_for_turma(&state.db_pool, user.ano).await {
+                    match presence_service::get_presence_list_for_turma(&state.db_pool, organizacao_id, user.ano).await {
                         Ok(pessoas_turma) => {
                             // Calcula stats atualizadas
                             update.stats = presence_service::calcular_stats(&pessoas_turma);
@@ -268,6 +336,7 @@ async fn process_presence_action(
             update.success = false;
             update.message = match e {
                 AppError::SqlxError(_) => "Erro na base de dados.".to_string(),
+                AppError::NotFound(msg) | AppError::EmManutencao(msg) => msg,
                 _ => "Erro desconhecido ao marcar presença.".to_string(),
             };
             // Tenta buscar stats mesmo assim? Ou deixa default? Vamos deixar default.
@@ -278,16 +347,16 @@ async fn process_presence_action(
 
 /// Função auxiliar para formatar a info de presença para HTML (usado no broadcast).
 fn format_presence_info_html(pessoa: &PresencePerson) -> (String, String) {
-    let format_single = |dt_opt: &Option<DateTime<Local>>, op_opt: &Option<String>| -> String {
+    let format_single = |dt_opt: &Option<DateTime<Utc>>, op_opt: &Option<String>| -> String {
         match (dt_opt, op_opt) {
             (Some(dt), Some(op)) => format!(
                 r#"<span class="datetime">{}</span><span class="operator">{}</span>"#,
-                dt.format("%d/%m %H:%M"),
+                tempo::local(dt).format("%d/%m %H:%M"),
                 op // Assume que op é o ID ou nome
             ),
             (Some(dt), None) => format!(
                 r#"<span class="datetime">{}</span><span class="operator">?</span>"#, // Operador desconhecido
-                dt.format("%d/%m %H:%M")
+                tempo::local(dt).format("%d/%m %H:%M")
             ),
             _ => "---".to_string(), // Sem data
         }
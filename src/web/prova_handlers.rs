@@ -0,0 +1,138 @@
+// src/web/prova_handlers.rs
+//! Calendário das provas (/admin/provas): a administração regista as provas de cada turma, que tiram os
+//! seus utilizadores da escala na véspera e no dia, e apaga as canceladas (com as indisponibilidades).
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, horario_service, prova_service},
+    state::AppState,
+    templates::AdminProvasPage,
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo da disciplina.
+const MAX_DISCIPLINA: usize = 120;
+/// Tamanho máximo das observações.
+const MAX_OBSERVACOES: usize = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct ProvaForm {
+    #[serde(default)]
+    turma: String,
+    #[serde(default)]
+    data: String,
+    #[serde(default)]
+    disciplina: String,
+    #[serde(default)]
+    observacoes: String,
+}
+
+/// Renderiza as próximas provas, as realizadas recentemente e o formulário de registo.
+async fn pagina_provas(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let hoje = tempo::hoje();
+    let template = AdminProvasPage {
+        proximas: prova_service::proximas(&state.db_leitura, organizacao_id, hoje).await?,
+        realizadas: prova_service::realizadas(&state.db_leitura, organizacao_id, hoje, prova_service::REALIZADAS_RECENTES).await?,
+        turmas: horario_service::turmas(&state.db_leitura, organizacao_id).await?,
+        hoje: hoje.to_string(),
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminProvasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /admin/provas - Calendário das provas
+pub async fn show_provas(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_provas(&state, atual.organizacao_id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /admin/provas - Regista uma prova e tira a turma da escala na véspera e no dia
+pub async fn handle_registar_prova(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<ProvaForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("turma", &form.turma, MAX_DISCIPLINA);
+    v.obrigatorio("disciplina", &form.disciplina, MAX_DISCIPLINA);
+    if form.observacoes.trim().chars().count() > MAX_OBSERVACOES {
+        v.erro("observacoes", format!("Máximo de {} caracteres.", MAX_OBSERVACOES));
+    }
+    let data = v.data("data", &form.data);
+    let resultado = match (v.resultado(), data) {
+        (Ok(()), Some(data)) => prova_service::registar(
+            &state.db_pool,
+            atual.organizacao_id,
+            form.turma.trim(),
+            data,
+            &form.disciplina,
+            &form.observacoes,
+            tempo::hoje(),
+            &atual.id,
+        )
+        .await
+        .map(|registada| (registada, data)),
+        (Err(e), _) => Err(e),
+        (Ok(()), None) => Err(AppError::validation("data", "Data inválida.")),
+    };
+    match resultado {
+        Ok((registada, data)) => {
+            let detalhes = format!("{} da turma {} em {} ({} fora da escala)", form.disciplina.trim(), form.turma.trim(), data, registada.bloqueados);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PROVA_REGISTADA, Some(&registada.id.to_string()), Some(&detalhes)).await;
+            let mut mensagem = format!(
+                "Prova de {} da turma {} registada para {}: {} utilizador(es) fora da escala na véspera e no dia.",
+                form.disciplina.trim(), form.turma.trim(), data.format("%d/%m"), registada.bloqueados
+            );
+            // A escala desses dias já foi gerada: as indisponibilidades só contam na próxima geração
+            if !registada.ja_escalados.is_empty() {
+                mensagem.push_str(&format!(
+                    " Já estavam escalados nesses dias: {}. Regenere a escala ou troque os serviços.",
+                    registada.ja_escalados.join(", ")
+                ));
+            }
+            Ok(flash::redirect_success(&session, "/admin/provas", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("turma", form.turma)
+                .com_valor("data", form.data)
+                .com_valor("disciplina", form.disciplina)
+                .com_valor("observacoes", form.observacoes);
+            pagina_provas(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /admin/provas/{id}/remover - Apaga uma prova e as indisponibilidades que criou
+pub async fn handle_remover_prova(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    let prova = prova_service::remover(&state.db_pool, atual.organizacao_id, id).await?;
+    let detalhes = format!("{} da turma {} em {} ({} indisponibilidades apagadas)", prova.disciplina, prova.turma, prova.data, prova.bloqueados);
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_PROVA_APAGADA, Some(&id.to_string()), Some(&detalhes)).await;
+    let mensagem = format!("Prova de {} da turma {} apagada; a turma volta a estar disponível para a escala.", prova.disciplina, prova.turma);
+    Ok(flash::redirect_success(&session, "/admin/provas", mensagem).await.into_response())
+}
@@ -0,0 +1,194 @@
+// src/web/quarto_handlers.rs
+//! Administração do alojamento (/admin/quartos): os quartos por corredor, as camas e a atribuição
+//! (ou mudança) de quarto de cada utilizador.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, quarto_service},
+    state::AppState,
+    templates::AdminQuartosPage,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do nome de um quarto e do corredor.
+const MAX_NOME: usize = 30;
+
+#[derive(Deserialize, Debug)]
+pub struct QuartoForm {
+    #[serde(default)]
+    nome: String, // Só na criação
+    #[serde(default)]
+    corredor: String,
+    capacidade: i64,
+    ativo: Option<String>, // Checkbox (só na alteração): presente = marcada
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AtribuicaoForm {
+    #[serde(default)]
+    user: String,
+    quarto_id: i64,
+    #[serde(default)]
+    cama: String, // Vazio = primeira cama livre
+}
+
+/// Renderiza os quartos (com os erros do formulário de novo quarto, se houver).
+async fn pagina_quartos(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = AdminQuartosPage {
+        quartos: quarto_service::listar(&state.db_leitura, organizacao_id).await?,
+        sem_quarto: quarto_service::sem_quarto(&state.db_leitura, organizacao_id).await?,
+        form,
+        max_capacidade: quarto_service::MAX_CAPACIDADE,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template AdminQuartosPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /admin/quartos - Quartos, ocupantes e utilizadores sem quarto
+pub async fn show_admin_quartos(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_quartos(&state, atual.organizacao_id, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /admin/quartos - Cria um quarto
+pub async fn handle_criar_quarto(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<QuartoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("nome", &form.nome, MAX_NOME);
+    if form.corredor.trim().chars().count() > MAX_NOME {
+        v.erro("corredor", format!("Máximo de {} caracteres.", MAX_NOME));
+    }
+    v.intervalo("capacidade", form.capacidade, 1, quarto_service::MAX_CAPACIDADE);
+    let resultado = match v.resultado() {
+        Ok(()) => quarto_service::criar(&state.db_pool, atual.organizacao_id, &form.nome, &form.corredor, form.capacidade).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!("Quarto {} ({} camas, corredor '{}')", form.nome.trim(), form.capacidade, form.corredor.trim());
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_QUARTO_CRIADO, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/admin/quartos", format!("Quarto {} criado.", form.nome.trim())).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("nome", form.nome)
+                .com_valor("corredor", form.corredor)
+                .com_valor("capacidade", form.capacidade.to_string());
+            pagina_quartos(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /admin/quartos/{id} - Altera o corredor, as camas ou o estado de um quarto
+pub async fn handle_atualizar_quarto(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<QuartoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    if form.corredor.trim().chars().count() > MAX_NOME {
+        v.erro("corredor", format!("Máximo de {} caracteres.", MAX_NOME));
+    }
+    v.intervalo("capacidade", form.capacidade, 1, quarto_service::MAX_CAPACIDADE);
+    let ativo = form.ativo.is_some();
+    let resultado = match v.resultado() {
+        Ok(()) => quarto_service::atualizar(&state.db_pool, atual.organizacao_id, id, &form.corredor, form.capacidade, ativo).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(nome) => {
+            let detalhes = format!(
+                "Quarto {}: {} camas, corredor '{}', {}",
+                nome,
+                form.capacidade,
+                form.corredor.trim(),
+                if ativo { "ativo" } else { "inativo" }
+            );
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_QUARTO_ALTERADO, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/admin/quartos", format!("Quarto {} atualizado.", nome)).await.into_response())
+        }
+        Err(e @ AppError::Validation(_)) => Ok(flash::redirect_error(&session, "/admin/quartos", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /admin/quartos/atribuir - Atribui (ou muda) o quarto e a cama de um utilizador
+pub async fn handle_atribuir_quarto(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<AtribuicaoForm>,
+) -> AppResult<Response> {
+    let user_id = form.user.trim();
+    let mut v = Validador::default();
+    v.obrigatorio("user", user_id, 10);
+    let cama = match form.cama.trim() {
+        "" => None,
+        c => match c.parse::<i64>() {
+            Ok(c) => Some(c),
+            Err(_) => {
+                v.erro("cama", "Cama inválida.");
+                None
+            }
+        },
+    };
+    let resultado = match v.resultado() {
+        Ok(()) => quarto_service::atribuir(&state.db_pool, atual.organizacao_id, user_id, form.quarto_id, cama, &atual.id).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok((quarto, cama, anterior)) => {
+            let detalhes = match &anterior {
+                Some(anterior) => format!("Quarto {} -> {} (cama {})", anterior, quarto, cama),
+                None => format!("Quarto {} (cama {})", quarto, cama),
+            };
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_QUARTO_ATRIBUIDO, Some(user_id), Some(&detalhes)).await;
+            let mensagem = format!("{} no quarto {}, cama {}.", user_id, quarto, cama);
+            Ok(flash::redirect_success(&session, "/admin/quartos", mensagem).await.into_response())
+        }
+        Err(e @ (AppError::Validation(_) | AppError::Conflict(_))) => {
+            Ok(flash::redirect_error(&session, "/admin/quartos", e.user_message()).await.into_response())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /admin/quartos/ocupantes/{user_id}/remover - Retira um utilizador do quarto
+pub async fn handle_remover_ocupante(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(user_id): Path<String>,
+) -> AppResult<Response> {
+    match quarto_service::remover(&state.db_pool, atual.organizacao_id, &user_id).await? {
+        Some(quarto) => {
+            let detalhes = format!("Retirado do quarto {}", quarto);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_QUARTO_ATRIBUIDO, Some(&user_id), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/admin/quartos", format!("{} retirado do quarto {}.", user_id, quarto)).await.into_response())
+        }
+        None => Ok(flash::redirect_error(&session, "/admin/quartos", format!("{} não tem quarto.", user_id)).await.into_response()),
+    }
+}
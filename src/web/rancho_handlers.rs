@@ -0,0 +1,333 @@
+// src/web/rancho_handlers.rs
+//! Arranchamento (/arranchamento, todos os utilizadores) e página do rancheiro (/rancho, permissão "rancho"):
+//! previsão do dia por refeição e turma, exportação em CSV, refeições com as horas de corte e cardápio semanal.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{arranchamento_service, audit_service, cardapio_service},
+    state::AppState,
+    templates::{ArranchamentoPage, CardapioPage, RanchoPage},
+    tempo,
+    validation::{validar, FormState, Validador, Validate},
+    web::{admin_handlers::csv_campo, flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::{Duration, NaiveDate};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use tower_sessions::Session;
+
+#[derive(Deserialize, Debug)]
+pub struct ArranchamentoForm {
+    // Cada checkbox marcada envia "YYYY-MM-DD|refeicao_id"
+    #[serde(default)]
+    marcadas: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RanchoQuery {
+    data: Option<String>, // Por omissão, amanhã
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CardapioQuery {
+    semana: Option<String>, // Qualquer dia da semana; por omissão, a atual
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RefeicaoForm {
+    #[serde(default)]
+    nome: String,
+    ordem: i64,
+    corte_dias: i64,
+    corte_hora: String,
+    ativa: Option<String>, // Checkbox (só na alteração): presente = marcada
+}
+
+impl Validate for RefeicaoForm {
+    fn validate(&self, v: &mut Validador) {
+        v.intervalo("ordem", self.ordem, 0, 99);
+        v.intervalo("corte_dias", self.corte_dias, 0, arranchamento_service::MAX_CORTE_DIAS);
+        v.hora("corte_hora", &self.corte_hora);
+    }
+}
+
+/// Dia pedido na página do rancho (amanhã, se não indicado ou inválido).
+fn dia_pedido(data: Option<&str>) -> NaiveDate {
+    data.and_then(|d| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").ok())
+        .unwrap_or_else(|| tempo::hoje() + Duration::days(1))
+}
+
+// --- ARRANCHAMENTO (utilizador) ---
+
+/// Handler para GET /arranchamento - Refeições dos próximos dias, para marcar
+pub async fn show_arranchamento(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    let (refeicoes, dias) = arranchamento_service::marcacoes_user(&state.db_leitura, atual.organizacao_id, &atual.id).await?;
+    let template = ArranchamentoPage {
+        refeicoes,
+        dias,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template ArranchamentoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /arranchamento - Grava as refeições marcadas (as já fechadas não mudam)
+pub async fn handle_arranchamento(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<ArranchamentoForm>,
+) -> AppResult<Response> {
+    let marcadas: HashSet<(NaiveDate, i64)> = form
+        .marcadas
+        .iter()
+        .filter_map(|valor| {
+            let (data, refeicao_id) = valor.split_once('|')?;
+            Some((NaiveDate::parse_from_str(data, "%Y-%m-%d").ok()?, refeicao_id.parse().ok()?))
+        })
+        .collect();
+    let (acrescentadas, retiradas) =
+        arranchamento_service::definir_marcacoes(&state.db_pool, atual.organizacao_id, &atual.id, &marcadas).await?;
+    let mensagem = match (acrescentadas, retiradas) {
+        (0, 0) => "Nenhuma alteração.".to_string(),
+        (a, r) => format!("Arranchamento guardado ({} refeição(ões) marcada(s), {} desmarcada(s)).", a, r),
+    };
+    Ok(flash::redirect_success(&session, "/arranchamento", mensagem).await.into_response())
+}
+
+// --- RANCHO (rancheiro) ---
+
+/// Renderiza a página do rancho para o dia `data` (com os erros do formulário de nova refeição, se houver).
+async fn pagina_rancho(
+    state: &AppState,
+    atual: &CurrentUser,
+    data: NaiveDate,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let template = RanchoPage {
+        previsao: arranchamento_service::previsao(&state.db_leitura, atual.organizacao_id, data).await?,
+        refeicoes: arranchamento_service::listar_refeicoes(&state.db_leitura, atual.organizacao_id, false).await?,
+        max_corte_dias: arranchamento_service::MAX_CORTE_DIAS,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template RanchoPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /rancho - Previsão do dia por refeição e turma, e refeições da organização
+pub async fn show_rancho(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<RanchoQuery>,
+    flash: Flash,
+) -> AppResult<Response> {
+    let data = dia_pedido(params.data.as_deref());
+    pagina_rancho(&state, &atual, data, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para GET /rancho/previsao.csv - Previsão do dia em CSV (uma linha por refeição, uma coluna por turma)
+pub async fn handle_previsao_csv(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<RanchoQuery>,
+) -> AppResult<Response> {
+    let data = dia_pedido(params.data.as_deref());
+    let previsao = arranchamento_service::previsao(&state.db_leitura, atual.organizacao_id, data).await?;
+
+    let mut cabecalho = vec!["data".to_string(), "refeicao".to_string(), "corte".to_string()];
+    cabecalho.extend(previsao.turmas.iter().map(|ano| format!("{}º ano", ano)));
+    cabecalho.push("total".to_string());
+
+    // BOM UTF-8: o Excel abre os acentos corretamente
+    let mut csv = String::from("\u{feff}");
+    csv.push_str(&cabecalho.join(","));
+    csv.push_str("\r\n");
+    for linha in &previsao.refeicoes {
+        let mut campos = vec![previsao.data.clone(), csv_campo(&linha.refeicao.nome), linha.corte.clone()];
+        campos.extend(linha.por_turma.iter().map(|n| n.to_string()));
+        campos.push(linha.total.to_string());
+        csv.push_str(&campos.join(","));
+        csv.push_str("\r\n");
+    }
+
+    let disposicao = format!("attachment; filename=\"previsao_rancho_{}.csv\"", previsao.data);
+    Ok((
+        [(header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()), (header::CONTENT_DISPOSITION, disposicao)],
+        csv,
+    )
+        .into_response())
+}
+
+/// Handler para POST /rancho/refeicoes - Cria uma refeição
+pub async fn handle_criar_refeicao(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<RefeicaoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("nome", &form.nome, 40);
+    form.validate(&mut v);
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            arranchamento_service::criar_refeicao(
+                &state.db_pool, atual.organizacao_id, &form.nome, form.ordem, form.corte_dias, &form.corte_hora,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(id) => {
+            let detalhes = format!("{} (corte: {} dia(s) antes, {})", form.nome.trim(), form.corte_dias, form.corte_hora.trim());
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_REFEICAO_CRIADA, Some(&id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/rancho", format!("Refeição '{}' criada.", form.nome.trim())).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("nome", form.nome)
+                .com_valor("ordem", form.ordem.to_string())
+                .com_valor("corte_dias", form.corte_dias.to_string())
+                .com_valor("corte_hora", form.corte_hora);
+            pagina_rancho(&state, &atual, dia_pedido(None), StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /rancho/refeicoes/{id} - Altera a ordem, a hora de corte ou o estado de uma refeição
+pub async fn handle_atualizar_refeicao(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<RefeicaoForm>,
+) -> AppResult<Response> {
+    if let Err(e) = validar(&form) {
+        return Ok(flash::redirect_error(&session, "/rancho", e.user_message()).await.into_response());
+    }
+    let ativa = form.ativa.is_some();
+    let nome = arranchamento_service::atualizar_refeicao(
+        &state.db_pool, atual.organizacao_id, id, form.ordem, form.corte_dias, &form.corte_hora, ativa,
+    )
+    .await?;
+    let detalhes = format!(
+        "{} (ordem {}, corte: {} dia(s) antes, {}, {})",
+        nome, form.ordem, form.corte_dias, form.corte_hora.trim(), if ativa { "ativa" } else { "inativa" }
+    );
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_REFEICAO_ALTERADA, Some(&id.to_string()), Some(&detalhes)).await;
+    Ok(flash::redirect_success(&session, "/rancho", format!("Refeição '{}' atualizada.", nome)).await.into_response())
+}
+
+// --- CARDÁPIO (rancheiro) ---
+
+/// Renderiza a página do cardápio da semana (com `erro`, o formulário volta com o que foi escrito).
+async fn pagina_cardapio(
+    state: &AppState,
+    atual: &CurrentUser,
+    semana: NaiveDate,
+    escrito: Option<(&cardapio_service::Pratos, String)>,
+    flash: Flash,
+) -> AppResult<Response> {
+    let mut cardapio = cardapio_service::semana(&state.db_leitura, atual.organizacao_id, semana, true).await?;
+    let (status, error_message) = match escrito {
+        Some((pratos, erro)) => {
+            for dia in &mut cardapio.dias {
+                let Some(data) = tempo::ler_data(&dia.data) else { continue };
+                for prato in &mut dia.pratos {
+                    if let Some(descricao) = pratos.get(&(data, prato.refeicao_id)) {
+                        prato.descricao = descricao.clone();
+                    }
+                }
+            }
+            (StatusCode::UNPROCESSABLE_ENTITY, Some(erro))
+        }
+        None => (StatusCode::OK, flash.error),
+    };
+    let inicio = cardapio_service::inicio_semana(semana);
+    let template = CardapioPage {
+        cardapio,
+        semana_anterior: (inicio - Duration::days(7)).to_string(),
+        semana_seguinte: (inicio + Duration::days(7)).to_string(),
+        historico: cardapio_service::historico(&state.db_leitura, atual.organizacao_id, cardapio_service::SEMANAS_HISTORICO).await?,
+        max_descricao: cardapio_service::MAX_DESCRICAO,
+        success_message: flash.success,
+        error_message,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template CardapioPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /rancho/cardapio - Cardápio de uma semana (por omissão, a atual) e semanas publicadas
+pub async fn show_cardapio(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<CardapioQuery>,
+    flash: Flash,
+) -> AppResult<Response> {
+    let semana = params.semana.as_deref().and_then(tempo::ler_data).unwrap_or_else(tempo::hoje);
+    pagina_cardapio(&state, &atual, semana, None, flash).await
+}
+
+/// Handler para POST /rancho/cardapio - Publica os pratos da semana (campos "prato|YYYY-MM-DD|refeicao_id")
+pub async fn handle_cardapio(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(campos): Form<HashMap<String, String>>,
+) -> AppResult<Response> {
+    let Some(semana) = campos.get("semana").and_then(|s| tempo::ler_data(s)) else {
+        return Ok(flash::redirect_error(&session, "/rancho/cardapio", "Semana inválida.").await.into_response());
+    };
+    let inicio = cardapio_service::inicio_semana(semana);
+    let pratos: cardapio_service::Pratos = campos
+        .iter()
+        .filter_map(|(campo, descricao)| {
+            let (data, refeicao_id) = campo.strip_prefix("prato|")?.split_once('|')?;
+            Some(((tempo::ler_data(data)?, refeicao_id.parse().ok()?), descricao.trim().to_string()))
+        })
+        .collect();
+
+    if pratos.values().any(|d| d.chars().count() > cardapio_service::MAX_DESCRICAO) {
+        let erro = format!("Cada prato pode ter no máximo {} caracteres.", cardapio_service::MAX_DESCRICAO);
+        return pagina_cardapio(&state, &atual, inicio, Some((&pratos, erro)), Flash::default()).await;
+    }
+
+    let (gravados, retirados) =
+        cardapio_service::publicar(&state.db_pool, atual.organizacao_id, inicio, &pratos, &atual.id).await?;
+    let destino = format!("/rancho/cardapio?semana={}", inicio);
+    if gravados + retirados == 0 {
+        return Ok(flash::redirect_success(&session, &destino, "Nenhuma alteração.").await.into_response());
+    }
+    let detalhes = format!("Semana de {}: {} prato(s) gravado(s), {} retirado(s)", inicio, gravados, retirados);
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_CARDAPIO_PUBLICADO, Some(&inicio.to_string()), Some(&detalhes)).await;
+    let mensagem = format!("Cardápio da semana de {} publicado ({} prato(s) gravado(s), {} retirado(s)).", tempo::data_curta(inicio), gravados, retirados);
+    Ok(flash::redirect_success(&session, &destino, mensagem).await.into_response())
+}
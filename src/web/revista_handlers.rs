@@ -0,0 +1,328 @@
+// src/web/revista_handlers.rs
+//! Revistas aos quartos (/revistas, permissão "revista"): o registo de cada revista com a lista de
+//! verificação, as listas e os seus itens, e as estatísticas por turma (ordem de mérito semanal) com
+//! as reincidências.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, cardapio_service, quarto_service, revista_service},
+    state::AppState,
+    templates::{RevistaEstatisticasPage, RevistaListasPage, RevistasPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do nome de uma lista e da descrição de um item.
+const MAX_NOME: usize = 80;
+/// Tamanho máximo da identificação do quarto.
+const MAX_QUARTO: usize = 20;
+/// Tamanho máximo das observações de uma revista.
+const MAX_OBSERVACOES: usize = 1000;
+
+#[derive(Deserialize, Debug)]
+pub struct RevistasQuery {
+    lista: Option<i64>, // Por omissão, a primeira lista ativa
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EstatisticasQuery {
+    de: Option<String>,  // Por omissão, a segunda-feira desta semana
+    ate: Option<String>, // Por omissão, hoje
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RevistaForm {
+    lista_id: i64,
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    quarto: String,
+    #[serde(default)]
+    data: String,
+    #[serde(default)]
+    falhou: Vec<i64>, // Checkboxes dos itens em falta
+    #[serde(default)]
+    observacoes: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListaForm {
+    #[serde(default)]
+    nome: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ItemForm {
+    #[serde(default)]
+    descricao: String,
+    pontos: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AtivoForm {
+    ativo: Option<String>, // Checkbox: presente = marcada
+}
+
+/// Renderiza a página das revistas: o formulário com a lista `lista_id` e as revistas recentes.
+async fn pagina_revistas(
+    state: &AppState,
+    atual: &CurrentUser,
+    lista_id: Option<i64>,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let listas = revista_service::listar_listas(&state.db_leitura, atual.organizacao_id, true).await?;
+    let lista = lista_id
+        .and_then(|id| listas.iter().find(|l| l.id == id))
+        .or_else(|| listas.first())
+        .cloned();
+    let template = RevistasPage {
+        listas,
+        lista,
+        recentes: revista_service::recentes(&state.db_leitura, atual.organizacao_id, revista_service::REVISTAS_RECENTES).await?,
+        hoje: tempo::hoje().to_string(),
+        form,
+        max_quarto: MAX_QUARTO,
+        max_observacoes: MAX_OBSERVACOES,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template RevistasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /revistas?lista= - Registo de revistas e revistas recentes
+pub async fn show_revistas(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<RevistasQuery>,
+) -> AppResult<Response> {
+    pagina_revistas(&state, &atual, params.lista, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /revistas - Regista uma revista (pontuação e reincidências na mensagem)
+pub async fn handle_registar_revista(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<RevistaForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("user", &form.user, 10);
+    // Sem quarto indicado, vale o atribuído ao utilizador (alojamento)
+    let quarto = match form.quarto.trim() {
+        "" if !form.user.trim().is_empty() => quarto_service::quarto_de(&state.db_leitura, atual.organizacao_id, form.user.trim()).await?,
+        q => Some(q.to_string()).filter(|q| !q.is_empty()),
+    };
+    match &quarto {
+        None => v.erro("quarto", "Indique o quarto (o utilizador não tem quarto atribuído)."),
+        Some(q) if q.chars().count() > MAX_QUARTO => v.erro("quarto", format!("Máximo de {} caracteres.", MAX_QUARTO)),
+        Some(_) => {}
+    }
+    let data = v.data("data", &form.data);
+    if let Some(data) = data.filter(|d| *d > tempo::hoje()) {
+        v.erro("data", format!("A data {} ainda não chegou.", data));
+    }
+    if form.observacoes.trim().chars().count() > MAX_OBSERVACOES {
+        v.erro("observacoes", format!("Máximo de {} caracteres.", MAX_OBSERVACOES));
+    }
+    let resultado = match (v.resultado(), data, quarto) {
+        (Ok(()), Some(data), Some(quarto)) => {
+            revista_service::registar(
+                &state.db_pool,
+                atual.organizacao_id,
+                form.lista_id,
+                form.user.trim(),
+                &quarto,
+                data,
+                &form.falhou,
+                &form.observacoes,
+                &atual.id,
+            )
+            .await
+        }
+        (Err(e), _, _) => Err(e),
+        (Ok(()), _, _) => Err(AppError::validation("data", "Data inválida.")),
+    };
+    let url = format!("/revistas?lista={}", form.lista_id);
+    match resultado {
+        Ok(revista) => {
+            let mut mensagem = format!(
+                "Revista #{} a {} registada: {}/{} pontos.",
+                revista.id,
+                form.user.trim(),
+                revista.pontuacao,
+                revista.maximo
+            );
+            if !revista.reincidencias.is_empty() {
+                let itens: Vec<String> = revista.reincidencias.iter().map(|(item, vezes)| format!("{} ({}x)", item, vezes)).collect();
+                mensagem.push_str(&format!(
+                    " Reincidência nos últimos {} dias: {}.",
+                    revista_service::JANELA_REINCIDENCIA_DIAS,
+                    itens.join(", ")
+                ));
+            }
+            Ok(flash::redirect_success(&session, &url, &mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("user", form.user)
+                .com_valor("quarto", form.quarto)
+                .com_valor("data", form.data)
+                .com_valor("observacoes", form.observacoes);
+            pagina_revistas(&state, &atual, Some(form.lista_id), StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /revistas/estatisticas?de=&ate= - Ordem de mérito por turma e reincidências
+pub async fn show_estatisticas_revistas(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<EstatisticasQuery>,
+) -> AppResult<Response> {
+    let ate = params.ate.as_deref().and_then(tempo::ler_data).unwrap_or_else(tempo::hoje);
+    let de: NaiveDate = params.de.as_deref().and_then(tempo::ler_data).unwrap_or_else(|| cardapio_service::inicio_semana(ate));
+    let (de, ate) = if de > ate { (ate, de) } else { (de, ate) };
+    let template = RevistaEstatisticasPage {
+        turmas: revista_service::estatisticas_turmas(&state.db_leitura, atual.organizacao_id, de, ate).await?,
+        reincidencias: revista_service::reincidencias(&state.db_leitura, atual.organizacao_id, ate).await?,
+        de: de.to_string(),
+        ate: ate.to_string(),
+        janela_dias: revista_service::JANELA_REINCIDENCIA_DIAS,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template RevistaEstatisticasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+// --- LISTAS DE VERIFICAÇÃO ---
+
+/// Renderiza as listas de verificação (todas, com os itens) e o formulário de nova lista.
+async fn pagina_listas(state: &AppState, atual: &CurrentUser, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = RevistaListasPage {
+        listas: revista_service::listar_listas(&state.db_leitura, atual.organizacao_id, false).await?,
+        form,
+        max_pontos: revista_service::MAX_PONTOS,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template RevistaListasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /revistas/listas - Listas de verificação
+pub async fn show_listas_revista(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_listas(&state, &atual, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /revistas/listas - Cria uma lista de verificação
+pub async fn handle_criar_lista_revista(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<ListaForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("nome", &form.nome, MAX_NOME);
+    let resultado = match v.resultado() {
+        Ok(()) => revista_service::criar_lista(&state.db_pool, atual.organizacao_id, &form.nome).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(id) => {
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_LISTA_REVISTA_CRIADA, Some(&id.to_string()), Some(form.nome.trim())).await;
+            let mensagem = format!("Lista '{}' criada; acrescente-lhe os itens.", form.nome.trim());
+            Ok(flash::redirect_success(&session, "/revistas/listas", &mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros).com_valor("nome", form.nome);
+            pagina_listas(&state, &atual, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /revistas/listas/{id}/itens - Acrescenta um item a uma lista
+pub async fn handle_adicionar_item_revista(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(lista_id): Path<i64>,
+    Form(form): Form<ItemForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("descricao", &form.descricao, MAX_NOME);
+    v.intervalo("pontos", form.pontos, 1, revista_service::MAX_PONTOS);
+    let resultado = match v.resultado() {
+        Ok(()) => revista_service::adicionar_item(&state.db_pool, atual.organizacao_id, lista_id, &form.descricao, form.pontos).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(lista) => {
+            let detalhes = format!("Lista '{}': item '{}' ({} pontos)", lista, form.descricao.trim(), form.pontos);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_LISTA_REVISTA_ALTERADA, Some(&lista_id.to_string()), Some(&detalhes)).await;
+            Ok(flash::redirect_success(&session, "/revistas/listas", "Item acrescentado.").await.into_response())
+        }
+        Err(e @ AppError::Validation(_)) => Ok(flash::redirect_error(&session, "/revistas/listas", e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /revistas/listas/{id}/ativa - Ativa ou desativa uma lista
+pub async fn handle_ativar_lista_revista(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(lista_id): Path<i64>,
+    Form(form): Form<AtivoForm>,
+) -> AppResult<Response> {
+    let ativa = form.ativo.is_some();
+    let lista = revista_service::definir_lista_ativa(&state.db_pool, atual.organizacao_id, lista_id, ativa).await?;
+    let detalhes = format!("Lista '{}' {}", lista, if ativa { "ativada" } else { "desativada" });
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_LISTA_REVISTA_ALTERADA, Some(&lista_id.to_string()), Some(&detalhes)).await;
+    Ok(flash::redirect_success(&session, "/revistas/listas", &format!("{}.", detalhes)).await.into_response())
+}
+
+/// Handler para POST /revistas/itens/{id}/ativo - Ativa ou desativa um item de uma lista
+pub async fn handle_ativar_item_revista(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(item_id): Path<i64>,
+    Form(form): Form<AtivoForm>,
+) -> AppResult<Response> {
+    let ativo = form.ativo.is_some();
+    let item = revista_service::definir_item_ativo(&state.db_pool, atual.organizacao_id, item_id, ativo).await?;
+    let detalhes = format!("Item '{}' {}", item, if ativo { "ativado" } else { "desativado" });
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_LISTA_REVISTA_ALTERADA, Some(&item_id.to_string()), Some(&detalhes)).await;
+    Ok(flash::redirect_success(&session, "/revistas/listas", &format!("{}.", detalhes)).await.into_response())
+}
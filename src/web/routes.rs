@@ -1,63 +1,382 @@
 // src/web/routes.rs
 use crate::{
+    services::permission_service,
     state::AppState,
     // Adicionar presence_handlers
-    web::{admin_handlers, auth_handlers, mw_auth, mw_admin, mw_presence, presence_handlers, user_handlers, escala_handlers},
+    web::{admin_handlers, api_auth_handlers, api_docs, api_handlers, api_v1_handlers, auth_handlers, estaticos, feed_handlers, graphql, mw_api, mw_auth, mw_admin, mw_erros, livro_handlers, loja_handlers, revista_handlers, cautela_handlers, claviculario_handlers, tfm_handlers, biblioteca_handlers, lavanderia_handlers, baixa_handlers, portaria_handlers, visitante_handlers, agenda_handlers, horario_handlers, documento_handlers, enquete_handlers, disciplina_handlers, antiguidade_handlers, prova_handlers, uniforme_handlers, sugestao_handlers, faxina_handlers, conceito_handlers, comitiva_handlers, chamada_handlers, enfermaria_handlers, quarto_handlers, mw_presence, mw_senha, presence_handlers, rancho_handlers, saude_handlers, user_handlers, escala_handlers},
 };
 use axum::{
-    middleware,
-    routing::{get, post},
+    extract::{DefaultBodyLimit, Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    routing::{delete, get, post},
     Router,
 };
+use tower_http::{limit::RequestBodyLimitLayer, timeout::TimeoutLayer};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
 pub fn create_router(app_state: AppState) -> Router {
 
     // --- Rotas Públicas --- (Mantido igual)
     let public_routes = Router::new()
         .route("/login", get(auth_handlers::show_login_form).post(auth_handlers::handle_login))
-        .route("/logout", get(auth_handlers::handle_logout))
+        .route("/logout", post(auth_handlers::handle_logout))
+        .route("/logout/todas", post(auth_handlers::handle_logout_todas))
+        // Login institucional (OIDC); redirecionam para /login se não estiver configurado
+        .route("/auth/oidc/login", get(auth_handlers::handle_oidc_login))
+        .route("/auth/oidc/callback", get(auth_handlers::handle_oidc_callback))
+        // Feed Atom da escala publicada (sessão ou ?token=, para os leitores de feeds)
+        .route("/feeds/escala.atom", get(feed_handlers::handle_feed_escala))
+        // Agenda institucional em iCal (sessão ou ?token=, para as aplicações de calendário)
+        .route("/feeds/agenda.ics", get(feed_handlers::handle_feed_agenda))
+        // Sondas para proxies/orquestradores: processo a correr / pronto (DB, migrações, sessões)
+        .route("/healthz", get(saude_handlers::healthz))
+        .route("/readyz", get(saude_handlers::readyz))
+        // CSS/JS/imagens (com Cache-Control e ETag)
+        .nest("/static", estaticos::router(&app_state.config.estaticos))
         .route("/", get(|| async { axum::response::Redirect::permanent("/login") }));
 
+    // --- Áreas da instância (todas as organizações) ---
+    // Roles, bloqueios, auditoria, webhooks, cópias, tarefas e dados: só super-admins
+    let instancia_routes = Router::new()
+        .route("/roles", get(admin_handlers::show_roles_page))
+        .route("/roles/matriz", post(admin_handlers::handle_save_role_matrix))
+        .route("/roles/create", post(admin_handlers::handle_create_role))
+        .route("/roles/{nome}/delete", post(admin_handlers::handle_delete_role))
+        .route("/bloqueios", get(admin_handlers::show_bloqueios_page))
+        .route("/bloqueios/bloquear", post(admin_handlers::handle_bloquear_conta))
+        .route("/bloqueios/ip/desbloquear", post(admin_handlers::handle_desbloquear_ip))
+        .route("/bloqueios/{user_id}/desbloquear", post(admin_handlers::handle_desbloquear_conta))
+        .route("/atributos", get(admin_handlers::show_atributos_page))
+        .route("/atributos/create", post(admin_handlers::handle_create_atributo))
+        .route("/atributos/{chave}/delete", post(admin_handlers::handle_delete_atributo))
+        .route("/logins", get(admin_handlers::show_logins_page))
+        .route("/audit", get(admin_handlers::show_audit_page))
+        .route("/webhooks", get(admin_handlers::show_webhooks_page))
+        .route("/webhooks/create", post(admin_handlers::handle_create_webhook))
+        .route("/webhooks/{id}/ativo", post(admin_handlers::handle_webhook_ativo))
+        .route("/webhooks/{id}/delete", post(admin_handlers::handle_delete_webhook))
+        .route("/webhooks/entregas/{id}/reenviar", post(admin_handlers::handle_reenviar_entrega))
+        .route("/backups", get(admin_handlers::show_backups_page))
+        .route("/backups/criar", post(admin_handlers::handle_criar_backup))
+        .route("/backups/{id}/download", get(admin_handlers::handle_download_backup))
+        .route("/tarefas", get(admin_handlers::show_tarefas_page))
+        .route("/tarefas/{nome}/executar", post(admin_handlers::handle_executar_tarefa))
+        .route("/retencao", get(admin_handlers::show_retencao_page))
+        .route("/dados", get(admin_handlers::show_dados_page))
+        .route("/dados/export.json", get(admin_handlers::handle_exportar_dados))
+        // (POST /dados/import está em upload_routes, com limites próprios)
+        .route_layer(middleware::from_fn(mw_admin::require_superadmin));
+
     // --- Rotas de Admin --- (Mantido igual)
-    // Exigem login E role admin
+    // Exigem login E permissão 'admin' (matriz de permissões)
     let admin_routes = Router::new()
+        .route("/", get(admin_handlers::show_admin_dashboard))
         .route("/users", get(admin_handlers::show_admin_users_page))
         .route("/users/create", post(admin_handlers::handle_create_user))
+        .route("/users/batch", post(admin_handlers::handle_batch_edit_users))
         .route("/users/change_password", post(admin_handlers::handle_change_password))
+        .route("/users/merge", post(admin_handlers::handle_merge_users))
+        .route("/users/export.csv", get(admin_handlers::handle_export_users_csv))
+        .route("/users/roster", get(admin_handlers::show_roster_page))
+        .route("/users/roster.pdf", get(admin_handlers::handle_roster_pdf))
+        .route("/antiguidade", get(antiguidade_handlers::show_antiguidade).post(antiguidade_handlers::handle_definir_antiguidade))
+        .route("/provas", get(prova_handlers::show_provas).post(prova_handlers::handle_registar_prova))
+        .route("/provas/{id}/remover", post(prova_handlers::handle_remover_prova))
         .route("/users/edit/{id}", // <-- MUDANÇA AQUI
             get(admin_handlers::show_edit_user_form)
             .post(admin_handlers::handle_edit_user)
         )
+        // (POST /users/{id}/foto/enviar está em upload_routes)
+        .route("/users/{id}/foto", get(admin_handlers::show_foto_user))
+        .route("/users/{id}/foto/remover", post(admin_handlers::handle_remover_foto_user))
+        .route("/users/{id}/sessoes", get(admin_handlers::show_sessoes_user_page))
+        .route("/users/{id}/sessoes/{sessao_id}/revogar", post(admin_handlers::handle_revogar_sessao_user))
+        .route("/users/{id}/sessoes/revogar_todas", post(admin_handlers::handle_revogar_sessoes_user))
+        .route("/temp_roles", get(admin_handlers::show_temp_roles_page))
+        .route("/temp_roles/grant", post(admin_handlers::handle_grant_temp_role))
+        .route("/temp_roles/{id}/revoke", post(admin_handlers::handle_revoke_temp_role))
+        .route("/grupos", get(admin_handlers::show_grupos_page))
+        .route("/grupos/create", post(admin_handlers::handle_create_grupo))
+        .route("/grupos/{id}", get(admin_handlers::show_grupo_page))
+        .route("/grupos/{id}/delete", post(admin_handlers::handle_delete_grupo))
+        .route("/grupos/{id}/membros/add", post(admin_handlers::handle_add_grupo_membros))
+        .route("/grupos/{id}/membros/{user_id}/remove", post(admin_handlers::handle_remove_grupo_membro))
+        .route("/grupos/{id}/postos", post(admin_handlers::handle_grupo_posto))
+        .route("/grupos/{id}/temp_role", post(admin_handlers::handle_grupo_temp_role))
+        .route("/rollover", get(admin_handlers::show_rollover_page).post(admin_handlers::handle_rollover))
+        .route("/contabilidade", get(admin_handlers::show_contabilidade_page))
+        .route("/contabilidade/reconciliar", post(admin_handlers::handle_reconciliar))
+        .route("/loja", get(loja_handlers::show_admin_loja))
+        .route("/loja/produtos", post(loja_handlers::handle_criar_produto))
+        .route("/loja/produtos/{id}", post(loja_handlers::handle_atualizar_produto))
+        .route("/loja/relatorio", get(loja_handlers::show_relatorio_loja))
+        .route("/loja/saldos", get(loja_handlers::show_saldos_negativos))
+        .route("/quartos", get(quarto_handlers::show_admin_quartos).post(quarto_handlers::handle_criar_quarto))
+        .route("/quartos/atribuir", post(quarto_handlers::handle_atribuir_quarto))
+        .route("/quartos/{id}", post(quarto_handlers::handle_atualizar_quarto))
+        .route("/quartos/ocupantes/{user_id}/remover", post(quarto_handlers::handle_remover_ocupante))
+        .merge(instancia_routes)
         // Aplica APENAS mw_admin aqui (mw_auth será aplicado no router pai)
         .route_layer(middleware::from_fn_with_state(
             app_state.clone(),
             mw_admin::require_admin,
         ));
 
+    // --- Organizações (super-admin) ---
+    let superadmin_routes = Router::new()
+        .route("/", get(admin_handlers::show_organizacoes_page))
+        .route("/organizacoes/create", post(admin_handlers::handle_create_organizacao))
+        .route("/organizacoes/{id}/ativa", post(admin_handlers::handle_organizacao_ativa))
+        .route("/organizacoes/{id}/entrar", post(admin_handlers::handle_entrar_organizacao))
+        .route("/manutencao", post(admin_handlers::handle_manutencao))
+        .route_layer(middleware::from_fn(mw_admin::require_superadmin));
+
     // *** ALTERADO: Criar router específico para Presença ***
     let presence_routes = Router::new()
         .route("/", get(presence_handlers::presence_page_handler)) // Rota base é /presence
         .route("/ws", get(presence_handlers::presence_websocket_handler)) // Rota é /presence/ws
+        .route("/pernoite", get(presence_handlers::pernoite_handler)) // Relatório de pernoite por quarto
+        .route("/visitantes", get(visitante_handlers::show_visitantes).post(visitante_handlers::handle_entrada_visitante)) // Visitantes na porta de armas
+        .route("/visitantes/{id}/saida", post(visitante_handlers::handle_saida_visitante))
+        .route("/visitantes/ws", get(visitante_handlers::visitantes_websocket_handler))
+        .route("/visitantes/export.csv", get(visitante_handlers::handle_export_visitantes))
         // Aplica APENAS mw_presence aqui (mw_auth será aplicado no router pai)
         .route_layer(middleware::from_fn_with_state(
             app_state.clone(),
             mw_presence::require_presence_access,
         ));
 
+    // --- Rancho (rancheiro: previsão do arranchamento e refeições) ---
+    let rancho_routes = Router::new()
+        .route("/", get(rancho_handlers::show_rancho))
+        .route("/previsao.csv", get(rancho_handlers::handle_previsao_csv))
+        .route("/refeicoes", post(rancho_handlers::handle_criar_refeicao))
+        .route("/refeicoes/{id}", post(rancho_handlers::handle_atualizar_refeicao))
+        .route("/cardapio", get(rancho_handlers::show_cardapio).post(rancho_handlers::handle_cardapio))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_RANCHO, state, request, next)
+            },
+        ));
+
+    // --- Loja (operador: ponto de venda, compras e conta corrente dos utilizadores) ---
+    let loja_routes = Router::new()
+        .route("/", get(loja_handlers::show_loja))
+        .route("/vendas", post(loja_handlers::handle_venda))
+        .route("/vendas/{id}/anular", post(loja_handlers::handle_anular_venda))
+        .route("/compras", get(loja_handlers::show_compras_loja))
+        .route("/conta", get(loja_handlers::show_conta_loja))
+        .route("/conta/carregar", post(loja_handlers::handle_carregar_conta))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_LOJA, state, request, next)
+            },
+        ));
+
+    // --- Livro de ocorrências (consulta; registar e fechar o dia exige "livro.escrever") ---
+    let livro_routes = Router::new()
+        .route("/", get(livro_handlers::show_livro))
+        .route("/pesquisa", get(livro_handlers::show_pesquisa_livro))
+        .route("/{data}/ocorrencias", post(livro_handlers::handle_registar_ocorrencia))
+        .route("/{data}/fechar", post(livro_handlers::handle_fechar_livro))
+        .route("/ocorrencias/{id}/corrigir", post(livro_handlers::handle_corrigir_ocorrencia))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_LIVRO, state, request, next)
+            },
+        ));
+
+    // --- Revistas aos quartos (registo, listas de verificação e estatísticas por turma) ---
+    let revista_routes = Router::new()
+        .route("/", get(revista_handlers::show_revistas).post(revista_handlers::handle_registar_revista))
+        .route("/estatisticas", get(revista_handlers::show_estatisticas_revistas))
+        .route("/listas", get(revista_handlers::show_listas_revista).post(revista_handlers::handle_criar_lista_revista))
+        .route("/listas/{id}/itens", post(revista_handlers::handle_adicionar_item_revista))
+        .route("/listas/{id}/ativa", post(revista_handlers::handle_ativar_lista_revista))
+        .route("/itens/{id}/ativo", post(revista_handlers::handle_ativar_item_revista))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_REVISTA, state, request, next)
+            },
+        ));
+
+    // --- Cautelas de material (entrega, devolução, atrasadas e catálogo) ---
+    let cautela_routes = Router::new()
+        .route("/", get(cautela_handlers::show_cautelas).post(cautela_handlers::handle_emitir_cautela))
+        .route("/{id}/devolver", get(cautela_handlers::show_devolver_cautela).post(cautela_handlers::handle_devolver_cautela))
+        .route("/atrasadas", get(cautela_handlers::show_cautelas_atrasadas))
+        .route("/registo", get(cautela_handlers::show_registo_cautelas))
+        .route("/material", get(cautela_handlers::show_material).post(cautela_handlers::handle_criar_material))
+        .route("/material/{id}", post(cautela_handlers::handle_atualizar_material))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_CAUTELA, state, request, next)
+            },
+        ));
+
+    // --- Claviculário (levantamento e devolução de chaves, atrasos, histórico e registo) ---
+    let claviculario_routes = Router::new()
+        .route("/", get(claviculario_handlers::show_claviculario).post(claviculario_handlers::handle_levantar_chave))
+        .route("/{id}/devolver", post(claviculario_handlers::handle_devolver_chave))
+        .route("/atrasadas", get(claviculario_handlers::show_chaves_atrasadas))
+        .route("/historico", get(claviculario_handlers::show_historico_chaves))
+        .route("/chaves", get(claviculario_handlers::show_chaves).post(claviculario_handlers::handle_criar_chave))
+        .route("/chaves/{id}", post(claviculario_handlers::handle_atualizar_chave))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_CLAVICULARIO, state, request, next)
+            },
+        ));
+
+    // --- TFM (resultados do teste físico e estatísticas por turma) ---
+    let tfm_routes = Router::new()
+        .route("/", get(tfm_handlers::show_tfm).post(tfm_handlers::handle_registar_tfm))
+        .route("/{id}/remover", post(tfm_handlers::handle_remover_tfm))
+        .route("/estatisticas", get(tfm_handlers::show_estatisticas_tfm))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_TFM, state, request, next)
+            },
+        ));
+
+    // --- Biblioteca (empréstimo e devolução de livros, histórico e catálogo) ---
+    let biblioteca_routes = Router::new()
+        .route("/", get(biblioteca_handlers::show_biblioteca).post(biblioteca_handlers::handle_emprestar_livro))
+        .route("/{id}/devolver", post(biblioteca_handlers::handle_devolver_livro))
+        .route("/historico", get(biblioteca_handlers::show_historico_biblioteca))
+        .route("/livros", get(biblioteca_handlers::show_livros).post(biblioteca_handlers::handle_criar_livro))
+        .route("/livros/{id}", post(biblioteca_handlers::handle_atualizar_livro))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_BIBLIOTECA, state, request, next)
+            },
+        ));
+
+    // --- Lavanderia (entrega, pronto e retirada dos sacos, relatório e histórico) ---
+    let lavanderia_routes = Router::new()
+        .route("/", get(lavanderia_handlers::show_lavanderia).post(lavanderia_handlers::handle_receber_saco))
+        .route("/{id}/pronto", post(lavanderia_handlers::handle_saco_pronto))
+        .route("/{id}/retirar", post(lavanderia_handlers::handle_retirar_saco))
+        .route("/relatorio", get(lavanderia_handlers::show_relatorio_lavanderia))
+        .route("/historico", get(lavanderia_handlers::show_historico_lavanderia))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_LAVANDERIA, state, request, next)
+            },
+        ));
+
+    // --- Baixas médicas (secção de saúde) ---
+    let baixa_routes = Router::new()
+        .route("/", get(baixa_handlers::show_baixas).post(baixa_handlers::handle_registar_baixa))
+        .route("/{id}/alta", post(baixa_handlers::handle_alta_baixa))
+        .route("/{id}/remover", post(baixa_handlers::handle_remover_baixa))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_BAIXAS, state, request, next)
+            },
+        ));
+
+    // --- Portaria (polícia: entradas e saídas de viaturas e entregas) ---
+    let portaria_routes = Router::new()
+        .route("/", get(portaria_handlers::show_portaria).post(portaria_handlers::handle_entrada_portaria))
+        .route("/{id}/saida", post(portaria_handlers::handle_saida_portaria))
+        .route("/export.csv", get(portaria_handlers::handle_export_portaria))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_PORTARIA, state, request, next)
+            },
+        ));
+
+    // Gestão da escala (gerar, publicar, aprovar trocas e errata): exige "escala.gerir"
+    let escala_gestao_routes = Router::new()
+        .route("/gerar_periodo", post(escala_handlers::handle_gerar_periodo))
+        .route("/publicar", post(escala_handlers::handle_publicar_periodo))
+        .route("/trocas/{id}/aprovar", post(escala_handlers::handle_aprovar_troca))
+        .route("/errata/{data}", post(escala_handlers::handle_errata))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            |state: State<AppState>, request: Request, next: Next| {
+                mw_admin::require_permission(permission_service::PERM_ESCALA_GERIR, state, request, next)
+            },
+        ));
+
     let escala_routes = Router::new()
-        // Gera a escala (JSON: { "data": "2025-10-25", "tipo": "RN" })
+        // Página ou JSON (Accept: application/json ou ?format=json)
         .route("/", get(escala_handlers::handle_pagina_escala))
-        // Vê a escala (URL: /escala/ver?data=2025-10-25)
         // Solicita troca (JSON: { "alocacao_id": "123", "substituto_id": "456", "motivo": "Motivo da Troca" })
-        .route("/gerar_periodo", post(escala_handlers::handle_gerar_periodo))
-        .route("/publicar", post(escala_handlers::handle_publicar_periodo))
         .route("/trocas/solicitar", post(escala_handlers::handle_solicitar_troca))
-        .route("/trocas/{id}/aprovar", post(escala_handlers::handle_aprovar_troca))
+        // A página verifica "escala.gerir" (redireciona com uma mensagem em vez de 403)
         .route("/admin", get(escala_handlers::handle_admin_escala_page))
-        .route("/errata/{data}", post(escala_handlers::handle_errata));
-        // Aqui você pode adicionar um middleware de Admin se quiser proteger estas ações
-        // .route_layer(middleware::from_fn_with_state(app_state.clone(), mw_admin::require_admin));
+        // Boletim do dia para impressão (uniforme, serviço e agenda)
+        .route("/{data}/boletim", get(escala_handlers::handle_boletim_dia))
+        // Um dia (HTML ou JSON, conforme Accept/?format=; as rotas estáticas acima têm prioridade)
+        .route("/{data}", get(escala_handlers::handle_dia_escala))
+        .merge(escala_gestao_routes);
+
+    // API JSON (consumida pelo JS das páginas); cada handler verifica a sua permissão
+    // Sessão OU token (Authorization: Bearer), como /api/v1
+    let api_routes = Router::new()
+        .route("/users/search", get(api_handlers::search_users))
+        // GraphQL só de leitura para relatórios (POST consulta, GET esquema)
+        .route("/graphql", get(graphql::mostrar_esquema).post(graphql::executar))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            mw_api::require_api_auth,
+        ));
 
+    // Autenticação da aplicação móvel (JWT + refresh token): públicas
+    let api_auth_routes = Router::new()
+        .route("/login", post(api_auth_handlers::login))
+        .route("/refresh", post(api_auth_handlers::refresh))
+        .route("/logout", post(api_auth_handlers::logout));
+
+    // Documentação da API v1: especificação OpenAPI e Swagger UI, só para administradores
+    let api_docs_routes = Router::from(
+        SwaggerUi::new("/api/docs").url("/api/openapi.json", api_docs::ApiDoc::openapi()),
+    )
+    .route_layer(middleware::from_fn_with_state(
+        app_state.clone(),
+        mw_admin::require_admin,
+    ));
+
+    // API JSON versionada para integrações: sessão OU token (Authorization: Bearer), erros em JSON
+    let api_v1_routes = Router::new()
+        .route("/me", get(api_v1_handlers::me))
+        .route("/users", get(api_v1_handlers::listar_users))
+        .route("/users/{id}", get(api_v1_handlers::obter_user))
+        .route("/escala", get(api_v1_handlers::listar_escala))
+        .route("/escala/gerar", post(api_v1_handlers::gerar_escala))
+        .route("/escala/publicar", post(api_v1_handlers::publicar_escala))
+        .route("/alocacoes", get(api_v1_handlers::listar_alocacoes))
+        .route("/trocas", get(api_v1_handlers::listar_trocas).post(api_v1_handlers::solicitar_troca))
+        .route("/trocas/{id}/resposta", post(api_v1_handlers::responder_troca))
+        .route("/trocas/{id}/aprovar", post(api_v1_handlers::aprovar_troca))
+        .route("/presenca", get(api_v1_handlers::listar_presenca))
+        .route("/presenca/{user_id}/saida", post(api_v1_handlers::marcar_saida))
+        .route("/presenca/{user_id}/retorno", post(api_v1_handlers::marcar_retorno))
+        .route("/indisponibilidades", get(api_v1_handlers::listar_indisponibilidades).post(api_v1_handlers::criar_indisponibilidade))
+        .route("/indisponibilidades/{id}", delete(api_v1_handlers::remover_indisponibilidade))
+        .route("/cardapio", get(api_v1_handlers::obter_cardapio))
+        .route("/cardapio/semanas", get(api_v1_handlers::listar_semanas_cardapio))
+        .route("/agenda", get(api_v1_handlers::listar_agenda))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            mw_api::require_api_auth,
+        ))
+        .fallback(api_v1_handlers::nao_encontrado);
 
     // --- Rotas Autenticadas (Combinando tudo) ---
     // Exigem *pelo menos* login
@@ -65,13 +384,132 @@ pub fn create_router(app_state: AppState) -> Router {
         // Rotas que exigem apenas login
         .route("/user", get(user_handlers::user_page_handler))
         .route("/user/responder_troca", post(user_handlers::handle_responder_troca))
+        .route("/user/sessoes/{id}/revogar", post(user_handlers::handle_revogar_sessao))
+        .route("/user/sessoes/revogar_outras", post(user_handlers::handle_revogar_outras_sessoes))
+        .route("/user/notificacoes", get(user_handlers::show_notificacoes))
+        .route("/user/notificacoes/lidas", post(user_handlers::handle_marcar_notificacoes_lidas))
+        .route("/user/notificacoes/{id}/lida", post(user_handlers::handle_marcar_notificacao_lida))
+        .route("/user/tokens", get(user_handlers::show_tokens).post(user_handlers::handle_criar_token))
+        .route("/user/tokens/{id}/revogar", post(user_handlers::handle_revogar_token))
+        .route("/user/telegram/ligar", post(user_handlers::handle_telegram_ligar))
+        .route("/user/telegram/desligar", post(user_handlers::handle_telegram_desligar))
+        .route("/user/emails", post(user_handlers::handle_emails_preferencia))
+        .route("/user/idioma", post(user_handlers::handle_idioma))
+        .route("/user/preferencias", get(user_handlers::show_preferencias).post(user_handlers::handle_preferencias))
+        .route("/user/compras", get(loja_handlers::show_minhas_compras))
+        .route("/user/conta", get(loja_handlers::show_minha_conta))
+        .route("/user/google/ligar", post(user_handlers::handle_google_ligar))
+        .route("/user/google/callback", get(user_handlers::handle_google_callback))
+        .route("/user/google/desligar", post(user_handlers::handle_google_desligar))
+        // Arranchamento (marcação das refeições pelo próprio)
+        .route("/arranchamento", get(rancho_handlers::show_arranchamento).post(rancho_handlers::handle_arranchamento))
+        // Agenda institucional (consulta por todos; criar/alterar/apagar exige "agenda")
+        .route("/agenda", get(agenda_handlers::show_agenda).post(agenda_handlers::handle_criar_evento))
+        .route("/agenda/{id}", post(agenda_handlers::handle_atualizar_evento))
+        .route("/agenda/{id}/remover", post(agenda_handlers::handle_remover_evento))
+        // Horário das aulas (consulta por todos; aulas e postos diurnos exigem "horario")
+        .route("/horario", get(horario_handlers::show_horario).post(horario_handlers::handle_criar_aula))
+        .route("/horario/postos", post(horario_handlers::handle_postos_diurnos))
+        .route("/horario/{id}", post(horario_handlers::handle_atualizar_aula))
+        .route("/horario/{id}/remover", post(horario_handlers::handle_remover_aula))
+        // Uniforme do dia (definido pelo chefe de dia ou pela administração: permissão "uniforme")
+        .route("/uniforme", get(uniforme_handlers::show_uniforme).post(uniforme_handlers::handle_definir_uniforme))
+        .route("/uniforme/{data}/remover", post(uniforme_handlers::handle_remover_uniforme))
+        // Repositório de documentos (consulta conforme as regras de cada pasta; gestão exige "documentos")
+        // (POST /documentos/enviar está em upload_routes)
+        .route("/documentos", get(documento_handlers::show_documentos))
+        .route("/documentos/pastas", post(documento_handlers::handle_criar_pasta))
+        .route("/documentos/pastas/{id}", post(documento_handlers::handle_atualizar_pasta))
+        .route("/documentos/pastas/{id}/remover", post(documento_handlers::handle_remover_pasta))
+        .route("/documentos/{id}/download", get(documento_handlers::handle_download_documento))
+        .route("/documentos/{id}/downloads", get(documento_handlers::show_downloads_documento))
+        .route("/documentos/{id}/remover", post(documento_handlers::handle_remover_documento))
+        // Enquetes (voto no painel por quem é visado; criar, resultados e exportação exigem "enquetes")
+        .route("/enquetes", get(enquete_handlers::show_enquetes).post(enquete_handlers::handle_criar_enquete))
+        .route("/enquetes/{id}", get(enquete_handlers::show_enquete))
+        .route("/enquetes/{id}/encerrar", post(enquete_handlers::handle_encerrar_enquete))
+        .route("/enquetes/{id}/export.csv", get(enquete_handlers::handle_export_enquete))
+        .route("/enquetes/{id}/votar", post(enquete_handlers::handle_votar_enquete))
+        // Processos disciplinares (FATD): registo e decisão exigem "disciplina"; o visado vê o seu e apresenta a defesa
+        .route("/disciplina", get(disciplina_handlers::show_disciplina).post(disciplina_handlers::handle_abrir_processo))
+        .route("/disciplina/{id}", get(disciplina_handlers::show_processo))
+        .route("/disciplina/{id}/defesa", post(disciplina_handlers::handle_defesa))
+        .route("/disciplina/{id}/sem-defesa", post(disciplina_handlers::handle_encerrar_defesa))
+        .route("/disciplina/{id}/decisao", post(disciplina_handlers::handle_decidir))
+        // Caixa de sugestões (envio por todos; triagem e resposta exigem "sugestoes")
+        .route("/sugestoes", get(sugestao_handlers::show_sugestoes).post(sugestao_handlers::handle_enviar_sugestao))
+        .route("/sugestoes/triagem", get(sugestao_handlers::show_triagem))
+        .route("/sugestoes/{id}/estado", post(sugestao_handlers::handle_estado_sugestao))
+        .route("/sugestoes/{id}/responder", post(sugestao_handlers::handle_responder_sugestao))
+        // Escala de faxina (consulta por todos; áreas, geração, publicação e errata exigem "escala.gerir")
+        .route("/faxina", get(faxina_handlers::show_faxina))
+        .route("/faxina/gerir", get(faxina_handlers::show_gerir_faxina))
+        .route("/faxina/areas", post(faxina_handlers::handle_criar_area))
+        .route("/faxina/areas/{id}", post(faxina_handlers::handle_atualizar_area))
+        .route("/faxina/gerar", post(faxina_handlers::handle_gerar_faxina))
+        .route("/faxina/{semana}/publicar", post(faxina_handlers::handle_publicar_faxina))
+        .route("/faxina/{semana}/errata", post(faxina_handlers::handle_errata_faxina))
+        // Conceito (cada um vê o seu; lançamentos e classificação por turma exigem "conceito")
+        .route("/conceito", get(conceito_handlers::show_conceito).post(conceito_handlers::handle_registar_conceito))
+        .route("/conceito/classificacao", get(conceito_handlers::show_classificacao))
+        .route("/conceito/{id}/remover", post(conceito_handlers::handle_remover_conceito))
+        // Comitivas (permissão "comitivas"; membros fora da escala e da presença enquanto abertas)
+        .route("/comitivas", get(comitiva_handlers::show_comitivas).post(comitiva_handlers::handle_criar_comitiva))
+        .route("/comitivas/{id}", get(comitiva_handlers::show_comitiva))
+        .route("/comitivas/{id}/membros", post(comitiva_handlers::handle_adicionar_membros))
+        .route("/comitivas/{id}/membros/{user_id}/remover", post(comitiva_handlers::handle_remover_membro))
+        .route("/comitivas/{id}/encerrar", post(comitiva_handlers::handle_encerrar_comitiva))
+        // Plano de chamada (contacto e confirmação pelo próprio; planos, cascata e exercícios exigem "chamada")
+        .route("/chamada", get(chamada_handlers::show_chamada))
+        .route("/chamada/contacto", post(chamada_handlers::handle_guardar_contacto))
+        .route("/chamada/planos", post(chamada_handlers::handle_criar_plano))
+        .route("/chamada/planos/{id}", get(chamada_handlers::show_plano))
+        .route("/chamada/planos/{id}/cascata", post(chamada_handlers::handle_definir_cascata))
+        .route("/chamada/planos/{id}/regenerar", post(chamada_handlers::handle_regenerar_plano))
+        .route("/chamada/planos/{id}/apagar", post(chamada_handlers::handle_apagar_plano))
+        .route("/chamada/planos/{id}/imprimir", get(chamada_handlers::show_imprimir_plano))
+        .route("/chamada/planos/{id}/exercicio", post(chamada_handlers::handle_iniciar_exercicio))
+        .route("/chamada/exercicios/{id}", get(chamada_handlers::show_exercicio))
+        .route("/chamada/exercicios/{id}/confirmar", post(chamada_handlers::handle_confirmar))
+        .route("/chamada/exercicios/{id}/encerrar", post(chamada_handlers::handle_encerrar_exercicio))
+        // Enfermaria (marcação e cancelamento pelo próprio; agenda e vagas exigem "enfermaria")
+        .route("/enfermaria", get(enfermaria_handlers::show_enfermaria))
+        .route("/enfermaria/marcar", post(enfermaria_handlers::handle_marcar_consulta))
+        .route("/enfermaria/{id}/cancelar", post(enfermaria_handlers::handle_cancelar_consulta))
+        .route("/enfermaria/agenda", get(enfermaria_handlers::show_agenda))
+        .route("/enfermaria/agenda/{id}/chegada", post(enfermaria_handlers::handle_chegada))
+        .route("/enfermaria/agenda/{id}/falta", post(enfermaria_handlers::handle_falta))
+        .route("/enfermaria/agenda/{id}/cancelar", post(enfermaria_handlers::handle_cancelar_agenda))
+        .route("/enfermaria/agenda/{id}/concluir", post(enfermaria_handlers::handle_concluir))
+        .route("/enfermaria/vagas", post(enfermaria_handlers::handle_abrir_vagas))
+        .route("/enfermaria/vagas/{id}/remover", post(enfermaria_handlers::handle_remover_vaga))
+        // Eventos em tempo real (SSE): notificações, estado da escala e trocas
+        .route("/events", get(user_handlers::handle_eventos))
         // Adicionar outras rotas autenticadas gerais aqui...
 
         // Aninha as rotas de admin sob /admin
         .nest("/admin", admin_routes)
+        .nest("/superadmin", superadmin_routes)
         .nest("/escala", escala_routes)
+        .merge(api_docs_routes)
         // *** ALTERADO: Aninha as rotas de presença sob /presence ***
         .nest("/presence", presence_routes)
+        .nest("/rancho", rancho_routes)
+        .nest("/loja", loja_routes)
+        .nest("/livro", livro_routes)
+        .nest("/revistas", revista_routes)
+        .nest("/cautelas", cautela_routes)
+        .nest("/claviculario", claviculario_routes)
+        .nest("/tfm", tfm_routes)
+        .nest("/biblioteca", biblioteca_routes)
+        .nest("/lavanderia", lavanderia_routes)
+        .nest("/baixas", baixa_routes)
+        .nest("/portaria", portaria_routes)
+
+        // Senha expirada (política de validade): tudo o que está ACIMA redireciona para /user/senha
+        .route_layer(middleware::from_fn(mw_senha::exigir_senha_valida))
+        // Alteração da senha pelo próprio (fora da verificação acima, para poder sair da senha expirada)
+        .route("/user/senha", get(user_handlers::show_alterar_senha).post(user_handlers::handle_alterar_senha))
 
         // Aplica o middleware geral require_auth a TODAS as rotas
         // definidas ACIMA neste router (incluindo as aninhadas /admin/* e /presence/*)
@@ -80,9 +518,45 @@ pub fn create_router(app_state: AppState) -> Router {
             mw_auth::require_auth,
         ));
 
-    // --- Router Final --- (Mantido igual)
+    // --- Envio de ficheiros ---
+    // Limites de tamanho e de tempo maiores (config `pedidos.upload_*`)
+    let pedidos = &app_state.config.pedidos;
+    let upload_routes = Router::new()
+        // O pacote de dados vem no corpo (JSON); mesmas verificações que as áreas da instância (super-admin)
+        .route(
+            "/admin/dados/import",
+            post(admin_handlers::handle_importar_dados)
+                .route_layer(middleware::from_fn(mw_admin::require_superadmin)),
+        )
+        // PDF do repositório de documentos no corpo (o handler verifica a permissão "documentos")
+        .route("/documentos/enviar", post(documento_handlers::handle_enviar_documento))
+        // Fotografia do utilizador (JPEG) no corpo; mesma verificação que as restantes rotas de admin
+        .route(
+            "/admin/users/{id}/foto/enviar",
+            post(admin_handlers::handle_enviar_foto_user)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), mw_admin::require_admin)),
+        )
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(pedidos.upload_max_bytes()))
+        .layer(TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, pedidos.upload_timeout()))
+        .route_layer(middleware::from_fn(mw_senha::exigir_senha_valida))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), mw_auth::require_auth));
+
+    // --- Router Final ---
     Router::new()
         .merge(public_routes)
         .merge(authenticated_routes)
+        .nest("/api", api_routes)
+        .nest("/api/auth", api_auth_routes)
+        .nest("/api/v1", api_v1_routes)
+        // Limites de todas as rotas ACIMA (config `pedidos`): corpo maior é recusado logo pelo Content-Length (413)
+        // e um pedido que não termina a tempo (cliente parado, query presa) responde 408 e liberta a ligação à DB.
+        // O limite do axum (2 MB nos extractors) é substituído por este.
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(pedidos.corpo_max_bytes()))
+        .layer(TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, pedidos.timeout()))
+        .merge(upload_routes)
+        // Endereços sem rota: página 404 (as de /api/v1 respondem em JSON, ver o fallback acima)
+        .fallback(mw_erros::nao_encontrado)
         .with_state(app_state)
 }
\ No newline at end of file
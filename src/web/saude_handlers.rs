@@ -0,0 +1,18 @@
+// src/web/saude_handlers.rs
+//! Sondas para proxies e orquestradores (públicas, sem sessão):
+//! `/healthz` (o processo responde) e `/readyz` (pronto a servir pedidos).
+
+use crate::{services::saude_service, state::AppState};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+
+/// Handler para GET /healthz - O processo está a correr (não consulta a base de dados)
+pub async fn healthz() -> impl IntoResponse {
+    Json(serde_json::json!({ "status": saude_service::STATUS_OK }))
+}
+
+/// Handler para GET /readyz - Base de dados, migrações e sessões; 503 se algum componente falhar
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let prontidao = saude_service::verificar(&state.db_pool, &state.session_store).await;
+    let status = if prontidao.pronto() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(prontidao))
+}
@@ -0,0 +1,220 @@
+// src/web/sugestao_handlers.rs
+//! Caixa de sugestões (/sugestoes): qualquer utilizador envia sugestões (identificadas ou anónimas),
+//! acompanha as suas e lê as respostas publicadas. A triagem (/sugestoes/triagem, permissão "sugestoes")
+//! altera o estado e responde; o autor de uma sugestão anónima nunca é mostrado.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, notificacao_service, permission_service, sugestao_service},
+    state::AppState,
+    templates::{SugestoesPage, SugestoesTriagemPage},
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo do assunto.
+const MAX_ASSUNTO: usize = 120;
+/// Tamanho máximo da sugestão e da resposta.
+const MAX_TEXTO: usize = 2000;
+/// Página da triagem (destino dos redirects).
+const URL_TRIAGEM: &str = "/sugestoes/triagem";
+
+#[derive(Deserialize, Debug)]
+pub struct TriagemQuery {
+    #[serde(default)]
+    estado: String, // Só as sugestões neste estado (vazio = todas)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SugestaoForm {
+    #[serde(default)]
+    assunto: String,
+    #[serde(default)]
+    texto: String,
+    anonima: Option<String>, // Checkbox: presente = marcada
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EstadoSugestaoForm {
+    #[serde(default)]
+    estado: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RespostaSugestaoForm {
+    #[serde(default)]
+    resposta: String,
+    publicar: Option<String>, // Checkbox
+}
+
+/// Pode fazer a triagem das sugestões?
+async fn pode_triar(state: &AppState, atual: &CurrentUser) -> AppResult<bool> {
+    atual.tem_permissao(state, permission_service::PERM_SUGESTOES).await
+}
+
+/// Falha (403) se o utilizador não pode fazer a triagem.
+async fn exigir_triagem(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if pode_triar(state, atual).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Sugestões: {} sem permissão '{}'.", atual.id, permission_service::PERM_SUGESTOES);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Renderiza o formulário, as sugestões do utilizador e as respostas publicadas.
+async fn pagina_sugestoes(state: &AppState, atual: &CurrentUser, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = SugestoesPage {
+        minhas: sugestao_service::do_autor(&state.db_leitura, atual.organizacao_id, &atual.id).await?,
+        publicadas: sugestao_service::publicadas(&state.db_leitura, atual.organizacao_id).await?,
+        pode_triar: pode_triar(state, atual).await?,
+        form,
+        max_assunto: MAX_ASSUNTO,
+        max_texto: MAX_TEXTO,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template SugestoesPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /sugestoes - Enviar sugestões, as do utilizador e as respostas publicadas
+pub async fn show_sugestoes(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_sugestoes(&state, &atual, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /sugestoes - Envia uma sugestão (anónima, se marcada)
+pub async fn handle_enviar_sugestao(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<SugestaoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("assunto", &form.assunto, MAX_ASSUNTO);
+    v.obrigatorio("texto", &form.texto, MAX_TEXTO);
+    let anonima = form.anonima.is_some();
+    let resultado = match v.resultado() {
+        Ok(()) => sugestao_service::enviar(&state.db_pool, atual.organizacao_id, &atual.id, &form.assunto, &form.texto, anonima).await,
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(_) => {
+            let mensagem = if anonima { "Sugestão anónima enviada. Obrigado!" } else { "Sugestão enviada. Obrigado!" };
+            Ok(flash::redirect_success(&session, "/sugestoes", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("assunto", form.assunto)
+                .com_valor("texto", form.texto)
+                .com_valor("anonima", if anonima { "on" } else { "" });
+            pagina_sugestoes(&state, &atual, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para GET /sugestoes/triagem?estado= - Sugestões recebidas, com o estado e a resposta
+pub async fn show_triagem(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<TriagemQuery>,
+) -> AppResult<Response> {
+    exigir_triagem(&state, &atual).await?;
+    let estado = Some(params.estado.trim()).filter(|e| sugestao_service::ESTADOS.iter().any(|(c, _)| c == e));
+    let template = SugestoesTriagemPage {
+        sugestoes: sugestao_service::para_triagem(&state.db_leitura, atual.organizacao_id, estado).await?,
+        estados: sugestao_service::ESTADOS,
+        estado: estado.unwrap_or_default().to_string(),
+        limite: sugestao_service::LIMITE_LISTA,
+        max_texto: MAX_TEXTO,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template SugestoesTriagemPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /sugestoes/{id}/estado - Passa a sugestão a recebida ou em análise
+pub async fn handle_estado_sugestao(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<EstadoSugestaoForm>,
+) -> AppResult<Response> {
+    exigir_triagem(&state, &atual).await?;
+    let estado = form.estado.trim();
+    match sugestao_service::alterar_estado(&state.db_pool, atual.organizacao_id, id, estado).await {
+        Ok(anterior) if anterior.estado == estado => {
+            Ok(flash::redirect_success(&session, URL_TRIAGEM, "Sem alterações.").await.into_response())
+        }
+        Ok(anterior) => {
+            let detalhes = format!(
+                "{}: {} -> {}",
+                anterior.assunto,
+                sugestao_service::descrever_estado(&anterior.estado),
+                sugestao_service::descrever_estado(estado)
+            );
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_SUGESTAO_ESTADO, Some(&id.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Sugestão {}: {}.", id, sugestao_service::descrever_estado(estado));
+            Ok(flash::redirect_success(&session, URL_TRIAGEM, mensagem).await.into_response())
+        }
+        Err(e @ AppError::Validation(_)) => Ok(flash::redirect_error(&session, URL_TRIAGEM, e.user_message()).await.into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /sugestoes/{id}/responder - Responde (e publica, se marcado)
+pub async fn handle_responder_sugestao(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+    Form(form): Form<RespostaSugestaoForm>,
+) -> AppResult<Response> {
+    exigir_triagem(&state, &atual).await?;
+    let mut v = Validador::default();
+    v.obrigatorio("resposta", &form.resposta, MAX_TEXTO);
+    if let Err(e) = v.resultado() {
+        return Ok(flash::redirect_error(&session, URL_TRIAGEM, e.user_message()).await.into_response());
+    }
+    let publicar = form.publicar.is_some();
+    let sugestao = sugestao_service::responder(&state.db_pool, atual.organizacao_id, id, &form.resposta, publicar, &atual.id).await?;
+
+    let detalhes = format!("{}{}", sugestao.assunto, if publicar { " (publicada)" } else { "" });
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_SUGESTAO_RESPONDIDA, Some(&id.to_string()), Some(&detalhes)).await;
+    // Só as identificadas: o autor de uma anónima vê a resposta em /sugestoes
+    if let Some(autor_id) = &sugestao.autor_id {
+        notificacao_service::notificar(
+            &state.db_pool,
+            autor_id,
+            notificacao_service::TIPO_SUGESTAO,
+            "Sugestão respondida",
+            &format!("A sua sugestão \"{}\" foi respondida. Veja a resposta em Sugestões.", sugestao.assunto),
+        )
+        .await;
+    }
+    let mensagem = format!("Sugestão {} respondida{}.", id, if publicar { " e publicada" } else { "" });
+    Ok(flash::redirect_success(&session, URL_TRIAGEM, mensagem).await.into_response())
+}
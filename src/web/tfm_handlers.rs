@@ -0,0 +1,228 @@
+// src/web/tfm_handlers.rs
+//! TFM (/tfm, permissão "tfm"): registar os resultados do Teste de Forma Física, consultá-los por
+//! utilizador ou turma, apagar os registados por engano e as estatísticas por turma
+//! (/tfm/estatisticas, para impressão). Cada utilizador vê o seu histórico na página pessoal.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, conceito_service, tfm_service},
+    state::AppState,
+    templates::{TfmEstatisticasPage, TfmPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::Datelike;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Tamanho máximo da marca.
+const MAX_MARCA: usize = 40;
+/// Tamanho máximo das observações.
+const MAX_OBSERVACOES: usize = 300;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct TfmQuery {
+    #[serde(default)]
+    user: String, // Só os resultados deste utilizador
+    #[serde(default)]
+    turma: String, // Só os desta turma (vazio = todas)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EstatisticasQuery {
+    #[serde(default)]
+    ano: String, // Vazio = o atual
+    #[serde(default)]
+    turma: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ResultadoForm {
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    data: String,
+    #[serde(default)]
+    exercicio: String,
+    #[serde(default)]
+    marca: String,
+    #[serde(default)]
+    pontuacao: String,
+    #[serde(default)]
+    aprovado: String, // "1" = aprovado, "0" = reprovado
+    #[serde(default)]
+    observacoes: String,
+}
+
+/// Renderiza a listagem dos resultados (filtrada) e o formulário de registo.
+async fn pagina_tfm(
+    state: &AppState,
+    organizacao_id: i64,
+    params: &TfmQuery,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let user = params.user.trim();
+    let turma = params.turma.trim();
+    let template = TfmPage {
+        resultados: tfm_service::resultados(
+            &state.db_leitura,
+            organizacao_id,
+            Some(user).filter(|u| !u.is_empty()),
+            Some(turma).filter(|t| !t.is_empty()),
+            tfm_service::LISTAGEM_RECENTE,
+        )
+        .await?,
+        turmas: conceito_service::turmas(&state.db_leitura, organizacao_id).await?,
+        user: user.to_string(),
+        turma: turma.to_string(),
+        limite: tfm_service::LISTAGEM_RECENTE,
+        exercicios: tfm_service::EXERCICIOS,
+        max_pontuacao: tfm_service::MAX_PONTUACAO,
+        max_marca: MAX_MARCA,
+        max_observacoes: MAX_OBSERVACOES,
+        hoje: tempo::hoje().to_string(),
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template TfmPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /tfm?user=&turma= - Resultados recentes e registo
+pub async fn show_tfm(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<TfmQuery>,
+) -> AppResult<Response> {
+    let form = FormState::default()
+        .com_valor("user", params.user.trim())
+        .com_valor("data", tempo::hoje().to_string())
+        .com_valor("aprovado", "1");
+    pagina_tfm(&state, atual.organizacao_id, &params, StatusCode::OK, form, flash).await
+}
+
+/// Handler para POST /tfm - Regista o resultado de um exercício
+pub async fn handle_registar_tfm(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<ResultadoForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("user", &form.user, 10);
+    if form.marca.trim().chars().count() > MAX_MARCA {
+        v.erro("marca", format!("Máximo de {} caracteres.", MAX_MARCA));
+    }
+    if form.observacoes.trim().chars().count() > MAX_OBSERVACOES {
+        v.erro("observacoes", format!("Máximo de {} caracteres.", MAX_OBSERVACOES));
+    }
+    let pontuacao = form.pontuacao.trim().parse::<i64>().ok();
+    match pontuacao {
+        Some(pontuacao) => v.intervalo("pontuacao", pontuacao, 0, tfm_service::MAX_PONTUACAO),
+        None => v.erro("pontuacao", "Indique a pontuação."),
+    }
+    v.opcao("aprovado", form.aprovado.trim(), &["1", "0"]);
+    let data = v.data("data", &form.data);
+    let pontuacao = pontuacao.unwrap_or(0);
+    let aprovado = form.aprovado.trim() == "1";
+    let resultado = match (v.resultado(), data) {
+        (Ok(()), Some(data)) => tfm_service::registar(
+            &state.db_pool,
+            atual.organizacao_id,
+            form.user.trim(),
+            data,
+            form.exercicio.trim(),
+            &form.marca,
+            pontuacao,
+            aprovado,
+            &form.observacoes,
+            tempo::hoje(),
+            &atual.id,
+        )
+        .await,
+        (Err(e), _) => Err(e),
+        (Ok(()), None) => Err(AppError::validation("data", "Data inválida.")),
+    };
+    match resultado {
+        Ok(id) => {
+            let exercicio = tfm_service::descrever_exercicio(form.exercicio.trim());
+            let mencao = if aprovado { "aprovado" } else { "reprovado" };
+            let detalhes = format!("{}: {} em {}, {} pontos ({})", form.user.trim(), exercicio, form.data.trim(), pontuacao, mencao);
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_TFM_REGISTADO, Some(&id.to_string()), Some(&detalhes)).await;
+            let destino = format!("/tfm?user={}", form.user.trim());
+            let mensagem = format!("{} de {} registado ({}).", exercicio, form.user.trim(), mencao);
+            Ok(flash::redirect_success(&session, &destino, mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("user", form.user)
+                .com_valor("data", form.data)
+                .com_valor("exercicio", form.exercicio)
+                .com_valor("marca", form.marca)
+                .com_valor("pontuacao", form.pontuacao)
+                .com_valor("aprovado", form.aprovado)
+                .com_valor("observacoes", form.observacoes);
+            pagina_tfm(&state, atual.organizacao_id, &TfmQuery::default(), StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /tfm/{id}/remover - Apaga um resultado registado por engano
+pub async fn handle_remover_tfm(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    let (user_id, exercicio, data) = tfm_service::remover(&state.db_pool, atual.organizacao_id, id).await?;
+    let detalhes = format!("{}: {} em {}", user_id, tfm_service::descrever_exercicio(&exercicio), data);
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_TFM_APAGADO, Some(&id.to_string()), Some(&detalhes)).await;
+    let destino = format!("/tfm?user={}", user_id);
+    Ok(flash::redirect_success(&session, &destino, format!("Resultado {} apagado.", id)).await.into_response())
+}
+
+/// Handler para GET /tfm/estatisticas?ano=&turma= - Estatísticas por turma e exercício no ano
+pub async fn show_estatisticas_tfm(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<EstatisticasQuery>,
+) -> AppResult<Response> {
+    let organizacao_id = atual.organizacao_id;
+    let atual_ano = tempo::hoje().year();
+    let ano = params.ano.trim().parse::<i32>().unwrap_or(atual_ano);
+    let turma = Some(params.turma.trim()).filter(|t| !t.is_empty());
+    let template = TfmEstatisticasPage {
+        estatisticas: tfm_service::estatisticas(&state.db_leitura, organizacao_id, ano, turma).await?,
+        turmas: conceito_service::turmas(&state.db_leitura, organizacao_id).await?,
+        turma: params.turma.trim().to_string(),
+        ano,
+        anos: tfm_service::anos(&state.db_leitura, organizacao_id, atual_ano).await?,
+        exercicios: tfm_service::EXERCICIOS,
+        gerado_em: tempo::agora().format(tempo::FORMATO_DATA_HORA).to_string(),
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template TfmEstatisticasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
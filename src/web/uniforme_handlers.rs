@@ -0,0 +1,141 @@
+// src/web/uniforme_handlers.rs
+//! Uniforme do dia (/uniforme): quem tem a permissão "uniforme" (o chefe de dia e a administração) define
+//! o uniforme dos próximos dias e apaga os definidos por engano. Todos o veem no dashboard e na escala.
+
+use crate::{
+    error::{AppError, AppResult},
+    services::{audit_service, permission_service, uniforme_service},
+    state::AppState,
+    templates::UniformePage,
+    tempo,
+    validation::{FormState, Validador},
+    web::{flash::{self, Flash}, mw_auth::CurrentUser},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+/// Último dia mostrado (sem limite: todos os já definidos a partir de hoje).
+const FIM_SEM_LIMITE: &str = "9999-12-31";
+/// Tamanho máximo do uniforme.
+const MAX_UNIFORME: usize = 120;
+/// Tamanho máximo das observações.
+const MAX_OBSERVACOES: usize = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct UniformeForm {
+    #[serde(default)]
+    data: String,
+    #[serde(default)]
+    uniforme: String,
+    #[serde(default)]
+    observacoes: String,
+}
+
+/// Falha (403) se o utilizador não pode definir o uniforme.
+async fn exigir_permissao(state: &AppState, atual: &CurrentUser) -> AppResult<()> {
+    if atual.tem_permissao(state, permission_service::PERM_UNIFORME).await? {
+        Ok(())
+    } else {
+        tracing::warn!("Uniforme: {} sem permissão '{}'.", atual.id, permission_service::PERM_UNIFORME);
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Renderiza os uniformes a partir de hoje e o formulário.
+async fn pagina_uniforme(state: &AppState, organizacao_id: i64, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let hoje = tempo::hoje().to_string();
+    let template = UniformePage {
+        uniformes: uniforme_service::periodo(&state.db_leitura, organizacao_id, &hoje, FIM_SEM_LIMITE).await?,
+        sugestoes: uniforme_service::sugestoes(&state.db_leitura, organizacao_id).await?,
+        hoje,
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template UniformePage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /uniforme - Uniformes definidos e formulário (o dia por omissão é hoje)
+pub async fn show_uniforme(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    exigir_permissao(&state, &atual).await?;
+    let form = FormState::default().com_valor("data", tempo::hoje().to_string());
+    pagina_uniforme(&state, atual.organizacao_id, StatusCode::OK, form, flash).await
+}
+
+/// Handler para POST /uniforme - Define (ou substitui) o uniforme de um dia
+pub async fn handle_definir_uniforme(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<UniformeForm>,
+) -> AppResult<Response> {
+    exigir_permissao(&state, &atual).await?;
+    let mut v = Validador::default();
+    v.obrigatorio("uniforme", &form.uniforme, MAX_UNIFORME);
+    if form.observacoes.trim().chars().count() > MAX_OBSERVACOES {
+        v.erro("observacoes", format!("Máximo de {} caracteres.", MAX_OBSERVACOES));
+    }
+    let data = v.data("data", &form.data);
+    let resultado = match (v.resultado(), data) {
+        (Ok(()), Some(data)) => uniforme_service::definir(
+            &state.db_pool,
+            atual.organizacao_id,
+            data,
+            &form.uniforme,
+            &form.observacoes,
+            tempo::hoje(),
+            &atual.id,
+        )
+        .await
+        .map(|()| data),
+        (Err(e), _) => Err(e),
+        (Ok(()), None) => Err(AppError::validation("data", "Data inválida.")),
+    };
+    match resultado {
+        Ok(data) => {
+            let detalhes = format!("{}: {}", data, form.uniforme.trim());
+            audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_UNIFORME_DEFINIDO, Some(&data.to_string()), Some(&detalhes)).await;
+            let mensagem = format!("Uniforme de {} definido: {}.", data.format("%d/%m"), form.uniforme.trim());
+            Ok(flash::redirect_success(&session, "/uniforme", mensagem).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("data", form.data)
+                .com_valor("uniforme", form.uniforme)
+                .com_valor("observacoes", form.observacoes);
+            pagina_uniforme(&state, atual.organizacao_id, StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /uniforme/{data}/remover - Apaga o uniforme de um dia
+pub async fn handle_remover_uniforme(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(data): Path<String>,
+) -> AppResult<Response> {
+    exigir_permissao(&state, &atual).await?;
+    let data = NaiveDate::parse_from_str(&data, "%Y-%m-%d")
+        .map_err(|_| AppError::validation("data", format!("Data inválida: '{}' (use AAAA-MM-DD).", data)))?;
+    let uniforme = uniforme_service::remover(&state.db_pool, atual.organizacao_id, data).await?;
+    let detalhes = format!("{}: {}", data, uniforme.uniforme);
+    audit_service::registar(&state.db_pool, &atual.id, audit_service::ACAO_UNIFORME_APAGADO, Some(&data.to_string()), Some(&detalhes)).await;
+    let mensagem = format!("Uniforme de {} apagado.", data.format("%d/%m"));
+    Ok(flash::redirect_success(&session, "/uniforme", mensagem).await.into_response())
+}
@@ -1,32 +1,36 @@
 // src/web/user_handlers.rs
+use crate::i18n::{self, Idioma};
 use crate::state::AppState;
+use crate::tempo;
 // Importar Template é obrigatório para usar .render()
 use askama::Template; 
-use crate::templates::{UserPage, MeuServico, NotificacaoTroca};
-use crate::services::escala_service;
+use crate::templates::{UserNotificacoesPage, UserPage, UserPreferenciasPage, UserSenhaPage, UserTokensPage, MeuServico, NotificacaoTroca, LoginExibicao};
+use crate::error::{AppError, AppResult, FieldError};
+use crate::models::{notificacao::NotificacoesFilter, preferencias::Preferencias};
+use crate::services::{agenda_service, api_token_service, auth_service, biblioteca_service, cardapio_service, enquete_service, cautela_service, claviculario_service, disciplina_service, horario_service, email_service, escala_service, evento_service, google_calendar_service, login_history_service, notificacao_service, preferencias_service, sessao_service, telegram_service, tfm_service, uniforme_service, user_service};
+use crate::validation::{validar, FormState, Validador, Validate};
+use crate::web::{flash::{self, Flash}, mw_auth::CurrentUser, mw_idioma::IDIOMA_KEY, mw_senha, paginacao::{Paginacao, MAX_POR_PAGINA}};
 use axum::{
-    extract::{State, Form},
-    response::{Html, IntoResponse, Redirect},
+    extract::{Path, Query, State, Form},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Redirect, Response,
+    },
 };
+use futures_util::stream::{self, Stream};
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
 use tower_sessions::Session;
-use chrono::{Datelike, Local};
 use serde::Deserialize;
 
-// Helper para traduzir dias
-fn weekday_to_pt(wd: chrono::Weekday) -> &'static str {
-    match wd {
-        chrono::Weekday::Mon => "Segunda", chrono::Weekday::Tue => "Terça",
-        chrono::Weekday::Wed => "Quarta", chrono::Weekday::Thu => "Quinta",
-        chrono::Weekday::Fri => "Sexta", chrono::Weekday::Sat => "Sábado",
-        chrono::Weekday::Sun => "Domingo",
-    }
-}
-fn month_to_pt(m: u32) -> &'static str {
-    match m {
-        1 => "Jan", 2 => "Fev", 3 => "Mar", 4 => "Abr", 5 => "Mai", 6 => "Jun",
-        7 => "Jul", 8 => "Ago", 9 => "Set", 10 => "Out", 11 => "Nov", 12 => "Dez", _ => ""
-    }
-}
+/// Chave da sessão com o `state` do pedido de autorização ao Google Calendar em curso.
+const GOOGLE_PENDENTE_KEY: &str = "google_calendar_pendente";
+
+/// Quantos logins recentes mostrar no painel do utilizador.
+const LOGINS_RECENTES: i64 = 10;
+/// Quantas notificações mostrar no painel do utilizador.
+const NOTIFICACOES_RECENTES: i64 = 10;
 
 // Payload do formulário de resposta
 #[derive(Deserialize)]
@@ -35,19 +39,87 @@ pub struct RespostaTrocaForm {
     pub acao: String, // "aceitar" | "recusar"
 }
 
+#[derive(Deserialize)]
+pub struct CriarTokenForm {
+    pub nome: String,
+}
+
+#[derive(Deserialize)]
+pub struct AlterarSenhaForm {
+    pub senha_atual: String,
+    pub nova_senha: String,
+    pub confirmacao: String,
+}
+
+impl Validate for AlterarSenhaForm {
+    fn validate(&self, v: &mut Validador) {
+        v.minimo("nova_senha", &self.nova_senha, 4);
+        if self.nova_senha == self.senha_atual {
+            v.erro("nova_senha", "A nova senha tem de ser diferente da atual.");
+        }
+        if self.confirmacao != self.nova_senha {
+            v.erro("confirmacao", "As senhas não coincidem.");
+        }
+    }
+}
+
+/// Preferências da interface. Os selects vazios = sem preferência; as caixas só vêm quando marcadas.
+#[derive(Deserialize)]
+pub struct PreferenciasForm {
+    pub tema: String,
+    #[serde(default)]
+    pub turma_presenca: String,
+    #[serde(default)]
+    pub por_pagina: String,
+    pub avisos_escala: Option<String>,
+    pub avisos_troca: Option<String>,
+    pub avisos_lembrete: Option<String>,
+}
+
+impl PreferenciasForm {
+    fn numero(valor: &str) -> Option<i64> {
+        valor.trim().parse().ok()
+    }
+
+    fn preferencias(&self) -> Preferencias {
+        Preferencias {
+            tema: self.tema.clone(),
+            turma_presenca: Self::numero(&self.turma_presenca),
+            por_pagina: Self::numero(&self.por_pagina),
+            avisos_escala: self.avisos_escala.is_some(),
+            avisos_troca: self.avisos_troca.is_some(),
+            avisos_lembrete: self.avisos_lembrete.is_some(),
+        }
+    }
+}
+
+impl Validate for PreferenciasForm {
+    fn validate(&self, v: &mut Validador) {
+        v.opcao("tema", &self.tema, preferencias_service::TEMAS);
+        for (campo, valor, max) in [("turma_presenca", &self.turma_presenca, 3), ("por_pagina", &self.por_pagina, MAX_POR_PAGINA)] {
+            if valor.trim().is_empty() {
+                continue;
+            }
+            match Self::numero(valor) {
+                Some(n) => v.intervalo(campo, n, 1, max),
+                None => v.erro(campo, "Valor inválido."),
+            }
+        }
+    }
+}
+
 // --- HANDLER DASHBOARD ---
 pub async fn user_page_handler(
     State(state): State<AppState>,
     session: Session,
+    atual: CurrentUser,
+    flash: Flash,
 ) -> impl IntoResponse {
-    let user_id = session.get::<String>("user_id").await.unwrap().unwrap_or_default();
-    
-    // 1. Dados do Utilizador
-    let user = sqlx::query!("SELECT name FROM users WHERE id = ?", user_id)
-        .fetch_one(&state.db_pool).await.unwrap();
+    // 1. Dados do Utilizador (carregados por require_auth)
+    let CurrentUser { id: user_id, name, roles, organizacao_id, .. } = atual;
 
     // 2. Meus Serviços Futuros
-    let hoje = Local::now().date_naive();
+    let hoje = tempo::hoje();
     let servicos_db = sqlx::query!(
         r#"
         SELECT a.data, p.nome as posto 
@@ -57,18 +129,9 @@ pub async fn user_page_handler(
         ORDER BY a.data ASC LIMIT 5
         "#,
         user_id, hoje
-    ).fetch_all(&state.db_pool).await.unwrap_or_default();
+    ).fetch_all(&state.db_leitura).await.unwrap_or_default();
 
-    let meus_servicos = servicos_db.into_iter().map(|s| {
-        let d = chrono::NaiveDate::parse_from_str(&s.data, "%Y-%m-%d").unwrap_or(hoje);
-        MeuServico {
-            data: s.data,
-            dia_semana: weekday_to_pt(d.weekday()).to_string(),
-            dia_mes: d.format("%d").to_string(),
-            mes_extenso: month_to_pt(d.month()).to_string(),
-            posto: s.posto,
-        }
-    }).collect();
+    let meus_servicos = servicos_db.into_iter().map(|s| MeuServico { data: s.data, posto: s.posto }).collect();
 
     // 3. Trocas Pendentes (Onde EU sou o substituto)
     let trocas_db = sqlx::query!(
@@ -82,25 +145,159 @@ pub async fn user_page_handler(
         ORDER BY t.criado_em DESC
         "#,
         user_id
-    ).fetch_all(&state.db_pool).await.unwrap_or_default();
+    ).fetch_all(&state.db_leitura).await.unwrap_or_default();
 
-    let trocas_pendentes = trocas_db.into_iter().map(|t| {
-        let d = chrono::NaiveDate::parse_from_str(&t.data, "%Y-%m-%d").unwrap_or(hoje);
-        NotificacaoTroca {
-            troca_id: t.id,
-            solicitante: t.solicitante,
-            data: d.format("%d/%m").to_string(),
-            posto: t.posto,
-            motivo: t.motivo.unwrap_or_default(),
-        }
+    let trocas_pendentes = trocas_db.into_iter().map(|t| NotificacaoTroca {
+        troca_id: t.id,
+        solicitante: t.solicitante,
+        data: t.data,
+        posto: t.posto,
+        motivo: t.motivo.unwrap_or_default(),
     }).collect();
 
+    // 4. Logins recentes (para o utilizador detetar acessos que não reconhece)
+    let logins_recentes = login_history_service::recentes_user(&state.db_leitura, &user_id, LOGINS_RECENTES)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|l| LoginExibicao {
+            quando: l.criado_em,
+            ip: l.ip,
+            dispositivo: l.user_agent.unwrap_or_else(|| "-".to_string()),
+            sucesso: l.sucesso,
+        })
+        .collect();
+
+    // 5. Sessões ativas (outros dispositivos com login feito)
+    let sessao_atual = session.id().map(|id| id.to_string());
+    let sessoes = sessao_service::listar_user(&state.db_pool, &user_id, sessao_atual.as_deref())
+        .await
+        .unwrap_or_default();
+
+    // 6. Notificações (ex: alterações de segurança na conta)
+    let notificacoes = notificacao_service::recentes_user(&state.db_leitura, &user_id, NOTIFICACOES_RECENTES)
+        .await
+        .unwrap_or_default();
+
+    // 7. Ligação ao Telegram (só se o bot estiver configurado)
+    let telegram_config = telegram_service::config();
+    let telegram = match telegram_config {
+        Some(_) => telegram_service::ligacao_user(&state.db_leitura, &user_id).await.unwrap_or_default(),
+        None => None,
+    };
+
+    // 8. Ligação ao Google Calendar (só se a integração estiver configurada)
+    let google_ativo = google_calendar_service::config().is_some();
+    let google = if google_ativo {
+        google_calendar_service::ligacao_user(&state.db_leitura, &user_id).await.unwrap_or_default()
+    } else {
+        None
+    };
+
+    // 9. Avisos por email (só se o envio de emails estiver configurado)
+    let email_ativo = email_service::config().is_some();
+    let (email, emails_avisos) = if email_ativo {
+        email_service::preferencia(&state.db_leitura, &user_id).await.unwrap_or((None, true))
+    } else {
+        (None, false)
+    };
+
+    // 10. Cardápio da semana (só se o rancheiro já publicou algum prato)
+    let cardapio = cardapio_service::semana(&state.db_leitura, organizacao_id, hoje, false)
+        .await
+        .ok()
+        .filter(|c| !c.vazio());
+
+    // 11. Material cautelado e chaves levantadas pelo utilizador (falha de leitura = cartão omitido)
+    let cautelas_abertas = cautela_service::abertas(&state.db_leitura, organizacao_id, Some(&user_id))
+        .await
+        .unwrap_or_default();
+    let chaves_em_posse = claviculario_service::fora(&state.db_leitura, organizacao_id, Some(&user_id))
+        .await
+        .unwrap_or_default();
+
+    // 12. Processos disciplinares por decidir (defesa a apresentar; falha de leitura = cartão omitido)
+    let processos_abertos = disciplina_service::listar(&state.db_leitura, organizacao_id, None, Some(&user_id), Some(true), i64::MAX)
+        .await
+        .unwrap_or_default();
+
+    // 13. Próximos eventos da agenda para o ano do utilizador (falha de leitura = cartão omitido)
+    let user = user_service::find_user_by_id(&state.db_leitura, &user_id).await.ok().flatten();
+    let proximos_eventos = match &user {
+        Some(user) => agenda_service::proximos(&state.db_leitura, organizacao_id, hoje, Some(user.ano), agenda_service::PROXIMOS_PAINEL)
+            .await
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    // 14. Horário semanal da turma do utilizador (falha de leitura = cartão omitido)
+    let aulas = match &user {
+        Some(user) => horario_service::listar(&state.db_leitura, organizacao_id, &user.turma, None)
+            .await
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    // 15. Uniforme de hoje e de amanhã (falha de leitura = cartão omitido)
+    let uniformes = uniforme_service::periodo(
+        &state.db_leitura,
+        organizacao_id,
+        &hoje.to_string(),
+        &(hoje + chrono::Duration::days(1)).to_string(),
+    )
+    .await
+    .unwrap_or_default();
+
+    // 16. Histórico do TFM do utilizador (falha de leitura = cartão omitido)
+    let tfm = tfm_service::resultados(&state.db_leitura, organizacao_id, Some(&user_id), None, tfm_service::HISTORICO_PERFIL)
+        .await
+        .unwrap_or_default();
+
+    // 17. Livros da biblioteca emprestados ao utilizador (falha de leitura = cartão omitido)
+    let emprestimos = biblioteca_service::abertos(&state.db_leitura, organizacao_id, Some(&user_id))
+        .await
+        .unwrap_or_default();
+
+    // 18. Enquetes dirigidas ao utilizador em que ainda não votou (falha de leitura = cartão omitido)
+    let enquetes = match &user {
+        Some(user) => enquete_service::por_votar(&state.db_leitura, organizacao_id, &user_id, &roles, user.ano, &hoje.to_string())
+            .await
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
     // Instancia a struct definida em templates.rs
     let template = UserPage {
         user_id,
-        name: user.name, // Campo correto (não é user_name)
+        name, // Campo correto (não é user_name)
         meus_servicos,
         trocas_pendentes, // Campo correto
+        logins_recentes,
+        sessoes,
+        notificacoes,
+        telegram_ativo: telegram_config.is_some(),
+        telegram_bot: telegram_config.and_then(|c| c.bot_username.clone()),
+        telegram,
+        google_ativo,
+        google,
+        email_ativo,
+        email,
+        emails_avisos,
+        idioma: i18n::atual(),
+        cardapio,
+        hoje: hoje.to_string(),
+        cautelas_abertas,
+        chaves_em_posse,
+        tfm,
+        emprestimos,
+        enquetes,
+        processos_abertos,
+        proximos_eventos,
+        aulas,
+        dia_semana: horario_service::dia_semana(hoje),
+        uniformes,
+        success_message: flash.success,
+        error_message: flash.error,
     };
     
     // Renderiza
@@ -117,14 +314,487 @@ pub async fn user_page_handler(
 pub async fn handle_responder_troca(
     State(state): State<AppState>,
     session: Session,
+    atual: CurrentUser,
     Form(form): Form<RespostaTrocaForm>,
 ) -> impl IntoResponse {
-    let user_id = match session.get::<String>("user_id").await {
-        Ok(Some(id)) => id,
-        _ => return Redirect::to("/").into_response(),
+    let user_id = atual.id;
+
+    match escala_service::responder_troca_usuario(&state.db_pool, &form.troca_id, &user_id, &form.acao).await {
+        Ok(msg) => flash::redirect_success(&session, "/user", msg).await.into_response(),
+        Err(e) => {
+            tracing::warn!("Resposta à troca {} falhou: {:?}", form.troca_id, e);
+            flash::redirect_error(&session, "/user", AppError::from(e).user_message()).await.into_response()
+        }
+    }
+}
+// --- HANDLER POST: TERMINAR UMA SESSÃO (outro dispositivo) ---
+pub async fn handle_revogar_sessao(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let user_id = atual.id;
+
+    match sessao_service::revogar(&state.db_pool, &user_id, id).await {
+        Ok(()) => flash::redirect_success(&session, "/user", i18n::t("flash.sessao_terminada")).await.into_response(),
+        Err(e) => {
+            tracing::warn!("Revogação da sessão {} por {} falhou: {:?}", id, user_id, e);
+            flash::redirect_error(&session, "/user", e.user_message()).await.into_response()
+        }
+    }
+}
+
+// --- HANDLER POST: TERMINAR TODAS AS OUTRAS SESSÕES ---
+pub async fn handle_revogar_outras_sessoes(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+) -> impl IntoResponse {
+    let user_id = atual.id;
+
+    let sessao_atual = session.id().map(|id| id.to_string());
+    match sessao_service::revogar_todas(&state.db_pool, &user_id, sessao_atual.as_deref()).await {
+        Ok(n) => flash::redirect_success(&session, "/user", i18n::tf("flash.sessoes_terminadas", &[("n", &n.to_string())])).await.into_response(),
+        Err(e) => {
+            tracing::warn!("Revogação das sessões de {} falhou: {:?}", user_id, e);
+            flash::redirect_error(&session, "/user", e.user_message()).await.into_response()
+        }
+    }
+}
+
+// --- NOTIFICAÇÕES ---
+
+/// Página com todas as notificações.
+const PAGINA_NOTIFICACOES: &str = "/user/notificacoes";
+
+#[derive(Deserialize)]
+pub struct MarcarLidasForm {
+    #[serde(default)]
+    voltar: String, // "/user/notificacoes" quando vem dessa página; o resto volta ao painel
+}
+
+/// Handler para GET /user/notificacoes?tipo=&por_ler= - Todas as notificações, por páginas
+pub async fn show_notificacoes(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(filtro): Query<NotificacoesFilter>,
+    paginacao: Paginacao,
+) -> AppResult<Response> {
+    let (notificacoes, total) =
+        notificacao_service::listar_user(&state.db_leitura, &atual.id, &filtro, paginacao.limite(), paginacao.offset()).await?;
+
+    let template = UserNotificacoesPage {
+        notificacoes,
+        paginacao: paginacao.navegacao(total),
+        por_ler: notificacao_service::por_ler(&state.db_leitura, &atual.id).await?,
+        tipos: notificacao_service::TIPOS,
+        filtro_tipo: filtro.tipo.unwrap_or_default(),
+        filtro_por_ler: filtro.por_ler,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template UserNotificacoesPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para POST /user/notificacoes/{id}/lida - Marca uma notificação como lida
+pub async fn handle_marcar_notificacao_lida(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match notificacao_service::marcar_lida(&state.db_pool, &atual.id, id).await {
+        Ok(true) => Redirect::to(PAGINA_NOTIFICACOES).into_response(),
+        Ok(false) => flash::redirect_error(&session, PAGINA_NOTIFICACOES, "Notificação não encontrada.").await.into_response(),
+        Err(e) => {
+            tracing::warn!("Falha ao marcar a notificação {} de {} como lida: {:?}", id, atual.id, e);
+            flash::redirect_error(&session, PAGINA_NOTIFICACOES, e.user_message()).await.into_response()
+        }
+    }
+}
+
+/// Handler para POST /user/notificacoes/lidas - Marca todas como lidas (no painel ou em /user/notificacoes)
+pub async fn handle_marcar_notificacoes_lidas(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<MarcarLidasForm>,
+) -> impl IntoResponse {
+    let user_id = atual.id;
+    let destino = if form.voltar == PAGINA_NOTIFICACOES { PAGINA_NOTIFICACOES } else { "/user" };
+
+    match notificacao_service::marcar_todas_lidas(&state.db_pool, &user_id).await {
+        Ok(_) => Redirect::to(destino).into_response(),
+        Err(e) => {
+            tracing::warn!("Falha ao marcar notificações de {} como lidas: {:?}", user_id, e);
+            flash::redirect_error(&session, destino, e.user_message()).await.into_response()
+        }
+    }
+}
+
+// --- ALTERAÇÃO DA SENHA PELO PRÓPRIO ---
+
+/// Renderiza a página de alteração de senha (com os erros do formulário, se houver).
+async fn pagina_senha(state: &AppState, session: &Session, status: StatusCode, form: FormState) -> AppResult<Response> {
+    let expirada = session.get::<bool>(mw_senha::SENHA_EXPIRADA_KEY).await.ok().flatten().unwrap_or(false);
+    let template = UserSenhaPage {
+        expirada,
+        max_dias: state.senha_max_dias,
+        form,
     };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template UserSenhaPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
 
-    let _ = escala_service::responder_troca_usuario(&state.db_pool, &form.troca_id, &user_id, &form.acao).await;
-    
-    Redirect::to("/user").into_response()
-}
\ No newline at end of file
+// GET /user/senha
+pub async fn show_alterar_senha(State(state): State<AppState>, session: Session) -> AppResult<Response> {
+    pagina_senha(&state, &session, StatusCode::OK, FormState::default()).await
+}
+
+// POST /user/senha
+pub async fn handle_alterar_senha(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<AlterarSenhaForm>,
+) -> AppResult<Response> {
+    let user_id = atual.id;
+
+    if let Err(AppError::Validation(erros)) = validar(&form) {
+        return pagina_senha(&state, &session, StatusCode::UNPROCESSABLE_ENTITY, FormState::com_erros(erros)).await;
+    }
+
+    let user = user_service::find_user_by_id(&state.db_pool, &user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Utilizador '{}' não encontrado.", user_id)))?;
+    if !auth_service::verify_password(&form.senha_atual, &user.password_hash, &user.password_esquema).await? {
+        tracing::warn!("Alteração de senha de {}: senha atual incorreta.", user_id);
+        let erros = vec![FieldError::new("senha_atual", "Senha atual incorreta.")];
+        return pagina_senha(&state, &session, StatusCode::UNPROCESSABLE_ENTITY, FormState::com_erros(erros)).await;
+    }
+
+    user_service::update_user_password(&state.db_pool, &user_id, &form.nova_senha).await?;
+    let _ = session.remove::<bool>(mw_senha::SENHA_EXPIRADA_KEY).await;
+
+    // Quem souber a senha antiga deixa de ter sessões abertas
+    let sessao_atual = session.id().map(|id| id.to_string());
+    let terminadas = sessao_service::revogar_todas(&state.db_pool, &user_id, sessao_atual.as_deref()).await.unwrap_or_else(|e| {
+        tracing::warn!("Falha ao terminar as outras sessões de {}: {:?}", user_id, e);
+        0
+    });
+    tracing::info!("🔑 {} alterou a senha ({} outras sessões terminadas).", user_id, terminadas);
+
+    Ok(flash::redirect_success(&session, "/user", i18n::t("flash.senha_alterada")).await.into_response())
+}
+
+// --- PREFERÊNCIAS ---
+
+/// Renderiza a página de preferências (com os erros do formulário, se houver).
+fn pagina_preferencias(preferencias: Preferencias, status: StatusCode, form: FormState, flash: Flash) -> AppResult<Response> {
+    let template = UserPreferenciasPage {
+        preferencias,
+        telegram_ativo: telegram_service::config().is_some(),
+        email_ativo: email_service::config().is_some(),
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template UserPreferenciasPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+// GET /user/preferencias
+pub async fn show_preferencias(atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    pagina_preferencias(atual.preferencias, StatusCode::OK, FormState::default(), flash)
+}
+
+// POST /user/preferencias
+pub async fn handle_preferencias(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<PreferenciasForm>,
+) -> AppResult<Response> {
+    if let Err(AppError::Validation(erros)) = validar(&form) {
+        return pagina_preferencias(atual.preferencias, StatusCode::UNPROCESSABLE_ENTITY, FormState::com_erros(erros), Flash::default());
+    }
+    preferencias_service::guardar(&state.db_pool, &atual.id, &form.preferencias()).await?;
+    Ok(flash::redirect_success(&session, "/user/preferencias", "Preferências guardadas.").await.into_response())
+}
+
+// --- TOKENS DE API ---
+
+// GET /user/tokens
+pub async fn show_tokens(State(state): State<AppState>, atual: CurrentUser, flash: Flash) -> AppResult<Response> {
+    let user_id = atual.id;
+    let template = UserTokensPage {
+        tokens: api_token_service::listar_user(&state.db_pool, &user_id).await?,
+        max_tokens: api_token_service::MAX_TOKENS_USER,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template UserTokensPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+// POST /user/tokens
+pub async fn handle_criar_token(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<CriarTokenForm>,
+) -> impl IntoResponse {
+    let user_id = atual.id;
+
+    match api_token_service::criar(&state.db_pool, &user_id, &form.nome).await {
+        Ok(token) => flash::redirect_success(
+            &session,
+            "/user/tokens",
+            i18n::tf("flash.token_criado", &[("token", &token)]),
+        )
+        .await
+        .into_response(),
+        Err(e) => {
+            tracing::warn!("Criação de token de API por {} falhou: {:?}", user_id, e);
+            flash::redirect_error(&session, "/user/tokens", e.user_message()).await.into_response()
+        }
+    }
+}
+
+// POST /user/tokens/{id}/revogar
+pub async fn handle_revogar_token(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let user_id = atual.id;
+
+    match api_token_service::revogar(&state.db_pool, &user_id, id).await {
+        Ok(nome) => flash::redirect_success(&session, "/user/tokens", i18n::tf("flash.token_revogado", &[("nome", &nome)])).await.into_response(),
+        Err(e) => {
+            tracing::warn!("Revogação do token {} por {} falhou: {:?}", id, user_id, e);
+            flash::redirect_error(&session, "/user/tokens", e.user_message()).await.into_response()
+        }
+    }
+}
+
+/// Handler para POST /user/telegram/ligar - Gera o código a enviar ao bot (mostrado no painel)
+pub async fn handle_telegram_ligar(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+) -> impl IntoResponse {
+    let user_id = atual.id;
+    if telegram_service::config().is_none() {
+        return flash::redirect_error(&session, "/user", i18n::t("flash.telegram_inativo")).await.into_response();
+    }
+
+    match telegram_service::gerar_codigo(&state.db_pool, &user_id).await {
+        Ok(_) => Redirect::to("/user").into_response(),
+        Err(e) => {
+            tracing::error!("Falha ao gerar código do Telegram para {}: {:?}", user_id, e);
+            flash::redirect_error(&session, "/user", e.user_message()).await.into_response()
+        }
+    }
+}
+
+/// Handler para POST /user/telegram/desligar - Deixa de enviar notificações para o Telegram
+pub async fn handle_telegram_desligar(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+) -> impl IntoResponse {
+    let user_id = atual.id;
+
+    match telegram_service::desligar(&state.db_pool, &user_id).await {
+        Ok(_) => flash::redirect_success(&session, "/user", i18n::t("flash.telegram_desligado")).await.into_response(),
+        Err(e) => {
+            tracing::error!("Falha ao desligar o Telegram de {}: {:?}", user_id, e);
+            flash::redirect_error(&session, "/user", e.user_message()).await.into_response()
+        }
+    }
+}
+
+/// Handler para POST /user/google/ligar - Envia o browser ao Google para autorizar o acesso ao calendário
+pub async fn handle_google_ligar(session: Session) -> impl IntoResponse {
+    let Some(config) = google_calendar_service::config() else {
+        return flash::redirect_error(&session, "/user", i18n::t("flash.google_inativo")).await.into_response();
+    };
+    let pedido_state = uuid::Uuid::new_v4().to_string();
+    if let Err(e) = session.insert(GOOGLE_PENDENTE_KEY, &pedido_state).await {
+        tracing::error!("Falha ao guardar o pedido de ligação ao Google Calendar: {}", e);
+        return flash::redirect_error(&session, "/user", i18n::t("flash.google_erro_iniciar")).await.into_response();
+    }
+    match google_calendar_service::url_autorizacao(config, &pedido_state) {
+        Ok(url) => Redirect::to(&url).into_response(),
+        Err(e) => flash::redirect_error(&session, "/user", e.user_message()).await.into_response(),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GoogleCallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>, // Ex: "access_denied" (o utilizador recusou)
+}
+
+/// Handler para GET /user/google/callback - Regresso do Google (code + state)
+pub async fn handle_google_callback(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    session: Session,
+    Query(params): Query<GoogleCallbackParams>,
+) -> impl IntoResponse {
+    let user_id = atual.id;
+    let Some(config) = google_calendar_service::config() else {
+        return Redirect::to("/user").into_response();
+    };
+    // O pedido só pode ser usado uma vez
+    let pendente: Option<String> = session.remove(GOOGLE_PENDENTE_KEY).await.ok().flatten();
+    if let Some(erro) = params.error {
+        tracing::info!("📅 Ligação ao Google Calendar cancelada por {} ({}).", user_id, erro);
+        return flash::redirect_error(&session, "/user", i18n::t("flash.google_cancelado")).await.into_response();
+    }
+    let (Some(code), Some(recebido)) = (params.code, params.state) else {
+        return flash::redirect_error(&session, "/user", i18n::t("flash.google_resposta_invalida")).await.into_response();
+    };
+    if pendente.as_deref() != Some(recebido.as_str()) {
+        tracing::warn!("Google Calendar: state inválido no regresso de {}.", user_id);
+        return flash::redirect_error(&session, "/user", i18n::t("flash.google_expirado")).await.into_response();
+    }
+
+    match google_calendar_service::ligar(&state.db_pool, config, &user_id, &code).await {
+        Ok(()) => {
+            // Primeira sincronização já, sem esperar pela tarefa periódica
+            let db_pool = state.db_pool.clone();
+            let alvo = user_id.clone();
+            tokio::spawn(async move {
+                if let Err(e) = google_calendar_service::sincronizar_user(&db_pool, &alvo).await {
+                    tracing::warn!("Google Calendar: falha na primeira sincronização de {}: {:?}", alvo, e);
+                }
+            });
+            flash::redirect_success(&session, "/user", i18n::t("flash.google_ligado"))
+                .await
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!("Falha ao ligar o Google Calendar de {}: {:?}", user_id, e);
+            flash::redirect_error(&session, "/user", e.user_message()).await.into_response()
+        }
+    }
+}
+
+/// Handler para POST /user/google/desligar - Apaga os serviços futuros do calendário e revoga o acesso
+pub async fn handle_google_desligar(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    session: Session,
+) -> impl IntoResponse {
+    match google_calendar_service::desligar(&state.db_pool, &atual.id).await {
+        Ok(_) => flash::redirect_success(&session, "/user", i18n::t("flash.google_desligado")).await.into_response(),
+        Err(e) => {
+            tracing::error!("Falha ao desligar o Google Calendar de {}: {:?}", atual.id, e);
+            flash::redirect_error(&session, "/user", e.user_message()).await.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EmailsForm {
+    pub ativos: bool,
+}
+
+/// Handler para POST /user/emails - Ativa ou desativa os avisos por email (escala, trocas)
+pub async fn handle_emails_preferencia(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<EmailsForm>,
+) -> impl IntoResponse {
+    let user_id = atual.id;
+
+    match email_service::definir_preferencia(&state.db_pool, &user_id, form.ativos).await {
+        Ok(_) => {
+            let msg = i18n::t(if form.ativos { "flash.emails_ativados" } else { "flash.emails_desativados" });
+            flash::redirect_success(&session, "/user", msg).await.into_response()
+        }
+        Err(e) => {
+            tracing::error!("Falha ao alterar os avisos por email de {}: {:?}", user_id, e);
+            flash::redirect_error(&session, "/user", e.user_message()).await.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct IdiomaForm {
+    pub idioma: String,
+}
+
+/// Handler para POST /user/idioma - Guarda o idioma das páginas escolhido pelo utilizador
+pub async fn handle_idioma(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<IdiomaForm>,
+) -> impl IntoResponse {
+    let Ok(idioma) = form.idioma.parse::<Idioma>() else {
+        return flash::redirect_error(&session, "/user", i18n::t("idioma.invalido")).await.into_response();
+    };
+    if let Err(e) = user_service::definir_idioma(&state.db_pool, &atual.id, idioma).await {
+        tracing::error!("Falha ao alterar o idioma de {}: {:?}", atual.id, e);
+        return flash::redirect_error(&session, "/user", e.user_message()).await.into_response();
+    }
+    if let Err(e) = session.insert(IDIOMA_KEY, idioma.codigo()).await {
+        tracing::warn!("Falha ao guardar o idioma na sessão: {:?}", e);
+    }
+    // A mensagem já vai no idioma escolhido (o pedido em curso ainda corre no anterior)
+    flash::redirect_success(&session, "/user", i18n::traduzir(idioma, "idioma.alterado")).await.into_response()
+}
+
+/// Handler para GET /events - Eventos em tempo real (Server-Sent Events) do utilizador autenticado:
+/// notificações, estado da escala e das suas trocas. As páginas usam-nos para se atualizarem sem polling.
+pub async fn handle_eventos(atual: CurrentUser) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    tracing::debug!("GET /events: {} ligado", atual.id);
+    let estado = (evento_service::subscrever(), atual.id, atual.organizacao_id);
+    let eventos = stream::unfold(estado, |(mut rx, user_id, organizacao_id)| async move {
+        loop {
+            match rx.recv().await {
+                Ok(evento) if evento.para(&user_id, organizacao_id) => {
+                    let sse = Event::default().event(evento.tipo).data(evento.dados.to_string());
+                    return Some((Ok(sse), (rx, user_id, organizacao_id)));
+                }
+                Ok(_) => continue,
+                // Ligação lenta: perdeu eventos, mas continua a receber os seguintes
+                Err(RecvError::Lagged(perdidos)) => {
+                    tracing::warn!("GET /events: {} perdeu {} eventos", user_id, perdidos);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(eventos).keep_alive(KeepAlive::default())
+}
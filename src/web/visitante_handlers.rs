@@ -0,0 +1,305 @@
+// src/web/visitante_handlers.rs
+//! Visitantes (/presence/visitantes, permissão "presenca"): registo da entrada e da saída na porta de
+//! armas, os visitantes dentro e as visitas do dia (com exportação em CSV). Como na presença, as páginas
+//! abertas recebem as entradas e saídas por WebSocket e a saída é marcada pelo próprio WebSocket.
+
+use crate::{
+    error::{AppError, AppResult},
+    models::visitante::{Visita, VisitanteSocketAction, VisitanteSocketUpdate},
+    services::{manutencao_service, visitante_service},
+    state::AppState,
+    templates::{VisitanteLinha, VisitantesPage},
+    tempo,
+    validation::{FormState, Validador},
+    web::{
+        admin_handlers::csv_campo,
+        flash::{self, Flash},
+        mw_auth::CurrentUser,
+    },
+};
+use askama::Template;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::Form;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tower_sessions::Session;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Tamanho máximo do nome, do documento e do motivo.
+const MAX_NOME: usize = 100;
+const MAX_DOCUMENTO: usize = 40;
+const MAX_MOTIVO: usize = 200;
+
+#[derive(Deserialize, Debug)]
+pub struct VisitantesQuery {
+    data: Option<String>, // Dia das visitas (omissão: hoje)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VisitanteForm {
+    #[serde(default)]
+    nome: String,
+    #[serde(default)]
+    documento: String,
+    #[serde(default)]
+    anfitriao: String,
+    #[serde(default)]
+    motivo: String,
+}
+
+/// Dia pedido (`?data=`), ou hoje.
+fn dia_pedido(data: Option<&str>) -> chrono::NaiveDate {
+    data.and_then(tempo::ler_data).unwrap_or_else(tempo::hoje)
+}
+
+/// Renderiza os visitantes dentro, as visitas do dia e o formulário de entrada.
+async fn pagina_visitantes(
+    state: &AppState,
+    atual: &CurrentUser,
+    data: chrono::NaiveDate,
+    status: StatusCode,
+    form: FormState,
+    flash: Flash,
+) -> AppResult<Response> {
+    let template = VisitantesPage {
+        presentes: visitante_service::presentes(&state.db_leitura, atual.organizacao_id).await?,
+        visitas: visitante_service::do_dia(&state.db_leitura, atual.organizacao_id, data).await?,
+        data: data.to_string(),
+        hoje: tempo::hoje().to_string(),
+        form,
+        success_message: flash.success,
+        error_message: flash.error,
+    };
+    match template.render() {
+        Ok(html) => Ok((status, Html(html)).into_response()),
+        Err(e) => {
+            tracing::error!("Falha ao renderizar template VisitantesPage: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Handler para GET /presence/visitantes?data= - Visitantes dentro e visitas do dia
+pub async fn show_visitantes(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    flash: Flash,
+    Query(params): Query<VisitantesQuery>,
+) -> AppResult<Response> {
+    let data = dia_pedido(params.data.as_deref());
+    pagina_visitantes(&state, &atual, data, StatusCode::OK, FormState::default(), flash).await
+}
+
+/// Handler para POST /presence/visitantes - Regista a entrada de um visitante
+pub async fn handle_entrada_visitante(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Form(form): Form<VisitanteForm>,
+) -> AppResult<Response> {
+    let mut v = Validador::default();
+    v.obrigatorio("nome", &form.nome, MAX_NOME);
+    v.obrigatorio("documento", &form.documento, MAX_DOCUMENTO);
+    v.obrigatorio("anfitriao", &form.anfitriao, 10);
+    if form.motivo.trim().chars().count() > MAX_MOTIVO {
+        v.erro("motivo", format!("Máximo de {} caracteres.", MAX_MOTIVO));
+    }
+    let resultado = match v.resultado() {
+        Ok(()) => {
+            visitante_service::registar_entrada(
+                &state.db_pool,
+                atual.organizacao_id,
+                &form.nome,
+                &form.documento,
+                form.anfitriao.trim(),
+                &form.motivo,
+                &atual.id,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    match resultado {
+        Ok(visita) => {
+            let update = difundir(&state, atual.organizacao_id, "entrada", Ok(visita)).await;
+            Ok(flash::redirect_success(&session, "/presence/visitantes", update.message).await.into_response())
+        }
+        Err(AppError::Validation(erros)) => {
+            let form_state = FormState::com_erros(erros)
+                .com_valor("nome", form.nome)
+                .com_valor("documento", form.documento)
+                .com_valor("anfitriao", form.anfitriao)
+                .com_valor("motivo", form.motivo);
+            pagina_visitantes(&state, &atual, tempo::hoje(), StatusCode::UNPROCESSABLE_ENTITY, form_state, Flash::default()).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handler para POST /presence/visitantes/{id}/saida - Regista a saída (sem JavaScript; a página
+/// usa o WebSocket)
+pub async fn handle_saida_visitante(
+    State(state): State<AppState>,
+    session: Session,
+    atual: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Response> {
+    let update = saida_e_difundir(&state, atual.organizacao_id, id, &atual.id).await;
+    if update.success {
+        Ok(flash::redirect_success(&session, "/presence/visitantes", update.message).await.into_response())
+    } else {
+        Ok(flash::redirect_error(&session, "/presence/visitantes", update.message).await.into_response())
+    }
+}
+
+/// Handler para GET /presence/visitantes/export.csv?data= - Visitas do dia em CSV
+pub async fn handle_export_visitantes(
+    State(state): State<AppState>,
+    atual: CurrentUser,
+    Query(params): Query<VisitantesQuery>,
+) -> AppResult<Response> {
+    let data = dia_pedido(params.data.as_deref());
+    let visitas = visitante_service::do_dia(&state.db_leitura, atual.organizacao_id, data).await?;
+
+    // BOM UTF-8: o Excel abre os acentos corretamente
+    let mut csv = String::from("\u{feff}");
+    csv.push_str("id,nome,documento,anfitriao_id,anfitriao,motivo,entrada,registada_por,saida,saida_registada_por\r\n");
+    for v in &visitas {
+        let linha = [
+            v.id.to_string(),
+            csv_campo(&v.nome),
+            csv_campo(&v.documento),
+            csv_campo(v.anfitriao_id.as_deref().unwrap_or_default()),
+            csv_campo(v.anfitriao.as_deref().unwrap_or_default()),
+            csv_campo(&v.motivo),
+            tempo::formatar(&v.entrada_em, tempo::FORMATO_DATA_HORA),
+            csv_campo(&v.entrada_por),
+            v.saida_em.as_deref().map(|s| tempo::formatar(s, tempo::FORMATO_DATA_HORA)).unwrap_or_default(),
+            csv_campo(v.saida_por.as_deref().unwrap_or_default()),
+        ];
+        csv.push_str(&linha.join(","));
+        csv.push_str("\r\n");
+    }
+
+    let disposicao = format!("attachment; filename=\"visitantes_{}.csv\"", data);
+    Ok((
+        [(header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()), (header::CONTENT_DISPOSITION, disposicao)],
+        csv,
+    )
+        .into_response())
+}
+
+// --- WebSocket (GET /presence/visitantes/ws) ---
+
+/// Handler para o upgrade da ligação para WebSocket (páginas dos visitantes).
+pub async fn visitantes_websocket_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    operador: CurrentUser,
+) -> impl IntoResponse {
+    // A ligação corre noutra task: leva o span do pedido, para os logs terem o mesmo request_id
+    let span = tracing::Span::current();
+    ws.on_upgrade(move |socket| handle_socket(socket, state, operador).instrument(span))
+}
+
+/// Gere uma ligação: regista-a no `visitantes_state` (para receber as atualizações da organização)
+/// e processa as saídas pedidas pelo cliente.
+async fn handle_socket(socket: WebSocket, state: AppState, operador: CurrentUser) {
+    let CurrentUser { id: operador_id, organizacao_id, .. } = operador;
+    let conn_id = Uuid::new_v4();
+    tracing::info!("🔌 Nova conexão WS Visitantes: {} (Operador: {})", conn_id, operador_id);
+
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+    let (tx, mut rx) = mpsc::channel::<Message>(32);
+    state.visitantes_state.connections.lock().await.insert(conn_id, (organizacao_id, tx));
+
+    // Envia ao cliente o que for difundido para esta ligação
+    let mut send_task = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if ws_sender.send(msg).await.is_err() {
+                break;
+            }
+        }
+    }.in_current_span());
+
+    // Recebe as ações do cliente
+    let state_recv = state.clone();
+    let mut recv_task = tokio::spawn(async move {
+        while let Some(Ok(msg)) = ws_receiver.next().await {
+            match msg {
+                Message::Text(text) => match serde_json::from_str::<VisitanteSocketAction>(&text) {
+                    Ok(action) if action.action == "saida" => {
+                        saida_e_difundir(&state_recv, organizacao_id, action.id, &operador_id).await;
+                    }
+                    Ok(action) => tracing::warn!("Ação WS Visitantes desconhecida: {}", action.action),
+                    Err(e) => tracing::warn!("Mensagem WS Visitantes inválida: {}, Erro: {}", text, e),
+                },
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+    }.in_current_span());
+
+    tokio::select! {
+        _ = (&mut send_task) => recv_task.abort(),
+        _ = (&mut recv_task) => send_task.abort(),
+    };
+    state.visitantes_state.connections.lock().await.remove(&conn_id);
+    tracing::info!("🔌 Conexão WS Visitantes {} fechada.", conn_id);
+}
+
+/// Regista a saída de um visitante e envia o resultado a todas as páginas abertas da organização.
+async fn saida_e_difundir(state: &AppState, organizacao_id: i64, id: i64, operador_id: &str) -> VisitanteSocketUpdate {
+    // O WebSocket não passa pelo middleware da manutenção: a saída é recusada aqui
+    let resultado = if manutencao_service::atual().is_some() {
+        Err(AppError::EmManutencao("A aplicação está em manutenção: os registos estão suspensos.".to_string()))
+    } else {
+        visitante_service::registar_saida(&state.db_pool, organizacao_id, id, operador_id).await
+    };
+    let resultado = resultado.map_err(|e| {
+        tracing::warn!("Saída do visitante {} recusada: {:?}", id, e);
+        (id, e.user_message())
+    });
+    difundir(state, organizacao_id, "saida", resultado).await
+}
+
+/// Monta a atualização de uma entrada ou saída e envia-a às páginas abertas da organização.
+async fn difundir(state: &AppState, organizacao_id: i64, acao: &str, resultado: Result<Visita, (i64, String)>) -> VisitanteSocketUpdate {
+    let mut update = VisitanteSocketUpdate { acao: acao.to_string(), ..Default::default() };
+    match resultado {
+        Ok(visita) => {
+            update.success = true;
+            update.id = visita.id;
+            update.message = match acao {
+                "entrada" => format!("Entrada de {} registada.", visita.nome),
+                _ => format!("Saída de {} registada.", visita.nome),
+            };
+            if acao == "entrada" {
+                update.linha_html = VisitanteLinha { v: &visita }.render().unwrap_or_else(|e| {
+                    tracing::error!("Falha ao renderizar template VisitanteLinha: {}", e);
+                    String::new()
+                });
+            }
+        }
+        Err((id, mensagem)) => {
+            update.id = id;
+            update.message = mensagem;
+        }
+    }
+    update.presentes = visitante_service::presentes(&state.db_pool, organizacao_id).await.map(|p| p.len()).unwrap_or_default();
+
+    match serde_json::to_string(&update) {
+        Ok(texto) => state.visitantes_state.broadcast(organizacao_id, texto).await,
+        Err(e) => tracing::error!("Erro ao serializar update WS Visitantes: {:?}", e),
+    }
+    update
+}
@@ -0,0 +1,7 @@
+// static/js/csrf.js - Incluído por templates/base.html
+// Formulários com data-csrf (ex.: Sair) enviam o token CSRF lido do cookie da sessão
+document.addEventListener('submit', (ev) => {
+    if (!ev.target.matches('form[data-csrf]')) return;
+    const token = document.cookie.split('; ').find(c => c.startsWith('csrf_token='));
+    ev.target.querySelector('input[name="csrf_token"]').value = token ? token.split('=')[1] : '';
+});